@@ -10,7 +10,7 @@ const SYSTEM_PROMPT: &str = r#"You are an autonomous coding agent. You receive H
 You implement features like "Add input validation to the login form" or "Refactor the config module to support multiple profiles" by autonomously understanding context, planning, and writing code.
 
 ## Implementation Strategy
-- **Context first**: ALWAYS read related code before writing anything
+- **Context first**: ALWAYS read related code before writing anything; hybrid_search finds it by name or by what it does
 - **Follow patterns**: Match existing style, naming, error handling approaches
 - **Minimal changes**: Do exactly what's needed, no more
 - **Incremental**: For complex tasks, build up in logical steps
@@ -18,11 +18,27 @@ You implement features like "Add input validation to the login form" or "Refacto
   code to /tmp files. Context can degrade over long sessions — /tmp files persist reliably.
   Example: gather info into /tmp/analysis.txt, draft code in /tmp/draft.rs, then write the final version.
 
+## Compile Checks
+After editing code in a project with a type checker (Rust, TypeScript, or Python with pyright), call check_build rather than running the compiler through `run`:
+- It returns each error with its code, exact file:line:col span, and any fix the compiler suggests
+- Apply the fixes that match your intent, then call it again until it reports no errors
+- Pass the files you changed as `paths` in a large project to keep the output focused
+
+## Running Tests
+To test a change in a project with a large test suite, call map_test_coverage first: it lists the tests that execute your changed lines, so you can run just those. Lines it marks NOT COVERED need a test if they carry logic.
+
+## Committing
+In a git repository, commit each logical milestone as you reach it (a feature working with its tests, a bug fixed) instead of leaving one large uncommitted change:
+- git_stage the files that belong to the milestone, then git_commit with a subject line in the imperative mood and a short body saying why
+- Keep unrelated edits out of the commit; the user approves each one and sees the staged diff
+- Don't commit if the task says not to, or when the work doesn't build
+- Use git_branch only when the task asks for a branch
+
 ## Output Expectations
 Your response should:
 - Confirm what you implemented
 - Note any design decisions you made
-- List files created or modified
+- List files created or modified, and the commits made
 - Highlight anything the caller should verify or test
 
 ## Anti-patterns to Avoid
@@ -45,14 +61,15 @@ impl Default for CoderAgent {
 }
 
 const COMPACT_PROMPT: &str = r#"Summarize this coding session so it can continue effectively with reduced context. Preserve:
-1. Files modified or created (with full paths) and what changes were made to each
-2. Code patterns and conventions discovered in the existing codebase
-3. The original coding goal and any sub-tasks identified
-4. Design decisions made and their rationale
-5. Errors encountered during implementation and how they were resolved
-6. Any remaining work or files still needing modification
+1. Files modified or created, each with its full path and what changed in it
+2. The diffs themselves: copy the changed hunks (function signatures, struct fields, config keys) verbatim in fenced blocks, not paraphrased
+3. Code patterns and conventions discovered in the existing codebase
+4. The original coding goal and any sub-tasks identified
+5. Design decisions made and their rationale
+6. Build and test commands run, with the exact error messages encountered and how they were resolved
+7. Any remaining work or files still needing modification
 
-Focus on file paths, concrete changes, and architectural decisions. Include key code snippets only if they represent patterns to follow."#;
+Focus on file paths, concrete changes, and architectural decisions. Never replace a path with "the file" or a diff with "updated the function" - the session continues from these details."#;
 
 const TOOL_DESCRIPTION: &str = concat!(
     "Autonomous coding agent that implements features, fixes bugs, and modifies code by understanding context and following existing patterns.\n\n",
@@ -90,7 +107,7 @@ impl InternalAgent for CoderAgent {
     }
 
     fn tool_names(&self) -> &[&str] {
-        &["run", "read_image", "update_my_task"]
+        &["run", "hybrid_search", "check_build", "map_test_coverage", "git_stage", "git_commit", "git_branch", "read_image", "update_my_task"]
     }
 
     fn tool_limits(&self) -> Option<HashMap<String, usize>> {
@@ -117,6 +134,9 @@ mod tests {
         assert!(!agent.description().is_empty());
         assert!(!agent.system_prompt().is_empty());
         assert!(agent.tool_names().contains(&"run"));
+        assert!(agent.tool_names().contains(&"check_build"));
+        assert!(agent.tool_names().contains(&"hybrid_search"));
+        assert!(agent.tool_names().contains(&"map_test_coverage"));
         assert!(agent.tool_names().contains(&"read_image"));
         assert!(agent.tool_names().contains(&"update_my_task"));
     }
@@ -4,6 +4,8 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 
 use anyhow::Result;
+use qq_core::SamplingOverrides;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 fn default_max_turns() -> usize {
@@ -15,7 +17,7 @@ fn default_compaction_strategy() -> AgentMemoryStrategy {
 }
 
 /// Strategy for managing agent memory across execution.
-#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum AgentMemoryStrategy {
     /// Post-execution LLM summarization with continuation support.
@@ -23,13 +25,17 @@ pub enum AgentMemoryStrategy {
     /// In-loop observational memory (messages -> observations -> reflections).
     #[default]
     ObsMemory,
+    /// Recent messages verbatim, older exchanges recalled by embedding
+    /// similarity (`[sliding_window]`). Chat sessions only; an agent given
+    /// it runs with `ObsMemory`.
+    SlidingWindow,
 }
 
 /// Configuration overrides for built-in agents.
 ///
 /// Allows customizing tool limits and other settings for internal agents
 /// without modifying the source code.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct BuiltinAgentOverride {
     /// Maximum agentic loop iterations (number of turns)
     #[serde(default)]
@@ -58,6 +64,7 @@ pub struct BuiltinAgentOverride {
     /// Supports the same pattern syntax as external agent `tools`: plain names,
     /// `mcp:server/tool`, `mcp:server/*`, `internal:*`.
     #[serde(default, alias = "extra_tools")]
+    #[schemars(extend("x-aliases" = ["extra_tools"]))]
     pub tools: Vec<String>,
 
     /// Disable bash tool for this agent (default: false = bash enabled).
@@ -81,10 +88,35 @@ pub struct BuiltinAgentOverride {
     pub observation_threshold_bytes: Option<usize>,
     #[serde(default)]
     pub context_budget_bytes: Option<usize>,
+
+    /// Sampling overrides for this agent's requests (provider default if unset).
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+
+    /// Extra provider parameters (e.g., `reasoning_effort`) for this agent's
+    /// requests, replacing same-named keys.
+    #[serde(default)]
+    pub parameters: HashMap<String, serde_json::Value>,
+}
+
+impl BuiltinAgentOverride {
+    /// Sampling settings to merge into this agent's requests.
+    pub fn sampling(&self) -> SamplingOverrides {
+        SamplingOverrides {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            extra: self.parameters.clone(),
+        }
+    }
 }
 
 /// External agent definition from agents.toml.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AgentDefinition {
     /// Short description for display (/agents, /tools)
     pub description: String,
@@ -101,11 +133,13 @@ pub struct AgentDefinition {
     /// Deprecated: prefer the `profile` field below, which selects a complete
     /// profile from `[profiles.*]` in the main config.
     #[serde(default)]
+    #[schemars(extend("deprecated" = true, "x-replaced-by" = "profile"))]
     pub provider: Option<String>,
 
     /// Optional model override (uses profile default if not set).
     /// Deprecated: prefer the `profile` field below.
     #[serde(default)]
+    #[schemars(extend("deprecated" = true, "x-replaced-by" = "profile"))]
     pub model: Option<String>,
 
     /// Profile override for this agent. When set, the agent uses the named
@@ -161,10 +195,320 @@ pub struct AgentDefinition {
     pub observation_threshold_bytes: Option<usize>,
     #[serde(default)]
     pub context_budget_bytes: Option<usize>,
+
+    /// Sampling overrides for this agent's requests (provider default if unset).
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+
+    /// Extra provider parameters (e.g., `reasoning_effort`) for this agent's
+    /// requests, replacing same-named keys.
+    #[serde(default)]
+    pub parameters: HashMap<String, serde_json::Value>,
+}
+
+impl AgentDefinition {
+    /// Sampling settings to merge into this agent's requests.
+    pub fn sampling(&self) -> SamplingOverrides {
+        SamplingOverrides {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            extra: self.parameters.clone(),
+        }
+    }
+}
+
+fn default_variant_weight() -> u32 {
+    1
+}
+
+/// One system-prompt variant in a prompt experiment.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PromptVariant {
+    /// Variant name, recorded alongside usage and outcome of every run.
+    pub name: String,
+
+    /// System prompt used instead of the agent's default when selected.
+    pub system_prompt: String,
+
+    /// Relative selection weight (default: 1). Zero disables random selection
+    /// but the variant can still be forced by name.
+    #[serde(default = "default_variant_weight")]
+    pub weight: u32,
+}
+
+/// A/B experiment over system-prompt variants for one agent.
+///
+/// ```toml
+/// [experiments.coder]
+/// variants = [
+///     { name = "baseline", system_prompt = "..." },
+///     { name = "terse", system_prompt = "...", weight = 2 },
+/// ]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct PromptExperiment {
+    #[serde(default)]
+    pub variants: Vec<PromptVariant>,
+
+    /// Variant pinned for this invocation (set from `--variant`, never read from toml).
+    #[serde(skip)]
+    pub forced: Option<String>,
+}
+
+impl PromptExperiment {
+    /// Select a variant.
+    ///
+    /// A forced variant wins if it exists; otherwise `roll` is mapped onto the
+    /// cumulative weights. Returns None when no variant is selectable.
+    pub fn pick(&self, roll: u64) -> Option<&PromptVariant> {
+        if let Some(ref forced) = self.forced {
+            return self.variants.iter().find(|v| &v.name == forced);
+        }
+        let total: u64 = self.variants.iter().map(|v| v.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut point = roll % total;
+        for variant in &self.variants {
+            let w = variant.weight as u64;
+            if point < w {
+                return Some(variant);
+            }
+            point -= w;
+        }
+        None
+    }
+}
+
+/// Budget scaling by delegation depth.
+///
+/// A sub-agent called from the main chat runs at depth 1 with its full
+/// `max_turns` and `tool_limits`; each level below that multiplies them by
+/// `scale`, so deep delegation chains can't quietly spend the session's
+/// budget.
+///
+/// ```toml
+/// [depth_budget]
+/// scale = 0.5        # depth 2 gets half, depth 3 a quarter
+/// min_turns = 5
+/// min_tool_calls = 1
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(default)]
+pub struct DepthBudgetPolicy {
+    /// Multiplier per level below depth 1 (1.0 disables scaling)
+    pub scale: f64,
+    /// Scaled `max_turns` never drops below this
+    pub min_turns: usize,
+    /// Scaled tool limits never drop below this
+    pub min_tool_calls: usize,
+}
+
+impl Default for DepthBudgetPolicy {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            min_turns: 5,
+            min_tool_calls: 1,
+        }
+    }
+}
+
+impl DepthBudgetPolicy {
+    /// Budget multiplier for an agent running at `depth`.
+    pub fn factor(&self, depth: u32) -> f64 {
+        if depth <= 1 {
+            return 1.0;
+        }
+        self.scale.clamp(0.0, 1.0).powi(depth as i32 - 1)
+    }
+
+    /// Scale `max_turns` for `depth`, keeping at least `min_turns` (or the
+    /// configured value, if that is smaller).
+    pub fn scale_turns(&self, max_turns: usize, depth: u32) -> usize {
+        scale_count(max_turns, self.factor(depth), self.min_turns)
+    }
+
+    /// Scale each per-tool limit for `depth`.
+    pub fn scale_tool_limits(
+        &self,
+        limits: &HashMap<String, usize>,
+        depth: u32,
+    ) -> HashMap<String, usize> {
+        let factor = self.factor(depth);
+        limits
+            .iter()
+            .map(|(tool, &limit)| (tool.clone(), scale_count(limit, factor, self.min_tool_calls)))
+            .collect()
+    }
+}
+
+/// Lifetime of agent instances (the memory each `Agent[..]` call resumes).
+///
+/// Unnamed instances live in memory for the session and are evicted by
+/// count and idle time. Named instances (`use_agent_instance`) are never
+/// evicted; they are saved across sessions and forgotten once unused for
+/// `named_ttl_days`.
+///
+/// ```toml
+/// [instances]
+/// max_instances = 32    # unnamed instances kept in memory (0 = unlimited)
+/// idle_minutes = 0      # drop unnamed instances idle this long (0 = never)
+/// named_ttl_days = 30   # forget named instances unused this long (0 = never)
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(default)]
+pub struct InstancePolicy {
+    pub max_instances: usize,
+    pub idle_minutes: u64,
+    pub named_ttl_days: u64,
+}
+
+impl Default for InstancePolicy {
+    fn default() -> Self {
+        Self {
+            max_instances: 32,
+            idle_minutes: 0,
+            named_ttl_days: 30,
+        }
+    }
+}
+
+impl InstancePolicy {
+    /// Eviction policy for the in-memory instance store.
+    pub fn eviction(&self) -> qq_core::EvictionPolicy {
+        qq_core::EvictionPolicy {
+            max_instances: (self.max_instances > 0).then_some(self.max_instances),
+            idle_ttl: (self.idle_minutes > 0)
+                .then(|| std::time::Duration::from_secs(self.idle_minutes * 60)),
+        }
+    }
+
+    /// How long a saved named instance survives without use.
+    pub fn named_ttl(&self) -> Option<std::time::Duration> {
+        (self.named_ttl_days > 0)
+            .then(|| std::time::Duration::from_secs(self.named_ttl_days * 24 * 60 * 60))
+    }
+}
+
+/// Verification of an agent's changes before its report goes back to the
+/// caller. After a verified agent finishes a run that changed files, the
+/// project is type-checked with `check_build` and, when `test_command` is
+/// set, tested through `run`. Failures are sent back to the same agent
+/// instance as a repair task, up to `max_repairs` times; the final report
+/// says whether verification passed. Set per profile in config.toml:
+///
+/// ```toml
+/// [profiles.dev.verify]
+/// agents = ["coder"]           # agents whose runs are verified
+/// build = true                 # type-check with check_build
+/// test_command = "cargo test"  # run through `run`; omit to skip tests
+/// test_timeout = 300
+/// max_repairs = 2
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(default)]
+pub struct VerifyPolicy {
+    pub agents: Vec<String>,
+    pub build: bool,
+    pub test_command: Option<String>,
+    /// Seconds the test command may run (the `run` tool caps this at 300)
+    pub test_timeout: u64,
+    /// Repair runs before failures are reported to the caller
+    pub max_repairs: u32,
+}
+
+impl Default for VerifyPolicy {
+    fn default() -> Self {
+        Self {
+            agents: vec!["coder".to_string()],
+            build: true,
+            test_command: None,
+            test_timeout: 300,
+            max_repairs: 2,
+        }
+    }
+}
+
+impl VerifyPolicy {
+    /// Whether runs of `agent` are verified.
+    pub fn applies_to(&self, agent: &str) -> bool {
+        (self.build || self.test_command.is_some()) && self.agents.iter().any(|a| a == agent)
+    }
+}
+
+/// How sure a sub-agent is of its result, as it reports through `finish`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        })
+    }
+}
+
+/// When a sub-agent's self-assessment calls for a second look. A report
+/// that falls short of these thresholds is flagged with a `review` note, and
+/// the PM has the reviewer check that work before building on it. Set per
+/// profile in config.toml:
+///
+/// ```toml
+/// [profiles.dev.review]
+/// agents = ["coder"]         # agents whose reports are checked
+/// min_confidence = "high"    # flag reports less confident than this
+/// require_complete = true    # flag partial or blocked work
+/// flag_risks = true          # flag reports that list risks
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(default)]
+pub struct ReviewPolicy {
+    pub agents: Vec<String>,
+    pub min_confidence: Confidence,
+    pub require_complete: bool,
+    pub flag_risks: bool,
+}
+
+impl Default for ReviewPolicy {
+    fn default() -> Self {
+        Self {
+            agents: vec!["coder".to_string()],
+            min_confidence: Confidence::High,
+            require_complete: true,
+            flag_risks: true,
+        }
+    }
+}
+
+impl ReviewPolicy {
+    /// Whether reports from `agent` are checked.
+    pub fn applies_to(&self, agent: &str) -> bool {
+        self.agents.iter().any(|a| a == agent)
+    }
+}
+
+fn scale_count(value: usize, factor: f64, floor: usize) -> usize {
+    let scaled = (value as f64 * factor).ceil() as usize;
+    scaled.max(floor.min(value))
 }
 
 /// Agents configuration file (agents.toml).
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct AgentsConfig {
     /// External agent definitions
     #[serde(default)]
@@ -173,6 +517,33 @@ pub struct AgentsConfig {
     /// Overrides for built-in agents (e.g., tool_limits)
     #[serde(default)]
     pub builtin: HashMap<String, BuiltinAgentOverride>,
+
+    /// System-prompt experiments, keyed by agent name
+    #[serde(default)]
+    pub experiments: HashMap<String, PromptExperiment>,
+
+    /// Budget scaling for nested delegations
+    #[serde(default)]
+    pub depth_budget: DepthBudgetPolicy,
+
+    /// Eviction and persistence of agent instances
+    #[serde(default)]
+    pub instances: InstancePolicy,
+
+    /// Verification of agent runs, set from the active profile's `verify`
+    /// table rather than agents.toml
+    #[serde(skip)]
+    pub verify: Option<VerifyPolicy>,
+
+    /// Review thresholds for agent reports, set from the active profile's
+    /// `review` table
+    #[serde(skip)]
+    pub review: Option<ReviewPolicy>,
+
+    /// Share of the context window agents' tool definitions may take before
+    /// their descriptions are shortened, set from `[tools] description_budget`
+    #[serde(skip)]
+    pub description_budget: Option<f64>,
 }
 
 impl AgentsConfig {
@@ -263,6 +634,14 @@ impl AgentsConfig {
             .and_then(|o| o.max_observations)
     }
 
+    /// Get sampling overrides for a built-in agent (empty if none configured).
+    pub fn get_builtin_sampling(&self, name: &str) -> SamplingOverrides {
+        self.builtin
+            .get(name)
+            .map(BuiltinAgentOverride::sampling)
+            .unwrap_or_default()
+    }
+
     /// Get the configured profile name for an agent (built-in or external).
     ///
     /// Looks up `[builtin.<name>].profile` first, then `[agents.<name>].profile`.
@@ -312,6 +691,32 @@ impl AgentsConfig {
             hysteresis: defaults.hysteresis,
         })
     }
+
+    /// Pin an agent's experiment to a named variant for this invocation.
+    ///
+    /// Errors if the agent has no experiment or the variant is unknown.
+    pub fn force_variant(&mut self, agent: &str, variant: &str) -> Result<()> {
+        let experiment = self
+            .experiments
+            .get_mut(agent)
+            .ok_or_else(|| anyhow::anyhow!("No prompt experiment configured for agent '{}'", agent))?;
+        if !experiment.variants.iter().any(|v| v.name == variant) {
+            let known: Vec<&str> = experiment.variants.iter().map(|v| v.name.as_str()).collect();
+            anyhow::bail!(
+                "Unknown variant '{}' for agent '{}' (known: {})",
+                variant,
+                agent,
+                known.join(", ")
+            );
+        }
+        experiment.forced = Some(variant.to_string());
+        Ok(())
+    }
+
+    /// Select a system-prompt variant for an agent, if it has an experiment.
+    pub fn pick_variant(&self, agent: &str, roll: u64) -> Option<&PromptVariant> {
+        self.experiments.get(agent)?.pick(roll)
+    }
 }
 
 #[cfg(test)]
@@ -703,6 +1108,43 @@ profile = "reasoning"
         assert_eq!(config.get_agent_profile("coder"), Some("fast"));
     }
 
+    #[test]
+    fn test_sampling_overrides() {
+        let toml_content = r#"
+[builtin.coder]
+temperature = 0.0
+max_tokens = 8192
+
+[builtin.writer]
+temperature = 0.9
+top_p = 0.95
+parameters = { reasoning_effort = "low", chat_template_kwargs = { enable_thinking = false } }
+
+[agents.poet]
+description = "Poet"
+system_prompt = "Rhyme"
+temperature = 1.2
+"#;
+        let config: AgentsConfig = toml::from_str(toml_content).unwrap();
+
+        let coder = config.get_builtin_sampling("coder");
+        assert_eq!(coder.temperature, Some(0.0));
+        assert_eq!(coder.max_tokens, Some(8192));
+        assert_eq!(coder.top_p, None);
+        assert!(coder.extra.is_empty());
+
+        let writer = config.get_builtin_sampling("writer");
+        assert_eq!(writer.top_p, Some(0.95));
+        assert_eq!(writer.extra["reasoning_effort"], "low");
+        assert_eq!(
+            writer.extra["chat_template_kwargs"],
+            serde_json::json!({ "enable_thinking": false })
+        );
+
+        assert!(config.get_builtin_sampling("planner").is_empty());
+        assert_eq!(config.get("poet").unwrap().sampling().temperature, Some(1.2));
+    }
+
     #[test]
     fn test_external_agent_observation_config_defaults_to_none() {
         let toml_content = r#"
@@ -718,4 +1160,133 @@ system_prompt = "Simple"
         assert!(agent.observation_threshold_bytes.is_none());
         assert!(agent.context_budget_bytes.is_none());
     }
+
+    #[test]
+    fn test_parse_experiments() {
+        let toml_content = r#"
+[experiments.coder]
+variants = [
+    { name = "baseline", system_prompt = "Base" },
+    { name = "terse", system_prompt = "Terse", weight = 3 },
+]
+"#;
+        let config: AgentsConfig = toml::from_str(toml_content).unwrap();
+
+        let exp = config.experiments.get("coder").unwrap();
+        assert_eq!(exp.variants.len(), 2);
+        assert_eq!(exp.variants[0].weight, 1);
+        assert_eq!(exp.variants[1].weight, 3);
+        assert!(exp.forced.is_none());
+        assert!(config.pick_variant("researcher", 0).is_none());
+    }
+
+    #[test]
+    fn test_pick_variant_follows_weights() {
+        let toml_content = r#"
+[experiments.coder]
+variants = [
+    { name = "a", system_prompt = "A" },
+    { name = "off", system_prompt = "Off", weight = 0 },
+    { name = "b", system_prompt = "B", weight = 2 },
+]
+"#;
+        let config: AgentsConfig = toml::from_str(toml_content).unwrap();
+
+        let names: Vec<&str> = (0..6)
+            .map(|roll| config.pick_variant("coder", roll).unwrap().name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a", "b", "b", "a", "b", "b"]);
+    }
+
+    #[test]
+    fn test_force_variant() {
+        let toml_content = r#"
+[experiments.coder]
+variants = [
+    { name = "a", system_prompt = "A" },
+    { name = "b", system_prompt = "B", weight = 0 },
+]
+"#;
+        let mut config: AgentsConfig = toml::from_str(toml_content).unwrap();
+
+        config.force_variant("coder", "b").unwrap();
+        for roll in 0..4 {
+            assert_eq!(config.pick_variant("coder", roll).unwrap().name, "b");
+        }
+        assert!(config.force_variant("coder", "missing").is_err());
+        assert!(config.force_variant("writer", "a").is_err());
+    }
+
+    #[test]
+    fn test_instance_policy() {
+        let config: AgentsConfig =
+            toml::from_str("[instances]\nmax_instances = 0\nidle_minutes = 90\n").unwrap();
+        let eviction = config.instances.eviction();
+        assert_eq!(eviction.max_instances, None);
+        assert_eq!(eviction.idle_ttl, Some(std::time::Duration::from_secs(90 * 60)));
+        assert_eq!(config.instances.named_ttl_days, 30);
+
+        let defaults = AgentsConfig::default().instances;
+        assert_eq!(defaults.eviction().max_instances, Some(32));
+        assert_eq!(defaults.eviction().idle_ttl, None);
+    }
+
+    #[test]
+    fn test_depth_budget_policy() {
+        let config: AgentsConfig = toml::from_str(
+            r#"
+[depth_budget]
+scale = 0.5
+min_turns = 4
+"#,
+        )
+        .unwrap();
+        let policy = &config.depth_budget;
+        assert_eq!(policy.min_tool_calls, 1);
+
+        assert_eq!(policy.scale_turns(40, 1), 40);
+        assert_eq!(policy.scale_turns(40, 2), 20);
+        assert_eq!(policy.scale_turns(40, 3), 10);
+        assert_eq!(policy.scale_turns(40, 6), 4);
+        // The floor never raises a budget above what was configured
+        assert_eq!(policy.scale_turns(3, 4), 3);
+
+        let limits = HashMap::from([("web_search".to_string(), 5), ("write_file".to_string(), 1)]);
+        let scaled = policy.scale_tool_limits(&limits, 2);
+        assert_eq!(scaled["web_search"], 3);
+        assert_eq!(scaled["write_file"], 1);
+
+        // Unconfigured: no scaling at any depth
+        let default = DepthBudgetPolicy::default();
+        assert_eq!(default.scale_turns(40, 5), 40);
+    }
+
+    #[test]
+    fn test_verify_policy() {
+        let policy: VerifyPolicy = toml::from_str("test_command = \"cargo test\"\n").unwrap();
+        assert!(policy.applies_to("coder"));
+        assert!(!policy.applies_to("writer"));
+        assert_eq!(policy.max_repairs, 2);
+
+        // Nothing to run verifies nothing
+        let policy: VerifyPolicy = toml::from_str("build = false\n").unwrap();
+        assert!(!policy.applies_to("coder"));
+
+        // Not read from agents.toml
+        let config: AgentsConfig = toml::from_str("[verify]\nmax_repairs = 5\n").unwrap();
+        assert!(config.verify.is_none());
+    }
+
+    #[test]
+    fn test_review_policy() {
+        let policy: ReviewPolicy =
+            toml::from_str("agents = [\"coder\", \"writer\"]\nmin_confidence = \"medium\"\n")
+                .unwrap();
+        assert!(policy.applies_to("writer"));
+        assert!(!policy.applies_to("explore"));
+        assert!(Confidence::Low < policy.min_confidence);
+        assert!(Confidence::High > policy.min_confidence);
+        assert!(policy.require_complete && policy.flag_risks);
+        assert!(toml::from_str::<ReviewPolicy>("min_confidence = \"sure\"\n").is_err());
+    }
 }
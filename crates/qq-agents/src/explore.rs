@@ -12,7 +12,10 @@ You answer questions like "What config files are in this directory?" or "Find al
 ## Exploration Strategies
 - **Top-down**: Start with find_files, identify relevant areas, dive deeper
 - **Pattern search**: Search for file names, extensions, or content patterns
+- **Concept search**: In a project, hybrid_search finds code by identifier and by meaning in one call ("where are retries configured")
 - **Content inspection**: Read files to understand their purpose or find specific information
+- **Code structure**: Use outline_file on a source file or directory to see its functions, types, and classes with line ranges, then read only the lines you need
+- **Large files**: Use summarize_file to learn what a big file is for before reading it; summaries of unchanged files are cached across sessions
 - **Size/date filtering**: Focus on recent files or files of certain sizes
 
 ## Output Expectations
@@ -86,7 +89,7 @@ impl InternalAgent for ExploreAgent {
     }
 
     fn tool_names(&self) -> &[&str] {
-        &["run", "read_image", "update_my_task"]
+        &["run", "hybrid_search", "outline_file", "summarize_file", "read_image", "update_my_task"]
     }
 
     fn tool_limits(&self) -> Option<HashMap<String, usize>> {
@@ -117,6 +120,9 @@ mod tests {
         assert!(!agent.description().is_empty());
         assert!(!agent.system_prompt().is_empty());
         assert!(agent.tool_names().contains(&"run"));
+        assert!(agent.tool_names().contains(&"outline_file"));
+        assert!(agent.tool_names().contains(&"summarize_file"));
+        assert!(agent.tool_names().contains(&"hybrid_search"));
         assert!(agent.tool_names().contains(&"read_image"));
         assert!(agent.tool_names().contains(&"update_my_task"));
     }
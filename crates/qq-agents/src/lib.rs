@@ -13,6 +13,7 @@ mod config;
 mod explore;
 mod planner;
 mod preamble;
+mod project;
 mod researcher;
 mod reviewer;
 mod summarizer;
@@ -21,9 +22,14 @@ mod writer;
 
 pub use project_manager::ProjectManagerAgent;
 pub use coder::CoderAgent;
-pub use config::{AgentDefinition, AgentMemoryStrategy, AgentsConfig, BuiltinAgentOverride};
+pub use config::{
+    AgentDefinition, AgentMemoryStrategy, AgentsConfig, BuiltinAgentOverride, Confidence,
+    DepthBudgetPolicy, InstancePolicy, PromptExperiment, PromptVariant, ReviewPolicy,
+    VerifyPolicy,
+};
 pub use preamble::{generate_preamble, PreambleContext};
 pub use preamble::AgentContext;
+pub use project::{detect_projects, ProjectLanguage, ProjectPreset};
 pub use explore::ExploreAgent;
 
 /// Default compaction prompt for agent memory summarization.
@@ -145,6 +151,8 @@ pub struct AgentInfo {
     pub is_internal: bool,
     /// Tool names this agent uses
     pub tools: Vec<String>,
+    /// Whether this agent is barred from writing files
+    pub is_read_only: bool,
 }
 
 /// All internal agent types.
@@ -241,6 +249,38 @@ impl InternalAgentType {
 mod tests {
     use super::*;
 
+    /// What an agent contributes to its prompts and tool definition, in the
+    /// form reviewed in `snapshots/agents/`.
+    fn agent_snapshot(agent: &dyn InternalAgent) -> String {
+        let mut limits: Vec<_> = agent.tool_limits().unwrap_or_default().into_iter().collect();
+        limits.sort();
+        let limits: Vec<String> = limits.iter().map(|(tool, n)| format!("{}={}", tool, n)).collect();
+        format!(
+            "name: {}\ndescription: {}\ntools: {}\ntool_limits: {}\nread_only: {}\nmemory: {:?}\n\n\
+             ## System Prompt\n\n{}\n\n## Tool Description\n\n{}\n\n## Compact Prompt\n\n{}\n",
+            agent.name(),
+            agent.description(),
+            agent.tool_names().join(", "),
+            limits.join(", "),
+            agent.is_read_only(),
+            agent.memory_strategy(),
+            agent.system_prompt(),
+            agent.tool_description(),
+            agent.compact_prompt(),
+        )
+    }
+
+    #[test]
+    fn test_agent_prompt_snapshots() {
+        for t in InternalAgentType::all_with_pm() {
+            let agent = t.create();
+            qq_core::assert_snapshot!(
+                format!("agents/{}", agent.name()),
+                agent_snapshot(agent.as_ref())
+            );
+        }
+    }
+
     #[test]
     fn test_internal_agent_types() {
         let types = InternalAgentType::all();
@@ -330,6 +370,33 @@ mod tests {
                 "Agent {} should have a non-empty compact_prompt",
                 agent.name()
             );
+            assert_ne!(
+                prompt,
+                DEFAULT_COMPACT_PROMPT,
+                "Agent {} should have a role-specific compact_prompt",
+                agent.name()
+            );
+        }
+
+        // The details each role can't continue without
+        let required: &[(InternalAgentType, &[&str])] = &[
+            (InternalAgentType::Coder, &["full path", "diffs", "verbatim", "error messages"]),
+            (InternalAgentType::Researcher, &["source URL", "quotes", "verbatim", "every URL"]),
+            (
+                InternalAgentType::Planner,
+                &["Phase", "step numbers", "Depends on", "Open Questions", "Do not renumber"],
+            ),
+        ];
+        for (t, phrases) in required {
+            let agent = t.create();
+            for phrase in *phrases {
+                assert!(
+                    agent.compact_prompt().contains(phrase),
+                    "{} compact_prompt should mention {:?}",
+                    agent.name(),
+                    phrase
+                );
+            }
         }
     }
 
@@ -126,12 +126,12 @@ impl Default for PlannerAgent {
 const COMPACT_PROMPT: &str = r#"Summarize this planning session so it can continue effectively with reduced context. Preserve:
 1. The original goal and any constraints or requirements gathered
 2. Context discovered through exploration (file structures, existing code patterns)
-3. The plan phases and steps (with dependencies between them)
+3. The current plan in its output format: Goal Summary, Open Questions, Prerequisites, each Phase with its numbered steps, Risks & Considerations, Verification. Keep phase names, step numbers, assigned agents, and "Depends on" links exactly as drafted
 4. Key design decisions made and alternatives considered
-5. Open questions or decisions still needing resolution
+5. Open questions or decisions still needing resolution, with the options listed for each
 6. Which steps have been completed vs remaining
 
-Focus on the plan structure and decisions. Omit verbose exploration outputs - keep only the conclusions that informed the plan."#;
+Focus on the plan structure and decisions. Omit verbose exploration outputs - keep only the conclusions that informed the plan. Do not renumber, merge, or flatten steps."#;
 
 const TOOL_DESCRIPTION: &str = concat!(
     "Agent that creates detailed, actionable implementation plans by breaking down complex goals into sequenced steps.\n\n",
@@ -0,0 +1,245 @@
+//! Project type detection.
+//!
+//! Looks for well-known manifests (Cargo.toml, go.mod, pyproject.toml,
+//! package.json) in the working directory and derives the project's test,
+//! format, lint, and build commands plus the source file extensions worth
+//! searching. The result is shown in the preamble's runtime context, so
+//! agents stop asking (or guessing) what kind of project they are in.
+
+use std::path::Path;
+
+/// Language ecosystem of a detected project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectLanguage {
+    Rust,
+    Go,
+    Python,
+    JavaScript,
+}
+
+impl ProjectLanguage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProjectLanguage::Rust => "Rust",
+            ProjectLanguage::Go => "Go",
+            ProjectLanguage::Python => "Python",
+            ProjectLanguage::JavaScript => "JavaScript/TypeScript",
+        }
+    }
+}
+
+/// Language-appropriate command defaults for a detected project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectPreset {
+    pub language: ProjectLanguage,
+    /// Manifest file that identified the project.
+    pub manifest: &'static str,
+    pub test_command: String,
+    pub format_command: Option<String>,
+    pub lint_command: Option<String>,
+    pub build_command: Option<String>,
+    /// Source file extensions (without the dot), most relevant first.
+    pub extensions: Vec<&'static str>,
+}
+
+impl ProjectPreset {
+    /// One-line description for the preamble.
+    pub fn summary(&self) -> String {
+        let mut commands = vec![format!("test `{}`", self.test_command)];
+        let optional = [
+            ("build", &self.build_command),
+            ("format", &self.format_command),
+            ("lint", &self.lint_command),
+        ];
+        for (label, command) in optional {
+            if let Some(command) = command {
+                commands.push(format!("{} `{}`", label, command));
+            }
+        }
+        let globs: Vec<String> = self.extensions.iter().map(|e| format!("*.{}", e)).collect();
+        format!(
+            "{} ({}): {}; source files {}",
+            self.language.label(),
+            self.manifest,
+            commands.join(", "),
+            globs.join(", ")
+        )
+    }
+}
+
+/// Detect the project types rooted at `dir`.
+///
+/// A directory can hold more than one (a Rust crate with a JS frontend), so
+/// every match is returned, in a fixed order: Rust, Go, Python, JavaScript.
+pub fn detect_projects(dir: &Path) -> Vec<ProjectPreset> {
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).ok();
+    let exists = |name: &str| dir.join(name).exists();
+    let mut presets = Vec::new();
+
+    if let Some(manifest) = read("Cargo.toml") {
+        let scope = if manifest.contains("[workspace]") {
+            " --workspace"
+        } else {
+            ""
+        };
+        presets.push(ProjectPreset {
+            language: ProjectLanguage::Rust,
+            manifest: "Cargo.toml",
+            test_command: format!("cargo test{}", scope),
+            format_command: Some("cargo fmt --all".to_string()),
+            lint_command: Some(format!("cargo clippy{} --all-targets", scope)),
+            build_command: Some(format!("cargo build{}", scope)),
+            extensions: vec!["rs"],
+        });
+    }
+
+    if exists("go.mod") {
+        presets.push(ProjectPreset {
+            language: ProjectLanguage::Go,
+            manifest: "go.mod",
+            test_command: "go test ./...".to_string(),
+            format_command: Some("gofmt -w .".to_string()),
+            lint_command: Some("go vet ./...".to_string()),
+            build_command: Some("go build ./...".to_string()),
+            extensions: vec!["go"],
+        });
+    }
+
+    if let Some(manifest) = read("pyproject.toml") {
+        let runner = if exists("uv.lock") {
+            "uv run "
+        } else if exists("poetry.lock") {
+            "poetry run "
+        } else {
+            ""
+        };
+        let ruff = manifest.contains("ruff");
+        presets.push(ProjectPreset {
+            language: ProjectLanguage::Python,
+            manifest: "pyproject.toml",
+            test_command: format!("{}pytest", runner),
+            format_command: if ruff {
+                Some(format!("{}ruff format .", runner))
+            } else if manifest.contains("black") {
+                Some(format!("{}black .", runner))
+            } else {
+                None
+            },
+            lint_command: if ruff {
+                Some(format!("{}ruff check .", runner))
+            } else if manifest.contains("flake8") {
+                Some(format!("{}flake8", runner))
+            } else {
+                None
+            },
+            build_command: None,
+            extensions: vec!["py"],
+        });
+    }
+
+    if let Some(manifest) = read("package.json") {
+        let manager = if exists("pnpm-lock.yaml") {
+            "pnpm"
+        } else if exists("yarn.lock") {
+            "yarn"
+        } else if exists("bun.lockb") || exists("bun.lock") {
+            "bun"
+        } else {
+            "npm"
+        };
+        // Only offer scripts the package actually defines
+        let script = |name: &str| {
+            manifest
+                .contains(&format!("\"{}\":", name))
+                .then(|| format!("{} run {}", manager, name))
+        };
+        let typescript = exists("tsconfig.json") || manifest.contains("\"typescript\"");
+        presets.push(ProjectPreset {
+            language: ProjectLanguage::JavaScript,
+            manifest: "package.json",
+            test_command: format!("{} test", manager),
+            format_command: script("format"),
+            lint_command: script("lint"),
+            build_command: script("build"),
+            extensions: if typescript {
+                vec!["ts", "tsx", "js", "jsx"]
+            } else {
+                vec!["js", "jsx", "mjs", "cjs"]
+            },
+        });
+    }
+
+    presets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, contents: &str) {
+        std::fs::write(dir.join(name), contents).unwrap();
+    }
+
+    #[test]
+    fn test_detects_rust_workspace_and_node_frontend() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(
+            tmp.path(),
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        );
+        write(
+            tmp.path(),
+            "package.json",
+            r#"{"scripts": {"test": "vitest", "lint": "eslint ."}, "devDependencies": {"typescript": "5"}}"#,
+        );
+        write(tmp.path(), "pnpm-lock.yaml", "");
+
+        let presets = detect_projects(tmp.path());
+        assert_eq!(presets.len(), 2);
+
+        let rust = &presets[0];
+        assert_eq!(rust.test_command, "cargo test --workspace");
+        assert_eq!(
+            rust.lint_command.as_deref(),
+            Some("cargo clippy --workspace --all-targets")
+        );
+
+        let node = &presets[1];
+        assert_eq!(node.language, ProjectLanguage::JavaScript);
+        assert_eq!(node.test_command, "pnpm test");
+        assert_eq!(node.lint_command.as_deref(), Some("pnpm run lint"));
+        assert_eq!(node.format_command, None);
+        assert_eq!(node.extensions[0], "ts");
+    }
+
+    #[test]
+    fn test_detects_python_and_go() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(
+            tmp.path(),
+            "pyproject.toml",
+            "[tool.ruff]\nline-length = 100\n",
+        );
+        write(tmp.path(), "uv.lock", "");
+        let presets = detect_projects(tmp.path());
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].test_command, "uv run pytest");
+        assert_eq!(
+            presets[0].lint_command.as_deref(),
+            Some("uv run ruff check .")
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        write(tmp.path(), "go.mod", "module example.com/x\n");
+        let presets = detect_projects(tmp.path());
+        assert_eq!(
+            presets[0].summary(),
+            "Go (go.mod): test `go test ./...`, build `go build ./...`, \
+             format `gofmt -w .`, lint `go vet ./...`; source files *.go"
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(detect_projects(tmp.path()).is_empty());
+    }
+}
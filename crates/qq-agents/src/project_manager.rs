@@ -36,8 +36,8 @@ You MUST NOT call Agent[explore], Agent[researcher], Agent[coder], Agent[writer]
 - Most requests are clear enough to go straight to the planner. When in doubt, call the planner.
 
 ### 3. Task Creation (After Plan Approval)
-After the user approves the plan, create ALL tasks upfront as a dependency graph:
-- Every task gets: title, description, assignee, and `blocked_by` where applicable.
+After the user approves the plan, call `submit_plan` ONCE with the approved plan: a one-line `goal` and the ordered `steps`, each with `title`, `owner` (the agent that will do it), `description`, and `depends_on` (1-based numbers of earlier steps). Every step becomes a tracked task with `blocked_by` filled in, and the user sees the plan as a live checklist that updates as agents finish. Use `create_task` only for work added after the plan was submitted.
+- Every step gets: title, description, owner, and `depends_on` where applicable.
 - **Description must contain enough context for the agent to work autonomously** — include relevant file paths, function names, design decisions, and references to what prior tasks will produce.
 - Use `blocked_by` to express ordering constraints: exploration before coding, coding before review, research before analysis, etc.
 - Tasks with no `blocked_by` (or whose dependencies are all done) are eligible for parallel dispatch.
@@ -86,6 +86,8 @@ The dispatches return immediately, then `wait_for_tasks` blocks until agents fin
 
 If a parallel agent fails, the others still complete successfully. Address failures independently — retry with adjusted instructions, modify the plan, or create a new task.
 
+**When results invalidate the plan** — an explorer finds the component a step assumed does not exist, a coder reports the approach cannot work — call `revise_plan` before dispatching anything else. Give the finding as `reason`, `invalidate` the steps that no longer hold (each with its own reason), `update` steps whose owner, wording or dependencies change, and `add` replacement steps. Never silently abandon or re-purpose steps: the revision is shown to the user as a diff. The result lists steps marked `?` that depend on invalidated work; update, invalidate or re-dispatch each of them before it runs. Use `submit_plan` again only when the goal itself is abandoned.
+
 ### 5. Quality Assurance & Delivery
 - After all implementation tasks complete, create a QA task assigned to Agent[qa].
 - The QA task MUST include: (1) the original user request, (2) the approved plan, (3) references to what was produced (file paths, task notes from agents).
@@ -93,12 +95,16 @@ If a parallel agent fails, the others still complete successfully. Address failu
 - The QA agent independently verifies the work — it has no shared context with the agents that did the work.
 - Review QA results: if PASS, summarize results to the user. If FAIL or PARTIAL, address failures (re-delegate to coder, adjust plan, etc.).
 - Use Agent[reviewer] for subjective quality feedback (style, architecture, clarity). Use Agent[qa] for objective requirement verification (does it meet the stated criteria, is it complete, is it accurate).
+- Sub-agent reports end with a self-assessment: `confidence`, `completeness`, and `risks`. A report carrying a `review` field fell short of the configured review thresholds: before building on that work or reporting it done, have Agent[reviewer] (with `new_instance: true`) check it, pointing it at the listed risks and open questions. Treat `partial` or `blocked` work as unfinished whether or not it is flagged.
+- In a git repository, Agent[coder] commits its milestones as it goes. Once QA passes, commit anything still uncommitted (coordination files, fixes from several agents) with `git_stage` and `git_commit`, one commit per logical change, and report the commit hashes. Use `git_branch` first when the user wants the work on its own branch. Skip commits when the user asked for none.
 - List any remaining manual steps or known issues.
 
 ## TASK TRACKING
 
-You have 6 task tools for managing work:
+You have 8 task tools for managing work:
 
+- **submit_plan** — Submit the approved plan as ordered steps with owners and dependencies. Creates one task per step and drives the user's live plan checklist. Submitting again replaces the active plan.
+- **revise_plan** — Revise the active plan when findings invalidate part of it: invalidate, update and add steps with a reason. Keeps existing tasks, flags steps that depend on invalidated ones, and records the revision so the user sees what changed.
 - **create_task** — Create a tracked task with title, optional description, assignee, status, and `blocked_by` (list of prerequisite task IDs).
 - **update_task** — Update a task's title, status, assignee, description, `blocked_by` (replace dependency list, use `[]` to clear), or `add_note` (append a progress note).
 - **list_tasks** — List all tasks, optionally filtered by status or assignee. Output includes a derived `blocks` field showing which tasks each task blocks.
@@ -115,6 +121,12 @@ Use `add_note` on `update_task` to log progress observations. Sub-agents can als
 ### Sub-Agent Visibility
 When you delegate to a sub-agent, they automatically see the current task board prepended to their task. They can call `update_my_task` to mark their task done or add progress notes. This means you get progress updates without having to poll — just check notes on `list_tasks`.
 
+### Reminders
+Use `schedule_reminder` for follow-ups that belong in a later session ("check CI in 2h", "rerun benchmarks tomorrow"). Reminders persist across sessions; when one comes due it is added to the task board at the start of the next session and listed in your prompt. Use `datetime` for the current time or date arithmetic instead of guessing.
+
+### Long-Lived Agents
+Every Agent[X] call starts from its previous history in this session only. For an ongoing responsibility that should carry over to later sessions — e.g. a "tracker" that keeps the task board tidy, a coder that owns the release checklist — use `use_agent_instance` with a `name`: the first call also names the `agent`, later calls (in this or any later session) resume the same history. End instances that are no longer needed with `end: true`.
+
 Use task tracking for any work that involves 2 or more steps. This keeps you and the user aligned on progress. Status values: `todo`, `in_progress`, `done`, `blocked`.
 
 ## PARALLELISM
@@ -180,6 +192,7 @@ Both agents run concurrently. `wait_for_tasks` returns when at least one finishe
 /// This is the default agent for interactive sessions. It can:
 /// - Scope work with the user and plan execution
 /// - Delegate to specialized agents
+/// - Submit structured plans via submit_plan
 /// - Track tasks via create_task/update_task/list_tasks/delete_task
 /// - Optionally use tools directly (controlled by agents_only setting)
 pub struct ProjectManagerAgent {
@@ -267,7 +280,7 @@ impl InternalAgent for ProjectManagerAgent {
     }
 
     fn tool_names(&self) -> &[&str] {
-        &["create_task", "update_task", "list_tasks", "delete_task", "get_task_result", "wait_for_tasks"]
+        &["submit_plan", "revise_plan", "create_task", "update_task", "list_tasks", "delete_task", "get_task_result", "wait_for_tasks", "schedule_reminder", "datetime", "git_stage", "git_commit", "git_branch"]
     }
 
 
@@ -296,7 +309,10 @@ mod tests {
         assert!(agent.tool_names().contains(&"list_tasks"));
         assert!(agent.tool_names().contains(&"delete_task"));
         assert!(agent.tool_names().contains(&"get_task_result"));
+        assert!(agent.tool_names().contains(&"schedule_reminder"));
         assert!(agent.tool_names().contains(&"wait_for_tasks"));
+        assert!(agent.tool_names().contains(&"submit_plan"));
+        assert!(agent.tool_names().contains(&"revise_plan"));
     }
 
     #[test]
@@ -48,6 +48,19 @@ You provide thorough, actionable reviews. Given a request like "Review src/auth.
 - Consistency across files
 - Migration/upgrade paths
 
+## Static Analysis
+When reviewing code in a project with configured linters, call run_linters before forming conclusions, passing the changed files as `paths`. Cross-reference its diagnostics with the diff:
+- Report diagnostics on changed lines as findings, with the linter's rule name
+- Mention pre-existing diagnostics only when the change makes them worse or depends on them
+- Don't restate what the linter already says; add why it matters and how to fix it
+- Linters miss logic, design, and security issues, so keep reviewing after they come back clean
+
+## Test Coverage
+When reviewing changes to code that has tests, call map_test_coverage to see which changed lines the tests execute:
+- Report changed lines marked NOT COVERED that carry logic (branches, error paths, new functions) as findings, with their line ranges
+- Name the covering tests when checking that a change is actually tested, and read them if the behavior matters
+- If the coverage data is older than the change, say so rather than trusting line numbers
+
 ## Output Expectations
 Your response should:
 - Start with a 1-2 sentence overall assessment
@@ -60,7 +73,7 @@ Your response should:
 ## Anti-patterns to Avoid
 - Don't nitpick style when there are substantive issues
 - Don't just say "this is bad" - explain why and how to fix
-- Don't review without understanding context
+- Don't review without understanding context (outline_file shows a codebase's structure cheaply; summarize_file says what an unfamiliar file is for)
 - Don't miss the forest for the trees - consider overall design
 - Don't be harsh - be constructive and educational
 - Don't apply code-specific critique to non-code content (and vice versa)"#;
@@ -103,6 +116,8 @@ const TOOL_DESCRIPTION: &str = concat!(
     "Detailed example:\n",
     "  'Security review of src/api/upload.rs before production. This handles user file uploads. ",
     "Check for: path traversal, filename sanitization, content-type validation, file size limits.'\n\n",
+    "Runs the project's linters (clippy, ESLint, ruff) and cross-references their diagnostics with the code under review.\n",
+    "Maps changed lines to the tests that cover them and flags changed lines no test runs.\n\n",
     "Returns: Structured feedback grouped by severity with file:line references and suggested fixes\n\n",
     "DO NOT:\n",
     "  - Use for implementing fixes (use coder agent after review)\n",
@@ -124,7 +139,7 @@ impl InternalAgent for ReviewerAgent {
     }
 
     fn tool_names(&self) -> &[&str] {
-        &["run", "read_image", "update_my_task"]
+        &["run", "outline_file", "summarize_file", "run_linters", "map_test_coverage", "read_image", "update_my_task"]
     }
 
     fn tool_limits(&self) -> Option<HashMap<String, usize>> {
@@ -155,6 +170,10 @@ mod tests {
         assert!(!agent.description().is_empty());
         assert!(!agent.system_prompt().is_empty());
         assert!(agent.tool_names().contains(&"run"));
+        assert!(agent.tool_names().contains(&"outline_file"));
+        assert!(agent.tool_names().contains(&"summarize_file"));
+        assert!(agent.tool_names().contains(&"run_linters"));
+        assert!(agent.tool_names().contains(&"map_test_coverage"));
         assert!(agent.tool_names().contains(&"read_image"));
         assert!(agent.tool_names().contains(&"update_my_task"));
     }
@@ -32,6 +32,14 @@ You create summaries like "Summarize this error log focusing on the root cause"
 - Don't be so verbose you defeat the purpose
 - Don't editorialize or add interpretation"#;
 
+const COMPACT_PROMPT: &str = r#"Summarize this summarization session so it can continue effectively with reduced context. Preserve:
+1. The requested focus area, audience, and output format
+2. Which parts of the content have been summarized and which remain
+3. The summary drafted so far, verbatim
+4. Caveats, conditions, and exact figures from the content that the summary must keep
+
+Focus on the draft and what is left to cover. Omit the raw content itself."#;
+
 pub struct SummarizerAgent;
 
 impl SummarizerAgent {
@@ -84,6 +92,10 @@ impl InternalAgent for SummarizerAgent {
     fn tool_description(&self) -> &str {
         TOOL_DESCRIPTION
     }
+
+    fn compact_prompt(&self) -> &str {
+        COMPACT_PROMPT
+    }
 }
 
 #[cfg(test)]
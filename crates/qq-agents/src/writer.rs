@@ -30,7 +30,7 @@ Your response should:
 - Suggest what to review or verify
 
 ## Quality Principles
-- **Context-first**: Read the local codebase and existing docs before writing or modifying anything
+- **Context-first**: Read the local codebase and existing docs before writing or modifying anything; use `read_document` for PDF, DOCX, or EPUB source material
 - **Accuracy**: Never document features that don't exist
 - **Clarity**: Simple words, short sentences, clear structure
 - **Completeness**: Cover what readers need, skip what they don't
@@ -104,7 +104,7 @@ impl InternalAgent for WriterAgent {
     }
 
     fn tool_names(&self) -> &[&str] {
-        &["run", "read_image", "update_my_task"]
+        &["run", "read_image", "read_document", "update_my_task"]
     }
 
     fn tool_limits(&self) -> Option<HashMap<String, usize>> {
@@ -132,6 +132,7 @@ mod tests {
         assert!(!agent.system_prompt().is_empty());
         assert!(agent.tool_names().contains(&"run"));
         assert!(agent.tool_names().contains(&"read_image"));
+        assert!(agent.tool_names().contains(&"read_document"));
         assert!(agent.tool_names().contains(&"update_my_task"));
     }
 
@@ -0,0 +1,231 @@
+//! Running qq sessions.
+//!
+//! Sessions in different terminals share the state directory: input and
+//! chat history, reminders, the knowledge base, named agent instances and
+//! the permission overrides in config.toml. Writes to those are merged under
+//! per-file locks (see [`qq_tools::FileLock`]); this registry only lets a
+//! new session tell the user that others are running, and gives each session
+//! an id no other session uses.
+//!
+//! Each session writes `<state>/sessions/<pid>.json` and removes it on exit.
+//! Records whose process is gone are pruned by the next session to start.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// What a running session records about itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub id: String,
+    pub pid: u32,
+    /// Working directory the session was started in
+    pub cwd: String,
+    pub started: DateTime<Local>,
+}
+
+/// This session's registration; the record is removed on drop.
+pub struct ActiveSession {
+    record: SessionRecord,
+    path: Option<PathBuf>,
+}
+
+impl ActiveSession {
+    /// Register this process in `dir` (no registry when `None`). Returns the
+    /// registration and the other sessions still running.
+    pub fn register(dir: Option<&Path>) -> (Self, Vec<SessionRecord>) {
+        let started = Local::now();
+        let pid = std::process::id();
+        let record = SessionRecord {
+            id: session_id(started),
+            pid,
+            cwd: std::env::current_dir()
+                .map(|d| d.display().to_string())
+                .unwrap_or_default(),
+            started,
+        };
+        let Some(dir) = dir else {
+            return (Self { record, path: None }, Vec::new());
+        };
+
+        let others = running_sessions(dir, pid);
+        let path = dir.join(format!("{}.json", pid));
+        let written = serde_json::to_vec(&record)
+            .map_err(std::io::Error::other)
+            .and_then(|json| qq_tools::write_atomically(&path, &json));
+        if let Err(ref e) = written {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to register session");
+        }
+        (
+            Self {
+                record,
+                path: written.is_ok().then_some(path),
+            },
+            others,
+        )
+    }
+
+    /// Unique id of this session, e.g. `20260306-142210-4711`.
+    pub fn id(&self) -> &str {
+        &self.record.id
+    }
+}
+
+impl Drop for ActiveSession {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Records in `dir` of live sessions other than `own_pid`, oldest first.
+/// Records of sessions that exited without cleaning up are removed.
+fn running_sessions(dir: &Path, own_pid: u32) -> Vec<SessionRecord> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut running = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let record: Option<SessionRecord> = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        match record {
+            Some(record) if record.pid == own_pid => {}
+            Some(record) if qq_tools::file_lock::process_alive(record.pid) => running.push(record),
+            _ => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+    running.sort_by_key(|r| r.started);
+    running
+}
+
+/// Id for a session started at `started` in this process, e.g.
+/// `20260306-142210-4711`: timestamp for readable citations, pid for
+/// uniqueness.
+pub fn session_id(started: DateTime<Local>) -> String {
+    format!("{}-{}", started.format("%Y%m%d-%H%M%S"), std::process::id())
+}
+
+/// Startup notice when other sessions are running, e.g.
+/// `1 other qq session is running (pid 4711 in ~/src/app) ...`.
+pub fn format_others(others: &[SessionRecord]) -> Option<String> {
+    if others.is_empty() {
+        return None;
+    }
+    let list = others
+        .iter()
+        .map(|r| format!("pid {} in {}", r.pid, r.cwd))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(format!(
+        "{} other qq session{} running ({}). History, reminders, knowledge and \
+         permission changes are shared; writes are merged, and a file held by \
+         another session for too long is left unsaved with a warning.",
+        others.len(),
+        if others.len() == 1 { " is" } else { "s are" },
+        list
+    ))
+}
+
+/// Find a file saved per session (`<id>.json`, e.g. a delegation graph): a
+/// file path, a session id or unique prefix of one in `dir`, or the most
+/// recent file when `session` is `None`. `what` names the file in errors.
+pub fn find_saved(dir: Option<&Path>, session: Option<&str>, what: &str) -> anyhow::Result<PathBuf> {
+    if let Some(session) = session {
+        let path = Path::new(session);
+        if path.is_file() {
+            return Ok(path.to_path_buf());
+        }
+    }
+    let dir = dir.ok_or_else(|| anyhow::anyhow!("No state directory for saved {}s", what))?;
+    let mut saved: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Session ids start with a timestamp, so names sort by start time
+    saved.sort();
+
+    let stem = |p: &PathBuf| {
+        p.file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    match session {
+        None => saved
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("No {}s in {}", what, dir.display())),
+        Some(session) => {
+            let matches: Vec<PathBuf> = saved
+                .into_iter()
+                .filter(|p| stem(p).starts_with(session))
+                .collect();
+            match matches.as_slice() {
+                [] => anyhow::bail!("No {} for session '{}'", what, session),
+                [one] => Ok(one.clone()),
+                _ => anyhow::bail!(
+                    "'{}' matches {} sessions: {}",
+                    session,
+                    matches.len(),
+                    matches.iter().map(stem).collect::<Vec<_>>().join(", ")
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_register_lists_live_sessions_and_prunes_dead_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = |pid: u32| SessionRecord {
+            id: format!("20260306-142210-{}", pid),
+            pid,
+            cwd: "/src/app".to_string(),
+            started: Local::now(),
+        };
+        // Another live process (our parent) and one that no longer runs
+        let parent = std::os::unix::process::parent_id();
+        let dead = dir.path().join("4294967.json");
+        std::fs::write(
+            dir.path().join(format!("{}.json", parent)),
+            serde_json::to_vec(&record(parent)).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(&dead, serde_json::to_vec(&record(4294967)).unwrap()).unwrap();
+
+        let (session, others) = ActiveSession::register(Some(dir.path()));
+        assert!(session.id().ends_with(&format!("-{}", std::process::id())));
+        let own = dir.path().join(format!("{}.json", std::process::id()));
+        assert!(own.is_file());
+        assert_eq!(others.len(), 1);
+        assert_eq!(others[0].pid, parent);
+        assert!(!dead.exists());
+
+        let notice = format_others(&others).unwrap();
+        assert!(notice.starts_with(&format!(
+            "1 other qq session is running (pid {} in /src/app).",
+            parent
+        )));
+        assert_eq!(format_others(&[]), None);
+
+        drop(session);
+        assert!(!own.exists());
+    }
+}
@@ -0,0 +1,380 @@
+//! Shareable agent definitions (`qq agents export` / `qq agents import`).
+//!
+//! A bundle is one TOML file holding an external agent's definition, the
+//! same fields as its `[agents.<name>]` entry in agents.toml (prompt, tools,
+//! limits, profile and sampling preferences), plus its prompt experiment if
+//! it has one. `schema_version` lets a later qq change the format: bundles
+//! written by a newer qq are refused rather than half-imported.
+//!
+//! ```toml
+//! schema_version = 1
+//! name = "sql_reviewer"
+//!
+//! [agent]
+//! description = "Reviews SQL migrations"
+//! system_prompt = "You review SQL migrations for locking and data loss..."
+//! tools = ["read_file", "search_files"]
+//! max_turns = 20
+//! ```
+//!
+//! Import validates the bundle and edits agents.toml in place, so comments
+//! and other agents survive.
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use qq_agents::{AgentDefinition, AgentsConfig, InternalAgentType, PromptExperiment};
+
+/// Bundle format written by this version.
+pub const BUNDLE_SCHEMA_VERSION: u32 = 1;
+
+/// One exported agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentBundle {
+    pub schema_version: u32,
+    pub name: String,
+    /// qq version that wrote the bundle, for reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported_by: Option<String>,
+    pub agent: AgentDefinition,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<PromptExperiment>,
+}
+
+impl AgentBundle {
+    /// Bundle the external agent `name` from agents.toml.
+    pub fn export(config: &AgentsConfig, name: &str) -> Result<Self> {
+        if InternalAgentType::from_name(name).is_some() {
+            bail!(
+                "'{}' is a built-in agent; only agents defined in agents.toml can be exported",
+                name
+            );
+        }
+        let Some(agent) = config.get(name) else {
+            let mut names: Vec<&str> = config.agents.keys().map(String::as_str).collect();
+            names.sort();
+            if names.is_empty() {
+                bail!("Agent '{}' not found: agents.toml defines no agents", name);
+            }
+            bail!(
+                "Agent '{}' not found. Defined in agents.toml: {}",
+                name,
+                names.join(", ")
+            );
+        };
+        Ok(Self {
+            schema_version: BUNDLE_SCHEMA_VERSION,
+            name: name.to_string(),
+            exported_by: Some(format!("qq {}", env!("CARGO_PKG_VERSION"))),
+            agent: agent.clone(),
+            experiment: config.experiments.get(name).cloned(),
+        })
+    }
+
+    /// The bundle as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        let body = toml::to_string_pretty(self).context("Failed to serialize agent bundle")?;
+        Ok(format!(
+            "# qq agent bundle. Import with: qq agents import <file>\n{}",
+            body
+        ))
+    }
+
+    /// Parse and validate a bundle. Returns it with warnings about settings
+    /// that won't take effect as written; problems that would break the
+    /// agent are errors.
+    pub fn parse(text: &str, known_profiles: &[String]) -> Result<(Self, Vec<String>)> {
+        let raw: toml::Table = toml::from_str(text).context("Not a TOML file")?;
+        match raw.get("schema_version") {
+            None => bail!("Not an agent bundle: schema_version is missing"),
+            Some(toml::Value::Integer(v)) if *v > BUNDLE_SCHEMA_VERSION as i64 => bail!(
+                "Bundle schema version {} is newer than this qq supports ({}); upgrade qq to import it",
+                v,
+                BUNDLE_SCHEMA_VERSION
+            ),
+            Some(toml::Value::Integer(v)) if *v >= 1 => {}
+            Some(other) => bail!("Invalid schema_version: {}", other),
+        }
+        let bundle: Self = toml::from_str(text).context("Invalid agent bundle")?;
+
+        let mut warnings = unknown_agent_fields(&raw, &bundle.agent);
+        warnings.extend(bundle.validate(known_profiles)?);
+        Ok((bundle, warnings))
+    }
+
+    /// Import under another name.
+    pub fn rename(&mut self, name: &str) -> Result<()> {
+        check_name(name)?;
+        self.name = name.to_string();
+        Ok(())
+    }
+
+    fn validate(&self, known_profiles: &[String]) -> Result<Vec<String>> {
+        check_name(&self.name)?;
+        let agent = &self.agent;
+        if agent.description.trim().is_empty() {
+            bail!("Agent '{}' has an empty description", self.name);
+        }
+        if agent.system_prompt.trim().is_empty() {
+            bail!("Agent '{}' has an empty system_prompt", self.name);
+        }
+        if agent.max_turns == 0 {
+            bail!("Agent '{}' has max_turns = 0", self.name);
+        }
+        if let Some(t) = agent.temperature {
+            if !(0.0..=2.0).contains(&t) {
+                bail!(
+                    "Agent '{}' has temperature {} outside 0.0-2.0",
+                    self.name,
+                    t
+                );
+            }
+        }
+        if let Some(ref experiment) = self.experiment {
+            if let Some(v) = experiment
+                .variants
+                .iter()
+                .find(|v| v.system_prompt.trim().is_empty())
+            {
+                bail!("Experiment variant '{}' has an empty system_prompt", v.name);
+            }
+        }
+
+        let mut warnings = Vec::new();
+        if let Some(ref profile) = agent.profile {
+            if !known_profiles.contains(profile) {
+                warnings.push(format!(
+                    "profile '{}' is not defined in config.toml; the agent will use the session's profile until it is",
+                    profile
+                ));
+            }
+        }
+        let tools: BTreeSet<&str> = agent.tools.iter().map(String::as_str).collect();
+        let mut limited: Vec<&str> = agent
+            .tool_limits
+            .keys()
+            .map(String::as_str)
+            .filter(|t| !tools.contains(t) && !t.starts_with("run"))
+            .collect();
+        limited.sort();
+        for tool in limited {
+            warnings.push(format!(
+                "tool_limits names '{}', which is not in the agent's tools",
+                tool
+            ));
+        }
+        Ok(warnings)
+    }
+}
+
+/// Bundle names become agent names and tool names (`Agent[name]`).
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!(
+            "Invalid agent name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    if InternalAgentType::from_name(name).is_some() {
+        bail!(
+            "'{}' is the name of a built-in agent; import it under another name with --name",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Warnings for `[agent]` keys this version doesn't know; serde ignores them.
+fn unknown_agent_fields(raw: &toml::Table, agent: &AgentDefinition) -> Vec<String> {
+    let Some(toml::Value::Table(given)) = raw.get("agent") else {
+        return Vec::new();
+    };
+    // Every field set in the input is set in the parsed definition, so a key
+    // missing from its serialization was not recognized
+    let known = toml::Table::try_from(agent).unwrap_or_default();
+    let mut unknown: Vec<&String> = given.keys().filter(|k| !known.contains_key(*k)).collect();
+    unknown.sort();
+    unknown
+        .into_iter()
+        .map(|k| format!("unknown field '{}' in [agent] ignored", k))
+        .collect()
+}
+
+/// Add `bundle` to an agents.toml document as `[agents.<name>]` (and
+/// `[experiments.<name>]`). An existing agent of that name is an error
+/// unless `replace` is set.
+pub fn insert_into(
+    content: &str,
+    name: &str,
+    bundle: &AgentBundle,
+    replace: bool,
+) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut =
+        content.parse().context("agents.toml is not valid TOML")?;
+    let agents = implicit_table(&mut doc, "agents")?;
+    if agents.contains_key(name) && !replace {
+        bail!(
+            "Agent '{}' already exists in agents.toml; use --force to replace it or --name to import under another name",
+            name
+        );
+    }
+    agents.insert(name, toml_edit::Item::Table(to_table(&bundle.agent)?));
+
+    let experiments = implicit_table(&mut doc, "experiments")?;
+    match bundle.experiment {
+        Some(ref experiment) => {
+            experiments.insert(name, toml_edit::Item::Table(to_table(experiment)?));
+        }
+        None if replace => {
+            experiments.remove(name);
+        }
+        None => {}
+    }
+    if experiments.is_empty() {
+        doc.remove("experiments");
+    }
+    Ok(doc.to_string())
+}
+
+/// Top-level table `key`, created without a header of its own.
+fn implicit_table<'a>(
+    doc: &'a mut toml_edit::DocumentMut,
+    key: &str,
+) -> Result<&'a mut toml_edit::Table> {
+    doc.entry(key)
+        .or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("[{}] in agents.toml is not a table", key))
+}
+
+fn to_table<T: Serialize>(value: &T) -> Result<toml_edit::Table> {
+    let text = toml::to_string_pretty(value)?;
+    let doc: toml_edit::DocumentMut = text.parse()?;
+    Ok(doc.as_table().clone())
+}
+
+/// Read a bundle from a file path or an http(s) URL.
+pub async fn read_source(source: &str) -> Result<String> {
+    if source.starts_with("https://") || source.starts_with("http://") {
+        let response = qq_core::http::client_builder()
+            .build()?
+            .get(source)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {}", source))?
+            .error_for_status()
+            .with_context(|| format!("Failed to fetch {}", source))?;
+        return response
+            .text()
+            .await
+            .with_context(|| format!("Failed to read {}", source));
+    }
+    std::fs::read_to_string(source).with_context(|| format!("Failed to read {}", source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENTS_TOML: &str = r#"# My agents
+
+[agents.sql_reviewer]
+description = "Reviews SQL migrations"
+system_prompt = "You review SQL migrations."
+tools = ["read_file"]
+max_turns = 12
+profile = "fast"
+tool_limits = { read_file = 30 }
+
+[experiments.sql_reviewer]
+variants = [{ name = "strict", system_prompt = "Be strict." }]
+"#;
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let config: AgentsConfig = toml::from_str(AGENTS_TOML).unwrap();
+        let bundle = AgentBundle::export(&config, "sql_reviewer").unwrap();
+        let text = bundle.to_toml().unwrap();
+        assert!(text.contains("schema_version = 1"));
+
+        let (parsed, warnings) = AgentBundle::parse(&text, &["fast".to_string()]).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(parsed.agent.max_turns, 12);
+        assert_eq!(parsed.experiment.as_ref().unwrap().variants.len(), 1);
+
+        // Import into another file under a new name; comments survive
+        let other = "# Team agents\n\n[builtin.coder]\ntool_limits = { run = 5 }\n";
+        let mut parsed = parsed;
+        assert!(parsed.rename("coder").is_err());
+        parsed.rename("sql").unwrap();
+        let updated = insert_into(other, &parsed.name, &parsed, false).unwrap();
+        assert!(updated.starts_with("# Team agents"));
+        let imported: AgentsConfig = toml::from_str(&updated).unwrap();
+        assert_eq!(
+            imported.get("sql").unwrap().system_prompt,
+            "You review SQL migrations."
+        );
+        assert!(imported.experiments.contains_key("sql"));
+        assert!(imported.builtin.contains_key("coder"));
+
+        // A second import needs --force
+        assert!(insert_into(&updated, "sql", &parsed, false).is_err());
+        assert!(insert_into(&updated, "sql", &parsed, true).is_ok());
+
+        assert!(AgentBundle::export(&config, "coder").is_err());
+        assert!(AgentBundle::export(&config, "missing").is_err());
+    }
+
+    #[test]
+    fn test_parse_validates() {
+        let bundle = |schema: &str, name: &str, agent: &str| {
+            format!(
+                "schema_version = {}\nname = \"{}\"\n[agent]\n{}\n",
+                schema, name, agent
+            )
+        };
+        let ok_agent = "description = \"d\"\nsystem_prompt = \"p\"";
+
+        let err = |text: String| AgentBundle::parse(&text, &[]).unwrap_err().to_string();
+        assert!(err(bundle("2", "a", ok_agent)).contains("newer"));
+        assert!(err(bundle("1", "coder", ok_agent)).contains("built-in"));
+        assert!(err(bundle("1", "a b", ok_agent)).contains("Invalid agent name"));
+        assert!(err(bundle(
+            "1",
+            "a",
+            "description = \"d\"\nsystem_prompt = \" \""
+        ))
+        .contains("empty system_prompt"));
+        assert!(err("name = \"a\"".to_string()).contains("schema_version"));
+
+        let (_, warnings) = AgentBundle::parse(
+            &bundle(
+                "1",
+                "a",
+                "description = \"d\"\nsystem_prompt = \"p\"\nprofile = \"gpu\"\nfancy = true\ntool_limits = { web_search = 3 }",
+            ),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            warnings,
+            [
+                "unknown field 'fancy' in [agent] ignored",
+                "profile 'gpu' is not defined in config.toml; the agent will use the session's profile until it is",
+                "tool_limits names 'web_search', which is not in the agent's tools",
+            ]
+        );
+    }
+}
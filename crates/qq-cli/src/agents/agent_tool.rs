@@ -4,19 +4,23 @@
 //! the LLM can invoke. Agents can call other agents up to a maximum depth,
 //! after which they only have access to base tools.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use serde::Deserialize;
+use tracing::Instrument;
 
-use qq_core::{AgentConfig, AgentMemory, CompletionRequest, DelegationPermissions, Error, Message, PropertySchema, Provider, Role, Tool, ToolDefinition, ToolOutput, ToolParameters, ToolRegistry};
+use qq_core::{AgentConfig, AgentMemory, AgentProgressHandler, CompletionRequest, DelegationPermissions, Error, Message, PropertySchema, Provider, Regrounding, Role, Tool, ToolDefinition, ToolOutput, ToolParameters, ToolRegistry};
 
-use qq_agents::{AgentDefinition, AgentMemoryStrategy, AgentsConfig, InternalAgent, InternalAgentType, DEFAULT_COMPACT_PROMPT};
+use qq_agents::{AgentDefinition, AgentMemoryStrategy, AgentsConfig, DepthBudgetPolicy, InternalAgent, InternalAgentType, DEFAULT_COMPACT_PROMPT};
 use qq_core::observation::ContextCompactor;
+use qq_tools::ChangeStats;
 use crate::agents::continuation::{execute_with_continuation, AgentExecutionResult, ContinuationConfig};
-use crate::agents::InformUserTool;
-use crate::event_bus::AgentEventBus;
+use crate::agents::{AgentReport, FinishTool, InformUserTool, FINISH_TOOL};
+use crate::agents::verify;
+use crate::event_bus::{AgentEvent, AgentEventBus};
+use crate::experiments;
 use crate::profile_registry::SharedProfileRegistry;
 use crate::ExecutionContext;
 
@@ -72,16 +76,37 @@ fn truncation_message(
 // =============================================================================
 
 /// Resolved configuration for executing an agent as a tool.
+#[derive(Clone)]
 struct AgentToolConfig {
     agent_name: String,
     system_prompt: String,
     tool_names: Vec<String>,
+    max_turns: Option<usize>,
     tool_limits: Option<HashMap<String, usize>>,
     compact_prompt: String,
     is_read_only: bool,
     memory_strategy: AgentMemoryStrategy,
     max_observations: Option<u32>,
     observation_config: Option<qq_core::ObservationConfig>,
+    sampling: qq_core::SamplingOverrides,
+}
+
+/// Apply the agent's turn and tool-call budgets, scaled for its delegation depth.
+fn apply_budget(
+    mut agent_cfg: AgentConfig,
+    config: &AgentToolConfig,
+    base_tools: &ToolRegistry,
+    policy: &DepthBudgetPolicy,
+    depth: u32,
+) -> AgentConfig {
+    let max_turns = config.max_turns.unwrap_or(agent_cfg.max_turns);
+    agent_cfg = agent_cfg.with_max_turns(policy.scale_turns(max_turns, depth));
+    if let Some(ref limits) = config.tool_limits {
+        agent_cfg = agent_cfg.with_tool_limits(
+            base_tools.resolve_tool_limits(policy.scale_tool_limits(limits, depth)),
+        );
+    }
+    agent_cfg
 }
 
 /// Build the standard agent tool definition.
@@ -131,10 +156,12 @@ fn agent_tool_definition(name: &str, description: &str) -> ToolDefinition {
 
 /// Shared execution logic for both internal and external agent tools.
 ///
-/// Handles scope management, tool setup, agent execution, memory compaction,
-/// and result formatting. Branches on `memory_strategy`:
-/// - `ObsMemory`: uses in-loop observational memory, stores obs log
-/// - `Compaction`: uses post-execution LLM summarization with continuation
+/// Runs the agent once, then, when the profile's `verify` policy covers it
+/// and the run changed files, builds and tests the result. Failed checks go
+/// back to the same instance as a repair task, up to `max_repairs` times,
+/// and the outcome is recorded in the report returned to the caller. Last,
+/// a report whose self-assessment falls short of the profile's `review`
+/// policy is flagged so the caller has the work reviewed.
 #[allow(clippy::too_many_arguments)]
 async fn execute_agent(
     config: AgentToolConfig,
@@ -154,6 +181,150 @@ async fn execute_agent(
     scope: &str,
     task_store: &Option<Arc<qq_tools::TaskStore>>,
     compactor: &Option<Arc<dyn ContextCompactor>>,
+    regrounding: &Option<Regrounding>,
+    context_window: Option<u32>,
+    ask_network: bool,
+    inherited_permissions: DelegationPermissions,
+) -> Result<ToolOutput, Error> {
+    let policy = external_agents
+        .verify
+        .as_ref()
+        .filter(|p| p.applies_to(&config.agent_name))
+        .filter(|_| !config.is_read_only && !inherited_permissions.read_only)
+        .cloned();
+    let run = |config: AgentToolConfig, task: String, new_instance: bool| {
+        // Tags the run's events with the agent for the TUI log pane
+        let span = tracing::info_span!("agent", agent = %config.agent_name);
+        execute_agent_once(
+            config,
+            task,
+            new_instance,
+            instance_id.clone(),
+            base_tools,
+            provider,
+            profile_registry,
+            external_agents,
+            enabled_agents,
+            current_depth,
+            max_depth,
+            execution_context,
+            event_bus,
+            agent_memory,
+            scope,
+            task_store,
+            compactor,
+            regrounding,
+            context_window,
+            ask_network,
+            inherited_permissions,
+        )
+        .instrument(span)
+    };
+    let agent_name = config.agent_name.clone();
+    let output = async {
+        let Some(policy) = policy else {
+            return run(config, task, new_instance).await;
+        };
+
+        let mut output = run(config.clone(), task.clone(), new_instance).await?;
+        let mut changes = verify::result_changes(&output.text_content());
+        if output.is_error || changes.is_empty() {
+            return Ok(output);
+        }
+
+        let mut repairs = 0;
+        loop {
+            let verification = verify::verify(&policy, base_tools).await?;
+            if !verification.ran_any() {
+                return Ok(output);
+            }
+            if verification.passed() || repairs >= policy.max_repairs {
+                tracing::info!(
+                    agent = %config.agent_name,
+                    passed = verification.passed(),
+                    repairs,
+                    "Verification complete"
+                );
+                let text = verify::annotate(&output.text_content(), &verification, repairs, changes);
+                return Ok(ToolOutput::success(text));
+            }
+
+            repairs += 1;
+            tracing::info!(
+                agent = %config.agent_name,
+                checks = %verification.summary(),
+                attempt = repairs,
+                "Verification failed, requesting a repair"
+            );
+            if let Some(bus) = event_bus {
+                bus.publish(AgentEvent::UserNotification {
+                    agent_name: config.agent_name.clone(),
+                    message: format!(
+                        "Verification failed ({}); repair attempt {} of {}",
+                        verification.summary(),
+                        repairs,
+                        policy.max_repairs
+                    ),
+                })
+                .await;
+            }
+            let repair_task = verify::repair_task(&task, &verification, repairs, policy.max_repairs);
+            let repaired = run(config.clone(), repair_task, false).await?;
+            if repaired.is_error {
+                return Ok(repaired);
+            }
+            changes.merge(verify::result_changes(&repaired.text_content()));
+            output = repaired;
+        }
+    }
+    .await?;
+
+    let review = external_agents
+        .review
+        .as_ref()
+        .filter(|p| p.applies_to(&agent_name))
+        .filter(|_| !output.is_error);
+    let Some((flagged, reason)) =
+        review.and_then(|p| AgentReport::flag_for_review(&output.text_content(), p))
+    else {
+        return Ok(output);
+    };
+    tracing::info!(agent = %agent_name, %reason, "Report flagged for review");
+    if let Some(bus) = event_bus {
+        bus.publish(AgentEvent::UserNotification {
+            agent_name: agent_name.clone(),
+            message: reason,
+        })
+        .await;
+    }
+    Ok(ToolOutput::success(flagged))
+}
+
+/// One run of an agent: scope management, tool setup, agent execution,
+/// memory compaction, and result formatting. Branches on `memory_strategy`:
+/// - `ObsMemory` (and `SlidingWindow`, which is for chat sessions): uses
+///   in-loop observational memory, stores obs log
+/// - `Compaction`: uses post-execution LLM summarization with continuation
+#[allow(clippy::too_many_arguments)]
+async fn execute_agent_once(
+    config: AgentToolConfig,
+    task: String,
+    new_instance: bool,
+    instance_id: Option<String>,
+    base_tools: &Arc<ToolRegistry>,
+    provider: &Arc<dyn Provider>,
+    profile_registry: &SharedProfileRegistry,
+    external_agents: &AgentsConfig,
+    enabled_agents: &Option<Vec<String>>,
+    current_depth: u32,
+    max_depth: u32,
+    execution_context: &Option<ExecutionContext>,
+    event_bus: &Option<AgentEventBus>,
+    agent_memory: &Option<AgentMemory>,
+    scope: &str,
+    task_store: &Option<Arc<qq_tools::TaskStore>>,
+    compactor: &Option<Arc<dyn ContextCompactor>>,
+    regrounding: &Option<Regrounding>,
     context_window: Option<u32>,
     ask_network: bool,
     inherited_permissions: DelegationPermissions,
@@ -166,6 +337,19 @@ async fn execute_agent(
     };
     let effective_permissions = inherited_permissions.restrict_with(declared_permissions);
 
+    // Agents called from the main chat run at depth 1; deeper ones get a
+    // smaller budget when `[depth_budget]` is configured
+    let agent_depth = current_depth + 1;
+    let depth_budget = &external_agents.depth_budget;
+    if depth_budget.factor(agent_depth) < 1.0 {
+        tracing::info!(
+            agent = %config.agent_name,
+            depth = agent_depth,
+            factor = depth_budget.factor(agent_depth),
+            "Scaling agent budget for delegation depth"
+        );
+    }
+
     let child_scope = match &instance_id {
         Some(id) if !id.is_empty() => format!("{}/{}:{}", scope, config.agent_name, id),
         _ => format!("{}/{}", scope, config.agent_name),
@@ -184,6 +368,11 @@ async fn execute_agent(
     } else {
         (Vec::new(), String::new())
     };
+    // Tool results already in memory belong to earlier runs
+    let prior_call_ids: HashSet<String> = prior_history
+        .iter()
+        .filter_map(|m| m.tool_call_id.clone())
+        .collect();
 
     tracing::info!(
         agent = %config.agent_name,
@@ -225,6 +414,7 @@ async fn execute_agent(
             child_scope.clone(),
             task_store.clone(),
             compactor.clone(),
+            regrounding.clone(),
             context_window,
             ask_network,
             effective_permissions,
@@ -255,6 +445,13 @@ async fn execute_agent(
         )));
     }
 
+    // The agent ends its run by reporting through `finish`
+    agent_tools.register(Arc::new(FinishTool));
+
+    let agent_tools = match (external_agents.description_budget, context_window) {
+        (Some(share), Some(cw)) => qq_core::fit_descriptions(&agent_tools, cw, share),
+        _ => agent_tools,
+    };
     let agent_tools = Arc::new(agent_tools);
 
     let has_sub_agents = next_depth < max_depth;
@@ -274,8 +471,17 @@ async fn execute_agent(
         has_bash: has_run,
         has_network: !ask_network,
         is_read_only: effective_permissions.read_only,
+        has_finish: true,
     }, &agent_ctx);
-    let full_prompt = format!("{}\n\n---\n\n{}", preamble, config.system_prompt);
+    // Swap in a prompt experiment variant, if the agent has one configured
+    let prompt_variant = external_agents.pick_variant(&config.agent_name, experiments::roll());
+    let system_prompt = prompt_variant
+        .map(|v| v.system_prompt.as_str())
+        .unwrap_or(config.system_prompt.as_str());
+    let full_prompt = format!("{}\n\n---\n\n{}", preamble, system_prompt);
+
+    // Re-grounding restates the task itself, without the board prefix
+    let goal = task.clone();
 
     // Prepend task board for non-PM agents
     let augmented_task = if config.agent_name != "pm" {
@@ -296,36 +502,64 @@ async fn execute_agent(
     // Pass the child_scope so the handler can inject the correct agent chain
     // into IterationStart events (avoids the shared ExecutionContext stack bug
     // where parallel agents appear as nested).
-    let progress = event_bus.as_ref().map(|bus| bus.create_handler_with_chain(&child_scope));
+    // Record the run in the delegation graph (`qq graph`), under its caller
+    let graph_node = event_bus
+        .as_ref()
+        .and_then(|bus| bus.delegation_graph())
+        .map(|graph| {
+            let node = graph.start(&child_scope, &config.agent_name, &goal);
+            (Arc::clone(graph), node)
+        });
+    let progress = event_bus.as_ref().map(|bus| {
+        bus.create_handler_with_chain(&child_scope, graph_node.as_ref().map(|(_, node)| *node))
+    });
+
+    // Tally usage for experiment runs so it can be attributed to the variant
+    let tally = prompt_variant.map(|_| Arc::new(experiments::UsageTally::new(progress.clone())));
+    let progress: Option<Arc<dyn AgentProgressHandler>> = match tally {
+        Some(ref t) => Some(Arc::clone(t) as Arc<dyn AgentProgressHandler>),
+        None => progress,
+    };
+    let record_outcome = |outcome: &str| {
+        if let (Some(v), Some(t)) = (prompt_variant, tally.as_ref()) {
+            experiments::append(&t.record(&config.agent_name, &v.name, outcome));
+        }
+    };
 
     // Branch on memory strategy
-    match config.memory_strategy {
-        AgentMemoryStrategy::ObsMemory => {
+    let output: Result<ToolOutput, Error> = match config.memory_strategy {
+        AgentMemoryStrategy::ObsMemory | AgentMemoryStrategy::SlidingWindow => {
             // Obs-memory path: compactor in the loop, no continuation
             let mut agent_cfg = AgentConfig::new(config.agent_name.as_str())
                 .with_system_prompt(&full_prompt)
-                .with_prior_observation_log(prior_observation_log);
-
-            if let Some(limits) = config.tool_limits.clone() {
-                agent_cfg = agent_cfg.with_tool_limits(
-                    base_tools.resolve_tool_limits(limits),
-                );
-            }
+                .with_prior_observation_log(prior_observation_log)
+                .with_sampling(config.sampling.clone())
+                .with_finish_tool(FINISH_TOOL);
+            agent_cfg = apply_budget(agent_cfg, &config, base_tools, depth_budget, agent_depth);
 
-            // Wire up compactor and obs config
+            // Wire up compactor and obs config. The agent's own compaction
+            // prompt steers what the observer keeps.
             if let Some(ref c) = compactor {
+                let c = focused_compactor(c, &config.compact_prompt);
                 let obs_config = config.observation_config
                     .unwrap_or_else(|| match context_window {
                         Some(cw) => qq_core::ObservationConfig::from_context_window_for_agents(cw),
                         None => qq_core::ObservationConfig::for_agents(),
                     });
                 agent_cfg = agent_cfg
-                    .with_compactor(Arc::clone(c))
+                    .with_compactor(c)
                     .with_observation_config(obs_config);
             }
             if let Some(max_obs) = config.max_observations {
                 agent_cfg = agent_cfg.with_max_observations(max_obs);
             }
+            if let Some(r) = regrounding {
+                let mut r = r.clone().with_goal(goal);
+                if let Some(store) = task_store.clone() {
+                    r = r.with_task_board(Arc::new(move || store.format_board()));
+                }
+                agent_cfg = agent_cfg.with_regrounding(r);
+            }
 
             // Build context: prior history + new task
             let mut context = prior_history;
@@ -376,10 +610,19 @@ async fn execute_agent(
                     tracing::warn!(agent = %config.agent_name, error = %e, "Agent run failed (obs-memory)");
                 }
             }
+            record_outcome(match &result {
+                Ok(qq_core::AgentRunResult::Success { .. }) => "success",
+                Ok(qq_core::AgentRunResult::ObservationLimitReached { .. }) => "obs_limit_reached",
+                Ok(qq_core::AgentRunResult::MaxIterationsExceeded { .. }) => "max_iterations",
+                Ok(qq_core::AgentRunResult::RepetitionDetected { .. }) => "repetition_detected",
+                Ok(qq_core::AgentRunResult::TruncatedByLength { .. }) => "truncated_by_length",
+                Err(_) => "error",
+            });
 
             match result {
-                Ok(qq_core::AgentRunResult::Success { content, .. }) => {
-                    Ok(ToolOutput::success(content))
+                Ok(qq_core::AgentRunResult::Success { content, messages, .. }) => {
+                    let changes = run_changes(&messages, &prior_call_ids);
+                    Ok(ToolOutput::success(AgentReport::with_changes(&content, changes)))
                 }
                 Ok(qq_core::AgentRunResult::ObservationLimitReached { content, .. }) => {
                     let output = if content.is_empty() {
@@ -412,14 +655,11 @@ async fn execute_agent(
 
         AgentMemoryStrategy::Compaction => {
             // Compaction path: post-execution LLM summarization with continuation
-            let mut agent_cfg = AgentConfig::new(config.agent_name.as_str())
-                .with_system_prompt(&full_prompt);
-
-            if let Some(limits) = config.tool_limits {
-                agent_cfg = agent_cfg.with_tool_limits(
-                    base_tools.resolve_tool_limits(limits),
-                );
-            }
+            let agent_cfg = AgentConfig::new(config.agent_name.as_str())
+                .with_system_prompt(&full_prompt)
+                .with_sampling(config.sampling.clone())
+                .with_finish_tool(FINISH_TOOL);
+            let agent_cfg = apply_budget(agent_cfg, &config, base_tools, depth_budget, agent_depth);
 
             let continuation_config = ContinuationConfig::default();
             let result = execute_with_continuation(
@@ -454,9 +694,17 @@ async fn execute_agent(
                 }
                 AgentExecutionResult::Error(_) => {} // don't store on error
             }
+            record_outcome(match &result {
+                AgentExecutionResult::Success { .. } => "success",
+                AgentExecutionResult::MaxContinuationsReached { .. } => "max_continuations",
+                AgentExecutionResult::Error(_) => "error",
+            });
 
             match result {
-                AgentExecutionResult::Success { content, .. } => Ok(ToolOutput::success(content)),
+                AgentExecutionResult::Success { content, messages } => {
+                    let changes = run_changes(&messages, &prior_call_ids);
+                    Ok(ToolOutput::success(AgentReport::with_changes(&content, changes)))
+                }
                 AgentExecutionResult::MaxContinuationsReached {
                     partial_result,
                     continuations,
@@ -470,7 +718,15 @@ async fn execute_agent(
                 }
             }
         }
+    };
+
+    if let Some((graph, node)) = graph_node {
+        match &output {
+            Ok(out) => graph.finish(node, out.is_error, &out.text_content()),
+            Err(e) => graph.finish(node, true, &e.to_string()),
+        }
     }
+    output
 }
 
 // =============================================================================
@@ -543,6 +799,7 @@ fn spawn_background_agent(
     scope: String,
     task_store: Option<Arc<qq_tools::TaskStore>>,
     compactor: Option<Arc<dyn ContextCompactor>>,
+    regrounding: Option<Regrounding>,
     context_window: Option<u32>,
     ask_network: bool,
     inherited_permissions: DelegationPermissions,
@@ -569,6 +826,7 @@ fn spawn_background_agent(
             &scope,
             &task_store,
             &compactor,
+            &regrounding,
             context_window,
             ask_network,
             inherited_permissions,
@@ -647,6 +905,8 @@ pub struct InternalAgentTool {
     task_store: Option<Arc<qq_tools::TaskStore>>,
     /// Context compactor for observational memory
     compactor: Option<Arc<dyn ContextCompactor>>,
+    /// Re-grounding settings for agent runs
+    regrounding: Option<Regrounding>,
     /// Context window size in tokens for deriving obs thresholds
     context_window: Option<u32>,
     /// Whether network access requires approval (--ask-network)
@@ -672,6 +932,7 @@ impl InternalAgentTool {
         scope: String,
         task_store: Option<Arc<qq_tools::TaskStore>>,
         compactor: Option<Arc<dyn ContextCompactor>>,
+        regrounding: Option<Regrounding>,
         context_window: Option<u32>,
         ask_network: bool,
         inherited_permissions: DelegationPermissions,
@@ -693,6 +954,7 @@ impl InternalAgentTool {
             scope,
             task_store,
             compactor,
+            regrounding,
             context_window,
             ask_network,
             inherited_permissions,
@@ -759,15 +1021,19 @@ impl Tool for InternalAgentTool {
 
         let mut patterns: Vec<qq_core::ToolPattern> = self.agent.tool_patterns();
 
-        // Auto-inject run + mount_external + access-request tools unless disabled via config
+        // Auto-inject run + read_files + mount_external + access-request tools unless disabled via config
         let no_run = self.external_agents.get_builtin_no_bash(self.agent.name());
         if !no_run && !patterns.is_empty() {
             let run_pat = qq_core::ToolPattern::Exact(qq_core::ToolRef::Internal("run".into()));
+            let read_pat = qq_core::ToolPattern::Exact(qq_core::ToolRef::Internal("read_files".into()));
             let mount_pat = qq_core::ToolPattern::Exact(qq_core::ToolRef::Internal("mount_external".into()));
             let sensitive_pat = qq_core::ToolPattern::Exact(qq_core::ToolRef::Internal("request_sensitive_access".into()));
             if !patterns.contains(&run_pat) {
                 patterns.push(run_pat);
             }
+            if !patterns.contains(&read_pat) {
+                patterns.push(read_pat);
+            }
             if !patterns.contains(&mount_pat) {
                 patterns.push(mount_pat);
             }
@@ -782,6 +1048,17 @@ impl Tool for InternalAgentTool {
             }
         }
 
+        // Every agent with tools can check the clock and the environment, and
+        // read back outputs saved as artifacts
+        if !patterns.is_empty() {
+            for name in ["datetime", "env_info", "fetch_artifact"] {
+                let pat = qq_core::ToolPattern::Exact(qq_core::ToolRef::Internal(name.into()));
+                if !patterns.contains(&pat) {
+                    patterns.push(pat);
+                }
+            }
+        }
+
         // Append config tools (replaces extra_tools)
         for tool_str in self.external_agents.get_builtin_tools(self.agent.name()) {
             let pattern = qq_core::ToolPattern::parse(tool_str);
@@ -796,12 +1073,14 @@ impl Tool for InternalAgentTool {
             agent_name: self.agent.name().to_string(),
             system_prompt: self.agent.system_prompt().to_string(),
             tool_names,
+            max_turns: self.external_agents.get_builtin_max_turns(self.agent.name()),
             tool_limits,
             compact_prompt,
             is_read_only: self.agent.is_read_only(),
             memory_strategy,
             max_observations,
             observation_config,
+            sampling: self.external_agents.get_builtin_sampling(self.agent.name()),
         };
 
         // Resolve the provider for this agent right now — picks up `/profiles`
@@ -838,6 +1117,7 @@ impl Tool for InternalAgentTool {
                 self.scope.clone(),
                 self.task_store.clone(),
                 self.compactor.clone(),
+                self.regrounding.clone(),
                 self.context_window,
                 self.ask_network,
                 self.inherited_permissions,
@@ -867,6 +1147,7 @@ impl Tool for InternalAgentTool {
             &self.scope,
             &self.task_store,
             &self.compactor,
+            &self.regrounding,
             self.context_window,
             self.ask_network,
             self.inherited_permissions,
@@ -919,6 +1200,8 @@ pub struct ExternalAgentTool {
     task_store: Option<Arc<qq_tools::TaskStore>>,
     /// Context compactor for observational memory
     compactor: Option<Arc<dyn ContextCompactor>>,
+    /// Re-grounding settings for agent runs
+    regrounding: Option<Regrounding>,
     /// Context window size in tokens for deriving obs thresholds
     context_window: Option<u32>,
     /// Whether network access requires approval (--ask-network)
@@ -945,6 +1228,7 @@ impl ExternalAgentTool {
         scope: String,
         task_store: Option<Arc<qq_tools::TaskStore>>,
         compactor: Option<Arc<dyn ContextCompactor>>,
+        regrounding: Option<Regrounding>,
         context_window: Option<u32>,
         ask_network: bool,
         inherited_permissions: DelegationPermissions,
@@ -967,6 +1251,7 @@ impl ExternalAgentTool {
             scope,
             task_store,
             compactor,
+            regrounding,
             context_window,
             ask_network,
             inherited_permissions,
@@ -1003,11 +1288,14 @@ impl Tool for ExternalAgentTool {
 
         let mut tool_names = self.agent_def.tools.clone();
 
-        // Auto-inject run + mount_external + access-request tools unless disabled via config
+        // Auto-inject run + read_files + mount_external + access-request tools unless disabled via config
         if !self.agent_def.no_bash && !tool_names.is_empty() {
             if !tool_names.iter().any(|n| n == "run") {
                 tool_names.push("run".to_string());
             }
+            if !tool_names.iter().any(|n| n == "read_files") {
+                tool_names.push("read_files".to_string());
+            }
             if !tool_names.iter().any(|n| n == "mount_external") {
                 tool_names.push("mount_external".to_string());
             }
@@ -1019,6 +1307,16 @@ impl Tool for ExternalAgentTool {
             }
         }
 
+        // Every agent with tools can check the clock and the environment, and
+        // read back outputs saved as artifacts
+        if !tool_names.is_empty() {
+            for name in ["datetime", "env_info", "fetch_artifact"] {
+                if !tool_names.iter().any(|n| n == name) {
+                    tool_names.push(name.to_string());
+                }
+            }
+        }
+
         // Build observation config from agent definition fields if any are set
         let observation_config = {
             let def = &self.agent_def;
@@ -1051,6 +1349,7 @@ impl Tool for ExternalAgentTool {
             agent_name: self.agent_name.clone(),
             system_prompt: self.agent_def.system_prompt.clone(),
             tool_names,
+            max_turns: Some(self.agent_def.max_turns),
             tool_limits,
             compact_prompt: self.agent_def.compact_prompt
                 .as_deref()
@@ -1060,6 +1359,7 @@ impl Tool for ExternalAgentTool {
             memory_strategy: self.agent_def.memory_strategy.clone(),
             max_observations: self.agent_def.max_observations,
             observation_config,
+            sampling: self.agent_def.sampling(),
         };
 
         // Resolve the provider for this agent right now — picks up `/profiles`
@@ -1096,6 +1396,7 @@ impl Tool for ExternalAgentTool {
                 self.scope.clone(),
                 self.task_store.clone(),
                 self.compactor.clone(),
+                self.regrounding.clone(),
                 self.context_window,
                 self.ask_network,
                 self.inherited_permissions,
@@ -1125,6 +1426,7 @@ impl Tool for ExternalAgentTool {
             &self.scope,
             &self.task_store,
             &self.compactor,
+            &self.regrounding,
             self.context_window,
             self.ask_network,
             self.inherited_permissions,
@@ -1161,6 +1463,7 @@ impl Tool for ExternalAgentTool {
 /// * `agent_memory` - Optional scoped agent memory for persistent instance state
 /// * `scope` - Current scope path (e.g., "pm", "pm/coder")
 /// * `compactor` - Optional context compactor for observational memory
+/// * `regrounding` - Optional re-grounding settings for agent runs
 /// * `context_window` - Optional context window size in tokens for deriving obs thresholds
 #[allow(clippy::too_many_arguments)]
 pub fn create_agent_tools(
@@ -1176,6 +1479,7 @@ pub fn create_agent_tools(
     scope: String,
     task_store: Option<Arc<qq_tools::TaskStore>>,
     compactor: Option<Arc<dyn ContextCompactor>>,
+    regrounding: Option<Regrounding>,
     context_window: Option<u32>,
     ask_network: bool,
     inherited_permissions: DelegationPermissions,
@@ -1209,6 +1513,7 @@ pub fn create_agent_tools(
                 scope.clone(),
                 task_store.clone(),
                 compactor.clone(),
+                regrounding.clone(),
                 context_window,
                 ask_network,
                 inherited_permissions,
@@ -1234,6 +1539,7 @@ pub fn create_agent_tools(
                 scope.clone(),
                 task_store.clone(),
                 compactor.clone(),
+                regrounding.clone(),
                 context_window,
                 ask_network,
                 inherited_permissions,
@@ -1285,12 +1591,44 @@ fn find_safe_compact_point(messages: &[Message], target: usize) -> usize {
     end
 }
 
+/// `compactor` specialized by an agent's compaction prompt, unless that's
+/// the generic one it already covers.
+fn focused_compactor(
+    compactor: &Arc<dyn ContextCompactor>,
+    compact_prompt: &str,
+) -> Arc<dyn ContextCompactor> {
+    if compact_prompt.is_empty() || compact_prompt == DEFAULT_COMPACT_PROMPT {
+        return Arc::clone(compactor);
+    }
+    compactor
+        .with_focus(compact_prompt)
+        .unwrap_or_else(|| Arc::clone(compactor))
+}
+
 /// Attempt LLM-summarized compaction of agent messages.
 ///
 /// If the total message bytes exceed `AGENT_COMPACT_THRESHOLD_BYTES`, older messages
 /// are summarized by the LLM using the provided `compact_prompt`, preserving recent
 /// messages. On failure, returns the original messages unchanged (the downstream
 /// `trim_to_budget` serves as safety net).
+/// Files changed by this run's `run` calls, summed from the `[changes]`
+/// footers of tool results not already in memory.
+fn run_changes(messages: &[Message], prior_call_ids: &HashSet<String>) -> ChangeStats {
+    let mut changes = ChangeStats::default();
+    for message in messages {
+        let Some(ref id) = message.tool_call_id else {
+            continue;
+        };
+        if prior_call_ids.contains(id) {
+            continue;
+        }
+        if let Some(stats) = ChangeStats::from_output(&message.content.to_string_lossy()) {
+            changes.merge(stats);
+        }
+    }
+    changes
+}
+
 async fn compact_agent_messages(
     provider: &Arc<dyn Provider>,
     messages: Vec<Message>,
@@ -1365,6 +1703,84 @@ async fn compact_agent_messages(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use qq_core::testing::MockProvider;
+    use qq_core::ToolCall;
+
+    /// One compaction round over a long synthetic session: the agent's own
+    /// prompt goes to the model, and its summary, with the role's key
+    /// details, replaces the older messages.
+    #[tokio::test]
+    async fn test_compaction_round_keeps_role_sections() {
+        let cases: [(InternalAgentType, &str); 3] = [
+            (
+                InternalAgentType::Coder,
+                "Modified crates/qq-core/src/text.rs:\n```diff\n-fn wrap(s: &str)\n+fn wrap(s: &str, width: usize)\n```",
+            ),
+            (
+                InternalAgentType::Researcher,
+                "Rust 1.80 stabilized LazyLock (https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html): \"LazyLock is now stable\"",
+            ),
+            (
+                InternalAgentType::Planner,
+                "## Phase 1: Parser\n1. Add tokenizer - Agent[coder]\n2. Add AST\n   - Depends on: step 1",
+            ),
+        ];
+        for (agent_type, summary) in cases {
+            let agent = agent_type.create();
+            let mut messages = vec![Message::user("task")];
+            for i in 0..12 {
+                let call = ToolCall::new(format!("c{}", i), "read_file", serde_json::json!({}));
+                messages.push(Message::assistant_with_tool_calls("", vec![call]));
+                messages.push(Message::tool_result(format!("c{}", i), "x".repeat(20_000)));
+                messages.push(Message::assistant(format!("step {}", i)));
+            }
+            messages.push(Message::assistant("latest"));
+
+            let mock = Arc::new(MockProvider::new());
+            mock.queue_response(summary);
+            let provider: Arc<dyn Provider> = mock.clone();
+            let compacted =
+                compact_agent_messages(&provider, messages.clone(), agent.compact_prompt()).await;
+
+            let request = mock.last_request().unwrap();
+            let asked = request.messages.last().unwrap().content.to_string_lossy();
+            assert_eq!(asked, agent.compact_prompt(), "{}", agent.name());
+
+            let head = compacted[0].content.to_string_lossy();
+            assert_eq!(head, format!("## Prior Session Summary\n\n{}", summary));
+            assert!(compacted.len() < messages.len());
+            assert_eq!(compacted.last().unwrap().content.to_string_lossy(), "latest");
+        }
+    }
+
+    #[test]
+    fn test_focused_compactor_skips_generic_prompt() {
+        struct Focusable;
+
+        #[async_trait]
+        impl ContextCompactor for Focusable {
+            async fn observe(&self, _messages: &[Message]) -> Result<String, Error> {
+                Ok(String::new())
+            }
+            async fn reflect(&self, _observation_log: &str) -> Result<String, Error> {
+                Ok(String::new())
+            }
+            fn with_focus(&self, _focus: &str) -> Option<Arc<dyn ContextCompactor>> {
+                Some(Arc::new(Focusable))
+            }
+        }
+
+        let compactor: Arc<dyn ContextCompactor> = Arc::new(Focusable);
+        let same = focused_compactor(&compactor, DEFAULT_COMPACT_PROMPT);
+        assert!(Arc::ptr_eq(&same, &compactor));
+        let coder = InternalAgentType::Coder.create();
+        let focused = focused_compactor(&compactor, coder.compact_prompt());
+        assert!(!Arc::ptr_eq(&focused, &compactor));
+
+        // Compactors that can't specialize are used as they are
+        let mock: Arc<dyn ContextCompactor> = Arc::new(qq_core::testing::MockCompactor::new());
+        assert!(Arc::ptr_eq(&focused_compactor(&mock, coder.compact_prompt()), &mock));
+    }
 
     #[test]
     fn test_agent_tool_definition_structure() {
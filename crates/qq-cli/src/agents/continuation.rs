@@ -5,7 +5,7 @@
 
 use std::sync::Arc;
 
-use qq_core::{Agent, AgentConfig, AgentProgressHandler, AgentRunResult, CompletionRequest, Message, Provider, ToolRegistry};
+use qq_core::{Agent, AgentConfig, AgentProgressHandler, AgentRunResult, CompletionRequest, Message, Provider, StateDelta, ToolRegistry};
 
 use crate::event_bus::{AgentEvent, AgentEventBus};
 
@@ -107,7 +107,18 @@ fn extract_tag(content: &str, tag: &str) -> String {
 }
 
 /// Format a summary for inclusion in agent context.
-pub fn format_summary_context(summary: &ExecutionSummary, original_task: &str) -> String {
+///
+/// The state delta is appended verbatim after the LLM summary so that file
+/// paths and task ids survive even when the summary leaves them out.
+pub fn format_summary_context(
+    summary: &ExecutionSummary,
+    original_task: &str,
+    state_delta: &StateDelta,
+) -> String {
+    let state_delta = state_delta
+        .render()
+        .map(|delta| format!("{}\n", delta))
+        .unwrap_or_default();
     format!(
         r#"## Continuation Context
 
@@ -131,7 +142,7 @@ You are continuing a task that was interrupted. Here is your previous progress:
 ### Important Context
 {important_context}
 
----
+{state_delta}---
 
 Continue from where you left off. Do NOT repeat work already done. Focus on completing the remaining tasks."#,
         original_task = original_task,
@@ -140,6 +151,7 @@ Continue from where you left off. Do NOT repeat work already done. Focus on comp
         accomplishments = summary.accomplishments,
         remaining_work = summary.remaining_work,
         important_context = summary.important_context,
+        state_delta = state_delta,
     )
 }
 
@@ -261,6 +273,8 @@ pub async fn execute_with_continuation(
     let mut current_context = prior_history;
     current_context.push(Message::user(original_task.as_str()));
     let mut last_partial_result = String::new();
+    // Accumulated across runs: each continuation starts from a fresh context
+    let mut state_delta = StateDelta::default();
 
     loop {
         let result = Agent::run_once_with_progress(
@@ -314,7 +328,8 @@ pub async fn execute_with_continuation(
                         agent_name: config.id.0.clone(),
                         continuation_number: continuation_count,
                         max_continuations: continuation_config.max_continuations,
-                    });
+                    })
+                    .await;
                 }
 
                 // Generate summary from the actual agent conversation history.
@@ -335,8 +350,10 @@ pub async fn execute_with_continuation(
                     summary.accomplishments, summary.remaining_work
                 );
 
-                // Build new context with summary
-                let continuation_prompt = format_summary_context(&summary, &original_task);
+                // Build new context with summary plus the journal-derived delta
+                state_delta.absorb(&agent_messages);
+                let continuation_prompt =
+                    format_summary_context(&summary, &original_task, &state_delta);
                 current_context = vec![Message::user(continuation_prompt.as_str())];
             }
             Err(e) => {
@@ -393,7 +410,8 @@ Found important data
             important_context: "File path: /foo/bar".to_string(),
         };
 
-        let context = format_summary_context(&summary, "Original task description");
+        let context =
+            format_summary_context(&summary, "Original task description", &StateDelta::default());
 
         assert!(context.contains("Original task description"));
         assert!(context.contains("Step 1, Step 2"));
@@ -404,6 +422,36 @@ Found important data
         assert!(context.contains("Continue from where you left off"));
     }
 
+    #[test]
+    fn test_format_summary_context_includes_state_delta() {
+        let summary = ExecutionSummary {
+            steps_taken: String::new(),
+            discoveries: String::new(),
+            accomplishments: "Edited the parser".to_string(),
+            remaining_work: "Run tests".to_string(),
+            important_context: String::new(),
+        };
+        let messages = vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![qq_core::ToolCall::new(
+                    "1",
+                    "run",
+                    serde_json::json!({"command": "sed -i 's/a/b/' src/parse.rs"}),
+                )],
+            ),
+            Message::tool_result("1", ""),
+        ];
+
+        let context =
+            format_summary_context(&summary, "Fix parser", &StateDelta::from_messages(&messages));
+
+        assert!(context.contains("### State Delta (from tool journal)"));
+        assert!(context.contains("- src/parse.rs"));
+        // Delta sits before the closing instructions
+        assert!(context.find("src/parse.rs") < context.find("Continue from where you left off"));
+    }
+
     #[test]
     fn test_continuation_config_default() {
         let config = ContinuationConfig::default();
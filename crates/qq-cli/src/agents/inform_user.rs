@@ -73,7 +73,8 @@ impl Tool for InformUserTool {
         self.event_bus.publish(AgentEvent::UserNotification {
             agent_name: self.agent_name.clone(),
             message: args.message,
-        });
+        })
+        .await;
 
         Ok(ToolOutput::success("Message sent to user"))
     }
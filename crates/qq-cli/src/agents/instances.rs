@@ -0,0 +1,539 @@
+//! Named, long-lived agent instances.
+//!
+//! Every `Agent[..]` call resumes the memory of its scope for the rest of
+//! the session, but nothing survives a restart. `use_agent_instance` gives
+//! the PM agents with a name — a "tracker" that owns the task board, a
+//! "release" coder that knows the release checklist — whose history is
+//! saved after each call and re-attached in later sessions of the same
+//! project.
+//!
+//! Lifecycle: an instance is created by the first call that names an agent,
+//! resumed by later calls with the same name, and ended with `end: true` or
+//! forgotten once unused for `[instances] named_ttl_days`. Named instances
+//! are pinned in [`AgentMemory`], so session eviction never drops them.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use qq_core::{
+    AgentMemory, Error, Message, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters,
+};
+use qq_tools::{write_atomically, FileLock};
+
+/// A saved named instance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedInstance {
+    pub name: String,
+    /// Project the instance belongs to (canonical tools root)
+    pub project: String,
+    /// Agent the instance runs, e.g. "coder"
+    pub agent: String,
+    pub created: DateTime<Utc>,
+    pub last_used: DateTime<Utc>,
+    #[serde(default)]
+    pub calls: u32,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+    #[serde(default)]
+    pub observation_log: String,
+}
+
+/// Named instances backed by a JSON file shared by all projects.
+///
+/// Every change is written through immediately, under the file lock and
+/// against the file's current contents, so concurrent sessions don't drop
+/// each other's instances. Write failures are logged and the in-memory
+/// state stays authoritative for the session.
+pub struct NamedInstanceStore {
+    path: Option<PathBuf>,
+    project: String,
+    instances: Mutex<Vec<NamedInstance>>,
+}
+
+impl NamedInstanceStore {
+    /// Load instances from `path`, dropping any unused for longer than `ttl`.
+    /// A missing or unreadable file starts empty.
+    pub fn load(path: PathBuf, project: impl Into<String>, ttl: Option<Duration>) -> Self {
+        let store = Self {
+            path: Some(path.clone()),
+            project: project.into(),
+            instances: Mutex::new(read_instances(&path).unwrap_or_default()),
+        };
+        if let Some(ttl) = ttl.and_then(|t| chrono::Duration::from_std(t).ok()) {
+            let cutoff = Utc::now() - ttl;
+            store.update(|instances| {
+                let before = instances.len();
+                instances.retain(|i| i.last_used >= cutoff);
+                let expired = before - instances.len();
+                if expired > 0 {
+                    tracing::info!(expired, "Expired named agent instances");
+                }
+                ((), expired > 0)
+            });
+        }
+        store
+    }
+
+    /// Store that is never written to disk.
+    pub fn in_memory(project: impl Into<String>) -> Self {
+        Self {
+            path: None,
+            project: project.into(),
+            instances: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// This project's instance named `name`.
+    pub fn get(&self, name: &str) -> Option<NamedInstance> {
+        self.update(|instances| {
+            let found = instances
+                .iter()
+                .find(|i| i.project == self.project && i.name == name)
+                .cloned();
+            (found, false)
+        })
+    }
+
+    /// This project's instances, most recently used first.
+    pub fn list(&self) -> Vec<NamedInstance> {
+        let mut instances: Vec<NamedInstance> = self.update(|instances| {
+            let mine = instances
+                .iter()
+                .filter(|i| i.project == self.project)
+                .cloned()
+                .collect();
+            (mine, false)
+        });
+        instances.sort_by_key(|i| std::cmp::Reverse(i.last_used));
+        instances
+    }
+
+    /// Insert or replace an instance (matched by project and name).
+    pub fn put(&self, instance: NamedInstance) {
+        self.update(|instances| {
+            instances.retain(|i| !(i.project == instance.project && i.name == instance.name));
+            instances.push(instance);
+            ((), true)
+        })
+    }
+
+    /// Remove this project's instance named `name`. Returns it if it existed.
+    pub fn remove(&self, name: &str) -> Option<NamedInstance> {
+        self.update(|instances| {
+            match instances
+                .iter()
+                .position(|i| i.project == self.project && i.name == name)
+            {
+                Some(pos) => (Some(instances.remove(pos)), true),
+                None => (None, false),
+            }
+        })
+    }
+
+    /// Apply `change` to the latest instances and save them if it reports a
+    /// change. Instances saved by other sessions are picked up first.
+    fn update<R>(&self, change: impl FnOnce(&mut Vec<NamedInstance>) -> (R, bool)) -> R {
+        let mut instances = self.instances.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(ref path) = self.path else {
+            return change(&mut instances).0;
+        };
+        let lock = FileLock::acquire(path);
+        if lock.is_ok() {
+            if let Some(latest) = read_instances(path) {
+                *instances = latest;
+            }
+        }
+        let (result, changed) = change(&mut instances);
+        if changed {
+            let saved = lock.and_then(|_lock| {
+                let json = serde_json::to_string(&*instances).map_err(std::io::Error::other)?;
+                write_atomically(path, json.as_bytes())
+            });
+            if let Err(e) = saved {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to save agent instances");
+            }
+        }
+        result
+    }
+}
+
+/// Read the instances file. `None` when missing or corrupt.
+fn read_instances(path: &Path) -> Option<Vec<NamedInstance>> {
+    let text = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text)
+        .inspect_err(|e| {
+            tracing::warn!(path = %path.display(), error = %e, "Ignoring corrupt agent instances file");
+        })
+        .ok()
+}
+
+/// Memory scope of a named instance: what `Agent[agent]` uses for
+/// `instance_id: "@name"` when called from `scope`.
+fn instance_scope(scope: &str, agent: &str, name: &str) -> String {
+    format!("{}/{}:@{}", scope, agent, name)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+const USE_INSTANCE_DESC: &str = "\
+Run a task on a named, long-lived agent instance. Unlike a plain Agent[..] call, \
+a named instance keeps its conversation history across turns AND sessions, so it \
+can own an ongoing responsibility (e.g. a 'tracker' that maintains the task board, \
+a 'release' coder that knows the release checklist).
+
+- First use: give `name`, `agent`, and `task`; the instance is created.
+- Later uses (this or a later session): give `name` and `task`; the saved history \
+is re-attached.
+- `end: true` deletes the instance (after running `task`, if given).";
+
+/// Tool that runs tasks on named agent instances.
+pub struct UseAgentInstanceTool {
+    /// Agent tools at the caller's depth (`Agent[..]`)
+    agent_tools: Vec<Arc<dyn Tool>>,
+    memory: AgentMemory,
+    store: Arc<NamedInstanceStore>,
+    /// Scope of the agent tools (e.g. "pm")
+    scope: String,
+}
+
+impl UseAgentInstanceTool {
+    pub fn new(
+        agent_tools: Vec<Arc<dyn Tool>>,
+        memory: AgentMemory,
+        store: Arc<NamedInstanceStore>,
+        scope: impl Into<String>,
+    ) -> Self {
+        Self {
+            agent_tools,
+            memory,
+            store,
+            scope: scope.into(),
+        }
+    }
+
+    fn agent_tool(&self, agent: &str) -> Option<&Arc<dyn Tool>> {
+        let tool_name = format!("Agent[{}]", agent);
+        self.agent_tools.iter().find(|t| t.name() == tool_name)
+    }
+
+    /// Delete an instance from disk and memory.
+    async fn end(&self, instance: &NamedInstance) {
+        self.store.remove(&instance.name);
+        self.memory
+            .clear_scope(&instance_scope(
+                &self.scope,
+                &instance.agent,
+                &instance.name,
+            ))
+            .await;
+    }
+}
+
+#[derive(Deserialize)]
+struct UseInstanceArgs {
+    name: String,
+    #[serde(default)]
+    agent: Option<String>,
+    #[serde(default)]
+    task: Option<String>,
+    #[serde(default)]
+    end: bool,
+}
+
+#[async_trait]
+impl Tool for UseAgentInstanceTool {
+    fn name(&self) -> &str {
+        "use_agent_instance"
+    }
+
+    fn description(&self) -> &str {
+        "Run a task on a named, long-lived agent instance"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        let existing: Vec<String> = self
+            .store
+            .list()
+            .iter()
+            .map(|i| format!("{} (Agent[{}], {} calls)", i.name, i.agent, i.calls))
+            .collect();
+        let name_desc = if existing.is_empty() {
+            "Instance name (letters, digits, '-', '_'). No instances exist yet.".to_string()
+        } else {
+            format!(
+                "Instance name (letters, digits, '-', '_'). Existing: {}",
+                existing.join(", ")
+            )
+        };
+        ToolDefinition::new(self.name(), USE_INSTANCE_DESC).with_parameters(
+            ToolParameters::new()
+                .add_property("name", PropertySchema::string(name_desc), true)
+                .add_property(
+                    "agent",
+                    PropertySchema::string(
+                        "Agent to run, e.g. 'coder'. Required when creating an instance; \
+                         must match the instance's agent otherwise.",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "task",
+                    PropertySchema::string("Task for the instance. Required unless ending it."),
+                    false,
+                )
+                .add_property(
+                    "end",
+                    PropertySchema::boolean("Delete the instance after this call.")
+                        .with_default(serde_json::Value::Bool(false)),
+                    false,
+                ),
+        )
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: UseInstanceArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool(self.name(), format!("Invalid arguments: {}", e)))?;
+        if !valid_name(&args.name) {
+            return Ok(ToolOutput::error(format!(
+                "Invalid instance name '{}': use letters, digits, '-' and '_'",
+                args.name
+            )));
+        }
+        let saved = self.store.get(&args.name);
+
+        let Some(task) = args.task else {
+            return Ok(match (args.end, saved) {
+                (true, Some(instance)) => {
+                    self.end(&instance).await;
+                    ToolOutput::success(format!(
+                        "Ended instance '{}' (Agent[{}], {} calls).",
+                        instance.name, instance.agent, instance.calls
+                    ))
+                }
+                (true, None) => ToolOutput::error(format!("No instance named '{}'", args.name)),
+                (false, _) => ToolOutput::error("task is required unless end is true"),
+            });
+        };
+
+        let requested = args.agent.as_deref().map(|a| {
+            a.trim_start_matches("Agent[")
+                .trim_end_matches(']')
+                .to_string()
+        });
+        let agent = match (&saved, requested) {
+            (Some(instance), Some(agent)) if agent != instance.agent => {
+                return Ok(ToolOutput::error(format!(
+                    "Instance '{}' runs Agent[{}], not Agent[{}]. End it first or pick another name.",
+                    instance.name, instance.agent, agent
+                )));
+            }
+            (Some(instance), _) => instance.agent.clone(),
+            (None, Some(agent)) => agent,
+            (None, None) => {
+                return Ok(ToolOutput::error(format!(
+                    "No instance named '{}'. To create it, also give `agent`.",
+                    args.name
+                )));
+            }
+        };
+        let Some(tool) = self.agent_tool(&agent) else {
+            return Ok(ToolOutput::error(format!("Unknown agent '{}'", agent)));
+        };
+
+        let scope = instance_scope(&self.scope, &agent, &args.name);
+        let now = Utc::now();
+        let (created, calls) = saved.as_ref().map_or((now, 0), |i| (i.created, i.calls));
+        tracing::info!(
+            instance = %args.name,
+            agent = %agent,
+            resumed = saved.is_some(),
+            "Running named agent instance"
+        );
+        self.memory
+            .pin(&scope, saved.map(|i| (i.messages, i.observation_log)))
+            .await;
+
+        let output = tool
+            .execute(serde_json::json!({
+                "task": task,
+                "instance_id": format!("@{}", args.name),
+            }))
+            .await?;
+
+        let (messages, observation_log) = self.memory.get_state(&scope).await;
+        let instance = NamedInstance {
+            name: args.name,
+            project: self.store.project.clone(),
+            agent,
+            created,
+            last_used: now,
+            calls: calls + 1,
+            messages,
+            observation_log,
+        };
+        if args.end {
+            self.end(&instance).await;
+        } else {
+            self.store.put(instance);
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Agent tool stand-in that records its arguments into the scope's memory.
+    struct EchoAgent {
+        memory: AgentMemory,
+    }
+
+    #[async_trait]
+    impl Tool for EchoAgent {
+        fn name(&self) -> &str {
+            "Agent[coder]"
+        }
+
+        fn description(&self) -> &str {
+            "echo"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new(self.name(), self.description())
+        }
+
+        async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+            let id = arguments["instance_id"].as_str().unwrap();
+            let task = arguments["task"].as_str().unwrap();
+            let scope = format!("pm/coder:{}", id);
+            let mut messages = self.memory.get_messages(&scope).await;
+            messages.push(Message::user(task));
+            let seen = messages.len();
+            self.memory.store_messages(&scope, messages, 0).await;
+            Ok(ToolOutput::success(format!("{} messages", seen)))
+        }
+    }
+
+    fn tool(memory: &AgentMemory, store: Arc<NamedInstanceStore>) -> UseAgentInstanceTool {
+        let agent: Arc<dyn Tool> = Arc::new(EchoAgent {
+            memory: memory.clone(),
+        });
+        UseAgentInstanceTool::new(vec![agent], memory.clone(), store, "pm")
+    }
+
+    async fn run(tool: &UseAgentInstanceTool, args: serde_json::Value) -> ToolOutput {
+        tool.execute(args).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_instance_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent_instances.json");
+
+        let memory = AgentMemory::new();
+        let store = Arc::new(NamedInstanceStore::load(path.clone(), "/proj", None));
+        let use_instance = tool(&memory, store);
+        let missing = run(
+            &use_instance,
+            serde_json::json!({"name": "tracker", "task": "x"}),
+        )
+        .await;
+        assert!(missing.is_error);
+        let first =
+            serde_json::json!({"name": "tracker", "agent": "coder", "task": "own the board"});
+        assert_eq!(run(&use_instance, first).await.text_content(), "1 messages");
+
+        // A new session: fresh memory, same file
+        let memory = AgentMemory::new();
+        let store = Arc::new(NamedInstanceStore::load(path.clone(), "/proj", None));
+        assert_eq!(store.get("tracker").unwrap().calls, 1);
+        let use_instance = tool(&memory, Arc::clone(&store));
+        let again = serde_json::json!({"name": "tracker", "task": "triage"});
+        assert_eq!(run(&use_instance, again).await.text_content(), "2 messages");
+        assert_eq!(store.get("tracker").unwrap().messages.len(), 2);
+
+        // Other projects don't see it
+        let other = NamedInstanceStore::load(path.clone(), "/other", None);
+        assert!(other.get("tracker").is_none());
+
+        let mismatch = serde_json::json!({"name": "tracker", "agent": "writer", "task": "x"});
+        assert!(run(&use_instance, mismatch).await.is_error);
+
+        let end = run(
+            &use_instance,
+            serde_json::json!({"name": "tracker", "end": true}),
+        )
+        .await;
+        assert!(!end.is_error);
+        assert!(store.get("tracker").is_none());
+        assert!(memory.get_messages("pm/coder:@tracker").await.is_empty());
+    }
+
+    #[test]
+    fn test_store_expires_unused_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent_instances.json");
+        let store = NamedInstanceStore::load(path.clone(), "/proj", None);
+        let now = Utc::now();
+        for (name, days) in [("fresh", 1), ("stale", 40)] {
+            store.put(NamedInstance {
+                name: name.to_string(),
+                project: "/proj".to_string(),
+                agent: "coder".to_string(),
+                created: now,
+                last_used: now - chrono::Duration::days(days),
+                calls: 1,
+                messages: Vec::new(),
+                observation_log: String::new(),
+            });
+        }
+
+        let reloaded =
+            NamedInstanceStore::load(path, "/proj", Some(Duration::from_secs(30 * 24 * 3600)));
+        let names: Vec<String> = reloaded.list().into_iter().map(|i| i.name).collect();
+        assert_eq!(names, vec!["fresh"]);
+        assert!(!valid_name("../etc"));
+    }
+
+    #[test]
+    fn test_concurrent_sessions_keep_each_others_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent_instances.json");
+        let instance = |name: &str| NamedInstance {
+            name: name.to_string(),
+            project: "/proj".to_string(),
+            agent: "coder".to_string(),
+            created: Utc::now(),
+            last_used: Utc::now(),
+            calls: 1,
+            messages: Vec::new(),
+            observation_log: String::new(),
+        };
+
+        let first = NamedInstanceStore::load(path.clone(), "/proj", None);
+        let second = NamedInstanceStore::load(path.clone(), "/proj", None);
+        first.put(instance("tracker"));
+        second.put(instance("release"));
+        assert!(first.get("release").is_some());
+
+        let names: Vec<String> = NamedInstanceStore::load(path, "/proj", None)
+            .list()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"tracker".to_string()));
+    }
+}
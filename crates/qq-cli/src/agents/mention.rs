@@ -0,0 +1,176 @@
+//! Validation of user-typed agent mentions (`@agent`, `/delegate agent`).
+//!
+//! Mentions are resolved when the input is parsed so a typo gets an inline
+//! "did you mean" instead of a failed delegation deep in the executor.
+
+/// Maximum number of suggestions offered for an unknown mention.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Why a mention could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MentionError {
+    /// No agent matches; carries the closest names, best first.
+    Unknown {
+        mention: String,
+        suggestions: Vec<String>,
+    },
+    /// The mention is a prefix of several agents.
+    Ambiguous {
+        mention: String,
+        candidates: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for MentionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown {
+                mention,
+                suggestions,
+            } => {
+                write!(f, "Unknown agent: @{}.", mention)?;
+                match suggestions.as_slice() {
+                    [] => write!(f, " Use /agents to list available agents."),
+                    [one] => write!(f, " Did you mean @{}?", one),
+                    many => write!(f, " Did you mean one of: {}?", format_mentions(many)),
+                }
+            }
+            Self::Ambiguous {
+                mention,
+                candidates,
+            } => write!(
+                f,
+                "Ambiguous agent: @{} matches {}.",
+                mention,
+                format_mentions(candidates)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MentionError {}
+
+fn format_mentions(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|n| format!("@{}", n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Resolve `mention` against the available agent `names`.
+///
+/// An exact (case-insensitive) match wins; otherwise a unique prefix expands
+/// to the full name, so `@res` resolves to `researcher`.
+pub fn resolve_agent_mention(mention: &str, names: &[String]) -> Result<String, MentionError> {
+    let lower = mention.to_lowercase();
+    if let Some(name) = names.iter().find(|n| n.to_lowercase() == lower) {
+        return Ok(name.clone());
+    }
+
+    let mut prefixed: Vec<String> = names
+        .iter()
+        .filter(|n| !lower.is_empty() && n.to_lowercase().starts_with(&lower))
+        .cloned()
+        .collect();
+    match prefixed.len() {
+        0 => {}
+        1 => return Ok(prefixed.remove(0)),
+        _ => {
+            prefixed.sort();
+            return Err(MentionError::Ambiguous {
+                mention: mention.to_string(),
+                candidates: prefixed,
+            });
+        }
+    }
+
+    // Suggestions only, so be generous: up to one edit per two characters
+    let max_distance = (lower.chars().count() / 2).max(2);
+    let mut scored: Vec<(usize, &String)> = names
+        .iter()
+        .map(|n| (edit_distance(&lower, &n.to_lowercase()), n))
+        .filter(|(d, _)| *d <= max_distance)
+        .collect();
+    scored.sort();
+
+    Err(MentionError::Unknown {
+        mention: mention.to_string(),
+        suggestions: scored
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, n)| n.clone())
+            .collect(),
+    })
+}
+
+/// Levenshtein distance over chars.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["researcher", "reviewer", "coder", "explore", "summarizer"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_and_prefix_resolution() {
+        assert_eq!(resolve_agent_mention("coder", &names()).unwrap(), "coder");
+        assert_eq!(resolve_agent_mention("Coder", &names()).unwrap(), "coder");
+        assert_eq!(
+            resolve_agent_mention("rese", &names()).unwrap(),
+            "researcher"
+        );
+        assert_eq!(
+            resolve_agent_mention("re", &names()),
+            Err(MentionError::Ambiguous {
+                mention: "re".into(),
+                candidates: vec!["researcher".into(), "reviewer".into()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_mention_suggests_closest() {
+        let err = resolve_agent_mention("reserch", &names()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown agent: @reserch. Did you mean @researcher?"
+        );
+
+        let err = resolve_agent_mention("deploy", &names()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown agent: @deploy. Use /agents to list available agents."
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("coder", "coder"), 0);
+    }
+}
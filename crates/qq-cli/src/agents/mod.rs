@@ -5,12 +5,23 @@
 //! - Agent tools that expose agents as callable tools for the LLM
 //! - AgentExecutor for manual agent invocation via chat commands
 //! - Continuation support for agents that exceed max_turns
+//! - Validation of `@agent` mentions with "did you mean" suggestions
+//! - Named long-lived agent instances persisted across sessions
+//! - The `finish` tool and structured reports sub-agents return
+//! - Build/test verification of agent runs, with automatic repair attempts
 
 pub mod agent_tool;
 pub mod continuation;
 pub mod inform_user;
+pub mod instances;
+pub mod mention;
+pub mod report;
+pub mod verify;
 
 pub use inform_user::InformUserTool;
+pub use instances::{NamedInstanceStore, UseAgentInstanceTool};
+pub use mention::resolve_agent_mention;
+pub use report::{AgentReport, FinishTool, FINISH_TOOL};
 
 pub use agent_tool::{create_agent_tools, DEFAULT_MAX_AGENT_DEPTH};
 
@@ -84,6 +95,7 @@ impl AgentExecutor {
                     description: agent.description().to_string(),
                     is_internal: true,
                     tools: agent.tool_names().iter().map(|s| s.to_string()).collect(),
+                    is_read_only: agent.is_read_only(),
                 });
             }
         }
@@ -96,6 +108,7 @@ impl AgentExecutor {
                     description: def.description.clone(),
                     is_internal: false,
                     tools: def.tools.clone(),
+                    is_read_only: def.read_only,
                 });
             }
         }
@@ -105,6 +118,11 @@ impl AgentExecutor {
         agents
     }
 
+    /// Names of all available (enabled) agents, sorted.
+    pub fn agent_names(&self) -> Vec<String> {
+        self.list_agents().into_iter().map(|a| a.name).collect()
+    }
+
     /// Check if an agent exists and is enabled.
     pub fn has_agent(&self, name: &str) -> bool {
         if !self.is_enabled(name) {
@@ -0,0 +1,368 @@
+//! Structured sub-agent results.
+//!
+//! Free-text results vary from agent to agent, and parents misread them. A
+//! sub-agent instead ends its run by calling the `finish` tool with an
+//! [`AgentReport`]; the agent loop stops on that call and hands the report,
+//! as JSON, to the parent as the delegation's tool output. The TUI parses it
+//! back to show the sections in the activity pane.
+//!
+//! The report ends with the agent's self-assessment: confidence, whether the
+//! work is complete, and the risks it sees. With `[profiles.X.review]` set,
+//! a report that falls short of those thresholds gets a `review` note, which
+//! tells the PM to have the reviewer check the work before relying on it.
+
+use std::fmt;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use qq_agents::ReviewPolicy;
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+use qq_tools::ChangeStats;
+
+pub use qq_agents::Confidence;
+
+/// Name of the tool that ends a sub-agent run.
+pub const FINISH_TOOL: &str = "finish";
+
+/// Whether the agent finished what it was asked to do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Completeness {
+    #[default]
+    Complete,
+    /// Some of the task is done; the rest is described in open questions
+    Partial,
+    /// Nothing could be done without the caller's help
+    Blocked,
+}
+
+impl fmt::Display for Completeness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Completeness::Complete => "complete",
+            Completeness::Partial => "partial",
+            Completeness::Blocked => "blocked",
+        })
+    }
+}
+
+/// A sub-agent's result, as returned to its parent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentReport {
+    /// What was done or found, in a few sentences
+    pub summary: String,
+    /// Files created, modified, or otherwise produced
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    #[serde(default)]
+    pub findings: Vec<String>,
+    /// Anything the parent or user still has to decide or check
+    #[serde(default)]
+    pub open_questions: Vec<String>,
+    pub confidence: Confidence,
+    #[serde(default)]
+    pub completeness: Completeness,
+    /// What could be wrong with the result, or break because of it
+    #[serde(default)]
+    pub risks: Vec<String>,
+    /// Files the run's commands changed, as measured by the `run` tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<ChangeStats>,
+    /// Why the review policy wants this work checked, when it does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<String>,
+}
+
+impl AgentReport {
+    /// Parse a report from a delegation's tool output. `None` for free text
+    /// (agents without the finish tool, or ones that never called it).
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if !text.starts_with('{') {
+            return None;
+        }
+        serde_json::from_str::<Self>(text)
+            .ok()
+            .filter(|r| !r.summary.trim().is_empty())
+    }
+
+    /// The report in a tool result, if the call was a successful delegation
+    /// (`Agent[name]`) to an agent that reported through `finish`.
+    pub fn from_delegation(tool_name: &str, result: &str, is_error: bool) -> Option<Self> {
+        if is_error || !tool_name.starts_with("Agent[") {
+            return None;
+        }
+        Self::parse(result)
+    }
+
+    /// Attach the changes a delegation made to its result: into the report
+    /// when the result is one, else as a `[changes]` footer.
+    pub fn with_changes(result: &str, changes: ChangeStats) -> String {
+        if changes.is_empty() {
+            return result.to_string();
+        }
+        match Self::parse(result) {
+            Some(mut report) => {
+                report.changes = Some(changes);
+                serde_json::to_string_pretty(&report).unwrap_or_else(|_| result.to_string())
+            }
+            None => format!("{}\n\n{}", result, changes.render()),
+        }
+    }
+
+    /// Flag the report in a delegation's result for review when its
+    /// self-assessment falls short of `policy`. Returns the flagged result
+    /// and the reason, or `None` when no review is needed (or the result is
+    /// free text, which has no self-assessment).
+    pub fn flag_for_review(result: &str, policy: &ReviewPolicy) -> Option<(String, String)> {
+        let mut report = Self::parse(result)?;
+        let mut reasons = Vec::new();
+        if report.confidence < policy.min_confidence {
+            reasons.push(format!(
+                "confidence is {} (below {})",
+                report.confidence, policy.min_confidence
+            ));
+        }
+        if policy.require_complete && report.completeness != Completeness::Complete {
+            reasons.push(format!("work is {}", report.completeness));
+        }
+        if policy.flag_risks && !report.risks.is_empty() {
+            reasons.push(match report.risks.len() {
+                1 => "1 risk listed".to_string(),
+                n => format!("{} risks listed", n),
+            });
+        }
+        if reasons.is_empty() {
+            return None;
+        }
+        let reason = format!("Review recommended: {}", reasons.join("; "));
+        report.review = Some(reason.clone());
+        let flagged = serde_json::to_string_pretty(&report).ok()?;
+        Some((flagged, reason))
+    }
+
+    /// The non-empty list sections, titled, in display order.
+    pub fn sections(&self) -> Vec<(&'static str, &[String])> {
+        [
+            ("Artifacts", self.artifacts.as_slice()),
+            ("Findings", self.findings.as_slice()),
+            ("Open questions", self.open_questions.as_slice()),
+            ("Risks", self.risks.as_slice()),
+        ]
+        .into_iter()
+        .filter(|(_, items)| !items.is_empty())
+        .collect()
+    }
+
+    /// Trim every field and drop empty list entries. Changes and the review
+    /// note are set by the caller, never taken from the agent.
+    fn normalize(mut self) -> Self {
+        self.summary = self.summary.trim().to_string();
+        self.changes = None;
+        self.review = None;
+        for list in [
+            &mut self.artifacts,
+            &mut self.findings,
+            &mut self.open_questions,
+            &mut self.risks,
+        ] {
+            *list = list
+                .iter()
+                .map(|item| item.trim())
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        self
+    }
+}
+
+/// Tool a sub-agent calls once, at the end, to report its result.
+pub struct FinishTool;
+
+#[async_trait]
+impl Tool for FinishTool {
+    fn name(&self) -> &str {
+        FINISH_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Report your result and end your turn"
+    }
+
+    fn tool_description(&self) -> &str {
+        "Report your result to the agent that delegated to you, and end your turn.\n\n\
+         Call this exactly once, when your task is done (or cannot be done). \
+         Nothing you write outside this call reaches the caller.\n\n\
+         Fields:\n\
+         - summary: what you did or found, in a few sentences. Lead with the answer.\n\
+         - artifacts: paths of files you created or modified\n\
+         - findings: one fact per entry, with file:line references where they apply\n\
+         - open_questions: decisions or checks left for the caller\n\
+         - confidence: low, medium, or high\n\
+         - completeness: complete, partial (say what is left in open_questions), \
+         or blocked\n\
+         - risks: what could be wrong with your result or break because of it \
+         (untested paths, assumptions, side effects). Be honest: a low score or \
+         a listed risk gets your work a second look, not a rejection."
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        let list = |description: &str| {
+            PropertySchema::array(description, PropertySchema::string("One entry"))
+        };
+        ToolDefinition::new(FINISH_TOOL, self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "summary",
+                    PropertySchema::string("What you did or found, leading with the answer"),
+                    true,
+                )
+                .add_property("artifacts", list("Files created or modified"), false)
+                .add_property("findings", list("Key facts, one per entry"), false)
+                .add_property(
+                    "open_questions",
+                    list("Decisions or checks left for the caller"),
+                    false,
+                )
+                .add_property(
+                    "confidence",
+                    PropertySchema::enum_string(
+                        "How sure you are of the result",
+                        vec!["low".into(), "medium".into(), "high".into()],
+                    ),
+                    true,
+                )
+                .add_property(
+                    "completeness",
+                    PropertySchema::enum_string(
+                        "Whether the task is done",
+                        vec!["complete".into(), "partial".into(), "blocked".into()],
+                    ),
+                    true,
+                )
+                .add_property("risks", list("What could be wrong or break"), false),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let report = serde_json::from_value::<AgentReport>(arguments)
+            .map_err(|e| Error::tool(FINISH_TOOL, format!("Invalid arguments: {}", e)))?
+            .normalize();
+        if report.summary.is_empty() {
+            return Err(Error::tool(FINISH_TOOL, "summary must not be empty"));
+        }
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| Error::tool(FINISH_TOOL, e.to_string()))?;
+        Ok(ToolOutput::success(json))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_finish_normalizes_and_round_trips() {
+        let output = FinishTool
+            .execute(serde_json::json!({
+                "summary": "  Fixed the parser.  ",
+                "artifacts": ["src/parse.rs", " "],
+                "findings": ["Off-by-one in split_pipeline (parse.rs:88)"],
+                "confidence": "high"
+            }))
+            .await
+            .unwrap();
+        let report = AgentReport::parse(&output.text_content()).unwrap();
+        assert_eq!(report.summary, "Fixed the parser.");
+        assert_eq!(report.artifacts, vec!["src/parse.rs"]);
+        assert!(report.open_questions.is_empty());
+        assert_eq!(report.confidence, Confidence::High);
+        let titles: Vec<&str> = report.sections().into_iter().map(|(t, _)| t).collect();
+        assert_eq!(titles, vec!["Artifacts", "Findings"]);
+    }
+
+    #[tokio::test]
+    async fn test_finish_rejects_bad_reports() {
+        let missing = FinishTool
+            .execute(serde_json::json!({"summary": "done"}))
+            .await;
+        assert!(missing.is_err());
+        let blank = FinishTool
+            .execute(serde_json::json!({"summary": " ", "confidence": "low"}))
+            .await;
+        assert!(blank.is_err());
+        let unknown = FinishTool
+            .execute(serde_json::json!({"summary": "done", "confidence": "certain"}))
+            .await;
+        assert!(unknown.is_err());
+
+        assert!(AgentReport::parse("All done, see src/lib.rs").is_none());
+        assert!(AgentReport::parse(r#"{"files": []}"#).is_none());
+        let json = r#"{"summary": "done", "confidence": "low"}"#;
+        assert!(AgentReport::from_delegation("Agent[coder]", json, false).is_some());
+        assert!(AgentReport::from_delegation("Agent[coder]", json, true).is_none());
+        assert!(AgentReport::from_delegation("run", json, false).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_flag_for_review() {
+        let policy = ReviewPolicy::default();
+        let output = FinishTool
+            .execute(serde_json::json!({
+                "summary": "Migrated the config loader",
+                "confidence": "medium",
+                "completeness": "partial",
+                "risks": ["Old config files untested", " "],
+                "open_questions": ["Env overrides not migrated"],
+                "review": "forged"
+            }))
+            .await
+            .unwrap();
+        let result = output.text_content();
+        assert_eq!(AgentReport::parse(&result).unwrap().review, None);
+
+        let (flagged, reason) = AgentReport::flag_for_review(&result, &policy).unwrap();
+        assert_eq!(
+            reason,
+            "Review recommended: confidence is medium (below high); work is partial; 1 risk listed"
+        );
+        let report = AgentReport::parse(&flagged).unwrap();
+        assert_eq!(report.review.as_deref(), Some(reason.as_str()));
+        assert_eq!(report.completeness, Completeness::Partial);
+        assert_eq!(report.risks, vec!["Old config files untested"]);
+
+        // A confident, complete report with no risks passes; so does free text
+        let clean = r#"{"summary": "Done", "confidence": "high"}"#;
+        assert!(AgentReport::flag_for_review(clean, &policy).is_none());
+        assert!(AgentReport::flag_for_review("Done.", &policy).is_none());
+        let lenient = ReviewPolicy {
+            min_confidence: Confidence::Medium,
+            require_complete: false,
+            flag_risks: false,
+            ..ReviewPolicy::default()
+        };
+        assert!(AgentReport::flag_for_review(&result, &lenient).is_none());
+    }
+
+    #[test]
+    fn test_with_changes() {
+        let changes = ChangeStats::from_output(
+            "ok\n\n[changes] 1 file, 1 hunk, +2 -0\n  M src/lib.rs  +2 -0  1 hunk",
+        )
+        .unwrap();
+        let report = r#"{"summary": "Added a helper", "confidence": "high"}"#;
+        let merged = AgentReport::parse(&AgentReport::with_changes(report, changes.clone())).unwrap();
+        assert_eq!(merged.changes.as_ref(), Some(&changes));
+
+        let text = AgentReport::with_changes("Done.", changes.clone());
+        assert!(text.starts_with("Done.\n\n[changes] 1 file"));
+        assert_eq!(ChangeStats::from_output(&text), Some(changes));
+        assert_eq!(AgentReport::with_changes("Done.", ChangeStats::default()), "Done.");
+    }
+}
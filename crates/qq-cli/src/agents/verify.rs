@@ -0,0 +1,357 @@
+//! Build and test verification of an agent's changes.
+//!
+//! With `[profiles.X.verify]` set, a verified agent's run that changed
+//! files is followed by `check_build` and the configured test command.
+//! Failures go back to the same agent instance as a repair task, so the
+//! caller gets work that builds and passes its tests, or a report that says
+//! it still doesn't, instead of having to notice and re-delegate itself.
+
+use std::sync::Arc;
+
+use qq_agents::VerifyPolicy;
+use qq_core::{execute_tool_dispatch, Error, ToolOutput, ToolRegistry};
+use qq_tools::ChangeStats;
+
+use crate::agents::report::{AgentReport, Confidence};
+
+/// Output kept from a failed check, from its end.
+const MAX_CHECK_OUTPUT_CHARS: usize = 4000;
+
+/// Failure detail kept in the caller's report.
+const MAX_REPORT_DETAIL_CHARS: usize = 600;
+
+/// Lines of test output returned by `run`.
+const TEST_TAIL_LINES: u64 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// The check couldn't run (no type checker, sandbox unavailable)
+    Skipped,
+}
+
+/// One verification step and what it printed.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub output: String,
+}
+
+/// The checks run after one agent run.
+#[derive(Debug, Clone, Default)]
+pub struct Verification {
+    pub checks: Vec<Check>,
+}
+
+impl Verification {
+    /// Whether any check actually ran.
+    pub fn ran_any(&self) -> bool {
+        self.checks.iter().any(|c| c.status != CheckStatus::Skipped)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Failed)
+    }
+
+    /// One line naming each check and its outcome.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = self
+            .checks
+            .iter()
+            .map(|c| {
+                let status = match c.status {
+                    CheckStatus::Passed => "passed",
+                    CheckStatus::Failed => "failed",
+                    CheckStatus::Skipped => "skipped",
+                };
+                format!("{} {}", c.name, status)
+            })
+            .collect();
+        parts.join(", ")
+    }
+}
+
+/// Run the checks `policy` asks for with the tools in `tools`. Checks whose
+/// tool isn't registered are left out.
+pub async fn verify(policy: &VerifyPolicy, tools: &ToolRegistry) -> Result<Verification, Error> {
+    let mut verification = Verification::default();
+
+    if let Some(tool) = policy.build.then(|| tools.get_arc("check_build")).flatten() {
+        let output = run_check(tool, serde_json::json!({})).await?;
+        let status = build_status(&output);
+        verification.checks.push(Check {
+            name: "check_build".to_string(),
+            status,
+            output: output.text_content(),
+        });
+    }
+
+    if let (Some(command), Some(tool)) = (&policy.test_command, tools.get_arc("run")) {
+        let output = run_check(
+            tool,
+            serde_json::json!({
+                "command": command,
+                "description": "Verify the agent's changes with the project's tests",
+                "timeout": policy.test_timeout,
+                "output_mode": "tail",
+                "tail_lines": TEST_TAIL_LINES,
+            }),
+        )
+        .await?;
+        verification.checks.push(Check {
+            name: format!("`{}`", command),
+            status: if output.is_error {
+                CheckStatus::Failed
+            } else {
+                CheckStatus::Passed
+            },
+            output: output.text_content(),
+        });
+    }
+
+    Ok(verification)
+}
+
+/// Execute a check's tool; only cancellation aborts verification.
+async fn run_check(
+    tool: Arc<dyn qq_core::Tool>,
+    arguments: serde_json::Value,
+) -> Result<ToolOutput, Error> {
+    match execute_tool_dispatch(tool, arguments).await {
+        Err(Error::Cancelled) => Err(Error::Cancelled),
+        Err(e) => Ok(ToolOutput::error(e.to_string())),
+        Ok(output) => Ok(output),
+    }
+}
+
+/// Outcome of a `check_build` report: failed when any checker reported
+/// errors, skipped when none of them ran.
+fn build_status(output: &ToolOutput) -> CheckStatus {
+    let text = output.text_content();
+    let mut ran = false;
+    for line in text.lines().take_while(|l| !l.trim().is_empty()) {
+        let Some((_, status)) = line.split_once(": ") else {
+            continue;
+        };
+        if let Some((errors, _)) = status.split_once(" error(s)") {
+            ran = true;
+            if errors.trim().parse::<usize>().is_ok_and(|n| n > 0) {
+                return CheckStatus::Failed;
+            }
+        }
+    }
+    if ran && !output.is_error {
+        CheckStatus::Passed
+    } else {
+        CheckStatus::Skipped
+    }
+}
+
+/// The task sent back to the agent when verification fails.
+pub fn repair_task(task: &str, verification: &Verification, attempt: u32, max: u32) -> String {
+    let mut out = format!(
+        "Verification of your changes failed (repair attempt {} of {}). \
+         Fix the cause of the failures below without changing the intent of \
+         the original task, then report again.\n\n## Original task\n{}\n",
+        attempt, max, task
+    );
+    for check in verification.failures() {
+        out.push_str(&format!(
+            "\n## {} failed\n{}\n",
+            check.name,
+            tail_chars(check.output.trim(), MAX_CHECK_OUTPUT_CHARS)
+        ));
+    }
+    out
+}
+
+/// Record the verification outcome in the agent's result. Reports gain a
+/// finding when it passed, or an open question (and low confidence) when it
+/// still fails; `changes` replaces the report's, covering every run.
+/// Free-text results get a `[verify]` footer.
+pub fn annotate(
+    result: &str,
+    verification: &Verification,
+    repairs: u32,
+    changes: ChangeStats,
+) -> String {
+    let after = match repairs {
+        0 => String::new(),
+        1 => " after 1 repair".to_string(),
+        n => format!(" after {} repairs", n),
+    };
+    let line = if verification.passed() {
+        format!("Verification passed{}: {}", after, verification.summary())
+    } else {
+        let detail: Vec<String> = verification
+            .failures()
+            .map(|c| {
+                format!(
+                    "{}: {}",
+                    c.name,
+                    tail_chars(c.output.trim(), MAX_REPORT_DETAIL_CHARS)
+                )
+            })
+            .collect();
+        format!(
+            "Verification still failing{}: {}\n{}",
+            after,
+            verification.summary(),
+            detail.join("\n")
+        )
+    };
+
+    match AgentReport::parse(result) {
+        Some(mut report) => {
+            if verification.passed() {
+                report.findings.push(line);
+            } else {
+                report.open_questions.push(line);
+                report.confidence = Confidence::Low;
+            }
+            if !changes.is_empty() {
+                report.changes = Some(changes);
+            }
+            serde_json::to_string_pretty(&report).unwrap_or_else(|_| result.to_string())
+        }
+        None => format!("{}\n\n[verify] {}", result, line),
+    }
+}
+
+/// Files a run changed, from its report or `[changes]` footer.
+pub fn result_changes(result: &str) -> ChangeStats {
+    AgentReport::parse(result)
+        .and_then(|r| r.changes)
+        .or_else(|| ChangeStats::from_output(result))
+        .unwrap_or_default()
+}
+
+/// The last `max` characters of `text`.
+fn tail_chars(text: &str, max: usize) -> &str {
+    let count = text.chars().count();
+    if count <= max {
+        return text;
+    }
+    let start = text
+        .char_indices()
+        .nth(count - max)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    &text[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use qq_core::{Tool, ToolDefinition};
+
+    struct Canned {
+        name: &'static str,
+        output: ToolOutput,
+    }
+
+    #[async_trait]
+    impl Tool for Canned {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "canned"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new(self.name, "canned")
+        }
+
+        async fn execute(&self, _arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+            Ok(self.output.clone())
+        }
+    }
+
+    fn registry(build: &str, tests: ToolOutput) -> ToolRegistry {
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(Canned {
+            name: "check_build",
+            output: ToolOutput::success(build),
+        }));
+        tools.register(Arc::new(Canned {
+            name: "run",
+            output: tests,
+        }));
+        tools
+    }
+
+    fn policy() -> VerifyPolicy {
+        VerifyPolicy {
+            test_command: Some("cargo test".to_string()),
+            ..VerifyPolicy::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_reports_failures() {
+        let tools = registry(
+            "cargo: 1 error(s), 0 warning(s)\n\n===== src/lib.rs =====\n3:5-3:9 error[E0425] cannot find value `x`\n",
+            ToolOutput::error("test result: FAILED. 1 passed; 1 failed"),
+        );
+        let verification = verify(&policy(), &tools).await.unwrap();
+        assert!(!verification.passed());
+        assert_eq!(
+            verification.summary(),
+            "check_build failed, `cargo test` failed"
+        );
+
+        let task = repair_task("Add a helper", &verification, 1, 2);
+        assert!(task.contains("repair attempt 1 of 2"));
+        assert!(task.contains("## Original task\nAdd a helper"));
+        assert!(task.contains("E0425"));
+        assert!(task.contains("## `cargo test` failed"));
+
+        let report = r#"{"summary": "Added a helper", "confidence": "high"}"#;
+        let annotated =
+            AgentReport::parse(&annotate(report, &verification, 2, ChangeStats::default()))
+                .unwrap();
+        assert_eq!(annotated.confidence, Confidence::Low);
+        assert!(
+            annotated.open_questions[0].starts_with("Verification still failing after 2 repairs")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_passes_and_skips() {
+        let tools = registry(
+            "cargo: 0 error(s), 2 warning(s)\n",
+            ToolOutput::success("test result: ok. 3 passed"),
+        );
+        let verification = verify(&policy(), &tools).await.unwrap();
+        assert!(verification.passed());
+        let annotated = annotate("Done.", &verification, 1, ChangeStats::default());
+        assert_eq!(
+            annotated,
+            "Done.\n\n[verify] Verification passed after 1 repair: check_build passed, `cargo test` passed"
+        );
+
+        // No checker in the project: nothing to repair
+        let mut tools_without_checker = ToolRegistry::new();
+        tools_without_checker.register(Arc::new(Canned {
+            name: "check_build",
+            output: ToolOutput::error("No type checker found"),
+        }));
+        let verification = verify(&VerifyPolicy::default(), &tools_without_checker)
+            .await
+            .unwrap();
+        assert_eq!(verification.checks[0].status, CheckStatus::Skipped);
+        assert!(!verification.ran_any());
+        assert_eq!(tail_chars("añb", 2), "ñb");
+    }
+}
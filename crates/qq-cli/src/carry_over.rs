@@ -0,0 +1,113 @@
+//! `/clear` with carry-over.
+//!
+//! Clearing a conversation used to discard it for good. Now the full history
+//! is first saved as Markdown to the transcripts directory, and a summary of
+//! it (the observation log plus an observer pass over the recent messages,
+//! the same summary saved to the knowledge base on exit) can seed the next
+//! conversation from the system message. `[session_memory] clear_summary`
+//! decides whether to ask, always carry over, or never; `/clear summary` and
+//! `/clear all` decide for a single clear.
+
+use std::path::PathBuf;
+
+use crate::config::ClearSummaryMode;
+
+/// Prompt for the `ask` mode.
+pub const CLEAR_PROMPT: &str = "Carry a summary of this conversation into the next one? [y/N] ";
+
+/// What to do with the conversation being cleared, from the `/clear`
+/// argument and the configured mode. `None` means ask.
+pub fn clear_choice(arg: &str, mode: ClearSummaryMode) -> Result<Option<bool>, String> {
+    match arg.trim().to_lowercase().as_str() {
+        "" => Ok(match mode {
+            ClearSummaryMode::Ask => None,
+            ClearSummaryMode::Always => Some(true),
+            ClearSummaryMode::Never => Some(false),
+        }),
+        "summary" | "summarize" | "keep" => Ok(Some(true)),
+        "all" | "discard" => Ok(Some(false)),
+        other => Err(format!(
+            "Unknown /clear option: {}. Use /clear, /clear summary or /clear all",
+            other
+        )),
+    }
+}
+
+/// Whether an answer to [`CLEAR_PROMPT`] is yes.
+pub fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// System-message section holding a summary carried over by `/clear`.
+pub fn previous_conversation_section(summary: &str) -> String {
+    format!(
+        "## Previous Conversation\n\n\
+         The user cleared an earlier conversation and kept this summary of it. \
+         Use it as background; the user may refer back to it.\n\n\
+         {}",
+        summary.trim()
+    )
+}
+
+/// Result of a `/clear`.
+#[derive(Debug, Default)]
+pub struct ClearOutcome {
+    /// Where the full history was saved
+    pub transcript: Option<PathBuf>,
+    /// Whether a summary was carried over
+    pub carried_over: bool,
+    /// Why the history could not be saved
+    pub save_error: Option<String>,
+}
+
+impl ClearOutcome {
+    /// One-line report for the user.
+    pub fn status(&self) -> String {
+        let mut status = if self.carried_over {
+            "Conversation cleared; summary carried over".to_string()
+        } else {
+            "Conversation cleared".to_string()
+        };
+        if let Some(ref path) = self.transcript {
+            status.push_str(&format!(" (history saved to {})", path.display()));
+        } else if let Some(ref e) = self.save_error {
+            status.push_str(&format!(" (history not saved: {})", e));
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_choice() {
+        assert_eq!(clear_choice("", ClearSummaryMode::Ask), Ok(None));
+        assert_eq!(clear_choice(" ", ClearSummaryMode::Always), Ok(Some(true)));
+        assert_eq!(clear_choice("", ClearSummaryMode::Never), Ok(Some(false)));
+        // An explicit option wins over the config
+        assert_eq!(
+            clear_choice("Summary", ClearSummaryMode::Never),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            clear_choice("all", ClearSummaryMode::Always),
+            Ok(Some(false))
+        );
+        assert!(clear_choice("everything", ClearSummaryMode::Ask).is_err());
+
+        assert!(is_yes(" Y\n"));
+        assert!(!is_yes(""));
+
+        let outcome = ClearOutcome {
+            transcript: Some(PathBuf::from("/state/transcripts/a.md")),
+            carried_over: true,
+            save_error: None,
+        };
+        assert_eq!(
+            outcome.status(),
+            "Conversation cleared; summary carried over (history saved to /state/transcripts/a.md)"
+        );
+    }
+}
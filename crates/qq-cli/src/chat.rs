@@ -10,20 +10,38 @@ use rustyline::history::FileHistory;
 use rustyline::{Config, Editor};
 use tokio::sync::RwLock;
 
-use futures::StreamExt;
+use async_trait::async_trait;
 
 use qq_core::{
-    execute_tools_parallel_with_chunker, AgentMemory, ChunkProcessor, ChunkerConfig,
-    CompletionRequest, ContextCompactor, Message, ObservationConfig, ObservationalMemory,
-    Provider, StreamChunk, ToolCall, ToolExecutionResult, ToolRegistry,
+    evict_for_context_overflow, AgentMemory, AgentRunner, ChunkProcessor, ChunkerConfig,
+    ContextCompactor, Conversation, Message, ObservationConfig, ObservationalMemory, Provider,
+    Regrounding, Route, RunEvent, RunHandler, SlidingWindowMemory, ToolRegistry,
 };
+use qq_core::text::ellipsize;
 
-use crate::agents::AgentExecutor;
+use crate::agents::{resolve_agent_mention, AgentExecutor};
 use crate::config;
 use crate::config::Config as AppConfig;
+use crate::context_files::ContextFiles;
 use crate::debug_log::DebugLogger;
+use crate::dropped_files::{self, Dropped, DroppedFiles};
+use crate::diff_pager;
 use crate::event_bus::{AgentEvent, AgentEventBus};
+use crate::event_socket::{EventSocket, SessionEvent};
+use crate::focus;
+use crate::hooks::{Hooks, PRIMARY_AGENT};
+use crate::latency::{LatencyStats, RequestMetrics};
+use crate::mirror::SessionMirror;
+use crate::context_slots::{self, ContextSlots, SlotsReply};
+use crate::share::SessionLog;
 use crate::markdown::MarkdownRenderer;
+use crate::permissions;
+use crate::routing::ModelRouter;
+use crate::carry_over::{self, ClearOutcome};
+use crate::session_memory::{export_path, history_markdown, save_transcript, HistorySpill};
+use crate::summaries::SummaryArchive;
+use crate::tui::diff_view::DiffView;
+use crate::turn_stats::{Pricing, TurnStats};
 use crate::Cli;
 
 /// Chat session state with observational memory compaction.
@@ -32,6 +50,25 @@ pub struct ChatSession {
     pub system_prompt: Option<String>,
     compactor: Option<Arc<dyn ContextCompactor>>,
     pub observation_memory: ObservationalMemory,
+    /// Ceiling on in-memory history; the oldest messages spill past it
+    history_spill: Option<HistorySpill>,
+    /// Summaries (and originals) of compacted history for `/summaries`
+    summaries: SummaryArchive,
+    /// Lifecycle hooks fired for the primary agent's tool calls
+    hooks: Option<Arc<Hooks>>,
+    /// Summary of the conversation before the last `/clear`
+    carried_over: Option<String>,
+    /// Restates the request after compaction or a long pause
+    regrounding: Option<Regrounding>,
+    /// `/context` slots placed in the system message of every request
+    context_slots: Option<ContextSlots>,
+    /// Where in `messages` each exchange the user started begins, oldest first
+    user_turns: Vec<usize>,
+    /// Exchanges taken back with `/undo`, most recent last
+    undone: Vec<Vec<Message>>,
+    /// Replaces observation compaction when the profile's memory strategy
+    /// is `sliding-window`
+    sliding_window: Option<SlidingWindowMemory>,
 }
 
 impl ChatSession {
@@ -40,7 +77,17 @@ impl ChatSession {
             messages: Vec::new(),
             system_prompt,
             compactor: None,
-            observation_memory: ObservationalMemory::new(ObservationConfig::default()),
+            observation_memory: ObservationalMemory::new(ObservationConfig::default())
+                .with_pass_recording(),
+            history_spill: None,
+            summaries: SummaryArchive::new(false),
+            hooks: None,
+            carried_over: None,
+            regrounding: None,
+            context_slots: None,
+            user_turns: Vec::new(),
+            undone: Vec::new(),
+            sliding_window: None,
         }
     }
 
@@ -52,18 +99,114 @@ impl ChatSession {
 
     /// Set the observation config (thresholds, preserve_recent, etc.).
     pub fn with_observation_config(mut self, config: ObservationConfig) -> Self {
-        self.observation_memory = ObservationalMemory::new(config);
+        self.observation_memory = ObservationalMemory::new(config).with_pass_recording();
         self
     }
 
+    /// Keep a window of recent messages plus recalled older exchanges instead
+    /// of compacting into the observation log (see [`SlidingWindowMemory`]).
+    pub fn with_sliding_window(mut self, memory: SlidingWindowMemory) -> Self {
+        self.sliding_window = Some(memory);
+        self
+    }
+
+    pub fn sliding_window(&self) -> Option<&SlidingWindowMemory> {
+        self.sliding_window.as_ref()
+    }
+
+    /// Cap the in-memory history (see [`HistorySpill`]).
+    pub fn with_history_spill(mut self, spill: HistorySpill) -> Self {
+        self.history_spill = Some(spill);
+        self
+    }
+
+    /// Set where compaction summaries go (see [`SummaryArchive`]).
+    pub fn with_summary_archive(mut self, summaries: SummaryArchive) -> Self {
+        self.summaries = summaries;
+        self
+    }
+
+    /// Fire lifecycle hooks for the primary agent's tool calls.
+    pub fn with_hooks(mut self, hooks: Arc<Hooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Re-ground each turn on its request (see [`Regrounding`]).
+    pub fn with_regrounding(mut self, regrounding: Regrounding) -> Self {
+        self.regrounding = Some(regrounding);
+        self
+    }
+
+    pub fn regrounding(&self) -> Option<&Regrounding> {
+        self.regrounding.as_ref()
+    }
+
+    /// Send the project's context slots with every request (see [`ContextSlots`]).
+    pub fn with_context_slots(mut self, slots: ContextSlots) -> Self {
+        self.context_slots = Some(slots);
+        self
+    }
+
+    /// Handle `/context <arg>`.
+    pub fn context_command(&mut self, arg: &str) -> SlotsReply {
+        match self.context_slots {
+            Some(ref mut slots) => slots.command(arg),
+            None => SlotsReply::Status("Context slots are unavailable".to_string()),
+        }
+    }
+
+    /// Add a message the user sent, starting a new exchange.
+    ///
+    /// Its position is recorded so [`undo`](Self::undo) can tell it from
+    /// user-role messages injected mid-turn, such as steering notes.
     pub fn add_user_message(&mut self, content: &str) {
-        self.messages.push(Message::user(content));
+        self.start_exchange(Message::user(content));
+    }
+
+    /// Add a message the user sent with attachments (text plus images).
+    pub fn add_user_parts(&mut self, parts: Vec<qq_core::TypedContent>) {
+        self.start_exchange(Message::user(parts));
+    }
+
+    fn start_exchange(&mut self, message: Message) {
+        self.undone.clear();
+        self.user_turns.push(self.messages.len());
+        self.messages.push(message.with_provenance(None, None, None));
+    }
+
+    /// The user's messages that start the recorded exchanges, to find them
+    /// again with [`relocate_user_turns`](Self::relocate_user_turns).
+    fn user_turn_keys(&self) -> Vec<(u64, String)> {
+        self.user_turns
+            .iter()
+            .filter_map(|&i| self.messages.get(i))
+            .filter_map(user_turn_key)
+            .collect()
+    }
+
+    /// Re-find the recorded exchanges after compaction or trimming removed
+    /// messages before them. Exchanges whose message was removed are gone.
+    fn relocate_user_turns(&mut self, keys: Vec<(u64, String)>) {
+        let mut remaining = keys.as_slice();
+        self.user_turns = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, message)| {
+                let key = user_turn_key(message)?;
+                let found = remaining.iter().position(|k| *k == key)?;
+                remaining = &remaining[found + 1..];
+                Some(i)
+            })
+            .collect();
     }
 
     pub fn add_message(&mut self, message: Message) {
         self.messages.push(message);
     }
 
+    #[cfg(test)]
     pub fn add_assistant_message(&mut self, content: &str) {
         self.messages.push(Message::assistant(content));
     }
@@ -73,7 +216,9 @@ impl ChatSession {
     }
 
     pub fn add_tool_result(&mut self, tool_call_id: &str, content: impl qq_core::IntoContent) {
-        self.messages.push(Message::tool_result(tool_call_id, content));
+        self.messages.push(
+            Message::tool_result(tool_call_id, content).with_provenance(None, None, None),
+        );
     }
 
     pub fn build_messages(&self) -> Vec<Message> {
@@ -82,7 +227,15 @@ impl ChatSession {
         // Merge system prompt and observation log into a single system message
         // to avoid multi-system-message errors with strict chat templates.
         let log = self.observation_memory.observation_log();
-        let has_system = self.system_prompt.is_some();
+        let slots = self.context_slots.as_ref().and_then(ContextSlots::system_section);
+        let recalled = self
+            .sliding_window
+            .as_ref()
+            .and_then(SlidingWindowMemory::recalled_section);
+        let has_system = self.system_prompt.is_some()
+            || self.carried_over.is_some()
+            || slots.is_some()
+            || recalled.is_some();
         let has_log = !log.is_empty();
 
         if has_log {
@@ -95,6 +248,18 @@ impl ChatSession {
 
         if has_system || has_log {
             let mut system_content = self.system_prompt.clone().unwrap_or_default();
+            if let Some(ref slots) = slots {
+                if !system_content.is_empty() {
+                    system_content.push_str("\n\n");
+                }
+                system_content.push_str(slots);
+            }
+            if let Some(ref summary) = self.carried_over {
+                if !system_content.is_empty() {
+                    system_content.push_str("\n\n");
+                }
+                system_content.push_str(&carry_over::previous_conversation_section(summary));
+            }
             if has_log {
                 if !system_content.is_empty() {
                     system_content.push_str("\n\n");
@@ -107,17 +272,179 @@ impl ChatSession {
                     log
                 ));
             }
+            if let Some(ref recalled) = recalled {
+                if !system_content.is_empty() {
+                    system_content.push_str("\n\n");
+                }
+                system_content.push_str(recalled);
+            }
             msgs.push(Message::system(system_content.as_str()));
         }
 
         // Recent unobserved messages
         msgs.extend(self.messages.clone());
+
+        // Stale copies of re-read files are replaced with a pointer to the latest
+        let dedup = crate::context_dedup::dedup_file_contents(&mut msgs);
+        if dedup.superseded > 0 {
+            tracing::debug!(
+                superseded = dedup.superseded,
+                bytes_saved = dedup.bytes_saved,
+                "Deduplicated repeated file contents"
+            );
+        }
         msgs
     }
 
     pub fn clear(&mut self) {
         self.messages.clear();
         self.observation_memory.clear();
+        if let Some(ref mut spill) = self.history_spill {
+            spill.clear();
+        }
+        self.summaries.clear();
+        self.carried_over = None;
+        self.user_turns.clear();
+        self.undone.clear();
+        if let Some(ref mut window) = self.sliding_window {
+            window.clear();
+        }
+    }
+
+    /// Take back the last exchange for `/undo`: the user's message and
+    /// everything after it, including tool calls and their results.
+    ///
+    /// Returns how many messages were removed. An exchange already compacted
+    /// into the observation log or spilled to disk can't be taken back.
+    pub fn undo(&mut self) -> Result<usize, String> {
+        let Some(start) = self.user_turns.pop() else {
+            let compacted = !self.messages.is_empty()
+                || self.observation_count() > 0
+                || self.history_spill.as_ref().is_some_and(|s| s.moved_count() > 0)
+                || self.sliding_window.as_ref().is_some_and(|w| w.archived_count() > 0);
+            return Err(if compacted {
+                "The last exchange was compacted out of the history and can't be undone".to_string()
+            } else {
+                "Nothing to undo".to_string()
+            });
+        };
+        let exchange = self.messages.split_off(start);
+        let removed = exchange.len();
+        self.undone.push(exchange);
+        Ok(removed)
+    }
+
+    /// Restore the exchange last taken back with [`undo`](Self::undo).
+    ///
+    /// Returns how many messages were restored.
+    pub fn redo(&mut self) -> Result<usize, String> {
+        let exchange = self.undone.pop().ok_or_else(|| "Nothing to redo".to_string())?;
+        let restored = exchange.len();
+        self.user_turns.push(self.messages.len());
+        self.messages.extend(exchange);
+        Ok(restored)
+    }
+
+    /// Pin or unpin the messages `target` names for `/pin` and `/unpin`.
+    /// Pinned messages survive compaction and trimming verbatim.
+    ///
+    /// Returns the 1-based range changed, widened to whole tool calls.
+    pub fn set_pinned(&mut self, target: &str, pinned: bool) -> Result<(usize, usize), String> {
+        let range = parse_pin_target(target, self.messages.len())?;
+        let range = qq_core::set_pinned_range(&mut self.messages, range, pinned);
+        Ok((range.start + 1, range.end))
+    }
+
+    /// Toggle the pin on the latest reply (the TUI keybinding). Returns
+    /// whether it's pinned now, or `None` with nothing to pin.
+    pub fn toggle_last_pin(&mut self) -> Option<bool> {
+        let pinned = !self.messages.last()?.is_pinned();
+        self.set_pinned("", pinned).ok()?;
+        Some(pinned)
+    }
+
+    /// Unpin every message. Returns how many were pinned.
+    pub fn unpin_all(&mut self) -> usize {
+        let pinned: Vec<&mut Message> = self.messages.iter_mut().filter(|m| m.is_pinned()).collect();
+        let count = pinned.len();
+        pinned.into_iter().for_each(|m| m.set_pinned(false));
+        count
+    }
+
+    /// Number of pinned messages in the history.
+    pub fn pinned_count(&self) -> usize {
+        self.messages.iter().filter(|m| m.is_pinned()).count()
+    }
+
+    /// Usage of the latest top-level completion still in the history, which
+    /// sizes the context the next request starts from.
+    pub fn last_usage(&self) -> Option<&qq_core::Usage> {
+        self.messages
+            .iter()
+            .rev()
+            .filter(|m| m.role == qq_core::Role::Assistant)
+            .filter_map(|m| m.metadata.as_ref())
+            .find(|meta| meta.agent.is_none() && meta.usage.is_some())
+            .and_then(|meta| meta.usage.as_ref())
+    }
+
+    /// Clear for `/clear`: save the full history to `transcripts` first and,
+    /// with `summarize`, carry a summary of it into the next conversation.
+    pub async fn clear_with_carry_over(
+        &mut self,
+        summarize: bool,
+        transcripts: Option<&std::path::Path>,
+        pricing: &Pricing,
+    ) -> ClearOutcome {
+        let mut outcome = ClearOutcome::default();
+        let has_history = !self.messages.is_empty()
+            || self.history_spill.as_ref().is_some_and(|s| s.moved_count() > 0);
+        if let Some(dir) = transcripts.filter(|_| has_history) {
+            let saved = self
+                .export_messages()
+                .and_then(|messages| save_transcript(dir, &history_markdown(&messages, pricing)));
+            match saved {
+                Ok(path) => outcome.transcript = Some(path),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Cannot save cleared conversation");
+                    outcome.save_error = Some(e.to_string());
+                }
+            }
+        }
+        let summary = if summarize {
+            self.session_summary().await
+        } else {
+            None
+        };
+        self.clear();
+        outcome.carried_over = summary.is_some();
+        self.carried_over = summary;
+        outcome
+    }
+
+    pub fn history_spill(&self) -> Option<&HistorySpill> {
+        self.history_spill.as_ref()
+    }
+
+    pub fn summaries(&self) -> &SummaryArchive {
+        &self.summaries
+    }
+
+    /// Move finished observation passes into the summary archive.
+    fn archive_passes(&mut self) {
+        for pass in self.observation_memory.take_passes() {
+            self.summaries.record(pass);
+        }
+    }
+
+    /// Every message of the session, including those spilled to disk.
+    pub fn export_messages(&self) -> std::io::Result<Vec<Message>> {
+        let mut messages = match self.history_spill {
+            Some(ref spill) => spill.spilled_messages()?,
+            None => Vec::new(),
+        };
+        messages.extend(self.messages.iter().cloned());
+        Ok(messages)
     }
 
     pub fn message_count(&self) -> usize {
@@ -142,8 +469,58 @@ impl ChatSession {
         msg_bytes + self.observation_memory.log_bytes()
     }
 
-    /// Compact the conversation history using observational memory.
-    pub async fn compact_if_needed(&mut self) {
+    /// Summarize the whole session: the observation log plus an observer
+    /// pass over the messages not yet folded into it.
+    ///
+    /// Returns `None` when nothing was answered or there is nothing to keep.
+    pub async fn session_summary(&self) -> Option<String> {
+        let log = self.observation_memory.observation_log();
+        let answered = self
+            .messages
+            .iter()
+            .any(|m| m.role == qq_core::Role::Assistant);
+        let recent = match self.compactor {
+            Some(ref compactor) if answered => compactor
+                .observe_with_prior(&self.messages, (!log.is_empty()).then_some(log))
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!(error = %e, "Session summary observation failed");
+                    String::new()
+                }),
+            _ => String::new(),
+        };
+        let carried_over = self.carried_over.as_deref().unwrap_or_default();
+        let summary = [carried_over.trim(), log.trim(), recent.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        (!summary.is_empty()).then_some(summary)
+    }
+
+    /// Compact the conversation history using observational memory, or slide
+    /// the window when one is set.
+    ///
+    /// Returns a description of what left the history, if anything did.
+    pub async fn compact_if_needed(&mut self) -> Option<String> {
+        let turns = self.user_turn_keys();
+        let action = self.compact_history().await;
+        self.relocate_user_turns(turns);
+        action
+    }
+
+    async fn compact_history(&mut self) -> Option<String> {
+        if self.sliding_window.is_some() {
+            let keep = self.sliding_window.as_ref().map_or(0, |w| w.config().recent_messages);
+            let action = self.slide_window(keep).await;
+            if let Some(ref mut spill) = self.history_spill {
+                spill.enforce(&mut self.messages);
+            }
+            return action;
+        }
+
+        let bytes_before = self.total_bytes();
+        let observations = self.observation_count();
         tracing::debug!(
             message_count = self.messages.len(),
             total_bytes = self.total_bytes(),
@@ -161,6 +538,240 @@ impl ChatSession {
             {
                 tracing::error!(error = %e, "Observation memory compaction failed");
             }
+            self.archive_passes();
+        }
+
+        if let Some(ref mut spill) = self.history_spill {
+            spill.enforce(&mut self.messages);
+        }
+        (self.observation_count() != observations).then(|| {
+            format!(
+                "compacted {} bytes into the observation log",
+                bytes_before.saturating_sub(self.total_bytes())
+            )
+        })
+    }
+
+    /// Move exchanges older than the last `keep` messages out of the sliding
+    /// window, then recall the ones relevant to the user's latest message.
+    async fn slide_window(&mut self, keep: usize) -> Option<String> {
+        let turns = self.user_turn_keys();
+        let window = self.sliding_window.as_mut()?;
+        let moved = window.slide(&mut self.messages, keep).await;
+        self.relocate_user_turns(turns);
+        let window = self.sliding_window.as_mut()?;
+        if let Some(query) = self.user_turns.last().and_then(|&i| self.messages.get(i)) {
+            window.recall(&query.content.to_string_lossy()).await;
+        }
+        if let Some(ref mut spill) = self.history_spill {
+            spill.retire(&moved);
+        }
+        (!moved.is_empty()).then(|| {
+            format!(
+                "moved {} messages out of the sliding window ({} earlier exchanges recalled)",
+                moved.len(),
+                window.recalled_count()
+            )
+        })
+    }
+
+    /// Shrink the history after the provider rejected it as too long.
+    ///
+    /// Force-compacts into the observation log when a compactor is set and
+    /// that frees anything; otherwise evicts the oldest turns and tool output.
+    /// Returns a description of what was removed, or `None` if nothing could be.
+    pub async fn recover_from_overflow(&mut self) -> Option<String> {
+        let turns = self.user_turn_keys();
+        let action = self.shrink_history().await;
+        self.relocate_user_turns(turns);
+        action
+    }
+
+    async fn shrink_history(&mut self) -> Option<String> {
+        let bytes_before = self.total_bytes();
+        if let Some(keep) = self.sliding_window.as_ref().map(|w| w.config().recent_messages / 2) {
+            if let Some(action) = self.slide_window(keep).await {
+                return Some(action);
+            }
+        } else if let Some(ref compactor) = self.compactor {
+            let compacted = self
+                .observation_memory
+                .compact_force(&mut self.messages, compactor.as_ref())
+                .await;
+            self.archive_passes();
+            match compacted {
+                Ok(()) if self.total_bytes() < bytes_before => {
+                    return Some(format!(
+                        "compacted {} bytes into the observation log",
+                        bytes_before - self.total_bytes()
+                    ));
+                }
+                Ok(()) => {}
+                Err(e) => {
+                    tracing::warn!(error = %e, "Emergency compaction failed, evicting instead");
+                }
+            }
+        }
+        let eviction = evict_for_context_overflow(&mut self.messages);
+        (!eviction.is_empty()).then(|| eviction.describe())
+    }
+}
+
+/// Resolve a `/pin` target against a history of `len` messages: empty for
+/// the latest message, `N` for one message, or `N-M` for a range (1-based,
+/// as numbered by `/history`).
+fn parse_pin_target(target: &str, len: usize) -> Result<std::ops::Range<usize>, String> {
+    let target = target.trim();
+    if len == 0 {
+        return Err("No messages to pin".to_string());
+    }
+    if target.is_empty() {
+        return Ok(len - 1..len);
+    }
+    let index = |s: &str| match s.trim().parse::<usize>() {
+        Ok(n) if (1..=len).contains(&n) => Ok(n),
+        _ => Err(format!(
+            "'{}' is not a message number; /history numbers messages 1-{}",
+            s.trim(),
+            len
+        )),
+    };
+    let (first, last) = match target.split_once('-') {
+        Some((first, last)) => (index(first)?, index(last)?),
+        None => {
+            let n = index(target)?;
+            (n, n)
+        }
+    };
+    if first > last {
+        return Err(format!("Range {} is backwards", target));
+    }
+    Ok(first - 1..last)
+}
+
+/// "message 4" or "messages 4-6".
+pub fn describe_messages(first: usize, last: usize) -> String {
+    if first == last {
+        format!("message {}", first)
+    } else {
+        format!("messages {}-{}", first, last)
+    }
+}
+
+/// One line per message for `/history`, numbered for `/pin`; pinned
+/// messages are marked with `*`.
+pub fn history_outline(messages: &[Message]) -> String {
+    let mut out = String::new();
+    for (i, message) in messages.iter().enumerate() {
+        let label = match message.role {
+            qq_core::Role::User => "you",
+            qq_core::Role::Assistant if !message.tool_calls.is_empty() => "tool call",
+            qq_core::Role::Assistant => "assistant",
+            qq_core::Role::Tool => "tool result",
+            qq_core::Role::System => "system",
+        };
+        let text = if message.tool_calls.is_empty() {
+            message.content.to_string_lossy()
+        } else {
+            let names: Vec<&str> = message.tool_calls.iter().map(|c| c.name.as_str()).collect();
+            names.join(", ")
+        };
+        let preview = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("").trim();
+        let preview = qq_core::text::truncate_bytes(preview, 72);
+        out.push_str(&format!(
+            "{:>4}{} {:<11} {}{}\n",
+            i + 1,
+            if message.is_pinned() { "*" } else { " " },
+            label,
+            preview,
+            if preview.len() < text.trim().len() { "..." } else { "" }
+        ));
+    }
+    out
+}
+
+/// Identifies a message that started an exchange once compaction has
+/// shifted the history: its role, time and text survive that verbatim.
+fn user_turn_key(message: &Message) -> Option<(u64, String)> {
+    let metadata = message.metadata.as_ref().filter(|_| message.role == qq_core::Role::User)?;
+    Some((metadata.timestamp_ms, message.content.to_string_lossy()))
+}
+
+#[async_trait]
+impl Conversation for ChatSession {
+    fn request_messages(&self) -> Vec<Message> {
+        self.build_messages()
+    }
+
+    fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    fn finish(&mut self, reply: Message) {
+        qq_core::message::strip_reasoning_from_history(&mut self.messages);
+        self.messages.push(reply);
+    }
+
+    async fn compact(&mut self) -> Option<String> {
+        self.compact_if_needed().await
+    }
+
+    async fn shrink(&mut self) -> Option<String> {
+        self.recover_from_overflow().await
+    }
+
+    fn observation_log(&self) -> String {
+        self.observation_memory.observation_log().to_string()
+    }
+}
+
+/// Re-grounding for the primary agent, quoting the task board when there is
+/// one, or `None` when `[compaction] regrounding` is off.
+pub fn session_regrounding(
+    config: &AppConfig,
+    task_store: Option<&Arc<qq_tools::TaskStore>>,
+) -> Option<Regrounding> {
+    let regrounding = config::CompactionConfig::regrounding(config.compaction.as_ref())?;
+    Some(match task_store {
+        Some(store) => {
+            let store = Arc::clone(store);
+            regrounding.with_task_board(Arc::new(move || store.format_board()))
+        }
+        None => regrounding,
+    })
+}
+
+/// The `/context` slots of the session's project.
+pub fn session_context_slots(config: &AppConfig) -> ContextSlots {
+    ContextSlots::load(
+        crate::paths::context_slots_file(),
+        &crate::project_defaults::project_key(&crate::tools_root(config)),
+        config.session_memory.context_slot_tokens,
+    )
+}
+
+/// Save the conversation for `qq share` (on exit).
+pub fn save_session_log(session: &ChatSession, log: Option<SessionLog>) {
+    let Some(log) = log else {
+        return;
+    };
+    match session.export_messages() {
+        Ok(messages) => log.save(messages),
+        Err(e) => tracing::warn!(error = %e, "Failed to read spilled history for the session log"),
+    }
+}
+
+/// Save the session summary to the knowledge base (on exit).
+pub async fn save_session_summary(
+    session: &ChatSession,
+    knowledge: Option<&qq_tools::KnowledgeStore>,
+) {
+    let Some(store) = knowledge else {
+        return;
+    };
+    if let Some(summary) = session.session_summary().await {
+        if let Some(entry) = store.add(qq_tools::KnowledgeKind::Summary, None, &summary) {
+            tracing::info!(id = entry.id, session = %entry.session, "Saved session summary");
         }
     }
 }
@@ -203,15 +814,26 @@ pub fn format_bytes(bytes: usize) -> String {
 /// Chat commands
 enum ChatCommand {
     Quit,
-    Clear,
+    Clear(String),
     Reset,
+    Undo,
+    Redo,
     History,
+    Pin(String),
+    Unpin(String),
     Help,
     Tools,
     Agents,
     Memory,
+    Export(String),
+    Costs,
     Mount(String),
     Mounts,
+    Focus(String),
+    Unfocus,
+    Context(String),
+    Permissions(String),
+    AccessLog,
     Mcp,
     Delegate { agent: String, task: String },
     AgentCall { agent: String, task: String }, // @agent syntax
@@ -220,7 +842,12 @@ enum ChatCommand {
     None(String),  // Regular message
 }
 
-fn parse_command(input: &str) -> ChatCommand {
+/// Parse a line of chat input.
+///
+/// `agent_names` lists the available agents; when present, `@agent` and
+/// `/delegate` targets are validated (and unique prefixes expanded) here so a
+/// typo is reported immediately with suggestions.
+fn parse_command(input: &str, agent_names: Option<&[String]>) -> ChatCommand {
     let trimmed = input.trim();
 
     if trimmed.is_empty() {
@@ -238,7 +865,10 @@ fn parse_command(input: &str) -> ChatCommand {
             return ChatCommand::None(String::new());
         }
 
-        return ChatCommand::AgentCall { agent, task };
+        return match resolve_agent(agent, agent_names) {
+            Some(agent) => ChatCommand::AgentCall { agent, task },
+            None => ChatCommand::None(String::new()),
+        };
     }
 
     if !trimmed.starts_with('/') {
@@ -251,9 +881,13 @@ fn parse_command(input: &str) -> ChatCommand {
 
     match cmd.as_str() {
         "/quit" | "/exit" | "/q" => ChatCommand::Quit,
-        "/clear" | "/c" => ChatCommand::Clear,
+        "/clear" | "/c" => ChatCommand::Clear(arg),
         "/reset" => ChatCommand::Reset,
+        "/undo" => ChatCommand::Undo,
+        "/redo" => ChatCommand::Redo,
         "/history" | "/h" => ChatCommand::History,
+        "/pin" => ChatCommand::Pin(arg),
+        "/unpin" => ChatCommand::Unpin(arg),
         "/help" | "/?" => ChatCommand::Help,
         "/tools" | "/t" => ChatCommand::Tools,
         "/agents" | "/a" => ChatCommand::Agents,
@@ -271,14 +905,24 @@ fn parse_command(input: &str) -> ChatCommand {
                     eprintln!("Usage: /delegate {} <task>", agent);
                     ChatCommand::None(String::new())
                 } else {
-                    ChatCommand::Delegate { agent, task }
+                    match resolve_agent(agent, agent_names) {
+                        Some(agent) => ChatCommand::Delegate { agent, task },
+                        None => ChatCommand::None(String::new()),
+                    }
                 }
             }
         }
         "/memory" | "/mem" => ChatCommand::Memory,
+        "/export" => ChatCommand::Export(arg),
+        "/costs" => ChatCommand::Costs,
         "/mcp" => ChatCommand::Mcp,
         "/mount" => ChatCommand::Mount(arg),
         "/mounts" => ChatCommand::Mounts,
+        "/focus" => ChatCommand::Focus(arg),
+        "/unfocus" => ChatCommand::Unfocus,
+        "/context" | "/ctx" => ChatCommand::Context(arg),
+        "/permissions" | "/perms" => ChatCommand::Permissions(arg),
+        "/access-log" => ChatCommand::AccessLog,
         "/system" | "/sys" => ChatCommand::System(arg),
         "/debug" => ChatCommand::Debug(arg),
         _ => {
@@ -288,31 +932,59 @@ fn parse_command(input: &str) -> ChatCommand {
     }
 }
 
+/// Resolve a typed agent name, printing an inline error on failure.
+fn resolve_agent(agent: String, agent_names: Option<&[String]>) -> Option<String> {
+    let Some(names) = agent_names else {
+        return Some(agent);
+    };
+    match resolve_agent_mention(&agent, names) {
+        Ok(resolved) => Some(resolved),
+        Err(e) => {
+            eprintln!("{}\n", e);
+            None
+        }
+    }
+}
+
 fn print_help() {
     println!(
         r#"
 Chat Commands:
   /help, /?           Show this help message
   /quit, /exit        Exit chat mode
-  /clear, /c          Clear conversation + reset counters
+  /clear, /c          Clear conversation + reset counters (history is saved)
+  /clear summary|all  Carry a summary into the next conversation, or don't
   /reset              Full reset (clear + agent memory + tasks)
-  /history, /h        Show message count
+  /undo               Take back the last message and its response
+  /redo               Restore the exchange /undo took back
+  /history, /h        List messages, numbered for /pin
+  /pin [N|N-M]        Keep messages verbatim through compaction (default: latest)
+  /unpin [N|N-M|all]  Release pinned messages
   /memory, /mem       Show memory usage diagnostics
+  /export [path]      Write the whole conversation to Markdown
+  /costs              Show/hide the token and cost line after each response
   /tools, /t          List available tools
   /agents, /a         List available agents
   /mcp                Show connected MCP servers and tools
   /delegate <a> <t>   Delegate task <t> to agent <a>
   /mount <path>       Add read-only mount to bash sandbox
   /mounts             List current bash sandbox mounts
+  /focus [path]       Work in a project subdirectory (bash cwd, relative paths)
+  /unfocus            Return to the project root
+  /context [...]      Notes sent with every request (set <name> <text>, rm, show)
+  /permissions [...]  Show or edit bash permission tiers (/permissions help)
+  /access-log         List files read that git doesn't track (.env, ignored files)
   /system <msg>       Set a new system prompt
-  /debug <subcmd>     Debug commands (messages, count, dump)
+  /debug <subcmd>     Debug commands (messages, count, events, dump)
 
 Agents:
   @agent <task>       Quick agent invocation (e.g., @explore Find all tests)
+                      Unique prefixes expand (@rev -> @reviewer)
 
 Debug subcommands:
   /debug messages     Show all messages with role and content preview
   /debug count        Show message counts by role
+  /debug events       Show agent event bus drop/delay counters
   /debug dump <file>  Dump messages to a JSON file
 
 Tips:
@@ -360,32 +1032,17 @@ fn format_tool_args(args: &serde_json::Value) -> String {
                 .iter()
                 .map(|(k, v)| {
                     let val = match v {
-                        serde_json::Value::String(s) => {
-                            if s.len() > 50 {
-                                format!("\"{}...\"", &s[..47])
-                            } else {
-                                format!("\"{}\"", s)
-                            }
-                        }
+                        serde_json::Value::String(s) => format!("\"{}\"", ellipsize(s, 50)),
                         serde_json::Value::Array(arr) => {
                             if arr.len() > 3 {
                                 format!("[{} items]", arr.len())
                             } else {
-                                let items: Vec<String> = arr.iter().map(|v| {
-                                    let s = v.to_string();
-                                    if s.len() > 20 { format!("{}...", &s[..17]) } else { s }
-                                }).collect();
+                                let items: Vec<String> =
+                                    arr.iter().map(|v| ellipsize(&v.to_string(), 20)).collect();
                                 format!("[{}]", items.join(", "))
                             }
                         }
-                        other => {
-                            let s = other.to_string();
-                            if s.len() > 50 {
-                                format!("{}...", &s[..47])
-                            } else {
-                                s
-                            }
-                        }
+                        other => ellipsize(&other.to_string(), 50),
                     };
                     format!("{}={}", k, val)
                 })
@@ -393,14 +1050,7 @@ fn format_tool_args(args: &serde_json::Value) -> String {
             parts.join(", ")
         }
         serde_json::Value::Null => String::new(),
-        other => {
-            let s = other.to_string();
-            if s.len() > 100 {
-                format!("{}...", &s[..97])
-            } else {
-                s
-            }
-        }
+        other => ellipsize(&other.to_string(), 100),
     }
 }
 
@@ -440,8 +1090,62 @@ fn print_prompt_hint() -> std::io::Result<()> {
     Ok(())
 }
 
+/// Dim `↳ tokens · cost · time · model` line under a response.
+fn print_turn_stats(footer: &str) -> std::io::Result<()> {
+    use crossterm::style::{Color, SetForegroundColor, ResetColor};
+    use std::io::Write;
+
+    let line = footer.trim_start_matches('*').trim_end_matches('*');
+    let mut stdout = std::io::stdout();
+    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
+    println!("{}\n", line);
+    stdout.execute(ResetColor)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Note that a turn was served by the routing profile.
+fn print_route_note(profile: &str, reason: &str) -> std::io::Result<()> {
+    use crossterm::style::{Color, SetForegroundColor, ResetColor};
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout();
+    stdout.execute(SetForegroundColor(Color::DarkGrey))?;
+    println!("[routed to {}: {}]", profile, reason);
+    stdout.execute(ResetColor)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Send a primary-session event to socket clients and the mirror file.
+fn emit_session(
+    event_socket: Option<&EventSocket>,
+    mirror: Option<&SessionMirror>,
+    event: SessionEvent,
+) {
+    if let Some(mirror) = mirror {
+        mirror.record(&event);
+    }
+    if let Some(socket) = event_socket {
+        socket.emit(event);
+    }
+}
+
+/// Yellow note that the turn hit trouble and is being retried.
+fn print_retry_note(note: &str) -> std::io::Result<()> {
+    use crossterm::style::{Color, SetForegroundColor, ResetColor};
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout();
+    stdout.execute(SetForegroundColor(Color::Yellow))?;
+    println!("{}", note);
+    stdout.execute(ResetColor)?;
+    stdout.flush()?;
+    Ok(())
+}
+
 /// Handle debug subcommands
-fn handle_debug_command(subcmd: &str, session: &ChatSession) {
+fn handle_debug_command(subcmd: &str, session: &ChatSession, event_bus: &AgentEventBus) {
     let parts: Vec<&str> = subcmd.splitn(2, ' ').collect();
     let cmd = parts.first().map(|s| s.trim()).unwrap_or("");
     let arg = parts.get(1).map(|s| s.trim()).unwrap_or("");
@@ -451,11 +1155,7 @@ fn handle_debug_command(subcmd: &str, session: &ChatSession) {
             println!("\n=== Message History ({} messages) ===\n", session.messages.len());
             for (i, msg) in session.messages.iter().enumerate() {
                 let content = msg.content.to_string_lossy();
-                let preview = if content.len() > 80 {
-                    format!("{}...", &content[..80].replace('\n', " "))
-                } else {
-                    content.replace('\n', " ")
-                };
+                let preview = ellipsize(&content.replace('\n', " "), 80);
                 let tool_info = if !msg.tool_calls.is_empty() {
                     format!(" [+{} tool calls]", msg.tool_calls.len())
                 } else if msg.tool_call_id.is_some() {
@@ -463,7 +1163,12 @@ fn handle_debug_command(subcmd: &str, session: &ChatSession) {
                 } else {
                     String::new()
                 };
-                println!("[{}] {} ({} chars){}: {}", i, msg.role, msg.content.to_string_lossy().len(), tool_info, preview);
+                let provenance = msg
+                    .metadata
+                    .as_ref()
+                    .map(format_provenance)
+                    .unwrap_or_default();
+                println!("[{}] {} ({} chars){}{}: {}", i, msg.role, msg.content.to_string_lossy().len(), tool_info, provenance, preview);
             }
             println!();
         }
@@ -477,6 +1182,17 @@ fn handle_debug_command(subcmd: &str, session: &ChatSession) {
             for (role, count) in &counts {
                 println!("  {}: {}", role, count);
             }
+            let usage = qq_core::usage_by_agent(&session.messages);
+            if !usage.is_empty() {
+                println!("\n=== Tokens by Agent ===");
+                for (agent, u) in &usage {
+                    let agent = if agent.is_empty() { "(chat)" } else { agent.as_str() };
+                    println!(
+                        "  {}: {} prompt + {} completion",
+                        agent, u.prompt_tokens, u.completion_tokens
+                    );
+                }
+            }
             println!();
         }
         "dump" => {
@@ -489,16 +1205,42 @@ fn handle_debug_command(subcmd: &str, session: &ChatSession) {
                 Err(e) => eprintln!("Failed to dump messages: {}", e),
             }
         }
+        "events" | "e" => {
+            let stats = event_bus.stats();
+            println!("\n=== Agent Event Bus ===");
+            println!("  Thinking deltas dropped: {}", stats.dropped);
+            println!("  Critical events delayed: {}", stats.critical_waits);
+            println!();
+        }
         "" => {
-            eprintln!("Debug subcommands: messages, count, dump <file>");
+            eprintln!("Debug subcommands: messages, count, events, dump <file>");
             eprintln!("Type /help for more information.");
         }
         _ => {
-            eprintln!("Unknown debug subcommand: {}. Use: messages, count, dump", cmd);
+            eprintln!("Unknown debug subcommand: {}. Use: messages, count, events, dump", cmd);
         }
     }
 }
 
+/// Short " {agent, model, tokens}" tag for `/debug messages`.
+fn format_provenance(meta: &qq_core::MessageMetadata) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(ref agent) = meta.agent {
+        parts.push(agent.clone());
+    }
+    if let Some(ref model) = meta.model {
+        parts.push(model.clone());
+    }
+    if let Some(ref usage) = meta.usage {
+        parts.push(format!("{} tok", usage.total_tokens));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" {{{}}}", parts.join(", "))
+    }
+}
+
 /// Dump messages to a JSON file for analysis
 fn dump_messages_to_file(messages: &[Message], filename: &str) -> std::io::Result<()> {
     use std::fs::File;
@@ -512,90 +1254,198 @@ fn dump_messages_to_file(messages: &[Message], filename: &str) -> std::io::Resul
     Ok(())
 }
 
-/// Run interactive chat mode
-#[allow(clippy::too_many_arguments)]
-pub async fn run_chat(
-    cli: &Cli,
-    _config: &AppConfig,
-    provider: Arc<dyn Provider>,
-    _profile_registry: crate::profile_registry::SharedProfileRegistry,
-    system_prompt: Option<String>,
-    tools_registry: ToolRegistry,
-    extra_params: std::collections::HashMap<String, serde_json::Value>,
-    model: Option<String>,
-    agent_executor: Option<Arc<RwLock<AgentExecutor>>>,
-    chunker_config: ChunkerConfig,
-    event_bus: AgentEventBus,
-    debug_logger: Option<Arc<DebugLogger>>,
-    agent_memory: AgentMemory,
-    bash_mounts: Option<Arc<qq_tools::SandboxMounts>>,
-    approval_rx: Option<tokio::sync::mpsc::Receiver<qq_tools::ApprovalRequest>>,
-    _bash_permissions: Option<Arc<qq_tools::PermissionStore>>,
-    task_store: Option<Arc<qq_tools::TaskStore>>,
-    compactor: Option<Arc<dyn ContextCompactor>>,
-    observation_config: ObservationConfig,
-    mcp_manager: Option<&qq_mcp::McpManager>,
-) -> Result<()> {
-    // Create chunk processor for large tool outputs
-    let chunk_processor = ChunkProcessor::new(Arc::clone(&provider), chunker_config);
+/// Receive from a channel that may not exist; `None` disables the branch.
+async fn recv_opt<T>(rx: &mut Option<tokio::sync::mpsc::Receiver<T>>) -> Option<T> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => None,
+    }
+}
 
-    // Subscribe to event bus for agent notifications
-    let mut event_rx = event_bus.subscribe();
-    tokio::spawn(async move {
-        while let Ok(event) = event_rx.recv().await {
-            if let AgentEvent::UserNotification { agent_name, message } = event {
-                // Print notification to stdout with visual distinction
-                println!("\n> [{}] {}", agent_name, message);
-            }
-        }
-    });
+/// Ask on stderr whether a command or file operation may proceed.
+fn prompt_approval(request: qq_tools::ApprovalRequest) {
+    eprintln!("\n--- {} approval required ---", request.category);
+    eprintln!("  {}", request.full_command);
+    if let Some(ref explanation) = request.explanation {
+        eprintln!("  Explanation: {}", explanation);
+    }
+    if !request.trigger_commands.is_empty() {
+        eprintln!(
+            "  Requires approval: {}",
+            request.trigger_commands.join(", ")
+        );
+    }
+    if let Some(ref preview) = request.preview {
+        eprintln!("  Changes: {}", DiffView::new(preview).title());
+        if let Err(e) = diff_pager::show_diff(preview) {
+            eprintln!("  Could not show the diff: {}", e);
+        }
+    }
 
-    // Spawn approval handler (handles per-call approval for bash commands and file operations)
-    if let Some(mut approval_rx) = approval_rx {
-        tokio::spawn(async move {
-            while let Some(request) = approval_rx.recv().await {
-                eprintln!("\n--- {} approval required ---", request.category);
-                eprintln!("  {}", request.full_command);
-                if !request.trigger_commands.is_empty() {
-                    eprintln!(
-                        "  Requires approval: {}",
-                        request.trigger_commands.join(", ")
-                    );
+    let choices = if request.preview.is_some() {
+        "[a]llow once / allow for [s]ession / [v]iew diff / [d]eny (default: deny)"
+    } else {
+        "[a]llow once / allow for [s]ession / [d]eny (default: deny)"
+    };
+    let mut input = String::new();
+    // `v` pages through the diff again, then asks again
+    let read = loop {
+        eprintln!("  {}", choices);
+        eprint!("  > ");
+        input.clear();
+        let read = std::io::stdin().read_line(&mut input);
+        match request.preview {
+            Some(ref preview) if read.is_ok() && matches!(input.trim(), "v" | "V" | "view") => {
+                if let Err(e) = diff_pager::show_diff(preview) {
+                    eprintln!("  Could not show the diff: {}", e);
                 }
-                eprintln!("  [a]llow once / allow for [s]ession / [d]eny (default: deny)");
-                eprint!("  > ");
-
-                let mut input = String::new();
-                match std::io::stdin().read_line(&mut input) {
-                    Ok(_) => {
-                        let response = match input.trim().to_lowercase().as_str() {
-                            "a" | "allow" => qq_tools::ApprovalResponse::Allow,
-                            "s" | "session" => qq_tools::ApprovalResponse::AllowForSession,
-                            _ => {
-                                eprint!("  Reason (optional, Enter to skip): ");
-                                let mut reason_input = String::new();
-                                let reason = match std::io::stdin().read_line(&mut reason_input) {
-                                    Ok(_) => {
-                                        let trimmed = reason_input.trim();
-                                        if trimmed.is_empty() {
-                                            None
-                                        } else {
-                                            Some(trimmed.to_string())
-                                        }
-                                    }
-                                    Err(_) => None,
-                                };
-                                qq_tools::ApprovalResponse::Deny(reason)
+            }
+            _ => break read,
+        }
+    };
+    match read {
+        Ok(_) => {
+            let response = match input.trim().to_lowercase().as_str() {
+                "a" | "allow" => qq_tools::ApprovalResponse::Allow,
+                "s" | "session" => qq_tools::ApprovalResponse::AllowForSession,
+                _ => {
+                    eprint!("  Reason (optional, Enter to skip): ");
+                    let mut reason_input = String::new();
+                    let reason = match std::io::stdin().read_line(&mut reason_input) {
+                        Ok(_) => {
+                            let trimmed = reason_input.trim();
+                            if trimmed.is_empty() {
+                                None
+                            } else {
+                                Some(trimmed.to_string())
                             }
-                        };
-                        let _ = request.response_tx.send(response);
-                    }
-                    Err(_) => {
-                        let _ =
-                            request
-                                .response_tx
-                                .send(qq_tools::ApprovalResponse::Deny(None));
+                        }
+                        Err(_) => None,
+                    };
+                    qq_tools::ApprovalResponse::Deny(reason)
+                }
+            };
+            let _ = request.response_tx.send(response);
+        }
+        Err(_) => {
+            let _ = request.response_tx.send(qq_tools::ApprovalResponse::Deny(None));
+        }
+    }
+}
+
+/// Read one trimmed line from stdin; `None` on EOF or error.
+fn read_stdin_line() -> Option<String> {
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input.trim().to_string()),
+    }
+}
+
+/// Ask on stderr whether a tool call runs as proposed, runs with edited
+/// arguments, or is skipped (supervised mode).
+fn prompt_tool_review(request: qq_tools::ToolReviewRequest) {
+    eprintln!("\n--- Review tool call: {} ---", request.tool);
+    for line in request.arguments_pretty().lines() {
+        eprintln!("  {}", line);
+    }
+    eprintln!("  [r]un / [e]dit arguments / [s]kip (default: run)");
+    eprint!("  > ");
+
+    let review = match read_stdin_line().map(|s| s.to_lowercase()).as_deref() {
+        None => qq_tools::ToolReview::Skip(None),
+        Some("e") | Some("edit") => loop {
+            eprintln!("  Arguments as one line of JSON (Enter to keep):");
+            eprintln!("  {}", request.arguments_compact());
+            eprint!("  > ");
+            match read_stdin_line().as_deref() {
+                None | Some("") => break qq_tools::ToolReview::Run,
+                Some(text) => match qq_tools::parse_edited_arguments(text) {
+                    Ok(arguments) if arguments == request.arguments => {
+                        break qq_tools::ToolReview::Run
                     }
+                    Ok(arguments) => break qq_tools::ToolReview::Edit(arguments),
+                    Err(e) => eprintln!("  {}", e),
+                },
+            }
+        },
+        Some("s") | Some("skip") => {
+            eprint!("  Reason (optional, Enter to skip): ");
+            let reason = read_stdin_line().filter(|r| !r.is_empty());
+            qq_tools::ToolReview::Skip(reason)
+        }
+        Some(_) => qq_tools::ToolReview::Run,
+    };
+    let _ = request.response_tx.send(review);
+}
+
+/// Run interactive chat mode
+#[allow(clippy::too_many_arguments)]
+pub async fn run_chat(
+    cli: &Cli,
+    app_config: &AppConfig,
+    provider: Arc<dyn Provider>,
+    profile_registry: crate::profile_registry::SharedProfileRegistry,
+    system_prompt: Option<String>,
+    tools_registry: ToolRegistry,
+    extra_params: std::collections::HashMap<String, serde_json::Value>,
+    model: Option<String>,
+    agent_executor: Option<Arc<RwLock<AgentExecutor>>>,
+    chunker_config: ChunkerConfig,
+    event_bus: AgentEventBus,
+    debug_logger: Option<Arc<DebugLogger>>,
+    agent_memory: AgentMemory,
+    bash_mounts: Option<Arc<qq_tools::SandboxMounts>>,
+    approval_rx: Option<tokio::sync::mpsc::Receiver<qq_tools::ApprovalRequest>>,
+    review_rx: Option<tokio::sync::mpsc::Receiver<qq_tools::ToolReviewRequest>>,
+    bash_permissions: Option<Arc<qq_tools::PermissionStore>>,
+    access_log: Option<Arc<qq_tools::AccessLog>>,
+    task_store: Option<Arc<qq_tools::TaskStore>>,
+    compactor: Option<Arc<dyn ContextCompactor>>,
+    observation_config: ObservationConfig,
+    sliding_window: Option<SlidingWindowMemory>,
+    mcp_manager: Option<&qq_mcp::McpManager>,
+    event_socket: Option<EventSocket>,
+    mirror: Option<SessionMirror>,
+    session_log: Option<SessionLog>,
+    model_router: Option<ModelRouter>,
+    knowledge: Option<Arc<qq_tools::KnowledgeStore>>,
+) -> Result<()> {
+    // Create chunk processor for large tool outputs
+    let chunk_processor = ChunkProcessor::new(Arc::clone(&provider), chunker_config);
+    let main_profile = profile_registry.read().await.default_profile().to_string();
+
+    // Subscribe to event bus for agent notifications
+    let mut event_rx = event_bus.subscribe();
+    tokio::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            match event {
+                AgentEvent::UserNotification { agent_name, message } => {
+                    // Print notification to stdout with visual distinction
+                    println!("\n> [{}] {}", agent_name, message);
+                }
+                AgentEvent::ContextRecovered { agent_name, action } => {
+                    println!(
+                        "\n> [{}] Context window exceeded: {}; retrying",
+                        agent_name, action
+                    );
+                }
+                _ => {}
+            }
+        }
+    });
+
+    // Spawn the prompt handler: per-call approval for bash commands and file
+    // operations, and tool call review in supervised mode. One task keeps
+    // concurrent calls from prompting over each other.
+    if approval_rx.is_some() || review_rx.is_some() {
+        let (mut approval_rx, mut review_rx) = (approval_rx, review_rx);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(request) = recv_opt(&mut approval_rx) => prompt_approval(request),
+                    Some(request) = recv_opt(&mut review_rx) => prompt_tool_review(request),
+                    else => break,
                 }
             }
         });
@@ -615,11 +1465,36 @@ pub async fn run_chat(
         let _ = rl.load_history(path);
     }
 
+    let pricing = Pricing::new(&app_config.pricing);
+    let mut show_turn_stats = app_config.tui.turn_stats;
+    // Request timing per model, for `request_metrics` socket events
+    let mut latency = LatencyStats::default();
+    // Tells the agent about a `/focus` change with the next message
+    let mut focus_note: Option<String> = None;
+    // Dropped files queued for the next message
+    let mut dropped_context = ContextFiles::new();
+    let mut dropped_images: Vec<qq_core::TypedContent> = Vec::new();
+    let memory_limits = &app_config.session_memory;
     let mut session = ChatSession::new(system_prompt)
-        .with_observation_config(observation_config);
+        .with_observation_config(observation_config)
+        .with_history_spill(HistorySpill::new(
+            memory_limits.history_bytes,
+            memory_limits.spill_to_disk,
+        ))
+        .with_summary_archive(SummaryArchive::new(memory_limits.spill_to_disk));
     if let Some(compactor) = compactor {
         session = session.with_compactor(compactor);
     }
+    if let Some(window) = sliding_window {
+        session = session.with_sliding_window(window);
+    }
+    if let Some(hooks) = event_bus.hooks() {
+        session = session.with_hooks(Arc::clone(hooks));
+    }
+    if let Some(regrounding) = session_regrounding(app_config, task_store.as_ref()) {
+        session = session.with_regrounding(regrounding);
+    }
+    session = session.with_context_slots(session_context_slots(app_config));
 
     // Log conversation start
     if let Some(ref logger) = debug_logger {
@@ -629,39 +1504,150 @@ pub async fn run_chat(
         );
     }
 
+    if let Some(ref notice) = cli.project_notice {
+        println!("{}", notice);
+    }
+
     loop {
         // Print hint line before prompt
         print_prompt_hint()?;
 
         match rl.readline("you> ") {
-            Ok(line) => {
+            Ok(mut line) => {
+                // `/context set <name> <<EOF`: read the body up to the marker
+                if let Some(marker) = context_slots::heredoc_marker(&line).map(str::to_string) {
+                    loop {
+                        match rl.readline("... ") {
+                            Ok(next) => {
+                                let done = next.trim() == marker;
+                                line.push('\n');
+                                line.push_str(&next);
+                                if done {
+                                    break;
+                                }
+                            }
+                            Err(_) => {
+                                println!("Cancelled.\n");
+                                line.clear();
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                // Files dragged onto the terminal arrive as pasted paths
+                if let Some(dropped) = DroppedFiles::parse(&line) {
+                    match rl.readline(&dropped.prompt()) {
+                        Ok(answer) if dropped_files::accepts(&answer) => {
+                            let root = std::env::current_dir().unwrap_or_default();
+                            let (files, errors) = dropped.ingest(&root);
+                            let (mut images, mut added) = (0, 0);
+                            for file in files {
+                                match file {
+                                    Dropped::Image(image) => {
+                                        dropped_images.push(qq_core::TypedContent::image(image));
+                                        images += 1;
+                                    }
+                                    Dropped::File(file) => {
+                                        dropped_context.add(file);
+                                        added += 1;
+                                    }
+                                }
+                            }
+                            println!("{}\n", dropped_files::summary(images, added, &errors));
+                            continue;
+                        }
+                        // Declined: send the paths as typed
+                        Ok(_) => {}
+                        Err(_) => {
+                            println!("Cancelled.\n");
+                            continue;
+                        }
+                    }
+                }
+
                 // Add to readline history
                 let _ = rl.add_history_entry(&line);
 
-                match parse_command(&line) {
+                let agent_names = match agent_executor {
+                    Some(ref executor) => Some(executor.read().await.agent_names()),
+                    None => None,
+                };
+                match parse_command(&line, agent_names.as_deref()) {
                     ChatCommand::Quit => {
                         println!("Goodbye!");
                         break;
                     }
-                    ChatCommand::Clear => {
-                        session.clear();
-                        println!("Conversation cleared.\n");
+                    ChatCommand::Clear(arg) => {
+                        let summarize = match carry_over::clear_choice(&arg, memory_limits.clear_summary) {
+                            Ok(Some(summarize)) => summarize,
+                            Ok(None) if session.message_count() == 0 => false,
+                            Ok(None) => match rl.readline(carry_over::CLEAR_PROMPT) {
+                                Ok(answer) => carry_over::is_yes(&answer),
+                                Err(_) => {
+                                    println!("Clear cancelled.\n");
+                                    continue;
+                                }
+                            },
+                            Err(e) => {
+                                println!("{}\n", e);
+                                continue;
+                            }
+                        };
+                        if summarize {
+                            println!("Summarizing...");
+                        }
+                        let outcome = session
+                            .clear_with_carry_over(summarize, crate::paths::transcripts_dir().as_deref(), &pricing)
+                            .await;
+                        focus_note = bash_mounts.as_deref().and_then(focus::current_note);
+                        println!("{}.\n", outcome.status());
                     }
                     ChatCommand::Reset => {
                         session.clear();
+                        focus_note = bash_mounts.as_deref().and_then(focus::current_note);
                         agent_memory.clear_all().await;
                         if let Some(ref ts) = task_store {
                             ts.clear();
                         }
                         println!("Session reset (conversation + agent memory + tasks cleared).\n");
                     }
+                    ChatCommand::Undo => match session.undo() {
+                        Ok(removed) => {
+                            println!("Took back the last exchange ({} messages). /redo restores it.\n", removed)
+                        }
+                        Err(e) => println!("{}.\n", e),
+                    },
+                    ChatCommand::Redo => match session.redo() {
+                        Ok(restored) => println!("Restored the exchange ({} messages).\n", restored),
+                        Err(e) => println!("{}.\n", e),
+                    },
                     ChatCommand::History => {
                         println!(
-                            "Messages in conversation: {} ({} user + assistant turns)\n",
+                            "Messages in conversation: {} ({} user + assistant turns, {} pinned)\n",
                             session.message_count(),
-                            session.message_count() / 2
+                            session.message_count() / 2,
+                            session.pinned_count()
                         );
+                        print!("{}", history_outline(&session.messages));
+                        if session.message_count() > 0 {
+                            println!();
+                        }
                     }
+                    ChatCommand::Pin(target) => match session.set_pinned(&target, true) {
+                        Ok((first, last)) => println!(
+                            "Pinned {}; compaction keeps it verbatim. /unpin releases it.\n",
+                            describe_messages(first, last)
+                        ),
+                        Err(e) => println!("{}.\n", e),
+                    },
+                    ChatCommand::Unpin(target) if target.trim() == "all" => {
+                        println!("Unpinned {} messages.\n", session.unpin_all());
+                    }
+                    ChatCommand::Unpin(target) => match session.set_pinned(&target, false) {
+                        Ok((first, last)) => println!("Unpinned {}.\n", describe_messages(first, last)),
+                        Err(e) => println!("{}.\n", e),
+                    },
                     ChatCommand::Help => {
                         print_help();
                     }
@@ -675,6 +1661,22 @@ pub async fn run_chat(
                         println!("  Total context:     {}", format_bytes(session.total_bytes()));
                         println!("  Observations:      {}", session.observation_memory.observation_count);
                         println!("  Reflections:       {}", session.observation_memory.reflection_count);
+                        if let Some(window) = session.sliding_window() {
+                            println!(
+                                "  Sliding window:    {} messages ({} exchanges archived, {} recalled)",
+                                window.config().recent_messages,
+                                window.archived_count(),
+                                window.recalled_count()
+                            );
+                        }
+                        if let Some(spill) = session.history_spill() {
+                            println!(
+                                "  History ceiling:   {} ({} messages moved out, {} on disk)",
+                                format_bytes(spill.ceiling()),
+                                spill.moved_count(),
+                                format_bytes(spill.spilled_bytes() as usize)
+                            );
+                        }
                         if let Some(rss) = get_rss_bytes() {
                             println!("  Process RSS:       {}", format_bytes(rss));
                         }
@@ -693,6 +1695,23 @@ pub async fn run_chat(
                         }
                         println!();
                     }
+                    ChatCommand::Export(arg) => {
+                        let path = export_path(&arg);
+                        let written = session
+                            .export_messages()
+                            .and_then(|messages| std::fs::write(&path, history_markdown(&messages, &pricing)));
+                        match written {
+                            Ok(()) => println!("Conversation exported to {}\n", path.display()),
+                            Err(e) => println!("Export failed: {}\n", e),
+                        }
+                    }
+                    ChatCommand::Costs => {
+                        show_turn_stats = !show_turn_stats;
+                        println!(
+                            "Turn cost lines {}.\n",
+                            if show_turn_stats { "shown" } else { "hidden" }
+                        );
+                    }
                     ChatCommand::Mount(path_str) => {
                         if path_str.is_empty() {
                             println!("Usage: /mount <path>");
@@ -705,11 +1724,16 @@ pub async fn run_chat(
                             } else {
                                 match expanded.canonicalize() {
                                     Ok(canonical) => {
-                                        mounts.add_mount(qq_tools::MountPoint {
+                                        match mounts.add_mount(qq_tools::MountPoint {
                                             host_path: canonical.clone(),
                                             label: None,
-                                        });
-                                        println!("Mount added: {} (read-only)", canonical.display());
+                                        }) {
+                                            Ok(()) => {
+                                                crate::project_defaults::remember_mount(mounts.project_root(), &canonical);
+                                                println!("Mount added: {} (read-only)", canonical.display());
+                                            }
+                                            Err(e) => println!("Mount not allowed: {}", e),
+                                        }
                                     }
                                     Err(e) => println!("Failed to resolve path: {}", e),
                                 }
@@ -727,6 +1751,49 @@ pub async fn run_chat(
                             println!("Bash tools are disabled.");
                         }
                     }
+                    ChatCommand::Focus(arg) => {
+                        if let Some(ref mounts) = bash_mounts {
+                            let change = focus::focus(mounts, &arg);
+                            println!("{}\n", change.status);
+                            focus_note = change.note.or(focus_note);
+                        } else {
+                            println!("Bash tools are disabled.");
+                        }
+                    }
+                    ChatCommand::Unfocus => {
+                        if let Some(ref mounts) = bash_mounts {
+                            let change = focus::unfocus(mounts);
+                            println!("{}\n", change.status);
+                            focus_note = change.note.or(focus_note);
+                        } else {
+                            println!("Bash tools are disabled.");
+                        }
+                    }
+                    ChatCommand::Context(arg) => match session.context_command(&arg) {
+                        SlotsReply::Listing(text) => println!("\n{}", text),
+                        SlotsReply::Status(status) => println!("{}\n", status),
+                    },
+                    ChatCommand::Permissions(args) => {
+                        if let Some(ref store) = bash_permissions {
+                            if args.trim().is_empty() {
+                                println!("\nBash permissions:");
+                                println!("{}", permissions::format_permissions_view(store));
+                            } else if args.trim() == "help" {
+                                println!("{}", permissions::PERMISSIONS_USAGE);
+                            } else {
+                                println!("{}", permissions::apply_permissions_edit(store, &args));
+                            }
+                        } else {
+                            println!("Bash tools are disabled.");
+                        }
+                    }
+                    ChatCommand::AccessLog => {
+                        if let Some(ref log) = access_log {
+                            println!("\n{}", log.format());
+                        } else {
+                            println!("Bash tools are disabled.");
+                        }
+                    }
                     ChatCommand::Tools => {
                         println!("\nAvailable tools:");
                         let mut entries: Vec<_> = tools_registry.names().into_iter()
@@ -820,14 +1887,37 @@ pub async fn run_chat(
                         }
                     }
                     ChatCommand::Debug(subcmd) => {
-                        handle_debug_command(&subcmd, &session);
+                        handle_debug_command(&subcmd, &session, &event_bus);
                     }
                     ChatCommand::None(text) => {
                         if text.is_empty() {
                             continue;
                         }
 
-                        session.add_user_message(&text);
+                        let history_len = session.messages.len();
+                        let sent_focus_note = focus_note.take();
+                        // Dropped text files go ahead of the message text
+                        let root = std::env::current_dir().unwrap_or_default();
+                        let (blocks, errors) = dropped_context.take_pending(&root);
+                        if !errors.is_empty() {
+                            eprintln!("Skipped: {}", errors.join("; "));
+                        }
+                        let content = if blocks.is_empty() {
+                            text.clone()
+                        } else {
+                            format!("{}\n\n{}", blocks, text)
+                        };
+                        let content = focus::with_note(sent_focus_note.clone(), &content);
+                        if dropped_images.is_empty() {
+                            session.add_user_message(&content);
+                        } else {
+                            let mut parts = std::mem::take(&mut dropped_images);
+                            parts.insert(0, qq_core::TypedContent::text(&content));
+                            session.add_user_parts(parts);
+                        }
+                        if let Some(ref mirror) = mirror {
+                            mirror.user(&text);
+                        }
 
                         // Log user message
                         if let Some(ref logger) = debug_logger {
@@ -835,7 +1925,7 @@ pub async fn run_chat(
                         }
 
                         // Run completion loop
-                        match run_completion(
+                        let completion = run_completion(
                             cli,
                             &provider,
                             &mut session,
@@ -845,14 +1935,51 @@ pub async fn run_chat(
                             debug_logger.as_ref(),
                             &chunk_processor,
                             &text,
-                        )
-                        .await
-                        {
-                            Ok(_) => {}
-                            Err(e) => {
+                            event_socket.as_ref(),
+                            mirror.as_ref(),
+                            model_router.as_ref().map(|r| (r, main_profile.as_str())),
+                            &mut latency,
+                        );
+                        // Event socket clients can cancel the in-flight turn
+                        let outcome = match event_socket {
+                            Some(ref socket) => {
+                                socket.take_cancel();
+                                tokio::select! {
+                                    result = completion => Some(result),
+                                    _ = socket.cancelled() => None,
+                                }
+                            }
+                            None => Some(completion.await),
+                        };
+                        match outcome {
+                            Some(Ok(_)) => {
+                                let turn_start = session
+                                    .messages
+                                    .iter()
+                                    .rposition(|m| m.role == qq_core::Role::User)
+                                    .unwrap_or(0);
+                                let stats = TurnStats::from_messages(&session.messages[turn_start..]);
+                                if let Some(footer) = stats.footer(&pricing).filter(|_| show_turn_stats) {
+                                    print_turn_stats(&footer)?;
+                                }
+                            }
+                            Some(Err(e)) => {
                                 eprintln!("\nError: {}\n", e);
+                                emit_session(
+                                    event_socket.as_ref(),
+                                    mirror.as_ref(),
+                                    SessionEvent::Error { message: e.to_string() },
+                                );
                                 // Remove the failed user message
                                 session.messages.pop();
+                                focus_note = sent_focus_note;
+                            }
+                            None => {
+                                eprintln!("\nCancelled\n");
+                                emit_session(event_socket.as_ref(), mirror.as_ref(), SessionEvent::Cancelled);
+                                // Drop the whole turn, including any unanswered tool calls
+                                session.messages.truncate(history_len);
+                                focus_note = sent_focus_note;
                             }
                         }
                     }
@@ -873,14 +2000,25 @@ pub async fn run_chat(
         }
     }
 
-    // Save history
+    // Append this session's lines rather than rewriting the file, so
+    // sessions in other terminals keep theirs
     if let Some(path) = &history_path {
-        let _ = rl.save_history(path);
+        let saved = qq_tools::FileLock::acquire_async(path)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|_lock| rl.append_history(path).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            eprintln!("Warning: chat history not saved: {}", e);
+        }
     }
 
+    save_session_log(&session, session_log);
+    save_session_summary(&session, knowledge.as_deref()).await;
+
     Ok(())
 }
 
+/// Run one turn through the shared agent loop, rendering it to the terminal.
 #[allow(clippy::too_many_arguments)]
 async fn run_completion(
     cli: &Cli,
@@ -892,388 +2030,271 @@ async fn run_completion(
     debug_logger: Option<&Arc<DebugLogger>>,
     chunk_processor: &ChunkProcessor,
     original_query: &str,
+    event_socket: Option<&EventSocket>,
+    mirror: Option<&SessionMirror>,
+    model_router: Option<(&ModelRouter, &str)>,
+    latency: &mut LatencyStats,
 ) -> Result<()> {
-    let include_tool_reasoning = provider.include_tool_reasoning();
-    let max_iterations = 100;
-
-    for iteration in 0..max_iterations {
-        // Compact context if needed before building messages
-        session.compact_if_needed().await;
-
-        // Log iteration start
-        if let Some(logger) = debug_logger {
-            logger.log_iteration(iteration, "chat_completion");
-        }
-
-        let messages = session.build_messages();
-
-        tracing::debug!(
-            iteration = iteration,
-            message_count = messages.len(),
-            context_bytes = session.total_bytes(),
-            "Chat LLM call context size"
-        );
-
-        // Log messages being sent
+    let mut runner = AgentRunner::new(Arc::clone(provider), tools_registry.clone())
+        .with_chunker(chunk_processor.clone())
+        .with_request(crate::sampling_request(cli, model.as_deref(), extra_params))
+        .with_streaming(!cli.no_stream);
+    if let Some(regrounding) = session.regrounding() {
+        runner = runner.with_regrounding(regrounding.clone());
+    }
+    let mut printer = ChatPrinter {
+        debug_logger,
+        event_socket,
+        mirror,
+        model_router,
+        latency,
+        hooks: session.hooks.clone(),
+        model: model.clone(),
+        thinking: MarkdownRenderer::new(),
+        content: MarkdownRenderer::new(),
+        in_thinking: false,
+        in_content: false,
+    };
+    let outcome = runner.run(session, &mut printer, original_query).await?;
+
+    if outcome.max_iterations_reached {
         if let Some(logger) = debug_logger {
-            logger.log_messages_sent(&messages, model.as_deref());
-        }
-
-        let mut request = CompletionRequest::new(messages);
-
-        if let Some(m) = model {
-            request = request.with_model(m);
-        }
-
-        if let Some(temp) = cli.temperature {
-            request = request.with_temperature(temp);
-        }
-
-        if let Some(max_tokens) = cli.max_tokens {
-            request = request.with_max_tokens(max_tokens);
-        }
-        if let Some(top_k) = cli.top_k {
-            request = request.with_top_k(top_k);
-        }
-
-        if let Some(min_p) = cli.min_p {
-            request = request.with_min_p(min_p);
-        }
-
-        if let Some(presence_penalty) = cli.presence_penalty {
-            request = request.with_presence_penalty(presence_penalty);
+            logger.log_warning(&format!("Max iterations ({}) reached", outcome.iterations));
         }
+        eprintln!("Warning: Max iterations ({}) reached", outcome.iterations);
+        return Ok(());
+    }
 
-        if let Some(repetition_penalty) = cli.repetition_penalty {
-            request = request.with_repetition_penalty(repetition_penalty);
-        }
-
-        if !extra_params.is_empty() {
-            request = request.with_extra(extra_params.clone());
-        }
-
-        request = request.with_tools(tools_registry.definitions());
-
-        // Non-streaming mode: use complete() instead of stream()
-        if cli.no_stream {
-            let response = provider.complete(request).await?;
+    printer.finish()?;
+    emit_session(event_socket, mirror, SessionEvent::ResponseComplete { usage: outcome.usage });
+    if let Some(logger) = debug_logger {
+        logger.log_message_stored("assistant", outcome.content.len(), false);
+    }
+    Ok(())
+}
 
-            tracing::debug!(
-                content_len = response.message.content.to_string_lossy().len(),
-                tool_calls = response.message.tool_calls.len(),
-                "Non-streaming response received"
-            );
+/// Renders a turn run by [`AgentRunner`] to the terminal, and reports it to
+/// socket clients, the mirror file, hooks and the debug log.
+struct ChatPrinter<'a> {
+    debug_logger: Option<&'a Arc<DebugLogger>>,
+    event_socket: Option<&'a EventSocket>,
+    mirror: Option<&'a SessionMirror>,
+    model_router: Option<(&'a ModelRouter, &'a str)>,
+    latency: &'a mut LatencyStats,
+    hooks: Option<Arc<Hooks>>,
+    /// Model of requests that are not routed, for the debug log
+    model: Option<String>,
+    thinking: MarkdownRenderer,
+    content: MarkdownRenderer,
+    in_thinking: bool,
+    in_content: bool,
+}
 
-            let content = response.message.content.to_string_lossy();
-            let tool_calls = response.message.tool_calls.clone();
-
-            // Log response received
-            if let Some(logger) = debug_logger {
-                logger.log_response_received(
-                    content.len(),
-                    None,
-                    tool_calls.len(),
-                    if tool_calls.is_empty() { "stop" } else { "tool_calls" },
-                );
-                logger.log_assistant_response(&content, None, tool_calls.len());
-            }
+impl ChatPrinter<'_> {
+    fn emit(&self, event: SessionEvent) {
+        emit_session(self.event_socket, self.mirror, event);
+    }
 
-            // Handle tool calls if any
-            if !tool_calls.is_empty() {
-                // Print any content before tool calls
-                if !content.is_empty() {
-                    print_section_header("Response")?;
-                    let mut renderer = MarkdownRenderer::new();
-                    renderer.push(&content)?;
-                    renderer.finish()?;
-                    println!();
+    fn render(&mut self, event: RunEvent) -> std::io::Result<()> {
+        match event {
+            RunEvent::IterationStart { iteration } => {
+                if let Some(logger) = self.debug_logger {
+                    logger.log_iteration(iteration - 1, "chat_completion");
                 }
-
-                // Add assistant message with tool calls; attach reasoning if configured
-                let reasoning = if include_tool_reasoning {
-                    response.thinking.clone()
-                } else {
-                    None
-                };
-                let assistant_msg =
-                    Message::assistant_with_tool_calls(content.as_str(), tool_calls.clone())
-                        .with_reasoning(reasoning);
-                session.add_assistant_with_tools(assistant_msg);
-
-                // Log message stored
-                if let Some(logger) = debug_logger {
-                    logger.log_message_stored("assistant", content.len(), true);
+                self.thinking = MarkdownRenderer::new();
+                self.content = MarkdownRenderer::new();
+                self.in_thinking = false;
+                self.in_content = false;
+            }
+            RunEvent::ThinkingDelta(delta) => {
+                if !self.in_thinking {
+                    print_section_header("Thinking")?;
+                    self.in_thinking = true;
                 }
-
-                // Show tool calls to user and log them
-                for tool_call in &tool_calls {
-                    print_tool_call(&tool_call.name, &tool_call.arguments)?;
-                    if let Some(logger) = debug_logger {
-                        let args_preview = format_tool_args(&tool_call.arguments);
-                        logger.log_tool_call(&tool_call.name, &args_preview);
-                        logger.log_tool_call_full(&tool_call.id, &tool_call.name, &tool_call.arguments);
+                self.emit(SessionEvent::ThinkingDelta { content: delta.clone() });
+                self.thinking.push(&delta)?;
+            }
+            RunEvent::ContentDelta(delta) => {
+                if !self.in_content {
+                    // Finish thinking section if we were in it
+                    if self.in_thinking {
+                        self.thinking.finish()?;
                     }
+                    print_section_header("Response")?;
+                    self.in_content = true;
                 }
-
-                // Build tool_call_id → tool_name map for result logging
-                let id_to_name: std::collections::HashMap<String, String> = tool_calls.iter()
-                    .map(|tc| (tc.id.clone(), tc.name.clone()))
-                    .collect();
-
-                let results = execute_tools_parallel_with_chunker(
-                    tools_registry,
-                    tool_calls,
-                    Some(chunk_processor),
-                    Some(original_query),
-                )
-                .await;
-
-                for result in results {
-                    let result_text = result.text_content();
-                    let ToolExecutionResult { tool_call_id, content, is_error, .. } = result;
-                    tracing::debug!(
-                        tool_call_id = %tool_call_id,
-                        result_len = result_text.len(),
-                        is_error = is_error,
-                        "Tool result received"
+                self.emit(SessionEvent::ContentDelta { content: delta.clone() });
+                self.content.push(&delta)?;
+            }
+            RunEvent::Response {
+                content,
+                thinking,
+                tool_calls,
+                usage,
+                model,
+                timing,
+                ..
+            } => {
+                let metrics = RequestMetrics::new(&timing, usage.as_ref());
+                let model = model.or_else(|| self.model.clone());
+                let key = model.as_deref().unwrap_or("unknown");
+                self.latency.record(key, metrics);
+                let average = self.latency.average(key).map(|(average, _)| average);
+                self.emit(SessionEvent::RequestMetrics {
+                    model,
+                    usage,
+                    metrics,
+                    average,
+                });
+                if let Some(logger) = self.debug_logger {
+                    logger.log_response_received(
+                        content.len(),
+                        thinking.as_ref().map(|t| t.len()),
+                        tool_calls,
+                        if tool_calls == 0 { "stop" } else { "tool_calls" },
                     );
-
-                    // Log tool result
-                    if let Some(logger) = debug_logger {
-                        logger.log_tool_result(&tool_call_id, result_text.len(), is_error);
-                        let tool_name = id_to_name.get(&tool_call_id).map(|s| s.as_str()).unwrap_or("unknown");
-                        logger.log_tool_result_full(&tool_call_id, tool_name, &result_text, is_error);
-                    }
-
-                    session.add_tool_result(&tool_call_id, content);
+                    logger.log_assistant_response(&content, thinking.as_deref(), tool_calls);
                 }
-
-                // Continue to get next response
-                continue;
-            }
-
-            // No tool calls - strip reasoning from history, print final response
-            qq_core::message::strip_reasoning_from_history(&mut session.messages);
-            print_section_header("Response")?;
-            let mut renderer = MarkdownRenderer::new();
-            renderer.push(&content)?;
-            renderer.finish()?;
-            session.add_assistant_message(&content);
-
-            // Log final message stored
-            if let Some(logger) = debug_logger {
-                logger.log_message_stored("assistant", content.len(), false);
-            }
-
-            return Ok(());
-        }
-
-        // Streaming mode: use stream()
-        let mut stream = provider.stream(request).await?;
-
-        // Set up markdown renderers for thinking and content
-        let mut thinking_renderer = MarkdownRenderer::new();
-        let mut content_renderer = MarkdownRenderer::new();
-        let mut in_thinking = false;
-        let mut in_content = false;
-        let mut tool_calls: Vec<ToolCall> = Vec::new();
-        let mut current_tool_call: Option<(String, String, String)> = None; // (id, name, arguments)
-        let mut accumulated_thinking = String::new();
-
-        while let Some(chunk) = stream.next().await {
-            match chunk? {
-                StreamChunk::Start { .. } => {}
-                StreamChunk::ThinkingDelta { content: delta } => {
-                    if !in_thinking {
-                        // Print thinking header
-                        print_section_header("Thinking")?;
-                        in_thinking = true;
-                    }
-                    if include_tool_reasoning {
-                        accumulated_thinking.push_str(&delta);
-                    }
-                    thinking_renderer.push(&delta)?;
+                if tool_calls > 0 && self.in_content {
+                    println!(); // Newline after any content
                 }
-                StreamChunk::Delta { content: delta } => {
-                    if !in_content {
-                        // Finish thinking section if we were in it
-                        if in_thinking {
-                            thinking_renderer.finish()?;
-                        }
-                        // Print content header
-                        print_section_header("Response")?;
-                        in_content = true;
-                    }
-                    content_renderer.push(&delta)?;
+            }
+            RunEvent::ToolCalls(message) => {
+                if let Some(logger) = self.debug_logger {
+                    logger.log_message_stored("assistant", message.content.to_string_lossy().len(), true);
                 }
-                StreamChunk::ToolCallStart { id, name } => {
-                    // Finish any pending tool call
-                    if let Some((tc_id, tc_name, tc_args)) = current_tool_call.take() {
-                        let args: serde_json::Value =
-                            serde_json::from_str(&tc_args).unwrap_or(serde_json::Value::Null);
-                        tool_calls.push(ToolCall::new(tc_id, tc_name, args));
-                    }
-                    current_tool_call = Some((id, name, String::new()));
+            }
+            RunEvent::ToolStart { id, name, arguments } => {
+                print_tool_call(&name, &arguments)?;
+                self.emit(SessionEvent::ToolStart {
+                    id: id.clone(),
+                    name: name.clone(),
+                    arguments: arguments.to_string(),
+                });
+                if let Some(ref hooks) = self.hooks {
+                    hooks.tool_started(PRIMARY_AGENT, &name, &arguments.to_string());
                 }
-                StreamChunk::ToolCallDelta { arguments } => {
-                    if let Some((_, _, ref mut args)) = current_tool_call {
-                        args.push_str(&arguments);
-                    }
+                if let Some(logger) = self.debug_logger {
+                    logger.log_tool_call(&name, &format_tool_args(&arguments));
+                    logger.log_tool_call_full(&id, &name, &arguments);
                 }
-                StreamChunk::Done { usage, finish_reason: _ } => {
-                    // Finish any pending tool call
-                    if let Some((tc_id, tc_name, tc_args)) = current_tool_call.take() {
-                        let args: serde_json::Value =
-                            serde_json::from_str(&tc_args).unwrap_or(serde_json::Value::Null);
-                        tool_calls.push(ToolCall::new(tc_id, tc_name, args));
-                    }
-
-                    if let Some(u) = usage {
-                        tracing::debug!(
-                            prompt_tokens = u.prompt_tokens,
-                            completion_tokens = u.completion_tokens,
-                            iteration = iteration + 1,
-                            "Stream iteration complete"
-                        );
-                    }
+            }
+            RunEvent::ToolComplete { id, name, result, is_error } => {
+                if let Some(logger) = self.debug_logger {
+                    logger.log_tool_result(&id, result.len(), is_error);
+                    logger.log_tool_result_full(&id, &name, &result, is_error);
                 }
-                StreamChunk::Error { message } => {
-                    return Err(anyhow::anyhow!("Stream error: {}", message));
+                if let Some(ref hooks) = self.hooks {
+                    hooks.tool_completed(PRIMARY_AGENT, &name, &result, is_error);
                 }
+                self.emit(SessionEvent::ToolComplete { id, name, is_error });
             }
-        }
-
-        // Get the actual content (not thinking) for storage
-        let content = content_renderer.content().to_string();
-        let thinking_len = if in_thinking {
-            Some(thinking_renderer.content().len())
-        } else {
-            None
-        };
-
-        // Log response received
-        if let Some(logger) = debug_logger {
-            logger.log_response_received(
-                content.len(),
-                thinking_len,
-                tool_calls.len(),
-                if tool_calls.is_empty() { "stop" } else { "tool_calls" },
-            );
-            let thinking = if in_thinking { Some(thinking_renderer.content()) } else { None };
-            logger.log_assistant_response(&content, thinking, tool_calls.len());
-        }
-
-        // Note: thinking_renderer content is displayed but NEVER stored in messages
-
-        // Handle tool calls if any
-        if !tool_calls.is_empty() {
-            if !content_renderer.is_empty() {
-                println!(); // Newline after any content
+            RunEvent::Retry { attempt, max_retries, error } => {
+                print_retry_note(&format!("[retrying ({}/{}): {}]", attempt, max_retries, error))?;
             }
-
-            // Add assistant message with tool calls; attach reasoning if configured
-            let reasoning = if include_tool_reasoning && !accumulated_thinking.is_empty() {
-                Some(std::mem::take(&mut accumulated_thinking))
-            } else {
-                None
-            };
-            let assistant_msg =
-                Message::assistant_with_tool_calls(content.as_str(), tool_calls.clone())
-                    .with_reasoning(reasoning);
-            session.add_assistant_with_tools(assistant_msg);
-
-            // Log message stored
-            if let Some(logger) = debug_logger {
-                logger.log_message_stored("assistant", content.len(), true);
+            RunEvent::ContextRecovered { action } => {
+                print_retry_note(&format!("[context window exceeded: {}; retrying]", action))?;
+                self.emit(SessionEvent::ContextRecovered { action });
             }
-
-            // Show tool calls to user and log them
-            for tool_call in &tool_calls {
-                print_tool_call(&tool_call.name, &tool_call.arguments)?;
-                if let Some(logger) = debug_logger {
-                    let args_preview = format_tool_args(&tool_call.arguments);
-                    logger.log_tool_call(&tool_call.name, &args_preview);
-                    logger.log_tool_call_full(&tool_call.id, &tool_call.name, &tool_call.arguments);
-                }
+            RunEvent::Regrounded { trigger, .. } => {
+                print_retry_note(&format!("[re-grounded on the request after {}]", trigger))?;
             }
-
-            // Build tool_call_id → tool_name map for result logging
-            let id_to_name: std::collections::HashMap<String, String> = tool_calls.iter()
-                .map(|tc| (tc.id.clone(), tc.name.clone()))
-                .collect();
-
-            let results = execute_tools_parallel_with_chunker(
-                tools_registry,
-                tool_calls,
-                Some(chunk_processor),
-                Some(original_query),
-            )
-            .await;
-
-            for result in results {
-                let result_text = result.text_content();
-                let ToolExecutionResult { tool_call_id, content, is_error, .. } = result;
-                tracing::debug!(
-                    tool_call_id = %tool_call_id,
-                    result_len = result_text.len(),
-                    is_error = is_error,
-                    "Tool result received"
-                );
-                tracing::trace!(
-                    tool_call_id = %tool_call_id,
-                    content = %result_text,
-                    "Tool result content"
-                );
-
-                // Log tool result
-                if let Some(logger) = debug_logger {
-                    logger.log_tool_result(&tool_call_id, result_text.len(), is_error);
-                    let tool_name = id_to_name.get(&tool_call_id).map(|s| s.as_str()).unwrap_or("unknown");
-                    logger.log_tool_result_full(&tool_call_id, tool_name, &result_text, is_error);
-                }
-
-                session.add_tool_result(&tool_call_id, content);
-            }
-
-            // Continue to get next response
-            continue;
+            _ => {}
         }
+        Ok(())
+    }
 
-        // No tool calls - strip reasoning from history and finish up
-        qq_core::message::strip_reasoning_from_history(&mut session.messages);
-        if in_thinking && !in_content {
+    /// Finish rendering the final response.
+    fn finish(&mut self) -> std::io::Result<()> {
+        if self.in_content {
+            self.content.finish()
+        } else if self.in_thinking {
             // Only had thinking, no content
-            thinking_renderer.finish()?;
-        } else if in_content {
-            content_renderer.finish()?;
+            self.thinking.finish()
+        } else {
+            Ok(())
         }
-        session.add_assistant_message(&content);
+    }
+}
 
-        // Log final message stored
-        if let Some(logger) = debug_logger {
-            logger.log_message_stored("assistant", content.len(), false);
+#[async_trait]
+impl RunHandler for ChatPrinter<'_> {
+    async fn on_event(&mut self, event: RunEvent) {
+        if let Err(e) = self.render(event) {
+            tracing::warn!(error = %e, "Failed to render response");
         }
-
-        return Ok(());
     }
 
-    // Log warning for max iterations
-    if let Some(logger) = debug_logger {
-        logger.log_warning(&format!("Max iterations ({}) reached", max_iterations));
+    async fn route(&mut self, messages: &[Message]) -> Option<Route> {
+        // Low-complexity calls go to the routing profile, if configured
+        let routed = self
+            .model_router
+            .and_then(|(router, main)| router.route(main, messages));
+        let route = routed.map(|turn| {
+            let model = turn.model();
+            tracing::info!(
+                profile = %turn.runtime.profile_name,
+                model = ?model,
+                reason = turn.class.label(),
+                "Routing turn to cheaper profile"
+            );
+            if let Err(e) = print_route_note(&turn.runtime.profile_name, turn.class.label()) {
+                tracing::warn!(error = %e, "Failed to render route note");
+            }
+            self.emit(SessionEvent::ModelRouted {
+                profile: turn.runtime.profile_name.clone(),
+                model: model.clone(),
+                reason: turn.class.label().to_string(),
+            });
+            Route {
+                provider: Arc::clone(&turn.runtime.provider),
+                model,
+                extra: turn.runtime.parameters.clone(),
+            }
+        });
+        if let Some(logger) = self.debug_logger {
+            let model = route.as_ref().map_or(self.model.as_deref(), |r| r.model.as_deref());
+            logger.log_messages_sent(messages, model);
+        }
+        route
     }
-    eprintln!("Warning: Max iterations ({}) reached", max_iterations);
-    Ok(())
 }
 
 fn get_history_path() -> Option<PathBuf> {
-    dirs::config_dir().map(|d| d.join("qq").join("chat_history"))
+    crate::paths::chat_history_file()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use qq_core::testing::MockCompactor;
-    use qq_core::ObservationConfig;
+    use qq_core::{ObservationConfig, ToolCall};
+
+    #[test]
+    fn test_parse_command_validates_agent_mentions() {
+        let names = vec!["coder".to_string(), "researcher".to_string()];
+
+        match parse_command("@res find the docs", Some(&names)) {
+            ChatCommand::AgentCall { agent, task } => {
+                assert_eq!(agent, "researcher");
+                assert_eq!(task, "find the docs");
+            }
+            _ => panic!("expected AgentCall"),
+        }
+        match parse_command("/delegate coder fix it", Some(&names)) {
+            ChatCommand::Delegate { agent, .. } => assert_eq!(agent, "coder"),
+            _ => panic!("expected Delegate"),
+        }
+        assert!(matches!(
+            parse_command("@reserch find the docs", Some(&names)),
+            ChatCommand::None(text) if text.is_empty()
+        ));
+        // Without an executor the name passes through unchecked
+        assert!(matches!(
+            parse_command("@anything task", None),
+            ChatCommand::AgentCall { .. }
+        ));
+    }
 
     #[test]
     fn test_new_session_empty() {
@@ -1305,6 +2326,22 @@ mod tests {
         assert_eq!(session.message_count(), 1);
     }
 
+    #[test]
+    fn test_tool_result_records_provenance() {
+        let mut session = ChatSession::new(None);
+        session.add_tool_result("tc-1", "tool output");
+        let meta = session.messages[0].metadata.as_ref().unwrap();
+        assert_eq!(meta.tool_call_ids, vec!["tc-1"]);
+        assert_eq!(format_provenance(meta), "");
+
+        let msg = Message::assistant("x").with_provenance(
+            Some("coder"),
+            Some("gpt-4o"),
+            Some(qq_core::Usage::new(10, 5)),
+        );
+        assert_eq!(format_provenance(msg.metadata.as_ref().unwrap()), " {coder, gpt-4o, 15 tok}");
+    }
+
     #[test]
     fn test_message_count_and_total_bytes() {
         let mut session = ChatSession::new(None);
@@ -1324,6 +2361,23 @@ mod tests {
         assert_eq!(msgs[0].content.as_text(), Some("Be helpful."));
     }
 
+    #[test]
+    fn test_build_messages_with_context_slots() {
+        let mut session = ChatSession::new(Some("Be helpful.".to_string()))
+            .with_context_slots(ContextSlots::load(None, "/work/app", 2000));
+        session.context_command("set api Never change the v1 API");
+        session.add_user_message("hi");
+
+        let msgs = session.build_messages();
+        let system = msgs[0].content.as_text().unwrap();
+        assert!(system.starts_with("Be helpful.\n\n## Context Slots"));
+        assert!(system.ends_with("### api\n\nNever change the v1 API"));
+
+        // Slots survive /clear
+        session.clear();
+        assert!(session.build_messages()[0].content.as_text().unwrap().contains("### api"));
+    }
+
     #[test]
     fn test_build_messages_without_system_prompt() {
         let mut session = ChatSession::new(None);
@@ -1333,6 +2387,25 @@ mod tests {
         assert_eq!(msgs.len(), 1); // just user
     }
 
+    #[test]
+    fn test_build_messages_dedups_rereads() {
+        let mut session = ChatSession::new(Some("system".to_string()));
+        let cat = serde_json::json!({"command": "cat src/lib.rs"});
+        for (id, version) in [("tc-1", "old"), ("tc-2", "new")] {
+            session.add_assistant_with_tools(Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall::new(id, "run", cat.clone())],
+            ));
+            session.add_tool_result(id, format!("fn {}() {{}}\n", version).repeat(50));
+        }
+
+        let msgs = session.build_messages();
+        assert_eq!(msgs[2].content.as_text(), Some("[superseded by the later run call tc-2]"));
+        assert!(msgs[4].content.to_string_lossy().contains("fn new()"));
+        // The session keeps the original
+        assert!(session.messages[1].content.to_string_lossy().contains("fn old()"));
+    }
+
     #[test]
     fn test_build_messages_with_observation_log() {
         let config = ObservationConfig {
@@ -1375,6 +2448,153 @@ mod tests {
         assert_eq!(session.observation_memory.reflection_count, 0);
     }
 
+    #[test]
+    fn test_undo_takes_back_whole_exchange() {
+        let mut session = ChatSession::new(None);
+        session.add_user_message("first");
+        session.add_message(
+            Message::assistant("one").with_provenance(None, None, Some(qq_core::Usage::new(100, 10))),
+        );
+        session.add_user_message("second");
+        session.add_assistant_with_tools(Message::assistant_with_tool_calls(
+            "",
+            vec![ToolCall::new("tc-1", "read_file", serde_json::json!({"path": "a"}))],
+        ));
+        session.add_tool_result("tc-1", "contents");
+        // Steering notes land mid-exchange without provenance
+        session.add_message(Message::user("[Note from the user] stop"));
+        session.add_message(
+            Message::assistant("two").with_provenance(None, None, Some(qq_core::Usage::new(300, 20))),
+        );
+        assert_eq!(session.last_usage().map(|u| u.prompt_tokens), Some(300));
+
+        assert_eq!(session.undo(), Ok(5));
+        assert_eq!(session.message_count(), 2);
+        assert_eq!(session.messages[1].content.to_string_lossy(), "one");
+        assert_eq!(session.last_usage().map(|u| u.prompt_tokens), Some(100));
+
+        assert_eq!(session.redo(), Ok(5));
+        assert_eq!(session.message_count(), 7);
+        assert_eq!(session.last_usage().map(|u| u.prompt_tokens), Some(300));
+        assert_eq!(session.redo(), Err("Nothing to redo".to_string()));
+
+        assert_eq!(session.undo(), Ok(5));
+        assert_eq!(session.undo(), Ok(2));
+        assert_eq!(session.undo(), Err("Nothing to undo".to_string()));
+        assert!(session.last_usage().is_none());
+    }
+
+    #[test]
+    fn test_undo_skips_injected_user_messages_with_provenance() {
+        let mut session = ChatSession::new(None);
+        session.add_user_message("fix the bug");
+        session.add_assistant_with_tools(Message::assistant_with_tool_calls(
+            "",
+            vec![ToolCall::new("tc-1", "read_file", serde_json::json!({"path": "a"}))],
+        ));
+        session.add_tool_result("tc-1", "contents");
+        // A user-role message injected mid-turn that carries provenance
+        session.push(Message::user("[Note from the user] use the new API").with_provenance(
+            Some("coder"),
+            None,
+            None,
+        ));
+        session.add_assistant_message("done");
+
+        assert_eq!(session.undo(), Ok(5));
+        assert_eq!(session.message_count(), 0);
+        assert_eq!(session.redo(), Ok(5));
+        assert_eq!(session.undo(), Ok(5));
+    }
+
+    #[test]
+    fn test_new_message_or_clear_drops_redo() {
+        let mut session = ChatSession::new(None);
+        session.add_user_message("typo");
+        session.add_assistant_message("huh?");
+        session.undo().unwrap();
+        session.add_user_message("what I meant");
+        assert_eq!(session.redo(), Err("Nothing to redo".to_string()));
+
+        session.undo().unwrap();
+        session.clear();
+        assert_eq!(session.redo(), Err("Nothing to redo".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_undo_refuses_compacted_exchange() {
+        let config = ObservationConfig {
+            message_threshold_bytes: 200,
+            observation_threshold_bytes: 100_000,
+            preserve_recent: 2,
+            hysteresis: 1.0,
+            ..Default::default()
+        };
+        let compactor = Arc::new(MockCompactor::new());
+        compactor.queue_observe(Ok("- asked for a long answer".to_string()));
+        let mut session = ChatSession::new(None)
+            .with_observation_config(config)
+            .with_compactor(compactor);
+
+        session.add_user_message("explain everything");
+        for _ in 0..6 {
+            session.add_assistant_message(&"x".repeat(100));
+        }
+        session.compact_if_needed().await;
+        assert!(session.message_count() > 0);
+
+        let err = session.undo().unwrap_err();
+        assert!(err.contains("compacted"), "{}", err);
+        assert_eq!(session.message_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_messages_survive_compaction() {
+        let config = ObservationConfig {
+            message_threshold_bytes: 200,
+            observation_threshold_bytes: 100_000,
+            preserve_recent: 2,
+            hysteresis: 1.0,
+            ..Default::default()
+        };
+        let compactor = Arc::new(MockCompactor::new());
+        compactor.queue_observe(Ok("- discussed the schema".to_string()));
+        let mut session = ChatSession::new(None)
+            .with_observation_config(config)
+            .with_compactor(compactor);
+
+        assert_eq!(session.set_pinned("", true), Err("No messages to pin".to_string()));
+        session.add_user_message("the API must stay backward compatible");
+        session.add_assistant_with_tools(Message::assistant_with_tool_calls(
+            "",
+            vec![ToolCall::new("tc-1", "read_file", serde_json::json!({"path": "schema.sql"}))],
+        ));
+        session.add_tool_result("tc-1", "CREATE TABLE users (id INTEGER);");
+        for _ in 0..6 {
+            session.add_assistant_message(&"x".repeat(100));
+        }
+
+        assert_eq!(session.set_pinned("1", true), Ok((1, 1)));
+        // Pinning a tool result pins its call too
+        assert_eq!(session.set_pinned("3", true), Ok((2, 3)));
+        assert!(session.set_pinned("4-2", true).unwrap_err().contains("backwards"));
+        assert!(session.set_pinned("12", true).unwrap_err().contains("1-9"));
+        assert_eq!(session.pinned_count(), 3);
+        assert!(history_outline(&session.messages).starts_with("   1* you         the API must"));
+
+        assert!(session.compact_if_needed().await.is_some());
+        assert_eq!(session.message_count(), 5);
+        assert_eq!(session.pinned_count(), 3);
+        assert_eq!(
+            session.messages[0].content.to_string_lossy(),
+            "the API must stay backward compatible"
+        );
+
+        assert_eq!(session.toggle_last_pin(), Some(true));
+        assert_eq!(session.unpin_all(), 4);
+        assert_eq!(session.set_pinned("1-2", false), Ok((1, 3)));
+    }
+
     #[tokio::test]
     async fn test_compact_if_needed_triggers_observation() {
         let config = ObservationConfig {
@@ -1419,6 +2639,119 @@ mod tests {
         assert_eq!(session.message_count(), count); // No change
     }
 
+    #[tokio::test]
+    async fn test_compact_if_needed_enforces_history_ceiling() {
+        let mut session =
+            ChatSession::new(None).with_history_spill(HistorySpill::new(1000, false));
+        for _ in 0..20 {
+            session.add_user_message(&"x".repeat(100));
+        }
+
+        session.compact_if_needed().await;
+
+        assert_eq!(session.message_count(), 5);
+        assert_eq!(session.history_spill().unwrap().moved_count(), 15);
+        // Discarded rather than spilled, so export only has what is left
+        assert_eq!(session.export_messages().unwrap().len(), 5);
+
+        session.clear();
+        assert_eq!(session.history_spill().unwrap().moved_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_recalls_relevant_exchange() {
+        let config = qq_core::SlidingWindowConfig {
+            recent_messages: 1,
+            recall: 1,
+            min_similarity: 0.3,
+            embedding_model: Some("mock-embed".to_string()),
+        };
+        let window = SlidingWindowMemory::new(config, Arc::new(qq_core::testing::MockProvider::new()));
+        let mut session = ChatSession::new(Some("Be brief.".to_string()))
+            .with_sliding_window(window)
+            .with_history_spill(HistorySpill::new(1_000_000, false));
+
+        session.add_user_message("where is the database pool configured");
+        session.add_assistant_message("In config/db.toml, under [pool].");
+        session.add_user_message("what time is it in Tokyo");
+        session.add_assistant_message("Evening.");
+        session.add_user_message("raise the database pool limit");
+
+        let action = session.compact_if_needed().await.unwrap();
+        assert!(action.starts_with("moved 4 messages"), "{}", action);
+        assert_eq!(session.message_count(), 1);
+        assert_eq!(session.history_spill().unwrap().moved_count(), 4);
+
+        let msgs = session.build_messages();
+        let system = msgs[0].content.to_string_lossy();
+        assert!(system.starts_with("Be brief."));
+        assert!(system.contains("## Relevant Earlier Conversation"));
+        assert!(system.contains("config/db.toml"));
+        assert!(!system.contains("Tokyo"));
+        assert_eq!(session.observation_count(), 0);
+
+        assert_eq!(session.undo(), Ok(1));
+        let err = session.undo().unwrap_err();
+        assert!(err.contains("compacted"), "{}", err);
+
+        session.clear();
+        assert_eq!(session.sliding_window().unwrap().archived_count(), 0);
+        assert_eq!(session.build_messages().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_summary_saved_to_knowledge() {
+        let compactor = Arc::new(MockCompactor::new());
+        compactor.queue_observe(Ok("- rustls chosen over openssl".to_string()));
+        let mut session = ChatSession::new(None).with_compactor(compactor);
+
+        // Nothing answered yet: nothing to keep
+        session.add_user_message("which TLS crate?");
+        assert!(session.session_summary().await.is_none());
+
+        session.add_assistant_message("rustls");
+        let store = qq_tools::KnowledgeStore::in_memory("/work/qq", "s1");
+        save_session_summary(&session, Some(&store)).await;
+
+        let hits = store.search("openssl", 5);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].entry.kind, qq_tools::KnowledgeKind::Summary);
+    }
+
+    #[tokio::test]
+    async fn test_clear_with_carry_over() {
+        let compactor = Arc::new(MockCompactor::new());
+        compactor.queue_observe(Ok("- rustls chosen over openssl".to_string()));
+        let mut session = ChatSession::new(Some("You are qq.".to_string())).with_compactor(compactor);
+        session.add_user_message("which TLS crate?");
+        session.add_assistant_message("rustls");
+
+        let dir = tempfile::tempdir().unwrap();
+        let outcome = session
+            .clear_with_carry_over(true, Some(dir.path()), &Pricing::default())
+            .await;
+        assert!(outcome.carried_over);
+        let saved = std::fs::read_to_string(outcome.transcript.unwrap()).unwrap();
+        assert!(saved.contains("which TLS crate?"));
+
+        assert_eq!(session.message_count(), 0);
+        let system = session.build_messages()[0].content.to_string_lossy();
+        assert!(system.starts_with("You are qq.\n\n## Previous Conversation"));
+        assert!(system.contains("rustls chosen over openssl"));
+
+        // Nothing new to save; a plain clear drops the summary
+        let outcome = session
+            .clear_with_carry_over(false, Some(dir.path()), &Pricing::default())
+            .await;
+        assert!(outcome.transcript.is_none());
+        assert!(!outcome.carried_over);
+        assert_eq!(session.build_messages().len(), 1);
+        assert!(!session.build_messages()[0]
+            .content
+            .to_string_lossy()
+            .contains("Previous Conversation"));
+    }
+
     #[tokio::test]
     async fn test_compact_if_needed_below_threshold_does_nothing() {
         let config = ObservationConfig {
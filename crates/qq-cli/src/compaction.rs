@@ -83,21 +83,45 @@ You will receive an observation log consisting of dated, prioritized observation
 - When merging, keep the most specific details (file paths, error messages, exact values)
 - Output should be meaningfully smaller than input (target: 40-60% of original size)"#;
 
+/// Appended to both prompts when compacting for an agent with its own
+/// compaction prompt. `{focus}` is replaced with that prompt.
+const FOCUS_SECTION: &str = r#"
+
+## Role Focus
+
+This log belongs to an agent whose own compaction instructions follow. Everything they ask to preserve must survive as observations, verbatim wherever they say so; rank it high priority.
+
+{focus}"#;
+
 /// LLM-powered context compactor implementing the Observer/Reflector pattern.
 pub struct LlmCompactor {
     provider: Arc<dyn Provider>,
     model: Option<String>,
+    focus: Option<String>,
 }
 
 impl LlmCompactor {
     pub fn new(provider: Arc<dyn Provider>, model: Option<String>) -> Self {
-        Self { provider, model }
+        Self {
+            provider,
+            model,
+            focus: None,
+        }
     }
 
     fn current_date(&self) -> String {
         chrono::Local::now().format("%Y-%m-%d").to_string()
     }
 
+    /// A prompt template with today's date and the role focus filled in.
+    fn system_prompt(&self, template: &str) -> String {
+        let mut system = template.replace("{current_date}", &self.current_date());
+        if let Some(ref focus) = self.focus {
+            system.push_str(&FOCUS_SECTION.replace("{focus}", focus));
+        }
+        system
+    }
+
     fn build_request(&self, system: &str, user_content: &str) -> CompletionRequest {
         let messages = vec![Message::system(system), Message::user(user_content)];
         let mut request = CompletionRequest::new(messages).with_stream(false);
@@ -121,8 +145,7 @@ impl ContextCompactor for LlmCompactor {
         messages: &[Message],
         prior_observations: Option<&str>,
     ) -> Result<String, Error> {
-        let date = self.current_date();
-        let system = OBSERVER_PROMPT.replace("{current_date}", &date);
+        let system = self.system_prompt(OBSERVER_PROMPT);
 
         // Format messages into a readable representation for the LLM
         let mut formatted = String::new();
@@ -177,8 +200,7 @@ impl ContextCompactor for LlmCompactor {
     }
 
     async fn reflect(&self, observation_log: &str) -> Result<String, Error> {
-        let date = self.current_date();
-        let system = REFLECTOR_PROMPT.replace("{current_date}", &date);
+        let system = self.system_prompt(REFLECTOR_PROMPT);
 
         tracing::debug!(
             input_bytes = observation_log.len(),
@@ -200,6 +222,14 @@ impl ContextCompactor for LlmCompactor {
 
         Ok(result)
     }
+
+    fn with_focus(&self, focus: &str) -> Option<Arc<dyn ContextCompactor>> {
+        Some(Arc::new(Self {
+            provider: Arc::clone(&self.provider),
+            model: self.model.clone(),
+            focus: Some(focus.to_string()),
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -360,6 +390,43 @@ mod tests {
         assert!(req.model.is_none());
     }
 
+    /// An agent's compaction prompt rides along on both passes, so its role
+    /// details are kept when the log is first written and when it's squeezed.
+    #[tokio::test]
+    async fn test_focus_added_to_observer_and_reflector() {
+        use qq_agents::InternalAgent;
+
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_response("- Observation");
+        provider.queue_response("- Reflection");
+        let focus = qq_agents::PlannerAgent::new().compact_prompt().to_string();
+
+        let compactor = LlmCompactor::new(provider.clone(), None)
+            .with_focus(&focus)
+            .unwrap();
+        compactor.observe(&[Message::user("plan it")]).await.unwrap();
+        let observer = provider.last_request().unwrap().messages[0].content.to_string_lossy();
+        compactor.reflect("- log").await.unwrap();
+        let reflector = provider.last_request().unwrap().messages[0].content.to_string_lossy();
+
+        for system in [&observer, &reflector] {
+            assert!(system.contains("## Role Focus"));
+            assert!(system.ends_with(&focus));
+            assert!(system.contains("Do not renumber"));
+        }
+        assert!(observer.starts_with("You are an Observer agent"));
+        assert!(reflector.starts_with("You are a Reflector agent"));
+
+        // Unfocused prompts are unchanged
+        provider.queue_response("- Observation");
+        LlmCompactor::new(provider.clone(), None)
+            .observe(&[Message::user("plan it")])
+            .await
+            .unwrap();
+        let plain = provider.last_request().unwrap().messages[0].content.to_string_lossy();
+        assert!(!plain.contains("Role Focus"));
+    }
+
     /// Regression: system prompt must be a Role::System message in the messages
     /// array — providers only read messages.
     #[tokio::test]
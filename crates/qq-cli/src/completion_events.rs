@@ -0,0 +1,271 @@
+//! Structured events for one-shot runs (`--events jsonl`).
+//!
+//! Completion mode normally streams the reply to stdout as it arrives. With
+//! `--events`, every step of the run is written as one JSON object per line
+//! to stderr (or `--events-file`), and stdout gets only the final reply once
+//! the run ends, so a wrapper can show progress from the events and still
+//! pipe the answer on.
+//!
+//! ```text
+//! {"type":"request_start","iteration":1}
+//! {"type":"content_delta","content":"Let me check the manifest."}
+//! {"type":"tool_call","id":"call_1","name":"read_file","arguments":{"path":"Cargo.toml"}}
+//! {"type":"tool_result","id":"call_1","name":"read_file","is_error":false,"result":"[package]..."}
+//! {"type":"response","tool_calls":1,"usage":{...},"model":"gpt-4o","duration_ms":812}
+//! {"type":"done","iterations":2,"usage":{...},"finish_reason":"stop","max_iterations_reached":false}
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+
+use qq_core::{FinishReason, RunEvent, RunHandler, RunOutcome, Usage};
+
+/// One line of the event stream.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CompletionEvent<'a> {
+    /// A request is about to be sent (1-based).
+    RequestStart { iteration: usize },
+    ThinkingDelta { content: &'a str },
+    ContentDelta { content: &'a str },
+    ToolCall {
+        id: &'a str,
+        name: &'a str,
+        arguments: &'a serde_json::Value,
+    },
+    /// `result` is the text the model sees, after chunking.
+    ToolResult {
+        id: &'a str,
+        name: &'a str,
+        is_error: bool,
+        result: &'a str,
+    },
+    /// A response was received in full.
+    Response {
+        tool_calls: usize,
+        usage: Option<&'a Usage>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        model: Option<&'a str>,
+        duration_ms: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        first_token_ms: Option<u64>,
+    },
+    Retry {
+        attempt: u32,
+        max_retries: u32,
+        error: &'a str,
+    },
+    Compacted { action: &'a str },
+    ContextRecovered { action: &'a str },
+    Regrounded { trigger: String },
+    /// The run finished; `usage` covers every request it made.
+    Done {
+        iterations: usize,
+        usage: Option<&'a Usage>,
+        finish_reason: Option<FinishReason>,
+        max_iterations_reached: bool,
+    },
+    Error { message: &'a str },
+}
+
+/// Writes a run's events as JSON lines.
+pub struct EventWriter {
+    out: Box<dyn Write + Send>,
+    /// Set after the first write error; later events are dropped
+    failed: bool,
+}
+
+impl EventWriter {
+    /// Write events to stderr.
+    pub fn stderr() -> Self {
+        Self::new(Box::new(std::io::stderr()))
+    }
+
+    /// Write events to `path`, replacing it.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create events file {}", path.display()))?;
+        Ok(Self::new(Box::new(BufWriter::new(file))))
+    }
+
+    fn new(out: Box<dyn Write + Send>) -> Self {
+        Self { out, failed: false }
+    }
+
+    /// Record the end of the run.
+    pub fn finish(&mut self, outcome: &RunOutcome) {
+        self.emit(&CompletionEvent::Done {
+            iterations: outcome.iterations,
+            usage: outcome.usage.as_ref(),
+            finish_reason: outcome.finish_reason,
+            max_iterations_reached: outcome.max_iterations_reached,
+        });
+    }
+
+    /// Record the error the run failed with.
+    pub fn error(&mut self, message: &str) {
+        self.emit(&CompletionEvent::Error { message });
+    }
+
+    /// Each line is flushed so a reader sees it as soon as it happens.
+    fn emit(&mut self, event: &CompletionEvent<'_>) {
+        if self.failed {
+            return;
+        }
+        let line = serde_json::to_string(event).unwrap_or_default();
+        if let Err(e) = writeln!(self.out, "{}", line).and_then(|_| self.out.flush()) {
+            tracing::warn!(error = %e, "Failed to write completion events; no more will be written");
+            self.failed = true;
+        }
+    }
+}
+
+#[async_trait]
+impl RunHandler for EventWriter {
+    async fn on_event(&mut self, event: RunEvent) {
+        match event {
+            RunEvent::IterationStart { iteration } => {
+                self.emit(&CompletionEvent::RequestStart { iteration });
+            }
+            RunEvent::ThinkingDelta(content) => {
+                self.emit(&CompletionEvent::ThinkingDelta { content: &content });
+            }
+            RunEvent::ContentDelta(content) => {
+                self.emit(&CompletionEvent::ContentDelta { content: &content });
+            }
+            RunEvent::ToolStart { id, name, arguments } => {
+                self.emit(&CompletionEvent::ToolCall { id: &id, name: &name, arguments: &arguments });
+            }
+            RunEvent::ToolComplete { id, name, result, is_error } => {
+                self.emit(&CompletionEvent::ToolResult {
+                    id: &id,
+                    name: &name,
+                    is_error,
+                    result: &result,
+                });
+            }
+            RunEvent::Response { tool_calls, usage, model, timing, .. } => {
+                self.emit(&CompletionEvent::Response {
+                    tool_calls,
+                    usage: usage.as_ref(),
+                    model: model.as_deref(),
+                    duration_ms: timing.duration.as_millis() as u64,
+                    first_token_ms: timing.first_token.map(|d| d.as_millis() as u64),
+                });
+            }
+            RunEvent::Retry { attempt, max_retries, error } => {
+                self.emit(&CompletionEvent::Retry { attempt, max_retries, error: &error });
+            }
+            RunEvent::Compacted { action } => {
+                self.emit(&CompletionEvent::Compacted { action: &action });
+            }
+            RunEvent::ContextRecovered { action } => {
+                self.emit(&CompletionEvent::ContextRecovered { action: &action });
+            }
+            RunEvent::Regrounded { trigger, .. } => {
+                self.emit(&CompletionEvent::Regrounded { trigger: trigger.to_string() });
+            }
+            // Streaming starts, tool-call deltas, and the messages added to
+            // the conversation repeat what the events above already carry
+            RunEvent::Start { .. }
+            | RunEvent::ToolCallStart { .. }
+            | RunEvent::ToolCallDelta { .. }
+            | RunEvent::ToolCalls(_)
+            | RunEvent::ToolResults(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::ResponseTiming;
+    use std::time::Duration;
+
+    fn lines(path: &Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_run_events_become_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let mut writer = EventWriter::create(&path).unwrap();
+
+        writer.on_event(RunEvent::IterationStart { iteration: 1 }).await;
+        writer.on_event(RunEvent::ContentDelta("Checking.".to_string())).await;
+        writer.on_event(RunEvent::ToolCallStart { id: "c1".into(), name: "read_file".into() }).await;
+        writer
+            .on_event(RunEvent::ToolStart {
+                id: "c1".into(),
+                name: "read_file".into(),
+                arguments: serde_json::json!({"path": "Cargo.toml"}),
+            })
+            .await;
+        writer
+            .on_event(RunEvent::ToolComplete {
+                id: "c1".into(),
+                name: "read_file".into(),
+                result: "[package]".into(),
+                is_error: false,
+            })
+            .await;
+        writer
+            .on_event(RunEvent::Response {
+                content: "Checking.".into(),
+                thinking: None,
+                tool_calls: 1,
+                usage: Some(Usage::new(120, 30)),
+                model: Some("gpt-4o".into()),
+                timing: ResponseTiming { first_token: None, duration: Duration::from_millis(812) },
+                input_bytes: 0,
+                output_bytes: 0,
+            })
+            .await;
+        writer.finish(&RunOutcome {
+            content: "Done.".into(),
+            usage: Some(Usage::new(300, 40)),
+            finish_reason: Some(FinishReason::Stop),
+            iterations: 2,
+            max_iterations_reached: false,
+        });
+        drop(writer);
+
+        let events = lines(&path);
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(
+            types,
+            ["request_start", "content_delta", "tool_call", "tool_result", "response", "done"]
+        );
+        assert_eq!(events[2]["arguments"]["path"], "Cargo.toml");
+        assert_eq!(events[3]["result"], "[package]");
+        assert_eq!(events[4]["duration_ms"], 812);
+        assert!(events[4].get("first_token_ms").is_none());
+        assert_eq!(events[4]["usage"]["prompt_tokens"], 120);
+        assert_eq!(events[5]["finish_reason"], "stop");
+        assert_eq!(events[5]["usage"]["completion_tokens"], 40);
+    }
+
+    #[test]
+    fn test_error_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let mut writer = EventWriter::create(&path).unwrap();
+        writer.error("connection refused");
+        drop(writer);
+
+        let events = lines(&path);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["type"], "error");
+        assert_eq!(events[0]["message"], "connection refused");
+    }
+}
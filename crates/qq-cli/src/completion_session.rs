@@ -0,0 +1,228 @@
+//! Multi-turn completion mode: `qq -p --continue` and `--session`.
+//!
+//! Each `qq -p` used to start from scratch, so a script asking a follow-up
+//! had to paste the previous answer back into the prompt. Completions are
+//! now saved like chat sessions (`<state>/session_logs/<id>.json`, which
+//! `qq share` also reads), and a later completion can pick one up:
+//! `--continue` takes the project's most recently saved session, and
+//! `--session <id>` takes the one with that id, or starts a new session
+//! under the id so a script can name its conversation up front.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use chrono::Local;
+
+use qq_core::Message;
+
+use crate::share::{SavedSession, SessionLog};
+
+/// Which saved session a completion continues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume<'a> {
+    /// A new session
+    New,
+    /// The project's most recently saved session (`--continue`)
+    Latest,
+    /// The session with this id or a unique prefix of it, else a new
+    /// session under the id (`--session`)
+    Named(&'a str),
+}
+
+/// The session a completion runs in.
+pub struct CompletionSession {
+    /// Saves the session once the completion is done
+    pub log: SessionLog,
+    /// Conversation so far (empty for a new session)
+    pub messages: Vec<Message>,
+    /// Observations older messages were folded into
+    pub observation_log: String,
+}
+
+impl CompletionSession {
+    /// Open the session `resume` asks for among the saved sessions in `dir`;
+    /// `root`, `profile` and `model` describe a new one.
+    pub fn open(
+        dir: &Path,
+        resume: Resume<'_>,
+        root: &Path,
+        profile: &str,
+        model: Option<&str>,
+    ) -> Result<Self> {
+        let found = match resume {
+            Resume::New => None,
+            Resume::Latest => Some(latest_in_project(dir, root).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No saved session in {} to continue; start one with `qq -p`",
+                    root.display()
+                )
+            })?),
+            Resume::Named(id) => match find_named(dir, id)? {
+                Some(path) => {
+                    let session = SavedSession::load(&path)?;
+                    Some((path, session))
+                }
+                None => None,
+            },
+        };
+
+        if let Some((path, mut session)) = found {
+            tracing::debug!(id = %session.id, messages = session.messages.len(), "Continuing session");
+            return Ok(Self {
+                messages: std::mem::take(&mut session.messages),
+                observation_log: std::mem::take(&mut session.observation_log),
+                log: SessionLog::resume(path, session),
+            });
+        }
+        let id = match resume {
+            Resume::Named(id) => {
+                validate_id(id)?;
+                id.to_string()
+            }
+            _ => crate::active_sessions::session_id(Local::now()),
+        };
+        Ok(Self {
+            log: SessionLog::new(dir, &id, root, profile, model),
+            messages: Vec::new(),
+            observation_log: String::new(),
+        })
+    }
+}
+
+/// The most recently saved session whose project root is `root`.
+fn latest_in_project(dir: &Path, root: &Path) -> Option<(PathBuf, SavedSession)> {
+    // Saved the same way as `SessionLog::new` records it
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let root = root.display().to_string();
+
+    let mut saved: Vec<(SystemTime, PathBuf)> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .filter_map(|p| Some((p.metadata().ok()?.modified().ok()?, p)))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Continued sessions are rewritten, so this is also most recently used
+    saved.sort();
+    saved.into_iter().rev().find_map(|(_, path)| {
+        let session = SavedSession::load(&path).ok()?;
+        (session.root == root).then_some((path, session))
+    })
+}
+
+/// The saved session `id` names, exactly or as a unique prefix.
+fn find_named(dir: &Path, id: &str) -> Result<Option<PathBuf>> {
+    let exact = dir.join(format!("{}.json", id));
+    if exact.is_file() {
+        return Ok(Some(exact));
+    }
+    let mut matches: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .filter(|p| {
+                    p.file_stem()
+                        .is_some_and(|stem| stem.to_string_lossy().starts_with(id))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    matches.sort();
+    match matches.len() {
+        0 => Ok(None),
+        1 => Ok(matches.pop()),
+        n => {
+            let ids: Vec<String> = matches
+                .iter()
+                .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                .collect();
+            anyhow::bail!("'{}' matches {} sessions: {}", id, n, ids.join(", "))
+        }
+    }
+}
+
+/// Ids name files in the session log directory.
+fn validate_id(id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid session id '{}': use letters, digits, '-', '_' and '.'",
+            id
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn open(dir: &Path, resume: Resume<'_>, root: &Path) -> Result<CompletionSession> {
+        CompletionSession::open(dir, resume, root, "default", None)
+    }
+
+    /// Save `session` with one more exchange, marked as saved `age` ago.
+    fn save(dir: &Path, session: CompletionSession, prompt: &str, age: u64) {
+        let mut messages = session.messages;
+        messages.push(Message::user(prompt));
+        messages.push(Message::assistant("ok"));
+        session.log.save(messages);
+
+        // The file just written is the newest
+        let modified = |p: &PathBuf| p.metadata().unwrap().modified().unwrap();
+        let newest = std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.path())
+            .max_by_key(modified)
+            .unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&newest)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_continue_latest_in_project_and_named_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = Path::new("/work/app");
+        let other = Path::new("/work/other");
+
+        assert!(open(dir.path(), Resume::Latest, app).is_err());
+
+        // Two sessions in the project, a newer one elsewhere
+        save(dir.path(), open(dir.path(), Resume::Named("first"), app).unwrap(), "one", 30);
+        save(dir.path(), open(dir.path(), Resume::Named("second"), app).unwrap(), "two", 20);
+        save(dir.path(), open(dir.path(), Resume::New, other).unwrap(), "elsewhere", 10);
+
+        let latest = open(dir.path(), Resume::Latest, app).unwrap();
+        assert_eq!(latest.messages[0].content.to_string_lossy(), "two");
+        save(dir.path(), latest, "two again", 0);
+
+        // Exact id, unique prefix, and a new id
+        let second = open(dir.path(), Resume::Named("sec"), app).unwrap();
+        assert_eq!(second.messages.len(), 4);
+        assert_eq!(second.messages[2].content.to_string_lossy(), "two again");
+        let first = open(dir.path(), Resume::Named("first"), app).unwrap();
+        assert_eq!(first.messages.len(), 2);
+        assert!(open(dir.path(), Resume::Named("third"), app)
+            .unwrap()
+            .messages
+            .is_empty());
+
+        assert!(open(dir.path(), Resume::Named("../x"), app).is_err());
+    }
+}
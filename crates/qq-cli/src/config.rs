@@ -1,11 +1,12 @@
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
 pub use qq_mcp::manager::McpServerConfig;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
     /// Default profile to use (required - bundles provider, prompt, model, parameters)
     pub default_profile: String,
@@ -22,13 +23,57 @@ pub struct Config {
     #[serde(default)]
     pub tools: ToolsConfigEntry,
 
+    #[serde(default)]
+    pub tui: TuiConfigEntry,
+
     /// Compaction configuration for observational memory
     #[serde(default)]
     pub compaction: Option<CompactionConfig>,
 
+    /// Routing of low-complexity turns to a cheaper profile
+    #[serde(default)]
+    pub routing: Option<RoutingConfig>,
+
+    /// Cross-session knowledge base (`recall` tool)
+    #[serde(default)]
+    pub knowledge: KnowledgeConfigEntry,
+
+    /// Memory ceilings for long sessions
+    #[serde(default)]
+    pub session_memory: SessionMemoryConfigEntry,
+
+    /// Window sizes and recall for the `sliding-window` memory strategy
+    #[serde(default)]
+    pub sliding_window: SlidingWindowConfigEntry,
+
+    /// Push approval requests to a phone for unattended runs
+    #[serde(default)]
+    pub remote_approval: Option<RemoteApprovalConfig>,
+
+    /// Model prices for per-turn cost estimates, keyed by model name or
+    /// prefix. Overrides the built-in list prices.
+    #[serde(default)]
+    pub pricing: HashMap<String, PricingEntry>,
+
+    /// Commands or URLs run on session, agent, and file-change events
+    #[serde(default)]
+    pub hooks: Vec<HookEntry>,
+
+    /// Agents and tools loaded from plugin directories
+    #[serde(default)]
+    pub plugins: PluginsConfigEntry,
+
+    /// Scrubbing of bundles written by `qq share`
+    #[serde(default)]
+    pub share: ShareConfigEntry,
+
     /// MCP server configurations, keyed by server name
     #[serde(default)]
     pub mcp_servers: HashMap<String, McpServerConfig>,
+
+    /// Proxy and CA certificates for every outgoing HTTP request
+    #[serde(default)]
+    pub network: NetworkConfigEntry,
 }
 
 /// Configuration for observational memory compaction.
@@ -42,7 +87,7 @@ pub struct Config {
 /// The defaults work well for most use cases. Only override these if you have
 /// a specific reason (e.g., running a very cheap model that benefits from a
 /// smaller window, or a very capable model where you want to delay compaction).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CompactionConfig {
     /// Provider name for compaction LLM calls (defaults to the session provider).
     /// Set this to route compaction through a cheaper/faster model provider.
@@ -88,6 +133,19 @@ pub struct CompactionConfig {
     /// individual messages.
     #[serde(default)]
     pub context_budget_bytes: Option<usize>,
+
+    /// Restate the original request verbatim, with the task board and the
+    /// latest observations, when the main chat or an agent resumes after
+    /// compaction has dropped the request or after a long pause.
+    /// Default: true.
+    #[serde(default)]
+    pub regrounding: Option<bool>,
+
+    /// Seconds between a response and the next request that count as a long
+    /// pause for re-grounding (a long tool batch, an approval prompt left
+    /// waiting). Default: 300.
+    #[serde(default)]
+    pub regrounding_pause_secs: Option<u64>,
 }
 
 impl CompactionConfig {
@@ -108,18 +166,422 @@ impl CompactionConfig {
             context_budget_bytes: self.context_budget_bytes.or(defaults.context_budget_bytes),
         }
     }
+
+    /// Re-grounding settings, or `None` when turned off. Applies the
+    /// defaults when `[compaction]` is absent.
+    pub fn regrounding(config: Option<&Self>) -> Option<qq_core::Regrounding> {
+        if config.and_then(|c| c.regrounding) == Some(false) {
+            return None;
+        }
+        let mut regrounding = qq_core::Regrounding::new();
+        if let Some(secs) = config.and_then(|c| c.regrounding_pause_secs) {
+            regrounding = regrounding.with_pause_threshold(std::time::Duration::from_secs(secs));
+        }
+        Some(regrounding)
+    }
+}
+
+/// Configuration for usage-aware model routing.
+///
+/// Short follow-ups ("yes, continue"), formatting requests, and the step after
+/// bookkeeping tool calls are sent to `profile` instead of the session's main
+/// profile. Planning and coding turns always stay on the main profile.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoutingConfig {
+    /// Profile (from `[profiles.*]`) that serves low-complexity turns.
+    pub profile: String,
+
+    /// Longest user message (in chars) treated as a short follow-up.
+    /// Default: 80.
+    #[serde(default)]
+    pub max_followup_chars: Option<usize>,
+
+    /// Longest user message (in chars) treated as a formatting request.
+    /// Default: 240.
+    #[serde(default)]
+    pub max_formatting_chars: Option<usize>,
+
+    /// Route the step after bookkeeping tool calls (`ack_tools`).
+    /// Default: true.
+    #[serde(default)]
+    pub route_tool_acks: Option<bool>,
+
+    /// Tools whose results count as acknowledgments. Default: the task,
+    /// `inform_user`, `schedule_reminder`, and `datetime` tools.
+    #[serde(default)]
+    pub ack_tools: Option<Vec<String>>,
+}
+
+impl RoutingConfig {
+    /// Convert to a routing policy, filling unset fields with defaults.
+    pub fn to_policy(&self) -> crate::routing::RoutingPolicy {
+        let defaults = crate::routing::RoutingPolicy::default();
+        crate::routing::RoutingPolicy {
+            max_followup_chars: self.max_followup_chars.unwrap_or(defaults.max_followup_chars),
+            max_formatting_chars: self.max_formatting_chars.unwrap_or(defaults.max_formatting_chars),
+            route_tool_acks: self.route_tool_acks.unwrap_or(defaults.route_tool_acks),
+            ack_tools: self.ack_tools.clone().unwrap_or(defaults.ack_tools),
+        }
+    }
+}
+
+/// Cross-session knowledge base configuration.
+///
+/// Session summaries and the results of `capture_agents` are saved per
+/// project (the tools root) and searched with the `recall` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct KnowledgeConfigEntry {
+    /// Save knowledge and offer the `recall` tool. Default: true.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Agents whose results are saved as findings. Default: ["researcher"].
+    #[serde(default = "default_capture_agents")]
+    pub capture_agents: Vec<String>,
+}
+
+impl Default for KnowledgeConfigEntry {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capture_agents: default_capture_agents(),
+        }
+    }
+}
+
+fn default_capture_agents() -> Vec<String> {
+    vec!["researcher".to_string()]
+}
+
+/// Bundles written by `qq share` (`[share]`).
+///
+/// API keys from this file, secret-looking environment variables, common
+/// token formats, and paths outside the project are always scrubbed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ShareConfigEntry {
+    /// More secrets to scrub, as regexes. A `(?P<secret>...)` group limits
+    /// the redaction to that group, e.g. `internal_id=(?P<secret>\w+)`.
+    #[serde(default)]
+    pub secret_patterns: Vec<String>,
+}
+
+/// Plugin loading (`[plugins]`).
+///
+/// Plugins live in subdirectories of `dir`, each with a `plugin.toml`
+/// (see `plugins.rs`). A plugin only loads once every capability it asks
+/// for is granted here.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PluginsConfigEntry {
+    /// Load plugins at startup. Default: true.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Plugins directory. Default: `~/.config/qq/plugins`.
+    #[serde(default)]
+    pub dir: Option<String>,
+
+    /// Capabilities granted per plugin: `write` (project root writable for
+    /// its tools) and `home` (`$HOME` visible, as for bash).
+    #[serde(default)]
+    pub grant: HashMap<String, Vec<String>>,
+
+    /// Plugins to skip, by name.
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+impl Default for PluginsConfigEntry {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: None,
+            grant: HashMap::new(),
+            disabled: Vec::new(),
+        }
+    }
 }
 
+/// Memory ceilings for long sessions.
+///
+/// Past a ceiling, the oldest part of the TUI transcript or chat history moves
+/// to a file in the cache directory (see [`crate::session_memory`]).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionMemoryConfigEntry {
+    /// TUI transcript bytes kept in memory. Default: 2 MiB.
+    #[serde(default = "default_transcript_bytes")]
+    pub transcript_bytes: usize,
+
+    /// Chat history bytes kept in memory. Older messages leave the context.
+    /// Default: 16 MiB.
+    #[serde(default = "default_history_bytes")]
+    pub history_bytes: usize,
+
+    /// Keep what is over a ceiling on disk (for scrollback and `/export`)
+    /// instead of discarding it. Default: true.
+    #[serde(default = "default_true")]
+    pub spill_to_disk: bool,
+
+    /// Whether `/clear` carries a summary of the cleared conversation into
+    /// the next one. Default: ask.
+    #[serde(default)]
+    pub clear_summary: ClearSummaryMode,
+
+    /// Tokens of `/context` slots sent with every request; slots past it
+    /// are left out. Default: 2000.
+    #[serde(default = "default_context_slot_tokens")]
+    pub context_slot_tokens: usize,
+}
+
+impl Default for SessionMemoryConfigEntry {
+    fn default() -> Self {
+        Self {
+            transcript_bytes: default_transcript_bytes(),
+            history_bytes: default_history_bytes(),
+            spill_to_disk: true,
+            clear_summary: ClearSummaryMode::default(),
+            context_slot_tokens: default_context_slot_tokens(),
+        }
+    }
+}
+
+/// Settings for the `sliding-window` memory strategy.
+///
+/// The last `recent_messages` stay in the context verbatim; older exchanges
+/// are archived with an embedding, and the `recall` most similar to the
+/// user's latest message are sent along with each request.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SlidingWindowConfigEntry {
+    /// Messages kept verbatim. Default: 16.
+    #[serde(default = "default_recent_messages")]
+    pub recent_messages: usize,
+
+    /// Older exchanges recalled into each request. Default: 4.
+    #[serde(default = "default_recall")]
+    pub recall: usize,
+
+    /// Exchanges less similar to the latest message than this are not
+    /// recalled. Default: 0.3.
+    #[serde(default = "default_recall_similarity")]
+    pub min_similarity: f32,
+
+    /// Embedding model for archived exchanges (e.g.,
+    /// "text-embedding-3-small"). Unset drops older messages without recall.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Profile whose provider serves `embedding_model` (default: the
+    /// session's provider)
+    #[serde(default)]
+    pub embedding_profile: Option<String>,
+}
+
+fn default_recent_messages() -> usize {
+    16
+}
+
+fn default_recall() -> usize {
+    4
+}
+
+fn default_recall_similarity() -> f32 {
+    0.3
+}
+
+impl Default for SlidingWindowConfigEntry {
+    fn default() -> Self {
+        Self {
+            recent_messages: default_recent_messages(),
+            recall: default_recall(),
+            min_similarity: default_recall_similarity(),
+            embedding_model: None,
+            embedding_profile: None,
+        }
+    }
+}
+
+impl SlidingWindowConfigEntry {
+    /// Convert to qq_core::SlidingWindowConfig. The embedding provider is
+    /// resolved by the caller.
+    pub fn to_sliding_window_config(&self) -> qq_core::SlidingWindowConfig {
+        qq_core::SlidingWindowConfig {
+            recent_messages: self.recent_messages,
+            recall: self.recall,
+            min_similarity: self.min_similarity,
+            embedding_model: self.embedding_model.clone(),
+        }
+    }
+}
+
+/// What `/clear` does with the conversation it clears.
+///
+/// The full history is saved to the transcripts directory either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClearSummaryMode {
+    /// Ask each time
+    #[default]
+    Ask,
+    /// Always summarize and carry the summary over
+    Always,
+    /// Start from nothing
+    Never,
+}
+
+fn default_transcript_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_history_bytes() -> usize {
+    16 * 1024 * 1024
+}
+
+fn default_context_slot_tokens() -> usize {
+    2000
+}
+
+/// Where remote approval requests are pushed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteApprovalBackend {
+    /// POST the request as JSON, with signed reply links
+    Webhook,
+    /// Publish to an ntfy topic with Allow / Allow for session / Deny buttons
+    Ntfy,
+    /// Pushover notification with the reply links in the message
+    Pushover,
+}
+
+impl std::fmt::Display for RemoteApprovalBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RemoteApprovalBackend::Webhook => "webhook",
+            RemoteApprovalBackend::Ntfy => "ntfy",
+            RemoteApprovalBackend::Pushover => "pushover",
+        })
+    }
+}
+
+/// Remote approval for unattended runs (see [`crate::remote_approval`]).
+///
+/// Requests still show locally; a reply from either side answers them.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemoteApprovalConfig {
+    pub backend: RemoteApprovalBackend,
+
+    /// Webhook URL, or ntfy topic URL (e.g. "https://ntfy.sh/qq-7f3a9c").
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Reply link; `{token}` is replaced with the signed reply, else it is
+    /// appended as `?token=`. For ntfy, the topic replies are published to.
+    /// Default (ntfy only): `<url>-reply`.
+    #[serde(default)]
+    pub reply_url: Option<String>,
+
+    /// Polled for signed replies; `{since}` is replaced with the request's
+    /// Unix time. Default (ntfy only): the reply topic's JSON poll endpoint.
+    #[serde(default)]
+    pub poll_url: Option<String>,
+
+    /// Bearer token (webhook, ntfy) or Pushover application token.
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// Pushover user key.
+    #[serde(default)]
+    pub user: Option<String>,
+
+    /// Seconds without an answer before the command is denied. Default: 900.
+    #[serde(default = "default_approval_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Seconds the local prompt gets before the phone is notified. Default: 0.
+    #[serde(default)]
+    pub notify_after_secs: u64,
+
+    /// Seconds between reply polls. Default: 5.
+    #[serde(default = "default_approval_poll_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_approval_timeout_secs() -> u64 {
+    900
+}
+
+fn default_approval_poll_secs() -> u64 {
+    5
+}
+
+/// Events a hook can run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEventKind {
+    SessionStart,
+    SessionEnd,
+    AgentStart,
+    AgentEnd,
+    FileChanged,
+}
+
+/// A lifecycle hook (see [`crate::hooks`]).
+///
+/// Set exactly one of `command` and `url`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HookEntry {
+    /// Name used in logs. Default: the command or URL.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Events that run the hook.
+    pub on: Vec<HookEventKind>,
+
+    /// Shell command (`sh -c`), run in the project root with the event JSON
+    /// on stdin.
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// URL the event JSON is POSTed to.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Extra HTTP headers for `url`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// Agent names (`*` wildcards) the hook runs for; empty means all.
+    /// Session events have no agent and ignore this.
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// Agent names the hook never runs for; wins over `allow`.
+    #[serde(default)]
+    pub deny: Vec<String>,
+
+    /// Seconds before the hook is abandoned. Default: 30.
+    #[serde(default = "default_hook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_hook_timeout_secs() -> u64 {
+    30
+}
+
+/// A model's price, in USD per million tokens.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub struct PricingEntry {
+    pub input: f64,
+    pub output: f64,
+}
 
 /// A named system prompt
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct PromptEntry {
     /// The system prompt text
     pub prompt: String,
 }
 
 /// A profile bundles provider, prompt, model, and parameters together
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct ProfileEntry {
     /// Provider name to use (references [providers.X])
     #[serde(default)]
@@ -154,9 +616,25 @@ pub struct ProfileEntry {
     /// Overrides the provider-level setting. Default: None (use provider setting).
     #[serde(default)]
     pub include_tool_reasoning: Option<bool>,
+
+    /// Build/test verification of coder runs, with automatic repair attempts.
+    /// Default: None (the caller checks the coder's work itself).
+    #[serde(default)]
+    pub verify: Option<qq_agents::VerifyPolicy>,
+
+    /// Self-assessment thresholds below which an agent's report is flagged
+    /// for the reviewer. Default: None (reports are never flagged).
+    #[serde(default)]
+    pub review: Option<qq_agents::ReviewPolicy>,
+
+    /// How the session's history is kept within the context window:
+    /// "obs-memory" (default) or "sliding-window". Set when the session
+    /// starts; switching profiles later doesn't change it.
+    #[serde(default)]
+    pub memory_strategy: Option<qq_agents::AgentMemoryStrategy>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct ProviderConfigEntry {
     /// Provider type: "openai", "anthropic", or "gemini".
     /// If omitted, inferred from base_url (defaults to openai) or provider name.
@@ -185,10 +663,235 @@ pub struct ProviderConfigEntry {
     /// Set to ["text"] for text-only models.
     #[serde(default)]
     pub supported_content_types: Option<Vec<String>>,
+
+    /// Probe tool calling, system-role, and output-limit support on first use
+    /// and adapt requests to what the server accepts. Results are cached.
+    /// Default: on for OpenAI-compatible providers with a custom base_url.
+    #[serde(default)]
+    pub probe_capabilities: Option<bool>,
+
+    /// Interchangeable endpoints (hosts or API keys serving the same models)
+    /// to rotate requests across by weight. Each inherits `api_key` and
+    /// `base_url` from this entry when unset.
+    #[serde(default)]
+    pub endpoints: Vec<ProviderEndpointEntry>,
+
+    /// Failure tracking and cooldown for `endpoints`.
+    #[serde(default)]
+    pub balance: BalanceConfigEntry,
+
+    /// Proxy and CA certificates for this provider's API requests, in place
+    /// of `[network]`'s proxy and in addition to its certificates
+    #[serde(default)]
+    pub network: Option<NetworkConfigEntry>,
+}
+
+impl ProviderConfigEntry {
+    /// Whether to probe capabilities for this provider, given its resolved type.
+    pub fn probe_capabilities_enabled(&self, provider_type: &str) -> bool {
+        self.probe_capabilities
+            .unwrap_or(provider_type == "openai" && self.base_url.is_some())
+    }
+
+    /// The entry's own API key. When every endpoint brings its own key the
+    /// entry needs none, and a placeholder stands in.
+    pub fn entry_api_key(&self) -> Option<String> {
+        self.api_key.clone().or_else(|| {
+            (!self.endpoints.is_empty() && self.endpoints.iter().all(|e| e.api_key.is_some()))
+                .then(|| "none".to_string())
+        })
+    }
+}
+
+/// One endpoint of a load-balanced provider
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProviderEndpointEntry {
+    /// Name shown in logs. Default: the base URL.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    /// Share of requests relative to the other endpoints. 0 keeps the
+    /// endpoint on standby for when the others are down. Default: 1.
+    #[serde(default = "default_endpoint_weight")]
+    pub weight: u32,
+}
+
+fn default_endpoint_weight() -> u32 {
+    1
+}
+
+/// When a provider endpoint is taken out of rotation
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BalanceConfigEntry {
+    /// Consecutive failures (network errors, 5xx, 429, rejected keys) that
+    /// bench an endpoint. Default: 2.
+    #[serde(default = "default_balance_max_failures")]
+    pub max_failures: u32,
+
+    /// Seconds a benched endpoint sits out. Default: 30.
+    #[serde(default = "default_balance_cooldown_secs")]
+    pub cooldown_secs: u64,
+
+    /// Send a one-token probe to a benched endpoint when its cooldown ends
+    /// and only return it to rotation if the probe succeeds. Default: false.
+    #[serde(default)]
+    pub health_check: bool,
+}
+
+impl Default for BalanceConfigEntry {
+    fn default() -> Self {
+        Self {
+            max_failures: default_balance_max_failures(),
+            cooldown_secs: default_balance_cooldown_secs(),
+            health_check: false,
+        }
+    }
+}
+
+impl BalanceConfigEntry {
+    pub fn to_policy(&self) -> qq_core::BalancePolicy {
+        qq_core::BalancePolicy {
+            max_failures: self.max_failures,
+            cooldown: std::time::Duration::from_secs(self.cooldown_secs),
+            health_check: self.health_check,
+        }
+    }
+}
+
+fn default_balance_max_failures() -> u32 {
+    2
+}
+
+fn default_balance_cooldown_secs() -> u64 {
+    30
+}
+
+/// Proxy and custom CA settings for outgoing HTTP (`[network]`, or
+/// `[providers.<name>.network]` for one provider).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct NetworkConfigEntry {
+    /// Proxy URL: http://, https://, socks5:// or socks5h:// (the proxy
+    /// resolves host names). Credentials may be given in the URL.
+    #[serde(default)]
+    pub proxy: Option<String>,
+
+    /// Proxy credentials, when not given in the URL
+    #[serde(default)]
+    pub proxy_username: Option<String>,
+
+    #[serde(default)]
+    pub proxy_password: Option<String>,
+
+    /// Hosts reached directly rather than through the proxy, comma-separated
+    /// as in NO_PROXY (e.g., "localhost,.internal.example.com,10.0.0.0/8")
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+
+    /// PEM files of extra CA certificates to trust alongside the system
+    /// roots, e.g. a corporate TLS-inspection CA (supports ~ and $VAR)
+    #[serde(default)]
+    pub ca_bundles: Vec<String>,
+}
+
+impl NetworkConfigEntry {
+    /// This entry layered over `base`: the proxy settings of whichever sets
+    /// a proxy (this one first), and both entries' CA bundles.
+    pub fn overlaid_on(&self, base: &Self) -> Self {
+        let proxy_from = if self.proxy.is_some() { self } else { base };
+        Self {
+            proxy: proxy_from.proxy.clone(),
+            proxy_username: proxy_from.proxy_username.clone(),
+            proxy_password: proxy_from.proxy_password.clone(),
+            no_proxy: proxy_from.no_proxy.clone(),
+            ca_bundles: base
+                .ca_bundles
+                .iter()
+                .chain(&self.ca_bundles)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Parse the proxy and read the CA bundles, failing on a bad URL or an
+    /// unreadable or empty bundle.
+    pub fn resolve(&self) -> Result<NetworkSettings> {
+        use anyhow::Context;
+
+        let proxy = match self.proxy {
+            Some(ref url) => {
+                let mut proxy = reqwest::Proxy::all(url)
+                    .with_context(|| format!("[network] invalid proxy URL '{}'", url))?;
+                if let Some(ref username) = self.proxy_username {
+                    proxy = proxy.basic_auth(username, self.proxy_password.as_deref().unwrap_or_default());
+                }
+                if let Some(ref hosts) = self.no_proxy {
+                    proxy = proxy.no_proxy(reqwest::NoProxy::from_string(hosts));
+                }
+                Some(proxy)
+            }
+            None => None,
+        };
+
+        let mut certificates = Vec::new();
+        let mut ca_bundles = Vec::new();
+        for bundle in &self.ca_bundles {
+            let path = expand_path(bundle);
+            let pem = std::fs::read(&path)
+                .with_context(|| format!("[network] cannot read CA bundle {}", path.display()))?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("[network] invalid CA bundle {}", path.display()))?;
+            if certs.is_empty() {
+                anyhow::bail!("[network] no certificates in CA bundle {}", path.display());
+            }
+            certificates.extend(certs);
+            ca_bundles.push(pem);
+        }
+
+        let options = qq_core::http::NetworkOptions {
+            proxy: self.proxy.clone(),
+            proxy_auth: self.proxy_username.clone().map(|username| {
+                (username, self.proxy_password.clone().unwrap_or_default())
+            }),
+            no_proxy: self.no_proxy.clone(),
+            ca_bundles,
+        };
+        Ok(NetworkSettings { proxy, certificates, options })
+    }
+}
+
+/// A parsed [`NetworkConfigEntry`], applied to each HTTP client as it's built.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkSettings {
+    proxy: Option<reqwest::Proxy>,
+    certificates: Vec<reqwest::Certificate>,
+    /// The same settings, for clients on another reqwest
+    options: qq_core::http::NetworkOptions,
+}
+
+impl NetworkSettings {
+    pub fn options(&self) -> &qq_core::http::NetworkOptions {
+        &self.options
+    }
+
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        for cert in &self.certificates {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        builder
+    }
 }
 
 /// Tools configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ToolsConfigEntry {
     /// Root directory for filesystem operations (supports $PWD, $HOME, ~)
     #[serde(default)]
@@ -202,7 +905,7 @@ pub struct ToolsConfigEntry {
     #[serde(default)]
     pub chunker: ChunkerConfigEntry,
 
-    /// Web search configuration (Perplexica)
+    /// Web search backend configuration
     #[serde(default)]
     pub web_search: Option<WebSearchConfigEntry>,
 
@@ -212,41 +915,199 @@ pub struct ToolsConfigEntry {
 
     /// Extra directories to mount read-only in the sandbox
     #[serde(default)]
-    pub bash_mounts: Vec<String>,
-
-    /// Permission overrides for run tool commands
+    pub bash_mounts: Vec<String>,
+
+    /// Permission overrides for run tool commands
+    #[serde(default)]
+    pub bash_permissions: Option<BashPermissionOverrides>,
+
+    /// Sensitive directories to pre-approve for sandbox access.
+    ///
+    /// By default, directories like `.ssh`, `.aws`, `.gnupg`, etc. are hidden
+    /// inside the sandbox with empty tmpfs mounts. Any directory listed here
+    /// will be excluded from that hiding — it will be accessible from the first
+    /// command with no tool call or user approval needed.
+    ///
+    /// Valid names: ".ssh", ".aws", ".gnupg", ".gpg", ".kube", ".docker",
+    /// ".password-store", ".netrc" (anything else is silently ignored since
+    /// it was never hidden in the first place).
+    ///
+    /// Example: bash_sensitive_dirs = [".ssh", ".aws"]
+    #[serde(default)]
+    pub bash_sensitive_dirs: Vec<String>,
+
+    /// Allow writes through symlinks that stay inside the project root.
+    ///
+    /// Symlinks that resolve outside the root are always refused. Set to
+    /// false to refuse writing through any symlink at all.
+    #[serde(default = "default_true")]
+    pub bash_follow_symlinks: bool,
+
+    /// Sandbox backends to try for the run tool, in priority order.
+    ///
+    /// Empty uses the default chain: hakoniwa, bubblewrap, firejail,
+    /// app-level. The first backend that works on this host is used; the
+    /// app-level fallback still requires `--insecure`.
+    ///
+    /// Example: bash_sandbox_backends = ["bubblewrap", "hakoniwa"]
+    #[serde(default)]
+    pub bash_sandbox_backends: Vec<String>,
+
+    /// Path policies checked before the run tool executes a command that writes
+    #[serde(default)]
+    pub write_policy: WritePolicyConfig,
+
+    /// Paths every write to needs its own approval, for read-only agents and
+    /// session-tier commands alike (gitignore-style globs, as in
+    /// `write_policy` rules).
+    ///
+    /// Example: protected_paths = ["Cargo.lock", ".github/**", "migrations/**"]
+    #[serde(default)]
+    pub protected_paths: Vec<String>,
+
+    /// Reads of files git doesn't track (`.env`, ignored build output):
+    /// "log" records them for `/access-log`, "ask" also requires approval
+    #[serde(default)]
+    pub untracked_reads: qq_tools::UntrackedReadPolicy,
+
+    /// Large tool outputs saved as artifacts the model can read back
+    #[serde(default)]
+    pub artifacts: ArtifactsConfigEntry,
+
+    /// Plain-English explanations shown with commands awaiting approval
+    #[serde(default)]
+    pub explain: ExplainConfigEntry,
+
+    /// Cached per-file summaries (`summarize_file`, `qq index --summaries`)
+    #[serde(default)]
+    pub summaries: SummariesConfigEntry,
+
+    /// Lexical + semantic project search (`hybrid_search`)
+    #[serde(default)]
+    pub hybrid_search: HybridSearchConfigEntry,
+
+    /// Share of the context window the tool definitions sent with each
+    /// request may take. Past it, long tool descriptions are replaced by
+    /// their one-line summaries and `describe_tool` serves the full text.
+    /// 0 always sends full descriptions.
+    #[serde(default = "default_description_budget")]
+    pub description_budget: f64,
+}
+
+fn default_description_budget() -> f64 {
+    qq_core::DEFAULT_DESCRIPTION_SHARE
+}
+
+/// TUI configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TuiConfigEntry {
+    /// Show follow-up suggestions after each response (Alt+1..3 to insert)
+    #[serde(default = "default_true")]
+    pub follow_up_suggestions: bool,
+    /// Show the sub-agent activity tree pane at startup (Ctrl+G toggles)
+    #[serde(default)]
+    pub activity_pane: bool,
+    /// Show a token/cost footer under each response (Ctrl+K toggles)
+    #[serde(default = "default_true")]
+    pub turn_stats: bool,
+    /// Warn when a response makes no progress (tokens or tool activity) for
+    /// this many seconds, offering to cancel, extend or nudge. 0 disables.
+    #[serde(default = "default_stall_warning_secs")]
+    pub stall_warning_secs: u64,
+}
+
+fn default_stall_warning_secs() -> u64 {
+    300
+}
+
+impl Default for TuiConfigEntry {
+    fn default() -> Self {
+        Self {
+            follow_up_suggestions: true,
+            activity_pane: false,
+            turn_stats: true,
+            stall_warning_secs: default_stall_warning_secs(),
+        }
+    }
+}
+
+/// Web search backend configuration.
+///
+/// `provider` selects the backend: "perplexica" (default, for configs that
+/// predate the field), "searxng", "brave", or "tavily". API keys fall back to
+/// `BRAVE_API_KEY` / `TAVILY_API_KEY`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct WebSearchConfigEntry {
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Host URL for self-hosted backends (e.g., "http://localhost:3000")
+    #[serde(default)]
+    pub host: Option<String>,
+    /// API key for hosted backends (Brave, Tavily)
     #[serde(default)]
-    pub bash_permissions: Option<BashPermissionOverrides>,
-
-    /// Sensitive directories to pre-approve for sandbox access.
-    ///
-    /// By default, directories like `.ssh`, `.aws`, `.gnupg`, etc. are hidden
-    /// inside the sandbox with empty tmpfs mounts. Any directory listed here
-    /// will be excluded from that hiding — it will be accessible from the first
-    /// command with no tool call or user approval needed.
-    ///
-    /// Valid names: ".ssh", ".aws", ".gnupg", ".gpg", ".kube", ".docker",
-    /// ".password-store", ".netrc" (anything else is silently ignored since
-    /// it was never hidden in the first place).
-    ///
-    /// Example: bash_sensitive_dirs = [".ssh", ".aws"]
+    pub api_key: Option<String>,
+    /// Maximum results per search (SearXNG, Brave, Tavily). Default: 8
     #[serde(default)]
-    pub bash_sensitive_dirs: Vec<String>,
+    pub max_results: Option<usize>,
+    /// Perplexica chat model name (e.g., "gpt-4o-mini")
+    #[serde(default)]
+    pub chat_model: Option<String>,
+    /// Perplexica embedding model name (e.g., "text-embedding-3-large")
+    #[serde(default)]
+    pub embed_model: Option<String>,
 }
 
-/// Web search (Perplexica) configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WebSearchConfigEntry {
-    /// Host URL (e.g., "http://localhost:3000")
-    pub host: String,
-    /// Chat model name (e.g., "gpt-4o-mini")
-    pub chat_model: String,
-    /// Embedding model name (e.g., "text-embedding-3-large")
-    pub embed_model: String,
+impl WebSearchConfigEntry {
+    /// Validate and convert to the qq-tools backend selection.
+    pub fn to_search_config(&self) -> Result<qq_tools::WebSearchConfig> {
+        let provider = self.provider.as_deref().unwrap_or("perplexica");
+        let max_results = self.max_results.unwrap_or(qq_tools::search::DEFAULT_MAX_RESULTS);
+        let require = |value: &Option<String>, field: &str| {
+            value.clone().ok_or_else(|| {
+                anyhow::anyhow!("[tools.web_search] provider '{}' requires '{}'", provider, field)
+            })
+        };
+        let api_key = |env_var: &str| {
+            self.api_key
+                .clone()
+                .or_else(|| std::env::var(env_var).ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "[tools.web_search] provider '{}' requires 'api_key' (or {})",
+                        provider,
+                        env_var
+                    )
+                })
+        };
+
+        Ok(match provider {
+            "perplexica" => qq_tools::WebSearchConfig::new(
+                require(&self.host, "host")?,
+                require(&self.chat_model, "chat_model")?,
+                require(&self.embed_model, "embed_model")?,
+            ),
+            "searxng" => qq_tools::WebSearchConfig::Searxng {
+                host: require(&self.host, "host")?,
+                max_results,
+            },
+            "brave" => qq_tools::WebSearchConfig::Brave {
+                api_key: api_key("BRAVE_API_KEY")?,
+                max_results,
+            },
+            "tavily" => qq_tools::WebSearchConfig::Tavily {
+                api_key: api_key("TAVILY_API_KEY")?,
+                max_results,
+            },
+            other => anyhow::bail!(
+                "[tools.web_search] unknown provider '{}' (expected perplexica, searxng, brave, or tavily)",
+                other
+            ),
+        })
+    }
 }
 
 /// Bash permission overrides for reclassifying commands.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct BashPermissionOverrides {
     /// Commands to promote to session tier (run without approval)
     #[serde(default)]
@@ -259,8 +1120,35 @@ pub struct BashPermissionOverrides {
     pub restricted: Vec<String>,
 }
 
+/// Write policies for the run tool (`[tools.write_policy]`).
+///
+/// ```toml
+/// [tools.write_policy]
+/// rules = [
+///     { path = "/infra", action = "ask" },
+///     { path = ".env", action = "block", reason = "secrets stay out of reach" },
+/// ]
+/// # Optional: also have a cheap model judge writes against prose policies
+/// classifier_profile = "fast"
+/// policies = ["Never modify CI workflows", "Don't delete migrations"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct WritePolicyConfig {
+    /// Path rules (gitignore-style globs) with an `ask` or `block` action
+    #[serde(default)]
+    pub rules: Vec<qq_tools::WriteRule>,
+
+    /// Profile of the model that judges writes against `policies`
+    #[serde(default)]
+    pub classifier_profile: Option<String>,
+
+    /// Policies in prose for the classifier model
+    #[serde(default)]
+    pub policies: Vec<String>,
+}
+
 /// Chunker configuration for processing large tool outputs
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ChunkerConfigEntry {
     /// Enable automatic chunking of large tool outputs
     #[serde(default = "default_true")]
@@ -281,6 +1169,24 @@ pub struct ChunkerConfigEntry {
     /// Process chunks in parallel (default: true)
     #[serde(default = "default_true")]
     pub parallel: bool,
+
+    /// Extract relevant lines before summarizing (default: true)
+    #[serde(default = "default_true")]
+    pub relevance_filter: bool,
+
+    /// Embedding model for ranking chunks by similarity to the query before
+    /// summarizing (e.g., "text-embedding-3-small"). Unset disables ranking.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Profile whose provider serves `embedding_model` (default: the
+    /// summarizing provider)
+    #[serde(default)]
+    pub embedding_profile: Option<String>,
+
+    /// Chunks less similar to the query than this are dropped (default: 0.2)
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
 }
 
 fn default_threshold_bytes() -> usize {
@@ -295,6 +1201,10 @@ fn default_max_chunks() -> usize {
     20
 }
 
+fn default_min_similarity() -> f32 {
+    0.2
+}
+
 impl Default for ChunkerConfigEntry {
     fn default() -> Self {
         Self {
@@ -303,12 +1213,17 @@ impl Default for ChunkerConfigEntry {
             chunk_size_bytes: default_chunk_size_bytes(),
             max_chunks: default_max_chunks(),
             parallel: true,
+            relevance_filter: true,
+            embedding_model: None,
+            embedding_profile: None,
+            min_similarity: default_min_similarity(),
         }
     }
 }
 
 impl ChunkerConfigEntry {
-    /// Convert to qq_core::ChunkerConfig
+    /// Convert to qq_core::ChunkerConfig. The embedding provider for
+    /// `embedding_profile` is resolved by the caller.
     pub fn to_chunker_config(&self) -> qq_core::ChunkerConfig {
         qq_core::ChunkerConfig {
             enabled: self.enabled,
@@ -316,6 +1231,177 @@ impl ChunkerConfigEntry {
             chunk_size_bytes: self.chunk_size_bytes,
             max_chunks: self.max_chunks,
             parallel: self.parallel,
+            relevance_filter: self.relevance_filter,
+            embedding_model: self.embedding_model.clone(),
+            min_similarity: self.min_similarity,
+            embedder: None,
+        }
+    }
+}
+
+/// Artifact store for large tool outputs: instead of being truncated or
+/// summarized, an output over `threshold_bytes` is saved in full and the
+/// model gets a preview and a digest to read the rest with `fetch_artifact`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArtifactsConfigEntry {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Outputs larger than this are saved as artifacts (default: 50KB)
+    #[serde(default = "default_threshold_bytes")]
+    pub threshold_bytes: usize,
+
+    /// Size of the head-and-tail preview shown instead (default: 4KB)
+    #[serde(default = "default_preview_bytes")]
+    pub preview_bytes: usize,
+
+    /// Artifacts older than this are deleted at startup (default: 7)
+    #[serde(default = "default_artifact_max_age_days")]
+    pub max_age_days: u64,
+}
+
+fn default_preview_bytes() -> usize {
+    qq_tools::artifact::DEFAULT_PREVIEW_BYTES
+}
+
+fn default_artifact_max_age_days() -> u64 {
+    7
+}
+
+impl Default for ArtifactsConfigEntry {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_bytes: default_threshold_bytes(),
+            preview_bytes: default_preview_bytes(),
+            max_age_days: default_artifact_max_age_days(),
+        }
+    }
+}
+
+/// One-line explanations of commands awaiting approval (`[tools.explain]`).
+/// Common commands are explained by rules; others by the `profile` model
+/// when one is set.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExplainConfigEntry {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Profile of the (cheap) model that explains commands the rules don't
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+impl Default for ExplainConfigEntry {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            profile: None,
+        }
+    }
+}
+
+/// The `summarize_file` tool (`[tools.summaries]`). Summaries are cached by
+/// file content in the cache directory, so each version of a file is
+/// summarized once.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SummariesConfigEntry {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Profile of the model that writes summaries (default: the session's)
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+impl Default for SummariesConfigEntry {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            profile: None,
+        }
+    }
+}
+
+/// The `hybrid_search` tool (`[tools.hybrid_search]`). Without an
+/// embedding model it searches lexically only.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HybridSearchConfigEntry {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Embedding model for the semantic half (e.g., "text-embedding-3-small")
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Profile whose provider serves `embedding_model` (default: the
+    /// session's provider)
+    #[serde(default)]
+    pub embedding_profile: Option<String>,
+
+    /// Weight of exact keyword matches in the ranking
+    #[serde(default = "default_hybrid_weight")]
+    pub lexical_weight: f32,
+
+    /// Weight of similarity in meaning in the ranking
+    #[serde(default = "default_hybrid_weight")]
+    pub semantic_weight: f32,
+
+    /// Results returned when the model doesn't ask for a number
+    #[serde(default = "default_hybrid_max_results")]
+    pub max_results: usize,
+
+    /// Most files embedded into the session's index
+    #[serde(default = "default_hybrid_max_indexed_files")]
+    pub max_indexed_files: usize,
+}
+
+fn default_hybrid_weight() -> f32 {
+    0.5
+}
+
+fn default_hybrid_max_results() -> usize {
+    qq_tools::hybrid_search::DEFAULT_MAX_RESULTS
+}
+
+fn default_hybrid_max_indexed_files() -> usize {
+    qq_tools::HybridSearchConfig::default().max_indexed_files
+}
+
+impl Default for HybridSearchConfigEntry {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            embedding_model: None,
+            embedding_profile: None,
+            lexical_weight: default_hybrid_weight(),
+            semantic_weight: default_hybrid_weight(),
+            max_results: default_hybrid_max_results(),
+            max_indexed_files: default_hybrid_max_indexed_files(),
+        }
+    }
+}
+
+impl HybridSearchConfigEntry {
+    /// Convert to qq_tools::HybridSearchConfig. The embedding provider for
+    /// `embedding_profile` is resolved by the caller.
+    pub fn to_search_config(&self) -> qq_tools::HybridSearchConfig {
+        qq_tools::HybridSearchConfig {
+            lexical_weight: self.lexical_weight,
+            semantic_weight: self.semantic_weight,
+            max_results: self.max_results,
+            embedding_model: self.embedding_model.clone(),
+            max_indexed_files: self.max_indexed_files,
+        }
+    }
+}
+
+impl ArtifactsConfigEntry {
+    pub fn to_policy(&self) -> qq_tools::OffloadPolicy {
+        qq_tools::OffloadPolicy {
+            threshold_bytes: self.threshold_bytes,
+            // A preview as large as the output would save nothing
+            preview_bytes: self.preview_bytes.min(self.threshold_bytes / 2),
         }
     }
 }
@@ -335,6 +1421,16 @@ impl Default for ToolsConfigEntry {
             bash_mounts: Vec::new(),
             bash_permissions: None,
             bash_sensitive_dirs: Vec::new(),
+            bash_follow_symlinks: true,
+            bash_sandbox_backends: Vec::new(),
+            write_policy: WritePolicyConfig::default(),
+            protected_paths: Vec::new(),
+            untracked_reads: qq_tools::UntrackedReadPolicy::Log,
+            artifacts: ArtifactsConfigEntry::default(),
+            explain: ExplainConfigEntry::default(),
+            summaries: SummariesConfigEntry::default(),
+            hybrid_search: HybridSearchConfigEntry::default(),
+            description_budget: default_description_budget(),
         }
     }
 }
@@ -438,6 +1534,9 @@ impl Config {
             agents: profile.agents.clone(),
             agent: profile.agent.clone().unwrap_or_else(|| "pm".to_string()),
             include_tool_reasoning: profile.include_tool_reasoning,
+            verify: profile.verify.clone(),
+            review: profile.review.clone(),
+            memory_strategy: profile.memory_strategy.clone().unwrap_or_default(),
         })
     }
 }
@@ -457,15 +1556,115 @@ pub struct ResolvedProfile {
     /// Whether to preserve reasoning/thinking content during tool-call exchanges.
     /// Profile-level override; None means fall back to provider setting.
     pub include_tool_reasoning: Option<bool>,
+    /// Verification of agent runs (`[profiles.X.verify]`)
+    pub verify: Option<qq_agents::VerifyPolicy>,
+    /// Review thresholds for agent reports (`[profiles.X.review]`)
+    pub review: Option<qq_agents::ReviewPolicy>,
+    /// How the session keeps its history within the context window
+    pub memory_strategy: qq_agents::AgentMemoryStrategy,
 }
 
 // Re-export agent config types from qq-agents
+/// Write `[tools.bash_permissions]` back to config.toml.
+///
+/// The document is edited in place, so comments and unrelated settings survive.
+/// It is read and replaced under the config file's lock, so another session
+/// saving at the same time can't interleave with this write.
+pub fn save_bash_permissions(overrides: &BashPermissionOverrides) -> Result<()> {
+    let path = Config::config_path()?;
+    // Replace the file a symlinked config points at, not the link
+    let path = std::fs::canonicalize(&path).unwrap_or(path);
+    let _lock = qq_tools::FileLock::acquire(&path)?;
+    let content = std::fs::read_to_string(&path).unwrap_or_default();
+    let updated = set_bash_permissions(&content, overrides)?;
+    qq_tools::write_atomically(&path, updated.as_bytes())?;
+    Ok(())
+}
+
+/// Replace the `[tools.bash_permissions]` lists in a config document.
+/// Empty lists are removed, and the table itself once all three are empty.
+fn set_bash_permissions(content: &str, overrides: &BashPermissionOverrides) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = content.parse()?;
+    let tools = doc
+        .entry("tools")
+        .or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("[tools] in config.toml is not a table"))?;
+    let perms = tools
+        .entry("bash_permissions")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("[tools.bash_permissions] in config.toml is not a table"))?;
+
+    for (key, commands) in [
+        ("session", &overrides.session),
+        ("per_call", &overrides.per_call),
+        ("restricted", &overrides.restricted),
+    ] {
+        if commands.is_empty() {
+            perms.remove(key);
+        } else {
+            let array: toml_edit::Array = commands.iter().map(String::as_str).collect();
+            perms.insert(key, toml_edit::value(array));
+        }
+    }
+    if perms.is_empty() {
+        tools.remove("bash_permissions");
+    }
+    Ok(doc.to_string())
+}
+
 pub use qq_agents::AgentsConfig;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_set_bash_permissions_preserves_document() {
+        let original = r#"# my config
+default_profile = "default"
+
+[tools]
+enable_web = false # keep this
+"#;
+        let overrides = BashPermissionOverrides {
+            session: vec!["make".to_string()],
+            per_call: vec![],
+            restricted: vec!["curl".to_string(), "wget".to_string()],
+        };
+        let updated = set_bash_permissions(original, &overrides).unwrap();
+        assert!(updated.starts_with("# my config"));
+        assert!(updated.contains("enable_web = false # keep this"));
+        assert!(updated.contains("[tools.bash_permissions]"));
+        assert!(!updated.contains("per_call"));
+
+        let config: Config = toml::from_str(&updated).unwrap();
+        let perms = config.tools.bash_permissions.unwrap();
+        assert_eq!(perms.session, vec!["make"]);
+        assert_eq!(perms.restricted, vec!["curl", "wget"]);
+
+        // Clearing every list drops the table
+        let cleared = set_bash_permissions(&updated, &BashPermissionOverrides::default()).unwrap();
+        assert!(!cleared.contains("bash_permissions"));
+        assert!(cleared.contains("enable_web = false # keep this"));
+    }
+
+    #[test]
+    fn test_set_bash_permissions_without_tools_table() {
+        let overrides = BashPermissionOverrides {
+            session: vec!["make".to_string()],
+            ..Default::default()
+        };
+        let updated = set_bash_permissions("default_profile = \"x\"\n", &overrides).unwrap();
+        assert!(updated.contains("[tools.bash_permissions]"));
+        assert!(!updated.contains("[tools]\n"));
+    }
+
     #[test]
     fn test_parse_config() {
         let toml = r#"
@@ -490,6 +1689,54 @@ mod tests {
         assert!(config.profiles.contains_key("default"));
     }
 
+    #[test]
+    fn test_web_search_providers() {
+        // Legacy Perplexica config without `provider`
+        let toml = r#"
+            default_profile = "default"
+
+            [tools.web_search]
+            host = "http://localhost:3000"
+            chat_model = "gpt-4o"
+            embed_model = "text-embedding-3-large"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let ws = config.tools.web_search.unwrap();
+        assert!(matches!(
+            ws.to_search_config().unwrap(),
+            qq_tools::WebSearchConfig::Perplexica { .. }
+        ));
+
+        let toml = r#"
+            default_profile = "default"
+
+            [tools.web_search]
+            provider = "searxng"
+            host = "http://localhost:8888"
+            max_results = 5
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        match config.tools.web_search.unwrap().to_search_config().unwrap() {
+            qq_tools::WebSearchConfig::Searxng { host, max_results } => {
+                assert_eq!(host, "http://localhost:8888");
+                assert_eq!(max_results, 5);
+            }
+            other => panic!("unexpected backend: {:?}", other),
+        }
+
+        let missing_host = WebSearchConfigEntry {
+            provider: Some("searxng".into()),
+            ..Default::default()
+        };
+        assert!(missing_host.to_search_config().unwrap_err().to_string().contains("'host'"));
+
+        let unknown = WebSearchConfigEntry {
+            provider: Some("bing".into()),
+            ..Default::default()
+        };
+        assert!(unknown.to_search_config().is_err());
+    }
+
     #[test]
     fn test_parse_provider_with_type() {
         let toml = r#"
@@ -540,6 +1787,7 @@ mod tests {
             provider = "openai"
             prompt = "coder"
             model = "gpt-4o"
+            memory_strategy = "sliding-window"
         "#;
 
         let config: Config = toml::from_str(toml).unwrap();
@@ -547,6 +1795,7 @@ mod tests {
         assert_eq!(resolved.provider_name, "openai");
         assert_eq!(resolved.system_prompt, Some("You are a coding assistant.".to_string()));
         assert_eq!(resolved.model, Some("gpt-4o".to_string()));
+        assert_eq!(resolved.memory_strategy, qq_agents::AgentMemoryStrategy::SlidingWindow);
     }
 
     #[test]
@@ -565,6 +1814,23 @@ mod tests {
         assert!(config.compaction.is_none());
     }
 
+    #[test]
+    fn test_compaction_regrounding() {
+        let regrounding = CompactionConfig::regrounding(None).unwrap();
+        assert_eq!(regrounding.pause_threshold(), qq_core::DEFAULT_PAUSE_THRESHOLD);
+
+        let parse = |extra: &str| -> CompactionConfig {
+            toml::from_str(extra).unwrap()
+        };
+        let custom = parse("regrounding_pause_secs = 60");
+        assert_eq!(
+            CompactionConfig::regrounding(Some(&custom)).unwrap().pause_threshold(),
+            std::time::Duration::from_secs(60)
+        );
+        let off = parse("regrounding = false");
+        assert!(CompactionConfig::regrounding(Some(&off)).is_none());
+    }
+
     #[test]
     fn test_compaction_config_from_toml() {
         let toml = r#"
@@ -629,6 +1895,59 @@ mod tests {
         assert!((obs_config.hysteresis - 1.1).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn test_routing_config_from_toml() {
+        let toml = r#"
+            default_profile = "default"
+
+            [profiles.default]
+            provider = "anthropic"
+
+            [profiles.fast]
+            provider = "anthropic"
+            model = "claude-3-5-haiku"
+
+            [providers.anthropic]
+            api_key = "sk-test"
+
+            [routing]
+            profile = "fast"
+            max_followup_chars = 40
+            ack_tools = ["update_my_task"]
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        let routing = config.routing.unwrap();
+        assert_eq!(routing.profile, "fast");
+
+        let policy = routing.to_policy();
+        assert_eq!(policy.max_followup_chars, 40);
+        assert_eq!(policy.max_formatting_chars, 240);
+        assert!(policy.route_tool_acks);
+        assert_eq!(policy.ack_tools, vec!["update_my_task".to_string()]);
+    }
+
+    #[test]
+    fn test_knowledge_config_defaults_and_overrides() {
+        let base = r#"
+            default_profile = "default"
+
+            [profiles.default]
+            provider = "anthropic"
+        "#;
+        let config: Config = toml::from_str(base).unwrap();
+        assert!(config.knowledge.enabled);
+        assert_eq!(config.knowledge.capture_agents, vec!["researcher".to_string()]);
+
+        let toml = format!(
+            "{}\n[knowledge]\nenabled = false\ncapture_agents = [\"researcher\", \"explore\"]\n",
+            base
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert!(!config.knowledge.enabled);
+        assert_eq!(config.knowledge.capture_agents.len(), 2);
+    }
+
     #[test]
     fn test_compaction_config_with_context_window() {
         // No explicit overrides, but context_window supplied → derived values
@@ -701,6 +2020,99 @@ mod tests {
         assert_eq!(provider.context_window, Some(8192));
     }
 
+    #[test]
+    fn test_network_config_overlay() {
+        let toml = r#"
+            default_profile = "default"
+
+            [network]
+            proxy = "http://proxy.corp:3128"
+            no_proxy = "localhost,.corp"
+            ca_bundles = ["/etc/corp/root.pem"]
+
+            [providers.openai]
+            api_key = "sk-test"
+
+            [providers.openai.network]
+            proxy = "socks5h://127.0.0.1:1080"
+            proxy_username = "me"
+            proxy_password = "secret"
+            ca_bundles = ["~/openai.pem"]
+
+            [providers.local]
+            base_url = "http://localhost:8080/v1"
+
+            [profiles.default]
+            provider = "openai"
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.providers["local"].network.is_none());
+
+        let network = config.providers["openai"]
+            .network
+            .as_ref()
+            .unwrap()
+            .overlaid_on(&config.network);
+        assert_eq!(network.proxy.as_deref(), Some("socks5h://127.0.0.1:1080"));
+        assert_eq!(network.proxy_username.as_deref(), Some("me"));
+        assert_eq!(network.no_proxy, None);
+        assert_eq!(network.ca_bundles, vec!["/etc/corp/root.pem", "~/openai.pem"]);
+
+        // Without a proxy of its own the provider keeps [network]'s
+        let certs_only = NetworkConfigEntry {
+            ca_bundles: vec!["/extra.pem".to_string()],
+            ..Default::default()
+        };
+        let network = certs_only.overlaid_on(&config.network);
+        assert_eq!(network.proxy.as_deref(), Some("http://proxy.corp:3128"));
+        assert_eq!(network.no_proxy.as_deref(), Some("localhost,.corp"));
+        assert_eq!(network.ca_bundles, vec!["/etc/corp/root.pem", "/extra.pem"]);
+    }
+
+    #[test]
+    fn test_network_config_resolve() {
+        assert!(NetworkConfigEntry::default().resolve().is_ok());
+
+        let socks = NetworkConfigEntry {
+            proxy: Some("socks5://127.0.0.1:1080".to_string()),
+            proxy_username: Some("me".to_string()),
+            no_proxy: Some("localhost".to_string()),
+            ..Default::default()
+        };
+        let settings = socks.resolve().unwrap();
+        assert!(settings.apply(reqwest::Client::builder()).build().is_ok());
+
+        let bad_proxy = NetworkConfigEntry {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        let err = bad_proxy.resolve().unwrap_err().to_string();
+        assert!(err.contains("invalid proxy URL 'not a url'"), "{}", err);
+
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.pem");
+        let err = NetworkConfigEntry {
+            ca_bundles: vec![missing.display().to_string()],
+            ..Default::default()
+        }
+        .resolve()
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("cannot read CA bundle"), "{}", err);
+
+        let empty = dir.path().join("empty.pem");
+        std::fs::write(&empty, "no certificates here\n").unwrap();
+        let err = NetworkConfigEntry {
+            ca_bundles: vec![empty.display().to_string()],
+            ..Default::default()
+        }
+        .resolve()
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("no certificates in CA bundle"), "{}", err);
+    }
+
     #[test]
     fn test_mcp_servers_config() {
         let toml = r#"
@@ -772,4 +2184,110 @@ mod tests {
         let config: Config = toml::from_str(toml).unwrap();
         assert!(config.mcp_servers.is_empty());
     }
+
+    #[test]
+    fn test_write_policy_config() {
+        let toml = r#"
+            default_profile = "default"
+
+            [profiles.default]
+            provider = "openai"
+
+            [tools.write_policy]
+            rules = [
+                { path = "/infra", action = "ask" },
+                { path = ".env", action = "block", reason = "secrets" },
+            ]
+            classifier_profile = "fast"
+            policies = ["Never modify CI workflows"]
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        let policy = &config.tools.write_policy;
+        assert_eq!(policy.rules.len(), 2);
+        assert_eq!(policy.rules[0].action, qq_tools::WriteAction::Ask);
+        assert_eq!(policy.rules[1].reason.as_deref(), Some("secrets"));
+        assert_eq!(policy.classifier_profile.as_deref(), Some("fast"));
+
+        let bad = toml.replace("\"ask\"", "\"warn\"");
+        assert!(toml::from_str::<Config>(&bad).is_err());
+
+        let protected = toml.replace(
+            "[tools.write_policy]",
+            "[tools]\nprotected_paths = [\"Cargo.lock\", \".github/**\"]\n\n[tools.write_policy]",
+        );
+        let config: Config = toml::from_str(&protected).unwrap();
+        assert_eq!(config.tools.protected_paths, ["Cargo.lock", ".github/**"]);
+    }
+
+    #[test]
+    fn test_untracked_reads_config() {
+        let base = "default_profile = \"default\"\n[profiles.default]\nprovider = \"openai\"\n";
+        let config: Config = toml::from_str(base).unwrap();
+        assert_eq!(config.tools.untracked_reads, qq_tools::UntrackedReadPolicy::Log);
+
+        let ask = format!("{}[tools]\nuntracked_reads = \"ask\"\n", base);
+        let config: Config = toml::from_str(&ask).unwrap();
+        assert_eq!(config.tools.untracked_reads, qq_tools::UntrackedReadPolicy::Ask);
+        assert!(toml::from_str::<Config>(&ask.replace("ask", "block")).is_err());
+    }
+
+    #[test]
+    fn test_artifacts_config() {
+        let base = "default_profile = \"default\"\n[profiles.default]\nprovider = \"openai\"\n";
+        let config: Config = toml::from_str(base).unwrap();
+        assert!(config.tools.artifacts.enabled);
+        assert_eq!(config.tools.artifacts.to_policy().threshold_bytes, 50_000);
+
+        let small = format!(
+            "{}[tools.artifacts]\nthreshold_bytes = 6000\npreview_bytes = 8000\n",
+            base
+        );
+        let config: Config = toml::from_str(&small).unwrap();
+        let policy = config.tools.artifacts.to_policy();
+        assert_eq!((policy.threshold_bytes, policy.preview_bytes), (6000, 3000));
+    }
+
+    #[test]
+    fn test_provider_endpoints_config() {
+        let toml = r#"
+            default_profile = "default"
+
+            [providers.ollama]
+            default_model = "qwen3:32b"
+
+            [[providers.ollama.endpoints]]
+            base_url = "http://gpu-1:11434/v1"
+            api_key = "none"
+            weight = 3
+
+            [[providers.ollama.endpoints]]
+            name = "laptop"
+            base_url = "http://localhost:11434/v1"
+            api_key = "none"
+
+            [providers.ollama.balance]
+            health_check = true
+
+            [profiles.default]
+            provider = "ollama"
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        let ollama = &config.providers["ollama"];
+        assert_eq!(ollama.endpoints.len(), 2);
+        assert_eq!(ollama.endpoints[0].weight, 3);
+        assert_eq!(ollama.endpoints[1].weight, 1);
+        assert_eq!(ollama.endpoints[1].name.as_deref(), Some("laptop"));
+        // Every endpoint has a key, so the entry needs none
+        assert_eq!(ollama.entry_api_key().as_deref(), Some("none"));
+
+        let policy = ollama.balance.to_policy();
+        assert_eq!(policy.max_failures, 2);
+        assert_eq!(policy.cooldown, std::time::Duration::from_secs(30));
+        assert!(policy.health_check);
+
+        let plain = ProviderConfigEntry::default();
+        assert_eq!(plain.entry_api_key(), None);
+    }
 }
@@ -0,0 +1,659 @@
+//! `qq config check`: validate config files against their schemas.
+//!
+//! serde ignores keys it does not know, so a typo like `bash_mount` in
+//! config.toml silently does nothing. The check walks each file against the
+//! JSON schema derived (schemars) from the types it deserializes into and
+//! reports unknown keys with the closest known one, type errors, values
+//! outside an enum, missing required keys, and deprecated fields, each with
+//! its line and column. Fields mark themselves deprecated with
+//! `#[schemars(extend("deprecated" = true, "x-replaced-by" = "..."))]`, and
+//! serde aliases are declared with `extend("x-aliases" = [...])` so old names
+//! are reported as such instead of as unknown keys.
+
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::agents::mention::edit_distance;
+use crate::config::{AgentsConfig, Config};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// 1-based line and column, when the location is known
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+    /// Suggested fix
+    pub hint: Option<String>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.position {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}", severity, self.message)?;
+        if let Some(ref hint) = self.hint {
+            write!(f, "\n    help: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the TOML `text` of a file that deserializes into `T`.
+pub fn check_toml<T: DeserializeOwned + JsonSchema>(text: &str) -> Vec<Issue> {
+    let doc = match toml_edit::Document::parse(text) {
+        Ok(doc) => doc,
+        Err(e) => {
+            return vec![Issue {
+                severity: Severity::Error,
+                position: e.span().map(|span| position(text, span.start)),
+                message: format!("invalid TOML: {}", e.message()),
+                hint: None,
+            }]
+        }
+    };
+    let schema = qq_core::extract::schema_for::<T>();
+    let root = Node::table(doc.as_table());
+    let mut issues = Checker {
+        text,
+        defs: &schema["$defs"],
+    }
+    .check(&root, &schema, "");
+
+    // Anything the schema walk cannot see (out-of-range integers and the
+    // like) still fails deserialization.
+    if !issues.iter().any(|i| i.severity == Severity::Error) {
+        if let Err(e) = toml::from_str::<T>(text) {
+            issues.push(Issue {
+                severity: Severity::Error,
+                position: e.span().map(|span| position(text, span.start)),
+                message: e.message().to_string(),
+                hint: None,
+            });
+        }
+    }
+    issues
+}
+
+/// Check config.toml and agents.toml, printing what was found. Fails when
+/// either file has errors.
+pub fn run() -> Result<()> {
+    let files = [
+        ("config.toml", Config::config_path()?, true),
+        ("agents.toml", AgentsConfig::config_path()?, false),
+    ];
+    let (mut errors, mut warnings) = (0, 0);
+    for (name, path, required) in files {
+        if !path.exists() {
+            if required {
+                errors += 1;
+                println!(
+                    "{}: error: not found (run `qq setup` to create it)",
+                    path.display()
+                );
+            } else {
+                println!("{}: not present (optional)", path.display());
+            }
+            continue;
+        }
+        let issues = check_file(name, &path)?;
+        if issues.is_empty() {
+            println!("{}: ok", path.display());
+        }
+        for issue in &issues {
+            match issue.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            match issue.position {
+                Some(_) => println!("{}:{}", path.display(), issue),
+                None => println!("{}: {}", path.display(), issue),
+            }
+        }
+    }
+    if errors > 0 {
+        anyhow::bail!(
+            "configuration has {} error{} and {} warning{}",
+            errors,
+            plural(errors),
+            warnings,
+            plural(warnings)
+        );
+    }
+    if warnings > 0 {
+        println!("{} warning{}", warnings, plural(warnings));
+    }
+    Ok(())
+}
+
+/// Check `path`, which is config.toml or (any other `name`) agents.toml.
+pub fn check_file(name: &str, path: &Path) -> Result<Vec<Issue>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(match name {
+        "config.toml" => check_toml::<Config>(&text),
+        _ => check_toml::<AgentsConfig>(&text),
+    })
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// 1-based line and column of byte `offset` in `text`.
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+/// A parsed TOML value with its location.
+struct Node {
+    span: Option<Range<usize>>,
+    kind: Kind,
+}
+
+enum Kind {
+    /// Entries with the span of their key
+    Table(Vec<(String, Option<Range<usize>>, Node)>),
+    Array(Vec<Node>),
+    String(String),
+    Integer(i64),
+    Float,
+    Boolean,
+    Datetime,
+}
+
+impl Node {
+    fn table(table: &toml_edit::Table) -> Self {
+        let entries = table
+            .iter()
+            .map(|(key, item)| {
+                let key_span = table.key(key).and_then(|k| k.span());
+                (key.to_string(), key_span, Self::item(item))
+            })
+            .collect();
+        Self {
+            span: table.span(),
+            kind: Kind::Table(entries),
+        }
+    }
+
+    fn item(item: &toml_edit::Item) -> Self {
+        match item {
+            toml_edit::Item::Table(table) => Self::table(table),
+            toml_edit::Item::ArrayOfTables(array) => Self {
+                span: array.span(),
+                kind: Kind::Array(array.iter().map(Self::table).collect()),
+            },
+            toml_edit::Item::Value(value) => Self::value(value),
+            toml_edit::Item::None => Self {
+                span: None,
+                kind: Kind::Table(Vec::new()),
+            },
+        }
+    }
+
+    fn value(value: &toml_edit::Value) -> Self {
+        use toml_edit::Value;
+        let kind = match value {
+            Value::String(s) => Kind::String(s.value().clone()),
+            Value::Integer(i) => Kind::Integer(*i.value()),
+            Value::Float(_) => Kind::Float,
+            Value::Boolean(_) => Kind::Boolean,
+            Value::Datetime(_) => Kind::Datetime,
+            Value::Array(array) => Kind::Array(array.iter().map(Self::value).collect()),
+            Value::InlineTable(table) => Kind::Table(
+                table
+                    .iter()
+                    .map(|(key, value)| {
+                        let key_span = table.key(key).and_then(|k| k.span());
+                        (key.to_string(), key_span, Self::value(value))
+                    })
+                    .collect(),
+            ),
+        };
+        Self {
+            span: value.span(),
+            kind,
+        }
+    }
+
+    /// Name of the value's type, with an article, for messages.
+    fn type_name(&self) -> &'static str {
+        match self.kind {
+            Kind::Table(_) => "a table",
+            Kind::Array(_) => "an array",
+            Kind::String(_) => "a string",
+            Kind::Integer(_) => "an integer",
+            Kind::Float => "a float",
+            Kind::Boolean => "a boolean",
+            Kind::Datetime => "a datetime",
+        }
+    }
+
+    /// Whether the value is of JSON schema type `ty`.
+    fn is(&self, ty: &str) -> bool {
+        matches!(
+            (ty, &self.kind),
+            ("object", Kind::Table(_))
+                | ("array", Kind::Array(_))
+                | ("string", Kind::String(_))
+                | ("integer", Kind::Integer(_))
+                | ("number", Kind::Integer(_) | Kind::Float)
+                | ("boolean", Kind::Boolean)
+        )
+    }
+}
+
+struct Checker<'a> {
+    text: &'a str,
+    defs: &'a Value,
+}
+
+impl Checker<'_> {
+    fn issue(
+        &self,
+        severity: Severity,
+        span: &Option<Range<usize>>,
+        message: String,
+        hint: Option<String>,
+    ) -> Issue {
+        Issue {
+            severity,
+            position: span.as_ref().map(|s| position(self.text, s.start)),
+            message,
+            hint,
+        }
+    }
+
+    /// Follow `$ref`s to the schema they name.
+    fn resolve<'s>(&'s self, mut schema: &'s Value) -> &'s Value {
+        while let Some(name) = schema["$ref"]
+            .as_str()
+            .and_then(|r| r.strip_prefix("#/$defs/"))
+        {
+            schema = &self.defs[name];
+        }
+        schema
+    }
+
+    fn check(&self, node: &Node, schema: &Value, path: &str) -> Vec<Issue> {
+        let schema = self.resolve(schema);
+        let branches: Vec<&Value> = ["anyOf", "oneOf"]
+            .iter()
+            .filter_map(|k| schema[*k].as_array())
+            .flatten()
+            .filter(|b| b["type"] != "null")
+            .collect();
+        if !branches.is_empty() {
+            return self.check_branches(node, &branches, path);
+        }
+
+        if let Some(allowed) = schema["enum"].as_array() {
+            return self.check_allowed(node, allowed.iter().collect(), path);
+        }
+        if !schema["const"].is_null() {
+            return self.check_allowed(node, vec![&schema["const"]], path);
+        }
+
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(tys) => tys.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|ty| node.is(ty)) {
+            return vec![self.type_error(node, &types, path)];
+        }
+
+        match &node.kind {
+            Kind::Table(entries) => self.check_table(node, entries, schema, path),
+            Kind::Array(items) => items
+                .iter()
+                .enumerate()
+                .flat_map(|(i, item)| {
+                    self.check(item, &schema["items"], &format!("{}[{}]", path, i))
+                })
+                .collect(),
+            Kind::Integer(n) if *n < 0 && schema["minimum"].as_f64() == Some(0.0) => {
+                vec![self.issue(
+                    Severity::Error,
+                    &node.span,
+                    format!("`{}` must not be negative", path),
+                    None,
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn type_error(&self, node: &Node, types: &[&str], path: &str) -> Issue {
+        let expected: Vec<&str> = types
+            .iter()
+            .filter(|ty| **ty != "null")
+            .map(|ty| match *ty {
+                "object" => "a table",
+                "array" => "an array",
+                "string" => "a string",
+                "integer" => "an integer",
+                "number" => "a number",
+                "boolean" => "a boolean",
+                other => other,
+            })
+            .collect();
+        // `enabled = "true"` and `max_chunks = "20"` are the usual slips
+        let hint = match &node.kind {
+            Kind::String(s)
+                if (types.contains(&"boolean") && s.parse::<bool>().is_ok())
+                    || (types.iter().any(|t| *t == "integer" || *t == "number")
+                        && s.parse::<f64>().is_ok()) =>
+            {
+                Some(format!("write it without quotes: {}", s))
+            }
+            Kind::Table(_) | Kind::Array(_) => None,
+            _ if types.contains(&"array") => Some("wrap the value in [ ]".to_string()),
+            _ => None,
+        };
+        self.issue(
+            Severity::Error,
+            &node.span,
+            format!(
+                "`{}` should be {}, found {}",
+                path,
+                expected.join(" or "),
+                node.type_name()
+            ),
+            hint,
+        )
+    }
+
+    /// Check a value that must equal one of `allowed`.
+    fn check_allowed(&self, node: &Node, allowed: Vec<&Value>, path: &str) -> Vec<Issue> {
+        let found = match &node.kind {
+            Kind::String(s) => Value::String(s.clone()),
+            Kind::Integer(n) => Value::from(*n),
+            _ => Value::Null,
+        };
+        if allowed.contains(&&found) {
+            return Vec::new();
+        }
+        let names: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+        let hint = found
+            .as_str()
+            .and_then(|s| closest(s, allowed.iter().filter_map(|v| v.as_str())))
+            .map(|s| format!("did you mean \"{}\"?", s));
+        let found = match found {
+            Value::Null => node.type_name().to_string(),
+            value => value.to_string(),
+        };
+        vec![self.issue(
+            Severity::Error,
+            &node.span,
+            format!(
+                "`{}` must be one of {}, found {}",
+                path,
+                names.join(", "),
+                found
+            ),
+            hint,
+        )]
+    }
+
+    /// Check against alternatives (`Option<T>`, unit enums, tagged enums).
+    fn check_branches(&self, node: &Node, branches: &[&Value], path: &str) -> Vec<Issue> {
+        let branches: Vec<&Value> = branches.iter().map(|b| self.resolve(b)).collect();
+        if let [only] = branches[..] {
+            return self.check(node, only, path);
+        }
+
+        // Unit enum variants
+        let consts: Vec<&Value> = branches
+            .iter()
+            .map(|b| &b["const"])
+            .filter(|c| !c.is_null())
+            .collect();
+        if consts.len() == branches.len() {
+            return self.check_allowed(node, consts, path);
+        }
+
+        // Internally tagged enum: pick the variant by its tag
+        if let Kind::Table(entries) = &node.kind {
+            if let Some(tag) = tag_key(&branches) {
+                let variants: Vec<&Value> = branches
+                    .iter()
+                    .map(|b| &b["properties"][tag]["const"])
+                    .collect();
+                let Some((_, _, value)) = entries.iter().find(|(k, _, _)| k == tag) else {
+                    let names: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+                    return vec![self.issue(
+                        Severity::Error,
+                        &node.span,
+                        format!("`{}` is missing `{}`", path, tag),
+                        Some(format!("set {} = {}", tag, names.join(" or "))),
+                    )];
+                };
+                let tag_path = join(path, tag);
+                let found = self.check_allowed(value, variants.clone(), &tag_path);
+                if !found.is_empty() {
+                    return found;
+                }
+                let chosen = branches
+                    .iter()
+                    .zip(&variants)
+                    .find(|(_, v)| matches!(&value.kind, Kind::String(s) if v.as_str() == Some(s)))
+                    .map(|(b, _)| *b)
+                    .expect("tag matched a variant");
+                return self.check(node, chosen, path);
+            }
+        }
+
+        // Otherwise the alternative that fits best
+        branches
+            .iter()
+            .map(|b| self.check(node, b, path))
+            .min_by_key(|issues| {
+                issues
+                    .iter()
+                    .filter(|i| i.severity == Severity::Error)
+                    .count()
+            })
+            .unwrap_or_default()
+    }
+
+    fn check_table(
+        &self,
+        node: &Node,
+        entries: &[(String, Option<Range<usize>>, Node)],
+        schema: &Value,
+        path: &str,
+    ) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let properties = schema["properties"].as_object();
+        let additional = &schema["additionalProperties"];
+
+        for (key, key_span, value) in entries {
+            let key_path = join(path, key);
+            if let Some(property) = properties.and_then(|p| p.get(key)) {
+                if property["deprecated"] == true {
+                    let hint = property["x-replaced-by"]
+                        .as_str()
+                        .map(|r| format!("use `{}` instead", r));
+                    issues.push(self.issue(
+                        Severity::Warning,
+                        key_span,
+                        format!("`{}` is deprecated", key_path),
+                        hint,
+                    ));
+                }
+                issues.extend(self.check(value, property, &key_path));
+            } else if additional.is_object() {
+                issues.extend(self.check(value, additional, &key_path));
+            } else if *additional == true || properties.is_none() {
+                // Free-form table (`parameters`, maps of anything)
+            } else if let Some((name, property)) =
+                properties.into_iter().flatten().find(|(_, p)| {
+                    p["x-aliases"]
+                        .as_array()
+                        .is_some_and(|a| a.contains(&Value::from(key.as_str())))
+                })
+            {
+                issues.push(self.issue(
+                    Severity::Warning,
+                    key_span,
+                    format!("`{}` is an old name for `{}`", key_path, name),
+                    Some(format!("rename it to `{}`", name)),
+                ));
+                issues.extend(self.check(value, property, &key_path));
+            } else {
+                let known = properties.into_iter().flatten().map(|(k, _)| k.as_str());
+                let hint = match closest(key, known.clone()) {
+                    Some(name) => format!("did you mean `{}`?", name),
+                    None => format!("known keys: {}", known.collect::<Vec<_>>().join(", ")),
+                };
+                issues.push(self.issue(
+                    Severity::Error,
+                    key_span,
+                    format!("unknown key `{}`", key_path),
+                    Some(hint),
+                ));
+            }
+        }
+
+        for required in schema["required"].as_array().into_iter().flatten() {
+            let Some(required) = required.as_str() else {
+                continue;
+            };
+            if !entries.iter().any(|(k, _, _)| k == required) {
+                let message = if path.is_empty() {
+                    format!("missing required key `{}`", required)
+                } else {
+                    format!("`{}` is missing required key `{}`", path, required)
+                };
+                issues.push(self.issue(Severity::Error, &node.span, message, None));
+            }
+        }
+        issues
+    }
+}
+
+/// Property every branch pins to a constant, e.g. `transport`.
+fn tag_key<'v>(branches: &[&'v Value]) -> Option<&'v str> {
+    let first = branches.first()?["properties"].as_object()?;
+    first
+        .iter()
+        .find(|(key, _)| {
+            branches
+                .iter()
+                .all(|b| b["properties"][key.as_str()]["const"].is_string())
+        })
+        .map(|(key, _)| key.as_str())
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// The candidate closest to `name`, if it is a plausible typo.
+fn closest<'c>(name: &str, candidates: impl Iterator<Item = &'c str>) -> Option<&'c str> {
+    let max_distance = (name.chars().count() / 3).max(2);
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min()
+        .map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_typos_types_and_deprecations() {
+        let config = r#"default_profile = "default"
+
+[tools]
+enable_web = "false"
+bash_mount = ["/opt/data"]
+
+[tools.chunker]
+max_chunks = -1
+
+[session_memory]
+clear_summary = "allways"
+
+[mcp_servers.files]
+transport = "stdio"
+comand = "mcp-files"
+
+[[hooks]]
+command = "notify-send done"
+"#;
+        let issues: Vec<String> = check_toml::<Config>(config)
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "4:14: error: `tools.enable_web` should be a boolean, found a string\n    help: write it without quotes: false",
+                "5:1: error: unknown key `tools.bash_mount`\n    help: did you mean `bash_mounts`?",
+                "8:14: error: `tools.chunker.max_chunks` must not be negative",
+                "11:17: error: `session_memory.clear_summary` must be one of \"ask\", \"always\", \"never\", found \"allways\"\n    help: did you mean \"always\"?",
+                "15:1: error: unknown key `mcp_servers.files.comand`\n    help: did you mean `command`?",
+                "13:1: error: `mcp_servers.files` is missing required key `command`",
+                "17:1: error: `hooks[0]` is missing required key `on`",
+            ]
+        );
+
+        let agents = r#"[agents.reviewer]
+description = "Reviews diffs"
+system_prompt = "Review the diff."
+model = "gpt-4o"
+
+[builtin.coder]
+extra_tools = ["mcp:github/*"]
+"#;
+        let issues: Vec<String> = check_toml::<AgentsConfig>(agents)
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "4:1: warning: `agents.reviewer.model` is deprecated\n    help: use `profile` instead",
+                "7:1: warning: `builtin.coder.extra_tools` is an old name for `tools`\n    help: rename it to `tools`",
+            ]
+        );
+
+        assert!(check_toml::<Config>("default_profile = \"default\"\n").is_empty());
+        let broken = check_toml::<Config>("default_profile = \n");
+        assert_eq!(broken.len(), 1);
+        assert!(broken[0].message.starts_with("invalid TOML"));
+        assert_eq!(broken[0].position.map(|p| p.0), Some(1));
+    }
+}
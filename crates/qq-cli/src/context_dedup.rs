@@ -0,0 +1,318 @@
+//! History-aware deduplication of file contents in the request context.
+//!
+//! Sessions that re-read a file after every edit accumulate stale copies of
+//! it, and those copies can crowd out a third of the context window. Before
+//! each request, [`dedup_file_contents`] replaces older copies with a stub
+//! naming the tool call that returned the latest one. Only the messages sent are
+//! rewritten; the session history keeps every copy.
+//!
+//! A copy is one of:
+//! - a `read_files` section, keyed by path, line range, and grep filter
+//!   (not by the file's line count, which changes as it is edited)
+//! - the output of a plain `cat` through `run`, keyed by command
+//! - any tool result repeated verbatim
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use qq_core::{Content, Message, Role};
+
+/// Tool results shorter than this are left alone; a stub would save little.
+const MIN_DEDUP_BYTES: usize = 256;
+
+/// What [`dedup_file_contents`] replaced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// Older copies replaced with a stub
+    pub superseded: usize,
+    pub bytes_saved: usize,
+}
+
+/// How a tool result is matched against later ones.
+enum Kind {
+    ReadFiles,
+    Cat(String),
+    Other,
+}
+
+/// Replace older copies of repeated file contents in `messages` with a
+/// stub naming the tool and call id of the latest copy, both of which the
+/// model can see.
+pub fn dedup_file_contents(messages: &mut [Message]) -> DedupStats {
+    let calls: HashMap<String, (String, Kind)> = messages
+        .iter()
+        .flat_map(|m| &m.tool_calls)
+        .map(|call| {
+            let kind = match call.name.as_str() {
+                "read_files" => Kind::ReadFiles,
+                "run" => call
+                    .arguments
+                    .get("command")
+                    .and_then(|c| c.as_str())
+                    .and_then(plain_cat)
+                    .map(Kind::Cat)
+                    .unwrap_or(Kind::Other),
+                _ => Kind::Other,
+            };
+            (call.id.clone(), (call.name.clone(), kind))
+        })
+        .collect();
+
+    let mut stats = DedupStats::default();
+    // Key -> stub pointing at the newest call that returned it
+    let mut latest: HashMap<String, String> = HashMap::new();
+    for i in (0..messages.len()).rev() {
+        let message = &messages[i];
+        // Pinned results are sent verbatim
+        if message.role != Role::Tool || message.is_pinned() {
+            continue;
+        }
+        let Some(text) = message.content.as_text() else {
+            continue;
+        };
+        if text.len() < MIN_DEDUP_BYTES {
+            continue;
+        }
+        let id = message.tool_call_id.as_deref().unwrap_or_default();
+        let (name, kind) = calls.get(id).map_or(("tool", &Kind::Other), |(n, k)| (n.as_str(), k));
+        let stub = stub(name, id);
+        let rewritten = match kind {
+            Kind::ReadFiles => dedup_sections(text, &stub, &mut latest),
+            Kind::Cat(command) => dedup_whole(format!("cat\0{}", command), stub, &mut latest),
+            Kind::Other => {
+                let mut hasher = DefaultHasher::new();
+                text.hash(&mut hasher);
+                dedup_whole(format!("output\0{:x}", hasher.finish()), stub, &mut latest)
+            }
+        };
+        if let Some(new_text) = rewritten {
+            if new_text.len() < text.len() {
+                stats.superseded += 1;
+                stats.bytes_saved += text.len() - new_text.len();
+                messages[i].content = Content::text(new_text);
+            }
+        }
+    }
+    stats
+}
+
+/// Stub for a copy superseded by the result of the `name` call `id`.
+fn stub(name: &str, id: &str) -> String {
+    format!("[superseded by the later {} call {}]", name, id)
+}
+
+/// Stub for a whole tool result already seen in a later message.
+fn dedup_whole(key: String, stub: String, latest: &mut HashMap<String, String>) -> Option<String> {
+    match latest.get(&key) {
+        Some(newer) => Some(newer.clone()),
+        None => {
+            latest.insert(key, stub);
+            None
+        }
+    }
+}
+
+/// Stub out the `read_files` sections already seen in a later message.
+fn dedup_sections(text: &str, stub: &str, latest: &mut HashMap<String, String>) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut changed = false;
+    for (header, section) in split_sections(text) {
+        let Some(header) = header else {
+            out.push_str(section);
+            continue;
+        };
+        let key = section_key(header);
+        match latest.get(&key) {
+            Some(newer) => {
+                out.push_str(header);
+                out.push('\n');
+                out.push_str(newer);
+                out.push_str(&section[section.trim_end().len()..]);
+                changed = true;
+            }
+            None => {
+                // A truncated or failed read doesn't replace an older copy
+                let body = &section[header.len()..];
+                let complete =
+                    !body.contains("\n[truncated: ") && !body.trim_start().starts_with("Error:");
+                if complete {
+                    latest.insert(key, stub.to_string());
+                }
+                out.push_str(section);
+            }
+        }
+    }
+    changed.then_some(out)
+}
+
+/// Split `read_files` output at its `===== path =====` headers. Each section
+/// runs to the next header; text before the first header has no header.
+fn split_sections(text: &str) -> Vec<(Option<&str>, &str)> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut header: Option<&str> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        if is_section_header(trimmed) {
+            if offset > start {
+                sections.push((header, &text[start..offset]));
+            }
+            start = offset;
+            header = Some(trimmed);
+        }
+        offset += line.len();
+    }
+    if offset > start {
+        sections.push((header, &text[start..]));
+    }
+    sections
+}
+
+fn is_section_header(line: &str) -> bool {
+    line.len() > 12 && line.starts_with("===== ") && line.ends_with(" =====")
+}
+
+/// Section identity: the header without the file's line total, so a re-read
+/// after an edit matches the read before it.
+fn section_key(header: &str) -> String {
+    let inner = &header[6..header.len() - 6];
+    for (pos, _) in inner.match_indices(" (") {
+        let rest = &inner[pos + 2..];
+        let Some(close) = rest.find(')') else {
+            continue;
+        };
+        let paren = &rest[..close];
+        let after = &rest[close + 1..];
+        // " (120 lines)"
+        if let Some(count) = paren.strip_suffix(" lines") {
+            if !count.is_empty() && count.bytes().all(|b| b.is_ascii_digit()) {
+                return format!("{}{}", &inner[..pos], after);
+            }
+        }
+        // " (lines 1-50 of 120)"
+        if let Some(range) = paren.strip_prefix("lines ") {
+            if let Some((range, _total)) = range.split_once(" of ") {
+                return format!("{} (lines {}){}", &inner[..pos], range, after);
+            }
+        }
+    }
+    inner.to_string()
+}
+
+/// The command, if it only prints files with `cat` (no pipes, redirects,
+/// substitutions, or chained commands).
+fn plain_cat(command: &str) -> Option<String> {
+    let command = command.trim();
+    let args = command.strip_prefix("cat ")?;
+    if args.contains(['|', ';', '&', '>', '<', '$', '`', '\n']) {
+        return None;
+    }
+    Some(command.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::ToolCall;
+
+    fn numbered(lines: usize, tag: &str) -> String {
+        (1..=lines)
+            .map(|i| format!("{:>3}| {} line {}\n", i, tag, i))
+            .collect()
+    }
+
+    fn tool_turn(id: &str, name: &str, args: serde_json::Value, output: &str) -> Vec<Message> {
+        vec![
+            Message::assistant_with_tool_calls("", vec![ToolCall::new(id, name, args)]),
+            Message::tool_result(id, output),
+        ]
+    }
+
+    fn text(message: &Message) -> &str {
+        message.content.as_text().unwrap()
+    }
+
+    #[test]
+    fn test_read_files_sections_superseded() {
+        let old = format!(
+            "===== src/lib.rs (20 lines) =====\n{}\n===== src/main.rs (20 lines) =====\n{}",
+            numbered(20, "old"),
+            numbered(20, "main")
+        );
+        let new = format!("===== src/lib.rs (21 lines) =====\n{}", numbered(21, "new"));
+        let args = serde_json::json!({"files": [{"path": "src/lib.rs"}]});
+        let mut messages = vec![Message::user("edit lib.rs")];
+        messages.extend(tool_turn("a", "read_files", args.clone(), &old));
+        messages.extend(tool_turn("b", "read_files", args, &new));
+
+        let stats = dedup_file_contents(&mut messages);
+        assert_eq!(stats.superseded, 1);
+        let first = text(&messages[2]);
+        assert!(first.starts_with(
+            "===== src/lib.rs (20 lines) =====\n[superseded by the later read_files call b]\n\n===== src/main.rs"
+        ));
+        assert!(first.contains("main line 20"));
+        assert!(!first.contains("old line"));
+        assert_eq!(text(&messages[4]), new);
+    }
+
+    #[test]
+    fn test_ranges_and_truncated_reads_kept() {
+        let full = format!("===== a.rs (30 lines) =====\n{}", numbered(30, "full"));
+        let range = format!(
+            "===== a.rs (lines 1-20 of 30) =====\n{}",
+            numbered(20, "part")
+        );
+        let truncated = format!(
+            "===== a.rs (31 lines) =====\n{}[truncated: output budget reached; continue with start_line=21]",
+            numbered(20, "cut")
+        );
+        let args = serde_json::json!({});
+        let mut messages = Vec::new();
+        messages.extend(tool_turn("a", "read_files", args.clone(), &full));
+        messages.extend(tool_turn("b", "read_files", args.clone(), &range));
+        messages.extend(tool_turn("c", "read_files", args, &truncated));
+
+        assert_eq!(dedup_file_contents(&mut messages), DedupStats::default());
+    }
+
+    #[test]
+    fn test_cat_and_verbatim_repeats() {
+        let mut messages = Vec::new();
+        let cat = serde_json::json!({"command": "cat  Cargo.toml"});
+        messages.extend(tool_turn("a", "run", cat.clone(), &numbered(20, "v1")));
+        messages.extend(tool_turn("b", "run", cat, &numbered(20, "v2")));
+        let piped = serde_json::json!({"command": "cat Cargo.toml | head"});
+        messages.extend(tool_turn("c", "run", piped.clone(), &numbered(20, "v1")));
+        messages.extend(tool_turn("d", "run", piped, &numbered(20, "v3")));
+        messages.extend(tool_turn(
+            "e",
+            "web_fetch",
+            serde_json::json!({}),
+            &numbered(20, "v3"),
+        ));
+
+        let stats = dedup_file_contents(&mut messages);
+        assert_eq!(stats.superseded, 2);
+        assert_eq!(text(&messages[1]), "[superseded by the later run call b]");
+        // Not a plain cat, and nothing later repeats its output
+        assert!(text(&messages[5]).contains("v1 line 1"));
+        // A different tool, but the output is repeated verbatim
+        assert_eq!(text(&messages[7]), "[superseded by the later web_fetch call e]");
+        assert!(text(&messages[9]).contains("v3 line 1"));
+    }
+
+    #[test]
+    fn test_section_key_ignores_totals() {
+        assert_eq!(section_key("===== a b.rs (12 lines) ====="), "a b.rs");
+        assert_eq!(
+            section_key("===== x.rs (lines 5-9 of 40) grep /fn (a)/ ====="),
+            "x.rs (lines 5-9) grep /fn (a)/"
+        );
+        assert_eq!(section_key("===== gone.rs ====="), "gone.rs");
+        assert_eq!(plain_cat("cat a.rs b.rs"), Some("cat a.rs b.rs".into()));
+        assert_eq!(plain_cat("cat a.rs > b.rs"), None);
+        assert_eq!(plain_cat("catalog"), None);
+    }
+}
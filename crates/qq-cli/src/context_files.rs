@@ -0,0 +1,461 @@
+//! Files added to the conversation with `/add`.
+//!
+//! Picked files (optionally a line range) are queued for the next message,
+//! where each becomes a `<file>` block ahead of the user's text. Added files
+//! stay tracked after they are sent: refreshing one stubs the copies already
+//! in the history and queues the current contents, and dropping one stubs
+//! them and stops tracking it.
+
+use std::path::Path;
+
+use qq_core::{Content, ContentPart, Message, Role};
+
+/// Upper bound on files listed for the picker.
+pub const MAX_LISTED_FILES: usize = 20_000;
+
+/// Files (or ranges) larger than this are cut off in their block.
+const MAX_FILE_BYTES: usize = 256 * 1024;
+
+/// Directories never listed in the picker.
+const SKIP_DIRS: &[&str] = &[
+    "target",
+    "node_modules",
+    "vendor",
+    "dist",
+    "build",
+    "__pycache__",
+    "venv",
+];
+
+/// A file added as context, with an optional inclusive 1-based line range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddedFile {
+    /// Path relative to the project root (or absolute outside it)
+    pub path: String,
+    pub range: Option<(usize, usize)>,
+}
+
+impl AddedFile {
+    /// `path`, or `path:start-end` for a range.
+    pub fn label(&self) -> String {
+        match self.range {
+            None => self.path.clone(),
+            Some((start, usize::MAX)) => format!("{}:{}-", self.path, start),
+            Some((start, end)) => format!("{}:{}-{}", self.path, start, end),
+        }
+    }
+
+    /// Opening tag of this file's block.
+    fn header(&self) -> String {
+        match self.range {
+            None => format!("<file path=\"{}\">", self.path),
+            Some(_) => {
+                let label = self.label();
+                let lines = &label[self.path.len() + 1..];
+                format!("<file path=\"{}\" lines=\"{}\">", self.path, lines)
+            }
+        }
+    }
+}
+
+/// Parse a line range: `10-40`, `10` (one line), `10-` (to the end), or
+/// empty for the whole file.
+pub fn parse_range(input: &str) -> Result<Option<(usize, usize)>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let number = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Not a line number: '{}'", s.trim()))
+    };
+    let (start, end) = match input.split_once('-') {
+        Some((start, "")) => (number(start)?, usize::MAX),
+        Some((start, end)) => (number(start)?, number(end)?),
+        None => {
+            let line = number(input)?;
+            (line, line)
+        }
+    };
+    if end < start {
+        return Err(format!("Range ends before it starts: {}", input));
+    }
+    Ok(Some((start, end)))
+}
+
+/// Files under `root` for the picker, relative and sorted. Hidden, build,
+/// and dependency directories are skipped; at most [`MAX_LISTED_FILES`].
+pub fn list_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !SKIP_DIRS.contains(&name.as_ref()) {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() {
+                if let Ok(relative) = entry.path().strip_prefix(root) {
+                    files.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+                if files.len() >= MAX_LISTED_FILES {
+                    files.sort();
+                    return files;
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Score `candidate` against a fuzzy `query`: every query character must
+/// appear in order (case-insensitive, whitespace ignored). Runs of
+/// consecutive characters, matches at the start of a path segment or word,
+/// and matches in the file name score higher. `None` if it doesn't match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let name_start = candidate.rfind('/').map_or(0, |i| i + 1);
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<(usize, char)> = None;
+    for (i, c) in candidate.char_indices() {
+        let Some(&wanted) = query.get(next) else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            score += 1;
+            if previous.is_some_and(|(p, pc)| p + pc.len_utf8() == i) {
+                score += 5;
+            }
+            let before = candidate[..i].chars().last();
+            if matches!(before, None | Some('/' | '_' | '-' | '.' | ' ')) {
+                score += 8;
+            }
+            if i >= name_start {
+                score += 2;
+            }
+            previous = Some((i, c));
+            next += 1;
+        }
+    }
+    (next == query.len()).then_some(score)
+}
+
+/// Indices of the files matching `query`, best first (shorter paths break
+/// ties), at most `limit`.
+pub fn fuzzy_matches(query: &str, files: &[String], limit: usize) -> Vec<usize> {
+    let mut scored: Vec<(u32, usize)> = files
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| fuzzy_score(query, f).map(|s| (s, i)))
+        .collect();
+    scored.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(files[a.1].len().cmp(&files[b.1].len()))
+            .then(files[a.1].cmp(&files[b.1]))
+    });
+    scored.into_iter().take(limit).map(|(_, i)| i).collect()
+}
+
+/// The `<file>` block for `file`, read from under `root`.
+pub fn file_block(root: &Path, file: &AddedFile) -> Result<String, String> {
+    let path = root.join(&file.path);
+    let bytes = std::fs::read(&path).map_err(|e| format!("Cannot read {}: {}", file.path, e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let mut body = match file.range {
+        None => text.into_owned(),
+        Some((start, end)) => {
+            let total = text.lines().count();
+            if start > total {
+                return Err(format!("{} has only {} lines", file.path, total));
+            }
+            let lines: Vec<&str> = text
+                .lines()
+                .skip(start - 1)
+                .take(end.saturating_sub(start).saturating_add(1))
+                .collect();
+            lines.join("\n")
+        }
+    };
+    let mut truncated = false;
+    if body.len() > MAX_FILE_BYTES {
+        body.truncate(qq_core::text::floor_grapheme_boundary(&body, MAX_FILE_BYTES));
+        truncated = true;
+    }
+    let mut block = format!("{}\n{}", file.header(), body.trim_end_matches('\n'));
+    if truncated {
+        block.push_str(&format!(
+            "\n[truncated at {} KB; add a line range for the rest]",
+            MAX_FILE_BYTES / 1024
+        ));
+    }
+    block.push_str("\n</file>");
+    Ok(block)
+}
+
+/// Replace the body of every block for `file` in user messages with `note`.
+/// Returns how many blocks were replaced.
+pub fn stub_blocks(messages: &mut [Message], file: &AddedFile, note: &str) -> usize {
+    let header = file.header();
+    let mut count = 0;
+    for message in messages.iter_mut().filter(|m| m.role == Role::User) {
+        let mut stub = |text: &mut String| {
+            let mut from = 0;
+            while let Some(pos) = text[from..].find(&header) {
+                let body_start = from + pos + header.len();
+                let Some(end) = text[body_start..].find("\n</file>") else {
+                    break;
+                };
+                let body_end = body_start + end;
+                let replacement = format!("\n{}", note);
+                if text[body_start..body_end] != replacement {
+                    text.replace_range(body_start..body_end, &replacement);
+                    count += 1;
+                }
+                from = body_start + replacement.len();
+            }
+        };
+        match &mut message.content {
+            Content::Text(text) => stub(text),
+            Content::Parts(parts) => {
+                for part in parts {
+                    if let ContentPart::Text { text } = part {
+                        stub(text);
+                    }
+                }
+            }
+        }
+    }
+    count
+}
+
+/// Files added with `/add`: which are tracked, and which are queued for the
+/// next message.
+#[derive(Debug, Default)]
+pub struct ContextFiles {
+    /// (file, queued for the next message)
+    tracked: Vec<(AddedFile, bool)>,
+}
+
+impl ContextFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track `file` and queue it for the next message. Adding a tracked
+    /// file again just queues it.
+    pub fn add(&mut self, file: AddedFile) {
+        match self.tracked.iter_mut().find(|(f, _)| *f == file) {
+            Some((_, queued)) => *queued = true,
+            None => self.tracked.push((file, true)),
+        }
+    }
+
+    pub fn tracked(&self) -> impl Iterator<Item = (&AddedFile, bool)> {
+        self.tracked.iter().map(|(f, queued)| (f, *queued))
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &AddedFile> {
+        self.tracked.iter().filter(|(_, q)| *q).map(|(f, _)| f)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracked.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tracked.clear();
+    }
+
+    /// Blocks for the queued files, joined, and the errors for those that
+    /// couldn't be read (which are untracked). Clears the queue.
+    pub fn take_pending(&mut self, root: &Path) -> (String, Vec<String>) {
+        let mut blocks = Vec::new();
+        let mut errors = Vec::new();
+        self.tracked.retain_mut(|(file, queued)| {
+            if !*queued {
+                return true;
+            }
+            *queued = false;
+            match file_block(root, file) {
+                Ok(block) => {
+                    blocks.push(block);
+                    true
+                }
+                Err(e) => {
+                    errors.push(e);
+                    false
+                }
+            }
+        });
+        (blocks.join("\n\n"), errors)
+    }
+
+    /// Queue the current contents of the tracked files whose path or label
+    /// is `target` (all of them if it's empty), stubbing the copies already
+    /// sent. Returns the number of files queued.
+    pub fn refresh(&mut self, target: &str, messages: &mut [Message]) -> usize {
+        let mut count = 0;
+        for (file, queued) in self.tracked.iter_mut().filter(|(f, _)| matches(f, target)) {
+            stub_blocks(messages, file, "[superseded: refreshed in a later message]");
+            *queued = true;
+            count += 1;
+        }
+        count
+    }
+
+    /// Stop tracking the files whose path or label is `target` (all of them
+    /// if it's empty or `all`), stubbing their copies in the history.
+    /// Returns the number of files dropped.
+    pub fn drop_files(&mut self, target: &str, messages: &mut [Message]) -> usize {
+        let target = if target == "all" { "" } else { target };
+        let before = self.tracked.len();
+        self.tracked.retain(|(file, _)| {
+            if !matches(file, target) {
+                return true;
+            }
+            stub_blocks(messages, file, "[removed from context]");
+            false
+        });
+        before - self.tracked.len()
+    }
+}
+
+fn matches(file: &AddedFile, target: &str) -> bool {
+    target.is_empty() || file.path == target || file.label() == target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, lines: usize) {
+        let full = dir.join(path);
+        std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+        let text: String = (1..=lines).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(full, text).unwrap();
+    }
+
+    #[test]
+    fn test_list_and_fuzzy_match() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in [
+            "src/main.rs",
+            "src/tui/app.rs",
+            "src/tui/widgets/activity_panel.rs",
+            "target/debug/app.rs",
+            ".git/config",
+        ] {
+            write(dir.path(), path, 1);
+        }
+        let files = list_files(dir.path());
+        assert_eq!(
+            files,
+            vec![
+                "src/main.rs",
+                "src/tui/app.rs",
+                "src/tui/widgets/activity_panel.rs"
+            ]
+        );
+
+        let names = |query: &str| -> Vec<&str> {
+            fuzzy_matches(query, &files, 10)
+                .into_iter()
+                .map(|i| files[i].as_str())
+                .collect()
+        };
+        assert_eq!(names("app"), vec!["src/tui/app.rs"]);
+        assert_eq!(
+            names("tui"),
+            vec!["src/tui/app.rs", "src/tui/widgets/activity_panel.rs"]
+        );
+        assert_eq!(names("ACT pan")[0], "src/tui/widgets/activity_panel.rs");
+        assert_eq!(names("main"), vec!["src/main.rs"]);
+        assert!(names("zzz").is_empty());
+        assert_eq!(names("").len(), 3);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(""), Ok(None));
+        assert_eq!(parse_range("10-40"), Ok(Some((10, 40))));
+        assert_eq!(parse_range(" 7 "), Ok(Some((7, 7))));
+        assert_eq!(parse_range("10-"), Ok(Some((10, usize::MAX))));
+        assert!(parse_range("40-10").is_err());
+        assert!(parse_range("0-3").is_err());
+        assert!(parse_range("a-b").is_err());
+    }
+
+    #[test]
+    fn test_blocks_refresh_and_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "src/lib.rs", 50);
+        write(dir.path(), "notes.md", 2);
+        let mut files = ContextFiles::new();
+        files.add(AddedFile {
+            path: "src/lib.rs".into(),
+            range: Some((10, 12)),
+        });
+        files.add(AddedFile {
+            path: "notes.md".into(),
+            range: None,
+        });
+        files.add(AddedFile {
+            path: "gone.rs".into(),
+            range: None,
+        });
+
+        let (blocks, errors) = files.take_pending(dir.path());
+        assert_eq!(
+            blocks,
+            "<file path=\"src/lib.rs\" lines=\"10-12\">\nline 10\nline 11\nline 12\n</file>\n\n\
+             <file path=\"notes.md\">\nline 1\nline 2\n</file>"
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(files.tracked().count(), 2);
+        assert_eq!(files.pending_count(), 0);
+
+        let mut messages = vec![
+            Message::user(format!("{}\n\nexplain this", blocks)),
+            Message::assistant("<file path=\"notes.md\">\nline 1\n</file>"),
+        ];
+        assert_eq!(files.refresh("src/lib.rs:10-12", &mut messages), 1);
+        assert_eq!(files.pending().next().unwrap().path, "src/lib.rs");
+        let text = messages[0].content.as_text().unwrap().to_string();
+        assert!(text.starts_with(
+            "<file path=\"src/lib.rs\" lines=\"10-12\">\n\
+             [superseded: refreshed in a later message]\n</file>"
+        ));
+        assert!(text.ends_with("line 2\n</file>\n\nexplain this"));
+
+        assert_eq!(files.drop_files("all", &mut messages), 2);
+        assert!(files.is_empty());
+        let text = messages[0].content.as_text().unwrap();
+        assert!(text.contains("<file path=\"notes.md\">\n[removed from context]\n</file>"));
+        // Only user messages are rewritten
+        assert!(messages[1].content.as_text().unwrap().contains("line 1"));
+    }
+}
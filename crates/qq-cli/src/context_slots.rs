@@ -0,0 +1,418 @@
+//! Context slots: named snippets sent with every request.
+//!
+//! Standing constraints ("never touch the v1 API", the architecture in three
+//! paragraphs) given early in a conversation are compacted away along with
+//! everything else. `/context set <name> <text>` keeps one as a slot instead:
+//! slots are saved per project in `context_slots.json` in the state
+//! directory and placed in the system message of every turn, in the order
+//! they were first set, as far as `[session_memory] context_slot_tokens`
+//! allows. A slot that doesn't fit is left out until others are removed.
+//!
+//! Multi-line text can be given as a heredoc:
+//!
+//! ```text
+//! /context set architecture <<EOF
+//! The API server owns the database; workers only talk to it over gRPC.
+//! EOF
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use qq_tools::{write_atomically, FileLock};
+use serde::{Deserialize, Serialize};
+
+use crate::turn_stats::format_tokens;
+
+pub const CONTEXT_USAGE: &str = "Usage: /context [list] | show <name> | set <name> <text> | \
+     set <name> <<EOF ... EOF | rm <name> | clear";
+
+/// One named snippet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Slot {
+    pub name: String,
+    pub text: String,
+}
+
+impl Slot {
+    /// Rough token count (4 bytes per token), enough for a budget.
+    pub fn tokens(&self) -> usize {
+        (self.name.len() + self.text.len()).div_ceil(4)
+    }
+}
+
+/// The slots of one project, kept in sync with the state file.
+#[derive(Debug, Clone)]
+pub struct ContextSlots {
+    /// Slots of every project; `None` keeps them for this session only
+    path: Option<PathBuf>,
+    project: String,
+    budget_tokens: usize,
+    slots: Vec<Slot>,
+}
+
+/// What `/context` has to show: a listing for the main view, or a status line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotsReply {
+    Listing(String),
+    Status(String),
+}
+
+impl ContextSlots {
+    /// Slots saved for `project` in `path`.
+    pub fn load(path: Option<PathBuf>, project: &str, budget_tokens: usize) -> Self {
+        let slots = path
+            .as_deref()
+            .and_then(|p| read_all(p).remove(project))
+            .unwrap_or_default();
+        Self {
+            path,
+            project: project.to_string(),
+            budget_tokens,
+            slots,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.slots
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.text.as_str())
+    }
+
+    /// Add a slot, or replace the text of an existing one in place.
+    pub fn set(&mut self, name: &str, text: &str) -> Result<(), String> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "Invalid slot name '{}': use letters, digits, '-', '_' or '.'",
+                name
+            ));
+        }
+        let slot = Slot {
+            name: name.to_string(),
+            text: text.trim().to_string(),
+        };
+        self.update(
+            |slots| match slots.iter_mut().find(|s| s.name == slot.name) {
+                Some(existing) => {
+                    let changed = *existing != slot;
+                    *existing = slot;
+                    changed
+                }
+                None => {
+                    slots.push(slot);
+                    true
+                }
+            },
+        );
+        Ok(())
+    }
+
+    /// Remove a slot. Returns whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let existed = self.get(name).is_some();
+        self.update(|slots| {
+            let before = slots.len();
+            slots.retain(|s| s.name != name);
+            slots.len() != before
+        });
+        existed
+    }
+
+    /// Remove every slot of the project. Returns how many there were.
+    pub fn clear(&mut self) -> usize {
+        let count = self.slots.len();
+        self.update(|slots| {
+            let changed = !slots.is_empty();
+            slots.clear();
+            changed
+        });
+        count
+    }
+
+    /// Slots within the budget, in order, and the names of those left out.
+    fn fitting(&self) -> (Vec<&Slot>, Vec<&str>) {
+        let mut left = self.budget_tokens;
+        let mut fit = Vec::new();
+        let mut over = Vec::new();
+        for slot in &self.slots {
+            let tokens = slot.tokens();
+            if tokens <= left {
+                left -= tokens;
+                fit.push(slot);
+            } else {
+                over.push(slot.name.as_str());
+            }
+        }
+        (fit, over)
+    }
+
+    /// The system-message section holding the slots that fit.
+    pub fn system_section(&self) -> Option<String> {
+        let (fit, _) = self.fitting();
+        if fit.is_empty() {
+            return None;
+        }
+        let mut out = String::from(
+            "## Context Slots\n\n\
+             Standing notes the user keeps for this project. They apply to every \
+             turn, including after earlier conversation is compacted.",
+        );
+        for slot in fit {
+            out.push_str(&format!("\n\n### {}\n\n{}", slot.name, slot.text));
+        }
+        Some(out)
+    }
+
+    /// Each slot with its size and first line, and the budget's use.
+    pub fn format_list(&self) -> String {
+        if self.slots.is_empty() {
+            return "No context slots for this project. /context set <name> <text> adds one.\n"
+                .to_string();
+        }
+        let (fit, over) = self.fitting();
+        let used: usize = fit.iter().map(|s| s.tokens()).sum();
+        let mut out = String::new();
+        for slot in &self.slots {
+            let first = slot.text.lines().next().unwrap_or("");
+            let more = if slot.text.lines().nth(1).is_some() {
+                " …"
+            } else {
+                ""
+            };
+            let flag = if over.contains(&slot.name.as_str()) {
+                " (over budget, not sent)"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "  {} · ~{} tok{} — {}{}\n",
+                slot.name,
+                format_tokens(slot.tokens() as u32),
+                flag,
+                first,
+                more
+            ));
+        }
+        out.push_str(&format!(
+            "\n~{} of {} tokens sent with every request\n",
+            format_tokens(used as u32),
+            format_tokens(self.budget_tokens as u32)
+        ));
+        out
+    }
+
+    /// Handle `/context <arg>`.
+    pub fn command(&mut self, arg: &str) -> SlotsReply {
+        let (first_line, rest) = arg
+            .trim_start()
+            .split_once('\n')
+            .unwrap_or((arg.trim(), ""));
+        let (sub, after) = next_word(first_line);
+        let (name, inline) = next_word(after);
+
+        match sub {
+            "" | "list" | "ls" => SlotsReply::Listing(self.format_list()),
+            "show" => match self.get(name) {
+                Some(text) => SlotsReply::Listing(format!("{}\n", text)),
+                None => SlotsReply::Status(format!("No context slot '{}'", name)),
+            },
+            "set" => {
+                let text = match inline.strip_prefix("<<") {
+                    Some(marker) => heredoc_body(marker, rest),
+                    None if rest.is_empty() => inline.to_string(),
+                    None => format!("{}\n{}", inline, rest),
+                };
+                if name.is_empty() || text.trim().is_empty() {
+                    return SlotsReply::Status(CONTEXT_USAGE.to_string());
+                }
+                let replaced = self.get(name).is_some();
+                if let Err(e) = self.set(name, &text) {
+                    return SlotsReply::Status(e);
+                }
+                let (_, over) = self.fitting();
+                let status = if over.contains(&name) {
+                    format!(
+                        "Context slot '{}' saved, but it doesn't fit the {}-token budget and won't be sent",
+                        name,
+                        format_tokens(self.budget_tokens as u32)
+                    )
+                } else {
+                    format!(
+                        "Context slot '{}' {}",
+                        name,
+                        if replaced { "updated" } else { "set" }
+                    )
+                };
+                SlotsReply::Status(status)
+            }
+            "rm" | "remove" | "unset" if !name.is_empty() => {
+                SlotsReply::Status(if self.remove(name) {
+                    format!("Context slot '{}' removed", name)
+                } else {
+                    format!("No context slot '{}'", name)
+                })
+            }
+            "clear" => SlotsReply::Status(format!("Removed {} context slot(s)", self.clear())),
+            _ => SlotsReply::Status(CONTEXT_USAGE.to_string()),
+        }
+    }
+
+    /// Apply `change` to the latest saved slots (other sessions may have
+    /// changed them) and save if it reports a change. Failures are logged;
+    /// the change still holds for this session.
+    fn update(&mut self, change: impl FnOnce(&mut Vec<Slot>) -> bool) {
+        let Some(path) = self.path.clone() else {
+            change(&mut self.slots);
+            return;
+        };
+        let lock = FileLock::acquire(&path);
+        let mut all = read_all(&path);
+        let slots = all.entry(self.project.clone()).or_default();
+        if !change(slots) {
+            self.slots = slots.clone();
+            return;
+        }
+        self.slots = slots.clone();
+        if slots.is_empty() {
+            all.remove(&self.project);
+        }
+        let saved = lock.and_then(|_lock| {
+            let json = serde_json::to_string_pretty(&all).map_err(std::io::Error::other)?;
+            write_atomically(&path, json.as_bytes())
+        });
+        if let Err(e) = saved {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to save context slots");
+        }
+    }
+}
+
+/// Terminator of a `/context set <name> <<MARKER` line typed on its own, so
+/// a line-based prompt can read the body that follows.
+pub fn heredoc_marker(line: &str) -> Option<&str> {
+    if line.contains('\n') {
+        return None;
+    }
+    let (command, rest) = next_word(line);
+    let (sub, rest) = next_word(rest);
+    if command != "/context" || sub != "set" {
+        return None;
+    }
+    let (_, marker) = next_word(rest);
+    let marker = marker.strip_prefix("<<")?.trim();
+    (!marker.is_empty()).then_some(marker)
+}
+
+/// The first word of `text` and what follows it, both trimmed.
+fn next_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+/// Lines of `rest` up to the one that is just `marker`.
+fn heredoc_body(marker: &str, rest: &str) -> String {
+    let marker = marker.trim().trim_matches(|c| c == '\'' || c == '"');
+    rest.lines()
+        .take_while(|line| line.trim() != marker)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Every project's slots. Empty when the file is missing or corrupt.
+fn read_all(path: &Path) -> BTreeMap<String, Vec<Slot>> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+    serde_json::from_str(&text)
+        .inspect_err(|e| {
+            tracing::warn!(path = %path.display(), error = %e, "Ignoring corrupt context slots file");
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_persist_per_project() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("context_slots.json");
+        let mut slots = ContextSlots::load(Some(path.clone()), "/work/app", 2000);
+
+        let reply = slots
+            .command("set architecture <<EOF\nAPI owns the DB.\nWorkers use gRPC.\nEOF\nignored");
+        assert_eq!(
+            reply,
+            SlotsReply::Status("Context slot 'architecture' set".to_string())
+        );
+        assert_eq!(
+            slots.get("architecture"),
+            Some("API owns the DB.\nWorkers use gRPC.")
+        );
+        slots.command("set style Keep functions short");
+        assert_eq!(
+            slots.command("set style No unwrap in library code"),
+            SlotsReply::Status("Context slot 'style' updated".to_string())
+        );
+
+        // Another session in the project sees them, in the order first set
+        let other = ContextSlots::load(Some(path.clone()), "/work/app", 2000);
+        let section = other.system_section().unwrap();
+        assert!(section.starts_with("## Context Slots"));
+        assert!(section.ends_with(
+            "### architecture\n\nAPI owns the DB.\nWorkers use gRPC.\n\n### style\n\nNo unwrap in library code"
+        ));
+        assert!(ContextSlots::load(Some(path.clone()), "/work/other", 2000)
+            .system_section()
+            .is_none());
+
+        assert_eq!(
+            slots.command("rm style"),
+            SlotsReply::Status("Context slot 'style' removed".to_string())
+        );
+        assert_eq!(
+            slots.command("clear"),
+            SlotsReply::Status("Removed 1 context slot(s)".to_string())
+        );
+        assert!(ContextSlots::load(Some(path), "/work/app", 2000)
+            .system_section()
+            .is_none());
+        assert!(
+            matches!(slots.command("set bad/name x"), SlotsReply::Status(s) if s.starts_with("Invalid"))
+        );
+    }
+
+    #[test]
+    fn test_slots_over_budget_are_left_out() {
+        let mut slots = ContextSlots::load(None, "/work/app", 10);
+        slots.set("a", "short").unwrap();
+        let reply = slots.command(&format!("set big {}", "x".repeat(100)));
+        assert!(matches!(reply, SlotsReply::Status(s) if s.contains("won't be sent")));
+        slots.set("b", "tiny").unwrap();
+
+        let section = slots.system_section().unwrap();
+        assert!(section.contains("### a") && section.contains("### b"));
+        assert!(!section.contains("### big"));
+        let list = slots.format_list();
+        assert!(list.contains("big · ~26 tok (over budget, not sent)"));
+        assert!(list.contains("~4 of 10 tokens sent"));
+    }
+
+    #[test]
+    fn test_heredoc_marker() {
+        assert_eq!(heredoc_marker("/context set arch <<EOF"), Some("EOF"));
+        assert_eq!(heredoc_marker("/context set arch << END "), Some("END"));
+        assert_eq!(heredoc_marker("/context set arch some text"), None);
+        assert_eq!(heredoc_marker("/context set arch <<EOF\nbody\nEOF"), None);
+        assert_eq!(heredoc_marker("/context list"), None);
+        assert_eq!(heredoc_marker("/context settings <<EOF"), None);
+    }
+}
@@ -10,7 +10,7 @@ use std::sync::Mutex;
 use chrono::Utc;
 use serde::Serialize;
 
-use qq_core::Message;
+use qq_core::{Message, MessageMetadata};
 
 /// Debug logger that writes JSON lines to a file.
 pub struct DebugLogger {
@@ -184,13 +184,15 @@ struct MessageSummary {
     content_preview: String,
     has_tool_calls: bool,
     tool_call_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<MessageMetadata>,
 }
 
 impl From<&Message> for MessageSummary {
     fn from(msg: &Message) -> Self {
         let content = msg.content.to_string_lossy();
         let preview = if content.len() > 100 {
-            format!("{}...", &content[..100])
+            format!("{}...", qq_core::text::truncate_bytes(&content, 100))
         } else {
             content.clone()
         };
@@ -201,6 +203,7 @@ impl From<&Message> for MessageSummary {
             content_preview: preview,
             has_tool_calls: !msg.tool_calls.is_empty(),
             tool_call_count: msg.tool_calls.len(),
+            metadata: msg.metadata.clone(),
         }
     }
 }
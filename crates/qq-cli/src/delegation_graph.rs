@@ -0,0 +1,469 @@
+//! Record of which agent delegated to which during a session.
+//!
+//! Every sub-agent run becomes a node under the run that called it, with its
+//! task, timing, token usage and result. The graph is saved to
+//! `<state>/graphs/<session id>.json` as the session goes, so `qq graph`
+//! can export it as DOT or Mermaid afterwards, and interactive sessions
+//! print an ASCII summary when they end.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use qq_core::Usage;
+
+use crate::agents::AgentReport;
+use crate::turn_stats::{format_duration, format_tokens};
+
+/// Longest task or result preview kept per node, in characters.
+const PREVIEW_CHARS: usize = 120;
+
+/// Where a delegation stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeStatus {
+    /// Still running, or the session ended before it finished
+    Running,
+    Ok,
+    Error,
+}
+
+/// One sub-agent run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DelegationNode {
+    pub id: usize,
+    /// The run that delegated; `None` for delegations from the session agent
+    pub parent: Option<usize>,
+    pub agent: String,
+    /// Scope path, e.g. `pm/coder/explore`
+    pub scope: String,
+    /// First line of the task, shortened
+    pub task: String,
+    pub started: DateTime<Local>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub llm_calls: u32,
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+    pub status: NodeStatus,
+    /// Report summary or first line of the result, shortened
+    #[serde(default)]
+    pub result: Option<String>,
+}
+
+impl DelegationNode {
+    pub fn total_tokens(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// One-line description: agent, duration, tokens, status.
+    fn label(&self) -> String {
+        let mut parts = vec![self.agent.clone()];
+        if let Some(ms) = self.duration_ms {
+            parts.push(format_duration(Duration::from_millis(ms)));
+        }
+        if self.total_tokens() > 0 {
+            parts.push(format!("{} tok", format_tokens(self.total_tokens())));
+        }
+        parts.push(
+            match self.status {
+                NodeStatus::Running => "unfinished",
+                NodeStatus::Ok => "ok",
+                NodeStatus::Error => "error",
+            }
+            .to_string(),
+        );
+        parts.join(" · ")
+    }
+}
+
+/// Serialized form of a graph.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GraphFile {
+    nodes: Vec<DelegationNode>,
+}
+
+/// Delegation graph of one session, shared by every agent tool through the
+/// event bus.
+#[derive(Debug, Default)]
+pub struct DelegationGraph {
+    nodes: Mutex<Vec<DelegationNode>>,
+    /// Saved here after every change, when set
+    path: Option<PathBuf>,
+}
+
+impl DelegationGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save the graph to `path` as it changes.
+    pub fn with_file(mut self, path: Option<PathBuf>) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Read a graph saved by a session.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let file: GraphFile = serde_json::from_slice(&bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(Self {
+            nodes: Mutex::new(file.nodes),
+            path: None,
+        })
+    }
+
+    pub fn nodes(&self) -> Vec<DelegationNode> {
+        self.lock().clone()
+    }
+
+    /// Record the start of a delegation at `scope` and return its node id.
+    /// The parent is the latest unfinished run at the enclosing scope.
+    pub fn start(&self, scope: &str, agent: &str, task: &str) -> usize {
+        let id = {
+            let mut nodes = self.lock();
+            let parent = scope.rsplit_once('/').and_then(|(parent_scope, _)| {
+                nodes
+                    .iter()
+                    .rev()
+                    .find(|n| n.scope == parent_scope && n.status == NodeStatus::Running)
+                    .map(|n| n.id)
+            });
+            let id = nodes.len();
+            nodes.push(DelegationNode {
+                id,
+                parent,
+                agent: agent.to_string(),
+                scope: scope.to_string(),
+                task: preview(task),
+                started: Local::now(),
+                duration_ms: None,
+                llm_calls: 0,
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                status: NodeStatus::Running,
+                result: None,
+            });
+            id
+        };
+        self.save();
+        id
+    }
+
+    /// Count one completion made by the run `id`.
+    pub fn add_usage(&self, id: usize, usage: &Usage) {
+        if let Some(node) = self.lock().get_mut(id) {
+            node.llm_calls += 1;
+            node.prompt_tokens += usage.prompt_tokens;
+            node.completion_tokens += usage.completion_tokens;
+        }
+    }
+
+    /// Record how the run `id` ended.
+    pub fn finish(&self, id: usize, is_error: bool, result: &str) {
+        {
+            let mut nodes = self.lock();
+            let Some(node) = nodes.get_mut(id) else {
+                return;
+            };
+            let elapsed = (Local::now() - node.started).to_std().unwrap_or_default();
+            node.duration_ms = Some(elapsed.as_millis() as u64);
+            node.status = if is_error {
+                NodeStatus::Error
+            } else {
+                NodeStatus::Ok
+            };
+            let summary = AgentReport::parse(result)
+                .map(|report| report.summary)
+                .unwrap_or_else(|| result.to_string());
+            node.result = Some(preview(&summary)).filter(|s| !s.is_empty());
+        }
+        self.save();
+    }
+
+    /// Graphviz DOT source.
+    pub fn to_dot(&self) -> String {
+        let nodes = self.lock();
+        let mut out =
+            String::from("digraph delegation {\n    rankdir=LR;\n    node [shape=box];\n");
+        let _ = writeln!(
+            out,
+            "    root [label=\"{}\"];",
+            dot_escape(root_name(&nodes))
+        );
+        for node in nodes.iter() {
+            let mut label = node.label();
+            if let Some(ref result) = node.result {
+                label = format!("{}\n{}", label, result);
+            }
+            let color = match node.status {
+                NodeStatus::Error => ", color=red",
+                NodeStatus::Running => ", style=dashed",
+                NodeStatus::Ok => "",
+            };
+            let _ = writeln!(
+                out,
+                "    n{} [label=\"{}\"{}];",
+                node.id,
+                dot_escape(&label),
+                color
+            );
+        }
+        for node in nodes.iter() {
+            let _ = writeln!(
+                out,
+                "    {} -> n{} [label=\"{}\"];",
+                parent_key(node),
+                node.id,
+                dot_escape(&node.task)
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Mermaid flowchart source.
+    pub fn to_mermaid(&self) -> String {
+        let nodes = self.lock();
+        let mut out = String::from("flowchart LR\n");
+        let _ = writeln!(out, "    root[\"{}\"]", mermaid_escape(root_name(&nodes)));
+        for node in nodes.iter() {
+            let mut label = mermaid_escape(&node.label());
+            if let Some(ref result) = node.result {
+                label = format!("{}<br/>{}", label, mermaid_escape(result));
+            }
+            let _ = writeln!(out, "    n{}[\"{}\"]", node.id, label);
+        }
+        for node in nodes.iter() {
+            let _ = writeln!(
+                out,
+                "    {} -->|\"{}\"| n{}",
+                parent_key(node),
+                mermaid_escape(&node.task),
+                node.id
+            );
+        }
+        for node in nodes.iter().filter(|n| n.status == NodeStatus::Error) {
+            let _ = writeln!(out, "    style n{} stroke:#d33", node.id);
+        }
+        out
+    }
+
+    /// Indented tree with one line per delegation and a totals header, or
+    /// `None` when nothing was delegated.
+    pub fn render_ascii(&self) -> Option<String> {
+        let nodes = self.lock();
+        if nodes.is_empty() {
+            return None;
+        }
+        let tokens: u32 = nodes.iter().map(DelegationNode::total_tokens).sum();
+        let mut out = format!(
+            "Delegations: {} run{}, {} tokens\n{}\n",
+            nodes.len(),
+            if nodes.len() == 1 { "" } else { "s" },
+            format_tokens(tokens),
+            root_name(&nodes)
+        );
+        render_children(&nodes, None, "", &mut out);
+        Some(out)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<DelegationNode>> {
+        self.nodes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Best-effort: a failed save is logged and the session carries on.
+    fn save(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        let file = GraphFile {
+            nodes: self.nodes(),
+        };
+        let written = serde_json::to_vec_pretty(&file)
+            .map_err(std::io::Error::other)
+            .and_then(|json| qq_tools::write_atomically(path, &json));
+        if let Err(e) = written {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to save delegation graph");
+        }
+    }
+}
+
+/// Find a saved graph: a file path, a session id or unique prefix of one in
+/// `dir`, or the most recent graph when `session` is `None`.
+pub fn find_graph(dir: Option<&Path>, session: Option<&str>) -> anyhow::Result<PathBuf> {
+    crate::active_sessions::find_saved(dir, session, "delegation graph")
+}
+
+fn render_children(
+    nodes: &[DelegationNode],
+    parent: Option<usize>,
+    indent: &str,
+    out: &mut String,
+) {
+    let children: Vec<&DelegationNode> = nodes.iter().filter(|n| n.parent == parent).collect();
+    for (i, node) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let _ = write!(
+            out,
+            "{}{}{}",
+            indent,
+            if last { "└─ " } else { "├─ " },
+            node.label()
+        );
+        if let Some(ref result) = node.result {
+            let _ = write!(out, " — {}", result);
+        }
+        out.push('\n');
+        let child_indent = format!("{}{}", indent, if last { "   " } else { "│  " });
+        render_children(nodes, Some(node.id), &child_indent, out);
+    }
+}
+
+/// Name of the session agent, from the first scope segment.
+fn root_name(nodes: &[DelegationNode]) -> &str {
+    nodes
+        .first()
+        .and_then(|n| n.scope.split('/').next())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("session")
+}
+
+fn parent_key(node: &DelegationNode) -> String {
+    match node.parent {
+        Some(parent) => format!("n{}", parent),
+        None => "root".to_string(),
+    }
+}
+
+/// First non-empty line of `text`, cut to [`PREVIEW_CHARS`].
+fn preview(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    if line.chars().count() <= PREVIEW_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(PREVIEW_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+        .replace('|', "#124;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> DelegationGraph {
+        let graph = DelegationGraph::new();
+        let research = graph.start(
+            "pm/researcher",
+            "researcher",
+            "Find the \"upload\" code\nmore",
+        );
+        let explore = graph.start("pm/researcher/explore", "explore", "List src/");
+        graph.add_usage(explore, &Usage::new(1_500, 500));
+        graph.finish(explore, false, "src/upload.rs\nsrc/lib.rs");
+        graph.finish(
+            research,
+            false,
+            r#"{"summary": "Upload lives in src/upload.rs", "confidence": "high"}"#,
+        );
+        let coder = graph.start("pm/coder", "coder", "Fix it");
+        graph.finish(coder, true, "Agent exceeded max iterations");
+        graph
+    }
+
+    #[test]
+    fn test_parents_and_results() {
+        let nodes = sample().nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].parent, None);
+        assert_eq!(nodes[1].parent, Some(0));
+        assert_eq!(nodes[2].parent, None);
+        assert_eq!(nodes[0].task, "Find the \"upload\" code");
+        assert_eq!(
+            nodes[0].result.as_deref(),
+            Some("Upload lives in src/upload.rs")
+        );
+        assert_eq!(nodes[1].result.as_deref(), Some("src/upload.rs"));
+        assert_eq!((nodes[1].llm_calls, nodes[1].total_tokens()), (1, 2_000));
+        assert_eq!(nodes[2].status, NodeStatus::Error);
+        assert!(nodes.iter().all(|n| n.duration_ms.is_some()));
+    }
+
+    #[test]
+    fn test_exports() {
+        let graph = sample();
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph delegation {"));
+        assert!(dot.contains("root [label=\"pm\"];"));
+        assert!(dot.contains("n0 -> n1 [label=\"List src/\"];"));
+        assert!(dot.contains("root -> n0 [label=\"Find the \\\"upload\\\" code\"];"));
+        assert!(dot.contains(", color=red];"));
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n    root[\"pm\"]\n"));
+        assert!(mermaid.contains("root -->|\"Find the #quot;upload#quot; code\"| n0"));
+        assert!(mermaid.contains("style n2 stroke:#d33"));
+
+        let ascii = graph.render_ascii().unwrap();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines[0], "Delegations: 3 runs, 2.0k tokens");
+        assert_eq!(lines[1], "pm");
+        assert!(lines[2].starts_with("├─ researcher · "));
+        assert!(lines[2].ends_with("ok — Upload lives in src/upload.rs"));
+        assert!(lines[3].starts_with("│  └─ explore · "));
+        assert!(lines[3].contains("2.0k tok"));
+        assert!(lines[4].starts_with("└─ coder · "));
+        assert!(DelegationGraph::new().render_ascii().is_none());
+    }
+
+    #[test]
+    fn test_save_load_and_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("20260301-101500-42.json");
+        let graph = DelegationGraph::new().with_file(Some(path.clone()));
+        let node = graph.start("pm/writer", "writer", "Draft the notes");
+        graph.finish(node, false, "Done");
+        std::fs::write(dir.path().join("20260302-090000-7.json"), "{\"nodes\": []}").unwrap();
+
+        let loaded = DelegationGraph::load(&path).unwrap();
+        assert_eq!(loaded.nodes(), graph.nodes());
+
+        let found = |session| find_graph(Some(dir.path()), session).map_err(|e| e.to_string());
+        assert_eq!(found(Some("20260301")).unwrap(), path);
+        assert_eq!(
+            found(None).unwrap(),
+            dir.path().join("20260302-090000-7.json")
+        );
+        assert_eq!(found(Some(path.to_str().unwrap())).unwrap(), path);
+        assert!(found(Some("2026"))
+            .unwrap_err()
+            .contains("matches 2 sessions"));
+        assert!(found(Some("1999"))
+            .unwrap_err()
+            .contains("No delegation graph"));
+    }
+}
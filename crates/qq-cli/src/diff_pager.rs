@@ -0,0 +1,200 @@
+//! Diff previews for readline-mode approval prompts.
+//!
+//! When a write awaiting approval comes with a preview (see
+//! [`ApprovalRequest::preview`](qq_tools::ApprovalRequest)), the prompt shows
+//! it before asking. A diff that fits on screen is printed inline; a longer
+//! one opens a `less`-style pager on the alternate screen. Both are colored
+//! like the TUI's `/diff` overlay. Without a terminal the diff is printed
+//! uncolored.
+
+use std::io::{self, Write};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{
+    self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+
+use qq_core::text::truncate_width;
+
+use crate::tui::diff_view::{DiffLineKind, DiffView};
+
+/// Rows kept free below an inline diff for the approval prompt.
+const PROMPT_ROWS: usize = 8;
+
+/// Keys listed in the pager's status line.
+const PAGER_HINT: &str = "Space/b page, j/k line, n/p file, g/G top/end, q done";
+
+/// Show `diff` on stderr: inline when it fits, paged otherwise.
+pub fn show_diff(diff: &str) -> io::Result<()> {
+    let view = DiffView::new(diff);
+    if !atty::is(atty::Stream::Stderr) || !atty::is(atty::Stream::Stdin) {
+        let mut stderr = io::stderr();
+        for line in &view.lines {
+            writeln!(stderr, "  {}", line.text)?;
+        }
+        return Ok(());
+    }
+    let rows = terminal::size().map(|(_, h)| h as usize).unwrap_or(24);
+    if view.lines.len() + PROMPT_ROWS <= rows {
+        print_inline(&view)
+    } else {
+        page(view)
+    }
+}
+
+fn color(kind: DiffLineKind) -> Color {
+    match kind {
+        DiffLineKind::File => Color::Yellow,
+        DiffLineKind::Meta => Color::DarkGrey,
+        DiffLineKind::Hunk => Color::Cyan,
+        DiffLineKind::Added => Color::Green,
+        DiffLineKind::Removed => Color::Red,
+        DiffLineKind::Context => Color::Reset,
+    }
+}
+
+fn print_inline(view: &DiffView) -> io::Result<()> {
+    let mut stderr = io::stderr();
+    let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+    for line in &view.lines {
+        queue!(
+            stderr,
+            SetForegroundColor(color(line.kind)),
+            Print(format!("  {}", truncate_width(&line.text, width.saturating_sub(2)))),
+            ResetColor,
+            Print("\n")
+        )?;
+    }
+    stderr.flush()
+}
+
+/// Leaves raw mode and the alternate screen however the pager exits.
+struct PagerGuard;
+
+impl PagerGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = PagerGuard;
+        execute!(io::stderr(), EnterAlternateScreen, Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for PagerGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Page through `view` until the user quits.
+fn page(mut view: DiffView) -> io::Result<()> {
+    let _guard = PagerGuard::enter()?;
+    loop {
+        let (width, height) = terminal::size()?;
+        let rows = (height as usize).saturating_sub(1).max(1);
+        draw(&view, width as usize, rows)?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Release && !handle_key(&mut view, key, rows) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn draw(view: &DiffView, width: usize, rows: usize) -> io::Result<()> {
+    let mut stderr = io::stderr();
+    queue!(stderr, Clear(ClearType::All))?;
+    for (row, line) in view.lines.iter().skip(view.scroll).take(rows).enumerate() {
+        queue!(
+            stderr,
+            MoveTo(0, row as u16),
+            SetForegroundColor(color(line.kind)),
+            Print(truncate_width(&line.text, width)),
+            ResetColor
+        )?;
+    }
+    let file = view
+        .current_file()
+        .map(|path| format!(" — {}", path))
+        .unwrap_or_default();
+    let status = format!(
+        " {}{}  line {}/{}  {} ",
+        view.title(),
+        file,
+        (view.scroll + 1).min(view.lines.len()),
+        view.lines.len(),
+        PAGER_HINT
+    );
+    queue!(
+        stderr,
+        MoveTo(0, rows as u16),
+        SetAttribute(Attribute::Reverse),
+        Print(truncate_width(&status, width)),
+        SetAttribute(Attribute::Reset)
+    )?;
+    stderr.flush()
+}
+
+/// Apply one key to the pager; `false` when it closes the pager.
+fn handle_key(view: &mut DiffView, key: KeyEvent, rows: usize) -> bool {
+    let page = rows.max(1) as isize;
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+        KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => return false,
+        KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter => view.scroll_by(1),
+        KeyCode::Char('k') | KeyCode::Up => view.scroll_by(-1),
+        KeyCode::Char(' ') | KeyCode::Char('f') | KeyCode::PageDown => view.scroll_by(page),
+        KeyCode::Char('b') | KeyCode::PageUp => view.scroll_by(-page),
+        KeyCode::Char('d') => view.scroll_by(page / 2),
+        KeyCode::Char('u') => view.scroll_by(-page / 2),
+        KeyCode::Char('g') | KeyCode::Home => view.scroll = 0,
+        KeyCode::Char('G') | KeyCode::End => view.scroll_to_end(),
+        KeyCode::Char('n') => view.next_file(),
+        KeyCode::Char('p') => view.prev_file(),
+        _ => {}
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(view: &mut DiffView, code: KeyCode) -> bool {
+        handle_key(view, KeyEvent::new(code, KeyModifiers::NONE), 10)
+    }
+
+    #[test]
+    fn test_pager_keys() {
+        let mut diff = String::from("diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1,30 +1,30 @@\n");
+        diff.extend((0..30).map(|i| format!("+line {}\n", i)));
+        diff.push_str("diff --git a/b.rs b/b.rs\n--- a/b.rs\n+++ b/b.rs\n@@ -1 +1 @@\n-x\n+y\n");
+        let mut view = DiffView::new(&diff);
+
+        assert!(press(&mut view, KeyCode::Char(' ')));
+        assert_eq!(view.scroll, 10);
+        press(&mut view, KeyCode::Char('k'));
+        press(&mut view, KeyCode::Char('b'));
+        assert_eq!(view.scroll, 0);
+        press(&mut view, KeyCode::Char('d'));
+        assert_eq!(view.scroll, 5);
+        press(&mut view, KeyCode::Char('n'));
+        assert_eq!(view.current_file(), Some("b.rs"));
+        press(&mut view, KeyCode::Char('p'));
+        assert_eq!(view.scroll, 0);
+        press(&mut view, KeyCode::Char('G'));
+        assert_eq!(view.scroll, view.lines.len() - 1);
+
+        assert!(!press(&mut view, KeyCode::Char('q')));
+        assert!(!press(&mut view, KeyCode::Esc));
+        assert!(!handle_key(
+            &mut view,
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            10
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+//! `qq doctor`: check that a setup works before a session depends on it.
+//!
+//! A bad API key, an unreachable base URL, or a sandbox blocked by AppArmor
+//! otherwise shows up mid-session as an opaque provider or tool error. The
+//! doctor walks the same steps a session takes — config, policy, profile,
+//! one small request per provider, sandbox detection, the knowledge base,
+//! and building the tool registry — and prints each result with a hint on
+//! how to fix it.
+//!
+//! ```text
+//! Providers
+//!   ok    openai: gpt-4o answered in 0.8s
+//!   FAIL  anthropic: authentication failed
+//!         help: check api_key under [providers.anthropic] or set ANTHROPIC_API_KEY
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use crate::config_check::{Issue, Severity};
+
+/// Sysctl set when AppArmor blocks unprivileged user namespaces.
+const APPARMOR_USERNS_SYSCTL: &str = "/proc/sys/kernel/apparmor_restrict_unprivileged_userns";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// The result of one check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub status: Status,
+    /// What was checked, e.g. a provider name
+    pub subject: String,
+    pub detail: String,
+    /// How to fix it
+    pub hint: Option<String>,
+}
+
+impl Check {
+    pub fn ok(subject: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self::new(Status::Ok, subject, detail, None)
+    }
+
+    pub fn warn(subject: impl Into<String>, detail: impl Into<String>, hint: Option<String>) -> Self {
+        Self::new(Status::Warn, subject, detail, hint)
+    }
+
+    pub fn fail(subject: impl Into<String>, detail: impl Into<String>, hint: Option<String>) -> Self {
+        Self::new(Status::Fail, subject, detail, hint)
+    }
+
+    fn new(status: Status, subject: impl Into<String>, detail: impl Into<String>, hint: Option<String>) -> Self {
+        Self {
+            status,
+            subject: subject.into(),
+            detail: detail.into(),
+            hint,
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        write!(f, "  {:<5} {}: {}", label, self.subject, self.detail)?;
+        if let Some(ref hint) = self.hint {
+            write!(f, "\n        help: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks grouped under headings, printed as they're added.
+#[derive(Default)]
+pub struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    /// Start a group of checks.
+    pub fn section(&mut self, title: &str) {
+        if !self.checks.is_empty() {
+            println!();
+        }
+        println!("{}", title);
+    }
+
+    pub fn add(&mut self, check: Check) {
+        println!("{}", check);
+        self.checks.push(check);
+    }
+
+    /// Number of checks with `status`.
+    pub fn count(&self, status: Status) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    /// Fail if any check failed.
+    pub fn finish(&self) -> anyhow::Result<()> {
+        let (failed, warned) = (self.count(Status::Fail), self.count(Status::Warn));
+        println!();
+        if failed > 0 {
+            anyhow::bail!("{} check{} failed, {} warning{}", failed, plural(failed), warned, plural(warned));
+        }
+        match warned {
+            0 => println!("All checks passed"),
+            n => println!("All checks passed with {} warning{}", n, plural(n)),
+        }
+        Ok(())
+    }
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Summarize `qq config check` issues for one file.
+pub fn config_file(path: &Path, issues: &[Issue]) -> Check {
+    let subject = path.display().to_string();
+    let errors = issues.iter().filter(|i| i.severity == Severity::Error).count();
+    let warnings = issues.len() - errors;
+    let first = issues
+        .iter()
+        .find(|i| i.severity == Severity::Error)
+        .or_else(|| issues.first())
+        .map(|issue| issue.to_string().lines().next().unwrap_or_default().to_string());
+    let hint = Some("run `qq config check` for every issue with its line".to_string());
+    match first {
+        None => Check::ok(subject, "valid"),
+        Some(first) if errors > 0 => Check::fail(
+            subject,
+            format!("{} error{} ({})", errors, plural(errors), first),
+            hint,
+        ),
+        Some(first) => Check::warn(
+            subject,
+            format!("{} warning{} ({})", warnings, plural(warnings), first),
+            hint,
+        ),
+    }
+}
+
+/// What a failed test request says about the provider's setup.
+pub fn provider_error(name: &str, model: &str, base_url: Option<&str>, error: &qq_core::Error) -> Check {
+    use qq_core::Error;
+
+    let endpoint = base_url.unwrap_or("the provider's API");
+    match error {
+        Error::Auth(_) | Error::Api { status: 401 | 403, .. } => Check::fail(
+            name,
+            format!("authentication failed ({})", error),
+            Some(format!(
+                "check api_key under [providers.{}] or set {}_API_KEY",
+                name,
+                name.to_uppercase()
+            )),
+        ),
+        Error::ModelNotFound(_) | Error::Api { status: 404, .. } => Check::fail(
+            name,
+            format!("model '{}' not found ({})", model, error),
+            Some(format!(
+                "set default_model under [providers.{}] to a model {} serves",
+                name, endpoint
+            )),
+        ),
+        Error::Network(_) | Error::Timeout(_) => Check::fail(
+            name,
+            format!("could not reach {} ({})", endpoint, error),
+            Some(format!(
+                "check base_url under [providers.{}], that the server is running, and any proxy settings",
+                name
+            )),
+        ),
+        Error::RateLimit(_) => Check::warn(
+            name,
+            format!("reachable and authenticated, but rate limited ({})", error),
+            None,
+        ),
+        Error::Config(_) => Check::fail(name, error.to_string(), None),
+        _ => Check::fail(
+            name,
+            format!("test request failed ({})", error),
+            Some("rerun with --debug for the full request and response".to_string()),
+        ),
+    }
+}
+
+/// Whether AppArmor blocks unprivileged user namespaces; `None` when the
+/// kernel has no such setting.
+pub fn apparmor_restricts_userns() -> Option<bool> {
+    std::fs::read_to_string(APPARMOR_USERNS_SYSCTL)
+        .ok()
+        .map(|s| s.trim() == "1")
+}
+
+/// Check the knowledge base file at `path`.
+pub fn knowledge_base(path: &Path) -> Check {
+    let subject = path.display().to_string();
+    if !path.exists() {
+        return Check::ok(subject, "not created yet");
+    }
+    match qq_tools::KnowledgeStore::check(path) {
+        Ok((entries, 0)) => Check::ok(subject, format!("{} entries", entries)),
+        Ok((entries, corrupt)) => Check::warn(
+            subject,
+            format!("{} entries, {} corrupt line{} skipped", entries, corrupt, plural(corrupt)),
+            Some("run `qq memory compact` to drop them".to_string()),
+        ),
+        Err(e) => Check::fail(
+            subject,
+            format!("cannot read ({})", e),
+            Some("check the file's permissions, or move it aside to start a new knowledge base".to_string()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_errors_get_actionable_hints() {
+        let auth = provider_error("anthropic", "claude", None, &qq_core::Error::auth("invalid x-api-key"));
+        assert_eq!(auth.status, Status::Fail);
+        assert!(auth.hint.unwrap().contains("ANTHROPIC_API_KEY"));
+
+        let missing = provider_error("local", "qwen3", Some("http://localhost:8080/v1"), &qq_core::Error::api(404, "no such model"));
+        assert!(missing.detail.contains("model 'qwen3' not found"));
+        assert!(missing.hint.unwrap().contains("http://localhost:8080/v1"));
+
+        let down = provider_error("local", "qwen3", Some("http://localhost:8080/v1"), &qq_core::Error::network("connection refused"));
+        assert!(down.detail.starts_with("could not reach http://localhost:8080/v1"));
+
+        let limited = provider_error("openai", "gpt-4o", None, &qq_core::Error::rate_limit("slow down"));
+        assert_eq!(limited.status, Status::Warn);
+    }
+
+    #[test]
+    fn test_knowledge_base_reports_corrupt_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("knowledge.jsonl");
+        assert_eq!(knowledge_base(&path).status, Status::Ok);
+
+        std::fs::write(&path, "{\"id\":1,\"proj").unwrap();
+        let check = knowledge_base(&path);
+        assert_eq!(check.status, Status::Warn);
+        assert_eq!(check.detail, "0 entries, 1 corrupt line skipped");
+    }
+
+    #[test]
+    fn test_check_display() {
+        let check = Check::fail("openai", "authentication failed", Some("set OPENAI_API_KEY".into()));
+        assert_eq!(
+            check.to_string(),
+            "  FAIL  openai: authentication failed\n        help: set OPENAI_API_KEY"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Files dropped onto the terminal or pasted as paths.
+//!
+//! Dragging a file into a terminal pastes its path, in whatever form the
+//! terminal likes: quoted (`'/tmp/a b.png'`), backslash-escaped
+//! (`/tmp/a\ b.png`), or as a `file://` URI, several separated by spaces or
+//! newlines. File managers put the same URIs on the clipboard. Without help
+//! that text lands in the prompt and the model gets a path it may not be able
+//! to read. [`DroppedFiles::parse`] recognizes a paste made only of existing
+//! paths, and after the user confirms, [`DroppedFiles::ingest`] turns them
+//! into attachments: images as image parts, text files as `/add` context
+//! files.
+
+use std::path::{Path, PathBuf};
+
+use qq_core::ImageData;
+
+use crate::context_files::AddedFile;
+
+/// Pastes naming more files than this are left as text.
+const MAX_DROPPED_FILES: usize = 20;
+
+/// Largest image attached, as with `/attach`.
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Bytes read to tell images, text and other binaries apart.
+const SNIFF_BYTES: u64 = 8192;
+
+/// Paths recognized in a paste, with the paste itself for when the user
+/// declines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedFiles {
+    pub paths: Vec<PathBuf>,
+    pub text: String,
+}
+
+/// A dropped file, ready to queue for the next message.
+#[derive(Debug)]
+pub enum Dropped {
+    Image(ImageData),
+    File(AddedFile),
+}
+
+impl DroppedFiles {
+    /// The files named by `text`, if it consists only of paths or
+    /// `file://` URIs of existing files.
+    pub fn parse(text: &str) -> Option<Self> {
+        let words = split_words(text, !cfg!(windows))?;
+        if words.is_empty() || words.len() > MAX_DROPPED_FILES {
+            return None;
+        }
+        let mut paths = Vec::new();
+        for word in words {
+            let path = match word.strip_prefix("file://") {
+                Some(uri) => uri_path(uri)?,
+                None => crate::config::expand_path(&word),
+            };
+            if !path.is_absolute() || !path.is_file() {
+                return None;
+            }
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        Some(Self {
+            paths,
+            text: text.to_string(),
+        })
+    }
+
+    /// The confirmation question: `Attach 2 dropped files (a.png, notes.md)? [Y/n] `
+    pub fn prompt(&self) -> String {
+        let names: Vec<String> = self
+            .paths
+            .iter()
+            .map(|p| {
+                p.file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| p.display().to_string())
+            })
+            .collect();
+        format!(
+            "Attach {} dropped file{} ({})? [Y/n] ",
+            self.paths.len(),
+            if self.paths.len() == 1 { "" } else { "s" },
+            qq_core::text::ellipsize(&names.join(", "), 80)
+        )
+    }
+
+    /// Read each file as an attachment; paths under `root` become relative.
+    /// Files that can't be attached are reported as errors.
+    pub fn ingest(&self, root: &Path) -> (Vec<Dropped>, Vec<String>) {
+        let mut dropped = Vec::new();
+        let mut errors = Vec::new();
+        for path in &self.paths {
+            match ingest_file(path, root) {
+                Ok(file) => dropped.push(file),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        (dropped, errors)
+    }
+}
+
+/// Whether an answer to [`DroppedFiles::prompt`] is yes (the default).
+pub fn accepts(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes")
+}
+
+/// One line saying what was attached, for the status bar or terminal.
+pub fn summary(images: usize, files: usize, errors: &[String]) -> String {
+    let mut parts = Vec::new();
+    if images > 0 {
+        parts.push(format!("{} image{}", images, if images == 1 { "" } else { "s" }));
+    }
+    if files > 0 {
+        parts.push(format!("{} file{}", files, if files == 1 { "" } else { "s" }));
+    }
+    let mut line = if parts.is_empty() {
+        "Nothing attached".to_string()
+    } else {
+        format!("Attached {} to the next message", parts.join(" and "))
+    };
+    if !errors.is_empty() {
+        line.push_str(&format!("; skipped {}", errors.join("; ")));
+    }
+    line
+}
+
+fn ingest_file(path: &Path, root: &Path) -> Result<Dropped, String> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let size = file.metadata().map_err(|e| e.to_string())?.len();
+    let mut head = Vec::with_capacity(SNIFF_BYTES as usize);
+    file.by_ref()
+        .take(SNIFF_BYTES)
+        .read_to_end(&mut head)
+        .map_err(|e| e.to_string())?;
+
+    // Image formats are recognized from their header
+    if ImageData::from_bytes(&head).is_ok() {
+        if size > MAX_IMAGE_BYTES {
+            return Err("image too large (max 20MB)".to_string());
+        }
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        return ImageData::from_bytes(&bytes)
+            .map(Dropped::Image)
+            .map_err(|e| format!("not a supported image: {}", e));
+    }
+    if head.contains(&0) {
+        return Err("binary file".to_string());
+    }
+    let path = match path.strip_prefix(root) {
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => path.to_string_lossy().into_owned(),
+    };
+    Ok(Dropped::File(AddedFile { path, range: None }))
+}
+
+/// Split a paste into words the way a shell would for quotes (and, unless
+/// `escapes` is off, backslashes). `None` for an unterminated quote.
+fn split_words(text: &str, escapes: bool) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = text.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        q if q == c => break,
+                        '\\' if escapes && c == '"' => word.push(chars.next()?),
+                        other => word.push(other),
+                    }
+                }
+            }
+            '\\' if escapes => {
+                in_word = true;
+                word.push(chars.next()?);
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Some(words)
+}
+
+/// The local path of a `file://` URI (after the scheme), percent-decoded.
+/// `None` for URIs naming another host.
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let (host, path) = match uri.find('/') {
+        Some(i) => uri.split_at(i),
+        None => return None,
+    };
+    if !host.is_empty() && host != "localhost" {
+        return None;
+    }
+    let path = percent_decode(path)?;
+    // `file:///C:/Users/...` on Windows
+    let bytes = path.as_bytes();
+    if bytes.len() > 2 && bytes[2] == b':' && bytes[1].is_ascii_alphabetic() {
+        return Some(PathBuf::from(&path[1..]));
+    }
+    Some(PathBuf::from(path))
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut iter = text.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1x1 PNG
+    const PNG: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F,
+        0x15, 0xC4, 0x89, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9C, 0x63, 0x00,
+        0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0D, 0x0A, 0x2D, 0xB4, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(r#"'/tmp/a b.png' /tmp/c\ d.rs "/tmp/e \"f\"" /tmp/g"#, true).unwrap(),
+            vec!["/tmp/a b.png", "/tmp/c d.rs", "/tmp/e \"f\"", "/tmp/g"]
+        );
+        assert_eq!(
+            split_words(r#""C:\Users\me\a b.png""#, false).unwrap(),
+            vec![r"C:\Users\me\a b.png"]
+        );
+        assert_eq!(split_words("'unterminated", true), None);
+    }
+
+    #[test]
+    fn test_uri_path() {
+        assert_eq!(uri_path("/tmp/a%20b.png"), Some(PathBuf::from("/tmp/a b.png")));
+        assert_eq!(uri_path("localhost/tmp/x"), Some(PathBuf::from("/tmp/x")));
+        assert_eq!(uri_path("/C:/Users/me/x.png"), Some(PathBuf::from("C:/Users/me/x.png")));
+        assert_eq!(uri_path("server/share/x"), None);
+        assert_eq!(uri_path("/bad%zz"), None);
+    }
+
+    #[test]
+    fn test_parse_and_ingest() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("shot one.png"), PNG).unwrap();
+        std::fs::write(root.join("notes.md"), "# notes\n").unwrap();
+        std::fs::write(root.join("blob.bin"), [0u8, 1, 2]).unwrap();
+
+        let uri = format!("file://{}", root.join("shot one.png").display()).replace(' ', "%20");
+        let text = format!("{} '{}'\n", uri, root.join("notes.md").display());
+        let dropped = DroppedFiles::parse(&text).unwrap();
+        assert_eq!(dropped.paths.len(), 2);
+        assert_eq!(dropped.prompt(), "Attach 2 dropped files (shot one.png, notes.md)? [Y/n] ");
+
+        let (files, errors) = dropped.ingest(root);
+        assert!(errors.is_empty());
+        assert!(matches!(files[0], Dropped::Image(ref img) if img.width == 1));
+        assert!(matches!(files[1], Dropped::File(ref f) if f.path == "notes.md"));
+
+        let blob = DroppedFiles::parse(&root.join("blob.bin").display().to_string()).unwrap();
+        let (files, errors) = blob.ingest(root);
+        assert!(files.is_empty());
+        assert!(errors[0].ends_with("binary file"));
+
+        // Prose, relative paths, missing files and directories stay text
+        assert_eq!(DroppedFiles::parse("fix the bug in main.rs"), None);
+        assert_eq!(DroppedFiles::parse("notes.md"), None);
+        let missing = format!("{} /no/such/file", root.join("notes.md").display());
+        assert_eq!(DroppedFiles::parse(&missing), None);
+        assert_eq!(DroppedFiles::parse(&root.display().to_string()), None);
+        assert_eq!(DroppedFiles::parse("   "), None);
+    }
+}
@@ -2,18 +2,35 @@
 //!
 //! Provides a decoupled way for agent tools to emit progress events
 //! that the TUI can subscribe to.
+//!
+//! Events travel on two paths. Thinking deltas are high-volume and only feed
+//! the live display, so they go over a broadcast channel and a subscriber
+//! that falls behind skips ahead (the skipped count is recorded in
+//! [`EventBusStats`]). Everything else — tool starts and completions, usage,
+//! notifications, retries — is critical: each subscriber gets its own bounded
+//! queue, and a publisher whose subscriber's queue is full waits for room
+//! instead of dropping the event.
 
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
-use tokio::sync::broadcast;
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc};
 
 use qq_core::{AgentProgressEvent, AgentProgressHandler, Usage};
+use qq_tools::ChangeStats;
 
+use crate::agents::AgentReport;
 use crate::debug_log::DebugLogger;
+use crate::delegation_graph::DelegationGraph;
+use crate::hooks::Hooks;
 
 /// Events emitted by agents for TUI consumption.
-#[derive(Debug, Clone)]
+///
+/// Serialized (tagged by `type`) for `--event-socket` clients.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum AgentEvent {
     /// An agent iteration has started.
     IterationStart {
@@ -39,6 +56,12 @@ pub enum AgentEvent {
         agent_name: String,
         tool_name: String,
         is_error: bool,
+        /// Structured result of a delegation to a sub-agent
+        #[serde(skip_serializing_if = "Option::is_none")]
+        report: Option<AgentReport>,
+        /// One-line `[changes]` summary when the call modified files
+        #[serde(skip_serializing_if = "Option::is_none")]
+        changes: Option<String>,
     },
     /// Usage update from an agent.
     UsageUpdate {
@@ -75,6 +98,39 @@ pub enum AgentEvent {
         observation_count: u32,
         log_bytes: usize,
     },
+    /// The context window overflowed; the context was shrunk and the call retried.
+    ContextRecovered {
+        agent_name: String,
+        action: String,
+    },
+}
+
+impl AgentEvent {
+    /// Whether every subscriber must see this event.
+    ///
+    /// Only thinking deltas may be dropped: they are display-only and the
+    /// next delta carries on. Byte counts and usage are accumulated by
+    /// subscribers, so losing one would skew the totals.
+    pub fn is_critical(&self) -> bool {
+        !matches!(self, AgentEvent::ThinkingDelta { .. })
+    }
+
+    /// Agent that emitted the event.
+    pub fn agent_name(&self) -> &str {
+        match self {
+            AgentEvent::IterationStart { agent_name, .. }
+            | AgentEvent::ThinkingDelta { agent_name, .. }
+            | AgentEvent::ToolStart { agent_name, .. }
+            | AgentEvent::ToolComplete { agent_name, .. }
+            | AgentEvent::UsageUpdate { agent_name, .. }
+            | AgentEvent::ByteCount { agent_name, .. }
+            | AgentEvent::UserNotification { agent_name, .. }
+            | AgentEvent::ContinuationStarted { agent_name, .. }
+            | AgentEvent::Retry { agent_name, .. }
+            | AgentEvent::ObservationComplete { agent_name, .. }
+            | AgentEvent::ContextRecovered { agent_name, .. } => agent_name,
+        }
+    }
 }
 
 impl From<AgentProgressEvent> for AgentEvent {
@@ -103,9 +159,12 @@ impl From<AgentProgressEvent> for AgentEvent {
             AgentProgressEvent::ToolComplete {
                 agent_name,
                 tool_name,
+                result,
                 is_error,
                 ..
             } => AgentEvent::ToolComplete {
+                report: AgentReport::from_delegation(&tool_name, &result, is_error),
+                changes: ChangeStats::from_output(&result).map(|stats| stats.summary()),
                 agent_name,
                 tool_name,
                 is_error,
@@ -142,6 +201,9 @@ impl From<AgentProgressEvent> for AgentEvent {
                 observation_count,
                 log_bytes,
             },
+            AgentProgressEvent::ContextRecovered { agent_name, action } => {
+                AgentEvent::ContextRecovered { agent_name, action }
+            }
             // AssistantResponse is only used for debug logging; never broadcast
             AgentProgressEvent::AssistantResponse { .. } => {
                 unreachable!("AssistantResponse is filtered before broadcast")
@@ -150,21 +212,53 @@ impl From<AgentProgressEvent> for AgentEvent {
     }
 }
 
+/// Delivery counters for the event bus, shown in debug output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventBusStats {
+    /// Thinking deltas skipped by subscribers that fell behind
+    pub dropped: u64,
+    /// Critical events whose publisher had to wait for a full subscriber queue
+    pub critical_waits: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    dropped: AtomicU64,
+    critical_waits: AtomicU64,
+}
+
 /// Event bus for broadcasting agent progress events.
 ///
 /// Clone this to share across agent tools. Each clone shares the same
-/// underlying broadcast channel.
+/// underlying channels.
 #[derive(Clone)]
 pub struct AgentEventBus {
     tx: broadcast::Sender<AgentEvent>,
+    /// One bounded queue per subscriber for critical events
+    critical: Arc<Mutex<Vec<mpsc::Sender<AgentEvent>>>>,
+    capacity: usize,
+    counters: Arc<Counters>,
     debug_logger: Option<Arc<DebugLogger>>,
+    hooks: Option<Arc<Hooks>>,
+    delegation_graph: Option<Arc<DelegationGraph>>,
 }
 
 impl AgentEventBus {
     /// Create a new event bus with the specified channel capacity.
+    ///
+    /// The capacity applies to the broadcast channel for thinking deltas and
+    /// to each subscriber's critical-event queue.
     pub fn new(capacity: usize) -> Self {
         let (tx, _) = broadcast::channel(capacity);
-        Self { tx, debug_logger: None }
+        Self {
+            tx,
+            critical: Arc::new(Mutex::new(Vec::new())),
+            capacity,
+            counters: Arc::new(Counters::default()),
+            debug_logger: None,
+            hooks: None,
+            delegation_graph: None,
+        }
     }
 
     /// Attach a debug logger for trace logging of agent tool calls and responses.
@@ -173,15 +267,86 @@ impl AgentEventBus {
         self
     }
 
+    /// Attach lifecycle hooks, fired for sub-agent runs and file changes.
+    pub fn with_hooks(mut self, hooks: Arc<Hooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// The attached lifecycle hooks, if any.
+    pub fn hooks(&self) -> Option<&Arc<Hooks>> {
+        self.hooks.as_ref()
+    }
+
+    /// Record sub-agent runs in a delegation graph.
+    pub fn with_delegation_graph(mut self, graph: Arc<DelegationGraph>) -> Self {
+        self.delegation_graph = Some(graph);
+        self
+    }
+
+    /// The attached delegation graph, if any.
+    pub fn delegation_graph(&self) -> Option<&Arc<DelegationGraph>> {
+        self.delegation_graph.as_ref()
+    }
+
     /// Subscribe to events from this bus.
-    pub fn subscribe(&self) -> broadcast::Receiver<AgentEvent> {
-        self.tx.subscribe()
+    ///
+    /// Subscribers must keep draining their receiver: a full critical queue
+    /// makes publishers wait.
+    pub fn subscribe(&self) -> AgentEventReceiver {
+        let (critical_tx, critical_rx) = mpsc::channel(self.capacity);
+        // Subscribe to both paths under the lock so a concurrent publish
+        // can't deliver to one and not the other
+        let mut critical = self.critical.lock().unwrap_or_else(|e| e.into_inner());
+        critical.push(critical_tx);
+        AgentEventReceiver {
+            lossy: self.tx.subscribe(),
+            critical: critical_rx,
+            counters: Arc::clone(&self.counters),
+        }
     }
 
     /// Publish an event to all subscribers.
-    pub fn publish(&self, event: AgentEvent) {
-        // Ignore send errors (no subscribers)
-        let _ = self.tx.send(event);
+    ///
+    /// Critical events wait for room in every subscriber's queue; thinking
+    /// deltas never wait.
+    pub async fn publish(&self, event: AgentEvent) {
+        if !event.is_critical() {
+            // Ignore send errors (no subscribers)
+            let _ = self.tx.send(event);
+            return;
+        }
+
+        let subscribers = self
+            .critical
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let mut closed = false;
+        for tx in &subscribers {
+            match tx.try_send(event.clone()) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(event)) => {
+                    self.counters.critical_waits.fetch_add(1, Ordering::Relaxed);
+                    closed |= tx.send(event).await.is_err();
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => closed = true,
+            }
+        }
+        if closed {
+            self.critical
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|tx| !tx.is_closed());
+        }
+    }
+
+    /// Delivery counters since the bus was created.
+    pub fn stats(&self) -> EventBusStats {
+        EventBusStats {
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            critical_waits: self.counters.critical_waits.load(Ordering::Relaxed),
+        }
     }
 
     /// Create a progress handler that publishes to this bus.
@@ -189,6 +354,7 @@ impl AgentEventBus {
         Arc::new(EventBusProgressHandler {
             bus: self.clone(),
             agent_chain: vec![],
+            graph_node: None,
         })
     }
 
@@ -196,20 +362,75 @@ impl AgentEventBus {
     ///
     /// The chain is injected into `IterationStart` events so the TUI can display
     /// the correct agent path even when parallel agents share the ExecutionContext stack.
-    pub fn create_handler_with_chain(&self, scope: &str) -> Arc<dyn AgentProgressHandler> {
+    /// Usage is counted against `graph_node` in the delegation graph.
+    pub fn create_handler_with_chain(
+        &self,
+        scope: &str,
+        graph_node: Option<usize>,
+    ) -> Arc<dyn AgentProgressHandler> {
         let agent_chain: Vec<String> = scope.split('/').map(String::from).collect();
         Arc::new(EventBusProgressHandler {
             bus: self.clone(),
             agent_chain,
+            graph_node,
         })
     }
 }
 
+/// Receiving end of an [`AgentEventBus`] subscription.
+///
+/// Critical events are returned ahead of pending thinking deltas.
+pub struct AgentEventReceiver {
+    lossy: broadcast::Receiver<AgentEvent>,
+    critical: mpsc::Receiver<AgentEvent>,
+    counters: Arc<Counters>,
+}
+
+impl AgentEventReceiver {
+    /// Wait for the next event; `None` once the bus is gone.
+    pub async fn recv(&mut self) -> Option<AgentEvent> {
+        loop {
+            tokio::select! {
+                biased;
+                event = self.critical.recv() => return event,
+                event = self.lossy.recv() => match event {
+                    Ok(event) => return Some(event),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => self.record_dropped(skipped),
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+            }
+        }
+    }
+
+    /// Take the next event if one is ready.
+    pub fn try_recv(&mut self) -> Option<AgentEvent> {
+        if let Ok(event) = self.critical.try_recv() {
+            return Some(event);
+        }
+        loop {
+            match self.lossy.try_recv() {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    self.record_dropped(skipped)
+                }
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn record_dropped(&self, skipped: u64) {
+        self.counters.dropped.fetch_add(skipped, Ordering::Relaxed);
+        tracing::debug!(skipped, "Agent event subscriber lagged; thinking deltas dropped");
+    }
+}
+
 /// Progress handler that publishes events to an event bus and optionally logs to DebugLogger.
 struct EventBusProgressHandler {
     bus: AgentEventBus,
     /// The full agent chain from root to the agent this handler belongs to.
     agent_chain: Vec<String>,
+    /// This run's node in the bus's delegation graph
+    graph_node: Option<usize>,
 }
 
 #[async_trait]
@@ -248,6 +469,30 @@ impl AgentProgressHandler for EventBusProgressHandler {
             }
         }
 
+        if let (Some(graph), Some(node)) = (&self.bus.delegation_graph, self.graph_node) {
+            if let AgentProgressEvent::UsageUpdate { ref usage, .. } = event {
+                graph.add_usage(node, usage);
+            }
+        }
+
+        if let Some(ref hooks) = self.bus.hooks {
+            match &event {
+                AgentProgressEvent::ToolStart {
+                    agent_name,
+                    tool_name,
+                    arguments,
+                } => hooks.tool_started(agent_name, tool_name, arguments),
+                AgentProgressEvent::ToolComplete {
+                    agent_name,
+                    tool_name,
+                    result,
+                    is_error,
+                    ..
+                } => hooks.tool_completed(agent_name, tool_name, result, *is_error),
+                _ => {}
+            }
+        }
+
         // Broadcast to TUI/subscribers (skipping events that are only for logging)
         if !matches!(event, AgentProgressEvent::AssistantResponse { .. }) {
             let mut agent_event: AgentEvent = event.into();
@@ -255,7 +500,87 @@ impl AgentProgressHandler for EventBusProgressHandler {
             if let AgentEvent::IterationStart { ref mut agent_chain, .. } = agent_event {
                 *agent_chain = self.agent_chain.clone();
             }
-            self.bus.publish(agent_event);
+            self.bus.publish(agent_event).await;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thinking(content: &str) -> AgentEvent {
+        AgentEvent::ThinkingDelta {
+            agent_name: "coder".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn notification(message: &str) -> AgentEvent {
+        AgentEvent::UserNotification {
+            agent_name: "coder".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_critical_events_survive_overflow() {
+        let bus = AgentEventBus::new(2);
+        let mut rx = bus.subscribe();
+
+        for i in 0..5 {
+            bus.publish(thinking(&i.to_string())).await;
+        }
+        // Critical events past the queue capacity wait for the subscriber
+        let publisher = {
+            let bus = bus.clone();
+            tokio::spawn(async move {
+                for i in 0..4 {
+                    bus.publish(notification(&format!("step {}", i))).await;
+                }
+            })
+        };
+
+        let mut notifications = Vec::new();
+        let mut deltas = Vec::new();
+        while notifications.len() < 4 {
+            match rx.recv().await.unwrap() {
+                AgentEvent::UserNotification { message, .. } => notifications.push(message),
+                AgentEvent::ThinkingDelta { content, .. } => deltas.push(content),
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        publisher.await.unwrap();
+
+        assert_eq!(notifications, ["step 0", "step 1", "step 2", "step 3"]);
+        while let Some(AgentEvent::ThinkingDelta { content, .. }) = rx.try_recv() {
+            deltas.push(content);
+        }
+        // Only the newest deltas fit in the broadcast buffer
+        assert_eq!(deltas, ["3", "4"]);
+
+        let stats = bus.stats();
+        assert_eq!(stats.dropped, 3);
+        assert!(stats.critical_waits >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_subscribers_are_pruned() {
+        let bus = AgentEventBus::new(4);
+        let rx = bus.subscribe();
+        let mut live = bus.subscribe();
+        drop(rx);
+
+        bus.publish(notification("one")).await;
+        assert_eq!(bus.critical.lock().unwrap().len(), 1);
+
+        assert!(matches!(
+            live.try_recv(),
+            Some(AgentEvent::UserNotification { message, .. }) if message == "one"
+        ));
+        assert!(live.try_recv().is_none());
+
+        drop(bus);
+        assert!(live.recv().await.is_none());
+    }
+}
@@ -0,0 +1,570 @@
+//! Unix domain socket bridge for external UIs (`--event-socket <path>`).
+//!
+//! Every connected client receives the agent event bus plus the primary
+//! session's stream (content deltas, tool calls, approvals) as JSON lines:
+//!
+//! ```text
+//! {"source":"agent","event":{"type":"tool_start","agent_name":"explore",...}}
+//! {"source":"session","event":{"type":"content_delta","content":"Hello"}}
+//! {"source":"session","event":{"type":"approval_requested","id":1,...}}
+//! ```
+//!
+//! Clients may write control messages back, one JSON object per line:
+//!
+//! ```text
+//! {"type":"cancel"}
+//! {"type":"approve","id":1,"decision":"allow_for_session"}
+//! {"type":"approve","id":2,"decision":"deny","reason":"not on main"}
+//! ```
+//!
+//! Approvals are raced against the local UI: whichever answers first wins, and
+//! the other side's prompt is withdrawn.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{broadcast, mpsc, oneshot, Notify};
+
+use qq_core::Usage;
+use qq_tools::{ApprovalRequest, ApprovalResponse};
+
+use crate::event_bus::{AgentEvent, AgentEventBus};
+use crate::latency::RequestMetrics;
+
+/// Outgoing lines buffered per client before it starts missing events.
+const CLIENT_BUFFER: usize = 1024;
+
+/// Events from the primary (user-facing) session, which doesn't go through the
+/// agent event bus.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    ContentDelta {
+        content: String,
+    },
+    ThinkingDelta {
+        content: String,
+    },
+    ToolStart {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    ToolComplete {
+        id: String,
+        name: String,
+        is_error: bool,
+    },
+    ResponseComplete {
+        usage: Option<Usage>,
+    },
+    /// Timing of one model request, with the model's rolling average.
+    RequestMetrics {
+        model: Option<String>,
+        usage: Option<Usage>,
+        metrics: RequestMetrics,
+        average: Option<RequestMetrics>,
+    },
+    Error {
+        message: String,
+    },
+    Cancelled,
+    /// A low-complexity call was served by the `[routing]` profile.
+    ModelRouted {
+        profile: String,
+        model: Option<String>,
+        reason: String,
+    },
+    /// The provider rejected the request as too long; the context was shrunk
+    /// (`action`) and the request is being retried once.
+    ContextRecovered {
+        action: String,
+    },
+    ApprovalRequested {
+        id: u64,
+        category: String,
+        command: String,
+        triggers: Vec<String>,
+        /// What the command does, in plain English
+        #[serde(skip_serializing_if = "Option::is_none")]
+        explanation: Option<String>,
+    },
+    ApprovalResolved {
+        id: u64,
+        decision: Decision,
+        /// `ui`, `socket`, or `closed` (both sides went away; treated as deny).
+        resolved_by: String,
+    },
+}
+
+/// Notices about the socket connection itself.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SocketEvent {
+    /// The client fell behind and this many events were dropped.
+    Lagged { skipped: u64 },
+    /// A control message couldn't be applied.
+    ControlError { message: String },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "source", content = "event", rename_all = "snake_case")]
+enum WireEvent<'a> {
+    Agent(&'a AgentEvent),
+    Session(&'a SessionEvent),
+    Socket(&'a SocketEvent),
+}
+
+impl WireEvent<'_> {
+    fn to_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Approval decision as sent over the socket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    #[default]
+    Allow,
+    AllowForSession,
+    Deny,
+}
+
+impl Decision {
+    fn of(response: &ApprovalResponse) -> Self {
+        match response {
+            ApprovalResponse::Allow => Decision::Allow,
+            ApprovalResponse::AllowForSession => Decision::AllowForSession,
+            ApprovalResponse::Deny(_) => Decision::Deny,
+        }
+    }
+}
+
+/// Control message from a client.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ControlMessage {
+    /// Cancel the in-flight response.
+    Cancel,
+    /// Answer a pending `approval_requested` event.
+    Approve {
+        id: u64,
+        #[serde(default)]
+        decision: Decision,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+struct Inner {
+    tx: broadcast::Sender<String>,
+    cancel_requested: AtomicBool,
+    cancel_notify: Notify,
+    /// Approval id -> socket side of the race
+    pending: Mutex<HashMap<u64, oneshot::Sender<ApprovalResponse>>>,
+    next_approval_id: AtomicU64,
+}
+
+/// Removes the socket file when the last [`EventSocket`] handle is dropped.
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Handle to a listening event socket. Cheap to clone.
+#[derive(Clone)]
+pub struct EventSocket {
+    inner: Arc<Inner>,
+    _file: Arc<SocketFile>,
+}
+
+impl EventSocket {
+    /// Bind the socket and start forwarding `bus` traffic to clients.
+    ///
+    /// A stale socket file left by a crashed run is replaced; one that still
+    /// accepts connections is an error. The socket is made owner-only (0600)
+    /// since clients can answer approvals, and connections from other users
+    /// that slip in before that are dropped.
+    pub fn bind(path: &Path, bus: &AgentEventBus) -> Result<Self> {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                anyhow::bail!("Event socket {} is already in use", path.display());
+            }
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("Failed to bind event socket {}", path.display()))?;
+        let file = SocketFile(path.to_path_buf());
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict event socket {}", path.display()))?;
+        let owner = std::fs::metadata(path)
+            .with_context(|| format!("Failed to stat event socket {}", path.display()))?
+            .uid();
+
+        let (tx, _) = broadcast::channel(CLIENT_BUFFER);
+        let inner = Arc::new(Inner {
+            tx,
+            cancel_requested: AtomicBool::new(false),
+            cancel_notify: Notify::new(),
+            pending: Mutex::new(HashMap::new()),
+            next_approval_id: AtomicU64::new(1),
+        });
+
+        let accept_inner = Arc::clone(&inner);
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let peer = stream.peer_cred().map(|cred| cred.uid());
+                        if peer.as_ref().ok() != Some(&owner) {
+                            tracing::warn!(peer = ?peer, "Event socket client of another user refused");
+                            continue;
+                        }
+                        tokio::spawn(serve_client(Arc::clone(&accept_inner), stream));
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Event socket accept failed");
+                        break;
+                    }
+                }
+            }
+        });
+
+        let bus_inner = Arc::clone(&inner);
+        let mut bus_rx = bus.subscribe();
+        tokio::spawn(async move {
+            while let Some(event) = bus_rx.recv().await {
+                bus_inner.send(&WireEvent::Agent(&event));
+            }
+        });
+
+        Ok(Self {
+            inner,
+            _file: Arc::new(file),
+        })
+    }
+
+    /// Stream a primary-session event to connected clients.
+    pub fn emit(&self, event: SessionEvent) {
+        self.inner.send(&WireEvent::Session(&event));
+    }
+
+    /// Consume a pending cancel request, if a client sent one.
+    pub fn take_cancel(&self) -> bool {
+        self.inner.cancel_requested.swap(false, Ordering::SeqCst)
+    }
+
+    /// Wait until a client asks to cancel.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.cancel_notify.notified();
+            if self.take_cancel() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Route approval requests through the socket as well as the local UI.
+    ///
+    /// Returns the receiver the UI should poll instead of `rx`. Each request
+    /// is announced to clients; the first answer from either side is used.
+    pub fn interpose_approvals(
+        &self,
+        mut rx: mpsc::Receiver<ApprovalRequest>,
+    ) -> mpsc::Receiver<ApprovalRequest> {
+        let (ui_tx, ui_rx) = mpsc::channel(8);
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            while let Some(request) = rx.recv().await {
+                tokio::spawn(relay_approval(Arc::clone(&inner), request, ui_tx.clone()));
+            }
+        });
+        ui_rx
+    }
+}
+
+impl Inner {
+    fn send(&self, event: &WireEvent<'_>) {
+        // No receivers just means no client is connected
+        let _ = self.tx.send(event.to_line());
+    }
+
+    fn handle_control(&self, line: &str) -> Result<(), String> {
+        let message: ControlMessage =
+            serde_json::from_str(line).map_err(|e| format!("Invalid control message: {}", e))?;
+        match message {
+            ControlMessage::Cancel => {
+                self.cancel_requested.store(true, Ordering::SeqCst);
+                self.cancel_notify.notify_one();
+                Ok(())
+            }
+            ControlMessage::Approve {
+                id,
+                decision,
+                reason,
+            } => {
+                let responder = self
+                    .pending
+                    .lock()
+                    .ok()
+                    .and_then(|mut pending| pending.remove(&id))
+                    .ok_or_else(|| format!("No pending approval with id {}", id))?;
+                let response = match decision {
+                    Decision::Allow => ApprovalResponse::Allow,
+                    Decision::AllowForSession => ApprovalResponse::AllowForSession,
+                    Decision::Deny => ApprovalResponse::Deny(reason),
+                };
+                responder
+                    .send(response)
+                    .map_err(|_| format!("Approval {} was already answered", id))
+            }
+        }
+    }
+}
+
+/// Race one approval request between the local UI and socket clients.
+async fn relay_approval(
+    inner: Arc<Inner>,
+    request: ApprovalRequest,
+    ui_tx: mpsc::Sender<ApprovalRequest>,
+) {
+    let ApprovalRequest {
+        full_command,
+        trigger_commands,
+        response_tx,
+        category,
+        explanation,
+        preview,
+    } = request;
+
+    let id = inner.next_approval_id.fetch_add(1, Ordering::SeqCst);
+    let (socket_tx, socket_rx) = oneshot::channel();
+    if let Ok(mut pending) = inner.pending.lock() {
+        pending.insert(id, socket_tx);
+    }
+    inner.send(&WireEvent::Session(&SessionEvent::ApprovalRequested {
+        id,
+        category: category.clone(),
+        command: full_command.clone(),
+        triggers: trigger_commands.clone(),
+        explanation: explanation.clone(),
+    }));
+
+    // If the UI is gone the proxy sender is dropped and only the socket can answer
+    let (proxy_tx, proxy_rx) = oneshot::channel();
+    let _ = ui_tx
+        .send(ApprovalRequest {
+            full_command,
+            trigger_commands,
+            response_tx: proxy_tx,
+            category,
+            explanation,
+            preview,
+        })
+        .await;
+
+    // Dropping the losing receiver withdraws that side's prompt
+    let (response, resolved_by) = tokio::select! {
+        Ok(response) = proxy_rx => (response, "ui"),
+        Ok(response) = socket_rx => (response, "socket"),
+        else => (ApprovalResponse::Deny(None), "closed"),
+    };
+    if let Ok(mut pending) = inner.pending.lock() {
+        pending.remove(&id);
+    }
+
+    inner.send(&WireEvent::Session(&SessionEvent::ApprovalResolved {
+        id,
+        decision: Decision::of(&response),
+        resolved_by: resolved_by.to_string(),
+    }));
+    let _ = response_tx.send(response);
+}
+
+/// Stream events to one client and apply its control messages.
+async fn serve_client(inner: Arc<Inner>, stream: UnixStream) {
+    let (read_half, mut write_half) = stream.into_split();
+    let mut events = inner.tx.subscribe();
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<String>();
+
+    let control_inner = Arc::clone(&inner);
+    let reader = tokio::spawn(async move {
+        let mut lines = BufReader::new(read_half).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Err(message) = control_inner.handle_control(&line) {
+                let _ = reply_tx.send(
+                    WireEvent::Socket(&SocketEvent::ControlError { message }).to_line(),
+                );
+            }
+        }
+    });
+
+    loop {
+        let line = tokio::select! {
+            event = events.recv() => match event {
+                Ok(line) => line,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    WireEvent::Socket(&SocketEvent::Lagged { skipped }).to_line()
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Reader finished: the client closed its end
+            reply = reply_rx.recv() => match reply {
+                Some(line) => line,
+                None => break,
+            },
+        };
+        if write_half.write_all(line.as_bytes()).await.is_err()
+            || write_half.write_all(b"\n").await.is_err()
+        {
+            break;
+        }
+    }
+    reader.abort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_control_messages() {
+        assert_eq!(
+            serde_json::from_str::<ControlMessage>(r#"{"type":"cancel"}"#).unwrap(),
+            ControlMessage::Cancel
+        );
+        assert_eq!(
+            serde_json::from_str::<ControlMessage>(r#"{"type":"approve","id":3}"#).unwrap(),
+            ControlMessage::Approve {
+                id: 3,
+                decision: Decision::Allow,
+                reason: None
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<ControlMessage>(
+                r#"{"type":"approve","id":4,"decision":"deny","reason":"no"}"#
+            )
+            .unwrap(),
+            ControlMessage::Approve {
+                id: 4,
+                decision: Decision::Deny,
+                reason: Some("no".to_string())
+            }
+        );
+        assert!(serde_json::from_str::<ControlMessage>(r#"{"type":"reboot"}"#).is_err());
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let agent = AgentEvent::ToolComplete {
+            agent_name: "explore".to_string(),
+            tool_name: "run".to_string(),
+            is_error: false,
+            report: None,
+            changes: None,
+        };
+        let line = WireEvent::Agent(&agent).to_line();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["source"], "agent");
+        assert_eq!(value["event"]["type"], "tool_complete");
+        assert_eq!(value["event"]["agent_name"], "explore");
+
+        let session = SessionEvent::ContentDelta {
+            content: "hi".to_string(),
+        };
+        let value: serde_json::Value =
+            serde_json::from_str(&WireEvent::Session(&session).to_line()).unwrap();
+        assert_eq!(value["source"], "session");
+        assert_eq!(value["event"], serde_json::json!({"type": "content_delta", "content": "hi"}));
+    }
+
+    #[tokio::test]
+    async fn test_socket_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("qq.sock");
+        let _socket = EventSocket::bind(&path, &AgentEventBus::new(16)).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // The owner can still connect
+        UnixStream::connect(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_socket_streams_events_and_races_approvals() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("qq.sock");
+        let bus = AgentEventBus::new(16);
+        let socket = EventSocket::bind(&path, &bus).unwrap();
+
+        let (approval_tx, approval_rx) = qq_tools::create_approval_channel();
+        let mut ui_rx = socket.interpose_approvals(approval_rx);
+
+        let client = UnixStream::connect(&path).await.unwrap();
+        let (read_half, mut write_half) = client.into_split();
+        let mut lines = BufReader::new(read_half).lines();
+        // Let the server subscribe this client before publishing
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        socket.emit(SessionEvent::ContentDelta {
+            content: "hello".to_string(),
+        });
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(line.contains(r#""content":"hello""#));
+
+        // Socket answers before the UI does
+        let approval = tokio::spawn(async move {
+            approval_tx
+                .request_explained_approval(
+                    "rm -rf build".to_string(),
+                    vec!["rm".to_string()],
+                    "Bash Command",
+                    Some("Deletes build and everything inside.".to_string()),
+                )
+                .await
+        });
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(line.contains("approval_requested"));
+        assert!(line.contains(r#""explanation":"Deletes build and everything inside.""#));
+        let ui_request = ui_rx.recv().await.unwrap();
+        assert_eq!(ui_request.explanation.as_deref(), Some("Deletes build and everything inside."));
+
+        write_half
+            .write_all(b"{\"type\":\"approve\",\"id\":1,\"decision\":\"allow\"}\n")
+            .await
+            .unwrap();
+        assert_eq!(approval.await.unwrap(), Ok(ApprovalResponse::Allow));
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(line.contains(r#""resolved_by":"socket""#));
+        // UI prompt is withdrawn
+        assert!(ui_request.response_tx.is_closed());
+
+        write_half.write_all(b"{\"type\":\"cancel\"}\n").await.unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(1), socket.cancelled())
+            .await
+            .unwrap();
+        assert!(!socket.take_cancel());
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,304 @@
+//! Prompt experiment bookkeeping.
+//!
+//! When an agent has an `[experiments.<agent>]` table in agents.toml, each run
+//! picks one system-prompt variant. The run's token usage and outcome are
+//! tagged with that variant and appended to `experiments.jsonl` in the state
+//! dir, which `qq stats` aggregates so prompt changes can be compared on real
+//! numbers.
+
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use qq_core::{AgentProgressEvent, AgentProgressHandler};
+
+/// Random roll for weighted variant selection.
+pub fn roll() -> u64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish()
+}
+
+/// Progress handler that sums token usage for one agent run and forwards
+/// every event to the wrapped handler.
+pub struct UsageTally {
+    inner: Option<Arc<dyn AgentProgressHandler>>,
+    llm_calls: AtomicU32,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+}
+
+impl UsageTally {
+    pub fn new(inner: Option<Arc<dyn AgentProgressHandler>>) -> Self {
+        Self {
+            inner,
+            llm_calls: AtomicU32::new(0),
+            prompt_tokens: AtomicU64::new(0),
+            completion_tokens: AtomicU64::new(0),
+        }
+    }
+
+    /// Build the log record for a finished run.
+    pub fn record(&self, agent: &str, variant: &str, outcome: &str) -> ExperimentRecord {
+        ExperimentRecord {
+            timestamp: chrono::Utc::now(),
+            agent: agent.to_string(),
+            variant: variant.to_string(),
+            outcome: outcome.to_string(),
+            llm_calls: self.llm_calls.load(Ordering::Relaxed),
+            prompt_tokens: self.prompt_tokens.load(Ordering::Relaxed),
+            completion_tokens: self.completion_tokens.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentProgressHandler for UsageTally {
+    async fn on_progress(&self, event: AgentProgressEvent) {
+        if let AgentProgressEvent::UsageUpdate { ref usage, .. } = event {
+            self.llm_calls.fetch_add(1, Ordering::Relaxed);
+            self.prompt_tokens
+                .fetch_add(usage.prompt_tokens as u64, Ordering::Relaxed);
+            self.completion_tokens
+                .fetch_add(usage.completion_tokens as u64, Ordering::Relaxed);
+        }
+        if let Some(ref inner) = self.inner {
+            inner.on_progress(event).await;
+        }
+    }
+}
+
+/// One agent run tagged with its prompt variant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentRecord {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub agent: String,
+    pub variant: String,
+    /// Run outcome, e.g. "success", "max_iterations", "error".
+    pub outcome: String,
+    pub llm_calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl ExperimentRecord {
+    pub fn is_success(&self) -> bool {
+        self.outcome == "success"
+    }
+}
+
+/// Get the path to the experiment log (~/.local/state/qq/experiments.jsonl).
+pub fn log_path() -> Option<PathBuf> {
+    crate::paths::experiments_file()
+}
+
+/// Append a record to the experiment log. Failures are logged, not returned,
+/// so bookkeeping never fails an agent run.
+pub fn append(record: &ExperimentRecord) {
+    tracing::info!(
+        agent = %record.agent,
+        variant = %record.variant,
+        outcome = %record.outcome,
+        llm_calls = record.llm_calls,
+        prompt_tokens = record.prompt_tokens,
+        completion_tokens = record.completion_tokens,
+        "Prompt experiment run recorded"
+    );
+
+    let Some(path) = log_path() else {
+        return;
+    };
+    if let Err(e) = append_to(&path, record) {
+        tracing::warn!(path = %path.display(), error = %e, "Failed to write experiment log");
+    }
+}
+
+fn append_to(path: &Path, record: &ExperimentRecord) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Load all records from an experiment log, skipping malformed lines.
+pub fn load(path: &Path) -> Result<Vec<ExperimentRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Aggregated results for one (agent, variant) pair.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariantStats {
+    pub agent: String,
+    pub variant: String,
+    pub runs: u32,
+    pub successes: u32,
+    pub llm_calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl VariantStats {
+    /// Average total tokens per run.
+    pub fn avg_tokens(&self) -> u64 {
+        if self.runs == 0 {
+            0
+        } else {
+            (self.prompt_tokens + self.completion_tokens) / self.runs as u64
+        }
+    }
+}
+
+/// Aggregate records per (agent, variant), ordered by agent then variant.
+pub fn summarize(records: &[ExperimentRecord]) -> Vec<VariantStats> {
+    let mut by_key: BTreeMap<(&str, &str), VariantStats> = BTreeMap::new();
+    for r in records {
+        let stats = by_key
+            .entry((r.agent.as_str(), r.variant.as_str()))
+            .or_insert_with(|| VariantStats {
+                agent: r.agent.clone(),
+                variant: r.variant.clone(),
+                ..Default::default()
+            });
+        stats.runs += 1;
+        if r.is_success() {
+            stats.successes += 1;
+        }
+        stats.llm_calls += r.llm_calls as u64;
+        stats.prompt_tokens += r.prompt_tokens;
+        stats.completion_tokens += r.completion_tokens;
+    }
+    by_key.into_values().collect()
+}
+
+/// Print per-variant experiment results (`qq stats`).
+pub fn print_stats() -> Result<()> {
+    let path = log_path().ok_or_else(|| anyhow::anyhow!("Could not determine state directory"))?;
+    let stats = summarize(&load(&path)?);
+
+    if stats.is_empty() {
+        println!("No prompt experiment runs recorded.");
+        println!("Configure variants under [experiments.<agent>] in agents.toml.");
+        return Ok(());
+    }
+
+    println!("Prompt experiments ({}):\n", path.display());
+    println!(
+        "  {:<14} {:<16} {:>5} {:>8} {:>10} {:>11}",
+        "AGENT", "VARIANT", "RUNS", "SUCCESS", "CALLS/RUN", "TOKENS/RUN"
+    );
+    for s in &stats {
+        let success_rate = s.successes as f64 * 100.0 / s.runs as f64;
+        let calls_per_run = s.llm_calls as f64 / s.runs as f64;
+        println!(
+            "  {:<14} {:<16} {:>5} {:>7.0}% {:>10.1} {:>11}",
+            s.agent,
+            s.variant,
+            s.runs,
+            success_rate,
+            calls_per_run,
+            s.avg_tokens()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::Usage;
+
+    fn record(agent: &str, variant: &str, outcome: &str, prompt: u64, completion: u64) -> ExperimentRecord {
+        ExperimentRecord {
+            timestamp: chrono::Utc::now(),
+            agent: agent.to_string(),
+            variant: variant.to_string(),
+            outcome: outcome.to_string(),
+            llm_calls: 2,
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+        }
+    }
+
+    #[test]
+    fn test_summarize_groups_by_agent_and_variant() {
+        let records = vec![
+            record("coder", "terse", "success", 100, 20),
+            record("coder", "baseline", "error", 300, 0),
+            record("coder", "terse", "max_iterations", 200, 40),
+            record("coder", "baseline", "success", 100, 100),
+        ];
+
+        let stats = summarize(&records);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].variant, "baseline");
+        assert_eq!(stats[0].runs, 2);
+        assert_eq!(stats[0].successes, 1);
+        assert_eq!(stats[0].avg_tokens(), 250);
+        assert_eq!(stats[1].variant, "terse");
+        assert_eq!(stats[1].llm_calls, 4);
+        assert_eq!(stats[1].avg_tokens(), 180);
+    }
+
+    #[test]
+    fn test_log_round_trip_skips_malformed_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("experiments.jsonl");
+
+        append_to(&path, &record("coder", "a", "success", 1, 2)).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+        append_to(&path, &record("coder", "b", "error", 3, 4)).unwrap();
+
+        let records = load(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].variant, "b");
+        assert!(load(&dir.path().join("missing.jsonl")).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_usage_tally_sums_usage_updates() {
+        let tally = UsageTally::new(None);
+        for _ in 0..2 {
+            tally
+                .on_progress(AgentProgressEvent::UsageUpdate {
+                    agent_name: "coder".into(),
+                    usage: Usage::new(10, 5),
+                })
+                .await;
+        }
+
+        let r = tally.record("coder", "terse", "success");
+        assert_eq!(r.llm_calls, 2);
+        assert_eq!(r.prompt_tokens, 20);
+        assert_eq!(r.completion_tokens, 10);
+        assert!(r.is_success());
+    }
+}
@@ -0,0 +1,141 @@
+//! `/focus`: scope the agent to a subdirectory of the project.
+//!
+//! In a monorepo the agent tends to wander into unrelated packages. `/focus
+//! <path>` moves the bash sandbox's working directory (see
+//! [`qq_tools::SandboxMounts::set_focus`]): commands start there, relative
+//! paths resolve against it, and `check_build` / `run_linters` default to
+//! it. The sandbox still mounts the whole project, so shared code stays
+//! readable. The agent learns about the change from a note placed ahead of
+//! the next user message; `/unfocus` restores the project root the same way.
+
+use std::path::Path;
+
+use qq_tools::SandboxMounts;
+
+/// Outcome of `/focus` or `/unfocus`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusChange {
+    /// Line shown to the user
+    pub status: String,
+    /// Note for the agent, when the working directory changed
+    pub note: Option<String>,
+}
+
+/// Handle `/focus [path]`; without a path, report the current focus.
+pub fn focus(mounts: &SandboxMounts, arg: &str) -> FocusChange {
+    let arg = arg.trim();
+    if arg.is_empty() {
+        return FocusChange {
+            status: match mounts.focus() {
+                Some(dir) => format!("Focused on {} (/unfocus to clear)", dir.display()),
+                None => "No focus set. Usage: /focus <path>".to_string(),
+            },
+            note: None,
+        };
+    }
+    let was = mounts.focus();
+    match mounts.set_focus(Some(Path::new(arg))) {
+        Ok(now) if now == was => FocusChange {
+            status: match now {
+                Some(dir) => format!("Already focused on {}", dir.display()),
+                None => "No focus set; the project root is the working directory".to_string(),
+            },
+            note: None,
+        },
+        Ok(Some(dir)) => FocusChange {
+            status: format!("Focused on {}", dir.display()),
+            note: Some(focus_note(&dir)),
+        },
+        Ok(None) => cleared(),
+        Err(e) => FocusChange {
+            status: format!("Cannot focus: {}", e),
+            note: None,
+        },
+    }
+}
+
+/// Handle `/unfocus`.
+pub fn unfocus(mounts: &SandboxMounts) -> FocusChange {
+    if mounts.focus().is_none() {
+        return FocusChange {
+            status: "No focus set".to_string(),
+            note: None,
+        };
+    }
+    match mounts.set_focus(None) {
+        Ok(_) => cleared(),
+        Err(e) => FocusChange {
+            status: format!("Cannot clear focus: {}", e),
+            note: None,
+        },
+    }
+}
+
+/// Note restating the current focus, for a conversation that no longer
+/// has the original one (after `/clear`).
+pub fn current_note(mounts: &SandboxMounts) -> Option<String> {
+    mounts.focus().map(|dir| focus_note(&dir))
+}
+
+fn cleared() -> FocusChange {
+    FocusChange {
+        status: "Focus cleared; the project root is the working directory".to_string(),
+        note: Some(
+            "[Focus cleared: commands start at the project root again, and relative \
+             paths resolve against it.]"
+                .to_string(),
+        ),
+    }
+}
+
+fn focus_note(dir: &Path) -> String {
+    format!(
+        "[Focus: until told otherwise, work in `{dir}`. Commands start there, relative \
+         paths resolve against it, and check_build and run_linters report only on it. \
+         Leave it only when the task needs code from elsewhere in the project.]",
+        dir = dir.display()
+    )
+}
+
+/// `text` with a pending focus note ahead of it.
+pub fn with_note(note: Option<String>, text: &str) -> String {
+    match note {
+        Some(note) => format!("{}\n\n{}", note, text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_and_unfocus() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(project.path().join("packages/api")).unwrap();
+        let mounts = SandboxMounts::new(project.path().to_path_buf()).unwrap();
+
+        assert_eq!(unfocus(&mounts).note, None);
+        assert_eq!(focus(&mounts, "").note, None);
+
+        let change = focus(&mounts, " packages/api ");
+        assert_eq!(change.status, "Focused on packages/api");
+        assert!(change
+            .note
+            .unwrap()
+            .starts_with("[Focus: until told otherwise, work in `packages/api`."));
+        assert_eq!(focus(&mounts, "packages/api").note, None);
+        assert!(current_note(&mounts).is_some());
+
+        let failed = focus(&mounts, "packages/web");
+        assert!(failed.status.starts_with("Cannot focus: packages/web"));
+        assert_eq!(failed.note, None);
+        assert_eq!(mounts.work_dir(), project.path().join("packages/api"));
+
+        let change = unfocus(&mounts);
+        assert!(change.note.unwrap().starts_with("[Focus cleared"));
+        assert_eq!(current_note(&mounts), None);
+        assert_eq!(with_note(None, "hi"), "hi");
+        assert_eq!(with_note(Some("[n]".into()), "hi"), "[n]\n\nhi");
+    }
+}
@@ -0,0 +1,590 @@
+//! Lifecycle hooks (`[[hooks]]` in config.toml).
+//!
+//! A hook runs a shell command or POSTs to a URL when one of its events
+//! fires:
+//!
+//! - `session_start` / `session_end`: an interactive session opens or closes
+//! - `agent_start` / `agent_end`: a sub-agent is delegated to, or returns
+//! - `file_changed`: a `run` command modified files
+//!
+//! The event is sent as one JSON object: on stdin for commands (with
+//! `QQ_HOOK_EVENT` set to the event name), as the request body for URLs. It
+//! carries a one-line `text` summary, so Slack-style incoming webhooks can
+//! post it as is:
+//!
+//! ```text
+//! {"event":"agent_end","agent":"coder","parent":"assistant","is_error":false,
+//!  "summary":"Added retry","changes":{...},"text":"coder finished: Added retry",
+//!  "cwd":"/home/me/proj","timestamp":"2026-10-16T09:12:03+02:00"}
+//! ```
+//!
+//! Hooks run in the background under a timeout; a failing or slow hook is
+//! logged and never blocks the session. `session_end` hooks are awaited
+//! before qq exits.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinHandle;
+
+use qq_tools::ChangeStats;
+
+use crate::agents::AgentReport;
+use crate::config::{HookEntry, HookEventKind};
+
+/// Agent name of the user-facing session, as the parent of its delegations.
+pub const PRIMARY_AGENT: &str = "assistant";
+
+/// Longest `task` or `summary` kept in a payload's `text`.
+const MAX_TEXT_CHARS: usize = 200;
+
+/// Stderr kept when a hook command fails.
+const MAX_STDERR_CHARS: usize = 500;
+
+/// An event hooks can run on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    SessionStart {
+        /// `chat` or `tui`
+        mode: String,
+        profile: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+    },
+    SessionEnd {
+        duration_secs: u64,
+        /// Sub-agent delegations that returned
+        agent_runs: usize,
+        /// Files modified during the session, sorted
+        files_changed: Vec<String>,
+    },
+    AgentStart {
+        agent: String,
+        /// The agent that delegated
+        parent: String,
+        task: String,
+    },
+    AgentEnd {
+        agent: String,
+        parent: String,
+        is_error: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        changes: Option<ChangeStats>,
+    },
+    FileChanged {
+        /// The agent whose tool call changed the files
+        agent: String,
+        tool: String,
+        changes: ChangeStats,
+    },
+}
+
+impl HookEvent {
+    pub fn kind(&self) -> HookEventKind {
+        match self {
+            HookEvent::SessionStart { .. } => HookEventKind::SessionStart,
+            HookEvent::SessionEnd { .. } => HookEventKind::SessionEnd,
+            HookEvent::AgentStart { .. } => HookEventKind::AgentStart,
+            HookEvent::AgentEnd { .. } => HookEventKind::AgentEnd,
+            HookEvent::FileChanged { .. } => HookEventKind::FileChanged,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self.kind() {
+            HookEventKind::SessionStart => "session_start",
+            HookEventKind::SessionEnd => "session_end",
+            HookEventKind::AgentStart => "agent_start",
+            HookEventKind::AgentEnd => "agent_end",
+            HookEventKind::FileChanged => "file_changed",
+        }
+    }
+
+    /// The agent `allow` / `deny` are matched against.
+    fn agent(&self) -> Option<&str> {
+        match self {
+            HookEvent::SessionStart { .. } | HookEvent::SessionEnd { .. } => None,
+            HookEvent::AgentStart { agent, .. }
+            | HookEvent::AgentEnd { agent, .. }
+            | HookEvent::FileChanged { agent, .. } => Some(agent),
+        }
+    }
+
+    /// One-line human-readable summary.
+    pub fn text(&self) -> String {
+        match self {
+            HookEvent::SessionStart {
+                mode,
+                profile,
+                model,
+            } => match model {
+                Some(model) => format!("qq {} session started ({}, {})", mode, profile, model),
+                None => format!("qq {} session started ({})", mode, profile),
+            },
+            HookEvent::SessionEnd {
+                duration_secs,
+                agent_runs,
+                files_changed,
+            } => {
+                let mut text = format!(
+                    "qq session ended after {}m {:02}s: {} agent run{}, {} file{} changed",
+                    duration_secs / 60,
+                    duration_secs % 60,
+                    agent_runs,
+                    if *agent_runs == 1 { "" } else { "s" },
+                    files_changed.len(),
+                    if files_changed.len() == 1 { "" } else { "s" },
+                );
+                if !files_changed.is_empty() {
+                    let shown: Vec<&str> =
+                        files_changed.iter().take(5).map(String::as_str).collect();
+                    text.push_str(&format!(" ({}", shown.join(", ")));
+                    if files_changed.len() > shown.len() {
+                        text.push_str(&format!(", +{} more", files_changed.len() - shown.len()));
+                    }
+                    text.push(')');
+                }
+                text
+            }
+            HookEvent::AgentStart { agent, task, .. } => {
+                format!("{} started: {}", agent, first_line(task))
+            }
+            HookEvent::AgentEnd {
+                agent,
+                is_error,
+                summary,
+                changes,
+                ..
+            } => {
+                let mut text = if *is_error {
+                    format!("{} failed", agent)
+                } else {
+                    format!("{} finished", agent)
+                };
+                if let Some(summary) = summary {
+                    text.push_str(&format!(": {}", first_line(summary)));
+                }
+                if let Some(changes) = changes {
+                    text.push_str(&format!(" [{}]", changes.summary()));
+                }
+                text
+            }
+            HookEvent::FileChanged { agent, changes, .. } => {
+                format!("{} changed files: {}", agent, changes.summary())
+            }
+        }
+    }
+}
+
+/// First line of `text`, cut to [`MAX_TEXT_CHARS`].
+fn first_line(text: &str) -> String {
+    let line = text
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or("")
+        .trim();
+    if line.chars().count() > MAX_TEXT_CHARS {
+        let cut: String = line.chars().take(MAX_TEXT_CHARS).collect();
+        format!("{}…", cut)
+    } else {
+        line.to_string()
+    }
+}
+
+/// The JSON a hook receives.
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a HookEvent,
+    text: String,
+    cwd: String,
+    timestamp: String,
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Whether `hook` runs for `event`.
+fn hook_matches(hook: &HookEntry, event: &HookEvent) -> bool {
+    if !hook.on.contains(&event.kind()) {
+        return false;
+    }
+    let Some(agent) = event.agent() else {
+        return true;
+    };
+    if hook.deny.iter().any(|p| wildcard_match(p, agent)) {
+        return false;
+    }
+    hook.allow.is_empty() || hook.allow.iter().any(|p| wildcard_match(p, agent))
+}
+
+fn hook_label(hook: &HookEntry) -> String {
+    hook.name
+        .clone()
+        .or_else(|| hook.command.clone())
+        .or_else(|| hook.url.clone())
+        .unwrap_or_default()
+}
+
+/// Runs the configured hooks and tracks what the session-end payload reports.
+pub struct Hooks {
+    hooks: Vec<HookEntry>,
+    cwd: PathBuf,
+    client: reqwest::Client,
+    started: Instant,
+    agent_runs: AtomicUsize,
+    files_changed: Mutex<BTreeSet<String>>,
+    running: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Hooks {
+    /// Validate `hooks`; commands run in `cwd`.
+    pub fn new(hooks: Vec<HookEntry>, cwd: PathBuf) -> Result<Self> {
+        for hook in &hooks {
+            let label = hook_label(hook);
+            match (&hook.command, &hook.url) {
+                (Some(_), Some(_)) => bail!("Hook '{}' sets both command and url", label),
+                (None, None) => bail!("Hook '{}' needs a command or a url", label),
+                _ => {}
+            }
+            if hook.on.is_empty() {
+                bail!("Hook '{}' has no events in `on`", label);
+            }
+        }
+        let client = qq_core::http::client_builder()
+            .build()
+            .context("Failed to create HTTP client for hooks")?;
+        Ok(Self {
+            hooks,
+            cwd,
+            client,
+            started: Instant::now(),
+            agent_runs: AtomicUsize::new(0),
+            files_changed: Mutex::new(BTreeSet::new()),
+            running: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Run the hooks matching `event` in the background.
+    pub fn fire(&self, event: HookEvent) {
+        match &event {
+            HookEvent::AgentEnd { .. } => {
+                self.agent_runs.fetch_add(1, Ordering::Relaxed);
+            }
+            HookEvent::FileChanged { changes, .. } => {
+                let mut files = self.files_changed.lock().unwrap_or_else(|e| e.into_inner());
+                files.extend(changes.files.iter().map(|f| f.path.clone()));
+            }
+            _ => {}
+        }
+
+        let matching: Vec<&HookEntry> = self
+            .hooks
+            .iter()
+            .filter(|h| hook_matches(h, &event))
+            .collect();
+        if matching.is_empty() {
+            return;
+        }
+        let payload = serde_json::to_string(&Payload {
+            event: &event,
+            text: event.text(),
+            cwd: self.cwd.display().to_string(),
+            timestamp: chrono::Local::now().to_rfc3339(),
+        })
+        .unwrap_or_default();
+
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        running.retain(|handle| !handle.is_finished());
+        for hook in matching {
+            running.push(tokio::spawn(run_hook(
+                hook.clone(),
+                event.name(),
+                payload.clone(),
+                self.cwd.clone(),
+                self.client.clone(),
+            )));
+        }
+    }
+
+    /// A tool call started. Delegations to `Agent[name]` fire `agent_start`.
+    pub fn tool_started(&self, agent: &str, tool_name: &str, arguments: &str) {
+        let Some(delegate) = delegated_agent(tool_name) else {
+            return;
+        };
+        let task = serde_json::from_str::<serde_json::Value>(arguments)
+            .ok()
+            .and_then(|args| args["task"].as_str().map(String::from))
+            .unwrap_or_else(|| arguments.to_string());
+        self.fire(HookEvent::AgentStart {
+            agent: delegate.to_string(),
+            parent: agent.to_string(),
+            task,
+        });
+    }
+
+    /// A tool call finished. Delegations fire `agent_end`; other tools that
+    /// report changed files fire `file_changed`. (A delegation's changes were
+    /// already reported by the sub-agent's own tool calls.)
+    pub fn tool_completed(&self, agent: &str, tool_name: &str, result: &str, is_error: bool) {
+        if let Some(delegate) = delegated_agent(tool_name) {
+            let report = AgentReport::from_delegation(tool_name, result, is_error);
+            let summary = match report {
+                Some(ref report) => Some(report.summary.clone()),
+                None => Some(first_line(result)).filter(|s| !s.is_empty()),
+            };
+            let changes = report
+                .and_then(|r| r.changes)
+                .or_else(|| ChangeStats::from_output(result));
+            self.fire(HookEvent::AgentEnd {
+                agent: delegate.to_string(),
+                parent: agent.to_string(),
+                is_error,
+                summary,
+                changes,
+            });
+        } else if let Some(changes) = ChangeStats::from_output(result) {
+            self.fire(HookEvent::FileChanged {
+                agent: agent.to_string(),
+                tool: tool_name.to_string(),
+                changes,
+            });
+        }
+    }
+
+    /// Fire `session_end` and wait for every hook still running.
+    pub async fn finish(&self) {
+        let files_changed = self
+            .files_changed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect();
+        self.fire(HookEvent::SessionEnd {
+            duration_secs: self.started.elapsed().as_secs(),
+            agent_runs: self.agent_runs.load(Ordering::Relaxed),
+            files_changed,
+        });
+        let running = std::mem::take(&mut *self.running.lock().unwrap_or_else(|e| e.into_inner()));
+        for handle in running {
+            let _ = handle.await;
+        }
+    }
+}
+
+/// `coder` for an `Agent[coder]` tool.
+fn delegated_agent(tool_name: &str) -> Option<&str> {
+    tool_name.strip_prefix("Agent[")?.strip_suffix(']')
+}
+
+async fn run_hook(
+    hook: HookEntry,
+    event: &'static str,
+    payload: String,
+    cwd: PathBuf,
+    client: reqwest::Client,
+) {
+    let label = hook_label(&hook);
+    let timeout = Duration::from_secs(hook.timeout_secs);
+    let run = async {
+        match (&hook.command, &hook.url) {
+            (Some(command), _) => run_command(command, event, &payload, &cwd).await,
+            (None, Some(url)) => post(&client, url, &hook, payload.clone()).await,
+            (None, None) => Ok(()),
+        }
+    };
+    match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(())) => tracing::debug!(hook = %label, event, "Hook finished"),
+        Ok(Err(e)) => tracing::warn!(hook = %label, event, error = %e, "Hook failed"),
+        Err(_) => tracing::warn!(
+            hook = %label,
+            event,
+            timeout_secs = hook.timeout_secs,
+            "Hook timed out"
+        ),
+    }
+}
+
+async fn run_command(command: &str, event: &str, payload: &str, cwd: &PathBuf) -> Result<()> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(cwd)
+        .env("QQ_HOOK_EVENT", event)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start hook command")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores its input may exit before reading it
+        let _ = stdin.write_all(payload.as_bytes()).await;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr: String = stderr.trim().chars().take(MAX_STDERR_CHARS).collect();
+        bail!("exited with {}: {}", output.status, stderr);
+    }
+    Ok(())
+}
+
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    hook: &HookEntry,
+    payload: String,
+) -> Result<()> {
+    let mut request = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(payload);
+    for (name, value) in &hook.headers {
+        request = request.header(name, value);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        bail!("HTTP {}", response.status());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(on: Vec<HookEventKind>, command: &str) -> HookEntry {
+        HookEntry {
+            name: None,
+            on,
+            command: Some(command.to_string()),
+            url: None,
+            headers: Default::default(),
+            allow: Vec::new(),
+            deny: Vec::new(),
+            timeout_secs: 5,
+        }
+    }
+
+    #[test]
+    fn test_matching_and_text() {
+        assert!(wildcard_match("coder", "coder"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("doc-*", "doc-researcher"));
+        assert!(wildcard_match("*-coder*", "rust-coder-2"));
+        assert!(!wildcard_match("doc-*", "coder"));
+        assert!(!wildcard_match("coder", "coder2"));
+
+        let mut fmt = hook(vec![HookEventKind::AgentEnd], "cargo fmt");
+        fmt.allow = vec!["*coder".into()];
+        fmt.deny = vec!["test-coder".into()];
+        let end = |agent: &str| HookEvent::AgentEnd {
+            agent: agent.into(),
+            parent: PRIMARY_AGENT.into(),
+            is_error: false,
+            summary: Some("Added retry\nwith backoff".into()),
+            changes: None,
+        };
+        assert!(hook_matches(&fmt, &end("coder")));
+        assert!(!hook_matches(&fmt, &end("test-coder")));
+        assert!(!hook_matches(&fmt, &end("explore")));
+        assert_eq!(end("coder").text(), "coder finished: Added retry");
+
+        let ended = HookEvent::SessionEnd {
+            duration_secs: 125,
+            agent_runs: 1,
+            files_changed: vec!["src/lib.rs".into()],
+        };
+        // Session events ignore agent filters
+        let mut slack = hook(vec![HookEventKind::SessionEnd], "true");
+        slack.allow = vec!["coder".into()];
+        assert!(hook_matches(&slack, &ended));
+        assert!(!hook_matches(&slack, &end("coder")));
+        assert_eq!(
+            ended.text(),
+            "qq session ended after 2m 05s: 1 agent run, 1 file changed (src/lib.rs)"
+        );
+
+        let json = serde_json::to_value(Payload {
+            event: &ended,
+            text: ended.text(),
+            cwd: "/proj".into(),
+            timestamp: String::new(),
+        })
+        .unwrap();
+        assert_eq!(json["event"], "session_end");
+        assert_eq!(json["files_changed"][0], "src/lib.rs");
+        assert_eq!(json["cwd"], "/proj");
+
+        let mut both = hook(vec![HookEventKind::AgentEnd], "true");
+        both.url = Some("http://localhost".into());
+        assert!(Hooks::new(vec![both], PathBuf::from(".")).is_err());
+        assert!(Hooks::new(vec![hook(Vec::new(), "true")], PathBuf::from(".")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_hooks_receive_payload() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = Hooks::new(
+            vec![
+                hook(
+                    vec![HookEventKind::AgentStart, HookEventKind::AgentEnd],
+                    "cat > \"$QQ_HOOK_EVENT.json\"",
+                ),
+                hook(vec![HookEventKind::SessionEnd], "echo done > end.txt"),
+            ],
+            dir.path().to_path_buf(),
+        )
+        .unwrap();
+
+        hooks.tool_started("pm", "Agent[coder]", r#"{"task":"Add retry"}"#);
+        hooks.tool_completed("pm", "Agent[coder]", "Added retry", false);
+        // Not a delegation and no changes: nothing fires
+        hooks.tool_completed("coder", "run", "ok", false);
+        hooks.finish().await;
+
+        let read = |name: &str| -> serde_json::Value {
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join(name)).unwrap()).unwrap()
+        };
+        let start = read("agent_start.json");
+        assert_eq!(start["agent"], "coder");
+        assert_eq!(start["parent"], "pm");
+        assert_eq!(start["task"], "Add retry");
+        let end = read("agent_end.json");
+        assert_eq!(end["summary"], "Added retry");
+        assert_eq!(end["text"], "coder finished: Added retry");
+        assert!(!dir.path().join("file_changed.json").exists());
+
+        let end = std::fs::read_to_string(dir.path().join("end.txt")).unwrap();
+        assert_eq!(end.trim(), "done");
+    }
+}
@@ -0,0 +1,153 @@
+//! Time-to-first-token and throughput of model requests.
+//!
+//! Every request the agent loop sends is timed (see
+//! [`qq_core::ResponseTiming`]). The TUI status bar shows the latest request
+//! next to the rolling average for its model, and `--event-socket` clients
+//! get a `request_metrics` event per request, so local model configurations
+//! can be compared without a stopwatch.
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::Serialize;
+
+use qq_core::{ResponseTiming, Usage};
+
+/// Requests per model in the rolling average.
+pub const WINDOW: usize = 20;
+
+/// Latency and throughput of one request, or an average of several.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RequestMetrics {
+    /// Milliseconds to the first streamed token; absent when not streamed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_token_ms: Option<u64>,
+    pub duration_ms: u64,
+    /// Output tokens per second while generating; absent without usage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens_per_sec: Option<f64>,
+}
+
+impl RequestMetrics {
+    pub fn new(timing: &ResponseTiming, usage: Option<&Usage>) -> Self {
+        Self {
+            first_token_ms: timing.first_token.map(|d| d.as_millis() as u64),
+            duration_ms: timing.duration.as_millis() as u64,
+            tokens_per_sec: usage.and_then(|u| timing.tokens_per_sec(u.completion_tokens)),
+        }
+    }
+
+    /// e.g. "TTFT 0.42s · 38.5 tok/s · 6.10s"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ms) = self.first_token_ms {
+            parts.push(format!("TTFT {}", format_secs(ms)));
+        }
+        if let Some(rate) = self.tokens_per_sec {
+            parts.push(format!("{:.1} tok/s", rate));
+        }
+        parts.push(format_secs(self.duration_ms));
+        parts.join(" · ")
+    }
+}
+
+fn format_secs(ms: u64) -> String {
+    if ms < 10_000 {
+        format!("{:.2}s", ms as f64 / 1000.0)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+/// Mean of the values that are present.
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Rolling per-model averages over the last [`WINDOW`] requests.
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    by_model: HashMap<String, VecDeque<RequestMetrics>>,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, model: &str, metrics: RequestMetrics) {
+        let samples = self.by_model.entry(model.to_string()).or_default();
+        if samples.len() == WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(metrics);
+    }
+
+    /// The model's average and how many requests it covers.
+    pub fn average(&self, model: &str) -> Option<(RequestMetrics, usize)> {
+        let samples = self.by_model.get(model).filter(|s| !s.is_empty())?;
+        let average = RequestMetrics {
+            first_token_ms: mean(samples.iter().filter_map(|m| m.first_token_ms).map(|ms| ms as f64))
+                .map(|ms| ms.round() as u64),
+            duration_ms: mean(samples.iter().map(|m| m.duration_ms as f64))
+                .map_or(0, |ms| ms.round() as u64),
+            tokens_per_sec: mean(samples.iter().filter_map(|m| m.tokens_per_sec)),
+        };
+        Some((average, samples.len()))
+    }
+
+    /// Status bar text: the latest request, then the model's average once
+    /// there is more than one request to average.
+    pub fn status_line(&self, model: &str, latest: &RequestMetrics) -> String {
+        match self.average(model) {
+            Some((average, count)) if count > 1 => format!(
+                "{} (avg of {}: {})",
+                latest.summary(),
+                count,
+                average.summary()
+            ),
+            _ => latest.summary(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn metrics(first_token_ms: u64, duration_ms: u64, completion_tokens: u32) -> RequestMetrics {
+        let timing = ResponseTiming {
+            first_token: Some(Duration::from_millis(first_token_ms)),
+            duration: Duration::from_millis(duration_ms),
+        };
+        RequestMetrics::new(&timing, Some(&Usage::new(100, completion_tokens)))
+    }
+
+    #[test]
+    fn test_rolling_average_per_model() {
+        let mut stats = LatencyStats::default();
+        let first = metrics(400, 2400, 100);
+        assert_eq!(first.tokens_per_sec, Some(50.0));
+        assert_eq!(first.summary(), "TTFT 0.40s · 50.0 tok/s · 2.40s");
+
+        stats.record("qwen3", first);
+        assert_eq!(stats.status_line("qwen3", &first), first.summary());
+
+        let second = metrics(600, 12600, 300);
+        stats.record("qwen3", second);
+        stats.record("llama", metrics(100, 1100, 10));
+        let (average, count) = stats.average("qwen3").unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(average.first_token_ms, Some(500));
+        assert_eq!(average.duration_ms, 7500);
+        assert_eq!(average.tokens_per_sec, Some(37.5));
+        assert_eq!(
+            stats.status_line("qwen3", &second),
+            "TTFT 0.60s · 25.0 tok/s · 12.6s \
+             (avg of 2: TTFT 0.50s · 37.5 tok/s · 7.50s)"
+        );
+
+        for _ in 0..WINDOW {
+            stats.record("qwen3", second);
+        }
+        assert_eq!(stats.average("qwen3").unwrap(), (second, WINDOW));
+        assert!(stats.average("mistral").is_none());
+    }
+}
@@ -0,0 +1,268 @@
+//! In-memory capture of tracing events for the TUI log pane.
+//!
+//! The TUI owns the terminal, so tracing output can't go to stderr, and
+//! debugging a provider used to mean running with `--log-file` and tailing
+//! the JSON from a second terminal. [`CaptureLayer`] keeps the most recent
+//! events in a [`LogBuffer`] instead, each tagged with the agent that emitted
+//! it: the event's own `agent` field, or the nearest enclosing span that has
+//! one (sub-agent runs are wrapped in an `agent` span).
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Local};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+use qq_core::text::truncate_bytes;
+
+/// Events kept; older ones are dropped.
+const CAPACITY: usize = 2000;
+
+/// Longest message kept per event (request dumps can be very large).
+const MAX_MESSAGE_BYTES: usize = 2000;
+
+/// Crates whose debug events are captured; dependencies only log warnings.
+const CAPTURED_CRATES: &[&str] = &["qq", "qq_core", "qq_providers", "qq_tools", "qq_agents", "qq_mcp"];
+
+/// Filter for the capture layer: qq's own debug events (trace with
+/// `verbose`), independent of the `--log-level` used for `--log-file`.
+pub fn filter(verbose: bool) -> EnvFilter {
+    let level = if verbose { "trace" } else { "debug" };
+    let directives: Vec<String> = CAPTURED_CRATES
+        .iter()
+        .map(|krate| format!("{}={}", krate, level))
+        .collect();
+    EnvFilter::new(format!("warn,{}", directives.join(",")))
+}
+
+/// One captured event.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    /// Agent that emitted the event; `None` outside sub-agent runs.
+    pub agent: Option<String>,
+    /// The message followed by the event's other fields as `key=value`.
+    pub message: String,
+}
+
+#[derive(Default)]
+struct LogState {
+    records: VecDeque<LogRecord>,
+    /// Agents seen so far, in order of first appearance.
+    agents: Vec<Option<String>>,
+    /// Events captured since startup, including dropped ones.
+    total: u64,
+}
+
+/// Shared ring buffer of recent events.
+#[derive(Clone, Default)]
+pub struct LogBuffer {
+    state: Arc<Mutex<LogState>>,
+}
+
+impl LogBuffer {
+    pub fn push(&self, record: LogRecord) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.agents.contains(&record.agent) {
+            state.agents.push(record.agent.clone());
+        }
+        if state.records.len() == CAPACITY {
+            state.records.pop_front();
+        }
+        state.records.push_back(record);
+        state.total += 1;
+    }
+
+    /// Events captured so far; changes whenever a new one arrives.
+    pub fn total(&self) -> u64 {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).total
+    }
+
+    /// Agents that have logged, for cycling the agent filter.
+    pub fn agents(&self) -> Vec<Option<String>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .agents
+            .clone()
+    }
+
+    /// The last `count` events at least as severe as `level`, oldest first,
+    /// limited to one agent when `agent` is set.
+    pub fn tail(&self, level: Level, agent: Option<&Option<String>>, count: usize) -> Vec<LogRecord> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut records: Vec<LogRecord> = state
+            .records
+            .iter()
+            .rev()
+            .filter(|r| r.level <= level && agent.is_none_or(|a| &r.agent == a))
+            .take(count)
+            .cloned()
+            .collect();
+        records.reverse();
+        records
+    }
+}
+
+/// Next level for the pane's filter, from most to least verbose and around.
+pub fn next_level(level: Level) -> Level {
+    match level {
+        Level::TRACE => Level::DEBUG,
+        Level::DEBUG => Level::INFO,
+        Level::INFO => Level::WARN,
+        Level::WARN => Level::ERROR,
+        _ => Level::TRACE,
+    }
+}
+
+/// Tracing layer that records events into a [`LogBuffer`].
+pub struct CaptureLayer {
+    buffer: LogBuffer,
+}
+
+impl CaptureLayer {
+    pub fn new(buffer: LogBuffer) -> Self {
+        Self { buffer }
+    }
+}
+
+/// Agent recorded on a span, looked up by the events inside it.
+struct SpanAgent(String);
+
+impl<S> Layer<S> for CaptureLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let (Some(agent), Some(span)) = (fields.agent, ctx.span(id)) {
+            span.extensions_mut().insert(SpanAgent(agent));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let agent = fields.agent.or_else(|| {
+            ctx.event_scope(event)?
+                .find_map(|span| span.extensions().get::<SpanAgent>().map(|a| a.0.clone()))
+        });
+        let mut message = fields.message;
+        message.push_str(&fields.rest);
+        message.truncate(truncate_bytes(&message, MAX_MESSAGE_BYTES).len());
+
+        let metadata = event.metadata();
+        self.buffer.push(LogRecord {
+            time: Local::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            agent,
+            message,
+        });
+    }
+}
+
+/// Collects an event's message, `agent` field, and remaining fields.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    agent: Option<String>,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            "agent" => self.agent = Some(value.to_string()),
+            name => {
+                let _ = write!(self.rest, " {}={}", name, value);
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "agent" => self.agent = Some(format!("{:?}", value)),
+            name => {
+                let _ = write!(self.rest, " {}={:?}", name, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::Instrument;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_events_tagged_with_agent_and_filtered() {
+        let buffer = LogBuffer::default();
+        let subscriber = tracing_subscriber::registry().with(CaptureLayer::new(buffer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(model = "gpt", "Sending request");
+            tracing::warn!(agent = "coder", "Stream error");
+            let span = tracing::info_span!("agent", agent = %"explore");
+            let _entered = span.enter();
+            tracing::info!(attempt = 2, "Retrying");
+            drop(_entered);
+            // Instrumented futures carry the span too
+            futures::executor::block_on(
+                async { tracing::error!("Failed") }.instrument(tracing::info_span!("agent", agent = "coder")),
+            );
+        });
+
+        assert_eq!(buffer.total(), 4);
+        assert_eq!(
+            buffer.agents(),
+            vec![None, Some("coder".to_string()), Some("explore".to_string())]
+        );
+
+        let all = buffer.tail(Level::TRACE, None, 10);
+        assert_eq!(all[0].message, "Sending request model=gpt");
+        assert_eq!(all[0].agent, None);
+        assert_eq!(all[2].message, "Retrying attempt=2");
+        assert_eq!(all[2].agent.as_deref(), Some("explore"));
+
+        let warnings = buffer.tail(Level::WARN, None, 10);
+        let messages: Vec<&str> = warnings.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, vec!["Stream error", "Failed"]);
+
+        let coder = buffer.tail(Level::TRACE, Some(&Some("coder".to_string())), 1);
+        assert_eq!(coder.len(), 1);
+        assert_eq!(coder[0].message, "Failed");
+        assert_eq!(buffer.tail(Level::TRACE, Some(&None), 10).len(), 1);
+    }
+
+    #[test]
+    fn test_buffer_keeps_most_recent() {
+        let buffer = LogBuffer::default();
+        for i in 0..CAPACITY + 5 {
+            buffer.push(LogRecord {
+                time: Local::now(),
+                level: Level::INFO,
+                target: "qq".to_string(),
+                agent: None,
+                message: i.to_string(),
+            });
+        }
+        assert_eq!(buffer.total(), (CAPACITY + 5) as u64);
+        let tail = buffer.tail(Level::INFO, None, CAPACITY + 5);
+        assert_eq!(tail.len(), CAPACITY);
+        assert_eq!(tail[0].message, "5");
+        assert_eq!(next_level(Level::ERROR), Level::TRACE);
+    }
+}
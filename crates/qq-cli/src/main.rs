@@ -1,33 +1,69 @@
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
-use qq_agents::{ProjectManagerAgent, InternalAgent};
+use qq_agents::{AgentMemoryStrategy, InternalAgent, ProjectManagerAgent};
 use qq_core::{
-    execute_tools_parallel_with_chunker, ChunkProcessor, CompletionRequest, ImageData, Message,
-    Provider, ToolRegistry, TypedContent,
+    AdaptiveProvider, AgentRunner, BalancedProvider, CapabilityCache, ChunkProcessor,
+    CompletionRequest, Conversation, Endpoint, ImageData, Message, Provider, RunEvent, RunHandler,
+    ToolRegistry, Transcript, TypedContent,
 };
 use qq_providers::{AnthropicProvider, GeminiProvider, OpenAIProvider};
 
+mod active_sessions;
+mod agent_bundle;
 mod agents;
+mod carry_over;
 mod chat;
 mod compaction;
+mod completion_events;
+mod completion_session;
 mod config;
+mod config_check;
+mod context_dedup;
+mod context_files;
+mod context_slots;
 mod debug_log;
+mod delegation_graph;
+mod diff_pager;
+mod doctor;
+mod dropped_files;
 mod event_bus;
+mod event_socket;
 mod execution_context;
+mod experiments;
+mod focus;
+mod hooks;
+mod html;
+mod latency;
+mod log_capture;
 mod markdown;
+mod mirror;
+mod paths;
+mod permissions;
+mod plugins;
+mod policy;
 mod profile_registry;
+mod project_defaults;
+mod remote_approval;
+mod routing;
+mod session_diff;
+mod session_memory;
 mod setup;
+mod share;
+mod summaries;
+mod templates;
 mod tui;
+mod turn_stats;
 
 pub use event_bus::AgentEventBus;
 pub use execution_context::ExecutionContext;
 
 use agents::{create_agent_tools, AgentExecutor, InformUserTool, DEFAULT_MAX_AGENT_DEPTH};
-use config::{expand_path, AgentsConfig, Config};
+use config::{expand_path, AgentsConfig, CompactionConfig, Config};
 use profile_registry::{ResolvedProfileRuntime, SharedProfileRegistry};
 use qq_core::{AgentMemory, ContextCompactor};
 use qq_tools::bash::permissions::parse_config_overrides;
@@ -131,6 +167,16 @@ pub struct Cli {
     #[arg(long, hide = true)]
     pub debug_file: Option<std::path::PathBuf>,
 
+    /// Stream events as JSON lines over a Unix socket at this path and accept
+    /// control messages (cancel, approve) from clients (chat mode only)
+    #[arg(long, value_name = "PATH")]
+    pub event_socket: Option<std::path::PathBuf>,
+
+    /// Append the live conversation to a Markdown file as it streams, with
+    /// tool calls as collapsible blocks (chat mode only)
+    #[arg(long, value_name = "PATH")]
+    pub mirror_file: Option<std::path::PathBuf>,
+
     /// Disable all tools (for testing)
     #[arg(long)]
     pub no_tools: bool,
@@ -163,15 +209,62 @@ pub struct Cli {
     #[arg(long)]
     pub ask_network: bool,
 
+    /// Review every tool call before it runs, with the option to edit its
+    /// arguments or skip it (chat mode only)
+    #[arg(long)]
+    pub supervised: bool,
+
+    /// Continue the most recently saved conversation in this project
+    /// (completion mode only)
+    #[arg(short = 'c', long = "continue", requires = "prompt", conflicts_with = "session")]
+    pub continue_session: bool,
+
+    /// Continue the saved conversation with this id or a unique prefix of
+    /// one, or start one under this id (completion mode only)
+    #[arg(long, value_name = "ID", requires = "prompt")]
+    pub session: Option<String>,
+
     /// Image files to include with the prompt (completion mode only, may be repeated)
     #[arg(short = 'i', long = "image", value_name = "FILE")]
     pub images: Vec<PathBuf>,
 
+    /// Documents to attach to the prompt (PDFs, datasets), uploaded to the
+    /// provider and sent by reference where supported (completion mode only,
+    /// may be repeated)
+    #[arg(long = "attach", value_name = "FILE")]
+    pub attachments: Vec<PathBuf>,
+
+    /// Write the run's events (text deltas, tool calls and results, usage)
+    /// to stderr; stdout gets only the final reply (completion mode only)
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "prompt")]
+    pub events: Option<EventsFormat>,
+
+    /// Write --events to this file instead of stderr
+    #[arg(long, value_name = "PATH", requires = "events")]
+    pub events_file: Option<PathBuf>,
+
     /// Primary agent to use for interactive sessions (overrides profile)
     /// Can be any internal agent: pm, explore, researcher, coder, reviewer, summarizer, planner, writer
     #[arg(short = 'A', long)]
     pub agent: Option<String>,
 
+    /// Pin an agent's prompt experiment to one variant (AGENT=VARIANT, may be repeated)
+    #[arg(long = "variant", value_name = "AGENT=VARIANT")]
+    pub variants: Vec<String>,
+
+    /// Forget the settings remembered for this project (agent, model, sandbox
+    /// flags, mounts) and start from this command line alone
+    #[arg(long)]
+    pub no_project_defaults: bool,
+
+    /// Confirmation line for settings applied from the project's record
+    #[arg(skip)]
+    pub project_notice: Option<String>,
+
+    /// Recent tracing events for the TUI log pane
+    #[arg(skip)]
+    pub log_buffer: Option<log_capture::LogBuffer>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -186,15 +279,155 @@ enum Commands {
     },
     /// List available profiles and their settings
     Profiles,
-    /// Show current configuration
-    Config,
+    /// Show current configuration (`qq config check` validates it)
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigCommand>,
+    },
+    /// Show per-variant results of prompt experiments
+    Stats,
+    /// Start a chat session from a template (lists templates without --template)
+    New {
+        /// Template name in ~/.config/qq/templates/, or a path to a template file
+        #[arg(short = 'T', long)]
+        template: Option<String>,
+    },
+    /// Show config, state, and cache directories
+    Paths,
     /// Initialize configuration files in ~/.config/qq
     Setup,
+    /// Export, import, or compact the cross-session knowledge base
+    Memory {
+        #[command(subcommand)]
+        action: MemoryCommand,
+    },
+    /// Export or import agent definitions as shareable TOML bundles
+    Agents {
+        #[command(subcommand)]
+        action: AgentsCommand,
+    },
+    /// Print which agent delegated to which in a past session
+    Graph {
+        /// Session id or a unique prefix of one, or a graph file (default: latest session)
+        session: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = GraphFormat::Mermaid)]
+        format: GraphFormat,
+    },
+    /// Write a past session as one file with secrets and outside paths scrubbed
+    Share {
+        /// Session id or a unique prefix of one, or a session log file (default: latest session)
+        session: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ShareFormat::Markdown)]
+        format: ShareFormat,
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Prepare caches for the project ahead of a session
+    Index {
+        /// Summarize the project's files into the `summarize_file` cache
+        #[arg(long)]
+        summaries: bool,
+        /// Files summarized at once
+        #[arg(short, long, default_value_t = 4)]
+        jobs: usize,
+        /// Skip files smaller than this many bytes (cheaper to read than summarize)
+        #[arg(long, default_value_t = 4096)]
+        min_bytes: u64,
+    },
+    /// Check config, providers, sandbox, knowledge base, and tools, with hints
+    /// for fixing what fails
+    Doctor {
+        /// Skip the test request to each provider
+        #[arg(long)]
+        no_network: bool,
+    },
+}
+
+/// Output formats of `qq graph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT source
+    Dot,
+    /// Mermaid flowchart, renders in Markdown viewers
+    Mermaid,
+    /// Indented tree for the terminal
+    Ascii,
+}
+
+/// Formats of `--events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EventsFormat {
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// Output formats of `qq share`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ShareFormat {
+    /// Markdown transcript with the session JSON appended
+    Markdown,
+    /// Standalone HTML page with the session JSON embedded
+    Html,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Report unknown keys, type errors, and deprecated fields in config.toml and agents.toml
+    Check,
+}
+
+#[derive(Subcommand)]
+enum AgentsCommand {
+    /// Write an agent from agents.toml as a bundle
+    Export {
+        /// Agent name in agents.toml
+        name: String,
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Validate a bundle and add its agent to agents.toml
+    Import {
+        /// Bundle file, or an http(s) URL
+        source: String,
+        /// Import under this name instead of the bundle's
+        #[arg(long)]
+        name: Option<String>,
+        /// Replace an existing agent of the same name
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MemoryCommand {
+    /// Write knowledge entries as JSON (this project's unless --all)
+    Export {
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Export every project's entries
+        #[arg(long)]
+        all: bool,
+    },
+    /// Merge entries from a JSON export, skipping ones already known
+    Import {
+        /// JSON file written by `qq memory export`
+        file: PathBuf,
+        /// Reassign imported entries to this project path (default: keep theirs)
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// Rewrite the knowledge file without corrupt lines and duplicates
+    Compact,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
     // Determine if TUI mode will be used (needed for logging configuration)
     let will_use_tui = cli.tui && !cli.no_tui && atty::is(atty::Stream::Stdout)
@@ -213,22 +446,28 @@ async fn main() -> Result<()> {
     // Set up logging
     let filter = EnvFilter::new(log_level.as_filter());
 
+    // TUI mode keeps recent events in memory for the log pane (Ctrl+L),
+    // independent of --log-level and --log-file
+    cli.log_buffer = will_use_tui.then(log_capture::LogBuffer::default);
+    let capture = cli.log_buffer.clone().map(|buffer| {
+        log_capture::CaptureLayer::new(buffer)
+            .with_filter(log_capture::filter(log_level == LogLevel::Trace))
+    });
+
     if will_use_tui && log_file.is_none() {
-        // TUI mode without log file: suppress all tracing output
-        tracing_subscriber::fmt()
-            .with_env_filter(filter)
-            .with_writer(std::io::sink)
-            .init();
+        // TUI mode without log file: nothing is written, only captured
+        tracing_subscriber::registry().with(capture).init();
     } else if let Some(log_path) = log_file {
         // Log file specified: write JSON to file
         let file = std::fs::File::create(log_path)
             .with_context(|| format!("Failed to create log file: {:?}", log_path))?;
         tracing_subscriber::registry()
-            .with(filter)
+            .with(capture)
             .with(
                 fmt::layer()
                     .json()
                     .with_writer(std::sync::Mutex::new(file))
+                    .with_filter(filter)
             )
             .init();
     } else {
@@ -238,31 +477,114 @@ async fn main() -> Result<()> {
             .init();
     }
 
-    // Handle setup before config is required
+    // Move history and other state out of the config dir (older versions kept it there)
+    paths::migrate_legacy_files();
+
+    // Handle setup and paths before config is required
     if matches!(&cli.command, Some(Commands::Setup)) {
         return setup::run();
     }
+    if matches!(&cli.command, Some(Commands::Paths)) {
+        paths::print_paths();
+        return Ok(());
+    }
+    if let Some(Commands::Graph { session, format }) = &cli.command {
+        return graph_command(session.as_deref(), *format);
+    }
+    // Checking must work on a config that fails to load
+    if matches!(&cli.command, Some(Commands::Config { action: Some(ConfigCommand::Check) })) {
+        return config_check::run();
+    }
+    // The doctor reports a policy or config that fails to load
+    if let Some(Commands::Doctor { no_network }) = &cli.command {
+        return doctor_command(&cli, *no_network).await;
+    }
+
+    // The organization policy applies before anything the user configures
+    policy::init()?;
 
     // Load configuration (required for all other commands)
-    let config = Config::load()?;
+    let mut config = Config::load()?;
+    install_network(&config)?;
+
+    // Choices made on the command line, before any template fills in more
+    let explicit = project_defaults::ProjectDefaults {
+        profile: cli.profile.clone(),
+        agent: cli.agent.clone(),
+        model: cli.model.clone(),
+        insecure: cli.insecure,
+        agent_mode: cli.agent_mode,
+        ask_network: cli.ask_network,
+        mounts: Vec::new(),
+    };
+
+    // A template fills in the profile and agent unless given on the command line
+    let template = match &cli.command {
+        Some(Commands::New { template: Some(name) }) => Some(templates::SessionTemplate::load(name)?),
+        _ => None,
+    };
+    if let Some(ref template) = template {
+        if cli.profile.is_none() {
+            cli.profile = template.profile.clone();
+        }
+        if cli.agent.is_none() {
+            cli.agent = template.agent.clone();
+        }
+    }
+
+    // Interactive sessions remember their choices per project and start
+    // with the ones remembered
+    let interactive = match &cli.command {
+        Some(Commands::Manage { .. }) | Some(Commands::New { template: Some(_) }) => true,
+        None => cli.prompt.is_none(),
+        _ => false,
+    };
+    if interactive {
+        apply_project_defaults(&mut cli, &mut config, &explicit);
+    }
 
     match &cli.command {
         Some(Commands::Manage { system }) => {
-            chat_mode(&cli, &config, system.clone()).await
+            chat_mode(&cli, &config, system.clone(), None).await
+        }
+        Some(Commands::New { template: None }) => {
+            templates::print_templates()
+        }
+        Some(Commands::New { template: Some(_) }) => {
+            chat_mode(&cli, &config, cli.system.clone(), template.as_ref()).await
         }
         Some(Commands::Profiles) => {
             list_profiles(&config)
         }
-        Some(Commands::Config) => {
+        Some(Commands::Config { action: None }) => {
             show_config(&config)
         }
-        Some(Commands::Setup) => unreachable!(),
+        Some(Commands::Stats) => {
+            experiments::print_stats()
+        }
+        Some(Commands::Memory { action }) => {
+            memory_command(&config, action)
+        }
+        Some(Commands::Agents { action }) => {
+            agents_command(&config, action).await
+        }
+        Some(Commands::Share { session, format, output }) => {
+            share_command(&config, session.as_deref(), *format, output.as_deref())
+        }
+        Some(Commands::Index { summaries, jobs, min_bytes }) => {
+            index_command(&cli, &config, *summaries, *jobs, *min_bytes).await
+        }
+        Some(Commands::Setup)
+        | Some(Commands::Paths)
+        | Some(Commands::Graph { .. })
+        | Some(Commands::Config { action: Some(ConfigCommand::Check) })
+        | Some(Commands::Doctor { .. }) => unreachable!(),
         None => {
             if let Some(prompt) = &cli.prompt {
                 completion_mode(&cli, &config, prompt).await
             } else {
                 // Default to chat mode if no prompt provided
-                chat_mode(&cli, &config, cli.system.clone()).await
+                chat_mode(&cli, &config, cli.system.clone(), None).await
             }
         }
     }
@@ -272,37 +594,68 @@ async fn main() -> Result<()> {
 struct RunResources {
     mounts: Arc<qq_tools::SandboxMounts>,
     permissions: Arc<qq_tools::PermissionStore>,
+    access_log: Arc<qq_tools::AccessLog>,
+    /// Sandbox for tools that run helper commands (e.g. ripgrep)
+    executor: qq_tools::SandboxExecutor,
+    path_policy: qq_tools::SandboxPathPolicy,
 }
 
-/// Build tools registry from config.
-fn build_tools_registry(config: &Config, insecure: bool, agent_mode: bool, supported_mimetypes: &[String], ask_network: bool) -> Result<(ToolRegistry, Option<RunResources>, Option<tokio::sync::mpsc::Receiver<qq_tools::ApprovalRequest>>)> {
-    // Resolve root directory: config > $PWD
-    let root = config.tools.root.as_ref()
+/// Resolve the tools root directory: config > $PWD
+fn tools_root(config: &Config) -> PathBuf {
+    config.tools.root.as_ref()
         .map(|s| expand_path(s))
-        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+}
+
+/// Send every HTTP client through `[network]`'s proxy and CA bundles. The
+/// settings are checked here, so a bad proxy URL or CA file stops startup
+/// rather than failing the first request.
+fn install_network(config: &Config) -> Result<()> {
+    let network = config.network.resolve()?;
+    qq_core::http::set_network_options(network.options().clone());
+    qq_core::http::set_client_factory(Box::new(move || network.apply(reqwest::Client::builder())));
+    Ok(())
+}
+
+/// Build tools registry from config.
+fn build_tools_registry(config: &Config, insecure: bool, agent_mode: bool, supported_mimetypes: &[String], ask_network: bool, plugins: &[plugins::Plugin]) -> Result<(ToolRegistry, Option<RunResources>, Option<tokio::sync::mpsc::Receiver<qq_tools::ApprovalRequest>>)> {
+    let root = tools_root(config);
 
     let mut registry = ToolRegistry::new();
 
     let use_run = config.tools.enable_bash;
+    let policy = policy::current();
+    if use_run {
+        policy.check_insecure(insecure)?;
+        policy.check_project_root(&root)?;
+    }
+
+    // Walk the sandbox backend chain once; the run tools share the result
+    let executor = if use_run {
+        let order = qq_tools::SandboxBackend::parse_order(&config.tools.bash_sandbox_backends)
+            .map_err(anyhow::Error::msg)?;
+        Some(qq_tools::SandboxExecutor::detect_with(&order))
+    } else {
+        None
+    };
 
     // If run is requested, verify sandbox unless --insecure
-    if use_run && !insecure {
-        let executor = qq_tools::SandboxExecutor::detect();
-        if !executor.supports_shell() {
-            if is_apparmor_restricting_userns() {
-                anyhow::bail!(
-                    "Kernel sandbox unavailable — AppArmor is restricting user namespaces.\n\n\
-                     To fix, run:  sudo ./scripts/setup-apparmor.sh\n\n\
-                     Alternatively:\n  \
-                     --insecure  Allow commands without kernel sandbox isolation"
-                );
-            } else {
-                anyhow::bail!(
-                    "Kernel sandbox unavailable — user namespaces are not supported.\n\n\
-                     Alternatively:\n  \
-                     --insecure  Allow commands without kernel sandbox isolation"
-                );
-            }
+    if !insecure && executor.as_ref().is_some_and(|e| !e.supports_shell()) {
+        if is_apparmor_restricting_userns() {
+            anyhow::bail!(
+                "Kernel sandbox unavailable — AppArmor is restricting user namespaces.\n\n\
+                 To fix, run:  sudo ./scripts/setup-apparmor.sh\n\n\
+                 Alternatively:\n  \
+                 Install bubblewrap (bwrap) or firejail, which qq uses when hakoniwa is blocked\n  \
+                 --insecure  Allow commands without kernel sandbox isolation"
+            );
+        } else {
+            anyhow::bail!(
+                "Kernel sandbox unavailable — user namespaces are not supported.\n\n\
+                 Alternatively:\n  \
+                 Install bubblewrap (bwrap) or firejail\n  \
+                 --insecure  Allow commands without kernel sandbox isolation"
+            );
         }
     }
 
@@ -312,17 +665,25 @@ fn build_tools_registry(config: &Config, insecure: bool, agent_mode: bool, suppo
             eprintln!("Warning: Running commands without kernel sandbox isolation (--insecure).");
         }
 
-        let m = Arc::new(qq_tools::SandboxMounts::new(root.clone())
-            .context("Failed to create per-instance /tmp directory")?);
+        let mut m = qq_tools::SandboxMounts::new(root.clone())
+            .context("Failed to create per-instance /tmp directory")?
+            .with_follow_symlinks(config.tools.bash_follow_symlinks);
+        if let Some(allowed) = policy.allowed_mounts() {
+            m = m.with_allowed_mounts(allowed.to_vec());
+        }
+        let m = Arc::new(m);
 
         // Add configured extra mounts
         for mount_path in &config.tools.bash_mounts {
             let expanded = expand_path(mount_path);
             if expanded.exists() && expanded.is_dir() {
-                m.add_mount(qq_tools::MountPoint {
-                    host_path: expanded,
+                let host_path = expanded.canonicalize().unwrap_or(expanded);
+                if let Err(e) = m.add_mount(qq_tools::MountPoint {
+                    host_path,
                     label: None,
-                });
+                }) {
+                    eprintln!("Warning: Skipping mount: {}", e);
+                }
             } else {
                 tracing::warn!(path = %mount_path, "Mount path does not exist or is not a directory");
             }
@@ -341,6 +702,25 @@ fn build_tools_registry(config: &Config, insecure: bool, agent_mode: bool, suppo
         (None, None)
     };
 
+    // Reads of untracked files, from run and the file tools
+    let access_log = match (&mounts, &approval_tx) {
+        (Some(mounts), Some(approval_tx)) => Some(Arc::new(
+            qq_tools::AccessLog::new(mounts.project_root())
+                .with_policy(config.tools.untracked_reads, approval_tx.clone()),
+        )),
+        _ => None,
+    };
+
+    // Date/time tool (always available)
+    registry.register(Arc::new(qq_tools::DateTimeTool::new()));
+
+    // Environment facts (always available; reports the sandbox backend when run is enabled)
+    let env_info = match executor {
+        Some(ref executor) => qq_tools::EnvInfoTool::new().with_sandbox(executor),
+        None => qq_tools::EnvInfoTool::new(),
+    };
+    registry.register(Arc::new(env_info));
+
     // Image tools (only if model supports image content)
     if supported_mimetypes.iter().any(|t| t == "image") {
         for tool in qq_tools::create_image_tools(root.clone()) {
@@ -348,18 +728,31 @@ fn build_tools_registry(config: &Config, insecure: bool, agent_mode: bool, suppo
         }
     }
 
+    // Document text extraction (PDF, DOCX, EPUB under the project root)
+    let mut read_document = qq_tools::ReadDocumentTool::new(root.clone());
+    if let Some(ref log) = access_log {
+        read_document = read_document.with_access_log(Arc::clone(log));
+    }
+    registry.register(Arc::new(read_document));
+
+    // Plugin WebAssembly modules run in-process and don't need the bash sandbox
+    plugins::register_module_tools(plugins, &mut registry, &root);
+
     // Web tools
     if config.tools.enable_web {
-        let web_search_config = config.tools.web_search.as_ref().map(|ws| {
-            qq_tools::WebSearchConfig::new(&ws.host, &ws.chat_model, &ws.embed_model)
-        });
+        let web_search_config = config
+            .tools
+            .web_search
+            .as_ref()
+            .map(|ws| ws.to_search_config())
+            .transpose()?;
         for tool in qq_tools::create_web_tools_with_search(web_search_config) {
             registry.register(tool);
         }
     }
 
     // Run tools
-    let run_resources = if let Some(mounts) = mounts {
+    let run_resources = if let (Some(mounts), Some(executor), Some(access_log)) = (mounts, executor, access_log) {
         // Build permission overrides from config
         let overrides = config.tools.bash_permissions.as_ref()
             .map(|p| parse_config_overrides(&p.session, &p.per_call, &p.restricted))
@@ -375,12 +768,26 @@ fn build_tools_registry(config: &Config, insecure: bool, agent_mode: bool, suppo
             qq_tools::SandboxPathPolicy::from_host_env(&config.tools.bash_sensitive_dirs)
         };
 
+        let write_classifiers = build_write_classifiers(config, mounts.project_root())?;
+        let explainer = build_command_explainer(config)?;
+        // map_test_coverage diffs against the worktree as the session started
+        let session_base = session_diff::SessionBaseline::capture(mounts.project_root())
+            .map(|baseline| baseline.base().to_string());
+
+        // Plugin tools run in the same sandbox, limited to their grants
+        plugins::register_tools(plugins, &mut registry, &mounts, &path_policy, &executor);
+
         let (run_tools, read_only_run) = qq_tools::create_run_tools(
             Arc::clone(&mounts),
             Arc::clone(&permissions),
             approval_tx,
-            path_policy,
+            path_policy.clone(),
             ask_network,
+            executor.clone(),
+            write_classifiers,
+            Arc::clone(&access_log),
+            explainer,
+            session_base,
         );
         for tool in run_tools {
             registry.register(tool);
@@ -390,18 +797,194 @@ fn build_tools_registry(config: &Config, insecure: bool, agent_mode: bool, suppo
         Some(RunResources {
             mounts,
             permissions,
+            access_log,
+            executor,
+            path_policy,
         })
     } else {
+        if plugins.iter().any(|p| !p.tools.is_empty()) {
+            eprintln!("Warning: plugin tools disabled: they run in the bash sandbox (tools.enable_bash)");
+        }
         None
     };
 
     Ok((registry, run_resources, approval_rx))
 }
 
+/// Write classifiers for the run tool from `[tools.write_policy]` and
+/// `[tools] protected_paths`: the path rules, then the classifier model if
+/// one is configured.
+fn build_write_classifiers(
+    config: &Config,
+    root: &std::path::Path,
+) -> Result<Vec<Arc<dyn qq_tools::WriteClassifier>>> {
+    let policy = &config.tools.write_policy;
+    let mut classifiers: Vec<Arc<dyn qq_tools::WriteClassifier>> = Vec::new();
+    let rules: Vec<qq_tools::WriteRule> = policy
+        .rules
+        .iter()
+        .cloned()
+        .chain(config.tools.protected_paths.iter().map(qq_tools::WriteRule::protected))
+        .collect();
+    if !rules.is_empty() {
+        classifiers.push(Arc::new(qq_tools::WritePolicy::new(rules, root)));
+    }
+    if let Some(ref profile) = policy.classifier_profile {
+        if policy.policies.is_empty() {
+            tracing::warn!(profile = %profile, "write_policy.classifier_profile is set but no policies are listed");
+        } else {
+            let settings = resolve_settings_for_profile_name(profile, config)
+                .context("Invalid [tools.write_policy] classifier_profile")?;
+            let provider: Arc<dyn Provider> = Arc::from(create_provider_from_settings(&settings)?);
+            classifiers.push(Arc::new(qq_tools::ModelWriteClassifier::new(
+                provider,
+                policy.policies.clone(),
+            )));
+        }
+    }
+    Ok(classifiers)
+}
+
+/// Explainer for commands awaiting approval, from `[tools.explain]`.
+fn build_command_explainer(config: &Config) -> Result<Option<Arc<qq_tools::CommandExplainer>>> {
+    let entry = &config.tools.explain;
+    if !entry.enabled {
+        return Ok(None);
+    }
+    let mut explainer = qq_tools::CommandExplainer::new();
+    if let Some(ref profile) = entry.profile {
+        let settings = resolve_settings_for_profile_name(profile, config)
+            .context("Invalid [tools.explain] profile")?;
+        let provider: Arc<dyn Provider> = Arc::from(create_provider_from_settings(&settings)?);
+        explainer = explainer.with_model(provider);
+    }
+    Ok(Some(Arc::new(explainer)))
+}
+
+/// `registry` with long tool descriptions shortened if its definitions
+/// outgrow `[tools] description_budget` of the context window.
+fn fit_tool_descriptions(registry: ToolRegistry, config: &Config, context_window: Option<u32>) -> ToolRegistry {
+    match context_window {
+        Some(cw) => qq_core::fit_descriptions(&registry, cw, config.tools.description_budget),
+        None => registry,
+    }
+}
+
+/// `attach_document`, when `provider` can hold uploaded files.
+fn register_document_tool(registry: &mut ToolRegistry, config: &Config, provider: &Arc<dyn Provider>) {
+    if provider.supports_file_upload() {
+        registry.register(Arc::new(qq_tools::AttachDocumentTool::new(
+            tools_root(config),
+            Arc::clone(provider),
+        )));
+    }
+}
+
+/// Summarizer for the tools root's files (`[tools.summaries]`), writing with
+/// the configured profile's model or else `provider`.
+fn build_file_summarizer(config: &Config, provider: &Arc<dyn Provider>, access_log: Option<&Arc<qq_tools::AccessLog>>) -> Result<Option<Arc<qq_tools::FileSummarizer>>> {
+    let entry = &config.tools.summaries;
+    let Some(dir) = paths::summaries_dir().filter(|_| entry.enabled) else {
+        return Ok(None);
+    };
+    let provider = match entry.profile {
+        Some(ref profile) => {
+            let settings = resolve_settings_for_profile_name(profile, config)
+                .context("Invalid [tools.summaries] profile")?;
+            Arc::from(create_provider_from_settings(&settings)?)
+        }
+        None => Arc::clone(provider),
+    };
+    let mut summarizer = qq_tools::FileSummarizer::new(tools_root(config), provider, &dir);
+    if let Some(log) = access_log {
+        summarizer = summarizer.with_access_log(Arc::clone(log));
+    }
+    Ok(Some(Arc::new(summarizer)))
+}
+
+/// `summarize_file`, unless `[tools.summaries]` disables it. With the run
+/// tool enabled, summaries of untracked files go through its access log.
+fn register_summary_tool(registry: &mut ToolRegistry, config: &Config, provider: &Arc<dyn Provider>, run: Option<&RunResources>) -> Result<()> {
+    if let Some(summarizer) = build_file_summarizer(config, provider, run.map(|r| &r.access_log))? {
+        registry.register(Arc::new(qq_tools::SummarizeFileTool::new(summarizer)));
+    }
+    Ok(())
+}
+
+/// `hybrid_search`, unless `[tools.hybrid_search]` disables it; embeddings
+/// come from `embedding_profile`'s provider or else `provider`. With the run
+/// tool enabled, ripgrep runs in its sandbox and reads go through its access
+/// log.
+fn register_hybrid_search_tool(registry: &mut ToolRegistry, config: &Config, provider: &Arc<dyn Provider>, run: Option<&RunResources>) -> Result<()> {
+    let entry = &config.tools.hybrid_search;
+    if !entry.enabled {
+        return Ok(());
+    }
+    let embedder: Arc<dyn Provider> = match entry.embedding_profile {
+        Some(ref profile) => {
+            let settings = resolve_settings_for_profile_name(profile, config)
+                .context("Invalid [tools.hybrid_search] embedding_profile")?;
+            Arc::from(create_provider_from_settings(&settings)?)
+        }
+        None => Arc::clone(provider),
+    };
+    let mut searcher = qq_tools::HybridSearcher::new(tools_root(config), entry.to_search_config())
+        .with_embedder(embedder);
+    if let Some(run) = run {
+        searcher = searcher
+            .with_sandbox(Arc::clone(&run.mounts), run.executor.clone(), run.path_policy.clone())
+            .with_access_log(Arc::clone(&run.access_log));
+    }
+    registry.register(Arc::new(qq_tools::HybridSearchTool::new(Arc::new(searcher))));
+    Ok(())
+}
+
+/// Save large tool outputs as artifacts (`[tools.artifacts]`) and add the
+/// `fetch_artifact` tool that reads them back.
+fn register_artifacts(registry: &mut ToolRegistry, config: &Config) {
+    let entry = &config.tools.artifacts;
+    if !entry.enabled || registry.is_empty() {
+        return;
+    }
+    let Some(dir) = paths::artifacts_dir() else {
+        return;
+    };
+    let store = match qq_tools::ArtifactStore::open(&dir) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            tracing::warn!(dir = %dir.display(), "Artifact store unavailable: {}", e);
+            return;
+        }
+    };
+    let pruned = store.prune(std::time::Duration::from_secs(entry.max_age_days * 86_400));
+    if pruned > 0 {
+        tracing::debug!(pruned, "Pruned old artifacts");
+    }
+    let policy = entry.to_policy();
+    *registry = qq_tools::offload(registry, &store, policy);
+    registry.register(Arc::new(qq_tools::FetchArtifactTool::new(store, policy)));
+}
+
+/// Chunker config from `[tools.chunker]`, with the provider of
+/// `embedding_profile` attached for chunk ranking.
+fn build_chunker_config(config: &Config) -> Result<qq_core::ChunkerConfig> {
+    let entry = &config.tools.chunker;
+    let mut chunker_config = entry.to_chunker_config();
+    if let Some(ref profile) = entry.embedding_profile {
+        if entry.embedding_model.is_none() {
+            tracing::warn!(profile = %profile, "chunker.embedding_profile is set but no embedding_model is configured");
+        } else {
+            let settings = resolve_settings_for_profile_name(profile, config)
+                .context("Invalid [tools.chunker] embedding_profile")?;
+            let provider: Arc<dyn Provider> = Arc::from(create_provider_from_settings(&settings)?);
+            chunker_config = chunker_config.with_embedder(provider);
+        }
+    }
+    Ok(chunker_config)
+}
+
 fn is_apparmor_restricting_userns() -> bool {
-    std::fs::read_to_string("/proc/sys/kernel/apparmor_restrict_unprivileged_userns")
-        .map(|s| s.trim() == "1")
-        .unwrap_or(false)
+    doctor::apparmor_restricts_userns() == Some(true)
 }
 
 async fn completion_mode(cli: &Cli, config: &Config, prompt: &str) -> Result<()> {
@@ -412,22 +995,51 @@ async fn completion_mode(cli: &Cli, config: &Config, prompt: &str) -> Result<()>
     // Set up tools
     let mimetypes = settings.supported_content_types.clone()
         .unwrap_or_else(|| vec!["text".into(), "image".into()]);
-    let (tools_registry, _run_resources, _approval_rx) = build_tools_registry(config, cli.insecure, cli.agent_mode, &mimetypes, cli.ask_network)?;
+    let plugins = plugins::load(&config.plugins);
+    let (mut tools_registry, run_resources, _approval_rx) = build_tools_registry(config, cli.insecure, cli.agent_mode, &mimetypes, cli.ask_network, &plugins)?;
+    register_document_tool(&mut tools_registry, config, &provider);
+    register_summary_tool(&mut tools_registry, config, &provider, run_resources.as_ref())?;
+    register_hybrid_search_tool(&mut tools_registry, config, &provider, run_resources.as_ref())?;
+    register_artifacts(&mut tools_registry, config);
 
     // Set up chunk processor for large tool outputs
-    let chunker_config = config.tools.chunker.to_chunker_config();
+    let chunker_config = build_chunker_config(config)?;
     let chunk_processor = ChunkProcessor::new(Arc::clone(&provider), chunker_config.clone());
 
-    let mut messages = Vec::new();
-
-    // Add system prompt (CLI overrides profile)
-    if let Some(system) = &settings.system_prompt {
-        messages.push(Message::system(system.as_str()));
-    }
+    // Fold older turns into an observation log if a long run outgrows the context
+    let context_window = settings.context_window.or_else(|| provider.context_window());
+    let (observation_config, compactor) = build_compaction(config, &provider, context_window)?;
+    // The saved conversation this run continues (`--continue`, `--session`),
+    // saved again with this exchange for the next run
+    let resume = match (cli.session.as_deref(), cli.continue_session) {
+        (Some(id), _) => completion_session::Resume::Named(id),
+        (None, true) => completion_session::Resume::Latest,
+        (None, false) => completion_session::Resume::New,
+    };
+    let session = match paths::session_logs_dir() {
+        Some(dir) => Some(completion_session::CompletionSession::open(
+            &dir,
+            resume,
+            &tools_root(config),
+            &settings.profile_name,
+            settings.model.as_deref(),
+        )?),
+        None if resume != completion_session::Resume::New => {
+            anyhow::bail!("No state directory for saved sessions")
+        }
+        None => None,
+    };
+    let (session_log, history, observation_log) = match session {
+        Some(s) => (Some(s.log), s.messages, s.observation_log),
+        None => (None, Vec::new(), String::new()),
+    };
+    let mut transcript = Transcript::new(settings.system_prompt.clone())
+        .with_compaction(observation_config, compactor)
+        .with_history(history, observation_log);
 
-    // Build user message: text prompt + optional images
-    if cli.images.is_empty() {
-        messages.push(Message::user(prompt));
+    // Build user message: text prompt + optional images and documents
+    if cli.images.is_empty() && cli.attachments.is_empty() {
+        transcript.push(Message::user(prompt));
     } else {
         const MAX_IMAGE_SIZE: u64 = 20 * 1024 * 1024; // 20 MB
 
@@ -454,131 +1066,216 @@ async fn completion_mode(cli: &Cli, config: &Config, prompt: &str) -> Result<()>
             parts.push(TypedContent::Image { image });
         }
 
-        messages.push(Message::user(parts));
-    }
-
-    // Agentic loop - keep going while LLM returns tool calls
-    let max_iterations = 100;
-    for iteration in 0..max_iterations {
-        let mut request = CompletionRequest::new(messages.clone());
-
-        // Apply model
-        if let Some(model) = &settings.model {
-            request = request.with_model(model.as_str());
-        }
-
-        if let Some(temp) = cli.temperature {
-            request = request.with_temperature(temp);
-        }
-
-        if let Some(max_tokens) = cli.max_tokens {
-            request = request.with_max_tokens(max_tokens);
-        }
-        if let Some(top_k) = cli.top_k {
-            request = request.with_top_k(top_k);
+        for path in &cli.attachments {
+            let document = qq_core::attach_file(provider.as_ref(), path)
+                .await
+                .with_context(|| format!("Failed to attach {}", path.display()))?;
+            parts.push(document);
         }
 
-        if let Some(min_p) = cli.min_p {
-            request = request.with_min_p(min_p);
-        }
+        transcript.push(Message::user(parts));
+    }
 
-        if let Some(presence_penalty) = cli.presence_penalty {
-            request = request.with_presence_penalty(presence_penalty);
+    let tools_registry = fit_tool_descriptions(tools_registry, config, context_window);
+    let mut runner = AgentRunner::new(Arc::clone(&provider), tools_registry)
+        .with_chunker(chunk_processor)
+        .with_request(sampling_request(cli, settings.model.as_deref(), &settings.parameters))
+        .with_streaming(!cli.no_stream);
+    if let Some(regrounding) = CompactionConfig::regrounding(config.compaction.as_ref()) {
+        runner = runner.with_regrounding(regrounding);
+    }
+    let outcome = match cli.events {
+        Some(EventsFormat::Jsonl) => {
+            let mut events = match cli.events_file {
+                Some(ref path) => completion_events::EventWriter::create(path)?,
+                None => completion_events::EventWriter::stderr(),
+            };
+            match runner.run(&mut transcript, &mut events, prompt).await {
+                Ok(outcome) => {
+                    events.finish(&outcome);
+                    // Only the final reply goes to stdout
+                    if !outcome.content.is_empty() {
+                        println!("{}", outcome.content.trim_end_matches('\n'));
+                    }
+                    outcome
+                }
+                Err(e) => {
+                    events.error(&e.to_string());
+                    return Err(e.into());
+                }
+            }
         }
-
-        if let Some(repetition_penalty) = cli.repetition_penalty {
-            request = request.with_repetition_penalty(repetition_penalty);
+        None => {
+            runner
+                .run(&mut transcript, &mut CompletionPrinter::default(), prompt)
+                .await?
         }
+    };
+    if let Some(log) = session_log {
+        log.with_observation_log(transcript.observation_log())
+            .save(transcript.messages().to_vec());
+    }
 
-        // Add merged parameters
-        if !settings.parameters.is_empty() {
-            request = request.with_extra(settings.parameters.clone());
+    if outcome.max_iterations_reached {
+        // The done event reports it when stderr carries events
+        if cli.events.is_none() {
+            eprintln!("Warning: Max iterations ({}) reached", outcome.iterations);
         }
+        return Ok(());
+    }
+    if let Some(usage) = outcome.usage {
+        tracing::info!(
+            prompt_tokens = usage.prompt_tokens,
+            completion_tokens = usage.completion_tokens,
+            total_tokens = usage.total_tokens,
+            iterations = outcome.iterations,
+            "Completion finished"
+        );
+    }
+    Ok(())
+}
 
-        // Add tool definitions
-        request = request.with_tools(tools_registry.definitions());
+/// Prints a one-shot completion: response text to stdout as it arrives,
+/// retries and context recovery to stderr. Thinking is not shown.
+#[derive(Default)]
+struct CompletionPrinter {
+    /// Whether the current response has printed text without a final newline
+    mid_line: bool,
+}
 
-        // Non-streaming mode for tool handling
-        let response = provider.complete(request).await?;
+#[async_trait]
+impl RunHandler for CompletionPrinter {
+    async fn on_event(&mut self, event: RunEvent) {
+        use std::io::Write;
 
-        // Check if we have tool calls
-        if !response.message.tool_calls.is_empty() {
-            // Print assistant message if any (content only, not thinking)
-            let content = response.message.content.to_string_lossy();
-            if !content.is_empty() {
-                println!("{}", content);
+        match event {
+            RunEvent::ContentDelta(content) => {
+                print!("{}", content);
+                let _ = std::io::stdout().flush();
+                self.mid_line = !content.ends_with('\n');
             }
-
-            // Add assistant message to history with empty content (don't store potential thinking)
-            // Note: Some providers may leak thinking into content; we explicitly clear it
-            let assistant_msg = Message::assistant_with_tool_calls("", response.message.tool_calls.clone());
-            messages.push(assistant_msg);
-
-            // Execute tools in parallel with chunking support
-            let tool_calls = response.message.tool_calls.clone();
-
-            for tool_call in &tool_calls {
-                tracing::debug!(
-                    tool = %tool_call.name,
-                    arguments = %tool_call.arguments,
-                    "Executing tool"
-                );
+            RunEvent::Response { .. } if self.mid_line => {
+                println!();
+                self.mid_line = false;
+            }
+            RunEvent::Retry { attempt, max_retries, error } => {
+                eprintln!("[retrying ({}/{}): {}]", attempt, max_retries, error);
+            }
+            RunEvent::ContextRecovered { action } => {
+                eprintln!("[context window exceeded: {}; retrying]", action);
+            }
+            RunEvent::Compacted { action } => {
+                tracing::info!(action = %action, "Compacted completion context");
             }
+            RunEvent::Regrounded { trigger, .. } => {
+                tracing::info!(trigger = %trigger, "Re-grounded completion on the prompt");
+            }
+            _ => {}
+        }
+    }
+}
 
-            let results = execute_tools_parallel_with_chunker(
-                &tools_registry,
-                tool_calls,
-                Some(&chunk_processor),
-                Some(prompt),
-            )
-            .await;
-
-            for result in results {
-                let result_text = result.text_content();
-                let qq_core::ToolExecutionResult { tool_call_id, content, is_error, .. } = result;
-                tracing::debug!(
-                    tool_call_id = %tool_call_id,
-                    result_len = result_text.len(),
-                    is_error = is_error,
-                    "Tool result"
-                );
-                tracing::trace!(
-                    tool_call_id = %tool_call_id,
-                    content = %result_text,
-                    "Tool result content"
-                );
+/// Request template carrying the model and the sampling flags given on the
+/// command line, plus the profile's extra parameters.
+fn sampling_request(
+    cli: &Cli,
+    model: Option<&str>,
+    parameters: &std::collections::HashMap<String, serde_json::Value>,
+) -> CompletionRequest {
+    let mut request = CompletionRequest::new(Vec::new());
+    request.model = model.map(str::to_string);
+    request.temperature = cli.temperature;
+    request.max_tokens = cli.max_tokens;
+    request.top_k = cli.top_k;
+    request.min_p = cli.min_p;
+    request.presence_penalty = cli.presence_penalty;
+    request.repetition_penalty = cli.repetition_penalty;
+    request.extra = parameters.clone();
+    request
+}
 
-                // Add tool result to messages (preserves images)
-                messages.push(Message::tool_result(&tool_call_id, content));
-            }
+/// Observation thresholds and the compactor that summarizes old turns:
+/// `[compaction]` settings if given, else sized to the context window.
+fn build_compaction(
+    config: &Config,
+    provider: &Arc<dyn Provider>,
+    context_window: Option<u32>,
+) -> Result<(qq_core::ObservationConfig, Arc<dyn ContextCompactor>)> {
+    let observation_config = config
+        .compaction
+        .as_ref()
+        .map(|c| c.to_observation_config(context_window))
+        .unwrap_or_else(|| match context_window {
+            Some(cw) => qq_core::ObservationConfig::from_context_window(cw),
+            None => qq_core::ObservationConfig::default(),
+        });
 
-            // Continue loop to get next response
-            continue;
+    let compaction_provider = if let Some(ref comp_config) = config.compaction {
+        if let Some(ref provider_name) = comp_config.provider {
+            // Create a separate provider for compaction
+            let comp_settings = resolve_settings_for_provider(provider_name, config)
+                .context("Invalid [compaction] provider")?;
+            Arc::from(create_provider_from_settings(&comp_settings)?)
+        } else {
+            Arc::clone(provider)
         }
+    } else {
+        Arc::clone(provider)
+    };
 
-        // No tool calls - print final response and exit
-        println!("{}", response.message.content.to_string_lossy());
+    let model_override = config
+        .compaction
+        .as_ref()
+        .and_then(|c| c.model.clone());
 
-        tracing::info!(
-            prompt_tokens = response.usage.prompt_tokens,
-            completion_tokens = response.usage.completion_tokens,
-            total_tokens = response.usage.total_tokens,
-            iterations = iteration + 1,
-            "Completion finished"
-        );
+    let compactor: Arc<dyn ContextCompactor> = Arc::new(compaction::LlmCompactor::new(
+        compaction_provider,
+        model_override,
+    ));
+    Ok((observation_config, compactor))
+}
 
-        return Ok(());
+/// Sliding-window memory from `[sliding_window]`, with the provider of
+/// `embedding_profile` (or the session's) serving the embeddings.
+fn build_sliding_window(
+    config: &Config,
+    provider: &Arc<dyn Provider>,
+) -> Result<qq_core::SlidingWindowMemory> {
+    let entry = &config.sliding_window;
+    if entry.embedding_model.is_none() {
+        tracing::warn!("sliding_window.embedding_model is not set; messages leaving the window won't be recalled");
     }
-
-    eprintln!("Warning: Max iterations ({}) reached", max_iterations);
-    Ok(())
+    let embedder: Arc<dyn Provider> = match entry.embedding_profile {
+        Some(ref profile) => {
+            let settings = resolve_settings_for_profile_name(profile, config)
+                .context("Invalid [sliding_window] embedding_profile")?;
+            Arc::from(create_provider_from_settings(&settings)?)
+        }
+        None => Arc::clone(provider),
+    };
+    Ok(qq_core::SlidingWindowMemory::new(
+        entry.to_sliding_window_config(),
+        embedder,
+    ))
 }
 
-async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result<()> {
+async fn chat_mode(
+    cli: &Cli,
+    config: &Config,
+    system: Option<String>,
+    template: Option<&templates::SessionTemplate>,
+) -> Result<()> {
     // Resolve settings from profile, CLI, and config
     let settings = resolve_settings(cli, config)?;
     let provider: Arc<dyn Provider> = Arc::from(create_provider_from_settings(&settings)?);
 
+    // Registered until this session ends; others share the state files
+    let (active_session, others) =
+        active_sessions::ActiveSession::register(paths::sessions_dir().as_deref());
+    if let Some(notice) = active_sessions::format_others(&others) {
+        eprintln!("{}", notice);
+    }
+
     // Determine system prompt: explicit arg > CLI > profile
     let user_system_prompt = system
         .or_else(|| cli.system.clone())
@@ -604,6 +1301,7 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
             has_bash: false,          // PM delegates bash to sub-agents
             has_network: !cli.ask_network,
             is_read_only: false,
+            has_finish: false,        // PM answers the user directly
         }, &agent_ctx);
         let combined = format!("{}\n\n---\n\n{}", preamble, base_prompt);
         match user_system_prompt {
@@ -617,6 +1315,13 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
         user_system_prompt
     };
 
+    // Plugin agents and tools (none with --no-tools)
+    let plugins = if disable_tools {
+        Vec::new()
+    } else {
+        plugins::load(&config.plugins)
+    };
+
     // Set up base tools (conditionally)
     let (mut base_tools, run_resources, approval_rx) = if disable_tools {
         tracing::debug!("Tools disabled (--no-tools or --minimal)");
@@ -624,7 +1329,11 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
     } else {
         let mimetypes = settings.supported_content_types.clone()
             .unwrap_or_else(|| vec!["text".into(), "image".into()]);
-        build_tools_registry(config, cli.insecure, cli.agent_mode, &mimetypes, cli.ask_network)?
+        let mut tools = build_tools_registry(config, cli.insecure, cli.agent_mode, &mimetypes, cli.ask_network, &plugins)?;
+        register_document_tool(&mut tools.0, config, &provider);
+        register_summary_tool(&mut tools.0, config, &provider, tools.1.as_ref())?;
+        register_hybrid_search_tool(&mut tools.0, config, &provider, tools.1.as_ref())?;
+        tools
     };
 
     // Register task tracking tools (session-scoped, in-memory)
@@ -638,6 +1347,66 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
         None
     };
 
+    // Persisted reminders: due ones become tasks on this session's board
+    let system_prompt = match task_store {
+        Some(ref tasks) => {
+            let reminders = Arc::new(
+                paths::reminders_file()
+                    .map(qq_tools::ReminderStore::load)
+                    .unwrap_or_else(qq_tools::ReminderStore::in_memory),
+            );
+            base_tools.register(Arc::new(qq_tools::ScheduleReminderTool::new(Arc::clone(&reminders))));
+            let surfaced = reminders.surface_due(tasks, chrono::Utc::now());
+            match qq_tools::format_surfaced(&surfaced) {
+                Some(note) => {
+                    eprintln!("{} due reminder(s) added to the task board", surfaced.len());
+                    Some(match system_prompt {
+                        Some(prompt) => format!("{}\n\n---\n\n{}", prompt, note),
+                        None => note,
+                    })
+                }
+                None => system_prompt,
+            }
+        }
+        None => system_prompt,
+    };
+
+    // Session template: extra context and attached files, plus seeded tasks
+    let system_prompt = match template {
+        Some(template) => {
+            if let Some(ref tasks) = task_store {
+                for task in &template.tasks {
+                    task.seed(tasks);
+                }
+                if !template.tasks.is_empty() {
+                    eprintln!("{} template task(s) added to the task board", template.tasks.len());
+                }
+            }
+            match template.build_context(&tools_root(config)) {
+                Some(context) => Some(match system_prompt {
+                    Some(prompt) => format!("{}\n\n---\n\n{}", prompt, context),
+                    None => context,
+                }),
+                None => system_prompt,
+            }
+        }
+        None => system_prompt,
+    };
+
+    // Cross-session knowledge base, scoped to the project (tools root)
+    let knowledge = if config.knowledge.enabled && !disable_tools {
+        let project = knowledge_project(config);
+        let session = active_session.id().to_string();
+        let store = Arc::new(match paths::knowledge_file() {
+            Some(path) => qq_tools::KnowledgeStore::load(path, project, session),
+            None => qq_tools::KnowledgeStore::in_memory(project, session),
+        });
+        base_tools.register(Arc::new(qq_tools::RecallTool::new(Arc::clone(&store))));
+        Some(store)
+    } else {
+        None
+    };
+
     // Connect to MCP servers
     let mcp_manager = if !config.mcp_servers.is_empty() && !disable_tools {
         let manager = qq_mcp::McpManager::connect_all(&config.mcp_servers).await;
@@ -657,7 +1426,17 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
     };
 
     // Load agents config
-    let agents_config = AgentsConfig::load().unwrap_or_default();
+    let mut agents_config = AgentsConfig::load().unwrap_or_default();
+    plugins::merge_agents(&plugins, &mut agents_config);
+    for spec in &cli.variants {
+        let (agent, variant) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("--variant expects AGENT=VARIANT, got '{}'", spec))?;
+        agents_config.force_variant(agent.trim(), variant.trim())?;
+    }
+    agents_config.verify = settings.verify.clone();
+    agents_config.review = settings.review.clone();
+    agents_config.description_budget = Some(config.tools.description_budget);
 
     // Create execution context for tracking agent/tool call stack
     let execution_context = ExecutionContext::new();
@@ -688,8 +1467,67 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
         event_bus = event_bus.with_debug_logger(Arc::clone(logger));
     }
 
+    // Lifecycle hooks: sub-agent events come through the bus, the primary
+    // agent's tool calls from the session loop
+    let hooks = if config.hooks.is_empty() {
+        None
+    } else {
+        let hooks = hooks::Hooks::new(config.hooks.clone(), tools_root(config))
+            .context("Invalid [[hooks]] in config")?;
+        Some(Arc::new(hooks))
+    };
+    if let Some(ref hooks) = hooks {
+        event_bus = event_bus.with_hooks(Arc::clone(hooks));
+    }
+
+    // Sub-agent runs, saved for `qq graph` and summarized when the session ends
+    let delegation_graph = Arc::new(
+        delegation_graph::DelegationGraph::new().with_file(
+            paths::graphs_dir().map(|d| d.join(format!("{}.json", active_session.id()))),
+        ),
+    );
+    event_bus = event_bus.with_delegation_graph(Arc::clone(&delegation_graph));
+
+    // External UI bridge: mirrors the bus and lets clients answer approvals
+    let event_socket = cli
+        .event_socket
+        .as_deref()
+        .map(|path| event_socket::EventSocket::bind(path, &event_bus))
+        .transpose()?;
+    let approval_rx = match (&event_socket, approval_rx) {
+        (Some(socket), Some(rx)) => Some(socket.interpose_approvals(rx)),
+        (_, rx) => rx,
+    };
+
+    // Push approvals to a phone for unattended runs, with a deny timeout
+    let approval_rx = match (&config.remote_approval, approval_rx) {
+        (Some(remote), Some(rx)) => Some(remote_approval::RemoteApprovals::new(remote)?.interpose(rx)),
+        (_, rx) => rx,
+    };
+
+    // Live Markdown transcript of the session
+    let mirror = cli
+        .mirror_file
+        .as_deref()
+        .map(mirror::SessionMirror::create)
+        .transpose()?;
+    if let Some(ref mirror) = mirror {
+        mirror.follow(&event_bus);
+    }
+
+    // The conversation, saved when the session ends for `qq share`
+    let session_log = paths::session_logs_dir().map(|dir| {
+        share::SessionLog::new(
+            &dir,
+            active_session.id(),
+            &tools_root(config),
+            &settings.profile_name,
+            settings.model.as_deref(),
+        )
+    });
+
     // Create scoped agent memory for persistent instance state
-    let agent_memory = AgentMemory::new();
+    let agent_memory = AgentMemory::new().with_eviction(agents_config.instances.eviction());
 
     // Resolve context window: config override > provider trait (known model lookup)
     // For OpenAI-compatible with custom base_url, we could probe, but that's
@@ -702,37 +1540,11 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
     }
 
     // Create observational memory compactor (used by both ChatSession and agents)
-    let observation_config = config
-        .compaction
-        .as_ref()
-        .map(|c| c.to_observation_config(context_window))
-        .unwrap_or_else(|| match context_window {
-            Some(cw) => qq_core::ObservationConfig::from_context_window(cw),
-            None => qq_core::ObservationConfig::default(),
-        });
-
-    let compactor: Option<Arc<dyn ContextCompactor>> = {
-        let compaction_provider = if let Some(ref comp_config) = config.compaction {
-            if let Some(ref provider_name) = comp_config.provider {
-                // Create a separate provider for compaction
-                let comp_settings = resolve_settings_for_provider(provider_name, config)?;
-                Arc::from(create_provider_from_settings(&comp_settings)?)
-            } else {
-                Arc::clone(&provider)
-            }
-        } else {
-            Arc::clone(&provider)
-        };
-
-        let model_override = config
-            .compaction
-            .as_ref()
-            .and_then(|c| c.model.clone());
-
-        Some(Arc::new(compaction::LlmCompactor::new(
-            compaction_provider,
-            model_override,
-        )))
+    let (observation_config, compactor) = build_compaction(config, &provider, context_window)?;
+    let compactor = Some(compactor);
+    let sliding_window = match settings.memory_strategy {
+        AgentMemoryStrategy::SlidingWindow => Some(build_sliding_window(config, &provider)?),
+        AgentMemoryStrategy::ObsMemory | AgentMemoryStrategy::Compaction => None,
     };
 
     // Build the profile registry for the session. The default profile uses
@@ -742,6 +1554,32 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
     let default_runtime = runtime_from_settings(settings.clone(), Arc::clone(&provider));
     let profile_registry = build_profile_registry(config, &agents_config, default_runtime)?;
 
+    // Route low-complexity turns to a cheaper profile when [routing] is set
+    let model_router = match config.routing {
+        Some(ref routing) => {
+            let runtime = profile_registry
+                .read()
+                .await
+                .get(&routing.profile)
+                .with_context(|| format!("Routing profile '{}' not found in [profiles]", routing.profile))?;
+            Some(routing::ModelRouter::new(routing.to_policy(), runtime))
+        }
+        None => None,
+    };
+
+    // Large outputs of every base tool become artifacts the model can page through
+    register_artifacts(&mut base_tools, config);
+
+    // Supervised mode: every tool call, sub-agents' included, is reviewed
+    // by the user first
+    let (review_channel, review_rx) = if cli.supervised && !disable_tools {
+        let (channel, rx) = qq_tools::create_review_channel();
+        base_tools = qq_tools::supervise(&base_tools, &channel);
+        (Some(channel), Some(rx))
+    } else {
+        (None, None)
+    };
+
     // Create agent tools (conditionally)
     let agent_tools = if disable_agents || disable_tools {
         if disable_agents {
@@ -762,16 +1600,66 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
             "pm".to_string(),
             task_store.clone(),
             compactor.clone(),
+            CompactionConfig::regrounding(config.compaction.as_ref()),
             context_window,
             cli.ask_network,
             qq_core::DelegationPermissions::default(),
         )
     };
 
+    // Save what capture agents find so later sessions can recall it
+    let agent_tools: Vec<Arc<dyn qq_core::Tool>> = match knowledge {
+        Some(ref store) => agent_tools
+            .into_iter()
+            .map(|tool| {
+                let agent = config
+                    .knowledge
+                    .capture_agents
+                    .iter()
+                    .find(|a| tool.name() == format!("Agent[{}]", a));
+                match agent {
+                    Some(agent) => Arc::new(qq_tools::KnowledgeCaptureTool::new(
+                        tool,
+                        Arc::clone(store),
+                        agent.as_str(),
+                    )) as Arc<dyn qq_core::Tool>,
+                    None => tool,
+                }
+            })
+            .collect(),
+        None => agent_tools,
+    };
+
+    // Named agent instances the PM can re-attach across sessions
+    let instance_tool = (!agent_tools.is_empty()).then(|| {
+        let project = knowledge_project(config);
+        let store = Arc::new(match paths::agent_instances_file() {
+            Some(path) => {
+                agents::NamedInstanceStore::load(path, project, agents_config.instances.named_ttl())
+            }
+            None => agents::NamedInstanceStore::in_memory(project),
+        });
+        Arc::new(agents::UseAgentInstanceTool::new(
+            agent_tools.clone(),
+            agent_memory.clone(),
+            store,
+            "pm",
+        ))
+    });
+
     // Build the tools registry with base tools and agent tools
     let mut tools_registry = base_tools.clone();
+    let supervised = |tool: Arc<dyn qq_core::Tool>| match review_channel {
+        Some(ref channel) => {
+            Arc::new(qq_tools::SupervisedTool::new(tool, channel.clone())) as Arc<dyn qq_core::Tool>
+        }
+        None => tool,
+    };
     for tool in agent_tools {
-        tools_registry.register(tool);
+        tools_registry.register(supervised(tool));
+    }
+    if let Some(tool) = instance_tool {
+        tools_registry.register(supervised(tool));
     }
 
     // Add inform_user tool for the main chat (allows primary agent to notify user)
@@ -779,9 +1667,10 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
         event_bus.clone(),
         "assistant", // Primary agent name for main chat
     )));
+    let tools_registry = fit_tool_descriptions(tools_registry, config, context_window);
 
     // Set up chunker config
-    let chunker_config = config.tools.chunker.to_chunker_config();
+    let chunker_config = build_chunker_config(config)?;
 
     // Create executor for manual agent commands (@agent, /delegate)
     // Uses base_tools so manual commands also can't recurse
@@ -798,11 +1687,19 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
     };
 
     // Destructure run resources for TUI/CLI
-    let (bash_mounts, bash_permissions) = match run_resources {
-        Some(rr) => (Some(rr.mounts), Some(rr.permissions)),
-        None => (None, None),
+    let (bash_mounts, bash_permissions, access_log) = match run_resources {
+        Some(rr) => (Some(rr.mounts), Some(rr.permissions), Some(rr.access_log)),
+        None => (None, None, None),
     };
 
+    if let Some(ref hooks) = hooks {
+        hooks.fire(hooks::HookEvent::SessionStart {
+            mode: if use_tui { "tui" } else { "chat" }.to_string(),
+            profile: settings.profile_name.clone(),
+            model: settings.model.clone(),
+        });
+    }
+
     let result = if use_tui {
         tui::run_tui(
             cli,
@@ -822,11 +1719,19 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
             agent_memory.clone(),
             bash_mounts.clone(),
             approval_rx,
+            review_rx,
             bash_permissions.clone(),
+            access_log.clone(),
             task_store.clone(),
             compactor.clone(),
             observation_config.clone(),
+            sliding_window,
             mcp_manager.as_ref(),
+            event_socket.clone(),
+            mirror.clone(),
+            session_log,
+            model_router.clone(),
+            knowledge.clone(),
         )
         .await
     } else {
@@ -846,11 +1751,19 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
             agent_memory.clone(),
             bash_mounts,
             approval_rx,
+            review_rx,
             bash_permissions,
+            access_log,
             task_store,
             compactor,
             observation_config,
+            sliding_window,
             mcp_manager.as_ref(),
+            event_socket,
+            mirror,
+            session_log,
+            model_router,
+            knowledge,
         )
         .await
     };
@@ -860,9 +1773,444 @@ async fn chat_mode(cli: &Cli, config: &Config, system: Option<String>) -> Result
         manager.shutdown().await;
     }
 
+    if let Some(hooks) = hooks {
+        hooks.finish().await;
+    }
+
+    // The TUI has left the alternate screen by now, so this stays visible
+    if let Some(summary) = delegation_graph.render_ascii() {
+        eprintln!("\n{}(qq graph {} --format dot|mermaid to export)", summary, active_session.id());
+    }
+
     result
 }
 
+/// Remember this session's explicit choices for the project and fill what the
+/// command line (and template) left unset from the project's record.
+fn apply_project_defaults(
+    cli: &mut Cli,
+    config: &mut Config,
+    explicit: &project_defaults::ProjectDefaults,
+) {
+    let Some(path) = paths::project_defaults_file() else {
+        return;
+    };
+    let project = project_defaults::project_key(&tools_root(config));
+    let saved = project_defaults::remember(&path, &project, explicit, cli.no_project_defaults);
+
+    let mut session = project_defaults::ProjectDefaults {
+        profile: cli.profile.take(),
+        agent: cli.agent.take(),
+        model: cli.model.take(),
+        insecure: cli.insecure,
+        agent_mode: cli.agent_mode,
+        ask_network: cli.ask_network,
+        mounts: config.tools.bash_mounts.clone(),
+    };
+    let applied = session.fill_from(&project_defaults::ProjectDefaults {
+        mounts: project_defaults::existing_mounts(&saved),
+        ..saved
+    });
+    cli.profile = session.profile;
+    cli.agent = session.agent;
+    cli.model = session.model;
+    cli.insecure = session.insecure;
+    cli.agent_mode = session.agent_mode;
+    cli.ask_network = session.ask_network;
+    config.tools.bash_mounts = session.mounts;
+    cli.project_notice = project_defaults::notice(&applied);
+}
+
+/// Project key for knowledge entries: the canonical tools root.
+fn knowledge_project(config: &Config) -> String {
+    let root = tools_root(config);
+    root.canonicalize().unwrap_or(root).display().to_string()
+}
+
+/// Write a saved session with secrets scrubbed (`qq share`).
+fn share_command(
+    config: &Config,
+    session: Option<&str>,
+    format: ShareFormat,
+    output: Option<&std::path::Path>,
+) -> Result<()> {
+    let path = active_sessions::find_saved(paths::session_logs_dir().as_deref(), session, "session log")?;
+    let saved = share::SavedSession::load(&path)?;
+    let mut scrubber = share::Scrubber::from_config(config, &saved.root)?;
+    let shared = saved.scrubbed(&mut scrubber)?;
+    let text = match format {
+        ShareFormat::Markdown => shared.to_markdown(&turn_stats::Pricing::new(&config.pricing))?,
+        ShareFormat::Html => shared.to_html()?,
+    };
+    match output {
+        Some(file) => {
+            std::fs::write(file, text).with_context(|| format!("Failed to write {}", file.display()))?;
+            eprintln!("Wrote session {} to {}", shared.id, file.display());
+        }
+        None => print!("{}", text),
+    }
+    eprintln!(
+        "Scrubbed {} secret(s) and {} path(s) outside the project; review before sharing.",
+        scrubber.counts.secrets, scrubber.counts.paths
+    );
+    Ok(())
+}
+
+/// Fill the `summarize_file` cache ahead of time (`qq index --summaries`).
+async fn index_command(cli: &Cli, config: &Config, summaries: bool, jobs: usize, min_bytes: u64) -> Result<()> {
+    use futures::StreamExt;
+
+    if !summaries {
+        anyhow::bail!("Nothing to index; pass --summaries to summarize the project's files");
+    }
+    let settings = resolve_settings(cli, config)?;
+    let provider: Arc<dyn Provider> = Arc::from(create_provider_from_settings(&settings)?);
+    let summarizer = build_file_summarizer(config, &provider, None)?
+        .context("File summaries are disabled ([tools.summaries] enabled = false)")?;
+
+    let files = qq_tools::summary_candidates(summarizer.root(), min_bytes);
+    let total = files.len();
+    eprintln!("Summarizing {} files in {}", total, summarizer.root().display());
+    let mut results = futures::stream::iter(files.into_iter().map(|file| {
+        let summarizer = Arc::clone(&summarizer);
+        async move {
+            let result = summarizer.summarize(&file, false).await;
+            (file, result)
+        }
+    }))
+    .buffer_unordered(jobs.max(1));
+
+    let (mut done, mut new, mut cached, mut failed) = (0, 0, 0, 0);
+    while let Some((file, result)) = results.next().await {
+        done += 1;
+        match result {
+            Ok(summary) if summary.cached => cached += 1,
+            Ok(_) => {
+                new += 1;
+                eprintln!("[{}/{}] {}", done, total, file);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("[{}/{}] skipped: {}", done, total, e);
+            }
+        }
+    }
+    println!(
+        "{} summarized, {} already cached, {} skipped ({})",
+        new,
+        cached,
+        failed,
+        summarizer.cache().path().display()
+    );
+    Ok(())
+}
+
+/// How long `qq doctor` waits for each provider's test request.
+const DOCTOR_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// `qq doctor`: walk the steps a session takes and report what would fail.
+async fn doctor_command(cli: &Cli, no_network: bool) -> Result<()> {
+    use doctor::Check;
+
+    let mut report = doctor::Report::default();
+
+    report.section("Config");
+    let files = [
+        ("config.toml", Config::config_path()?, true),
+        ("agents.toml", AgentsConfig::config_path()?, false),
+    ];
+    for (name, path, required) in files {
+        let check = if !path.exists() && required {
+            Check::fail(
+                path.display().to_string(),
+                "not found",
+                Some("run `qq setup` to create it".to_string()),
+            )
+        } else if !path.exists() {
+            Check::ok(path.display().to_string(), "not present (optional)")
+        } else {
+            match config_check::check_file(name, &path) {
+                Ok(issues) => doctor::config_file(&path, &issues),
+                Err(e) => Check::fail(path.display().to_string(), format!("cannot read ({})", e), None),
+            }
+        };
+        report.add(check);
+    }
+    match policy::init() {
+        Ok(policy) => {
+            if let Some(ref path) = policy.path {
+                report.add(Check::ok("organization policy", format!("loaded from {}", path.display())));
+            }
+        }
+        Err(e) => report.add(Check::fail(
+            "organization policy",
+            format!("{:#}", e),
+            Some(format!("qq won't start until {} is fixed; ask whoever installed it", policy::POLICY_PATH)),
+        )),
+    }
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            let message = e.to_string();
+            report.add(Check::fail("config", message.lines().next().unwrap_or_default(), None));
+            return report.finish();
+        }
+    };
+    if let Err(e) = install_network(&config) {
+        report.add(Check::fail(
+            "network",
+            format!("{:#}", e),
+            Some("fix the proxy or ca_bundles under [network] in config.toml".to_string()),
+        ));
+    }
+    let settings = resolve_settings(cli, &config);
+    match settings {
+        Ok(ref settings) => report.add(Check::ok(
+            format!("profile '{}'", settings.profile_name),
+            format!(
+                "{} provider, model {}",
+                settings.provider_type,
+                settings.model.as_deref().unwrap_or("(provider default)")
+            ),
+        )),
+        Err(ref e) => report.add(Check::fail(
+            "default profile",
+            format!("{:#}", e),
+            Some("check default_profile and [profiles] in config.toml; `qq profiles` lists them".to_string()),
+        )),
+    }
+
+    report.section("Providers");
+    let mut names: Vec<&String> = config.providers.keys().collect();
+    names.sort();
+    if names.is_empty() {
+        report.add(Check::fail(
+            "providers",
+            "none configured",
+            Some("add a [providers.<name>] section to config.toml".to_string()),
+        ));
+    }
+    for name in names {
+        report.add(doctor_provider(name, &config, no_network).await);
+    }
+
+    if config.tools.enable_bash {
+        report.section("Sandbox");
+        match qq_tools::SandboxBackend::parse_order(&config.tools.bash_sandbox_backends) {
+            Ok(order) => {
+                let executor = qq_tools::SandboxExecutor::detect_with(&order);
+                let detail = format!("{} ({})", executor.mode_name(), executor.capabilities().summary());
+                report.add(if executor.supports_shell() {
+                    Check::ok("backend", detail)
+                } else {
+                    Check::fail(
+                        "backend",
+                        format!("no kernel sandbox available, only {}", detail),
+                        Some("install bubblewrap (bwrap) or firejail, or pass --insecure to run commands without isolation".to_string()),
+                    )
+                });
+            }
+            Err(e) => report.add(Check::fail(
+                "backend",
+                e,
+                Some("fix tools.bash_sandbox_backends in config.toml".to_string()),
+            )),
+        }
+        report.add(match doctor::apparmor_restricts_userns() {
+            Some(true) => Check::warn(
+                "AppArmor",
+                "restricts unprivileged user namespaces, so the hakoniwa backend can't run",
+                Some("run `sudo ./scripts/setup-apparmor.sh`, or rely on bubblewrap or firejail".to_string()),
+            ),
+            Some(false) => Check::ok("AppArmor", "allows unprivileged user namespaces"),
+            None => Check::ok("AppArmor", "no user namespace restriction"),
+        });
+    }
+
+    report.section("Memory");
+    report.add(match paths::knowledge_file() {
+        Some(path) => doctor::knowledge_base(&path),
+        None => Check::fail(
+            "knowledge base",
+            "no state directory",
+            Some("set HOME or XDG_STATE_HOME".to_string()),
+        ),
+    });
+
+    report.section("Tools");
+    let mimetypes = settings
+        .ok()
+        .and_then(|s| s.supported_content_types)
+        .unwrap_or_else(|| vec!["text".into(), "image".into()]);
+    let plugins = plugins::load(&config.plugins);
+    report.add(
+        match build_tools_registry(&config, cli.insecure, cli.agent_mode, &mimetypes, cli.ask_network, &plugins) {
+            Ok((registry, _, _)) => Check::ok("registry", format!("{} tools", registry.names().len())),
+            Err(e) => {
+                let message = e.to_string();
+                Check::fail("registry", message.lines().next().unwrap_or_default(), None)
+            }
+        },
+    );
+
+    report.finish()
+}
+
+/// Resolve provider `name` and, unless `no_network`, send it a tiny request.
+async fn doctor_provider(name: &str, config: &Config, no_network: bool) -> doctor::Check {
+    use doctor::Check;
+
+    let settings = match resolve_settings_for_provider(name, config) {
+        Ok(settings) => settings,
+        Err(e) => {
+            return Check::fail(
+                name,
+                format!("{:#}", e),
+                Some(format!("set api_key under [providers.{}] or {}_API_KEY", name, name.to_uppercase())),
+            )
+        }
+    };
+    let provider = match create_provider_from_settings(&settings) {
+        Ok(provider) => provider,
+        Err(e) => return Check::fail(name, format!("{:#}", e), None),
+    };
+    let Some(model) = settings
+        .model
+        .clone()
+        .or_else(|| provider.default_model().map(str::to_string))
+    else {
+        return Check::warn(
+            name,
+            "no model to test with",
+            Some(format!("set default_model under [providers.{}]", name)),
+        );
+    };
+    if no_network {
+        return Check::ok(name, format!("{} provider, model {} (not contacted)", settings.provider_type, model));
+    }
+
+    let request = CompletionRequest::new(vec![Message::user("Reply with OK.")])
+        .with_model(&model)
+        .with_max_tokens(16);
+    let started = std::time::Instant::now();
+    let error = match tokio::time::timeout(DOCTOR_REQUEST_TIMEOUT, provider.complete(request)).await {
+        Ok(Ok(_)) => {
+            return Check::ok(
+                name,
+                format!("{} answered in {:.1}s", model, started.elapsed().as_secs_f64()),
+            )
+        }
+        Ok(Err(e)) => e,
+        Err(_) => qq_core::Error::Timeout(format!(
+            "no response in {}s",
+            DOCTOR_REQUEST_TIMEOUT.as_secs()
+        )),
+    };
+    doctor::provider_error(name, &model, settings.base_url.as_deref(), &error)
+}
+
+/// `qq agents export|import`.
+/// Print a saved delegation graph (`qq graph`).
+fn graph_command(session: Option<&str>, format: GraphFormat) -> Result<()> {
+    let path = delegation_graph::find_graph(paths::graphs_dir().as_deref(), session)?;
+    let graph = delegation_graph::DelegationGraph::load(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let output = match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Mermaid => graph.to_mermaid(),
+        GraphFormat::Ascii => graph
+            .render_ascii()
+            .unwrap_or_else(|| "No delegations in this session.\n".to_string()),
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+async fn agents_command(config: &Config, action: &AgentsCommand) -> Result<()> {
+    let agents_config = AgentsConfig::load()?;
+    match action {
+        AgentsCommand::Export { name, output } => {
+            let text = agent_bundle::AgentBundle::export(&agents_config, name)?.to_toml()?;
+            match output {
+                Some(file) => {
+                    std::fs::write(file, text)?;
+                    eprintln!("Exported agent '{}' to {}", name, file.display());
+                }
+                None => print!("{}", text),
+            }
+        }
+        AgentsCommand::Import { source, name, force } => {
+            let text = agent_bundle::read_source(source).await?;
+            let profiles: Vec<String> = config.profiles.keys().cloned().collect();
+            let (mut bundle, warnings) = agent_bundle::AgentBundle::parse(&text, &profiles)
+                .with_context(|| format!("Invalid agent bundle {}", source))?;
+            if let Some(name) = name {
+                bundle.rename(name)?;
+            }
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+
+            let path = AgentsConfig::config_path()?;
+            let path = std::fs::canonicalize(&path).unwrap_or(path);
+            let _lock = qq_tools::FileLock::acquire_async(&path).await?;
+            let content = std::fs::read_to_string(&path).unwrap_or_default();
+            let updated = agent_bundle::insert_into(&content, &bundle.name, &bundle, *force)?;
+            qq_tools::write_atomically(&path, updated.as_bytes())?;
+            println!("Imported agent '{}' into {}", bundle.name, path.display());
+        }
+    }
+    Ok(())
+}
+
+/// `qq memory export|import|compact`.
+fn memory_command(config: &Config, action: &MemoryCommand) -> Result<()> {
+    let path = paths::knowledge_file()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine state directory"))?;
+    let session = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let store = qq_tools::KnowledgeStore::load(path.clone(), knowledge_project(config), session);
+
+    match action {
+        MemoryCommand::Export { output, all } => {
+            let entries = store.export(*all);
+            let json = serde_json::to_string_pretty(&entries)?;
+            match output {
+                Some(file) => {
+                    std::fs::write(file, json)?;
+                    eprintln!("Exported {} entries to {}", entries.len(), file.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+        MemoryCommand::Import { file, project } => {
+            let text = std::fs::read_to_string(file)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.display(), e))?;
+            let entries: Vec<qq_tools::KnowledgeEntry> = serde_json::from_str(&text)
+                .map_err(|e| anyhow::anyhow!("{} is not a knowledge export: {}", file.display(), e))?;
+            let report = store.import(entries, project.as_deref())?;
+            println!(
+                "Imported {} entries into {} ({} already known)",
+                report.added,
+                path.display(),
+                report.skipped
+            );
+        }
+        MemoryCommand::Compact => {
+            let report = store.compact()?;
+            println!(
+                "Compacted {}: kept {} entries, dropped {} duplicates and {} corrupt lines ({} -> {} bytes)",
+                path.display(),
+                report.kept,
+                report.duplicates,
+                report.corrupt_lines,
+                report.bytes_before,
+                report.bytes_after
+            );
+        }
+    }
+    Ok(())
+}
+
 fn list_profiles(config: &Config) -> Result<()> {
     if config.profiles.is_empty() {
         println!("No profiles configured.");
@@ -905,11 +2253,7 @@ fn list_profiles(config: &Config) -> Result<()> {
 
             // System prompt (truncated)
             if let Some(ref prompt) = resolved.system_prompt {
-                let preview = if prompt.len() > 60 {
-                    format!("{}...", prompt[..60].replace('\n', " "))
-                } else {
-                    prompt.replace('\n', " ")
-                };
+                let preview = qq_core::text::ellipsize(&prompt.replace('\n', " "), 60);
                 println!("    System: {}", preview);
             }
 
@@ -958,10 +2302,8 @@ fn show_config(config: &Config) -> Result<()> {
                 // Show truncated prompt name or inline prompt
                 let display = if config.prompts.contains_key(prompt) {
                     format!("@{}", prompt)
-                } else if prompt.len() > 50 {
-                    format!("{}...", &prompt[..50])
                 } else {
-                    prompt.clone()
+                    qq_core::text::ellipsize(prompt, 50)
                 };
                 println!("    Prompt: {}", display);
             }
@@ -977,11 +2319,7 @@ fn show_config(config: &Config) -> Result<()> {
     if !config.prompts.is_empty() {
         println!("\nPrompts:");
         for (name, prompt_entry) in &config.prompts {
-            let preview = if prompt_entry.prompt.len() > 60 {
-                format!("{}...", &prompt_entry.prompt[..60].replace('\n', " "))
-            } else {
-                prompt_entry.prompt.replace('\n', " ")
-            };
+            let preview = qq_core::text::ellipsize(&prompt_entry.prompt.replace('\n', " "), 60);
             println!("  {}: {}", name, preview);
         }
     }
@@ -1024,6 +2362,20 @@ struct ResolvedSettings {
     context_window: Option<u32>,
     /// Content types this provider/model supports
     supported_content_types: Option<Vec<String>>,
+    /// Probe and adapt to the provider's capabilities
+    probe_capabilities: bool,
+    /// Verification of agent runs from the profile
+    verify: Option<qq_agents::VerifyPolicy>,
+    /// Review thresholds for agent reports from the profile
+    review: Option<qq_agents::ReviewPolicy>,
+    /// How the session keeps its history within the context window
+    memory_strategy: AgentMemoryStrategy,
+    /// Endpoints to balance requests across; empty for a single endpoint
+    endpoints: Vec<config::ProviderEndpointEntry>,
+    balance: config::BalanceConfigEntry,
+    /// The provider's own `network` table over `[network]`; None when it
+    /// has none and the shared client settings apply
+    network: Option<config::NetworkConfigEntry>,
 }
 
 /// Resolve all settings from CLI args, profile, and config
@@ -1049,7 +2401,7 @@ fn resolve_settings(cli: &Cli, config: &Config) -> Result<ResolvedSettings> {
         .base_url
         .as_ref()
         .map(|_| "none".to_string()) // If base_url provided via CLI, allow dummy key
-        .or_else(|| provider_config.and_then(|p| p.api_key.clone()))
+        .or_else(|| provider_config.and_then(|p| p.entry_api_key()))
         .or_else(|| std::env::var(format!("{}_API_KEY", provider_name.to_uppercase())).ok())
         .with_context(|| {
             format!(
@@ -1101,6 +2453,14 @@ fn resolve_settings(cli: &Cli, config: &Config) -> Result<ResolvedSettings> {
 
     // Resolve supported content types
     let supported_content_types = provider_config.and_then(|p| p.supported_content_types.clone());
+    let probe_capabilities = provider_config
+        .is_some_and(|p| p.probe_capabilities_enabled(&provider_type));
+
+    // A base URL on the command line pins a single server
+    let endpoints = match cli.base_url {
+        Some(_) => Vec::new(),
+        None => provider_config.map(|p| p.endpoints.clone()).unwrap_or_default(),
+    };
 
     Ok(ResolvedSettings {
         profile_name,
@@ -1116,6 +2476,15 @@ fn resolve_settings(cli: &Cli, config: &Config) -> Result<ResolvedSettings> {
             .unwrap_or(false),
         context_window,
         supported_content_types,
+        probe_capabilities,
+        verify: resolved_profile.verify.clone(),
+        review: resolved_profile.review.clone(),
+        memory_strategy: resolved_profile.memory_strategy,
+        endpoints,
+        balance: provider_config.map(|p| p.balance.clone()).unwrap_or_default(),
+        network: provider_config
+            .and_then(|p| p.network.as_ref())
+            .map(|n| n.overlaid_on(&config.network)),
     })
 }
 
@@ -1152,17 +2521,18 @@ fn resolve_provider_type(
 /// Resolve minimal settings for a provider by name (used for compaction provider override).
 fn resolve_settings_for_provider(provider_name: &str, config: &Config) -> Result<ResolvedSettings> {
     let provider_config = config.providers.get(provider_name)
-        .with_context(|| format!("Compaction provider '{}' not found in config", provider_name))?;
+        .with_context(|| format!("Provider '{}' not found in config", provider_name))?;
 
-    let api_key = provider_config.api_key.clone()
+    let api_key = provider_config.entry_api_key()
         .or_else(|| std::env::var(format!("{}_API_KEY", provider_name.to_uppercase())).ok())
-        .with_context(|| format!("API key not found for compaction provider '{}'", provider_name))?;
+        .with_context(|| format!("API key not found for provider '{}'", provider_name))?;
 
     let provider_type = resolve_provider_type(
         provider_config.provider_type.as_deref(),
         provider_name,
         provider_config.base_url.as_deref(),
     );
+    let probe_capabilities = provider_config.probe_capabilities_enabled(&provider_type);
 
     Ok(ResolvedSettings {
         profile_name: String::new(),
@@ -1177,6 +2547,13 @@ fn resolve_settings_for_provider(provider_name: &str, config: &Config) -> Result
         include_tool_reasoning: false,
         context_window: provider_config.context_window,
         supported_content_types: provider_config.supported_content_types.clone(),
+        probe_capabilities,
+        verify: None,
+        review: None,
+        memory_strategy: AgentMemoryStrategy::default(),
+        endpoints: provider_config.endpoints.clone(),
+        balance: provider_config.balance.clone(),
+        network: provider_config.network.as_ref().map(|n| n.overlaid_on(&config.network)),
     })
 }
 
@@ -1191,7 +2568,7 @@ fn resolve_settings_for_profile_name(profile_name: &str, config: &Config) -> Res
     let provider_config = config.providers.get(&provider_name);
 
     let api_key = provider_config
-        .and_then(|p| p.api_key.clone())
+        .and_then(|p| p.entry_api_key())
         .or_else(|| std::env::var(format!("{}_API_KEY", provider_name.to_uppercase())).ok())
         .with_context(|| format!("API key not found for provider '{}'", provider_name))?;
 
@@ -1208,6 +2585,9 @@ fn resolve_settings_for_profile_name(profile_name: &str, config: &Config) -> Res
 
     let context_window = provider_config.and_then(|p| p.context_window);
     let supported_content_types = provider_config.and_then(|p| p.supported_content_types.clone());
+    let probe_capabilities = provider_config
+        .is_some_and(|p| p.probe_capabilities_enabled(&provider_type));
+    let endpoints = provider_config.map(|p| p.endpoints.clone()).unwrap_or_default();
 
     Ok(ResolvedSettings {
         profile_name: profile_name.to_string(),
@@ -1222,6 +2602,15 @@ fn resolve_settings_for_profile_name(profile_name: &str, config: &Config) -> Res
         include_tool_reasoning: resolved_profile.include_tool_reasoning.unwrap_or(false),
         context_window,
         supported_content_types,
+        probe_capabilities,
+        verify: resolved_profile.verify.clone(),
+        review: resolved_profile.review.clone(),
+        memory_strategy: resolved_profile.memory_strategy,
+        endpoints,
+        balance: provider_config.map(|p| p.balance.clone()).unwrap_or_default(),
+        network: provider_config
+            .and_then(|p| p.network.as_ref())
+            .map(|n| n.overlaid_on(&config.network)),
     })
 }
 
@@ -1272,10 +2661,59 @@ fn build_profile_registry(
 }
 
 fn create_provider_from_settings(settings: &ResolvedSettings) -> Result<Box<dyn Provider>> {
+    if settings.endpoints.is_empty() {
+        return Ok(policy::current().wrap(create_endpoint_provider(settings)?));
+    }
+
+    // Several hosts or keys serving the same models: rotate across them
+    let endpoints = settings
+        .endpoints
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let mut endpoint_settings = settings.clone();
+            endpoint_settings.endpoints = Vec::new();
+            if let Some(key) = &entry.api_key {
+                endpoint_settings.api_key = key.clone();
+            }
+            if let Some(url) = &entry.base_url {
+                endpoint_settings.base_url = Some(url.clone());
+            }
+            let label = entry
+                .name
+                .clone()
+                .or_else(|| entry.base_url.clone())
+                .unwrap_or_else(|| format!("endpoint {}", i + 1));
+            let provider = create_endpoint_provider(&endpoint_settings)?;
+            Ok(Endpoint::new(label, Arc::from(provider), entry.weight))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(policy::current().wrap(Box::new(
+        BalancedProvider::new(endpoints).with_policy(settings.balance.to_policy()),
+    )))
+}
+
+/// Provider for one endpoint of the resolved settings.
+fn create_endpoint_provider(settings: &ResolvedSettings) -> Result<Box<dyn Provider>> {
+    policy::current().check_provider(&settings.provider_type, settings.base_url.as_deref())?;
+    let client = match settings.network {
+        Some(ref network) => Some(
+            network
+                .resolve()?
+                .apply(reqwest::Client::builder())
+                .connect_timeout(qq_core::http::PROVIDER_CONNECT_TIMEOUT)
+                .build()
+                .context("Failed to build the provider's HTTP client")?,
+        ),
+        None => None,
+    };
     match settings.provider_type.as_str() {
         "anthropic" => {
-            let mut provider = AnthropicProvider::new(&settings.api_key)
+            let mut provider = AnthropicProvider::new(&settings.api_key)?
                 .with_include_tool_reasoning(settings.include_tool_reasoning);
+            if let Some(ref client) = client {
+                provider = provider.with_client(client.clone());
+            }
             if let Some(model) = &settings.model {
                 provider = provider.with_default_model(model);
             }
@@ -1288,8 +2726,11 @@ fn create_provider_from_settings(settings: &ResolvedSettings) -> Result<Box<dyn
             Ok(Box::new(provider))
         }
         "gemini" => {
-            let mut provider = GeminiProvider::new(&settings.api_key)
+            let mut provider = GeminiProvider::new(&settings.api_key)?
                 .with_include_tool_reasoning(settings.include_tool_reasoning);
+            if let Some(ref client) = client {
+                provider = provider.with_client(client.clone());
+            }
             if let Some(model) = &settings.model {
                 provider = provider.with_default_model(model);
             }
@@ -1303,8 +2744,11 @@ fn create_provider_from_settings(settings: &ResolvedSettings) -> Result<Box<dyn
         }
         _ => {
             // Default: OpenAI-compatible
-            let mut provider = OpenAIProvider::new(&settings.api_key)
+            let mut provider = OpenAIProvider::new(&settings.api_key)?
                 .with_include_tool_reasoning(settings.include_tool_reasoning);
+            if let Some(ref client) = client {
+                provider = provider.with_client(client.clone());
+            }
             if let Some(model) = &settings.model {
                 provider = provider.with_default_model(model);
             }
@@ -1314,7 +2758,22 @@ fn create_provider_from_settings(settings: &ResolvedSettings) -> Result<Box<dyn
             if let Some(types) = &settings.supported_content_types {
                 provider = provider.with_supported_content_types(types.clone());
             }
-            Ok(Box::new(provider))
+            if !settings.probe_capabilities {
+                return Ok(Box::new(provider));
+            }
+
+            // Local OpenAI-compatible servers vary in what they support;
+            // probe once per server/model and adapt requests accordingly.
+            let mut adaptive = AdaptiveProvider::new(Arc::new(provider));
+            if let Some(path) = paths::capabilities_cache_file() {
+                let key = format!(
+                    "{}|{}",
+                    settings.base_url.as_deref().unwrap_or_default(),
+                    settings.model.as_deref().unwrap_or_default()
+                );
+                adaptive = adaptive.with_cache(CapabilityCache::new(path, key));
+            }
+            Ok(Box::new(adaptive))
         }
     }
 }
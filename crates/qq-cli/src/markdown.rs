@@ -18,6 +18,7 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
 };
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
 /// Style definitions for markdown elements, matching the previous termimad color scheme.
@@ -534,29 +535,29 @@ fn styled_border_line(
 }
 
 /// Find a byte offset for breaking `text` at approximately `avail` display-width characters.
-/// Prefers breaking at a space; falls back to hard-breaking at the width limit.
+/// Prefers breaking at a space or after a wide (CJK) character, since CJK text
+/// wraps between characters without spaces; falls back to hard-breaking at the
+/// width limit. Breaks always fall between grapheme clusters.
 fn find_break_point(text: &str, avail: usize) -> usize {
     let mut width_so_far = 0usize;
-    let mut last_space_byte = None;
+    let mut last_break = None;
     let mut byte_at_avail = text.len();
 
-    for (byte_idx, ch) in text.char_indices() {
-        let ch_width = unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
-        if width_so_far + ch_width > avail {
+    for (byte_idx, grapheme) in text.grapheme_indices(true) {
+        let grapheme_width = UnicodeWidthStr::width(grapheme);
+        if width_so_far + grapheme_width > avail {
             byte_at_avail = byte_idx;
             break;
         }
-        if ch == ' ' {
-            last_space_byte = Some(byte_idx);
+        if grapheme == " " {
+            last_break = Some(byte_idx);
+        } else if grapheme_width > 1 {
+            last_break = Some(byte_idx + grapheme.len());
         }
-        width_so_far += ch_width;
+        width_so_far += grapheme_width;
     }
 
-    if let Some(pos) = last_space_byte {
-        pos
-    } else {
-        byte_at_avail
-    }
+    last_break.unwrap_or(byte_at_avail)
 }
 
 /// Wrap a sequence of styled spans to fit within `width` visible characters.
@@ -604,7 +605,11 @@ fn wrap_spans(spans: &[Span<'static>], width: usize) -> Vec<Vec<Span<'static>>>
                 break;
             }
 
-            let break_pos = find_break_point(remaining, avail);
+            let mut break_pos = find_break_point(remaining, avail);
+            if break_pos == 0 && current_line.is_empty() {
+                // Wider than a whole line (a CJK char in a one-column pane): place it anyway
+                break_pos = remaining.graphemes(true).next().map_or(remaining.len(), str::len);
+            }
             let (chunk, rest) = remaining.split_at(break_pos);
 
             if !chunk.is_empty() {
@@ -957,12 +962,8 @@ impl MarkdownRenderer {
         Ok(())
     }
 
-    /// Get the accumulated content (for saving to session).
-    pub fn content(&self) -> &str {
-        &self.content
-    }
-
     /// Check if any content has been added.
+    #[cfg(test)]
     pub fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
@@ -1555,6 +1556,27 @@ Some text after";
         assert_eq!(output, input);
     }
 
+    #[test]
+    fn test_wrap_cjk_by_display_width() {
+        let line = |spans: &[Span]| spans.iter().map(|s| s.content.as_ref()).collect::<String>();
+
+        // No spaces: breaks between characters, each line at most 10 columns
+        let text = "これは日本語の長い文章です";
+        let wrapped = wrap_spans(&[Span::raw(text)], 10);
+        let rows: Vec<String> = wrapped.iter().map(|s| line(s)).collect();
+        assert_eq!(rows, vec!["これは日本", "語の長い文", "章です"]);
+
+        // Mixed text breaks after the last wide char that fits, not at the space
+        let wrapped = wrap_spans(&[Span::raw("see 中文字符串")], 8);
+        assert_eq!(line(&wrapped[0]), "see 中文");
+        assert!(wrapped.iter().all(|s| visible_width_of_spans(s) <= 8));
+
+        // Emoji with modifiers are never split, and a too-narrow pane still terminates
+        let wrapped = wrap_spans(&[Span::raw("👍🏽👍🏽👍🏽")], 1);
+        assert_eq!(wrapped.len(), 3);
+        assert_eq!(line(&wrapped[0]), "👍🏽");
+    }
+
     #[test]
     fn test_preprocess_skips_code_fences() {
         let input = "```\n+---+\n| A |\n+---+\n```";
@@ -0,0 +1,327 @@
+//! Live Markdown mirror of the session (`--mirror-file <path>`).
+//!
+//! The primary session is appended to a Markdown file as it streams: user
+//! turns and assistant text as they arrive, and each tool call as a
+//! collapsible `<details>` block once it finishes. The file is only ever
+//! appended to, so it can be tailed or opened in a Markdown previewer while
+//! the session runs, and a crash leaves everything up to that point intact.
+//!
+//! ```text
+//! ## You
+//!
+//! Why does the build fail?
+//!
+//! ## Assistant
+//!
+//! Let me check the error.
+//!
+//! <details>
+//! <summary>🔧 <code>run</code> ✓</summary>
+//! ...
+//! </details>
+//! ```
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+
+use crate::event_bus::{AgentEvent, AgentEventBus};
+use crate::event_socket::SessionEvent;
+use crate::html;
+
+/// Appends the session to a Markdown file; cheap to clone.
+#[derive(Clone)]
+pub struct SessionMirror {
+    inner: Arc<Mutex<MirrorState>>,
+}
+
+struct MirrorState {
+    path: PathBuf,
+    file: File,
+    /// Whether the current assistant turn has its heading yet
+    in_assistant: bool,
+    /// Trailing newlines of the last write, to keep blocks separated
+    trailing_newlines: usize,
+    /// Tool calls started but not yet finished: id -> (name, arguments)
+    pending_tools: HashMap<String, (String, String)>,
+    /// Set after the first write error; later writes are skipped
+    failed: bool,
+}
+
+impl SessionMirror {
+    /// Open `path` for appending and write a session header.
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open mirror file {}", path.display()))?;
+        let existing = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+        let mirror = Self {
+            inner: Arc::new(Mutex::new(MirrorState {
+                path: path.to_path_buf(),
+                file,
+                in_assistant: false,
+                trailing_newlines: 2,
+                pending_tools: HashMap::new(),
+                failed: false,
+            })),
+        };
+        let started = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let separator = if existing > 0 { "\n---\n\n" } else { "" };
+        mirror.with_state(|state| {
+            state.block(&format!("{}# qq session — {}", separator, started));
+        });
+        Ok(mirror)
+    }
+
+    /// Mirror agent notifications (`inform_user`) from the event bus.
+    pub fn follow(&self, bus: &AgentEventBus) {
+        let mut rx = bus.subscribe();
+        let mirror = self.clone();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if let AgentEvent::UserNotification {
+                    agent_name,
+                    message,
+                } = event
+                {
+                    mirror.with_state(|state| {
+                        state.block(&quote(&format!("**[{}]** {}", agent_name, message)));
+                    });
+                }
+            }
+        });
+    }
+
+    /// Start a new user turn.
+    pub fn user(&self, text: &str) {
+        self.with_state(|state| {
+            state.in_assistant = false;
+            state.pending_tools.clear();
+            state.block("## You");
+            state.block(text.trim_end());
+        });
+    }
+
+    /// Record one event from the primary session.
+    pub fn record(&self, event: &SessionEvent) {
+        self.with_state(|state| match event {
+            SessionEvent::ContentDelta { content } => {
+                if content.is_empty() {
+                    return;
+                }
+                state.assistant_heading();
+                state.write(content);
+            }
+            // Reasoning stays in the UI; the mirror is the readable transcript
+            SessionEvent::ThinkingDelta { .. }
+            | SessionEvent::ModelRouted { .. }
+            | SessionEvent::RequestMetrics { .. } => {}
+            SessionEvent::ToolStart {
+                id,
+                name,
+                arguments,
+            } => {
+                state
+                    .pending_tools
+                    .insert(id.clone(), (name.clone(), arguments.clone()));
+            }
+            SessionEvent::ToolComplete { id, name, is_error } => {
+                let arguments = state
+                    .pending_tools
+                    .remove(id)
+                    .map(|(_, arguments)| arguments)
+                    .unwrap_or_default();
+                state.assistant_heading();
+                state.block(&tool_block(name, &arguments, *is_error));
+            }
+            SessionEvent::ResponseComplete { .. } => {
+                state.end_paragraph();
+            }
+            SessionEvent::Error { message } => {
+                state.block(&quote(&format!("**Error:** {}", message)));
+            }
+            SessionEvent::Cancelled => {
+                state.pending_tools.clear();
+                state.block(&quote("*Cancelled*"));
+            }
+            SessionEvent::ContextRecovered { action } => {
+                state.block(&quote(&format!(
+                    "*Context window exceeded: {}; retrying*",
+                    action
+                )));
+            }
+            SessionEvent::ApprovalRequested {
+                category,
+                command,
+                explanation,
+                ..
+            } => {
+                let mut block = format!("*Approval requested ({}):* `{}`", category, command);
+                if let Some(explanation) = explanation {
+                    block.push_str(&format!("\n{}", explanation));
+                }
+                state.block(&quote(&block));
+            }
+            SessionEvent::ApprovalResolved { decision, .. } => {
+                state.block(&quote(&format!("*Approval: {:?}*", decision)));
+            }
+        });
+    }
+
+    fn with_state(&self, f: impl FnOnce(&mut MirrorState)) {
+        let mut state = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.failed {
+            f(&mut state);
+        }
+    }
+}
+
+impl MirrorState {
+    fn assistant_heading(&mut self) {
+        if !self.in_assistant {
+            self.in_assistant = true;
+            self.block("## Assistant");
+        }
+    }
+
+    /// Write `text` as its own block, separated from what came before by a
+    /// blank line.
+    fn block(&mut self, text: &str) {
+        self.end_paragraph();
+        self.write(text);
+        self.write("\n\n");
+    }
+
+    fn end_paragraph(&mut self) {
+        match self.trailing_newlines {
+            0 => self.write("\n\n"),
+            1 => self.write("\n"),
+            _ => {}
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        if text.is_empty() || self.failed {
+            return;
+        }
+        if let Err(e) = self
+            .file
+            .write_all(text.as_bytes())
+            .and_then(|_| self.file.flush())
+        {
+            tracing::warn!(path = %self.path.display(), error = %e, "Mirror file write failed; mirroring stopped");
+            self.failed = true;
+            return;
+        }
+        let trailing = text.len() - text.trim_end_matches('\n').len();
+        self.trailing_newlines = if trailing == text.len() {
+            self.trailing_newlines + trailing
+        } else {
+            trailing
+        };
+    }
+}
+
+/// Collapsible block for one finished tool call.
+fn tool_block(name: &str, arguments: &str, is_error: bool) -> String {
+    let status = if is_error { "✗" } else { "✓" };
+    let mut block = format!(
+        "<details>\n<summary>🔧 <code>{}</code> {}</summary>\n\n",
+        html::escape(name),
+        status
+    );
+    let arguments = serde_json::from_str::<serde_json::Value>(arguments)
+        .ok()
+        .and_then(|v| serde_json::to_string_pretty(&v).ok())
+        .unwrap_or_else(|| arguments.to_string());
+    if !arguments.trim().is_empty() && arguments.trim() != "{}" {
+        block.push_str(&format!("```json\n{}\n```\n\n", arguments.trim_end()));
+    }
+    block.push_str("</details>");
+    block
+}
+
+/// Render `text` as a Markdown blockquote.
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("> {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(text: &str) -> SessionEvent {
+        SessionEvent::ContentDelta {
+            content: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mirrors_turns_and_tool_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        let mirror = SessionMirror::create(&path).unwrap();
+
+        mirror.user("Why does the build fail?");
+        mirror.record(&content("Let me "));
+        mirror.record(&content("check."));
+        mirror.record(&SessionEvent::ToolStart {
+            id: "call_1".into(),
+            name: "run".into(),
+            arguments: r#"{"command":"cargo build"}"#.into(),
+        });
+        mirror.record(&SessionEvent::ThinkingDelta {
+            content: "hidden".into(),
+        });
+        mirror.record(&SessionEvent::ToolComplete {
+            id: "call_1".into(),
+            name: "run".into(),
+            is_error: true,
+        });
+        mirror.record(&content("A missing import."));
+        mirror.record(&SessionEvent::ResponseComplete { usage: None });
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let body = text.split_once("\n\n").unwrap().1;
+        assert!(text.starts_with("# qq session — "));
+        assert_eq!(
+            body,
+            "## You\n\nWhy does the build fail?\n\n## Assistant\n\nLet me check.\n\n\
+             <details>\n<summary>🔧 <code>run</code> ✗</summary>\n\n\
+             ```json\n{\n  \"command\": \"cargo build\"\n}\n```\n\n</details>\n\n\
+             A missing import.\n\n"
+        );
+        assert!(!text.contains("hidden"));
+    }
+
+    #[test]
+    fn test_appends_to_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "earlier notes\n").unwrap();
+
+        let mirror = SessionMirror::create(&path).unwrap();
+        mirror.user("hi");
+        mirror.record(&SessionEvent::Error {
+            message: "rate limited\nretry later".into(),
+        });
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("earlier notes\n\n---\n\n# qq session"));
+        assert!(text.ends_with("## You\n\nhi\n\n> **Error:** rate limited\n> retry later\n\n"));
+    }
+}
@@ -0,0 +1,229 @@
+//! On-disk locations, split along XDG lines.
+//!
+//! - config (`~/.config/qq`): hand-edited files only — config.toml, agents.toml,
+//!   session templates
+//! - state (`~/.local/state/qq`): history, experiment logs, anything qq writes
+//!   as a side effect of use
+//! - cache (`~/.cache/qq`): regenerable data that is safe to delete
+//!
+//! Keeping generated files out of the config dir means it can be backed up
+//! and synced without dragging history along. Files written by older versions
+//! into the config dir are moved on startup by [`migrate_legacy_files`].
+
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "qq";
+
+/// State files that older versions wrote into the config directory.
+const LEGACY_STATE_FILES: &[&str] = &["input_history.json", "chat_history", "experiments.jsonl"];
+
+/// Config directory (`$XDG_CONFIG_HOME/qq`).
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join(APP_DIR))
+}
+
+/// State directory (`$XDG_STATE_HOME/qq`).
+///
+/// Platforms without a state dir (macOS, Windows) fall back to the local
+/// data directory.
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|d| d.join(APP_DIR))
+}
+
+/// Cache directory (`$XDG_CACHE_HOME/qq`).
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join(APP_DIR))
+}
+
+/// Session templates for `qq new --template`.
+pub fn templates_dir() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("templates"))
+}
+
+/// Plugin directories, each with a `plugin.toml`.
+pub fn plugins_dir() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("plugins"))
+}
+
+/// TUI input history.
+pub fn input_history_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("input_history.json"))
+}
+
+/// Readline chat history.
+pub fn chat_history_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("chat_history"))
+}
+
+/// Prompt experiment run log.
+pub fn experiments_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("experiments.jsonl"))
+}
+
+/// Reminders scheduled with `schedule_reminder`.
+pub fn reminders_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("reminders.json"))
+}
+
+/// Cross-session knowledge base searched by `recall`.
+pub fn knowledge_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("knowledge.jsonl"))
+}
+
+/// Named agent instances saved by `use_agent_instance`.
+pub fn agent_instances_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("agent_instances.json"))
+}
+
+/// Settings remembered per project (agent, model, sandbox flags, mounts).
+pub fn project_defaults_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("projects.json"))
+}
+
+/// `/context` slots, keyed by project root.
+pub fn context_slots_file() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("context_slots.json"))
+}
+
+/// Records of the qq sessions currently running.
+pub fn sessions_dir() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("sessions"))
+}
+
+/// Conversations saved by `/clear`.
+pub fn transcripts_dir() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("transcripts"))
+}
+
+/// Delegation graphs of past sessions, read by `qq graph`.
+pub fn graphs_dir() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("graphs"))
+}
+
+/// Conversations of past sessions, read by `qq share`.
+pub fn session_logs_dir() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("session_logs"))
+}
+
+/// Probed provider capabilities, keyed by server and model.
+pub fn capabilities_cache_file() -> Option<PathBuf> {
+    cache_dir().map(|d| d.join("capabilities.json"))
+}
+
+/// Large tool outputs saved by digest for `fetch_artifact`.
+pub fn artifacts_dir() -> Option<PathBuf> {
+    cache_dir().map(|d| d.join("artifacts"))
+}
+
+/// Per-project caches of file summaries, keyed by content hash.
+pub fn summaries_dir() -> Option<PathBuf> {
+    cache_dir().map(|d| d.join("summaries"))
+}
+
+/// Older transcript and chat history moved out of memory by long sessions.
+pub fn spill_dir() -> Option<PathBuf> {
+    cache_dir().map(|d| d.join("spill"))
+}
+
+/// Move state files left in the config directory by older versions.
+///
+/// Best-effort: failures are logged and the file is left where it was.
+pub fn migrate_legacy_files() {
+    let (Some(from), Some(to)) = (config_dir(), state_dir()) else {
+        return;
+    };
+    if from == to {
+        return;
+    }
+    for (old, new) in migrate_files(&from, &to, LEGACY_STATE_FILES) {
+        tracing::info!(from = %old.display(), to = %new.display(), "Migrated state file");
+    }
+}
+
+/// Move each named file from `from` to `to`, skipping files that are missing
+/// or already present at the destination. Returns the moves performed.
+fn migrate_files(from: &Path, to: &Path, names: &[&str]) -> Vec<(PathBuf, PathBuf)> {
+    let mut moved = Vec::new();
+    for name in names {
+        let old = from.join(name);
+        let new = to.join(name);
+        if !old.is_file() || new.exists() {
+            continue;
+        }
+        match move_file(&old, &new) {
+            Ok(()) => moved.push((old, new)),
+            Err(e) => {
+                tracing::warn!(path = %old.display(), error = %e, "Failed to migrate state file");
+            }
+        }
+    }
+    moved
+}
+
+/// Rename, falling back to copy + remove when crossing filesystems.
+fn move_file(old: &Path, new: &Path) -> std::io::Result<()> {
+    if let Some(parent) = new.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(old, new).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(old, new)?;
+    std::fs::remove_file(old)
+}
+
+/// Print resolved locations (`qq paths`).
+pub fn print_paths() {
+    let show = |p: Option<PathBuf>| {
+        p.map(|p| p.display().to_string())
+            .unwrap_or_else(|| "(unavailable)".to_string())
+    };
+
+    println!("Config: {}", show(config_dir()));
+    println!("  config.toml, agents.toml, templates/, plugins/");
+    println!("State:  {}", show(state_dir()));
+    println!("  input_history.json, chat_history, experiments.jsonl, reminders.json, knowledge.jsonl,");
+    println!("  agent_instances.json, projects.json, context_slots.json, sessions/, transcripts/, graphs/, session_logs/");
+    println!("Cache:  {}", show(cache_dir()));
+    println!("  capabilities.json, artifacts/, summaries/, spill/");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_files_moves_missing_destinations_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let from = tmp.path().join("config");
+        let to = tmp.path().join("state");
+        std::fs::create_dir_all(&from).unwrap();
+        std::fs::write(from.join("a"), "old a").unwrap();
+        std::fs::write(from.join("b"), "old b").unwrap();
+        std::fs::create_dir_all(&to).unwrap();
+        std::fs::write(to.join("b"), "new b").unwrap();
+
+        let moved = migrate_files(&from, &to, &["a", "b", "missing"]);
+
+        assert_eq!(moved, vec![(from.join("a"), to.join("a"))]);
+        assert!(!from.join("a").exists());
+        assert_eq!(std::fs::read_to_string(to.join("a")).unwrap(), "old a");
+        // Existing destination wins; the legacy copy is left untouched
+        assert_eq!(std::fs::read_to_string(to.join("b")).unwrap(), "new b");
+        assert!(from.join("b").exists());
+    }
+
+    #[test]
+    fn test_migrate_files_creates_destination_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let to = tmp.path().join("nested").join("state");
+        std::fs::write(tmp.path().join("h"), "x").unwrap();
+
+        let moved = migrate_files(tmp.path(), &to, &["h"]);
+
+        assert_eq!(moved.len(), 1);
+        assert!(to.join("h").is_file());
+    }
+}
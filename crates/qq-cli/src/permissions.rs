@@ -0,0 +1,176 @@
+//! `/permissions`: inspect and edit bash permission tiers at runtime.
+//!
+//! Edits apply to the live `PermissionStore` immediately. Tier overrides are
+//! also written back to `[tools.bash_permissions]` in config.toml; session
+//! promotions stay in memory unless explicitly saved.
+
+use qq_tools::bash::{PermissionStore, Tier, TierSource};
+
+use crate::config::{self, BashPermissionOverrides};
+
+pub const PERMISSIONS_USAGE: &str = "\
+Usage:
+  /permissions                               Show overrides, session promotions, and defaults
+  /permissions check <cmd>                   Show a command's effective tier and its source
+  /permissions <session|per-call|restricted> <cmd>...
+                                             Override tier (saved to config.toml)
+  /permissions reset <cmd>...                Remove overrides (saved to config.toml)
+  /permissions revoke <cmd>...               Drop session promotions
+  /permissions save                          Save all session promotions as session overrides";
+
+/// A parsed `/permissions` subcommand.
+#[derive(Debug, PartialEq)]
+enum PermissionsEdit {
+    Check(String),
+    Set(Tier, Vec<String>),
+    Reset(Vec<String>),
+    Revoke(Vec<String>),
+    Save,
+}
+
+fn parse_edit(args: &str) -> Result<PermissionsEdit, String> {
+    let mut words = args.split_whitespace();
+    let action = words.next().unwrap_or_default().to_ascii_lowercase();
+    let commands: Vec<String> = words.map(str::to_string).collect();
+
+    let needs_commands = |edit: PermissionsEdit| {
+        if commands.is_empty() {
+            Err(format!("/permissions {} needs at least one command", action))
+        } else {
+            Ok(edit)
+        }
+    };
+
+    match action.as_str() {
+        "check" => match commands.as_slice() {
+            [command] => Ok(PermissionsEdit::Check(command.clone())),
+            _ => Err("Usage: /permissions check <cmd>".to_string()),
+        },
+        "reset" => needs_commands(PermissionsEdit::Reset(commands.clone())),
+        "revoke" => needs_commands(PermissionsEdit::Revoke(commands.clone())),
+        "save" => Ok(PermissionsEdit::Save),
+        other => match Tier::parse(other) {
+            Some(tier) => needs_commands(PermissionsEdit::Set(tier, commands.clone())),
+            None => Err(format!("Unknown /permissions action '{}'\n{}", other, PERMISSIONS_USAGE)),
+        },
+    }
+}
+
+/// Full listing for `/permissions` with no arguments.
+pub fn format_permissions_view(store: &PermissionStore) -> String {
+    format!("{}\n{}", store.format_permissions(), PERMISSIONS_USAGE)
+}
+
+/// Apply a `/permissions <action> ...` edit and return a one-line result.
+pub fn apply_permissions_edit(store: &PermissionStore, args: &str) -> String {
+    let edit = match parse_edit(args) {
+        Ok(edit) => edit,
+        Err(e) => return e,
+    };
+
+    let message = match edit {
+        PermissionsEdit::Check(command) => {
+            let (tier, source) = store.explain_tier(&command);
+            let source = match source {
+                TierSource::Config => "config override",
+                TierSource::SessionPromotion => "session promotion",
+                TierSource::Default => "built-in default",
+            };
+            return format!("{}: {} ({})", command, tier.name(), source);
+        }
+        PermissionsEdit::Set(tier, commands) => {
+            for command in &commands {
+                store.set_override(command, tier);
+            }
+            format!("{} -> {}", commands.join(", "), tier.name())
+        }
+        PermissionsEdit::Reset(commands) => {
+            let cleared: Vec<_> = commands
+                .into_iter()
+                .filter(|c| store.clear_override(c).is_some())
+                .collect();
+            if cleared.is_empty() {
+                return "No matching overrides".to_string();
+            }
+            format!("Overrides removed: {}", cleared.join(", "))
+        }
+        PermissionsEdit::Revoke(commands) => {
+            let revoked: Vec<_> = commands
+                .into_iter()
+                .filter(|c| store.revoke_session(c))
+                .collect();
+            // Session promotions are never persisted, so nothing to save
+            return if revoked.is_empty() {
+                "No matching session promotions".to_string()
+            } else {
+                format!("Session promotions revoked: {}", revoked.join(", "))
+            };
+        }
+        PermissionsEdit::Save => {
+            let promoted = store.session_promotions();
+            if promoted.is_empty() {
+                return "No session promotions to save".to_string();
+            }
+            for command in &promoted {
+                store.set_override(command, Tier::Session);
+            }
+            format!("Saved as session overrides: {}", promoted.join(", "))
+        }
+    };
+
+    let (session, per_call, restricted) = store.overrides_by_tier();
+    let overrides = BashPermissionOverrides {
+        session,
+        per_call,
+        restricted,
+    };
+    match config::save_bash_permissions(&overrides) {
+        Ok(()) => format!("{} (saved to config.toml)", message),
+        Err(e) => format!("{} (this session only; saving config.toml failed: {})", message, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_edit() {
+        assert_eq!(
+            parse_edit("per-call make cmake"),
+            Ok(PermissionsEdit::Set(
+                Tier::PerCall,
+                vec!["make".to_string(), "cmake".to_string()]
+            ))
+        );
+        assert_eq!(
+            parse_edit("check git-push"),
+            Ok(PermissionsEdit::Check("git-push".to_string()))
+        );
+        assert_eq!(parse_edit("SAVE"), Ok(PermissionsEdit::Save));
+        assert!(parse_edit("restricted").is_err());
+        assert!(parse_edit("check a b").is_err());
+        assert!(parse_edit("promote make").unwrap_err().contains("Unknown"));
+    }
+
+    #[test]
+    fn test_apply_without_persisting() {
+        let store = PermissionStore::new(Default::default());
+        store.promote_to_session("make");
+
+        assert_eq!(
+            apply_permissions_edit(&store, "check make"),
+            "make: session (session promotion)"
+        );
+        assert_eq!(
+            apply_permissions_edit(&store, "revoke make"),
+            "Session promotions revoked: make"
+        );
+        assert_eq!(
+            apply_permissions_edit(&store, "revoke make"),
+            "No matching session promotions"
+        );
+        assert_eq!(apply_permissions_edit(&store, "save"), "No session promotions to save");
+        assert_eq!(apply_permissions_edit(&store, "reset make"), "No matching overrides");
+    }
+}
@@ -0,0 +1,555 @@
+//! Plugins: agents and tools added without recompiling qq.
+//!
+//! Each subdirectory of the plugins directory (`~/.config/qq/plugins`)
+//! holding a `plugin.toml` is a plugin. The manifest declares the plugin API
+//! version it speaks, the capabilities it needs, tools (executables run in
+//! the sandbox per call, see [`qq_tools::PluginTool`]) and agents (the same
+//! fields as an `[agents.<name>]` entry in agents.toml). With the
+//! `wasm-plugins` feature, `module = "plugin.wasm"` adds a WebAssembly
+//! module whose tools and agents are merged with the manifest's; see
+//! `qq_tools::wasm_plugin` for its interface.
+//!
+//! ```toml
+//! api_version = 1
+//! name = "k8s"
+//! version = "0.3.0"
+//! capabilities = ["home"]
+//!
+//! [[tools]]
+//! name = "pod_logs"
+//! description = "Recent logs of a pod"
+//! command = ["./bin/pod-logs"]
+//! read_only = true
+//! parameters = { type = "object", properties = { pod = { type = "string" } }, required = ["pod"] }
+//!
+//! [agents.k8s_debugger]
+//! description = "Diagnoses failing pods"
+//! system_prompt = "You debug Kubernetes workloads..."
+//! tools = ["pod_logs", "read_file"]
+//! ```
+//!
+//! A plugin is skipped, with a warning, when it needs a newer plugin API or
+//! capabilities that `[plugins.grant]` in config.toml doesn't give it.
+//! Agent tools naming one of the plugin's own tools are rewritten to its
+//! namespaced name (`plugin__k8s__pod_logs`).
+//!
+//! Capabilities are enforced where the tools run: by the bash sandbox's
+//! mounts for executables, and by the host functions for modules, which have
+//! no other access to the system. qq refuses manifests newer than
+//! [`PLUGIN_API_VERSION`] and tells executables the version it speaks in
+//! `QQ_PLUGIN_API`; modules negotiate a version when they are instantiated.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use qq_agents::{AgentDefinition, AgentsConfig, InternalAgentType};
+use qq_core::ToolRegistry;
+use qq_tools::bash::{plugin_tool_name, PLUGIN_API_VERSION};
+use qq_tools::{PluginGrants, PluginTool, PluginToolSpec};
+
+use crate::config::PluginsConfigEntry;
+
+/// Manifest file in each plugin directory.
+const MANIFEST: &str = "plugin.toml";
+
+const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 60;
+
+/// Capabilities a plugin can ask for; everything else is denied.
+const CAPABILITIES: &[&str] = &["write", "home"];
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// Plugin API version the plugin was written against
+    api_version: u32,
+    name: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    tools: Vec<ManifestTool>,
+    #[serde(default)]
+    agents: HashMap<String, AgentDefinition>,
+    /// WebAssembly module in the plugin directory
+    #[serde(default)]
+    module: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestTool {
+    name: String,
+    description: String,
+    command: Vec<String>,
+    #[serde(default)]
+    parameters: Option<toml::Value>,
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+    #[serde(default)]
+    read_only: bool,
+}
+
+/// A loaded plugin whose capabilities are granted.
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub dir: PathBuf,
+    pub grants: PluginGrants,
+    pub tools: Vec<PluginToolSpec>,
+    pub agents: HashMap<String, AgentDefinition>,
+    #[cfg(feature = "wasm-plugins")]
+    pub module: Option<Arc<qq_tools::WasmPlugin>>,
+}
+
+/// Load the plugins in `[plugins] dir` (default `~/.config/qq/plugins`).
+/// Plugins that fail to load are reported on stderr and skipped.
+pub fn load(config: &PluginsConfigEntry) -> Vec<Plugin> {
+    if !config.enabled {
+        return Vec::new();
+    }
+    let dir = match config.dir {
+        Some(ref dir) => Some(crate::config::expand_path(dir)),
+        None => crate::paths::plugins_dir(),
+    };
+    let Some(dir) = dir else {
+        return Vec::new();
+    };
+    let (plugins, warnings) = load_dir(&dir, config);
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    plugins
+}
+
+/// Plugins in `dir`, in name order, and why the others were skipped.
+fn load_dir(dir: &Path, config: &PluginsConfigEntry) -> (Vec<Plugin>, Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join(MANIFEST).is_file())
+        .collect();
+    dirs.sort();
+
+    let mut plugins: Vec<Plugin> = Vec::new();
+    let mut warnings = Vec::new();
+    for plugin_dir in dirs {
+        match load_plugin(&plugin_dir, config) {
+            Ok(Some(plugin)) if plugins.iter().any(|p| p.name == plugin.name) => {
+                warnings.push(format!(
+                    "plugin '{}' in {} skipped: another plugin has the same name",
+                    plugin.name,
+                    plugin_dir.display()
+                ));
+            }
+            Ok(Some(plugin)) => {
+                tracing::info!(
+                    plugin = %plugin.name,
+                    version = %plugin.version,
+                    description = %plugin.description,
+                    "Loaded plugin"
+                );
+                plugins.push(plugin);
+            }
+            Ok(None) => {}
+            Err(e) => warnings.push(format!(
+                "plugin in {} skipped: {:#}",
+                plugin_dir.display(),
+                e
+            )),
+        }
+    }
+    (plugins, warnings)
+}
+
+/// Load one plugin. `None` when it is listed in `[plugins] disabled`.
+fn load_plugin(dir: &Path, config: &PluginsConfigEntry) -> Result<Option<Plugin>> {
+    let path = dir.join(MANIFEST);
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest: Manifest =
+        toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    let name = manifest.name;
+    if config.disabled.contains(&name) {
+        return Ok(None);
+    }
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("invalid plugin name '{}'", name);
+    }
+
+    // Version negotiation: older APIs are kept working, newer ones need an upgrade
+    match manifest.api_version {
+        0 => bail!("'{}' declares api_version 0", name),
+        v if v > PLUGIN_API_VERSION => bail!(
+            "'{}' needs plugin API {}, this qq supports up to {}; upgrade qq",
+            name,
+            v,
+            PLUGIN_API_VERSION
+        ),
+        _ => {}
+    }
+
+    let requested: BTreeSet<&str> = manifest.capabilities.iter().map(String::as_str).collect();
+    if let Some(unknown) = requested.iter().find(|c| !CAPABILITIES.contains(c)) {
+        bail!(
+            "'{}' asks for unknown capability '{}' (known: {})",
+            name,
+            unknown,
+            CAPABILITIES.join(", ")
+        );
+    }
+    let granted = config.grant.get(&name);
+    let missing: Vec<&str> = requested
+        .iter()
+        .copied()
+        .filter(|c| !granted.is_some_and(|g| g.iter().any(|g| g == c)))
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "'{}' needs capabilities it wasn't granted; to allow, add to config.toml:\n  \
+             [plugins.grant]\n  {} = [{}]",
+            name,
+            name,
+            requested
+                .iter()
+                .map(|c| format!("\"{}\"", c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let grants = PluginGrants {
+        write: requested.contains("write"),
+        home: requested.contains("home"),
+    };
+
+    let mut tools = Vec::new();
+    for tool in manifest.tools {
+        if tool.command.is_empty() {
+            bail!("tool '{}' has an empty command", tool.name);
+        }
+        let parameters = match tool.parameters {
+            Some(value) => serde_json::to_value(value)?,
+            None => serde_json::json!({"type": "object", "properties": {}}),
+        };
+        tools.push(PluginToolSpec {
+            plugin: name.clone(),
+            name: tool.name,
+            description: tool.description,
+            parameters,
+            command: tool.command,
+            timeout_secs: tool.timeout_secs.unwrap_or(DEFAULT_TOOL_TIMEOUT_SECS),
+            read_only: tool.read_only,
+        });
+    }
+
+    let mut agents = manifest.agents;
+    #[cfg(feature = "wasm-plugins")]
+    let module = match manifest.module {
+        Some(ref file) => {
+            let module = load_module(&name, dir, file, grants)?;
+            for tool in &module.manifest().tools {
+                if tools.iter().any(|t| t.name == tool.name) {
+                    bail!("tool '{}' is declared twice", tool.name);
+                }
+            }
+            for (agent, definition) in &module.manifest().agents {
+                if agents.contains_key(agent) {
+                    bail!("agent '{}' is declared twice", agent);
+                }
+                let definition = serde_json::from_value(definition.clone())
+                    .with_context(|| format!("Invalid agent '{}' in module", agent))?;
+                agents.insert(agent.clone(), definition);
+            }
+            Some(Arc::new(module))
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "wasm-plugins"))]
+    if manifest.module.is_some() {
+        bail!(
+            "'{}' ships a WebAssembly module; this qq was built without the wasm-plugins feature",
+            name
+        );
+    }
+    let tool_names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+    #[cfg(feature = "wasm-plugins")]
+    let tool_names: Vec<&str> = match module {
+        Some(ref module) => tool_names
+            .into_iter()
+            .chain(module.manifest().tools.iter().map(|t| t.name.as_str()))
+            .collect(),
+        None => tool_names,
+    };
+    for agent in agents.values_mut() {
+        for tool in agent.tools.iter_mut() {
+            if tool_names.contains(&tool.as_str()) {
+                *tool = plugin_tool_name(&name, tool);
+            }
+        }
+    }
+
+    Ok(Some(Plugin {
+        name,
+        version: manifest.version,
+        description: manifest.description,
+        dir: dir.to_path_buf(),
+        grants,
+        tools,
+        agents,
+        #[cfg(feature = "wasm-plugins")]
+        module,
+    }))
+}
+
+/// Compile a plugin's module and negotiate its API version. `file` must be
+/// a path inside the plugin directory.
+#[cfg(feature = "wasm-plugins")]
+fn load_module(
+    name: &str,
+    dir: &Path,
+    file: &str,
+    grants: PluginGrants,
+) -> Result<qq_tools::WasmPlugin> {
+    if !Path::new(file)
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        bail!("module '{}' must be a path inside the plugin directory", file);
+    }
+    qq_tools::WasmPlugin::load(name, &dir.join(file), grants)
+        .map_err(anyhow::Error::msg)
+        .with_context(|| format!("Failed to load module '{}'", file))
+}
+
+/// Register the plugins' tools, run in the session sandbox.
+pub fn register_tools(
+    plugins: &[Plugin],
+    registry: &mut ToolRegistry,
+    mounts: &Arc<qq_tools::SandboxMounts>,
+    policy: &qq_tools::SandboxPathPolicy,
+    executor: &qq_tools::SandboxExecutor,
+) {
+    for plugin in plugins {
+        if !plugin.tools.is_empty() && !executor.supports_shell() {
+            eprintln!(
+                "Warning: tools of plugin '{}' disabled: they need the kernel sandbox",
+                plugin.name
+            );
+            continue;
+        }
+        for spec in &plugin.tools {
+            registry.register(Arc::new(PluginTool::new(
+                spec.clone(),
+                &plugin.dir,
+                plugin.grants,
+                Arc::clone(mounts),
+                policy,
+                executor.clone(),
+            )));
+        }
+    }
+}
+
+/// Register the tools of the plugins' WebAssembly modules. They run
+/// in-process under the host functions' limits, so unlike executables they
+/// don't need the bash sandbox.
+pub fn register_module_tools(plugins: &[Plugin], registry: &mut ToolRegistry, project_root: &Path) {
+    #[cfg(feature = "wasm-plugins")]
+    for plugin in plugins {
+        let Some(ref module) = plugin.module else {
+            continue;
+        };
+        for spec in &module.manifest().tools {
+            registry.register(Arc::new(qq_tools::WasmPluginTool::new(
+                Arc::clone(module),
+                spec.clone(),
+                project_root,
+                dirs::home_dir(),
+            )));
+        }
+    }
+    #[cfg(not(feature = "wasm-plugins"))]
+    let _ = (plugins, registry, project_root);
+}
+
+/// Add the plugins' agents to the external agents. Agents.toml and the
+/// built-in agents win over a plugin agent with the same name.
+pub fn merge_agents(plugins: &[Plugin], agents_config: &mut AgentsConfig) {
+    for plugin in plugins {
+        for (name, definition) in &plugin.agents {
+            if agents_config.contains(name) || InternalAgentType::from_name(name).is_some() {
+                eprintln!(
+                    "Warning: agent '{}' of plugin '{}' skipped: an agent with that name exists",
+                    name, plugin.name
+                );
+                continue;
+            }
+            agents_config
+                .agents
+                .insert(name.clone(), definition.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_plugin(root: &Path, dir: &str, manifest: &str) {
+        let dir = root.join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(MANIFEST), manifest).unwrap();
+    }
+
+    const K8S: &str = r#"
+api_version = 1
+name = "k8s"
+version = "0.3.0"
+capabilities = ["home"]
+
+[[tools]]
+name = "pod_logs"
+description = "Recent logs of a pod"
+command = ["./bin/pod-logs"]
+read_only = true
+parameters = { type = "object", properties = { pod = { type = "string" } }, required = ["pod"] }
+
+[agents.k8s_debugger]
+description = "Diagnoses failing pods"
+system_prompt = "You debug Kubernetes workloads."
+tools = ["pod_logs", "read_file"]
+"#;
+
+    #[test]
+    fn test_load_checks_version_and_grants() {
+        let root = tempfile::tempdir().unwrap();
+        write_plugin(root.path(), "k8s", K8S);
+        write_plugin(
+            root.path(),
+            "future",
+            "api_version = 7\nname = \"future\"\n",
+        );
+        write_plugin(
+            root.path(),
+            "greedy",
+            "api_version = 1\nname = \"greedy\"\ncapabilities = [\"root\"]\n",
+        );
+
+        // Nothing granted: k8s needs `home`
+        let mut config = PluginsConfigEntry::default();
+        let (plugins, warnings) = load_dir(root.path(), &config);
+        assert!(plugins.is_empty());
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].contains("needs plugin API 7"));
+        assert!(warnings[1].contains("unknown capability 'root'"));
+        assert!(warnings[2].contains("k8s = [\"home\"]"));
+
+        config.grant.insert("k8s".into(), vec!["home".into()]);
+        config.disabled.push("future".into());
+        let (plugins, warnings) = load_dir(root.path(), &config);
+        assert_eq!(warnings.len(), 1);
+        let k8s = &plugins[0];
+        assert_eq!(k8s.version, "0.3.0");
+        assert_eq!(
+            k8s.grants,
+            PluginGrants {
+                write: false,
+                home: true
+            }
+        );
+        assert_eq!(k8s.tools[0].parameters["required"][0], "pod");
+        assert_eq!(
+            k8s.agents["k8s_debugger"].tools,
+            vec!["plugin__k8s__pod_logs", "read_file"]
+        );
+    }
+
+    const NOTES: &str = "api_version = 1\nname = \"notes\"\nmodule = \"notes.wasm\"\n";
+
+    #[cfg(feature = "wasm-plugins")]
+    #[test]
+    fn test_load_module_tools_and_agents() {
+        let manifest = r#"{"tools": [{"name": "lookup", "description": "Look up a note"}], "agents": {"notes": {"description": "Notes", "system_prompt": "You keep notes.", "tools": ["lookup", "read_file"]}}}"#;
+        let wat = format!(
+            r#"(module
+  (import "qq" "output" (func $output (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{}")
+  (func (export "qq_negotiate") (param i32) (result i32) (i32.const 1))
+  (func (export "qq_alloc") (param i32) (result i32) (i32.const 4096))
+  (func (export "qq_manifest") (result i32)
+    (call $output (i32.const 0) (i32.const {}))
+    (i32.const 0))
+  (func (export "qq_call") (param i32 i32 i32 i32) (result i32) (i32.const 0)))"#,
+            manifest.replace('"', "\\\""),
+            manifest.len()
+        );
+        let root = tempfile::tempdir().unwrap();
+        write_plugin(root.path(), "notes", NOTES);
+        std::fs::write(
+            root.path().join("notes/notes.wasm"),
+            wat::parse_str(wat).unwrap(),
+        )
+        .unwrap();
+        write_plugin(
+            root.path(),
+            "sneaky",
+            "api_version = 1\nname = \"sneaky\"\nmodule = \"../notes/notes.wasm\"\n",
+        );
+
+        let (plugins, warnings) = load_dir(root.path(), &PluginsConfigEntry::default());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("inside the plugin directory"));
+        let notes = &plugins[0];
+        assert_eq!(notes.module.as_ref().unwrap().api_version(), 1);
+        assert_eq!(
+            notes.agents["notes"].tools,
+            vec!["plugin__notes__lookup", "read_file"]
+        );
+
+        let mut registry = ToolRegistry::new();
+        register_module_tools(&plugins, &mut registry, root.path());
+        assert!(registry.get("plugin__notes__lookup").is_some());
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    #[test]
+    fn test_module_needs_wasm_feature() {
+        let root = tempfile::tempdir().unwrap();
+        write_plugin(root.path(), "notes", NOTES);
+        let (plugins, warnings) = load_dir(root.path(), &PluginsConfigEntry::default());
+        assert!(plugins.is_empty());
+        assert!(warnings[0].contains("wasm-plugins"));
+    }
+
+    #[test]
+    fn test_merge_agents_keeps_existing_names() {
+        let root = tempfile::tempdir().unwrap();
+        write_plugin(root.path(), "k8s", K8S);
+        let mut config = PluginsConfigEntry::default();
+        config.grant.insert("k8s".into(), vec!["home".into()]);
+        let (mut plugins, _) = load_dir(root.path(), &config);
+        let debugger = plugins[0].agents["k8s_debugger"].clone();
+        plugins[0].agents.insert("coder".into(), debugger);
+
+        let mut agents = AgentsConfig::default();
+        merge_agents(&plugins, &mut agents);
+        assert!(agents.contains("k8s_debugger"));
+        assert!(!agents.contains("coder"));
+    }
+}
@@ -0,0 +1,538 @@
+//! Organization policy: restrictions an admin provisions in
+//! `/etc/qq/policy.toml` that users can't override.
+//!
+//! The file sits outside the user's config directory and has no
+//! command-line or environment override. A policy that fails to parse
+//! stops qq rather than being ignored. Without the file, nothing is
+//! restricted.
+//!
+//! ```toml
+//! # Provider types or API hosts (subdomains included)
+//! banned_providers = ["gemini", "api.openai.com"]
+//! # Redacted from every request and from `qq share` bundles; a
+//! # `(?P<secret>...)` group limits the redaction to that group
+//! redact_patterns = ['ACME-[0-9]{6}', 'customer_id=(?P<secret>\w+)']
+//! # Project roots and extra mounts must be inside one of these
+//! allowed_mount_paths = ["~/work", "/srv/data"]
+//! # Refuse `--insecure`, including from project defaults and resumed sessions
+//! disable_insecure = true
+//! # USD per qq process, at list prices; `[pricing]` overrides don't apply
+//! max_session_cost = 5.0
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use regex::Regex;
+use serde::Deserialize;
+
+use qq_core::{
+    CompletionRequest, CompletionResponse, Content, ContentPart, Error, FileRef, Message, Provider,
+    StreamChunk, StreamResult, TypedContent, Usage,
+};
+use qq_providers::pricing::known_pricing;
+
+use crate::config::expand_path;
+use crate::share::redact_matches;
+
+/// Where the policy is read from.
+pub const POLICY_PATH: &str = "/etc/qq/policy.toml";
+
+/// The policy file as written.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    banned_providers: Vec<String>,
+    #[serde(default)]
+    redact_patterns: Vec<String>,
+    #[serde(default)]
+    allowed_mount_paths: Option<Vec<String>>,
+    #[serde(default)]
+    disable_insecure: bool,
+    #[serde(default)]
+    max_session_cost: Option<f64>,
+}
+
+/// A loaded organization policy.
+#[derive(Debug, Default)]
+pub struct OrgPolicy {
+    /// Where it was loaded from, if a file was found
+    pub path: Option<PathBuf>,
+    /// Lowercased provider types and hosts
+    banned_providers: Vec<String>,
+    redact_patterns: Vec<Regex>,
+    /// Canonical directories mounts must be inside; `None` allows any
+    allowed_mounts: Option<Vec<PathBuf>>,
+    pub disable_insecure: bool,
+    pub max_session_cost: Option<f64>,
+    /// Estimated USD spent so far by this process
+    spent: Mutex<f64>,
+}
+
+static POLICY: OnceLock<OrgPolicy> = OnceLock::new();
+
+/// Load the policy from [`POLICY_PATH`]; call once at startup, before any
+/// provider or tool is created.
+pub fn init() -> Result<&'static OrgPolicy> {
+    let policy = OrgPolicy::load_from(Path::new(POLICY_PATH))?;
+    if let Some(path) = &policy.path {
+        tracing::info!(path = %path.display(), "Organization policy loaded");
+    }
+    Ok(POLICY.get_or_init(|| policy))
+}
+
+/// The policy loaded by [`init`], or no restrictions before it.
+pub fn current() -> &'static OrgPolicy {
+    POLICY.get_or_init(OrgPolicy::default)
+}
+
+impl OrgPolicy {
+    /// Policy from `path`; no restrictions when the file doesn't exist.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read organization policy {}", path.display()))?;
+        let file: PolicyFile = toml::from_str(&content)
+            .with_context(|| format!("Invalid organization policy {}", path.display()))?;
+
+        let redact_patterns = file
+            .redact_patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p).with_context(|| {
+                    format!("Invalid redact_patterns entry '{}' in {}", p, path.display())
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let allowed_mounts = file.allowed_mount_paths.map(|paths| {
+            paths
+                .iter()
+                .map(|p| {
+                    let expanded = expand_path(p);
+                    expanded.canonicalize().unwrap_or(expanded)
+                })
+                .collect()
+        });
+        if file.max_session_cost.is_some_and(|c| c.is_nan() || c < 0.0) {
+            anyhow::bail!("max_session_cost in {} must be zero or more", path.display());
+        }
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            banned_providers: file
+                .banned_providers
+                .iter()
+                .map(|p| p.trim().to_lowercase())
+                .collect(),
+            redact_patterns,
+            allowed_mounts,
+            disable_insecure: file.disable_insecure,
+            max_session_cost: file.max_session_cost,
+            spent: Mutex::new(0.0),
+        })
+    }
+
+    /// Refuse a provider whose type or API host is banned.
+    pub fn check_provider(&self, provider_type: &str, base_url: Option<&str>) -> Result<()> {
+        let provider_type = provider_type.to_lowercase();
+        let host = base_url
+            .map(url_host)
+            .unwrap_or_else(|| default_host(&provider_type))
+            .to_lowercase();
+        let banned = self.banned_providers.iter().find(|entry| {
+            **entry == provider_type
+                || host == **entry
+                || host.strip_suffix(entry.as_str()).is_some_and(|rest| rest.ends_with('.'))
+        });
+        if let Some(entry) = banned {
+            anyhow::bail!(
+                "Provider '{}' ({}) is banned by the organization policy ({}: '{}')",
+                provider_type,
+                host,
+                self.source(),
+                entry
+            );
+        }
+        Ok(())
+    }
+
+    /// Refuse `--insecure` when the policy disables it.
+    pub fn check_insecure(&self, insecure: bool) -> Result<()> {
+        if insecure && self.disable_insecure {
+            anyhow::bail!(
+                "--insecure is disabled by the organization policy ({}); \
+                 commands must run in the kernel sandbox",
+                self.source()
+            );
+        }
+        Ok(())
+    }
+
+    /// Directories the project root and extra mounts must be inside, if
+    /// the policy limits them.
+    pub fn allowed_mounts(&self) -> Option<&[PathBuf]> {
+        self.allowed_mounts.as_deref()
+    }
+
+    /// Refuse a project root outside the allowed mount paths.
+    pub fn check_project_root(&self, root: &Path) -> Result<()> {
+        let Some(allowed) = &self.allowed_mounts else {
+            return Ok(());
+        };
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        if !allowed.iter().any(|dir| root.starts_with(dir)) {
+            anyhow::bail!(
+                "Project root {} is outside the paths the organization policy ({}) allows: {}",
+                root.display(),
+                self.source(),
+                allowed
+                    .iter()
+                    .map(|d| d.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    pub fn redact_patterns(&self) -> &[Regex] {
+        &self.redact_patterns
+    }
+
+    /// `text` with every redaction pattern applied.
+    pub fn redact(&self, text: &str) -> String {
+        let mut count = 0;
+        let mut out = text.to_string();
+        for pattern in &self.redact_patterns {
+            out = redact_matches(pattern, &out, &mut count).into_owned();
+        }
+        out
+    }
+
+    /// `bytes` of a file upload with the redaction patterns applied. Text is
+    /// redacted like message text; anything else can't be, so it's refused
+    /// while patterns are configured.
+    pub fn redact_upload(&self, filename: &str, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if self.redact_patterns.is_empty() {
+            return Ok(bytes);
+        }
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(self.redact(&text).into_bytes()),
+            Err(_) => Err(Error::invalid_request(format!(
+                "Not uploading {}: the organization policy ({}) redacts outgoing content, \
+                 and only text files can be redacted",
+                filename,
+                self.source()
+            ))),
+        }
+    }
+
+    /// Redact the text of every message the request sends.
+    pub fn redact_messages(&self, messages: &mut [Message]) {
+        if self.redact_patterns.is_empty() {
+            return;
+        }
+        for message in messages {
+            match &mut message.content {
+                Content::Text(text) => *text = self.redact(text),
+                Content::Parts(parts) => {
+                    for part in parts {
+                        match part {
+                            ContentPart::Text { text } => *text = self.redact(text),
+                            ContentPart::ToolResult(result) => {
+                                for content in &mut result.content {
+                                    if let TypedContent::Text { text } = content {
+                                        *text = self.redact(text);
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            if let Some(reasoning) = &mut message.reasoning_content {
+                *reasoning = self.redact(reasoning);
+            }
+        }
+    }
+
+    /// Estimated USD spent so far.
+    pub fn spent(&self) -> f64 {
+        self.spent.lock().map(|s| *s).unwrap_or_default()
+    }
+
+    /// Count a response's usage against the cost limit. Models without a
+    /// list price (local servers) cost nothing.
+    fn record(&self, model: &str, usage: &Usage) {
+        if let Some(price) = known_pricing(model) {
+            if let Ok(mut spent) = self.spent.lock() {
+                *spent += price.cost(usage.prompt_tokens, usage.completion_tokens);
+            }
+        }
+    }
+
+    /// Refuse further requests once the cost limit is reached.
+    fn check_budget(&self) -> Result<(), Error> {
+        match self.max_session_cost {
+            Some(max) if self.spent() >= max => Err(Error::config(format!(
+                "Session cost ~${:.2} has reached the organization policy's limit of ${:.2} ({}); \
+                 start a new session to continue",
+                self.spent(),
+                max,
+                self.source()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Wrap `provider` so requests are redacted and counted against the
+    /// cost limit; unchanged when the policy does neither.
+    pub fn wrap(&'static self, provider: Box<dyn Provider>) -> Box<dyn Provider> {
+        if self.redact_patterns.is_empty() && self.max_session_cost.is_none() {
+            return provider;
+        }
+        Box::new(PolicyProvider {
+            inner: Arc::from(provider),
+            policy: self,
+        })
+    }
+
+    fn source(&self) -> String {
+        self.path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| POLICY_PATH.to_string())
+    }
+}
+
+/// Host of an API base URL.
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    match authority.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => authority.split(':').next().unwrap_or(authority),
+    }
+}
+
+/// API host a provider type uses without a base URL.
+fn default_host(provider_type: &str) -> &'static str {
+    match provider_type {
+        "anthropic" => "api.anthropic.com",
+        "gemini" => "generativelanguage.googleapis.com",
+        _ => "api.openai.com",
+    }
+}
+
+/// Applies the policy's redaction and cost limit to a provider.
+struct PolicyProvider {
+    inner: Arc<dyn Provider>,
+    policy: &'static OrgPolicy,
+}
+
+impl PolicyProvider {
+    fn prepare(&self, mut request: CompletionRequest) -> Result<CompletionRequest, Error> {
+        self.policy.check_budget()?;
+        self.policy.redact_messages(&mut request.messages);
+        Ok(request)
+    }
+}
+
+#[async_trait]
+impl Provider for PolicyProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.inner.default_model()
+    }
+
+    fn available_models(&self) -> Vec<&str> {
+        self.inner.available_models()
+    }
+
+    fn include_tool_reasoning(&self) -> bool {
+        self.inner.include_tool_reasoning()
+    }
+
+    fn context_window(&self) -> Option<u32> {
+        self.inner.context_window()
+    }
+
+    async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        self.policy.check_budget()?;
+        let inputs: Vec<String> = inputs.iter().map(|i| self.policy.redact(i)).collect();
+        self.inner.embed(model, &inputs).await
+    }
+
+    fn supports_file_upload(&self) -> bool {
+        self.inner.supports_file_upload()
+    }
+
+    async fn upload_file(
+        &self,
+        filename: &str,
+        media_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        self.policy.check_budget()?;
+        let bytes = self.policy.redact_upload(filename, bytes)?;
+        self.inner.upload_file(filename, media_type, bytes).await
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, Error> {
+        let request = self.prepare(request)?;
+        let response = self.inner.complete(request).await?;
+        self.policy.record(&response.model, &response.usage);
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamResult, Error> {
+        let request = self.prepare(request)?;
+        let model = request
+            .model
+            .clone()
+            .or_else(|| self.inner.default_model().map(str::to_string))
+            .unwrap_or_default();
+        let stream = self.inner.stream(request).await?;
+        let policy = self.policy;
+        Ok(Box::pin(stream.inspect(move |item| {
+            if let Ok(StreamChunk::Done { usage: Some(usage), .. }) = item {
+                policy.record(&model, usage);
+            }
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::testing::MockProvider;
+    use qq_core::FinishReason;
+
+    fn load(toml: &str) -> Result<OrgPolicy> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.toml");
+        std::fs::write(&path, toml).unwrap();
+        OrgPolicy::load_from(&path)
+    }
+
+    #[test]
+    fn test_load_restrictions_and_reject_invalid_policies() {
+        let none = OrgPolicy::load_from(Path::new("/nonexistent/policy.toml")).unwrap();
+        assert!(none.path.is_none());
+        none.check_provider("gemini", None).unwrap();
+        none.check_insecure(true).unwrap();
+        assert!(none.allowed_mounts().is_none());
+
+        let data = tempfile::tempdir().unwrap();
+        let data_path = data.path().canonicalize().unwrap();
+        let policy = load(&format!(
+            "banned_providers = [\"Gemini\", \"openai.com\"]\n\
+             allowed_mount_paths = [\"{}\"]\n\
+             disable_insecure = true\n",
+            data_path.display()
+        ))
+        .unwrap();
+        assert!(policy.check_provider("gemini", None).is_err());
+        // Hosts match with their subdomains, by default host or base URL
+        assert!(policy.check_provider("openai", None).is_err());
+        assert!(policy
+            .check_provider("openai", Some("https://user@eu.api.openai.com:443/v1"))
+            .is_err());
+        policy
+            .check_provider("openai", Some("http://localhost:11434/v1"))
+            .unwrap();
+        policy
+            .check_provider("openai", Some("https://notopenai.com/v1"))
+            .unwrap();
+        policy.check_provider("anthropic", None).unwrap();
+
+        let err = policy.check_insecure(true).unwrap_err().to_string();
+        assert!(err.contains("--insecure is disabled"), "{}", err);
+        policy.check_insecure(false).unwrap();
+
+        policy.check_project_root(&data_path.join("app")).unwrap();
+        assert!(policy.check_project_root(Path::new("/")).is_err());
+
+        // Fail closed on anything it can't enforce
+        assert!(load("redact_patterns = [\"(\"]").is_err());
+        assert!(load("disable_insecur = true").is_err());
+        assert!(load("max_session_cost = -1.0").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provider_redacts_requests_and_stops_at_cost_limit() {
+        let policy: &'static OrgPolicy = Box::leak(Box::new(
+            load("redact_patterns = ['ACME-\\d+', 'customer=(?P<secret>\\w+)']\nmax_session_cost = 1.0")
+                .unwrap(),
+        ));
+        let mock = Arc::new(MockProvider::new());
+        let provider = PolicyProvider {
+            inner: Arc::clone(&mock) as Arc<dyn Provider>,
+            policy,
+        };
+
+        let request = CompletionRequest::new(vec![
+            Message::user("look up customer=bob in ACME-1234"),
+            Message::tool_result("call_1", "ticket ACME-42"),
+        ]);
+        // 400k prompt tokens at $2.50/Mtok: exactly the limit
+        let response = |usage| CompletionResponse {
+            message: Message::assistant("done"),
+            thinking: None,
+            usage,
+            model: "gpt-4o".to_string(),
+            finish_reason: FinishReason::Stop,
+        };
+        mock.queue_raw_response(response(Usage::new(400_000, 0)));
+        provider.complete(request.clone()).await.unwrap();
+
+        let sent = serde_json::to_string(&mock.last_request().unwrap().messages).unwrap();
+        assert!(sent.contains("look up customer=[redacted] in [redacted]"), "{}", sent);
+        assert!(sent.contains("ticket [redacted]"), "{}", sent);
+        assert!((policy.spent() - 1.0).abs() < 1e-9);
+
+        let err = provider.complete(request).await.unwrap_err().to_string();
+        assert!(err.contains("limit of $1.00"), "{}", err);
+        assert_eq!(mock.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_provider_redacts_text_uploads_and_refuses_binary() {
+        let policy: &'static OrgPolicy =
+            Box::leak(Box::new(load("redact_patterns = ['ACME-\\d+']").unwrap()));
+        let mock = Arc::new(MockProvider::new().with_file_uploads());
+        let provider = PolicyProvider {
+            inner: Arc::clone(&mock) as Arc<dyn Provider>,
+            policy,
+        };
+
+        let file = provider
+            .upload_file("notes.txt", "text/plain", b"ticket ACME-1234\n".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(file.filename, "notes.txt");
+        assert_eq!(
+            *mock.uploaded_bytes.lock().unwrap(),
+            vec![b"ticket [redacted]\n".to_vec()]
+        );
+
+        let err = provider
+            .upload_file("scan.pdf", "application/pdf", vec![0x25, 0xff, 0xfe, 0x00])
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("only text files can be redacted"), "{}", err);
+        assert_eq!(mock.uploaded_files.lock().unwrap().len(), 1);
+    }
+}
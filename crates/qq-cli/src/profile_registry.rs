@@ -142,6 +142,11 @@ impl ProfileRegistry {
         )
     }
 
+    /// Look up a profile by name.
+    pub fn get(&self, name: &str) -> Option<Arc<ResolvedProfileRuntime>> {
+        self.profiles.get(name).cloned()
+    }
+
     /// Sorted list of all configured profile names.
     pub fn list_profile_names(&self) -> Vec<String> {
         let mut names: Vec<String> = self.profiles.keys().cloned().collect();
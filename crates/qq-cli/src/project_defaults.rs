@@ -0,0 +1,265 @@
+//! Settings remembered per project.
+//!
+//! Starting qq in the same project with `-A coder --insecure` every time is
+//! tedious. Interactive sessions record the choices given on the command line
+//! (profile, primary agent, model and the sandbox flags), plus directories
+//! added with `/mount`, under the project's canonical root. Later sessions
+//! there start with them and say so in a confirmation line. A choice given on
+//! the command line still wins and replaces the remembered one;
+//! `--no-project-defaults` forgets everything remembered for the project.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use qq_tools::{write_atomically, FileLock};
+use serde::{Deserialize, Serialize};
+
+/// Settings remembered for one project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectDefaults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub insecure: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub agent_mode: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub ask_network: bool,
+    /// Extra read-only sandbox mounts, canonical paths
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<String>,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl ProjectDefaults {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Record explicit `choices` over the remembered ones. Returns whether
+    /// anything changed.
+    pub fn merge(&mut self, choices: &ProjectDefaults) -> bool {
+        let before = self.clone();
+        for (field, choice) in [
+            (&mut self.profile, &choices.profile),
+            (&mut self.agent, &choices.agent),
+            (&mut self.model, &choices.model),
+        ] {
+            if choice.is_some() {
+                field.clone_from(choice);
+            }
+        }
+        self.insecure |= choices.insecure;
+        self.agent_mode |= choices.agent_mode;
+        self.ask_network |= choices.ask_network;
+        for mount in &choices.mounts {
+            if !self.mounts.contains(mount) {
+                self.mounts.push(mount.clone());
+            }
+        }
+        *self != before
+    }
+
+    /// Fill what this session left unset from `saved`. Returns a short
+    /// description of each setting taken from `saved`.
+    pub fn fill_from(&mut self, saved: &ProjectDefaults) -> Vec<String> {
+        let mut applied = Vec::new();
+        for (name, field, remembered) in [
+            ("profile", &mut self.profile, &saved.profile),
+            ("agent", &mut self.agent, &saved.agent),
+            ("model", &mut self.model, &saved.model),
+        ] {
+            if field.is_none() {
+                if let Some(value) = remembered {
+                    applied.push(format!("{} {}", name, value));
+                    *field = Some(value.clone());
+                }
+            }
+        }
+        for (flag, field, remembered) in [
+            ("--insecure", &mut self.insecure, saved.insecure),
+            ("--agent-mode", &mut self.agent_mode, saved.agent_mode),
+            ("--ask-network", &mut self.ask_network, saved.ask_network),
+        ] {
+            if remembered && !*field {
+                applied.push(flag.to_string());
+                *field = true;
+            }
+        }
+        let mut mounts = 0;
+        for mount in &saved.mounts {
+            if !self.mounts.contains(mount) {
+                self.mounts.push(mount.clone());
+                mounts += 1;
+            }
+        }
+        match mounts {
+            0 => {}
+            1 => applied.push("1 mount".to_string()),
+            n => applied.push(format!("{} mounts", n)),
+        }
+        applied
+    }
+}
+
+/// Key for a project: its canonical root.
+pub fn project_key(root: &Path) -> String {
+    root.canonicalize()
+        .unwrap_or_else(|_| root.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Start of an interactive session: remember the explicit `choices` for
+/// `project` (after forgetting the old record when `forget` is set) and
+/// return the full record to fill the rest of the session's settings from.
+pub fn remember(
+    path: &Path,
+    project: &str,
+    choices: &ProjectDefaults,
+    forget: bool,
+) -> ProjectDefaults {
+    update(path, project, |saved| {
+        let mut changed = false;
+        if forget && !saved.is_empty() {
+            *saved = ProjectDefaults::default();
+            changed = true;
+        }
+        changed |= saved.merge(choices);
+        (saved.clone(), changed)
+    })
+}
+
+/// Remember a directory added with `/mount` for the project rooted at `root`.
+pub fn remember_mount(root: &Path, mount: &Path) {
+    if let Some(path) = crate::paths::project_defaults_file() {
+        add_mount(&path, &project_key(root), &mount.display().to_string());
+    }
+}
+
+fn add_mount(path: &Path, project: &str, mount: &str) {
+    let choices = ProjectDefaults {
+        mounts: vec![mount.to_string()],
+        ..Default::default()
+    };
+    update(path, project, |saved| ((), saved.merge(&choices)));
+}
+
+/// One-line report of settings applied from the project's record.
+pub fn notice(applied: &[String]) -> Option<String> {
+    (!applied.is_empty()).then(|| {
+        format!(
+            "Project defaults: {} (--no-project-defaults to forget)",
+            applied.join(", ")
+        )
+    })
+}
+
+/// Mount paths from a record that still exist, for `[tools] bash_mounts`.
+pub fn existing_mounts(defaults: &ProjectDefaults) -> Vec<String> {
+    defaults
+        .mounts
+        .iter()
+        .filter(|m| PathBuf::from(m).is_dir())
+        .cloned()
+        .collect()
+}
+
+/// Apply `change` to the latest record for `project` and save the file if it
+/// reports a change. Failures are logged; the session goes on without them.
+fn update<R>(
+    path: &Path,
+    project: &str,
+    change: impl FnOnce(&mut ProjectDefaults) -> (R, bool),
+) -> R {
+    let lock = FileLock::acquire(path);
+    let mut all = read_all(path);
+    let entry = all.entry(project.to_string()).or_default();
+    let (result, changed) = change(entry);
+    if changed {
+        if entry.is_empty() {
+            all.remove(project);
+        }
+        let saved = lock.and_then(|_lock| {
+            let json = serde_json::to_string_pretty(&all).map_err(std::io::Error::other)?;
+            write_atomically(path, json.as_bytes())
+        });
+        if let Err(e) = saved {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to save project defaults");
+        }
+    }
+    result
+}
+
+/// Every project's record. Empty when the file is missing or corrupt.
+fn read_all(path: &Path) -> BTreeMap<String, ProjectDefaults> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+    serde_json::from_str(&text)
+        .inspect_err(|e| {
+            tracing::warn!(path = %path.display(), error = %e, "Ignoring corrupt project defaults file");
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remember_fills_later_sessions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("projects.json");
+
+        // First session: `qq -A coder --insecure`
+        let first = ProjectDefaults {
+            agent: Some("coder".to_string()),
+            insecure: true,
+            ..Default::default()
+        };
+        remember(&path, "/work/app", &first, false);
+        add_mount(&path, "/work/app", "/work/shared");
+
+        // Next session with no flags gets them back
+        let saved = remember(&path, "/work/app", &ProjectDefaults::default(), false);
+        let mut session = ProjectDefaults::default();
+        let applied = session.fill_from(&saved);
+        assert_eq!(applied, ["agent coder", "--insecure", "1 mount"]);
+        assert_eq!(session.agent.as_deref(), Some("coder"));
+        assert!(session.insecure);
+        assert_eq!(
+            notice(&applied).unwrap(),
+            "Project defaults: agent coder, --insecure, 1 mount (--no-project-defaults to forget)"
+        );
+
+        // An explicit choice wins and is remembered
+        let explicit = ProjectDefaults {
+            agent: Some("pm".to_string()),
+            ..Default::default()
+        };
+        let saved = remember(&path, "/work/app", &explicit, false);
+        let mut session = explicit.clone();
+        assert_eq!(session.fill_from(&saved), ["--insecure", "1 mount"]);
+        assert_eq!(saved.agent.as_deref(), Some("pm"));
+
+        // Other projects are untouched
+        let other = remember(&path, "/work/other", &ProjectDefaults::default(), false);
+        assert!(other.is_empty());
+
+        // Forgetting keeps only this session's choices
+        let saved = remember(&path, "/work/app", &explicit, true);
+        assert_eq!(saved, explicit);
+        let saved = remember(&path, "/work/app", &ProjectDefaults::default(), true);
+        assert!(saved.is_empty());
+        assert!(read_all(&path).is_empty());
+    }
+}
@@ -0,0 +1,647 @@
+//! Remote approval for unattended runs (`[remote_approval]` in config.toml).
+//!
+//! Each approval request is pushed to a webhook, an ntfy topic, or Pushover
+//! with three signed reply links: allow, allow for session, and deny. qq polls
+//! the reply endpoint for those tokens while the local prompt stays open, and
+//! whichever answers first wins. With no answer within `timeout_secs` the
+//! command is denied, so a long run moves on instead of stalling at the first
+//! per-call command.
+//!
+//! A reply token looks like `qqa-<id>-<decision>-<sig>`. `sig` is an
+//! HMAC-SHA256 of the request id and decision under a key generated at
+//! startup, so tokens from another session, for another request, or made up
+//! by whoever can read the reply topic don't verify.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, oneshot};
+
+use qq_tools::{ApprovalRequest, ApprovalResponse};
+
+use crate::config::{RemoteApprovalBackend, RemoteApprovalConfig};
+use crate::html;
+
+const PUSHOVER_API: &str = "https://api.pushover.net/1/messages.json";
+
+/// Longest command shown in a notification; phones truncate anyway.
+const MAX_COMMAND_CHARS: usize = 1000;
+
+/// Hex characters of the HMAC kept in a token (128 bits).
+const SIG_HEX_CHARS: usize = 32;
+
+static TOKEN_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"qqa-(\d+)-(allow|session|deny)-([0-9a-f]{32})").unwrap());
+
+/// A reply a token can carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Allow,
+    AllowForSession,
+    Deny,
+}
+
+impl Decision {
+    const ALL: [Decision; 3] = [Decision::Allow, Decision::AllowForSession, Decision::Deny];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Decision::Allow => "allow",
+            Decision::AllowForSession => "session",
+            Decision::Deny => "deny",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Decision::Allow => "Allow",
+            Decision::AllowForSession => "Allow for session",
+            Decision::Deny => "Deny",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        Decision::ALL.into_iter().find(|d| d.as_str() == s)
+    }
+
+    fn response(self) -> ApprovalResponse {
+        match self {
+            Decision::Allow => ApprovalResponse::Allow,
+            Decision::AllowForSession => ApprovalResponse::AllowForSession,
+            Decision::Deny => ApprovalResponse::Deny(Some("Denied remotely".to_string())),
+        }
+    }
+}
+
+/// Signs reply tokens and verifies the ones that come back.
+struct Signer {
+    key: [u8; 32],
+}
+
+impl Signer {
+    fn random() -> Result<Self> {
+        let mut key = [0u8; 32];
+        getrandom::getrandom(&mut key)
+            .map_err(|e| anyhow::anyhow!("Failed to generate remote approval key: {}", e))?;
+        Ok(Self { key })
+    }
+
+    fn sig(&self, id: u64, decision: Decision) -> String {
+        let mac = hmac_sha256(
+            &self.key,
+            format!("{}:{}", id, decision.as_str()).as_bytes(),
+        );
+        let mut hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+        hex.truncate(SIG_HEX_CHARS);
+        hex
+    }
+
+    fn token(&self, id: u64, decision: Decision) -> String {
+        format!(
+            "qqa-{}-{}-{}",
+            id,
+            decision.as_str(),
+            self.sig(id, decision)
+        )
+    }
+
+    /// The first valid reply to request `id` in a polled response body.
+    fn find_reply(&self, id: u64, body: &str) -> Option<Decision> {
+        TOKEN_RE.captures_iter(body).find_map(|caps| {
+            if caps[1].parse::<u64>().ok()? != id {
+                return None;
+            }
+            let decision = Decision::parse(&caps[2])?;
+            constant_time_eq(self.sig(id, decision).as_bytes(), caps[3].as_bytes())
+                .then_some(decision)
+        })
+    }
+}
+
+fn hmac_sha256(key: &[u8; 32], message: &[u8]) -> [u8; 32] {
+    let mut inner_pad = [0x36u8; 64];
+    let mut outer_pad = [0x5cu8; 64];
+    for (i, byte) in key.iter().enumerate() {
+        inner_pad[i] ^= byte;
+        outer_pad[i] ^= byte;
+    }
+    let inner = Sha256::new()
+        .chain_update(inner_pad)
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(outer_pad)
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Fill `{token}` in a reply link template, or append it as a query parameter.
+fn reply_link(template: &str, token: &str) -> String {
+    if template.contains("{token}") {
+        template.replace("{token}", token)
+    } else if template.contains('?') {
+        format!("{}&token={}", template, token)
+    } else {
+        format!("{}?token={}", template, token)
+    }
+}
+
+fn truncate_command(command: &str) -> String {
+    match command.char_indices().nth(MAX_COMMAND_CHARS) {
+        Some((end, _)) => format!("{}…", &command[..end]),
+        None => command.to_string(),
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// One approval request, as sent to the phone.
+struct Notice<'a> {
+    id: u64,
+    category: &'a str,
+    command: &'a str,
+    triggers: &'a [String],
+    explanation: Option<&'a str>,
+}
+
+/// Pushes approval requests out and polls for signed replies.
+pub struct RemoteApprovals {
+    config: RemoteApprovalConfig,
+    client: reqwest::Client,
+    signer: Signer,
+    next_id: AtomicU64,
+}
+
+impl RemoteApprovals {
+    /// Check the config for the chosen backend and generate this session's key.
+    pub fn new(config: &RemoteApprovalConfig) -> Result<Self> {
+        let missing = |field: &str| {
+            anyhow::anyhow!(
+                "[remote_approval] backend = \"{}\" needs `{}`",
+                config.backend,
+                field
+            )
+        };
+        match config.backend {
+            RemoteApprovalBackend::Ntfy => {
+                config.url.as_ref().ok_or_else(|| missing("url"))?;
+            }
+            RemoteApprovalBackend::Webhook => {
+                config.url.as_ref().ok_or_else(|| missing("url"))?;
+                config
+                    .reply_url
+                    .as_ref()
+                    .ok_or_else(|| missing("reply_url"))?;
+                config
+                    .poll_url
+                    .as_ref()
+                    .ok_or_else(|| missing("poll_url"))?;
+            }
+            RemoteApprovalBackend::Pushover => {
+                config.token.as_ref().ok_or_else(|| missing("token"))?;
+                config.user.as_ref().ok_or_else(|| missing("user"))?;
+                config
+                    .reply_url
+                    .as_ref()
+                    .ok_or_else(|| missing("reply_url"))?;
+                config
+                    .poll_url
+                    .as_ref()
+                    .ok_or_else(|| missing("poll_url"))?;
+            }
+        }
+        if config.timeout_secs == 0 {
+            bail!("[remote_approval] timeout_secs must be greater than 0");
+        }
+
+        let client = qq_core::http::client_builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("Failed to build remote approval HTTP client")?;
+        Ok(Self {
+            config: config.clone(),
+            client,
+            signer: Signer::random()?,
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Sit between the approval channel and the local UI, the way the event
+    /// socket does: each request also goes to the phone, and the UI's prompt
+    /// is withdrawn if the remote reply (or the timeout) comes first.
+    pub fn interpose(
+        self,
+        mut rx: mpsc::Receiver<ApprovalRequest>,
+    ) -> mpsc::Receiver<ApprovalRequest> {
+        let (ui_tx, ui_rx) = mpsc::channel(8);
+        let remote = Arc::new(self);
+        tokio::spawn(async move {
+            while let Some(request) = rx.recv().await {
+                tokio::spawn(Arc::clone(&remote).relay(request, ui_tx.clone()));
+            }
+        });
+        ui_rx
+    }
+
+    /// Race one request between the local UI, the remote reply, and the timeout.
+    async fn relay(
+        self: Arc<Self>,
+        request: ApprovalRequest,
+        ui_tx: mpsc::Sender<ApprovalRequest>,
+    ) {
+        let ApprovalRequest {
+            full_command,
+            trigger_commands,
+            mut response_tx,
+            category,
+            explanation,
+            preview,
+        } = request;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+        // If the UI is gone the proxy sender is dropped and only the phone can answer
+        let (proxy_tx, mut proxy_rx) = oneshot::channel();
+        let _ = ui_tx
+            .send(ApprovalRequest {
+                full_command: full_command.clone(),
+                trigger_commands: trigger_commands.clone(),
+                response_tx: proxy_tx,
+                category: category.clone(),
+                explanation: explanation.clone(),
+                preview: preview.clone(),
+            })
+            .await;
+
+        // Give someone at the terminal a head start before buzzing the phone
+        let head_start = Duration::from_secs(self.config.notify_after_secs);
+        let mut ui_open = true;
+        if !head_start.is_zero() {
+            tokio::select! {
+                response = &mut proxy_rx => match response {
+                    Ok(response) => {
+                        let _ = response_tx.send(response);
+                        return;
+                    }
+                    // Nobody at the terminal: notify right away
+                    Err(_) => ui_open = false,
+                },
+                _ = response_tx.closed() => return,
+                _ = tokio::time::sleep(head_start) => {}
+            }
+        }
+
+        let notice = Notice {
+            id,
+            category: &category,
+            command: &full_command,
+            triggers: &trigger_commands,
+            explanation: explanation.as_deref(),
+        };
+        let since = unix_now();
+        if let Err(e) = self.notify(&notice).await {
+            tracing::warn!(id, error = %e, "Failed to send remote approval request");
+        } else {
+            tracing::info!(id, backend = %self.config.backend, "Sent remote approval request");
+        }
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        // Dropping the losing receiver withdraws the UI's prompt
+        let response = tokio::select! {
+            Ok(response) = &mut proxy_rx, if ui_open => response,
+            decision = self.wait_for_reply(id, since) => {
+                tracing::info!(id, decision = decision.as_str(), "Remote approval answered");
+                decision.response()
+            }
+            _ = response_tx.closed() => return,
+            _ = tokio::time::sleep(timeout) => {
+                tracing::warn!(id, "Remote approval timed out; denying");
+                ApprovalResponse::Deny(Some(format!(
+                    "No approval within {}s (remote approval timed out)",
+                    self.config.timeout_secs
+                )))
+            }
+        };
+        let _ = response_tx.send(response);
+    }
+
+    /// Push the request to the configured backend.
+    async fn notify(&self, notice: &Notice<'_>) -> Result<()> {
+        let request = match self.config.backend {
+            RemoteApprovalBackend::Webhook => self.webhook_request(notice),
+            RemoteApprovalBackend::Ntfy => self.ntfy_request(notice),
+            RemoteApprovalBackend::Pushover => self.pushover_request(notice),
+        };
+        request
+            .send()
+            .await
+            .context("request failed")?
+            .error_for_status()
+            .context("backend rejected the notification")?;
+        Ok(())
+    }
+
+    fn with_auth(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.config.token {
+            Some(ref token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn links(&self, id: u64) -> Vec<(Decision, String)> {
+        let template = self.config.reply_url.as_deref().unwrap_or_default();
+        Decision::ALL
+            .into_iter()
+            .map(|d| (d, reply_link(template, &self.signer.token(id, d))))
+            .collect()
+    }
+
+    fn webhook_request(&self, notice: &Notice<'_>) -> reqwest::RequestBuilder {
+        let replies: serde_json::Map<String, serde_json::Value> = self
+            .links(notice.id)
+            .into_iter()
+            .map(|(d, link)| (d.as_str().to_string(), link.into()))
+            .collect();
+        let body = serde_json::json!({
+            "id": notice.id,
+            "category": notice.category,
+            "command": notice.command,
+            "triggers": notice.triggers,
+            "explanation": notice.explanation,
+            "timeout_secs": self.config.timeout_secs,
+            "replies": replies,
+        });
+        self.with_auth(
+            self.client
+                .post(self.config.url.as_deref().unwrap_or_default()),
+        )
+        .json(&body)
+    }
+
+    /// Topic that ntfy replies are published to.
+    fn ntfy_reply_topic(&self) -> String {
+        match self.config.reply_url {
+            Some(ref url) => url.trim_end_matches('/').to_string(),
+            None => format!(
+                "{}-reply",
+                self.config
+                    .url
+                    .as_deref()
+                    .unwrap_or_default()
+                    .trim_end_matches('/')
+            ),
+        }
+    }
+
+    /// ntfy action buttons that POST each token to the reply topic in the
+    /// background, without opening a browser.
+    fn ntfy_actions(&self, id: u64) -> String {
+        let topic = self.ntfy_reply_topic();
+        Decision::ALL
+            .into_iter()
+            .map(|d| {
+                format!(
+                    "http, {}, {}, method=POST, body={}, clear=true",
+                    d.label(),
+                    topic,
+                    self.signer.token(id, d)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    fn ntfy_request(&self, notice: &Notice<'_>) -> reqwest::RequestBuilder {
+        let mut message = truncate_command(notice.command);
+        if let Some(explanation) = notice.explanation {
+            message.push_str(&format!("\n{}", explanation));
+        }
+        if !notice.triggers.is_empty() {
+            message.push_str(&format!(
+                "\n\nRequires approval: {}",
+                notice.triggers.join(", ")
+            ));
+        }
+        self.with_auth(
+            self.client
+                .post(self.config.url.as_deref().unwrap_or_default()),
+        )
+        .header("Title", format!("qq: {} approval", notice.category))
+        .header("Priority", "high")
+        .header("Tags", "warning")
+        .header("Actions", self.ntfy_actions(notice.id))
+        .body(message)
+    }
+
+    fn pushover_request(&self, notice: &Notice<'_>) -> reqwest::RequestBuilder {
+        let links: Vec<String> = self
+            .links(notice.id)
+            .into_iter()
+            .map(|(d, link)| format!("<a href=\"{}\">{}</a>", html::escape(&link), d.label()))
+            .collect();
+        let explanation = notice
+            .explanation
+            .map(|e| format!("\n<i>{}</i>", html::escape(e)))
+            .unwrap_or_default();
+        let message = format!(
+            "<b>{}</b>\n{}{}\n\n{}",
+            html::escape(notice.category),
+            html::escape(&truncate_command(notice.command)),
+            explanation,
+            links.join(" · ")
+        );
+        let form = [
+            ("token", self.config.token.clone().unwrap_or_default()),
+            ("user", self.config.user.clone().unwrap_or_default()),
+            ("title", "qq approval".to_string()),
+            ("message", message),
+            ("html", "1".to_string()),
+            ("priority", "1".to_string()),
+        ];
+        self.client.post(PUSHOVER_API).form(&form)
+    }
+
+    /// Where replies to requests sent at `since` can be read.
+    fn poll_url(&self, since: u64) -> String {
+        match (self.config.backend, self.config.poll_url.as_deref()) {
+            (_, Some(url)) => url.replace("{since}", &since.to_string()),
+            (RemoteApprovalBackend::Ntfy, None) => {
+                format!("{}/json?poll=1&since={}", self.ntfy_reply_topic(), since)
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Poll until a valid reply for `id` shows up. Poll errors are logged
+    /// and retried; the caller's timeout bounds the wait.
+    async fn wait_for_reply(&self, id: u64, since: u64) -> Decision {
+        let url = self.poll_url(since);
+        let interval = Duration::from_secs(self.config.poll_interval_secs.max(1));
+        loop {
+            match self.poll(&url).await {
+                Ok(body) => {
+                    if let Some(decision) = self.signer.find_reply(id, &body) {
+                        return decision;
+                    }
+                }
+                Err(e) => tracing::debug!(id, error = %e, "Remote approval poll failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn poll(&self, url: &str) -> Result<String> {
+        let request = match self.config.backend {
+            // The Pushover token is an application key, not a credential for the poll endpoint
+            RemoteApprovalBackend::Pushover => self.client.get(url),
+            _ => self.with_auth(self.client.get(url)),
+        };
+        Ok(request.send().await?.error_for_status()?.text().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(backend: RemoteApprovalBackend) -> RemoteApprovalConfig {
+        RemoteApprovalConfig {
+            backend,
+            url: Some("https://ntfy.example/qq-approvals".to_string()),
+            reply_url: None,
+            poll_url: None,
+            token: None,
+            user: None,
+            timeout_secs: 900,
+            notify_after_secs: 0,
+            poll_interval_secs: 5,
+        }
+    }
+
+    #[test]
+    fn test_tokens_verify_only_for_their_request() {
+        let signer = Signer::random().unwrap();
+        let token = signer.token(7, Decision::AllowForSession);
+        let body = format!(r#"{{"event":"message","message":"{}"}}"#, token);
+        assert_eq!(signer.find_reply(7, &body), Some(Decision::AllowForSession));
+        assert_eq!(signer.find_reply(8, &body), None);
+
+        // Decision swapped, or signed by another session's key
+        let forged = token.replace("-session-", "-allow-");
+        assert_eq!(signer.find_reply(7, &forged), None);
+        let other = Signer::random().unwrap().token(7, Decision::Allow);
+        assert_eq!(signer.find_reply(7, &other), None);
+
+        // A stale reply to another request doesn't hide the real one
+        let body = format!(
+            "{}\n{}",
+            signer.token(6, Decision::Deny),
+            signer.token(7, Decision::Deny)
+        );
+        assert_eq!(signer.find_reply(7, &body), Some(Decision::Deny));
+    }
+
+    #[test]
+    fn test_hmac_sha256_matches_rfc4231() {
+        // RFC 4231 test case 2, with the key zero-padded to 32 bytes (same block)
+        let mut key = [0u8; 32];
+        key[..4].copy_from_slice(b"Jefe");
+        let mac = hmac_sha256(&key, b"what do ya want for nothing?");
+        let hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_reply_links_and_ntfy_defaults() {
+        assert_eq!(
+            reply_link("https://ntfy.sh/r/publish?message={token}", "qqa-1"),
+            "https://ntfy.sh/r/publish?message=qqa-1"
+        );
+        assert_eq!(
+            reply_link("https://hook.example/reply", "t"),
+            "https://hook.example/reply?token=t"
+        );
+        assert_eq!(
+            reply_link("https://hook.example/r?a=1", "t"),
+            "https://hook.example/r?a=1&token=t"
+        );
+
+        let remote = RemoteApprovals::new(&config(RemoteApprovalBackend::Ntfy)).unwrap();
+        assert_eq!(
+            remote.poll_url(1700000000),
+            "https://ntfy.example/qq-approvals-reply/json?poll=1&since=1700000000"
+        );
+        let actions = remote.ntfy_actions(3);
+        assert_eq!(actions.matches("http, ").count(), 3);
+        assert!(actions.starts_with(
+            "http, Allow, https://ntfy.example/qq-approvals-reply, method=POST, body=qqa-3-allow-"
+        ));
+
+        // Webhook and Pushover have no reply channel of their own
+        assert!(RemoteApprovals::new(&config(RemoteApprovalBackend::Webhook)).is_err());
+        let mut pushover = config(RemoteApprovalBackend::Pushover);
+        pushover.token = Some("app".to_string());
+        pushover.user = Some("user".to_string());
+        pushover.reply_url = Some("https://ntfy.sh/r/publish?message={token}".to_string());
+        assert!(RemoteApprovals::new(&pushover).is_err());
+        pushover.poll_url = Some("https://ntfy.sh/r/json?poll=1&since={since}".to_string());
+        assert!(RemoteApprovals::new(&pushover).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_local_answer_wins_and_silence_denies() {
+        // Nothing listens on the discard port, so notifications and polls fail
+        let mut unattended = config(RemoteApprovalBackend::Ntfy);
+        unattended.url = Some("http://127.0.0.1:9/qq".to_string());
+        unattended.timeout_secs = 1;
+        let (approvals, rx) = qq_tools::create_approval_channel();
+        let mut ui_rx = RemoteApprovals::new(&unattended).unwrap().interpose(rx);
+
+        let answer = tokio::spawn(async move {
+            let request = ui_rx.recv().await.unwrap();
+            request.response_tx.send(ApprovalResponse::Allow).unwrap();
+            // Leave the second prompt unanswered
+            let _second = ui_rx.recv().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(3)).await;
+        });
+
+        let first = approvals
+            .request_approval(
+                "git push".to_string(),
+                vec!["git".to_string()],
+                "Bash Command",
+            )
+            .await
+            .unwrap();
+        assert_eq!(first, ApprovalResponse::Allow);
+
+        let second = approvals
+            .request_approval(
+                "rm -rf build".to_string(),
+                vec!["rm".to_string()],
+                "Bash Command",
+            )
+            .await
+            .unwrap();
+        assert!(
+            matches!(second, ApprovalResponse::Deny(Some(ref reason)) if reason.contains("timed out"))
+        );
+        answer.abort();
+    }
+}
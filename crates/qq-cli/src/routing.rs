@@ -0,0 +1,349 @@
+//! Usage-aware model routing.
+//!
+//! Low-complexity turns — a short "yes, continue", a "put that in a table",
+//! the step after a task-list update — don't need the session's main model.
+//! When `[routing]` names a cheaper profile, [`ModelRouter`] classifies each
+//! LLM call from the tail of its context and sends the low-complexity ones
+//! there. Anything that looks like planning or coding stays on the main model.
+//!
+//! Classification is deliberately conservative: the cost of a false
+//! "low-complexity" is a worse answer, so every rule needs a positive signal
+//! and any planning keyword vetoes the downgrade.
+
+use std::sync::Arc;
+
+use qq_core::{Message, Role};
+
+use crate::profile_registry::ResolvedProfileRuntime;
+
+/// Default maximum length of a user message treated as a short follow-up.
+pub const DEFAULT_MAX_FOLLOWUP_CHARS: usize = 80;
+
+/// Default maximum length of a user message treated as a formatting request.
+pub const DEFAULT_MAX_FORMATTING_CHARS: usize = 240;
+
+/// Bookkeeping tools whose results need no reasoning to acknowledge.
+pub const DEFAULT_ACK_TOOLS: &[&str] = &[
+    "create_task",
+    "update_task",
+    "delete_task",
+    "list_tasks",
+    "update_my_task",
+    "inform_user",
+    "schedule_reminder",
+    "datetime",
+];
+
+/// Words that signal real work; any of them keeps the main model.
+const PLANNING_KEYWORDS: &[&str] = &[
+    "implement",
+    "refactor",
+    "design",
+    "plan",
+    "debug",
+    "fix",
+    "write",
+    "add",
+    "create",
+    "build",
+    "change",
+    "update",
+    "review",
+    "test",
+    "migrate",
+    "why",
+    "how",
+    "explain",
+    "investigate",
+    "optimize",
+];
+
+/// Words that mark a request to reshape the previous answer.
+const FORMATTING_KEYWORDS: &[&str] = &[
+    "format",
+    "table",
+    "bullet",
+    "shorter",
+    "concise",
+    "rephrase",
+    "reword",
+    "markdown",
+    "json",
+    "yaml",
+    "summarize",
+    "tl;dr",
+    "translate",
+    "capitalize",
+];
+
+/// How a single LLM call was classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnClass {
+    /// Planning, coding, or anything not positively identified as cheap.
+    Standard,
+    /// A short reply to the previous answer ("yes", "continue", "thanks").
+    ShortFollowUp,
+    /// A request to reformat or condense the previous answer.
+    Formatting,
+    /// The step after bookkeeping tool calls (task updates, notices).
+    ToolAcknowledgment,
+}
+
+impl TurnClass {
+    /// Human-readable reason, used in events and status lines.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TurnClass::Standard => "standard",
+            TurnClass::ShortFollowUp => "short follow-up",
+            TurnClass::Formatting => "formatting request",
+            TurnClass::ToolAcknowledgment => "tool acknowledgment",
+        }
+    }
+
+    pub fn is_low_complexity(&self) -> bool {
+        !matches!(self, TurnClass::Standard)
+    }
+}
+
+/// Thresholds for [`RoutingPolicy::classify`].
+#[derive(Debug, Clone)]
+pub struct RoutingPolicy {
+    pub max_followup_chars: usize,
+    pub max_formatting_chars: usize,
+    pub route_tool_acks: bool,
+    pub ack_tools: Vec<String>,
+}
+
+impl Default for RoutingPolicy {
+    fn default() -> Self {
+        Self {
+            max_followup_chars: DEFAULT_MAX_FOLLOWUP_CHARS,
+            max_formatting_chars: DEFAULT_MAX_FORMATTING_CHARS,
+            route_tool_acks: true,
+            ack_tools: DEFAULT_ACK_TOOLS.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl RoutingPolicy {
+    /// Classify the LLM call about to be made with `messages` as context.
+    ///
+    /// Trailing tool results are judged by which tools produced them; a
+    /// trailing user message by its text. Follow-ups and formatting requests
+    /// only count when there is an earlier assistant answer to follow up on.
+    pub fn classify(&self, messages: &[Message]) -> TurnClass {
+        let Some(last) = messages.last() else {
+            return TurnClass::Standard;
+        };
+        match last.role {
+            Role::Tool => self.classify_tool_results(messages),
+            Role::User => {
+                let has_prior_answer = messages[..messages.len() - 1]
+                    .iter()
+                    .any(|m| m.role == Role::Assistant);
+                match last.content.as_text() {
+                    Some(text) if has_prior_answer => self.classify_user_text(text),
+                    _ => TurnClass::Standard,
+                }
+            }
+            _ => TurnClass::Standard,
+        }
+    }
+
+    fn classify_user_text(&self, text: &str) -> TurnClass {
+        let text = text.trim();
+        let lower = text.to_lowercase();
+        if text.is_empty() || text.contains("```") || has_keyword(&lower, PLANNING_KEYWORDS) {
+            return TurnClass::Standard;
+        }
+        let len = text.chars().count();
+        if len <= self.max_formatting_chars && has_keyword(&lower, FORMATTING_KEYWORDS) {
+            TurnClass::Formatting
+        } else if len <= self.max_followup_chars && !text.contains('\n') {
+            TurnClass::ShortFollowUp
+        } else {
+            TurnClass::Standard
+        }
+    }
+
+    fn classify_tool_results(&self, messages: &[Message]) -> TurnClass {
+        if !self.route_tool_acks {
+            return TurnClass::Standard;
+        }
+        let split = messages
+            .iter()
+            .rposition(|m| m.role != Role::Tool)
+            .unwrap_or(0);
+        let issuer = &messages[split];
+        if issuer.role != Role::Assistant || issuer.tool_calls.is_empty() {
+            return TurnClass::Standard;
+        }
+        let all_acks = issuer
+            .tool_calls
+            .iter()
+            .all(|tc| self.ack_tools.iter().any(|t| t == &tc.name));
+        let any_error = messages[split + 1..]
+            .iter()
+            .any(|m| m.content.to_string_lossy().starts_with("Error"));
+        if all_acks && !any_error {
+            TurnClass::ToolAcknowledgment
+        } else {
+            TurnClass::Standard
+        }
+    }
+}
+
+/// Whole-word (or word-prefix) keyword match, so "fix" hits "fixing" but
+/// "add" does not hit "address".
+fn has_keyword(lower: &str, keywords: &[&str]) -> bool {
+    lower
+        .split(|c: char| !(c.is_alphanumeric() || c == ';'))
+        .any(|word| {
+            keywords
+                .iter()
+                .any(|k| word == *k || (word.starts_with(k) && word.len() <= k.len() + 3))
+        })
+}
+
+/// A downgraded LLM call: which profile serves it and why.
+pub struct RoutedTurn {
+    pub class: TurnClass,
+    pub runtime: Arc<ResolvedProfileRuntime>,
+}
+
+impl RoutedTurn {
+    /// Model to request from the cheap profile's provider.
+    pub fn model(&self) -> Option<String> {
+        self.runtime
+            .model
+            .clone()
+            .or_else(|| self.runtime.provider.default_model().map(|s| s.to_string()))
+    }
+}
+
+/// Routes low-complexity LLM calls to a cheaper profile.
+#[derive(Clone)]
+pub struct ModelRouter {
+    policy: RoutingPolicy,
+    runtime: Arc<ResolvedProfileRuntime>,
+}
+
+impl ModelRouter {
+    pub fn new(policy: RoutingPolicy, runtime: Arc<ResolvedProfileRuntime>) -> Self {
+        Self { policy, runtime }
+    }
+
+    /// Route the call with `messages` as context, or `None` to keep the
+    /// main model. Never routes when the main profile already is the cheap one.
+    pub fn route(&self, main_profile: &str, messages: &[Message]) -> Option<RoutedTurn> {
+        if main_profile == self.runtime.profile_name {
+            return None;
+        }
+        let class = self.policy.classify(messages);
+        class.is_low_complexity().then(|| RoutedTurn {
+            class,
+            runtime: Arc::clone(&self.runtime),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::ToolCall;
+
+    fn answered(user: &str) -> Vec<Message> {
+        vec![
+            Message::user("Summarize the open issues in this repo"),
+            Message::assistant("There are three open issues: ..."),
+            Message::user(user),
+        ]
+    }
+
+    #[test]
+    fn test_short_follow_ups_and_formatting_are_low_complexity() {
+        let policy = RoutingPolicy::default();
+        assert_eq!(
+            policy.classify(&answered("yes, continue")),
+            TurnClass::ShortFollowUp
+        );
+        assert_eq!(
+            policy.classify(&answered("thanks!")),
+            TurnClass::ShortFollowUp
+        );
+        assert_eq!(
+            policy.classify(&answered("Can you put that in a markdown table?")),
+            TurnClass::Formatting
+        );
+        // Nothing to follow up on yet
+        assert_eq!(
+            policy.classify(&[Message::user("yes")]),
+            TurnClass::Standard
+        );
+    }
+
+    #[test]
+    fn test_planning_and_coding_stay_standard() {
+        let policy = RoutingPolicy::default();
+        for text in [
+            "fix the failing test",
+            "why is that?",
+            "Now implement the second one",
+            "format the code in src/main.rs and then refactor parse()",
+            "```rust\nfn main() {}\n```",
+        ] {
+            assert_eq!(
+                policy.classify(&answered(text)),
+                TurnClass::Standard,
+                "{}",
+                text
+            );
+        }
+        // Long messages are not follow-ups
+        let long = "ok ".repeat(40);
+        assert_eq!(policy.classify(&answered(&long)), TurnClass::Standard);
+        // Word-prefix match does not fire on unrelated words
+        assert_eq!(
+            policy.classify(&answered("the address is fine")),
+            TurnClass::ShortFollowUp
+        );
+    }
+
+    #[test]
+    fn test_tool_acknowledgments() {
+        let policy = RoutingPolicy::default();
+        let issue = |name: &str| {
+            Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall::new("tc-1", name, serde_json::json!({}))],
+            )
+        };
+
+        let ack = vec![
+            Message::user("go"),
+            issue("update_my_task"),
+            Message::tool_result("tc-1", "Task updated"),
+        ];
+        assert_eq!(policy.classify(&ack), TurnClass::ToolAcknowledgment);
+
+        let work = vec![
+            Message::user("go"),
+            issue("run"),
+            Message::tool_result("tc-1", "ok"),
+        ];
+        assert_eq!(policy.classify(&work), TurnClass::Standard);
+
+        let failed = vec![
+            Message::user("go"),
+            issue("update_task"),
+            Message::tool_result("tc-1", "Error: no such task"),
+        ];
+        assert_eq!(policy.classify(&failed), TurnClass::Standard);
+
+        let disabled = RoutingPolicy {
+            route_tool_acks: false,
+            ..RoutingPolicy::default()
+        };
+        assert_eq!(disabled.classify(&ack), TurnClass::Standard);
+    }
+}
@@ -0,0 +1,171 @@
+//! Changes made during a session, for `/diff`.
+//!
+//! When the session starts, [`SessionBaseline::capture`] records the worktree
+//! as a git object (`git stash create`, which leaves the worktree and stash
+//! list alone; `HEAD` when nothing is modified) along with the untracked
+//! files. [`SessionBaseline::diff`] diffs the worktree against that object and
+//! appends the files created since, so edits already in progress when the
+//! session started don't show up. Files that were untracked at the start are
+//! not followed.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use anyhow::{bail, Context, Result};
+
+/// Git's empty tree, the baseline of a repository without commits.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Diff text kept for the pager; the rest is cut with a note.
+const MAX_DIFF_BYTES: usize = 4 * 1024 * 1024;
+
+/// The worktree as it was when the session started.
+#[derive(Debug, Clone)]
+pub struct SessionBaseline {
+    root: PathBuf,
+    /// Commit or tree the session's changes are diffed against
+    base: String,
+    /// Untracked files at the start, relative to the repository root
+    untracked: HashSet<String>,
+}
+
+impl SessionBaseline {
+    /// Record the worktree containing `dir`. `None` outside a git repository.
+    pub fn capture(dir: &Path) -> Option<Self> {
+        let root = git(dir, &["rev-parse", "--show-toplevel"]).ok()?;
+        let root = PathBuf::from(root.trim());
+        let base = match git(&root, &["stash", "create"]) {
+            Ok(stash) if !stash.trim().is_empty() => stash.trim().to_string(),
+            _ => git(&root, &["rev-parse", "--verify", "-q", "HEAD"])
+                .map(|head| head.trim().to_string())
+                .unwrap_or_else(|_| EMPTY_TREE.to_string()),
+        };
+        let untracked = untracked_files(&root).unwrap_or_default();
+        Some(Self {
+            root,
+            base,
+            untracked,
+        })
+    }
+
+    /// Commit or tree the session's changes are diffed against.
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Unified diff of everything changed since [`capture`](Self::capture).
+    /// Empty when nothing changed.
+    pub fn diff(&self) -> Result<String> {
+        let mut diff = git(
+            &self.root,
+            &["diff", "--no-color", "--no-ext-diff", &self.base, "--"],
+        )?;
+        let mut created: Vec<String> = untracked_files(&self.root)?
+            .into_iter()
+            .filter(|path| !self.untracked.contains(path))
+            .collect();
+        created.sort();
+        for path in created {
+            if diff.len() > MAX_DIFF_BYTES {
+                break;
+            }
+            // Exit status 1 means the files differ, which they always do here
+            let output = git_output(
+                &self.root,
+                &["diff", "--no-color", "--no-index", "--", "/dev/null", &path],
+            )?;
+            if output.status.code() == Some(1) {
+                diff.push_str(&String::from_utf8_lossy(&output.stdout));
+            }
+        }
+        if diff.len() > MAX_DIFF_BYTES {
+            let end = qq_core::text::floor_grapheme_boundary(&diff, MAX_DIFF_BYTES);
+            let cut = diff[..end].rfind('\n').map_or(end, |i| i + 1);
+            diff.truncate(cut);
+            diff.push_str("[diff truncated]\n");
+        }
+        Ok(diff)
+    }
+}
+
+fn untracked_files(root: &Path) -> Result<HashSet<String>> {
+    let listing = git(root, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+    Ok(listing
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = git_output(dir, args)?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn run(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn test_diff_covers_only_session_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        run(root, &["init", "-q"]);
+        run(root, &["config", "user.email", "qq@example.com"]);
+        run(root, &["config", "user.name", "qq"]);
+        fs::write(root.join("lib.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        run(root, &["add", "."]);
+        run(root, &["commit", "-qm", "init"]);
+
+        // In progress before the session: not part of its diff
+        fs::write(root.join("main.rs"), "fn main() { todo!() }\n").unwrap();
+        fs::write(root.join("notes.txt"), "scratch\n").unwrap();
+
+        let baseline = SessionBaseline::capture(root).unwrap();
+        assert_eq!(baseline.diff().unwrap(), "");
+
+        fs::write(root.join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        fs::write(root.join("new.rs"), "fn c() {}\n").unwrap();
+        let diff = baseline.diff().unwrap();
+        assert!(diff.contains("diff --git a/lib.rs b/lib.rs"));
+        assert!(diff.contains("+fn b() {}"));
+        assert!(diff.contains("+fn c() {}"));
+        assert!(!diff.contains("main.rs"));
+        assert!(!diff.contains("notes.txt"));
+
+        // Stash list untouched by capture
+        let stashes = git(root, &["stash", "list"]).unwrap();
+        assert!(stashes.is_empty());
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(SessionBaseline::capture(outside.path()).is_none());
+    }
+}
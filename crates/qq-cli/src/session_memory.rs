@@ -0,0 +1,628 @@
+//! Memory ceilings for long-running sessions.
+//!
+//! The TUI transcript and the chat history both grow for as long as a session
+//! runs. Past their ceilings (`[session_memory]` in the config), the oldest
+//! parts move to a [`SpillFile`] in the cache directory instead of staying in
+//! RAM. The transcript pages spilled sections back in when scrolled to the
+//! top; `/export` reads everything back for the full record.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use qq_core::{find_safe_split_point, Message, Role};
+
+use crate::turn_stats::{Pricing, TurnStats};
+
+/// Spill files untouched for this long belong to sessions that crashed.
+const STALE_SPILL_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Prefix of the transcript when older content was discarded.
+const TRUNCATED_MARKER: &str = "...[earlier conversation truncated]...\n\n";
+
+static NEXT_SPILL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Append-only stack of text sections backed by a temporary file.
+///
+/// Sections come back newest first with [`pop`](Self::pop), or all at once,
+/// oldest first, with [`read_all`](Self::read_all). The file is removed on drop.
+#[derive(Debug)]
+pub struct SpillFile {
+    path: PathBuf,
+    file: File,
+    /// Start offset of each section, oldest first
+    sections: Vec<u64>,
+    len: u64,
+}
+
+impl SpillFile {
+    /// Create an empty spill file in `dir`, sweeping stale ones left behind.
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        sweep_stale(dir);
+        let path = dir.join(format!(
+            "{}-{}.spill",
+            std::process::id(),
+            NEXT_SPILL_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            sections: Vec::new(),
+            len: 0,
+        })
+    }
+
+    /// Append a section.
+    pub fn push(&mut self, text: &str) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(self.len))?;
+        self.file.write_all(text.as_bytes())?;
+        self.sections.push(self.len);
+        self.len += text.len() as u64;
+        Ok(())
+    }
+
+    /// Remove and return the newest section.
+    pub fn pop(&mut self) -> io::Result<Option<String>> {
+        let Some(start) = self.sections.pop() else {
+            return Ok(None);
+        };
+        let text = self.read_range(start, self.len)?;
+        self.file.set_len(start)?;
+        self.len = start;
+        Ok(Some(text))
+    }
+
+    /// All sections concatenated, oldest first.
+    pub fn read_all(&self) -> io::Result<String> {
+        self.read_range(0, self.len)
+    }
+
+    /// One section by index, oldest first.
+    pub fn read_section(&self, index: usize) -> io::Result<Option<String>> {
+        let Some(&start) = self.sections.get(index) else {
+            return Ok(None);
+        };
+        let end = self.sections.get(index + 1).copied().unwrap_or(self.len);
+        self.read_range(start, end).map(Some)
+    }
+
+    /// Bytes currently on disk.
+    pub fn bytes(&self) -> u64 {
+        self.len
+    }
+
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    fn read_range(&self, start: u64, end: u64) -> io::Result<String> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(start))?;
+        let mut buf = vec![0; (end - start) as usize];
+        file.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Remove spill files whose session never cleaned up after itself.
+fn sweep_stale(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "spill") {
+            continue;
+        }
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| {
+                now.duration_since(modified)
+                    .is_ok_and(|age| age > STALE_SPILL_AGE)
+            });
+        if stale {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Where spill files go: the cache directory, or the system temp directory.
+pub(crate) fn spill_dir() -> PathBuf {
+    crate::paths::spill_dir().unwrap_or_else(|| std::env::temp_dir().join("qq-spill"))
+}
+
+/// Open the spill file on first use. Failures are logged and leave `None`,
+/// so the caller falls back to discarding.
+pub(crate) fn ensure_spill<'a>(slot: &'a mut Option<SpillFile>, dir: &Path) -> Option<&'a mut SpillFile> {
+    if slot.is_none() {
+        match SpillFile::create(dir) {
+            Ok(file) => *slot = Some(file),
+            Err(e) => {
+                tracing::warn!(error = %e, dir = %dir.display(), "Cannot create spill file");
+            }
+        }
+    }
+    slot.as_mut()
+}
+
+/// In-memory ceiling for the TUI transcript.
+///
+/// Past `ceiling` bytes the oldest half moves to disk (or is discarded when
+/// spilling is off) and a marker line takes its place.
+#[derive(Debug)]
+pub struct TranscriptSpill {
+    ceiling: usize,
+    spill_to_disk: bool,
+    dir: PathBuf,
+    file: Option<SpillFile>,
+    /// Marker currently prefixed to the transcript, if any
+    marker: Option<String>,
+    /// Bytes paged back in since the last reset; allowed over the ceiling
+    paged_in: usize,
+}
+
+impl TranscriptSpill {
+    pub fn new(ceiling: usize, spill_to_disk: bool) -> Self {
+        Self::with_dir(ceiling, spill_to_disk, spill_dir())
+    }
+
+    fn with_dir(ceiling: usize, spill_to_disk: bool, dir: PathBuf) -> Self {
+        Self {
+            ceiling,
+            spill_to_disk,
+            dir,
+            file: None,
+            marker: None,
+            paged_in: 0,
+        }
+    }
+
+    pub fn ceiling(&self) -> usize {
+        self.ceiling
+    }
+
+    /// Bytes of transcript on disk.
+    pub fn spilled_bytes(&self) -> u64 {
+        self.file.as_ref().map_or(0, SpillFile::bytes)
+    }
+
+    /// Whether scrolling to the top has anything to load.
+    pub fn has_spilled(&self) -> bool {
+        self.file.as_ref().is_some_and(|f| !f.is_empty())
+    }
+
+    /// Count paged-in sections against the ceiling again.
+    pub fn reset_paging(&mut self) {
+        self.paged_in = 0;
+    }
+
+    /// Forget everything spilled (the transcript was cleared).
+    pub fn clear(&mut self) {
+        self.file = None;
+        self.marker = None;
+        self.paged_in = 0;
+    }
+
+    /// Move the oldest part of `content` out once it exceeds the ceiling.
+    /// Returns true if `content` changed.
+    pub fn enforce(&mut self, content: &mut String) -> bool {
+        if content.len() <= self.ceiling + self.paged_in {
+            return false;
+        }
+        let start = self.marker_len(content);
+        let target = qq_core::text::ceil_grapheme_boundary(
+            content,
+            (content.len() - self.ceiling / 2).max(start),
+        );
+        // Cut at a paragraph break so neither side ends mid-block
+        let split_at = content[target..]
+            .find("\n\n")
+            .map(|i| target + i + 2)
+            .unwrap_or(target);
+
+        let spilled = self.spill_to_disk
+            && match ensure_spill(&mut self.file, &self.dir) {
+                Some(file) => file
+                    .push(&content[start..split_at])
+                    .inspect_err(|e| tracing::warn!(error = %e, "Transcript spill failed"))
+                    .is_ok(),
+                None => false,
+            };
+        if !spilled {
+            // Whatever is already on disk predates the discarded part
+            self.file = None;
+        }
+        self.paged_in = 0;
+        let marker = self.next_marker();
+        *content = format!("{}{}", marker, &content[split_at..]);
+        self.marker = Some(marker);
+        true
+    }
+
+    /// Load the newest spilled section back in front of `content`.
+    /// Returns the bytes loaded.
+    pub fn page_in(&mut self, content: &mut String) -> Option<usize> {
+        let section = match self.file.as_mut()?.pop() {
+            Ok(section) => section?,
+            Err(e) => {
+                tracing::warn!(error = %e, "Cannot read spilled transcript");
+                return None;
+            }
+        };
+        let start = self.marker_len(content);
+        let marker = self.has_spilled().then(|| self.next_marker());
+        *content = format!(
+            "{}{}{}",
+            marker.as_deref().unwrap_or(""),
+            section,
+            &content[start..]
+        );
+        self.marker = marker;
+        self.paged_in += section.len();
+        Some(section.len())
+    }
+
+    /// The whole transcript: spilled sections followed by `content`.
+    pub fn full_transcript(&self, content: &str) -> io::Result<String> {
+        let mut full = match self.file {
+            Some(ref file) => file.read_all()?,
+            None => String::new(),
+        };
+        full.push_str(&content[self.marker_len(content)..]);
+        Ok(full)
+    }
+
+    fn marker_len(&self, content: &str) -> usize {
+        match self.marker {
+            Some(ref marker) if content.starts_with(marker.as_str()) => marker.len(),
+            _ => 0,
+        }
+    }
+
+    fn next_marker(&self) -> String {
+        match self.file {
+            Some(ref file) if !file.is_empty() => format!(
+                "...[{} of earlier conversation on disk; scroll up to load]...\n\n",
+                crate::chat::format_bytes(file.bytes() as usize)
+            ),
+            _ => TRUNCATED_MARKER.to_string(),
+        }
+    }
+}
+
+/// In-memory ceiling for the chat history.
+///
+/// Past `ceiling` bytes the oldest messages leave the history (and with it the
+/// model's context) until half the ceiling remains. They are kept on disk as
+/// JSON lines for `/export` unless spilling is off.
+#[derive(Debug)]
+pub struct HistorySpill {
+    ceiling: usize,
+    spill_to_disk: bool,
+    dir: PathBuf,
+    file: Option<SpillFile>,
+    /// Messages moved out so far, including discarded ones
+    moved: usize,
+}
+
+impl HistorySpill {
+    pub fn new(ceiling: usize, spill_to_disk: bool) -> Self {
+        Self::with_dir(ceiling, spill_to_disk, spill_dir())
+    }
+
+    fn with_dir(ceiling: usize, spill_to_disk: bool, dir: PathBuf) -> Self {
+        Self {
+            ceiling,
+            spill_to_disk,
+            dir,
+            file: None,
+            moved: 0,
+        }
+    }
+
+    pub fn ceiling(&self) -> usize {
+        self.ceiling
+    }
+
+    /// Messages moved out of memory so far.
+    pub fn moved_count(&self) -> usize {
+        self.moved
+    }
+
+    /// Bytes of history on disk.
+    pub fn spilled_bytes(&self) -> u64 {
+        self.file.as_ref().map_or(0, SpillFile::bytes)
+    }
+
+    pub fn clear(&mut self) {
+        self.file = None;
+        self.moved = 0;
+    }
+
+    /// Move the oldest messages out once `messages` exceeds the ceiling.
+    /// Never splits a tool call from its results, and pinned messages
+    /// neither count toward the ceiling nor move. Returns the count moved.
+    pub fn enforce(&mut self, messages: &mut Vec<Message>) -> usize {
+        let unpinned_bytes = |m: &Message| if m.is_pinned() { 0 } else { m.byte_count() };
+        let total: usize = messages.iter().map(unpinned_bytes).sum();
+        if total <= self.ceiling {
+            return 0;
+        }
+        let mut remaining = total;
+        let mut desired_end = 0;
+        while desired_end + 1 < messages.len() && remaining > self.ceiling / 2 {
+            remaining -= unpinned_bytes(&messages[desired_end]);
+            desired_end += 1;
+        }
+        let end = find_safe_split_point(messages, desired_end);
+        if end == 0 {
+            return 0;
+        }
+
+        let moved = qq_core::drain_unpinned(messages, 0..end);
+        self.retire(&moved);
+        tracing::info!(
+            moved = moved.len(),
+            freed_bytes = total - remaining,
+            spilled = self.spill_to_disk,
+            "Chat history exceeded its memory ceiling"
+        );
+        moved.len()
+    }
+
+    /// Record messages that left the history some other way, such as the
+    /// sliding window, so `/export` still has them.
+    pub fn retire(&mut self, moved: &[Message]) {
+        if moved.is_empty() {
+            return;
+        }
+        if self.spill_to_disk {
+            let lines: String = moved
+                .iter()
+                .filter_map(|m| serde_json::to_string(m).ok())
+                .map(|line| line + "\n")
+                .collect();
+            if let Some(file) = ensure_spill(&mut self.file, &self.dir) {
+                if let Err(e) = file.push(&lines) {
+                    tracing::warn!(error = %e, "History spill failed");
+                }
+            }
+        }
+        self.moved += moved.len();
+    }
+
+    /// Messages moved to disk so far, oldest first.
+    pub fn spilled_messages(&self) -> io::Result<Vec<Message>> {
+        let Some(ref file) = self.file else {
+            return Ok(Vec::new());
+        };
+        file.read_all()?
+            .lines()
+            .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+            .collect()
+    }
+}
+
+/// Destination for `/export [path]`: the given path, or a timestamped file
+/// in the working directory.
+pub fn export_path(arg: &str) -> PathBuf {
+    if arg.trim().is_empty() {
+        PathBuf::from(format!(
+            "qq-session-{}.md",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ))
+    } else {
+        crate::config::expand_path(arg.trim())
+    }
+}
+
+/// Save the Markdown history of a cleared conversation in `dir`. Files are
+/// named for the time and process, so repeated clears never overwrite.
+pub fn save_transcript(dir: &Path, markdown: &str) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let stem = format!(
+        "{}-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    );
+    let mut path = dir.join(format!("{}.md", stem));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{}-{}.md", stem, n));
+    }
+    std::fs::write(&path, markdown)?;
+    Ok(path)
+}
+
+/// Render a chat history as Markdown for `/export`, with a token/cost
+/// footer after each turn's final answer.
+pub fn history_markdown(messages: &[Message], pricing: &Pricing) -> String {
+    let mut out = String::new();
+    let mut turn_start = 0;
+    for (i, message) in messages.iter().enumerate() {
+        let text = message.content.to_string_lossy();
+        match message.role {
+            Role::System => continue,
+            Role::User => {
+                turn_start = i;
+                out.push_str("## You\n\n");
+            }
+            Role::Assistant => out.push_str("## Assistant\n\n"),
+            Role::Tool => {
+                out.push_str("### Tool result\n\n```\n");
+                out.push_str(text.trim_end());
+                out.push_str("\n```\n\n");
+                continue;
+            }
+        }
+        if !text.is_empty() {
+            out.push_str(text.trim_end());
+            out.push_str("\n\n");
+        }
+        for call in &message.tool_calls {
+            out.push_str(&format!("- `{}` {}\n", call.name, call.arguments));
+        }
+        if !message.tool_calls.is_empty() {
+            out.push('\n');
+        }
+        let turn_ends = messages.get(i + 1).is_none_or(|next| next.role == Role::User);
+        if message.role == Role::Assistant && message.tool_calls.is_empty() && turn_ends {
+            let stats = TurnStats::from_messages(&messages[turn_start..=i]);
+            if let Some(footer) = stats.footer(pricing) {
+                out.push_str(&footer);
+                out.push_str("\n\n");
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> tempfile::TempDir {
+        tempfile::Builder::new().prefix(name).tempdir().unwrap()
+    }
+
+    #[test]
+    fn test_spill_file_stack() {
+        let dir = temp_dir("spill-file");
+        let mut file = SpillFile::create(dir.path()).unwrap();
+        file.push("first\n").unwrap();
+        file.push("second ✓\n").unwrap();
+        assert_eq!(file.section_count(), 2);
+        assert_eq!(file.read_all().unwrap(), "first\nsecond ✓\n");
+
+        assert_eq!(file.pop().unwrap().as_deref(), Some("second ✓\n"));
+        file.push("third\n").unwrap();
+        assert_eq!(file.read_all().unwrap(), "first\nthird\n");
+        assert_eq!(file.bytes(), 12);
+
+        let path = file.path.clone();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_transcript_spill_and_page_in() {
+        let dir = temp_dir("spill-transcript");
+        let mut spill = TranscriptSpill::with_dir(100, true, dir.path().to_path_buf());
+        let mut content: String = (0..12).map(|i| format!("paragraph {:02}\n\n", i)).collect();
+        let original = content.clone();
+
+        assert!(spill.enforce(&mut content));
+        assert!(spill.has_spilled());
+        assert!(content.starts_with("...["));
+        assert!(content.len() < original.len());
+        assert_eq!(spill.full_transcript(&content).unwrap(), original);
+
+        let loaded = spill.page_in(&mut content).unwrap();
+        assert!(loaded > 0);
+        assert!(!spill.has_spilled());
+        assert_eq!(content, original);
+        // Paged-in content is allowed over the ceiling until paging resets
+        assert!(!spill.enforce(&mut content));
+        spill.reset_paging();
+        assert!(spill.enforce(&mut content));
+        assert_eq!(spill.full_transcript(&content).unwrap(), original);
+    }
+
+    #[test]
+    fn test_transcript_without_spill_discards() {
+        let mut spill = TranscriptSpill::with_dir(50, false, PathBuf::from("/nonexistent"));
+        let mut content = "é".repeat(60);
+        assert!(spill.enforce(&mut content));
+        assert!(content.starts_with(TRUNCATED_MARKER));
+        assert!(!spill.has_spilled());
+        assert_eq!(spill.page_in(&mut content), None);
+    }
+
+    #[test]
+    fn test_export_path() {
+        assert_eq!(export_path(" notes/out.md "), PathBuf::from("notes/out.md"));
+        let default = export_path("").display().to_string();
+        assert!(default.starts_with("qq-session-") && default.ends_with(".md"));
+    }
+
+    #[test]
+    fn test_history_spill_keeps_tool_sequences() {
+        let dir = temp_dir("spill-history");
+        let mut spill = HistorySpill::with_dir(100, true, dir.path().to_path_buf());
+        let call = qq_core::ToolCall::new("c1", "read_file", serde_json::json!({"path": "a"}));
+        let mut messages = vec![
+            Message::user("x".repeat(60).as_str()),
+            Message::assistant_with_tool_calls("", vec![call]),
+            Message::tool_result("c1", "y".repeat(60).as_str()),
+            Message::assistant("done"),
+            Message::user("next"),
+        ];
+        // Cutting after the tool result would be enough, but the split point
+        // backs off to before the whole tool sequence
+        assert_eq!(spill.enforce(&mut messages), 1);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].tool_calls.len(), 1);
+        assert_eq!(spill.enforce(&mut messages), 0);
+
+        let spilled = spill.spilled_messages().unwrap();
+        assert_eq!(spilled.len(), 1);
+        assert_eq!(spilled[0].content.to_string_lossy(), "x".repeat(60));
+        assert_eq!(spill.moved_count(), 1);
+
+        let markdown = history_markdown(&[spilled, messages].concat(), &Pricing::default());
+        assert!(markdown.contains("- `read_file` {\"path\":\"a\"}"));
+        assert!(markdown.contains("### Tool result"));
+        assert!(markdown.ends_with("## You\n\nnext\n\n"));
+    }
+
+    #[test]
+    fn test_history_spill_keeps_pinned_messages() {
+        let dir = temp_dir("spill-pinned");
+        let mut spill = HistorySpill::with_dir(100, false, dir.path().to_path_buf());
+        let mut messages = vec![
+            Message::user("x".repeat(200).as_str()),
+            Message::user("y".repeat(60).as_str()),
+            Message::user("next"),
+        ];
+        messages[0].set_pinned(true);
+        // The pinned message is over the ceiling alone but never moves
+        assert_eq!(spill.enforce(&mut messages), 0);
+        messages.push(Message::user("z".repeat(60).as_str()));
+        assert_eq!(spill.enforce(&mut messages), 2);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].is_pinned());
+    }
+
+    #[test]
+    fn test_save_transcript_never_overwrites() {
+        let dir = temp_dir("transcripts");
+        let nested = dir.path().join("transcripts");
+        let first = save_transcript(&nested, "## You\n\nhi\n\n").unwrap();
+        let second = save_transcript(&nested, "## You\n\nagain\n\n").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "## You\n\nhi\n\n");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "## You\n\nagain\n\n");
+    }
+}
@@ -0,0 +1,197 @@
+//! Summaries of compacted history, for the `/summaries` pane.
+//!
+//! Each observation pass replaces older messages with a summary in the
+//! observation log. The [`SummaryArchive`] keeps every pass's summary for the
+//! session and, with `[session_memory] spill_to_disk` on, the messages it
+//! replaced (as JSON lines in a [`SpillFile`]), so the user can check what a
+//! summary left out and re-inject a detail with [`recall_block`].
+
+use std::io;
+use std::path::PathBuf;
+
+use qq_core::{Message, ObservationPass, Role};
+
+use crate::session_memory::{ensure_spill, spill_dir, SpillFile};
+
+/// One observation pass as shown in the pane.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionSummary {
+    /// Pass number within the session
+    pub number: u32,
+    pub observations: String,
+    /// Messages the pass replaced
+    pub message_count: usize,
+    pub message_bytes: usize,
+    /// Section of the originals in the archive file
+    section: Option<usize>,
+}
+
+/// Summaries of the session's observation passes, oldest first.
+#[derive(Debug)]
+pub struct SummaryArchive {
+    keep_originals: bool,
+    dir: PathBuf,
+    file: Option<SpillFile>,
+    entries: Vec<CompactionSummary>,
+}
+
+impl SummaryArchive {
+    pub fn new(keep_originals: bool) -> Self {
+        Self::with_dir(keep_originals, spill_dir())
+    }
+
+    fn with_dir(keep_originals: bool, dir: PathBuf) -> Self {
+        Self {
+            keep_originals,
+            dir,
+            file: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Archive a pass taken from the observational memory.
+    pub fn record(&mut self, pass: ObservationPass) {
+        let section = if self.keep_originals {
+            self.store(&pass.messages)
+        } else {
+            None
+        };
+        self.entries.push(CompactionSummary {
+            number: pass.number,
+            observations: pass.observations,
+            message_count: pass.messages.len(),
+            message_bytes: pass.messages.iter().map(|m| m.byte_count()).sum(),
+            section,
+        });
+    }
+
+    fn store(&mut self, messages: &[Message]) -> Option<usize> {
+        let lines: String = messages
+            .iter()
+            .filter_map(|m| serde_json::to_string(m).ok())
+            .map(|line| line + "\n")
+            .collect();
+        let file = ensure_spill(&mut self.file, &self.dir)?;
+        let section = file.section_count();
+        match file.push(&lines) {
+            Ok(()) => Some(section),
+            Err(e) => {
+                tracing::warn!(error = %e, "Cannot archive compacted messages");
+                None
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[CompactionSummary] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The messages summary `index` replaced, or `None` if they weren't kept.
+    pub fn originals(&self, index: usize) -> io::Result<Option<Vec<Message>>> {
+        let section = self.entries.get(index).and_then(|e| e.section);
+        let (Some(section), Some(file)) = (section, self.file.as_ref()) else {
+            return Ok(None);
+        };
+        let Some(text) = file.read_section(section)? else {
+            return Ok(None);
+        };
+        text.lines()
+            .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+            .collect::<io::Result<Vec<Message>>>()
+            .map(Some)
+    }
+
+    pub fn clear(&mut self) {
+        self.file = None;
+        self.entries.clear();
+    }
+}
+
+/// Label for an original message in the pane: role plus tool calls.
+pub fn message_label(message: &Message) -> String {
+    let role = match message.role {
+        Role::System => "system",
+        Role::User => "you",
+        Role::Assistant => "assistant",
+        Role::Tool => "tool result",
+    };
+    if message.tool_calls.is_empty() {
+        role.to_string()
+    } else {
+        let calls: Vec<&str> = message.tool_calls.iter().map(|c| c.name.as_str()).collect();
+        format!("{} → {}", role, calls.join(", "))
+    }
+}
+
+/// Context block that puts an original message back in front of the model
+/// with the next user message.
+pub fn recall_block(summary: &CompactionSummary, message: &Message) -> String {
+    let mut text = message.content.to_string_lossy();
+    for call in &message.tool_calls {
+        text.push_str(&format!("\n[tool call] {} {}", call.name, call.arguments));
+    }
+    format!(
+        "<recalled pass=\"{}\" role=\"{}\">\nRestored from history that was summarized earlier:\n{}\n</recalled>",
+        summary.number,
+        message_label(message),
+        text.trim()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(number: u32, messages: Vec<Message>) -> ObservationPass {
+        ObservationPass {
+            number,
+            observations: format!("- pass {}", number),
+            messages,
+        }
+    }
+
+    #[test]
+    fn test_archive_keeps_originals() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut archive = SummaryArchive::with_dir(true, dir.path().to_path_buf());
+        archive.record(pass(
+            1,
+            vec![Message::user("use port 8443"), Message::assistant("noted")],
+        ));
+        archive.record(pass(2, vec![Message::user("second")]));
+
+        assert_eq!(archive.entries().len(), 2);
+        assert_eq!(archive.entries()[0].message_count, 2);
+        assert!(archive.entries()[1].section.is_some());
+
+        let first = archive.originals(0).unwrap().unwrap();
+        assert_eq!(first[0].content.to_string_lossy(), "use port 8443");
+        assert_eq!(first[1].role, Role::Assistant);
+        let second = archive.originals(1).unwrap().unwrap();
+        assert_eq!(second.len(), 1);
+        assert!(archive.originals(5).unwrap().is_none());
+
+        let block = recall_block(&archive.entries()[0], &first[0]);
+        assert!(block.starts_with("<recalled pass=\"1\" role=\"you\">"));
+        assert!(block.contains("use port 8443"));
+
+        archive.clear();
+        assert!(archive.is_empty());
+    }
+
+    #[test]
+    fn test_archive_without_originals() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut archive = SummaryArchive::with_dir(false, dir.path().to_path_buf());
+        archive.record(pass(1, vec![Message::user("gone")]));
+
+        assert_eq!(archive.entries()[0].observations, "- pass 1");
+        assert!(archive.entries()[0].section.is_none());
+        assert!(archive.originals(0).unwrap().is_none());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,323 @@
+//! Session templates for recurring workflows (`qq new --template <name>`).
+//!
+//! A template bundles what would otherwise be set up by hand at the start of
+//! every session: the profile and primary agent, extra system context, tasks
+//! to put on the board, and files whose contents the model should see first.
+//!
+//! Templates live in `~/.config/qq/templates/` as `<name>.toml` or
+//! `<name>.md`. A Markdown template is its context as prose, optionally
+//! preceded by TOML front matter between `+++` lines:
+//!
+//! ```text
+//! +++
+//! description = "Morning bug triage"
+//! profile = "fast"
+//! attach = ["docs/triage.md"]
+//! tasks = ["Review issues opened since yesterday"]
+//! +++
+//! Triage new bugs: reproduce, label severity, and link duplicates.
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Attached files larger than this are truncated in the context.
+const MAX_ATTACH_BYTES: usize = 100 * 1024;
+
+/// A loaded session template.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionTemplate {
+    /// One-line summary shown by `qq new`
+    pub description: Option<String>,
+    /// Profile to use unless `--profile` is given
+    pub profile: Option<String>,
+    /// Primary agent unless `--agent` is given
+    pub agent: Option<String>,
+    /// Extra system context for the session
+    pub context: Option<String>,
+    /// Tasks put on the board before the first message
+    pub tasks: Vec<TemplateTask>,
+    /// Files (relative to the working directory) included in the context
+    pub attach: Vec<String>,
+}
+
+/// A task seeded by a template: a bare title or a table.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum TemplateTask {
+    Title(String),
+    Detailed {
+        title: String,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        assignee: Option<String>,
+    },
+}
+
+impl TemplateTask {
+    /// Add this task to `store` as `todo`.
+    pub fn seed(&self, store: &qq_tools::TaskStore) -> String {
+        match self {
+            TemplateTask::Title(title) => store.add_task(title.as_str(), None, None),
+            TemplateTask::Detailed {
+                title,
+                description,
+                assignee,
+            } => store.add_task(title.as_str(), description.clone(), assignee.clone()),
+        }
+    }
+}
+
+impl SessionTemplate {
+    /// Parse a template from its file contents; `markdown` selects the
+    /// front-matter format.
+    pub fn parse(text: &str, markdown: bool) -> Result<Self> {
+        if !markdown {
+            return toml::from_str(text).context("Invalid template TOML");
+        }
+        let (mut template, body) = match split_front_matter(text) {
+            Some((front, body)) => (
+                toml::from_str::<Self>(front).context("Invalid template front matter")?,
+                body,
+            ),
+            None => (Self::default(), text),
+        };
+        let body = body.trim();
+        if !body.is_empty() {
+            template.context = Some(match template.context.take() {
+                Some(context) => format!("{}\n\n{}", context.trim_end(), body),
+                None => body.to_string(),
+            });
+        }
+        Ok(template)
+    }
+
+    /// Load a template by name from the templates directory, or from a path.
+    pub fn load(name: &str) -> Result<Self> {
+        let path = resolve(name, crate::paths::templates_dir().as_deref())?;
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read template {}", path.display()))?;
+        let markdown = path.extension().is_some_and(|ext| ext == "md");
+        Self::parse(&text, markdown).with_context(|| format!("In template {}", path.display()))
+    }
+
+    /// System context for the session: the template's context followed by
+    /// each attached file. Unreadable files are reported and skipped.
+    pub fn build_context(&self, root: &Path) -> Option<String> {
+        let mut sections: Vec<String> = Vec::new();
+        if let Some(ref context) = self.context {
+            sections.push(context.trim().to_string());
+        }
+        for file in &self.attach {
+            let path = crate::config::expand_path(file);
+            let path = if path.is_absolute() {
+                path
+            } else {
+                root.join(path)
+            };
+            match std::fs::read(&path) {
+                Ok(bytes) => sections.push(attachment_section(file, &bytes)),
+                Err(e) => eprintln!("Template attachment {} skipped: {}", path.display(), e),
+            }
+        }
+        (!sections.is_empty()).then(|| sections.join("\n\n"))
+    }
+}
+
+/// Find the template file for `name`: an existing path, or
+/// `<dir>/<name>.toml` / `<dir>/<name>.md`.
+fn resolve(name: &str, dir: Option<&Path>) -> Result<PathBuf> {
+    let direct = PathBuf::from(name);
+    if direct.is_file() {
+        return Ok(direct);
+    }
+    if let Some(dir) = dir {
+        for ext in ["toml", "md"] {
+            let path = dir.join(format!("{}.{}", name, ext));
+            if path.is_file() {
+                return Ok(path);
+            }
+        }
+    }
+    let available = dir.map(list).unwrap_or_default();
+    if available.is_empty() {
+        anyhow::bail!("Template '{}' not found", name);
+    }
+    let names: Vec<&str> = available.iter().map(|(n, _)| n.as_str()).collect();
+    anyhow::bail!(
+        "Template '{}' not found. Available: {}",
+        name,
+        names.join(", ")
+    )
+}
+
+/// Templates in `dir` as (name, path), sorted by name.
+fn list(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<(String, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .is_some_and(|ext| ext == "toml" || ext == "md")
+        })
+        .filter_map(|p| Some((p.file_stem()?.to_string_lossy().into_owned(), p)))
+        .collect();
+    templates.sort();
+    templates.dedup_by(|a, b| a.0 == b.0);
+    templates
+}
+
+/// Print available templates (`qq new` without `--template`).
+pub fn print_templates() -> Result<()> {
+    let dir = crate::paths::templates_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+    let templates = list(&dir);
+    if templates.is_empty() {
+        println!("No templates in {}", dir.display());
+        println!("Add <name>.toml or <name>.md there, then run: qq new --template <name>");
+        return Ok(());
+    }
+    println!("Templates in {}:\n", dir.display());
+    for (name, path) in templates {
+        let description = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| {
+                SessionTemplate::parse(&text, path.extension().is_some_and(|e| e == "md")).ok()
+            })
+            .and_then(|t| t.description)
+            .unwrap_or_default();
+        println!("  {:<20} {}", name, description);
+    }
+    Ok(())
+}
+
+/// Split `+++`-delimited TOML front matter from a Markdown body.
+fn split_front_matter(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix("+++")?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+    let end = rest.find("\n+++")?;
+    let body = &rest[end + 4..];
+    let body = body.split_once('\n').map(|(_, b)| b).unwrap_or("");
+    Some((&rest[..end], body))
+}
+
+/// Context section for one attached file.
+fn attachment_section(name: &str, bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let (text, truncated) = if text.len() > MAX_ATTACH_BYTES {
+        (qq_core::text::truncate_bytes(&text, MAX_ATTACH_BYTES), true)
+    } else {
+        (&text[..], false)
+    };
+    let mut section = format!("## Attached file: {}\n\n```\n{}", name, text.trim_end());
+    section.push_str("\n```");
+    if truncated {
+        section.push_str(&format!(
+            "\n\n[truncated at {} KB of {} KB]",
+            MAX_ATTACH_BYTES / 1024,
+            bytes.len() / 1024
+        ));
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml_template() {
+        let template = SessionTemplate::parse(
+            r#"
+description = "Morning bug triage"
+profile = "fast"
+agent = "researcher"
+context = "Focus on crashes."
+attach = ["NOTES.md"]
+tasks = [
+    "Review new issues",
+    { title = "Check CI", assignee = "reviewer" },
+]
+"#,
+            false,
+        )
+        .unwrap();
+        assert_eq!(template.profile.as_deref(), Some("fast"));
+        assert_eq!(template.agent.as_deref(), Some("researcher"));
+        assert_eq!(
+            template.tasks,
+            vec![
+                TemplateTask::Title("Review new issues".into()),
+                TemplateTask::Detailed {
+                    title: "Check CI".into(),
+                    description: None,
+                    assignee: Some("reviewer".into()),
+                },
+            ]
+        );
+
+        let store = qq_tools::TaskStore::new();
+        let id = template.tasks[1].seed(&store);
+        assert_eq!(
+            store.get_task(&id).unwrap().assignee.as_deref(),
+            Some("reviewer")
+        );
+
+        assert!(SessionTemplate::parse("profle = \"fast\"", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_markdown_template() {
+        let text = "+++\nprofile = \"fast\"\ncontext = \"Be brief.\"\n+++\n\nTriage new bugs.\n";
+        let template = SessionTemplate::parse(text, true).unwrap();
+        assert_eq!(template.profile.as_deref(), Some("fast"));
+        assert_eq!(
+            template.context.as_deref(),
+            Some("Be brief.\n\nTriage new bugs.")
+        );
+
+        let plain = SessionTemplate::parse("Just context.\n", true).unwrap();
+        assert_eq!(plain.context.as_deref(), Some("Just context."));
+        assert_eq!(plain.profile, None);
+    }
+
+    #[test]
+    fn test_context_includes_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("NOTES.md"), "known flaky: test_net\n").unwrap();
+        let template = SessionTemplate {
+            context: Some("Triage.".into()),
+            attach: vec!["NOTES.md".into(), "missing.md".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            template.build_context(dir.path()).unwrap(),
+            "Triage.\n\n## Attached file: NOTES.md\n\n```\nknown flaky: test_net\n```"
+        );
+        assert_eq!(SessionTemplate::default().build_context(dir.path()), None);
+    }
+
+    #[test]
+    fn test_resolve_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("bug-triage.md"), "x").unwrap();
+        std::fs::write(dir.path().join("release.toml"), "").unwrap();
+
+        assert_eq!(
+            resolve("bug-triage", Some(dir.path())).unwrap(),
+            dir.path().join("bug-triage.md")
+        );
+        let err = resolve("nope", Some(dir.path())).unwrap_err().to_string();
+        assert!(err.contains("Available: bug-triage, release"), "{}", err);
+    }
+}
@@ -0,0 +1,543 @@
+//! Live sub-agent activity tree.
+//!
+//! Mirrors the delegation call stack (PM → coder → bash) as a tree built from
+//! the primary stream and the agent event bus. Unlike the flat
+//! `ExecutionContext` stack shown in the status bar, the tree keeps finished
+//! calls and parallel siblings, so the side pane can show per-node status,
+//! elapsed time, token counts, each agent's latest thinking, and the report
+//! a finished agent returned.
+
+use std::time::{Duration, Instant};
+
+use crate::agents::AgentReport;
+use crate::event_bus::AgentEvent;
+
+use super::events::StreamEvent;
+
+/// Characters of thinking kept per agent for the excerpt view.
+pub const THINKING_EXCERPT_CHARS: usize = 600;
+
+/// Upper bound on nodes per turn; further tool calls are not tracked.
+const MAX_NODES: usize = 500;
+
+/// What a node represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityKind {
+    Agent,
+    Tool,
+}
+
+/// Lifecycle state of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+/// One agent or tool call in the tree.
+#[derive(Debug, Clone)]
+pub struct ActivityNode {
+    pub name: String,
+    pub kind: ActivityKind,
+    pub status: ActivityStatus,
+    pub parent: Option<usize>,
+    pub depth: usize,
+    pub started: Instant,
+    pub finished: Option<Instant>,
+    /// Latest agent iteration (agents only)
+    pub iteration: u32,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Tail of the agent's thinking, at most `THINKING_EXCERPT_CHARS`
+    pub thinking: String,
+    /// Structured result, once a delegated agent reports through `finish`
+    pub report: Option<AgentReport>,
+    /// `[changes]` summary, once a call that modified files completes
+    pub changes: Option<String>,
+    /// Tool call id, for primary-stream calls
+    call_id: Option<String>,
+}
+
+impl ActivityNode {
+    fn new(name: &str, kind: ActivityKind, parent: Option<usize>, depth: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            status: ActivityStatus::Running,
+            parent,
+            depth,
+            started: Instant::now(),
+            finished: None,
+            iteration: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            thinking: String::new(),
+            report: None,
+            changes: None,
+            call_id: None,
+        }
+    }
+
+    /// Time spent so far (or in total, once finished).
+    pub fn elapsed(&self) -> Duration {
+        self.finished
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started)
+    }
+
+    pub fn total_tokens(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn finish(&mut self, status: ActivityStatus) {
+        if self.status == ActivityStatus::Running {
+            self.status = status;
+            self.finished = Some(Instant::now());
+        }
+    }
+
+    fn push_thinking(&mut self, delta: &str) {
+        self.thinking.push_str(delta);
+        let len = self.thinking.chars().count();
+        if len > THINKING_EXCERPT_CHARS {
+            let cut = self
+                .thinking
+                .char_indices()
+                .nth(len - THINKING_EXCERPT_CHARS)
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            self.thinking.drain(..cut);
+        }
+    }
+}
+
+/// Tree of agent and tool calls for the current turn.
+///
+/// Node 0 is always the primary agent; nodes are only appended, so indices
+/// stay valid for the whole turn.
+#[derive(Debug)]
+pub struct ActivityTree {
+    nodes: Vec<ActivityNode>,
+    /// Node picked with Alt+Up/Down; `None` follows the newest running agent.
+    selected: Option<usize>,
+}
+
+impl ActivityTree {
+    pub fn new(primary_agent: &str) -> Self {
+        let mut root = ActivityNode::new(primary_agent, ActivityKind::Agent, None, 0);
+        root.finish(ActivityStatus::Done);
+        Self {
+            nodes: vec![root],
+            selected: None,
+        }
+    }
+
+    /// Start a fresh tree for a new turn.
+    pub fn reset(&mut self) {
+        let name = self.nodes[0].name.clone();
+        self.nodes = vec![ActivityNode::new(&name, ActivityKind::Agent, None, 0)];
+        self.selected = None;
+    }
+
+    pub fn node(&self, index: usize) -> Option<&ActivityNode> {
+        self.nodes.get(index)
+    }
+
+    /// Whether anything is still running (elapsed times need a redraw).
+    pub fn has_running(&self) -> bool {
+        self.nodes
+            .iter()
+            .any(|n| n.status == ActivityStatus::Running)
+    }
+
+    /// Node indices in display order (depth-first, children by start time).
+    pub fn rows(&self) -> Vec<usize> {
+        let mut rows = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            rows.push(index);
+            let children =
+                (index + 1..self.nodes.len()).filter(|&i| self.nodes[i].parent == Some(index));
+            let mut children: Vec<usize> = children.collect();
+            children.reverse();
+            stack.extend(children);
+        }
+        rows
+    }
+
+    /// Index of the highlighted node.
+    pub fn selected(&self) -> usize {
+        self.selected.unwrap_or_else(|| {
+            (0..self.nodes.len())
+                .rev()
+                .find(|&i| {
+                    let n = &self.nodes[i];
+                    n.kind == ActivityKind::Agent && n.status == ActivityStatus::Running
+                })
+                .unwrap_or(0)
+        })
+    }
+
+    pub fn select_prev(&mut self) {
+        self.move_selection(-1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.move_selection(1);
+    }
+
+    fn move_selection(&mut self, step: isize) {
+        let rows = self.rows();
+        let current = self.selected();
+        let pos = rows.iter().position(|&i| i == current).unwrap_or(0);
+        let next = (pos as isize + step).clamp(0, rows.len() as isize - 1) as usize;
+        self.selected = Some(rows[next]);
+    }
+
+    /// Update from the primary agent's stream.
+    pub fn on_stream_event(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::ThinkingDelta(delta) => self.nodes[0].push_thinking(delta),
+            StreamEvent::IterationStart { iteration } => self.nodes[0].iteration = *iteration,
+            StreamEvent::ToolExecuting { id, name, .. } => {
+                if let Some(index) = self.add_call(0, name) {
+                    self.nodes[index].call_id = Some(id.clone());
+                }
+            }
+            StreamEvent::ToolComplete {
+                id,
+                is_error,
+                report,
+                changes,
+                ..
+            } => {
+                if let Some(node) = self
+                    .nodes
+                    .iter_mut()
+                    .find(|n| n.call_id.as_deref() == Some(id.as_str()))
+                {
+                    node.finish(status_for(*is_error));
+                    node.report = report.clone();
+                    node.changes = changes.clone();
+                }
+            }
+            StreamEvent::Done { usage, .. } => {
+                if let Some(usage) = usage {
+                    self.nodes[0].prompt_tokens = usage.prompt_tokens;
+                    self.nodes[0].completion_tokens = usage.completion_tokens;
+                }
+                self.finish_all(ActivityStatus::Done);
+            }
+            StreamEvent::Error { .. } => self.finish_all(ActivityStatus::Failed),
+            _ => {}
+        }
+    }
+
+    /// Update from a sub-agent's progress event.
+    pub fn on_agent_event(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::IterationStart {
+                agent_name,
+                iteration,
+                agent_chain,
+            } => {
+                if let Some(index) = self.agent_for_chain(agent_name, agent_chain) {
+                    self.nodes[index].iteration = *iteration;
+                }
+            }
+            AgentEvent::ThinkingDelta {
+                agent_name,
+                content,
+            } => {
+                let index = self.running_agent(agent_name);
+                self.nodes[index].push_thinking(content);
+            }
+            AgentEvent::ToolStart {
+                agent_name,
+                tool_name,
+                ..
+            } => {
+                let parent = self.running_agent(agent_name);
+                self.add_call(parent, tool_name);
+            }
+            AgentEvent::ToolComplete {
+                agent_name,
+                tool_name,
+                is_error,
+                report,
+                changes,
+            } => {
+                let parent = self.running_agent(agent_name);
+                let (kind, name) = call_target(tool_name);
+                // Oldest matching call first: completions arrive in start order
+                if let Some(node) = self.nodes.iter_mut().find(|n| {
+                    n.parent == Some(parent)
+                        && n.kind == kind
+                        && n.name == name
+                        && n.status == ActivityStatus::Running
+                }) {
+                    node.finish(status_for(*is_error));
+                    node.report = report.clone();
+                    node.changes = changes.clone();
+                }
+            }
+            AgentEvent::UsageUpdate { agent_name, usage } => {
+                let index = self.running_agent(agent_name);
+                let node = &mut self.nodes[index];
+                node.prompt_tokens += usage.prompt_tokens;
+                node.completion_tokens += usage.completion_tokens;
+            }
+            _ => {}
+        }
+    }
+
+    /// Mark the primary agent and anything left running as finished.
+    pub fn finish_all(&mut self, status: ActivityStatus) {
+        for node in &mut self.nodes {
+            node.finish(status);
+        }
+    }
+
+    /// Add a tool call (or an `Agent[name]` delegation) under `parent`.
+    fn add_call(&mut self, parent: usize, tool_name: &str) -> Option<usize> {
+        if self.nodes.len() >= MAX_NODES {
+            return None;
+        }
+        let (kind, name) = call_target(tool_name);
+        let depth = self.nodes[parent].depth + 1;
+        self.nodes
+            .push(ActivityNode::new(name, kind, Some(parent), depth));
+        Some(self.nodes.len() - 1)
+    }
+
+    /// Newest running agent node with this name, or the root.
+    fn running_agent(&self, name: &str) -> usize {
+        (1..self.nodes.len())
+            .rev()
+            .find(|&i| {
+                let n = &self.nodes[i];
+                n.kind == ActivityKind::Agent
+                    && n.status == ActivityStatus::Running
+                    && n.name == name
+            })
+            .unwrap_or(0)
+    }
+
+    /// Resolve an agent from its scope chain (`["pm", "coder:fix", ...]`),
+    /// creating any links that were never announced by a `ToolStart`.
+    fn agent_for_chain(&mut self, agent_name: &str, chain: &[String]) -> Option<usize> {
+        if chain.len() < 2 {
+            return Some(self.running_agent(agent_name));
+        }
+        let mut current = 0;
+        for link in &chain[1..] {
+            let name = link.split_once(':').map_or(link.as_str(), |(name, _)| name);
+            let existing = (current + 1..self.nodes.len()).rev().find(|&i| {
+                let n = &self.nodes[i];
+                n.parent == Some(current)
+                    && n.kind == ActivityKind::Agent
+                    && n.status == ActivityStatus::Running
+                    && n.name == name
+            });
+            current = match existing {
+                Some(index) => index,
+                None => self.add_call(current, &format!("Agent[{}]", name))?,
+            };
+        }
+        Some(current)
+    }
+}
+
+/// Split a tool name into node kind and display name (`Agent[coder]` → coder).
+fn call_target(tool_name: &str) -> (ActivityKind, &str) {
+    match tool_name
+        .strip_prefix("Agent[")
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        Some(agent) => (ActivityKind::Agent, agent),
+        None => (ActivityKind::Tool, tool_name),
+    }
+}
+
+fn status_for(is_error: bool) -> ActivityStatus {
+    if is_error {
+        ActivityStatus::Failed
+    } else {
+        ActivityStatus::Done
+    }
+}
+
+/// Compact elapsed time: "850ms", "12s", "3m05s".
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs == 0 {
+        format!("{}ms", elapsed.as_millis())
+    } else if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::Usage;
+
+    fn agent_event_tool(agent: &str, tool: &str) -> AgentEvent {
+        AgentEvent::ToolStart {
+            agent_name: agent.to_string(),
+            tool_name: tool.to_string(),
+            arguments: String::new(),
+        }
+    }
+
+    fn names(tree: &ActivityTree) -> Vec<(usize, String)> {
+        tree.rows()
+            .into_iter()
+            .map(|i| {
+                let n = tree.node(i).unwrap();
+                (n.depth, n.name.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_builds_delegation_tree() {
+        let mut tree = ActivityTree::new("pm");
+        tree.reset();
+        tree.on_stream_event(&StreamEvent::ToolExecuting {
+            id: "call-1".to_string(),
+            name: "Agent[coder]".to_string(),
+            arguments: "{}".to_string(),
+        });
+        tree.on_agent_event(&AgentEvent::IterationStart {
+            agent_name: "coder".to_string(),
+            iteration: 2,
+            agent_chain: vec!["pm".to_string(), "coder".to_string()],
+        });
+        tree.on_agent_event(&agent_event_tool("coder", "run"));
+        tree.on_agent_event(&agent_event_tool("coder", "Agent[explore]"));
+        tree.on_agent_event(&AgentEvent::ThinkingDelta {
+            agent_name: "explore".to_string(),
+            content: "looking for the parser".to_string(),
+        });
+        tree.on_agent_event(&AgentEvent::UsageUpdate {
+            agent_name: "coder".to_string(),
+            usage: Usage::new(100, 20),
+        });
+        tree.on_agent_event(&AgentEvent::ToolComplete {
+            agent_name: "coder".to_string(),
+            tool_name: "run".to_string(),
+            is_error: true,
+            report: None,
+            changes: None,
+        });
+
+        assert_eq!(
+            names(&tree),
+            vec![
+                (0, "pm".to_string()),
+                (1, "coder".to_string()),
+                (2, "run".to_string()),
+                (2, "explore".to_string()),
+            ]
+        );
+        let coder = tree.node(1).unwrap();
+        assert_eq!(coder.iteration, 2);
+        assert_eq!(coder.total_tokens(), 120);
+        assert_eq!(tree.node(2).unwrap().status, ActivityStatus::Failed);
+
+        // Auto-selection follows the newest running agent
+        let explore = tree.selected();
+        assert_eq!(
+            tree.node(explore).unwrap().thinking,
+            "looking for the parser"
+        );
+
+        tree.on_agent_event(&AgentEvent::ToolComplete {
+            agent_name: "coder".to_string(),
+            tool_name: "Agent[explore]".to_string(),
+            is_error: false,
+            report: AgentReport::parse(r#"{"summary": "Parser is in parse.rs", "confidence": "medium"}"#),
+            changes: None,
+        });
+        let explore = tree.node(3).unwrap();
+        assert_eq!(explore.status, ActivityStatus::Done);
+        assert_eq!(explore.report.as_ref().unwrap().summary, "Parser is in parse.rs");
+
+        let report = AgentReport::parse(r#"{"summary": "Fixed it", "confidence": "high"}"#);
+        tree.on_stream_event(&StreamEvent::ToolComplete {
+            id: "call-1".to_string(),
+            name: "Agent[coder]".to_string(),
+            result_len: 10,
+            is_error: false,
+            report: report.clone(),
+            changes: Some("1 file, 1 hunk, +3 -1".to_string()),
+        });
+        assert_eq!(tree.node(1).unwrap().status, ActivityStatus::Done);
+        assert_eq!(tree.node(1).unwrap().report, report);
+        assert_eq!(tree.node(1).unwrap().changes.as_deref(), Some("1 file, 1 hunk, +3 -1"));
+
+        tree.on_stream_event(&StreamEvent::Done {
+            usage: None,
+            content: String::new(),
+            finish_reason: None,
+        });
+        assert!(!tree.has_running());
+    }
+
+    #[test]
+    fn test_chain_creates_missing_links_and_selection_moves() {
+        let mut tree = ActivityTree::new("pm");
+        tree.reset();
+        tree.on_agent_event(&AgentEvent::IterationStart {
+            agent_name: "doc-researcher".to_string(),
+            iteration: 1,
+            agent_chain: vec![
+                "pm".to_string(),
+                "researcher:docs".to_string(),
+                "doc-researcher".to_string(),
+            ],
+        });
+        assert_eq!(
+            names(&tree),
+            vec![
+                (0, "pm".to_string()),
+                (1, "researcher".to_string()),
+                (2, "doc-researcher".to_string()),
+            ]
+        );
+
+        assert_eq!(tree.selected(), 2);
+        tree.select_prev();
+        assert_eq!(tree.selected(), 1);
+        tree.select_prev();
+        tree.select_prev();
+        assert_eq!(tree.selected(), 0);
+        tree.select_next();
+        tree.select_next();
+        tree.select_next();
+        assert_eq!(tree.selected(), 2);
+
+        tree.reset();
+        assert_eq!(names(&tree), vec![(0, "pm".to_string())]);
+    }
+
+    #[test]
+    fn test_thinking_excerpt_and_elapsed_format() {
+        let mut tree = ActivityTree::new("pm");
+        tree.reset();
+        let long = "é".repeat(THINKING_EXCERPT_CHARS + 50);
+        tree.on_stream_event(&StreamEvent::ThinkingDelta(long));
+        assert_eq!(
+            tree.node(0).unwrap().thinking.chars().count(),
+            THINKING_EXCERPT_CHARS
+        );
+
+        assert_eq!(format_elapsed(Duration::from_millis(850)), "850ms");
+        assert_eq!(format_elapsed(Duration::from_secs(12)), "12s");
+        assert_eq!(format_elapsed(Duration::from_secs(185)), "3m05s");
+    }
+}
@@ -0,0 +1,326 @@
+//! Agent picker and task composer overlay (Ctrl+A).
+//!
+//! Two stages: type to filter the available agents and pick one, then write
+//! the task in a multi-line editor. Dispatching yields a [`Delegation`] whose
+//! message asks the primary agent to hand the task to `Agent[name]`.
+
+use tui_input::Input;
+
+use super::line_edit;
+use crate::agents::AgentInfo;
+use crate::context_files::fuzzy_matches;
+
+#[derive(Debug, Clone)]
+pub enum AgentPickerStage {
+    /// Filtering the agent list.
+    PickAgent,
+    /// Writing the task for `agent`.
+    Compose {
+        agent: String,
+        task: Input,
+        error: Option<String>,
+    },
+}
+
+/// A composed task, ready to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delegation {
+    pub agent: String,
+    pub task: String,
+}
+
+impl Delegation {
+    /// The user message that routes the task to the agent's tool.
+    pub fn message(&self) -> String {
+        format!(
+            "Delegate this task to Agent[{}], passing it on as written:\n\n{}",
+            self.agent, self.task
+        )
+    }
+}
+
+/// State of the Ctrl+A picker.
+#[derive(Debug, Clone)]
+pub struct AgentPicker {
+    agents: Vec<AgentInfo>,
+    names: Vec<String>,
+    pub query: String,
+    /// Indices into `agents`, best match first
+    matches: Vec<usize>,
+    pub cursor: usize,
+    pub stage: AgentPickerStage,
+    /// Input-line text the composer starts from
+    draft: String,
+}
+
+impl AgentPicker {
+    /// Pick from `agents`; the task starts as `draft` (the unsent input line).
+    pub fn new(agents: Vec<AgentInfo>, draft: &str) -> Self {
+        let names = agents.iter().map(|a| a.name.clone()).collect();
+        let mut picker = Self {
+            agents,
+            names,
+            query: String::new(),
+            matches: Vec::new(),
+            cursor: 0,
+            stage: AgentPickerStage::PickAgent,
+            draft: draft.to_string(),
+        };
+        picker.refilter();
+        picker
+    }
+
+    /// Agents matching the query, best first.
+    pub fn matches(&self) -> impl Iterator<Item = &AgentInfo> {
+        self.matches.iter().map(|&i| &self.agents[i])
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn agent_count(&self) -> usize {
+        self.agents.len()
+    }
+
+    /// The highlighted agent, or the one being written for.
+    pub fn selected(&self) -> Option<&AgentInfo> {
+        match &self.stage {
+            AgentPickerStage::PickAgent => self.matches().nth(self.cursor),
+            AgentPickerStage::Compose { agent, .. } => {
+                self.agents.iter().find(|a| &a.name == agent)
+            }
+        }
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        match &mut self.stage {
+            AgentPickerStage::PickAgent => {
+                self.query.push_str(text);
+                self.refilter();
+            }
+            AgentPickerStage::Compose { task, error, .. } => {
+                *task = line_edit::insert(task, text);
+                *error = None;
+            }
+        }
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn backspace(&mut self) {
+        match &mut self.stage {
+            AgentPickerStage::PickAgent => {
+                self.query.pop();
+                self.refilter();
+            }
+            AgentPickerStage::Compose { task, .. } => {
+                if let Some(edited) = line_edit::backspace(task) {
+                    *task = edited;
+                }
+            }
+        }
+    }
+
+    pub fn delete(&mut self) {
+        self.edit_task(line_edit::delete);
+    }
+
+    pub fn move_left(&mut self) {
+        self.edit_task(line_edit::left);
+    }
+
+    pub fn move_right(&mut self) {
+        self.edit_task(line_edit::right);
+    }
+
+    /// Up/Down: through the list, or between the task's lines.
+    pub fn move_cursor(&mut self, step: isize) {
+        match &self.stage {
+            AgentPickerStage::PickAgent => {
+                if !self.matches.is_empty() {
+                    let last = self.matches.len() as isize - 1;
+                    self.cursor = (self.cursor as isize + step).clamp(0, last) as usize;
+                }
+            }
+            AgentPickerStage::Compose { .. } => {
+                let motion = if step < 0 { line_edit::up } else { line_edit::down };
+                for _ in 0..step.unsigned_abs() {
+                    self.edit_task(motion);
+                }
+            }
+        }
+    }
+
+    /// Enter: pick the highlighted agent, or start a new line in the task.
+    pub fn confirm(&mut self) {
+        match &self.stage {
+            AgentPickerStage::PickAgent => {
+                let Some(&index) = self.matches.get(self.cursor) else {
+                    return;
+                };
+                self.stage = AgentPickerStage::Compose {
+                    agent: self.agents[index].name.clone(),
+                    task: Input::new(self.draft.clone()),
+                    error: None,
+                };
+            }
+            AgentPickerStage::Compose { .. } => self.push_char('\n'),
+        }
+    }
+
+    /// Ctrl+S: the composed task, once it has some text.
+    pub fn dispatch(&mut self) -> Option<Delegation> {
+        let AgentPickerStage::Compose { agent, task, error } = &mut self.stage else {
+            return None;
+        };
+        let text = task.value().trim();
+        if text.is_empty() {
+            *error = Some("Write a task first".to_string());
+            return None;
+        }
+        Some(Delegation {
+            agent: agent.clone(),
+            task: text.to_string(),
+        })
+    }
+
+    /// Show `message` under the task, e.g. when it can't be sent yet.
+    pub fn fail(&mut self, message: &str) {
+        if let AgentPickerStage::Compose { error, .. } = &mut self.stage {
+            *error = Some(message.to_string());
+        }
+    }
+
+    /// Esc: back from the composer to the list, keeping the task as the
+    /// draft. Returns true when the picker should close.
+    pub fn back(&mut self) -> bool {
+        match &self.stage {
+            AgentPickerStage::PickAgent => true,
+            AgentPickerStage::Compose { task, .. } => {
+                self.draft = task.value().to_string();
+                self.stage = AgentPickerStage::PickAgent;
+                false
+            }
+        }
+    }
+
+    fn edit_task(&mut self, edit: fn(&Input) -> Option<Input>) {
+        if let AgentPickerStage::Compose { task, .. } = &mut self.stage {
+            if let Some(edited) = edit(task) {
+                *task = edited;
+            }
+        }
+    }
+
+    fn refilter(&mut self) {
+        self.matches = fuzzy_matches(&self.query, &self.names, self.names.len());
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(name: &str, is_read_only: bool) -> AgentInfo {
+        AgentInfo {
+            name: name.to_string(),
+            description: format!("The {} agent", name),
+            is_internal: true,
+            tools: vec!["read_file".to_string()],
+            is_read_only,
+        }
+    }
+
+    fn picker(draft: &str) -> AgentPicker {
+        let agents = vec![
+            agent("coder", false),
+            agent("explore", true),
+            agent("researcher", true),
+        ];
+        AgentPicker::new(agents, draft)
+    }
+
+    fn task(p: &AgentPicker) -> &str {
+        match &p.stage {
+            AgentPickerStage::Compose { task, .. } => task.value(),
+            AgentPickerStage::PickAgent => panic!("not composing"),
+        }
+    }
+
+    #[test]
+    fn test_filter_and_pick() {
+        let mut p = picker("");
+        assert_eq!(p.match_count(), p.agent_count());
+        p.push_char('e');
+        p.push_char('x');
+        assert_eq!(p.match_count(), 1);
+        assert_eq!(p.selected().unwrap().name, "explore");
+        assert!(p.selected().unwrap().is_read_only);
+        p.backspace();
+        p.backspace();
+        p.move_cursor(10);
+        assert_eq!(p.cursor, 2);
+
+        p.confirm();
+        assert!(matches!(p.stage, AgentPickerStage::Compose { .. }));
+        assert_eq!(p.selected().unwrap().name, "researcher");
+
+        let mut p = picker("");
+        p.push_str("nothing-here");
+        p.confirm();
+        assert!(matches!(p.stage, AgentPickerStage::PickAgent));
+    }
+
+    #[test]
+    fn test_compose_multi_line_and_dispatch() {
+        let mut p = picker("Fix the race");
+        p.confirm();
+        assert!(p.dispatch().is_some());
+
+        p.confirm();
+        p.push_str("- in src/main.rs");
+        p.move_cursor(-1);
+        p.push_char('!');
+        assert_eq!(task(&p), "Fix the race!\n- in src/main.rs");
+
+        let delegation = p.dispatch().unwrap();
+        assert_eq!(delegation.agent, "coder");
+        assert_eq!(
+            delegation.message(),
+            "Delegate this task to Agent[coder], passing it on as written:\n\n\
+             Fix the race!\n- in src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_empty_task_and_back_keeps_draft() {
+        let mut p = picker("");
+        assert!(p.dispatch().is_none());
+        p.confirm();
+        p.push_str("  \n ");
+        assert!(p.dispatch().is_none());
+        assert!(matches!(
+            p.stage,
+            AgentPickerStage::Compose { error: Some(_), .. }
+        ));
+        for _ in 0..4 {
+            p.backspace();
+        }
+        p.push_str("Map the crate");
+
+        // Back to the list and into another agent keeps the text
+        assert!(!p.back());
+        assert!(matches!(p.stage, AgentPickerStage::PickAgent));
+        p.move_cursor(1);
+        p.confirm();
+        assert_eq!(task(&p), "Map the crate");
+        assert_eq!(p.dispatch().unwrap().agent, "explore");
+
+        p.back();
+        assert!(p.back());
+    }
+}
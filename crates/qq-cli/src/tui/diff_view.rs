@@ -0,0 +1,180 @@
+//! `/diff` overlay: page through the session's changes.
+//!
+//! Holds the diff from [`SessionBaseline`](crate::session_diff::SessionBaseline)
+//! split into classified lines for coloring. Up/Down and PageUp/PageDown
+//! scroll, `n` / `p` jump between files.
+
+/// What a diff line is, for coloring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    /// `diff --git a/… b/…`
+    File,
+    /// `index`, `---`/`+++`, mode and rename lines
+    Meta,
+    /// `@@ -a,b +c,d @@`
+    Hunk,
+    Added,
+    Removed,
+    Context,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+/// State of the `/diff` overlay.
+#[derive(Debug, Clone)]
+pub struct DiffView {
+    pub lines: Vec<DiffLine>,
+    /// First visible line
+    pub scroll: usize,
+    /// Index of each file's header line
+    files: Vec<usize>,
+    added: usize,
+    removed: usize,
+}
+
+impl DiffView {
+    pub fn new(diff: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut files = Vec::new();
+        let (mut added, mut removed) = (0, 0);
+        let mut in_hunk = false;
+        for text in diff.lines() {
+            let kind = if text.starts_with("diff --git ") {
+                in_hunk = false;
+                files.push(lines.len());
+                DiffLineKind::File
+            } else if text.starts_with("@@") {
+                in_hunk = true;
+                DiffLineKind::Hunk
+            } else if !in_hunk {
+                DiffLineKind::Meta
+            } else if text.starts_with('+') {
+                added += 1;
+                DiffLineKind::Added
+            } else if text.starts_with('-') {
+                removed += 1;
+                DiffLineKind::Removed
+            } else {
+                DiffLineKind::Context
+            };
+            lines.push(DiffLine {
+                kind,
+                text: text.replace('\t', "    "),
+            });
+        }
+        Self {
+            lines,
+            scroll: 0,
+            files,
+            added,
+            removed,
+        }
+    }
+
+    /// `3 files, +12 -4`
+    pub fn title(&self) -> String {
+        format!(
+            "{} file{}, +{} -{}",
+            self.files.len(),
+            if self.files.len() == 1 { "" } else { "s" },
+            self.added,
+            self.removed
+        )
+    }
+
+    pub fn scroll_by(&mut self, step: isize) {
+        let max = self.lines.len().saturating_sub(1) as isize;
+        self.scroll = (self.scroll as isize + step).clamp(0, max.max(0)) as usize;
+    }
+
+    pub fn scroll_to_end(&mut self) {
+        self.scroll = self.lines.len().saturating_sub(1);
+    }
+
+    /// `n`: the next file's header.
+    pub fn next_file(&mut self) {
+        if let Some(&line) = self.files.iter().find(|&&line| line > self.scroll) {
+            self.scroll = line;
+        }
+    }
+
+    /// `p`: the current file's header, or the previous one if already there.
+    pub fn prev_file(&mut self) {
+        if let Some(&line) = self.files.iter().rev().find(|&&line| line < self.scroll) {
+            self.scroll = line;
+        }
+    }
+
+    /// Path of the file shown at the top, for the title.
+    pub fn current_file(&self) -> Option<&str> {
+        let header = self.files.iter().rev().find(|&&line| line <= self.scroll)?;
+        let text = &self.lines[*header].text;
+        text.rsplit_once(" b/").map(|(_, path)| path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ fn a() {}
+--- old comment
++fn b() {}
+diff --git a/new.rs b/new.rs
+new file mode 100644
+--- /dev/null
++++ b/new.rs
+@@ -0,0 +1 @@
++\tfn c() {}
+";
+
+    #[test]
+    fn test_classifies_lines() {
+        let view = DiffView::new(DIFF);
+        let kinds: Vec<DiffLineKind> = view.lines.iter().map(|l| l.kind).collect();
+        use DiffLineKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                File, Meta, Meta, Meta, Hunk, Context, Removed, Added, File, Meta, Meta, Meta,
+                Hunk, Added
+            ]
+        );
+        assert_eq!(view.title(), "2 files, +2 -1");
+        assert_eq!(view.lines[13].text, "+    fn c() {}");
+    }
+
+    #[test]
+    fn test_file_navigation() {
+        let mut view = DiffView::new(DIFF);
+        assert_eq!(view.current_file(), Some("src/lib.rs"));
+        view.next_file();
+        assert_eq!(view.scroll, 8);
+        assert_eq!(view.current_file(), Some("new.rs"));
+        view.next_file();
+        assert_eq!(view.scroll, 8);
+        view.scroll_by(2);
+        view.prev_file();
+        assert_eq!(view.scroll, 8);
+        view.prev_file();
+        assert_eq!(view.scroll, 0);
+        view.scroll_by(100);
+        assert_eq!(view.scroll, 13);
+        view.scroll_by(-100);
+        assert_eq!(view.scroll, 0);
+
+        let empty = DiffView::new("");
+        assert_eq!(empty.title(), "0 files, +0 -0");
+        assert_eq!(empty.current_file(), None);
+    }
+}
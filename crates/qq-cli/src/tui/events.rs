@@ -2,10 +2,13 @@
 //!
 //! Defines stream events and keyboard input processing.
 
-use qq_core::{FinishReason, Usage};
+use qq_core::{FinishReason, ResponseTiming, Usage};
 
 use qq_core::Message;
 
+use crate::agents::AgentReport;
+use crate::event_socket::SessionEvent;
+
 /// Events sent from the LLM streaming task to the TUI.
 #[derive(Debug, Clone)]
 #[allow(dead_code)] // Fields used via Debug formatting
@@ -33,15 +36,81 @@ pub enum StreamEvent {
     /// Tool execution started
     ToolExecuting { id: String, name: String, arguments: String },
     /// Tool execution completed
-    ToolComplete { id: String, name: String, result_len: usize, is_error: bool },
+    ToolComplete {
+        id: String,
+        name: String,
+        result_len: usize,
+        is_error: bool,
+        /// Structured result of a delegation to a sub-agent
+        report: Option<AgentReport>,
+        /// One-line `[changes]` summary when the call modified files
+        changes: Option<String>,
+    },
     /// Iteration started (for multi-turn tool calls)
     IterationStart { iteration: u32 },
     /// Messages to add to session (for tool calls and results)
     SessionUpdate { messages: Vec<Message> },
     /// Byte counts for input/output
     ByteCount { input_bytes: usize, output_bytes: usize },
+    /// Timing of one model request; the main loop records it
+    RequestTimed {
+        model: Option<String>,
+        usage: Option<Usage>,
+        timing: ResponseTiming,
+    },
     /// A transient error occurred and the stream is being retried
     RetryNotice { attempt: u32, max_retries: u32, error: String },
+    /// This iteration was routed to the cheaper `[routing]` profile
+    ModelRouted { profile: String, model: Option<String>, reason: String },
+    /// The context window overflowed; the history was shrunk and the request retried
+    ContextRecovered { action: String },
+    /// Steering notes typed during the stream were injected as a user message
+    SteeringApplied { notes: Vec<String> },
+}
+
+impl StreamEvent {
+    /// The `--event-socket` view of this event, if clients care about it.
+    pub fn to_session_event(&self) -> Option<SessionEvent> {
+        match self {
+            StreamEvent::ContentDelta(content) => Some(SessionEvent::ContentDelta {
+                content: content.clone(),
+            }),
+            StreamEvent::ThinkingDelta(content) => Some(SessionEvent::ThinkingDelta {
+                content: content.clone(),
+            }),
+            StreamEvent::ToolExecuting { id, name, arguments } => Some(SessionEvent::ToolStart {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: arguments.clone(),
+            }),
+            StreamEvent::ToolComplete {
+                id, name, is_error, ..
+            } => Some(SessionEvent::ToolComplete {
+                id: id.clone(),
+                name: name.clone(),
+                is_error: *is_error,
+            }),
+            StreamEvent::Done { usage, .. } => Some(SessionEvent::ResponseComplete {
+                usage: usage.clone(),
+            }),
+            StreamEvent::Error { message } => Some(SessionEvent::Error {
+                message: message.clone(),
+            }),
+            StreamEvent::ModelRouted {
+                profile,
+                model,
+                reason,
+            } => Some(SessionEvent::ModelRouted {
+                profile: profile.clone(),
+                model: model.clone(),
+                reason: reason.clone(),
+            }),
+            StreamEvent::ContextRecovered { action } => Some(SessionEvent::ContextRecovered {
+                action: action.clone(),
+            }),
+            _ => None,
+        }
+    }
 }
 
 /// Input action from keyboard events
@@ -92,6 +161,8 @@ pub enum InputAction {
     DeleteWord,
     /// Hide/show thinking panel entirely
     HideThinking,
+    /// Filter the thinking panel to the next agent (or back to all)
+    CycleThinkingAgent,
     /// Move cursor forward one word
     WordForward,
     /// Move cursor backward one word
@@ -100,4 +171,24 @@ pub enum InputAction {
     ToggleMouse,
     /// Paste image from clipboard
     PasteImage,
+    /// Pin or unpin the latest message
+    TogglePin,
+    /// Insert the follow-up suggestion at this index into the input
+    InsertSuggestion(usize),
+    /// Show/hide the activity tree pane
+    ToggleActivity,
+    /// Select the previous node in the activity tree
+    ActivitySelectPrev,
+    /// Select the next node in the activity tree
+    ActivitySelectNext,
+    /// Show/hide the token/cost footer under responses
+    ToggleTurnStats,
+    /// Show/hide the log pane
+    ToggleLogs,
+    /// Show the next log level up in the log pane (or back to trace)
+    CycleLogLevel,
+    /// Filter the log pane to the next agent (or back to all)
+    CycleLogAgent,
+    /// Open the agent picker and task composer
+    OpenAgentPicker,
 }
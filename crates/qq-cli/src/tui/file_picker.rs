@@ -0,0 +1,192 @@
+//! Fuzzy file picker overlay for `/add`.
+//!
+//! Two stages: type to filter the project's files and pick one, then enter
+//! an optional line range. Confirming the range yields the [`AddedFile`].
+
+use crate::context_files::{fuzzy_matches, parse_range, AddedFile};
+
+/// Matches shown in the list; narrowing the query reaches the rest.
+const MAX_MATCHES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilePickerStage {
+    /// Filtering the file list.
+    PickFile,
+    /// Entering a line range for `path` (empty = whole file).
+    PickRange {
+        path: String,
+        input: String,
+        error: Option<String>,
+    },
+}
+
+/// State of the `/add` picker.
+#[derive(Debug, Clone)]
+pub struct FilePicker {
+    files: Vec<String>,
+    pub query: String,
+    /// Indices into `files`, best match first
+    matches: Vec<usize>,
+    pub cursor: usize,
+    pub stage: FilePickerStage,
+}
+
+impl FilePicker {
+    pub fn new(files: Vec<String>, query: &str) -> Self {
+        let mut picker = Self {
+            files,
+            query: query.to_string(),
+            matches: Vec::new(),
+            cursor: 0,
+            stage: FilePickerStage::PickFile,
+        };
+        picker.refilter();
+        picker
+    }
+
+    /// Paths of the current matches, best first.
+    pub fn matches(&self) -> impl Iterator<Item = &str> {
+        self.matches.iter().map(|&i| self.files[i].as_str())
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        match &mut self.stage {
+            FilePickerStage::PickFile => {
+                self.query.push(c);
+                self.refilter();
+            }
+            FilePickerStage::PickRange { input, error, .. } => {
+                input.push(c);
+                *error = None;
+            }
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        match &mut self.stage {
+            FilePickerStage::PickFile => {
+                self.query.pop();
+                self.refilter();
+            }
+            FilePickerStage::PickRange { input, error, .. } => {
+                input.pop();
+                *error = None;
+            }
+        }
+    }
+
+    pub fn move_cursor(&mut self, step: isize) {
+        if self.stage == FilePickerStage::PickFile && !self.matches.is_empty() {
+            let last = self.matches.len() as isize - 1;
+            self.cursor = (self.cursor as isize + step).clamp(0, last) as usize;
+        }
+    }
+
+    /// Enter: pick the highlighted file, or confirm the range. Returns the
+    /// file once the range is confirmed.
+    pub fn confirm(&mut self) -> Option<AddedFile> {
+        match &mut self.stage {
+            FilePickerStage::PickFile => {
+                let &index = self.matches.get(self.cursor)?;
+                self.stage = FilePickerStage::PickRange {
+                    path: self.files[index].clone(),
+                    input: String::new(),
+                    error: None,
+                };
+                None
+            }
+            FilePickerStage::PickRange { path, input, error } => match parse_range(input) {
+                Ok(range) => Some(AddedFile {
+                    path: path.clone(),
+                    range,
+                }),
+                Err(e) => {
+                    *error = Some(e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Esc: back from the range to the file list. Returns true when the
+    /// picker should close.
+    pub fn back(&mut self) -> bool {
+        match self.stage {
+            FilePickerStage::PickFile => true,
+            FilePickerStage::PickRange { .. } => {
+                self.stage = FilePickerStage::PickFile;
+                false
+            }
+        }
+    }
+
+    fn refilter(&mut self) {
+        self.matches = fuzzy_matches(&self.query, &self.files, MAX_MATCHES);
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker(query: &str) -> FilePicker {
+        let files = [
+            "README.md",
+            "src/chat.rs",
+            "src/tui/app.rs",
+            "src/tui/ui.rs",
+        ];
+        FilePicker::new(files.iter().map(|f| f.to_string()).collect(), query)
+    }
+
+    #[test]
+    fn test_filter_pick_and_range() {
+        let mut p = picker("tui");
+        assert_eq!(p.match_count(), 2);
+        p.push_char('a');
+        assert_eq!(p.matches().collect::<Vec<_>>(), vec!["src/tui/app.rs"]);
+        p.backspace();
+        p.move_cursor(5);
+        assert_eq!(p.cursor, 1);
+        let second = p.matches().nth(1).unwrap().to_string();
+
+        assert_eq!(p.confirm(), None);
+        assert!(matches!(p.stage, FilePickerStage::PickRange { .. }));
+        for c in "20-5".chars() {
+            p.push_char(c);
+        }
+        assert_eq!(p.confirm(), None);
+        assert!(matches!(
+            p.stage,
+            FilePickerStage::PickRange { error: Some(_), .. }
+        ));
+        p.backspace();
+        p.push_char('9');
+        p.push_char('9');
+        let picked = p.confirm().unwrap();
+        assert_eq!(picked.label(), format!("{}:20-99", second));
+    }
+
+    #[test]
+    fn test_back_and_empty_matches() {
+        let mut p = picker("");
+        assert_eq!(p.match_count(), p.file_count());
+        p.confirm();
+        assert!(!p.back());
+        assert_eq!(p.stage, FilePickerStage::PickFile);
+        assert!(p.back());
+
+        let mut p = picker("nothing-here");
+        assert_eq!(p.confirm(), None);
+        assert_eq!(p.stage, FilePickerStage::PickFile);
+    }
+}
@@ -0,0 +1,131 @@
+//! Rule-based follow-up suggestions.
+//!
+//! After a response completes, the tool activity and the tail of the answer
+//! are matched against a few common next steps. Suggestions are shown as
+//! numbered quick-picks above the input and inserted with Alt+1..3.
+
+use super::widgets::{ToolNotification, ToolNotificationStatus};
+
+/// Maximum number of suggestions shown at once.
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Tools whose use means files were changed.
+const WRITE_TOOLS: &[&str] = &[
+    "write_file",
+    "replace_in_file",
+    "insert_in_file",
+    "delete_lines",
+    "replace_lines",
+    "move_file",
+    "copy_file",
+    "rm_file",
+];
+
+/// Build follow-up suggestions for a completed response.
+pub fn suggest(response: &str, tools: &[ToolNotification]) -> Vec<String> {
+    let used = |names: &[&str]| {
+        tools
+            .iter()
+            .any(|t| names.iter().any(|n| t.tool_name == *n))
+    };
+    let used_agent = |agent: &str| {
+        let name = format!("Agent[{}]", agent);
+        tools.iter().any(|t| t.tool_name == name)
+    };
+
+    let mut out: Vec<String> = Vec::new();
+
+    if tools
+        .iter()
+        .any(|t| t.status == ToolNotificationStatus::Error)
+    {
+        out.push("Explain what failed and try a different approach".to_string());
+    }
+
+    if used(WRITE_TOOLS) || used_agent("coder") {
+        out.push("Run the tests and fix any failures".to_string());
+        out.push("Review the changes for bugs and edge cases".to_string());
+    }
+
+    if used(&["web_search", "fetch_webpage"]) || used_agent("researcher") {
+        out.push("Summarize the key findings with sources".to_string());
+    }
+
+    if used_agent("planner") {
+        out.push("Start on the first step of the plan".to_string());
+    }
+
+    let asks_question = response.trim_end().ends_with('?');
+    match (asks_question, has_numbered_list(response)) {
+        (true, true) => out.push("Go with option 1".to_string()),
+        (true, false) => out.push("Yes, go ahead".to_string()),
+        (false, true) => out.push("Go deeper on point 1".to_string()),
+        (false, false) => {}
+    }
+
+    if out.is_empty() && response.len() > 1500 {
+        out.push("Summarize that in a few bullet points".to_string());
+    }
+
+    out.truncate(MAX_SUGGESTIONS);
+    out
+}
+
+/// Whether the response contains a markdown numbered list ("1. ...").
+fn has_numbered_list(response: &str) -> bool {
+    response
+        .lines()
+        .any(|line| line.trim_start().starts_with("1. "))
+        && response.lines().any(|line| line.trim_start().starts_with("2. "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, status: ToolNotificationStatus) -> ToolNotification {
+        ToolNotification::new(name.to_string(), status)
+    }
+
+    #[test]
+    fn test_write_tools_suggest_tests_and_review() {
+        let tools = vec![tool("write_file", ToolNotificationStatus::Completed)];
+        let s = suggest("Done.", &tools);
+        assert_eq!(
+            s,
+            vec![
+                "Run the tests and fix any failures",
+                "Review the changes for bugs and edge cases",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_comes_first_and_list_is_capped() {
+        let tools = vec![
+            tool("Agent[coder]", ToolNotificationStatus::Completed),
+            tool("read_file", ToolNotificationStatus::Error),
+        ];
+        let s = suggest("Should I continue?", &tools);
+        assert_eq!(s.len(), MAX_SUGGESTIONS);
+        assert_eq!(s[0], "Explain what failed and try a different approach");
+    }
+
+    #[test]
+    fn test_question_and_numbered_list() {
+        assert_eq!(suggest("Shall I proceed?", &[]), vec!["Yes, go ahead"]);
+        assert_eq!(
+            suggest("Options:\n1. Foo\n2. Bar\n\nWhich one?", &[]),
+            vec!["Go with option 1"]
+        );
+        assert_eq!(
+            suggest("Steps:\n1. Foo\n2. Bar", &[]),
+            vec!["Go deeper on point 1"]
+        );
+    }
+
+    #[test]
+    fn test_short_plain_answer_has_no_suggestions() {
+        assert!(suggest("The answer is 42.", &[]).is_empty());
+    }
+}
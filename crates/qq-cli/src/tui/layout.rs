@@ -11,14 +11,25 @@ use ratatui::layout::{Constraint, Direction, Layout, Rect};
 pub enum PaneId {
     /// Main content/response area
     Content,
+    /// Live plan checklist (hidden when no plan is active)
+    Plan,
     /// Thinking/reasoning panel (collapsible)
     Thinking,
+    /// Tail of the captured debug log (toggleable)
+    Logs,
     /// Status bar showing tokens, profile, etc.
     Status,
+    /// Follow-up suggestion quick-picks (hidden when there are none)
+    Suggestions,
     /// User input area
     Input,
+    /// Sub-agent activity tree, split off the right of Content (toggleable)
+    Activity,
 }
 
+/// Narrowest terminal that still gets the activity pane.
+const MIN_WIDTH_WITH_ACTIVITY: u16 = 80;
+
 /// Specification for pane sizing behavior.
 #[derive(Debug, Clone, Copy)]
 pub enum PaneSize {
@@ -76,10 +87,12 @@ impl PaneSpec {
 /// Layout configuration defining pane arrangement.
 ///
 /// Panes are arranged top-to-bottom in the order they appear in the `panes` vector.
-/// The new default order is: Content > Thinking > Status > Input
+/// The new default order is: Content > Plan > Thinking > Logs > Status > Suggestions > Input
 #[derive(Debug, Clone)]
 pub struct LayoutConfig {
     panes: Vec<PaneSpec>,
+    /// Whether the activity pane takes the right side of the content area
+    activity: bool,
 }
 
 impl Default for LayoutConfig {
@@ -89,23 +102,34 @@ impl Default for LayoutConfig {
 }
 
 impl LayoutConfig {
-    /// Create a new layout with default pane order: Content > Thinking > Status > Input
+    /// Create a new layout with default pane order:
+    /// Content > Plan > Thinking > Logs > Status > Suggestions > Input
     pub fn new() -> Self {
+        let mut plan = PaneSpec::new(PaneId::Plan, PaneSize::Fixed(0));
+        plan.visible = false;
+        let mut logs = PaneSpec::new(PaneId::Logs, PaneSize::Fixed(0));
+        logs.visible = false;
+        let mut suggestions = PaneSpec::new(PaneId::Suggestions, PaneSize::Fixed(0));
+        suggestions.visible = false;
         Self {
             panes: vec![
                 PaneSpec::new(PaneId::Content, PaneSize::Fill),
+                plan,
                 PaneSpec::new(PaneId::Thinking, PaneSize::Dynamic {
                     min: 8,
                     max: 10,
                     content_lines: 0,
                 }),
+                logs,
                 PaneSpec::new(PaneId::Status, PaneSize::Fixed(2)),
+                suggestions,
                 PaneSpec::new(PaneId::Input, PaneSize::Dynamic {
                     min: 3,
                     max: 10,
                     content_lines: 1,
                 }),
             ],
+            activity: false,
         }
     }
 
@@ -133,6 +157,41 @@ impl LayoutConfig {
         self.set_pane(PaneId::Thinking, visible, size);
     }
 
+    /// Show the plan pane sized to its step count, or hide it.
+    pub fn set_plan(&mut self, visible: bool, content_lines: u16) {
+        let size = if visible {
+            PaneSize::Dynamic {
+                min: 3,
+                max: 8,
+                content_lines,
+            }
+        } else {
+            PaneSize::Fixed(0)
+        };
+        self.set_pane(PaneId::Plan, visible, size);
+    }
+
+    /// Show the one-line suggestions bar, or hide it.
+    pub fn set_suggestions(&mut self, visible: bool) {
+        let size = PaneSize::Fixed(if visible { 1 } else { 0 });
+        self.set_pane(PaneId::Suggestions, visible, size);
+    }
+
+    /// Show the log pane at 30% of the height, or hide it.
+    pub fn set_logs(&mut self, visible: bool) {
+        let size = if visible {
+            PaneSize::Percentage(30)
+        } else {
+            PaneSize::Fixed(0)
+        };
+        self.set_pane(PaneId::Logs, visible, size);
+    }
+
+    /// Show or hide the activity side pane.
+    pub fn set_activity(&mut self, visible: bool) {
+        self.activity = visible;
+    }
+
     /// Update status bar height (2 when top border shown, 1 without).
     pub fn set_status_height(&mut self, height: u16) {
         self.set_pane(PaneId::Status, true, PaneSize::Fixed(height));
@@ -162,6 +221,7 @@ impl LayoutConfig {
         for pane in &self.panes {
             result.insert(pane.id, Rect::default());
         }
+        result.insert(PaneId::Activity, Rect::default());
 
         // Collect visible panes and their constraints
         let visible_panes: Vec<&PaneSpec> = self.panes.iter().filter(|p| p.visible).collect();
@@ -185,6 +245,19 @@ impl LayoutConfig {
             result.insert(pane.id, *chunk);
         }
 
+        // Activity pane: ~35% of the content row, skipped on narrow terminals
+        if let Some(content) = result.get(&PaneId::Content).copied() {
+            if self.activity && content.width >= MIN_WIDTH_WITH_ACTIVITY {
+                let width = (content.width * 35 / 100).clamp(28, 56);
+                let chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Min(1), Constraint::Length(width)])
+                    .split(content);
+                result.insert(PaneId::Content, chunks[0]);
+                result.insert(PaneId::Activity, chunks[1]);
+            }
+        }
+
         result
     }
 }
@@ -232,6 +305,86 @@ mod tests {
         assert!(thinking.height >= 25); // ~70% of 40
     }
 
+    #[test]
+    fn test_plan_pane_hidden_by_default() {
+        let config = LayoutConfig::new();
+        let layout = config.compute(Rect::new(0, 0, 80, 40));
+        assert_eq!(layout.get(&PaneId::Plan).unwrap().height, 0);
+    }
+
+    #[test]
+    fn test_plan_pane_between_content_and_thinking() {
+        let mut config = LayoutConfig::new();
+        config.set_plan(true, 4);
+
+        let layout = config.compute(Rect::new(0, 0, 80, 40));
+        let content = layout.get(&PaneId::Content).unwrap();
+        let plan = layout.get(&PaneId::Plan).unwrap();
+        let thinking = layout.get(&PaneId::Thinking).unwrap();
+
+        // 4 steps + 2 borders
+        assert_eq!(plan.height, 6);
+        assert!(content.y < plan.y, "Content should be above Plan");
+        assert!(plan.y < thinking.y, "Plan should be above Thinking");
+    }
+
+    #[test]
+    fn test_suggestions_bar_sits_between_status_and_input() {
+        let mut config = LayoutConfig::new();
+        let layout = config.compute(Rect::new(0, 0, 80, 40));
+        assert_eq!(layout.get(&PaneId::Suggestions).unwrap().height, 0);
+
+        config.set_suggestions(true);
+        let layout = config.compute(Rect::new(0, 0, 80, 40));
+        let status = layout.get(&PaneId::Status).unwrap();
+        let suggestions = layout.get(&PaneId::Suggestions).unwrap();
+        let input = layout.get(&PaneId::Input).unwrap();
+
+        assert_eq!(suggestions.height, 1);
+        assert!(status.y < suggestions.y, "Status should be above Suggestions");
+        assert!(suggestions.y < input.y, "Suggestions should be above Input");
+    }
+
+    #[test]
+    fn test_activity_pane_splits_content_horizontally() {
+        let mut config = LayoutConfig::new();
+        let layout = config.compute(Rect::new(0, 0, 120, 40));
+        assert_eq!(layout.get(&PaneId::Activity).unwrap().width, 0);
+        assert_eq!(layout.get(&PaneId::Content).unwrap().width, 120);
+
+        config.set_activity(true);
+        let layout = config.compute(Rect::new(0, 0, 120, 40));
+        let content = layout.get(&PaneId::Content).unwrap();
+        let activity = layout.get(&PaneId::Activity).unwrap();
+        assert_eq!(activity.width, 42);
+        assert_eq!(content.width + activity.width, 120);
+        assert_eq!(content.y, activity.y);
+        assert_eq!(content.height, activity.height);
+        assert!(content.x < activity.x, "Activity should be right of Content");
+
+        // Too narrow: content keeps the full width
+        let layout = config.compute(Rect::new(0, 0, 70, 40));
+        assert_eq!(layout.get(&PaneId::Activity).unwrap().width, 0);
+        assert_eq!(layout.get(&PaneId::Content).unwrap().width, 70);
+    }
+
+    #[test]
+    fn test_log_pane_sits_between_thinking_and_status() {
+        let mut config = LayoutConfig::new();
+        let layout = config.compute(Rect::new(0, 0, 80, 40));
+        assert_eq!(layout.get(&PaneId::Logs).unwrap().height, 0);
+
+        config.set_logs(true);
+        let layout = config.compute(Rect::new(0, 0, 80, 40));
+        let thinking = layout.get(&PaneId::Thinking).unwrap();
+        let logs = layout.get(&PaneId::Logs).unwrap();
+        let status = layout.get(&PaneId::Status).unwrap();
+
+        assert_eq!(logs.height, 12);
+        assert!(thinking.y < logs.y, "Thinking should be above Logs");
+        assert!(logs.y < status.y, "Logs should be above Status");
+    }
+
     #[test]
     fn test_pane_order() {
         let config = LayoutConfig::new();
@@ -0,0 +1,247 @@
+//! Grapheme-aware editing of a [`tui_input::Input`].
+//!
+//! `Input` keeps its cursor as a char index, while its visual cursor is in
+//! terminal columns; mixing the two is what corrupted CJK input. These
+//! helpers convert the char cursor to a byte offset and move or delete whole
+//! grapheme clusters, so an accented letter, a CJK character, or an emoji
+//! with a skin-tone modifier is one keystroke, not two or three.
+
+use tui_input::Input;
+
+use qq_core::text::{ceil_grapheme_boundary, floor_grapheme_boundary};
+
+/// Byte offset of the cursor in the input's value.
+pub fn byte_cursor(input: &Input) -> usize {
+    let value = input.value();
+    value
+        .char_indices()
+        .nth(input.cursor())
+        .map_or(value.len(), |(i, _)| i)
+}
+
+/// `value` with the cursor at byte offset `byte`.
+fn at_byte(value: String, byte: usize) -> Input {
+    let cursor = value[..byte].chars().count();
+    Input::new(value).with_cursor(cursor)
+}
+
+/// Insert `text` at the cursor and move past it.
+pub fn insert(input: &Input, text: &str) -> Input {
+    let byte = byte_cursor(input);
+    let mut value = input.value().to_string();
+    value.insert_str(byte, text);
+    at_byte(value, byte + text.len())
+}
+
+/// Delete the grapheme before the cursor.
+pub fn backspace(input: &Input) -> Option<Input> {
+    let value = input.value();
+    let end = byte_cursor(input);
+    if end == 0 {
+        return None;
+    }
+    let start = floor_grapheme_boundary(value, end - 1);
+    let mut value = value.to_string();
+    value.replace_range(start..end, "");
+    Some(at_byte(value, start))
+}
+
+/// Delete the grapheme under the cursor.
+pub fn delete(input: &Input) -> Option<Input> {
+    let value = input.value();
+    let start = byte_cursor(input);
+    if start == value.len() {
+        return None;
+    }
+    let end = ceil_grapheme_boundary(value, start + 1);
+    let mut value = value.to_string();
+    value.replace_range(start..end, "");
+    Some(at_byte(value, start))
+}
+
+/// Move the cursor one grapheme left.
+pub fn left(input: &Input) -> Option<Input> {
+    let byte = byte_cursor(input);
+    if byte == 0 {
+        return None;
+    }
+    let value = input.value();
+    Some(at_byte(value.to_string(), floor_grapheme_boundary(value, byte - 1)))
+}
+
+/// Move the cursor one grapheme right.
+pub fn right(input: &Input) -> Option<Input> {
+    let value = input.value();
+    let byte = byte_cursor(input);
+    if byte == value.len() {
+        return None;
+    }
+    Some(at_byte(value.to_string(), ceil_grapheme_boundary(value, byte + 1)))
+}
+
+/// Byte offset of the start of the word before `byte`.
+fn word_start(value: &str, byte: usize) -> usize {
+    let before = value[..byte].trim_end();
+    before
+        .rfind(char::is_whitespace)
+        .map_or(0, |i| i + before[i..].chars().next().map_or(1, char::len_utf8))
+}
+
+/// Move the cursor past the next word.
+pub fn word_forward(input: &Input) -> Option<Input> {
+    let value = input.value();
+    let byte = byte_cursor(input);
+    if byte == value.len() {
+        return None;
+    }
+    let after = &value[byte..];
+    let word = after.len() - after.trim_start().len();
+    let end = after[word..]
+        .find(char::is_whitespace)
+        .map_or(value.len(), |i| byte + word + i);
+    Some(at_byte(value.to_string(), end))
+}
+
+/// Move the cursor to the start of the previous word.
+pub fn word_backward(input: &Input) -> Option<Input> {
+    let byte = byte_cursor(input);
+    if byte == 0 {
+        return None;
+    }
+    let value = input.value();
+    Some(at_byte(value.to_string(), word_start(value, byte)))
+}
+
+/// Delete the word before the cursor.
+pub fn delete_word(input: &Input) -> Option<Input> {
+    let end = byte_cursor(input);
+    if end == 0 {
+        return None;
+    }
+    let start = word_start(input.value(), end);
+    let mut value = input.value().to_string();
+    value.replace_range(start..end, "");
+    Some(at_byte(value, start))
+}
+
+/// Byte offset `column` chars into the line starting at `line_start`,
+/// clamped to that line's end and snapped to a grapheme boundary.
+fn column_in_line(value: &str, line_start: usize, column: usize) -> usize {
+    let line = value[line_start..].split('\n').next().unwrap_or("");
+    let offset = line
+        .char_indices()
+        .nth(column)
+        .map_or(line.len(), |(i, _)| i);
+    floor_grapheme_boundary(value, line_start + offset)
+}
+
+/// Cursor's line start and column (in chars) within that line.
+fn line_position(input: &Input) -> (usize, usize) {
+    let value = input.value();
+    let byte = byte_cursor(input);
+    let start = value[..byte].rfind('\n').map_or(0, |i| i + 1);
+    (start, value[start..byte].chars().count())
+}
+
+/// Move the cursor to the same column on the previous line of a
+/// multi-line value.
+pub fn up(input: &Input) -> Option<Input> {
+    let value = input.value();
+    let (start, column) = line_position(input);
+    if start == 0 {
+        return None;
+    }
+    let prev_start = value[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+    Some(at_byte(value.to_string(), column_in_line(value, prev_start, column)))
+}
+
+/// Move the cursor to the same column on the next line of a multi-line
+/// value.
+pub fn down(input: &Input) -> Option<Input> {
+    let value = input.value();
+    let (_, column) = line_position(input);
+    let byte = byte_cursor(input);
+    let next_start = byte + value[byte..].find('\n')? + 1;
+    Some(at_byte(value.to_string(), column_in_line(value, next_start, column)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> Input {
+        text.chars()
+            .fold(Input::default(), |input, c| insert(&input, &c.to_string()))
+    }
+
+    #[test]
+    fn test_editing_cjk_and_emoji() {
+        // Each CJK char is two columns wide; the cursor must stay a char index
+        let input = typed("日本語");
+        assert_eq!(input.value(), "日本語");
+        assert_eq!(input.cursor(), 3);
+
+        let input = left(&input).unwrap();
+        let input = insert(&input, "の");
+        assert_eq!(input.value(), "日本の語");
+        let input = backspace(&backspace(&input).unwrap()).unwrap();
+        assert_eq!(input.value(), "日語");
+        assert_eq!(input.cursor(), 1);
+        assert_eq!(left(&input).unwrap().cursor(), 0);
+
+        // A skin-toned thumbs up and a flag are one grapheme each
+        let input = typed("ok 👍🏽🇯🇵");
+        let input = backspace(&input).unwrap();
+        assert_eq!(input.value(), "ok 👍🏽");
+        let input = left(&input).unwrap();
+        assert_eq!(byte_cursor(&input), 3);
+        let input = delete(&input).unwrap();
+        assert_eq!(input.value(), "ok ");
+        assert!(delete(&input).is_none());
+        assert!(right(&input).is_none());
+
+        // Combining accent stays with its letter
+        let input = typed("cafe\u{301}");
+        assert_eq!(backspace(&input).unwrap().value(), "caf");
+    }
+
+    #[test]
+    fn test_word_motion_with_non_ascii() {
+        let input = typed("héllo wörld  ünï");
+        let input = word_backward(&input).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "ünï");
+        let input = word_backward(&input).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "wörld  ünï");
+        let input = word_forward(&input).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "  ünï");
+
+        let input = delete_word(&typed("héllo wörld ")).unwrap();
+        assert_eq!(input.value(), "héllo ");
+        assert_eq!(input.cursor(), 6);
+        let input = delete_word(&input).unwrap();
+        assert_eq!(input.value(), "");
+        assert!(delete_word(&input).is_none());
+    }
+
+    #[test]
+    fn test_line_motion() {
+        let input = typed("first line\nab\nthird");
+        assert!(down(&input).is_none());
+
+        // From column 5 of "third", up clamps to the end of "ab"
+        let input = up(&input).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "\nthird");
+        let input = up(&input).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "rst line\nab\nthird");
+        assert!(up(&input).is_none());
+
+        let input = down(&input).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "\nthird");
+        let input = down(&input).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "ird");
+
+        // Columns count chars, not bytes
+        let input = up(&typed("日本語\nabc")).unwrap();
+        assert_eq!(&input.value()[byte_cursor(&input)..], "\nabc");
+    }
+}
@@ -2,12 +2,23 @@
 //!
 //! Provides a proper TUI with separate panels for thinking, content, and input.
 
+pub mod activity;
+pub mod agent_picker;
 pub mod app;
+pub mod diff_view;
 pub mod events;
+pub mod file_picker;
+pub mod follow_ups;
 pub mod layout;
+pub mod line_edit;
 pub mod markdown;
+pub mod review;
 pub mod scroll;
+pub mod steering;
+pub mod summaries_view;
+pub mod thinking;
 pub mod ui;
+pub mod watchdog;
 pub mod widgets;
 
 pub use app::run_tui;
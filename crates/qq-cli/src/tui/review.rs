@@ -0,0 +1,205 @@
+//! Tool review overlay for `--supervised` sessions.
+//!
+//! Each tool call waits on this overlay before it runs. Enter runs it as
+//! proposed, `e` opens its arguments as one line of JSON to edit (a wrong
+//! path, a regex that needs escaping), and `s` skips it with an optional
+//! reason passed back to the model.
+
+use crossterm::event::KeyCode;
+use tui_input::Input;
+
+use qq_tools::{ToolReview, ToolReviewRequest};
+
+use super::line_edit;
+
+/// What the user is doing in the overlay.
+pub enum ReviewInput {
+    /// Choosing run, edit, or skip.
+    Choosing,
+    /// Editing the arguments; `error` is why the last submit was rejected.
+    Arguments { text: Input, error: Option<String> },
+    /// Typing the reason for skipping.
+    SkipReason(String),
+}
+
+/// A tool call on screen for review.
+pub struct ToolReviewState {
+    pub request: ToolReviewRequest,
+    pub input: ReviewInput,
+}
+
+impl ToolReviewState {
+    pub fn new(request: ToolReviewRequest) -> Self {
+        Self {
+            request,
+            input: ReviewInput::Choosing,
+        }
+    }
+
+    /// Apply a key press; returns the decision once the user has made one.
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<ToolReview> {
+        match self.input {
+            ReviewInput::Choosing => match code {
+                KeyCode::Enter | KeyCode::Char('r') | KeyCode::Char('R') => Some(ToolReview::Run),
+                KeyCode::Char('e') | KeyCode::Char('E') => {
+                    self.input = ReviewInput::Arguments {
+                        text: Input::new(self.request.arguments_compact()),
+                        error: None,
+                    };
+                    None
+                }
+                KeyCode::Char('s') | KeyCode::Char('S') => {
+                    self.input = ReviewInput::SkipReason(String::new());
+                    None
+                }
+                // Quick skip, no reason prompt
+                KeyCode::Esc => Some(ToolReview::Skip(None)),
+                _ => None,
+            },
+            ReviewInput::Arguments {
+                ref mut text,
+                ref mut error,
+            } => {
+                match code {
+                    KeyCode::Enter => match qq_tools::parse_edited_arguments(text.value()) {
+                        Ok(arguments) if arguments == self.request.arguments => {
+                            return Some(ToolReview::Run);
+                        }
+                        Ok(arguments) => return Some(ToolReview::Edit(arguments)),
+                        Err(e) => *error = Some(e),
+                    },
+                    // Back to the choice, discarding the edit
+                    KeyCode::Esc => self.input = ReviewInput::Choosing,
+                    code => {
+                        if let Some(edited) = edit_line(text, code) {
+                            *text = edited;
+                            *error = None;
+                        }
+                    }
+                }
+                None
+            }
+            ReviewInput::SkipReason(ref mut reason) => match code {
+                KeyCode::Enter => {
+                    let reason = reason.trim();
+                    Some(ToolReview::Skip(
+                        (!reason.is_empty()).then(|| reason.to_string()),
+                    ))
+                }
+                KeyCode::Esc => Some(ToolReview::Skip(None)),
+                KeyCode::Backspace => {
+                    reason.pop();
+                    None
+                }
+                KeyCode::Char(c) => {
+                    reason.push(c);
+                    None
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Single-line editing with a cursor; `None` when the key changes nothing.
+fn edit_line(input: &Input, code: KeyCode) -> Option<Input> {
+    match code {
+        KeyCode::Char(c) => Some(line_edit::insert(input, c.encode_utf8(&mut [0; 4]))),
+        KeyCode::Backspace => line_edit::backspace(input),
+        KeyCode::Delete => line_edit::delete(input),
+        KeyCode::Left => line_edit::left(input),
+        KeyCode::Right => line_edit::right(input),
+        KeyCode::Home => Some(Input::new(input.value().to_string()).with_cursor(0)),
+        KeyCode::End => Some(Input::new(input.value().to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::sync::oneshot;
+
+    fn review(arguments: serde_json::Value) -> ToolReviewState {
+        let (response_tx, _) = oneshot::channel();
+        ToolReviewState::new(ToolReviewRequest {
+            tool: "read_files".to_string(),
+            arguments,
+            response_tx,
+        })
+    }
+
+    fn keys(state: &mut ToolReviewState, codes: &[KeyCode]) -> Option<ToolReview> {
+        codes.iter().find_map(|&code| state.handle_key(code))
+    }
+
+    #[test]
+    fn test_edit_arguments_before_running() {
+        let mut state = review(json!({"path": "src/mian.rs"}));
+        assert_eq!(state.handle_key(KeyCode::Char('e')), None);
+
+        // {"path":"src/mian.rs"} -> {"path":"src/main.rs"}
+        let mut codes = vec![KeyCode::End];
+        codes.extend([KeyCode::Left; 8]);
+        codes.extend([KeyCode::Delete, KeyCode::Delete, KeyCode::Char('a')]);
+        codes.extend([KeyCode::Char('i'), KeyCode::Enter]);
+        assert_eq!(
+            keys(&mut state, &codes),
+            Some(ToolReview::Edit(json!({"path": "src/main.rs"})))
+        );
+
+        // Invalid JSON keeps the editor open with the error
+        let mut state = review(json!({"path": "a"}));
+        let decision = keys(
+            &mut state,
+            &[
+                KeyCode::Char('e'),
+                KeyCode::End,
+                KeyCode::Backspace,
+                KeyCode::Enter,
+            ],
+        );
+        assert_eq!(decision, None);
+        assert!(matches!(
+            state.input,
+            ReviewInput::Arguments { error: Some(ref e), .. } if e.starts_with("Invalid JSON")
+        ));
+
+        // An unchanged edit is a plain run
+        let mut state = review(json!({"path": "a"}));
+        assert_eq!(
+            keys(&mut state, &[KeyCode::Char('e'), KeyCode::Enter]),
+            Some(ToolReview::Run)
+        );
+    }
+
+    #[test]
+    fn test_run_and_skip() {
+        assert_eq!(
+            review(json!({})).handle_key(KeyCode::Enter),
+            Some(ToolReview::Run)
+        );
+        assert_eq!(
+            review(json!({})).handle_key(KeyCode::Esc),
+            Some(ToolReview::Skip(None))
+        );
+
+        let mut state = review(json!({}));
+        let decision = keys(
+            &mut state,
+            &[
+                KeyCode::Char('s'),
+                KeyCode::Char('n'),
+                KeyCode::Char('o'),
+                KeyCode::Enter,
+            ],
+        );
+        assert_eq!(decision, Some(ToolReview::Skip(Some("no".to_string()))));
+
+        // Esc leaves the editor without deciding
+        let mut state = review(json!({}));
+        assert_eq!(keys(&mut state, &[KeyCode::Char('e'), KeyCode::Esc]), None);
+        assert!(matches!(state.input, ReviewInput::Choosing));
+    }
+}
@@ -0,0 +1,89 @@
+//! Steering notes typed while a response is streaming.
+//!
+//! Pressing Enter during a stream queues the input instead of ignoring it.
+//! The streaming task drains the queue at its next safe boundary — after the
+//! current tool batch has finished, before the next LLM call — and injects
+//! the notes as a user message, so a correction like "stop, wrong file"
+//! takes effect without cancelling and retyping the request. Notes still
+//! queued when the response ends are handed back to the input box.
+
+use std::sync::{Arc, Mutex};
+
+/// Preamble telling the model the message arrived mid-task.
+const STEERING_PREAMBLE: &str =
+    "[Note from the user, sent while you were working. Take it into account before continuing.]";
+
+/// Queue of steering notes shared between the input loop and the streaming task.
+#[derive(Clone, Default)]
+pub struct SteeringQueue {
+    notes: Arc<Mutex<Vec<String>>>,
+}
+
+impl SteeringQueue {
+    /// Queue a note and return how many are waiting; blank notes are ignored.
+    pub fn push(&self, note: &str) -> usize {
+        let mut notes = self.lock();
+        let note = note.trim();
+        if !note.is_empty() {
+            notes.push(note.to_string());
+        }
+        notes.len()
+    }
+
+    /// Remove and return all queued notes, oldest first.
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        self.notes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Text of the user message injected for `notes`.
+pub fn steering_message(notes: &[String]) -> String {
+    let mut text = String::from(STEERING_PREAMBLE);
+    for note in notes {
+        text.push('\n');
+        text.push_str(note);
+    }
+    text
+}
+
+/// Text to put back in the input box for notes the stream never reached.
+pub fn restore_input(notes: &[String], current: &str) -> String {
+    let mut parts: Vec<&str> = notes.iter().map(String::as_str).collect();
+    if !current.trim().is_empty() {
+        parts.push(current);
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_drains_in_order() {
+        let queue = SteeringQueue::default();
+        let shared = queue.clone();
+        assert_eq!(queue.push("stop, wrong file"), 1);
+        assert_eq!(queue.push("   "), 1);
+        assert_eq!(shared.push(" use src/lib.rs instead "), 2);
+
+        let notes = shared.take();
+        assert_eq!(notes, vec!["stop, wrong file", "use src/lib.rs instead"]);
+        assert!(queue.take().is_empty());
+
+        let message = steering_message(&notes);
+        assert!(message.starts_with(STEERING_PREAMBLE));
+        assert!(message.ends_with("\nstop, wrong file\nuse src/lib.rs instead"));
+    }
+
+    #[test]
+    fn test_restore_input() {
+        let notes = vec!["stop".to_string(), "wrong file".to_string()];
+        assert_eq!(restore_input(&notes, ""), "stop wrong file");
+        assert_eq!(restore_input(&notes, "and"), "stop wrong file and");
+    }
+}
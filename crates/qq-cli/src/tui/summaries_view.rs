@@ -0,0 +1,180 @@
+//! `/summaries` overlay: browse compacted history.
+//!
+//! Lists the session's compaction summaries. Enter expands one to its
+//! summary text and, if they were kept, the original messages; `r` on an
+//! original queues it as context for the next message.
+
+use qq_core::Message;
+
+use crate::summaries::{message_label, recall_block, CompactionSummary};
+
+#[derive(Debug, Clone)]
+pub enum SummariesStage {
+    /// Choosing a summary.
+    List,
+    /// Reading summary `cursor`. `originals` is `None` when they weren't kept.
+    Expanded {
+        originals: Option<Vec<Message>>,
+        cursor: usize,
+    },
+}
+
+/// State of the `/summaries` overlay.
+#[derive(Debug, Clone)]
+pub struct SummariesView {
+    /// Snapshot of the archive when the overlay opened
+    pub summaries: Vec<CompactionSummary>,
+    pub cursor: usize,
+    pub stage: SummariesStage,
+}
+
+impl SummariesView {
+    /// Opens on the newest summary.
+    pub fn new(summaries: Vec<CompactionSummary>) -> Self {
+        Self {
+            cursor: summaries.len().saturating_sub(1),
+            summaries,
+            stage: SummariesStage::List,
+        }
+    }
+
+    pub fn selected(&self) -> Option<&CompactionSummary> {
+        self.summaries.get(self.cursor)
+    }
+
+    pub fn move_cursor(&mut self, step: isize) {
+        let (cursor, len) = match &mut self.stage {
+            SummariesStage::List => (&mut self.cursor, self.summaries.len()),
+            SummariesStage::Expanded {
+                originals: Some(originals),
+                cursor,
+            } => (cursor, originals.len()),
+            SummariesStage::Expanded { .. } => return,
+        };
+        if len > 0 {
+            *cursor = (*cursor as isize + step).clamp(0, len as isize - 1) as usize;
+        }
+    }
+
+    /// Enter: expand the selected summary with its originals (read by the
+    /// caller from the archive).
+    pub fn expand(&mut self, originals: Option<Vec<Message>>) {
+        if self.selected().is_some() {
+            self.stage = SummariesStage::Expanded {
+                originals,
+                cursor: 0,
+            };
+        }
+    }
+
+    /// The highlighted original message, when expanded.
+    pub fn selected_original(&self) -> Option<&Message> {
+        match &self.stage {
+            SummariesStage::Expanded {
+                originals: Some(originals),
+                cursor,
+            } => originals.get(*cursor),
+            _ => None,
+        }
+    }
+
+    /// `r`: the context block that re-injects the highlighted original.
+    pub fn recall(&self) -> Option<String> {
+        let summary = self.selected()?;
+        let message = self.selected_original()?;
+        Some(recall_block(summary, message))
+    }
+
+    /// Label and first line of each original, for the expanded list.
+    pub fn original_rows(&self) -> Vec<String> {
+        let SummariesStage::Expanded {
+            originals: Some(originals),
+            ..
+        } = &self.stage
+        else {
+            return Vec::new();
+        };
+        originals
+            .iter()
+            .map(|m| {
+                let text = m.content.to_string_lossy();
+                let first = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+                format!("{}: {}", message_label(m), first.trim())
+            })
+            .collect()
+    }
+
+    /// Esc: back from a summary to the list. Returns true when the overlay
+    /// should close.
+    pub fn back(&mut self) -> bool {
+        match self.stage {
+            SummariesStage::List => true,
+            SummariesStage::Expanded { .. } => {
+                self.stage = SummariesStage::List;
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summaries::SummaryArchive;
+    use qq_core::ObservationPass;
+
+    fn view() -> (SummariesView, Vec<Message>) {
+        let originals = vec![
+            Message::user("deploy to staging\nthen prod"),
+            Message::assistant("Done: staging uses port 8443"),
+        ];
+        let mut archive = SummaryArchive::new(false);
+        for number in 1..=2 {
+            archive.record(ObservationPass {
+                number,
+                observations: format!("- pass {}", number),
+                messages: originals.clone(),
+            });
+        }
+        (SummariesView::new(archive.entries().to_vec()), originals)
+    }
+
+    #[test]
+    fn test_browse_expand_and_recall() {
+        let (mut v, originals) = view();
+        assert_eq!(v.cursor, 1);
+        v.move_cursor(-5);
+        assert_eq!(v.selected().unwrap().number, 1);
+        assert_eq!(v.recall(), None);
+
+        v.expand(Some(originals));
+        assert_eq!(
+            v.original_rows(),
+            vec![
+                "you: deploy to staging",
+                "assistant: Done: staging uses port 8443"
+            ]
+        );
+        v.move_cursor(1);
+        let block = v.recall().unwrap();
+        assert!(block.starts_with("<recalled pass=\"1\" role=\"assistant\">"));
+        assert!(block.contains("port 8443"));
+
+        assert!(!v.back());
+        assert!(matches!(v.stage, SummariesStage::List));
+        assert!(v.back());
+    }
+
+    #[test]
+    fn test_expand_without_originals() {
+        let (mut v, _) = view();
+        v.expand(None);
+        v.move_cursor(1);
+        assert!(v.original_rows().is_empty());
+        assert_eq!(v.recall(), None);
+
+        let mut empty = SummariesView::new(Vec::new());
+        empty.expand(None);
+        assert!(matches!(empty.stage, SummariesStage::List));
+    }
+}
@@ -0,0 +1,340 @@
+//! Thinking panel buffer, tagged by the agent that produced each line.
+//!
+//! The primary agent streams its reasoning directly while sub-agents report
+//! theirs through the event bus, and the deltas interleave. Each source keeps
+//! its own partial line, so a sub-agent's half-finished thought never splices
+//! into the primary's, and completed lines remember their agent. The panel
+//! prefixes lines with the agent name once more than one agent has spoken,
+//! and can filter to one agent (Ctrl+O cycles).
+
+use std::collections::VecDeque;
+
+/// One line of thinking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThinkingLine {
+    /// Sub-agent that produced it; `None` for the primary agent
+    pub agent: Option<String>,
+    pub text: String,
+}
+
+/// Ring buffer for thinking content to prevent unbounded memory growth.
+///
+/// Stores lines in a fixed-capacity deque, discarding oldest lines when full.
+#[derive(Debug, Default)]
+pub struct ThinkingBuffer {
+    lines: VecDeque<ThinkingLine>,
+    /// Partial line being accumulated per source, in order of first delta
+    partials: Vec<ThinkingLine>,
+    /// Every source seen since the last clear, in order of first delta
+    sources: Vec<Option<String>>,
+}
+
+impl ThinkingBuffer {
+    /// Maximum number of lines to retain
+    const MAX_LINES: usize = 100;
+
+    /// Create a new empty buffer.
+    pub fn new() -> Self {
+        Self {
+            lines: VecDeque::with_capacity(Self::MAX_LINES),
+            partials: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    /// Append primary-agent content to the buffer.
+    pub fn push_str(&mut self, s: &str) {
+        self.push_from(None, s);
+    }
+
+    /// Append content from `agent` (`None` for the primary agent).
+    pub fn push_from(&mut self, agent: Option<&str>, s: &str) {
+        if !self.sources.iter().any(|seen| seen.as_deref() == agent) {
+            self.sources.push(agent.map(str::to_string));
+        }
+        let index = match self
+            .partials
+            .iter()
+            .position(|p| p.agent.as_deref() == agent)
+        {
+            Some(index) => index,
+            None => {
+                self.partials.push(ThinkingLine {
+                    agent: agent.map(str::to_string),
+                    text: String::new(),
+                });
+                self.partials.len() - 1
+            }
+        };
+
+        // Split input by newlines; the first part extends the partial line
+        // and each later part means we hit a newline
+        let mut parts = s.split('\n');
+        if let Some(first) = parts.next() {
+            self.partials[index].text.push_str(first);
+        }
+        for part in parts {
+            let complete_line = ThinkingLine {
+                agent: agent.map(str::to_string),
+                text: std::mem::replace(&mut self.partials[index].text, part.to_string()),
+            };
+            self.lines.push_back(complete_line);
+
+            // Evict oldest if over capacity
+            if self.lines.len() > Self::MAX_LINES {
+                self.lines.pop_front();
+            }
+        }
+    }
+
+    /// Clear the buffer.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.partials.clear();
+        self.sources.clear();
+    }
+
+    /// Check if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.partials.iter().all(|p| p.text.is_empty())
+    }
+
+    /// Count the number of lines (including partial).
+    #[cfg(test)]
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + self.partials.iter().filter(|p| !p.text.is_empty()).count()
+    }
+
+    /// Whether more than one agent has thought since the last clear.
+    pub fn is_mixed(&self) -> bool {
+        self.sources.len() > 1
+    }
+
+    /// Agents that have thought since the last clear, in order of first delta.
+    pub fn sources(&self) -> &[Option<String>] {
+        &self.sources
+    }
+
+    /// Lines to display: completed lines, then each source's partial line.
+    /// With `only`, lines from that source alone.
+    pub fn view(&self, only: Option<&Option<String>>) -> Vec<ThinkingLine> {
+        self.lines
+            .iter()
+            .chain(self.partials.iter().filter(|p| !p.text.is_empty()))
+            .filter(|line| only.is_none_or(|agent| &line.agent == agent))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the content as a string, without agent tags.
+    #[cfg(test)]
+    pub fn as_str(&self) -> String {
+        let mut result = String::new();
+        for line in &self.lines {
+            result.push_str(&line.text);
+            result.push('\n');
+        }
+        let partials: Vec<&str> = self
+            .partials
+            .iter()
+            .map(|p| p.text.as_str())
+            .filter(|text| !text.is_empty())
+            .collect();
+        result.push_str(&partials.join("\n"));
+        result
+    }
+
+    /// Capture the current size for later rollback via `restore_to`.
+    pub fn snapshot(&self) -> ThinkingSnapshot {
+        ThinkingSnapshot {
+            line_count: self.lines.len(),
+            partials: self.partials.clone(),
+        }
+    }
+
+    /// Truncate the buffer back to a previously taken snapshot.
+    ///
+    /// If MAX_LINES eviction has occurred since the snapshot, some original
+    /// lines may have been lost; the restore is best-effort in that case.
+    pub fn restore_to(&mut self, snap: &ThinkingSnapshot) {
+        while self.lines.len() > snap.line_count {
+            self.lines.pop_back();
+        }
+        self.partials = snap.partials.clone();
+    }
+}
+
+/// Position marker in a `ThinkingBuffer`, captured by `snapshot`.
+#[derive(Debug, Clone, Default)]
+pub struct ThinkingSnapshot {
+    line_count: usize,
+    partials: Vec<ThinkingLine>,
+}
+
+/// Next agent for the thinking filter: all agents, then each source in turn,
+/// then all again. A filter whose agent is no longer present resets to all.
+pub fn next_filter(
+    current: Option<&Option<String>>,
+    sources: &[Option<String>],
+) -> Option<Option<String>> {
+    let next = match current {
+        None => 0,
+        Some(agent) => match sources.iter().position(|s| s == agent) {
+            Some(i) => i + 1,
+            None => return None,
+        },
+    };
+    sources.get(next).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: &[ThinkingLine]) -> Vec<&str> {
+        lines.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_interleaved_sources_keep_their_lines() {
+        let mut buffer = ThinkingBuffer::new();
+        buffer.push_str("Let me deleg");
+        buffer.push_from(Some("coder"), "Reading main");
+        buffer.push_str("ate this.\nWaiting");
+        buffer.push_from(Some("coder"), ".rs first\n");
+        assert!(buffer.is_mixed());
+        assert_eq!(buffer.line_count(), 3);
+
+        let all = buffer.view(None);
+        assert_eq!(
+            texts(&all),
+            ["Let me delegate this.", "Reading main.rs first", "Waiting"]
+        );
+        assert_eq!(all[1].agent.as_deref(), Some("coder"));
+        assert_eq!(
+            buffer.as_str(),
+            "Let me delegate this.\nReading main.rs first\nWaiting"
+        );
+
+        let coder = Some("coder".to_string());
+        assert_eq!(texts(&buffer.view(Some(&coder))), ["Reading main.rs first"]);
+        assert_eq!(
+            texts(&buffer.view(Some(&None))),
+            ["Let me delegate this.", "Waiting"]
+        );
+
+        let snap = buffer.snapshot();
+        buffer.push_str(" for coder\n");
+        buffer.restore_to(&snap);
+        assert_eq!(
+            buffer.as_str(),
+            "Let me delegate this.\nReading main.rs first\nWaiting"
+        );
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert!(!buffer.is_mixed());
+    }
+
+    #[test]
+    fn test_next_filter_cycles_through_sources() {
+        let sources = vec![
+            None,
+            Some("coder".to_string()),
+            Some("reviewer".to_string()),
+        ];
+        let mut filter = None;
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            filter = next_filter(filter.as_ref(), &sources);
+            seen.push(filter.clone());
+        }
+        assert_eq!(
+            seen,
+            [
+                Some(None),
+                Some(Some("coder".to_string())),
+                Some(Some("reviewer".to_string())),
+                None
+            ]
+        );
+        // A filter on an agent that has gone resets to everything
+        assert_eq!(
+            next_filter(Some(&Some("planner".to_string())), &sources),
+            None
+        );
+    }
+
+    #[test]
+    fn test_thinking_buffer_empty() {
+        let buf = ThinkingBuffer::new();
+        assert!(buf.is_empty());
+        assert_eq!(buf.line_count(), 0);
+        assert_eq!(buf.as_str(), "");
+    }
+
+    #[test]
+    fn test_thinking_buffer_single_line() {
+        let mut buf = ThinkingBuffer::new();
+        buf.push_str("hello world");
+        assert!(!buf.is_empty());
+        assert_eq!(buf.line_count(), 1);
+        assert_eq!(buf.as_str(), "hello world");
+    }
+
+    #[test]
+    fn test_thinking_buffer_multiple_lines() {
+        let mut buf = ThinkingBuffer::new();
+        buf.push_str("line1\nline2\nline3");
+        assert_eq!(buf.line_count(), 3);
+        assert_eq!(buf.as_str(), "line1\nline2\nline3");
+    }
+
+    #[test]
+    fn test_thinking_buffer_incremental_append() {
+        let mut buf = ThinkingBuffer::new();
+        buf.push_str("hel");
+        buf.push_str("lo\nwor");
+        buf.push_str("ld");
+        assert_eq!(buf.line_count(), 2);
+        assert_eq!(buf.as_str(), "hello\nworld");
+    }
+
+    #[test]
+    fn test_thinking_buffer_eviction() {
+        let mut buf = ThinkingBuffer::new();
+        // Push more than MAX_LINES lines
+        for i in 0..150 {
+            buf.push_str(&format!("line{}\n", i));
+        }
+        // Should only have MAX_LINES lines (100)
+        assert_eq!(buf.line_count(), ThinkingBuffer::MAX_LINES);
+        // First line should be line 50 (0-49 evicted)
+        let content = buf.as_str();
+        assert!(content.starts_with("line50\n"));
+        assert!(content.contains("line149\n"));
+    }
+
+    #[test]
+    fn test_thinking_buffer_clear() {
+        let mut buf = ThinkingBuffer::new();
+        buf.push_str("some content\nmore content");
+        buf.clear();
+        assert!(buf.is_empty());
+        assert_eq!(buf.line_count(), 0);
+        assert_eq!(buf.as_str(), "");
+    }
+
+    #[test]
+    fn test_thinking_buffer_snapshot_restore() {
+        let mut buf = ThinkingBuffer::new();
+        buf.push_str("kept line 1\nkept line 2\npar");
+        let snap = buf.snapshot();
+
+        buf.push_str("tial-suffix\nlost line\nlost partial");
+        assert_eq!(buf.as_str(), "kept line 1\nkept line 2\npartial-suffix\nlost line\nlost partial");
+
+        buf.restore_to(&snap);
+        assert_eq!(buf.as_str(), "kept line 1\nkept line 2\npar");
+    }
+}
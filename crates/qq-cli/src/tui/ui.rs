@@ -4,15 +4,26 @@ use std::collections::HashMap;
 
 use ratatui::{
     layout::Rect,
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph},
     Frame,
 };
+use unicode_segmentation::UnicodeSegmentation;
 
+use qq_core::text::{display_width, ellipsize, truncate_width};
+
+use super::agent_picker::{AgentPicker, AgentPickerStage};
 use super::app::{ProfilesPickerStage, ProfilesTarget, TuiApp};
+use super::file_picker::{FilePicker, FilePickerStage};
 use super::layout::PaneId;
-use super::widgets::{ContentArea, InputArea, StatusBar, ThinkingPanel};
+use super::line_edit;
+use super::diff_view::{DiffLineKind, DiffView};
+use super::summaries_view::{SummariesStage, SummariesView};
+use super::widgets::{
+    wrap_input, ActivityPanel, ContentArea, InputArea, LogPanel, PlanPanel, StatusBar,
+    SuggestionsBar, ThinkingPanel,
+};
 
 /// Render the entire TUI using a pre-computed layout.
 ///
@@ -37,12 +48,31 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
         }
     }
 
-    // Render Thinking panel (below content)
+    // Render activity tree (right of content)
+    if let Some(&activity_rect) = layout.get(&PaneId::Activity) {
+        if activity_rect.width > 0 {
+            frame.render_widget(ActivityPanel::new(&app.activity), activity_rect);
+        }
+    }
+
+    // Render Plan checklist (below content)
+    if let (Some(&plan_rect), Some(plan)) = (layout.get(&PaneId::Plan), app.plan.as_ref()) {
+        if plan_rect.height > 0 {
+            frame.render_widget(PlanPanel::new(plan), plan_rect);
+        }
+    }
+
+    // Render Thinking panel (below plan)
     if let Some(&thinking_rect) = layout.get(&PaneId::Thinking) {
         if thinking_rect.height > 0 && has_thinking {
             let is_thinking_streaming = app.is_streaming && app.content.is_empty();
-            let thinking_str = app.thinking_content.as_str();
-            let thinking = ThinkingPanel::new(&thinking_str)
+            let thinking_lines = app.thinking_content.view(app.thinking_filter.as_ref());
+            let thinking = ThinkingPanel::new(&thinking_lines)
+                .tagged(
+                    app.thinking_content.is_mixed() && app.thinking_filter.is_none(),
+                    &app.primary_agent,
+                )
+                .filter(app.thinking_filter.as_ref())
                 .tool_notifications(&app.tool_notifications)
                 .expanded(app.thinking_expanded)
                 .streaming(is_thinking_streaming)
@@ -52,6 +82,16 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
         }
     }
 
+    // Render log pane (below thinking)
+    if let Some(&logs_rect) = layout.get(&PaneId::Logs) {
+        if logs_rect.height > 0 {
+            let logs = LogPanel::new(&app.logs, &app.primary_agent)
+                .level(app.log_level)
+                .agent(app.log_agent.as_ref());
+            frame.render_widget(logs, logs_rect);
+        }
+    }
+
     // Render Status bar (below thinking)
     if let Some(&status_rect) = layout.get(&PaneId::Status) {
         if status_rect.height > 0 {
@@ -59,11 +99,10 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
                 .agent_progress
                 .as_ref()
                 .map(|(name, iter, chain)| (name.as_str(), *iter, chain.as_slice()));
-            // Show top border only when thinking panel isn't visible (avoids double line)
-            let thinking_visible = has_thinking
-                && layout
-                    .get(&PaneId::Thinking)
-                    .is_some_and(|r| r.height > 0);
+            // Show top border only when no pane sits above it (avoids double line)
+            let pane_visible = |id| layout.get(&id).is_some_and(|r: &Rect| r.height > 0);
+            let pane_above =
+                (has_thinking && pane_visible(PaneId::Thinking)) || pane_visible(PaneId::Logs);
 
             let mut status_bar = StatusBar::new(&app.profile, &app.primary_agent)
                 .tokens(app.prompt_tokens, app.completion_tokens)
@@ -74,7 +113,9 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
                 .agent_progress(agent_progress)
                 .agent_bytes(app.agent_input_bytes, app.agent_output_bytes)
                 .session_bytes(app.session_input_bytes, app.session_output_bytes)
-                .show_top_border(!thinking_visible);
+                .untracked_reads(app.untracked_reads)
+                .request_metrics(app.request_metrics.as_deref())
+                .show_top_border(!pane_above);
 
             if let Some(ref msg) = app.status_message {
                 status_bar = status_bar.status(msg);
@@ -84,6 +125,13 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
         }
     }
 
+    // Render follow-up suggestions (between status and input)
+    if let Some(&suggestions_rect) = layout.get(&PaneId::Suggestions) {
+        if suggestions_rect.height > 0 {
+            frame.render_widget(SuggestionsBar::new(&app.suggestions), suggestions_rect);
+        }
+    }
+
     // Render Input area (at bottom)
     if let Some(&input_rect) = layout.get(&PaneId::Input) {
         if input_rect.height > 0 {
@@ -98,6 +146,12 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
                     app.pending_content.len()
                 );
                 &pending_hint
+            } else if app.context_files.pending_count() > 0 {
+                pending_hint = format!(
+                    "{} file(s) queued for the next message | /files | /drop <path|all>",
+                    app.context_files.pending_count()
+                );
+                &pending_hint
             } else {
                 "/help | /quit | PgUp/PgDn scroll | Shift+select to copy"
             };
@@ -110,6 +164,11 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
         }
     }
 
+    // Stall warning, just above the input
+    if let (Some(ref stall), Some(&input_rect)) = (&app.stall, layout.get(&PaneId::Input)) {
+        render_stall_warning(frame, stall, input_rect);
+    }
+
     // Show help overlay if requested
     if app.show_help {
         render_help_overlay(frame);
@@ -118,12 +177,34 @@ pub fn render(app: &TuiApp, frame: &mut Frame, layout: &HashMap<PaneId, Rect>) {
     // Show approval overlay if pending (renders on top of everything)
     if let Some(ref request) = app.pending_approval {
         render_approval_overlay(frame, request, app.denial_reason_input.as_deref());
+    } else if let Some(ref review) = app.pending_review {
+        render_review_overlay(frame, review);
     }
 
     // Show /profiles picker overlay if open (always on top)
     if let Some(ref stage) = app.profiles_picker {
         render_profiles_overlay(frame, stage);
     }
+
+    // Show /add file picker overlay if open
+    if let Some(ref picker) = app.file_picker {
+        render_file_picker_overlay(frame, picker);
+    }
+
+    // Show Ctrl+A agent picker if open
+    if let Some(ref picker) = app.agent_picker {
+        render_agent_picker_overlay(frame, picker);
+    }
+
+    // Show /summaries overlay if open
+    if let Some(ref view) = app.summaries_view {
+        render_summaries_overlay(frame, view);
+    }
+
+    // Show /diff pager if open
+    if let Some(ref view) = app.diff_view {
+        render_diff_overlay(frame, view);
+    }
 }
 
 /// Calculate the number of wrapped lines for input text.
@@ -135,9 +216,8 @@ pub fn calculate_input_lines(input_text: &str, available_width: u16) -> u16 {
         return 1; // Minimum 1 line for input
     }
 
-    // Calculate wrapped line count
-    let wrapped_lines = input_text.len().div_ceil(text_width);
-    wrapped_lines as u16
+    // Same wrapping as the input widget, in display columns
+    wrap_input(input_text, text_width, available_width as usize).len() as u16
 }
 
 /// Render help overlay
@@ -146,7 +226,7 @@ fn render_help_overlay(frame: &mut Frame) {
 
     // Create centered overlay
     let overlay_width = 60u16.min(area.width.saturating_sub(4));
-    let overlay_height = 35u16.min(area.height.saturating_sub(4));
+    let overlay_height = 75u16.min(area.height.saturating_sub(4));
 
     let x = (area.width.saturating_sub(overlay_width)) / 2;
     let y = (area.height.saturating_sub(overlay_height)) / 2;
@@ -165,32 +245,58 @@ fn render_help_overlay(frame: &mut Frame) {
         Line::from(Span::styled("Navigation:", Style::default().fg(Color::Cyan))),
         Line::from("  PgUp/Ctrl+B  Page up"),
         Line::from("  PgDn/Ctrl+F  Page down"),
-        Line::from("  Ctrl+Home    Scroll to top"),
+        Line::from("  Ctrl+Home    Scroll to top (again: load spilled text)"),
         Line::from("  Ctrl+End     Scroll to bottom"),
         Line::from("  Ctrl+T       Expand/shrink thinking panel"),
         Line::from("  Ctrl+H       Hide/show thinking panel"),
+        Line::from("  Ctrl+O       Thinking from one agent (cycles; then all)"),
+        Line::from("  Ctrl+G       Show/hide sub-agent activity tree"),
+        Line::from("  Alt+Up/Down  Select activity node (shows its thinking)"),
+        Line::from("  Ctrl+L       Show/hide debug log pane"),
+        Line::from("  Alt+L/Alt+A  Log pane: cycle level / agent"),
+        Line::from("  Ctrl+K       Show/hide per-turn token/cost footers"),
         Line::from("  Mouse wheel  Scroll content (when captured)"),
         Line::from(""),
         Line::from(Span::styled("Commands:", Style::default().fg(Color::Cyan))),
         Line::from("  /help        Show this help"),
         Line::from("  /quit        Exit the application"),
-        Line::from("  /clear       Clear conversation + counters"),
+        Line::from("  /clear       Clear conversation + counters (history is saved)"),
+        Line::from("  /clear summary|all  Carry a summary over, or don't"),
         Line::from("  /reset       Full reset (clear + agent memory + tasks)"),
-        Line::from("  /history     Show message count"),
+        Line::from("  /undo        Take back the last message and its response"),
+        Line::from("  /redo        Restore the exchange /undo took back"),
+        Line::from("  /history     List messages, numbered for /pin"),
+        Line::from("  /pin [N|N-M] Keep messages verbatim through compaction"),
+        Line::from("  /unpin [..]  Release pinned messages (N, N-M, or all)"),
         Line::from("  /memory      Show memory diagnostics"),
+        Line::from("  /export [p]  Write the full transcript to a file"),
         Line::from("  /tools       List available tools"),
         Line::from("  /agents      List available agents"),
         Line::from("  /mount <p>   Add read-only bash sandbox mount"),
         Line::from("  /mounts      List bash sandbox mounts"),
+        Line::from("  /focus <p>   Work in a project subdirectory"),
+        Line::from("  /unfocus     Return to the project root"),
+        Line::from("  /context     Notes sent with every request (set, rm, show)"),
+        Line::from("  /permissions Show or edit bash permission tiers"),
+        Line::from("  /access-log  List files read that git doesn't track"),
         Line::from("  /attach <p>  Attach an image file"),
         Line::from("  /attachments List pending attachments"),
         Line::from("  /clear-attachments  Remove all attachments"),
         Line::from("  /profiles    Switch profile for chat or any agent"),
+        Line::from("  /add [q]     Pick a file (and lines) to add as context"),
+        Line::from("  /files       List added files"),
+        Line::from("  /refresh [p] Re-send current contents of added files"),
+        Line::from("  /drop <p>    Remove an added file (or all) from context"),
+        Line::from("  /summaries   Browse compacted history; r re-injects a message"),
+        Line::from("  /diff        Review all file changes made this session"),
         Line::from(""),
         Line::from(Span::styled("Other:", Style::default().fg(Color::Cyan))),
         Line::from("  Shift+drag   Select text (works in most terminals)"),
         Line::from("  Ctrl+Y       Toggle select mode (fallback for copy)"),
         Line::from("  Alt+V        Paste image from clipboard"),
+        Line::from("  Ctrl+A       Pick an agent and write it a task"),
+        Line::from("  Alt+1..3     Insert a follow-up suggestion"),
+        Line::from("  Alt+P        Pin/unpin the latest message"),
         Line::from("  Ctrl+C       Cancel streaming"),
         Line::from("  Ctrl+D       Exit"),
         Line::from(""),
@@ -213,6 +319,37 @@ fn render_help_overlay(frame: &mut Frame) {
 }
 
 /// Render approval overlay modal for per-call approval (bash commands, file operations, mounts)
+/// Render the stall warning as a box sitting on top of the input area.
+fn render_stall_warning(frame: &mut Frame, stall: &super::watchdog::Stall, input_rect: Rect) {
+    let height = 4;
+    if input_rect.y < height || input_rect.width < 20 {
+        return;
+    }
+    let area = Rect::new(input_rect.x, input_rect.y - height, input_rect.width, height);
+    frame.render_widget(Clear, area);
+
+    let key = |k: &'static str| {
+        Span::styled(k, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    };
+    let lines = vec![
+        Line::from(stall.message()),
+        Line::from(vec![
+            key("c"),
+            Span::raw(" cancel  "),
+            key("e"),
+            Span::raw(" keep waiting  "),
+            key("n"),
+            Span::raw(" nudge the agent  "),
+            Span::styled("(or type a note)", Style::default().fg(Color::DarkGray)),
+        ]),
+    ];
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(" No progress ");
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
 fn render_approval_overlay(
     frame: &mut Frame,
     request: &qq_tools::ApprovalRequest,
@@ -241,11 +378,12 @@ fn render_approval_overlay(
     let max_cmd_lines: usize = 8;
     let cmd_lines = cmd_lines.min(max_cmd_lines);
 
-    // Height: border(1) + header(1) + blank(1) + cmd_lines + triggers(0..1) + blank(1) + keys/reason(1..3) + border(1)
+    // Height: border(1) + header(1) + blank(1) + cmd_lines + explanation(0..1) + triggers(0..1) + blank(1) + keys/reason(1..3) + border(1)
+    let explanation_line = if request.explanation.is_some() { 1u16 } else { 0 };
     let triggers_line = if triggers.is_empty() { 0u16 } else { 1 };
     let reason_lines = if denial_reason.is_some() { 2u16 } else { 0 };
-    let overlay_height =
-        (6 + cmd_lines as u16 + triggers_line + reason_lines).min(area.height.saturating_sub(4));
+    let overlay_height = (6 + cmd_lines as u16 + explanation_line + triggers_line + reason_lines)
+        .min(area.height.saturating_sub(4));
 
     let x = (area.width.saturating_sub(overlay_width)) / 2;
     let y = (area.height.saturating_sub(overlay_height)) / 2;
@@ -257,11 +395,7 @@ fn render_approval_overlay(
 
     // Truncate command only if it exceeds the wrapped line cap
     let max_chars = inner_width * max_cmd_lines;
-    let cmd_display = if request.full_command.len() > max_chars {
-        format!("{}...", &request.full_command[..max_chars.saturating_sub(3)])
-    } else {
-        request.full_command.clone()
-    };
+    let cmd_display = ellipsize(&request.full_command, max_chars);
 
     let header_text = format!("{} Approval Required", request.category);
     let mut lines = vec![
@@ -283,6 +417,13 @@ fn render_approval_overlay(
         )));
     }
 
+    if let Some(ref explanation) = request.explanation {
+        lines.push(Line::from(Span::styled(
+            ellipsize(explanation, inner_width),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
+        )));
+    }
+
     if !triggers.is_empty() {
         lines.push(Line::from(Span::styled(
             triggers,
@@ -324,6 +465,118 @@ fn render_approval_overlay(
     frame.render_widget(paragraph, overlay_area);
 }
 
+/// Render the tool review overlay of `--supervised` sessions.
+fn render_review_overlay(frame: &mut Frame, review: &super::review::ToolReviewState) {
+    use super::review::ReviewInput;
+
+    let area = frame.area();
+    let overlay_width = (area.width * 4 / 5).max(40).min(area.width.saturating_sub(4));
+    let inner_width = overlay_width.saturating_sub(2) as usize;
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} wants to run", review.request.tool),
+            Style::default().fg(Color::Yellow),
+        )),
+        Line::from(""),
+    ];
+
+    let key = |k: &'static str, color: Color| Span::styled(k, Style::default().fg(color));
+    match review.input {
+        ReviewInput::Arguments { ref text, ref error } => {
+            // Scroll the one-line editor so the cursor stays in view
+            let width = inner_width.saturating_sub(3).max(1);
+            let value = text.value();
+            let cursor = line_edit::byte_cursor(text);
+            let mut start = cursor;
+            let mut used = 1; // the cursor marker
+            for (i, grapheme) in value[..cursor].grapheme_indices(true).rev() {
+                used += display_width(grapheme);
+                if used > width {
+                    break;
+                }
+                start = i;
+            }
+            let before = value[start..cursor].to_string();
+            let after = truncate_width(&value[cursor..], width.saturating_sub(used.min(width)))
+                .to_string();
+            lines.push(Line::from(Span::styled(
+                "Arguments (Enter to run, Esc to cancel the edit):",
+                Style::default().fg(Color::Cyan),
+            )));
+            lines.push(Line::from(vec![
+                Span::styled("> ", Style::default().fg(Color::Cyan)),
+                Span::styled(before, Style::default().fg(Color::White)),
+                Span::styled("|", Style::default().fg(Color::DarkGray)),
+                Span::styled(after, Style::default().fg(Color::White)),
+            ]));
+            if let Some(ref error) = error {
+                lines.push(Line::from(Span::styled(
+                    error.clone(),
+                    Style::default().fg(Color::Red),
+                )));
+            }
+        }
+        _ => {
+            // Pretty-printed arguments, capped so the keys stay on screen
+            let max_lines = (area.height as usize).saturating_sub(12).clamp(3, 20);
+            let pretty = review.request.arguments_pretty();
+            let total = pretty.lines().count();
+            for line in pretty.lines().take(max_lines) {
+                let line = truncate_width(line, inner_width).to_string();
+                lines.push(Line::from(Span::styled(
+                    line,
+                    Style::default().fg(Color::White),
+                )));
+            }
+            if total > max_lines {
+                lines.push(Line::from(Span::styled(
+                    format!("... {} more lines", total - max_lines),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            lines.push(Line::from(""));
+            if let ReviewInput::SkipReason(ref reason) = review.input {
+                lines.push(Line::from(Span::styled(
+                    "Skip reason (Enter to submit, Esc to skip):",
+                    Style::default().fg(Color::Red),
+                )));
+                lines.push(Line::from(vec![
+                    Span::styled("> ", Style::default().fg(Color::Red)),
+                    Span::styled(reason.clone(), Style::default().fg(Color::White)),
+                    Span::styled("_", Style::default().fg(Color::DarkGray)),
+                ]));
+            } else {
+                lines.push(Line::from(vec![
+                    key("[r]", Color::Green),
+                    Span::raw("un  "),
+                    key("[e]", Color::Cyan),
+                    Span::raw("dit arguments  "),
+                    key("[s]", Color::Red),
+                    Span::raw("kip"),
+                ]));
+            }
+        }
+    }
+
+    let overlay_height = (lines.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let x = (area.width.saturating_sub(overlay_width)) / 2;
+    let y = (area.height.saturating_sub(overlay_height)) / 2;
+    let overlay_area = Rect::new(x, y, overlay_width, overlay_height);
+    frame.render_widget(Clear, overlay_area);
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" Review Tool Call ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
 /// Render the `/profiles` picker overlay.
 fn render_profiles_overlay(frame: &mut Frame, stage: &ProfilesPickerStage) {
     let area = frame.area();
@@ -383,6 +636,449 @@ fn render_profiles_overlay(frame: &mut Frame, stage: &ProfilesPickerStage) {
     frame.render_widget(paragraph, overlay_area);
 }
 
+/// Render the `/add` file picker overlay.
+fn render_file_picker_overlay(frame: &mut Frame, picker: &FilePicker) {
+    let area = frame.area();
+
+    let overlay_width = 80u16.min(area.width.saturating_sub(4));
+    let overlay_height = 24u16.min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(overlay_width)) / 2;
+    let y = (area.height.saturating_sub(overlay_height)) / 2;
+    let overlay_area = Rect::new(x, y, overlay_width, overlay_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let mut lines: Vec<Line> = Vec::new();
+    let hint = match &picker.stage {
+        FilePickerStage::PickFile => {
+            lines.push(Line::from(vec![
+                Span::styled("Find: ", Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("{}_", picker.query),
+                    Style::default().fg(Color::White),
+                ),
+                Span::styled(
+                    format!("  ({} of {})", picker.match_count(), picker.file_count()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+            lines.push(Line::from(""));
+
+            // Title (2) + blank (1) + hint (1) + 2 borders
+            let visible_rows = (overlay_height as usize).saturating_sub(6);
+            let (start, end) = visible_window(picker.cursor, picker.match_count(), visible_rows);
+            for (i, path) in picker.matches().enumerate().take(end).skip(start) {
+                let style = if i == picker.cursor {
+                    Style::default().fg(Color::Black).bg(Color::Cyan)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                lines.push(Line::from(Span::styled(path.to_string(), style)));
+            }
+            if picker.match_count() == 0 {
+                lines.push(Line::from(Span::styled(
+                    "No matching files",
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            "Type to filter, Up/Down to move, Enter to pick, Esc to cancel"
+        }
+        FilePickerStage::PickRange { path, input, error } => {
+            lines.push(Line::from(Span::styled(
+                path.clone(),
+                Style::default().fg(Color::Cyan),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::styled("Lines: ", Style::default().fg(Color::Cyan)),
+                Span::styled(format!("{}_", input), Style::default().fg(Color::White)),
+            ]));
+            lines.push(Line::from(Span::styled(
+                "Empty for the whole file, or 10-40, 10-, 25",
+                Style::default().fg(Color::DarkGray),
+            )));
+            if let Some(error) = error {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    error.clone(),
+                    Style::default().fg(Color::Red),
+                )));
+            }
+            "Enter to add, Esc to go back"
+        }
+    };
+
+    // Pin the hint to the last inner row
+    let inner_rows = (overlay_height as usize).saturating_sub(2);
+    while lines.len() + 1 < inner_rows {
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(
+        hint,
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" Add File ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
+/// Render the Ctrl+A agent picker and task composer overlay.
+fn render_agent_picker_overlay(frame: &mut Frame, picker: &AgentPicker) {
+    let area = frame.area();
+
+    let overlay_width = 90u16.min(area.width.saturating_sub(4));
+    let overlay_height = 28u16.min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(overlay_width)) / 2;
+    let y = (area.height.saturating_sub(overlay_height)) / 2;
+    let overlay_area = Rect::new(x, y, overlay_width, overlay_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let text_width = (overlay_width as usize).saturating_sub(2);
+    let inner_rows = (overlay_height as usize).saturating_sub(2);
+    let dim = Style::default().fg(Color::DarkGray);
+
+    let mut lines: Vec<Line> = Vec::new();
+    let hint = match &picker.stage {
+        AgentPickerStage::PickAgent => {
+            lines.push(Line::from(vec![
+                Span::styled("Find: ", Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("{}_", picker.query),
+                    Style::default().fg(Color::White),
+                ),
+                Span::styled(
+                    format!("  ({} of {})", picker.match_count(), picker.agent_count()),
+                    dim,
+                ),
+            ]));
+            lines.push(Line::from(""));
+
+            let name_width = picker.matches().map(|a| a.name.len()).max().unwrap_or(0);
+            // Title (2), tools (2) and hint (1) around the list
+            let visible_rows = inner_rows.saturating_sub(5);
+            let (start, end) = visible_window(picker.cursor, picker.match_count(), visible_rows);
+            for (i, agent) in picker.matches().enumerate().take(end).skip(start) {
+                let row = format!(
+                    "{:<name_width$}  {:<9}  {}",
+                    agent.name,
+                    if agent.is_read_only { "read-only" } else { "" },
+                    agent.description,
+                );
+                let style = if i == picker.cursor {
+                    Style::default().fg(Color::Black).bg(Color::Cyan)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                lines.push(Line::from(Span::styled(ellipsize(&row, text_width), style)));
+            }
+            if picker.match_count() == 0 {
+                lines.push(Line::from(Span::styled("No matching agents", dim)));
+            }
+
+            // Tools of the highlighted agent, above the hint
+            while lines.len() + 3 < inner_rows {
+                lines.push(Line::from(""));
+            }
+            if let Some(agent) = picker.selected() {
+                lines.push(Line::from(""));
+                lines.push(Line::from(vec![
+                    Span::styled("Tools: ", Style::default().fg(Color::Cyan)),
+                    Span::styled(
+                        ellipsize(&agent.tools.join(", "), text_width.saturating_sub(7)),
+                        dim,
+                    ),
+                ]));
+            }
+            "Type to filter, Up/Down to move, Enter to write a task, Esc to close"
+        }
+        AgentPickerStage::Compose { agent, task, error } => {
+            let mut title = vec![Span::styled(
+                format!("Task for {}", agent),
+                Style::default().fg(Color::Cyan),
+            )];
+            if let Some(info) = picker.selected() {
+                if info.is_read_only {
+                    title.push(Span::styled(" (read-only)", dim));
+                }
+                lines.push(Line::from(title));
+                lines.push(Line::from(Span::styled(
+                    ellipsize(&info.description, text_width),
+                    dim,
+                )));
+            } else {
+                lines.push(Line::from(title));
+            }
+            lines.push(Line::from(""));
+
+            // Leave room for the error and hint
+            let task_rows = inner_rows.saturating_sub(lines.len() + 3);
+            let (rows, cursor_row) = composer_rows(task, text_width);
+            let (start, end) = visible_window(cursor_row, rows.len(), task_rows);
+            lines.extend(rows.into_iter().take(end).skip(start));
+
+            if let Some(error) = error {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    error.clone(),
+                    Style::default().fg(Color::Red),
+                )));
+            }
+            "Enter for a new line, Ctrl+S to send, Esc to go back"
+        }
+    };
+
+    // Pin the hint to the last inner row
+    while lines.len() + 1 < inner_rows {
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(hint, dim)));
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" Agents ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
+/// The composer's task wrapped to `width` columns, with a `|` marker at the
+/// cursor, and the index of the row holding it.
+fn composer_rows(task: &tui_input::Input, width: usize) -> (Vec<Line<'static>>, usize) {
+    let value = task.value();
+    let cursor = line_edit::byte_cursor(task);
+    let text = Style::default().fg(Color::White);
+    // One column stays free for the marker
+    let wrap_width = width.saturating_sub(1);
+
+    let mut rows = Vec::new();
+    let mut cursor_row = None;
+    let mut line_start = 0;
+    for line in value.split('\n') {
+        let line_end = line_start + line.len();
+        for range in wrap_input(line, wrap_width, wrap_width) {
+            let (start, end) = (line_start + range.start, line_start + range.end);
+            let holds_cursor = cursor_row.is_none()
+                && start <= cursor
+                && (cursor < end || (cursor == end && end == line_end));
+            if holds_cursor {
+                cursor_row = Some(rows.len());
+                rows.push(Line::from(vec![
+                    Span::styled(value[start..cursor].to_string(), text),
+                    Span::styled("|", Style::default().fg(Color::DarkGray)),
+                    Span::styled(value[cursor..end].to_string(), text),
+                ]));
+            } else {
+                rows.push(Line::from(Span::styled(value[start..end].to_string(), text)));
+            }
+        }
+        line_start = line_end + 1;
+    }
+    (rows, cursor_row.unwrap_or(0))
+}
+
+/// Render the `/summaries` overlay.
+fn render_summaries_overlay(frame: &mut Frame, view: &SummariesView) {
+    let area = frame.area();
+
+    let overlay_width = 90u16.min(area.width.saturating_sub(4));
+    let overlay_height = 30u16.min(area.height.saturating_sub(2));
+    let x = (area.width.saturating_sub(overlay_width)) / 2;
+    let y = (area.height.saturating_sub(overlay_height)) / 2;
+    let overlay_area = Rect::new(x, y, overlay_width, overlay_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    let text_width = (overlay_width as usize).saturating_sub(2);
+    let clip = |s: &str| -> String {
+        if display_width(s) > text_width {
+            let mut clipped =
+                truncate_width(s, text_width.saturating_sub(1)).to_string();
+            clipped.push('…');
+            clipped
+        } else {
+            s.to_string()
+        }
+    };
+    let inner_rows = (overlay_height as usize).saturating_sub(2);
+
+    let mut lines: Vec<Line> = Vec::new();
+    let hint = match &view.stage {
+        SummariesStage::List => {
+            // Leave room for the blank line and hint
+            let visible_rows = inner_rows.saturating_sub(2);
+            let (start, end) = visible_window(view.cursor, view.summaries.len(), visible_rows);
+            for (i, summary) in view.summaries.iter().enumerate().take(end).skip(start) {
+                let first = summary
+                    .observations
+                    .lines()
+                    .find(|l| !l.trim().is_empty())
+                    .unwrap_or("");
+                let row = format!(
+                    "#{:<3} {:>4} msgs {:>9}  {}",
+                    summary.number,
+                    summary.message_count,
+                    crate::chat::format_bytes(summary.message_bytes),
+                    first.trim()
+                );
+                let style = if i == view.cursor {
+                    Style::default().fg(Color::Black).bg(Color::Cyan)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                lines.push(Line::from(Span::styled(clip(&row), style)));
+            }
+            "Up/Down to move, Enter to expand, Esc to close"
+        }
+        SummariesStage::Expanded { originals, cursor } => {
+            let Some(summary) = view.selected() else {
+                return;
+            };
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "Summary #{} of {} messages ({})",
+                    summary.number,
+                    summary.message_count,
+                    crate::chat::format_bytes(summary.message_bytes)
+                ),
+                Style::default().fg(Color::Cyan),
+            )));
+            // Summary text gets up to half the rows, originals the rest
+            let summary_rows = inner_rows.saturating_sub(4) / 2;
+            let summary_lines: Vec<&str> = summary.observations.lines().collect();
+            for line in summary_lines.iter().take(summary_rows) {
+                lines.push(Line::from(clip(line)));
+            }
+            if summary_lines.len() > summary_rows {
+                lines.push(Line::from(Span::styled(
+                    format!("… {} more lines", summary_lines.len() - summary_rows),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            lines.push(Line::from(""));
+            match originals {
+                Some(_) => {
+                    lines.push(Line::from(Span::styled(
+                        "Original messages:",
+                        Style::default().fg(Color::Cyan),
+                    )));
+                    let rows = view.original_rows();
+                    let visible_rows = inner_rows.saturating_sub(lines.len() + 2);
+                    let (start, end) = visible_window(*cursor, rows.len(), visible_rows);
+                    for (i, row) in rows.iter().enumerate().take(end).skip(start) {
+                        let style = if i == *cursor {
+                            Style::default().fg(Color::Black).bg(Color::Cyan)
+                        } else {
+                            Style::default().fg(Color::White)
+                        };
+                        lines.push(Line::from(Span::styled(clip(row), style)));
+                    }
+                    "Up/Down to move, r to re-inject with the next message, Esc to go back"
+                }
+                None => {
+                    lines.push(Line::from(Span::styled(
+                        "Original messages were not kept ([session_memory] spill_to_disk is off)",
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                    "Esc to go back"
+                }
+            }
+        }
+    };
+
+    // Pin the hint to the last inner row
+    while lines.len() + 1 < inner_rows {
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(
+        hint,
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" Compacted History ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
+/// Render the `/diff` pager: the session's changes, colored by line kind.
+fn render_diff_overlay(frame: &mut Frame, view: &DiffView) {
+    let area = frame.area();
+
+    // Nearly full screen: diffs need the width
+    let overlay_width = area.width.saturating_sub(4);
+    let overlay_height = area.height.saturating_sub(2);
+    let overlay_area = Rect::new(2, 1, overlay_width, overlay_height);
+
+    frame.render_widget(Clear, overlay_area);
+
+    // Leave the last inner row for the hint
+    let visible_rows = (overlay_height as usize).saturating_sub(3);
+    let mut lines: Vec<Line> = view
+        .lines
+        .iter()
+        .skip(view.scroll)
+        .take(visible_rows)
+        .map(|line| {
+            let style = match line.kind {
+                DiffLineKind::File => Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+                DiffLineKind::Meta => Style::default().fg(Color::DarkGray),
+                DiffLineKind::Hunk => Style::default().fg(Color::Cyan),
+                DiffLineKind::Added => Style::default().fg(Color::Green),
+                DiffLineKind::Removed => Style::default().fg(Color::Red),
+                DiffLineKind::Context => Style::default().fg(Color::White),
+            };
+            Line::from(Span::styled(line.text.as_str(), style))
+        })
+        .collect();
+    while lines.len() < visible_rows {
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(
+        format!(
+            "line {}/{}  Up/Down/PgUp/PgDn to scroll, n/p next/previous file, Esc to close",
+            (view.scroll + 1).min(view.lines.len()),
+            view.lines.len()
+        ),
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let title = match view.current_file() {
+        Some(path) => format!(" Session Changes: {} — {} ", view.title(), path),
+        None => format!(" Session Changes: {} ", view.title()),
+    };
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .style(Style::default().bg(Color::Black));
+
+    frame.render_widget(paragraph, overlay_area);
+}
+
 /// Build (title, row-strings, cursor) for the picker stage.
 fn build_profiles_rows(stage: &ProfilesPickerStage) -> (String, Vec<String>, usize) {
     match stage {
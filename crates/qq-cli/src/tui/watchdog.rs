@@ -0,0 +1,222 @@
+//! Liveness watchdog for running responses.
+//!
+//! An agent occasionally deadlocks — a tool waiting on input that never
+//! comes, a server that holds the connection open — and the only sign is a
+//! spinner that keeps spinning. The watchdog records when each agent last
+//! produced tokens or tool activity, and once nothing has moved for
+//! `[tui] stall_warning_secs` it reports a [`Stall`] naming the agent that
+//! was last active and the tool it is waiting on. The TUI then offers to
+//! cancel, extend (another full period before warning again), or nudge (a
+//! steering note asking the agent to report what it is stuck on).
+
+use std::time::{Duration, Instant};
+
+use crate::event_bus::AgentEvent;
+
+use super::events::StreamEvent;
+
+/// Steering note sent by the nudge option.
+pub const NUDGE: &str = "You have made no visible progress for a while. If you are waiting on \
+something that will not finish (a hung command, a prompt for input, a stalled download), stop \
+waiting, say what you are stuck on, and continue another way or hand back to the user.";
+
+/// Activity of one agent (`None` for the primary).
+#[derive(Debug)]
+struct AgentActivity {
+    agent: Option<String>,
+    last: Instant,
+    /// Tools started and not yet finished, oldest first
+    running: Vec<String>,
+}
+
+/// Tracks progress of the running response.
+#[derive(Debug)]
+pub struct Watchdog {
+    /// Silence that counts as a stall; `None` disables the watchdog
+    stall_after: Option<Duration>,
+    /// Start of the current response or the last extension
+    since: Instant,
+    agents: Vec<AgentActivity>,
+    /// Whether the current stall has been reported
+    warned: bool,
+}
+
+/// A response that has stopped making progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stall {
+    /// Agent active last; `None` for the primary
+    pub agent: Option<String>,
+    pub idle: Duration,
+    /// Tool that agent is waiting on, if any
+    pub waiting_on: Option<String>,
+}
+
+impl Stall {
+    /// One-line description for the warning.
+    pub fn message(&self) -> String {
+        let agent = self.agent.as_deref().unwrap_or("The assistant");
+        let minutes = self.idle.as_secs() / 60;
+        let idle = if minutes > 0 {
+            format!("{}m", minutes)
+        } else {
+            format!("{}s", self.idle.as_secs())
+        };
+        match self.waiting_on {
+            Some(ref tool) => format!(
+                "{} has made no progress for {} (waiting on {})",
+                agent, idle, tool
+            ),
+            None => format!("{} has made no progress for {}", agent, idle),
+        }
+    }
+}
+
+impl Watchdog {
+    pub fn new(stall_after: Option<Duration>) -> Self {
+        Self {
+            stall_after,
+            since: Instant::now(),
+            agents: Vec::new(),
+            warned: false,
+        }
+    }
+
+    /// Forget all activity; the next response starts a fresh clock.
+    pub fn reset(&mut self, now: Instant) {
+        self.since = now;
+        self.agents.clear();
+        self.warned = false;
+    }
+
+    /// Tokens or other progress from `agent`.
+    pub fn progress(&mut self, agent: Option<&str>, now: Instant) {
+        self.activity(agent, now);
+    }
+
+    /// `agent` started running `tool`.
+    pub fn tool_started(&mut self, agent: Option<&str>, tool: &str, now: Instant) {
+        self.activity(agent, now).running.push(tool.to_string());
+    }
+
+    /// `agent` finished a tool (the oldest still running).
+    pub fn tool_finished(&mut self, agent: Option<&str>, now: Instant) {
+        let activity = self.activity(agent, now);
+        if !activity.running.is_empty() {
+            activity.running.remove(0);
+        }
+    }
+
+    /// Record an event from the primary agent's stream.
+    pub fn on_stream_event(&mut self, event: &StreamEvent, now: Instant) {
+        match event {
+            StreamEvent::ToolExecuting { name, .. } => {
+                let display = qq_core::ToolRef::from_wire_name(name).to_string();
+                self.tool_started(None, &display, now);
+            }
+            StreamEvent::ToolComplete { .. } => self.tool_finished(None, now),
+            _ => self.progress(None, now),
+        }
+    }
+
+    /// Record an event from a sub-agent.
+    pub fn on_agent_event(&mut self, event: &AgentEvent, now: Instant) {
+        let agent = Some(event.agent_name());
+        match event {
+            AgentEvent::ToolStart { tool_name, .. } => {
+                let display = qq_core::ToolRef::from_wire_name(tool_name).to_string();
+                self.tool_started(agent, &display, now);
+            }
+            AgentEvent::ToolComplete { .. } => self.tool_finished(agent, now),
+            _ => self.progress(agent, now),
+        }
+    }
+
+    /// Wait another full period before warning again.
+    pub fn extend(&mut self, now: Instant) {
+        self.since = now;
+        self.warned = false;
+    }
+
+    /// The stall, the first time the silence passes the threshold. Returns
+    /// `None` again until there is progress or an extension.
+    pub fn check(&mut self, now: Instant) -> Option<Stall> {
+        let stall_after = self.stall_after?;
+        if self.warned {
+            return None;
+        }
+        let latest = self.agents.iter().max_by_key(|a| a.last);
+        let last_seen = latest.map_or(self.since, |a| a.last);
+        if now.saturating_duration_since(last_seen.max(self.since)) < stall_after {
+            return None;
+        }
+        self.warned = true;
+        Some(Stall {
+            agent: latest.and_then(|a| a.agent.clone()),
+            idle: now.saturating_duration_since(last_seen),
+            waiting_on: latest.and_then(|a| a.running.last().cloned()),
+        })
+    }
+
+    fn activity(&mut self, agent: Option<&str>, now: Instant) -> &mut AgentActivity {
+        self.warned = false;
+        let index = match self.agents.iter().position(|a| a.agent.as_deref() == agent) {
+            Some(index) => index,
+            None => {
+                self.agents.push(AgentActivity {
+                    agent: agent.map(str::to_string),
+                    last: now,
+                    running: Vec::new(),
+                });
+                self.agents.len() - 1
+            }
+        };
+        let activity = &mut self.agents[index];
+        activity.last = now;
+        activity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warns_once_per_stall() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut watchdog = Watchdog::new(Some(Duration::from_secs(300)));
+        watchdog.reset(start);
+
+        watchdog.progress(None, at(10));
+        watchdog.tool_started(Some("coder"), "run", at(20));
+        watchdog.tool_started(Some("coder"), "run", at(30));
+        watchdog.tool_finished(Some("coder"), at(40));
+        assert_eq!(watchdog.check(at(300)), None);
+
+        let stall = watchdog.check(at(400)).unwrap();
+        assert_eq!(stall.agent.as_deref(), Some("coder"));
+        assert_eq!(stall.waiting_on.as_deref(), Some("run"));
+        assert_eq!(
+            stall.message(),
+            "coder has made no progress for 6m (waiting on run)"
+        );
+        // Reported once
+        assert_eq!(watchdog.check(at(500)), None);
+
+        // Extending waits a full period again
+        watchdog.extend(at(500));
+        assert_eq!(watchdog.check(at(700)), None);
+        let stall = watchdog.check(at(800)).unwrap();
+        assert_eq!(stall.idle, Duration::from_secs(760));
+
+        // Progress clears the stall
+        watchdog.progress(None, at(900));
+        assert_eq!(watchdog.check(at(1000)), None);
+        let stall = watchdog.check(at(1200)).unwrap();
+        assert_eq!(stall.agent, None);
+        assert_eq!(stall.message(), "The assistant has made no progress for 5m");
+
+        let mut disabled = Watchdog::new(None);
+        assert_eq!(disabled.check(at(10_000)), None);
+    }
+}
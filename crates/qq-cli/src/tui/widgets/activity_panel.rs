@@ -0,0 +1,211 @@
+//! Sub-agent activity side pane.
+//!
+//! Renders the activity tree with per-node status, elapsed time, and token
+//! counts, and below it the selected agent's report once it has finished,
+//! or its latest thinking while it runs.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+
+use crate::agents::AgentReport;
+use crate::tui::activity::{format_elapsed, ActivityKind, ActivityStatus, ActivityTree};
+
+/// Side pane showing the delegation tree for the current turn.
+pub struct ActivityPanel<'a> {
+    tree: &'a ActivityTree,
+}
+
+impl<'a> ActivityPanel<'a> {
+    pub fn new(tree: &'a ActivityTree) -> Self {
+        Self { tree }
+    }
+
+    /// Status marker and style for a node.
+    fn marker(status: ActivityStatus) -> (&'static str, Style) {
+        match status {
+            ActivityStatus::Running => (
+                "●",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            ActivityStatus::Done => ("✓", Style::default().fg(Color::Green)),
+            ActivityStatus::Failed => ("✗", Style::default().fg(Color::Red)),
+        }
+    }
+
+    /// Lines for a finished agent's report: summary, list sections, and the
+    /// self-assessment with any review flag.
+    fn report_lines(name: &str, report: &AgentReport) -> Vec<Line<'static>> {
+        let heading = Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD);
+        let mut lines = vec![Line::from(Span::styled(
+            format!("─ {} report ", name),
+            Style::default().fg(Color::Cyan),
+        ))];
+        lines.extend(report.summary.lines().map(|l| Line::from(l.to_string())));
+        for (title, items) in report.sections() {
+            lines.push(Line::from(Span::styled(title, heading)));
+            lines.extend(items.iter().map(|item| {
+                Line::from(vec![
+                    Span::styled("• ", Style::default().fg(Color::DarkGray)),
+                    Span::raw(item.clone()),
+                ])
+            }));
+        }
+        if let Some(ref changes) = report.changes {
+            lines.push(Line::from(vec![
+                Span::styled("Changes ", heading),
+                Span::raw(changes.summary()),
+            ]));
+            lines.extend(changes.files.iter().map(|file| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} ", file.kind.letter()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(format!("{} +{} -{}", file.path, file.added, file.removed)),
+                ])
+            }));
+        }
+        lines.push(Line::from(vec![
+            Span::styled("Confidence ", heading),
+            Span::raw(report.confidence.to_string()),
+            Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+            Span::raw(report.completeness.to_string()),
+        ]));
+        if let Some(ref review) = report.review {
+            lines.push(Line::from(Span::styled(
+                review.clone(),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        lines
+    }
+
+    fn format_tokens(tokens: u32) -> String {
+        if tokens >= 1000 {
+            format!("{:.1}k tok", tokens as f64 / 1000.0)
+        } else {
+            format!("{} tok", tokens)
+        }
+    }
+}
+
+impl Widget for ActivityPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title(Span::styled(" Activity ", Style::default().fg(Color::Cyan)))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let selected = self.tree.selected();
+        let selected_node = self.tree.node(selected);
+        let report = selected_node.and_then(|n| n.report.as_ref());
+        let thinking = selected_node
+            .map(|n| n.thinking.trim())
+            .filter(|t| !t.is_empty());
+
+        // Give the excerpt the bottom ~40% when there is room for both
+        let (tree_area, excerpt_area) = match (report, thinking) {
+            (Some(_), _) | (_, Some(_)) if inner.height >= 8 => {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(3), Constraint::Percentage(40)])
+                    .split(inner);
+                (chunks[0], Some(chunks[1]))
+            }
+            _ => (inner, None),
+        };
+
+        let rows = self.tree.rows();
+        let lines: Vec<Line> = rows
+            .iter()
+            .filter_map(|&index| self.tree.node(index).map(|node| (index, node)))
+            .map(|(index, node)| {
+                let (marker, marker_style) = Self::marker(node.status);
+                let name_style = match node.kind {
+                    ActivityKind::Agent => Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                    ActivityKind::Tool => Style::default().fg(Color::Gray),
+                };
+                let mut spans = vec![
+                    Span::raw("  ".repeat(node.depth)),
+                    Span::styled(marker, marker_style),
+                    Span::raw(" "),
+                    Span::styled(node.name.clone(), name_style),
+                ];
+                let mut detail = format!(" {}", format_elapsed(node.elapsed()));
+                if node.kind == ActivityKind::Agent {
+                    if node.iteration > 0 {
+                        detail.push_str(&format!(" #{}", node.iteration));
+                    }
+                    if node.total_tokens() > 0 {
+                        detail.push_str(&format!(" {}", Self::format_tokens(node.total_tokens())));
+                    }
+                }
+                if let Some(ref changes) = node.changes {
+                    detail.push_str(&format!(" {}", changes));
+                }
+                spans.push(Span::styled(detail, Style::default().fg(Color::DarkGray)));
+
+                let line = Line::from(spans);
+                if index == selected {
+                    line.style(Style::default().bg(Color::DarkGray))
+                } else {
+                    line
+                }
+            })
+            .collect();
+
+        // Keep the selected row in view
+        let selected_row = rows.iter().position(|&i| i == selected).unwrap_or(0);
+        let height = tree_area.height as usize;
+        let scroll_offset = (selected_row + 1).saturating_sub(height) as u16;
+        Paragraph::new(lines)
+            .scroll((scroll_offset, 0))
+            .render(tree_area, buf);
+
+        let (Some(excerpt_area), Some(node)) = (excerpt_area, selected_node) else {
+            return;
+        };
+        if let Some(report) = report {
+            // The summary leads, so show the report from the top
+            Paragraph::new(Self::report_lines(&node.name, report))
+                .wrap(Wrap { trim: false })
+                .render(excerpt_area, buf);
+        } else if let Some(thinking) = thinking {
+            let title = Line::from(Span::styled(
+                format!("─ {} thinking ", node.name),
+                Style::default().fg(Color::Cyan),
+            ));
+            let mut lines = vec![title];
+            lines.extend(thinking.lines().map(|l| {
+                Line::from(Span::styled(
+                    l.to_string(),
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                ))
+            }));
+
+            // Show the tail: the newest reasoning is the interesting part
+            let width = excerpt_area.width.max(1) as usize;
+            let wrapped: usize = lines.iter().map(|l| l.width().max(1).div_ceil(width)).sum();
+            let scroll_offset = wrapped.saturating_sub(excerpt_area.height as usize) as u16;
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .scroll((scroll_offset, 0))
+                .render(excerpt_area, buf);
+        }
+    }
+}
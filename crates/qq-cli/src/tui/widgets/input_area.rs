@@ -1,6 +1,8 @@
 //! Input area widget with line editing support.
 
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
 use ratatui::{
@@ -10,9 +12,13 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Widget},
 };
-use unicode_width::UnicodeWidthChar;
 use serde::{Deserialize, Serialize};
 use tui_input::Input;
+use unicode_segmentation::UnicodeSegmentation;
+
+use qq_core::text::display_width;
+
+use qq_tools::{write_atomically, FileLock};
 
 /// Maximum number of history entries to persist
 const MAX_HISTORY_ENTRIES: usize = 1000;
@@ -57,7 +63,6 @@ impl Widget for InputArea<'_> {
 
         let prompt_len = self.prompt.len();
         let input_value = self.input.value();
-        let cursor_pos = self.input.visual_cursor();
 
         let prompt_style = if self.is_active {
             Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
@@ -76,39 +81,19 @@ impl Widget for InputArea<'_> {
         }
 
         // Split input into wrapped lines
+        let rows = wrap_input(input_value, first_line_width, full_line_width);
         let mut lines: Vec<Line> = Vec::new();
-        let mut remaining = input_value;
-        let mut is_first_line = true;
-
-        while !remaining.is_empty() || is_first_line {
-            let line_width = if is_first_line { first_line_width } else { full_line_width };
-
-            let (line_text, rest) = {
-                let mut width = 0;
-                let mut split_byte = remaining.len();
-                for (byte_idx, ch) in remaining.char_indices() {
-                    let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
-                    if width + ch_width > line_width {
-                        split_byte = byte_idx;
-                        break;
-                    }
-                    width += ch_width;
-                }
-                remaining.split_at(split_byte)
-            };
-
-            if is_first_line {
+        for (i, row) in rows.iter().enumerate() {
+            let line_text = input_value[row.clone()].to_string();
+            if i == 0 {
                 lines.push(Line::from(vec![
                     Span::styled(self.prompt, prompt_style),
-                    Span::styled(line_text.to_string(), input_style),
+                    Span::styled(line_text, input_style),
                 ]));
-                is_first_line = false;
             } else {
-                lines.push(Line::from(Span::styled(line_text.to_string(), input_style)));
+                lines.push(Line::from(Span::styled(line_text, input_style)));
             }
 
-            remaining = rest;
-
             // Safety: break if we've rendered enough lines for the area
             if lines.len() >= inner.height.saturating_sub(1) as usize {
                 break;
@@ -135,26 +120,28 @@ impl Widget for InputArea<'_> {
         // Draw cursor if active
         if self.is_active {
             // Calculate cursor position accounting for wrapped lines
-            let (cursor_line, cursor_col) = if cursor_pos <= first_line_width {
-                (0, prompt_len + cursor_pos)
-            } else {
-                let pos_after_first = cursor_pos - first_line_width;
-                let line_num = 1 + pos_after_first / full_line_width;
-                let col = pos_after_first % full_line_width;
-                (line_num, col)
-            };
+            let cursor = crate::tui::line_edit::byte_cursor(self.input);
+            let cursor_line = rows
+                .iter()
+                .rposition(|row| row.start <= cursor)
+                .unwrap_or(0);
+            let row_start = rows[cursor_line].start;
+            let mut cursor_col = display_width(&input_value[row_start..cursor]);
+            if cursor_line == 0 {
+                cursor_col += prompt_len;
+            }
 
             let cursor_y = inner.y + cursor_line as u16;
             let cursor_x = inner.x + cursor_col as u16;
 
             if cursor_y < inner.bottom().saturating_sub(1) && cursor_x < inner.right() {
-                // Get the character at cursor position or use space
-                let cursor_char = input_value.chars().nth(cursor_pos).unwrap_or(' ');
+                // Get the grapheme at cursor position or use space
+                let cursor_grapheme = input_value[cursor..].graphemes(true).next().unwrap_or(" ");
 
                 buf.set_string(
                     cursor_x,
                     cursor_y,
-                    cursor_char.to_string(),
+                    cursor_grapheme,
                     Style::default()
                         .bg(Color::White)
                         .fg(Color::Black),
@@ -177,6 +164,36 @@ impl Widget for InputArea<'_> {
     }
 }
 
+/// Byte ranges of `value` per display row: the first row is `first_width`
+/// columns (it follows the prompt), the rest `full_width`.
+///
+/// Rows break between grapheme clusters by display width, so a wide CJK
+/// character moves whole to the next row. A full last row is followed by an
+/// empty one, where the cursor goes after the last character.
+pub fn wrap_input(value: &str, first_width: usize, full_width: usize) -> Vec<Range<usize>> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut width = 0;
+    let mut limit = first_width.max(1);
+    for (i, grapheme) in value.grapheme_indices(true) {
+        let grapheme_width = display_width(grapheme);
+        // A row always takes at least one grapheme, however narrow it is
+        if width + grapheme_width > limit && i > start {
+            rows.push(start..i);
+            start = i;
+            width = 0;
+            limit = full_width.max(1);
+        }
+        width += grapheme_width;
+    }
+    let full = width >= limit;
+    rows.push(start..value.len());
+    if full {
+        rows.push(value.len()..value.len());
+    }
+    rows
+}
+
 /// A single history entry with metadata
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HistoryEntry {
@@ -223,9 +240,9 @@ impl InputHistory {
         }
     }
 
-    /// Returns the default history file path: ~/.config/qq/input_history.json
+    /// Returns the default history file path: ~/.local/state/qq/input_history.json
     fn history_file_path() -> Option<PathBuf> {
-        dirs::config_dir().map(|p| p.join("qq").join("input_history.json"))
+        crate::paths::input_history_file()
     }
 
     /// Load history from the default path, returning empty history on any error
@@ -235,51 +252,40 @@ impl InputHistory {
 
     /// Load history from a specific path (for testing)
     pub fn load_from_path(path: Option<PathBuf>) -> Self {
-        let Some(path) = path else {
+        let Some(entries) = path.as_deref().and_then(read_entries) else {
             return Self::new();
         };
 
-        let content = match std::fs::read_to_string(&path) {
-            Ok(c) => c,
-            Err(_) => return Self::new(),
-        };
-
-        let persisted: PersistedHistory = match serde_json::from_str(&content) {
-            Ok(p) => p,
-            Err(_) => return Self::new(),
-        };
-
         Self {
-            entries: persisted.entries,
+            entries,
             position: None,
             current_input: String::new(),
         }
     }
 
-    /// Save history to the default path, silently ignoring errors
-    pub fn save(&self) {
-        self.save_to_path(Self::history_file_path());
+    /// Save history to the default path
+    pub fn save(&self) -> std::io::Result<()> {
+        self.save_to_path(Self::history_file_path())
     }
 
     /// Save history to a specific path (for testing)
-    pub fn save_to_path(&self, path: Option<PathBuf>) {
+    ///
+    /// Other sessions may have saved since this one loaded, so under the
+    /// file lock our entries are merged into the file's rather than
+    /// replacing them.
+    pub fn save_to_path(&self, path: Option<PathBuf>) -> std::io::Result<()> {
         let Some(path) = path else {
-            return;
+            return Ok(());
         };
 
-        // Create parent directory if needed
-        if let Some(parent) = path.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
-
+        let _lock = FileLock::acquire(&path)?;
+        let on_disk = read_entries(&path).unwrap_or_default();
         let persisted = PersistedHistory {
             version: 1,
-            entries: self.entries.clone(),
+            entries: merge_entries(on_disk, &self.entries),
         };
-
-        if let Ok(content) = serde_json::to_string_pretty(&persisted) {
-            let _ = std::fs::write(&path, content);
-        }
+        let content = serde_json::to_string_pretty(&persisted).map_err(std::io::Error::other)?;
+        write_atomically(&path, content.as_bytes())
     }
 
     pub fn add(&mut self, entry: String) {
@@ -352,6 +358,30 @@ impl InputHistory {
     }
 }
 
+/// Entries of a persisted history file. `None` when missing or corrupt.
+fn read_entries(path: &Path) -> Option<Vec<HistoryEntry>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let persisted: PersistedHistory = serde_json::from_str(&content).ok()?;
+    Some(persisted.entries)
+}
+
+/// Union of two histories in timestamp order, keeping the newest copy of
+/// each entry and the newest [`MAX_HISTORY_ENTRIES`].
+fn merge_entries(on_disk: Vec<HistoryEntry>, ours: &[HistoryEntry]) -> Vec<HistoryEntry> {
+    let mut merged = on_disk;
+    merged.extend(ours.iter().cloned());
+    merged.sort_by_key(|e| e.timestamp);
+    let mut seen = HashSet::new();
+    let mut kept: Vec<HistoryEntry> = merged
+        .into_iter()
+        .rev()
+        .filter(|e| seen.insert(e.text.clone()))
+        .take(MAX_HISTORY_ENTRIES)
+        .collect();
+    kept.reverse();
+    kept
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -429,7 +459,7 @@ mod tests {
         let mut history = InputHistory::new();
         history.add("query one".to_string());
         history.add("query two".to_string());
-        history.save_to_path(Some(path.clone()));
+        history.save_to_path(Some(path.clone())).unwrap();
 
         // Load history
         let loaded = InputHistory::load_from_path(Some(path));
@@ -439,6 +469,29 @@ mod tests {
         assert_eq!(loaded.entries[1].text, "query two");
     }
 
+    #[test]
+    fn test_save_merges_concurrent_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test_history.json");
+
+        let mut first = InputHistory::new();
+        first.add("shared".to_string());
+        first.save_to_path(Some(path.clone())).unwrap();
+
+        // Two sessions start from the same file and both add entries
+        let mut second = InputHistory::load_from_path(Some(path.clone()));
+        let mut third = InputHistory::load_from_path(Some(path.clone()));
+        second.add("from second".to_string());
+        third.add("from third".to_string());
+        third.add("shared".to_string());
+        second.save_to_path(Some(path.clone())).unwrap();
+        third.save_to_path(Some(path.clone())).unwrap();
+
+        let loaded = InputHistory::load_from_path(Some(path));
+        let texts: Vec<&str> = loaded.entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["from second", "from third", "shared"]);
+    }
+
     #[test]
     fn test_load_missing_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -459,6 +512,23 @@ mod tests {
         assert!(history.entries.is_empty());
     }
 
+    #[test]
+    fn test_wrap_input_by_display_width() {
+        let rows = |value: &str, first, full| -> Vec<String> {
+            wrap_input(value, first, full)
+                .into_iter()
+                .map(|r| value[r].to_string())
+                .collect()
+        };
+        assert_eq!(rows("", 5, 10), vec![""]);
+        assert_eq!(rows("abc", 5, 10), vec!["abc"]);
+        // A wide char that would straddle the edge moves to the next row
+        assert_eq!(rows("日本語です", 5, 7), vec!["日本", "語です"]);
+        // A full last row leaves an empty row for the cursor
+        assert_eq!(rows("abcde", 5, 10), vec!["abcde", ""]);
+        assert_eq!(rows("e\u{301}e\u{301}", 1, 1), vec!["e\u{301}", "e\u{301}", ""]);
+    }
+
     #[test]
     fn test_empty_input_not_added() {
         let mut history = InputHistory::new();
@@ -0,0 +1,108 @@
+//! Log pane tailing captured tracing events.
+//!
+//! Shows the newest events from the [`LogBuffer`] that pass the pane's level
+//! and agent filters, one per row, so provider errors and retries can be
+//! read without leaving the TUI.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+use tracing::Level;
+
+use super::thinking_panel::agent_color;
+use crate::log_capture::LogBuffer;
+
+/// Bottom pane with the tail of the debug log.
+pub struct LogPanel<'a> {
+    buffer: &'a LogBuffer,
+    level: Level,
+    /// `Some(agent)` shows only that agent's events (`None`: the primary agent)
+    agent: Option<&'a Option<String>>,
+    primary: &'a str,
+}
+
+impl<'a> LogPanel<'a> {
+    pub fn new(buffer: &'a LogBuffer, primary: &'a str) -> Self {
+        Self {
+            buffer,
+            level: Level::DEBUG,
+            agent: None,
+            primary,
+        }
+    }
+
+    /// Hide events less severe than `level`.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Show only one agent's events.
+    pub fn agent(mut self, agent: Option<&'a Option<String>>) -> Self {
+        self.agent = agent;
+        self
+    }
+
+    fn level_style(level: Level) -> Style {
+        match level {
+            Level::ERROR => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            Level::WARN => Style::default().fg(Color::Yellow),
+            Level::INFO => Style::default().fg(Color::Green),
+            Level::DEBUG => Style::default().fg(Color::Blue),
+            _ => Style::default().fg(Color::DarkGray),
+        }
+    }
+}
+
+impl Widget for LogPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let agent = match self.agent {
+            None => "all agents".to_string(),
+            Some(agent) => agent.as_deref().unwrap_or(self.primary).to_string(),
+        };
+        let title = format!(
+            " Logs: {}+ · {} [Ctrl+L hide | Alt+L level | Alt+A agent] ",
+            self.level, agent
+        );
+        let block = Block::default()
+            .title(Span::styled(title, Style::default().fg(Color::Cyan)))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray));
+
+        let rows = area.height.saturating_sub(2) as usize;
+        let dim = Style::default().fg(Color::DarkGray);
+        let lines: Vec<Line> = self
+            .buffer
+            .tail(self.level, self.agent, rows)
+            .into_iter()
+            .map(|record| {
+                let mut spans = vec![
+                    Span::styled(record.time.format("%H:%M:%S ").to_string(), dim),
+                    Span::styled(format!("{:<5} ", record.level), Self::level_style(record.level)),
+                ];
+                if let Some(ref agent) = record.agent {
+                    spans.push(Span::styled(
+                        format!("{}│ ", agent),
+                        Style::default().fg(agent_color(Some(agent))),
+                    ));
+                }
+                spans.push(Span::styled(format!("{}: ", record.target), dim));
+                // One row per event; embedded newlines would break the tail
+                spans.push(Span::raw(record.message.replace('\n', " ⏎ ")));
+                Line::from(spans)
+            })
+            .collect();
+
+        if lines.is_empty() {
+            Paragraph::new(Line::from(Span::styled("No events yet", dim)))
+                .block(block)
+                .render(area, buf);
+        } else {
+            Paragraph::new(lines).block(block).render(area, buf);
+        }
+    }
+}
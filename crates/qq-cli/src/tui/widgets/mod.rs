@@ -1,11 +1,19 @@
 //! TUI Widget components.
 
+pub mod activity_panel;
 pub mod content_area;
 pub mod input_area;
+pub mod log_panel;
+pub mod plan_panel;
 pub mod status_bar;
+pub mod suggestions_bar;
 pub mod thinking_panel;
 
+pub use activity_panel::ActivityPanel;
 pub use content_area::ContentArea;
-pub use input_area::{InputArea, InputHistory};
+pub use input_area::{wrap_input, InputArea, InputHistory};
+pub use log_panel::LogPanel;
+pub use plan_panel::PlanPanel;
 pub use status_bar::StatusBar;
+pub use suggestions_bar::SuggestionsBar;
 pub use thinking_panel::{ThinkingPanel, ToolNotification, ToolNotificationStatus};
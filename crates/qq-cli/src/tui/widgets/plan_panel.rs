@@ -0,0 +1,115 @@
+//! Live plan checklist panel.
+//!
+//! Renders the plan submitted via `submit_plan` as a checklist whose step
+//! states are read from the task board, so it updates as sub-agents finish.
+//! Steps invalidated by `revise_plan` stay listed, struck through.
+
+use qq_tools::tasks::TaskStatus;
+use qq_tools::PlanSnapshot;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+/// Checklist panel for the active plan.
+pub struct PlanPanel<'a> {
+    plan: &'a PlanSnapshot,
+}
+
+impl<'a> PlanPanel<'a> {
+    pub fn new(plan: &'a PlanSnapshot) -> Self {
+        Self { plan }
+    }
+
+    /// Number of content lines the panel wants (one per step).
+    pub fn content_lines(plan: &PlanSnapshot) -> u16 {
+        plan.steps.len().max(1) as u16
+    }
+
+    /// Checkbox marker and style for a step status.
+    fn marker(status: &TaskStatus) -> (&'static str, Style) {
+        match status {
+            TaskStatus::Todo => ("[ ]", Style::default().fg(Color::DarkGray)),
+            TaskStatus::InProgress => (
+                "[~]",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            TaskStatus::Done => ("[x]", Style::default().fg(Color::Green)),
+            TaskStatus::Blocked => ("[!]", Style::default().fg(Color::Red)),
+        }
+    }
+}
+
+impl Widget for PlanPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let revision = match self.plan.revisions.len() {
+            0 => String::new(),
+            n => format!(", rev {}", n),
+        };
+        let title = format!(
+            " Plan: {} ({}/{}{}) ",
+            self.plan.goal,
+            self.plan.done_count(),
+            self.plan.active_count(),
+            revision
+        );
+        let block = Block::default()
+            .title(Span::styled(title, Style::default().fg(Color::Cyan)))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray));
+
+        let lines: Vec<Line> = self
+            .plan
+            .steps
+            .iter()
+            .map(|step| {
+                let (marker, marker_style) = if step.invalidated.is_some() {
+                    ("[-]", Style::default().fg(Color::DarkGray))
+                } else {
+                    Self::marker(&step.status)
+                };
+                let title_style = if step.invalidated.is_some() || step.status == TaskStatus::Done {
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::CROSSED_OUT)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                let mut spans = vec![
+                    Span::styled(marker, marker_style),
+                    Span::styled(format!(" #{} ", step.task_id), Style::default().fg(Color::DarkGray)),
+                    Span::styled(step.title.clone(), title_style),
+                ];
+                if let Some(ref owner) = step.owner {
+                    spans.push(Span::styled(
+                        format!(" ({})", owner),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                Line::from(spans)
+            })
+            .collect();
+
+        // Keep the first unfinished step in view when the plan is taller
+        // than the panel.
+        let inner_height = area.height.saturating_sub(2) as usize;
+        let first_open = self
+            .plan
+            .steps
+            .iter()
+            .position(|s| s.invalidated.is_none() && s.status != TaskStatus::Done)
+            .unwrap_or(0);
+        let max_offset = lines.len().saturating_sub(inner_height);
+        let scroll_offset = first_open.min(max_offset) as u16;
+
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((scroll_offset, 0))
+            .render(area, buf);
+    }
+}
@@ -29,6 +29,10 @@ pub struct StatusBar<'a> {
     agent_bytes: Option<(usize, usize)>,
     /// Session byte counts: (input_bytes, output_bytes)
     session_bytes: Option<(usize, usize)>,
+    /// Files read that git doesn't track (badge hidden at 0)
+    untracked_reads: usize,
+    /// Last request's latency and throughput, with its model's average
+    request_metrics: Option<&'a str>,
     /// Whether to show a top border (hidden when thinking panel is visible, to avoid double line)
     show_top_border: bool,
 }
@@ -48,6 +52,8 @@ impl<'a> StatusBar<'a> {
             agent_progress: None,
             agent_bytes: None,
             session_bytes: None,
+            untracked_reads: 0,
+            request_metrics: None,
             show_top_border: true,
         }
     }
@@ -101,6 +107,16 @@ impl<'a> StatusBar<'a> {
         self
     }
 
+    pub fn untracked_reads(mut self, count: usize) -> Self {
+        self.untracked_reads = count;
+        self
+    }
+
+    pub fn request_metrics(mut self, metrics: Option<&'a str>) -> Self {
+        self.request_metrics = metrics;
+        self
+    }
+
     pub fn show_top_border(mut self, show: bool) -> Self {
         self.show_top_border = show;
         self
@@ -161,6 +177,8 @@ impl Widget for StatusBar<'_> {
                     self.session_bytes,
                     self.agent_bytes,
                     self.prompt_tokens + self.completion_tokens,
+                    self.untracked_reads,
+                    self.request_metrics,
                 );
                 let total_width = area.width as usize;
                 let budget = total_width.saturating_sub(prefix_width + right_reserve);
@@ -219,9 +237,13 @@ impl Widget for StatusBar<'_> {
             }
         }
 
-        // Build right side content: session bytes and/or tokens
+        // Build right side content: request timing, session bytes and/or tokens
         let mut right_content = Vec::new();
 
+        if let Some(metrics) = self.request_metrics {
+            right_content.push(Span::styled(format!("{} | ", metrics), style_dim));
+        }
+
         // Session bytes
         if let Some((input_bytes, output_bytes)) = self.session_bytes {
             let total = input_bytes + output_bytes;
@@ -241,6 +263,17 @@ impl Widget for StatusBar<'_> {
             right_content.push(Span::styled(format!("{}t", total_tokens), style_tokens));
         }
 
+        // Reads of files git doesn't track (listed by /access-log)
+        if self.untracked_reads > 0 {
+            if !right_content.is_empty() {
+                right_content.push(Span::styled(" | ", style_dim));
+            }
+            right_content.push(Span::styled(
+                untracked_badge(self.untracked_reads),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+
         // Add right-aligned content
         if !right_content.is_empty() {
             let current_len: usize = spans.iter().map(|s| s.content.len()).sum();
@@ -274,6 +307,11 @@ impl Widget for StatusBar<'_> {
     }
 }
 
+/// Status bar badge for reads of untracked files, e.g. "! 2 untracked".
+fn untracked_badge(count: usize) -> String {
+    format!("! {} untracked", count)
+}
+
 /// Format byte count with Kb/Mb suffixes for readability
 fn format_bytes(bytes: usize) -> String {
     if bytes >= 1_000_000 {
@@ -367,9 +405,16 @@ fn estimate_right_width(
     session_bytes: Option<(usize, usize)>,
     agent_bytes: Option<(usize, usize)>,
     total_tokens: u32,
+    untracked_reads: usize,
+    request_metrics: Option<&str>,
 ) -> usize {
     let mut width = 0;
 
+    // Request timing: "TTFT 0.42s · 38.5 tok/s · 6.10s | "
+    if let Some(metrics) = request_metrics {
+        width += metrics.chars().count() + 3;
+    }
+
     // Agent bytes: " 1.2Mb"
     if let Some((input, output)) = agent_bytes {
         width += 1 + format_bytes(input + output).len();
@@ -403,6 +448,10 @@ fn estimate_right_width(
         width += format!("{}t", total_tokens).len();
     }
 
+    if untracked_reads > 0 {
+        width += 3 + untracked_badge(untracked_reads).chars().count();
+    }
+
     // Trailing space
     width += 1;
 
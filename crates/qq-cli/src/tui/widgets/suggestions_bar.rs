@@ -0,0 +1,44 @@
+//! Follow-up suggestion quick-picks shown above the input.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+
+/// Single-line bar of numbered follow-up suggestions.
+pub struct SuggestionsBar<'a> {
+    suggestions: &'a [String],
+}
+
+impl<'a> SuggestionsBar<'a> {
+    pub fn new(suggestions: &'a [String]) -> Self {
+        Self { suggestions }
+    }
+}
+
+impl Widget for SuggestionsBar<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let key_style = Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD);
+        let text_style = Style::default().fg(Color::Gray);
+
+        let mut spans = vec![Span::styled(" ", text_style)];
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled("  ", text_style));
+            }
+            spans.push(Span::styled(format!("[{}]", i + 1), key_style));
+            spans.push(Span::styled(format!(" {}", suggestion), text_style));
+        }
+        spans.push(Span::styled(
+            "  (Alt+number to insert)",
+            Style::default().fg(Color::DarkGray),
+        ));
+
+        Paragraph::new(Line::from(spans)).render(area, buf);
+    }
+}
@@ -1,5 +1,8 @@
 //! Expandable thinking/reasoning panel widget with tool notifications.
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -8,6 +11,30 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Widget, Wrap},
 };
 
+use crate::tui::thinking::ThinkingLine;
+
+/// Colors for sub-agent tags, picked by a hash of the name.
+const AGENT_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Blue,
+    Color::Green,
+    Color::LightYellow,
+    Color::LightRed,
+];
+
+/// Stable tag color for an agent (`None`: the primary agent).
+pub(super) fn agent_color(agent: Option<&str>) -> Color {
+    match agent {
+        None => Color::Gray,
+        Some(name) => {
+            let mut hasher = DefaultHasher::new();
+            name.hash(&mut hasher);
+            AGENT_COLORS[hasher.finish() as usize % AGENT_COLORS.len()]
+        }
+    }
+}
+
 /// Status of a tool notification.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ToolNotificationStatus {
@@ -92,14 +119,14 @@ fn format_args_preview(json_str: &str, max_len: usize) -> String {
             .collect();
         let formatted = pairs.join(", ");
         if formatted.len() > max_len {
-            // Truncate on a char boundary
-            let truncated = &formatted[..formatted.floor_char_boundary(max_len)];
+            // Truncate between grapheme clusters
+            let truncated = qq_core::text::truncate_bytes(&formatted, max_len);
             format!("{}...", truncated)
         } else {
             formatted
         }
     } else if json_str.len() > max_len {
-        let truncated = &json_str[..json_str.floor_char_boundary(max_len)];
+        let truncated = qq_core::text::truncate_bytes(json_str, max_len);
         format!("{}...", truncated)
     } else {
         json_str.to_string()
@@ -109,7 +136,13 @@ fn format_args_preview(json_str: &str, max_len: usize) -> String {
 /// Thinking panel that can be expanded to fullscreen.
 /// Also displays tool notifications at the bottom.
 pub struct ThinkingPanel<'a> {
-    content: &'a str,
+    lines: &'a [ThinkingLine],
+    /// Label for lines from the primary agent
+    primary: &'a str,
+    /// Prefix each line with its agent
+    tagged: bool,
+    /// Agent the lines were filtered to
+    filter: Option<&'a Option<String>>,
     tool_notifications: &'a [ToolNotification],
     is_expanded: bool,
     is_streaming: bool,
@@ -117,9 +150,12 @@ pub struct ThinkingPanel<'a> {
 }
 
 impl<'a> ThinkingPanel<'a> {
-    pub fn new(content: &'a str) -> Self {
+    pub fn new(lines: &'a [ThinkingLine]) -> Self {
         Self {
-            content,
+            lines,
+            primary: "",
+            tagged: false,
+            filter: None,
             tool_notifications: &[],
             is_expanded: false,
             is_streaming: false,
@@ -127,6 +163,19 @@ impl<'a> ThinkingPanel<'a> {
         }
     }
 
+    /// Tag lines with their agent, naming the primary agent `primary`.
+    pub fn tagged(mut self, tagged: bool, primary: &'a str) -> Self {
+        self.tagged = tagged;
+        self.primary = primary;
+        self
+    }
+
+    /// Note in the title that only `agent`'s lines are shown.
+    pub fn filter(mut self, agent: Option<&'a Option<String>>) -> Self {
+        self.filter = agent;
+        self
+    }
+
     pub fn tool_notifications(mut self, notifications: &'a [ToolNotification]) -> Self {
         self.tool_notifications = notifications;
         self
@@ -211,18 +260,21 @@ impl Widget for ThinkingPanel<'_> {
             .fg(Color::DarkGray)
             .add_modifier(Modifier::DIM);
 
-        // Show expansion state and streaming status in title
-        let title = if self.is_streaming {
-            if self.is_expanded {
-                " Thinking... [Ctrl+T shrink | Ctrl+H hide] "
-            } else {
-                " Thinking... [Ctrl+T expand | Ctrl+H hide] "
-            }
-        } else if self.is_expanded {
-            " Thinking [Ctrl+T shrink | Ctrl+H hide] "
+        // Show streaming status, agent filter and expansion state in title
+        let mut title = String::from(if self.is_streaming {
+            " Thinking..."
         } else {
-            " Thinking [Ctrl+T expand | Ctrl+H hide] "
-        };
+            " Thinking"
+        });
+        if let Some(agent) = self.filter {
+            title.push_str(&format!(" ({} only)", agent.as_deref().unwrap_or(self.primary)));
+        }
+        let resize = if self.is_expanded { "shrink" } else { "expand" };
+        if self.tagged || self.filter.is_some() {
+            title.push_str(&format!(" [Ctrl+O agent | Ctrl+T {} | Ctrl+H hide] ", resize));
+        } else {
+            title.push_str(&format!(" [Ctrl+T {} | Ctrl+H hide] ", resize));
+        }
 
         let block = Block::default()
             .title(Span::styled(title, title_style))
@@ -233,9 +285,20 @@ impl Widget for ThinkingPanel<'_> {
         // Thinking content is raw model output, not formatted markdown
         let text_style = Style::default().fg(Color::DarkGray);
         let mut lines: Vec<Line> = self
-            .content
-            .lines()
-            .map(|line| Line::from(Span::styled(line.to_string(), text_style)))
+            .lines
+            .iter()
+            .map(|line| {
+                let text = Span::styled(line.text.clone(), text_style);
+                if !self.tagged {
+                    return Line::from(text);
+                }
+                let agent = line.agent.as_deref();
+                let tag = Span::styled(
+                    format!("{}│ ", agent.unwrap_or(self.primary)),
+                    Style::default().fg(agent_color(agent)),
+                );
+                Line::from(vec![tag, text])
+            })
             .collect();
 
         // Add tool notifications at the bottom
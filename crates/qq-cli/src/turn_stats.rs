@@ -0,0 +1,269 @@
+//! Per-turn token and cost annotations.
+//!
+//! After each response, a footer line records what the turn took:
+//!
+//! ```text
+//! *↳ 12.4k in · 845 out · +31.0k sub-agent · ~$0.15 · 14.2s · claude-sonnet-4-5*
+//! ```
+//!
+//! In the TUI the footer is part of the transcript, so `/export` keeps it;
+//! Ctrl+K only hides it from view. Costs are estimates from list prices (see
+//! [`qq_providers::pricing`]) or `[pricing]` overrides, with sub-agent tokens
+//! priced at the turn's model.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use qq_core::{Message, Role, Usage};
+use qq_providers::pricing::{known_pricing, ModelPricing};
+
+use crate::config::PricingEntry;
+
+/// Start of a footer line (Markdown italics).
+pub const FOOTER_PREFIX: &str = "*↳ ";
+
+/// Model prices: `[pricing]` overrides first, then built-in list prices.
+#[derive(Debug, Clone, Default)]
+pub struct Pricing {
+    /// Lowercased name or prefix, longest first
+    overrides: Vec<(String, ModelPricing)>,
+}
+
+impl Pricing {
+    pub fn new(entries: &HashMap<String, PricingEntry>) -> Self {
+        let mut overrides: Vec<(String, ModelPricing)> = entries
+            .iter()
+            .map(|(model, entry)| {
+                (
+                    model.to_lowercase(),
+                    ModelPricing::new(entry.input, entry.output),
+                )
+            })
+            .collect();
+        overrides.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Self { overrides }
+    }
+
+    pub fn for_model(&self, model: &str) -> Option<ModelPricing> {
+        let lower = model.to_lowercase();
+        self.overrides
+            .iter()
+            .find(|(prefix, _)| lower.starts_with(prefix.as_str()))
+            .map(|(_, pricing)| *pricing)
+            .or_else(|| known_pricing(model))
+    }
+}
+
+/// Tokens, model, and wall time of one user turn.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TurnStats {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Tokens spent by sub-agents the turn delegated to
+    pub agent_prompt_tokens: u32,
+    pub agent_completion_tokens: u32,
+    pub model: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+impl TurnStats {
+    /// Count one completion of the primary agent.
+    pub fn add(&mut self, usage: &Usage) {
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+    }
+
+    /// Count one completion of a sub-agent.
+    pub fn add_agent(&mut self, usage: &Usage) {
+        self.agent_prompt_tokens += usage.prompt_tokens;
+        self.agent_completion_tokens += usage.completion_tokens;
+    }
+
+    /// Stats recorded in the provenance of one turn's messages, from its
+    /// user message through the final answer. Sub-agent usage isn't in the
+    /// history, so only the primary agent is counted.
+    pub fn from_messages(turn: &[Message]) -> Self {
+        let mut stats = Self::default();
+        let mut first_ms = None;
+        let mut last_ms = None;
+        for meta in turn.iter().filter_map(|m| m.metadata.as_ref()) {
+            first_ms.get_or_insert(meta.timestamp_ms);
+            last_ms = Some(meta.timestamp_ms);
+        }
+        for meta in turn
+            .iter()
+            .filter(|m| m.role == Role::Assistant)
+            .filter_map(|m| m.metadata.as_ref())
+        {
+            if let Some(ref usage) = meta.usage {
+                stats.add(usage);
+            }
+            if meta.model.is_some() {
+                stats.model = meta.model.clone();
+            }
+        }
+        if let (Some(first), Some(last)) = (first_ms, last_ms) {
+            stats.duration = Some(Duration::from_millis(last.saturating_sub(first)));
+        }
+        stats
+    }
+
+    fn total_prompt_tokens(&self) -> u32 {
+        self.prompt_tokens + self.agent_prompt_tokens
+    }
+
+    fn total_completion_tokens(&self) -> u32 {
+        self.completion_tokens + self.agent_completion_tokens
+    }
+
+    /// Estimated cost in USD, if the model's price is known.
+    pub fn cost(&self, pricing: &Pricing) -> Option<f64> {
+        let price = pricing.for_model(self.model.as_deref()?)?;
+        Some(price.cost(self.total_prompt_tokens(), self.total_completion_tokens()))
+    }
+
+    /// The footer line, or `None` when nothing was recorded.
+    pub fn footer(&self, pricing: &Pricing) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.prompt_tokens + self.completion_tokens > 0 {
+            parts.push(format!("{} in", format_tokens(self.prompt_tokens)));
+            parts.push(format!("{} out", format_tokens(self.completion_tokens)));
+        }
+        let agent_tokens = self.agent_prompt_tokens + self.agent_completion_tokens;
+        if agent_tokens > 0 {
+            parts.push(format!("+{} sub-agent", format_tokens(agent_tokens)));
+        }
+        if let Some(cost) = self.cost(pricing).filter(|_| !parts.is_empty()) {
+            parts.push(format_cost(cost));
+        }
+        if let Some(duration) = self.duration {
+            parts.push(format_duration(duration));
+        }
+        if let Some(ref model) = self.model {
+            parts.push(model.clone());
+        }
+        if parts.is_empty() {
+            return None;
+        }
+        Some(format!("{}{}*", FOOTER_PREFIX, parts.join(" · ")))
+    }
+}
+
+pub fn is_footer(line: &str) -> bool {
+    line.starts_with(FOOTER_PREFIX) && line.ends_with('*')
+}
+
+/// The transcript without footer lines, for when they are toggled off.
+pub fn hide_footers(content: &str) -> Cow<'_, str> {
+    if !content.contains(FOOTER_PREFIX) {
+        return Cow::Borrowed(content);
+    }
+    let kept: Vec<&str> = content
+        .split('\n')
+        .filter(|line| !is_footer(line))
+        .collect();
+    Cow::Owned(kept.join("\n"))
+}
+
+pub(crate) fn format_tokens(tokens: u32) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.2}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+fn format_cost(cost: f64) -> String {
+    if cost >= 0.01 {
+        format!("~${:.2}", cost)
+    } else {
+        format!("~${:.4}", cost)
+    }
+}
+
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs_f64();
+    if secs < 60.0 {
+        format!("{:.1}s", secs)
+    } else {
+        let secs = duration.as_secs();
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footer_and_pricing_overrides() {
+        let mut stats = TurnStats {
+            model: Some("claude-sonnet-4-5".to_string()),
+            duration: Some(Duration::from_millis(14_230)),
+            ..Default::default()
+        };
+        stats.add(&Usage::new(10_000, 500));
+        stats.add(&Usage::new(2_400, 345));
+        stats.add_agent(&Usage::new(30_000, 1_000));
+
+        let pricing = Pricing::default();
+        // (42_400 * 3 + 1_845 * 15) / 1M
+        assert!((stats.cost(&pricing).unwrap() - 0.154875).abs() < 1e-9);
+        let footer = stats.footer(&pricing).unwrap();
+        assert_eq!(
+            footer,
+            "*↳ 12.4k in · 845 out · +31.0k sub-agent · ~$0.15 · 14.2s · claude-sonnet-4-5*"
+        );
+        assert!(is_footer(&footer));
+
+        // Longest matching override wins over the built-in price
+        let entries = HashMap::from([
+            (
+                "claude-".to_string(),
+                PricingEntry {
+                    input: 1.0,
+                    output: 1.0,
+                },
+            ),
+            (
+                "claude-sonnet-4-5".to_string(),
+                PricingEntry {
+                    input: 0.0,
+                    output: 0.0,
+                },
+            ),
+        ]);
+        assert_eq!(stats.cost(&Pricing::new(&entries)), Some(0.0));
+
+        // Unknown model: no cost, the rest still shows
+        stats.model = Some("llama-3.1-70b".to_string());
+        assert!(!stats.footer(&pricing).unwrap().contains('$'));
+        assert_eq!(TurnStats::default().footer(&pricing), None);
+    }
+
+    #[test]
+    fn test_from_messages_and_hide_footers() {
+        let mut user = Message::user("hi").with_provenance(None, None, None);
+        user.metadata.as_mut().unwrap().timestamp_ms = 1_000;
+        let mut call =
+            Message::assistant("").with_provenance(None, Some("gpt-4o"), Some(Usage::new(100, 10)));
+        call.metadata.as_mut().unwrap().timestamp_ms = 2_000;
+        let mut answer = Message::assistant("done").with_provenance(
+            None,
+            Some("gpt-4o"),
+            Some(Usage::new(150, 20)),
+        );
+        answer.metadata.as_mut().unwrap().timestamp_ms = 4_500;
+
+        let stats = TurnStats::from_messages(&[user, call, answer]);
+        assert_eq!(stats.prompt_tokens, 250);
+        assert_eq!(stats.completion_tokens, 30);
+        assert_eq!(stats.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(stats.duration, Some(Duration::from_millis(3_500)));
+
+        let content = "answer\n\n*↳ 250 in · 30 out · 3.5s · gpt-4o*\n\n*just italics*\n";
+        assert_eq!(hide_footers(content), "answer\n\n\n*just italics*\n");
+        assert!(matches!(hide_footers("plain"), Cow::Borrowed(_)));
+    }
+}
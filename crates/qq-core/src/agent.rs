@@ -9,15 +9,18 @@
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use futures::StreamExt;
 use tokio::sync::{mpsc, RwLock};
 
 use crate::error::Error;
+use crate::grounding::Regrounding;
 use crate::message::{FinishReason, Message, Role, StreamChunk, Usage};
 use crate::observation::{ContextCompactor, ObservationConfig, ObservationalMemory};
-use crate::provider::{CompletionRequest, Provider};
+use crate::overflow::evict_for_context_overflow;
+use crate::provider::{CompletionRequest, Provider, SamplingOverrides};
 use crate::tool::ToolRegistry;
 
 /// Result of a single agent execution.
@@ -168,11 +171,80 @@ impl RepetitionDetector {
     }
 }
 
+/// Default dedup window: identical calls within this many tool calls are
+/// served from the cache instead of being re-executed.
+pub const DEFAULT_DEDUP_WINDOW: usize = 8;
+
+/// Short-circuits identical tool calls made within a sliding window.
+///
+/// Successful results of [`Tool::is_read_only`](crate::tool::Tool::is_read_only)
+/// tools are cached under the same canonical hash the [`RepetitionDetector`]
+/// uses. A repeat inside the window gets the cached result plus a nudge
+/// instead of re-running the tool. Other tools are never cached, since their
+/// result may differ each time (`datetime`, `list_tasks`, `run`), and executing
+/// one may have changed what a repeat would see, so it flushes the cache.
+struct ToolCallCache {
+    /// hash(tool_name, canonical_args) -> (call sequence number, result)
+    entries: HashMap<u64, (usize, String)>,
+    /// Tool calls seen so far (executed or served from cache)
+    seq: usize,
+    window: usize,
+}
+
+impl ToolCallCache {
+    fn new(window: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            seq: 0,
+            window,
+        }
+    }
+
+    /// Look up a prior identical call. Returns the cached result with a nudge
+    /// appended, or `None` if the call must be executed.
+    fn lookup(&mut self, tool_name: &str, arguments: &serde_json::Value) -> Option<String> {
+        self.seq += 1;
+        if self.window == 0 {
+            return None;
+        }
+        let hash = RepetitionDetector::canonical_hash(tool_name, arguments);
+        let (at, result) = self.entries.get(&hash)?;
+        let ago = self.seq - at;
+        if ago > self.window {
+            self.entries.remove(&hash);
+            return None;
+        }
+        Some(format!(
+            "{}\n\n[Duplicate call: '{}' was already called with identical arguments {} tool call(s) ago \
+             and nothing has changed since. This is the cached result; the tool was not re-run. \
+             Use it instead of repeating the call.]",
+            result, tool_name, ago
+        ))
+    }
+
+    /// Flush every cached result (a mutating tool ran).
+    fn invalidate(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Record the result of an executed call. Errors are never cached so a
+    /// retry after a transient failure still runs.
+    fn store(&mut self, tool_name: &str, arguments: &serde_json::Value, result: &str) {
+        if self.window == 0 || result.starts_with("Error") {
+            return;
+        }
+        let hash = RepetitionDetector::canonical_hash(tool_name, arguments);
+        self.entries.insert(hash, (self.seq, result.to_string()));
+    }
+}
+
 /// Metadata about an agent instance's execution history.
 #[derive(Debug, Clone, Default)]
 pub struct AgentInstanceMetadata {
     pub call_count: u32,
     pub total_tool_calls: u32,
+    /// When the instance was last stored (None if never run this session)
+    pub last_used: Option<Instant>,
 }
 
 /// Stored state for a single agent instance (keyed by scope path).
@@ -182,6 +254,8 @@ pub struct AgentInstanceState {
     pub metadata: AgentInstanceMetadata,
     /// Observation log from observational memory (empty if using compaction strategy).
     pub observation_log: String,
+    /// Named long-lived instance: never evicted by [`EvictionPolicy`].
+    pub pinned: bool,
 }
 
 impl AgentInstanceState {
@@ -190,6 +264,7 @@ impl AgentInstanceState {
             messages: Vec::new(),
             metadata: AgentInstanceMetadata::default(),
             observation_log: String::new(),
+            pinned: false,
         }
     }
 
@@ -221,12 +296,25 @@ impl Default for AgentInstanceState {
 /// Default byte budget per agent instance (200KB).
 pub const DEFAULT_MAX_INSTANCE_BYTES: usize = 200_000;
 
+/// When unpinned agent instances are dropped from [`AgentMemory`].
+///
+/// Applied after every store; pinned (named) instances are exempt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionPolicy {
+    /// Keep at most this many unpinned instances, dropping the least
+    /// recently used (None = unlimited)
+    pub max_instances: Option<usize>,
+    /// Drop unpinned instances not used for this long
+    pub idle_ttl: Option<Duration>,
+}
+
 /// Central memory store for all scoped agent instances.
 /// Keyed by scope path strings like "chat/explore", "chat/coder/explore".
 #[derive(Debug, Clone)]
 pub struct AgentMemory {
     instances: Arc<RwLock<HashMap<String, AgentInstanceState>>>,
     max_instance_bytes: usize,
+    eviction: EvictionPolicy,
 }
 
 impl AgentMemory {
@@ -234,6 +322,7 @@ impl AgentMemory {
         Self {
             instances: Arc::new(RwLock::new(HashMap::new())),
             max_instance_bytes: DEFAULT_MAX_INSTANCE_BYTES,
+            eviction: EvictionPolicy::default(),
         }
     }
 
@@ -241,9 +330,16 @@ impl AgentMemory {
         Self {
             instances: Arc::new(RwLock::new(HashMap::new())),
             max_instance_bytes: max_bytes,
+            eviction: EvictionPolicy::default(),
         }
     }
 
+    /// Set the eviction policy for unpinned instances.
+    pub fn with_eviction(mut self, eviction: EvictionPolicy) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
     /// Clone stored messages for a scope (or empty if none).
     pub async fn get_messages(&self, scope: &str) -> Vec<Message> {
         let instances = self.instances.read().await;
@@ -271,7 +367,9 @@ impl AgentMemory {
         state.messages = messages;
         state.metadata.call_count += 1;
         state.metadata.total_tool_calls += tool_calls;
+        state.metadata.last_used = Some(Instant::now());
         state.trim_to_budget(self.max_instance_bytes);
+        self.evict(&mut instances, scope);
     }
 
     /// Store messages and observation log for a scope (obs-memory strategy).
@@ -290,7 +388,9 @@ impl AgentMemory {
         state.observation_log = observation_log;
         state.metadata.call_count += 1;
         state.metadata.total_tool_calls += tool_calls;
+        state.metadata.last_used = Some(Instant::now());
         state.trim_to_budget(self.max_instance_bytes);
+        self.evict(&mut instances, scope);
     }
 
     /// Get messages and observation log for a scope.
@@ -302,6 +402,51 @@ impl AgentMemory {
             .unwrap_or_default()
     }
 
+    /// Pin a scope as a named long-lived instance, seeding it with saved
+    /// state if it isn't in memory yet (re-attach after a restart).
+    pub async fn pin(&self, scope: &str, saved: Option<(Vec<Message>, String)>) {
+        let mut instances = self.instances.write().await;
+        let state = instances.entry(scope.to_string()).or_insert_with(|| {
+            let mut state = AgentInstanceState::new();
+            if let Some((messages, observation_log)) = saved {
+                state.messages = messages;
+                state.observation_log = observation_log;
+            }
+            state
+        });
+        state.pinned = true;
+    }
+
+    /// Drop unpinned instances per the eviction policy, keeping `keep`.
+    fn evict(&self, instances: &mut HashMap<String, AgentInstanceState>, keep: &str) {
+        let now = Instant::now();
+        let idle = |state: &AgentInstanceState| {
+            state.metadata.last_used.map_or(Duration::ZERO, |t| now - t)
+        };
+        let before = instances.len();
+        if let Some(ttl) = self.eviction.idle_ttl {
+            instances.retain(|scope, state| state.pinned || scope == keep || idle(state) <= ttl);
+        }
+        if let Some(max) = self.eviction.max_instances {
+            let mut unpinned: Vec<(Duration, String)> = instances
+                .iter()
+                .filter(|(scope, state)| !state.pinned && scope.as_str() != keep)
+                .map(|(scope, state)| (idle(state), scope.clone()))
+                .collect();
+            // `keep` is unpinned too unless it was pinned
+            let keep_counts = instances.get(keep).is_some_and(|s| !s.pinned) as usize;
+            let excess = (unpinned.len() + keep_counts).saturating_sub(max);
+            unpinned.sort_by_key(|(idle, _)| std::cmp::Reverse(*idle));
+            for (_, scope) in unpinned.into_iter().take(excess) {
+                instances.remove(&scope);
+            }
+        }
+        let evicted = before - instances.len();
+        if evicted > 0 {
+            tracing::debug!(evicted, remaining = instances.len(), "Evicted idle agent instances");
+        }
+    }
+
     /// Remove a single scope's instance.
     pub async fn clear_scope(&self, scope: &str) {
         let mut instances = self.instances.write().await;
@@ -665,6 +810,15 @@ pub struct AgentConfig {
     pub max_observations: Option<u32>,
     /// Prior observation log to restore (for resuming stateful agents).
     pub prior_observation_log: Option<String>,
+    /// Tool-call dedup window (in tool calls). 0 disables the cache.
+    pub dedup_window: usize,
+    /// Temperature, top_p, max_tokens, and extra parameters for this agent's requests.
+    pub sampling: SamplingOverrides,
+    /// Tool whose successful call ends the run, its output becoming the
+    /// agent's result. None = the run ends on a reply without tool calls.
+    pub finish_tool: Option<String>,
+    /// Restate the task after compaction drops it or a long tool batch.
+    pub regrounding: Option<Regrounding>,
 }
 
 impl AgentConfig {
@@ -680,6 +834,10 @@ impl AgentConfig {
             observation_config: None,
             max_observations: None,
             prior_observation_log: None,
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            sampling: SamplingOverrides::default(),
+            finish_tool: None,
+            regrounding: None,
         }
     }
 
@@ -735,6 +893,44 @@ impl AgentConfig {
         }
         self
     }
+
+    /// Set the tool-call dedup window.
+    ///
+    /// An identical call (same tool, same canonical arguments) within this
+    /// many tool calls returns the cached result instead of re-running.
+    /// Pass 0 to disable.
+    pub fn with_dedup_window(mut self, window: usize) -> Self {
+        self.dedup_window = window;
+        self
+    }
+
+    /// Set sampling overrides merged into every request the agent sends.
+    pub fn with_sampling(mut self, sampling: SamplingOverrides) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Set the tool that ends the run.
+    ///
+    /// A successful call to it returns the tool's output as the result. A
+    /// reply without tool calls gets one reminder to call it; a second is
+    /// accepted as free text.
+    pub fn with_finish_tool(mut self, name: impl Into<String>) -> Self {
+        self.finish_tool = Some(name.into());
+        self
+    }
+
+    /// Re-ground the agent on its task.
+    ///
+    /// After a tool batch, if compaction has dropped the task from the
+    /// conversation or the batch ran past the pause threshold, the task is
+    /// restated verbatim with the task board and latest observations. The
+    /// task is the regrounding's goal, else the last user message of the
+    /// context.
+    pub fn with_regrounding(mut self, regrounding: Regrounding) -> Self {
+        self.regrounding = Some(regrounding);
+        self
+    }
 }
 
 impl std::fmt::Debug for AgentConfig {
@@ -742,7 +938,7 @@ impl std::fmt::Debug for AgentConfig {
         f.debug_struct("AgentConfig")
             .field("id", &self.id)
             .field("system_prompt", &self.system_prompt.as_deref().map(|s| {
-                if s.len() > 50 { &s[..50] } else { s }
+                crate::text::truncate_bytes(s, 50)
             }))
             .field("max_turns", &self.max_turns)
             .field("stateful", &self.stateful)
@@ -751,6 +947,10 @@ impl std::fmt::Debug for AgentConfig {
             .field("observation_config", &self.observation_config)
             .field("max_observations", &self.max_observations)
             .field("has_prior_obs_log", &self.prior_observation_log.is_some())
+            .field("dedup_window", &self.dedup_window)
+            .field("sampling", &self.sampling)
+            .field("finish_tool", &self.finish_tool)
+            .field("regrounding", &self.regrounding)
             .finish()
     }
 }
@@ -814,6 +1014,12 @@ pub enum AgentProgressEvent {
         observation_count: u32,
         log_bytes: usize,
     },
+    /// The provider rejected the request as too long for the context window;
+    /// the context was shrunk (`action`) and the request is being retried.
+    ContextRecovered {
+        agent_name: String,
+        action: String,
+    },
 }
 
 /// Handler for receiving agent progress events.
@@ -982,10 +1188,29 @@ impl Agent {
         // Repetition detector: catches agents stuck calling the same tool with same args
         let mut repetition_detector = RepetitionDetector::new();
 
+        // Dedup cache: serves identical calls from the last result instead of re-running
+        let mut call_cache = ToolCallCache::new(config.dedup_window);
+
         // Wrap-up tracking for obs memory
         let mut wrap_up_injected = false;
         let mut wrap_up_iteration: usize = 0;
 
+        // Whether the agent was already told to end with the finish tool
+        let mut finish_reminded = false;
+
+        // Task to restate when re-grounding, and the observation count at the
+        // last check (a change means the history was compacted since)
+        let regrounding_goal = config.regrounding.as_ref().map(|r| match r.goal() {
+            Some(goal) => goal.to_string(),
+            None => messages
+                .iter()
+                .rev()
+                .find(|m| m.role == Role::User)
+                .map(|m| m.content.to_string_lossy())
+                .unwrap_or_default(),
+        });
+        let mut grounded_observations = obs_memory.as_ref().map_or(0, |om| om.observation_count());
+
         // Run agentic loop (safety ceiling only; repetition detector is primary stop)
         for iteration in 0..config.max_turns {
             // Emit iteration start event
@@ -1036,7 +1261,7 @@ impl Agent {
 
             // Use streaming if we have a progress handler, otherwise use complete()
             // Wrap with retry logic for transient transport/stream errors
-            let (content, tool_calls, usage, thinking, finish_reason) = {
+            let outcome = {
                 let mut last_error = None;
                 let mut result = None;
                 for attempt in 0..=MAX_STREAM_RETRIES {
@@ -1066,10 +1291,10 @@ impl Agent {
                     }
 
                     // Re-build the request for each attempt (need a fresh clone)
-                    let attempt_request = CompletionRequest::new(
-                        request_messages.clone(),
-                    )
-                    .with_tools(tools.definitions());
+                    let attempt_request = config.sampling.apply(
+                        CompletionRequest::new(request_messages.clone())
+                            .with_tools(tools.definitions()),
+                    );
 
                     let iter_result = if progress.is_some() {
                         run_streaming_iteration(
@@ -1085,7 +1310,7 @@ impl Agent {
 
                     match iter_result {
                         Ok(val) => {
-                            result = Some(val);
+                            result = Some(Ok(val));
                             break;
                         }
                         Err(e) if e.is_retryable() && attempt < MAX_STREAM_RETRIES => {
@@ -1097,13 +1322,80 @@ impl Agent {
                             last_error = Some(e);
                             continue;
                         }
-                        Err(e) => return Err(e),
+                        Err(e) => {
+                            result = Some(Err(e));
+                            break;
+                        }
                     }
                 }
-                // Safe: loop either sets result or returns Err
+                // Safe: loop always sets result before breaking out
                 result.unwrap()
             };
 
+            // Context overflow: shrink the context and retry the iteration.
+            // Shares the emergency-compaction budget with length truncation so a
+            // context that can't be shrunk enough still fails instead of looping.
+            let (content, tool_calls, usage, thinking, finish_reason) = match outcome {
+                Ok(val) => val,
+                Err(e)
+                    if e.is_context_overflow()
+                        && consecutive_emergency_compactions
+                            < MAX_CONSECUTIVE_EMERGENCY_COMPACTIONS =>
+                {
+                    tracing::warn!(
+                        agent = %config.id,
+                        error = %e,
+                        message_count = messages.len(),
+                        "Context window exceeded, shrinking context"
+                    );
+                    let action = match (&mut obs_memory, &config.compactor) {
+                        (Some(ref mut om), Some(ref compactor)) => {
+                            let bytes_before: usize =
+                                messages.iter().map(|m| m.byte_count()).sum();
+                            match om.compact_force(&mut messages, compactor.as_ref()).await {
+                                Ok(()) => {
+                                    let bytes_after: usize =
+                                        messages.iter().map(|m| m.byte_count()).sum();
+                                    (bytes_after < bytes_before).then(|| {
+                                        format!(
+                                            "compacted {} bytes into the observation log",
+                                            bytes_before - bytes_after
+                                        )
+                                    })
+                                }
+                                Err(compact_err) => {
+                                    tracing::warn!(
+                                        agent = %config.id,
+                                        error = %compact_err,
+                                        "Emergency compaction failed, falling back to eviction"
+                                    );
+                                    None
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+                    let action = action.or_else(|| {
+                        let eviction = evict_for_context_overflow(&mut messages);
+                        (!eviction.is_empty()).then(|| eviction.describe())
+                    });
+                    let Some(action) = action else {
+                        return Err(e);
+                    };
+                    consecutive_emergency_compactions += 1;
+                    if let Some(ref handler) = progress {
+                        handler
+                            .on_progress(AgentProgressEvent::ContextRecovered {
+                                agent_name: agent_name.clone(),
+                                action,
+                            })
+                            .await;
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
             // Count output bytes (response received)
             let output_bytes = content.len();
 
@@ -1239,6 +1531,7 @@ impl Agent {
 
             // Check for tool calls
             if !tool_calls.is_empty() {
+                let batch_started = Instant::now();
                 debug!(
                     agent = %config.id,
                     tool_count = tool_calls.len(),
@@ -1248,9 +1541,19 @@ impl Agent {
                 // Store message with tool calls; attach reasoning if configured
                 let reasoning = if provider.include_tool_reasoning() { thinking } else { None };
                 let msg = Message::assistant_with_tool_calls("", tool_calls.clone())
-                    .with_reasoning(reasoning);
+                    .with_reasoning(reasoning)
+                    .with_provenance(Some(&agent_name), provider.default_model(), Some(usage));
                 messages.push(msg);
 
+                // A write in this batch runs before the cache is flushed, so a
+                // cached read of what it writes would be stale: skip the cache
+                if tool_calls
+                    .iter()
+                    .any(|tc| !tools.get(&tc.name).is_some_and(|t| t.is_read_only()))
+                {
+                    call_cache.invalidate();
+                }
+
                 // Check tool limits and repetition, partition into executable vs blocked
                 let mut executable_calls = Vec::new();
                 let mut blocked_count = 0usize;
@@ -1312,7 +1615,10 @@ impl Agent {
                                 .await;
                         }
 
-                        messages.push(Message::tool_result(&tool_call.id, result));
+                        messages.push(
+                            Message::tool_result(&tool_call.id, result)
+                                .with_provenance(Some(&agent_name), None, None),
+                        );
                         blocked_count += 1;
                         continue;
                     }
@@ -1350,11 +1656,48 @@ impl Agent {
                                 .await;
                         }
 
-                        messages.push(Message::tool_result(&tool_call.id, result));
+                        messages.push(
+                            Message::tool_result(&tool_call.id, result)
+                                .with_provenance(Some(&agent_name), None, None),
+                        );
                         blocked_count += 1;
                         continue;
                     }
 
+                    // Serve identical recent calls from the cache
+                    if let Some(cached) = call_cache.lookup(&tool_call.name, &tool_call.arguments) {
+                        debug!(
+                            agent = %config.id,
+                            tool = %tool_call.name,
+                            "Duplicate tool call served from cache"
+                        );
+
+                        if let Some(ref handler) = progress {
+                            handler
+                                .on_progress(AgentProgressEvent::ToolStart {
+                                    agent_name: agent_name.clone(),
+                                    tool_name: tool_call.name.clone(),
+                                    arguments: tool_call.arguments.to_string(),
+                                })
+                                .await;
+                            handler
+                                .on_progress(AgentProgressEvent::ToolComplete {
+                                    agent_name: agent_name.clone(),
+                                    tool_name: tool_call.name.clone(),
+                                    tool_call_id: tool_call.id.clone(),
+                                    result: cached.clone(),
+                                    is_error: false,
+                                })
+                                .await;
+                        }
+
+                        messages.push(
+                            Message::tool_result(&tool_call.id, cached)
+                                .with_provenance(Some(&agent_name), None, None),
+                        );
+                        continue;
+                    }
+
                     *tool_call_counts.entry(tool_call.name.clone()).or_insert(0) += 1;
                     executable_calls.push(tool_call);
                 }
@@ -1389,13 +1732,19 @@ impl Agent {
                     );
                 }
 
-                // Execute all tools concurrently
+                // Execute all tools concurrently, rejecting conflicting writes
+                let conflicts =
+                    crate::task::write_conflicts(&tools, executable_calls.iter().copied());
                 let futures: Vec<_> = executable_calls
                     .iter()
-                    .map(|tool_call| {
+                    .zip(conflicts)
+                    .map(|(tool_call, conflict)| {
                         let tools_ref = &tools;
                         async move {
-                            let result = execute_tool(tools_ref, tool_call).await;
+                            let result = match conflict {
+                                Some(conflict) => format!("Error: {}", conflict),
+                                None => execute_tool(tools_ref, tool_call).await,
+                            };
                             let is_error = result.starts_with("Error:");
                             (tool_call, result, is_error)
                         }
@@ -1404,8 +1753,25 @@ impl Agent {
 
                 let results = futures::future::join_all(futures).await;
 
+                // A mutating call may change what any cached call would return
+                if executable_calls
+                    .iter()
+                    .any(|tc| !tools.get(&tc.name).is_some_and(|t| t.is_read_only()))
+                {
+                    call_cache.invalidate();
+                }
+
                 // Process results and emit completion events
+                let mut finished: Option<String> = None;
                 for (tool_call, result, is_error) in results {
+                    if !is_error {
+                        if tools.get(&tool_call.name).is_some_and(|t| t.is_read_only()) {
+                            call_cache.store(&tool_call.name, &tool_call.arguments, &result);
+                        }
+                        if config.finish_tool.as_deref() == Some(tool_call.name.as_str()) {
+                            finished = Some(result.clone());
+                        }
+                    }
                     if let Some(ref handler) = progress {
                         handler
                             .on_progress(AgentProgressEvent::ToolComplete {
@@ -1418,7 +1784,29 @@ impl Agent {
                             .await;
                     }
 
-                    messages.push(Message::tool_result(&tool_call.id, result));
+                    messages.push(
+                        Message::tool_result(&tool_call.id, result)
+                            .with_provenance(Some(&agent_name), None, None),
+                    );
+                }
+
+                // The finish tool's output is the agent's result
+                if let Some(content) = finished {
+                    crate::message::strip_reasoning_from_history(&mut messages);
+                    debug!(
+                        agent = %config.id,
+                        iterations = iteration + 1,
+                        response_len = content.len(),
+                        "Agent finished via finish tool"
+                    );
+                    let obs_log = obs_memory
+                        .map(|om| om.into_parts().0)
+                        .unwrap_or_default();
+                    return Ok(AgentRunResult::Success {
+                        content,
+                        messages,
+                        observation_log: obs_log,
+                    });
                 }
 
                 // Run observational memory compaction after tool execution
@@ -1478,9 +1866,47 @@ impl Agent {
                     }
                 }
 
+                // Restate the task if compaction dropped it or the tools ran long
+                if let (Some(ref regrounding), Some(ref goal)) =
+                    (&config.regrounding, &regrounding_goal)
+                {
+                    let observations = obs_memory.as_ref().map_or(0, |om| om.observation_count());
+                    let compacted = observations != grounded_observations;
+                    grounded_observations = observations;
+                    let gap = Some(batch_started.elapsed());
+                    if let Some(trigger) = regrounding.trigger(goal, compacted, gap, &messages) {
+                        debug!(agent = %config.id, trigger = %trigger, "Re-grounding agent on its task");
+                        let log = obs_memory.as_ref().map(|om| om.observation_log()).unwrap_or_default();
+                        messages.push(regrounding.message(goal, trigger, log));
+                    }
+                }
+
                 continue;
             }
 
+            // No tool calls, but the result is expected through the finish
+            // tool: remind once, then accept free text
+            if let Some(finish) = config.finish_tool.as_deref() {
+                if !finish_reminded && tools.get(finish).is_some() {
+                    debug!(
+                        agent = %config.id,
+                        finish_tool = finish,
+                        "Agent replied without calling the finish tool, reminding"
+                    );
+                    messages.push(
+                        Message::assistant(content.as_str())
+                            .with_provenance(Some(&agent_name), provider.default_model(), Some(usage)),
+                    );
+                    messages.push(Message::user(format!(
+                        "Report your result by calling the `{}` tool. \
+                         Put the answer above in its summary and fill in the other fields.",
+                        finish
+                    )));
+                    finish_reminded = true;
+                    continue;
+                }
+            }
+
             // No tool calls - strip reasoning from history and return final response
             crate::message::strip_reasoning_from_history(&mut messages);
             debug!(
@@ -1518,8 +1944,10 @@ impl Agent {
             ));
         }
 
+        let agent_name = self.config.id.0.clone();
+
         // Add user input
-        self.messages.push(Message::user(input));
+        self.messages.push(Message::user(input).with_provenance(None, None, None));
 
         // Run agentic loop
         // Build request messages from self.messages each iteration to avoid double-cloning.
@@ -1530,8 +1958,9 @@ impl Agent {
             }
             request_messages.extend(self.messages.iter().cloned());
 
-            let mut request = CompletionRequest::new(request_messages)
-                .with_tools(self.tools.definitions());
+            let mut request = self.config.sampling.apply(
+                CompletionRequest::new(request_messages).with_tools(self.tools.definitions()),
+            );
 
             request.stream = false;
 
@@ -1546,13 +1975,17 @@ impl Agent {
                     None
                 };
                 let msg = Message::assistant_with_tool_calls("", response.message.tool_calls.clone())
-                    .with_reasoning(reasoning);
+                    .with_reasoning(reasoning)
+                    .with_provenance(Some(&agent_name), Some(&response.model), Some(response.usage.clone()));
                 self.messages.push(msg);
 
                 // Execute tools
                 for tool_call in &response.message.tool_calls {
                     let result = execute_tool(&self.tools, tool_call).await;
-                    self.messages.push(Message::tool_result(&tool_call.id, result));
+                    self.messages.push(
+                        Message::tool_result(&tool_call.id, result)
+                            .with_provenance(Some(&agent_name), None, None),
+                    );
                 }
 
                 continue;
@@ -1561,7 +1994,13 @@ impl Agent {
             // No tool calls - strip reasoning from history, save and return response
             crate::message::strip_reasoning_from_history(&mut self.messages);
             let content = response.message.content.to_string_lossy();
-            self.messages.push(Message::assistant(content.as_str()));
+            self.messages.push(
+                Message::assistant(content.as_str()).with_provenance(
+                    Some(&agent_name),
+                    Some(&response.model),
+                    Some(response.usage),
+                ),
+            );
             return Ok(content);
         }
 
@@ -1634,13 +2073,13 @@ impl Agent {
 
 /// Per-chunk inactivity timeout for streaming responses.
 /// If no data is received for this duration, the stream is considered stalled.
-const STREAM_CHUNK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+pub(crate) const STREAM_CHUNK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
 
 /// Maximum number of retries for transient streaming/transport errors.
-const MAX_STREAM_RETRIES: u32 = 3;
+pub(crate) const MAX_STREAM_RETRIES: u32 = 3;
 
 /// Initial retry delay (doubles each attempt: 1s, 2s, 4s).
-const INITIAL_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+pub(crate) const INITIAL_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
 
 /// Tokens reserved when comparing usage against `Provider::context_window()`.
 /// Providers often stop a few hundred tokens shy of the hard ceiling, and a
@@ -1798,11 +2237,8 @@ fn truncate_tool_result(content: String, max_bytes: usize) -> String {
         return content;
     }
 
-    // Find a valid UTF-8 char boundary at or before max_bytes
-    let mut boundary = max_bytes;
-    while boundary > 0 && !content.is_char_boundary(boundary) {
-        boundary -= 1;
-    }
+    // Cut between grapheme clusters at or before max_bytes
+    let boundary = crate::text::floor_grapheme_boundary(&content, max_bytes);
 
     // Try to find the last newline before the boundary for a clean cut
     if let Some(newline_pos) = content[..boundary].rfind('\n') {
@@ -1840,15 +2276,29 @@ async fn execute_tool(
         return format!("Error: Unknown tool '{}'", tool_call.name);
     };
 
-    match crate::tool::execute_tool_dispatch(tool, tool_call.arguments.clone()).await {
+    match crate::tool::execute_tool_dispatch(Arc::clone(&tool), tool_call.arguments.clone()).await {
         Ok(output) => {
             let text = output.text_content();
-            let content = if output.is_error {
-                format!("Error: {}", text)
-            } else {
-                text
-            };
-            truncate_tool_result(content, MAX_AGENT_TOOL_RESULT_BYTES)
+            if output.is_error {
+                return truncate_tool_result(format!("Error: {}", text), MAX_AGENT_TOOL_RESULT_BYTES);
+            }
+            // Too large to keep whole: ask for a narrower call if the tool has one
+            if text.len() > MAX_AGENT_TOOL_RESULT_BYTES {
+                let fraction = MAX_AGENT_TOOL_RESULT_BYTES as f64 / text.len() as f64;
+                if let Some(retry) = tool.narrower_scope(&tool_call.arguments, fraction) {
+                    let output_bytes = text.len();
+                    // Leave room in the budget for the retry itself
+                    let partial = truncate_tool_result(text, MAX_AGENT_TOOL_RESULT_BYTES / 2);
+                    return crate::narrowing::overflow_error(
+                        &tool_call.name,
+                        &retry,
+                        output_bytes,
+                        MAX_AGENT_TOOL_RESULT_BYTES,
+                        &partial,
+                    );
+                }
+            }
+            truncate_tool_result(text, MAX_AGENT_TOOL_RESULT_BYTES)
         }
         Err(e) => format!("Error executing tool: {}", e),
     }
@@ -2080,6 +2530,51 @@ mod tests {
         assert!(detector.check("read_file", &args).is_none());
     }
 
+    #[test]
+    fn test_tool_call_cache_serves_repeat_within_window() {
+        let mut cache = ToolCallCache::new(3);
+        let args = serde_json::json!({"query": "rust async", "limit": 5});
+        assert!(cache.lookup("web_search", &args).is_none());
+        cache.store("web_search", &args, "result A");
+
+        // Key order doesn't matter
+        let reordered = serde_json::json!({"limit": 5, "query": "rust async"});
+        let cached = cache.lookup("web_search", &reordered).unwrap();
+        assert!(cached.starts_with("result A"));
+        assert!(cached.contains("Duplicate call"));
+        assert!(cached.contains("1 tool call(s) ago"));
+
+        // Different args are independent
+        assert!(cache.lookup("web_search", &serde_json::json!({"query": "other"})).is_none());
+    }
+
+    #[test]
+    fn test_tool_call_cache_expires_outside_window() {
+        let mut cache = ToolCallCache::new(2);
+        let args = serde_json::json!({"query": "q"});
+        cache.lookup("web_search", &args);
+        cache.store("web_search", &args, "result");
+        cache.lookup("read_files", &serde_json::json!({"paths": ["a"]}));
+        cache.lookup("read_files", &serde_json::json!({"paths": ["b"]}));
+        assert!(cache.lookup("web_search", &args).is_none());
+    }
+
+    #[test]
+    fn test_tool_call_cache_invalidate_and_errors() {
+        let mut cache = ToolCallCache::new(8);
+        let args = serde_json::json!({"command": "cargo test"});
+        cache.store("run", &args, "Error: build failed");
+        assert!(cache.lookup("run", &args).is_none());
+
+        cache.store("run", &args, "ok");
+        cache.invalidate();
+        assert!(cache.lookup("run", &args).is_none());
+
+        let mut disabled = ToolCallCache::new(0);
+        disabled.store("run", &args, "ok");
+        assert!(disabled.lookup("run", &args).is_none());
+    }
+
     #[test]
     fn test_canonical_hash_key_order_independent() {
         let args1 = serde_json::json!({"a": 1, "b": 2});
@@ -2455,4 +2950,386 @@ mod tests {
             other => panic!("expected TruncatedByLength after cap, got {:?}", other),
         }
     }
+
+    #[tokio::test]
+    async fn context_overflow_error_evicts_and_retries() {
+        // The provider rejects the first request as too long. Without a
+        // compactor the agent evicts the oldest turns and retries once.
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        provider.queue_error(Error::context_overflow("maximum context length exceeded"));
+        provider.queue_response("done after eviction");
+        let mock = Arc::clone(&provider);
+        let provider: Arc<dyn Provider> = provider;
+
+        let result = Agent::run_once(
+            provider,
+            empty_tools(),
+            AgentConfig::new("test-agent"),
+            long_messages(10),
+        )
+        .await
+        .expect("agent should recover from overflow");
+        assert_eq!(result, "done after eviction");
+
+        let requests = mock.captured_requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].messages.len() < requests[0].messages.len());
+    }
+
+    #[tokio::test]
+    async fn context_overflow_surfaces_when_nothing_to_evict() {
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        provider.queue_error(Error::context_overflow("prompt is too long"));
+        let provider: Arc<dyn Provider> = provider;
+
+        let err = Agent::run_once(
+            provider,
+            empty_tools(),
+            AgentConfig::new("test-agent"),
+            vec![Message::user("do a thing")],
+        )
+        .await
+        .unwrap_err();
+        assert!(err.is_context_overflow());
+    }
+
+    #[tokio::test]
+    async fn sampling_overrides_reach_every_request() {
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        provider.queue_response("done");
+        let mock = Arc::clone(&provider);
+        let provider: Arc<dyn Provider> = provider;
+
+        let mut extra = HashMap::new();
+        extra.insert("reasoning_effort".to_string(), serde_json::json!("low"));
+        let config = AgentConfig::new("coder").with_sampling(SamplingOverrides {
+            temperature: Some(0.0),
+            top_p: None,
+            max_tokens: Some(2048),
+            extra,
+        });
+
+        Agent::run_once(provider, empty_tools(), config, vec![Message::user("fix it")])
+            .await
+            .unwrap();
+
+        let request = mock.last_request().unwrap();
+        assert_eq!(request.temperature, Some(0.0));
+        assert_eq!(request.top_p, None);
+        assert_eq!(request.max_tokens, Some(2048));
+        assert_eq!(request.extra["reasoning_effort"], "low");
+    }
+
+    #[tokio::test]
+    async fn agent_memory_evicts_least_recently_used_unpinned() {
+        let memory = AgentMemory::new().with_eviction(EvictionPolicy {
+            max_instances: Some(2),
+            idle_ttl: None,
+        });
+        memory
+            .pin("pm/tracker:@board", Some((vec![Message::user("saved")], String::new())))
+            .await;
+        for scope in ["pm/a", "pm/b", "pm/c"] {
+            memory.store_messages(scope, vec![Message::user(scope)], 0).await;
+        }
+
+        let scopes: Vec<String> = memory.diagnostics().await.into_iter().map(|d| d.0).collect();
+        assert_eq!(scopes, vec!["pm/b", "pm/c", "pm/tracker:@board"]);
+        assert_eq!(memory.get_messages("pm/tracker:@board").await.len(), 1);
+
+        // Pinning an instance already in memory keeps its current state
+        memory.pin("pm/c", Some((Vec::new(), String::new()))).await;
+        assert_eq!(memory.get_messages("pm/c").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn agent_memory_idle_ttl_spares_pinned() {
+        let memory = AgentMemory::new().with_eviction(EvictionPolicy {
+            max_instances: None,
+            idle_ttl: Some(Duration::ZERO),
+        });
+        memory.pin("pm/tracker:@board", None).await;
+        memory.store_messages("pm/a", vec![Message::user("a")], 0).await;
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        memory.store_messages("pm/b", vec![Message::user("b")], 0).await;
+
+        let scopes: Vec<String> = memory.diagnostics().await.into_iter().map(|d| d.0).collect();
+        assert_eq!(scopes, vec!["pm/b", "pm/tracker:@board"]);
+    }
+
+    use crate::message::ToolCall;
+    use crate::tool::Tool;
+
+    /// Echoes its arguments back, like a structured-report finish tool.
+    struct EchoFinish;
+
+    #[async_trait]
+    impl Tool for EchoFinish {
+        fn name(&self) -> &str {
+            "finish"
+        }
+        fn description(&self) -> &str {
+            "test"
+        }
+        fn definition(&self) -> crate::tool::ToolDefinition {
+            crate::tool::ToolDefinition::new("finish", "test")
+        }
+        async fn execute(
+            &self,
+            arguments: serde_json::Value,
+        ) -> Result<crate::tool::ToolOutput, Error> {
+            Ok(crate::tool::ToolOutput::success(arguments.to_string()))
+        }
+    }
+
+    fn finish_tools() -> Arc<ToolRegistry> {
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(EchoFinish));
+        Arc::new(tools)
+    }
+
+    fn finish_call(summary: &str) -> CompletionResponse {
+        let call = ToolCall::new("call_1", "finish", serde_json::json!({"summary": summary}));
+        CompletionResponse {
+            message: Message::assistant_with_tool_calls("", vec![call]),
+            ..ok_response("")
+        }
+    }
+
+    #[tokio::test]
+    async fn finish_tool_output_ends_the_run() {
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(finish_call("all done"));
+        provider.queue_response("never requested");
+        let mock = Arc::clone(&provider);
+        let provider: Arc<dyn Provider> = provider;
+
+        let config = AgentConfig::new("coder").with_finish_tool("finish");
+        let result =
+            Agent::run_once(provider, finish_tools(), config, vec![Message::user("fix it")])
+                .await
+                .unwrap();
+        assert_eq!(result, r#"{"summary":"all done"}"#);
+        assert_eq!(mock.captured_requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn free_text_reply_is_reminded_once_to_finish() {
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_response("here is my answer");
+        provider.queue_raw_response(finish_call("here is my answer"));
+        let mock = Arc::clone(&provider);
+        let provider: Arc<dyn Provider> = provider;
+
+        let config = AgentConfig::new("coder").with_finish_tool("finish");
+        let result =
+            Agent::run_once(provider, finish_tools(), config, vec![Message::user("fix it")])
+                .await
+                .unwrap();
+        assert_eq!(result, r#"{"summary":"here is my answer"}"#);
+        let reminder = mock.last_request().unwrap().messages.last().unwrap().clone();
+        assert_eq!(reminder.role, Role::User);
+        assert!(reminder.content.to_string_lossy().contains("`finish`"));
+
+        // A second free-text reply is accepted as the result
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_response("first");
+        provider.queue_response("second");
+        let provider: Arc<dyn Provider> = provider;
+        let config = AgentConfig::new("coder").with_finish_tool("finish");
+        let result =
+            Agent::run_once(provider, finish_tools(), config, vec![Message::user("fix it")])
+                .await
+                .unwrap();
+        assert_eq!(result, "second");
+
+        // Without the tool registered there is nothing to remind about
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_response("plain");
+        let provider: Arc<dyn Provider> = provider;
+        let config = AgentConfig::new("coder").with_finish_tool("finish");
+        let result = Agent::run_once(provider, empty_tools(), config, vec![Message::user("hi")])
+            .await
+            .unwrap();
+        assert_eq!(result, "plain");
+    }
+
+    /// Counts its executions; read-only or not as configured.
+    struct Counting {
+        name: &'static str,
+        read_only: bool,
+        runs: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for Counting {
+        fn name(&self) -> &str {
+            self.name
+        }
+        fn description(&self) -> &str {
+            "test"
+        }
+        fn definition(&self) -> crate::tool::ToolDefinition {
+            crate::tool::ToolDefinition::new(self.name, "test")
+        }
+        fn is_read_only(&self) -> bool {
+            self.read_only
+        }
+        async fn execute(&self, _: serde_json::Value) -> Result<crate::tool::ToolOutput, Error> {
+            let run = self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            Ok(crate::tool::ToolOutput::success(format!("run {}", run)))
+        }
+    }
+
+    /// Runs of `name` when the model calls it twice with the same arguments.
+    async fn runs_for_repeated_call(name: &'static str, read_only: bool) -> usize {
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(Counting {
+            name,
+            read_only,
+            runs: Arc::clone(&runs),
+        }));
+
+        let call = |id: &str| CompletionResponse {
+            message: Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall::new(id, name, serde_json::json!({"path": "a"}))],
+            ),
+            ..ok_response("")
+        };
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(call("call_1"));
+        provider.queue_raw_response(call("call_2"));
+        provider.queue_response("done");
+        let provider: Arc<dyn Provider> = provider;
+
+        let result = Agent::run_once(
+            provider,
+            Arc::new(tools),
+            AgentConfig::new("coder"),
+            vec![Message::user("go")],
+        )
+        .await
+        .unwrap();
+        assert_eq!(result, "done");
+        runs.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn repeated_read_only_call_is_served_from_cache() {
+        assert_eq!(runs_for_repeated_call("read_file", true).await, 1);
+    }
+
+    #[tokio::test]
+    async fn repeated_mutating_call_runs_again() {
+        assert_eq!(runs_for_repeated_call("write_file", false).await, 2);
+    }
+
+    #[tokio::test]
+    async fn read_batched_with_a_write_runs_again() {
+        let reads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(Counting {
+            name: "read_file",
+            read_only: true,
+            runs: Arc::clone(&reads),
+        }));
+        tools.register(Arc::new(Counting {
+            name: "write_file",
+            read_only: false,
+            runs: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+        }));
+
+        let args = serde_json::json!({"path": "a"});
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(CompletionResponse {
+            message: Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall::new("call_1", "read_file", args.clone())],
+            ),
+            ..ok_response("")
+        });
+        provider.queue_raw_response(CompletionResponse {
+            message: Message::assistant_with_tool_calls(
+                "",
+                vec![
+                    ToolCall::new("call_2", "write_file", args.clone()),
+                    ToolCall::new("call_3", "read_file", args.clone()),
+                ],
+            ),
+            ..ok_response("")
+        });
+        provider.queue_response("done");
+        let provider: Arc<dyn Provider> = provider;
+
+        Agent::run_once(
+            provider,
+            Arc::new(tools),
+            AgentConfig::new("coder"),
+            vec![Message::user("go")],
+        )
+        .await
+        .unwrap();
+        assert_eq!(reads.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn repeated_clock_call_runs_again() {
+        // Not read-only, so its result is never cached
+        assert_eq!(runs_for_repeated_call("datetime", false).await, 2);
+    }
+
+    #[tokio::test]
+    async fn regrounding_restates_the_task() {
+        use crate::grounding::Regrounding;
+
+        // A long tool batch: the task comes from the last user message
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(finish_call("step"));
+        provider.queue_response("done");
+        let mock = Arc::clone(&provider);
+        let provider: Arc<dyn Provider> = provider;
+        let config = AgentConfig::new("coder")
+            .with_regrounding(Regrounding::new().with_pause_threshold(Duration::ZERO));
+        let result =
+            Agent::run_once(provider, finish_tools(), config, vec![Message::user("fix it")])
+                .await
+                .unwrap();
+        assert_eq!(result, "done");
+        let note = mock.last_request().unwrap().messages.last().unwrap().clone();
+        assert_eq!(note.role, Role::User);
+        let text = note.content.to_string_lossy();
+        assert!(text.starts_with("[Re-grounding after a 0s pause]"));
+        assert!(text.contains("<original-request>\nfix it\n</original-request>"));
+
+        // Compaction drops the task from the conversation
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(finish_call("step"));
+        provider.queue_response("done");
+        let mock = Arc::clone(&provider);
+        let provider: Arc<dyn Provider> = provider;
+        let compactor = Arc::new(MockCompactor::new());
+        compactor.queue_observe(Ok("- saw a thing".to_string()));
+        let config = AgentConfig::new("coder")
+            .with_compactor(compactor)
+            .with_observation_config(ObservationConfig {
+                message_threshold_bytes: 1_000,
+                observation_threshold_bytes: 1_000_000,
+                preserve_recent: 2,
+                hysteresis: 1.0,
+                context_budget_bytes: None,
+            })
+            .with_regrounding(Regrounding::new().with_goal("fix the parser"));
+        let mut context = vec![Message::user("fix the parser")];
+        context.extend(long_messages(4));
+        Agent::run_once(provider, finish_tools(), config, context)
+            .await
+            .unwrap();
+        let note = mock.last_request().unwrap().messages.last().unwrap().clone();
+        let text = note.content.to_string_lossy();
+        assert!(text.starts_with("[Re-grounding after compaction]"));
+        assert!(text.contains("<original-request>\nfix the parser\n</original-request>"));
+        assert!(text.contains("- saw a thing"));
+    }
 }
@@ -0,0 +1,175 @@
+//! Attaching files to a conversation.
+//!
+//! Large documents such as PDFs and datasets are uploaded to the provider's
+//! file store and sent as a [`FileRef`](crate::FileRef), so each request
+//! carries an ID rather than the whole file. When the provider has no files
+//! API, or the upload fails, text files are inlined as a `<file>` block
+//! instead; other files are an error.
+
+use std::path::Path;
+
+use crate::error::Error;
+use crate::message::TypedContent;
+use crate::provider::Provider;
+
+/// Largest file accepted for attachment (Anthropic's upload limit, the
+/// smallest of the providers).
+pub const MAX_ATTACHMENT_SIZE: u64 = 500 * 1024 * 1024;
+
+/// MIME type of a file: from its content for binary formats, from its
+/// extension for text.
+pub fn detect_media_type(path: &Path, bytes: &[u8]) -> String {
+    if let Some(kind) = infer::get(bytes) {
+        return kind.mime_type().to_string();
+    }
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let known = match extension.as_deref() {
+        Some("csv") => Some("text/csv"),
+        Some("tsv") => Some("text/tab-separated-values"),
+        Some("md" | "markdown") => Some("text/markdown"),
+        Some("html" | "htm") => Some("text/html"),
+        Some("json") => Some("application/json"),
+        Some("xml") => Some("application/xml"),
+        _ => None,
+    };
+    match known {
+        Some(media_type) => media_type.to_string(),
+        None if std::str::from_utf8(bytes).is_ok() => "text/plain".to_string(),
+        None => "application/octet-stream".to_string(),
+    }
+}
+
+/// Attach the file at `path`, uploading it to `provider` when it supports
+/// uploads and falling back to inlining text files.
+pub async fn attach_file(provider: &dyn Provider, path: &Path) -> Result<TypedContent, Error> {
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| Error::Unknown(format!("Cannot read {}: {}", path.display(), e)))?;
+    if !metadata.is_file() {
+        return Err(Error::invalid_request(format!(
+            "Not a file: {}",
+            path.display()
+        )));
+    }
+    if metadata.len() > MAX_ATTACHMENT_SIZE {
+        return Err(Error::invalid_request(format!(
+            "File exceeds {} MB limit ({:.1} MB): {}",
+            MAX_ATTACHMENT_SIZE / (1024 * 1024),
+            metadata.len() as f64 / (1024.0 * 1024.0),
+            path.display()
+        )));
+    }
+
+    let mut bytes = tokio::fs::read(path)
+        .await
+        .map_err(|e| Error::Unknown(format!("Failed to read {}: {}", path.display(), e)))?;
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+    let media_type = detect_media_type(path, &bytes);
+    let is_text = std::str::from_utf8(&bytes).is_ok();
+
+    let mut upload_error = None;
+    if provider.supports_file_upload() {
+        // Keep text around in case the upload fails and it must be inlined
+        let upload = if is_text {
+            bytes.clone()
+        } else {
+            std::mem::take(&mut bytes)
+        };
+        match provider.upload_file(&filename, &media_type, upload).await {
+            Ok(file) => {
+                tracing::debug!(file = %filename, id = %file.id, "Uploaded attachment");
+                return Ok(TypedContent::document(file));
+            }
+            Err(e) => upload_error = Some(e),
+        }
+    }
+
+    if !is_text {
+        return Err(upload_error.unwrap_or_else(|| {
+            Error::invalid_request(format!(
+                "provider '{}' does not support file uploads, and {} is not text",
+                provider.name(),
+                filename
+            ))
+        }));
+    }
+    if let Some(e) = upload_error {
+        tracing::warn!(file = %filename, error = %e, "Upload failed, inlining attachment");
+    }
+    let text = String::from_utf8(bytes).unwrap_or_default();
+    Ok(TypedContent::text(format!(
+        "<file path=\"{}\">\n{}\n</file>",
+        path.display(),
+        text.trim_end()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+
+    #[test]
+    fn test_detect_media_type() {
+        assert_eq!(
+            detect_media_type(Path::new("report.pdf"), b"%PDF-1.7\n..."),
+            "application/pdf"
+        );
+        assert_eq!(
+            detect_media_type(Path::new("data.CSV"), b"a,b\n1,2\n"),
+            "text/csv"
+        );
+        assert_eq!(
+            detect_media_type(Path::new("notes"), b"plain words"),
+            "text/plain"
+        );
+        assert_eq!(
+            detect_media_type(Path::new("blob.bin"), &[0xff, 0xfe, 0x00, 0x81]),
+            "application/octet-stream"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attach_uploads_or_inlines() {
+        let dir = std::env::temp_dir().join("qq_test_attachment");
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("data.csv");
+        std::fs::write(&text, "a,b\n1,2\n").unwrap();
+        let binary = dir.join("blob.bin");
+        std::fs::write(&binary, [0xff, 0xfe, 0x00, 0x81]).unwrap();
+
+        let uploads = MockProvider::new().with_file_uploads();
+        match attach_file(&uploads, &text).await.unwrap() {
+            TypedContent::Document { document } => {
+                assert_eq!(document.id, "file-1");
+                assert_eq!(document.filename, "data.csv");
+                assert_eq!(document.media_type, "text/csv");
+                assert_eq!(document.size, 8);
+            }
+            other => panic!("expected a document, got {:?}", other),
+        }
+
+        // Without uploads, text is inlined and anything else is refused
+        let plain = MockProvider::new();
+        match attach_file(&plain, &text).await.unwrap() {
+            TypedContent::Text { text: block } => {
+                assert_eq!(
+                    block,
+                    format!("<file path=\"{}\">\na,b\n1,2\n</file>", text.display())
+                );
+            }
+            other => panic!("expected text, got {:?}", other),
+        }
+        let err = attach_file(&plain, &binary).await.unwrap_err();
+        assert!(err.to_string().contains("does not support file uploads"));
+        assert!(attach_file(&plain, &dir).await.is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
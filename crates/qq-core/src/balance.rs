@@ -0,0 +1,444 @@
+//! Weighted round-robin across interchangeable provider endpoints.
+//!
+//! A provider entry can list several endpoints that serve the same models:
+//! a handful of Ollama hosts, or one API with several keys.
+//! [`BalancedProvider`] spreads requests across them in proportion to their
+//! weights, fails over to the next endpoint when one errors, and benches an
+//! endpoint for a cooldown after repeated failures. With health checks on, a
+//! benched endpoint must answer a one-token probe before it gets traffic
+//! again.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use crate::error::Error;
+use crate::message::{FileRef, Message, StreamChunk};
+use crate::provider::{CompletionRequest, CompletionResponse, Provider, StreamResult};
+
+/// One endpoint of a balanced provider.
+pub struct Endpoint {
+    /// Name used in logs (the configured name or the endpoint's URL)
+    pub label: String,
+    pub provider: Arc<dyn Provider>,
+    /// Share of requests relative to the other endpoints; 0 means standby,
+    /// used only when every weighted endpoint is unavailable
+    pub weight: u32,
+}
+
+impl Endpoint {
+    pub fn new(label: impl Into<String>, provider: Arc<dyn Provider>, weight: u32) -> Self {
+        Self {
+            label: label.into(),
+            provider,
+            weight,
+        }
+    }
+}
+
+/// When an endpoint is taken out of rotation and how it comes back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BalancePolicy {
+    /// Consecutive failures that bench an endpoint
+    pub max_failures: u32,
+    /// How long a benched endpoint sits out
+    pub cooldown: Duration,
+    /// Probe a benched endpoint once its cooldown ends, instead of sending it
+    /// real traffic straight away
+    pub health_check: bool,
+}
+
+impl Default for BalancePolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 2,
+            cooldown: Duration::from_secs(30),
+            health_check: false,
+        }
+    }
+}
+
+/// Whether an error says something about the endpoint rather than the
+/// request, so another endpoint might succeed.
+fn is_endpoint_failure(error: &Error) -> bool {
+    match error {
+        Error::Api { status, .. } => *status >= 500 || *status == 429,
+        e => e.is_retryable() || e.is_auth_error(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    /// Smooth weighted round-robin counter
+    current: i64,
+    failures: u32,
+    benched_until: Option<Instant>,
+}
+
+/// Failure tracking shared with in-flight streams.
+struct Health {
+    policy: BalancePolicy,
+    states: Mutex<Vec<EndpointState>>,
+}
+
+impl Health {
+    fn new(policy: BalancePolicy, endpoints: usize) -> Arc<Self> {
+        Arc::new(Self {
+            policy,
+            states: Mutex::new((0..endpoints).map(|_| EndpointState::default()).collect()),
+        })
+    }
+
+    fn record_success(&self, index: usize) {
+        let mut states = self.states.lock().unwrap();
+        states[index].failures = 0;
+        states[index].benched_until = None;
+    }
+
+    /// Count a failure; returns whether it benched the endpoint.
+    fn record_failure(&self, index: usize) -> bool {
+        let mut states = self.states.lock().unwrap();
+        let state = &mut states[index];
+        state.failures += 1;
+        if state.failures < self.policy.max_failures.max(1) {
+            return false;
+        }
+        state.benched_until = Some(Instant::now() + self.policy.cooldown);
+        true
+    }
+}
+
+/// Provider that rotates requests across [`Endpoint`]s by weight.
+///
+/// Model, context window and other metadata come from the first endpoint;
+/// endpoints are expected to be interchangeable.
+pub struct BalancedProvider {
+    endpoints: Vec<Endpoint>,
+    health: Arc<Health>,
+}
+
+impl BalancedProvider {
+    /// Balance across `endpoints`, which must not be empty.
+    pub fn new(endpoints: Vec<Endpoint>) -> Self {
+        assert!(!endpoints.is_empty(), "BalancedProvider needs an endpoint");
+        let health = Health::new(BalancePolicy::default(), endpoints.len());
+        Self { endpoints, health }
+    }
+
+    pub fn with_policy(mut self, policy: BalancePolicy) -> Self {
+        self.health = Health::new(policy, self.endpoints.len());
+        self
+    }
+
+    fn first(&self) -> &dyn Provider {
+        self.endpoints[0].provider.as_ref()
+    }
+
+    /// Endpoints to try for the next request, best first: the round-robin
+    /// pick, then the other endpoints in rotation by weight. When none are
+    /// in rotation, the benched ones in the order they recover.
+    async fn attempt_order(&self) -> Vec<usize> {
+        let health_check = self.health.policy.health_check;
+        if health_check {
+            self.probe_recovered().await;
+        }
+
+        // A benched endpoint rejoins when its cooldown ends, or with health
+        // checks on, once a probe clears it
+        let now = Instant::now();
+        let mut states = self.health.states.lock().unwrap();
+        let mut ready: Vec<usize> = (0..self.endpoints.len())
+            .filter(|&i| match states[i].benched_until {
+                None => true,
+                Some(until) => !health_check && until <= now,
+            })
+            .collect();
+
+        if ready.is_empty() {
+            let mut benched: Vec<usize> = (0..self.endpoints.len()).collect();
+            benched.sort_by_key(|&i| states[i].benched_until);
+            return benched;
+        }
+
+        // Smooth weighted round-robin: every ready endpoint gains its weight,
+        // the leader is picked and pays back the total.
+        let total: i64 = ready.iter().map(|&i| self.endpoints[i].weight as i64).sum();
+        if total > 0 {
+            for &i in &ready {
+                states[i].current += self.endpoints[i].weight as i64;
+            }
+            let pick = ready
+                .iter()
+                .copied()
+                .filter(|&i| self.endpoints[i].weight > 0)
+                .max_by_key(|&i| (states[i].current, std::cmp::Reverse(i)))
+                .unwrap_or(ready[0]);
+            states[pick].current -= total;
+            ready.retain(|&i| i != pick);
+            ready.sort_by_key(|&i| std::cmp::Reverse(self.endpoints[i].weight));
+            ready.insert(0, pick);
+        }
+        ready
+    }
+
+    /// Probe endpoints whose cooldown has ended; failures bench them again.
+    async fn probe_recovered(&self) {
+        let now = Instant::now();
+        let due: Vec<usize> = {
+            let states = self.health.states.lock().unwrap();
+            (0..self.endpoints.len())
+                .filter(|&i| states[i].benched_until.is_some_and(|until| until <= now))
+                .collect()
+        };
+        for i in due {
+            let endpoint = &self.endpoints[i];
+            let probe = CompletionRequest::new(vec![Message::user("ping")]).with_max_tokens(1);
+            match endpoint.provider.complete(probe).await {
+                Ok(_) => {
+                    tracing::info!(
+                        endpoint = %endpoint.label,
+                        "Provider endpoint passed health check"
+                    );
+                    self.health.record_success(i);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        endpoint = %endpoint.label,
+                        error = %e,
+                        "Provider endpoint failed health check"
+                    );
+                    self.health.record_failure(i);
+                }
+            }
+        }
+    }
+
+    /// Run `call` against endpoints in attempt order until one succeeds or
+    /// fails for a reason another endpoint wouldn't fix. Returns the result
+    /// and the endpoint that produced it; success isn't recorded here.
+    async fn with_failover<T, F, Fut>(&self, mut call: F) -> Result<(T, usize), Error>
+    where
+        F: FnMut(Arc<dyn Provider>) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut last_error = None;
+        for i in self.attempt_order().await {
+            let endpoint = &self.endpoints[i];
+            match call(Arc::clone(&endpoint.provider)).await {
+                Ok(value) => return Ok((value, i)),
+                Err(e) if is_endpoint_failure(&e) => {
+                    let benched = self.health.record_failure(i);
+                    tracing::warn!(
+                        endpoint = %endpoint.label,
+                        error = %e,
+                        benched,
+                        "Provider endpoint failed; trying the next one"
+                    );
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::config("no provider endpoints available")))
+    }
+}
+
+#[async_trait]
+impl Provider for BalancedProvider {
+    fn name(&self) -> &str {
+        self.first().name()
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.first().default_model()
+    }
+
+    fn available_models(&self) -> Vec<&str> {
+        self.first().available_models()
+    }
+
+    fn include_tool_reasoning(&self) -> bool {
+        self.first().include_tool_reasoning()
+    }
+
+    fn context_window(&self) -> Option<u32> {
+        self.first().context_window()
+    }
+
+    async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        let (vectors, i) = self
+            .with_failover(|p| async move { p.embed(model, inputs).await })
+            .await?;
+        self.health.record_success(i);
+        Ok(vectors)
+    }
+
+    /// Uploaded files belong to one account, so only single-endpoint pools
+    /// can upload.
+    fn supports_file_upload(&self) -> bool {
+        self.endpoints.len() == 1 && self.first().supports_file_upload()
+    }
+
+    async fn upload_file(
+        &self,
+        filename: &str,
+        media_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        if !self.supports_file_upload() {
+            return Err(Error::invalid_request(
+                "file uploads aren't supported across several provider endpoints",
+            ));
+        }
+        self.first().upload_file(filename, media_type, bytes).await
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, Error> {
+        let (response, i) = self
+            .with_failover(|p| {
+                let request = request.clone();
+                async move { p.complete(request).await }
+            })
+            .await?;
+        self.health.record_success(i);
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamResult, Error> {
+        let (stream, i) = self
+            .with_failover(|p| {
+                let request = request.clone();
+                async move { p.stream(request).await }
+            })
+            .await?;
+
+        // Output has reached the caller by the time a stream fails, so there
+        // is no failing over; the endpoint's record still counts it.
+        let health = Arc::clone(&self.health);
+        Ok(Box::pin(stream.inspect(move |item| match item {
+            Ok(StreamChunk::Done { .. }) => health.record_success(i),
+            Ok(StreamChunk::Error { .. }) => {
+                health.record_failure(i);
+            }
+            Err(e) if is_endpoint_failure(e) => {
+                health.record_failure(i);
+            }
+            _ => {}
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+
+    fn mock(name: &str) -> Arc<MockProvider> {
+        let mut provider = MockProvider::new();
+        provider.name = name.to_string();
+        Arc::new(provider)
+    }
+
+    #[tokio::test]
+    async fn test_weighted_rotation() {
+        let (a, b) = (mock("a"), mock("b"));
+        for _ in 0..6 {
+            a.queue_response("from a");
+            b.queue_response("from b");
+        }
+        let balanced = BalancedProvider::new(vec![
+            Endpoint::new("a", a.clone(), 2),
+            Endpoint::new("b", b.clone(), 1),
+        ]);
+
+        let mut replies = Vec::new();
+        for _ in 0..6 {
+            let response = balanced
+                .complete(CompletionRequest::new(vec![]))
+                .await
+                .unwrap();
+            replies.push(response.message.content.to_string_lossy());
+        }
+        // Smooth: a's share is spread out rather than sent in a burst
+        assert_eq!(
+            replies,
+            ["from a", "from b", "from a", "from a", "from b", "from a"]
+        );
+        assert_eq!((a.request_count(), b.request_count()), (4, 2));
+    }
+
+    #[tokio::test]
+    async fn test_failover_and_cooldown() {
+        let (a, b) = (mock("a"), mock("b"));
+        a.queue_error(Error::network("connection refused"));
+        a.queue_error(Error::api(503, "overloaded"));
+        for _ in 0..4 {
+            b.queue_response("from b");
+        }
+        let balanced = BalancedProvider::new(vec![
+            Endpoint::new("a", a.clone(), 1),
+            Endpoint::new("b", b.clone(), 1),
+        ])
+        .with_policy(BalancePolicy {
+            cooldown: Duration::from_secs(60),
+            ..BalancePolicy::default()
+        });
+
+        // Each failure on a falls through to b; the second benches a
+        for _ in 0..4 {
+            let response = balanced
+                .complete(CompletionRequest::new(vec![]))
+                .await
+                .unwrap();
+            assert_eq!(response.message.content.to_string_lossy(), "from b");
+        }
+        assert_eq!((a.request_count(), b.request_count()), (2, 4));
+
+        // Request errors aren't the endpoint's fault and don't fail over
+        b.queue_error(Error::invalid_request("bad schema"));
+        let err = balanced
+            .complete(CompletionRequest::new(vec![]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidRequest(_)));
+        assert_eq!(a.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_gates_recovery() {
+        let (a, b) = (mock("a"), mock("b"));
+        a.queue_error(Error::network("down"));
+        a.queue_error(Error::network("still down")); // first probe
+        a.queue_response("pong"); // second probe
+        a.queue_response("from a");
+        for _ in 0..3 {
+            b.queue_response("from b");
+        }
+        let balanced = BalancedProvider::new(vec![
+            Endpoint::new("a", a.clone(), 1),
+            Endpoint::new("b", b.clone(), 0),
+        ])
+        .with_policy(BalancePolicy {
+            max_failures: 1,
+            cooldown: Duration::ZERO,
+            health_check: true,
+        });
+
+        let mut replies = Vec::new();
+        for _ in 0..3 {
+            let response = balanced
+                .complete(CompletionRequest::new(vec![]))
+                .await
+                .unwrap();
+            replies.push(response.message.content.to_string_lossy());
+        }
+        // The standby endpoint covers until a passes its probe
+        assert_eq!(replies, ["from b", "from b", "from a"]);
+        let requests = a.captured_requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[2].max_tokens, Some(1));
+    }
+}
@@ -0,0 +1,112 @@
+//! Cancellation checkpoints for tool execution.
+//!
+//! A run's [`CancellationToken`] stops the agent loop between model calls,
+//! but a tool that was already executing used to run to completion: a
+//! directory walk on the blocking pool or a large output being summarized
+//! chunk by chunk kept going for many seconds after Ctrl+C. The runner now
+//! executes tool calls inside [`with_cancellation`], and long-running tools
+//! take a [`Checkpoint`] when they start and check it between units of work.
+//! [`crate::execute_tool_dispatch`] carries the checkpoint onto blocking
+//! threads and returns [`Error::Cancelled`] as soon as the token fires.
+
+use std::future::Future;
+
+pub use tokio_util::sync::CancellationToken;
+
+use crate::Error;
+
+tokio::task_local! {
+    static TOOL_CANCEL: CancellationToken;
+}
+
+/// Run `future` with `token` as the cancellation token its tool calls see.
+/// Without a token, calls inside run as if never cancelled.
+pub async fn with_cancellation<F: Future>(
+    token: Option<CancellationToken>,
+    future: F,
+) -> F::Output {
+    match token {
+        Some(token) => TOOL_CANCEL.scope(token, future).await,
+        None => future.await,
+    }
+}
+
+/// Handle for checking, between units of work, whether the run that
+/// started a tool call was cancelled. Cheap to clone and `Send`, so it can
+/// move into `spawn_blocking` closures.
+#[derive(Debug, Clone, Default)]
+pub struct Checkpoint(Option<CancellationToken>);
+
+impl Checkpoint {
+    /// The checkpoint of the current tool call; never cancelled outside
+    /// [`with_cancellation`].
+    pub fn current() -> Self {
+        Self(TOOL_CANCEL.try_with(CancellationToken::clone).ok())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// `Err(Error::Cancelled)` once the run is cancelled.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Await `future` unless the run is cancelled first.
+    pub async fn run<T>(&self, future: impl Future<Output = T>) -> Result<T, Error> {
+        match self.0 {
+            Some(ref token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => Err(Error::Cancelled),
+                output = future => Ok(output),
+            },
+            None => Ok(future.await),
+        }
+    }
+
+    /// Run `future` with this checkpoint's token, e.g. on a blocking thread
+    /// where the caller's task-local token isn't visible.
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        with_cancellation(self.0.clone(), future).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checkpoint_follows_scoped_token() {
+        assert!(!Checkpoint::current().is_cancelled());
+
+        let token = CancellationToken::new();
+        with_cancellation(Some(token.clone()), async {
+            let checkpoint = Checkpoint::current();
+            assert!(checkpoint.check().is_ok());
+            token.cancel();
+            assert!(matches!(checkpoint.check(), Err(Error::Cancelled)));
+
+            // A future that never finishes is abandoned once cancelled
+            let result = checkpoint.run(std::future::pending::<()>()).await;
+            assert!(matches!(result, Err(Error::Cancelled)));
+        })
+        .await;
+
+        // Blocking threads see the token through an explicit scope
+        let token = CancellationToken::new();
+        token.cancel();
+        let checkpoint = with_cancellation(Some(token), async { Checkpoint::current() }).await;
+        let handle = tokio::runtime::Handle::current();
+        let seen = tokio::task::spawn_blocking(move || {
+            handle.block_on(checkpoint.scope(async { Checkpoint::current().is_cancelled() }))
+        })
+        .await
+        .unwrap();
+        assert!(seen);
+    }
+}
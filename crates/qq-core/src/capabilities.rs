@@ -0,0 +1,809 @@
+//! Provider capability probing and graceful degradation.
+//!
+//! OpenAI-compatible servers differ widely in what they accept: some have no
+//! native tool calling, some reject a `system` role, some only emit one tool
+//! call per turn, and many cap `max_tokens` well below what callers ask for.
+//! [`probe`] discovers these limits with a handful of tiny requests, and
+//! [`AdaptiveProvider`] wraps a provider so the rest of the stack keeps
+//! working against the full [`Provider`] contract:
+//!
+//! - no native tools: tool definitions are described in the system prompt and
+//!   `<tool_call>` blocks in the reply are parsed back into [`ToolCall`]s
+//! - no parallel tools: only the first tool call of a turn is kept
+//! - no system role: system messages are folded into the first user message
+//! - output cap: `max_tokens` is clamped to the discovered limit
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use crate::error::Error;
+use crate::message::{FileRef, FinishReason, Message, Role, StreamChunk, ToolCall, Usage};
+use crate::provider::{CompletionRequest, CompletionResponse, Provider, StreamResult};
+use crate::tool::{PropertySchema, ToolDefinition, ToolParameters};
+
+/// What a provider/model pair supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderCapabilities {
+    /// Native tool calling via the `tools` request field.
+    pub tool_calling: bool,
+    /// More than one tool call per assistant turn.
+    pub parallel_tool_calls: bool,
+    /// Messages with the `system` role.
+    pub system_role: bool,
+    /// Largest accepted `max_tokens`, if the server enforces one.
+    pub max_output_tokens: Option<u32>,
+}
+
+impl Default for ProviderCapabilities {
+    /// Full support — what hosted providers offer.
+    fn default() -> Self {
+        Self {
+            tool_calling: true,
+            parallel_tool_calls: true,
+            system_role: true,
+            max_output_tokens: None,
+        }
+    }
+}
+
+impl ProviderCapabilities {
+    /// Whether requests and responses can pass through unchanged.
+    pub fn is_full(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+// =============================================================================
+// Probing
+// =============================================================================
+
+const PROBE_TOOL: &str = "probe_echo";
+
+/// `max_tokens` sent by the output-cap probe; servers with a lower cap reject it.
+const PROBE_MAX_TOKENS: u32 = 1_000_000;
+
+fn probe_tool() -> ToolDefinition {
+    ToolDefinition::new(PROBE_TOOL, "Echo the given text back.").with_parameters(
+        ToolParameters::new().add_property("text", PropertySchema::string("Text to echo"), true),
+    )
+}
+
+fn probe_request(messages: Vec<Message>, model: Option<&str>) -> CompletionRequest {
+    let mut request = CompletionRequest::new(messages)
+        .with_stream(false)
+        .with_max_tokens(512);
+    if let Some(m) = model {
+        request = request.with_model(m);
+    }
+    request
+}
+
+/// Whether an error means the server rejected the request shape (as opposed
+/// to being unreachable, unauthorized, or overloaded).
+pub(crate) fn is_rejection(e: &Error) -> bool {
+    matches!(e, Error::Api { .. } | Error::InvalidRequest(_) | Error::Serialization(_))
+}
+
+/// Run one probe request, mapping rejections to `Ok(None)`.
+async fn try_probe(
+    provider: &dyn Provider,
+    request: CompletionRequest,
+) -> Result<Option<CompletionResponse>, Error> {
+    match provider.complete(request).await {
+        Ok(response) => Ok(Some(response)),
+        Err(e) if is_rejection(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Probe a provider's capabilities with a few small requests.
+///
+/// Returns an error only when the server could not be reached at all; in that
+/// case nothing should be cached.
+pub async fn probe(
+    provider: &dyn Provider,
+    model: Option<&str>,
+) -> Result<ProviderCapabilities, Error> {
+    let system_role = try_probe(
+        provider,
+        probe_request(
+            vec![
+                Message::system("Reply with the single word: ok"),
+                Message::user("ping"),
+            ],
+            model,
+        ),
+    )
+    .await?
+    .is_some();
+
+    let tool_calls = |instruction: &str| {
+        probe_request(vec![Message::user(instruction)], model).with_tools(vec![probe_tool()])
+    };
+
+    let tool_calling = try_probe(
+        provider,
+        tool_calls("Call the probe_echo tool with text \"ok\". Do not reply with text."),
+    )
+    .await?
+    .is_some_and(|r| r.message.tool_calls.iter().any(|c| c.name == PROBE_TOOL));
+
+    let parallel_tool_calls = tool_calling
+        && try_probe(
+            provider,
+            tool_calls(
+                "Call the probe_echo tool twice in this single reply: once with text \"a\" \
+                 and once with text \"b\". Do not reply with text.",
+            ),
+        )
+        .await?
+        .is_some_and(|r| r.message.tool_calls.len() >= 2);
+
+    let max_output_tokens = match provider
+        .complete(
+            probe_request(vec![Message::user("Reply with the single word: ok")], model)
+                .with_max_tokens(PROBE_MAX_TOKENS),
+        )
+        .await
+    {
+        Ok(_) => None,
+        Err(e) if is_rejection(&e) => parse_max_tokens_limit(&e.to_string()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(ProviderCapabilities {
+        tool_calling,
+        parallel_tool_calls,
+        system_role,
+        max_output_tokens,
+    })
+}
+
+/// Extract the output-token cap from a rejection message such as
+/// "max_tokens must be <= 8192" or "maximum is 4096 tokens".
+///
+/// Picks the largest number below the probe value; the probe value itself
+/// and numbers above it are usually echoes of the request.
+pub fn parse_max_tokens_limit(message: &str) -> Option<u32> {
+    message
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|s| s.parse::<u32>().ok())
+        .filter(|&n| (1024..PROBE_MAX_TOKENS).contains(&n))
+        .max()
+}
+
+// =============================================================================
+// Capability cache
+// =============================================================================
+
+/// On-disk cache of probe results, keyed by server and model.
+#[derive(Debug, Clone)]
+pub struct CapabilityCache {
+    path: PathBuf,
+    key: String,
+}
+
+impl CapabilityCache {
+    pub fn new(path: impl Into<PathBuf>, key: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            key: key.into(),
+        }
+    }
+
+    fn read_all(&self) -> HashMap<String, ProviderCapabilities> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Cached capabilities for this key, if any.
+    pub fn load(&self) -> Option<ProviderCapabilities> {
+        self.read_all().remove(&self.key)
+    }
+
+    /// Store capabilities for this key, keeping other entries.
+    pub fn store(&self, caps: ProviderCapabilities) -> std::io::Result<()> {
+        let mut all = self.read_all();
+        all.insert(self.key.clone(), caps);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&all)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(&self.path, json)
+    }
+}
+
+// =============================================================================
+// Prompted tool calling
+// =============================================================================
+
+/// Build the system-prompt section that describes tools for a model without
+/// native tool calling.
+pub fn tools_prompt(tools: &[ToolDefinition], parallel: bool) -> String {
+    let mut out = String::from(
+        "## Tools\n\n\
+         You can call tools. To call one, reply with a block in exactly this form:\n\n\
+         <tool_call>\n{\"name\": \"tool_name\", \"arguments\": {\"param\": \"value\"}}\n</tool_call>\n\n",
+    );
+    if parallel {
+        out.push_str("You may include several <tool_call> blocks in one reply. ");
+    } else {
+        out.push_str("Call at most one tool per reply. ");
+    }
+    out.push_str(
+        "Stop after your tool calls; results arrive in the next message inside \
+         <tool_result> blocks. Reply without a <tool_call> block when you are done.\n\n\
+         Available tools:\n",
+    );
+    for tool in tools {
+        let params = serde_json::to_string(&tool.parameters).unwrap_or_default();
+        out.push_str(&format!(
+            "\n- {}: {}\n  parameters: {}\n",
+            tool.name, tool.description, params
+        ));
+    }
+    out
+}
+
+fn render_tool_call(call: &ToolCall) -> String {
+    let body = serde_json::json!({ "name": call.name, "arguments": call.arguments });
+    format!("<tool_call>\n{}\n</tool_call>", body)
+}
+
+static PROMPTED_CALL_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Split `<tool_call>` blocks out of a prompted-tools reply.
+///
+/// Returns the remaining text and the parsed calls. Blocks that are not valid
+/// JSON with a `name` are left in the text so the model's output isn't lost.
+/// An unterminated final block (the model stopped at the closing tag) is
+/// still parsed.
+pub fn parse_prompted_tool_calls(content: &str) -> (String, Vec<ToolCall>) {
+    const OPEN: &str = "<tool_call>";
+    const CLOSE: &str = "</tool_call>";
+
+    let mut text = String::new();
+    let mut calls = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find(OPEN) {
+        text.push_str(&rest[..start]);
+        let after_open = &rest[start + OPEN.len()..];
+        let (body, remaining) = match after_open.find(CLOSE) {
+            Some(end) => (&after_open[..end], &after_open[end + CLOSE.len()..]),
+            None => (after_open, ""),
+        };
+
+        let parsed = serde_json::from_str::<serde_json::Value>(body.trim())
+            .ok()
+            .and_then(|v| {
+                let name = v.get("name")?.as_str()?.to_string();
+                let arguments = v
+                    .get("arguments")
+                    .cloned()
+                    .unwrap_or_else(|| serde_json::json!({}));
+                Some((name, arguments))
+            });
+        match parsed {
+            Some((name, arguments)) => {
+                let id = format!(
+                    "call_prompted_{}",
+                    PROMPTED_CALL_ID.fetch_add(1, Ordering::Relaxed)
+                );
+                calls.push(ToolCall::new(id, name, arguments));
+            }
+            None => text.push_str(&rest[start..rest.len() - remaining.len()]),
+        }
+        rest = remaining;
+    }
+    text.push_str(rest);
+
+    (text.trim().to_string(), calls)
+}
+
+/// Rewrite a tool-using conversation into plain messages for a model without
+/// native tool calling.
+fn inline_tools(messages: Vec<Message>, tools: &[ToolDefinition], parallel: bool) -> Vec<Message> {
+    let prompt = tools_prompt(tools, parallel);
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+    let mut out: Vec<Message> = Vec::with_capacity(messages.len() + 1);
+    let mut prompt_placed = false;
+
+    for msg in messages {
+        match msg.role {
+            Role::System if !prompt_placed => {
+                let text = format!("{}\n\n{}", msg.content.to_string_lossy(), prompt);
+                out.push(Message::system(text));
+                prompt_placed = true;
+            }
+            Role::Assistant if !msg.tool_calls.is_empty() => {
+                let mut text = msg.content.to_string_lossy();
+                for call in &msg.tool_calls {
+                    tool_names.insert(call.id.clone(), call.name.clone());
+                    if !text.is_empty() {
+                        text.push_str("\n\n");
+                    }
+                    text.push_str(&render_tool_call(call));
+                }
+                out.push(Message::assistant(text));
+            }
+            Role::Tool => {
+                let name = msg
+                    .tool_call_id
+                    .as_ref()
+                    .and_then(|id| tool_names.get(id))
+                    .map(String::as_str)
+                    .unwrap_or("tool");
+                let block = format!(
+                    "<tool_result name=\"{}\">\n{}\n</tool_result>",
+                    name,
+                    msg.content.to_string_lossy()
+                );
+                // Merge consecutive results into one user turn
+                match out.last_mut() {
+                    Some(prev) if prev.role == Role::User && prev.tool_call_id.is_none()
+                        && prev.content.to_string_lossy().starts_with("<tool_result") =>
+                    {
+                        let merged = format!("{}\n\n{}", prev.content.to_string_lossy(), block);
+                        *prev = Message::user(merged);
+                    }
+                    _ => out.push(Message::user(block)),
+                }
+            }
+            _ => out.push(msg),
+        }
+    }
+
+    if !prompt_placed {
+        out.insert(0, Message::system(prompt));
+    }
+    out
+}
+
+/// Fold system messages into the first user message for servers that reject
+/// the `system` role.
+fn fold_system_messages(messages: Vec<Message>) -> Vec<Message> {
+    let (system, mut rest): (Vec<Message>, Vec<Message>) =
+        messages.into_iter().partition(|m| m.role == Role::System);
+    if system.is_empty() {
+        return rest;
+    }
+    let preamble = system
+        .iter()
+        .map(|m| m.content.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    match rest.iter_mut().find(|m| m.role == Role::User) {
+        Some(first_user) => {
+            let text = format!("{}\n\n---\n\n{}", preamble, first_user.content.to_string_lossy());
+            *first_user = Message::user(text);
+        }
+        None => rest.insert(0, Message::user(preamble)),
+    }
+    rest
+}
+
+// =============================================================================
+// AdaptiveProvider
+// =============================================================================
+
+/// Provider wrapper that probes capabilities on first use and adapts requests
+/// and responses to them.
+pub struct AdaptiveProvider {
+    inner: Arc<dyn Provider>,
+    cache: Option<CapabilityCache>,
+    capabilities: OnceCell<ProviderCapabilities>,
+}
+
+impl AdaptiveProvider {
+    /// Wrap a provider; capabilities are probed on the first request.
+    pub fn new(inner: Arc<dyn Provider>) -> Self {
+        Self {
+            inner,
+            cache: None,
+            capabilities: OnceCell::new(),
+        }
+    }
+
+    /// Persist probe results so later sessions skip probing.
+    pub fn with_cache(mut self, cache: CapabilityCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Use known capabilities instead of probing.
+    pub fn with_capabilities(self, caps: ProviderCapabilities) -> Self {
+        let _ = self.capabilities.set(caps);
+        self
+    }
+
+    /// Capabilities in effect, probing (or reading the cache) on first call.
+    pub async fn capabilities(&self) -> ProviderCapabilities {
+        *self
+            .capabilities
+            .get_or_init(|| async {
+                if let Some(caps) = self.cache.as_ref().and_then(|c| c.load()) {
+                    return caps;
+                }
+                match probe(self.inner.as_ref(), self.inner.default_model()).await {
+                    Ok(caps) => {
+                        tracing::info!(
+                            provider = self.inner.name(),
+                            model = ?self.inner.default_model(),
+                            ?caps,
+                            "Probed provider capabilities"
+                        );
+                        if let Some(ref cache) = self.cache {
+                            if let Err(e) = cache.store(caps) {
+                                tracing::warn!(error = %e, "Failed to cache provider capabilities");
+                            }
+                        }
+                        caps
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            provider = self.inner.name(),
+                            error = %e,
+                            "Capability probe failed; assuming full support"
+                        );
+                        ProviderCapabilities::default()
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Rewrite a request for the given capabilities. Returns the request and
+    /// whether tool calls must be parsed out of the reply text.
+    fn adapt_request(
+        caps: &ProviderCapabilities,
+        mut request: CompletionRequest,
+    ) -> (CompletionRequest, bool) {
+        let prompted = !caps.tool_calling && !request.tools.is_empty();
+        if prompted {
+            let tools = std::mem::take(&mut request.tools);
+            request.messages = inline_tools(
+                std::mem::take(&mut request.messages),
+                &tools,
+                caps.parallel_tool_calls,
+            );
+        }
+        if !caps.system_role {
+            request.messages = fold_system_messages(std::mem::take(&mut request.messages));
+        }
+        if let (Some(cap), Some(requested)) = (caps.max_output_tokens, request.max_tokens) {
+            request.max_tokens = Some(requested.min(cap));
+        }
+        (request, prompted)
+    }
+}
+
+#[async_trait]
+impl Provider for AdaptiveProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.inner.default_model()
+    }
+
+    fn available_models(&self) -> Vec<&str> {
+        self.inner.available_models()
+    }
+
+    fn include_tool_reasoning(&self) -> bool {
+        self.inner.include_tool_reasoning()
+    }
+
+    fn context_window(&self) -> Option<u32> {
+        self.inner.context_window()
+    }
+
+    async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        self.inner.embed(model, inputs).await
+    }
+
+    fn supports_file_upload(&self) -> bool {
+        self.inner.supports_file_upload()
+    }
+
+    async fn upload_file(
+        &self,
+        filename: &str,
+        media_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        self.inner.upload_file(filename, media_type, bytes).await
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, Error> {
+        let caps = self.capabilities().await;
+        if caps.is_full() {
+            return self.inner.complete(request).await;
+        }
+
+        let (request, prompted) = Self::adapt_request(&caps, request);
+        let mut response = self.inner.complete(request).await?;
+
+        if prompted {
+            let (text, calls) = parse_prompted_tool_calls(&response.message.content.to_string_lossy());
+            if !calls.is_empty() {
+                response.message = Message::assistant_with_tool_calls(text, calls);
+                response.finish_reason = FinishReason::ToolCalls;
+            }
+        }
+        if !caps.parallel_tool_calls {
+            response.message.tool_calls.truncate(1);
+        }
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamResult, Error> {
+        let caps = self.capabilities().await;
+        if caps.is_full() {
+            return self.inner.stream(request).await;
+        }
+
+        let (request, prompted) = Self::adapt_request(&caps, request);
+        let stream = self.inner.stream(request).await?;
+
+        if prompted {
+            // Tool calls can only be recognized once the reply is complete,
+            // so buffer the text and replay it with the parsed calls.
+            return Ok(Box::pin(
+                futures::stream::once(collect_prompted(stream, caps.parallel_tool_calls))
+                    .flat_map(|chunks| futures::stream::iter(chunks.into_iter().map(Ok))),
+            ));
+        }
+
+        if caps.parallel_tool_calls {
+            return Ok(stream);
+        }
+
+        // Drop every tool call after the first
+        Ok(Box::pin(
+            stream
+                .scan(0usize, |calls_seen, item| {
+                    let keep = match &item {
+                        Ok(StreamChunk::ToolCallStart { .. }) => {
+                            *calls_seen += 1;
+                            *calls_seen <= 1
+                        }
+                        Ok(StreamChunk::ToolCallDelta { .. }) => *calls_seen <= 1,
+                        _ => true,
+                    };
+                    futures::future::ready(Some(keep.then_some(item)))
+                })
+                .filter_map(futures::future::ready),
+        ))
+    }
+}
+
+/// Drain a prompted-tools stream and re-emit it with parsed tool calls.
+async fn collect_prompted(mut stream: StreamResult, parallel: bool) -> Vec<StreamChunk> {
+    let mut out = Vec::new();
+    let mut content = String::new();
+    let mut usage: Option<Usage> = None;
+    let mut finish_reason = None;
+
+    while let Some(item) = stream.next().await {
+        match item {
+            Ok(StreamChunk::Delta { content: delta }) => content.push_str(&delta),
+            Ok(StreamChunk::Done {
+                usage: u,
+                finish_reason: f,
+            }) => {
+                usage = u;
+                finish_reason = f;
+            }
+            Ok(chunk) => out.push(chunk),
+            Err(e) => {
+                out.push(StreamChunk::Error {
+                    message: e.to_string(),
+                });
+                return out;
+            }
+        }
+    }
+
+    let (text, mut calls) = parse_prompted_tool_calls(&content);
+    if !parallel {
+        calls.truncate(1);
+    }
+    if !text.is_empty() {
+        out.push(StreamChunk::Delta { content: text });
+    }
+    if !calls.is_empty() {
+        finish_reason = Some(FinishReason::ToolCalls);
+    }
+    for call in calls {
+        out.push(StreamChunk::ToolCallStart {
+            id: call.id,
+            name: call.name,
+        });
+        out.push(StreamChunk::ToolCallDelta {
+            arguments: call.arguments.to_string(),
+        });
+    }
+    out.push(StreamChunk::Done {
+        usage,
+        finish_reason,
+    });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+
+    fn limited() -> ProviderCapabilities {
+        ProviderCapabilities {
+            tool_calling: false,
+            parallel_tool_calls: false,
+            system_role: false,
+            max_output_tokens: Some(2048),
+        }
+    }
+
+    #[test]
+    fn test_parse_prompted_tool_calls() {
+        let reply = "Let me look.\n<tool_call>\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"a.rs\"}}\n</tool_call>\n\
+                     <tool_call>{\"name\": \"list_files\"}";
+        let (text, calls) = parse_prompted_tool_calls(reply);
+
+        assert_eq!(text, "Let me look.");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].name, "read_file");
+        assert_eq!(calls[0].arguments["path"], "a.rs");
+        assert_eq!(calls[1].name, "list_files");
+        assert_ne!(calls[0].id, calls[1].id);
+    }
+
+    #[test]
+    fn test_parse_prompted_keeps_malformed_blocks() {
+        let reply = "x <tool_call>not json</tool_call> y";
+        let (text, calls) = parse_prompted_tool_calls(reply);
+        assert!(calls.is_empty());
+        assert_eq!(text, reply);
+    }
+
+    #[test]
+    fn test_parse_max_tokens_limit() {
+        assert_eq!(
+            parse_max_tokens_limit("API error: max_tokens must be <= 8192, got 1000000 (status: 400)"),
+            Some(8192)
+        );
+        assert_eq!(parse_max_tokens_limit("API error: bad request (status: 400)"), None);
+    }
+
+    #[test]
+    fn test_inline_tools_rewrites_history() {
+        let call = ToolCall::new("c1", "read_file", serde_json::json!({"path": "a"}));
+        let messages = vec![
+            Message::system("Be helpful."),
+            Message::user("Read a"),
+            Message::assistant_with_tool_calls("", vec![call]),
+            Message::tool_result("c1", "contents"),
+        ];
+        let tools = vec![ToolDefinition::new("read_file", "Read a file")];
+
+        let out = inline_tools(messages, &tools, false);
+
+        assert_eq!(out.len(), 4);
+        let system = out[0].content.to_string_lossy();
+        assert!(system.starts_with("Be helpful."));
+        assert!(system.contains("- read_file: Read a file"));
+        assert!(system.contains("at most one tool"));
+        assert!(out[2].tool_calls.is_empty());
+        assert!(out[2].content.to_string_lossy().contains("\"name\":\"read_file\""));
+        assert_eq!(out[3].role, Role::User);
+        assert!(out[3]
+            .content
+            .to_string_lossy()
+            .starts_with("<tool_result name=\"read_file\">"));
+    }
+
+    #[test]
+    fn test_fold_system_messages() {
+        let out = fold_system_messages(vec![Message::system("Rules"), Message::user("Hi")]);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].role, Role::User);
+        assert_eq!(out[0].content.to_string_lossy(), "Rules\n\n---\n\nHi");
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_complete_parses_prompted_calls() {
+        let mock = Arc::new(MockProvider::new());
+        mock.queue_response("<tool_call>{\"name\": \"a\", \"arguments\": {}}</tool_call><tool_call>{\"name\": \"b\"}</tool_call>");
+        let provider = AdaptiveProvider::new(mock.clone()).with_capabilities(limited());
+
+        let request = CompletionRequest::new(vec![Message::system("S"), Message::user("U")])
+            .with_tools(vec![ToolDefinition::new("a", "A"), ToolDefinition::new("b", "B")])
+            .with_max_tokens(100_000);
+        let response = provider.complete(request).await.unwrap();
+
+        // Parallel calls unsupported: only the first survives
+        assert_eq!(response.message.tool_calls.len(), 1);
+        assert_eq!(response.message.tool_calls[0].name, "a");
+        assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+
+        let sent = mock.last_request().unwrap();
+        assert!(sent.tools.is_empty());
+        assert_eq!(sent.max_tokens, Some(2048));
+        assert!(sent.messages.iter().all(|m| m.role != Role::System));
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_stream_emits_parsed_calls() {
+        let mock = Arc::new(MockProvider::new());
+        mock.queue_stream(vec![
+            StreamChunk::Delta { content: "Checking <tool_call>{\"name\": \"a\", ".into() },
+            StreamChunk::Delta { content: "\"arguments\": {\"x\": 1}}</tool_call>".into() },
+            StreamChunk::Done { usage: None, finish_reason: Some(FinishReason::Stop) },
+        ]);
+        let provider = AdaptiveProvider::new(mock).with_capabilities(limited());
+
+        let request = CompletionRequest::new(vec![Message::user("U")])
+            .with_tools(vec![ToolDefinition::new("a", "A")]);
+        let chunks: Vec<StreamChunk> = provider
+            .stream(request)
+            .await
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+
+        assert!(matches!(&chunks[0], StreamChunk::Delta { content } if content == "Checking"));
+        assert!(matches!(&chunks[1], StreamChunk::ToolCallStart { name, .. } if name == "a"));
+        assert!(matches!(&chunks[2], StreamChunk::ToolCallDelta { arguments } if arguments == "{\"x\":1}"));
+        assert!(matches!(
+            &chunks[3],
+            StreamChunk::Done { finish_reason: Some(FinishReason::ToolCalls), .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_full_capabilities_pass_through() {
+        let mock = Arc::new(MockProvider::new());
+        mock.queue_response("hi");
+        let provider =
+            AdaptiveProvider::new(mock.clone()).with_capabilities(ProviderCapabilities::default());
+
+        let request = CompletionRequest::new(vec![Message::system("S"), Message::user("U")])
+            .with_tools(vec![ToolDefinition::new("a", "A")]);
+        provider.complete(request).await.unwrap();
+
+        let sent = mock.last_request().unwrap();
+        assert_eq!(sent.tools.len(), 1);
+        assert_eq!(sent.messages[0].role, Role::System);
+    }
+
+    #[test]
+    fn test_capability_cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("qq-caps-{}", std::process::id()));
+        let path = dir.join("capabilities.json");
+        let a = CapabilityCache::new(&path, "http://a|m1");
+        let b = CapabilityCache::new(&path, "http://b|m2");
+
+        assert!(a.load().is_none());
+        a.store(limited()).unwrap();
+        b.store(ProviderCapabilities::default()).unwrap();
+
+        assert_eq!(a.load(), Some(limited()));
+        assert_eq!(b.load(), Some(ProviderCapabilities::default()));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,17 +1,38 @@
 //! Chunk-and-process system for handling large tool outputs.
 //!
 //! When tool outputs exceed the configured threshold, this module:
-//! 1. Splits content into manageable chunks at natural boundaries
-//! 2. Summarizes each chunk using the LLM
-//! 3. Combines summaries into a coherent result
-
+//! 1. Extracts relevant lines (see [`crate::relevance`]), returning them
+//!    directly when they fit
+//! 2. Splits content into manageable chunks at natural boundaries
+//! 3. Optionally ranks chunks by embedding similarity to the query and drops
+//!    the ones that are clearly irrelevant
+//! 4. Summarizes each chunk using the LLM
+//! 5. Combines summaries into a coherent result
+
+use std::fmt;
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
+use crate::cancel::Checkpoint;
 use crate::error::Error;
 use crate::message::Message;
 use crate::provider::{CompletionRequest, Provider};
+use crate::relevance::filter_relevant;
+
+/// Prefix of the line tools append when their output was cut short and the
+/// rest can be fetched with a `continue_from` argument. The chunker carries
+/// this line through summarization so the model can still page forward.
+pub const CONTINUATION_MARKER: &str = "Continue with continue_from=";
+
+/// The last continuation line in `content`, if any.
+pub fn continuation_line(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| line.starts_with(CONTINUATION_MARKER))
+}
 
 /// Configuration for the chunk processor.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +56,36 @@ pub struct ChunkerConfig {
     /// Process chunks in parallel.
     #[serde(default = "default_parallel")]
     pub parallel: bool,
+
+    /// Extract relevant lines (errors, task terms) before falling back to
+    /// LLM summarization.
+    #[serde(default = "default_relevance_filter")]
+    pub relevance_filter: bool,
+
+    /// Embedding model used to rank chunks by similarity to the query before
+    /// summarizing. `None` disables ranking.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Chunks whose cosine similarity to the query falls below this are
+    /// dropped without being summarized. The best chunk is always kept.
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
+
+    /// Provider serving `embedding_model`. Defaults to the summarizing
+    /// provider.
+    #[serde(skip)]
+    pub embedder: Option<Embedder>,
+}
+
+/// Provider used for chunk embeddings.
+#[derive(Clone)]
+pub struct Embedder(pub Arc<dyn Provider>);
+
+impl fmt::Debug for Embedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Embedder").field(&self.0.name()).finish()
+    }
 }
 
 fn default_enabled() -> bool {
@@ -57,6 +108,24 @@ fn default_parallel() -> bool {
     true
 }
 
+fn default_relevance_filter() -> bool {
+    true
+}
+
+fn default_min_similarity() -> f32 {
+    0.2
+}
+
+/// With ranking on, up to `max_chunks` times this many chunks are scored.
+const RANK_CANDIDATE_FACTOR: usize = 4;
+
+/// Inputs per embedding request.
+const EMBED_BATCH_SIZE: usize = 32;
+
+/// Longest text sent for one embedding; embedding models have small input
+/// limits and the start of a chunk is enough to judge it.
+const MAX_EMBED_INPUT_BYTES: usize = 16_000;
+
 impl Default for ChunkerConfig {
     fn default() -> Self {
         Self {
@@ -65,6 +134,10 @@ impl Default for ChunkerConfig {
             chunk_size_bytes: default_chunk_size_bytes(),
             max_chunks: default_max_chunks(),
             parallel: default_parallel(),
+            relevance_filter: default_relevance_filter(),
+            embedding_model: None,
+            min_similarity: default_min_similarity(),
+            embedder: None,
         }
     }
 }
@@ -104,9 +177,91 @@ impl ChunkerConfig {
         self.parallel = parallel;
         self
     }
+
+    /// Set whether to filter by relevance before summarizing.
+    pub fn with_relevance_filter(mut self, relevance_filter: bool) -> Self {
+        self.relevance_filter = relevance_filter;
+        self
+    }
+
+    /// Set the embedding model used to rank chunks.
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = Some(model.into());
+        self
+    }
+
+    /// Set the minimum similarity for a chunk to be summarized.
+    pub fn with_min_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_similarity = min_similarity;
+        self
+    }
+
+    /// Set the provider used for embeddings.
+    pub fn with_embedder(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.embedder = Some(Embedder(provider));
+        self
+    }
+}
+
+/// Cosine similarity of two vectors; 0 when either is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// `text` cut to at most [`MAX_EMBED_INPUT_BYTES`] on a grapheme boundary.
+fn embedding_input(text: &str) -> String {
+    crate::text::truncate_bytes(text, MAX_EMBED_INPUT_BYTES).to_string()
+}
+
+/// `word` in pieces of at most `max_bytes`, cut between grapheme clusters.
+///
+/// Text without whitespace (CJK prose, minified data, base64) arrives as one
+/// "word" and would otherwise become a single oversized chunk.
+fn split_oversized(word: &str, max_bytes: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = word;
+    while rest.len() > max_bytes {
+        let mut cut = crate::text::floor_grapheme_boundary(rest, max_bytes);
+        if cut == 0 {
+            // One grapheme larger than the budget still has to advance
+            cut = crate::text::ceil_grapheme_boundary(rest, 1);
+        }
+        pieces.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Large content after [`ChunkProcessor::process`].
+#[derive(Debug, Clone)]
+pub struct Processed {
+    pub text: String,
+    /// False when chunks past `max_chunks` were never processed, so part
+    /// of the content is missing from `text`.
+    pub complete: bool,
+}
+
+impl Processed {
+    fn whole(text: String) -> Self {
+        Self {
+            text,
+            complete: true,
+        }
+    }
 }
 
 /// Processor for chunking and summarizing large content.
+#[derive(Clone)]
 pub struct ChunkProcessor {
     provider: Arc<dyn Provider>,
     config: ChunkerConfig,
@@ -141,7 +296,16 @@ impl ChunkProcessor {
     /// 1. Primary split: Double newlines (paragraph boundaries)
     /// 2. Secondary split: Single newlines (line boundaries)
     /// 3. Fallback: Character split at word boundaries
+    ///
+    /// At most `max_chunks` chunks are returned.
     pub fn chunk_content(&self, content: &str) -> Vec<String> {
+        let mut chunks = self.split_chunks(content);
+        chunks.truncate(self.config.max_chunks);
+        chunks
+    }
+
+    /// All chunks of `content`, see [`Self::chunk_content`].
+    fn split_chunks(&self, content: &str) -> Vec<String> {
         let target_size = self.config.chunk_size_bytes;
         let mut chunks = Vec::new();
         let mut current_chunk = String::new();
@@ -179,7 +343,10 @@ impl ChunkProcessor {
                         }
 
                         let mut line_chunk = String::new();
-                        for word in line.split_whitespace() {
+                        let words = line
+                            .split_whitespace()
+                            .flat_map(|word| split_oversized(word, target_size));
+                        for word in words {
                             if !line_chunk.is_empty()
                                 && line_chunk.len() + word.len() + 1 > target_size
                             {
@@ -215,12 +382,75 @@ impl ChunkProcessor {
             chunks.push(current_chunk);
         }
 
-        // Respect max_chunks limit
-        if chunks.len() > self.config.max_chunks {
-            chunks.truncate(self.config.max_chunks);
+        chunks
+    }
+
+    /// Rank chunks by embedding similarity to `query`.
+    ///
+    /// Scores up to `max_chunks * RANK_CANDIDATE_FACTOR` chunks and returns
+    /// the indices of the best `max_chunks` that reach `min_similarity`, in
+    /// their original order. Returns `None` when ranking is off, there is no
+    /// query, or the embedding call fails; the caller then keeps the first
+    /// chunks as before.
+    async fn rank_chunks(&self, chunks: &[String], query: Option<&str>) -> Option<Vec<usize>> {
+        let model = self.config.embedding_model.as_deref()?;
+        let query = query.map(str::trim).filter(|q| !q.is_empty())?;
+        let provider = self
+            .config
+            .embedder
+            .as_ref()
+            .map_or(&self.provider, |e| &e.0);
+
+        let candidates = chunks
+            .len()
+            .min(self.config.max_chunks.saturating_mul(RANK_CANDIDATE_FACTOR));
+        let mut inputs = Vec::with_capacity(candidates + 1);
+        inputs.push(embedding_input(query));
+        inputs.extend(chunks[..candidates].iter().map(|c| embedding_input(c)));
+
+        let checkpoint = Checkpoint::current();
+        let mut vectors = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(EMBED_BATCH_SIZE) {
+            if checkpoint.is_cancelled() {
+                return None;
+            }
+            match provider.embed(model, batch).await {
+                Ok(batch_vectors) if batch_vectors.len() == batch.len() => {
+                    vectors.extend(batch_vectors)
+                }
+                Ok(_) => {
+                    tracing::warn!(model, "Embedding count mismatch, chunks not ranked");
+                    return None;
+                }
+                Err(e) => {
+                    tracing::warn!(model, error = %e, "Embedding failed, chunks not ranked");
+                    return None;
+                }
+            }
         }
 
-        chunks
+        let (query_vector, chunk_vectors) = vectors.split_first()?;
+        let mut scored: Vec<(usize, f32)> = chunk_vectors
+            .iter()
+            .map(|v| cosine_similarity(query_vector, v))
+            .enumerate()
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut kept: Vec<usize> = scored
+            .iter()
+            .enumerate()
+            .filter(|(rank, (_, score))| *rank == 0 || *score >= self.config.min_similarity)
+            .map(|(_, (index, _))| *index)
+            .take(self.config.max_chunks.max(1))
+            .collect();
+        kept.sort_unstable();
+        tracing::debug!(
+            scored = candidates,
+            kept = kept.len(),
+            "Ranked chunks by similarity to the query"
+        );
+        Some(kept)
     }
 
     /// Summarize a single chunk using the LLM.
@@ -265,6 +495,14 @@ impl ChunkProcessor {
 
     /// Process large content by chunking and summarizing.
     ///
+    /// When relevance filtering is enabled, lines matching error signals or
+    /// terms from `original_query` are extracted first; if that extract fits
+    /// under the threshold it is returned verbatim and no LLM call is made.
+    /// Otherwise the extract (or the full content, if nothing matched) is
+    /// chunked and summarized. With an `embedding_model` set, chunks are
+    /// first ranked by similarity to `original_query` and only the most
+    /// relevant are summarized.
+    ///
     /// Returns the original content if:
     /// - Content is below threshold
     /// - Content appears to be binary
@@ -275,91 +513,165 @@ impl ChunkProcessor {
         content: &str,
         original_query: Option<&str>,
     ) -> Result<String, Error> {
+        self.process(content, original_query).await.map(|p| p.text)
+    }
+
+    /// [`Self::process_large_content`], also reporting whether every chunk
+    /// was looked at.
+    pub async fn process(
+        &self,
+        content: &str,
+        original_query: Option<&str>,
+    ) -> Result<Processed, Error> {
         // Skip if disabled or below threshold
         if !self.should_chunk(content) {
-            return Ok(content.to_string());
+            return Ok(Processed::whole(content.to_string()));
         }
 
         // Skip error outputs
         if content.starts_with("Error:") || content.starts_with("Error ") {
-            return Ok(content.to_string());
+            return Ok(Processed::whole(content.to_string()));
         }
 
         // Skip binary content
         if Self::is_binary_content(content) {
-            return Ok(format!(
+            return Ok(Processed::whole(format!(
                 "[Binary content detected, {} bytes]\n\n{}",
                 content.len(),
                 &content[..content.len().min(500)]
-            ));
+            )));
+        }
+
+        // Query-aware extraction; only summarize what doesn't fit
+        let filtered = if self.config.relevance_filter {
+            filter_relevant(content, original_query)
+        } else {
+            None
+        };
+        if let Some(filtered) = &filtered {
+            if filtered.text.len() <= self.config.threshold_bytes {
+                let mut result = format!(
+                    "[Large output filtered by relevance: {} bytes, kept {} of {} lines]\n\n{}",
+                    content.len(),
+                    filtered.kept_lines,
+                    filtered.total_lines,
+                    filtered.text
+                );
+                if let Some(line) = continuation_line(content) {
+                    if !filtered.text.contains(line) {
+                        result.push_str(line);
+                        result.push('\n');
+                    }
+                }
+                return Ok(Processed::whole(result));
+            }
         }
+        let source = filtered.as_ref().map_or(content, |f| f.text.as_str());
 
         // Split into chunks
-        let chunks = self.chunk_content(content);
-        let total_chunks = chunks.len();
-        let was_truncated = content.len() > self.config.chunk_size_bytes * self.config.max_chunks;
+        let mut chunks = self.split_chunks(source);
 
         if chunks.is_empty() {
-            return Ok(content.to_string());
+            return Ok(Processed::whole(content.to_string()));
         }
 
         // If only one chunk (content was just above threshold but chunked to one), return it
         if chunks.len() == 1 {
-            return Ok(chunks.into_iter().next().unwrap());
+            return Ok(Processed::whole(chunks.into_iter().next().unwrap()));
         }
 
+        // Rank by similarity to the query, or keep the first max_chunks.
+        // `limit` is set when chunks past it were never looked at.
+        let split_count = chunks.len();
+        let (selected, total_chunks, mut result, limit): (Vec<(usize, String)>, _, _, _) =
+            match self.rank_chunks(&chunks, original_query).await {
+                Some(kept) => {
+                    let scored = split_count
+                        .min(self.config.max_chunks.saturating_mul(RANK_CANDIDATE_FACTOR));
+                    let header = format!(
+                        "[Large output processed: {} bytes split into {} chunks, {} most relevant to the query summarized, {} dropped]\n\n",
+                        content.len(),
+                        split_count,
+                        kept.len(),
+                        scored - kept.len()
+                    );
+                    let selected = kept
+                        .into_iter()
+                        .map(|i| (i, std::mem::take(&mut chunks[i])))
+                        .collect();
+                    let limit = (scored < split_count).then_some(scored);
+                    (selected, split_count, header, limit)
+                }
+                None => {
+                    chunks.truncate(self.config.max_chunks);
+                    let header = format!(
+                        "[Large output processed: {} bytes split into {} chunks]\n\n",
+                        content.len(),
+                        chunks.len()
+                    );
+                    let total = chunks.len();
+                    let was_truncated =
+                        source.len() > self.config.chunk_size_bytes * self.config.max_chunks;
+                    let limit = was_truncated.then_some(self.config.max_chunks);
+                    (chunks.into_iter().enumerate().collect(), total, header, limit)
+                }
+            };
+
         // Summarize chunks
         let summaries = if self.config.parallel {
-            self.summarize_chunks_parallel(&chunks, original_query).await?
+            self.summarize_chunks_parallel(&selected, total_chunks, original_query)
+                .await?
         } else {
-            self.summarize_chunks_sequential(&chunks, original_query).await?
+            self.summarize_chunks_sequential(&selected, total_chunks, original_query)
+                .await?
         };
 
         // Combine summaries
-        let mut result = format!(
-            "[Large output processed: {} bytes split into {} chunks]\n\n",
-            content.len(),
-            total_chunks
-        );
-
-        for (i, summary) in summaries.iter().enumerate() {
+        for ((i, _), summary) in selected.iter().zip(&summaries) {
             result.push_str(&format!("### Chunk {} of {}\n", i + 1, total_chunks));
             result.push_str(summary);
             result.push_str("\n\n");
         }
 
-        if was_truncated {
+        if let Some(limit) = limit {
             result.push_str(&format!(
                 "[Note: Output was truncated. Only first {} chunks processed.]\n",
-                self.config.max_chunks
+                limit
             ));
         }
 
-        Ok(result)
+        if let Some(line) = continuation_line(content) {
+            result.push_str(line);
+            result.push('\n');
+        }
+
+        Ok(Processed {
+            text: result,
+            complete: limit.is_none(),
+        })
     }
 
     /// Summarize chunks in parallel.
     async fn summarize_chunks_parallel(
         &self,
-        chunks: &[String],
+        chunks: &[(usize, String)],
+        total: usize,
         original_query: Option<&str>,
     ) -> Result<Vec<String>, Error> {
         use futures::future::join_all;
 
-        let total = chunks.len();
         let futures: Vec<_> = chunks
             .iter()
-            .enumerate()
-            .map(|(i, chunk)| self.summarize_chunk(chunk, i, total, original_query))
+            .map(|(i, chunk)| self.summarize_chunk(chunk, *i, total, original_query))
             .collect();
 
-        let results = join_all(futures).await;
+        let results = Checkpoint::current().run(join_all(futures)).await?;
 
         // Collect results, replacing errors with error messages
         let summaries: Vec<String> = results
             .into_iter()
-            .enumerate()
-            .map(|(i, r)| {
+            .zip(chunks)
+            .map(|(r, (i, _))| {
                 r.unwrap_or_else(|e| format!("[Error summarizing chunk {}: {}]", i + 1, e))
             })
             .collect();
@@ -370,13 +682,15 @@ impl ChunkProcessor {
     /// Summarize chunks sequentially.
     async fn summarize_chunks_sequential(
         &self,
-        chunks: &[String],
+        chunks: &[(usize, String)],
+        total: usize,
         original_query: Option<&str>,
     ) -> Result<Vec<String>, Error> {
-        let total = chunks.len();
-        let mut summaries = Vec::with_capacity(total);
+        let checkpoint = Checkpoint::current();
+        let mut summaries = Vec::with_capacity(chunks.len());
 
-        for (i, chunk) in chunks.iter().enumerate() {
+        for &(i, ref chunk) in chunks {
+            checkpoint.check()?;
             let summary = self
                 .summarize_chunk(chunk, i, total, original_query)
                 .await
@@ -405,6 +719,9 @@ mod tests {
         assert_eq!(config.chunk_size_bytes, 10_000);
         assert_eq!(config.max_chunks, 20);
         assert!(config.parallel);
+        assert!(config.relevance_filter);
+        assert_eq!(config.embedding_model, None);
+        assert_eq!(config.min_similarity, 0.2);
     }
 
     #[test]
@@ -414,13 +731,22 @@ mod tests {
             .with_threshold(100_000)
             .with_chunk_size(20_000)
             .with_max_chunks(10)
-            .with_parallel(false);
+            .with_parallel(false)
+            .with_relevance_filter(false)
+            .with_embedding_model("text-embedding-3-small")
+            .with_min_similarity(0.5);
 
         assert!(!config.enabled);
         assert_eq!(config.threshold_bytes, 100_000);
         assert_eq!(config.chunk_size_bytes, 20_000);
         assert_eq!(config.max_chunks, 10);
         assert!(!config.parallel);
+        assert!(!config.relevance_filter);
+        assert_eq!(
+            config.embedding_model.as_deref(),
+            Some("text-embedding-3-small")
+        );
+        assert_eq!(config.min_similarity, 0.5);
     }
 
     #[test]
@@ -447,6 +773,56 @@ mod tests {
         assert_eq!(paragraphs.len(), 3);
     }
 
+    #[tokio::test]
+    async fn test_summary_keeps_continuation_line() {
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        for _ in 0..10 {
+            provider.queue_response("summary");
+        }
+        let config = ChunkerConfig::new()
+            .with_threshold(100)
+            .with_chunk_size(60)
+            .with_parallel(false);
+        let processor = ChunkProcessor::new(provider, config);
+
+        let content = format!(
+            "{}\n---\nExit code: 0 (success)\n{}\"qq-spill-1:81\"",
+            "line of output\n\n".repeat(20),
+            CONTINUATION_MARKER
+        );
+        let result = processor.process_large_content(&content, None).await.unwrap();
+
+        assert!(result.starts_with("[Large output processed"));
+        assert!(result.trim_end().ends_with("Continue with continue_from=\"qq-spill-1:81\""));
+        assert_eq!(continuation_line("no marker here"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_stops_summarizing() {
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        for _ in 0..10 {
+            provider.queue_response("summary");
+        }
+        let content = "line of output\n\n".repeat(20);
+        let token = tokio_util::sync::CancellationToken::new();
+        token.cancel();
+
+        for parallel in [false, true] {
+            let config = ChunkerConfig::new()
+                .with_threshold(100)
+                .with_chunk_size(60)
+                .with_parallel(parallel);
+            let processor = ChunkProcessor::new(provider.clone(), config);
+            let result = crate::cancel::with_cancellation(
+                Some(token.clone()),
+                processor.process_large_content(&content, None),
+            )
+            .await;
+            assert!(matches!(result, Err(Error::Cancelled)));
+        }
+        assert_eq!(provider.request_count(), 0);
+    }
+
     #[test]
     fn test_is_binary_content() {
         // Text content
@@ -457,4 +833,105 @@ mod tests {
         let binary = (0u8..255).map(|b| b as char).collect::<String>();
         assert!(ChunkProcessor::is_binary_content(&binary));
     }
+
+    #[tokio::test]
+    async fn test_relevance_filter_skips_summarization() {
+        // No queued responses: any LLM call would fail the test
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        let config = ChunkerConfig::new().with_threshold(2_000).with_chunk_size(500);
+        let processor = ChunkProcessor::new(provider, config);
+
+        let mut content = String::new();
+        for i in 0..200 {
+            content.push_str(&format!("   Compiling crate{} v1.0.0\n", i));
+        }
+        content.push_str("error[E0425]: cannot find value `x` in this scope\n");
+        content.push_str(&"   Compiling tail v1.0.0\n".repeat(50));
+        content.push_str(&format!("{}\"qq-spill-2:300\"", CONTINUATION_MARKER));
+
+        let result = processor
+            .process_large_content(&content, Some("fix the compile error"))
+            .await
+            .unwrap();
+
+        assert!(result.starts_with("[Large output filtered by relevance"));
+        assert!(result.contains("error[E0425]: cannot find value `x` in this scope"));
+        assert_eq!(result.matches(CONTINUATION_MARKER).count(), 1);
+        assert!(result.len() < 2_000);
+    }
+
+    #[tokio::test]
+    async fn test_embedding_ranking_drops_irrelevant_chunks() {
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        for _ in 0..10 {
+            provider.queue_response("summary");
+        }
+        let config = ChunkerConfig::new()
+            .with_threshold(100)
+            .with_chunk_size(60)
+            .with_parallel(false)
+            .with_relevance_filter(false)
+            .with_embedding_model("mock-embed")
+            .with_min_similarity(0.35);
+        let processor = ChunkProcessor::new(provider.clone(), config);
+
+        let content = [
+            "button color padding margin styles",
+            "database connection timeout after 30s",
+            "font sizes for headings and lists",
+            "retrying database connection, timeout again",
+        ]
+        .join("\n\n");
+        let content = format!("{}\n\n{}", content, "x".repeat(40));
+        let result = processor
+            .process_large_content(&content, Some("why does the database connection timeout"))
+            .await
+            .unwrap();
+
+        assert!(result.contains("2 most relevant to the query summarized, 3 dropped"));
+        assert!(result.contains("### Chunk 2 of 5"));
+        assert!(result.contains("### Chunk 4 of 5"));
+        assert!(!result.contains("### Chunk 1 of 5"));
+        let prompts: Vec<String> = provider
+            .captured_requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|r| r.messages[0].content.to_string_lossy())
+            .collect();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[0].contains("chunk 2 of 5"));
+        assert!(prompts.iter().all(|p| p.contains("database")));
+
+        // Without a query there is nothing to rank against
+        let result = processor.process_large_content(&content, None).await.unwrap();
+        assert!(result.starts_with("[Large output processed: "));
+        assert!(!result.contains("dropped"));
+        assert!(result.contains("### Chunk 1 of 5"));
+    }
+
+    #[test]
+    fn test_cosine_similarity_and_embedding_input() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+
+        let long = "é".repeat(MAX_EMBED_INPUT_BYTES);
+        let input = embedding_input(&long);
+        assert!(input.len() <= MAX_EMBED_INPUT_BYTES);
+        assert!(input.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn test_split_chunks_without_whitespace() {
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        let processor = ChunkProcessor::new(provider, ChunkerConfig::new().with_chunk_size(100));
+
+        // CJK prose has no spaces to split at
+        let content = "これは長い文章です。".repeat(40);
+        let chunks = processor.split_chunks(&content);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.len() <= 100));
+        assert_eq!(chunks.concat(), content);
+    }
 }
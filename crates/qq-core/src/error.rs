@@ -14,6 +14,9 @@ pub enum Error {
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
+    #[error("Context window exceeded: {0}")]
+    ContextOverflow(String),
+
     #[error("Network error: {0}")]
     Network(String),
 
@@ -65,6 +68,10 @@ impl Error {
         Self::InvalidRequest(message.into())
     }
 
+    pub fn context_overflow(message: impl Into<String>) -> Self {
+        Self::ContextOverflow(message.into())
+    }
+
     pub fn network(message: impl Into<String>) -> Self {
         Self::Network(message.into())
     }
@@ -98,6 +105,12 @@ impl Error {
     pub fn is_auth_error(&self) -> bool {
         matches!(self, Error::Auth(_))
     }
+
+    /// The request didn't fit the model's context window. Not retryable as-is;
+    /// the caller has to shrink the context first.
+    pub fn is_context_overflow(&self) -> bool {
+        matches!(self, Error::ContextOverflow(_))
+    }
 }
 
 impl From<serde_json::Error> for Error {
@@ -123,5 +136,7 @@ mod tests {
         assert!(Error::rate_limit("too many requests").is_retryable());
         assert!(Error::stream("transport error").is_retryable());
         assert!(!Error::auth("invalid key").is_retryable());
+        assert!(!Error::context_overflow("prompt is too long").is_retryable());
+        assert!(Error::context_overflow("prompt is too long").is_context_overflow());
     }
 }
@@ -0,0 +1,284 @@
+//! Typed structured extraction.
+//!
+//! [`extract`] asks the model for a value of type `T`: the JSON schema is
+//! generated from `T` with schemars, described in the system prompt, and —
+//! for OpenAI-compatible providers — also sent as a native `response_format`.
+//! Replies are parsed with serde; when parsing fails the error is fed back to
+//! the model and the request is retried.
+//!
+//! ```ignore
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct Verdict { approved: bool, reasons: Vec<String> }
+//!
+//! let verdict: Verdict = extract(provider.as_ref(), messages, &ExtractOptions::default()).await?;
+//! ```
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+use crate::capabilities::is_rejection;
+use crate::error::Error;
+use crate::message::{strip_thinking_tags, Message, Role};
+use crate::provider::{CompletionRequest, Provider};
+
+/// Options for [`extract`].
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Extra attempts after a reply that fails to parse.
+    pub max_retries: u32,
+    /// Send the schema as a native `response_format` when the provider
+    /// supports it. Falls back to prompt-only if the server rejects it.
+    pub native: bool,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            max_retries: 2,
+            native: true,
+        }
+    }
+}
+
+impl ExtractOptions {
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_native(mut self, native: bool) -> Self {
+        self.native = native;
+        self
+    }
+}
+
+/// JSON schema for `T`, as sent to the model.
+pub fn schema_for<T: JsonSchema>() -> serde_json::Value {
+    schemars::schema_for!(T).to_value()
+}
+
+/// Ask the model for a `T`, retrying with the parse error on invalid replies.
+pub async fn extract<T>(
+    provider: &dyn Provider,
+    messages: Vec<Message>,
+    options: &ExtractOptions,
+) -> Result<T, Error>
+where
+    T: DeserializeOwned + JsonSchema,
+{
+    let schema = schema_for::<T>();
+    let mut messages = with_schema_instructions(messages, &schema);
+    let mut native = options.native && provider.name() == "openai";
+    let mut attempt = 0;
+
+    loop {
+        let mut request = CompletionRequest::new(messages.clone()).with_stream(false);
+        if let Some(ref model) = options.model {
+            request = request.with_model(model);
+        }
+        if let Some(temperature) = options.temperature {
+            request = request.with_temperature(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            request = request.with_max_tokens(max_tokens);
+        }
+        if native {
+            request
+                .extra
+                .insert("response_format".to_string(), response_format(&T::schema_name(), &schema));
+        }
+
+        let response = match provider.complete(request).await {
+            Ok(r) => r,
+            Err(e) if native && is_rejection(&e) => {
+                tracing::debug!(error = %e, "Native structured output rejected, using prompt only");
+                native = false;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let content = response.message.content.to_string_lossy();
+        let (content, _) = strip_thinking_tags(&content);
+        let err = match parse_reply::<T>(&content) {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        if attempt >= options.max_retries {
+            return Err(Error::serialization(format!(
+                "Structured extraction failed after {} attempts: {}",
+                attempt + 1,
+                err
+            )));
+        }
+        attempt += 1;
+        tracing::debug!(attempt, error = %err, "Extraction reply did not match schema, retrying");
+
+        messages.push(Message::assistant(content.as_str()));
+        messages.push(Message::user(format!(
+            "That reply could not be parsed: {}\n\
+             Reply again with only the JSON value matching the schema.",
+            err
+        )));
+    }
+}
+
+/// Append the schema instructions to the system message (or add one).
+fn with_schema_instructions(mut messages: Vec<Message>, schema: &serde_json::Value) -> Vec<Message> {
+    let instructions = format!(
+        "Respond with only a JSON value matching this JSON Schema. \
+         Do not include any other text.\n\n```json\n{}\n```",
+        serde_json::to_string_pretty(schema).unwrap_or_default()
+    );
+    match messages.first_mut() {
+        Some(first) if first.role == Role::System => {
+            let system = first.content.to_string_lossy();
+            *first = Message::system(format!("{}\n\n{}", system, instructions));
+        }
+        _ => messages.insert(0, Message::system(instructions)),
+    }
+    messages
+}
+
+/// OpenAI `response_format` for a JSON schema.
+fn response_format(name: &str, schema: &serde_json::Value) -> serde_json::Value {
+    // Schema names are limited to [a-zA-Z0-9_-]
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    serde_json::json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": name,
+            "schema": schema,
+            "strict": false,
+        }
+    })
+}
+
+/// Parse a reply, tolerating code fences and prose around the JSON.
+fn parse_reply<T: DeserializeOwned>(content: &str) -> Result<T, String> {
+    let trimmed = content.trim();
+    let first_err = match serde_json::from_str(trimmed) {
+        Ok(value) => return Ok(value),
+        Err(e) => e.to_string(),
+    };
+
+    let Some(candidate) = json_span(trimmed) else {
+        return Err(first_err);
+    };
+    serde_json::from_str(candidate).map_err(|e| e.to_string())
+}
+
+/// The span from the first `{`/`[` to the last matching closer.
+fn json_span(text: &str) -> Option<&str> {
+    let start = text.find(['{', '['])?;
+    let closer = if text[start..].starts_with('{') { '}' } else { ']' };
+    let end = text.rfind(closer)?;
+    (end > start).then(|| &text[start..=end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Verdict {
+        approved: bool,
+        reasons: Vec<String>,
+    }
+
+    #[tokio::test]
+    async fn test_extract_parses_fenced_reply() {
+        let provider = MockProvider::new();
+        provider.queue_response("Sure:\n```json\n{\"approved\": true, \"reasons\": [\"ok\"]}\n```");
+
+        let v: Verdict = extract(&provider, vec![Message::user("review")], &ExtractOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(v, Verdict { approved: true, reasons: vec!["ok".into()] });
+
+        let request = provider.last_request().unwrap();
+        assert_eq!(request.messages[0].role, Role::System);
+        assert!(request.messages[0].content.to_string_lossy().contains("\"approved\""));
+        // Not an OpenAI provider: no native response_format
+        assert!(request.extra.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_extract_retries_with_parse_error() {
+        let provider = MockProvider::new();
+        provider.queue_response("{\"approved\": \"yes\"}");
+        provider.queue_response("{\"approved\": false, \"reasons\": []}");
+
+        let v: Verdict = extract(&provider, vec![Message::user("review")], &ExtractOptions::default())
+            .await
+            .unwrap();
+        assert!(!v.approved);
+        assert_eq!(provider.request_count(), 2);
+
+        let retry = provider.last_request().unwrap();
+        let feedback = retry.messages.last().unwrap().content.to_string_lossy();
+        assert!(feedback.contains("could not be parsed"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_gives_up_after_retries() {
+        let provider = MockProvider::new();
+        provider.queue_response("nope");
+        provider.queue_response("still nope");
+
+        let err = extract::<Verdict>(
+            &provider,
+            vec![Message::user("review")],
+            &ExtractOptions::default().with_max_retries(1),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::Serialization(_)));
+        assert_eq!(provider.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_extract_sends_native_schema_to_openai() {
+        let mut provider = MockProvider::new();
+        provider.name = "openai".to_string();
+        provider.queue_response("{\"approved\": true, \"reasons\": []}");
+
+        let messages = vec![Message::system("You review code."), Message::user("review")];
+        let _: Verdict = extract(&provider, messages, &ExtractOptions::default()).await.unwrap();
+
+        let request = provider.last_request().unwrap();
+        let format = &request.extra["response_format"];
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["name"], "Verdict");
+        // Instructions are merged into the existing system message
+        assert_eq!(request.messages.len(), 2);
+        assert!(request.messages[0].content.to_string_lossy().starts_with("You review code."));
+    }
+}
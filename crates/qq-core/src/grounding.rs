@@ -0,0 +1,245 @@
+//! Re-grounding an agent on its original goal.
+//!
+//! After a few compaction cycles the request that started a run survives
+//! only as paraphrase in the observation log, and after a long stretch of
+//! tool work an agent can lose track of what it was asked to do. A
+//! [`Regrounding`] builds a user message that restates the goal verbatim,
+//! with the current task board and the latest observations, which the agent
+//! loops add when a run resumes after compaction or a long pause.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::message::{Message, Role};
+
+/// Gap between a response and the next request that counts as a long pause.
+pub const DEFAULT_PAUSE_THRESHOLD: Duration = Duration::from_secs(300);
+
+/// Most observation log lines quoted in a re-grounding message.
+const RECENT_OBSERVATIONS: usize = 8;
+
+/// Renders the current task board, or `None` when there are no tasks.
+pub type TaskBoardFn = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// Why a re-grounding message was added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegroundTrigger {
+    /// Earlier turns were compacted and the goal is no longer among them.
+    Compaction,
+    /// This long passed between the last response and the next request.
+    Pause(Duration),
+}
+
+impl fmt::Display for RegroundTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compaction => write!(f, "compaction"),
+            Self::Pause(gap) if gap.as_secs() >= 60 => {
+                write!(f, "a {}m pause", gap.as_secs() / 60)
+            }
+            Self::Pause(gap) => write!(f, "a {}s pause", gap.as_secs()),
+        }
+    }
+}
+
+/// Settings for re-grounding messages.
+#[derive(Clone)]
+pub struct Regrounding {
+    goal: Option<String>,
+    task_board: Option<TaskBoardFn>,
+    pause_threshold: Duration,
+}
+
+impl Default for Regrounding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Regrounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Regrounding")
+            .field("goal", &self.goal)
+            .field("has_task_board", &self.task_board.is_some())
+            .field("pause_threshold", &self.pause_threshold)
+            .finish()
+    }
+}
+
+impl Regrounding {
+    pub fn new() -> Self {
+        Self {
+            goal: None,
+            task_board: None,
+            pause_threshold: DEFAULT_PAUSE_THRESHOLD,
+        }
+    }
+
+    /// Set the goal to restate. Without one, the loop uses the request that
+    /// started the run.
+    pub fn with_goal(mut self, goal: impl Into<String>) -> Self {
+        self.goal = Some(goal.into());
+        self
+    }
+
+    /// Include the task board in each message.
+    pub fn with_task_board(mut self, board: TaskBoardFn) -> Self {
+        self.task_board = Some(board);
+        self
+    }
+
+    /// Set the gap that counts as a long pause.
+    pub fn with_pause_threshold(mut self, threshold: Duration) -> Self {
+        self.pause_threshold = threshold;
+        self
+    }
+
+    pub fn goal(&self) -> Option<&str> {
+        self.goal.as_deref()
+    }
+
+    pub fn pause_threshold(&self) -> Duration {
+        self.pause_threshold
+    }
+
+    /// Whether to re-ground before the next request.
+    ///
+    /// `compacted` says whether the history was compacted since the last
+    /// check and `gap` is the time since the last response (`None` before
+    /// the first). After compaction
+    /// this only fires once `goal` is no longer in any user message of
+    /// `messages`; an earlier re-grounding message counts, so one is added
+    /// per compaction that drops the goal rather than per compaction.
+    pub fn trigger(
+        &self,
+        goal: &str,
+        compacted: bool,
+        gap: Option<Duration>,
+        messages: &[Message],
+    ) -> Option<RegroundTrigger> {
+        let goal = goal.trim();
+        if goal.is_empty() {
+            return None;
+        }
+        if let Some(gap) = gap.filter(|gap| *gap >= self.pause_threshold) {
+            return Some(RegroundTrigger::Pause(gap));
+        }
+        let goal_visible = messages
+            .iter()
+            .any(|m| m.role == Role::User && m.content.to_string_lossy().contains(goal));
+        (compacted && !goal_visible).then_some(RegroundTrigger::Compaction)
+    }
+
+    /// The re-grounding message: `goal` verbatim, the task board, and the
+    /// last lines of `observation_log`.
+    pub fn message(&self, goal: &str, trigger: RegroundTrigger, observation_log: &str) -> Message {
+        let mut text = format!(
+            "[Re-grounding after {}]\n\
+             This is the original request, verbatim:\n\n\
+             <original-request>\n{}\n</original-request>",
+            trigger,
+            goal.trim()
+        );
+        if let Some(board) = self.task_board.as_ref().and_then(|board| board()) {
+            text.push_str("\n\n");
+            text.push_str(board.trim());
+        }
+        let recent = recent_observations(observation_log, RECENT_OBSERVATIONS);
+        if !recent.is_empty() {
+            text.push_str("\n\n## Latest Observations\n");
+            for line in recent {
+                text.push('\n');
+                text.push_str(line);
+            }
+        }
+        text.push_str(
+            "\n\nCheck your progress against the original request and continue with what it \
+             still needs. Drop any line of work that does not serve it.",
+        );
+        Message::user(text.as_str())
+    }
+}
+
+/// The last `n` non-empty lines of an observation log, oldest first.
+pub fn recent_observations(log: &str, n: usize) -> Vec<&str> {
+    let mut lines: Vec<&str> = log
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .rev()
+        .take(n)
+        .collect();
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger() {
+        let regrounding = Regrounding::new().with_pause_threshold(Duration::from_secs(60));
+        let goal = "Fix the flaky upload test";
+        let short = Some(Duration::from_secs(5));
+        let kept = vec![Message::user(goal), Message::assistant("on it")];
+        let dropped = vec![Message::assistant("still going")];
+
+        assert_eq!(regrounding.trigger(goal, false, short, &dropped), None);
+        assert_eq!(regrounding.trigger(goal, true, short, &kept), None);
+        assert_eq!(
+            regrounding.trigger(goal, true, short, &dropped),
+            Some(RegroundTrigger::Compaction)
+        );
+        assert_eq!(
+            regrounding.trigger(goal, false, Some(Duration::from_secs(90)), &kept),
+            Some(RegroundTrigger::Pause(Duration::from_secs(90)))
+        );
+        assert_eq!(regrounding.trigger(goal, false, None, &kept), None);
+        assert_eq!(regrounding.trigger("  ", true, short, &dropped), None);
+
+        // An earlier re-grounding message keeps the goal visible
+        let message = regrounding.message(goal, RegroundTrigger::Compaction, "");
+        assert_eq!(regrounding.trigger(goal, true, short, &[message]), None);
+    }
+
+    #[test]
+    fn test_message() {
+        let regrounding = Regrounding::new().with_task_board(Arc::new(|| {
+            Some("## Current Task Board\n\n- [done] #1: Reproduce".to_string())
+        }));
+        let log = "- saw A\n\n- saw B\n- saw C\n";
+        let message = regrounding.message(
+            "Fix the flaky upload test",
+            RegroundTrigger::Pause(Duration::from_secs(420)),
+            log,
+        );
+        assert_eq!(message.role, Role::User);
+        assert_eq!(
+            message.content.to_string_lossy(),
+            "[Re-grounding after a 7m pause]\n\
+             This is the original request, verbatim:\n\n\
+             <original-request>\nFix the flaky upload test\n</original-request>\n\n\
+             ## Current Task Board\n\n- [done] #1: Reproduce\n\n\
+             ## Latest Observations\n\n- saw A\n- saw B\n- saw C\n\n\
+             Check your progress against the original request and continue with what it \
+             still needs. Drop any line of work that does not serve it."
+        );
+
+        let bare = Regrounding::new().message("Goal", RegroundTrigger::Compaction, "");
+        assert!(bare
+            .content
+            .to_string_lossy()
+            .starts_with("[Re-grounding after compaction]"));
+        assert!(!bare.content.to_string_lossy().contains("Observations"));
+    }
+
+    #[test]
+    fn test_recent_observations() {
+        let log = "one\ntwo\n\nthree\nfour\n";
+        assert_eq!(recent_observations(log, 2), vec!["three", "four"]);
+        assert_eq!(recent_observations(log, 10).len(), 4);
+        assert!(recent_observations("", 3).is_empty());
+    }
+}
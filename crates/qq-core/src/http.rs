@@ -0,0 +1,57 @@
+//! Shared starting point for HTTP clients.
+//!
+//! Providers, web tools and the CLI's own clients start from
+//! [`client_builder`], so network settings installed once at startup (a
+//! proxy, extra CA certificates) reach every request. Until a factory is
+//! installed it's a plain `reqwest::Client::builder()`.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Produces the builder every new HTTP client starts from.
+pub type ClientFactory = Box<dyn Fn() -> reqwest::ClientBuilder + Send + Sync>;
+
+/// Connect timeout for model provider APIs.
+pub const PROVIDER_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+static FACTORY: OnceLock<ClientFactory> = OnceLock::new();
+static OPTIONS: OnceLock<NetworkOptions> = OnceLock::new();
+
+/// The network settings as plain values, for clients built on a reqwest
+/// other than the one [`client_builder`] returns (rmcp's HTTP transport).
+#[derive(Debug, Clone, Default)]
+pub struct NetworkOptions {
+    /// Proxy URL for all requests
+    pub proxy: Option<String>,
+    /// Proxy basic auth as (username, password)
+    pub proxy_auth: Option<(String, String)>,
+    /// Hosts reached directly, comma-separated as in NO_PROXY
+    pub no_proxy: Option<String>,
+    /// PEM bundles of extra CA certificates
+    pub ca_bundles: Vec<Vec<u8>>,
+}
+
+/// Install the factory later [`client_builder`] calls use. Only the first
+/// call takes effect; returns whether this one did.
+pub fn set_client_factory(factory: ClientFactory) -> bool {
+    FACTORY.set(factory).is_ok()
+}
+
+/// Record the settings [`network_options`] returns, alongside the factory.
+/// Only the first call takes effect; returns whether this one did.
+pub fn set_network_options(options: NetworkOptions) -> bool {
+    OPTIONS.set(options).is_ok()
+}
+
+/// The installed network settings; empty until some are set.
+pub fn network_options() -> NetworkOptions {
+    OPTIONS.get().cloned().unwrap_or_default()
+}
+
+/// A client builder carrying the installed network settings.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    match FACTORY.get() {
+        Some(factory) => factory(),
+        None => reqwest::Client::builder(),
+    }
+}
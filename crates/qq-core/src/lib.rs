@@ -4,14 +4,29 @@
 //! the quick-query LLM CLI tool.
 
 pub mod agent;
+pub mod attachment;
+pub mod balance;
 pub mod blocking;
+pub mod cancel;
+pub mod capabilities;
 pub mod chunker;
 pub mod error;
+pub mod extract;
+pub mod grounding;
+pub mod http;
 pub mod message;
+pub mod narrowing;
 pub mod observation;
+pub mod overflow;
 pub mod provider;
+pub mod relevance;
+pub mod runner;
+pub mod sliding_window;
+pub mod state_delta;
 pub mod task;
+pub mod text;
 pub mod tool;
+pub mod tool_budget;
 
 #[cfg(any(test, feature = "testing"))]
 pub mod testing;
@@ -19,20 +34,35 @@ pub mod testing;
 pub use agent::{
     Agent, AgentChannel, AgentConfig, AgentId, AgentInstanceMetadata, AgentInstanceState,
     AgentMemory, AgentMessage, AgentProgressEvent, AgentProgressHandler, AgentRegistry,
-    AgentRunResult, AgentSender, DelegationPermissions, DEFAULT_MAX_INSTANCE_BYTES,
+    AgentRunResult, AgentSender, DelegationPermissions, EvictionPolicy, DEFAULT_DEDUP_WINDOW,
+    DEFAULT_MAX_INSTANCE_BYTES,
 };
+pub use attachment::{attach_file, detect_media_type, MAX_ATTACHMENT_SIZE};
+pub use balance::{BalancePolicy, BalancedProvider, Endpoint};
 pub use error::Error;
-pub use message::{Content, ContentPart, FinishReason, ImageData, IntoContent, Message, Role, StreamChunk, ToolCall, ToolResult, TypedContent, Usage, strip_thinking_tags, strip_reasoning_from_history};
+pub use extract::{extract, ExtractOptions};
+pub use grounding::{Regrounding, RegroundTrigger, TaskBoardFn, DEFAULT_PAUSE_THRESHOLD};
+pub use message::{Content, ContentPart, FileRef, FinishReason, ImageData, IntoContent, Message, MessageMetadata, Role, StreamChunk, ToolCall, ToolResult, TypedContent, Usage, drain_unpinned, set_pinned_range, strip_thinking_tags, strip_reasoning_from_history, usage_by_agent};
+pub use state_delta::{CommandRecord, StateDelta};
 pub use provider::{
-    CompletionRequest, CompletionResponse, Provider, StreamResult,
+    CompletionRequest, CompletionResponse, Provider, SamplingOverrides, StreamResult,
 };
 pub use task::{
     complete_parallel, execute_tools_parallel, execute_tools_parallel_with_chunker,
-    TaskHandle, TaskId, TaskInfo, TaskManager, TaskState, ToolExecutionResult,
+    write_conflicts, TaskHandle, TaskId, TaskInfo, TaskManager, TaskState, ToolExecutionResult,
 };
 pub use tool::{PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters, ToolPattern, ToolRef, ToolRegistry, execute_tool_dispatch};
-pub use chunker::{ChunkProcessor, ChunkerConfig};
+pub use chunker::{
+    continuation_line, ChunkProcessor, ChunkerConfig, Embedder, Processed, CONTINUATION_MARKER,
+};
 pub use blocking::run_blocking;
-pub use observation::{ContextCompactor, ObservationalMemory, ObservationConfig, find_safe_split_point, strip_images_from_messages};
+pub use cancel::{with_cancellation, CancellationToken, Checkpoint};
+pub use capabilities::{AdaptiveProvider, CapabilityCache, ProviderCapabilities};
+pub use observation::{ContextCompactor, ObservationPass, ObservationalMemory, ObservationConfig, find_safe_split_point, strip_images_from_messages};
+pub use narrowing::ScopeRetry;
+pub use tool_budget::{definition_tokens, fit_descriptions, DescribeToolTool, DEFAULT_DESCRIPTION_SHARE, DESCRIBE_TOOL};
+pub use sliding_window::{SlidingWindowConfig, SlidingWindowMemory};
+pub use overflow::{evict_for_context_overflow, mentions_context_overflow, OverflowEviction};
+pub use runner::{AgentRunner, Conversation, ResponseTiming, Route, RunEvent, RunHandler, RunOutcome, Transcript, DEFAULT_MAX_ITERATIONS};
 
 pub type Result<T> = std::result::Result<T, Error>;
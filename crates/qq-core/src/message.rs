@@ -77,6 +77,49 @@ impl ImageData {
     }
 }
 
+// ---------------------------------------------------------------------------
+// FileRef
+// ---------------------------------------------------------------------------
+
+/// A file uploaded to a provider's file store, sent by reference instead of
+/// inlined.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRef {
+    /// Provider holding the file ("anthropic", "openai", "gemini"). The ID
+    /// means nothing to any other provider.
+    pub provider: String,
+    /// Provider file ID (for Gemini, the file URI).
+    pub id: String,
+    /// Original file name.
+    pub filename: String,
+    /// MIME type the file was uploaded as.
+    pub media_type: String,
+    /// File size in bytes.
+    pub size: u64,
+}
+
+impl FileRef {
+    /// Byte-equivalent for compaction thresholds: text counts in full,
+    /// binary formats such as PDF at a quarter of their size.
+    pub fn estimated_bytes(&self) -> usize {
+        if self.media_type.starts_with("text/") {
+            self.size as usize
+        } else {
+            self.size as usize / 4
+        }
+    }
+
+    /// Text stand-in for providers that cannot read the file.
+    pub fn placeholder(&self) -> String {
+        format!(
+            "[Document: {}, {}, ~{} KB]",
+            self.filename,
+            self.media_type,
+            self.size / 1024
+        )
+    }
+}
+
 // ---------------------------------------------------------------------------
 // TypedContent
 // ---------------------------------------------------------------------------
@@ -90,6 +133,8 @@ pub enum TypedContent {
     Text { text: String },
     /// image/* content (base64-encoded with dimensions)
     Image { image: ImageData },
+    /// A file uploaded to the provider, by reference
+    Document { document: FileRef },
 }
 
 impl TypedContent {
@@ -101,14 +146,20 @@ impl TypedContent {
         TypedContent::Image { image }
     }
 
+    pub fn document(document: FileRef) -> Self {
+        TypedContent::Document { document }
+    }
+
     /// Byte count for budget/threshold calculations.
     ///
     /// For text: string length.
     /// For images: estimated_tokens * 4 (bytes-equivalent for compaction thresholds).
+    /// For documents: see [`FileRef::estimated_bytes`].
     pub fn byte_count(&self) -> usize {
         match self {
             TypedContent::Text { text } => text.len(),
             TypedContent::Image { image } => image.estimated_tokens() as usize * 4,
+            TypedContent::Document { document } => document.estimated_bytes(),
         }
     }
 }
@@ -131,6 +182,12 @@ impl From<ImageData> for TypedContent {
     }
 }
 
+impl From<FileRef> for TypedContent {
+    fn from(document: FileRef) -> Self {
+        TypedContent::document(document)
+    }
+}
+
 // ---------------------------------------------------------------------------
 // IntoContent — ergonomic conversion for Message constructors
 // ---------------------------------------------------------------------------
@@ -232,6 +289,7 @@ impl Content {
                 .map(|p| match p {
                     ContentPart::Text { text } => text.len(),
                     ContentPart::Image { image } => image.estimated_tokens() as usize * 4,
+                    ContentPart::Document { document } => document.estimated_bytes(),
                     ContentPart::ToolUse(tc) => tc.name.len() + tc.arguments.to_string().len(),
                     ContentPart::ToolResult(tr) => tr.byte_count(),
                 })
@@ -261,6 +319,7 @@ impl From<&str> for Content {
 pub enum ContentPart {
     Text { text: String },
     Image { image: ImageData },
+    Document { document: FileRef },
     ToolUse(ToolCall),
     ToolResult(ToolResult),
 }
@@ -270,6 +329,7 @@ impl From<TypedContent> for ContentPart {
         match tc {
             TypedContent::Text { text } => ContentPart::Text { text },
             TypedContent::Image { image } => ContentPart::Image { image },
+            TypedContent::Document { document } => ContentPart::Document { document },
         }
     }
 }
@@ -373,6 +433,10 @@ pub struct Message {
     /// then stripped after the final answer.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub reasoning_content: Option<String>,
+    /// Provenance (agent, model, time, usage). Local bookkeeping only —
+    /// providers never send it to the API.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub metadata: Option<MessageMetadata>,
 }
 
 impl Message {
@@ -384,6 +448,7 @@ impl Message {
             tool_calls: Vec::new(),
             tool_call_id: None,
             reasoning_content: None,
+            metadata: None,
         }
     }
 
@@ -395,6 +460,7 @@ impl Message {
             tool_calls: Vec::new(),
             tool_call_id: None,
             reasoning_content: None,
+            metadata: None,
         }
     }
 
@@ -406,6 +472,7 @@ impl Message {
             tool_calls: Vec::new(),
             tool_call_id: None,
             reasoning_content: None,
+            metadata: None,
         }
     }
 
@@ -417,6 +484,7 @@ impl Message {
             tool_calls,
             tool_call_id: None,
             reasoning_content: None,
+            metadata: None,
         }
     }
 
@@ -428,6 +496,7 @@ impl Message {
             tool_calls: Vec::new(),
             tool_call_id: Some(tool_call_id.into()),
             reasoning_content: None,
+            metadata: None,
         }
     }
 
@@ -443,6 +512,50 @@ impl Message {
         self
     }
 
+    /// Attach provenance metadata stamped with the current time. Tool call
+    /// ids are taken from the message itself.
+    pub fn with_provenance(
+        mut self,
+        agent: Option<&str>,
+        model: Option<&str>,
+        usage: Option<Usage>,
+    ) -> Self {
+        let mut tool_call_ids: Vec<String> = self.tool_calls.iter().map(|c| c.id.clone()).collect();
+        tool_call_ids.extend(self.tool_call_id.clone());
+        self.metadata = Some(MessageMetadata {
+            agent: agent.map(str::to_string),
+            model: model.map(str::to_string),
+            timestamp_ms: MessageMetadata::now_ms(),
+            usage,
+            tool_call_ids,
+            pinned: false,
+        });
+        self
+    }
+
+    /// Whether the message is pinned to survive compaction verbatim.
+    pub fn is_pinned(&self) -> bool {
+        self.metadata.as_ref().is_some_and(|m| m.pinned)
+    }
+
+    /// Pin or unpin the message. A message without metadata gets it.
+    pub fn set_pinned(&mut self, pinned: bool) {
+        match self.metadata {
+            Some(ref mut meta) => meta.pinned = pinned,
+            None if pinned => {
+                self.metadata = Some(MessageMetadata {
+                    agent: None,
+                    model: None,
+                    timestamp_ms: MessageMetadata::now_ms(),
+                    usage: None,
+                    tool_call_ids: Vec::new(),
+                    pinned,
+                })
+            }
+            None => {}
+        }
+    }
+
     /// Count the approximate number of bytes in this message.
     pub fn byte_count(&self) -> usize {
         self.content.byte_count()
@@ -480,6 +593,38 @@ impl Message {
     }
 }
 
+/// Pin or unpin the messages in `range`, widened so a tool call and its
+/// results are always pinned together. Returns the widened range.
+pub fn set_pinned_range(
+    messages: &mut [Message],
+    range: std::ops::Range<usize>,
+    pinned: bool,
+) -> std::ops::Range<usize> {
+    let mut start = range.start.min(messages.len());
+    let mut end = range.end.min(messages.len()).max(start);
+    while start > 0 && start < messages.len() && messages[start].role == Role::Tool {
+        start -= 1;
+    }
+    while end < messages.len() && messages[end].role == Role::Tool {
+        end += 1;
+    }
+    for message in &mut messages[start..end] {
+        message.set_pinned(pinned);
+    }
+    start..end
+}
+
+/// Remove the messages in `range` except pinned ones, which stay in place.
+/// Returns the removed messages in order.
+pub fn drain_unpinned(messages: &mut Vec<Message>, range: std::ops::Range<usize>) -> Vec<Message> {
+    let tail = messages.split_off(range.end);
+    let (pinned, drained): (Vec<Message>, Vec<Message>) =
+        messages.split_off(range.start).into_iter().partition(Message::is_pinned);
+    messages.extend(pinned);
+    messages.extend(tail);
+    drained
+}
+
 /// Strip reasoning content from all messages in a history slice.
 /// Called after the model delivers its final answer (no tool calls)
 /// to avoid sending stale reasoning on future turns.
@@ -489,6 +634,51 @@ pub fn strip_reasoning_from_history(messages: &mut [Message]) {
     }
 }
 
+/// Where a message came from and what producing it cost.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageMetadata {
+    /// Agent that produced the message (None for the top-level session).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub model: Option<String>,
+    /// Creation time, milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Token usage of the completion that produced this message.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub usage: Option<Usage>,
+    /// Tool calls made by this message, or answered by it.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub tool_call_ids: Vec<String>,
+    /// Pinned by the user: compaction and trimming keep it verbatim.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub pinned: bool,
+}
+
+impl MessageMetadata {
+    fn now_ms() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+    }
+}
+
+/// Sum recorded usage per agent. Messages without an agent are grouped
+/// under `""` (the top-level session).
+pub fn usage_by_agent(messages: &[Message]) -> std::collections::BTreeMap<String, Usage> {
+    let mut totals: std::collections::BTreeMap<String, Usage> = std::collections::BTreeMap::new();
+    for meta in messages.iter().filter_map(|m| m.metadata.as_ref()) {
+        if let Some(ref usage) = meta.usage {
+            let entry = totals.entry(meta.agent.clone().unwrap_or_default()).or_default();
+            entry.prompt_tokens += usage.prompt_tokens;
+            entry.completion_tokens += usage.completion_tokens;
+            entry.total_tokens += usage.total_tokens;
+        }
+    }
+    totals
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
@@ -866,4 +1056,93 @@ mod tests {
             _ => panic!("Expected Text variant"),
         }
     }
+
+    #[test]
+    fn test_metadata_omitted_when_absent() {
+        let json = serde_json::to_value(Message::user("hi")).unwrap();
+        assert!(json.get("metadata").is_none());
+        let msg: Message = serde_json::from_value(json).unwrap();
+        assert!(msg.metadata.is_none());
+    }
+
+    #[test]
+    fn test_with_provenance_records_tool_call_ids() {
+        let calls = vec![
+            ToolCall::new("a", "run", serde_json::json!({})),
+            ToolCall::new("b", "run", serde_json::json!({})),
+        ];
+        let msg = Message::assistant_with_tool_calls("", calls).with_provenance(
+            Some("coder"),
+            Some("gpt-4o"),
+            Some(Usage::new(10, 5)),
+        );
+        let meta = msg.metadata.as_ref().unwrap();
+        assert_eq!(meta.tool_call_ids, vec!["a", "b"]);
+        assert!(meta.timestamp_ms > 0);
+
+        let msg: Message = serde_json::from_str(&serde_json::to_string(&msg).unwrap()).unwrap();
+        let meta = msg.metadata.unwrap();
+        assert_eq!(meta.agent.as_deref(), Some("coder"));
+        assert_eq!(meta.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(meta.usage.unwrap().total_tokens, 15);
+
+        let result = Message::tool_result("a", "ok").with_provenance(None, None, None);
+        assert_eq!(result.metadata.unwrap().tool_call_ids, vec!["a"]);
+    }
+
+    #[test]
+    fn test_usage_by_agent() {
+        let messages = vec![
+            Message::assistant("x").with_provenance(Some("coder"), None, Some(Usage::new(10, 5))),
+            Message::assistant("y").with_provenance(Some("coder"), None, Some(Usage::new(20, 5))),
+            Message::assistant("z").with_provenance(None, None, Some(Usage::new(1, 1))),
+            Message::tool_result("a", "ok").with_provenance(Some("coder"), None, None),
+            Message::user("no metadata"),
+        ];
+        let totals = usage_by_agent(&messages);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals["coder"].prompt_tokens, 30);
+        assert_eq!(totals["coder"].total_tokens, 40);
+        assert_eq!(totals[""].total_tokens, 2);
+    }
+
+    #[test]
+    fn test_pinned_messages_survive_drain() {
+        let mut pinned = Message::user("keep this");
+        pinned.set_pinned(true);
+        assert!(pinned.is_pinned());
+        let json = serde_json::to_value(&pinned).unwrap();
+        assert_eq!(json["metadata"]["pinned"], true);
+
+        let mut messages = vec![
+            Message::user("a"),
+            pinned,
+            Message::assistant("b"),
+            Message::user("c"),
+        ];
+        let drained = drain_unpinned(&mut messages, 0..3);
+        let texts = |ms: &[Message]| ms.iter().map(|m| m.content.to_string_lossy()).collect::<Vec<_>>();
+        assert_eq!(texts(&drained), ["a", "b"]);
+        assert_eq!(texts(&messages), ["keep this", "c"]);
+
+        messages[0].set_pinned(false);
+        assert!(!messages[0].is_pinned());
+        assert!(messages.iter().all(|m| !m.is_pinned()));
+        let json = serde_json::to_value(&messages[0]).unwrap();
+        assert!(json["metadata"].get("pinned").is_none());
+    }
+
+    #[test]
+    fn test_set_pinned_range_keeps_tool_calls_whole() {
+        let mut messages = vec![
+            Message::user("task"),
+            Message::assistant_with_tool_calls("", vec![ToolCall::new("a", "run", serde_json::json!({}))]),
+            Message::tool_result("a", "ok"),
+            Message::assistant("done"),
+        ];
+        assert_eq!(set_pinned_range(&mut messages, 2..3, true), 1..3);
+        assert_eq!(set_pinned_range(&mut messages, 1..2, true), 1..3);
+        let pinned: Vec<bool> = messages.iter().map(Message::is_pinned).collect();
+        assert_eq!(pinned, [false, true, true, false]);
+    }
 }
@@ -0,0 +1,233 @@
+//! Narrower retries for tool outputs too large to keep.
+//!
+//! When a tool's output is over the result budget and chunking can't cover
+//! all of it, the model used to get whatever fit plus a generic truncation
+//! note, with nothing telling it how to ask for less. The caller instead asks
+//! the tool for a narrower version of the call ([`Tool::narrower_scope`]) and
+//! returns it as a structured error: arguments for the retry, what changed,
+//! and hints for filters only the model can fill in. Tools without rules of
+//! their own get [`from_schema`], which shrinks limit-style parameters
+//! (`max_depth`, `limit`, ...) in proportion to the overflow and points at
+//! filter parameters the call left unset (`file_pattern`, `glob`, ...).
+//!
+//! [`Tool::narrower_scope`]: crate::tool::Tool::narrower_scope
+
+use serde_json::{json, Value};
+
+use crate::tool::ToolDefinition;
+
+/// Integer parameters that cap how much a call returns.
+const LIMIT_PARAMS: &[&str] = &[
+    "max_depth",
+    "depth",
+    "limit",
+    "max_results",
+    "max_items",
+    "max_entries",
+    "max_lines",
+    "max_count",
+    "head_limit",
+    "top_k",
+    "max_diagnostics",
+];
+
+/// String parameters that filter what a call returns.
+const FILTER_PARAMS: &[&str] = &[
+    "file_pattern",
+    "glob",
+    "include",
+    "pattern",
+    "path_filter",
+    "filter",
+    "grep",
+    "search",
+    "selector",
+];
+
+/// Depth suggested when a depth parameter was left at an unknown default.
+const SUGGESTED_DEPTH: u64 = 2;
+
+/// A narrower version of a call whose output didn't fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeRetry {
+    /// Arguments for the retry: the original ones with `changes` applied.
+    pub arguments: Value,
+    /// What was changed, e.g. "max_depth 5 -> 1".
+    pub changes: Vec<String>,
+    /// Narrowing only the model can do, e.g. "add `file_pattern` ...".
+    pub hints: Vec<String>,
+}
+
+impl ScopeRetry {
+    pub fn new(arguments: Value) -> Self {
+        Self {
+            arguments,
+            changes: Vec::new(),
+            hints: Vec::new(),
+        }
+    }
+
+    /// `None` when nothing was changed or suggested.
+    pub fn into_option(self) -> Option<Self> {
+        (!self.changes.is_empty() || !self.hints.is_empty()).then_some(self)
+    }
+}
+
+/// `value` scaled by `fraction`, at least 1.
+pub fn scale(value: u64, fraction: f64) -> u64 {
+    ((value as f64 * fraction).floor() as u64).max(1)
+}
+
+/// Narrower retry from the tool's parameter schema alone.
+///
+/// `fraction` is the share of the output that fit (budget / output size).
+pub fn from_schema(definition: &ToolDefinition, arguments: &Value, fraction: f64) -> Option<ScopeRetry> {
+    let schema = serde_json::to_value(&definition.parameters).ok()?;
+    let properties = schema.get("properties")?.as_object()?;
+    let mut retry = ScopeRetry::new(arguments.clone());
+    let args = retry.arguments.as_object_mut()?;
+
+    for &name in LIMIT_PARAMS {
+        let Some(property) = properties.get(name) else {
+            continue;
+        };
+        if !has_type(property, "integer") && !has_type(property, "number") {
+            continue;
+        }
+        let current = args
+            .get(name)
+            .and_then(Value::as_u64)
+            .or_else(|| property.get("default").and_then(Value::as_u64));
+        match current {
+            Some(current) => {
+                let narrowed = scale(current, fraction);
+                if narrowed < current {
+                    args.insert(name.to_string(), json!(narrowed));
+                    retry.changes.push(format!("{} {} -> {}", name, current, narrowed));
+                }
+            }
+            None if name.contains("depth") => {
+                args.insert(name.to_string(), json!(SUGGESTED_DEPTH));
+                retry.changes.push(format!("{} set to {}", name, SUGGESTED_DEPTH));
+            }
+            None => retry.hints.push(format!("set `{}` to cap the results", name)),
+        }
+    }
+
+    for &name in FILTER_PARAMS {
+        let unset = args
+            .get(name)
+            .and_then(Value::as_str)
+            .is_none_or(|v| v.trim().is_empty());
+        if unset && properties.get(name).is_some_and(|p| has_type(p, "string")) {
+            retry
+                .hints
+                .push(format!("add `{}` to match only what you need", name));
+        }
+    }
+
+    retry.into_option()
+}
+
+/// Whether a JSON Schema property has `ty` as (one of) its types.
+fn has_type(property: &Value, ty: &str) -> bool {
+    match property.get("type") {
+        Some(Value::String(t)) => t == ty,
+        Some(Value::Array(types)) => types.iter().any(|t| t == ty),
+        _ => false,
+    }
+}
+
+/// Error result returned in place of an overflowing output: the retry as
+/// JSON, then `partial`, what of the output could be kept.
+pub fn overflow_error(
+    tool: &str,
+    retry: &ScopeRetry,
+    output_bytes: usize,
+    budget_bytes: usize,
+    partial: &str,
+) -> String {
+    let details = json!({
+        "error": "output_too_large",
+        "output_bytes": output_bytes,
+        "budget_bytes": budget_bytes,
+        "retry": {"tool": tool, "arguments": retry.arguments},
+        "changes": retry.changes,
+        "hints": retry.hints,
+    });
+    format!(
+        "Error: Output too large: {} bytes is over the {}-byte budget and could only be kept \
+         in part. Retry with a narrower scope:\n{}\n\nPartial result:\n{}",
+        output_bytes,
+        budget_bytes,
+        serde_json::to_string_pretty(&details).unwrap_or_default(),
+        partial
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::{PropertySchema, ToolParameters};
+
+    fn definition() -> ToolDefinition {
+        ToolDefinition::new("list_tree", "List a directory tree").with_parameters(
+            ToolParameters::new()
+                .add_property("path", PropertySchema::string("Directory"), true)
+                .add_property("max_depth", PropertySchema::integer("Depth"), false)
+                .add_property("limit", PropertySchema::integer("Entries"), false)
+                .add_property("file_pattern", PropertySchema::string("Glob"), false),
+        )
+    }
+
+    #[test]
+    fn test_from_schema_shrinks_limits_and_suggests_filters() {
+        let retry = from_schema(&definition(), &json!({"path": "src", "max_depth": 6}), 0.25).unwrap();
+        assert_eq!(retry.arguments, json!({"path": "src", "max_depth": 1}));
+        assert_eq!(retry.changes, vec!["max_depth 6 -> 1"]);
+        assert_eq!(
+            retry.hints,
+            vec![
+                "set `limit` to cap the results",
+                "add `file_pattern` to match only what you need"
+            ]
+        );
+
+        // Unknown depth gets a small one; a filter already given isn't suggested
+        let retry = from_schema(
+            &definition(),
+            &json!({"path": ".", "limit": 40, "file_pattern": "*.rs"}),
+            0.5,
+        )
+        .unwrap();
+        assert_eq!(retry.arguments["max_depth"], 2);
+        assert_eq!(retry.arguments["limit"], 20);
+        assert!(retry.hints.is_empty());
+
+        // MCP tools pass their schema through raw
+        let raw = ToolDefinition::new("search", "").with_parameters(ToolParameters::from_raw(json!({
+            "type": "object",
+            "properties": {"max_results": {"type": ["integer", "null"], "default": 100}}
+        })));
+        let retry = from_schema(&raw, &json!({}), 0.1).unwrap();
+        assert_eq!(retry.arguments, json!({"max_results": 10}));
+
+        // Nothing to narrow
+        let plain = ToolDefinition::new("datetime", "");
+        assert!(from_schema(&plain, &json!({}), 0.1).is_none());
+    }
+
+    #[test]
+    fn test_overflow_error_is_parseable() {
+        let retry = from_schema(&definition(), &json!({"path": "src"}), 0.5).unwrap();
+        let error = overflow_error("list_tree", &retry, 200_000, 50_000, "src/\n  a.rs");
+        assert!(error.starts_with("Error: Output too large: 200000 bytes"));
+
+        let json_start = error.find('{').unwrap();
+        let json_end = error.find("\n\nPartial result:").unwrap();
+        let details: Value = serde_json::from_str(&error[json_start..json_end]).unwrap();
+        assert_eq!(details["retry"]["tool"], "list_tree");
+        assert_eq!(details["retry"]["arguments"]["max_depth"], 2);
+        assert!(error.ends_with("src/\n  a.rs"));
+    }
+}
@@ -3,10 +3,12 @@
 //! Implements a three-tier architecture (messages -> observations -> reflections)
 //! that maintains a structured, append-only observation log with dated, prioritized entries.
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 
 use crate::error::Error;
-use crate::message::{Content, ContentPart, Message, Role, TypedContent};
+use crate::message::{drain_unpinned, Content, ContentPart, Message, Role, TypedContent};
 
 /// Async trait for LLM-powered observation and reflection.
 /// Implemented in qq-cli with a concrete provider.
@@ -26,6 +28,12 @@ pub trait ContextCompactor: Send + Sync {
     ) -> Result<String, Error> {
         self.observe(messages).await
     }
+
+    /// A compactor that also preserves what `focus` asks for (an agent's
+    /// compaction prompt), or `None` if this one can't be specialized.
+    fn with_focus(&self, _focus: &str) -> Option<Arc<dyn ContextCompactor>> {
+        None
+    }
 }
 
 /// Configuration for the OM thresholds.
@@ -129,6 +137,16 @@ impl Default for ObservationConfig {
     }
 }
 
+/// One observation pass: the observations it produced and the messages it
+/// drained from the history to make them.
+#[derive(Debug, Clone)]
+pub struct ObservationPass {
+    /// 1-based pass number (the `observation_count` after the pass).
+    pub number: u32,
+    pub observations: String,
+    pub messages: Vec<Message>,
+}
+
 /// The core Observational Memory state machine.
 pub struct ObservationalMemory {
     /// The accumulated observation log (formatted text).
@@ -141,6 +159,9 @@ pub struct ObservationalMemory {
     pub reflection_count: u32,
     /// Configuration thresholds.
     config: ObservationConfig,
+    /// Passes not yet taken with [`take_passes`](Self::take_passes); `None`
+    /// when recording is off.
+    passes: Option<Vec<ObservationPass>>,
 }
 
 impl ObservationalMemory {
@@ -151,6 +172,7 @@ impl ObservationalMemory {
             observation_count: 0,
             reflection_count: 0,
             config,
+            passes: None,
         }
     }
 
@@ -163,9 +185,23 @@ impl ObservationalMemory {
             observation_count: 0,
             reflection_count: 0,
             config,
+            passes: None,
         }
     }
 
+    /// Keep each pass's observations and drained messages until they are
+    /// taken, so the caller can archive what compaction replaced.
+    pub fn with_pass_recording(mut self) -> Self {
+        self.passes = Some(Vec::new());
+        self
+    }
+
+    /// Passes recorded since the last call, oldest first. Always empty
+    /// unless [`with_pass_recording`](Self::with_pass_recording) was used.
+    pub fn take_passes(&mut self) -> Vec<ObservationPass> {
+        self.passes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     /// Decompose into parts for storage: (observation_log, observation_count, reflection_count).
     pub fn into_parts(self) -> (String, u32, u32) {
         (self.observation_log, self.observation_count, self.reflection_count)
@@ -201,10 +237,7 @@ impl ObservationalMemory {
             return false;
         }
 
-        let safe_bytes: usize = messages[self.observed_up_to..safe_end]
-            .iter()
-            .map(|m| m.observable_byte_count())
-            .sum();
+        let safe_bytes = observable_bytes(&messages[self.observed_up_to..safe_end]);
 
         let threshold =
             (self.config.message_threshold_bytes as f64 * self.config.hysteresis) as usize;
@@ -285,10 +318,7 @@ impl ObservationalMemory {
         let unobserved_end = messages.len().saturating_sub(preserve);
 
         if unobserved_end > self.observed_up_to {
-            let unobserved_bytes: usize = messages[self.observed_up_to..unobserved_end]
-                .iter()
-                .map(|m| m.observable_byte_count())
-                .sum();
+            let unobserved_bytes = observable_bytes(&messages[self.observed_up_to..unobserved_end]);
 
             let msg_threshold =
                 (self.config.message_threshold_bytes as f64 * self.config.hysteresis) as usize;
@@ -297,20 +327,22 @@ impl ObservationalMemory {
                 // Find safe split point (don't break tool call sequences)
                 let safe_end = find_safe_split_point(messages, unobserved_end);
                 if safe_end > self.observed_up_to {
-                    let to_observe = &messages[self.observed_up_to..safe_end];
+                    // Pinned messages are never observed; they stay verbatim
+                    let to_observe: Vec<Message> = messages[self.observed_up_to..safe_end]
+                        .iter()
+                        .filter(|m| !m.is_pinned())
+                        .cloned()
+                        .collect();
 
                     // Re-check threshold on the actual safe range — find_safe_split_point
                     // may have shrunk the range significantly (e.g. backing up past tool
                     // call sequences). Without this check we'd observe and drain a tiny
                     // slice of messages (sometimes just the initial user task).
-                    let safe_bytes: usize = to_observe
-                        .iter()
-                        .map(|m| m.observable_byte_count())
-                        .sum();
+                    let safe_bytes = observable_bytes(&to_observe);
 
                     // In force mode, observe whatever's in the safe range — even
                     // a small drain helps when we're already at the context wall.
-                    if !force && safe_bytes <= msg_threshold {
+                    if to_observe.is_empty() || (!force && safe_bytes <= msg_threshold) {
                         tracing::info!(
                             safe_bytes = safe_bytes,
                             threshold = msg_threshold,
@@ -320,7 +352,7 @@ impl ObservationalMemory {
                         );
                     } else {
 
-                    let stripped = strip_images_from_messages(to_observe);
+                    let stripped = strip_images_from_messages(&to_observe);
 
                     tracing::info!(
                         messages_to_observe = to_observe.len(),
@@ -338,22 +370,31 @@ impl ObservationalMemory {
                             }
                             self.observation_log.push_str(&observations);
 
-                            // Drain the observed messages
-                            messages.drain(self.observed_up_to..safe_end);
-                            // After draining, all remaining messages are unobserved recent ones.
-                            // observed_up_to stays at same index (pointing at start of recent msgs)
-                            // since we drained everything before it.
+                            // Drain the observed messages, leaving pinned ones in place
+                            let drained =
+                                drain_unpinned(messages, self.observed_up_to..safe_end);
+                            // After draining, all remaining messages are unobserved recent ones
+                            // (or pinned). observed_up_to stays at same index since we drained
+                            // everything else before it.
 
                             self.observation_count += 1;
 
                             tracing::info!(
                                 observation_bytes = observations.len(),
-                                messages_drained = safe_end - self.observed_up_to,
+                                messages_drained = drained.len(),
                                 remaining_messages = messages.len(),
                                 log_bytes = self.observation_log.len(),
                                 observation_count = self.observation_count,
                                 "Observation pass complete"
                             );
+
+                            if let Some(ref mut passes) = self.passes {
+                                passes.push(ObservationPass {
+                                    number: self.observation_count,
+                                    observations,
+                                    messages: drained,
+                                });
+                            }
                         }
                         Ok(_) => {
                             tracing::warn!("Observer returned empty result, skipping");
@@ -430,9 +471,21 @@ impl ObservationalMemory {
         self.observed_up_to = 0;
         self.observation_count = 0;
         self.reflection_count = 0;
+        if let Some(ref mut passes) = self.passes {
+            passes.clear();
+        }
     }
 }
 
+/// Bytes the observer would see, leaving out pinned messages.
+fn observable_bytes(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .filter(|m| !m.is_pinned())
+        .map(|m| m.observable_byte_count())
+        .sum()
+}
+
 /// Replace image content with text placeholders before passing to the compactor.
 /// This prevents sending huge base64 data to the compaction LLM.
 pub fn strip_images_from_messages(messages: &[Message]) -> Vec<Message> {
@@ -480,6 +533,7 @@ pub fn strip_images_from_messages(messages: &[Message]) -> Vec<Message> {
             tool_calls: msg.tool_calls.clone(),
             tool_call_id: msg.tool_call_id.clone(),
             reasoning_content: msg.reasoning_content.clone(),
+            metadata: msg.metadata.clone(),
         }
     }).collect()
 }
@@ -775,6 +829,65 @@ mod tests {
         assert!(om.observation_log.contains("Found something"));
     }
 
+    #[tokio::test]
+    async fn test_compact_records_passes() {
+        let config = ObservationConfig {
+            message_threshold_bytes: 200,
+            observation_threshold_bytes: 100_000,
+            preserve_recent: 2,
+            hysteresis: 1.0,
+            ..Default::default()
+        };
+        let compactor = TestCompactor::observe_ok("- Found something");
+        let messages: Vec<Message> = (0..6).map(|_| msg_with_bytes(Role::User, 100)).collect();
+
+        // Off by default
+        let mut om = ObservationalMemory::new(config.clone());
+        om.compact(&mut messages.clone(), &compactor).await.unwrap();
+        assert!(om.take_passes().is_empty());
+
+        let mut om = ObservationalMemory::new(config).with_pass_recording();
+        let mut history = messages.clone();
+        om.compact(&mut history, &compactor).await.unwrap();
+        let passes = om.take_passes();
+        assert_eq!(passes.len(), 1);
+        assert_eq!(passes[0].number, 1);
+        assert_eq!(passes[0].observations, "- Found something");
+        assert_eq!(passes[0].messages.len() + history.len(), messages.len());
+        assert!(om.take_passes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compact_keeps_pinned_messages() {
+        let config = ObservationConfig {
+            message_threshold_bytes: 200,
+            observation_threshold_bytes: 100_000,
+            preserve_recent: 2,
+            hysteresis: 1.0,
+            ..Default::default()
+        };
+        let mut om = ObservationalMemory::new(config);
+        let compactor = TestCompactor::observe_ok("- Found something");
+
+        let mut messages: Vec<Message> = (0..6).map(|_| msg_with_bytes(Role::User, 100)).collect();
+        messages[1] = Message::user("the schema must stay backward compatible");
+        messages[1].set_pinned(true);
+
+        om.compact(&mut messages, &compactor).await.unwrap();
+        assert_eq!(om.observation_count, 1);
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].is_pinned());
+
+        // Pinned bytes alone never trigger another pass
+        let mut pinned: Vec<Message> = (0..6).map(|_| msg_with_bytes(Role::User, 100)).collect();
+        pinned.iter_mut().for_each(|m| m.set_pinned(true));
+        let mut om = ObservationalMemory::new(om.config().clone());
+        assert!(!om.needs_observation(&pinned));
+        om.compact_force(&mut pinned, &compactor).await.unwrap();
+        assert_eq!(pinned.len(), 6);
+        assert_eq!(om.observation_count, 0);
+    }
+
     #[tokio::test]
     async fn test_compact_no_action_below_threshold() {
         let config = ObservationConfig {
@@ -0,0 +1,227 @@
+//! Context-window overflow detection and recovery.
+//!
+//! Providers map their own "prompt too long" errors to
+//! [`Error::ContextOverflow`](crate::Error::ContextOverflow), falling back to
+//! [`mentions_context_overflow`] for servers that only report it in prose.
+//! Callers recover by compacting (when a compactor is configured) or by
+//! [`evict_for_context_overflow`], then retry the request once.
+
+use crate::message::{drain_unpinned, Content, Message, Role};
+
+/// Most recent tool results kept intact when clearing tool output.
+const KEEP_RECENT_TOOL_RESULTS: usize = 2;
+
+/// Phrases servers use for context-length errors (lowercase).
+const OVERFLOW_PHRASES: &[&str] = &[
+    "context_length_exceeded",
+    "maximum context length",
+    "context length",
+    "context window",
+    "context size",
+    "prompt is too long",
+    "input is too long",
+    "too many tokens",
+    "input token count",
+    "reduce the length of the messages",
+];
+
+/// Whether an error message describes a context-window overflow.
+///
+/// Generic fallback for OpenAI-compatible servers (vLLM, llama.cpp, Ollama,
+/// LiteLLM) that don't set a machine-readable error code.
+pub fn mentions_context_overflow(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    OVERFLOW_PHRASES.iter().any(|p| lower.contains(p))
+}
+
+/// What [`evict_for_context_overflow`] removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OverflowEviction {
+    /// Whole messages dropped from the start of the history
+    pub removed_messages: usize,
+    /// Older tool results replaced with a placeholder
+    pub cleared_tool_results: usize,
+    pub bytes_freed: usize,
+}
+
+impl OverflowEviction {
+    pub fn is_empty(&self) -> bool {
+        self.bytes_freed == 0
+    }
+
+    /// Short description for warnings, e.g. "dropped 6 messages, cleared 2 tool results".
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.removed_messages > 0 {
+            parts.push(format!("dropped {} oldest messages", self.removed_messages));
+        }
+        if self.cleared_tool_results > 0 {
+            parts.push(format!("cleared {} old tool results", self.cleared_tool_results));
+        }
+        if parts.is_empty() {
+            "nothing to evict".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Shrink `messages` after a context overflow, aiming to free half its bytes.
+///
+/// First drops whole turns from the start of the history, cutting only at
+/// user messages so tool call sequences stay intact; the leading system
+/// message and the latest user message (with everything after it) are kept.
+/// If that is not enough — a single long agent turn — the oldest tool
+/// results are replaced with a placeholder, keeping the most recent ones.
+/// Pinned messages are never dropped or cleared.
+pub fn evict_for_context_overflow(messages: &mut Vec<Message>) -> OverflowEviction {
+    let total: usize = messages.iter().map(|m| m.byte_count()).sum();
+    let target = total / 2;
+    let mut eviction = OverflowEviction::default();
+
+    // Stage 1: drop the oldest turns before the latest user message
+    let start = messages
+        .iter()
+        .position(|m| m.role != Role::System)
+        .unwrap_or(messages.len());
+    let protected = messages
+        .iter()
+        .rposition(|m| m.role == Role::User)
+        .unwrap_or(messages.len())
+        .max(start);
+    let mut cut = start;
+    let mut freed = 0;
+    for i in start..protected {
+        if !messages[i].is_pinned() {
+            freed += messages[i].byte_count();
+        }
+        let next_is_boundary = i + 1 == protected || messages[i + 1].role == Role::User;
+        if next_is_boundary {
+            cut = i + 1;
+            if freed >= target {
+                break;
+            }
+        }
+    }
+    if cut > start {
+        let dropped = drain_unpinned(messages, start..cut);
+        eviction.removed_messages = dropped.len();
+        eviction.bytes_freed += dropped.iter().map(|m| m.byte_count()).sum::<usize>();
+    }
+
+    // Stage 2: clear the oldest tool results
+    let tool_results: Vec<usize> = (0..messages.len())
+        .filter(|&i| messages[i].role == Role::Tool && !messages[i].is_pinned())
+        .collect();
+    let clearable = tool_results.len().saturating_sub(KEEP_RECENT_TOOL_RESULTS);
+    for &i in &tool_results[..clearable] {
+        if eviction.bytes_freed >= target {
+            break;
+        }
+        let before = messages[i].byte_count();
+        let placeholder = format!(
+            "[{} bytes of tool output removed to fit the context window]",
+            before
+        );
+        if placeholder.len() >= before {
+            continue;
+        }
+        messages[i].content = Content::text(placeholder);
+        eviction.cleared_tool_results += 1;
+        eviction.bytes_freed += before - messages[i].byte_count();
+    }
+
+    eviction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ToolCall;
+
+    fn tool_turn(id: &str, output: &str) -> Vec<Message> {
+        vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall::new(id, "run", serde_json::json!({"command": "ls"}))],
+            ),
+            Message::tool_result(id, output),
+        ]
+    }
+
+    #[test]
+    fn test_mentions_context_overflow() {
+        assert!(mentions_context_overflow(
+            "This model's maximum context length is 8192 tokens. However, you requested 9000 tokens"
+        ));
+        assert!(mentions_context_overflow(
+            "the request exceeds the available context size, try increasing it"
+        ));
+        assert!(mentions_context_overflow("prompt is too long: 210000 tokens > 200000 maximum"));
+        assert!(!mentions_context_overflow("Invalid API key"));
+        assert!(!mentions_context_overflow("temperature must be between 0 and 2"));
+    }
+
+    #[test]
+    fn test_drops_oldest_turns_at_user_boundaries() {
+        let big = "x".repeat(4000);
+        let mut messages = vec![Message::system("sys"), Message::user("first question")];
+        messages.extend(tool_turn("a", &big));
+        messages.push(Message::assistant("first answer"));
+        messages.push(Message::user("second question"));
+        messages.push(Message::assistant("second answer"));
+        messages.push(Message::user("third question"));
+
+        let eviction = evict_for_context_overflow(&mut messages);
+        assert_eq!(eviction.removed_messages, 4);
+        assert_eq!(eviction.cleared_tool_results, 0);
+        assert!(!eviction.is_empty());
+
+        // System prompt kept; history now starts at a user message
+        assert_eq!(messages[0].role, Role::System);
+        assert_eq!(messages[1].content.as_text(), Some("second question"));
+        assert_eq!(messages.last().unwrap().content.as_text(), Some("third question"));
+    }
+
+    #[test]
+    fn test_clears_old_tool_results_within_a_single_turn() {
+        let big = "y".repeat(3000);
+        let mut messages = vec![Message::user("task")];
+        for id in ["a", "b", "c", "d"] {
+            messages.extend(tool_turn(id, &big));
+        }
+
+        let eviction = evict_for_context_overflow(&mut messages);
+        assert_eq!(eviction.removed_messages, 0);
+        assert_eq!(eviction.cleared_tool_results, 2);
+        assert_eq!(messages.len(), 9);
+        assert!(messages[2]
+            .content
+            .as_text()
+            .unwrap()
+            .contains("removed to fit the context window"));
+        // The two most recent results are untouched
+        assert_eq!(messages[8].content.as_text(), Some(big.as_str()));
+        assert_eq!(messages[6].content.as_text(), Some(big.as_str()));
+
+        // Nothing left to free on a bare prompt
+        let mut bare = vec![Message::system("sys"), Message::user("hi")];
+        assert!(evict_for_context_overflow(&mut bare).is_empty());
+        assert_eq!(bare.len(), 2);
+    }
+
+    #[test]
+    fn test_keeps_pinned_messages() {
+        let big = "x".repeat(4000);
+        let mut messages = vec![Message::user("first question")];
+        messages.extend(tool_turn("a", &big));
+        messages.push(Message::assistant("use port 8443, never 443"));
+        messages.push(Message::user("second question"));
+        messages[3].set_pinned(true);
+
+        let eviction = evict_for_context_overflow(&mut messages);
+        assert_eq!(eviction.removed_messages, 3);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content.as_text(), Some("use port 8443, never 443"));
+    }
+}
@@ -5,7 +5,7 @@ use futures::Stream;
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::message::{FinishReason, Message, StreamChunk, Usage};
+use crate::message::{FileRef, FinishReason, Message, StreamChunk, Usage};
 use crate::tool::ToolDefinition;
 
 pub type StreamResult = Pin<Box<dyn Stream<Item = Result<StreamChunk, Error>> + Send>>;
@@ -71,6 +71,11 @@ impl CompletionRequest {
         self
     }
 
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
     pub fn with_top_k(mut self, top_k: i32) -> Self {
         self.top_k = Some(top_k);
         self
@@ -108,6 +113,44 @@ impl CompletionRequest {
 
 }
 
+/// Sampling settings an agent applies to every request it sends.
+///
+/// Unset fields keep the provider's default. `extra` entries are added to the
+/// request's extra parameters, replacing any with the same key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SamplingOverrides {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub extra: std::collections::HashMap<String, serde_json::Value>,
+}
+
+impl SamplingOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.temperature.is_none()
+            && self.top_p.is_none()
+            && self.max_tokens.is_none()
+            && self.extra.is_empty()
+    }
+
+    /// Merge these settings into `request`.
+    pub fn apply(&self, mut request: CompletionRequest) -> CompletionRequest {
+        if let Some(temperature) = self.temperature {
+            request.temperature = Some(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            request.top_p = Some(top_p);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            request.max_tokens = Some(max_tokens);
+        }
+        request
+            .extra
+            .extend(self.extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+        request
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionResponse {
     /// The assistant's response message (content only, no thinking).
@@ -147,6 +190,35 @@ pub trait Provider: Send + Sync {
     fn context_window(&self) -> Option<u32> {
         None
     }
+
+    /// Embed each input with `model`, returning one vector per input in the
+    /// same order. Providers without an embeddings API return an error.
+    async fn embed(&self, _model: &str, _inputs: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        Err(Error::invalid_request(format!(
+            "provider '{}' does not support embeddings",
+            self.name()
+        )))
+    }
+
+    /// Whether [`Provider::upload_file`] is available.
+    fn supports_file_upload(&self) -> bool {
+        false
+    }
+
+    /// Upload a file to the provider's file store so messages can reference
+    /// it by ID instead of inlining it. Providers without a files API return
+    /// an error.
+    async fn upload_file(
+        &self,
+        _filename: &str,
+        _media_type: &str,
+        _bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        Err(Error::invalid_request(format!(
+            "provider '{}' does not support file uploads",
+            self.name()
+        )))
+    }
 }
 
 #[cfg(test)]
@@ -202,6 +274,37 @@ mod tests {
         assert_eq!(request.repetition_penalty, Some(1.5));
     }
 
+    #[test]
+    fn test_sampling_overrides_apply() {
+        let mut base_extra = std::collections::HashMap::new();
+        base_extra.insert("reasoning_effort".to_string(), serde_json::json!("high"));
+        base_extra.insert("seed".to_string(), serde_json::json!(7));
+        let request = CompletionRequest::new(vec![Message::user("Test")])
+            .with_temperature(0.7)
+            .with_max_tokens(4096)
+            .with_extra(base_extra);
+
+        let mut extra = std::collections::HashMap::new();
+        extra.insert("reasoning_effort".to_string(), serde_json::json!("low"));
+        let overrides = SamplingOverrides {
+            temperature: Some(0.0),
+            top_p: Some(0.9),
+            max_tokens: None,
+            extra,
+        };
+        assert!(!overrides.is_empty());
+        let request = overrides.apply(request);
+
+        assert_eq!(request.temperature, Some(0.0));
+        assert_eq!(request.top_p, Some(0.9));
+        // Unset overrides keep the request's value
+        assert_eq!(request.max_tokens, Some(4096));
+        assert_eq!(request.extra["reasoning_effort"], "low");
+        assert_eq!(request.extra["seed"], 7);
+
+        assert!(SamplingOverrides::default().is_empty());
+    }
+
     #[test]
     fn test_completion_request_default_values() {
         // Verify new params default to None for backward compatibility
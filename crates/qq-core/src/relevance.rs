@@ -0,0 +1,254 @@
+//! Query-aware line filtering for oversized tool output.
+//!
+//! Runs before LLM chunk summarization. Each line is scored by error/failure
+//! signals and by terms from the current task; high-scoring lines are kept with
+//! a little surrounding context, build/download progress noise is dropped, and
+//! the head and tail of the output are always kept. Summarizing a 200KB build
+//! log tends to lose the one `error[E0308]` line; this keeps it verbatim.
+
+/// Lines of context kept on each side of a relevant line.
+const CONTEXT_LINES: usize = 2;
+
+/// Leading lines always kept (command echo, headers).
+const HEAD_LINES: usize = 5;
+
+/// Trailing lines always kept (exit codes, test summaries).
+const TAIL_LINES: usize = 10;
+
+/// Query terms shorter than this are ignored.
+const MIN_TERM_LEN: usize = 4;
+
+/// At most this many query terms count toward one line's score.
+const MAX_TERM_HITS: usize = 3;
+
+/// Substrings (lowercased) that mark a line as a failure signal.
+const ERROR_SIGNALS: &[&str] = &[
+    "error",
+    "failed",
+    "failure",
+    "panic",
+    "exception",
+    "traceback",
+    "fatal",
+    "assert",
+    "denied",
+    "not found",
+    "no such file",
+    "undefined",
+    "cannot ",
+    "unable to",
+    "segmentation fault",
+];
+
+/// Substrings (lowercased) that mark a line as a weaker warning signal.
+const WARNING_SIGNALS: &[&str] = &["warning", "warn:", "deprecated"];
+
+/// Line prefixes (after trimming) that are progress noise.
+const BOILERPLATE_PREFIXES: &[&str] = &[
+    "Compiling ",
+    "Checking ",
+    "Downloading ",
+    "Downloaded ",
+    "Fresh ",
+    "Updating ",
+    "Installing ",
+    "Unpacking ",
+    "Resolving ",
+    "Fetching ",
+    "Building [",
+    "Progress:",
+    "Collecting ",
+    "Requirement already satisfied",
+    "Using cached ",
+];
+
+/// Common words that say nothing about relevance.
+const STOPWORDS: &[&str] = &[
+    "about", "after", "also", "before", "could", "does", "file", "files", "find", "from", "have",
+    "into", "make", "more", "only", "please", "should", "show", "that", "their", "there", "them",
+    "then", "these", "they", "this", "what", "when", "where", "which", "while", "will", "with",
+    "would", "your",
+];
+
+/// Result of a relevance pass.
+#[derive(Debug, Clone)]
+pub struct RelevanceFilter {
+    /// Kept lines in original order, with `[... N lines omitted ...]` markers.
+    pub text: String,
+    /// Lines kept (excluding omission markers).
+    pub kept_lines: usize,
+    /// Lines in the original content.
+    pub total_lines: usize,
+    /// Lines that matched an error/warning signal or a query term.
+    pub matched_lines: usize,
+}
+
+/// Extract the lines of `content` that matter for `query`.
+///
+/// Returns `None` when no line scores (nothing recognizable to anchor on), in
+/// which case the caller should fall back to LLM summarization.
+pub fn filter_relevant(content: &str, query: Option<&str>) -> Option<RelevanceFilter> {
+    let lines: Vec<&str> = content.lines().collect();
+    let total_lines = lines.len();
+    let terms = query.map(query_terms).unwrap_or_default();
+
+    let scores: Vec<usize> = lines.iter().map(|line| score_line(line, &terms)).collect();
+    let matched_lines = scores.iter().filter(|&&s| s > 0).count();
+    if matched_lines == 0 {
+        return None;
+    }
+
+    let mut keep = vec![false; total_lines];
+    for (i, &score) in scores.iter().enumerate() {
+        if score > 0 {
+            let start = i.saturating_sub(CONTEXT_LINES);
+            let end = (i + CONTEXT_LINES + 1).min(total_lines);
+            keep[start..end].iter_mut().for_each(|k| *k = true);
+        }
+    }
+    keep.iter_mut().take(HEAD_LINES).for_each(|k| *k = true);
+    keep.iter_mut()
+        .rev()
+        .take(TAIL_LINES)
+        .for_each(|k| *k = true);
+
+    let mut text = String::new();
+    let mut kept_lines = 0;
+    let mut omitted = 0;
+    let mut previous: Option<&str> = None;
+    for (i, line) in lines.iter().enumerate() {
+        // Noise and repeats are dropped even inside a context window,
+        // but a scored line is always kept
+        let droppable = scores[i] == 0 && (is_boilerplate(line) || previous == Some(*line));
+        if !keep[i] || droppable {
+            omitted += 1;
+            continue;
+        }
+        if omitted > 0 {
+            text.push_str(&format!("[... {} lines omitted ...]\n", omitted));
+            omitted = 0;
+        }
+        text.push_str(line);
+        text.push('\n');
+        kept_lines += 1;
+        previous = Some(line);
+    }
+    if omitted > 0 {
+        text.push_str(&format!("[... {} lines omitted ...]\n", omitted));
+    }
+
+    Some(RelevanceFilter {
+        text,
+        kept_lines,
+        total_lines,
+        matched_lines,
+    })
+}
+
+/// Distinct lowercase terms from the task worth matching on.
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split(|c: char| !(c.is_alphanumeric() || "_-./:".contains(c))) {
+        let word = word
+            .trim_matches(|c: char| ".:-".contains(c))
+            .to_lowercase();
+        if word.len() >= MIN_TERM_LEN
+            && !STOPWORDS.contains(&word.as_str())
+            && !terms.contains(&word)
+        {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+fn score_line(line: &str, terms: &[String]) -> usize {
+    if line.trim().is_empty() || is_boilerplate(line) {
+        return 0;
+    }
+    let lower = line.to_lowercase();
+    let mut score = 0;
+    if ERROR_SIGNALS.iter().any(|s| lower.contains(s)) {
+        score += 3;
+    } else if WARNING_SIGNALS.iter().any(|s| lower.contains(s)) {
+        score += 1;
+    }
+    let hits = terms.iter().filter(|t| lower.contains(t.as_str())).count();
+    score + 2 * hits.min(MAX_TERM_HITS)
+}
+
+fn is_boilerplate(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if BOILERPLATE_PREFIXES.iter().any(|p| trimmed.starts_with(p)) {
+        return true;
+    }
+    // Progress bars: "[=====>    ] 45%" or "#### 80%"
+    trimmed.ends_with('%') && trimmed.chars().filter(|c| "=#>".contains(*c)).count() >= 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_error_line_from_build_log() {
+        let mut log = String::from("$ cargo build\n");
+        for i in 0..500 {
+            log.push_str(&format!("   Compiling dep{} v0.1.{}\n", i, i));
+        }
+        log.push_str("error[E0308]: mismatched types\n");
+        log.push_str("  --> src/parse.rs:42:9\n");
+        for i in 0..300 {
+            log.push_str(&format!("note line {}\n", i));
+        }
+        log.push_str("error: could not compile `qq-core`\n");
+
+        let filtered = filter_relevant(&log, Some("fix the build")).unwrap();
+        assert!(filtered.text.contains("error[E0308]: mismatched types"));
+        assert!(filtered.text.contains("src/parse.rs:42:9"));
+        assert!(filtered.text.contains("could not compile"));
+        assert!(filtered.text.starts_with("$ cargo build\n"));
+        assert!(!filtered.text.contains("Compiling dep400"));
+        assert!(filtered.text.contains("lines omitted"));
+        assert_eq!(filtered.total_lines, 804);
+        assert!(filtered.text.len() < log.len() / 10);
+    }
+
+    #[test]
+    fn test_query_terms_drive_selection() {
+        let content = (0..200)
+            .map(|i| {
+                if i == 120 {
+                    "src/session.rs:88: fn restore_session_state() {".to_string()
+                } else {
+                    format!("src/other{}.rs: fn helper() {{}}", i)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let filtered =
+            filter_relevant(&content, Some("Where is restore_session_state defined?")).unwrap();
+        assert_eq!(filtered.matched_lines, 1);
+        assert!(filtered.text.contains("restore_session_state"));
+        assert!(filtered.text.contains("other118"));
+        assert!(!filtered.text.contains("other60.rs"));
+    }
+
+    #[test]
+    fn test_nothing_relevant_falls_back() {
+        let content = "plain line\n".repeat(100);
+        assert!(filter_relevant(&content, Some("the and with")).is_none());
+        assert!(filter_relevant(&content, None).is_none());
+    }
+
+    #[test]
+    fn test_query_terms() {
+        assert_eq!(
+            query_terms("Why does `cargo test` fail in qq-core/src/agent.rs? Please check."),
+            vec!["cargo", "test", "fail", "qq-core/src/agent.rs", "check"]
+        );
+        assert!(is_boilerplate("[=====>      ] 45%"));
+        assert!(!is_boilerplate("Finished in 45%"));
+    }
+}
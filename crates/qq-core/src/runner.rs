@@ -0,0 +1,1123 @@
+//! The agentic loop behind one-shot completion and interactive chat.
+//!
+//! `qq -p`, readline chat and the TUI each used to drive their own copy of
+//! the request → tool calls → results loop, and features landed in one copy
+//! but not the others: one-shot completion never compacted, never retried and
+//! reported nothing. [`AgentRunner`] is the one loop they share. Before each
+//! request it compacts the [`Conversation`]; it retries transient errors with
+//! backoff, recovers once per turn from a context-window overflow, runs tool
+//! calls in parallel with large outputs chunked, and stops on cancellation.
+//! With a [`Regrounding`] it restates the user's request when compaction has
+//! dropped it or the run resumes after a long pause.
+//! Everything that happens is reported to a [`RunHandler`] as a [`RunEvent`],
+//! so callers only decide where messages live and how events are shown.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::cancel::with_cancellation;
+use crate::agent::{INITIAL_RETRY_DELAY, MAX_STREAM_RETRIES, STREAM_CHUNK_TIMEOUT};
+use crate::chunker::ChunkProcessor;
+use crate::error::Error;
+use crate::grounding::{RegroundTrigger, Regrounding};
+use crate::message::{strip_reasoning_from_history, FinishReason, Message, StreamChunk, ToolCall, Usage};
+use crate::observation::{ContextCompactor, ObservationConfig, ObservationalMemory};
+use crate::overflow::{evict_for_context_overflow, mentions_context_overflow};
+use crate::provider::{CompletionRequest, Provider};
+use crate::task::{chunk_tool_output, execute_tool_call, write_conflicts, ToolExecutionResult};
+use crate::tool::ToolRegistry;
+
+/// Iteration ceiling for a run unless set with [`AgentRunner::with_max_iterations`].
+pub const DEFAULT_MAX_ITERATIONS: usize = 100;
+
+/// Something that happened during a run.
+#[derive(Debug, Clone)]
+pub enum RunEvent {
+    /// A request is about to be sent (1-based).
+    IterationStart { iteration: usize },
+    /// The provider started streaming a response.
+    Start { model: String },
+    ThinkingDelta(String),
+    ContentDelta(String),
+    ToolCallStart { id: String, name: String },
+    ToolCallDelta { arguments: String },
+    /// A response was received in full.
+    Response {
+        content: String,
+        thinking: Option<String>,
+        tool_calls: usize,
+        usage: Option<Usage>,
+        /// Model that served the request, when known
+        model: Option<String>,
+        timing: ResponseTiming,
+        input_bytes: usize,
+        output_bytes: usize,
+    },
+    /// The assistant message carrying tool calls, as added to the conversation.
+    ToolCalls(Message),
+    ToolStart {
+        id: String,
+        name: String,
+        arguments: serde_json::Value,
+    },
+    /// A tool finished; `result` is its text after chunking.
+    ToolComplete {
+        id: String,
+        name: String,
+        result: String,
+        is_error: bool,
+    },
+    /// The batch's tool results, as added to the conversation.
+    ToolResults(Vec<Message>),
+    /// A transient error; the request is being sent again.
+    Retry {
+        attempt: u32,
+        max_retries: u32,
+        error: String,
+    },
+    /// The context was compacted before a request.
+    Compacted { action: String },
+    /// The user's request was restated; `message` is as added to the
+    /// conversation.
+    Regrounded {
+        trigger: RegroundTrigger,
+        message: Message,
+    },
+    /// The provider rejected the context as too long; it was shrunk
+    /// (`action`) and the request is being sent again.
+    ContextRecovered { action: String },
+}
+
+/// How long one request took, from sending it to the end of the reply.
+/// Retried attempts aren't counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResponseTiming {
+    /// Until the first streamed token (thinking, text or tool call); `None`
+    /// when the response wasn't streamed
+    pub first_token: Option<Duration>,
+    pub duration: Duration,
+}
+
+impl ResponseTiming {
+    /// Output tokens per second while generating: after the first token for
+    /// streamed responses, over the whole request otherwise.
+    pub fn tokens_per_sec(&self, completion_tokens: u32) -> Option<f64> {
+        let generating = self.duration - self.first_token.unwrap_or_default();
+        (completion_tokens > 0 && !generating.is_zero())
+            .then(|| completion_tokens as f64 / generating.as_secs_f64())
+    }
+}
+
+/// Receives the events of a run and may steer it between requests.
+#[async_trait]
+pub trait RunHandler: Send {
+    async fn on_event(&mut self, event: RunEvent);
+
+    /// Messages to add to the conversation before the next request, such as
+    /// notes the user typed while the last one ran.
+    async fn pending_messages(&mut self) -> Vec<Message> {
+        Vec::new()
+    }
+
+    /// Called with each request's messages just before it is sent. A
+    /// [`Route`] sends it somewhere other than the runner's provider.
+    async fn route(&mut self, _messages: &[Message]) -> Option<Route> {
+        None
+    }
+}
+
+/// Provider, model and extra parameters for one request, replacing the runner's own.
+pub struct Route {
+    pub provider: Arc<dyn Provider>,
+    pub model: Option<String>,
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Where a run's messages live.
+#[async_trait]
+pub trait Conversation: Send {
+    /// Messages for the next request.
+    fn request_messages(&self) -> Vec<Message>;
+
+    /// Add an assistant turn with tool calls, a tool result or a steering note.
+    fn push(&mut self, message: Message);
+
+    /// Add the final reply.
+    fn finish(&mut self, reply: Message) {
+        self.push(reply);
+    }
+
+    /// Compact before a request if the history has grown past its threshold.
+    /// Returns what was done, if anything.
+    async fn compact(&mut self) -> Option<String> {
+        None
+    }
+
+    /// The observation log compaction has built, quoted in re-grounding
+    /// messages.
+    fn observation_log(&self) -> String {
+        String::new()
+    }
+
+    /// Shrink after the provider rejected the context as too long. Returns
+    /// what was removed, or `None` when nothing could be.
+    async fn shrink(&mut self) -> Option<String>;
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Default)]
+pub struct RunOutcome {
+    /// The final reply; empty when the iteration limit was reached.
+    pub content: String,
+    pub usage: Option<Usage>,
+    pub finish_reason: Option<FinishReason>,
+    /// Requests sent, not counting retries.
+    pub iterations: usize,
+    /// The run stopped at the iteration limit without a final reply.
+    pub max_iterations_reached: bool,
+}
+
+/// Runs the agentic loop for the primary conversation.
+pub struct AgentRunner {
+    provider: Arc<dyn Provider>,
+    tools: ToolRegistry,
+    chunker: Option<ChunkProcessor>,
+    /// Model and sampling settings for every request; messages and tools are filled in
+    request: CompletionRequest,
+    max_iterations: usize,
+    stream: bool,
+    cancel: Option<CancellationToken>,
+    regrounding: Option<Regrounding>,
+}
+
+/// One response, streamed or not.
+#[derive(Default)]
+struct Reply {
+    content: String,
+    thinking: String,
+    tool_calls: Vec<ToolCall>,
+    usage: Option<Usage>,
+    model: Option<String>,
+    finish_reason: Option<FinishReason>,
+    output_bytes: usize,
+    timing: ResponseTiming,
+}
+
+/// Why one attempt at a request failed.
+enum AttemptError {
+    /// Transport trouble; retried when the error is retryable
+    Transport(Error),
+    /// An error reported by the server inside the stream; never retried
+    Protocol(String),
+}
+
+impl AgentRunner {
+    pub fn new(provider: Arc<dyn Provider>, tools: ToolRegistry) -> Self {
+        Self {
+            provider,
+            tools,
+            chunker: None,
+            request: CompletionRequest::new(Vec::new()),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            stream: true,
+            cancel: None,
+            regrounding: None,
+        }
+    }
+
+    /// Chunk and summarize large tool outputs.
+    pub fn with_chunker(mut self, chunker: ChunkProcessor) -> Self {
+        self.chunker = Some(chunker);
+        self
+    }
+
+    /// Model and sampling settings for every request. Its messages and tools
+    /// are replaced.
+    pub fn with_request(mut self, request: CompletionRequest) -> Self {
+        self.request = request;
+        self
+    }
+
+    /// Set the iteration ceiling.
+    pub fn with_max_iterations(mut self, max: usize) -> Self {
+        self.max_iterations = max;
+        self
+    }
+
+    /// Stream responses (the default) or wait for each in full.
+    pub fn with_streaming(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Stop the run with [`Error::Cancelled`] when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Restate the user's request after compaction drops it or a long pause.
+    pub fn with_regrounding(mut self, regrounding: Regrounding) -> Self {
+        self.regrounding = Some(regrounding);
+        self
+    }
+
+    /// Run until the model replies without tool calls. `query` is the user's
+    /// request, which guides the summaries of chunked tool output.
+    pub async fn run(
+        &self,
+        conversation: &mut dyn Conversation,
+        handler: &mut dyn RunHandler,
+        query: &str,
+    ) -> Result<RunOutcome, Error> {
+        let include_tool_reasoning = self.provider.include_tool_reasoning();
+        // Context overflow is recovered from once per run
+        let mut overflow_recovered = false;
+        // Whether the history shrank since the last re-grounding check
+        let mut compacted = false;
+        let mut last_response: Option<Instant> = None;
+
+        for iteration in 0..self.max_iterations {
+            if self.cancel.as_ref().is_some_and(|t| t.is_cancelled()) {
+                return Err(Error::Cancelled);
+            }
+
+            if let Some(action) = conversation.compact().await {
+                compacted = true;
+                handler.on_event(RunEvent::Compacted { action }).await;
+            }
+            for message in handler.pending_messages().await {
+                conversation.push(message);
+            }
+            let gap = last_response.map(|at| at.elapsed());
+            self.reground(conversation, handler, query, compacted, gap).await;
+            compacted = false;
+
+            handler
+                .on_event(RunEvent::IterationStart {
+                    iteration: iteration + 1,
+                })
+                .await;
+
+            let messages = conversation.request_messages();
+            let input_bytes: usize = messages.iter().map(|m| m.byte_count()).sum();
+            tracing::debug!(
+                iteration = iteration,
+                message_count = messages.len(),
+                input_bytes = input_bytes,
+                "LLM call context size"
+            );
+
+            let route = handler.route(&messages).await;
+            let provider = route.as_ref().map_or(&self.provider, |r| &r.provider);
+            let request = self.build_request(messages, route.as_ref());
+            let model = request
+                .model
+                .clone()
+                .or_else(|| provider.default_model().map(str::to_string));
+
+            let reply = match self.send(provider.as_ref(), request, handler).await {
+                Ok(reply) => reply,
+                Err(e) if e.is_context_overflow() && !overflow_recovered => {
+                    overflow_recovered = true;
+                    let Some(action) = conversation.shrink().await else {
+                        return Err(e);
+                    };
+                    tracing::warn!(action = %action, "Context window exceeded, retrying");
+                    handler.on_event(RunEvent::ContextRecovered { action }).await;
+                    compacted = true;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            last_response = Some(Instant::now());
+
+            let Reply {
+                content,
+                thinking,
+                tool_calls,
+                usage,
+                model: served_by,
+                finish_reason,
+                output_bytes,
+                timing,
+            } = reply;
+            let model = served_by.filter(|m| !m.is_empty()).or(model);
+            handler
+                .on_event(RunEvent::Response {
+                    content: content.clone(),
+                    thinking: (!thinking.is_empty()).then(|| thinking.clone()),
+                    tool_calls: tool_calls.len(),
+                    usage: usage.clone(),
+                    model: model.clone(),
+                    timing,
+                    input_bytes,
+                    output_bytes,
+                })
+                .await;
+
+            if tool_calls.is_empty() {
+                conversation.finish(Message::assistant(content.as_str()).with_provenance(
+                    None,
+                    model.as_deref(),
+                    usage.clone(),
+                ));
+                return Ok(RunOutcome {
+                    content,
+                    usage,
+                    finish_reason,
+                    iterations: iteration + 1,
+                    max_iterations_reached: false,
+                });
+            }
+
+            // Reasoning rides along with tool calls when the provider wants it back
+            let reasoning = include_tool_reasoning.then_some(thinking);
+            let assistant = Message::assistant_with_tool_calls(content.as_str(), tool_calls.clone())
+                .with_reasoning(reasoning)
+                .with_provenance(None, model.as_deref(), usage);
+            conversation.push(assistant.clone());
+            handler.on_event(RunEvent::ToolCalls(assistant)).await;
+
+            let results = self.run_tools(tool_calls, handler, query).await;
+            for message in &results {
+                conversation.push(message.clone());
+            }
+            handler.on_event(RunEvent::ToolResults(results)).await;
+        }
+
+        Ok(RunOutcome {
+            iterations: self.max_iterations,
+            max_iterations_reached: true,
+            ..Default::default()
+        })
+    }
+
+    /// Add a re-grounding message if the history was compacted past the
+    /// request or `gap` since the last response is a long pause.
+    async fn reground(
+        &self,
+        conversation: &mut dyn Conversation,
+        handler: &mut dyn RunHandler,
+        query: &str,
+        compacted: bool,
+        gap: Option<Duration>,
+    ) {
+        let Some(ref regrounding) = self.regrounding else {
+            return;
+        };
+        if !compacted && gap.is_none_or(|gap| gap < regrounding.pause_threshold()) {
+            return;
+        }
+        let goal = regrounding.goal().unwrap_or(query);
+        let messages = conversation.request_messages();
+        let Some(trigger) = regrounding.trigger(goal, compacted, gap, &messages) else {
+            return;
+        };
+        tracing::info!(trigger = %trigger, "Re-grounding on the original request");
+        let message = regrounding.message(goal, trigger, &conversation.observation_log());
+        conversation.push(message.clone());
+        handler
+            .on_event(RunEvent::Regrounded { trigger, message })
+            .await;
+    }
+
+    fn build_request(&self, messages: Vec<Message>, route: Option<&Route>) -> CompletionRequest {
+        let mut request = self.request.clone();
+        request.messages = messages;
+        request.tools = self.tools.definitions();
+        request.stream = self.stream;
+        if let Some(route) = route {
+            request.model = route.model.clone();
+            request.extra = route.extra.clone();
+        }
+        request
+    }
+
+    /// Send one request, retrying transient errors with backoff.
+    async fn send(
+        &self,
+        provider: &dyn Provider,
+        request: CompletionRequest,
+        handler: &mut dyn RunHandler,
+    ) -> Result<Reply, Error> {
+        let mut attempt = 0;
+        loop {
+            let result = if self.stream {
+                self.stream_once(provider, request.clone(), handler).await
+            } else {
+                self.complete_once(provider, request.clone(), handler).await
+            };
+            let error = match result {
+                Ok(reply) => return Ok(reply),
+                Err(AttemptError::Protocol(message)) if mentions_context_overflow(&message) => {
+                    return Err(Error::context_overflow(message));
+                }
+                Err(AttemptError::Protocol(message)) => return Err(Error::stream(message)),
+                Err(AttemptError::Transport(e)) if e.is_retryable() && attempt < MAX_STREAM_RETRIES => e,
+                Err(AttemptError::Transport(e)) => return Err(e),
+            };
+            attempt += 1;
+            tracing::warn!(attempt = attempt, error = %error, "Transient error, will retry");
+            handler
+                .on_event(RunEvent::Retry {
+                    attempt,
+                    max_retries: MAX_STREAM_RETRIES,
+                    error: error.to_string(),
+                })
+                .await;
+            self.cancellable(tokio::time::sleep(INITIAL_RETRY_DELAY * 2u32.pow(attempt - 1)))
+                .await?;
+        }
+    }
+
+    async fn complete_once(
+        &self,
+        provider: &dyn Provider,
+        request: CompletionRequest,
+        handler: &mut dyn RunHandler,
+    ) -> Result<Reply, AttemptError> {
+        let started = Instant::now();
+        let response = self
+            .cancellable(provider.complete(request))
+            .await
+            .and_then(|r| r)
+            .map_err(AttemptError::Transport)?;
+
+        let content = response.message.content.to_string_lossy();
+        let thinking = response.thinking.unwrap_or_default();
+        if !thinking.is_empty() {
+            handler.on_event(RunEvent::ThinkingDelta(thinking.clone())).await;
+        }
+        if !content.is_empty() {
+            handler.on_event(RunEvent::ContentDelta(content.clone())).await;
+        }
+        for call in &response.message.tool_calls {
+            handler
+                .on_event(RunEvent::ToolCallStart {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                })
+                .await;
+        }
+        Ok(Reply {
+            output_bytes: content.len() + thinking.len(),
+            content,
+            thinking,
+            tool_calls: response.message.tool_calls,
+            usage: Some(response.usage),
+            model: Some(response.model),
+            finish_reason: Some(response.finish_reason),
+            timing: ResponseTiming {
+                first_token: None,
+                duration: started.elapsed(),
+            },
+        })
+    }
+
+    async fn stream_once(
+        &self,
+        provider: &dyn Provider,
+        request: CompletionRequest,
+        handler: &mut dyn RunHandler,
+    ) -> Result<Reply, AttemptError> {
+        let started = Instant::now();
+        let mut stream = self
+            .cancellable(provider.stream(request))
+            .await
+            .and_then(|r| r)
+            .map_err(AttemptError::Transport)?;
+
+        let mut reply = Reply::default();
+        // (id, name, arguments) of the tool call being streamed
+        let mut current_tool_call: Option<(String, String, String)> = None;
+        loop {
+            let next = self
+                .cancellable(tokio::time::timeout(STREAM_CHUNK_TIMEOUT, stream.next()))
+                .await
+                .map_err(AttemptError::Transport)?;
+            let chunk = match next {
+                Ok(Some(chunk)) => chunk.map_err(AttemptError::Transport)?,
+                Ok(None) => break,
+                Err(_elapsed) => {
+                    return Err(AttemptError::Transport(Error::stream(format!(
+                        "Stream timed out: no data received for {} seconds",
+                        STREAM_CHUNK_TIMEOUT.as_secs()
+                    ))));
+                }
+            };
+            if reply.timing.first_token.is_none()
+                && matches!(
+                    chunk,
+                    StreamChunk::ThinkingDelta { .. }
+                        | StreamChunk::Delta { .. }
+                        | StreamChunk::ToolCallStart { .. }
+                )
+            {
+                reply.timing.first_token = Some(started.elapsed());
+            }
+            match chunk {
+                StreamChunk::Start { model } => {
+                    reply.model = Some(model.clone());
+                    handler.on_event(RunEvent::Start { model }).await;
+                }
+                StreamChunk::ThinkingDelta { content } => {
+                    reply.output_bytes += content.len();
+                    reply.thinking.push_str(&content);
+                    handler.on_event(RunEvent::ThinkingDelta(content)).await;
+                }
+                StreamChunk::Delta { content } => {
+                    reply.output_bytes += content.len();
+                    reply.content.push_str(&content);
+                    handler.on_event(RunEvent::ContentDelta(content)).await;
+                }
+                StreamChunk::ToolCallStart { id, name } => {
+                    reply.finish_tool_call(current_tool_call.take());
+                    current_tool_call = Some((id.clone(), name.clone(), String::new()));
+                    handler.on_event(RunEvent::ToolCallStart { id, name }).await;
+                }
+                StreamChunk::ToolCallDelta { arguments } => {
+                    reply.output_bytes += arguments.len();
+                    if let Some((_, _, ref mut args)) = current_tool_call {
+                        args.push_str(&arguments);
+                    }
+                    handler.on_event(RunEvent::ToolCallDelta { arguments }).await;
+                }
+                StreamChunk::Done {
+                    usage,
+                    finish_reason,
+                } => {
+                    reply.usage = usage;
+                    reply.finish_reason = finish_reason;
+                    break;
+                }
+                StreamChunk::Error { message } => return Err(AttemptError::Protocol(message)),
+            }
+        }
+        reply.finish_tool_call(current_tool_call);
+        reply.timing.duration = started.elapsed();
+        Ok(reply)
+    }
+
+    /// Run a batch of tool calls concurrently, reporting each as it finishes.
+    /// Returns the tool result messages in the order they finished.
+    async fn run_tools(
+        &self,
+        tool_calls: Vec<ToolCall>,
+        handler: &mut dyn RunHandler,
+        query: &str,
+    ) -> Vec<Message> {
+        let calls: HashMap<String, ToolCall> = tool_calls
+            .iter()
+            .map(|call| (call.id.clone(), call.clone()))
+            .collect();
+        for call in &tool_calls {
+            tracing::debug!(tool = %call.name, arguments = %call.arguments, "Executing tool");
+            handler
+                .on_event(RunEvent::ToolStart {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                    arguments: call.arguments.clone(),
+                })
+                .await;
+        }
+
+        // Writes that conflict with an earlier call in the batch are rejected, not raced.
+        // Tools see the run's token, so a cancelled run doesn't wait them out.
+        let conflicts = write_conflicts(&self.tools, &tool_calls);
+        let mut running: FuturesUnordered<_> = tool_calls
+            .into_iter()
+            .zip(conflicts)
+            .map(|(call, conflict)| {
+                let call = execute_tool_call(&self.tools, call, conflict);
+                with_cancellation(self.cancel.clone(), call)
+            })
+            .collect();
+
+        let mut messages = Vec::new();
+        while let Some(mut result) = running.next().await {
+            if let (Some(chunker), Some(call)) = (&self.chunker, calls.get(&result.tool_call_id)) {
+                with_cancellation(
+                    self.cancel.clone(),
+                    chunk_tool_output(chunker, &self.tools, call, &mut result, Some(query)),
+                )
+                .await;
+            }
+            let text = result.text_content();
+            let ToolExecutionResult {
+                tool_call_id,
+                content,
+                is_error,
+            } = result;
+            tracing::debug!(
+                tool_call_id = %tool_call_id,
+                result_len = text.len(),
+                is_error = is_error,
+                "Tool result"
+            );
+            tracing::trace!(tool_call_id = %tool_call_id, content = %text, "Tool result content");
+            handler
+                .on_event(RunEvent::ToolComplete {
+                    name: calls
+                        .get(&tool_call_id)
+                        .map(|call| call.name.clone())
+                        .unwrap_or_default(),
+                    id: tool_call_id.clone(),
+                    result: text,
+                    is_error,
+                })
+                .await;
+            messages.push(
+                Message::tool_result(&tool_call_id, content).with_provenance(None, None, None),
+            );
+        }
+        messages
+    }
+
+    /// Await `future` unless the run is cancelled first.
+    async fn cancellable<T>(&self, future: impl Future<Output = T>) -> Result<T, Error> {
+        match self.cancel {
+            Some(ref token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => Err(Error::Cancelled),
+                output = future => Ok(output),
+            },
+            None => Ok(future.await),
+        }
+    }
+}
+
+impl Reply {
+    fn finish_tool_call(&mut self, call: Option<(String, String, String)>) {
+        if let Some((id, name, arguments)) = call {
+            let arguments = serde_json::from_str(&arguments).unwrap_or(serde_json::Value::Null);
+            self.tool_calls.push(ToolCall::new(id, name, arguments));
+        }
+    }
+}
+
+/// A conversation held in memory, for runs without a session of their own
+/// such as `qq -p`.
+pub struct Transcript {
+    system_prompt: Option<String>,
+    messages: Vec<Message>,
+    memory: Option<(ObservationalMemory, Arc<dyn ContextCompactor>)>,
+}
+
+impl Transcript {
+    pub fn new(system_prompt: Option<String>) -> Self {
+        Self {
+            system_prompt,
+            messages: Vec::new(),
+            memory: None,
+        }
+    }
+
+    /// Fold older messages into an observation log as the history grows.
+    pub fn with_compaction(
+        mut self,
+        config: ObservationConfig,
+        compactor: Arc<dyn ContextCompactor>,
+    ) -> Self {
+        self.memory = Some((ObservationalMemory::new(config), compactor));
+        self
+    }
+
+    /// Pick up a saved conversation: `messages` and the observation log
+    /// older messages were folded into. The log is dropped without
+    /// compaction, so call this after [`with_compaction`](Self::with_compaction).
+    pub fn with_history(mut self, messages: Vec<Message>, observation_log: String) -> Self {
+        self.messages = messages;
+        if let Some((ref mut memory, _)) = self.memory {
+            *memory = ObservationalMemory::with_observation_log(memory.config().clone(), observation_log);
+        }
+        self
+    }
+
+    /// Messages not yet folded into the observation log.
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    fn total_bytes(&self) -> usize {
+        let log_bytes = self.memory.as_ref().map_or(0, |(memory, _)| memory.log_bytes());
+        self.messages.iter().map(|m| m.byte_count()).sum::<usize>() + log_bytes
+    }
+}
+
+#[async_trait]
+impl Conversation for Transcript {
+    fn request_messages(&self) -> Vec<Message> {
+        let log = self
+            .memory
+            .as_ref()
+            .map(|(memory, _)| memory.observation_log())
+            .unwrap_or_default();
+        let mut system = self.system_prompt.clone().unwrap_or_default();
+        if !log.is_empty() {
+            if !system.is_empty() {
+                system.push_str("\n\n");
+            }
+            system.push_str(&format!(
+                "## Observation Log\n\n\
+                 The following is a structured log of observations from earlier in this \
+                 conversation. Each entry captures a specific event, decision, or finding.\n\n\
+                 {}",
+                log
+            ));
+        }
+
+        let mut messages = Vec::with_capacity(self.messages.len() + 1);
+        if !system.is_empty() {
+            messages.push(Message::system(system.as_str()));
+        }
+        messages.extend(self.messages.iter().cloned());
+        messages
+    }
+
+    fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    fn finish(&mut self, reply: Message) {
+        strip_reasoning_from_history(&mut self.messages);
+        self.messages.push(reply);
+    }
+
+    fn observation_log(&self) -> String {
+        self.memory
+            .as_ref()
+            .map(|(memory, _)| memory.observation_log().to_string())
+            .unwrap_or_default()
+    }
+
+    async fn compact(&mut self) -> Option<String> {
+        let bytes_before = self.total_bytes();
+        let (memory, compactor) = self.memory.as_mut()?;
+        let observations = memory.observation_count();
+        if let Err(e) = memory.compact(&mut self.messages, compactor.as_ref()).await {
+            tracing::error!(error = %e, "Observation memory compaction failed");
+        }
+        if memory.observation_count() == observations {
+            return None;
+        }
+        Some(format!(
+            "compacted {} bytes into the observation log",
+            bytes_before.saturating_sub(self.total_bytes())
+        ))
+    }
+
+    async fn shrink(&mut self) -> Option<String> {
+        let bytes_before = self.total_bytes();
+        if let Some((memory, compactor)) = self.memory.as_mut() {
+            match memory.compact_force(&mut self.messages, compactor.as_ref()).await {
+                Ok(()) if self.total_bytes() < bytes_before => {
+                    return Some(format!(
+                        "compacted {} bytes into the observation log",
+                        bytes_before - self.total_bytes()
+                    ));
+                }
+                Ok(()) => {}
+                Err(e) => {
+                    tracing::warn!(error = %e, "Emergency compaction failed, evicting instead");
+                }
+            }
+        }
+        let eviction = evict_for_context_overflow(&mut self.messages);
+        (!eviction.is_empty()).then(|| eviction.describe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::CompletionResponse;
+    use crate::testing::MockProvider;
+    use crate::tool::{Tool, ToolDefinition, ToolOutput};
+
+    struct Echo;
+
+    #[async_trait]
+    impl Tool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            "test"
+        }
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new("echo", "test")
+        }
+        async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+            Ok(ToolOutput::success(arguments["text"].as_str().unwrap_or_default()))
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    #[async_trait]
+    impl RunHandler for Recorder {
+        async fn on_event(&mut self, event: RunEvent) {
+            let line = match event {
+                RunEvent::IterationStart { iteration } => format!("iteration {}", iteration),
+                RunEvent::ContentDelta(content) => format!("content {}", content),
+                RunEvent::ToolStart { name, .. } => format!("start {}", name),
+                RunEvent::ToolComplete { name, result, .. } => format!("done {}: {}", name, result),
+                RunEvent::Retry { attempt, .. } => format!("retry {}", attempt),
+                RunEvent::ContextRecovered { .. } => "recovered".to_string(),
+                RunEvent::Regrounded { trigger, .. } => format!("regrounded after {}", trigger),
+                _ => return,
+            };
+            self.0.push(line);
+        }
+    }
+
+    fn echo_call(text: &str) -> CompletionResponse {
+        let call = ToolCall::new("call_1", "echo", serde_json::json!({ "text": text }));
+        CompletionResponse {
+            message: Message::assistant_with_tool_calls("", vec![call]),
+            thinking: None,
+            usage: Usage::new(10, 5),
+            model: "mock-model".to_string(),
+            finish_reason: FinishReason::ToolCalls,
+        }
+    }
+
+    fn runner(provider: &Arc<MockProvider>) -> AgentRunner {
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(Echo));
+        let provider: Arc<dyn Provider> = provider.clone();
+        AgentRunner::new(provider, tools).with_streaming(false)
+    }
+
+    #[tokio::test]
+    async fn test_runs_tools_until_final_reply() {
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(echo_call("pong"));
+        provider.queue_response("It said pong.");
+
+        let mut transcript = Transcript::new(Some("Be brief.".to_string()));
+        transcript.push(Message::user("ping"));
+        let mut events = Recorder::default();
+        let outcome = runner(&provider)
+            .run(&mut transcript, &mut events, "ping")
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.content, "It said pong.");
+        assert_eq!(outcome.iterations, 2);
+        assert_eq!(
+            events.0,
+            [
+                "iteration 1",
+                "start echo",
+                "done echo: pong",
+                "iteration 2",
+                "content It said pong."
+            ]
+        );
+        // user, assistant with the call, tool result, reply
+        assert_eq!(transcript.messages().len(), 4);
+        let request = provider.last_request().unwrap();
+        assert_eq!(request.messages[0].content.to_string_lossy(), "Be brief.");
+        assert_eq!(request.messages[3].content.to_string_lossy(), "pong");
+        assert_eq!(request.tools.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_and_recovers_from_overflow() {
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_error(Error::network("connection reset"));
+        provider.queue_response("first");
+
+        let mut transcript = Transcript::new(None);
+        transcript.push(Message::user("hi"));
+        let mut events = Recorder::default();
+        let outcome = runner(&provider)
+            .run(&mut transcript, &mut events, "hi")
+            .await
+            .unwrap();
+        assert_eq!(outcome.content, "first");
+        assert_eq!(events.0, ["iteration 1", "retry 1", "content first"]);
+
+        // An overflow evicts old turns and sends the request again, once
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_error(Error::context_overflow("too long"));
+        provider.queue_response("second");
+        for i in 0..6 {
+            transcript.push(Message::user(format!("question {}", i).as_str()));
+            transcript.push(Message::assistant("x".repeat(1000).as_str()));
+        }
+        transcript.push(Message::user("and now?"));
+        let mut events = Recorder::default();
+        let outcome = runner(&provider)
+            .run(&mut transcript, &mut events, "and now?")
+            .await
+            .unwrap();
+        assert_eq!(outcome.content, "second");
+        assert_eq!(events.0, ["iteration 1", "recovered", "iteration 2", "content second"]);
+
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_error(Error::context_overflow("too long"));
+        provider.queue_error(Error::context_overflow("still too long"));
+        let result = runner(&provider)
+            .run(&mut transcript, &mut Recorder::default(), "and now?")
+            .await;
+        assert!(result.unwrap_err().is_context_overflow());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_stops_before_sending() {
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_response("never sent");
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let mut transcript = Transcript::new(None);
+        transcript.push(Message::user("hi"));
+        let result = runner(&provider)
+            .with_cancellation(token)
+            .run(&mut transcript, &mut Recorder::default(), "hi")
+            .await;
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(provider.request_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reports_response_timing() {
+        #[derive(Default)]
+        struct Timings(Vec<(Option<String>, ResponseTiming)>);
+
+        #[async_trait]
+        impl RunHandler for Timings {
+            async fn on_event(&mut self, event: RunEvent) {
+                if let RunEvent::Response { model, timing, .. } = event {
+                    self.0.push((model, timing));
+                }
+            }
+        }
+
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_stream(vec![
+            StreamChunk::Start {
+                model: "local-7b".to_string(),
+            },
+            StreamChunk::Delta {
+                content: "hi".to_string(),
+            },
+            StreamChunk::Done {
+                usage: Some(Usage::new(5, 2)),
+                finish_reason: Some(FinishReason::Stop),
+            },
+        ]);
+        let mut transcript = Transcript::new(None);
+        transcript.push(Message::user("hi"));
+        let mut timings = Timings::default();
+        runner(&provider)
+            .with_streaming(true)
+            .run(&mut transcript, &mut timings, "hi")
+            .await
+            .unwrap();
+
+        let (model, timing) = &timings.0[0];
+        assert_eq!(model.as_deref(), Some("local-7b"));
+        let first_token = timing.first_token.unwrap();
+        assert!(first_token <= timing.duration);
+
+        let timing = ResponseTiming {
+            first_token: Some(Duration::from_millis(500)),
+            duration: Duration::from_millis(2500),
+        };
+        assert_eq!(timing.tokens_per_sec(100), Some(50.0));
+        assert_eq!(timing.tokens_per_sec(0), None);
+        let unstreamed = ResponseTiming {
+            first_token: None,
+            ..timing
+        };
+        assert_eq!(unstreamed.tokens_per_sec(100), Some(40.0));
+    }
+
+    #[test]
+    fn test_transcript_with_history_keeps_observation_log() {
+        let compactor = Arc::new(crate::testing::MockCompactor::new());
+        let transcript = Transcript::new(Some("Be brief.".to_string()))
+            .with_compaction(ObservationConfig::default(), compactor)
+            .with_history(
+                vec![Message::user("hi"), Message::assistant("hello")],
+                "- asked about the config".to_string(),
+            );
+        let messages = transcript.request_messages();
+        assert_eq!(messages.len(), 3);
+        let system = messages[0].content.to_string_lossy();
+        assert!(system.starts_with("Be brief.\n\n## Observation Log"));
+        assert!(system.ends_with("- asked about the config"));
+        assert_eq!(transcript.observation_log(), "- asked about the config");
+    }
+
+    #[tokio::test]
+    async fn test_regrounds_after_compaction_and_long_pauses() {
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(echo_call("pong"));
+        provider.queue_response("done");
+        let compactor = Arc::new(crate::testing::MockCompactor::new());
+        compactor.queue_observe(Ok("- read the config file".to_string()));
+        let config = ObservationConfig {
+            message_threshold_bytes: 100,
+            observation_threshold_bytes: 1_000_000,
+            preserve_recent: 1,
+            hysteresis: 1.0,
+            context_budget_bytes: None,
+        };
+
+        // The goal is compacted away before the first request
+        let goal = "Summarize the config";
+        let mut transcript = Transcript::new(None).with_compaction(config, compactor);
+        transcript.push(Message::user(goal));
+        transcript.push(Message::assistant("x".repeat(500).as_str()));
+        transcript.push(Message::user("go on"));
+        let mut events = Recorder::default();
+        runner(&provider)
+            .with_regrounding(Regrounding::new())
+            .run(&mut transcript, &mut events, goal)
+            .await
+            .unwrap();
+        assert_eq!(events.0[0], "regrounded after compaction");
+        let message = transcript.messages()[1].content.to_string_lossy();
+        assert!(message.contains("<original-request>\nSummarize the config\n</original-request>"));
+        assert!(message.contains("- read the config file"));
+        // The restated goal stays visible, so the second request needs none
+        assert_eq!(events.0.iter().filter(|e| e.starts_with("regrounded")).count(), 1);
+
+        // Any gap counts as long with a zero threshold, but not before the first response
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_raw_response(echo_call("pong"));
+        provider.queue_response("done");
+        let mut transcript = Transcript::new(None);
+        transcript.push(Message::user("ping"));
+        let mut events = Recorder::default();
+        runner(&provider)
+            .with_regrounding(Regrounding::new().with_pause_threshold(Duration::ZERO))
+            .run(&mut transcript, &mut events, "ping")
+            .await
+            .unwrap();
+        assert_eq!(
+            events.0,
+            [
+                "iteration 1",
+                "start echo",
+                "done echo: pong",
+                "regrounded after a 0s pause",
+                "iteration 2",
+                "content done"
+            ]
+        );
+    }
+}
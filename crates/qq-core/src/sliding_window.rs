@@ -0,0 +1,385 @@
+//! Sliding-window memory: an alternative to observation compaction for
+//! providers with a small context.
+//!
+//! The most recent messages stay in the request verbatim. Older exchanges (a
+//! user message and everything up to the next one) leave the window whole and
+//! are archived with an embedding. Before each turn, the archived exchanges
+//! most similar to the user's latest message are recalled into the request.
+//! Nothing is summarized, so memory costs no completion calls and recalled
+//! turns keep their original wording.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::chunker::cosine_similarity;
+use crate::message::{drain_unpinned, Message, Role};
+use crate::provider::Provider;
+use crate::text::truncate_bytes;
+
+/// Longest text kept (and embedded) for one archived exchange.
+const MAX_EXCHANGE_BYTES: usize = 6_000;
+
+/// Longest text kept for one message of an archived exchange.
+const MAX_MESSAGE_BYTES: usize = 1_500;
+
+/// Inputs per embedding request.
+const EMBED_BATCH_SIZE: usize = 32;
+
+/// Thresholds for [`SlidingWindowMemory`].
+#[derive(Debug, Clone)]
+pub struct SlidingWindowConfig {
+    /// Messages kept verbatim. The window never splits an exchange, so one
+    /// long exchange can keep more.
+    pub recent_messages: usize,
+    /// Archived exchanges recalled into each request.
+    pub recall: usize,
+    /// Exchanges less similar to the user's message than this are not recalled.
+    pub min_similarity: f32,
+    /// Embedding model for archived exchanges. `None` archives without
+    /// recall, a plain sliding window.
+    pub embedding_model: Option<String>,
+}
+
+impl Default for SlidingWindowConfig {
+    fn default() -> Self {
+        Self {
+            recent_messages: 16,
+            recall: 4,
+            min_similarity: 0.3,
+            embedding_model: None,
+        }
+    }
+}
+
+/// An exchange that left the window.
+struct ArchivedExchange {
+    text: String,
+    /// `None` when there is no embedding model or the embedding failed;
+    /// such exchanges are never recalled.
+    embedding: Option<Vec<f32>>,
+}
+
+/// Recent messages verbatim plus recalled older ones (see the module docs).
+pub struct SlidingWindowMemory {
+    config: SlidingWindowConfig,
+    embedder: Arc<dyn Provider>,
+    archive: Vec<ArchivedExchange>,
+    /// Indices into `archive` of the recalled exchanges, oldest first
+    recalled: Vec<usize>,
+    /// The query `recalled` was chosen for, its embedding, and the archive
+    /// size at the time
+    recalled_for: Option<(String, Vec<f32>, usize)>,
+}
+
+impl fmt::Debug for SlidingWindowMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlidingWindowMemory")
+            .field("config", &self.config)
+            .field("embedder", &self.embedder.name())
+            .field("archived", &self.archive.len())
+            .field("recalled", &self.recalled)
+            .finish()
+    }
+}
+
+impl SlidingWindowMemory {
+    /// `embedder` serves `config.embedding_model`.
+    pub fn new(config: SlidingWindowConfig, embedder: Arc<dyn Provider>) -> Self {
+        Self {
+            config,
+            embedder,
+            archive: Vec::new(),
+            recalled: Vec::new(),
+            recalled_for: None,
+        }
+    }
+
+    pub fn config(&self) -> &SlidingWindowConfig {
+        &self.config
+    }
+
+    /// Exchanges moved out of the window so far.
+    pub fn archived_count(&self) -> usize {
+        self.archive.len()
+    }
+
+    /// Exchanges recalled into the next request.
+    pub fn recalled_count(&self) -> usize {
+        self.recalled.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.archive.clear();
+        self.recalled.clear();
+        self.recalled_for = None;
+    }
+
+    /// Move whole exchanges older than the last `keep` messages out of
+    /// `messages` and archive them. Pinned messages are kept. Returns the
+    /// moved messages, oldest first.
+    pub async fn slide(&mut self, messages: &mut Vec<Message>, keep: usize) -> Vec<Message> {
+        if messages.len() <= keep {
+            return Vec::new();
+        }
+        // Cutting before a user message never separates a tool call from
+        // its results or a request from its answer
+        let desired = messages.len() - keep;
+        let Some(cut) = (1..=desired).rev().find(|&i| starts_exchange(&messages[i])) else {
+            return Vec::new();
+        };
+        // Pinned messages stay in the window verbatim
+        let moved = drain_unpinned(messages, 0..cut);
+        if moved.is_empty() {
+            return moved;
+        }
+
+        let texts: Vec<String> = exchanges(&moved).map(render_exchange).collect();
+        let embeddings = match self.config.embedding_model.as_deref() {
+            Some(model) => self.embed(model, &texts).await,
+            None => None,
+        };
+        let mut embeddings = embeddings.map(Vec::into_iter);
+        self.archive.extend(texts.into_iter().map(|text| ArchivedExchange {
+            text,
+            embedding: embeddings.as_mut().and_then(Iterator::next),
+        }));
+        tracing::debug!(
+            moved = moved.len(),
+            archived = self.archive.len(),
+            "Moved messages out of the sliding window"
+        );
+        moved
+    }
+
+    /// Choose the archived exchanges to recall for `query`, the user's latest
+    /// message. Keeps the previous choice when neither changed.
+    pub async fn recall(&mut self, query: &str) {
+        let Some(model) = self.config.embedding_model.clone() else {
+            return;
+        };
+        let query = query.trim();
+        if query.is_empty() || self.archive.is_empty() || self.config.recall == 0 {
+            self.recalled.clear();
+            return;
+        }
+        let query_vector = match self.recalled_for {
+            Some((ref q, _, size)) if q == query && size == self.archive.len() => return,
+            Some((ref q, ref vector, _)) if q == query => vector.clone(),
+            _ => {
+                let input = truncate_bytes(query, MAX_EXCHANGE_BYTES).to_string();
+                match self.embed(&model, &[input]).await.and_then(|v| v.into_iter().next()) {
+                    Some(vector) => vector,
+                    None => {
+                        self.recalled.clear();
+                        self.recalled_for = None;
+                        return;
+                    }
+                }
+            }
+        };
+
+        let mut scored: Vec<(usize, f32)> = self
+            .archive
+            .iter()
+            .enumerate()
+            .filter_map(|(i, exchange)| {
+                let score = cosine_similarity(&query_vector, exchange.embedding.as_ref()?);
+                (score >= self.config.min_similarity).then_some((i, score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        self.recalled = scored
+            .into_iter()
+            .take(self.config.recall)
+            .map(|(i, _)| i)
+            .collect();
+        self.recalled.sort_unstable();
+        tracing::debug!(
+            recalled = self.recalled.len(),
+            archived = self.archive.len(),
+            "Recalled earlier exchanges"
+        );
+        self.recalled_for = Some((query.to_string(), query_vector, self.archive.len()));
+    }
+
+    /// The recalled exchanges, for the system message.
+    pub fn recalled_section(&self) -> Option<String> {
+        if self.recalled.is_empty() {
+            return None;
+        }
+        let exchanges = self
+            .recalled
+            .iter()
+            .map(|&i| self.archive[i].text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n---\n\n");
+        Some(format!(
+            "## Relevant Earlier Conversation\n\n\
+             These earlier exchanges are no longer in the conversation below but \
+             relate to the latest request. Tool output in them may be cut short.\n\n{}",
+            exchanges
+        ))
+    }
+
+    /// One vector per text, or `None` when the embedding call fails.
+    async fn embed(&self, model: &str, texts: &[String]) -> Option<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(EMBED_BATCH_SIZE) {
+            match self.embedder.embed(model, batch).await {
+                Ok(batch_vectors) if batch_vectors.len() == batch.len() => {
+                    vectors.extend(batch_vectors)
+                }
+                Ok(_) => {
+                    tracing::warn!(model, "Embedding count mismatch, exchanges can't be recalled");
+                    return None;
+                }
+                Err(e) => {
+                    tracing::warn!(model, error = %e, "Embedding failed, exchanges can't be recalled");
+                    return None;
+                }
+            }
+        }
+        Some(vectors)
+    }
+}
+
+/// Whether `message` is the user's request rather than a tool result.
+fn starts_exchange(message: &Message) -> bool {
+    message.role == Role::User && message.tool_call_id.is_none()
+}
+
+/// `messages` split before each user message.
+fn exchanges(messages: &[Message]) -> impl Iterator<Item = &[Message]> {
+    let mut rest = messages;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .iter()
+            .skip(1)
+            .position(starts_exchange)
+            .map_or(rest.len(), |i| i + 1);
+        let (exchange, tail) = rest.split_at(end);
+        rest = tail;
+        Some(exchange)
+    })
+}
+
+/// An exchange as text, each message cut to [`MAX_MESSAGE_BYTES`].
+fn render_exchange(messages: &[Message]) -> String {
+    let mut text = String::new();
+    for message in messages {
+        let content = message.content.to_string_lossy();
+        let content = content.trim();
+        let label = match message.role {
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+            Role::Tool => "Tool result",
+            Role::System => "System",
+        };
+        if !content.is_empty() {
+            text.push_str(&format!("**{}:** {}\n", label, cut(content)));
+        }
+        for call in &message.tool_calls {
+            let call = format!("{}({})", call.name, call.arguments);
+            text.push_str(&format!("**Tool call:** {}\n", cut(&call)));
+        }
+    }
+    truncate_bytes(text.trim_end(), MAX_EXCHANGE_BYTES).to_string()
+}
+
+fn cut(text: &str) -> String {
+    match truncate_bytes(text, MAX_MESSAGE_BYTES) {
+        kept if kept.len() < text.len() => format!("{} [...]", kept),
+        kept => kept.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ToolCall;
+    use crate::testing::MockProvider;
+
+    fn memory(recent_messages: usize) -> SlidingWindowMemory {
+        let config = SlidingWindowConfig {
+            recent_messages,
+            recall: 1,
+            min_similarity: 0.3,
+            embedding_model: Some("mock-embed".to_string()),
+        };
+        SlidingWindowMemory::new(config, Arc::new(MockProvider::new()))
+    }
+
+    fn exchange(question: &str, answer: &str) -> Vec<Message> {
+        vec![Message::user(question), Message::assistant(answer)]
+    }
+
+    #[tokio::test]
+    async fn test_slide_moves_whole_exchanges() {
+        let mut window = memory(3);
+        let mut messages = exchange("first question", "first answer");
+        messages.push(Message::user("read the manifest"));
+        messages.push(Message::assistant_with_tool_calls(
+            "",
+            vec![ToolCall::new("c1", "read_file", serde_json::json!({"path": "Cargo.toml"}))],
+        ));
+        messages.push(Message::tool_result("c1", "[package]"));
+        messages.push(Message::assistant("It is a workspace."));
+
+        // Keeping exactly 3 would cut inside the tool exchange, so it stays
+        // whole and only the first exchange moves
+        let moved = window.slide(&mut messages, 3).await;
+        assert_eq!(moved.len(), 2);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(window.archived_count(), 1);
+
+        // A single exchange is never split, even past the window
+        assert!(window.slide(&mut messages, 1).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recall_picks_the_relevant_exchange() {
+        let mut window = memory(2);
+        let mut messages = Vec::new();
+        messages.extend(exchange("how do I configure the database pool", "set pool size in db.toml"));
+        messages.extend(exchange("what is the weather like", "sunny"));
+        messages.extend(exchange("thanks", "you're welcome"));
+        window.slide(&mut messages, 2).await;
+        assert_eq!(window.archived_count(), 2);
+
+        window.recall("increase the database pool size").await;
+        let section = window.recalled_section().unwrap();
+        assert!(section.contains("db.toml"), "{}", section);
+        assert!(!section.contains("sunny"));
+
+        window.recall("zzz qqq").await;
+        assert_eq!(window.recalled_count(), 0);
+        assert!(window.recalled_section().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_without_embedding_model_nothing_is_recalled() {
+        let mut window = memory(2);
+        window.config.embedding_model = None;
+        let mut messages = exchange("database pool", "db.toml");
+        messages.extend(exchange("next", "ok"));
+        window.slide(&mut messages, 2).await;
+        window.recall("database pool").await;
+        assert_eq!(window.archived_count(), 1);
+        assert!(window.recalled_section().is_none());
+    }
+
+    #[test]
+    fn test_render_exchange_cuts_long_output() {
+        let messages = vec![
+            Message::user("show the log"),
+            Message::tool_result("c1", "x".repeat(MAX_MESSAGE_BYTES * 2)),
+        ];
+        let text = render_exchange(&messages);
+        assert!(text.starts_with("**User:** show the log\n**Tool result:** xxx"));
+        assert!(text.ends_with("[...]"));
+        assert!(text.len() < MAX_MESSAGE_BYTES + 100);
+    }
+}
@@ -0,0 +1,470 @@
+//! Deterministic state delta from an agent's tool journal.
+//!
+//! LLM summaries written at continuation time often drop the file paths and
+//! task ids the next run needs, forcing it to rediscover them. A
+//! [`StateDelta`] is built directly from the tool calls and results in the
+//! conversation, so that information survives exactly.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::message::{Message, Role};
+
+/// Most entries listed per section when rendering.
+const MAX_RENDERED_FILES: usize = 40;
+const MAX_RENDERED_TASKS: usize = 20;
+const MAX_RENDERED_COMMANDS: usize = 10;
+const MAX_RENDERED_ERRORS: usize = 5;
+const MAX_RENDERED_COMMITS: usize = 20;
+const MAX_LINE_CHARS: usize = 160;
+
+/// Tools whose path arguments are writes.
+const WRITE_TOOLS: &[&str] = &[
+    "write_file",
+    "edit_file",
+    "replace_in_file",
+    "insert_in_file",
+    "delete_lines",
+    "replace_lines",
+    "move_file",
+    "copy_file",
+    "create_directory",
+    "rm_file",
+    "rm_directory",
+];
+
+/// Tools whose path arguments are reads.
+const READ_TOOLS: &[&str] = &[
+    "read_file",
+    "read_files",
+    "read_image",
+    "read_document",
+    "list_files",
+];
+
+/// Argument keys that hold a path.
+const PATH_KEYS: &[&str] = &["path", "file", "file_path", "source", "destination", "dest"];
+
+/// Shell programs whose path arguments are writes.
+const WRITE_COMMANDS: &[&str] = &["touch", "mkdir", "rm", "rmdir", "tee", "cp", "mv", "truncate"];
+
+/// Shell programs whose path arguments are reads.
+const READ_COMMANDS: &[&str] = &["cat", "head", "tail", "wc", "nl", "less", "sed"];
+
+/// A shell command from the journal and whether it succeeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandRecord {
+    pub command: String,
+    pub ok: bool,
+}
+
+/// What an agent's tool calls touched: files, tasks, commands, and errors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDelta {
+    pub files_modified: BTreeSet<String>,
+    pub files_read: BTreeSet<String>,
+    /// Task id → latest status (or note) set through the task tools.
+    pub tasks: BTreeMap<String, String>,
+    /// `run` commands in call order.
+    pub commands: Vec<CommandRecord>,
+    /// `(tool, first line of the error)` for failed calls, in call order.
+    pub errors: Vec<(String, String)>,
+    /// Commits made with `git_commit` (`<hash> on <branch>: <subject>`), in
+    /// call order.
+    pub commits: Vec<String>,
+}
+
+impl StateDelta {
+    /// Build a delta from a conversation.
+    pub fn from_messages(messages: &[Message]) -> Self {
+        let mut delta = Self::default();
+        delta.absorb(messages);
+        delta
+    }
+
+    /// Fold another stretch of conversation into this delta.
+    pub fn absorb(&mut self, messages: &[Message]) {
+        let mut results: HashMap<&str, String> = HashMap::new();
+        for msg in messages {
+            if msg.role == Role::Tool {
+                if let Some(ref id) = msg.tool_call_id {
+                    results.insert(id.as_str(), msg.content.to_string_lossy());
+                }
+            }
+        }
+
+        for call in messages.iter().flat_map(|m| &m.tool_calls) {
+            let result = results.get(call.id.as_str());
+            let ok = result.is_none_or(|r| !r.starts_with("Error"));
+            let args = &call.arguments;
+
+            if let Some(error) = result.filter(|_| !ok) {
+                let first = error.lines().next().unwrap_or_default();
+                self.errors.push((call.name.clone(), clip(first)));
+            }
+
+            match call.name.as_str() {
+                "run" | "bash" => {
+                    if let Some(command) = args.get("command").and_then(|c| c.as_str()) {
+                        if ok {
+                            self.absorb_command(command);
+                        }
+                        self.commands.push(CommandRecord {
+                            command: clip(command),
+                            ok,
+                        });
+                    }
+                }
+                "git_commit" if ok => {
+                    let first = result.and_then(|r| r.lines().next()).unwrap_or_default();
+                    if let Some(commit) = first.strip_prefix("Committed ") {
+                        self.commits.push(clip(commit));
+                    }
+                }
+                "read_files" => {
+                    let files = args.get("files").and_then(|f| f.as_array());
+                    for file in files.into_iter().flatten() {
+                        if let Some(path) = file.get("path").and_then(|p| p.as_str()) {
+                            self.files_read.insert(path.to_string());
+                        }
+                    }
+                }
+                "create_task" if ok => {
+                    // The new id is only in the result; the title identifies it
+                    if let Some(title) = args.get("title").and_then(|t| t.as_str()) {
+                        let status = args.get("status").and_then(|s| s.as_str()).unwrap_or("todo");
+                        self.tasks.insert(format!("new \"{}\"", clip(title)), status.to_string());
+                    }
+                }
+                "update_task" | "update_my_task" if ok => {
+                    let id = args
+                        .get("task_id")
+                        .or_else(|| args.get("id"))
+                        .and_then(|i| i.as_str())
+                        .unwrap_or("(own task)");
+                    let state = args
+                        .get("status")
+                        .and_then(|s| s.as_str())
+                        .map(str::to_string)
+                        .or_else(|| {
+                            args.get("add_note")
+                                .or_else(|| args.get("note"))
+                                .and_then(|n| n.as_str())
+                                .map(|n| format!("note: {}", clip(n)))
+                        });
+                    if let Some(state) = state {
+                        self.tasks.insert(id.to_string(), state);
+                    }
+                }
+                name if ok && (WRITE_TOOLS.contains(&name) || READ_TOOLS.contains(&name)) => {
+                    let target = if WRITE_TOOLS.contains(&name) {
+                        &mut self.files_modified
+                    } else {
+                        &mut self.files_read
+                    };
+                    for key in PATH_KEYS {
+                        if let Some(path) = args.get(*key).and_then(|p| p.as_str()) {
+                            target.insert(path.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let modified = &self.files_modified;
+        self.files_read.retain(|p| !modified.contains(p));
+    }
+
+    /// Record paths a successful shell command read or wrote.
+    fn absorb_command(&mut self, command: &str) {
+        for segment in split_segments(command) {
+            let mut words = segment.iter().map(String::as_str);
+            let mut program = None;
+            let mut operands = Vec::new();
+            let mut in_place = false;
+            while let Some(word) = words.next() {
+                if let Some(target) = word.strip_prefix(">>").or_else(|| word.strip_prefix('>')) {
+                    let target = if target.is_empty() {
+                        words.next().unwrap_or_default()
+                    } else {
+                        target
+                    };
+                    if looks_like_path(target) {
+                        self.files_modified.insert(target.to_string());
+                    }
+                    continue;
+                }
+                if program.is_none() {
+                    // Skip leading VAR=value assignments
+                    if !word.contains('=') {
+                        program = Some(word.rsplit('/').next().unwrap_or(word));
+                    }
+                    continue;
+                }
+                if word == "-i" || word.starts_with("-i.") || word == "--in-place" {
+                    in_place = true;
+                }
+                if !word.starts_with('-') && looks_like_path(word) {
+                    operands.push(word);
+                }
+            }
+
+            let Some(program) = program else { continue };
+            match program {
+                "sed" if in_place => {
+                    if let Some(last) = operands.last() {
+                        self.files_modified.insert(last.to_string());
+                    }
+                }
+                "cp" | "mv" => {
+                    if let Some(last) = operands.last() {
+                        self.files_modified.insert(last.to_string());
+                    }
+                    if program == "mv" {
+                        for source in &operands[..operands.len().saturating_sub(1)] {
+                            self.files_modified.insert(source.to_string());
+                        }
+                    }
+                }
+                p if WRITE_COMMANDS.contains(&p) => {
+                    self.files_modified.extend(operands.iter().map(|o| o.to_string()));
+                }
+                p if READ_COMMANDS.contains(&p) => {
+                    self.files_read.extend(operands.iter().map(|o| o.to_string()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files_modified.is_empty()
+            && self.files_read.is_empty()
+            && self.tasks.is_empty()
+            && self.commands.is_empty()
+            && self.errors.is_empty()
+            && self.commits.is_empty()
+    }
+
+    /// Render as a markdown section, or `None` if nothing was recorded.
+    pub fn render(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut out = String::from("### State Delta (from tool journal)\n");
+        render_list(&mut out, "Files modified", self.files_modified.iter(), MAX_RENDERED_FILES);
+        render_list(&mut out, "Files read", self.files_read.iter(), MAX_RENDERED_FILES);
+        render_list(
+            &mut out,
+            "Tasks updated",
+            self.tasks.iter().map(|(id, state)| format!("{}: {}", id, state)),
+            MAX_RENDERED_TASKS,
+        );
+        let skipped = self.commits.len().saturating_sub(MAX_RENDERED_COMMITS);
+        render_list(
+            &mut out,
+            "Commits",
+            self.commits.iter().skip(skipped),
+            MAX_RENDERED_COMMITS,
+        );
+
+        let skipped = self.commands.len().saturating_sub(MAX_RENDERED_COMMANDS);
+        render_list(
+            &mut out,
+            "Recent commands",
+            self.commands.iter().skip(skipped).map(|c| {
+                format!("[{}] {}", if c.ok { "ok" } else { "error" }, c.command)
+            }),
+            MAX_RENDERED_COMMANDS,
+        );
+
+        let skipped = self.errors.len().saturating_sub(MAX_RENDERED_ERRORS);
+        render_list(
+            &mut out,
+            "Recent errors",
+            self.errors
+                .iter()
+                .skip(skipped)
+                .map(|(tool, error)| format!("{}: {}", tool, error)),
+            MAX_RENDERED_ERRORS,
+        );
+        Some(out)
+    }
+}
+
+fn render_list<I, S>(out: &mut String, title: &str, items: I, max: usize)
+where
+    I: ExactSizeIterator<Item = S>,
+    S: std::fmt::Display,
+{
+    let total = items.len();
+    if total == 0 {
+        return;
+    }
+    out.push_str(&format!("\n{}:\n", title));
+    for item in items.take(max) {
+        out.push_str(&format!("- {}\n", item));
+    }
+    if total > max {
+        out.push_str(&format!("- ... and {} more\n", total - max));
+    }
+}
+
+/// Split a command line into simple commands on `|`, `;`, `&&`, `||`, and
+/// newlines, tokenizing each on whitespace with quotes stripped.
+fn split_segments(command: &str) -> Vec<Vec<String>> {
+    let mut segments = vec![Vec::new()];
+    let mut word = String::new();
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+
+    let flush = |word: &mut String, segments: &mut Vec<Vec<String>>| {
+        if !word.is_empty() {
+            segments.last_mut().unwrap().push(std::mem::take(word));
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '|' | ';' | '\n' | '&') => {
+                if c == '&' && chars.peek() != Some(&'&') {
+                    // `2>&1` and background `&` are not separators
+                    word.push(c);
+                    continue;
+                }
+                chars.next_if(|n| *n == c);
+                flush(&mut word, &mut segments);
+                segments.push(Vec::new());
+            }
+            (None, c) if c.is_whitespace() => flush(&mut word, &mut segments),
+            (None, c) => word.push(c),
+        }
+    }
+    flush(&mut word, &mut segments);
+    segments.retain(|s| !s.is_empty());
+    segments
+}
+
+/// Whether a shell word plausibly names a file rather than a flag, number,
+/// pattern, or fd redirect.
+fn looks_like_path(word: &str) -> bool {
+    !word.is_empty()
+        && !word.starts_with('-')
+        && !word.starts_with('&')
+        && word != "/dev/null"
+        && !word.contains(['*', '?', '$', '`'])
+        && (word.contains('/') || word.contains('.'))
+        && word.parse::<f64>().is_err()
+}
+
+fn clip(s: &str) -> String {
+    let s = s.trim();
+    if s.chars().count() <= MAX_LINE_CHARS {
+        return s.to_string();
+    }
+    let clipped: String = s.chars().take(MAX_LINE_CHARS).collect();
+    format!("{}…", clipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ToolCall;
+    use serde_json::json;
+
+    fn call(id: &str, name: &str, args: serde_json::Value, result: &str) -> Vec<Message> {
+        vec![
+            Message::assistant_with_tool_calls("", vec![ToolCall::new(id, name, args)]),
+            Message::tool_result(id, result),
+        ]
+    }
+
+    #[test]
+    fn test_delta_from_run_commands() {
+        let mut messages = Vec::new();
+        messages.extend(call("1", "run", json!({"command": "cat src/lib.rs | head -n 20"}), "..."));
+        messages.extend(call(
+            "2",
+            "run",
+            json!({"command": "sed -i 's/a/b/' src/main.rs && cargo build 2>&1 > /tmp/build.log"}),
+            "ok",
+        ));
+        messages.extend(call("3", "run", json!({"command": "mv old.rs new.rs"}), "ok"));
+        messages.extend(call("4", "run", json!({"command": "cargo test"}), "Error: 2 tests failed\nmore"));
+
+        let delta = StateDelta::from_messages(&messages);
+        assert_eq!(
+            delta.files_modified.iter().collect::<Vec<_>>(),
+            ["/tmp/build.log", "new.rs", "old.rs", "src/main.rs"]
+        );
+        assert_eq!(delta.files_read.iter().collect::<Vec<_>>(), ["src/lib.rs"]);
+        assert_eq!(delta.commands.len(), 4);
+        assert!(!delta.commands[3].ok);
+        assert_eq!(delta.errors, vec![("run".to_string(), "Error: 2 tests failed".to_string())]);
+    }
+
+    #[test]
+    fn test_delta_from_structured_tools() {
+        let mut messages = Vec::new();
+        messages.extend(call(
+            "1",
+            "read_files",
+            json!({"files": [{"path": "a.rs"}, {"path": "b.rs"}]}),
+            "...",
+        ));
+        messages.extend(call("2", "write_file", json!({"path": "b.rs", "content": "x"}), "ok"));
+        messages.extend(call("3", "update_my_task", json!({"status": "done"}), "ok"));
+        messages.extend(call("4", "update_task", json!({"task_id": "3", "add_note": "blocked on CI"}), "ok"));
+        messages.extend(call("5", "write_file", json!({"path": "c.rs"}), "Error: permission denied"));
+        messages.extend(call(
+            "6",
+            "git_commit",
+            json!({"message": "Add b"}),
+            "Committed 1a2b3c4 on main: Add b\n b.rs | 1 +",
+        ));
+        messages.extend(call("7", "git_commit", json!({"message": "x"}), "Error: nothing staged"));
+
+        let delta = StateDelta::from_messages(&messages);
+        assert_eq!(delta.files_modified.iter().collect::<Vec<_>>(), ["b.rs"]);
+        // Files later modified are only listed as modified
+        assert_eq!(delta.files_read.iter().collect::<Vec<_>>(), ["a.rs"]);
+        assert_eq!(delta.tasks["(own task)"], "done");
+        assert_eq!(delta.tasks["3"], "note: blocked on CI");
+        assert_eq!(delta.errors.len(), 2);
+        assert_eq!(delta.commits, vec!["1a2b3c4 on main: Add b".to_string()]);
+        assert!(delta.render().unwrap().contains("Commits:\n- 1a2b3c4 on main: Add b\n"));
+    }
+
+    #[test]
+    fn test_render() {
+        assert!(StateDelta::default().render().is_none());
+
+        let mut messages = Vec::new();
+        for i in 0..12 {
+            messages.extend(call(&i.to_string(), "run", json!({"command": format!("echo {}", i)}), "ok"));
+        }
+        messages.extend(call("w", "write_file", json!({"path": "src/x.rs"}), "ok"));
+
+        let text = StateDelta::from_messages(&messages).render().unwrap();
+        assert!(text.contains("Files modified:\n- src/x.rs\n"));
+        assert!(text.contains("- [ok] echo 11\n"));
+        // Only the most recent commands are listed
+        assert!(!text.contains("echo 0\n") && !text.contains("echo 1\n"));
+    }
+
+    #[test]
+    fn test_split_segments() {
+        assert_eq!(
+            split_segments("grep 'a b' x.txt | sort; echo \"done\" 2>&1"),
+            vec![
+                vec!["grep".to_string(), "a b".into(), "x.txt".into()],
+                vec!["sort".into()],
+                vec!["echo".into(), "done".into(), "2>&1".into()],
+            ]
+        );
+    }
+}
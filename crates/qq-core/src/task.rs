@@ -7,6 +7,7 @@
 
 use std::collections::HashMap;
 use std::future::Future;
+use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
@@ -334,6 +335,88 @@ impl ToolExecutionResult {
     }
 }
 
+/// For each call in a batch that runs concurrently, the reason it must not
+/// run: it writes a path that overlaps one an earlier call in the batch
+/// writes (the same file, or a directory and something inside it).
+///
+/// The earliest call to claim a path wins and later ones are rejected, so
+/// the outcome depends only on call order, not on scheduling. Targets come
+/// from [`crate::Tool::write_targets`] and are compared after lexical
+/// normalization, so `./src/a.rs` and `src/b/../a.rs` are the same file.
+pub fn write_conflicts<'a>(
+    registry: &ToolRegistry,
+    tool_calls: impl IntoIterator<Item = &'a ToolCall>,
+) -> Vec<Option<String>> {
+    let mut claimed: Vec<(String, &'a ToolCall)> = Vec::new();
+    tool_calls
+        .into_iter()
+        .map(|call| {
+            let targets: Vec<String> = registry
+                .get(&call.name)
+                .map(|tool| tool.write_targets(&call.arguments))
+                .unwrap_or_default()
+                .iter()
+                .map(|target| normalize_target(target))
+                .collect();
+            let conflict = targets.iter().find_map(|target| {
+                claimed
+                    .iter()
+                    .find(|(other, _)| paths_overlap(target, other))
+                    .map(|(other, earlier)| (target, other, *earlier))
+            });
+            match conflict {
+                Some((target, other, earlier)) => {
+                    tracing::warn!(
+                        tool = %call.name,
+                        target = %target,
+                        earlier = %earlier.id,
+                        "Rejected conflicting write in parallel batch"
+                    );
+                    Some(format!(
+                        "Write conflict: this call writes `{}`, which overlaps `{}` written by the earlier `{}` call ({}) in the same batch. \
+                         Calls in a batch run concurrently, so this one was not run; issue it again after that call's result.",
+                        target, other, earlier.name, earlier.id
+                    ))
+                }
+                None => {
+                    claimed.extend(targets.into_iter().map(|target| (target, call)));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// `path` without `.` segments, with `..` applied to the segment before it.
+/// Leading `..` of a relative path are kept; an empty result is `.`.
+fn normalize_target(path: &str) -> String {
+    let mut normalized = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        normalized.to_string_lossy().into_owned()
+    }
+}
+
+/// Whether one path is the other or lies inside it. `.` is the whole project.
+fn paths_overlap(a: &str, b: &str) -> bool {
+    let (a, b) = (Path::new(a), Path::new(b));
+    a == Path::new(".") || b == Path::new(".") || a.starts_with(b) || b.starts_with(a)
+}
+
 /// Execute multiple tool calls in parallel.
 ///
 /// Returns results in the same order as the input tool calls.
@@ -349,7 +432,9 @@ pub async fn execute_tools_parallel(
 
 /// Execute multiple tool calls in parallel with optional chunking support.
 ///
-/// Returns results in the same order as the input tool calls.
+/// Returns results in the same order as the input tool calls. Calls whose
+/// writes conflict with an earlier call's are not run and return an error
+/// (see [`write_conflicts`]).
 ///
 /// If a `chunk_processor` is provided and tool output exceeds the configured
 /// threshold, the content will be automatically chunked and summarized.
@@ -361,107 +446,180 @@ pub async fn execute_tools_parallel_with_chunker(
 ) -> Vec<ToolExecutionResult> {
     use futures::future::join_all;
 
-    use crate::message::TypedContent;
-
+    let conflicts = write_conflicts(registry, &tool_calls);
     let futures: Vec<_> = tool_calls
-        .into_iter()
-        .map(|tool_call| {
-            let tool_call_id = tool_call.id.clone();
-            let tool_name = tool_call.name.clone();
-            let arguments = tool_call.arguments.clone();
-
-            async move {
-                let Some(tool) = registry.get_arc(&tool_name) else {
-                    return ToolExecutionResult {
-                        tool_call_id,
-                        content: vec![TypedContent::text(format!("Error: Unknown tool '{}'", tool_name))],
-                        is_error: true,
-                    };
-                };
-
-                match crate::tool::execute_tool_dispatch(tool, arguments).await {
-                    Ok(output) => {
-                        let content = if output.is_error {
-                            // Prepend "Error: " to the text content for error outputs
-                            let error_text = format!("Error: {}", output.text_content());
-                            vec![TypedContent::text(error_text)]
-                        } else {
-                            output.content
-                        };
-                        ToolExecutionResult {
-                            tool_call_id,
-                            content,
-                            is_error: output.is_error,
-                        }
-                    }
-                    Err(e) => ToolExecutionResult {
-                        tool_call_id,
-                        content: vec![TypedContent::text(format!("Error executing tool: {}", e))],
-                        is_error: true,
-                    },
-                }
-            }
-        })
+        .iter()
+        .cloned()
+        .zip(conflicts)
+        .map(|(tool_call, conflict)| execute_tool_call(registry, tool_call, conflict))
         .collect();
 
     let mut results = join_all(futures).await;
 
     // Apply chunking to large text outputs if processor is provided
     if let Some(processor) = chunk_processor {
-        for result in &mut results {
-            // Skip error results - keep them readable
-            if result.is_error {
-                continue;
+        for (result, tool_call) in results.iter_mut().zip(&tool_calls) {
+            chunk_tool_output(processor, registry, tool_call, result, original_query).await;
+        }
+    }
+
+    results
+}
+
+/// Run one tool call. `conflict` is the reason it must not run, from
+/// [`write_conflicts`]; failures become error results.
+pub(crate) async fn execute_tool_call(
+    registry: &ToolRegistry,
+    tool_call: ToolCall,
+    conflict: Option<String>,
+) -> ToolExecutionResult {
+    use crate::message::TypedContent;
+
+    let ToolCall { id: tool_call_id, name: tool_name, arguments } = tool_call;
+    if let Some(conflict) = conflict {
+        return ToolExecutionResult {
+            tool_call_id,
+            content: vec![TypedContent::text(format!("Error: {}", conflict))],
+            is_error: true,
+        };
+    }
+    let Some(tool) = registry.get_arc(&tool_name) else {
+        return ToolExecutionResult {
+            tool_call_id,
+            content: vec![TypedContent::text(format!("Error: Unknown tool '{}'", tool_name))],
+            is_error: true,
+        };
+    };
+
+    match crate::tool::execute_tool_dispatch(tool, arguments).await {
+        Ok(output) => {
+            let content = if output.is_error {
+                // Prepend "Error: " to the text content for error outputs
+                let error_text = format!("Error: {}", output.text_content());
+                vec![TypedContent::text(error_text)]
+            } else {
+                output.content
+            };
+            ToolExecutionResult {
+                tool_call_id,
+                content,
+                is_error: output.is_error,
             }
+        }
+        Err(e) => ToolExecutionResult {
+            tool_call_id,
+            content: vec![TypedContent::text(format!("Error executing tool: {}", e))],
+            is_error: true,
+        },
+    }
+}
 
-            // Only chunk text content; pass images through unchanged
-            let text = result.text_content();
-            if processor.should_chunk(&text) {
-                match processor
-                    .process_large_content(&text, original_query)
-                    .await
-                {
-                    Ok(processed) => {
-                        // Replace text parts with chunked version, keep non-text parts
-                        let mut new_content: Vec<TypedContent> = result
-                            .content
-                            .iter()
-                            .filter(|c| !matches!(c, TypedContent::Text { .. }))
-                            .cloned()
-                            .collect();
-                        new_content.insert(0, TypedContent::text(processed));
-                        result.content = new_content;
-                    }
-                    Err(e) => {
-                        // Log warning but keep original content
-                        tracing::warn!(
-                            "Failed to process large content for chunking: {}",
-                            e
-                        );
-                        // Truncate with a note instead
-                        let truncate_at = processor.config().threshold_bytes;
-                        if text.len() > truncate_at {
-                            let mut new_content: Vec<TypedContent> = result
-                                .content
-                                .iter()
-                                .filter(|c| !matches!(c, TypedContent::Text { .. }))
-                                .cloned()
-                                .collect();
-                            new_content.insert(0, TypedContent::text(format!(
-                                "[Large output: {} bytes, showing first {} bytes]\n\n{}",
-                                text.len(),
-                                truncate_at,
-                                &text[..truncate_at]
-                            )));
-                            result.content = new_content;
-                        }
-                    }
+/// Replace a large text output with its chunked summary. Error results are
+/// left readable and images pass through unchanged.
+///
+/// When part of the output had to be dropped, the result becomes an error
+/// carrying a narrower retry of `tool_call` from its tool, if it has one
+/// (see [`crate::narrowing`]).
+pub(crate) async fn chunk_tool_output(
+    processor: &ChunkProcessor,
+    registry: &ToolRegistry,
+    tool_call: &ToolCall,
+    result: &mut ToolExecutionResult,
+    original_query: Option<&str>,
+) {
+    use crate::message::TypedContent;
+
+    if result.is_error {
+        return;
+    }
+
+    // Only chunk text content; pass images through unchanged
+    let text = result.text_content();
+    if !processor.should_chunk(&text) {
+        return;
+    }
+    match processor.process(&text, original_query).await {
+        Ok(processed) => {
+            // Replace text parts with chunked version, keep non-text parts
+            let mut new_content: Vec<TypedContent> = result
+                .content
+                .iter()
+                .filter(|c| !matches!(c, TypedContent::Text { .. }))
+                .cloned()
+                .collect();
+            new_content.insert(0, TypedContent::text(processed.text));
+            result.content = new_content;
+            if !processed.complete {
+                suggest_narrower_retry(registry, tool_call, result, text.len(), processor);
+            }
+        }
+        Err(e) => {
+            // Log warning but keep original content
+            tracing::warn!(
+                "Failed to process large content for chunking: {}",
+                e
+            );
+            // Truncate with a note instead
+            let truncate_at = processor.config().threshold_bytes;
+            if text.len() > truncate_at {
+                let mut new_content: Vec<TypedContent> = result
+                    .content
+                    .iter()
+                    .filter(|c| !matches!(c, TypedContent::Text { .. }))
+                    .cloned()
+                    .collect();
+                let truncate_at = crate::text::floor_grapheme_boundary(&text, truncate_at);
+                let mut truncated = format!(
+                    "[Large output: {} bytes, showing first {} bytes]\n\n{}",
+                    text.len(),
+                    truncate_at,
+                    &text[..truncate_at]
+                );
+                if let Some(line) = crate::chunker::continuation_line(&text) {
+                    truncated.push_str("\n\n");
+                    truncated.push_str(line);
                 }
+                new_content.insert(0, TypedContent::text(truncated));
+                result.content = new_content;
+                suggest_narrower_retry(registry, tool_call, result, text.len(), processor);
             }
         }
     }
+}
 
-    results
+/// Turn a result whose output was only partly kept into an error asking for
+/// a narrower call, when the tool can suggest one.
+fn suggest_narrower_retry(
+    registry: &ToolRegistry,
+    tool_call: &ToolCall,
+    result: &mut ToolExecutionResult,
+    output_bytes: usize,
+    processor: &ChunkProcessor,
+) {
+    use crate::message::TypedContent;
+
+    let budget = processor.config().threshold_bytes;
+    let fraction = budget as f64 / output_bytes.max(1) as f64;
+    let Some(retry) = registry
+        .get_arc(&tool_call.name)
+        .and_then(|tool| tool.narrower_scope(&tool_call.arguments, fraction))
+    else {
+        return;
+    };
+    tracing::debug!(tool = %tool_call.name, changes = ?retry.changes, "Suggesting a narrower retry");
+    let partial = result.text_content();
+    result.content.retain(|c| !matches!(c, TypedContent::Text { .. }));
+    result.content.insert(
+        0,
+        TypedContent::text(crate::narrowing::overflow_error(
+            &tool_call.name,
+            &retry,
+            output_bytes,
+            budget,
+            &partial,
+        )),
+    );
+    result.is_error = true;
 }
 
 /// Execute multiple LLM completion requests in parallel.
@@ -543,4 +701,146 @@ mod tests {
         let id = TaskId(42);
         assert_eq!(format!("{}", id), "42");
     }
+
+    /// Writes `arguments.path` and counts executions.
+    struct WriteTool(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl crate::tool::Tool for WriteTool {
+        fn name(&self) -> &str {
+            "write"
+        }
+        fn description(&self) -> &str {
+            "test"
+        }
+        fn definition(&self) -> crate::tool::ToolDefinition {
+            crate::tool::ToolDefinition::new("write", "test")
+        }
+        fn write_targets(&self, arguments: &serde_json::Value) -> Vec<String> {
+            arguments["path"].as_str().map(String::from).into_iter().collect()
+        }
+        async fn execute(
+            &self,
+            _arguments: serde_json::Value,
+        ) -> Result<crate::tool::ToolOutput, Error> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(crate::tool::ToolOutput::success("ok"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_writes_rejected_in_call_order() {
+        let tool = Arc::new(WriteTool(Default::default()));
+        let mut registry = ToolRegistry::new();
+        registry.register(tool.clone());
+
+        let call = |id: &str, path: &str| {
+            ToolCall::new(id, "write", serde_json::json!({ "path": path }))
+        };
+        let calls = vec![
+            call("a", "src/lib.rs"),
+            call("b", "src/main.rs"),
+            call("c", "src/lib.rs"),
+            call("d", "src"),
+            call("e", "srcs/other.rs"),
+            call("f", "./src/main.rs"),
+            call("g", "docs/../src/lib.rs/"),
+            call("h", "./"),
+        ];
+
+        let conflicts = write_conflicts(&registry, &calls);
+        assert!(conflicts[0].is_none() && conflicts[1].is_none());
+        assert!(conflicts[2].as_ref().unwrap().contains("`src/lib.rs`"));
+        assert!(conflicts[2].as_ref().unwrap().contains("(a)"));
+        assert!(conflicts[3].as_ref().unwrap().contains("overlaps `src/lib.rs`"));
+        assert!(conflicts[4].is_none());
+        assert!(conflicts[5].as_ref().unwrap().contains("`src/main.rs`"));
+        assert!(conflicts[5].as_ref().unwrap().contains("(b)"));
+        assert!(conflicts[6].as_ref().unwrap().contains("(a)"));
+        assert!(conflicts[7].as_ref().unwrap().contains("writes `.`"));
+
+        let results = execute_tools_parallel(&registry, calls).await;
+        let errors: Vec<&str> = results
+            .iter()
+            .filter(|r| r.is_error)
+            .map(|r| r.tool_call_id.as_str())
+            .collect();
+        assert_eq!(errors, vec!["c", "d", "f", "g", "h"]);
+        assert!(results[2].text_content().starts_with("Error: Write conflict"));
+        assert_eq!(tool.0.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_normalize_target() {
+        assert_eq!(normalize_target("./src/a.rs"), "src/a.rs");
+        assert_eq!(normalize_target("src/x/../a.rs"), "src/a.rs");
+        assert_eq!(normalize_target("src/.."), ".");
+        assert_eq!(normalize_target("../other/a.rs"), "../other/a.rs");
+        assert_eq!(normalize_target("/work/./src/../a.rs"), "/work/a.rs");
+        assert_eq!(normalize_target("/.."), "/");
+    }
+
+    /// Lists `arguments.count` entries, capped by a `limit` parameter.
+    struct ListTool;
+
+    #[async_trait::async_trait]
+    impl crate::tool::Tool for ListTool {
+        fn name(&self) -> &str {
+            "list"
+        }
+        fn description(&self) -> &str {
+            "test"
+        }
+        fn definition(&self) -> crate::tool::ToolDefinition {
+            crate::tool::ToolDefinition::new("list", "test").with_parameters(
+                crate::tool::ToolParameters::new().add_property(
+                    "limit",
+                    crate::tool::PropertySchema::integer("Entries"),
+                    false,
+                ),
+            )
+        }
+        async fn execute(
+            &self,
+            arguments: serde_json::Value,
+        ) -> Result<crate::tool::ToolOutput, Error> {
+            let limit = arguments["limit"].as_u64().unwrap_or(100) as usize;
+            Ok(crate::tool::ToolOutput::success(
+                "entry\n\n".repeat(limit),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow_past_max_chunks_suggests_narrower_retry() {
+        let provider = Arc::new(crate::testing::MockProvider::new());
+        for _ in 0..4 {
+            provider.queue_response("summary");
+        }
+        let config = crate::chunker::ChunkerConfig::new()
+            .with_threshold(100)
+            .with_chunk_size(70)
+            .with_max_chunks(2)
+            .with_parallel(false)
+            .with_relevance_filter(false);
+        let processor = ChunkProcessor::new(provider, config);
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(ListTool));
+
+        // 700 bytes in ~10 chunks; only 2 are summarized
+        let calls = vec![ToolCall::new("a", "list", serde_json::json!({"limit": 100}))];
+        let results =
+            execute_tools_parallel_with_chunker(&registry, calls, Some(&processor), None).await;
+        let text = results[0].text_content();
+        assert!(results[0].is_error);
+        assert!(text.starts_with("Error: Output too large: 700 bytes"));
+        assert!(text.contains("\"limit\": 14"));
+        assert!(text.contains("Partial result:\n[Large output processed"));
+
+        // Everything summarized: no retry
+        let calls = vec![ToolCall::new("b", "list", serde_json::json!({"limit": 20}))];
+        let results =
+            execute_tools_parallel_with_chunker(&registry, calls, Some(&processor), None).await;
+        assert!(!results[0].is_error);
+    }
 }
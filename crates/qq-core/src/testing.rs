@@ -2,16 +2,20 @@
 //! Only compiled when running tests or with the `testing` feature.
 
 use async_trait::async_trait;
+use std::path::Path;
 use std::sync::Mutex;
 
 use crate::error::Error;
-use crate::message::{FinishReason, Message, StreamChunk, Usage};
+use crate::message::{
+    FileRef, FinishReason, ImageData, Message, StreamChunk, ToolCall, TypedContent, Usage,
+};
 use crate::observation::ContextCompactor;
 use crate::provider::{CompletionRequest, CompletionResponse, Provider, StreamResult};
+use crate::tool::{PropertySchema, ToolDefinition, ToolParameters};
 
 /// A mock provider that returns pre-configured responses.
 pub struct MockProvider {
-    responses: Mutex<Vec<CompletionResponse>>,
+    responses: Mutex<Vec<Result<CompletionResponse, Error>>>,
     /// Each entry is the chunk sequence to replay for one stream() call. FIFO.
     stream_chunks: Mutex<Vec<Vec<StreamChunk>>>,
     /// Captured requests (for assertion).
@@ -19,6 +23,11 @@ pub struct MockProvider {
     pub name: String,
     pub default_model: Option<String>,
     pub context_window: Option<u32>,
+    /// Whether `upload_file` succeeds; uploaded file names and contents are
+    /// recorded.
+    pub file_uploads: bool,
+    pub uploaded_files: Mutex<Vec<String>>,
+    pub uploaded_bytes: Mutex<Vec<Vec<u8>>>,
 }
 
 impl MockProvider {
@@ -30,9 +39,18 @@ impl MockProvider {
             name: "mock".to_string(),
             default_model: None,
             context_window: None,
+            file_uploads: false,
+            uploaded_files: Mutex::new(Vec::new()),
+            uploaded_bytes: Mutex::new(Vec::new()),
         }
     }
 
+    /// Accept file uploads, returning IDs `file-1`, `file-2`, ...
+    pub fn with_file_uploads(mut self) -> Self {
+        self.file_uploads = true;
+        self
+    }
+
     /// Set the context window so tests can exercise the
     /// "context window full vs max_tokens cap" branch in the agent loop.
     pub fn with_context_window(mut self, tokens: u32) -> Self {
@@ -56,12 +74,18 @@ impl MockProvider {
             model: "mock-model".to_string(),
             finish_reason,
         };
-        self.responses.lock().unwrap().insert(0, response);
+        self.responses.lock().unwrap().insert(0, Ok(response));
     }
 
     /// Queue a raw CompletionResponse.
     pub fn queue_raw_response(&self, response: CompletionResponse) {
-        self.responses.lock().unwrap().insert(0, response);
+        self.responses.lock().unwrap().insert(0, Ok(response));
+    }
+
+    /// Queue an error to be returned by the next complete() call, in FIFO
+    /// order with queued responses.
+    pub fn queue_error(&self, error: Error) {
+        self.responses.lock().unwrap().insert(0, Err(error));
     }
 
     /// Queue a stream — the next `stream()` call replays these chunks in order.
@@ -104,7 +128,7 @@ impl Provider for MockProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, Error> {
         self.captured_requests.lock().unwrap().push(request);
         match self.responses.lock().unwrap().pop() {
-            Some(response) => Ok(response),
+            Some(response) => response,
             None => Err(Error::Unknown("No mock response queued".to_string())),
         }
     }
@@ -121,6 +145,52 @@ impl Provider for MockProvider {
         let stream = futures::stream::iter(chunks.into_iter().map(Ok));
         Ok(Box::pin(stream))
     }
+
+    fn supports_file_upload(&self) -> bool {
+        self.file_uploads
+    }
+
+    async fn upload_file(
+        &self,
+        filename: &str,
+        media_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        if !self.file_uploads {
+            return Err(Error::invalid_request("mock provider does not support file uploads"));
+        }
+        let mut uploaded = self.uploaded_files.lock().unwrap();
+        uploaded.push(filename.to_string());
+        let size = bytes.len() as u64;
+        self.uploaded_bytes.lock().unwrap().push(bytes);
+        Ok(FileRef {
+            provider: self.name.clone(),
+            id: format!("file-{}", uploaded.len()),
+            filename: filename.to_string(),
+            media_type: media_type.to_string(),
+            size,
+        })
+    }
+
+    /// Hashed bag-of-words vectors, so texts that share words score as similar.
+    async fn embed(&self, _model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        Ok(inputs.iter().map(|text| bag_of_words(text)).collect())
+    }
+}
+
+fn bag_of_words(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0; 64];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        // FNV-1a
+        let hash = word.to_lowercase().bytes().fold(0xcbf29ce484222325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        vector[(hash % 64) as usize] += 1.0;
+    }
+    vector
 }
 
 /// A mock context compactor for testing ObservationalMemory.
@@ -196,3 +266,237 @@ impl ContextCompactor for MockCompactor {
         self.observe(messages).await
     }
 }
+
+// =============================================================================
+// Snapshots
+// =============================================================================
+
+/// A request exercising every part of a conversation a provider has to
+/// serialize: system prompt, text and image user turns, an assistant turn
+/// with parallel tool calls, their results (one empty), a tool definition,
+/// and sampling parameters. Provider golden-file tests build their request
+/// bodies from it.
+pub fn golden_request() -> CompletionRequest {
+    let image = ImageData {
+        data: "iVBORw0KGgo=".to_string(),
+        media_type: "image/png".to_string(),
+        width: 1,
+        height: 1,
+    };
+    let read = ToolDefinition::new("read_file", "Read a file from the project").with_parameters(
+        ToolParameters::new()
+            .add_property("path", PropertySchema::string("Path to read"), true)
+            .add_property("start_line", PropertySchema::integer("First line, 1-based"), false),
+    );
+    CompletionRequest::new(vec![
+        Message::system("You are a careful assistant."),
+        Message::user(vec![
+            TypedContent::text("What does this screenshot show, and where is main defined?"),
+            TypedContent::image(image),
+        ]),
+        Message::assistant_with_tool_calls(
+            "Let me look.",
+            vec![
+                ToolCall::new(
+                    "call_1",
+                    "read_file",
+                    serde_json::json!({"path": "src/main.rs", "start_line": 1}),
+                ),
+                ToolCall::new("call_2", "read_file", serde_json::json!({"path": "README.md"})),
+            ],
+        ),
+        Message::tool_result("call_1", "fn main() {}"),
+        Message::tool_result("call_2", ""),
+        Message::assistant("`main` is in src/main.rs; the screenshot shows a terminal."),
+        Message::user("Thanks. Summarize in one line."),
+    ])
+    .with_model("golden-model")
+    .with_temperature(0.2)
+    .with_max_tokens(1024)
+    .with_tools(vec![read])
+}
+
+/// Environment variable that rewrites snapshots instead of comparing them.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "QQ_UPDATE_SNAPSHOTS";
+
+/// Compare `$actual` with the golden file `snapshots/<name>.snap` in the
+/// calling crate. See [`assert_snapshot`].
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($name:expr, $actual:expr $(,)?) => {
+        $crate::testing::assert_snapshot(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("snapshots")
+                .join(format!("{}.snap", $name)),
+            &$actual,
+        )
+    };
+}
+
+/// Compare `actual` with the golden file at `path`, panicking with a line
+/// diff when they differ. Generated prompts, preambles, and request bodies
+/// are snapshotted so changes to them show up in review instead of
+/// regressing silently.
+///
+/// - `QQ_UPDATE_SNAPSHOTS=1` rewrites the file and passes.
+/// - A missing file is written and the check passes, except under CI
+///   (`CI` set), where it fails.
+/// - On a mismatch the new output is written next to the file as
+///   `<name>.snap.new`, for inspection or to copy over.
+pub fn assert_snapshot(path: impl AsRef<Path>, actual: &str) {
+    let update = std::env::var(UPDATE_SNAPSHOTS_ENV).is_ok_and(|v| v == "1" || v == "true");
+    let ci = std::env::var_os("CI").is_some();
+    if let Err(e) = check_snapshot(path.as_ref(), actual, update, ci) {
+        panic!("{}", e);
+    }
+}
+
+/// Snapshot text as stored: trailing whitespace trimmed from each line and
+/// a single final newline.
+fn normalize_snapshot(text: &str) -> String {
+    let mut out: String = text
+        .trim_end()
+        .lines()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+    out.push('\n');
+    out
+}
+
+fn check_snapshot(path: &Path, actual: &str, update: bool, ci: bool) -> Result<(), String> {
+    let actual = normalize_snapshot(actual);
+    let pending = path.with_extension("snap.new");
+    let write = |target: &Path| {
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("cannot create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(target, &actual)
+            .map_err(|e| format!("cannot write {}: {}", target.display(), e))
+    };
+
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(_) if ci && !update => {
+            return Err(format!(
+                "snapshot {} is missing; run the tests with {}=1 and commit it",
+                path.display(),
+                UPDATE_SNAPSHOTS_ENV
+            ));
+        }
+        Err(_) => return write(path),
+    };
+    if update {
+        let _ = std::fs::remove_file(&pending);
+        return write(path);
+    }
+    if normalize_snapshot(&expected) == actual {
+        let _ = std::fs::remove_file(&pending);
+        return Ok(());
+    }
+    write(&pending)?;
+    Err(format!(
+        "snapshot {} changed (new output in {}):\n{}\nIf the change is intended, rerun with {}=1.",
+        path.display(),
+        pending.display(),
+        line_diff(&normalize_snapshot(&expected), &actual),
+        UPDATE_SNAPSHOTS_ENV
+    ))
+}
+
+/// Unchanged lines kept around each change in [`line_diff`].
+const DIFF_CONTEXT: usize = 2;
+
+/// Line diff of `expected` → `actual`: `-` removed, `+` added, two lines
+/// of context around changes, and `@@ line N` before each hunk.
+pub fn line_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Longest common subsequence table, from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // (tag, old line number, text)
+    let mut ops: Vec<(char, usize, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', i + 1, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', i + 1, old[i]));
+            i += 1;
+        } else {
+            ops.push(('+', i + 1, new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    let mut out = String::new();
+    let mut next = 0;
+    while next < changed.len() {
+        // Merge changes whose context windows touch into one hunk
+        let start = changed[next].saturating_sub(DIFF_CONTEXT);
+        let mut end = changed[next] + DIFF_CONTEXT + 1;
+        next += 1;
+        while next < changed.len() && changed[next].saturating_sub(DIFF_CONTEXT) <= end {
+            end = changed[next] + DIFF_CONTEXT + 1;
+            next += 1;
+        }
+        out.push_str(&format!("@@ line {}\n", ops[start].1));
+        for (tag, _, text) in &ops[start..end.min(ops.len())] {
+            out.push_str(&format!("{}{}\n", tag, text));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        assert_eq!(
+            line_diff(old, new),
+            "@@ line 1\n a\n-b\n+B\n c\n d\n@@ line 8\n h\n i\n+j\n"
+        );
+        assert_eq!(line_diff("same\n", "same\n"), "");
+    }
+
+    #[test]
+    fn test_check_snapshot_lifecycle() {
+        let dir = std::env::temp_dir().join(format!("qq-snapshot-test-{}", std::process::id()));
+        let path = dir.join("nested/prompt.snap");
+        let pending = dir.join("nested/prompt.snap.new");
+
+        // Missing: fails under CI, written otherwise
+        assert!(check_snapshot(&path, "one\ntwo", false, true).is_err());
+        check_snapshot(&path, "one\ntwo  \n\n", false, false).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        check_snapshot(&path, "one\ntwo", false, true).unwrap();
+
+        let err = check_snapshot(&path, "one\n2", false, false).unwrap_err();
+        assert!(err.contains("-two\n+2"));
+        assert_eq!(std::fs::read_to_string(&pending).unwrap(), "one\n2\n");
+
+        check_snapshot(&path, "one\n2", true, true).unwrap();
+        assert!(!pending.exists());
+        check_snapshot(&path, "one\n2", false, true).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,102 @@
+//! Unicode-safe truncation and measurement of text.
+//!
+//! Byte offsets picked by size limits (tool output caps, preview lengths)
+//! rarely land where text can be cut. These helpers move a cut to the
+//! nearest grapheme cluster boundary, so truncation never splits a UTF-8
+//! sequence (which panics) or a user-perceived character such as an
+//! accented letter, a flag, or an emoji with modifiers (which garbles it).
+//! Display helpers measure in terminal columns, where CJK characters and
+//! most emoji are two columns wide.
+
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+use unicode_width::UnicodeWidthStr;
+
+/// Largest grapheme cluster boundary `<= index`.
+pub fn floor_grapheme_boundary(s: &str, index: usize) -> usize {
+    let index = s.floor_char_boundary(index);
+    let mut cursor = GraphemeCursor::new(index, s.len(), true);
+    match cursor.is_boundary(s, 0) {
+        Ok(true) => index,
+        _ => cursor.prev_boundary(s, 0).ok().flatten().unwrap_or(0),
+    }
+}
+
+/// Smallest grapheme cluster boundary `>= index`.
+pub fn ceil_grapheme_boundary(s: &str, index: usize) -> usize {
+    let index = s.ceil_char_boundary(index);
+    let mut cursor = GraphemeCursor::new(index, s.len(), true);
+    match cursor.is_boundary(s, 0) {
+        Ok(true) => index,
+        _ => cursor.next_boundary(s, 0).ok().flatten().unwrap_or(s.len()),
+    }
+}
+
+/// Longest prefix of `s` of at most `max_bytes` that ends on a grapheme
+/// cluster boundary.
+pub fn truncate_bytes(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    &s[..floor_grapheme_boundary(s, max_bytes)]
+}
+
+/// Width of `s` in terminal columns.
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Longest prefix of `s` of whole grapheme clusters that fits in `max_width`
+/// terminal columns.
+pub fn truncate_width(s: &str, max_width: usize) -> &str {
+    let mut width = 0;
+    for (i, grapheme) in s.grapheme_indices(true) {
+        width += display_width(grapheme);
+        if width > max_width {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+/// `s` cut to fit in `max_width` columns, ending in "..." when cut.
+pub fn ellipsize(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
+        return s.to_string();
+    }
+    format!("{}...", truncate_width(s, max_width.saturating_sub(3)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_truncation_keeps_graphemes_whole() {
+        // "e" + combining acute accent, then a family emoji joined by ZWJs
+        let s = "cafe\u{301} 👨‍👩‍👧 ok";
+        let accent = s.find('\u{301}').unwrap();
+        assert_eq!(floor_grapheme_boundary(s, accent), 3);
+        assert_eq!(ceil_grapheme_boundary(s, accent), accent + 2);
+
+        let family = s.find('👨').unwrap();
+        for cut in family + 1..family + "👨‍👩‍👧".len() {
+            assert_eq!(truncate_bytes(s, cut), &s[..family]);
+        }
+        assert_eq!(truncate_bytes(s, s.len()), s);
+        assert_eq!(truncate_bytes(s, 0), "");
+        assert_eq!(floor_grapheme_boundary(s, 1000), s.len());
+        assert_eq!(ceil_grapheme_boundary(s, 1000), s.len());
+    }
+
+    #[test]
+    fn test_width_truncation_counts_columns() {
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(truncate_width("日本語", 5), "日本");
+        assert_eq!(truncate_width("日本語", 6), "日本語");
+        assert_eq!(truncate_width("👍🏽 yes", 2), "👍🏽");
+
+        assert_eq!(ellipsize("short", 10), "short");
+        assert_eq!(ellipsize("中文字符串很长", 9), "中文字...");
+        assert_eq!(ellipsize("abcdefghij", 6), "abc...");
+    }
+}
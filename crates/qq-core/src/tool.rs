@@ -4,7 +4,9 @@ use serde_json::Value;
 use std::fmt;
 use std::sync::Arc;
 
+use crate::cancel::Checkpoint;
 use crate::error::Error;
+use crate::narrowing::ScopeRetry;
 
 // =============================================================================
 // ToolRef — A resolved reference to a single tool
@@ -430,6 +432,35 @@ pub trait Tool: Send + Sync {
         false
     }
 
+    /// Whether this tool only observes state and never changes it.
+    ///
+    /// The agent loop's dedup cache flushes whenever a tool that isn't
+    /// read-only executes, since its side effects may change what a repeated
+    /// call would return. Default: false (assume side effects).
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Filesystem paths a call with `arguments` would write, relative to the
+    /// project root (absolute only when outside it).
+    ///
+    /// Calls issued together run concurrently; a call whose targets overlap
+    /// an earlier call's in the same batch is rejected instead of racing it
+    /// (see [`crate::task::write_conflicts`]). Default: none.
+    fn write_targets(&self, _arguments: &Value) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// A narrower version of a call with `arguments` whose output was too
+    /// large to keep whole; `fraction` is the share that fit.
+    ///
+    /// Returned to the model as a structured error so it can retry with less
+    /// (see [`crate::narrowing`]). Default: shrink limit parameters and point
+    /// at unset filters found in the tool's schema.
+    fn narrower_scope(&self, arguments: &Value, fraction: f64) -> Option<ScopeRetry> {
+        crate::narrowing::from_schema(&self.definition(), arguments, fraction)
+    }
+
     async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error>;
 }
 
@@ -498,6 +529,18 @@ impl ToolRegistry {
         self.tools.keys().filter(|k| !k.starts_with("__")).count()
     }
 
+    /// Copy of this registry with every tool, `__` variants included, passed
+    /// through `wrap` under its existing key.
+    pub fn wrapped(&self, wrap: impl Fn(Arc<dyn Tool>) -> Arc<dyn Tool>) -> Self {
+        Self {
+            tools: self
+                .tools
+                .iter()
+                .map(|(key, tool)| (key.clone(), wrap(Arc::clone(tool))))
+                .collect(),
+        }
+    }
+
     /// Create a subset registry containing only the specified tools.
     ///
     /// Tools not found in the registry are silently ignored.
@@ -636,17 +679,28 @@ impl ToolRegistry {
 /// (where `is_blocking()` returns true) are dispatched to a blocking thread
 /// via `tokio::task::spawn_blocking`, using `Handle::block_on` to drive the
 /// async `execute()` method to completion on that thread.
+///
+/// Returns [`crate::Error::Cancelled`] as soon as the run's cancellation
+/// token fires (see [`crate::cancel`]). A non-blocking tool's future is
+/// dropped; a blocking tool stops at its next [`Checkpoint`] check.
 pub async fn execute_tool_dispatch(
     tool: Arc<dyn Tool>,
     arguments: Value,
 ) -> Result<ToolOutput, crate::Error> {
+    let checkpoint = Checkpoint::current();
+    checkpoint.check()?;
     if tool.is_blocking() {
         let handle = tokio::runtime::Handle::current();
-        tokio::task::spawn_blocking(move || handle.block_on(tool.execute(arguments)))
-            .await
+        let scope = checkpoint.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            handle.block_on(scope.scope(tool.execute(arguments)))
+        });
+        checkpoint
+            .run(task)
+            .await?
             .map_err(|e| crate::Error::Unknown(format!("Blocking tool task failed: {}", e)))?
     } else {
-        tool.execute(arguments).await
+        checkpoint.run(tool.execute(arguments)).await?
     }
 }
 
@@ -0,0 +1,324 @@
+//! Token budget for the tool definitions sent with every request.
+//!
+//! Each request carries every tool's schema, and the long
+//! [`Tool::tool_description`] texts (usage examples, dos and don'ts) add up to
+//! several thousand tokens. When a registry's definitions outgrow their share
+//! of the context window, [`fit_descriptions`] sends the one-line
+//! [`Tool::description`] in their place and adds a `describe_tool` tool that
+//! returns the full text when the model needs it.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::narrowing::ScopeRetry;
+use crate::tool::{PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters, ToolRegistry};
+
+/// Share of the context window tool definitions may take before their
+/// descriptions are shortened.
+pub const DEFAULT_DESCRIPTION_SHARE: f64 = 0.05;
+
+/// Name of the tool serving full descriptions.
+pub const DESCRIBE_TOOL: &str = "describe_tool";
+
+/// Rough token count of `definitions` as serialized into a request.
+pub fn definition_tokens(definitions: &[ToolDefinition]) -> usize {
+    serde_json::to_string(definitions).map_or(0, |json| json.len() / 4)
+}
+
+/// `registry` as it should be sent to a model with `context_window` tokens:
+/// unchanged while its definitions fit in `share` of the window, otherwise
+/// with long descriptions shortened and `describe_tool` added. A `share` of
+/// 0 never shortens.
+pub fn fit_descriptions(registry: &ToolRegistry, context_window: u32, share: f64) -> ToolRegistry {
+    if share <= 0.0 || registry.get(DESCRIBE_TOOL).is_some() {
+        return registry.clone();
+    }
+    let budget = (context_window as f64 * share) as usize;
+    let full = definition_tokens(&registry.definitions());
+    if full <= budget {
+        return registry.clone();
+    }
+
+    let mut shortened: Vec<String> = registry
+        .names()
+        .into_iter()
+        .filter(|name| registry.get(name).is_some_and(has_long_description))
+        .map(String::from)
+        .collect();
+    if shortened.is_empty() {
+        return registry.clone();
+    }
+    shortened.sort();
+
+    let mut fitted = registry.wrapped(|tool| {
+        if has_long_description(tool.as_ref()) {
+            Arc::new(ShortDescriptionTool { inner: tool })
+        } else {
+            tool
+        }
+    });
+    fitted.register(Arc::new(DescribeToolTool::new(registry.clone(), &shortened)));
+    tracing::info!(
+        tokens = full,
+        budget,
+        fitted_tokens = definition_tokens(&fitted.definitions()),
+        shortened = shortened.len(),
+        "Tool definitions over budget; shortening descriptions"
+    );
+    fitted
+}
+
+fn has_long_description(tool: &dyn Tool) -> bool {
+    tool.tool_description().len() > tool.description().len()
+}
+
+/// A tool sent with its one-line `description()` as its definition text.
+struct ShortDescriptionTool {
+    inner: Arc<dyn Tool>,
+}
+
+#[async_trait]
+impl Tool for ShortDescriptionTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn display_name(&self) -> &str {
+        self.inner.display_name()
+    }
+
+    fn tool_description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        let mut definition = self.inner.definition();
+        definition.description = self.inner.description().to_string();
+        definition
+    }
+
+    fn is_blocking(&self) -> bool {
+        self.inner.is_blocking()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    fn write_targets(&self, arguments: &Value) -> Vec<String> {
+        self.inner.write_targets(arguments)
+    }
+
+    fn narrower_scope(&self, arguments: &Value, fraction: f64) -> Option<ScopeRetry> {
+        self.inner.narrower_scope(arguments, fraction)
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        self.inner.execute(arguments).await
+    }
+}
+
+/// Returns the full description of a tool whose definition was shortened.
+pub struct DescribeToolTool {
+    tools: ToolRegistry,
+    description: String,
+}
+
+impl DescribeToolTool {
+    /// Serve descriptions from `tools`, whose `shortened` tools are listed in
+    /// this tool's own description.
+    pub fn new(tools: ToolRegistry, shortened: &[String]) -> Self {
+        let description = format!(
+            "Get the full usage guide for a tool. These tools are described in one line \
+             to save context; call this before relying on details of their behavior: {}",
+            shortened.join(", ")
+        );
+        Self { tools, description }
+    }
+}
+
+#[async_trait]
+impl Tool for DescribeToolTool {
+    fn name(&self) -> &str {
+        DESCRIBE_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Show a tool's full usage guide"
+    }
+
+    fn tool_description(&self) -> &str {
+        &self.description
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new().add_property(
+                "name",
+                PropertySchema::string("Name of the tool to describe"),
+                true,
+            ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let name = arguments
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::tool(DESCRIBE_TOOL, "Missing 'name'"))?;
+        let tool = self
+            .tools
+            .names()
+            .contains(&name)
+            .then(|| self.tools.get(name))
+            .flatten();
+        Ok(match tool {
+            Some(tool) => ToolOutput::success(format!("{}\n\n{}", name, tool.tool_description())),
+            None => {
+                let mut names = self.tools.names();
+                names.sort();
+                ToolOutput::error(format!(
+                    "Unknown tool '{}'. Available: {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tool with a short summary and a long model-facing description.
+    struct Verbose(&'static str);
+
+    #[async_trait]
+    impl Tool for Verbose {
+        fn name(&self) -> &str {
+            self.0
+        }
+        fn description(&self) -> &str {
+            "Short summary"
+        }
+        fn tool_description(&self) -> &str {
+            "Short summary. Long guidance with examples, dos and don'ts that goes on \
+             for a while so that it costs real tokens on every request."
+        }
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new(self.0, self.tool_description())
+        }
+        async fn execute(&self, _: Value) -> Result<ToolOutput, Error> {
+            Ok(ToolOutput::success(format!("{} ran", self.0)))
+        }
+    }
+
+    /// A tool with nothing beyond its summary.
+    struct Terse;
+
+    #[async_trait]
+    impl Tool for Terse {
+        fn name(&self) -> &str {
+            "terse"
+        }
+        fn description(&self) -> &str {
+            "Does one thing"
+        }
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new("terse", "Does one thing")
+        }
+        async fn execute(&self, _: Value) -> Result<ToolOutput, Error> {
+            Ok(ToolOutput::success("ok"))
+        }
+    }
+
+    fn registry() -> ToolRegistry {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(Verbose("alpha")));
+        registry.register(Arc::new(Verbose("beta")));
+        registry.register(Arc::new(Terse));
+        registry
+    }
+
+    fn description_of(registry: &ToolRegistry, name: &str) -> String {
+        registry
+            .definitions()
+            .into_iter()
+            .find(|d| d.name == name)
+            .unwrap()
+            .description
+    }
+
+    #[test]
+    fn test_fitting_definitions_are_unchanged() {
+        let registry = registry();
+        let fitted = fit_descriptions(&registry, 200_000, DEFAULT_DESCRIPTION_SHARE);
+        assert_eq!(fitted.len(), 3);
+        assert!(fitted.get(DESCRIBE_TOOL).is_none());
+        assert!(description_of(&fitted, "alpha").contains("Long guidance"));
+    }
+
+    #[test]
+    fn test_zero_share_never_shortens() {
+        let fitted = fit_descriptions(&registry(), 100, 0.0);
+        assert!(fitted.get(DESCRIBE_TOOL).is_none());
+        assert!(description_of(&fitted, "alpha").contains("Long guidance"));
+    }
+
+    #[tokio::test]
+    async fn test_over_budget_shortens_and_adds_describe_tool() {
+        let fitted = fit_descriptions(&registry(), 1_000, 0.05);
+
+        assert_eq!(description_of(&fitted, "alpha"), "Short summary");
+        assert_eq!(description_of(&fitted, "terse"), "Does one thing");
+
+        let describe = description_of(&fitted, DESCRIBE_TOOL);
+        assert!(describe.ends_with("alpha, beta"), "{}", describe);
+
+        // Shortened tools still run as before
+        let tool = fitted.get_arc("beta").unwrap();
+        let output = tool.execute(Value::Null).await.unwrap();
+        assert_eq!(output.text_content(), "beta ran");
+
+        // Fitting again doesn't stack a second describe_tool
+        let refitted = fit_descriptions(&fitted, 1_000, 0.05);
+        assert_eq!(refitted.len(), fitted.len());
+    }
+
+    #[tokio::test]
+    async fn test_describe_tool_returns_full_description() {
+        let fitted = fit_descriptions(&registry(), 1_000, 0.05);
+        let describe = fitted.get_arc(DESCRIBE_TOOL).unwrap();
+
+        let output = describe
+            .execute(serde_json::json!({"name": "alpha"}))
+            .await
+            .unwrap();
+        assert!(!output.is_error);
+        assert!(output.text_content().starts_with("alpha\n\nShort summary. Long guidance"));
+
+        let output = describe
+            .execute(serde_json::json!({"name": "gamma"}))
+            .await
+            .unwrap();
+        assert!(output.is_error);
+        assert_eq!(
+            output.text_content(),
+            "Unknown tool 'gamma'. Available: alpha, beta, terse"
+        );
+
+        assert!(describe.execute(serde_json::json!({})).await.is_err());
+    }
+}
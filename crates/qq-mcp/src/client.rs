@@ -95,7 +95,13 @@ impl McpClient {
             }
         }
 
-        let transport = StreamableHttpClientTransport::from_config(config);
+        let client = http_client(&qq_core::http::network_options()).map_err(|e| {
+            McpError::Connection {
+                server: name.clone(),
+                source: e.into(),
+            }
+        })?;
+        let transport = StreamableHttpClientTransport::with_client(client, config);
         let service = ().serve(transport).await.map_err(|e| McpError::Connection {
             server: name.clone(),
             source: Box::new(e),
@@ -146,3 +152,34 @@ impl McpClient {
         }
     }
 }
+
+/// HTTP client for the streamable HTTP transport, with the `[network]` proxy
+/// and CA bundles. rmcp is built on a newer reqwest than
+/// [`qq_core::http::client_builder`] returns, so the settings are applied
+/// here from their plain form.
+fn http_client(options: &qq_core::http::NetworkOptions) -> Result<reqwest::Client, String> {
+    // Like rmcp's default client: idle pooled connections stall on delayed ACKs
+    let mut builder = reqwest::Client::builder().pool_max_idle_per_host(0);
+    if let Some(ref url) = options.proxy {
+        let mut proxy = reqwest::Proxy::all(url)
+            .map_err(|e| format!("invalid proxy URL '{}': {}", url, e))?;
+        if let Some((ref username, ref password)) = options.proxy_auth {
+            proxy = proxy.basic_auth(username, password);
+        }
+        if let Some(ref hosts) = options.no_proxy {
+            proxy = proxy.no_proxy(reqwest::NoProxy::from_string(hosts));
+        }
+        builder = builder.proxy(proxy);
+    }
+    #[cfg(any(feature = "native-tls", feature = "static-tls"))]
+    for pem in &options.ca_bundles {
+        let certs = reqwest::Certificate::from_pem_bundle(pem)
+            .map_err(|e| format!("invalid CA bundle: {}", e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    builder
+        .build()
+        .map_err(|e| format!("failed to build HTTP client: {}", e))
+}
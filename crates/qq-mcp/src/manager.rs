@@ -9,7 +9,7 @@ use crate::error::McpError;
 use crate::tool::McpTool;
 
 /// MCP server transport configuration.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 #[serde(tag = "transport", rename_all = "snake_case")]
 pub enum McpServerConfig {
     Stdio {
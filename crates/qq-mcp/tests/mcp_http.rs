@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use qq_mcp::McpClient;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// The streamable HTTP transport goes through the `[network]` proxy.
+#[tokio::test]
+async fn test_http_transport_uses_network_proxy() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy = format!("http://{}", listener.local_addr().unwrap());
+    assert!(qq_core::http::set_network_options(
+        qq_core::http::NetworkOptions {
+            proxy: Some(proxy),
+            ..Default::default()
+        }
+    ));
+
+    let proxy_task = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        socket
+            .write_all(b"HTTP/1.1 502 Bad Gateway\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    });
+
+    let result =
+        McpClient::connect_http("proxied".into(), "http://mcp.invalid/mcp", &HashMap::new()).await;
+    assert!(result.is_err());
+
+    let request = proxy_task.await.unwrap();
+    let request_line = request.lines().next().unwrap_or_default();
+    assert!(
+        request_line.contains("http://mcp.invalid/mcp"),
+        "proxy saw: {}",
+        request_line
+    );
+}
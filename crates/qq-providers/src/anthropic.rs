@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 use async_trait::async_trait;
 use futures::StreamExt;
 use reqwest::Client;
@@ -10,13 +8,16 @@ use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error, trace, warn};
 
 use qq_core::{
-    CompletionRequest, CompletionResponse, Content, ContentPart, Error, FinishReason, Message,
-    Provider, Role, StreamChunk, StreamResult, ToolCall, ToolDefinition, Usage,
+    mentions_context_overflow, CompletionRequest, CompletionResponse, Content, ContentPart, Error,
+    FileRef, FinishReason, Message, Provider, Role, StreamChunk, StreamResult, ToolCall,
+    ToolDefinition, Usage,
 };
 
 const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
 const DEFAULT_MAX_TOKENS: u32 = 8192;
+/// Beta flag for uploading files and referencing them in messages.
+const FILES_BETA: &str = "files-api-2025-04-14";
 
 /// Parse Anthropic's `stop_reason` string into the cross-provider enum.
 /// Returns `None` for unknown values so callers can decide on a default.
@@ -40,20 +41,23 @@ pub struct AnthropicProvider {
 }
 
 impl AnthropicProvider {
-    pub fn new(api_key: impl Into<String>) -> Self {
-        let client = Client::builder()
-            .connect_timeout(Duration::from_secs(30))
-            .build()
-            .unwrap_or_else(|_| Client::new());
-        Self {
-            client,
+    pub fn new(api_key: impl Into<String>) -> Result<Self, Error> {
+        Ok(Self {
+            client: crate::http_client()?,
             api_key: api_key.into(),
             base_url: DEFAULT_BASE_URL.to_string(),
             default_model: None,
             include_tool_reasoning: true,
             context_window: None,
             supported_content_types: None,
-        }
+        })
+    }
+
+    /// Send requests through `client`, e.g. one with this provider's own
+    /// proxy or CA certificates.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
     }
 
     pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
@@ -216,6 +220,17 @@ impl AnthropicProvider {
                                 },
                             });
                         }
+                        ContentPart::Document { document } => {
+                            blocks.push(match AnthropicDocumentSource::for_file(document) {
+                                Some(source) => AnthropicContentBlock::Document {
+                                    source,
+                                    title: Some(document.filename.clone()),
+                                },
+                                None => AnthropicContentBlock::Text {
+                                    text: document.placeholder(),
+                                },
+                            });
+                        }
                         _ => {} // ToolUse/ToolResult handled elsewhere
                     }
                 }
@@ -273,6 +288,17 @@ impl AnthropicProvider {
                                 },
                             });
                         }
+                        ContentPart::Document { document } => {
+                            blocks.push(match AnthropicDocumentSource::for_file(document) {
+                                Some(source) => AnthropicToolResultContent::Document {
+                                    source,
+                                    title: Some(document.filename.clone()),
+                                },
+                                None => AnthropicToolResultContent::Text {
+                                    text: document.placeholder(),
+                                },
+                            });
+                        }
                         _ => {}
                     }
                 }
@@ -349,7 +375,24 @@ impl AnthropicProvider {
         })
     }
 
-    fn parse_error(&self, status: u16, body: &str) -> Error {
+    /// POST to the messages endpoint, opting into the files beta when the
+    /// request references uploaded files.
+    fn messages_request(&self, api_request: &AnthropicRequest) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(api_request);
+        if api_request.references_files() {
+            builder.header("anthropic-beta", FILES_BETA)
+        } else {
+            builder
+        }
+    }
+
+    fn parse_error(status: u16, body: &str) -> Error {
         #[derive(Deserialize)]
         struct ErrorResponse {
             error: ErrorDetail,
@@ -359,11 +402,17 @@ impl AnthropicProvider {
         struct ErrorDetail {
             message: String,
             #[serde(rename = "type")]
-            #[allow(dead_code)]
             error_type: Option<String>,
         }
 
         if let Ok(err) = serde_json::from_str::<ErrorResponse>(body) {
+            // "prompt is too long: N tokens > M maximum" (400), or the whole
+            // request body over the size limit (413 request_too_large)
+            let overflow = err.error.error_type.as_deref() == Some("request_too_large")
+                || (status == 400 && mentions_context_overflow(&err.error.message));
+            if overflow {
+                return Error::context_overflow(err.error.message);
+            }
             match status {
                 401 => Error::auth(err.error.message),
                 429 => Error::rate_limit(err.error.message),
@@ -414,6 +463,69 @@ impl Provider for AnthropicProvider {
         })
     }
 
+    fn supports_file_upload(&self) -> bool {
+        true
+    }
+
+    async fn upload_file(
+        &self,
+        filename: &str,
+        media_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        // Document blocks read PDFs and plain text, so other text formats
+        // (CSV, JSON, markdown) go up as plain text
+        let media_type = if media_type == "application/pdf" {
+            media_type
+        } else if crate::is_text_media_type(media_type) {
+            "text/plain"
+        } else {
+            return Err(Error::invalid_request(format!(
+                "Anthropic reads uploaded PDFs and text files, not {}",
+                media_type
+            )));
+        };
+        let size = bytes.len() as u64;
+        let part = reqwest::multipart::Part::bytes(bytes)
+            .file_name(filename.to_string())
+            .mime_str(media_type)
+            .map_err(|e| Error::invalid_request(e.to_string()))?;
+        let form = reqwest::multipart::Form::new().part("file", part);
+
+        debug!(filename, media_type, size, "Anthropic file upload");
+
+        let response = self
+            .client
+            .post(format!("{}/files", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("anthropic-beta", FILES_BETA)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| Error::network(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            error!(status = status.as_u16(), body = %error_text, "Anthropic file upload failed");
+            return Err(Self::parse_error(status.as_u16(), &error_text));
+        }
+
+        let file: AnthropicFile = response
+            .json()
+            .await
+            .map_err(|e| Error::serialization(e.to_string()))?;
+
+        Ok(FileRef {
+            provider: self.name().to_string(),
+            id: file.id,
+            filename: filename.to_string(),
+            media_type: media_type.to_string(),
+            size,
+        })
+    }
+
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, Error> {
         let mut req = request;
         req.stream = false;
@@ -429,12 +541,7 @@ impl Provider for AnthropicProvider {
         trace!(request = %serde_json::to_string(&api_request).unwrap_or_default(), "Anthropic request payload");
 
         let response = self
-            .client
-            .post(format!("{}/messages", self.base_url))
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", ANTHROPIC_VERSION)
-            .header("Content-Type", "application/json")
-            .json(&api_request)
+            .messages_request(&api_request)
             .send()
             .await
             .map_err(|e| Error::network(e.to_string()))?;
@@ -443,7 +550,7 @@ impl Provider for AnthropicProvider {
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
             error!(status = status.as_u16(), body = %error_text, "Anthropic request failed");
-            return Err(self.parse_error(status.as_u16(), &error_text));
+            return Err(Self::parse_error(status.as_u16(), &error_text));
         }
 
         let response_text = response
@@ -485,13 +592,7 @@ impl Provider for AnthropicProvider {
         );
         trace!(request = %serde_json::to_string(&api_request).unwrap_or_default(), "Anthropic stream request payload");
 
-        let request_builder = self
-            .client
-            .post(format!("{}/messages", self.base_url))
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", ANTHROPIC_VERSION)
-            .header("Content-Type", "application/json")
-            .json(&api_request);
+        let request_builder = self.messages_request(&api_request);
 
         let es = EventSource::new(request_builder).map_err(|e| Error::stream(e.to_string()))?;
 
@@ -594,6 +695,14 @@ impl Provider for AnthropicProvider {
                             }
                         }
                     }
+                    Err(reqwest_eventsource::Error::InvalidStatusCode(status, response)) => {
+                        let error_text = response.text().await.unwrap_or_default();
+                        error!(status = status.as_u16(), body = %error_text, "Anthropic stream request failed");
+                        let _ = tx
+                            .send(Err(Self::parse_error(status.as_u16(), &error_text)))
+                            .await;
+                        break;
+                    }
                     Err(e) => {
                         error!(error = ?e, "Anthropic SSE error");
                         let _ = tx
@@ -648,6 +757,22 @@ struct AnthropicRequest {
     tools: Option<Vec<AnthropicTool>>,
 }
 
+impl AnthropicRequest {
+    /// Whether any block references an uploaded file.
+    fn references_files(&self) -> bool {
+        self.messages
+            .iter()
+            .flat_map(|message| &message.content)
+            .any(|block| match block {
+                AnthropicContentBlock::Document { .. } => true,
+                AnthropicContentBlock::ToolResult { content, .. } => content
+                    .iter()
+                    .any(|c| matches!(c, AnthropicToolResultContent::Document { .. })),
+                _ => false,
+            })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct AnthropicMessage {
     role: String,
@@ -663,6 +788,11 @@ enum AnthropicContentBlock {
     Image {
         source: AnthropicImageSource,
     },
+    Document {
+        source: AnthropicDocumentSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
     ToolUse {
         id: String,
         name: String,
@@ -683,6 +813,11 @@ enum AnthropicContentBlock {
 enum AnthropicToolResultContent {
     Text { text: String },
     Image { source: AnthropicImageSource },
+    Document {
+        source: AnthropicDocumentSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -693,6 +828,24 @@ struct AnthropicImageSource {
     data: String,
 }
 
+/// An uploaded file referenced from a document block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AnthropicDocumentSource {
+    #[serde(rename = "type")]
+    source_type: String,
+    file_id: String,
+}
+
+impl AnthropicDocumentSource {
+    /// Source for `file`, or `None` when it was uploaded to another provider.
+    fn for_file(file: &FileRef) -> Option<Self> {
+        (file.provider == "anthropic").then(|| Self {
+            source_type: "file".to_string(),
+            file_id: file.id.clone(),
+        })
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct AnthropicTool {
     name: String,
@@ -710,6 +863,11 @@ struct AnthropicResponse {
     usage: AnthropicUsage,
 }
 
+#[derive(Debug, Deserialize)]
+struct AnthropicFile {
+    id: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct AnthropicUsage {
     input_tokens: u32,
@@ -774,9 +932,16 @@ struct MessageDeltaUsage {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_request_serialization_golden() {
+        let provider = AnthropicProvider::new("test-key").unwrap();
+        let body = provider.build_request(&qq_core::testing::golden_request());
+        qq_core::assert_snapshot!("anthropic_request", serde_json::to_string_pretty(&body).unwrap());
+    }
+
     #[test]
     fn test_provider_creation() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         assert_eq!(provider.name(), "anthropic");
         assert_eq!(provider.default_model(), None);
     }
@@ -784,6 +949,7 @@ mod tests {
     #[test]
     fn test_provider_with_custom_url() {
         let provider = AnthropicProvider::new("test-key")
+            .unwrap()
             .with_base_url("https://custom.proxy.com/v1");
         assert_eq!(provider.base_url, "https://custom.proxy.com/v1");
     }
@@ -791,6 +957,7 @@ mod tests {
     #[test]
     fn test_provider_with_custom_model() {
         let provider = AnthropicProvider::new("test-key")
+            .unwrap()
             .with_default_model("claude-sonnet-4-20250514");
         assert_eq!(provider.default_model(), Some("claude-sonnet-4-20250514"));
     }
@@ -798,6 +965,7 @@ mod tests {
     #[test]
     fn test_build_request_basic() {
         let provider = AnthropicProvider::new("test-key")
+            .unwrap()
             .with_default_model("claude-sonnet-4-20250514");
         let request = CompletionRequest::new(vec![Message::user("Hello")]);
         let api_request = provider.build_request(&request);
@@ -811,7 +979,7 @@ mod tests {
 
     #[test]
     fn test_build_request_system_extraction() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let request = CompletionRequest::new(vec![
             Message::system("You are helpful."),
             Message::user("Hello"),
@@ -826,7 +994,7 @@ mod tests {
 
     #[test]
     fn test_build_request_tool_conversion() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let tool = ToolDefinition::new("test_tool", "A test tool");
         let request = CompletionRequest::new(vec![Message::user("Use tool")])
             .with_tools(vec![tool]);
@@ -841,7 +1009,7 @@ mod tests {
 
     #[test]
     fn test_build_request_max_tokens_override() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let request = CompletionRequest::new(vec![Message::user("Hello")])
             .with_max_tokens(4096);
         let api_request = provider.build_request(&request);
@@ -878,7 +1046,7 @@ mod tests {
 
     #[test]
     fn test_parse_response_text() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let response = AnthropicResponse {
             model: "claude-sonnet-4-20250514".to_string(),
             content: vec![AnthropicContentBlock::Text {
@@ -900,7 +1068,7 @@ mod tests {
 
     #[test]
     fn test_parse_response_tool_calls() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let response = AnthropicResponse {
             model: "claude-sonnet-4-20250514".to_string(),
             content: vec![
@@ -928,7 +1096,7 @@ mod tests {
 
     #[test]
     fn test_parse_response_thinking() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let response = AnthropicResponse {
             model: "claude-sonnet-4-20250514".to_string(),
             content: vec![
@@ -953,23 +1121,35 @@ mod tests {
 
     #[test]
     fn test_parse_error_auth() {
-        let provider = AnthropicProvider::new("test-key");
         let body = r#"{"error": {"type": "authentication_error", "message": "Invalid API key"}}"#;
-        let err = provider.parse_error(401, body);
+        let err = AnthropicProvider::parse_error(401, body);
         assert!(err.is_auth_error());
     }
 
     #[test]
     fn test_parse_error_rate_limit() {
-        let provider = AnthropicProvider::new("test-key");
         let body = r#"{"error": {"type": "rate_limit_error", "message": "Too many requests"}}"#;
-        let err = provider.parse_error(429, body);
+        let err = AnthropicProvider::parse_error(429, body);
         assert!(err.is_retryable());
     }
 
+    #[test]
+    fn test_parse_error_context_overflow() {
+        let body = r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "prompt is too long: 215000 tokens > 200000 maximum"}}"#;
+        let err = AnthropicProvider::parse_error(400, body);
+        assert!(err.is_context_overflow());
+        assert!(!err.is_retryable());
+
+        let body = r#"{"type": "error", "error": {"type": "request_too_large", "message": "Request exceeds the maximum allowed number of bytes."}}"#;
+        assert!(AnthropicProvider::parse_error(413, body).is_context_overflow());
+
+        let body = r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "messages: roles must alternate"}}"#;
+        assert!(!AnthropicProvider::parse_error(400, body).is_context_overflow());
+    }
+
     #[test]
     fn test_tool_result_as_user_message() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let messages = vec![
             Message::user("Use a tool"),
             Message::assistant_with_tool_calls(
@@ -988,9 +1168,47 @@ mod tests {
         assert_eq!(api_request.messages[2].role, "user"); // tool result
     }
 
+    #[test]
+    fn test_uploaded_documents() {
+        let provider = AnthropicProvider::new("test-key").unwrap();
+        let file = |provider: &str| FileRef {
+            provider: provider.to_string(),
+            id: "file_011".to_string(),
+            filename: "report.pdf".to_string(),
+            media_type: "application/pdf".to_string(),
+            size: 4096,
+        };
+        let request = CompletionRequest::new(vec![Message::user(vec![
+            qq_core::TypedContent::text("Summarize"),
+            qq_core::TypedContent::document(file("anthropic")),
+            qq_core::TypedContent::document(file("openai")),
+        ])]);
+        let api_request = provider.build_request(&request);
+        assert!(api_request.references_files());
+
+        let body = serde_json::to_value(&api_request).unwrap();
+        let blocks = &body["messages"][0]["content"];
+        assert_eq!(
+            blocks[1],
+            serde_json::json!({
+                "type": "document",
+                "source": {"type": "file", "file_id": "file_011"},
+                "title": "report.pdf"
+            })
+        );
+        // Another provider's file cannot be read here
+        assert_eq!(
+            blocks[2],
+            serde_json::json!({"type": "text", "text": "[Document: report.pdf, application/pdf, ~4 KB]"})
+        );
+
+        let plain = provider.build_request(&CompletionRequest::new(vec![Message::user("Hi")]));
+        assert!(!plain.references_files());
+    }
+
     #[test]
     fn test_available_models() {
-        let provider = AnthropicProvider::new("test-key");
+        let provider = AnthropicProvider::new("test-key").unwrap();
         let models = provider.available_models();
         assert!(models.contains(&"claude-sonnet-4-20250514"));
         assert!(models.contains(&"claude-opus-4-20250514"));
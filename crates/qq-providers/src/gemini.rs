@@ -10,12 +10,18 @@ use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error, trace, warn};
 
 use qq_core::{
-    CompletionRequest, CompletionResponse, Content, ContentPart, Error, FinishReason, Message,
-    Provider, Role, StreamChunk, StreamResult, ToolCall, ToolDefinition, Usage,
+    mentions_context_overflow, CompletionRequest, CompletionResponse, Content, ContentPart, Error,
+    FileRef, FinishReason, Message, Provider, Role, StreamChunk, StreamResult, ToolCall,
+    ToolDefinition, Usage,
 };
 
 const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
 
+/// How often, and how many times, to check whether an uploaded file has
+/// finished processing.
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const FILE_POLL_ATTEMPTS: u32 = 60;
+
 /// Parse Gemini's `finishReason` string into the cross-provider enum.
 /// Returns `None` for unknown values so callers can decide on a default.
 fn parse_finish_reason(reason: Option<&str>) -> Option<FinishReason> {
@@ -38,20 +44,23 @@ pub struct GeminiProvider {
 }
 
 impl GeminiProvider {
-    pub fn new(api_key: impl Into<String>) -> Self {
-        let client = Client::builder()
-            .connect_timeout(Duration::from_secs(30))
-            .build()
-            .unwrap_or_else(|_| Client::new());
-        Self {
-            client,
+    pub fn new(api_key: impl Into<String>) -> Result<Self, Error> {
+        Ok(Self {
+            client: crate::http_client()?,
             api_key: api_key.into(),
             base_url: DEFAULT_BASE_URL.to_string(),
             default_model: None,
             include_tool_reasoning: true,
             context_window: None,
             supported_content_types: None,
-        }
+        })
+    }
+
+    /// Send requests through `client`, e.g. one with this provider's own
+    /// proxy or CA certificates.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
     }
 
     pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
@@ -155,14 +164,24 @@ impl GeminiProvider {
 
                     let response_value = serde_json::json!({ "result": result_text });
 
+                    // Function responses carry text only; files the tool
+                    // attached follow as their own parts
+                    let mut parts = vec![GeminiPart::FunctionResponse {
+                        function_response: GeminiFunctionResponse {
+                            name: fn_name,
+                            response: response_value,
+                        },
+                    }];
+                    if let Content::Parts(content_parts) = &msg.content {
+                        parts.extend(content_parts.iter().filter_map(|part| match part {
+                            ContentPart::Document { document } => Some(document_part(document)),
+                            _ => None,
+                        }));
+                    }
+
                     contents.push(GeminiContent {
                         role: Some("user".to_string()),
-                        parts: vec![GeminiPart::FunctionResponse {
-                            function_response: GeminiFunctionResponse {
-                                name: fn_name,
-                                response: response_value,
-                            },
-                        }],
+                        parts,
                     });
                 }
             }
@@ -228,6 +247,9 @@ impl GeminiProvider {
                                 },
                             });
                         }
+                        ContentPart::Document { document } => {
+                            gemini_parts.push(document_part(document));
+                        }
                         _ => {}
                     }
                 }
@@ -336,7 +358,7 @@ impl GeminiProvider {
         })
     }
 
-    fn parse_error(&self, status: u16, body: &str) -> Error {
+    fn parse_error(status: u16, body: &str) -> Error {
         #[derive(Deserialize)]
         struct ErrorResponse {
             error: ErrorDetail,
@@ -350,6 +372,11 @@ impl GeminiProvider {
         }
 
         if let Ok(err) = serde_json::from_str::<ErrorResponse>(body) {
+            // INVALID_ARGUMENT: "The input token count (N) exceeds the maximum
+            // number of tokens allowed (M)."
+            if status == 400 && mentions_context_overflow(&err.error.message) {
+                return Error::context_overflow(err.error.message);
+            }
             match status {
                 401 | 403 => Error::auth(err.error.message),
                 429 => Error::rate_limit(err.error.message),
@@ -362,6 +389,37 @@ impl GeminiProvider {
     }
 }
 
+/// Part referencing an uploaded file, or its placeholder when the file was
+/// uploaded to another provider.
+fn document_part(document: &FileRef) -> GeminiPart {
+    if document.provider == "gemini" {
+        GeminiPart::FileData {
+            file_data: GeminiFileData {
+                mime_type: document.media_type.clone(),
+                file_uri: document.id.clone(),
+            },
+        }
+    } else {
+        GeminiPart::Text {
+            text: document.placeholder(),
+        }
+    }
+}
+
+/// Media upload endpoint for `base_url`: the same API version under `/upload`.
+fn upload_url(base_url: &str) -> String {
+    let path_start = base_url
+        .find("://")
+        .map(|scheme| scheme + 3)
+        .and_then(|host| base_url[host..].find('/').map(|path| host + path))
+        .unwrap_or(base_url.len());
+    format!(
+        "{}/upload{}/files",
+        &base_url[..path_start],
+        &base_url[path_start..]
+    )
+}
+
 /// Find the function name for a given tool_call_id by searching previous messages
 fn find_function_name_by_id(
     _contents: &[GeminiContent],
@@ -449,7 +507,7 @@ impl Provider for GeminiProvider {
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
             error!(status = status.as_u16(), body = %error_text, "Gemini request failed");
-            return Err(self.parse_error(status.as_u16(), &error_text));
+            return Err(Self::parse_error(status.as_u16(), &error_text));
         }
 
         let response_text = response
@@ -594,6 +652,14 @@ impl Provider for GeminiProvider {
                         debug!("Gemini SSE stream ended");
                         break;
                     }
+                    Err(reqwest_eventsource::Error::InvalidStatusCode(status, response)) => {
+                        let error_text = response.text().await.unwrap_or_default();
+                        error!(status = status.as_u16(), body = %error_text, "Gemini stream request failed");
+                        let _ = tx
+                            .send(Err(Self::parse_error(status.as_u16(), &error_text)))
+                            .await;
+                        break;
+                    }
                     Err(e) => {
                         error!(error = ?e, "Gemini SSE error");
                         let _ = tx
@@ -609,6 +675,124 @@ impl Provider for GeminiProvider {
         Ok(Box::pin(stream) as StreamResult)
     }
 
+    async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        debug!(model = %model, inputs = inputs.len(), "Gemini embedding request");
+
+        let request = serde_json::json!({
+            "requests": inputs
+                .iter()
+                .map(|text| serde_json::json!({
+                    "model": format!("models/{}", model),
+                    "content": { "parts": [{ "text": text }] },
+                }))
+                .collect::<Vec<_>>(),
+        });
+        let url = format!(
+            "{}/models/{}:batchEmbedContents?key={}",
+            self.base_url, model, self.api_key
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| Error::network(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            error!(status = status.as_u16(), body = %error_text, "Gemini embedding request failed");
+            return Err(Self::parse_error(status.as_u16(), &error_text));
+        }
+
+        let api_response: GeminiEmbedResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::serialization(e.to_string()))?;
+        if api_response.embeddings.len() != inputs.len() {
+            return Err(Error::serialization(format!(
+                "expected {} embeddings, got {}",
+                inputs.len(),
+                api_response.embeddings.len()
+            )));
+        }
+        Ok(api_response.embeddings.into_iter().map(|e| e.values).collect())
+    }
+
+    fn supports_file_upload(&self) -> bool {
+        true
+    }
+
+    async fn upload_file(
+        &self,
+        filename: &str,
+        media_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        let size = bytes.len() as u64;
+        debug!(filename, media_type, size, "Gemini file upload");
+
+        let response = self
+            .client
+            .post(format!("{}?key={}", upload_url(&self.base_url), self.api_key))
+            .header("X-Goog-Upload-Protocol", "raw")
+            .header("Content-Type", media_type)
+            .body(bytes)
+            .send()
+            .await
+            .map_err(|e| Error::network(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            error!(status = status.as_u16(), body = %error_text, "Gemini file upload failed");
+            return Err(Self::parse_error(status.as_u16(), &error_text));
+        }
+
+        let mut file = response
+            .json::<GeminiUploadResponse>()
+            .await
+            .map_err(|e| Error::serialization(e.to_string()))?
+            .file;
+
+        // Documents are processed after upload and can't be used until active
+        let mut attempts = 0;
+        while file.state.as_deref() == Some("PROCESSING") {
+            attempts += 1;
+            if attempts > FILE_POLL_ATTEMPTS {
+                return Err(Error::Timeout(format!(
+                    "{} was still processing after {}s",
+                    filename,
+                    (FILE_POLL_INTERVAL * FILE_POLL_ATTEMPTS).as_secs()
+                )));
+            }
+            tokio::time::sleep(FILE_POLL_INTERVAL).await;
+            file = self
+                .client
+                .get(format!("{}/{}?key={}", self.base_url, file.name, self.api_key))
+                .send()
+                .await
+                .map_err(|e| Error::network(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| Error::serialization(e.to_string()))?;
+        }
+        if file.state.as_deref() == Some("FAILED") {
+            return Err(Error::api(500, format!("Gemini could not process {}", filename)));
+        }
+
+        Ok(FileRef {
+            provider: self.name().to_string(),
+            id: file.uri,
+            filename: filename.to_string(),
+            media_type: file.mime_type.unwrap_or_else(|| media_type.to_string()),
+            size,
+        })
+    }
+
     fn available_models(&self) -> Vec<&str> {
         vec![
             "gemini-2.5-pro",
@@ -654,6 +838,10 @@ enum GeminiPart {
         #[serde(rename = "inlineData")]
         inline_data: GeminiInlineData,
     },
+    FileData {
+        #[serde(rename = "fileData")]
+        file_data: GeminiFileData,
+    },
     Text {
         text: String,
     },
@@ -666,6 +854,13 @@ struct GeminiInlineData {
     data: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiFileData {
+    mime_type: String,
+    file_uri: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct GeminiFunctionCall {
     name: String,
@@ -748,15 +943,49 @@ struct GeminiPromptFeedback {
     block_reason: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct GeminiUploadResponse {
+    file: GeminiFile,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiFile {
+    name: String,
+    uri: String,
+    #[serde(default)]
+    mime_type: Option<String>,
+    #[serde(default)]
+    state: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<GeminiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiEmbedding {
+    values: Vec<f32>,
+}
+
 // ── Tests ────────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_request_serialization_golden() {
+        let provider = GeminiProvider::new("test-key").unwrap();
+        let body = provider.build_request(&qq_core::testing::golden_request());
+        qq_core::assert_snapshot!("gemini_request", serde_json::to_string_pretty(&body).unwrap());
+    }
+
     #[test]
     fn test_provider_creation() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         assert_eq!(provider.name(), "gemini");
         assert_eq!(provider.default_model(), None);
     }
@@ -764,6 +993,7 @@ mod tests {
     #[test]
     fn test_provider_with_custom_url() {
         let provider = GeminiProvider::new("test-key")
+            .unwrap()
             .with_base_url("https://custom.proxy.com/v1beta");
         assert_eq!(provider.base_url, "https://custom.proxy.com/v1beta");
     }
@@ -771,6 +1001,7 @@ mod tests {
     #[test]
     fn test_provider_with_custom_model() {
         let provider = GeminiProvider::new("test-key")
+            .unwrap()
             .with_default_model("gemini-2.5-pro");
         assert_eq!(provider.default_model(), Some("gemini-2.5-pro"));
     }
@@ -778,6 +1009,7 @@ mod tests {
     #[test]
     fn test_build_request_basic() {
         let provider = GeminiProvider::new("test-key")
+            .unwrap()
             .with_default_model("gemini-2.5-flash");
         let request = CompletionRequest::new(vec![Message::user("Hello")]);
         let api_request = provider.build_request(&request);
@@ -790,7 +1022,7 @@ mod tests {
 
     #[test]
     fn test_build_request_system_instruction() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let request = CompletionRequest::new(vec![
             Message::system("You are helpful."),
             Message::user("Hello"),
@@ -809,7 +1041,7 @@ mod tests {
 
     #[test]
     fn test_build_request_tool_conversion() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let tool = ToolDefinition::new("test_tool", "A test tool");
         let request = CompletionRequest::new(vec![Message::user("Use tool")])
             .with_tools(vec![tool]);
@@ -824,7 +1056,7 @@ mod tests {
 
     #[test]
     fn test_build_request_assistant_role_mapping() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let request = CompletionRequest::new(vec![
             Message::user("Hello"),
             Message::assistant("Hi there"),
@@ -840,7 +1072,7 @@ mod tests {
 
     #[test]
     fn test_build_request_tool_result_as_user() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let messages = vec![
             Message::user("Search for rust"),
             Message::assistant_with_tool_calls(
@@ -859,7 +1091,7 @@ mod tests {
 
     #[test]
     fn test_parse_response_text() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let response = GeminiResponse {
             candidates: Some(vec![GeminiCandidate {
                 content: Some(GeminiContent {
@@ -887,7 +1119,7 @@ mod tests {
 
     #[test]
     fn test_parse_response_tool_calls() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let response = GeminiResponse {
             candidates: Some(vec![GeminiCandidate {
                 content: Some(GeminiContent {
@@ -918,7 +1150,7 @@ mod tests {
 
     #[test]
     fn test_parse_response_empty_candidates() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let response = GeminiResponse {
             candidates: None,
             usage_metadata: None,
@@ -934,20 +1166,24 @@ mod tests {
 
     #[test]
     fn test_parse_error_auth() {
-        let provider = GeminiProvider::new("test-key");
         let body = r#"{"error": {"message": "API key not valid", "status": "PERMISSION_DENIED"}}"#;
-        let err = provider.parse_error(403, body);
+        let err = GeminiProvider::parse_error(403, body);
         assert!(err.is_auth_error());
     }
 
     #[test]
     fn test_parse_error_rate_limit() {
-        let provider = GeminiProvider::new("test-key");
         let body = r#"{"error": {"message": "Quota exceeded", "status": "RESOURCE_EXHAUSTED"}}"#;
-        let err = provider.parse_error(429, body);
+        let err = GeminiProvider::parse_error(429, body);
         assert!(err.is_retryable());
     }
 
+    #[test]
+    fn test_parse_error_context_overflow() {
+        let body = r#"{"error": {"code": 400, "message": "The input token count (1200000) exceeds the maximum number of tokens allowed (1048576).", "status": "INVALID_ARGUMENT"}}"#;
+        assert!(GeminiProvider::parse_error(400, body).is_context_overflow());
+    }
+
     #[test]
     fn test_merge_adjacent_contents() {
         let contents = vec![
@@ -977,9 +1213,52 @@ mod tests {
         assert_eq!(merged[1].role, Some("model".to_string()));
     }
 
+    #[test]
+    fn test_uploaded_documents() {
+        assert_eq!(
+            upload_url(DEFAULT_BASE_URL),
+            "https://generativelanguage.googleapis.com/upload/v1beta/files"
+        );
+
+        let provider = GeminiProvider::new("test-key").unwrap();
+        let document = FileRef {
+            provider: "gemini".to_string(),
+            id: "https://generativelanguage.googleapis.com/v1beta/files/abc".to_string(),
+            filename: "data.csv".to_string(),
+            media_type: "text/csv".to_string(),
+            size: 100,
+        };
+        let request = CompletionRequest::new(vec![
+            Message::user(vec![
+                qq_core::TypedContent::text("Plot this"),
+                qq_core::TypedContent::document(document.clone()),
+            ]),
+            Message::assistant_with_tool_calls(
+                "",
+                vec![ToolCall::new("tc_1", "attach_document", serde_json::json!({}))],
+            ),
+            Message::tool_result(
+                "tc_1",
+                vec![
+                    qq_core::TypedContent::text("Attached data.csv"),
+                    qq_core::TypedContent::document(document),
+                ],
+            ),
+        ]);
+        let body = serde_json::to_value(provider.build_request(&request)).unwrap();
+        let file_part = serde_json::json!({"fileData": {
+            "mimeType": "text/csv",
+            "fileUri": "https://generativelanguage.googleapis.com/v1beta/files/abc"
+        }});
+        assert_eq!(body["contents"][0]["parts"][1], file_part);
+        // The tool's file follows its function response
+        assert!(body["contents"][2]["parts"][0].get("functionResponse").is_some());
+        assert_eq!(body["contents"][2]["parts"][1], file_part);
+    }
+
     #[test]
     fn test_available_models() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let models = provider.available_models();
         assert!(models.contains(&"gemini-2.5-pro"));
         assert!(models.contains(&"gemini-2.5-flash"));
@@ -988,7 +1267,7 @@ mod tests {
 
     #[test]
     fn test_resolve_model_default() {
-        let provider = GeminiProvider::new("test-key");
+        let provider = GeminiProvider::new("test-key").unwrap();
         let request = CompletionRequest::new(vec![Message::user("Hello")]);
         assert_eq!(provider.resolve_model(&request), "gemini-2.5-flash");
     }
@@ -996,6 +1275,7 @@ mod tests {
     #[test]
     fn test_resolve_model_from_provider() {
         let provider = GeminiProvider::new("test-key")
+            .unwrap()
             .with_default_model("gemini-2.5-pro");
         let request = CompletionRequest::new(vec![Message::user("Hello")]);
         assert_eq!(provider.resolve_model(&request), "gemini-2.5-pro");
@@ -1004,6 +1284,7 @@ mod tests {
     #[test]
     fn test_resolve_model_from_request() {
         let provider = GeminiProvider::new("test-key")
+            .unwrap()
             .with_default_model("gemini-2.5-pro");
         let request = CompletionRequest::new(vec![Message::user("Hello")])
             .with_model("gemini-2.0-flash");
@@ -6,11 +6,22 @@ pub mod anthropic;
 pub mod context_windows;
 pub mod gemini;
 pub mod openai;
+pub mod pricing;
+mod tool_stream;
 
 pub use anthropic::AnthropicProvider;
 pub use gemini::GeminiProvider;
 pub use openai::OpenAIProvider;
 
+/// HTTP client for a provider, with the `[network]` settings. Fails rather
+/// than falling back to a plain client, which would bypass a configured proxy.
+pub(crate) fn http_client() -> Result<reqwest::Client, qq_core::Error> {
+    qq_core::http::client_builder()
+        .connect_timeout(qq_core::http::PROVIDER_CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| qq_core::Error::config(format!("Failed to build the HTTP client: {}", e)))
+}
+
 /// Check if image content is supported based on the provider's supported_content_types.
 pub fn supports_images(supported_types: &Option<Vec<String>>) -> bool {
     match supported_types {
@@ -19,6 +30,12 @@ pub fn supports_images(supported_types: &Option<Vec<String>>) -> bool {
     }
 }
 
+/// Whether a file of `media_type` holds text (datasets, markup, source).
+pub fn is_text_media_type(media_type: &str) -> bool {
+    media_type.starts_with("text/")
+        || matches!(media_type, "application/json" | "application/xml")
+}
+
 /// Check if content contains any image parts.
 pub fn content_has_images(content: &qq_core::Content) -> bool {
     match content {
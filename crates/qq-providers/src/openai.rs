@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 use async_trait::async_trait;
 use futures::StreamExt;
 use reqwest::Client;
@@ -10,10 +8,13 @@ use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error, trace, warn};
 
 use qq_core::{
-    CompletionRequest, CompletionResponse, Content, ContentPart, Error, FinishReason, Message,
-    Provider, Role, StreamChunk, StreamResult, ToolCall, ToolDefinition, Usage,
+    mentions_context_overflow, CompletionRequest, CompletionResponse, Content, ContentPart, Error,
+    FileRef, FinishReason, Message, Provider, Role, StreamChunk, StreamResult, ToolCall,
+    ToolDefinition, Usage,
 };
 
+use crate::tool_stream::{ToolCallAssembler, ToolCallFragment};
+
 const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 
 /// Parse OpenAI's `finish_reason` string into the cross-provider enum.
@@ -39,20 +40,23 @@ pub struct OpenAIProvider {
 }
 
 impl OpenAIProvider {
-    pub fn new(api_key: impl Into<String>) -> Self {
-        let client = Client::builder()
-            .connect_timeout(Duration::from_secs(30))
-            .build()
-            .unwrap_or_else(|_| Client::new());
-        Self {
-            client,
+    pub fn new(api_key: impl Into<String>) -> Result<Self, Error> {
+        Ok(Self {
+            client: crate::http_client()?,
             api_key: api_key.into(),
             base_url: DEFAULT_BASE_URL.to_string(),
             default_model: None,
             include_tool_reasoning: true,
             context_window: None,
             supported_content_types: None,
-        }
+        })
+    }
+
+    /// Send requests through `client`, e.g. one with this provider's own
+    /// proxy or CA certificates.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
     }
 
     pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
@@ -218,6 +222,16 @@ impl OpenAIProvider {
                                 url: format!("data:{};base64,{}", image.media_type, image.data),
                             },
                         }),
+                        ContentPart::Document { document } if document.provider == "openai" => {
+                            Some(OpenAIContentBlock::File {
+                                file: OpenAIFileInput {
+                                    file_id: document.id.clone(),
+                                },
+                            })
+                        }
+                        ContentPart::Document { document } => Some(OpenAIContentBlock::Text {
+                            text: document.placeholder(),
+                        }),
                         _ => None,
                     })
                     .collect();
@@ -345,7 +359,7 @@ impl Provider for OpenAIProvider {
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
             error!(status = status.as_u16(), body = %error_text, "LLM request failed");
-            return Err(self.parse_error(status.as_u16(), &error_text));
+            return Err(Self::parse_error(status.as_u16(), &error_text));
         }
 
         let response_text = response
@@ -400,6 +414,8 @@ impl Provider for OpenAIProvider {
 
         tokio::spawn(async move {
             let mut es = es;
+            // Streamed tool-call fragments, reordered into one call at a time
+            let mut tool_calls_in = ToolCallAssembler::default();
 
             while let Some(event) = es.next().await {
                 match event {
@@ -409,6 +425,9 @@ impl Provider for OpenAIProvider {
                     Ok(Event::Message(msg)) => {
                         if msg.data == "[DONE]" {
                             debug!("SSE stream complete");
+                            for chunk in tool_calls_in.finish() {
+                                let _ = tx.send(Ok(chunk)).await;
+                            }
                             let _ = tx.send(Ok(StreamChunk::Done { usage: None, finish_reason: None })).await;
                             break;
                         }
@@ -445,31 +464,11 @@ impl Provider for OpenAIProvider {
 
                                     if let Some(tool_calls) = choice.delta.tool_calls {
                                         for tc in tool_calls {
-                                            if let Some(id) = tc.id {
-                                                let name =
-                                                    tc.function.as_ref().and_then(|f| f.name.clone()).unwrap_or_default();
-                                                debug!(tool_id = %id, tool_name = %name, "Tool call started");
-                                                if tx
-                                                    .send(Ok(StreamChunk::ToolCallStart {
-                                                        id,
-                                                        name,
-                                                    }))
-                                                    .await
-                                                    .is_err()
-                                                {
-                                                    debug!("Stream receiver dropped, exiting");
-                                                    return;
+                                            for chunk in tool_calls_in.push(tc.into_fragment()) {
+                                                if let StreamChunk::ToolCallStart { ref id, ref name } = chunk {
+                                                    debug!(tool_id = %id, tool_name = %name, "Tool call started");
                                                 }
-                                            }
-                                            if let Some(args) = tc.function.and_then(|f| f.arguments) {
-                                                if !args.is_empty()
-                                                    && tx
-                                                        .send(Ok(StreamChunk::ToolCallDelta {
-                                                            arguments: args,
-                                                        }))
-                                                        .await
-                                                        .is_err()
-                                                {
+                                                if tx.send(Ok(chunk)).await.is_err() {
                                                     debug!("Stream receiver dropped, exiting");
                                                     return;
                                                 }
@@ -478,6 +477,9 @@ impl Provider for OpenAIProvider {
                                     }
 
                                     if let Some(ref reason) = choice.finish_reason {
+                                        for chunk in tool_calls_in.finish() {
+                                            let _ = tx.send(Ok(chunk)).await;
+                                        }
                                         let usage = response.usage.as_ref().map(|u| {
                                             Usage::new(u.prompt_tokens, u.completion_tokens)
                                         });
@@ -505,6 +507,17 @@ impl Provider for OpenAIProvider {
                             }
                         }
                     }
+                    Err(reqwest_eventsource::Error::InvalidStatusCode(status, response)) => {
+                        // HTTP errors (bad request, context overflow) get the same
+                        // mapping as non-streaming requests instead of a retryable
+                        // stream error
+                        let error_text = response.text().await.unwrap_or_default();
+                        error!(status = status.as_u16(), body = %error_text, "LLM stream request failed");
+                        let _ = tx
+                            .send(Err(Self::parse_error(status.as_u16(), &error_text)))
+                            .await;
+                        break;
+                    }
                     Err(e) => {
                         error!(error = ?e, "SSE error");
                         let _ = tx
@@ -520,6 +533,100 @@ impl Provider for OpenAIProvider {
         Ok(Box::pin(stream) as StreamResult)
     }
 
+    async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        debug!(model = %model, inputs = inputs.len(), "Embedding request");
+
+        let response = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&OpenAIEmbeddingRequest { model, input: inputs })
+            .send()
+            .await
+            .map_err(|e| Error::network(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            error!(status = status.as_u16(), body = %error_text, "Embedding request failed");
+            return Err(Self::parse_error(status.as_u16(), &error_text));
+        }
+
+        let mut api_response: OpenAIEmbeddingResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::serialization(e.to_string()))?;
+        if api_response.data.len() != inputs.len() {
+            return Err(Error::serialization(format!(
+                "expected {} embeddings, got {}",
+                inputs.len(),
+                api_response.data.len()
+            )));
+        }
+        api_response.data.sort_by_key(|d| d.index);
+        Ok(api_response.data.into_iter().map(|d| d.embedding).collect())
+    }
+
+    /// Only OpenAI itself; compatible local servers rarely implement `/files`.
+    fn supports_file_upload(&self) -> bool {
+        self.base_url == DEFAULT_BASE_URL
+    }
+
+    async fn upload_file(
+        &self,
+        filename: &str,
+        media_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<FileRef, Error> {
+        // Chat completions read uploaded PDFs; text is cheaper inlined
+        if media_type != "application/pdf" {
+            return Err(Error::invalid_request(format!(
+                "OpenAI reads uploaded PDFs, not {}",
+                media_type
+            )));
+        }
+        let size = bytes.len() as u64;
+        let part = reqwest::multipart::Part::bytes(bytes)
+            .file_name(filename.to_string())
+            .mime_str(media_type)
+            .map_err(|e| Error::invalid_request(e.to_string()))?;
+        let form = reqwest::multipart::Form::new()
+            .text("purpose", "user_data")
+            .part("file", part);
+
+        debug!(filename, media_type, size, "File upload request");
+
+        let response = self
+            .client
+            .post(format!("{}/files", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| Error::network(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            error!(status = status.as_u16(), body = %error_text, "File upload failed");
+            return Err(Self::parse_error(status.as_u16(), &error_text));
+        }
+
+        let file: OpenAIFileObject = response
+            .json()
+            .await
+            .map_err(|e| Error::serialization(e.to_string()))?;
+
+        Ok(FileRef {
+            provider: self.name().to_string(),
+            id: file.id,
+            filename: filename.to_string(),
+            media_type: media_type.to_string(),
+            size,
+        })
+    }
+
     fn available_models(&self) -> Vec<&str> {
         vec![
             "gpt-4o",
@@ -535,27 +642,40 @@ impl Provider for OpenAIProvider {
 }
 
 impl OpenAIProvider {
-    fn parse_error(&self, status: u16, body: &str) -> Error {
+    fn parse_error(status: u16, body: &str) -> Error {
         #[derive(Deserialize)]
         struct ErrorResponse {
             error: ErrorDetail,
         }
 
         #[derive(Deserialize)]
-        #[allow(dead_code)]
         struct ErrorDetail {
             message: String,
             #[serde(rename = "type")]
             error_type: Option<String>,
+            /// String on OpenAI, sometimes numeric on compatible servers
+            #[serde(default)]
+            code: Option<serde_json::Value>,
         }
 
         if let Ok(err) = serde_json::from_str::<ErrorResponse>(body) {
+            // OpenAI sets code "context_length_exceeded"; llama.cpp uses type
+            // "exceed_context_size_error"; vLLM/Ollama/LiteLLM only say so in prose
+            let overflow = err.error.code.as_ref().and_then(|c| c.as_str())
+                == Some("context_length_exceeded")
+                || err.error.error_type.as_deref() == Some("exceed_context_size_error")
+                || (status != 401 && status != 429 && mentions_context_overflow(&err.error.message));
+            if overflow {
+                return Error::context_overflow(err.error.message);
+            }
             match status {
                 401 => Error::auth(err.error.message),
                 429 => Error::rate_limit(err.error.message),
                 400 => Error::invalid_request(err.error.message),
                 _ => Error::api(status, err.error.message),
             }
+        } else if matches!(status, 400 | 413) && mentions_context_overflow(body) {
+            Error::context_overflow(body)
         } else {
             Error::api(status, body.to_string())
         }
@@ -639,6 +759,7 @@ pub async fn probe_context_window(
 enum OpenAIContentBlock {
     Text { text: String },
     ImageUrl { image_url: OpenAIImageUrl },
+    File { file: OpenAIFileInput },
 }
 
 #[derive(Debug, Serialize)]
@@ -646,6 +767,12 @@ struct OpenAIImageUrl {
     url: String,
 }
 
+/// An uploaded file referenced from a content array.
+#[derive(Debug, Serialize)]
+struct OpenAIFileInput {
+    file_id: String,
+}
+
 /// Content for outgoing (request) messages. Either a plain string or an array
 /// of content blocks (text + image_url). Always present for non-assistant roles.
 #[derive(Debug, Serialize)]
@@ -786,6 +913,29 @@ struct OpenAIUsage {
     completion_tokens: u32,
 }
 
+#[derive(Debug, Serialize)]
+struct OpenAIEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIFileObject {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingResponse {
+    data: Vec<OpenAIEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbedding {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamResponse {
@@ -810,11 +960,10 @@ struct OpenAIStreamDelta {
     tool_calls: Option<Vec<OpenAIStreamToolCall>>,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamToolCall {
-    #[serde(default)]
-    index: usize,
+    /// Missing on some compatible servers
+    index: Option<usize>,
     id: Option<String>,
     function: Option<OpenAIStreamFunction>,
 }
@@ -822,29 +971,60 @@ struct OpenAIStreamToolCall {
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamFunction {
     name: Option<String>,
-    arguments: Option<String>,
+    /// Normally a JSON-text fragment; some servers send the parsed object
+    arguments: Option<serde_json::Value>,
+}
+
+impl OpenAIStreamToolCall {
+    fn into_fragment(self) -> ToolCallFragment {
+        let non_empty = |s: String| (!s.is_empty()).then_some(s);
+        let (name, arguments) = match self.function {
+            Some(f) => (
+                f.name.and_then(non_empty),
+                match f.arguments {
+                    Some(serde_json::Value::String(s)) => non_empty(s),
+                    Some(serde_json::Value::Null) | None => None,
+                    Some(value) => Some(value.to_string()),
+                },
+            ),
+            None => (None, None),
+        };
+        ToolCallFragment {
+            index: self.index,
+            id: self.id.and_then(non_empty),
+            name,
+            arguments,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_request_serialization_golden() {
+        let provider = OpenAIProvider::new("test-key").unwrap();
+        let body = provider.build_request(&qq_core::testing::golden_request());
+        qq_core::assert_snapshot!("openai_request", serde_json::to_string_pretty(&body).unwrap());
+    }
+
     #[test]
     fn test_provider_creation() {
-        let provider = OpenAIProvider::new("test-key");
+        let provider = OpenAIProvider::new("test-key").unwrap();
         assert_eq!(provider.name(), "openai");
         assert_eq!(provider.default_model(), None);
     }
 
     #[test]
     fn test_provider_with_custom_model() {
-        let provider = OpenAIProvider::new("test-key").with_default_model("gpt-4-turbo");
+        let provider = OpenAIProvider::new("test-key").unwrap().with_default_model("gpt-4-turbo");
         assert_eq!(provider.default_model(), Some("gpt-4-turbo"));
     }
 
     #[test]
     fn test_build_request() {
-        let provider = OpenAIProvider::new("test-key").with_default_model("test-model");
+        let provider = OpenAIProvider::new("test-key").unwrap().with_default_model("test-model");
         let request = CompletionRequest::new(vec![Message::user("Hello")]);
         let api_request = provider.build_request(&request);
 
@@ -857,7 +1037,7 @@ mod tests {
 
     #[test]
     fn test_build_request_no_model() {
-        let provider = OpenAIProvider::new("test-key");
+        let provider = OpenAIProvider::new("test-key").unwrap();
         let request = CompletionRequest::new(vec![Message::user("Hello")]);
         let api_request = provider.build_request(&request);
 
@@ -867,7 +1047,7 @@ mod tests {
 
     #[test]
     fn test_tool_message_empty_content_serializes_content_field() {
-        let provider = OpenAIProvider::new("test-key");
+        let provider = OpenAIProvider::new("test-key").unwrap();
         let msg = Message::tool_result("call-id", "");
         let converted = provider.convert_message(&msg);
         let json = serde_json::to_value(&converted).unwrap();
@@ -879,7 +1059,7 @@ mod tests {
 
     #[test]
     fn test_user_message_empty_content_serializes_content_field() {
-        let provider = OpenAIProvider::new("test-key");
+        let provider = OpenAIProvider::new("test-key").unwrap();
         let msg = Message::user("");
         let converted = provider.convert_message(&msg);
         let json = serde_json::to_value(&converted).unwrap();
@@ -888,9 +1068,38 @@ mod tests {
         assert_eq!(json["content"], "");
     }
 
+    #[test]
+    fn test_user_message_with_uploaded_file() {
+        let provider = OpenAIProvider::new("test-key").unwrap();
+        assert!(provider.supports_file_upload());
+        assert!(!OpenAIProvider::new("").unwrap().with_base_url("http://localhost:8080/v1").supports_file_upload());
+
+        let file = |provider: &str| FileRef {
+            provider: provider.to_string(),
+            id: "file-abc".to_string(),
+            filename: "report.pdf".to_string(),
+            media_type: "application/pdf".to_string(),
+            size: 2048,
+        };
+        let msg = Message::user(vec![
+            qq_core::TypedContent::text("Summarize"),
+            qq_core::TypedContent::document(file("openai")),
+            qq_core::TypedContent::document(file("gemini")),
+        ]);
+        let json = serde_json::to_value(provider.convert_message(&msg)).unwrap();
+        assert_eq!(
+            json["content"][1],
+            serde_json::json!({"type": "file", "file": {"file_id": "file-abc"}})
+        );
+        assert_eq!(
+            json["content"][2],
+            serde_json::json!({"type": "text", "text": "[Document: report.pdf, application/pdf, ~2 KB]"})
+        );
+    }
+
     #[test]
     fn test_assistant_tool_calls_omits_content() {
-        let provider = OpenAIProvider::new("test-key");
+        let provider = OpenAIProvider::new("test-key").unwrap();
         let tc = ToolCall::new("id1", "my_tool", serde_json::json!({"key": "val"}));
         let msg = Message::assistant_with_tool_calls("", vec![tc]);
         let converted = provider.convert_message(&msg);
@@ -903,7 +1112,7 @@ mod tests {
 
     #[test]
     fn test_assistant_with_content_and_tool_calls_includes_both() {
-        let provider = OpenAIProvider::new("test-key");
+        let provider = OpenAIProvider::new("test-key").unwrap();
         let tc = ToolCall::new("id1", "my_tool", serde_json::json!({}));
         let msg = Message::assistant_with_tool_calls("thinking...", vec![tc]);
         let converted = provider.convert_message(&msg);
@@ -915,7 +1124,7 @@ mod tests {
 
     #[test]
     fn test_non_assistant_messages_always_have_content_key() {
-        let provider = OpenAIProvider::new("test-key");
+        let provider = OpenAIProvider::new("test-key").unwrap();
         let messages = vec![
             Message::system("You are helpful."),
             Message::user("Hi"),
@@ -951,4 +1160,20 @@ mod tests {
         assert_eq!(parse_finish_reason(Some("")), None);
         assert_eq!(parse_finish_reason(Some("function_call")), None);
     }
+
+    #[test]
+    fn parse_error_detects_context_overflow() {
+        let openai = r#"{"error": {"message": "This model's maximum context length is 128000 tokens.", "type": "invalid_request_error", "code": "context_length_exceeded"}}"#;
+        assert!(OpenAIProvider::parse_error(400, openai).is_context_overflow());
+
+        let llama_cpp = r#"{"error": {"code": 400, "message": "the request exceeds the available context size", "type": "exceed_context_size_error"}}"#;
+        assert!(OpenAIProvider::parse_error(400, llama_cpp).is_context_overflow());
+
+        let vllm = r#"{"error": {"message": "This model's maximum context length is 32768 tokens. However, you requested 40000 tokens.", "type": "BadRequestError", "code": 400}}"#;
+        assert!(OpenAIProvider::parse_error(400, vllm).is_context_overflow());
+
+        let other = r#"{"error": {"message": "Invalid value for 'temperature'", "type": "invalid_request_error", "code": null}}"#;
+        assert!(matches!(OpenAIProvider::parse_error(400, other), Error::InvalidRequest(_)));
+        assert!(OpenAIProvider::parse_error(413, "Request too large: context length exceeded").is_context_overflow());
+    }
 }
@@ -0,0 +1,151 @@
+/// List price of a model, in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPricing {
+    pub const fn new(input_per_mtok: f64, output_per_mtok: f64) -> Self {
+        Self {
+            input_per_mtok,
+            output_per_mtok,
+        }
+    }
+
+    /// Estimated cost in USD of the given token counts. Cached-input
+    /// discounts are not applied, so this is an upper bound.
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> f64 {
+        (prompt_tokens as f64 * self.input_per_mtok
+            + completion_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Look up the list price for a model by name.
+///
+/// Uses prefix matching on the lowercased model name, like
+/// [`known_context_window`](crate::context_windows::known_context_window).
+/// Prices go stale; `[pricing]` in config.toml overrides them.
+///
+/// Returns `None` for unknown models.
+pub fn known_pricing(model: &str) -> Option<ModelPricing> {
+    let model = model.to_lowercase();
+
+    // Claude models
+    if model.starts_with("claude-opus-4-5") || model.starts_with("claude-opus-4-6") {
+        return Some(ModelPricing::new(5.0, 25.0));
+    }
+    if model.starts_with("claude-opus-4") || model.starts_with("claude-3-opus") {
+        return Some(ModelPricing::new(15.0, 75.0));
+    }
+    if model.starts_with("claude-sonnet-4")
+        || model.starts_with("claude-3-7-sonnet")
+        || model.starts_with("claude-3-5-sonnet")
+    {
+        return Some(ModelPricing::new(3.0, 15.0));
+    }
+    if model.starts_with("claude-haiku-4") {
+        return Some(ModelPricing::new(1.0, 5.0));
+    }
+    if model.starts_with("claude-3-5-haiku") {
+        return Some(ModelPricing::new(0.8, 4.0));
+    }
+    if model.starts_with("claude-3-haiku") {
+        return Some(ModelPricing::new(0.25, 1.25));
+    }
+
+    // Gemini models
+    if model.starts_with("gemini-2.5-pro") || model.starts_with("gemini-1.5-pro") {
+        return Some(ModelPricing::new(1.25, 10.0));
+    }
+    if model.starts_with("gemini-2.5-flash") {
+        return Some(ModelPricing::new(0.3, 2.5));
+    }
+    if model.starts_with("gemini-2.0-flash") {
+        return Some(ModelPricing::new(0.1, 0.4));
+    }
+    if model.starts_with("gemini-1.5-flash") {
+        return Some(ModelPricing::new(0.075, 0.3));
+    }
+
+    // OpenAI o-series reasoning models
+    if model.starts_with("o4-mini") || model.starts_with("o3-mini") || model.starts_with("o1-mini")
+    {
+        return Some(ModelPricing::new(1.1, 4.4));
+    }
+    if model.starts_with("o3") {
+        return Some(ModelPricing::new(2.0, 8.0));
+    }
+    if model == "o1" || model.starts_with("o1-2") {
+        return Some(ModelPricing::new(15.0, 60.0));
+    }
+
+    // GPT-4.1 and GPT-4o families (mini first: they share the prefix)
+    if model.starts_with("gpt-4.1-nano") {
+        return Some(ModelPricing::new(0.1, 0.4));
+    }
+    if model.starts_with("gpt-4.1-mini") {
+        return Some(ModelPricing::new(0.4, 1.6));
+    }
+    if model.starts_with("gpt-4.1") {
+        return Some(ModelPricing::new(2.0, 8.0));
+    }
+    if model.starts_with("gpt-4o-mini") {
+        return Some(ModelPricing::new(0.15, 0.6));
+    }
+    if model.starts_with("gpt-4o") || model.starts_with("chatgpt-4o") {
+        return Some(ModelPricing::new(2.5, 10.0));
+    }
+
+    // GPT-4 Turbo
+    if model.starts_with("gpt-4-turbo") {
+        return Some(ModelPricing::new(10.0, 30.0));
+    }
+
+    // GPT-3.5 Turbo
+    if model.starts_with("gpt-3.5-turbo") {
+        return Some(ModelPricing::new(0.5, 1.5));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_pricing() {
+        assert_eq!(
+            known_pricing("claude-sonnet-4-20250514"),
+            Some(ModelPricing::new(3.0, 15.0))
+        );
+        assert_eq!(
+            known_pricing("Claude-Opus-4-5-20251101"),
+            Some(ModelPricing::new(5.0, 25.0))
+        );
+        assert_eq!(
+            known_pricing("claude-opus-4-1-20250805"),
+            Some(ModelPricing::new(15.0, 75.0))
+        );
+        assert_eq!(
+            known_pricing("gpt-4o-mini-2024-07-18"),
+            Some(ModelPricing::new(0.15, 0.6))
+        );
+        assert_eq!(known_pricing("gpt-4o"), Some(ModelPricing::new(2.5, 10.0)));
+        assert_eq!(known_pricing("o3-mini"), Some(ModelPricing::new(1.1, 4.4)));
+        assert_eq!(
+            known_pricing("gemini-2.5-flash"),
+            Some(ModelPricing::new(0.3, 2.5))
+        );
+        assert_eq!(known_pricing("llama-3.1-70b"), None);
+    }
+
+    #[test]
+    fn test_cost() {
+        let pricing = ModelPricing::new(3.0, 15.0);
+        assert!((pricing.cost(1_000_000, 0) - 3.0).abs() < 1e-9);
+        assert!((pricing.cost(10_000, 2_000) - 0.06).abs() < 1e-9);
+    }
+}
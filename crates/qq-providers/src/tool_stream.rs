@@ -0,0 +1,511 @@
+//! Reassembly of streamed tool-call fragments for OpenAI-compatible servers.
+//!
+//! Consumers of [`StreamChunk`] treat a `ToolCallStart` as opening a new call
+//! and append every following `ToolCallDelta` to it, so calls must come out
+//! one at a time. Servers don't all cooperate: parallel calls can arrive
+//! interleaved, some servers omit `index` or reuse index 0 for every call,
+//! and names can be split across fragments. [`ToolCallAssembler`] takes raw
+//! fragments and emits a well-ordered sequence.
+//!
+//! The first call streams live. When another call appears, the live one is
+//! closed if its arguments already form a complete JSON value and the new
+//! call streams live in its place; otherwise the calls are interleaved, and
+//! everything from then on is buffered and emitted in order by
+//! [`ToolCallAssembler::finish`].
+
+use qq_core::StreamChunk;
+
+/// One tool-call fragment from a stream delta.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ToolCallFragment {
+    pub index: Option<usize>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+#[derive(Debug)]
+struct Slot {
+    index: Option<usize>,
+    id: Option<String>,
+    name: String,
+    arguments: String,
+    /// `ToolCallStart` has been emitted
+    started: bool,
+    /// Bytes of `arguments` already emitted
+    emitted: usize,
+}
+
+/// Orders tool-call fragments into start/delta chunks, one call at a time.
+#[derive(Debug, Default)]
+pub(crate) struct ToolCallAssembler {
+    slots: Vec<Slot>,
+    /// Slot currently streaming live
+    live: Option<usize>,
+    /// Slots before this one are closed; nothing more is emitted for them
+    closed: usize,
+    /// Calls arrived interleaved; buffer until `finish`
+    interleaved: bool,
+}
+
+impl ToolCallAssembler {
+    /// Feed one fragment; returns the chunks that can be emitted now.
+    pub fn push(&mut self, fragment: ToolCallFragment) -> Vec<StreamChunk> {
+        let slot = self.slot_for(&fragment);
+        {
+            let s = &mut self.slots[slot];
+            if s.id.is_none() {
+                s.id = fragment.id;
+            }
+            if s.index.is_none() {
+                s.index = fragment.index;
+            }
+            if let Some(name) = fragment.name {
+                // Some servers repeat the full name on every fragment
+                if !s.started && !s.name.ends_with(&name) {
+                    s.name.push_str(&name);
+                }
+            }
+            if let Some(args) = fragment.arguments {
+                s.arguments.push_str(&args);
+            }
+        }
+
+        let mut out = Vec::new();
+        if self.interleaved || slot < self.closed {
+            if slot < self.closed {
+                tracing::debug!(slot, "Tool call fragment arrived after its call was closed");
+            }
+            self.interleaved = true;
+            return out;
+        }
+        match self.live {
+            Some(live) if live == slot => self.emit(slot, &mut out),
+            Some(live) => {
+                if json_complete(&self.slots[live].arguments) {
+                    self.emit(live, &mut out);
+                    self.closed = live + 1;
+                    self.live = Some(slot);
+                    self.emit(slot, &mut out);
+                } else {
+                    self.interleaved = true;
+                }
+            }
+            None => {
+                self.live = Some(slot);
+                self.emit(slot, &mut out);
+            }
+        }
+        out
+    }
+
+    /// Emit everything still buffered, in call order.
+    pub fn finish(&mut self) -> Vec<StreamChunk> {
+        let mut out = Vec::new();
+        for slot in self.closed..self.slots.len() {
+            let s = &mut self.slots[slot];
+            if s.name.is_empty() && s.arguments.is_empty() {
+                continue;
+            }
+            if !s.started {
+                s.started = true;
+                out.push(StreamChunk::ToolCallStart {
+                    id: s.id.clone().unwrap_or_else(|| format!("call_{}", slot)),
+                    name: std::mem::take(&mut s.name),
+                });
+            }
+            if s.emitted < s.arguments.len() {
+                out.push(StreamChunk::ToolCallDelta {
+                    arguments: s.arguments[s.emitted..].to_string(),
+                });
+                s.emitted = s.arguments.len();
+            }
+        }
+        *self = Self::default();
+        out
+    }
+
+    /// Match a fragment to its call: by id when it has one, then by index,
+    /// then the most recent call.
+    fn slot_for(&mut self, fragment: &ToolCallFragment) -> usize {
+        if let Some(ref id) = fragment.id {
+            if let Some(pos) = self.slots.iter().position(|s| s.id.as_ref() == Some(id)) {
+                return pos;
+            }
+            // A new id is a new call, even if it reuses an index
+            let reuse = self
+                .slots
+                .last()
+                .is_some_and(|s| s.id.is_none() && s.name.is_empty() && s.arguments.is_empty());
+            if !reuse {
+                self.new_slot(fragment.index);
+            }
+            return self.slots.len() - 1;
+        }
+        if let Some(index) = fragment.index {
+            if let Some(pos) = self.slots.iter().rposition(|s| s.index == Some(index)) {
+                return pos;
+            }
+            self.new_slot(Some(index));
+            return self.slots.len() - 1;
+        }
+        if self.slots.is_empty() {
+            self.new_slot(None);
+        }
+        self.slots.len() - 1
+    }
+
+    fn new_slot(&mut self, index: Option<usize>) {
+        self.slots.push(Slot {
+            index,
+            id: None,
+            name: String::new(),
+            arguments: String::new(),
+            started: false,
+            emitted: 0,
+        });
+    }
+
+    /// Stream what `slot` has so far. The start is held back until the
+    /// arguments begin, since the name may still be arriving in pieces.
+    fn emit(&mut self, slot: usize, out: &mut Vec<StreamChunk>) {
+        let s = &mut self.slots[slot];
+        if !s.started {
+            if s.name.is_empty() || s.arguments.is_empty() {
+                return;
+            }
+            s.started = true;
+            out.push(StreamChunk::ToolCallStart {
+                id: s.id.clone().unwrap_or_else(|| format!("call_{}", slot)),
+                name: s.name.clone(),
+            });
+        }
+        if s.emitted < s.arguments.len() {
+            out.push(StreamChunk::ToolCallDelta {
+                arguments: s.arguments[s.emitted..].to_string(),
+            });
+            s.emitted = s.arguments.len();
+        }
+    }
+}
+
+/// Whether `text` holds one complete JSON object or array (plus whitespace).
+///
+/// Tracks nesting and string state only; it answers "has the value closed",
+/// not "is it valid".
+pub(crate) fn json_complete(text: &str) -> bool {
+    let mut depth = 0usize;
+    let mut opened = false;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                depth += 1;
+                opened = true;
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && opened {
+                    return text[i + 1..].trim().is_empty();
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frag(
+        index: Option<usize>,
+        id: Option<&str>,
+        name: Option<&str>,
+        args: &str,
+    ) -> ToolCallFragment {
+        ToolCallFragment {
+            index,
+            id: id.map(String::from),
+            name: name.map(String::from),
+            arguments: (!args.is_empty()).then(|| args.to_string()),
+        }
+    }
+
+    /// Rebuild calls the way stream consumers do: a start opens a call and
+    /// each delta appends to the current one.
+    fn reassemble(chunks: &[StreamChunk]) -> Vec<(String, String)> {
+        let mut calls: Vec<(String, String)> = Vec::new();
+        for chunk in chunks {
+            match chunk {
+                StreamChunk::ToolCallStart { name, .. } => {
+                    calls.push((name.clone(), String::new()))
+                }
+                StreamChunk::ToolCallDelta { arguments } => calls
+                    .last_mut()
+                    .expect("delta before start")
+                    .1
+                    .push_str(arguments),
+                other => panic!("unexpected chunk {:?}", other),
+            }
+        }
+        calls
+    }
+
+    /// Chunks as comparable strings (`StreamChunk` has no `PartialEq`).
+    fn plain(chunks: &[StreamChunk]) -> Vec<String> {
+        chunks
+            .iter()
+            .map(|chunk| match chunk {
+                StreamChunk::ToolCallStart { id, name } => format!("start {} {}", id, name),
+                StreamChunk::ToolCallDelta { arguments } => format!("delta {}", arguments),
+                other => panic!("unexpected chunk {:?}", other),
+            })
+            .collect()
+    }
+
+    fn run(fragments: Vec<ToolCallFragment>) -> (Vec<StreamChunk>, Vec<StreamChunk>) {
+        let mut assembler = ToolCallAssembler::default();
+        let live: Vec<StreamChunk> = fragments
+            .into_iter()
+            .flat_map(|f| assembler.push(f))
+            .collect();
+        let rest = assembler.finish();
+        (live, rest)
+    }
+
+    #[test]
+    fn test_json_complete() {
+        assert!(json_complete(r#"{"a": 1}"#));
+        assert!(json_complete(" {} \n"));
+        assert!(json_complete(r#"{"a": "}{", "b": [1, {"c": "\"}"}]}"#));
+        assert!(!json_complete(""));
+        assert!(!json_complete(r#"{"a": "}"#));
+        assert!(!json_complete(r#"{"a": [1, 2]"#));
+        assert!(!json_complete(r#"{"a": "\"}"#));
+        assert!(!json_complete(r#"{} {"#));
+    }
+
+    #[test]
+    fn test_sequential_calls_stream_live() {
+        let (live, rest) = run(vec![
+            frag(Some(0), Some("a"), Some("read_file"), ""),
+            frag(Some(0), None, None, r#"{"path": "#),
+            frag(Some(0), None, None, r#""x.rs"}"#),
+            frag(Some(1), Some("b"), Some("list_files"), "{}"),
+        ]);
+        assert!(rest.is_empty());
+        assert_eq!(
+            plain(&live),
+            vec![
+                r#"start a read_file"#,
+                r#"delta {"path": "#,
+                r#"delta "x.rs"}"#,
+                r#"start b list_files"#,
+                r#"delta {}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interleaved_calls_are_ordered() {
+        let (live, rest) = run(vec![
+            frag(Some(0), Some("a"), Some("read_file"), r#"{"path":"#),
+            frag(Some(1), Some("b"), Some("read_file"), r#"{"path":"#),
+            frag(Some(0), None, None, r#""a.rs"}"#),
+            frag(Some(1), None, None, r#""b.rs"}"#),
+        ]);
+        assert_eq!(live.len(), 2);
+        let mut all = live;
+        all.extend(rest);
+        assert_eq!(
+            reassemble(&all),
+            vec![
+                ("read_file".into(), r#"{"path":"a.rs"}"#.into()),
+                ("read_file".into(), r#"{"path":"b.rs"}"#.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_and_reused_index() {
+        // No index at all: ids separate the calls, id-less fragments continue the last one
+        let (live, rest) = run(vec![
+            frag(None, Some("a"), Some("bash"), r#"{"cmd":"ls"}"#),
+            frag(None, Some("b"), Some("bash"), r#"{"cmd":"#),
+            frag(None, None, None, r#""pwd"}"#),
+        ]);
+        let mut all = live;
+        all.extend(rest);
+        assert_eq!(
+            reassemble(&all),
+            vec![
+                ("bash".into(), r#"{"cmd":"ls"}"#.into()),
+                ("bash".into(), r#"{"cmd":"pwd"}"#.into()),
+            ]
+        );
+
+        // Every call reports index 0
+        let (live, rest) = run(vec![
+            frag(Some(0), Some("a"), Some("bash"), r#"{"cmd":"ls"}"#),
+            frag(Some(0), Some("b"), Some("bash"), r#"{"cmd":"pwd"}"#),
+        ]);
+        let mut all = live;
+        all.extend(rest);
+        assert_eq!(reassemble(&all).len(), 2);
+        assert_eq!(plain(&all[2..3]), vec!["start b bash"]);
+    }
+
+    #[test]
+    fn test_split_and_repeated_names() {
+        let (live, rest) = run(vec![
+            frag(Some(0), Some("a"), Some("read"), ""),
+            frag(Some(0), None, Some("_file"), ""),
+            frag(Some(0), None, None, "{}"),
+        ]);
+        let mut all = live;
+        all.extend(rest);
+        // The start waits for the arguments, so the whole name is in it
+        assert_eq!(reassemble(&all), vec![("read_file".into(), "{}".into())]);
+
+        let (live, rest) = run(vec![
+            frag(Some(0), Some("a"), Some("bash"), ""),
+            frag(Some(0), None, Some("bash"), "{}"),
+        ]);
+        let mut all = live;
+        all.extend(rest);
+        assert_eq!(reassemble(&all), vec![("bash".into(), "{}".into())]);
+    }
+
+    #[test]
+    fn test_finish_names_calls_without_ids() {
+        let (live, rest) = run(vec![frag(Some(0), None, None, r#"{"a":1}"#)]);
+        assert!(live.is_empty());
+        assert_eq!(plain(&rest), vec!["start call_0 ", r#"delta {"a":1}"#]);
+    }
+
+    /// Small deterministic PRNG so the fuzz cases are reproducible.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    /// Split `text` into 1..=4 char pieces at random char boundaries.
+    fn split(text: &str, rng: &mut XorShift) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut pieces = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let n = (1 + rng.below(4)).min(chars.len() - i);
+            pieces.push(chars[i..i + n].iter().collect());
+            i += n;
+        }
+        pieces
+    }
+
+    #[test]
+    fn test_fuzz_fragmented_streams() {
+        let names = ["read_file", "bash", "write_file", "search_files"];
+        let values = [
+            "plain",
+            "héllo wörld",
+            "crab 🦀 emoji",
+            r#"quote \" and brace }"#,
+            r"escaped é and \\ backslash",
+            "日本語のテキスト",
+        ];
+
+        for seed in 1..=500u64 {
+            let mut rng = XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+            let count = 1 + rng.below(4);
+            let drop_index = rng.below(3) == 0;
+            let interleave = rng.below(2) == 0;
+
+            let mut expected = Vec::new();
+            let mut streams: Vec<Vec<ToolCallFragment>> = Vec::new();
+            for call in 0..count {
+                let name = names[rng.below(names.len())];
+                let args = format!(
+                    r#"{{"value": "{}", "n": [{}, {{"k": "{}"}}]}}"#,
+                    values[rng.below(values.len())],
+                    call,
+                    values[rng.below(values.len())]
+                );
+                let index = (!drop_index).then_some(call);
+                let name_split = rng.below(name.len());
+                let mut fragments = vec![frag(
+                    index,
+                    Some(&format!("call_{}_{}", seed, call)),
+                    Some(&name[..name_split.max(1)]),
+                    "",
+                )];
+                if name_split > 1 {
+                    fragments.push(frag(index, None, Some(&name[name_split..]), ""));
+                } else {
+                    fragments[0].name = Some(name.to_string());
+                }
+                for piece in split(&args, &mut rng) {
+                    fragments.push(frag(index, None, None, &piece));
+                }
+                expected.push((name.to_string(), args));
+                streams.push(fragments);
+            }
+
+            // Without an index, id-less fragments can only be attributed to
+            // the latest call, so only interleave when the server sends indexes.
+            let mut order = Vec::new();
+            if interleave && !drop_index {
+                let mut cursors = vec![0usize; count];
+                let mut remaining: usize = streams.iter().map(Vec::len).sum();
+                let mut opened = 0;
+                while remaining > 0 {
+                    // Calls open in order; any opened call can continue
+                    let choices: Vec<usize> = (0..count)
+                        .filter(|&c| cursors[c] < streams[c].len() && c <= opened)
+                        .collect();
+                    let c = choices[rng.below(choices.len())];
+                    if c == opened {
+                        opened += 1;
+                    }
+                    order.push(streams[c][cursors[c]].clone());
+                    cursors[c] += 1;
+                    remaining -= 1;
+                }
+            } else {
+                order = streams.into_iter().flatten().collect();
+            }
+
+            let (live, rest) = run(order);
+            let mut all = live;
+            all.extend(rest);
+            assert_eq!(reassemble(&all), expected, "seed {}", seed);
+            for pair in all.windows(2) {
+                if let [StreamChunk::ToolCallStart { id, .. }, _] = pair {
+                    assert!(id.starts_with(&format!("call_{}_", seed)), "seed {}", seed);
+                }
+            }
+        }
+    }
+}
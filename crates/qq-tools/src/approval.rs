@@ -15,6 +15,11 @@ pub struct ApprovalRequest {
     pub response_tx: oneshot::Sender<ApprovalResponse>,
     /// UI category label (e.g. "Bash Command", "File Operation", "Mount").
     pub category: String,
+    /// Plain-English line saying what the command does, shown next to it.
+    pub explanation: Option<String>,
+    /// Unified diff of the files the command would change, when that can be
+    /// worked out before it runs.
+    pub preview: Option<String>,
 }
 
 /// User's response to an approval request.
@@ -41,6 +46,34 @@ impl ApprovalChannel {
         full_command: String,
         trigger_commands: Vec<String>,
         category: &str,
+    ) -> Result<ApprovalResponse, String> {
+        self.request_explained_approval(full_command, trigger_commands, category, None)
+            .await
+    }
+
+    /// Like [`request_approval`](Self::request_approval), with a line saying
+    /// what the command does for the UI to show next to it.
+    pub async fn request_explained_approval(
+        &self,
+        full_command: String,
+        trigger_commands: Vec<String>,
+        category: &str,
+        explanation: Option<String>,
+    ) -> Result<ApprovalResponse, String> {
+        self.request_previewed_approval(full_command, trigger_commands, category, explanation, None)
+            .await
+    }
+
+    /// Like [`request_explained_approval`](Self::request_explained_approval),
+    /// with a diff of what the command would change for the UI to page
+    /// through before the user decides.
+    pub async fn request_previewed_approval(
+        &self,
+        full_command: String,
+        trigger_commands: Vec<String>,
+        category: &str,
+        explanation: Option<String>,
+        preview: Option<String>,
     ) -> Result<ApprovalResponse, String> {
         let (response_tx, response_rx) = oneshot::channel();
 
@@ -50,6 +83,8 @@ impl ApprovalChannel {
                 trigger_commands,
                 response_tx,
                 category: category.to_string(),
+                explanation,
+                preview,
             })
             .await
             .map_err(|_| "Approval channel closed".to_string())?;
@@ -0,0 +1,690 @@
+//! Content-addressed store for large tool outputs.
+//!
+//! Outputs over the agent loop's size budget used to be truncated or
+//! summarized, and whatever fell past the cut was lost to the model.
+//! [`OffloadTool`] instead saves the full text under its SHA-256 digest and
+//! hands the model a head-and-tail preview plus the digest; the
+//! `fetch_artifact` tool ([`FetchArtifactTool`]) then reads any line or byte
+//! range of it, or the lines matching a pattern.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use qq_core::{
+    Error, PropertySchema, ScopeRetry, Tool, ToolDefinition, ToolOutput, ToolParameters,
+    ToolRegistry, TypedContent,
+};
+use qq_core::text::floor_grapheme_boundary;
+
+use crate::file_lock::write_atomically;
+
+/// Hex digits of the digest shown to the model; any unique prefix of at
+/// least [`MIN_PREFIX`] digits resolves.
+const SHORT_DIGEST: usize = 16;
+const MIN_PREFIX: usize = 8;
+
+/// Outputs above this are offloaded (matches the agent loop's truncation
+/// limit and the chunker's default threshold).
+pub const DEFAULT_THRESHOLD_BYTES: usize = 50_000;
+/// Size of the preview left in place of an offloaded output.
+pub const DEFAULT_PREVIEW_BYTES: usize = 4_000;
+
+/// Artifacts saved to disk, named by the SHA-256 of their content.
+pub struct ArtifactStore {
+    dir: PathBuf,
+}
+
+impl ArtifactStore {
+    pub fn open(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, digest: &str) -> PathBuf {
+        self.dir.join(format!("{}.txt", digest))
+    }
+
+    /// Save `text` and return its full hex digest. Identical outputs share
+    /// one file.
+    pub fn put(&self, text: &str) -> std::io::Result<String> {
+        let digest: String = Sha256::digest(text.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let path = self.path(&digest);
+        if !path.exists() {
+            write_atomically(&path, text.as_bytes())?;
+        }
+        Ok(digest)
+    }
+
+    /// Read the artifact whose digest starts with `prefix`.
+    pub fn get(&self, prefix: &str) -> Result<String, String> {
+        let prefix = prefix
+            .trim()
+            .trim_start_matches("sha256:")
+            .to_ascii_lowercase();
+        if prefix.len() < MIN_PREFIX || !prefix.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!(
+                "Invalid digest '{}': use the hex digest from the truncated result",
+                prefix
+            ));
+        }
+        let mut matches = self
+            .digests()
+            .into_iter()
+            .filter(|d| d.starts_with(&prefix));
+        let digest = match (matches.next(), matches.next()) {
+            (Some(digest), None) => digest,
+            (None, _) => return Err(format!("No artifact with digest '{}'", prefix)),
+            (Some(_), Some(_)) => {
+                return Err(format!("Digest '{}' is ambiguous; give more of it", prefix))
+            }
+        };
+        std::fs::read_to_string(self.path(&digest))
+            .map_err(|e| format!("Failed to read artifact '{}': {}", prefix, e))
+    }
+
+    fn digests(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                name.strip_suffix(".txt").map(str::to_string)
+            })
+            .collect()
+    }
+
+    /// Delete artifacts written `max_age` or longer ago; returns how many.
+    pub fn prune(&self, max_age: Duration) -> usize {
+        let now = SystemTime::now();
+        self.digests()
+            .into_iter()
+            .map(|digest| self.path(&digest))
+            .filter(|path| is_older(path, now, max_age))
+            .filter(|path| std::fs::remove_file(path).is_ok())
+            .count()
+    }
+}
+
+fn is_older(path: &Path, now: SystemTime, max_age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .is_some_and(|age| age >= max_age)
+}
+
+/// When outputs are offloaded and how much of them stays in view.
+#[derive(Debug, Clone, Copy)]
+pub struct OffloadPolicy {
+    pub threshold_bytes: usize,
+    pub preview_bytes: usize,
+}
+
+impl Default for OffloadPolicy {
+    fn default() -> Self {
+        Self {
+            threshold_bytes: DEFAULT_THRESHOLD_BYTES,
+            preview_bytes: DEFAULT_PREVIEW_BYTES,
+        }
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes < 1024 * 1024 {
+        format!("{:.1}KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// The first lines and the last lines of `text` within `budget` bytes (three
+/// quarters to the head, where the answer usually starts; the rest to the
+/// tail, where commands put their summaries), and the reference to the rest.
+pub fn preview(text: &str, digest: &str, budget: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let short = &digest[..SHORT_DIGEST.min(digest.len())];
+
+    let head_budget = budget * 3 / 4;
+    let mut head = 0;
+    let mut used = 0;
+    while head < lines.len() && used + lines[head].len() < head_budget {
+        used += lines[head].len() + 1;
+        head += 1;
+    }
+    let mut tail = lines.len();
+    used = 0;
+    while tail > head && used + lines[tail - 1].len() < budget - head_budget {
+        used += lines[tail - 1].len() + 1;
+        tail -= 1;
+    }
+
+    let mut out = String::new();
+    if head == 0 && tail == lines.len() {
+        // One long line (minified JSON, a base64 blob): cut it by bytes
+        let cut = floor_grapheme_boundary(text, budget);
+        out.push_str(&text[..cut]);
+        out.push_str(&format!(
+            "\n[... {} of {} bytes omitted ...]\n",
+            text.len() - cut,
+            text.len()
+        ));
+        out.push_str(&format!(
+            "\n---\nFull output ({}) saved as artifact {}. Read more with \
+             fetch_artifact(digest=\"{}\", range=\"{}-{}\", unit=\"bytes\").",
+            format_size(text.len()),
+            short,
+            short,
+            cut,
+            (cut + budget).min(text.len())
+        ));
+        return out;
+    }
+
+    for line in &lines[..head] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    if tail > head {
+        out.push_str(&format!(
+            "[... lines {}-{} of {} omitted ...]\n",
+            head + 1,
+            tail,
+            lines.len()
+        ));
+    }
+    for line in &lines[tail..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "\n---\nFull output ({} lines, {}) saved as artifact {}. Read the omitted \
+         lines with fetch_artifact(digest=\"{}\", range=\"{}-{}\"), or search it with \
+         fetch_artifact(digest=\"{}\", pattern=\"...\").",
+        lines.len(),
+        format_size(text.len()),
+        short,
+        short,
+        head + 1,
+        tail,
+        short
+    ));
+    out
+}
+
+// =============================================================================
+// OffloadTool
+// =============================================================================
+
+/// Wraps a tool so a text output over the threshold is saved to the store
+/// and replaced by a preview and its digest.
+pub struct OffloadTool {
+    inner: Arc<dyn Tool>,
+    store: Arc<ArtifactStore>,
+    policy: OffloadPolicy,
+}
+
+impl OffloadTool {
+    pub fn new(inner: Arc<dyn Tool>, store: Arc<ArtifactStore>, policy: OffloadPolicy) -> Self {
+        Self {
+            inner,
+            store,
+            policy,
+        }
+    }
+
+    fn offload(&self, mut output: ToolOutput) -> ToolOutput {
+        let text = output.text_content();
+        if text.len() <= self.policy.threshold_bytes {
+            return output;
+        }
+        let digest = match self.store.put(&text) {
+            Ok(digest) => digest,
+            Err(e) => {
+                tracing::warn!(tool = %self.inner.name(), "Failed to save artifact: {}", e);
+                return output;
+            }
+        };
+        tracing::debug!(
+            tool = %self.inner.name(),
+            digest = %digest,
+            bytes = text.len(),
+            "Offloaded tool output to artifact"
+        );
+        // Keep images and documents; the text parts become the preview
+        output
+            .content
+            .retain(|c| !matches!(c, TypedContent::Text { .. }));
+        output.content.insert(
+            0,
+            TypedContent::text(preview(&text, &digest, self.policy.preview_bytes)),
+        );
+        output
+    }
+}
+
+/// Copy of `registry` with every tool's large outputs offloaded to `store`.
+pub fn offload(
+    registry: &ToolRegistry,
+    store: &Arc<ArtifactStore>,
+    policy: OffloadPolicy,
+) -> ToolRegistry {
+    registry.wrapped(|tool| Arc::new(OffloadTool::new(tool, Arc::clone(store), policy)))
+}
+
+#[async_trait]
+impl Tool for OffloadTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn display_name(&self) -> &str {
+        self.inner.display_name()
+    }
+
+    fn tool_description(&self) -> &str {
+        self.inner.tool_description()
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        self.inner.definition()
+    }
+
+    fn is_blocking(&self) -> bool {
+        self.inner.is_blocking()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    fn write_targets(&self, arguments: &Value) -> Vec<String> {
+        self.inner.write_targets(arguments)
+    }
+
+    fn narrower_scope(&self, arguments: &Value, fraction: f64) -> Option<ScopeRetry> {
+        self.inner.narrower_scope(arguments, fraction)
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let output = self.inner.execute(arguments).await?;
+        Ok(self.offload(output))
+    }
+}
+
+// =============================================================================
+// FetchArtifactTool
+// =============================================================================
+
+const FETCH_ARTIFACT_DESC: &str = "\
+Read part of a large tool output that was saved as an artifact instead of \
+being shown in full. The truncated result names the artifact's digest and \
+the omitted line range.
+
+Ask for a line range (range=\"120-240\"), a byte range for single-line \
+outputs such as minified JSON (range=\"4000-8000\", unit=\"bytes\"), or the \
+lines matching a regex (pattern=\"error|warning\"). Results are capped in \
+size and end with the range to ask for next.";
+
+/// Tool for reading back offloaded tool outputs.
+pub struct FetchArtifactTool {
+    store: Arc<ArtifactStore>,
+    /// Cap on one result, so a fetch is never itself offloaded
+    max_bytes: usize,
+}
+
+impl FetchArtifactTool {
+    pub fn new(store: Arc<ArtifactStore>, policy: OffloadPolicy) -> Self {
+        Self {
+            store,
+            max_bytes: policy.threshold_bytes,
+        }
+    }
+
+    fn run(&self, args: FetchArtifactArgs) -> Result<String, String> {
+        let text = self.store.get(&args.digest)?;
+        let range = args.range.as_deref().map(parse_range).transpose()?;
+        if let Some(ref pattern) = args.pattern {
+            let re = regex::Regex::new(pattern)
+                .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+            return Ok(self.grep(&text, &re, range));
+        }
+        match args.unit.as_deref().unwrap_or("lines") {
+            "lines" => Ok(self.lines(&text, range.unwrap_or((1, None)))),
+            "bytes" => Ok(self.bytes(&text, range.unwrap_or((0, None)))),
+            other => Err(format!(
+                "Unknown unit '{}': use \"lines\" or \"bytes\"",
+                other
+            )),
+        }
+    }
+
+    fn lines(&self, text: &str, (start, end): (usize, Option<usize>)) -> String {
+        let lines: Vec<&str> = text.lines().collect();
+        let total = lines.len();
+        let start = start.max(1);
+        if start > total {
+            return format!(
+                "(no lines past {}; the artifact has {} lines)",
+                total, total
+            );
+        }
+        let end = end.unwrap_or(total).clamp(start, total);
+
+        let mut out = String::new();
+        let mut last = start - 1;
+        for line in &lines[start - 1..end] {
+            if !out.is_empty() && out.len() + line.len() + 1 > self.max_bytes {
+                break;
+            }
+            if line.len() > self.max_bytes {
+                let cut = floor_grapheme_boundary(line, self.max_bytes);
+                out.push_str(&line[..cut]);
+                out.push_str(" [... line continues; use unit=\"bytes\" ...]\n");
+            } else {
+                out.push_str(line);
+                out.push('\n');
+            }
+            last += 1;
+        }
+        out.push_str(&format!("\n---\nLines {}-{} of {}", start, last, total));
+        if last < end {
+            out.push_str(&format!(
+                "; size limit reached, continue with range=\"{}-{}\"",
+                last + 1,
+                end
+            ));
+        }
+        out
+    }
+
+    fn bytes(&self, text: &str, (start, end): (usize, Option<usize>)) -> String {
+        let total = text.len();
+        let start = floor_grapheme_boundary(text, start);
+        let end = end
+            .unwrap_or(total)
+            .min(start + self.max_bytes)
+            .clamp(start, total);
+        let end = floor_grapheme_boundary(text, end);
+        let mut out = text[start..end].to_string();
+        out.push_str(&format!("\n---\nBytes {}-{} of {}", start, end, total));
+        if end < total {
+            out.push_str(&format!(
+                "; continue with range=\"{}-{}\", unit=\"bytes\"",
+                end,
+                (end + self.max_bytes).min(total)
+            ));
+        }
+        out
+    }
+
+    fn grep(&self, text: &str, re: &regex::Regex, range: Option<(usize, Option<usize>)>) -> String {
+        let (start, end) = range.unwrap_or((1, None));
+        let mut out = String::new();
+        let mut shown = 0;
+        let mut total = 0;
+        for (i, line) in text.lines().enumerate() {
+            let number = i + 1;
+            if number < start || end.is_some_and(|end| number > end) || !re.is_match(line) {
+                continue;
+            }
+            total += 1;
+            if out.len() + line.len() > self.max_bytes {
+                continue;
+            }
+            out.push_str(&format!("{}: {}\n", number, line));
+            shown += 1;
+        }
+        if total == 0 {
+            return "No matching lines".to_string();
+        }
+        out.push_str(&format!("\n---\n{} matching lines", total));
+        if shown < total {
+            out.push_str(&format!(
+                " ({} shown; narrow the pattern or the range)",
+                shown
+            ));
+        }
+        out
+    }
+}
+
+/// "START-END", "START-" or "START", 1-based for lines.
+fn parse_range(range: &str) -> Result<(usize, Option<usize>), String> {
+    let invalid = || {
+        format!(
+            "Invalid range '{}': use \"START-END\", e.g. \"120-240\"",
+            range
+        )
+    };
+    let (start, end) = match range.trim().split_once('-') {
+        Some((start, end)) => (start.trim(), Some(end.trim()).filter(|e| !e.is_empty())),
+        None => (range.trim(), None),
+    };
+    let start = start.parse().map_err(|_| invalid())?;
+    let end = end.map(|e| e.parse().map_err(|_| invalid())).transpose()?;
+    if end.is_some_and(|end| end < start) {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+#[derive(Deserialize)]
+struct FetchArtifactArgs {
+    digest: String,
+    #[serde(default)]
+    range: Option<String>,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    pattern: Option<String>,
+}
+
+#[async_trait]
+impl Tool for FetchArtifactTool {
+    fn name(&self) -> &str {
+        "fetch_artifact"
+    }
+
+    fn description(&self) -> &str {
+        "Read part of a large tool output saved as an artifact"
+    }
+
+    fn tool_description(&self) -> &str {
+        FETCH_ARTIFACT_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "digest",
+                    PropertySchema::string("Artifact digest from the truncated result"),
+                    true,
+                )
+                .add_property(
+                    "range",
+                    PropertySchema::string(
+                        "\"START-END\" (inclusive; lines are 1-based, bytes 0-based). \
+                         Default: from the start",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "unit",
+                    PropertySchema::enum_string(
+                        "Whether range counts lines or bytes (default lines)",
+                        vec!["lines".into(), "bytes".into()],
+                    ),
+                    false,
+                )
+                .add_property(
+                    "pattern",
+                    PropertySchema::string(
+                        "Regex; return only matching lines with their numbers \
+                         (within range if given)",
+                    ),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let args: FetchArtifactArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("fetch_artifact", format!("Invalid arguments: {}", e)))?;
+        Ok(match self.run(args) {
+            Ok(text) => ToolOutput::success(text),
+            Err(e) => ToolOutput::error(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct BigOutput(String);
+
+    #[async_trait]
+    impl Tool for BigOutput {
+        fn name(&self) -> &str {
+            "big"
+        }
+
+        fn description(&self) -> &str {
+            "Large output"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new("big", "Large output")
+        }
+
+        async fn execute(&self, _arguments: Value) -> Result<ToolOutput, Error> {
+            Ok(ToolOutput::success(self.0.clone()))
+        }
+    }
+
+    fn log(lines: usize) -> String {
+        (1..=lines)
+            .map(|i| {
+                format!(
+                    "line {:04}: {}",
+                    i,
+                    if i == 700 { "CRITICAL" } else { "ok" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[tokio::test]
+    async fn test_offload_and_fetch() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ArtifactStore::open(dir.path()).unwrap());
+        let policy = OffloadPolicy {
+            threshold_bytes: 2_000,
+            preview_bytes: 400,
+        };
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(BigOutput(log(1000))));
+        let registry = offload(&registry, &store, policy);
+        let fetch = FetchArtifactTool::new(Arc::clone(&store), policy);
+
+        let out = registry
+            .get("big")
+            .unwrap()
+            .execute(json!({}))
+            .await
+            .unwrap();
+        let text = out.text_content();
+        assert!(text.len() < 1_000);
+        assert!(text.starts_with("line 0001: ok\n"));
+        assert!(text.contains("line 1000: ok\n"));
+        assert!(!text.contains("CRITICAL"));
+        let digest = text
+            .split("saved as artifact ")
+            .nth(1)
+            .unwrap()
+            .split('.')
+            .next()
+            .unwrap()
+            .to_string();
+        assert_eq!(digest.len(), SHORT_DIGEST);
+
+        // The omitted middle comes back exactly
+        let out = fetch
+            .execute(json!({"digest": digest, "range": "699-701"}))
+            .await
+            .unwrap();
+        assert_eq!(
+            out.text_content(),
+            "line 0699: ok\nline 0700: CRITICAL\nline 0701: ok\n\n---\nLines 699-701 of 1000"
+        );
+
+        let out = fetch
+            .execute(json!({"digest": digest, "pattern": "CRIT"}))
+            .await
+            .unwrap();
+        assert!(out.text_content().starts_with("700: line 0700: CRITICAL\n"));
+
+        // Capped results say where to continue
+        let out = fetch.execute(json!({"digest": digest})).await.unwrap();
+        assert!(out
+            .text_content()
+            .contains("continue with range=\"143-1000\""));
+
+        let out = fetch
+            .execute(json!({"digest": "deadbeef", "range": "1-2"}))
+            .await
+            .unwrap();
+        assert!(out.is_error);
+
+        // Identical outputs share one artifact; old ones are pruned
+        registry
+            .get("big")
+            .unwrap()
+            .execute(json!({}))
+            .await
+            .unwrap();
+        assert_eq!(store.digests().len(), 1);
+        assert_eq!(store.prune(Duration::from_secs(3600)), 0);
+        assert_eq!(store.prune(Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn test_single_line_preview_uses_bytes() {
+        let text = format!("{{\"data\": \"{}\"}}", "x".repeat(10_000));
+        let preview = preview(&text, &"a".repeat(64), 1_000);
+        assert!(preview.starts_with("{\"data\": \"xxx"));
+        assert!(preview.contains("range=\"1000-2000\", unit=\"bytes\""));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("120-240"), Ok((120, Some(240))));
+        assert_eq!(parse_range("5-"), Ok((5, None)));
+        assert_eq!(parse_range(" 7 "), Ok((7, None)));
+        assert!(parse_range("9-3").is_err());
+        assert!(parse_range("a-b").is_err());
+    }
+}
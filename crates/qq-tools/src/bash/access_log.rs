@@ -0,0 +1,534 @@
+//! Log of reads of files git does not track.
+//!
+//! Source files are tracked; `.env`, local credentials and build output
+//! usually are not. `read_files`, `outline_file` and read commands run
+//! through `run` report the files they read to an [`AccessLog`], which keeps
+//! every read of an untracked or ignored file under the project root so the
+//! user can see what the agent looked at beyond the source (`/access-log`,
+//! and a badge in the TUI status bar). With [`UntrackedReadPolicy::Ask`]
+//! each such read needs approval first; "allow for session" approves the
+//! file for the rest of the session.
+//!
+//! Reads outside the project root are not logged: they are limited to the
+//! sandbox mounts the user set up. Nothing is logged when the project root
+//! is not in a git work tree.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::parse::{self, SUBCOMMAND_TOOLS};
+use super::permissions::{ApprovalChannel, ApprovalResponse};
+use super::write_policy::is_assignment;
+
+/// Commands whose file arguments are read. Arguments that aren't existing
+/// files (patterns, scripts) are skipped.
+const READ_COMMANDS: &[&str] = &[
+    "cat", "tac", "head", "tail", "less", "more", "bat", "nl", "grep", "egrep", "fgrep", "rg",
+    "ag", "sed", "awk", "cut", "sort", "uniq", "wc", "diff", "cmp", "jq", "yq", "xxd", "od",
+    "hexdump", "strings", "base64", "md5sum", "sha1sum", "sha256sum", "source", ".",
+];
+
+/// Whether reads of untracked files need approval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UntrackedReadPolicy {
+    /// Record the read and let it through
+    #[default]
+    Log,
+    /// Ask before each read of a file not yet approved for the session
+    Ask,
+}
+
+/// How a file falls outside git's tracked set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    /// Not tracked and not ignored (e.g. a new file)
+    Untracked,
+    /// Matched by a `.gitignore` rule (e.g. `.env`, `target/`)
+    Ignored,
+}
+
+impl AccessKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccessKind::Untracked => "untracked",
+            AccessKind::Ignored => "ignored",
+        }
+    }
+}
+
+/// One logged file.
+#[derive(Debug, Clone)]
+pub struct AccessEntry {
+    /// Path relative to the project root
+    pub path: String,
+    pub kind: AccessKind,
+    /// Tools that read it, in first-use order
+    pub tools: Vec<String>,
+    pub reads: usize,
+    pub first_read: DateTime<Local>,
+    pub last_read: DateTime<Local>,
+}
+
+/// Tracked files of the project, refreshed when the git index changes.
+struct TrackedFiles {
+    index_modified: Option<SystemTime>,
+    files: HashSet<PathBuf>,
+}
+
+/// Session log of reads outside git's tracked set (see the module docs).
+pub struct AccessLog {
+    root: PathBuf,
+    policy: UntrackedReadPolicy,
+    approval: Option<ApprovalChannel>,
+    tracked: Mutex<Option<TrackedFiles>>,
+    entries: Mutex<Vec<AccessEntry>>,
+    /// Files approved for the session under [`UntrackedReadPolicy::Ask`]
+    approved: Mutex<HashSet<String>>,
+}
+
+impl AccessLog {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+            policy: UntrackedReadPolicy::Log,
+            approval: None,
+            tracked: Mutex::new(None),
+            entries: Mutex::new(Vec::new()),
+            approved: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Ask through `approval` before untracked reads when `policy` is `Ask`.
+    pub fn with_policy(mut self, policy: UntrackedReadPolicy, approval: ApprovalChannel) -> Self {
+        self.policy = policy;
+        self.approval = Some(approval);
+        self
+    }
+
+    pub fn policy(&self) -> UntrackedReadPolicy {
+        self.policy
+    }
+
+    /// The files read so far, in first-read order.
+    pub fn entries(&self) -> Vec<AccessEntry> {
+        self.entries.lock().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    /// Number of distinct files logged.
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|e| e.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check and record that `tool` is about to read `paths` (host paths).
+    ///
+    /// Returns the message to give the agent when the user denies the read.
+    pub async fn check(&self, tool: &str, paths: &[PathBuf]) -> Result<(), String> {
+        let mut flagged: Vec<(String, AccessKind)> = Vec::new();
+        for path in paths {
+            if let Some(found) = self.classify(path) {
+                if !flagged.contains(&found) {
+                    flagged.push(found);
+                }
+            }
+        }
+        if flagged.is_empty() {
+            return Ok(());
+        }
+
+        if self.policy == UntrackedReadPolicy::Ask {
+            self.approve(tool, &flagged).await?;
+        }
+        for (path, kind) in &flagged {
+            tracing::info!(tool = %tool, path = %path, kind = kind.label(), "Read outside tracked files");
+            self.record(tool, path, *kind);
+        }
+        Ok(())
+    }
+
+    /// Ask for the files not yet approved for the session.
+    async fn approve(&self, tool: &str, flagged: &[(String, AccessKind)]) -> Result<(), String> {
+        let pending: Vec<&(String, AccessKind)> = {
+            let approved = self.approved.lock().map_err(|_| "access log lock poisoned")?;
+            flagged.iter().filter(|(path, _)| !approved.contains(path)).collect()
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let Some(ref approval) = self.approval else {
+            return Ok(());
+        };
+
+        let files: Vec<&str> = pending.iter().map(|(path, _)| path.as_str()).collect();
+        let triggers = pending
+            .iter()
+            .map(|(path, kind)| format!("{} file: {}", kind.label(), path))
+            .collect();
+        let request = format!("{} reads {}", tool, files.join(", "));
+        match approval.request_approval(request, triggers, "File Read").await {
+            Ok(ApprovalResponse::Allow) => Ok(()),
+            Ok(ApprovalResponse::AllowForSession) => {
+                if let Ok(mut approved) = self.approved.lock() {
+                    approved.extend(files.iter().map(|f| f.to_string()));
+                }
+                Ok(())
+            }
+            Ok(ApprovalResponse::Deny(reason)) => Err(match reason {
+                Some(r) => format!("Read of {} denied by user: {}", files.join(", "), r),
+                None => format!(
+                    "Read of {} denied by user. These files are not tracked by git; \
+                     don't read them another way.",
+                    files.join(", ")
+                ),
+            }),
+            Err(e) => Err(format!("Approval system unavailable: {}", e)),
+        }
+    }
+
+    fn record(&self, tool: &str, path: &str, kind: AccessKind) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let now = Local::now();
+        match entries.iter_mut().find(|e| e.path == path) {
+            Some(entry) => {
+                entry.reads += 1;
+                entry.last_read = now;
+                if !entry.tools.iter().any(|t| t == tool) {
+                    entry.tools.push(tool.to_string());
+                }
+            }
+            None => entries.push(AccessEntry {
+                path: path.to_string(),
+                kind,
+                tools: vec![tool.to_string()],
+                reads: 1,
+                first_read: now,
+                last_read: now,
+            }),
+        }
+    }
+
+    /// The root-relative path and kind of `path` when it is a file under
+    /// the project root that git does not track.
+    pub fn classify(&self, path: &Path) -> Option<(String, AccessKind)> {
+        let canonical = path.canonicalize().ok()?;
+        if !canonical.is_file() {
+            return None;
+        }
+        let relative = canonical.strip_prefix(&self.root).ok()?.to_path_buf();
+        if !self.refresh_tracked() {
+            return None;
+        }
+        let tracked = self.tracked.lock().ok()?;
+        if tracked.as_ref()?.files.contains(&relative) {
+            return None;
+        }
+        drop(tracked);
+
+        let ignored = Command::new("git")
+            .arg("check-ignore")
+            .arg("-q")
+            .arg(&relative)
+            .current_dir(&self.root)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        let kind = if ignored {
+            AccessKind::Ignored
+        } else {
+            AccessKind::Untracked
+        };
+        Some((relative.to_string_lossy().into_owned(), kind))
+    }
+
+    /// Load the tracked set if the index changed; false outside a git work tree.
+    fn refresh_tracked(&self) -> bool {
+        let Ok(mut tracked) = self.tracked.lock() else {
+            return false;
+        };
+        let index_modified = git_index(&self.root).and_then(|p| p.metadata().ok()?.modified().ok());
+        if let Some(ref current) = *tracked {
+            if current.index_modified == index_modified && index_modified.is_some() {
+                return true;
+            }
+        }
+        let output = Command::new("git")
+            .args(["ls-files", "-z"])
+            .current_dir(&self.root)
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                let files = output
+                    .stdout
+                    .split(|&b| b == 0)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| PathBuf::from(String::from_utf8_lossy(name).into_owned()))
+                    .collect();
+                *tracked = Some(TrackedFiles {
+                    index_modified,
+                    files,
+                });
+                true
+            }
+            _ => {
+                *tracked = None;
+                false
+            }
+        }
+    }
+
+    /// Markdown table of the log for `/access-log`.
+    pub fn format(&self) -> String {
+        let entries = self.entries();
+        let policy = match self.policy {
+            UntrackedReadPolicy::Log => "logged",
+            UntrackedReadPolicy::Ask => "need approval",
+        };
+        if entries.is_empty() {
+            return format!(
+                "No reads of untracked or ignored files this session (such reads are {}).",
+                policy
+            );
+        }
+        let mut out = format!(
+            "Files read outside git's tracked set ({}, reads {}):\n\n\
+             | File | Kind | Reads | Tools | Last read |\n\
+             |------|------|-------|-------|-----------|\n",
+            entries.len(),
+            policy
+        );
+        for entry in &entries {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                entry.path,
+                entry.kind.label(),
+                entry.reads,
+                entry.tools.join(", "),
+                entry.last_read.format("%H:%M:%S")
+            ));
+        }
+        out
+    }
+}
+
+/// Path of the git index for the work tree containing `root`.
+fn git_index(root: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "index"])
+        .current_dir(root)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    Some(if path.is_absolute() {
+        path
+    } else {
+        root.join(path)
+    })
+}
+
+/// Files that read commands in `command` take as arguments or `<` input,
+/// resolved from `cwd`. Only existing files are returned; a pattern that
+/// happens to name one counts as a read.
+pub fn read_paths(command: &str, cwd: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    for segment in parse::split_pipeline(command.trim()) {
+        // A heredoc body is data, not arguments
+        let line = if segment.contains("<<") {
+            segment.lines().next().unwrap_or_default().to_string()
+        } else {
+            segment.replace('\n', " ")
+        };
+        let tokens = parse::tokenize(&line)
+            .unwrap_or_else(|_| line.split_whitespace().map(String::from).collect());
+
+        let mut candidates = Vec::new();
+        let mut reads_args = false;
+        let mut words = 0;
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            if token == "<" {
+                candidates.extend(tokens.next());
+                continue;
+            }
+            if token == "<<" || token == "<<<" {
+                tokens.next();
+                continue;
+            }
+            if let Some(input) = token.strip_prefix('<') {
+                if !input.starts_with('<') && !input.starts_with('(') {
+                    candidates.push(input.to_string());
+                }
+                continue;
+            }
+            if token.contains('>') {
+                // Output redirect; its target (if separate) is written, not read
+                if token.ends_with('>') {
+                    tokens.next();
+                }
+                continue;
+            }
+            if words == 0 && is_assignment(&token) {
+                continue;
+            }
+            words += 1;
+            if words == 1 {
+                let base = token.rsplit('/').next().unwrap_or(&token).to_string();
+                reads_args = READ_COMMANDS.contains(&base.as_str());
+                if SUBCOMMAND_TOOLS.contains(&base.as_str())
+                    && tokens.peek().is_some_and(|next| !next.starts_with('-'))
+                {
+                    tokens.next();
+                }
+                continue;
+            }
+            if reads_args && !token.starts_with('-') {
+                candidates.push(token);
+            }
+        }
+
+        for candidate in candidates {
+            let path = cwd.join(&candidate);
+            if path.is_file() && seen.insert(path.clone()) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approval::create_approval_channel;
+
+    fn git(root: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+
+    /// A repo with `src/main.rs` tracked, `.env` ignored and `notes.txt` new.
+    fn repo() -> Option<tempfile::TempDir> {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join(".gitignore"), ".env\n").unwrap();
+        std::fs::write(root.join(".env"), "TOKEN=secret\n").unwrap();
+        std::fs::write(root.join("notes.txt"), "todo\n").unwrap();
+        (git(root, &["init", "-q"]) && git(root, &["add", "src", ".gitignore"])).then_some(dir)
+    }
+
+    #[test]
+    fn test_read_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(".env"), "A=1\n").unwrap();
+        std::fs::write(root.join("input.txt"), "x\n").unwrap();
+
+        assert_eq!(read_paths("cat .env", root), vec![root.join(".env")]);
+        assert_eq!(
+            read_paths("grep -n TOKEN .env | head -5 && wc -l < input.txt", root),
+            vec![root.join(".env"), root.join("input.txt")]
+        );
+        // Patterns, missing files, write targets and non-read commands
+        assert!(read_paths("grep TOKEN missing.txt", root).is_empty());
+        assert!(read_paths("echo hi > input.txt", root).is_empty());
+        assert!(read_paths("rm .env", root).is_empty());
+        assert!(read_paths("git log -- .env", root).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_logs_untracked_and_ignored_reads() {
+        let Some(dir) = repo() else {
+            return; // git unavailable
+        };
+        let root = dir.path();
+        let log = AccessLog::new(root);
+
+        assert_eq!(log.classify(&root.join("src/main.rs")), None);
+        assert_eq!(
+            log.classify(&root.join(".env")),
+            Some((".env".to_string(), AccessKind::Ignored))
+        );
+        assert_eq!(
+            log.classify(&root.join("notes.txt")),
+            Some(("notes.txt".to_string(), AccessKind::Untracked))
+        );
+        assert_eq!(log.classify(&root.join("src")), None);
+
+        let files = [root.join("src/main.rs"), root.join(".env")];
+        log.check("read_files", &files).await.unwrap();
+        log.check("run", &[root.join(".env")]).await.unwrap();
+        let entries = log.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, ".env");
+        assert_eq!(entries[0].reads, 2);
+        assert_eq!(entries[0].tools, vec!["read_files", "run"]);
+        assert!(log.format().contains("| .env | ignored | 2 | read_files, run |"));
+
+        // Newly tracked files drop out once the index changes
+        assert!(git(root, &["add", "notes.txt"]));
+        assert_eq!(log.classify(&root.join("notes.txt")), None);
+    }
+
+    #[tokio::test]
+    async fn test_ask_policy() {
+        let Some(dir) = repo() else {
+            return;
+        };
+        let root = dir.path();
+        let (approval, mut rx) = create_approval_channel();
+        let log = AccessLog::new(root).with_policy(UntrackedReadPolicy::Ask, approval);
+
+        let answers = tokio::spawn(async move {
+            let mut triggers = Vec::new();
+            for response in [
+                ApprovalResponse::Deny(None),
+                ApprovalResponse::AllowForSession,
+            ] {
+                let request = rx.recv().await.unwrap();
+                triggers.push(request.trigger_commands.clone());
+                request.response_tx.send(response).unwrap();
+            }
+            triggers
+        });
+
+        let env = [root.join(".env")];
+        let denied = log.check("read_files", &env).await.unwrap_err();
+        assert!(denied.contains("denied by user"));
+        assert!(log.is_empty());
+        log.check("read_files", &env).await.unwrap();
+        // Approved for the session: no third prompt
+        log.check("run", &env).await.unwrap();
+        // Tracked files never prompt
+        log.check("run", &[root.join("src/main.rs")]).await.unwrap();
+
+        let triggers = answers.await.unwrap();
+        assert_eq!(triggers[0], vec!["ignored file: .env"]);
+        assert_eq!(log.entries()[0].reads, 2);
+    }
+}
@@ -0,0 +1,863 @@
+//! Compile checks for the coder.
+//!
+//! `check_build` runs the project's type checker (`cargo check`, `tsc
+//! --noEmit`, or pyright) in the read-only sandbox and returns its
+//! diagnostics as records: severity, error code, file and span, message, and
+//! any fix the compiler suggests. The model can iterate on compile errors
+//! from exact line numbers instead of reading raw compiler output.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use super::linters::{
+    at_project_root, first_line, in_paths, relative_to, scope_paths, shell_quote, tail, Severity,
+};
+use super::mounts::SandboxMounts;
+use super::sandbox::{SandboxExecutor, SandboxPathPolicy};
+
+/// Default timeout. `cargo check` builds dependencies on the first run.
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// Upper bound for a caller-supplied `timeout`.
+const MAX_TIMEOUT_SECS: u64 = 900;
+
+/// Default cap on diagnostics returned.
+const DEFAULT_MAX_DIAGNOSTICS: usize = 50;
+
+/// Cargo target directory, inside the session `/tmp` so the read-only
+/// project root isn't written and later checks build incrementally.
+const CHECK_TARGET_DIR: &str = "/tmp/qq-check-target";
+
+/// Notes and help lines kept per diagnostic.
+const MAX_NOTES: usize = 3;
+
+/// Output kept when a checker fails without diagnostics.
+const MAX_ERROR_CHARS: usize = 1500;
+
+const CHECK_BUILD_TOOL_DESC: &str = "\
+Type-check the project and return the compiler's diagnostics as records, grouped by file:
+  line:col-end_line:end_col severity[code] message
+followed by indented notes and suggested fixes (`fix: replace L:C-L:C with `...``).
+
+Detects cargo check (Cargo.toml), tsc --noEmit (tsconfig.json plus \
+node_modules/.bin/tsc), and pyright (pyrightconfig.json or [tool.pyright] in \
+pyproject.toml). Runs in the read-only sandbox; cargo builds into a scratch target \
+directory, so the first check of a Rust project can take minutes.
+
+Use after editing code instead of running the compiler through `run`: the line numbers \
+and fixes come straight from the compiler. Only errors are listed unless warnings is \
+true; the summary line always counts both. Pass paths (relative to the working directory) \
+to keep only diagnostics in those files or directories.";
+
+/// A supported type checker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Checker {
+    Cargo,
+    Tsc,
+    Pyright,
+}
+
+impl Checker {
+    pub const ALL: &'static [Checker] = &[Checker::Cargo, Checker::Tsc, Checker::Pyright];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Checker::Cargo => "cargo",
+            Checker::Tsc => "tsc",
+            Checker::Pyright => "pyright",
+        }
+    }
+
+    /// Parse a checker name: `cargo`, `tsc`, or `pyright`.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Whether the project at `root` is set up for this checker.
+    fn is_configured(&self, root: &Path) -> bool {
+        match self {
+            Checker::Cargo => root.join("Cargo.toml").is_file(),
+            Checker::Tsc => {
+                root.join("tsconfig.json").is_file() && root.join("node_modules/.bin/tsc").exists()
+            }
+            Checker::Pyright => {
+                root.join("pyrightconfig.json").is_file()
+                    || std::fs::read_to_string(root.join("pyproject.toml"))
+                        .is_ok_and(|s| s.contains("[tool.pyright"))
+            }
+        }
+    }
+
+    /// Shell command printing diagnostics to stdout. Pyright is given
+    /// `paths` when set; cargo and tsc check the whole project (tsc ignores
+    /// tsconfig.json when given files) and are filtered afterwards.
+    fn command(&self, paths: &[String]) -> String {
+        match self {
+            Checker::Cargo => format!(
+                "cargo check --workspace --all-targets --message-format=json --quiet --target-dir {}",
+                CHECK_TARGET_DIR
+            ),
+            Checker::Tsc => "node_modules/.bin/tsc --noEmit --pretty false".to_string(),
+            Checker::Pyright if paths.is_empty() => "pyright --outputjson".to_string(),
+            Checker::Pyright => format!(
+                "pyright --outputjson {}",
+                paths
+                    .iter()
+                    .map(|p| shell_quote(p))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        }
+    }
+
+    fn parse_output(&self, stdout: &str, root: &Path) -> Result<Vec<BuildDiagnostic>, String> {
+        match self {
+            Checker::Cargo => Ok(parse_cargo(stdout, root)),
+            Checker::Tsc => Ok(parse_tsc(stdout, root)),
+            Checker::Pyright => parse_pyright(stdout, root),
+        }
+    }
+}
+
+/// Checkers the project at `root` is configured for.
+pub fn detect_checkers(root: &Path) -> Vec<Checker> {
+    Checker::ALL
+        .iter()
+        .copied()
+        .filter(|c| c.is_configured(root))
+        .collect()
+}
+
+/// A 1-based line and column range; the end column is exclusive, as rustc
+/// reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    fn point(line: usize, column: usize) -> Self {
+        Self {
+            line,
+            column,
+            end_line: line,
+            end_column: column,
+        }
+    }
+
+    fn render(&self) -> String {
+        if (self.line, self.column) == (self.end_line, self.end_column) {
+            format!("{}:{}", self.line, self.column)
+        } else {
+            format!(
+                "{}:{}-{}:{}",
+                self.line, self.column, self.end_line, self.end_column
+            )
+        }
+    }
+}
+
+/// A fix the compiler proposes: replace `span` with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// One compiler diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BuildDiagnostic {
+    pub checker: Checker,
+    /// Path relative to the project root
+    pub file: String,
+    pub span: Span,
+    pub severity: Severity,
+    /// Error code (`E0308`, `TS2322`, `reportAttributeAccessIssue`)
+    pub code: Option<String>,
+    pub message: String,
+    /// Span label, notes, and help without a fix
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// Cargo's `--message-format=json` stream. Rustc's `children` carry notes
+/// and help; help with a `suggested_replacement` becomes a [`Suggestion`].
+fn parse_cargo(stdout: &str, root: &Path) -> Vec<BuildDiagnostic> {
+    #[derive(Deserialize)]
+    struct Line {
+        reason: String,
+        message: Option<Message>,
+    }
+    #[derive(Deserialize)]
+    struct Message {
+        message: String,
+        level: String,
+        code: Option<Code>,
+        #[serde(default)]
+        spans: Vec<RustcSpan>,
+        #[serde(default)]
+        children: Vec<Message>,
+    }
+    #[derive(Deserialize)]
+    struct Code {
+        code: String,
+    }
+    #[derive(Deserialize)]
+    struct RustcSpan {
+        file_name: String,
+        line_start: usize,
+        column_start: usize,
+        line_end: usize,
+        column_end: usize,
+        is_primary: bool,
+        label: Option<String>,
+        suggested_replacement: Option<String>,
+    }
+    impl RustcSpan {
+        fn span(&self) -> Span {
+            Span {
+                line: self.line_start,
+                column: self.column_start,
+                end_line: self.line_end,
+                end_column: self.column_end,
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    for line in stdout.lines() {
+        let Ok(line) = serde_json::from_str::<Line>(line) else {
+            continue;
+        };
+        let Some(message) = line.message.filter(|_| line.reason == "compiler-message") else {
+            continue;
+        };
+        let severity = match message.level.as_str() {
+            "error" | "error: internal compiler error" => Severity::Error,
+            "warning" => Severity::Warning,
+            _ => continue,
+        };
+        // Summaries ("aborting due to ...") have no span
+        let Some(primary) = message.spans.iter().find(|s| s.is_primary) else {
+            continue;
+        };
+        let Some(file) = relative_to(root, &primary.file_name) else {
+            continue;
+        };
+
+        let mut notes: Vec<String> = primary
+            .label
+            .iter()
+            .filter(|label| !label.is_empty())
+            .cloned()
+            .collect();
+        let mut suggestions = Vec::new();
+        for child in &message.children {
+            let fixes: Vec<&RustcSpan> = child
+                .spans
+                .iter()
+                .filter(|s| s.suggested_replacement.is_some() && s.file_name == primary.file_name)
+                .collect();
+            if fixes.is_empty() {
+                notes.push(format!("{}: {}", child.level, first_line(&child.message)));
+            }
+            for span in fixes {
+                suggestions.push(Suggestion {
+                    message: first_line(&child.message),
+                    span: span.span(),
+                    replacement: span.suggested_replacement.clone().unwrap_or_default(),
+                });
+            }
+        }
+        notes.truncate(MAX_NOTES);
+
+        out.push(BuildDiagnostic {
+            checker: Checker::Cargo,
+            file,
+            span: primary.span(),
+            severity,
+            code: message.code.map(|c| c.code),
+            message: first_line(&message.message),
+            notes,
+            suggestions,
+        });
+    }
+    out
+}
+
+/// `tsc --pretty false`: `file(line,col): error TS2322: message`, with
+/// indented continuation lines for message chains.
+fn parse_tsc(stdout: &str, root: &Path) -> Vec<BuildDiagnostic> {
+    fn parse_line(line: &str) -> Option<(&str, Span, Severity, &str, &str)> {
+        let (location, rest) = line.split_once("): ")?;
+        let (file, position) = location.rsplit_once('(')?;
+        let (row, column) = position.split_once(',')?;
+        let (severity, rest) = rest.split_once(' ')?;
+        let (code, message) = rest.split_once(": ")?;
+        let severity = match severity {
+            "error" => Severity::Error,
+            "warning" => Severity::Warning,
+            _ => return None,
+        };
+        if !code.starts_with("TS") {
+            return None;
+        }
+        let span = Span::point(row.parse().ok()?, column.parse().ok()?);
+        Some((file, span, severity, code, message))
+    }
+
+    let mut out: Vec<BuildDiagnostic> = Vec::new();
+    for line in stdout.lines() {
+        if line.starts_with(' ') {
+            // Only continuations of a diagnostic we kept
+            if let Some(last) = out.last_mut() {
+                if last.notes.len() < MAX_NOTES && !line.trim().is_empty() {
+                    last.notes.push(line.trim().to_string());
+                }
+            }
+            continue;
+        }
+        let Some((file, span, severity, code, message)) = parse_line(line) else {
+            continue;
+        };
+        let Some(file) = relative_to(root, file) else {
+            continue;
+        };
+        out.push(BuildDiagnostic {
+            checker: Checker::Tsc,
+            file,
+            span,
+            severity,
+            code: Some(code.to_string()),
+            message: message.trim().to_string(),
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+        });
+    }
+    out
+}
+
+/// Pyright's `--outputjson`. Its ranges are 0-based.
+fn parse_pyright(stdout: &str, root: &Path) -> Result<Vec<BuildDiagnostic>, String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Report {
+        #[serde(default)]
+        general_diagnostics: Vec<Diagnostic>,
+    }
+    #[derive(Deserialize)]
+    struct Diagnostic {
+        file: String,
+        severity: String,
+        message: String,
+        range: Option<Range>,
+        rule: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Range {
+        start: Position,
+        end: Position,
+    }
+    #[derive(Deserialize)]
+    struct Position {
+        line: usize,
+        character: usize,
+    }
+
+    let report: Report = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("unexpected pyright output: {}", e))?;
+    Ok(report
+        .general_diagnostics
+        .into_iter()
+        .filter_map(|d| {
+            let severity = match d.severity.as_str() {
+                "error" => Severity::Error,
+                "warning" => Severity::Warning,
+                _ => return None,
+            };
+            let span = d.range.map_or(Span::point(1, 1), |r| Span {
+                line: r.start.line + 1,
+                column: r.start.character + 1,
+                end_line: r.end.line + 1,
+                end_column: r.end.character + 1,
+            });
+            let mut lines = d.message.lines();
+            let message = lines.next().unwrap_or("").trim().to_string();
+            let notes = lines
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .take(MAX_NOTES)
+                .collect();
+            Some(BuildDiagnostic {
+                checker: Checker::Pyright,
+                file: relative_to(root, &d.file)?,
+                span,
+                severity,
+                code: d.rule,
+                message,
+                notes,
+                suggestions: Vec::new(),
+            })
+        })
+        .collect())
+}
+
+/// Errors and warnings a checker found, or why it failed.
+type CheckStatus = Result<(usize, usize), String>;
+
+/// Render the check: a summary line per checker, then the diagnostics
+/// grouped by file, errors first when `max` cuts the list.
+fn format_report(
+    statuses: &[(Checker, CheckStatus)],
+    diagnostics: Vec<BuildDiagnostic>,
+    warnings: bool,
+    max: usize,
+) -> String {
+    let mut out = String::new();
+    for (checker, status) in statuses {
+        match status {
+            Ok((errors, warnings)) => out.push_str(&format!(
+                "{}: {} error(s), {} warning(s)\n",
+                checker.name(),
+                errors,
+                warnings
+            )),
+            Err(e) => out.push_str(&format!("{}: failed: {}\n", checker.name(), e)),
+        }
+    }
+
+    let mut shown: Vec<BuildDiagnostic> = diagnostics
+        .into_iter()
+        .filter(|d| warnings || d.severity == Severity::Error)
+        .collect();
+    let total = shown.len();
+    shown.sort_by(|a, b| {
+        (a.severity, &a.file, a.span.line, a.span.column).cmp(&(
+            b.severity,
+            &b.file,
+            b.span.line,
+            b.span.column,
+        ))
+    });
+    shown.truncate(max);
+
+    let mut by_file: BTreeMap<&str, Vec<&BuildDiagnostic>> = BTreeMap::new();
+    for d in &shown {
+        by_file.entry(d.file.as_str()).or_default().push(d);
+    }
+    for (file, mut entries) in by_file {
+        entries.sort_by_key(|d| (d.span.line, d.span.column));
+        out.push_str(&format!("\n===== {} =====\n", file));
+        for d in entries {
+            let code = d
+                .code
+                .as_deref()
+                .map(|c| format!("[{}]", c))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "{} {}{} {}\n",
+                d.span.render(),
+                d.severity.as_str(),
+                code,
+                d.message
+            ));
+            for note in &d.notes {
+                out.push_str(&format!("  {}\n", note));
+            }
+            for s in &d.suggestions {
+                out.push_str(&format!(
+                    "  fix: {}: replace {} with `{}`\n",
+                    s.message,
+                    s.span.render(),
+                    s.replacement
+                ));
+            }
+        }
+    }
+
+    let hidden_warnings = statuses
+        .iter()
+        .filter_map(|(_, s)| s.as_ref().ok())
+        .map(|(_, w)| w)
+        .sum::<usize>();
+    if total > shown.len() {
+        out.push_str(&format!(
+            "\n[{} of {} diagnostics not shown; pass paths or raise max_diagnostics]\n",
+            total - shown.len(),
+            total
+        ));
+    } else if total == 0 && statuses.iter().all(|(_, s)| s.is_ok()) {
+        if !warnings && hidden_warnings > 0 {
+            out.push_str("\nNo errors. Pass warnings: true to list the warnings.\n");
+        } else {
+            out.push_str("\nNo errors.\n");
+        }
+    }
+    out.trim_end().to_string()
+}
+
+#[derive(Deserialize)]
+struct CheckArgs {
+    #[serde(default)]
+    checkers: Vec<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    warnings: bool,
+    #[serde(default)]
+    max_diagnostics: Option<usize>,
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+/// Tool running the project's type checker in the read-only sandbox.
+pub struct CheckBuildTool {
+    mounts: Arc<SandboxMounts>,
+    path_policy: Arc<RwLock<SandboxPathPolicy>>,
+    executor: SandboxExecutor,
+}
+
+impl CheckBuildTool {
+    pub fn new(
+        mounts: Arc<SandboxMounts>,
+        path_policy: Arc<RwLock<SandboxPathPolicy>>,
+        executor: SandboxExecutor,
+    ) -> Self {
+        Self {
+            mounts,
+            path_policy,
+            executor,
+        }
+    }
+
+    /// Run one checker; its diagnostics, or why it produced none.
+    async fn run_checker(
+        &self,
+        checker: Checker,
+        paths: &[String],
+        timeout: u64,
+        policy: &SandboxPathPolicy,
+    ) -> Result<Vec<BuildDiagnostic>, String> {
+        let root = self.mounts.project_root();
+        let command = at_project_root(&self.mounts, checker.command(paths));
+        tracing::info!(checker = checker.name(), command = %command, "Running build check");
+        let result = self
+            .executor
+            .execute(&command, &self.mounts, timeout, policy, None, true)
+            .await?;
+        if let Some(e) = result.sandbox_error {
+            return Err(format!("sandbox error: {}", e));
+        }
+        if result.timed_out {
+            return Err(format!("timed out after {}s", timeout));
+        }
+        if result.exit_code == 127 {
+            return Err("not installed in the sandbox".to_string());
+        }
+        // tsc reports config errors on stdout
+        let failure_output = if result.stderr.trim().is_empty() {
+            &result.stdout
+        } else {
+            &result.stderr
+        };
+        match checker.parse_output(&result.stdout, root) {
+            // Nothing parseable from a failed run: the check didn't get to
+            // the code (manifest errors, missing dependencies)
+            Ok(diagnostics)
+                if diagnostics.is_empty()
+                    && result.exit_code != 0
+                    && !failure_output.trim().is_empty() =>
+            {
+                Err(tail(failure_output, MAX_ERROR_CHARS))
+            }
+            Ok(diagnostics) => {
+                let mut seen = HashSet::new();
+                // --all-targets reports a lib's diagnostics once per target
+                Ok(diagnostics
+                    .into_iter()
+                    .filter(|d| in_paths(&d.file, paths) && seen.insert(d.clone()))
+                    .collect())
+            }
+            Err(e) if !result.stderr.trim().is_empty() => Err(format!(
+                "{} (exit code {}): {}",
+                e,
+                result.exit_code,
+                tail(&result.stderr, MAX_ERROR_CHARS)
+            )),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for CheckBuildTool {
+    fn name(&self) -> &str {
+        "check_build"
+    }
+
+    fn description(&self) -> &str {
+        "Type-check the project (cargo check, tsc, pyright) and return structured compiler diagnostics"
+    }
+
+    fn tool_description(&self) -> &str {
+        CHECK_BUILD_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "checkers",
+                    PropertySchema::array(
+                        "Checkers to run (default: all configured)",
+                        PropertySchema::enum_string(
+                            "Checker",
+                            Checker::ALL.iter().map(|c| c.name().to_string()).collect(),
+                        ),
+                    ),
+                    false,
+                )
+                .add_property(
+                    "paths",
+                    PropertySchema::array(
+                        "Files or directories, relative to the working directory, to keep diagnostics for",
+                        PropertySchema::string("Path"),
+                    ),
+                    false,
+                )
+                .add_property(
+                    "warnings",
+                    PropertySchema::boolean("List warnings as well as errors (default false)"),
+                    false,
+                )
+                .add_property(
+                    "max_diagnostics",
+                    PropertySchema::integer("Most diagnostics returned (default 50)"),
+                    false,
+                )
+                .add_property(
+                    "timeout",
+                    PropertySchema::integer("Timeout per checker in seconds (default 300, max 900)"),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: CheckArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("check_build", format!("Invalid arguments: {}", e)))?;
+
+        // Builds run project code (build scripts, proc macros)
+        if !self.executor.supports_shell() {
+            return Ok(ToolOutput::error(
+                "check_build is not available in app-level sandbox mode. \
+                 Kernel sandbox (Linux with user namespaces) is required.",
+            ));
+        }
+
+        let paths = match scope_paths(&self.mounts, &args.paths) {
+            Ok(paths) => paths,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let root = self.mounts.project_root();
+        let detected = detect_checkers(root);
+        let checkers = if args.checkers.is_empty() {
+            if detected.is_empty() {
+                return Ok(ToolOutput::error(
+                    "No type checker found (looked for Cargo.toml, tsconfig.json with \
+                     node_modules/.bin/tsc, and pyrightconfig.json or [tool.pyright] in \
+                     pyproject.toml).",
+                ));
+            }
+            detected
+        } else {
+            let mut checkers = Vec::new();
+            for name in &args.checkers {
+                let Some(checker) = Checker::parse(name) else {
+                    return Ok(ToolOutput::error(format!(
+                        "Unknown checker '{}' (expected cargo, tsc, or pyright)",
+                        name
+                    )));
+                };
+                if !detected.contains(&checker) {
+                    return Ok(ToolOutput::error(format!(
+                        "{} is not configured in this project",
+                        checker.name()
+                    )));
+                }
+                if !checkers.contains(&checker) {
+                    checkers.push(checker);
+                }
+            }
+            checkers
+        };
+
+        let timeout = args
+            .timeout
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS);
+        let policy = match self.path_policy.read() {
+            Ok(p) => p.clone(),
+            Err(_) => return Ok(ToolOutput::error("Path policy lock poisoned.")),
+        };
+
+        let mut statuses = Vec::new();
+        let mut diagnostics = Vec::new();
+        for checker in checkers {
+            match self.run_checker(checker, &paths, timeout, &policy).await {
+                Ok(found) => {
+                    let errors = found
+                        .iter()
+                        .filter(|d| d.severity == Severity::Error)
+                        .count();
+                    statuses.push((checker, Ok((errors, found.len() - errors))));
+                    diagnostics.extend(found);
+                }
+                Err(e) => statuses.push((checker, Err(e))),
+            }
+        }
+
+        let failed = statuses.iter().all(|(_, s)| s.is_err());
+        let report = format_report(
+            &statuses,
+            diagnostics,
+            args.warnings,
+            args.max_diagnostics
+                .unwrap_or(DEFAULT_MAX_DIAGNOSTICS)
+                .max(1),
+        );
+        Ok(if failed {
+            ToolOutput::error(report)
+        } else {
+            ToolOutput::success(report)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_checkers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        assert!(detect_checkers(root).is_empty());
+
+        std::fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(root.join("pyproject.toml"), "[tool.pyright]\nstrict = []\n").unwrap();
+        // tsconfig.json without the local compiler doesn't count
+        std::fs::write(root.join("tsconfig.json"), "{}\n").unwrap();
+        assert_eq!(
+            detect_checkers(root),
+            vec![Checker::Cargo, Checker::Pyright]
+        );
+
+        std::fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+        std::fs::write(root.join("node_modules/.bin/tsc"), "").unwrap();
+        assert_eq!(detect_checkers(root).len(), 3);
+        assert_eq!(Checker::parse(" TSC"), Some(Checker::Tsc));
+        assert_eq!(Checker::parse("mypy"), None);
+    }
+
+    #[test]
+    fn test_parse_cargo_with_suggestions() {
+        let root = Path::new("/work/proj");
+        let stdout = [
+            r#"{"reason":"compiler-artifact","package_id":"x"}"#,
+            r#"{"reason":"compiler-message","message":{"message":"mismatched types","level":"error","code":{"code":"E0308"},"spans":[{"file_name":"src/lib.rs","line_start":4,"column_start":18,"line_end":4,"column_end":25,"is_primary":true,"label":"expected `u32`, found `&str`","suggested_replacement":null}],"children":[{"message":"try parsing the string","level":"help","spans":[{"file_name":"src/lib.rs","line_start":4,"column_start":18,"line_end":4,"column_end":25,"is_primary":true,"label":null,"suggested_replacement":"\"42\".parse().unwrap()"}],"children":[]},{"message":"expected due to this","level":"note","spans":[],"children":[]}]}}"#,
+            r#"{"reason":"compiler-message","message":{"message":"unused variable: `x`","level":"warning","code":{"code":"unused_variables"},"spans":[{"file_name":"/work/proj/src/main.rs","line_start":2,"column_start":9,"line_end":2,"column_end":10,"is_primary":true,"label":null,"suggested_replacement":null}],"children":[]}}"#,
+            r#"{"reason":"compiler-message","message":{"message":"aborting due to 1 previous error","level":"error","code":null,"spans":[],"children":[]}}"#,
+        ]
+        .join("\n");
+        let diagnostics = parse_cargo(&stdout, root);
+        assert_eq!(diagnostics.len(), 2);
+        let error = &diagnostics[0];
+        assert_eq!(error.code.as_deref(), Some("E0308"));
+        assert_eq!(error.span.render(), "4:18-4:25");
+        assert_eq!(
+            error.notes,
+            vec!["expected `u32`, found `&str`", "note: expected due to this"]
+        );
+        assert_eq!(error.suggestions[0].replacement, "\"42\".parse().unwrap()");
+        assert_eq!(diagnostics[1].file, "src/main.rs");
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_parse_tsc_and_pyright() {
+        let root = Path::new("/work/proj");
+        let tsc = "\
+src/app.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.
+  Type 'x' is not assignable.
+src/util.ts(10,1): error TS1005: ';' expected.
+error TS5083: Cannot read file 'tsconfig.base.json'.
+";
+        let diagnostics = parse_tsc(tsc, root);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("TS2322"));
+        assert_eq!(diagnostics[0].span.render(), "3:7");
+        assert_eq!(diagnostics[0].notes, vec!["Type 'x' is not assignable."]);
+        assert_eq!(diagnostics[1].file, "src/util.ts");
+
+        let pyright = r#"{"version":"1.1","generalDiagnostics":[
+            {"file":"/work/proj/pkg/a.py","severity":"error","message":"Cannot access attribute \"foo\"\n  Attribute \"foo\" is unknown","range":{"start":{"line":4,"character":8},"end":{"line":4,"character":11}},"rule":"reportAttributeAccessIssue"},
+            {"file":"/work/proj/pkg/a.py","severity":"information","message":"ignored","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":1}}}],
+            "summary":{"errorCount":1}}"#;
+        let diagnostics = parse_pyright(pyright, root).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span.render(), "5:9-5:12");
+        assert_eq!(diagnostics[0].notes, vec!["Attribute \"foo\" is unknown"]);
+        assert!(parse_pyright("Error: no config", root).is_err());
+    }
+
+    #[test]
+    fn test_format_report() {
+        let root = Path::new("/work/proj");
+        let stdout = r#"{"reason":"compiler-message","message":{"message":"cannot find value `y`","level":"error","code":{"code":"E0425"},"spans":[{"file_name":"src/lib.rs","line_start":7,"column_start":5,"line_end":7,"column_end":6,"is_primary":true,"label":"not found in this scope","suggested_replacement":null}],"children":[{"message":"a local variable with a similar name exists","level":"help","spans":[{"file_name":"src/lib.rs","line_start":7,"column_start":5,"line_end":7,"column_end":6,"is_primary":true,"label":null,"suggested_replacement":"x"}],"children":[]}]}}
+{"reason":"compiler-message","message":{"message":"unused import","level":"warning","code":null,"spans":[{"file_name":"src/a.rs","line_start":1,"column_start":5,"line_end":1,"column_end":9,"is_primary":true,"label":null,"suggested_replacement":null}],"children":[]}}"#;
+        let diagnostics = parse_cargo(stdout, root);
+        let statuses = vec![(Checker::Cargo, Ok((1, 1)))];
+        let report = format_report(&statuses, diagnostics.clone(), false, 50);
+        assert_eq!(
+            report,
+            "cargo: 1 error(s), 1 warning(s)\n\n===== src/lib.rs =====\n\
+             7:5-7:6 error[E0425] cannot find value `y`\n  not found in this scope\n  \
+             fix: a local variable with a similar name exists: replace 7:5-7:6 with `x`"
+        );
+
+        let with_warnings = format_report(&statuses, diagnostics.clone(), true, 1);
+        assert!(with_warnings.contains("===== src/lib.rs ====="));
+        assert!(!with_warnings.contains("src/a.rs"));
+        assert!(with_warnings
+            .ends_with("[1 of 2 diagnostics not shown; pass paths or raise max_diagnostics]"));
+
+        let warnings_only: Vec<BuildDiagnostic> = diagnostics
+            .into_iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .collect();
+        let clean = format_report(&[(Checker::Cargo, Ok((0, 1)))], warnings_only, false, 50);
+        assert!(clean.ends_with("No errors. Pass warnings: true to list the warnings."));
+    }
+}
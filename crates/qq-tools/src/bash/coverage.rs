@@ -0,0 +1,886 @@
+//! Test coverage of changed lines.
+//!
+//! `map_test_coverage` diffs the worktree against the session's starting
+//! point (or a given `base`), looks each changed line up in the project's
+//! coverage data, and reports which lines no test executes and, when the
+//! data records it per test, which tests cover the rest. The reviewer can
+//! flag untested changes by line instead of guessing, and the coder can run
+//! just the tests that exercise its edit.
+//!
+//! Coverage is read from lcov files (`lcov.info`, `coverage/lcov.info`,
+//! `target/llvm-cov/lcov.info`, ...) or coverage.py JSON (`coverage.json`,
+//! with test contexts when recorded). When none exists, or `regenerate` is
+//! set, it is produced in the sandbox with cargo-llvm-cov, pytest-cov, or
+//! Jest, into the session `/tmp`. Only pytest-cov records which test ran
+//! each line; lcov has it when the file has one `TN:` section per test.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use super::linters::{at_project_root, relative_to, scope_paths, tail};
+use super::mounts::SandboxMounts;
+use super::sandbox::{SandboxExecutor, SandboxPathPolicy};
+
+/// Default timeout for producing coverage: the whole test suite runs.
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// Upper bound for a caller-supplied `timeout`.
+const MAX_TIMEOUT_SECS: u64 = 1800;
+
+/// Directory in the session `/tmp` that produced coverage is written to.
+const COVERAGE_DIR: &str = "qq-coverage";
+
+/// Coverage files looked for in the project, relative to its root.
+const PROJECT_COVERAGE_FILES: &[&str] = &[
+    "lcov.info",
+    "coverage.lcov",
+    "coverage/lcov.info",
+    "target/llvm-cov/lcov.info",
+    "target/coverage/lcov.info",
+    "coverage.json",
+    "htmlcov/coverage.json",
+];
+
+/// Most line ranges listed per file.
+const MAX_RANGES_PER_FILE: usize = 40;
+
+/// Most covering tests named for one range.
+const MAX_TESTS_PER_RANGE: usize = 5;
+
+/// Most tests in the "Tests to run" list.
+const MAX_TESTS_TO_RUN: usize = 30;
+
+/// Output kept when producing coverage fails.
+const MAX_ERROR_CHARS: usize = 1500;
+
+const COVERAGE_TOOL_DESC: &str = "\
+Map the lines changed in this session to the tests that execute them, from the project's \
+coverage data. Lists, per changed file, line ranges as `covered by: <tests>`, `covered` \
+(when the data doesn't record tests), `NOT COVERED`, or `not executable`, then the tests \
+to run for the change.
+
+Changes are diffed against the session's starting point, or `base` (a git revision such as \
+HEAD or main); new untracked files count as changed. Coverage is read from lcov files \
+(lcov.info, coverage/lcov.info, target/llvm-cov/lcov.info) or coverage.py's coverage.json. \
+When there is none, or regenerate is true, it is produced by running the test suite in the \
+read-only sandbox with cargo-llvm-cov, pytest-cov, or Jest, which can take minutes. \
+Per-test mapping needs pytest-cov (recorded with test contexts) or an lcov file with a \
+TN: section per test; otherwise lines are only marked covered or not.";
+
+/// A test runner that can produce coverage data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageRunner {
+    LlvmCov,
+    PytestCov,
+    Jest,
+}
+
+impl CoverageRunner {
+    pub const ALL: &'static [CoverageRunner] = &[
+        CoverageRunner::LlvmCov,
+        CoverageRunner::PytestCov,
+        CoverageRunner::Jest,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoverageRunner::LlvmCov => "cargo-llvm-cov",
+            CoverageRunner::PytestCov => "pytest-cov",
+            CoverageRunner::Jest => "jest",
+        }
+    }
+
+    /// Whether the project at `root` has tests this runner can run.
+    fn is_configured(&self, root: &Path) -> bool {
+        match self {
+            CoverageRunner::LlvmCov => root.join("Cargo.toml").is_file(),
+            CoverageRunner::PytestCov => {
+                ["pytest.ini", "conftest.py", "tox.ini"]
+                    .iter()
+                    .any(|f| root.join(f).is_file())
+                    || std::fs::read_to_string(root.join("pyproject.toml"))
+                        .is_ok_and(|s| s.contains("[tool.pytest"))
+                    || std::fs::read_to_string(root.join("setup.cfg"))
+                        .is_ok_and(|s| s.contains("[tool:pytest]"))
+            }
+            CoverageRunner::Jest => root.join("node_modules/.bin/jest").exists(),
+        }
+    }
+
+    /// Directory under the session `/tmp` this runner writes to.
+    fn dir(&self) -> String {
+        format!("/tmp/{}/{}", COVERAGE_DIR, self.name())
+    }
+
+    /// File the command leaves its coverage in, inside [`dir`](Self::dir).
+    fn output_file(&self) -> &'static str {
+        match self {
+            CoverageRunner::PytestCov => "coverage.json",
+            CoverageRunner::LlvmCov | CoverageRunner::Jest => "lcov.info",
+        }
+    }
+
+    /// Shell command running the tests with coverage. Everything it writes
+    /// goes under `/tmp`, since the project root is mounted read-only.
+    fn command(&self) -> String {
+        let dir = self.dir();
+        match self {
+            CoverageRunner::LlvmCov => format!(
+                "mkdir -p {dir} && CARGO_TARGET_DIR=/tmp/qq-coverage-target \
+                 cargo llvm-cov --workspace --lcov --output-path {dir}/lcov.info"
+            ),
+            // Test contexts record which test ran each line
+            CoverageRunner::PytestCov => format!(
+                "mkdir -p {dir} && export COVERAGE_FILE={dir}/.coverage PYTHONDONTWRITEBYTECODE=1 && \
+                 python -m pytest -q -p no:cacheprovider --cov=. --cov-context=test --cov-report= ; \
+                 python -m coverage json --show-contexts --data-file={dir}/.coverage -o {dir}/coverage.json"
+            ),
+            CoverageRunner::Jest => format!(
+                "node_modules/.bin/jest --coverage --coverageReporters=lcov --coverageDirectory={dir}"
+            ),
+        }
+    }
+}
+
+/// Coverage runners the project at `root` is set up for.
+pub fn detect_coverage_runners(root: &Path) -> Vec<CoverageRunner> {
+    CoverageRunner::ALL
+        .iter()
+        .copied()
+        .filter(|r| r.is_configured(root))
+        .collect()
+}
+
+/// Line coverage of a project.
+#[derive(Debug, Default)]
+pub struct CoverageData {
+    /// Executable lines per file (relative to the project root) with their
+    /// hit counts
+    lines: BTreeMap<String, BTreeMap<usize, u64>>,
+    /// Tests that executed each line; empty without per-test data
+    tests: BTreeMap<String, BTreeMap<usize, BTreeSet<String>>>,
+}
+
+impl CoverageData {
+    /// Whether the data records which tests ran each line.
+    pub fn per_test(&self) -> bool {
+        !self.tests.is_empty()
+    }
+
+    fn add(&mut self, file: &str, line: usize, hits: u64, test: Option<&str>) {
+        *self
+            .lines
+            .entry(file.to_string())
+            .or_default()
+            .entry(line)
+            .or_default() += hits;
+        if let Some(test) = test.filter(|_| hits > 0) {
+            self.tests
+                .entry(file.to_string())
+                .or_default()
+                .entry(line)
+                .or_default()
+                .insert(test.to_string());
+        }
+    }
+
+    /// Parse lcov tracefile data. Test names (`TN:`) count as per-test data
+    /// only when there is more than one: a single name is the whole run.
+    pub fn from_lcov(text: &str, root: &Path) -> Self {
+        let mut data = Self::default();
+        let mut test_names = HashSet::new();
+        let mut test = String::new();
+        let mut file: Option<String> = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix("TN:") {
+                test = name.trim().to_string();
+                if !test.is_empty() {
+                    test_names.insert(test.clone());
+                }
+            } else if let Some(path) = line.strip_prefix("SF:") {
+                file = relative_to(root, path.trim());
+            } else if line == "end_of_record" {
+                file = None;
+            } else if let (Some(record), Some(file)) = (line.strip_prefix("DA:"), file.as_deref()) {
+                let mut fields = record.split(',');
+                let (Some(Ok(number)), Some(Ok(hits))) = (
+                    fields.next().map(|n| n.trim().parse::<usize>()),
+                    // Some tools write fractional or negative counts
+                    fields.next().map(|h| h.trim().parse::<f64>()),
+                ) else {
+                    continue;
+                };
+                let test = (!test.is_empty()).then_some(test.as_str());
+                data.add(file, number, hits.max(0.0) as u64, test);
+            }
+        }
+        if test_names.len() < 2 {
+            data.tests.clear();
+        }
+        data
+    }
+
+    /// Parse coverage.py's JSON report. With `--show-contexts`, each line's
+    /// contexts name the tests that ran it (`tests/test_a.py::test_x|run`).
+    pub fn from_coverage_py(text: &str, root: &Path) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        struct Report {
+            files: BTreeMap<String, FileReport>,
+        }
+        #[derive(Deserialize)]
+        struct FileReport {
+            #[serde(default)]
+            executed_lines: Vec<usize>,
+            #[serde(default)]
+            missing_lines: Vec<usize>,
+            #[serde(default)]
+            contexts: BTreeMap<String, Vec<String>>,
+        }
+
+        let report: Report = serde_json::from_str(text)
+            .map_err(|e| format!("unexpected coverage.json format: {}", e))?;
+        let mut data = Self::default();
+        for (path, file_report) in report.files {
+            let Some(file) = relative_to(root, &path) else {
+                continue;
+            };
+            for line in file_report.missing_lines {
+                data.add(&file, line, 0, None);
+            }
+            for line in file_report.executed_lines {
+                let tests: BTreeSet<&str> = file_report
+                    .contexts
+                    .get(&line.to_string())
+                    .into_iter()
+                    .flatten()
+                    .map(|c| c.split('|').next().unwrap_or(c).trim())
+                    .filter(|c| !c.is_empty())
+                    .collect();
+                if tests.is_empty() {
+                    data.add(&file, line, 1, None);
+                }
+                for test in tests {
+                    data.add(&file, line, 1, Some(test));
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    /// Parse a coverage file by its format: coverage.py JSON or lcov.
+    fn load(path: &Path, root: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        if path.extension().is_some_and(|e| e == "json") {
+            Self::from_coverage_py(&text, root)
+        } else {
+            Ok(Self::from_lcov(&text, root))
+        }
+    }
+}
+
+/// Lines added or modified per file, relative to the project root.
+pub type ChangedLines = BTreeMap<String, BTreeSet<usize>>;
+
+/// New-side line numbers from a `git diff --unified=0`.
+pub fn parse_changed_lines(diff: &str) -> ChangedLines {
+    let mut changed = ChangedLines::new();
+    let mut file: Option<String> = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            let path = path.trim().trim_matches('"');
+            file = path.strip_prefix("b/").map(str::to_string);
+        } else if let (Some(hunk), Some(file)) = (line.strip_prefix("@@ "), file.as_ref()) {
+            // @@ -old[,count] +new[,count] @@
+            let Some(new) = hunk.split_whitespace().find_map(|f| f.strip_prefix('+')) else {
+                continue;
+            };
+            let mut parts = new.splitn(2, ',');
+            let start: usize = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+            let count: usize = parts.next().and_then(|c| c.parse().ok()).unwrap_or(1);
+            // A count of 0 is a pure deletion: nothing on the new side to cover
+            if count > 0 {
+                changed
+                    .entry(file.clone())
+                    .or_default()
+                    .extend(start..start + count);
+            }
+        }
+    }
+    changed
+}
+
+/// Lines changed in the worktree at `root` since `base`, plus every line of
+/// untracked files, limited to `paths`.
+fn changed_lines(root: &Path, base: &str, paths: &[String]) -> Result<ChangedLines, String> {
+    let git = |args: &[&str]| -> Result<String, String> {
+        let output = Command::new("git")
+            .args(args)
+            .args(paths)
+            .current_dir(root)
+            .output()
+            .map_err(|e| format!("failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    // --relative: paths from the project root, even in a subdirectory of the repository
+    let diff = git(&[
+        "diff",
+        "--unified=0",
+        "--no-color",
+        "--no-ext-diff",
+        "--relative",
+        base,
+        "--",
+    ])?;
+    let mut changed = parse_changed_lines(&diff);
+    let untracked = git(&["ls-files", "--others", "--exclude-standard", "-z", "--"])?;
+    for file in untracked.split('\0').filter(|f| !f.is_empty()) {
+        let Ok(text) = std::fs::read_to_string(root.join(file)) else {
+            continue;
+        };
+        let count = text.lines().count();
+        if count > 0 {
+            changed.entry(file.to_string()).or_default().extend(1..=count);
+        }
+    }
+    Ok(changed)
+}
+
+/// The most recently written coverage file: produced this session, or one
+/// of [`PROJECT_COVERAGE_FILES`].
+fn find_coverage(root: &Path, tmp: &Path) -> Option<(PathBuf, SystemTime)> {
+    let produced = CoverageRunner::ALL
+        .iter()
+        .map(|r| tmp.join(COVERAGE_DIR).join(r.name()).join(r.output_file()));
+    PROJECT_COVERAGE_FILES
+        .iter()
+        .map(|f| root.join(f))
+        .chain(produced)
+        .filter(|p| p.is_file())
+        .filter_map(|p| Some((p.metadata().ok()?.modified().ok()?, p)))
+        .max()
+        .map(|(modified, path)| (path, modified))
+}
+
+/// How one changed line stands in the coverage data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LineStatus {
+    /// Executed, by these tests when known
+    Covered(BTreeSet<String>),
+    NotCovered,
+    /// Not instrumented: blank, a comment, a declaration
+    NotExecutable,
+}
+
+/// Render the mapping: a summary, ranges per file, then the tests to run.
+fn format_report(changed: &ChangedLines, data: &CoverageData, source: &str) -> String {
+    let mut covered = 0;
+    let mut uncovered = 0;
+    let mut not_executable = 0;
+    let mut to_run: BTreeMap<String, usize> = BTreeMap::new();
+    let mut without_data = Vec::new();
+    let mut sections = String::new();
+
+    for (file, lines) in changed {
+        let Some(file_lines) = data.lines.get(file) else {
+            without_data.push(file.as_str());
+            continue;
+        };
+        let file_tests = data.tests.get(file);
+        // Consecutive lines with the same status share a range
+        let mut ranges: Vec<(usize, usize, LineStatus)> = Vec::new();
+        for &line in lines {
+            let status = match file_lines.get(&line) {
+                None => {
+                    not_executable += 1;
+                    LineStatus::NotExecutable
+                }
+                Some(0) => {
+                    uncovered += 1;
+                    LineStatus::NotCovered
+                }
+                Some(_) => {
+                    covered += 1;
+                    let tests = file_tests
+                        .and_then(|t| t.get(&line))
+                        .cloned()
+                        .unwrap_or_default();
+                    for test in &tests {
+                        *to_run.entry(test.clone()).or_default() += 1;
+                    }
+                    LineStatus::Covered(tests)
+                }
+            };
+            match ranges.last_mut() {
+                Some((_, end, last)) if *end + 1 == line && *last == status => *end = line,
+                _ => ranges.push((line, line, status)),
+            }
+        }
+
+        sections.push_str(&format!("\n===== {} =====\n", file));
+        for (start, end, status) in ranges.iter().take(MAX_RANGES_PER_FILE) {
+            let span = if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            };
+            let status = match status {
+                LineStatus::Covered(tests) if tests.is_empty() => "covered".to_string(),
+                LineStatus::Covered(tests) => {
+                    let names: Vec<&str> =
+                        tests.iter().take(MAX_TESTS_PER_RANGE).map(String::as_str).collect();
+                    let more = tests.len().saturating_sub(MAX_TESTS_PER_RANGE);
+                    let more = if more > 0 {
+                        format!(" (+{} more)", more)
+                    } else {
+                        String::new()
+                    };
+                    format!("covered by: {}{}", names.join(", "), more)
+                }
+                LineStatus::NotCovered => "NOT COVERED".to_string(),
+                LineStatus::NotExecutable => "not executable".to_string(),
+            };
+            sections.push_str(&format!("{} {}\n", span, status));
+        }
+        if ranges.len() > MAX_RANGES_PER_FILE {
+            sections.push_str(&format!(
+                "[{} more ranges; pass this file alone as paths]\n",
+                ranges.len() - MAX_RANGES_PER_FILE
+            ));
+        }
+    }
+
+    let total: usize = changed.values().map(BTreeSet::len).sum();
+    let mut out = format!(
+        "Coverage: {} ({})\n{} changed line(s) in {} file(s): {} covered, {} NOT COVERED, {} not executable",
+        source,
+        if data.per_test() {
+            "per test"
+        } else {
+            "no per-test data"
+        },
+        total,
+        changed.len(),
+        covered,
+        uncovered,
+        not_executable
+    );
+    if !without_data.is_empty() {
+        out.push_str(&format!(", {} file(s) without coverage data", without_data.len()));
+    }
+    out.push('\n');
+    out.push_str(&sections);
+    if !without_data.is_empty() {
+        out.push_str(&format!(
+            "\nNo coverage data (not source, or not reached by the test run): {}\n",
+            without_data.join(", ")
+        ));
+    }
+    if !to_run.is_empty() {
+        // Tests covering the most changed lines first
+        let mut tests: Vec<(String, usize)> = to_run.into_iter().collect();
+        tests.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let more = tests.len().saturating_sub(MAX_TESTS_TO_RUN);
+        let names: Vec<String> = tests
+            .into_iter()
+            .take(MAX_TESTS_TO_RUN)
+            .map(|(name, _)| name)
+            .collect();
+        out.push_str(&format!("\nTests to run: {}", names.join(" ")));
+        if more > 0 {
+            out.push_str(&format!(" (+{} more)", more));
+        }
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+#[derive(Deserialize)]
+struct CoverageArgs {
+    #[serde(default)]
+    base: Option<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    regenerate: bool,
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+/// Tool mapping changed lines to the tests that cover them.
+pub struct CoverageMapTool {
+    mounts: Arc<SandboxMounts>,
+    path_policy: Arc<RwLock<SandboxPathPolicy>>,
+    executor: SandboxExecutor,
+    /// Revision the session's changes are diffed against (default `HEAD`)
+    base: Option<String>,
+}
+
+impl CoverageMapTool {
+    pub fn new(
+        mounts: Arc<SandboxMounts>,
+        path_policy: Arc<RwLock<SandboxPathPolicy>>,
+        executor: SandboxExecutor,
+    ) -> Self {
+        Self {
+            mounts,
+            path_policy,
+            executor,
+            base: None,
+        }
+    }
+
+    /// Diff against `base` (the worktree as the session started) when the
+    /// call doesn't name a revision.
+    pub fn with_base(mut self, base: impl Into<String>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Run the project's tests with coverage; the file it wrote.
+    async fn produce(&self, runner: CoverageRunner, timeout: u64) -> Result<PathBuf, String> {
+        let policy = self
+            .path_policy
+            .read()
+            .map_err(|_| "Path policy lock poisoned.".to_string())?
+            .clone();
+        let output = self
+            .mounts
+            .tmp_dir()
+            .join(COVERAGE_DIR)
+            .join(runner.name())
+            .join(runner.output_file());
+        // A stale file would pass for this run's output
+        let _ = std::fs::remove_file(&output);
+
+        let command = at_project_root(&self.mounts, runner.command());
+        tracing::info!(runner = runner.name(), command = %command, "Producing coverage");
+        let result = self
+            .executor
+            .execute(&command, &self.mounts, timeout, &policy, None, true)
+            .await?;
+        if let Some(e) = result.sandbox_error {
+            return Err(format!("sandbox error: {}", e));
+        }
+        if result.timed_out {
+            return Err(format!("timed out after {}s", timeout));
+        }
+        // Failing tests still leave coverage behind
+        if output.is_file() {
+            return Ok(output);
+        }
+        let detail = if result.stderr.trim().is_empty() {
+            tail(&result.stdout, MAX_ERROR_CHARS)
+        } else {
+            tail(&result.stderr, MAX_ERROR_CHARS)
+        };
+        Err(format!(
+            "no coverage written (exit code {}; is {} installed?): {}",
+            result.exit_code,
+            runner.name(),
+            detail
+        ))
+    }
+}
+
+#[async_trait]
+impl Tool for CoverageMapTool {
+    fn name(&self) -> &str {
+        "map_test_coverage"
+    }
+
+    fn description(&self) -> &str {
+        "Map changed lines to the tests that cover them, and flag changed lines no test runs"
+    }
+
+    fn tool_description(&self) -> &str {
+        COVERAGE_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "base",
+                    PropertySchema::string(
+                        "Git revision to diff against (default: the worktree as the session started)",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "paths",
+                    PropertySchema::array(
+                        "Files or directories, relative to the working directory, to map",
+                        PropertySchema::string("Path"),
+                    ),
+                    false,
+                )
+                .add_property(
+                    "regenerate",
+                    PropertySchema::boolean(
+                        "Run the test suite with coverage even when coverage data exists (default false)",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "timeout",
+                    PropertySchema::integer(
+                        "Timeout for producing coverage in seconds (default 600, max 1800)",
+                    ),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: CoverageArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("map_test_coverage", format!("Invalid arguments: {}", e)))?;
+
+        let paths = match scope_paths(&self.mounts, &args.paths) {
+            Ok(paths) => paths,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        let root = self.mounts.project_root();
+        let base = args
+            .base
+            .as_deref()
+            .or(self.base.as_deref())
+            .unwrap_or("HEAD");
+        if base.starts_with('-') {
+            return Ok(ToolOutput::error(format!("Invalid base revision: {}", base)));
+        }
+        let changed = match changed_lines(root, base, &paths) {
+            Ok(changed) => changed,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        if changed.is_empty() {
+            return Ok(ToolOutput::success(format!(
+                "No changed lines since {}.",
+                args.base.as_deref().unwrap_or("the session started")
+            )));
+        }
+
+        let found = find_coverage(root, self.mounts.tmp_dir()).filter(|_| !args.regenerate);
+        let (path, modified) = match found {
+            Some(found) => found,
+            None => {
+                // Test suites run project code
+                if !self.executor.supports_shell() {
+                    return Ok(ToolOutput::error(
+                        "No coverage data found, and producing it is not available in \
+                         app-level sandbox mode.",
+                    ));
+                }
+                let runners = detect_coverage_runners(root);
+                if runners.is_empty() {
+                    return Ok(ToolOutput::error(format!(
+                        "No coverage data found (looked for {}) and no test setup to produce it \
+                         (Cargo.toml, pytest config, or node_modules/.bin/jest).",
+                        PROJECT_COVERAGE_FILES.join(", ")
+                    )));
+                }
+                let timeout = args
+                    .timeout
+                    .unwrap_or(DEFAULT_TIMEOUT_SECS)
+                    .clamp(1, MAX_TIMEOUT_SECS);
+                let mut failures = Vec::new();
+                let mut produced = None;
+                for runner in runners {
+                    match self.produce(runner, timeout).await {
+                        Ok(path) => {
+                            produced = Some(path);
+                            break;
+                        }
+                        Err(e) => failures.push(format!("{}: {}", runner.name(), e)),
+                    }
+                }
+                match produced {
+                    Some(path) => (path, SystemTime::now()),
+                    None => {
+                        return Ok(ToolOutput::error(format!(
+                            "Failed to produce coverage:\n{}",
+                            failures.join("\n")
+                        )))
+                    }
+                }
+            }
+        };
+
+        let data = match CoverageData::load(&path, root) {
+            Ok(data) => data,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        let source = match path.strip_prefix(self.mounts.tmp_dir()) {
+            Ok(rel) => format!("/tmp/{}", rel.display()),
+            Err(_) => relative_to(root, &path.display().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+        };
+        let mut report = format_report(&changed, &data, &source);
+
+        // Lines shift when a file changes after the coverage run
+        let stale: Vec<&str> = changed
+            .keys()
+            .filter(|f| {
+                root.join(f)
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|m| m > modified)
+            })
+            .map(String::as_str)
+            .collect();
+        if !stale.is_empty() {
+            report.push_str(&format!(
+                "\n\nNote: {} changed after the coverage data was written, so its lines may be \
+                 off; pass regenerate=true to rerun the tests with coverage.",
+                stale.join(", ")
+            ));
+        }
+        Ok(ToolOutput::success(report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_changed_lines() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3 +3 @@ fn a() {
+@@ -10,0 +11,2 @@ fn b() {
+@@ -20,3 +21,0 @@ fn c() {
+diff --git a/old.rs b/old.rs
+--- a/old.rs
++++ /dev/null
+@@ -1,5 +0,0 @@
+";
+        let changed = parse_changed_lines(diff);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(
+            changed["src/lib.rs"].iter().copied().collect::<Vec<_>>(),
+            vec![3, 11, 12]
+        );
+    }
+
+    #[test]
+    fn test_lcov_with_and_without_test_names() {
+        let root = Path::new("/work/proj");
+        let lcov = "\
+TN:parser::tests::empty
+SF:/work/proj/src/parser.rs
+DA:10,1
+DA:11,0
+end_of_record
+TN:parser::tests::nested
+SF:/work/proj/src/parser.rs
+DA:10,3
+DA:11,2
+end_of_record
+SF:/home/me/.cargo/registry/dep.rs
+DA:1,1
+end_of_record
+";
+        let data = CoverageData::from_lcov(lcov, root);
+        assert!(data.per_test());
+        assert_eq!(data.lines["src/parser.rs"][&10], 4);
+        assert_eq!(data.lines.len(), 1);
+        assert_eq!(
+            data.tests["src/parser.rs"][&11].iter().collect::<Vec<_>>(),
+            vec!["parser::tests::nested"]
+        );
+
+        // One test name is the whole run, not per-test data
+        let data = CoverageData::from_lcov("TN:\nSF:src/a.rs\nDA:1,5\nend_of_record\n", root);
+        assert!(!data.per_test());
+        assert_eq!(data.lines["src/a.rs"][&1], 5);
+    }
+
+    #[test]
+    fn test_report_maps_lines_to_tests() {
+        let root = Path::new("/work/proj");
+        let json = r#"{"files": {"pkg/calc.py": {
+            "executed_lines": [1, 4, 5],
+            "missing_lines": [8, 9],
+            "contexts": {
+                "1": [""],
+                "4": ["tests/test_calc.py::test_add|run"],
+                "5": ["tests/test_calc.py::test_add|run", "tests/test_calc.py::test_big|run"]
+            }
+        }}}"#;
+        let data = CoverageData::from_coverage_py(json, root).unwrap();
+        assert!(data.per_test());
+
+        let mut changed = ChangedLines::new();
+        changed.insert("pkg/calc.py".into(), [4, 5, 6, 8, 9].into_iter().collect());
+        changed.insert("README.md".into(), [1].into_iter().collect());
+        let report = format_report(&changed, &data, "coverage.json");
+        assert_eq!(
+            report,
+            "\
+Coverage: coverage.json (per test)
+6 changed line(s) in 2 file(s): 2 covered, 2 NOT COVERED, 1 not executable, 1 file(s) without coverage data
+
+===== pkg/calc.py =====
+4 covered by: tests/test_calc.py::test_add
+5 covered by: tests/test_calc.py::test_add, tests/test_calc.py::test_big
+6 not executable
+8-9 NOT COVERED
+
+No coverage data (not source, or not reached by the test run): README.md
+
+Tests to run: tests/test_calc.py::test_add tests/test_calc.py::test_big"
+        );
+    }
+
+    #[test]
+    fn test_changed_lines_include_untracked_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            let status = Command::new("git").args(args).current_dir(root).output().unwrap().status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&["config", "user.email", "qq@example.com"]);
+        git(&["config", "user.name", "qq"]);
+        std::fs::write(root.join("a.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-qm", "init"]);
+
+        std::fs::write(root.join("a.rs"), "fn a() {}\nfn b() { todo!() }\nfn c() {}\n").unwrap();
+        std::fs::write(root.join("new.rs"), "fn n() {}\n").unwrap();
+        let changed = changed_lines(root, "HEAD", &[]).unwrap();
+        assert_eq!(changed["a.rs"].iter().copied().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(changed["new.rs"].iter().copied().collect::<Vec<_>>(), vec![1]);
+
+        let only_new = changed_lines(root, "HEAD", &["new.rs".to_string()]).unwrap();
+        assert_eq!(only_new.keys().collect::<Vec<_>>(), vec!["new.rs"]);
+    }
+}
@@ -0,0 +1,631 @@
+//! Change statistics for commands that edit files.
+//!
+//! Before `run` executes a command that writes (see
+//! [`write_operation`](super::write_policy::write_operation)), the files it
+//! targets are snapshotted; afterwards each is diffed against its snapshot.
+//! The result is appended to the command's output as a `[changes]` footer:
+//! files, hunks, lines added and removed, and (with the `outline` feature) the
+//! functions and types the hunks fall in. [`ChangeStats::from_output`] parses
+//! the footer back, so the TUI and parent agents can show and sum it.
+//!
+//! Only paths named on the command line are tracked: a script that edits other
+//! files, or an unexpanded glob, produces no footer.
+
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// First line of the footer.
+const FOOTER_PREFIX: &str = "[changes] ";
+
+/// Most targets snapshotted for one command.
+const MAX_TRACKED_FILES: usize = 64;
+
+/// Larger files are not snapshotted.
+const MAX_TRACKED_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Differing middles larger than this (old × new lines) count as one hunk
+/// replacing everything instead of being diffed line by line.
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// Unchanged lines kept around a change; closer changes share a hunk, as in
+/// `git diff`.
+const CONTEXT_LINES: usize = 3;
+
+/// Most symbols listed per file.
+#[cfg(feature = "outline")]
+const MAX_SYMBOLS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn letter(self) -> char {
+        match self {
+            ChangeKind::Added => 'A',
+            ChangeKind::Modified => 'M',
+            ChangeKind::Deleted => 'D',
+        }
+    }
+
+    fn from_letter(letter: &str) -> Option<Self> {
+        match letter {
+            "A" => Some(ChangeKind::Added),
+            "M" => Some(ChangeKind::Modified),
+            "D" => Some(ChangeKind::Deleted),
+            _ => None,
+        }
+    }
+}
+
+/// How one file changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Relative to the project root
+    pub path: String,
+    pub kind: ChangeKind,
+    pub hunks: usize,
+    pub added: usize,
+    pub removed: usize,
+    /// Functions, types, and impl blocks the hunks fall in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+}
+
+/// Changes made by one command, or summed over several.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeStats {
+    pub files: Vec<FileChange>,
+}
+
+impl ChangeStats {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn hunks(&self) -> usize {
+        self.files.iter().map(|f| f.hunks).sum()
+    }
+
+    pub fn added(&self) -> usize {
+        self.files.iter().map(|f| f.added).sum()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.files.iter().map(|f| f.removed).sum()
+    }
+
+    /// Fold `other` in, summing files changed by both. A file added and then
+    /// edited stays added; a file deleted last is deleted.
+    pub fn merge(&mut self, other: ChangeStats) {
+        for change in other.files {
+            match self.files.iter_mut().find(|f| f.path == change.path) {
+                Some(existing) => {
+                    existing.kind = match (existing.kind, change.kind) {
+                        (_, ChangeKind::Deleted) => ChangeKind::Deleted,
+                        (ChangeKind::Added, _) => ChangeKind::Added,
+                        (_, kind) => kind,
+                    };
+                    existing.hunks += change.hunks;
+                    existing.added += change.added;
+                    existing.removed += change.removed;
+                    for symbol in change.symbols {
+                        if !existing.symbols.contains(&symbol) {
+                            existing.symbols.push(symbol);
+                        }
+                    }
+                }
+                None => self.files.push(change),
+            }
+        }
+    }
+
+    /// One line: `3 files, 4 hunks, +42 -7`.
+    pub fn summary(&self) -> String {
+        let plural =
+            |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+        format!(
+            "{}, {}, +{} -{}",
+            plural(self.files.len(), "file"),
+            plural(self.hunks(), "hunk"),
+            self.added(),
+            self.removed()
+        )
+    }
+
+    /// The footer appended to `run` output: the summary, then a line per file.
+    pub fn render(&self) -> String {
+        let mut out = format!("{}{}", FOOTER_PREFIX, self.summary());
+        for file in &self.files {
+            let _ = write!(
+                out,
+                "\n  {} {}  +{} -{}  {} hunk{}",
+                file.kind.letter(),
+                file.path,
+                file.added,
+                file.removed,
+                file.hunks,
+                if file.hunks == 1 { "" } else { "s" }
+            );
+            if !file.symbols.is_empty() {
+                let _ = write!(out, "  {}", file.symbols.join(", "));
+            }
+        }
+        out
+    }
+
+    /// Parse the footer of a `run` result, if it has one.
+    pub fn from_output(text: &str) -> Option<Self> {
+        let start = text.rfind(FOOTER_PREFIX)?;
+        let mut lines = text[start..].lines();
+        lines.next()?;
+        let files: Vec<FileChange> = lines
+            .map_while(|line| parse_file_line(line.strip_prefix("  ")?))
+            .collect();
+        (!files.is_empty()).then_some(Self { files })
+    }
+}
+
+/// `M src/lib.rs  +3 -1  2 hunks  fn parse, impl Config`
+fn parse_file_line(line: &str) -> Option<FileChange> {
+    let mut fields = line.split("  ");
+    let (letter, path) = fields.next()?.split_once(' ')?;
+    let (added, removed) = fields.next()?.split_once(' ')?;
+    let hunks = fields.next()?.split_once(' ')?.0;
+    let symbols = fields
+        .next()
+        .map(|s| s.split(", ").map(str::to_string).collect())
+        .unwrap_or_default();
+    Some(FileChange {
+        path: path.to_string(),
+        kind: ChangeKind::from_letter(letter)?,
+        hunks: hunks.parse().ok()?,
+        added: added.strip_prefix('+')?.parse().ok()?,
+        removed: removed.strip_prefix('-')?.parse().ok()?,
+        symbols,
+    })
+}
+
+// =============================================================================
+// Snapshots
+// =============================================================================
+
+/// Contents of a command's target files before it ran.
+#[derive(Debug)]
+pub struct ChangeSnapshot {
+    root: PathBuf,
+    /// Display path, absolute path, contents (`None` if the file didn't exist)
+    files: Vec<(String, PathBuf, Option<String>)>,
+}
+
+impl ChangeSnapshot {
+    /// Snapshot `targets` (relative to `root`, or absolute). Directories,
+    /// paths outside the root, and unreadable, binary, or large files are
+    /// not tracked.
+    pub fn capture(root: &Path, targets: &[String]) -> Self {
+        let mut files = Vec::new();
+        for target in targets.iter().take(MAX_TRACKED_FILES) {
+            let path = root.join(target);
+            let escapes = Path::new(target)
+                .components()
+                .any(|c| c == Component::ParentDir);
+            if escapes || !path.starts_with(root) || path.is_dir() {
+                continue;
+            }
+            let before = if path.exists() {
+                match read_tracked(&path) {
+                    Some(text) => Some(text),
+                    None => continue,
+                }
+            } else {
+                None
+            };
+            files.push((target.clone(), path, before));
+        }
+        Self {
+            root: root.to_path_buf(),
+            files,
+        }
+    }
+
+    /// Whether any target is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Diff every tracked file against its snapshot.
+    pub fn changes(&self) -> ChangeStats {
+        let mut stats = ChangeStats::default();
+        for (display, path, before) in &self.files {
+            let after = if path.is_file() {
+                match read_tracked(path) {
+                    Some(text) => Some(text),
+                    None => continue,
+                }
+            } else {
+                None
+            };
+            let change = match (before, after) {
+                (None, None) => continue,
+                (Some(before), Some(after)) if *before == after => continue,
+                (None, Some(after)) => FileChange {
+                    path: display.clone(),
+                    kind: ChangeKind::Added,
+                    hunks: 1,
+                    added: after.lines().count(),
+                    removed: 0,
+                    symbols: touched_symbols(path, &after, &[(1, after.lines().count())]),
+                },
+                (Some(before), None) => FileChange {
+                    path: display.clone(),
+                    kind: ChangeKind::Deleted,
+                    hunks: 1,
+                    added: 0,
+                    removed: before.lines().count(),
+                    symbols: Vec::new(),
+                },
+                (Some(before), Some(after)) => {
+                    let old: Vec<&str> = before.lines().collect();
+                    let new: Vec<&str> = after.lines().collect();
+                    let diff = diff_lines(&old, &new);
+                    FileChange {
+                        path: display.clone(),
+                        kind: ChangeKind::Modified,
+                        hunks: diff.hunks,
+                        added: diff.added,
+                        removed: diff.removed,
+                        symbols: touched_symbols(path, &after, &diff.new_ranges),
+                    }
+                }
+            };
+            stats.files.push(change);
+        }
+        tracing::debug!(root = %self.root.display(), files = stats.files.len(), "Computed change stats");
+        stats
+    }
+}
+
+pub(super) fn read_tracked(path: &Path) -> Option<String> {
+    let size = std::fs::metadata(path).ok()?.len();
+    if size > MAX_TRACKED_BYTES {
+        return None;
+    }
+    let text = std::fs::read_to_string(path).ok()?;
+    (!text.contains('\0')).then_some(text)
+}
+
+#[cfg(feature = "outline")]
+fn touched_symbols(path: &Path, source: &str, ranges: &[(usize, usize)]) -> Vec<String> {
+    let mut symbols = super::outline::touched_symbols(path, source, ranges);
+    symbols.truncate(MAX_SYMBOLS);
+    symbols
+}
+
+#[cfg(not(feature = "outline"))]
+fn touched_symbols(_path: &Path, _source: &str, _ranges: &[(usize, usize)]) -> Vec<String> {
+    Vec::new()
+}
+
+// =============================================================================
+// Line diff
+// =============================================================================
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct LineDiff {
+    hunks: usize,
+    added: usize,
+    removed: usize,
+    /// Changed line ranges in the new file (1-based, inclusive); a pure
+    /// deletion is the line where it happened
+    new_ranges: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Edit script turning `old` into `new`: LCS over the middle that differs,
+/// after trimming the common prefix and suffix.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops = vec![Op::Equal; prefix];
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_DIFF_CELLS {
+        ops.extend(std::iter::repeat_n(Op::Delete, old_mid.len()));
+        ops.extend(std::iter::repeat_n(Op::Insert, new_mid.len()));
+    } else {
+        ops.extend(lcs_ops(old_mid, new_mid));
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+/// Line-level diff counts and changed ranges.
+fn diff_lines(old: &[&str], new: &[&str]) -> LineDiff {
+    let ops = edit_script(old, new);
+    let mut diff = LineDiff::default();
+    let mut new_line = 0;
+    // Equal lines since the last change; None before the first change
+    let mut gap: Option<usize> = None;
+    let mut i = 0;
+    while i < ops.len() {
+        if ops[i] == Op::Equal {
+            new_line += 1;
+            if let Some(gap) = gap.as_mut() {
+                *gap += 1;
+            }
+            i += 1;
+            continue;
+        }
+        if gap.is_none_or(|g| g > 2 * CONTEXT_LINES) {
+            diff.hunks += 1;
+        }
+        gap = Some(0);
+        let start = new_line + 1;
+        while i < ops.len() && ops[i] != Op::Equal {
+            if ops[i] == Op::Insert {
+                diff.added += 1;
+                new_line += 1;
+            } else {
+                diff.removed += 1;
+            }
+            i += 1;
+        }
+        // Pure deletions point at the line after them (or the last line)
+        let last = new.len().max(1);
+        diff.new_ranges
+            .push((start.min(last), new_line.max(start).min(last)));
+    }
+    diff
+}
+
+/// Unified diff of `path` from `before` to `after` in `git diff` format;
+/// `None` is a missing file. Empty when nothing differs.
+pub fn unified_diff(path: &str, before: Option<&str>, after: Option<&str>) -> String {
+    if before == after {
+        return String::new();
+    }
+    let old: Vec<&str> = before.map(|t| t.lines().collect()).unwrap_or_default();
+    let new: Vec<&str> = after.map(|t| t.lines().collect()).unwrap_or_default();
+    let ops = edit_script(&old, &new);
+
+    // Each op with the old and new line it starts at
+    let mut entries = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for op in ops {
+        entries.push((op, i, j));
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+
+    let mut out = format!("diff --git a/{path} b/{path}\n");
+    match (before, after) {
+        (None, _) => out.push_str("new file mode 100644\n--- /dev/null\n"),
+        (_, None) => out.push_str(&format!("deleted file mode 100644\n--- a/{path}\n")),
+        _ => out.push_str(&format!("--- a/{path}\n")),
+    }
+    match after {
+        Some(_) => out.push_str(&format!("+++ b/{path}\n")),
+        None => out.push_str("+++ /dev/null\n"),
+    }
+
+    // Changes closer than twice the context share a hunk
+    let changed: Vec<usize> = (0..entries.len())
+        .filter(|&k| entries[k].0 != Op::Equal)
+        .collect();
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &k in &changed {
+        match groups.last_mut() {
+            Some((_, last)) if k - *last - 1 <= 2 * CONTEXT_LINES => *last = k,
+            _ => groups.push((k, k)),
+        }
+    }
+    for (first, last) in groups {
+        let lo = first.saturating_sub(CONTEXT_LINES);
+        let hi = (last + CONTEXT_LINES + 1).min(entries.len());
+        let hunk = &entries[lo..hi];
+        let old_len = hunk.iter().filter(|e| e.0 != Op::Insert).count();
+        let new_len = hunk.iter().filter(|e| e.0 != Op::Delete).count();
+        let (_, old_at, new_at) = hunk[0];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_at, old_len),
+            hunk_range(new_at, new_len)
+        ));
+        for &(op, i, j) in hunk {
+            let line = match op {
+                Op::Equal => format!(" {}", old[i]),
+                Op::Delete => format!("-{}", old[i]),
+                Op::Insert => format!("+{}", new[j]),
+            };
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// `start,len` of a hunk side as `git diff` writes it: 1-based, the length
+/// left out when it is 1, and the line before when the side is empty.
+fn hunk_range(at: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", at),
+        1 => format!("{}", at + 1),
+        _ => format!("{},{}", at + 1, len),
+    }
+}
+
+/// Edit script turning `old` into `new`, deletions before insertions.
+fn lcs_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j] = LCS length of old[i..] and new[j..]
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[at(i, j)] = if old[i] == new[j] {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            ops.push(Op::Equal);
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[at(i + 1, j)] >= lcs[at(i, j + 1)]) {
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines_counts_and_groups_hunks() {
+        let old: Vec<String> = (1..=30).map(|i| format!("line {}", i)).collect();
+        let mut new = old.clone();
+        new[1] = "changed 2".to_string();
+        new[3] = "changed 4".to_string(); // within context of the first: same hunk
+        new.remove(20);
+        new.insert(25, "inserted".to_string());
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+
+        let diff = diff_lines(&old, &new);
+        assert_eq!(diff.hunks, 2);
+        assert_eq!(diff.added, 3);
+        assert_eq!(diff.removed, 3);
+        assert_eq!(diff.new_ranges, vec![(2, 2), (4, 4), (21, 21), (26, 26)]);
+
+        assert_eq!(diff_lines(&old, &old), LineDiff::default());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old: String = (1..=12).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 2\n", "two\n").replace("line 11\n", "");
+        let diff = unified_diff("src/a.txt", Some(&old), Some(&new));
+        assert_eq!(
+            diff,
+            "diff --git a/src/a.txt b/src/a.txt\n\
+             --- a/src/a.txt\n\
+             +++ b/src/a.txt\n\
+             @@ -1,5 +1,5 @@\n line 1\n-line 2\n+two\n line 3\n line 4\n line 5\n\
+             @@ -8,5 +8,4 @@\n line 8\n line 9\n line 10\n-line 11\n line 12\n"
+        );
+
+        let created = unified_diff("new.txt", None, Some("a\n"));
+        assert!(created.contains("new file mode 100644\n--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+a\n"));
+        assert_eq!(unified_diff("same.txt", Some("x\n"), Some("x\n")), "");
+    }
+
+    #[test]
+    fn test_snapshot_reports_added_modified_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("keep.txt"), "a\nb\nc\n").unwrap();
+        std::fs::write(root.join("gone.txt"), "x\ny\n").unwrap();
+        std::fs::create_dir(root.join("sub")).unwrap();
+        let targets: Vec<String> = ["keep.txt", "gone.txt", "new.txt", "sub", "s/a/b/", "../out"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+
+        let snapshot = ChangeSnapshot::capture(root, &targets);
+        std::fs::write(root.join("keep.txt"), "a\nB\nc\nd\n").unwrap();
+        std::fs::remove_file(root.join("gone.txt")).unwrap();
+        std::fs::write(root.join("new.txt"), "1\n2\n3\n").unwrap();
+
+        let stats = snapshot.changes();
+        let summary: Vec<(&str, ChangeKind, usize, usize)> = stats
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.kind, f.added, f.removed))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("keep.txt", ChangeKind::Modified, 2, 1),
+                ("gone.txt", ChangeKind::Deleted, 0, 2),
+                ("new.txt", ChangeKind::Added, 3, 0),
+            ]
+        );
+        assert_eq!(stats.summary(), "3 files, 3 hunks, +5 -3");
+    }
+
+    #[test]
+    fn test_render_parse_round_trip_and_merge() {
+        let mut stats = ChangeStats {
+            files: vec![
+                FileChange {
+                    path: "src/lib.rs".into(),
+                    kind: ChangeKind::Modified,
+                    hunks: 2,
+                    added: 10,
+                    removed: 3,
+                    symbols: vec!["fn parse".into(), "impl Config".into()],
+                },
+                FileChange {
+                    path: "notes.md".into(),
+                    kind: ChangeKind::Added,
+                    hunks: 1,
+                    added: 1,
+                    removed: 0,
+                    symbols: Vec::new(),
+                },
+            ],
+        };
+        let output = format!("Exit code: 0\n\n{}", stats.render());
+        assert!(output.ends_with("  A notes.md  +1 -0  1 hunk"));
+        assert_eq!(ChangeStats::from_output(&output), Some(stats.clone()));
+        assert_eq!(ChangeStats::from_output("Exit code: 0"), None);
+
+        stats.merge(ChangeStats {
+            files: vec![FileChange {
+                path: "notes.md".into(),
+                kind: ChangeKind::Modified,
+                hunks: 1,
+                added: 2,
+                removed: 1,
+                symbols: Vec::new(),
+            }],
+        });
+        assert_eq!(stats.files[1].kind, ChangeKind::Added);
+        assert_eq!(stats.summary(), "2 files, 4 hunks, +13 -4");
+    }
+}
@@ -0,0 +1,972 @@
+//! One-line explanations of commands awaiting approval.
+//!
+//! Approval prompts used to show only the raw command, so a long pipeline
+//! or an unfamiliar flag got approved on trust. [`CommandExplainer`] puts a
+//! plain-English line next to it ("Deletes build and everything inside
+//! without asking for confirmation."). Common commands are explained by
+//! rules; when a rule can't cover every part of the command, a cheap model
+//! is asked instead, if one is configured. A command neither can explain is
+//! shown as before.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use qq_core::text::ellipsize;
+use qq_core::{CompletionRequest, Message, Provider};
+
+use super::parse;
+use super::write_policy::{is_assignment, redirect_target};
+
+/// Longest wait for the model before prompting without an explanation.
+const MODEL_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Longer pipelines are left to the model; a line built from rules for
+/// each part would be too long to read at a glance.
+const MAX_SEGMENTS: usize = 4;
+
+/// Longest explanation shown, in columns.
+const MAX_EXPLANATION_WIDTH: usize = 200;
+
+/// Longest command, pattern or text quoted inside an explanation.
+const MAX_QUOTE_WIDTH: usize = 40;
+
+const EXPLAIN_PROMPT: &str = "\
+Explain in one plain-English sentence of at most 25 words what this shell \
+command does, for someone deciding whether to allow it. Say what it changes \
+(files deleted or overwritten, packages installed, data sent over the \
+network) before anything else. Reply with the sentence only.
+
+Command:
+{command}";
+
+/// Explains commands for approval prompts: rules first, then the model.
+#[derive(Default)]
+pub struct CommandExplainer {
+    provider: Option<Arc<dyn Provider>>,
+}
+
+impl CommandExplainer {
+    /// Explanations from rules only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask `provider` about commands the rules don't cover.
+    pub fn with_model(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// One line saying what `command` does, or `None` when neither the rules
+    /// nor the model could say.
+    pub async fn explain(&self, command: &str) -> Option<String> {
+        if let Some(line) = explain_with_rules(command) {
+            return Some(line);
+        }
+        let provider = self.provider.as_ref()?;
+        let mut request = CompletionRequest::new(vec![Message::user(
+            EXPLAIN_PROMPT.replace("{command}", command).as_str(),
+        )])
+        .with_max_tokens(80);
+        if let Some(model) = provider.default_model() {
+            request = request.with_model(model);
+        }
+        match tokio::time::timeout(MODEL_TIMEOUT, provider.complete(request)).await {
+            Ok(Ok(response)) => clean_reply(&response.message.content.to_string_lossy()),
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "Command explainer failed");
+                None
+            }
+            Err(_) => {
+                tracing::warn!("Command explainer timed out");
+                None
+            }
+        }
+    }
+}
+
+/// First line of the model's reply, without Markdown emphasis or quotes.
+fn clean_reply(reply: &str) -> Option<String> {
+    let line = reply
+        .lines()
+        .map(|l| l.trim().trim_matches(['*', '"']).trim())
+        .find(|l| !l.is_empty())?;
+    Some(ellipsize(line, MAX_EXPLANATION_WIDTH))
+}
+
+/// Explanation built from rules, when every part of `command` has one.
+pub fn explain_with_rules(command: &str) -> Option<String> {
+    let segments: Vec<String> = parse::split_pipeline(command.trim())
+        .into_iter()
+        .filter(|s| !s.trim().is_empty())
+        .collect();
+    if segments.is_empty() || segments.len() > MAX_SEGMENTS {
+        return None;
+    }
+    let parts = segments
+        .iter()
+        .map(|s| Segment::parse(s).and_then(|s| s.describe()))
+        .collect::<Option<Vec<String>>>()?;
+
+    let line = parts.join(", then ");
+    let mut chars = line.chars();
+    let first = chars.next()?;
+    let line = format!("{}{}.", first.to_uppercase(), chars.as_str());
+    Some(ellipsize(&line, MAX_EXPLANATION_WIDTH))
+}
+
+/// One command of a pipeline.
+struct Segment {
+    /// Command name without its path
+    name: String,
+    args: Args,
+    /// Output redirect targets, and whether each appends
+    writes: Vec<(String, bool)>,
+}
+
+impl Segment {
+    /// `None` for segments that don't tokenize (unbalanced quotes) or have
+    /// no command.
+    fn parse(segment: &str) -> Option<Segment> {
+        // A heredoc body is data, not arguments
+        let line = if segment.contains("<<") {
+            segment.lines().next().unwrap_or_default().to_string()
+        } else {
+            segment.replace('\n', " ")
+        };
+        let mut words: Vec<String> = Vec::new();
+        let mut writes = Vec::new();
+        let mut tokens = parse::tokenize(&line).ok()?.into_iter();
+        while let Some(token) = tokens.next() {
+            if let Some(rest) = redirect_target(&token) {
+                let append = token.contains(">>");
+                let target = if rest.is_empty() { tokens.next() } else { Some(rest) };
+                if let Some(target) = target {
+                    // Redirects to other descriptors or /dev/null write nothing
+                    if !target.starts_with('&') && !target.starts_with("/dev/") {
+                        writes.push((target, append));
+                    }
+                }
+                continue;
+            }
+            if token == "<" || token == "<<<" || token == "<<" || token == "<<-" {
+                tokens.next();
+                continue;
+            }
+            if token.starts_with('<') {
+                continue;
+            }
+            if words.is_empty() && is_assignment(&token) {
+                continue;
+            }
+            words.push(token);
+        }
+        if words.is_empty() {
+            return None;
+        }
+        let first = words.remove(0);
+        Some(Segment {
+            name: first.rsplit('/').next().unwrap_or(&first).to_string(),
+            args: Args(words),
+            writes,
+        })
+    }
+
+    fn describe(&self) -> Option<String> {
+        // Text written straight to a file reads better as one action
+        if let Some(line) = self.describe_text_write() {
+            return Some(line);
+        }
+        let mut line = describe_command(&self.name, &self.args)?;
+        if !self.writes.is_empty() {
+            let (overwrite, append): (Vec<_>, Vec<_>) = self.writes.iter().partition(|(_, a)| !a);
+            let names = |writes: &[&(String, bool)]| {
+                list(&writes.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>())
+            };
+            if !overwrite.is_empty() {
+                line.push_str(&format!(", writing the output to {}", names(&overwrite)));
+            }
+            if !append.is_empty() {
+                line.push_str(&format!(", appending the output to {}", names(&append)));
+            }
+        }
+        Some(line)
+    }
+
+    /// `echo text > file`, `cat > file <<EOF`, `printf ... >> file`.
+    fn describe_text_write(&self) -> Option<String> {
+        let text = match self.name.as_str() {
+            "echo" | "printf" => self.args.operands(&[]).join(" "),
+            "cat" if self.args.operands(&[]).is_empty() => String::new(),
+            _ => return None,
+        };
+        let [(target, append)] = self.writes.as_slice() else {
+            return None;
+        };
+        let what = if text.is_empty() {
+            "the given text".to_string()
+        } else {
+            quote(&text)
+        };
+        Some(if *append {
+            format!("appends {} to {}", what, target)
+        } else {
+            format!("writes {} to {}, replacing its contents", what, target)
+        })
+    }
+}
+
+/// A command's arguments.
+struct Args(Vec<String>);
+
+impl Args {
+    /// Whether the short flag `short` (alone or bundled, as in `-rf`) or any
+    /// of the long flags `long` is given.
+    fn flag(&self, short: Option<char>, long: &[&str]) -> bool {
+        self.0.iter().take_while(|a| *a != "--").any(|a| {
+            long.iter().any(|l| a == l || a.starts_with(&format!("{}=", l)))
+                || short.is_some_and(|s| {
+                    a.len() > 1
+                        && a.starts_with('-')
+                        && !a.starts_with("--")
+                        && a[1..].chars().all(|c| c.is_ascii_alphanumeric())
+                        && a[1..].contains(s)
+                })
+        })
+    }
+
+    /// Value of the first of `names` given, as `-o value` or `--output=value`.
+    fn value(&self, names: &[&str]) -> Option<&str> {
+        let mut args = self.0.iter().take_while(|a| *a != "--");
+        while let Some(arg) = args.next() {
+            for name in names {
+                if arg == name {
+                    return args.next().map(String::as_str);
+                }
+                if let Some(value) = arg.strip_prefix(&format!("{}=", name)) {
+                    return Some(value);
+                }
+            }
+        }
+        None
+    }
+
+    /// Arguments that aren't flags, skipping the values of `value_flags`;
+    /// everything after `--` counts.
+    fn operands(&self, value_flags: &[&str]) -> Vec<&str> {
+        let mut operands = Vec::new();
+        let mut args = self.0.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                operands.extend(args.by_ref().map(String::as_str));
+                break;
+            }
+            if value_flags.contains(&arg.as_str()) {
+                args.next();
+            } else if !arg.starts_with('-') || arg == "-" {
+                operands.push(arg.as_str());
+            }
+        }
+        operands
+    }
+
+    /// Subcommand (first operand) and the arguments after it.
+    fn subcommand(&self, value_flags: &[&str]) -> Option<(&str, Args)> {
+        let mut i = 0;
+        while i < self.0.len() {
+            let arg = &self.0[i];
+            if value_flags.contains(&arg.as_str()) {
+                i += 2;
+            } else if arg.starts_with('-') {
+                i += 1;
+            } else {
+                return Some((arg.as_str(), Args(self.0[i + 1..].to_vec())));
+            }
+        }
+        None
+    }
+}
+
+/// `a`, `a and b`, `a, b and c`, `a, b and 3 more`.
+fn list(items: &[&str]) -> String {
+    match items {
+        [] => String::new(),
+        [one] => one.to_string(),
+        [init @ .., last] if items.len() <= 3 => format!("{} and {}", init.join(", "), last),
+        [a, b, rest @ ..] => format!("{}, {} and {} more", a, b, rest.len()),
+    }
+}
+
+/// `text` in backticks, shortened.
+fn quote(text: &str) -> String {
+    format!("`{}`", ellipsize(text, MAX_QUOTE_WIDTH))
+}
+
+/// `singular` or `plural` by the number of `items`.
+fn noun<'a>(items: &[&str], singular: &'a str, plural: &'a str) -> &'a str {
+    if items.len() == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+/// `files`, or `fallback` when there are none.
+fn files_or(files: &[&str], fallback: &str) -> String {
+    if files.is_empty() {
+        fallback.to_string()
+    } else {
+        list(files)
+    }
+}
+
+fn describe_command(name: &str, args: &Args) -> Option<String> {
+    let recursive = args.flag(Some('r'), &["--recursive"]) || args.flag(Some('R'), &[]);
+    Some(match name {
+        "rm" => {
+            let targets = args.operands(&[]);
+            if targets.is_empty() {
+                return None;
+            }
+            let mut line = format!("deletes {}", list(&targets));
+            if recursive {
+                line.push_str(" and everything inside");
+            }
+            if args.flag(Some('f'), &["--force"]) {
+                line.push_str(" without asking for confirmation");
+            }
+            line
+        }
+        "rmdir" => {
+            let dirs = args.operands(&[]);
+            format!("removes the empty {} {}", noun(&dirs, "directory", "directories"), list(&dirs))
+        }
+        "mkdir" => {
+            let dirs = args.operands(&["-m", "--mode"]);
+            let mut line = format!("creates the {} {}", noun(&dirs, "directory", "directories"), list(&dirs));
+            if args.flag(Some('p'), &["--parents"]) {
+                line.push_str(" and any missing parents");
+            }
+            line
+        }
+        "touch" => format!(
+            "creates {} if missing, or updates the modification time",
+            list(&args.operands(&[]))
+        ),
+        "mv" | "cp" => {
+            let operands = args.operands(&["-t", "--target-directory"]);
+            let (sources, dest) = match args.value(&["-t", "--target-directory"]) {
+                Some(dest) => (operands.as_slice(), dest),
+                None => match operands.split_last() {
+                    Some((dest, sources)) if !sources.is_empty() => (sources, *dest),
+                    _ => return None,
+                },
+            };
+            let verb = if name == "mv" { "moves" } else { "copies" };
+            let mut line = format!("{} {} to {}", verb, list(sources), dest);
+            if name == "cp" && (recursive || args.flag(Some('a'), &["--archive"])) {
+                line.push_str(" with everything inside");
+            }
+            line
+        }
+        "ln" => {
+            let operands = args.operands(&[]);
+            let (target, link) = match operands.as_slice() {
+                [target] => (*target, target.rsplit('/').next().unwrap_or(target)),
+                [target, link] => (*target, *link),
+                _ => return None,
+            };
+            let kind = if args.flag(Some('s'), &["--symbolic"]) { "symlink" } else { "hard link" };
+            format!("creates a {} {} pointing to {}", kind, link, target)
+        }
+        "chmod" => {
+            let operands = args.operands(&[]);
+            let (mode, files) = operands.split_first()?;
+            let mut line = format!("changes the permissions of {} to {}", list(files), mode);
+            if recursive {
+                line.push_str(", including everything inside");
+            }
+            line
+        }
+        "chown" | "chgrp" => {
+            let operands = args.operands(&[]);
+            let (owner, files) = operands.split_first()?;
+            let what = if name == "chown" { "owner" } else { "group" };
+            format!("changes the {} of {} to {}", what, list(files), owner)
+        }
+        "tee" => {
+            let files = args.operands(&[]);
+            if args.flag(Some('a'), &["--append"]) {
+                format!("appends its input to {}", list(&files))
+            } else {
+                format!("copies its input to {}, replacing their contents", list(&files))
+            }
+        }
+        "sed" => {
+            let script_flags = ["-e", "--expression", "-f", "--file"];
+            let mut operands = args.operands(&script_flags);
+            let script = match args.value(&script_flags) {
+                Some(script) => script.to_string(),
+                None if operands.is_empty() => return None,
+                None => operands.remove(0).to_string(),
+            };
+            if args.flag(Some('i'), &["--in-place"]) {
+                format!("edits {} in place with the sed script {}", list(&operands), quote(&script))
+            } else {
+                format!("transforms {} with the sed script {}", files_or(&operands, "its input"), quote(&script))
+            }
+        }
+        "find" => {
+            let paths: Vec<&str> = args
+                .0
+                .iter()
+                .take_while(|a| !a.starts_with('-') && *a != "(" && *a != "!")
+                .map(String::as_str)
+                .collect();
+            let paths = files_or(&paths, ".");
+            if let Some(i) = args.0.iter().position(|a| a == "-exec" || a == "-execdir") {
+                let command: Vec<&str> = args.0[i + 1..]
+                    .iter()
+                    .take_while(|a| *a != ";" && *a != "+" && *a != "\\;")
+                    .map(String::as_str)
+                    .collect();
+                format!("runs {} on every file found under {}", quote(&command.join(" ")), paths)
+            } else if args.0.iter().any(|a| a == "-delete") {
+                format!("deletes every file under {} that matches the search", paths)
+            } else {
+                format!("searches for files under {}", paths)
+            }
+        }
+        "curl" => {
+            let value_flags = [
+                "-o", "--output", "-X", "--request", "-d", "--data", "--data-raw", "--data-binary",
+                "-H", "--header", "-F", "--form", "-u", "--user", "-A", "--user-agent", "-e",
+                "--referer", "-T", "--upload-file", "-m", "--max-time",
+            ];
+            let url = args.operands(&value_flags).first().copied()?.to_string();
+            let method = args.value(&["-X", "--request"]).map(str::to_uppercase);
+            let sends = args.flag(None, &["-d", "--data", "--data-raw", "--data-binary", "-F", "--form", "-T", "--upload-file"]);
+            if sends || method.as_deref().is_some_and(|m| m != "GET" && m != "HEAD") {
+                let method = method.unwrap_or_else(|| "POST".to_string());
+                format!("sends a {} request with data to {}", method, url)
+            } else if let Some(file) = args.value(&["-o", "--output"]) {
+                format!("downloads {} to {}", url, file)
+            } else if args.flag(Some('O'), &["--remote-name"]) {
+                format!("downloads {} to a file named after it", url)
+            } else {
+                format!("fetches {} and prints the response", url)
+            }
+        }
+        "wget" => {
+            let value_flags = ["-O", "--output-document", "-P", "--directory-prefix"];
+            let url = args.operands(&value_flags).first().copied()?.to_string();
+            match args.value(&["-O", "--output-document"]) {
+                Some("-") => format!("fetches {} and prints the response", url),
+                Some(file) => format!("downloads {} to {}", url, file),
+                None => format!("downloads {}", url),
+            }
+        }
+        "sh" | "bash" | "zsh" | "dash" => match args.value(&["-c"]) {
+            Some(script) => format!("runs the shell command {}", quote(script)),
+            None => match args.operands(&[]).first() {
+                Some(script) => format!("runs the shell script {}", script),
+                None => "runs its input as a shell script".to_string(),
+            },
+        },
+        "python" | "python3" | "node" | "ruby" | "perl" => {
+            let language = match name {
+                "node" => "JavaScript",
+                "ruby" => "Ruby",
+                "perl" => "Perl",
+                _ => "Python",
+            };
+            if args.flag(None, &["-c", "-e", "--eval"]) {
+                format!("runs an inline {} snippet", language)
+            } else if let Some(module) = args.value(&["-m"]) {
+                format!("runs the Python module {}", module)
+            } else {
+                match args.operands(&[]).first() {
+                    Some(script) => format!("runs the {} script {}", language, script),
+                    None => format!("runs {} code from its input", language),
+                }
+            }
+        }
+        "kill" | "pkill" | "killall" => {
+            let targets = args.operands(&["-s", "--signal"]);
+            let force = args.flag(Some('9'), &["-KILL", "-SIGKILL"]);
+            let verb = if force { "force-kills" } else { "stops" };
+            if name == "kill" {
+                format!("{} {} {}", verb, noun(&targets, "process", "processes"), list(&targets))
+            } else {
+                format!("{} processes named {}", verb, list(&targets))
+            }
+        }
+        "xargs" => {
+            let command = args.operands(&["-n", "-I", "-d", "-P", "-L", "--max-args", "--delimiter"]);
+            if command.is_empty() {
+                return None;
+            }
+            format!("runs {} with the lines of its input as arguments", quote(&command.join(" ")))
+        }
+        "dd" => {
+            let operand = |key: &str| {
+                args.0
+                    .iter()
+                    .find_map(|a| a.strip_prefix(key).map(str::to_string))
+            };
+            let to = operand("of=")?;
+            match operand("if=") {
+                Some(from) => format!("copies raw bytes from {} to {}", from, to),
+                None => format!("writes raw bytes from its input to {}", to),
+            }
+        }
+        "truncate" => {
+            let files = args.operands(&["-s", "--size"]);
+            match args.value(&["-s", "--size"]) {
+                Some(size) => format!("resizes {} to {}", list(&files), size),
+                None => return None,
+            }
+        }
+        "tar" => describe_tar(args)?,
+        "unzip" => {
+            let operands = args.operands(&["-d"]);
+            let mut line = format!("extracts {}", operands.first()?);
+            if let Some(dir) = args.value(&["-d"]) {
+                line.push_str(&format!(" into {}", dir));
+            }
+            line
+        }
+        "make" => {
+            let targets: Vec<&str> = args
+                .operands(&["-C", "-f", "-j"])
+                .into_iter()
+                .filter(|t| !is_assignment(t))
+                .collect();
+            match targets.as_slice() {
+                [] => "runs the default make target".to_string(),
+                _ => format!("runs the make {} {}", noun(&targets, "target", "targets"), list(&targets)),
+            }
+        }
+        "git" => describe_git(args)?,
+        "cargo" => describe_cargo(args)?,
+        "npm" | "pnpm" | "yarn" => describe_node_packages(name, args)?,
+        "npx" | "pnpx" | "bunx" => {
+            let package = args.operands(&["-p", "--package"]).first().copied()?.to_string();
+            format!("downloads the package {} if needed and runs it", package)
+        }
+        "pip" | "pip3" => {
+            let (sub, rest) = args.subcommand(&[])?;
+            let packages = rest.operands(&["-r", "--requirement", "-e", "--editable", "-c", "--constraint"]);
+            match sub {
+                "install" => {
+                    if let Some(file) = rest.value(&["-r", "--requirement"]) {
+                        format!("installs the Python packages listed in {}", file)
+                    } else if let Some(path) = rest.value(&["-e", "--editable"]) {
+                        format!("installs {} as an editable Python package", path)
+                    } else {
+                        format!("installs the Python {} {}", noun(&packages, "package", "packages"), list(&packages))
+                    }
+                }
+                "uninstall" => format!("uninstalls the Python {} {}", noun(&packages, "package", "packages"), list(&packages)),
+                _ => return None,
+            }
+        }
+        "docker" | "podman" => {
+            let (sub, rest) = args.subcommand(&["-H", "--host", "--context"])?;
+            let operands = rest.operands(&[]);
+            match sub {
+                "build" => "builds a container image".to_string(),
+                "run" => "starts a new container".to_string(),
+                "exec" => format!("runs a command inside the container {}", operands.first()?),
+                "stop" | "kill" => format!("stops the {} {}", noun(&operands, "container", "containers"), list(&operands)),
+                "rm" => format!("deletes the {} {}", noun(&operands, "container", "containers"), list(&operands)),
+                "rmi" => format!("deletes the {} {}", noun(&operands, "image", "images"), list(&operands)),
+                "pull" => format!("downloads the image {}", operands.first()?),
+                "push" => format!("uploads the image {} to its registry", operands.first()?),
+                _ => return None,
+            }
+        }
+        // Read-only commands, for pipelines that also write
+        "cat" => format!("prints {}", files_or(&args.operands(&[]), "its input")),
+        "ls" => format!("lists {}", files_or(&args.operands(&[]), "the current directory")),
+        "grep" | "rg" | "egrep" => {
+            let mut operands = args.operands(&["-e", "-f", "-A", "-B", "-C", "-m", "--max-count", "-g", "--glob", "-t", "--type"]);
+            let pattern = match args.value(&["-e", "--regexp"]) {
+                Some(pattern) => pattern.to_string(),
+                None if operands.is_empty() => return None,
+                None => operands.remove(0).to_string(),
+            };
+            let fallback = if name == "rg" { "the current directory" } else { "its input" };
+            format!("searches {} for {}", files_or(&operands, fallback), quote(&pattern))
+        }
+        "head" | "tail" => {
+            let which = if name == "head" { "first" } else { "last" };
+            let files = args.operands(&["-n", "-c", "--lines", "--bytes"]);
+            format!("shows the {} lines of {}", which, files_or(&files, "its input"))
+        }
+        "wc" => format!("counts the lines, words and bytes of {}", files_or(&args.operands(&[]), "its input")),
+        "sort" => "sorts the lines".to_string(),
+        "uniq" => "drops repeated lines".to_string(),
+        "jq" => format!("processes JSON with the filter {}", quote(args.operands(&[]).first()?)),
+        "echo" | "printf" => "prints text".to_string(),
+        "cd" => format!("changes to {}", files_or(&args.operands(&[]), "the home directory")),
+        "pwd" => "prints the current directory".to_string(),
+        "diff" => format!("compares {}", list(&args.operands(&[]))),
+        _ => return None,
+    })
+}
+
+/// `tar` with its mode and flags bundled (`-xzf`) or bare (`xzf`).
+fn describe_tar(args: &Args) -> Option<String> {
+    let bundle = args.0.first()?.trim_start_matches('-');
+    let file = args
+        .value(&["-f", "--file"])
+        .or_else(|| {
+            // `tar xzf archive.tgz`: the file follows the bundle
+            bundle.ends_with('f').then(|| args.0.get(1).map(String::as_str)).flatten()
+        })?;
+    let mode = |short: char, long: &str| bundle.contains(short) || args.flag(None, &[long]);
+    if mode('x', "--extract") {
+        let mut line = format!("extracts the archive {}", file);
+        if let Some(dir) = args.value(&["-C", "--directory"]) {
+            line.push_str(&format!(" into {}", dir));
+        }
+        Some(line)
+    } else if mode('c', "--create") {
+        let operands: Vec<&str> = args.0[1..]
+            .iter()
+            .map(String::as_str)
+            .filter(|a| !a.starts_with('-') && *a != file)
+            .collect();
+        Some(format!("creates the archive {} from {}", file, list(&operands)))
+    } else if mode('t', "--list") {
+        Some(format!("lists the contents of the archive {}", file))
+    } else {
+        None
+    }
+}
+
+fn describe_git(args: &Args) -> Option<String> {
+    let (sub, rest) = args.subcommand(&["-C", "-c", "--git-dir", "--work-tree"])?;
+    let operands = rest.operands(&["-m", "--message", "-b", "-B", "-c", "-C", "--author", "-u"]);
+    let current_or = |fallback: &str| operands.first().map_or(fallback.to_string(), |o| o.to_string());
+    Some(match sub {
+        "status" => "shows the working tree status".to_string(),
+        "diff" => "shows uncommitted changes".to_string(),
+        "log" => "shows the commit history".to_string(),
+        "show" => format!("shows {}", current_or("the last commit")),
+        "add" => {
+            if rest.flag(Some('A'), &["--all"]) || operands == ["."] {
+                "stages all changes for the next commit".to_string()
+            } else {
+                format!("stages {} for the next commit", list(&operands))
+            }
+        }
+        "commit" => {
+            let mut line = if rest.flag(None, &["--amend"]) {
+                "rewrites the last commit".to_string()
+            } else {
+                "creates a commit".to_string()
+            };
+            if rest.flag(Some('a'), &["--all"]) {
+                line.push_str(" of all modified tracked files");
+            }
+            if let Some(message) = rest.value(&["-m", "--message"]) {
+                line.push_str(&format!(" with the message {}", quote(message)));
+            }
+            line
+        }
+        "push" => {
+            let remote = operands.first().copied().unwrap_or("the default remote");
+            let refs = &operands[operands.len().min(1)..];
+            let what = files_or(refs, "the current branch");
+            if rest.flag(Some('d'), &["--delete"]) {
+                format!("deletes {} on {}", what, remote)
+            } else if rest.flag(Some('f'), &["--force", "--force-with-lease"])
+                || refs.iter().any(|r| r.starts_with('+'))
+            {
+                format!("force-pushes {} to {}, overwriting its history there", what, remote)
+            } else {
+                format!("pushes {} to {}", what, remote)
+            }
+        }
+        "pull" => {
+            let remote = operands.first().copied().unwrap_or("the default remote");
+            if rest.flag(None, &["--rebase"]) {
+                format!("fetches changes from {} and rebases local commits onto them", remote)
+            } else {
+                format!("fetches changes from {} and merges them into the current branch", remote)
+            }
+        }
+        "fetch" => format!(
+            "downloads new commits from {} without changing local files",
+            current_or("the default remote")
+        ),
+        "checkout" | "switch" => {
+            if let Some(branch) = rest.value(&["-b", "-B", "-c", "-C", "--create"]) {
+                format!("creates the branch {} and switches to it", branch)
+            } else if rest.0.iter().any(|a| a == "--") || operands == ["."] {
+                let files: Vec<&str> = rest.0.iter().skip_while(|a| *a != "--").skip(1).map(String::as_str).collect();
+                format!("discards uncommitted changes to {}", files_or(&files, "all files"))
+            } else {
+                format!("switches to {}", operands.first()?)
+            }
+        }
+        "restore" => {
+            if rest.flag(Some('S'), &["--staged"]) {
+                format!("unstages {}", list(&operands))
+            } else {
+                format!("discards uncommitted changes to {}", list(&operands))
+            }
+        }
+        "reset" => {
+            let target = current_or("the last commit");
+            if rest.flag(None, &["--hard"]) {
+                format!("resets the current branch to {}, discarding all uncommitted changes", target)
+            } else if rest.flag(None, &["--soft"]) {
+                format!("moves the current branch to {}, keeping the changes staged", target)
+            } else if operands.is_empty() {
+                "unstages all staged changes".to_string()
+            } else {
+                format!("moves the current branch to {}, keeping the changes as unstaged edits", target)
+            }
+        }
+        "clean" => {
+            if rest.flag(Some('n'), &["--dry-run"]) {
+                "lists the untracked files that would be deleted".to_string()
+            } else {
+                let mut line = "deletes untracked files".to_string();
+                if rest.flag(Some('d'), &[]) {
+                    line.push_str(" and directories");
+                }
+                if rest.flag(Some('x'), &[]) {
+                    line.push_str(", including ignored ones such as build output");
+                }
+                line
+            }
+        }
+        "stash" => match operands.first().copied().unwrap_or("push") {
+            "push" | "save" => "shelves uncommitted changes".to_string(),
+            "pop" => "restores the latest shelved changes and drops them from the stash".to_string(),
+            "apply" => "restores shelved changes, keeping them in the stash".to_string(),
+            "drop" => "deletes a stash entry".to_string(),
+            "clear" => "deletes all stashed changes".to_string(),
+            "list" => "lists stashed changes".to_string(),
+            _ => return None,
+        },
+        "branch" => {
+            if rest.flag(Some('D'), &[]) || (rest.flag(Some('d'), &["--delete"]) && rest.flag(Some('f'), &["--force"])) {
+                format!("force-deletes the branch {}, even if it isn't merged", list(&operands))
+            } else if rest.flag(Some('d'), &["--delete"]) {
+                format!("deletes the branch {}", list(&operands))
+            } else if rest.flag(Some('m'), &["--move"]) {
+                format!("renames the branch to {}", operands.last()?)
+            } else if let Some(branch) = operands.first() {
+                format!("creates the branch {}", branch)
+            } else {
+                "lists branches".to_string()
+            }
+        }
+        "merge" => {
+            if rest.flag(None, &["--abort"]) {
+                "abandons the merge in progress".to_string()
+            } else {
+                format!("merges {} into the current branch", list(&operands))
+            }
+        }
+        "rebase" => {
+            if rest.flag(None, &["--abort"]) {
+                "abandons the rebase in progress".to_string()
+            } else if rest.flag(None, &["--continue"]) {
+                "continues the rebase in progress".to_string()
+            } else {
+                format!(
+                    "replays the current branch's commits onto {}, rewriting their history",
+                    current_or("its upstream")
+                )
+            }
+        }
+        "cherry-pick" => format!("applies {} to the current branch", list(&operands)),
+        "revert" => format!("creates a commit undoing {}", list(&operands)),
+        "tag" => {
+            if rest.flag(Some('d'), &["--delete"]) {
+                format!("deletes the tag {}", list(&operands))
+            } else if let Some(tag) = operands.first() {
+                format!("creates the tag {}", tag)
+            } else {
+                "lists tags".to_string()
+            }
+        }
+        "rm" => {
+            if rest.flag(None, &["--cached"]) {
+                format!("stops tracking {} but keeps the files", list(&operands))
+            } else {
+                format!("deletes {} and stages the removal", list(&operands))
+            }
+        }
+        "mv" => {
+            let (dest, sources) = operands.split_last()?;
+            format!("moves {} to {} and stages the move", list(sources), dest)
+        }
+        "clone" => {
+            let url = operands.first()?;
+            match operands.get(1) {
+                Some(dir) => format!("copies the repository {} into {}", url, dir),
+                None => format!("copies the repository {} into a new directory", url),
+            }
+        }
+        "init" => format!("creates a Git repository in {}", current_or("the current directory")),
+        _ => return None,
+    })
+}
+
+fn describe_cargo(args: &Args) -> Option<String> {
+    let (sub, rest) = args.subcommand(&["--manifest-path", "-Z", "--config"])?;
+    let operands = rest.operands(&["-p", "--package", "--bin", "--example", "--features", "-F", "--target", "-j"]);
+    let release = if rest.flag(Some('r'), &["--release"]) { " in release mode" } else { "" };
+    Some(match sub {
+        "build" | "b" => format!("compiles the project{}", release),
+        "check" | "c" => "checks the code for errors without building it".to_string(),
+        "clippy" => {
+            if rest.flag(None, &["--fix"]) {
+                "applies Clippy's suggested fixes to the source files".to_string()
+            } else {
+                "checks the code with Clippy lints".to_string()
+            }
+        }
+        "test" | "t" => match operands.first() {
+            Some(filter) => format!("builds and runs the tests matching {}", quote(filter)),
+            None => "builds and runs the tests".to_string(),
+        },
+        "run" | "r" => format!("builds and runs the program{}", release),
+        "bench" => "builds and runs the benchmarks".to_string(),
+        "doc" => "builds the documentation".to_string(),
+        "fmt" => {
+            if rest.flag(None, &["--check"]) {
+                "checks the source formatting".to_string()
+            } else {
+                "reformats the source files".to_string()
+            }
+        }
+        "fix" => "applies the compiler's suggested fixes to the source files".to_string(),
+        "add" => format!(
+            "adds the {} {} to Cargo.toml",
+            noun(&operands, "dependency", "dependencies"),
+            list(&operands)
+        ),
+        "remove" | "rm" => format!(
+            "removes the {} {} from Cargo.toml",
+            noun(&operands, "dependency", "dependencies"),
+            list(&operands)
+        ),
+        "update" => "updates the dependency versions in Cargo.lock".to_string(),
+        "clean" => "deletes the build output in target/".to_string(),
+        "install" => format!("builds and installs {} as a command", files_or(&operands, "this crate")),
+        "uninstall" => format!("uninstalls the command {}", list(&operands)),
+        "publish" => "uploads the crate to crates.io (a published version can't be removed)".to_string(),
+        "new" | "init" => format!("creates a new Cargo package in {}", current_dir_or(&operands)),
+        _ => return None,
+    })
+}
+
+fn current_dir_or(operands: &[&str]) -> String {
+    files_or(operands, "the current directory")
+}
+
+fn describe_node_packages(name: &str, args: &Args) -> Option<String> {
+    let (sub, rest) = match args.subcommand(&["--prefix", "-C", "--dir", "--cwd"]) {
+        Some(found) => found,
+        // Bare `yarn` and `pnpm` install dependencies
+        None if name != "npm" => ("install", Args(Vec::new())),
+        None => return None,
+    };
+    let packages = rest.operands(&[]);
+    let global = if rest.flag(Some('g'), &["--global"]) { " globally" } else { "" };
+    let packages_noun = noun(&packages, "package", "packages");
+    Some(match sub {
+        "install" | "i" | "add" | "ci" => {
+            if packages.is_empty() {
+                "installs the dependencies in package.json".to_string()
+            } else {
+                format!("installs the {} {}{}", packages_noun, list(&packages), global)
+            }
+        }
+        "uninstall" | "remove" | "rm" | "un" => {
+            format!("uninstalls the {} {}{}", packages_noun, list(&packages), global)
+        }
+        "update" | "upgrade" | "up" => format!("updates {}", files_or(&packages, "all dependencies")),
+        "run" | "run-script" => format!("runs the package script {}", packages.first()?),
+        "test" | "t" => "runs the package's test script".to_string(),
+        "start" => "runs the package's start script".to_string(),
+        "build" => "runs the package's build script".to_string(),
+        "publish" => "publishes the package to the npm registry".to_string(),
+        // `yarn lint`, `pnpm dev`: scripts run by name
+        script if name != "npm" && !script.is_empty() => format!("runs the package script {}", script),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::testing::MockProvider;
+
+    #[test]
+    fn test_explains_common_commands() {
+        let cases = [
+            ("rm -rf build", "Deletes build and everything inside without asking for confirmation."),
+            ("git push --force origin main", "Force-pushes main to origin, overwriting its history there."),
+            ("git reset --hard HEAD~1", "Resets the current branch to HEAD~1, discarding all uncommitted changes."),
+            ("git clean -fdx", "Deletes untracked files and directories, including ignored ones such as build output."),
+            ("sed -i 's/foo/bar/g' src/a.rs src/b.rs", "Edits src/a.rs and src/b.rs in place with the sed script `s/foo/bar/g`."),
+            ("find . -name '*.orig' -delete", "Deletes every file under . that matches the search."),
+            ("curl -fsSL https://example.com/install.sh | sh", "Fetches https://example.com/install.sh and prints the response, then runs its input as a shell script."),
+            ("cargo add serde tokio", "Adds the dependencies serde and tokio to Cargo.toml."),
+            ("npm install -g typescript", "Installs the package typescript globally."),
+            ("echo 'API_URL=x' >> .env", "Appends `API_URL=x` to .env."),
+            ("RUST_LOG=debug cargo test parser 2>&1 | tail -20", "Builds and runs the tests matching `parser`, then shows the last lines of its input."),
+            ("tar xzf dist.tgz -C /tmp/out", "Extracts the archive dist.tgz into /tmp/out."),
+            ("git commit -am 'Fix parser'", "Creates a commit of all modified tracked files."),
+            ("mkdir -p a/b && mv x.txt a/b/", "Creates the directory a/b and any missing parents, then moves x.txt to a/b/."),
+        ];
+        for (command, expected) in cases {
+            assert_eq!(explain_with_rules(command).as_deref(), Some(expected), "{}", command);
+        }
+
+        // Redirects are part of the explanation
+        assert_eq!(
+            explain_with_rules("cargo build --release > build.log").as_deref(),
+            Some("Compiles the project in release mode, writing the output to build.log.")
+        );
+    }
+
+    #[test]
+    fn test_unknown_parts_leave_it_to_the_model() {
+        assert_eq!(explain_with_rules("frobnicate --all"), None);
+        assert_eq!(explain_with_rules("rm -rf build && frobnicate"), None);
+        assert_eq!(explain_with_rules("git frobnicate"), None);
+        assert_eq!(explain_with_rules("rm"), None);
+        // Unbalanced quotes
+        assert_eq!(explain_with_rules("rm 'build"), None);
+        // Too long to read at a glance
+        assert_eq!(explain_with_rules("ls | sort | uniq | head | wc -l"), None);
+    }
+
+    #[tokio::test]
+    async fn test_model_fallback() {
+        let provider = Arc::new(MockProvider::new());
+        provider.queue_response("**Converts every PNG in assets to WebP with quality 80.**\nMore detail");
+        let explainer = CommandExplainer::new().with_model(provider.clone());
+
+        assert_eq!(
+            explainer.explain("rm -r dist").await.as_deref(),
+            Some("Deletes dist and everything inside.")
+        );
+        assert_eq!(
+            explainer.explain("magick mogrify -format webp -quality 80 assets/*.png").await.as_deref(),
+            Some("Converts every PNG in assets to WebP with quality 80.")
+        );
+        // Rules only: nothing to say
+        assert_eq!(CommandExplainer::new().explain("magick mogrify x").await, None);
+    }
+}
@@ -0,0 +1,834 @@
+//! Git write tools: `git_stage`, `git_commit`, and `git_branch`.
+//!
+//! Agents that can only commit through `run` tend not to, and a long session
+//! ends with one large uncommitted working tree. These tools let the coder
+//! and PM commit at logical milestones instead. Git runs in the sandbox with
+//! the project root writable, so hooks and filters get no more access than
+//! `run` does. Each call goes through the same permission tiers as the
+//! matching `run` command (`git-add`, `git-commit`, `git-switch`): approval
+//! per call by default, "allow for session" and `[tools.bash_permissions]`
+//! overrides apply. A commit's approval prompt carries the staged diff.
+//! Write policies (protected paths included) see the files a branch switch
+//! would change, so a switch that touches one asks or is refused.
+//!
+//! `git_commit` reports `Committed <hash> on <branch>: <subject>`, which the
+//! tool journal picks up (see `qq_core::StateDelta`), so continuations and
+//! summaries know what was committed.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use qq_core::text::truncate_bytes;
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use super::linters::{at_project_root, scope_paths, shell_quote};
+use super::mounts::SandboxMounts;
+use super::permissions::{ApprovalChannel, ApprovalResponse, PermissionStore, Tier};
+use super::sandbox::{SandboxExecutor, SandboxPathPolicy};
+use super::write_policy::{self, WriteClassifier, WriteOperation, WriteVerdict};
+
+/// Timeout for one git invocation; commit hooks may run tests.
+const GIT_TIMEOUT_SECS: u64 = 120;
+
+/// Larger diffs in approval previews are cut with a note.
+const MAX_PREVIEW_BYTES: usize = 512 * 1024;
+
+/// Where the tools write: calls in one batch touching it are serialized.
+const GIT_DIR: &str = ".git";
+
+/// First line of a `git_commit` result, before the hash. `StateDelta`
+/// looks for it.
+const COMMITTED_PREFIX: &str = "Committed ";
+
+const GIT_STAGE_TOOL_DESC: &str = "\
+Stage files for the next commit (`git add`). Requires user approval.
+
+Pass the paths (relative to the working directory) that belong to one logical change, \
+or all: true to stage every change in the work tree, new and deleted files included. \
+Returns the staged summary (`git diff --cached --stat`). Stage, then call git_commit.";
+
+const GIT_COMMIT_TOOL_DESC: &str = "\
+Commit the staged changes (`git commit`). Requires user approval; the user sees the \
+staged diff and your message.
+
+Commit at logical milestones (a feature working, a bug fixed with its test) rather than \
+once at the end. Write the message like a developer would: a subject line under 72 \
+characters in the imperative mood (\"Add retry to fetch client\"), then a blank line and \
+a short body saying why when it isn't obvious. Returns the new commit's hash and stats. \
+Fails when nothing is staged.";
+
+const GIT_BRANCH_TOOL_DESC: &str = "\
+Create a branch and switch to it (`git switch -c`), or switch to an existing branch \
+with create: false. Requires user approval.
+
+Use before committing work the user may want to review separately. Uncommitted changes \
+carry over to the new branch; git refuses a switch that would overwrite them. A switch \
+that changes protected paths asks the user again or is refused.";
+
+/// Runs git in the sandbox against the project root and asks for approval.
+pub struct GitSandbox {
+    mounts: Arc<SandboxMounts>,
+    permissions: Arc<PermissionStore>,
+    approval: ApprovalChannel,
+    path_policy: Arc<RwLock<SandboxPathPolicy>>,
+    executor: SandboxExecutor,
+    write_classifiers: Vec<Arc<dyn WriteClassifier>>,
+}
+
+impl GitSandbox {
+    pub fn new(
+        mounts: Arc<SandboxMounts>,
+        permissions: Arc<PermissionStore>,
+        approval: ApprovalChannel,
+        path_policy: Arc<RwLock<SandboxPathPolicy>>,
+        executor: SandboxExecutor,
+    ) -> Self {
+        Self {
+            mounts,
+            permissions,
+            approval,
+            path_policy,
+            executor,
+            write_classifiers: Vec::new(),
+        }
+    }
+
+    /// Check the files a branch switch would change against `classifier`.
+    /// Classifiers run in the order added; the strictest verdict wins.
+    pub fn with_write_classifier(mut self, classifier: Arc<dyn WriteClassifier>) -> Self {
+        self.write_classifiers.push(classifier);
+        self
+    }
+
+    /// The three git write tools over one sandbox.
+    pub fn into_tools(self) -> Vec<Arc<dyn Tool>> {
+        let git = Arc::new(self);
+        vec![
+            Arc::new(GitStageTool {
+                git: Arc::clone(&git),
+            }),
+            Arc::new(GitCommitTool {
+                git: Arc::clone(&git),
+            }),
+            Arc::new(GitBranchTool { git }),
+        ]
+    }
+
+    /// Error for tools used where git can't be sandboxed.
+    fn unsupported(&self, tool: &str) -> Option<ToolOutput> {
+        (!self.executor.supports_shell()).then(|| {
+            ToolOutput::error(format!(
+                "{} is not available in app-level sandbox mode. \
+                 Kernel sandbox (Linux with user namespaces) is required.",
+                tool
+            ))
+        })
+    }
+
+    /// Run `git <args>` from the project root; stdout on success, the
+    /// error output otherwise.
+    async fn git(&self, args: &[&str], stdin: Option<&str>) -> Result<String, String> {
+        let root = self.mounts.project_root();
+        // User namespaces can make the repository look foreign-owned
+        let mut command = format!(
+            "git -c safe.directory={} -c core.pager=cat -c color.ui=never",
+            shell_quote(&root.display().to_string())
+        );
+        // The sandbox's HOME has no gitconfig; commit as the user would
+        for (key, value) in identity(root).await {
+            command.push_str(&format!(" -c {}", shell_quote(&format!("{}={}", key, value))));
+        }
+        for arg in args {
+            command.push(' ');
+            command.push_str(&shell_quote(arg));
+        }
+
+        // Paths are relative to the project root, even under `/focus`
+        let command = at_project_root(&self.mounts, command);
+        let policy = match self.path_policy.read() {
+            Ok(p) => p.clone(),
+            Err(_) => return Err("Path policy lock poisoned.".to_string()),
+        };
+        let result = self
+            .executor
+            .execute(&command, &self.mounts, GIT_TIMEOUT_SECS, &policy, stdin, false)
+            .await?;
+        if let Some(e) = result.sandbox_error {
+            return Err(format!("sandbox error: {}", e));
+        }
+        if result.timed_out {
+            return Err(format!("git {} timed out after {}s", args[0], GIT_TIMEOUT_SECS));
+        }
+        if result.exit_code != 0 {
+            let output = if result.stderr.trim().is_empty() {
+                result.stdout
+            } else {
+                result.stderr
+            };
+            return Err(format!("git {} failed: {}", args[0], output.trim()));
+        }
+        Ok(result.stdout)
+    }
+
+    /// Ask to run `command` (summarized for the prompt) under the tier of
+    /// `trigger`. A write policy's `policy_reason` asks even at session
+    /// tier. `Err` carries the tool's reply when it may not run.
+    async fn approve(
+        &self,
+        trigger: &str,
+        command: String,
+        preview: Option<String>,
+        policy_reason: Option<String>,
+    ) -> Result<(), ToolOutput> {
+        match self.permissions.check_tier(trigger) {
+            Tier::Session if policy_reason.is_none() => return Ok(()),
+            Tier::Restricted => {
+                return Err(ToolOutput::error(format!(
+                    "{} is restricted by the permission settings.",
+                    trigger
+                )))
+            }
+            Tier::Session | Tier::PerCall => {}
+        }
+        let mut triggers = vec![trigger.to_string()];
+        let category = match policy_reason {
+            Some(reason) => {
+                triggers.push(format!("write policy: {}", reason));
+                "Write policy"
+            }
+            None => "Git",
+        };
+        match self
+            .approval
+            .request_previewed_approval(command, triggers, category, None, preview)
+            .await
+        {
+            Ok(ApprovalResponse::Allow) => Ok(()),
+            Ok(ApprovalResponse::AllowForSession) => {
+                // Promotes the command only; policy checks still apply
+                self.permissions.promote_to_session(trigger);
+                Ok(())
+            }
+            Ok(ApprovalResponse::Deny(reason)) => Err(ToolOutput::error(match reason {
+                Some(r) => format!("Denied by user: {r}"),
+                None => "Denied by user.".to_string(),
+            })),
+            Err(e) => Err(ToolOutput::error(format!("Approval system unavailable: {}", e))),
+        }
+    }
+
+    /// Verdict of the write classifiers on the files that differ between
+    /// HEAD and `target`, which a switch to it rewrites.
+    async fn switch_verdict(&self, command: &str, target: &str) -> Result<WriteVerdict, String> {
+        let changed = self
+            .git(&["diff", "--no-ext-diff", "--name-only", "HEAD", target, "--"], None)
+            .await?;
+        let op = WriteOperation {
+            command: command.to_string(),
+            commands: vec!["git-switch".to_string()],
+            targets: changed.lines().filter(|l| !l.is_empty()).map(str::to_string).collect(),
+        };
+        if op.targets.is_empty() {
+            return Ok(WriteVerdict::Allow);
+        }
+        Ok(write_policy::classify_write(&self.write_classifiers, &op).await)
+    }
+}
+
+/// `user.name` and `user.email` from the host's git config for `root`.
+async fn identity(root: &Path) -> Vec<(&'static str, String)> {
+    let mut found = Vec::new();
+    for key in ["user.name", "user.email"] {
+        let output = tokio::process::Command::new("git")
+            .args(["config", "--get", key])
+            .current_dir(root)
+            .output()
+            .await;
+        if let Ok(output) = output {
+            let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if output.status.success() && !value.is_empty() {
+                found.push((key, value));
+            }
+        }
+    }
+    found
+}
+
+/// A diff for an approval prompt, `None` when empty.
+fn preview(diff: String) -> Option<String> {
+    if diff.trim().is_empty() {
+        return None;
+    }
+    if diff.len() <= MAX_PREVIEW_BYTES {
+        return Some(diff);
+    }
+    let cut = truncate_bytes(&diff, MAX_PREVIEW_BYTES);
+    let cut = cut.rfind('\n').map_or(cut, |i| &cut[..=i]);
+    Some(format!("{}[preview truncated]\n", cut))
+}
+
+/// A commit message with trailing whitespace trimmed from each line and
+/// surrounding blank lines removed; `Err` when nothing is left.
+fn clean_message(message: &str) -> Result<String, String> {
+    let lines: Vec<&str> = message.lines().map(str::trim_end).collect();
+    let text = lines.join("\n");
+    let text = text.trim_matches('\n');
+    if text.is_empty() {
+        return Err("Commit message is empty".to_string());
+    }
+    Ok(format!("{}\n", text))
+}
+
+/// Whether git would accept `name` for a new branch. Checked again by git
+/// itself; this catches option-like and obviously malformed names early.
+fn valid_branch_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.starts_with('/')
+        && !name.ends_with('/')
+        && !name.ends_with(".lock")
+        && !name.ends_with('.')
+        && !name.contains("..")
+        && !name.contains("@{")
+        && !name.contains("//")
+        && name != "@"
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "~^:?*[\\".contains(c))
+}
+
+#[derive(Deserialize)]
+struct StageArgs {
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    all: bool,
+}
+
+/// `git_stage`: `git add` for paths under the project root.
+pub struct GitStageTool {
+    git: Arc<GitSandbox>,
+}
+
+#[async_trait]
+impl Tool for GitStageTool {
+    fn name(&self) -> &str {
+        "git_stage"
+    }
+
+    fn description(&self) -> &str {
+        "Stage files for the next commit (with approval)"
+    }
+
+    fn tool_description(&self) -> &str {
+        GIT_STAGE_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "paths",
+                    PropertySchema::array(
+                        "Files or directories to stage, relative to the working directory",
+                        PropertySchema::string("Path"),
+                    ),
+                    false,
+                )
+                .add_property(
+                    "all",
+                    PropertySchema::boolean("Stage every change in the work tree (default false)"),
+                    false,
+                ),
+        )
+    }
+
+    fn write_targets(&self, _arguments: &serde_json::Value) -> Vec<String> {
+        vec![GIT_DIR.to_string()]
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: StageArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("git_stage", format!("Invalid arguments: {}", e)))?;
+        if let Some(unsupported) = self.git.unsupported("git_stage") {
+            return Ok(unsupported);
+        }
+        if args.paths.is_empty() && !args.all {
+            return Ok(ToolOutput::error("Pass the paths to stage, or all: true."));
+        }
+        let paths = match scope_paths(&self.git.mounts, &args.paths) {
+            Ok(paths) => paths,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let mut add: Vec<&str> = vec!["add"];
+        let mut diff: Vec<&str> = vec!["diff", "--no-ext-diff"];
+        if args.all {
+            add.push("--all");
+        }
+        if !paths.is_empty() {
+            for command in [&mut add, &mut diff] {
+                command.push("--");
+                command.extend(paths.iter().map(String::as_str));
+            }
+        }
+        // Untracked files aren't in the preview; the command line names them
+        let diff = self.git.git(&diff, None).await.unwrap_or_default();
+        if let Err(denied) = self
+            .git
+            .approve("git-add", format!("git {}", add.join(" ")), preview(diff), None)
+            .await
+        {
+            return Ok(denied);
+        }
+        if let Err(e) = self.git.git(&add, None).await {
+            return Ok(ToolOutput::error(e));
+        }
+
+        let staged = self
+            .git
+            .git(&["diff", "--cached", "--stat"], None)
+            .await
+            .unwrap_or_default();
+        Ok(ToolOutput::success(if staged.trim().is_empty() {
+            "Nothing staged: the paths have no changes.".to_string()
+        } else {
+            format!("Staged for commit:\n{}", staged.trim_end())
+        }))
+    }
+}
+
+#[derive(Deserialize)]
+struct CommitArgs {
+    message: String,
+}
+
+/// `git_commit`: commits the index with the agent's message.
+pub struct GitCommitTool {
+    git: Arc<GitSandbox>,
+}
+
+#[async_trait]
+impl Tool for GitCommitTool {
+    fn name(&self) -> &str {
+        "git_commit"
+    }
+
+    fn description(&self) -> &str {
+        "Commit staged changes with a message (with approval)"
+    }
+
+    fn tool_description(&self) -> &str {
+        GIT_COMMIT_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new().add_property(
+                "message",
+                PropertySchema::string("Commit message: subject line, blank line, optional body"),
+                true,
+            ),
+        )
+    }
+
+    fn write_targets(&self, _arguments: &serde_json::Value) -> Vec<String> {
+        vec![GIT_DIR.to_string()]
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: CommitArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("git_commit", format!("Invalid arguments: {}", e)))?;
+        if let Some(unsupported) = self.git.unsupported("git_commit") {
+            return Ok(unsupported);
+        }
+        let message = match clean_message(&args.message) {
+            Ok(message) => message,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let staged = match self.git.git(&["diff", "--cached", "--no-ext-diff"], None).await {
+            Ok(diff) => diff,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        if staged.trim().is_empty() {
+            return Ok(ToolOutput::error("Nothing staged to commit; call git_stage first."));
+        }
+        let summary = format!("git commit\n\n{}", message.trim_end());
+        if let Err(denied) = self.git.approve("git-commit", summary, preview(staged), None).await {
+            return Ok(denied);
+        }
+        if let Err(e) = self.git.git(&["commit", "--quiet", "--file=-"], Some(&message)).await {
+            return Ok(ToolOutput::error(e));
+        }
+
+        let head = self
+            .git
+            .git(&["log", "-1", "--format=%h%n%s"], None)
+            .await
+            .unwrap_or_default();
+        let mut head = head.lines();
+        let hash = head.next().unwrap_or("HEAD").to_string();
+        let subject = head.next().unwrap_or_default().to_string();
+        let branch = self
+            .git
+            .git(&["branch", "--show-current"], None)
+            .await
+            .ok()
+            .map(|b| b.trim().to_string())
+            .filter(|b| !b.is_empty())
+            .unwrap_or_else(|| "detached HEAD".to_string());
+        let stat = self
+            .git
+            .git(&["show", "--stat", "--format=", "HEAD"], None)
+            .await
+            .unwrap_or_default();
+        Ok(ToolOutput::success(format!(
+            "{}{} on {}: {}\n{}",
+            COMMITTED_PREFIX,
+            hash,
+            branch,
+            subject,
+            stat.trim_end()
+        )))
+    }
+}
+
+#[derive(Deserialize)]
+struct BranchArgs {
+    name: String,
+    #[serde(default)]
+    start_point: Option<String>,
+    #[serde(default = "default_create")]
+    create: bool,
+}
+
+fn default_create() -> bool {
+    true
+}
+
+/// `git_branch`: creates and switches to a branch.
+pub struct GitBranchTool {
+    git: Arc<GitSandbox>,
+}
+
+#[async_trait]
+impl Tool for GitBranchTool {
+    fn name(&self) -> &str {
+        "git_branch"
+    }
+
+    fn description(&self) -> &str {
+        "Create or switch to a git branch (with approval)"
+    }
+
+    fn tool_description(&self) -> &str {
+        GIT_BRANCH_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property("name", PropertySchema::string("Branch name"), true)
+                .add_property(
+                    "start_point",
+                    PropertySchema::string("Commit or branch to start from (default: HEAD)"),
+                    false,
+                )
+                .add_property(
+                    "create",
+                    PropertySchema::boolean(
+                        "Create the branch (default true); false switches to an existing one",
+                    ),
+                    false,
+                ),
+        )
+    }
+
+    fn write_targets(&self, _arguments: &serde_json::Value) -> Vec<String> {
+        vec![GIT_DIR.to_string()]
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: BranchArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("git_branch", format!("Invalid arguments: {}", e)))?;
+        if let Some(unsupported) = self.git.unsupported("git_branch") {
+            return Ok(unsupported);
+        }
+        let name = args.name.trim();
+        if !valid_branch_name(name) {
+            return Ok(ToolOutput::error(format!("Invalid branch name: '{}'", name)));
+        }
+        let start = args.start_point.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        if start.is_some_and(|s| s.starts_with('-')) {
+            return Ok(ToolOutput::error("start_point must name a commit or branch"));
+        }
+
+        let mut switch = vec!["switch"];
+        if args.create {
+            switch.push("--create");
+        } else if start.is_some() {
+            return Ok(ToolOutput::error("start_point only applies with create: true"));
+        }
+        switch.push(name);
+        switch.extend(start);
+        let command = format!("git {}", switch.join(" "));
+
+        // Where the work tree ends up; a new branch at HEAD changes nothing
+        let target = if args.create { start } else { Some(name) };
+        let policy_reason = match target {
+            Some(target) if !self.git.write_classifiers.is_empty() => {
+                match self.git.switch_verdict(&command, target).await {
+                    Ok(WriteVerdict::Allow) => None,
+                    Ok(WriteVerdict::Ask(reason)) => Some(reason),
+                    Ok(WriteVerdict::Block(reason)) => {
+                        return Ok(ToolOutput::error(format!(
+                            "Blocked by write policy: {}. Do not retry this or an equivalent \
+                             switch; ask the user if the change is needed.",
+                            reason
+                        )))
+                    }
+                    Err(e) => return Ok(ToolOutput::error(e)),
+                }
+            }
+            _ => None,
+        };
+        if let Err(denied) = self
+            .git
+            .approve("git-switch", command, None, policy_reason)
+            .await
+        {
+            return Ok(denied);
+        }
+        if let Err(e) = self.git.git(&switch, None).await {
+            return Ok(ToolOutput::error(e));
+        }
+        Ok(ToolOutput::success(if args.create {
+            format!("Created branch {} and switched to it.", name)
+        } else {
+            format!("Switched to branch {}.", name)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approval::create_approval_channel;
+    use crate::bash::write_policy::{WritePolicy, WriteRule};
+    use std::collections::HashMap;
+    use std::process::Command;
+
+    #[test]
+    fn test_clean_message() {
+        assert_eq!(
+            clean_message("\n\nAdd parser  \n\nWhy it helps.\n\n").unwrap(),
+            "Add parser\n\nWhy it helps.\n"
+        );
+        assert!(clean_message("  \n\n").is_err());
+    }
+
+    #[test]
+    fn test_valid_branch_name() {
+        for name in ["feature/parser", "fix-123", "wip.v2"] {
+            assert!(valid_branch_name(name), "{}", name);
+        }
+        for name in ["", "-f", "a..b", "a b", "x.lock", "a:b", "topic/", "@", "a@{1}"] {
+            assert!(!valid_branch_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_preview_truncates() {
+        assert_eq!(preview("  \n".to_string()), None);
+        let long = "+line\n".repeat(MAX_PREVIEW_BYTES / 6 + 10);
+        let cut = preview(long).unwrap();
+        assert!(cut.ends_with("+line\n[preview truncated]\n"));
+        assert!(cut.len() <= MAX_PREVIEW_BYTES + 20);
+    }
+
+    /// Stage, commit, and branch in a scratch repository, answering the
+    /// approval prompts.
+    #[tokio::test]
+    async fn test_stage_commit_and_branch() {
+        let executor = SandboxExecutor::detect();
+        if !executor.supports_shell() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            let status = Command::new("git").args(args).current_dir(root).output().unwrap().status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["config", "user.email", "qq@example.com"]);
+        git(&["config", "user.name", "qq"]);
+        std::fs::write(root.join("a.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.join("b.rs"), "fn b() {}\n").unwrap();
+
+        let mounts = Arc::new(SandboxMounts::new(root.to_path_buf()).unwrap());
+        let permissions = Arc::new(PermissionStore::new(HashMap::new()));
+        let (approval, mut rx) = create_approval_channel();
+        let tools = GitSandbox::new(
+            mounts,
+            Arc::clone(&permissions),
+            approval,
+            Arc::new(RwLock::new(SandboxPathPolicy::system_only())),
+            executor,
+        )
+        .into_tools();
+        let (stage, commit, branch) = (&tools[0], &tools[1], &tools[2]);
+
+        let prompts = tokio::spawn(async move {
+            let mut seen = Vec::new();
+            while let Some(request) = rx.recv().await {
+                let response = match request.trigger_commands[0].as_str() {
+                    "git-switch" => ApprovalResponse::Deny(Some("not now".into())),
+                    _ => ApprovalResponse::AllowForSession,
+                };
+                seen.push((request.full_command, request.preview));
+                let _ = request.response_tx.send(response);
+            }
+            seen
+        });
+
+        let result = commit
+            .execute(serde_json::json!({"message": "Add a"}))
+            .await
+            .unwrap();
+        assert!(result.text_content().contains("Nothing staged"));
+
+        let result = stage
+            .execute(serde_json::json!({"paths": ["a.rs"]}))
+            .await
+            .unwrap();
+        assert!(!result.is_error, "{}", result.text_content());
+        assert!(result.text_content().contains("a.rs"));
+        assert!(!result.text_content().contains("b.rs"));
+
+        let result = commit
+            .execute(serde_json::json!({"message": "Add a\n\nFirst file."}))
+            .await
+            .unwrap();
+        let text = result.text_content();
+        assert!(text.starts_with("Committed "), "{}", text);
+        assert!(text.contains(" on main: Add a\n"));
+        assert!(text.contains("a.rs"));
+
+        // Promoted for the session: the second commit isn't asked about
+        stage.execute(serde_json::json!({"all": true})).await.unwrap();
+        commit
+            .execute(serde_json::json!({"message": "Add b"}))
+            .await
+            .unwrap();
+        let log = Command::new("git")
+            .args(["log", "--format=%s"])
+            .current_dir(root)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&log.stdout), "Add b\nAdd a\n");
+
+        let result = branch
+            .execute(serde_json::json!({"name": "feature/x"}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("not now"));
+        let result = branch
+            .execute(serde_json::json!({"name": "--force"}))
+            .await
+            .unwrap();
+        assert!(result.text_content().contains("Invalid branch name"));
+
+        drop(tools);
+        let seen = prompts.await.unwrap();
+        assert_eq!(seen.len(), 3, "{:?}", seen);
+        assert_eq!(seen[0].0, "git add -- a.rs");
+        assert!(seen[1].0.starts_with("git commit\n\nAdd a\n\nFirst file."));
+        assert!(seen[1].1.as_deref().unwrap().contains("+fn a() {}"));
+        assert_eq!(seen[2].0, "git switch --create feature/x");
+    }
+
+    /// A switch that would rewrite a protected path asks even with
+    /// `git-switch` allowed for the session.
+    #[tokio::test]
+    async fn test_branch_switch_checks_protected_paths() {
+        let executor = SandboxExecutor::detect();
+        if !executor.supports_shell() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            let status = Command::new("git").args(args).current_dir(root).output().unwrap().status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["config", "user.email", "qq@example.com"]);
+        git(&["config", "user.name", "qq"]);
+        std::fs::write(root.join("Cargo.lock"), "v1\n").unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "base"]);
+        git(&["switch", "-q", "-c", "lock-bump"]);
+        std::fs::write(root.join("Cargo.lock"), "v2\n").unwrap();
+        git(&["commit", "-q", "-am", "bump"]);
+        git(&["switch", "-q", "-c", "docs", "main"]);
+        std::fs::write(root.join("a.rs"), "/// A.\nfn a() {}\n").unwrap();
+        git(&["commit", "-q", "-am", "docs"]);
+        git(&["switch", "-q", "main"]);
+
+        let mounts = Arc::new(SandboxMounts::new(root.to_path_buf()).unwrap());
+        let permissions = Arc::new(PermissionStore::new(HashMap::new()));
+        permissions.promote_to_session("git-switch");
+        let (approval, mut rx) = create_approval_channel();
+        let policy = WritePolicy::new(vec![WriteRule::protected("Cargo.lock")], root);
+        let tools = GitSandbox::new(
+            mounts,
+            permissions,
+            approval,
+            Arc::new(RwLock::new(SandboxPathPolicy::system_only())),
+            executor,
+        )
+        .with_write_classifier(Arc::new(policy))
+        .into_tools();
+        let branch = &tools[2];
+
+        let prompts = tokio::spawn(async move {
+            let mut seen = Vec::new();
+            while let Some(request) = rx.recv().await {
+                seen.push(request.trigger_commands.clone());
+                let _ = request.response_tx.send(ApprovalResponse::Deny(None));
+            }
+            seen
+        });
+
+        let result = branch
+            .execute(serde_json::json!({"name": "lock-bump", "create": false}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("Denied by user"));
+        let result = branch
+            .execute(serde_json::json!({"name": "from-bump", "start_point": "lock-bump"}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert_eq!(std::fs::read_to_string(root.join("Cargo.lock")).unwrap(), "v1\n");
+
+        // Session tier still covers switches that leave protected paths alone
+        let result = branch
+            .execute(serde_json::json!({"name": "docs", "create": false}))
+            .await
+            .unwrap();
+        assert!(!result.is_error, "{}", result.text_content());
+
+        drop(tools);
+        let seen = prompts.await.unwrap();
+        assert_eq!(seen.len(), 2, "{:?}", seen);
+        assert_eq!(seen[0][1], "write policy: 'Cargo.lock' is a protected path");
+    }
+}
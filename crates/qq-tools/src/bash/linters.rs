@@ -0,0 +1,869 @@
+//! Static analysis for reviews.
+//!
+//! `run_linters` detects the linters a project is configured for (clippy,
+//! ESLint, ruff), runs them in the read-only sandbox, and returns their
+//! diagnostics as `file:line:col` records grouped by file, so a reviewer can
+//! cross-reference them with a diff instead of relying on what it spots by
+//! reading.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path};
+use std::sync::{Arc, RwLock};
+
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use super::mounts::SandboxMounts;
+use super::sandbox::{SandboxExecutor, SandboxPathPolicy};
+
+/// Default per-linter timeout. Clippy compiles the project first.
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// Upper bound for a caller-supplied `timeout`.
+const MAX_TIMEOUT_SECS: u64 = 900;
+
+/// Default cap on diagnostics returned across all linters.
+const DEFAULT_MAX_DIAGNOSTICS: usize = 200;
+
+/// Cargo target directory for clippy, inside the session `/tmp` so the
+/// read-only project root isn't written and later runs build incrementally.
+const CLIPPY_TARGET_DIR: &str = "/tmp/qq-lint-target";
+
+/// Stderr kept when a linter fails.
+const MAX_ERROR_CHARS: usize = 1500;
+
+const LINTERS_TOOL_DESC: &str = "\
+Run the project's configured linters and return their diagnostics, one per line as \
+line:col severity [linter code] message, grouped by file.
+
+Detects clippy (Cargo.toml), ESLint (a config file plus node_modules/.bin/eslint), and \
+ruff (ruff.toml, .ruff.toml, or [tool.ruff] in pyproject.toml). Linters run in the \
+read-only sandbox; clippy builds into a scratch target directory, so the first run on a \
+Rust project can take minutes.
+
+Pass paths (files or directories, relative to the working directory) to keep only their \
+diagnostics, e.g. the files changed in the diff under review. Errors are kept first \
+when max_diagnostics cuts the list.";
+
+/// A supported linter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Linter {
+    Clippy,
+    Eslint,
+    Ruff,
+}
+
+impl Linter {
+    pub const ALL: &'static [Linter] = &[Linter::Clippy, Linter::Eslint, Linter::Ruff];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Linter::Clippy => "clippy",
+            Linter::Eslint => "eslint",
+            Linter::Ruff => "ruff",
+        }
+    }
+
+    /// Parse a linter name: `clippy`, `eslint`, or `ruff`.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|l| l.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Extensions of the files this linter checks.
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Linter::Clippy => &["rs"],
+            Linter::Eslint => &["js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts"],
+            Linter::Ruff => &["py", "pyi"],
+        }
+    }
+
+    /// Whether the project at `root` is set up for this linter.
+    fn is_configured(&self, root: &Path) -> bool {
+        let contains = |file: &str, needle: &str| {
+            std::fs::read_to_string(root.join(file)).is_ok_and(|s| s.contains(needle))
+        };
+        match self {
+            Linter::Clippy => root.join("Cargo.toml").is_file(),
+            Linter::Eslint => {
+                let config = [
+                    "eslint.config.js",
+                    "eslint.config.mjs",
+                    "eslint.config.cjs",
+                    "eslint.config.ts",
+                    ".eslintrc",
+                    ".eslintrc.js",
+                    ".eslintrc.cjs",
+                    ".eslintrc.json",
+                    ".eslintrc.yml",
+                    ".eslintrc.yaml",
+                ]
+                .iter()
+                .any(|f| root.join(f).is_file())
+                    || contains("package.json", "\"eslintConfig\"");
+                config && root.join("node_modules/.bin/eslint").exists()
+            }
+            Linter::Ruff => {
+                root.join("ruff.toml").is_file()
+                    || root.join(".ruff.toml").is_file()
+                    || contains("pyproject.toml", "[tool.ruff")
+            }
+        }
+    }
+
+    /// Shell command printing JSON diagnostics to stdout. ESLint and ruff
+    /// are given `paths` (already filtered to their extensions) when set;
+    /// clippy always checks the workspace and is filtered afterwards.
+    fn command(&self, paths: &[String]) -> String {
+        let targets = if paths.is_empty() {
+            ".".to_string()
+        } else {
+            paths
+                .iter()
+                .map(|p| shell_quote(p))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match self {
+            Linter::Clippy => format!(
+                "cargo clippy --workspace --all-targets --message-format=json --quiet --target-dir {}",
+                CLIPPY_TARGET_DIR
+            ),
+            Linter::Eslint => format!("node_modules/.bin/eslint --format json {}", targets),
+            Linter::Ruff => format!(
+                "ruff check --output-format json --no-cache --exit-zero {}",
+                targets
+            ),
+        }
+    }
+
+    /// Parse the linter's JSON output. Paths are made relative to `root`.
+    fn parse_output(&self, stdout: &str, root: &Path) -> Result<Vec<Diagnostic>, String> {
+        match self {
+            Linter::Clippy => Ok(parse_clippy(stdout, root)),
+            Linter::Eslint => parse_eslint(stdout, root),
+            Linter::Ruff => parse_ruff(stdout, root),
+        }
+    }
+}
+
+/// Linters the project at `root` is configured for.
+pub fn detect_linters(root: &Path) -> Vec<Linter> {
+    Linter::ALL
+        .iter()
+        .copied()
+        .filter(|l| l.is_configured(root))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+/// One linter finding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub linter: Linter,
+    /// Path relative to the project root
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    /// Rule or lint name (`clippy::needless_return`, `F401`, `no-unused-vars`)
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// `path` relative to `root`; `None` outside it (dependency sources).
+pub(super) fn relative_to(root: &Path, path: &str) -> Option<String> {
+    let p = Path::new(path);
+    if p.is_relative() {
+        return Some(path.trim_start_matches("./").to_string());
+    }
+    p.strip_prefix(root)
+        .ok()
+        .map(|rel| rel.to_string_lossy().into_owned())
+}
+
+pub(super) fn first_line(message: &str) -> String {
+    message.lines().next().unwrap_or("").trim().to_string()
+}
+
+/// Cargo's `--message-format=json` stream: one JSON object per line, of
+/// which `compiler-message` entries carry rustc and clippy diagnostics.
+fn parse_clippy(stdout: &str, root: &Path) -> Vec<Diagnostic> {
+    #[derive(Deserialize)]
+    struct Line {
+        reason: String,
+        message: Option<Message>,
+    }
+    #[derive(Deserialize)]
+    struct Message {
+        message: String,
+        level: String,
+        code: Option<Code>,
+        #[serde(default)]
+        spans: Vec<Span>,
+    }
+    #[derive(Deserialize)]
+    struct Code {
+        code: String,
+    }
+    #[derive(Deserialize)]
+    struct Span {
+        file_name: String,
+        line_start: usize,
+        column_start: usize,
+        is_primary: bool,
+    }
+
+    let mut out = Vec::new();
+    for line in stdout.lines() {
+        let Ok(line) = serde_json::from_str::<Line>(line) else {
+            continue;
+        };
+        let Some(message) = line.message.filter(|_| line.reason == "compiler-message") else {
+            continue;
+        };
+        let severity = match message.level.as_str() {
+            "error" => Severity::Error,
+            "warning" => Severity::Warning,
+            "note" | "help" => Severity::Note,
+            _ => continue,
+        };
+        // Summaries ("aborting due to ...") have no span
+        let Some(span) = message.spans.iter().find(|s| s.is_primary) else {
+            continue;
+        };
+        let Some(file) = relative_to(root, &span.file_name) else {
+            continue;
+        };
+        out.push(Diagnostic {
+            linter: Linter::Clippy,
+            file,
+            line: span.line_start,
+            column: span.column_start,
+            severity,
+            code: message.code.map(|c| c.code),
+            message: first_line(&message.message),
+        });
+    }
+    out
+}
+
+/// ESLint's `--format json`: one entry per file with its messages.
+fn parse_eslint(stdout: &str, root: &Path) -> Result<Vec<Diagnostic>, String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FileResult {
+        file_path: String,
+        messages: Vec<Message>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Message {
+        rule_id: Option<String>,
+        severity: u8,
+        message: String,
+        #[serde(default)]
+        line: usize,
+        #[serde(default)]
+        column: usize,
+    }
+
+    let results: Vec<FileResult> = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("unexpected ESLint output: {}", e))?;
+    let mut out = Vec::new();
+    for result in results {
+        let Some(file) = relative_to(root, &result.file_path) else {
+            continue;
+        };
+        for m in result.messages {
+            out.push(Diagnostic {
+                linter: Linter::Eslint,
+                file: file.clone(),
+                line: m.line,
+                column: m.column,
+                severity: if m.severity >= 2 {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                },
+                code: m.rule_id,
+                message: first_line(&m.message),
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Ruff's `--output-format json`: one entry per violation. Ruff has no
+/// severities; syntax errors (no rule code) are reported as errors.
+fn parse_ruff(stdout: &str, root: &Path) -> Result<Vec<Diagnostic>, String> {
+    #[derive(Deserialize)]
+    struct Violation {
+        code: Option<String>,
+        message: String,
+        filename: String,
+        location: Location,
+    }
+    #[derive(Deserialize)]
+    struct Location {
+        row: usize,
+        column: usize,
+    }
+
+    let violations: Vec<Violation> = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("unexpected ruff output: {}", e))?;
+    Ok(violations
+        .into_iter()
+        .filter_map(|v| {
+            Some(Diagnostic {
+                linter: Linter::Ruff,
+                file: relative_to(root, &v.filename)?,
+                line: v.location.row,
+                column: v.location.column,
+                severity: if v.code.is_some() {
+                    Severity::Warning
+                } else {
+                    Severity::Error
+                },
+                code: v.code,
+                message: first_line(&v.message),
+            })
+        })
+        .collect())
+}
+
+/// Whether `file` is one of `paths` or under one of them.
+pub(super) fn in_paths(file: &str, paths: &[String]) -> bool {
+    paths.is_empty()
+        || paths.iter().any(|p| {
+            let p = p.trim_end_matches('/');
+            p == "." || file == p || file.starts_with(&format!("{}/", p))
+        })
+}
+
+/// Tool `paths`, given relative to the working directory, made relative to
+/// the project root. Under `/focus`, no paths means the focus directory.
+pub(super) fn scope_paths(mounts: &SandboxMounts, paths: &[String]) -> Result<Vec<String>, String> {
+    let focus = mounts.focus();
+    let mut scoped = Vec::new();
+    for p in paths {
+        let path = Path::new(p.trim());
+        if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(format!(
+                "paths must be relative to the working directory: {}",
+                p
+            ));
+        }
+        let p = p.trim().trim_start_matches("./");
+        scoped.push(match &focus {
+            Some(focus) if p.is_empty() || p == "." => focus.display().to_string(),
+            Some(focus) => focus.join(p).display().to_string(),
+            None => p.to_string(),
+        });
+    }
+    if scoped.is_empty() {
+        scoped.extend(focus.map(|f| f.display().to_string()));
+    }
+    Ok(scoped)
+}
+
+/// `command` run from the project root, where linters and checkers find
+/// their configuration and report paths from, even under `/focus`.
+pub(super) fn at_project_root(mounts: &SandboxMounts, command: String) -> String {
+    if mounts.focus().is_none() {
+        return command;
+    }
+    format!(
+        "cd {} && {}",
+        shell_quote(&mounts.project_root().display().to_string()),
+        command
+    )
+}
+
+/// Render the run: a status line per linter, then the diagnostics grouped
+/// by file. When more than `max` were found, errors are kept first.
+fn format_report(
+    statuses: &[(Linter, Result<usize, String>)],
+    mut diagnostics: Vec<Diagnostic>,
+    max: usize,
+) -> String {
+    let mut out = String::new();
+    for (linter, status) in statuses {
+        match status {
+            Ok(count) => out.push_str(&format!("{}: {} diagnostic(s)\n", linter.name(), count)),
+            Err(e) => out.push_str(&format!("{}: failed: {}\n", linter.name(), e)),
+        }
+    }
+
+    let total = diagnostics.len();
+    diagnostics.sort_by(|a, b| {
+        (a.severity, &a.file, a.line, a.column).cmp(&(b.severity, &b.file, b.line, b.column))
+    });
+    diagnostics.truncate(max);
+
+    let mut by_file: BTreeMap<&str, Vec<&Diagnostic>> = BTreeMap::new();
+    for d in &diagnostics {
+        by_file.entry(d.file.as_str()).or_default().push(d);
+    }
+    for (file, mut entries) in by_file {
+        entries.sort_by_key(|d| (d.line, d.column));
+        out.push_str(&format!("\n===== {} =====\n", file));
+        for d in entries {
+            let code = d
+                .code
+                .as_deref()
+                .map(|c| format!(" {}", c))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "{}:{} {} [{}{}] {}\n",
+                d.line,
+                d.column,
+                d.severity.as_str(),
+                d.linter.name(),
+                code,
+                d.message
+            ));
+        }
+    }
+    if total > diagnostics.len() {
+        out.push_str(&format!(
+            "\n[{} of {} diagnostics not shown; pass paths or raise max_diagnostics]\n",
+            total - diagnostics.len(),
+            total
+        ));
+    } else if total == 0 && statuses.iter().all(|(_, s)| s.is_ok()) {
+        out.push_str("\nNo diagnostics.\n");
+    }
+    out.trim_end().to_string()
+}
+
+pub(super) fn shell_quote(s: &str) -> String {
+    if s.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-+".contains(c))
+    {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+pub(super) fn tail(s: &str, max_chars: usize) -> String {
+    let s = s.trim();
+    let count = s.chars().count();
+    if count <= max_chars {
+        return s.to_string();
+    }
+    let skip = count - max_chars;
+    format!("...{}", s.chars().skip(skip).collect::<String>())
+}
+
+#[derive(Deserialize)]
+struct LintArgs {
+    #[serde(default)]
+    linters: Vec<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    max_diagnostics: Option<usize>,
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+/// Tool running the project's linters in the read-only sandbox.
+pub struct RunLintersTool {
+    mounts: Arc<SandboxMounts>,
+    path_policy: Arc<RwLock<SandboxPathPolicy>>,
+    executor: SandboxExecutor,
+}
+
+impl RunLintersTool {
+    pub fn new(
+        mounts: Arc<SandboxMounts>,
+        path_policy: Arc<RwLock<SandboxPathPolicy>>,
+        executor: SandboxExecutor,
+    ) -> Self {
+        Self {
+            mounts,
+            path_policy,
+            executor,
+        }
+    }
+
+    /// Run one linter; its diagnostics, or why it produced none.
+    async fn run_linter(
+        &self,
+        linter: Linter,
+        paths: &[String],
+        timeout: u64,
+        policy: &SandboxPathPolicy,
+    ) -> Result<Vec<Diagnostic>, String> {
+        let root = self.mounts.project_root();
+        let targets: Vec<String> = paths
+            .iter()
+            .filter(|p| {
+                let ext = Path::new(p).extension().and_then(|e| e.to_str());
+                // Directories and extensionless paths go to every linter
+                ext.is_none_or(|e| linter.extensions().contains(&e))
+            })
+            .cloned()
+            .collect();
+        if !paths.is_empty() && targets.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let command = at_project_root(
+            &self.mounts,
+            linter.command(if linter == Linter::Clippy {
+                &[]
+            } else {
+                &targets
+            }),
+        );
+        tracing::info!(linter = linter.name(), command = %command, "Running linter");
+        let result = self
+            .executor
+            .execute(&command, &self.mounts, timeout, policy, None, true)
+            .await?;
+        if let Some(e) = result.sandbox_error {
+            return Err(format!("sandbox error: {}", e));
+        }
+        if result.timed_out {
+            return Err(format!("timed out after {}s", timeout));
+        }
+        if result.exit_code == 127 {
+            return Err("not installed in the sandbox".to_string());
+        }
+        match linter.parse_output(&result.stdout, root) {
+            // Clippy prints nothing parseable when the build fails early
+            Ok(diagnostics)
+                if diagnostics.is_empty()
+                    && result.exit_code != 0
+                    && !result.stderr.trim().is_empty() =>
+            {
+                Err(tail(&result.stderr, MAX_ERROR_CHARS))
+            }
+            Ok(diagnostics) => {
+                let mut seen = HashSet::new();
+                Ok(diagnostics
+                    .into_iter()
+                    .filter(|d| in_paths(&d.file, paths) && seen.insert(d.clone()))
+                    .collect())
+            }
+            Err(e) if !result.stderr.trim().is_empty() => Err(format!(
+                "{} (exit code {}): {}",
+                e,
+                result.exit_code,
+                tail(&result.stderr, MAX_ERROR_CHARS)
+            )),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for RunLintersTool {
+    fn name(&self) -> &str {
+        "run_linters"
+    }
+
+    fn description(&self) -> &str {
+        "Run the project's linters (clippy, ESLint, ruff) and return file:line diagnostics"
+    }
+
+    fn tool_description(&self) -> &str {
+        LINTERS_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "linters",
+                    PropertySchema::array(
+                        "Linters to run (default: all configured)",
+                        PropertySchema::enum_string(
+                            "Linter",
+                            Linter::ALL.iter().map(|l| l.name().to_string()).collect(),
+                        ),
+                    ),
+                    false,
+                )
+                .add_property(
+                    "paths",
+                    PropertySchema::array(
+                        "Files or directories, relative to the working directory, to keep diagnostics for",
+                        PropertySchema::string("Path"),
+                    ),
+                    false,
+                )
+                .add_property(
+                    "max_diagnostics",
+                    PropertySchema::integer("Most diagnostics returned (default 200)"),
+                    false,
+                )
+                .add_property(
+                    "timeout",
+                    PropertySchema::integer("Timeout per linter in seconds (default 300, max 900)"),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: LintArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("run_linters", format!("Invalid arguments: {}", e)))?;
+
+        // Linters run project code (build scripts, JS configs)
+        if !self.executor.supports_shell() {
+            return Ok(ToolOutput::error(
+                "run_linters is not available in app-level sandbox mode. \
+                 Kernel sandbox (Linux with user namespaces) is required.",
+            ));
+        }
+
+        let paths = match scope_paths(&self.mounts, &args.paths) {
+            Ok(paths) => paths,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+
+        let root = self.mounts.project_root();
+        let detected = detect_linters(root);
+        let linters = if args.linters.is_empty() {
+            if detected.is_empty() {
+                return Ok(ToolOutput::error(
+                    "No configured linters found (looked for Cargo.toml, an ESLint config with \
+                     node_modules/.bin/eslint, and ruff.toml or [tool.ruff] in pyproject.toml).",
+                ));
+            }
+            detected
+        } else {
+            let mut linters = Vec::new();
+            for name in &args.linters {
+                let Some(linter) = Linter::parse(name) else {
+                    return Ok(ToolOutput::error(format!(
+                        "Unknown linter '{}' (expected clippy, eslint, or ruff)",
+                        name
+                    )));
+                };
+                if !detected.contains(&linter) {
+                    return Ok(ToolOutput::error(format!(
+                        "{} is not configured in this project",
+                        linter.name()
+                    )));
+                }
+                if !linters.contains(&linter) {
+                    linters.push(linter);
+                }
+            }
+            linters
+        };
+
+        let timeout = args
+            .timeout
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS);
+        let policy = match self.path_policy.read() {
+            Ok(p) => p.clone(),
+            Err(_) => return Ok(ToolOutput::error("Path policy lock poisoned.")),
+        };
+
+        let mut statuses = Vec::new();
+        let mut diagnostics = Vec::new();
+        for linter in linters {
+            match self.run_linter(linter, &paths, timeout, &policy).await {
+                Ok(found) => {
+                    statuses.push((linter, Ok(found.len())));
+                    diagnostics.extend(found);
+                }
+                Err(e) => statuses.push((linter, Err(e))),
+            }
+        }
+
+        let failed = statuses.iter().all(|(_, s)| s.is_err());
+        let report = format_report(
+            &statuses,
+            diagnostics,
+            args.max_diagnostics
+                .unwrap_or(DEFAULT_MAX_DIAGNOSTICS)
+                .max(1),
+        );
+        Ok(if failed {
+            ToolOutput::error(report)
+        } else {
+            ToolOutput::success(report)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_linters() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        assert!(detect_linters(root).is_empty());
+
+        std::fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(
+            root.join("pyproject.toml"),
+            "[tool.ruff]\nline-length = 100\n",
+        )
+        .unwrap();
+        // An ESLint config without the local binary doesn't count
+        std::fs::write(root.join("eslint.config.js"), "export default [];\n").unwrap();
+        assert_eq!(detect_linters(root), vec![Linter::Clippy, Linter::Ruff]);
+
+        std::fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+        std::fs::write(root.join("node_modules/.bin/eslint"), "").unwrap();
+        assert_eq!(detect_linters(root).len(), 3);
+        assert_eq!(Linter::parse(" ESLint"), Some(Linter::Eslint));
+        assert_eq!(Linter::parse("pylint"), None);
+    }
+
+    #[test]
+    fn test_parse_clippy_stream() {
+        let root = Path::new("/work/proj");
+        let stdout = [
+            r#"{"reason":"compiler-artifact","package_id":"x"}"#,
+            r#"{"reason":"compiler-message","message":{"message":"unneeded `return` statement","level":"warning","code":{"code":"clippy::needless_return"},"spans":[{"file_name":"src/lib.rs","line_start":12,"column_start":5,"is_primary":true}]}}"#,
+            r#"{"reason":"compiler-message","message":{"message":"mismatched types\nexpected u32","level":"error","code":{"code":"E0308"},"spans":[{"file_name":"src/a.rs","line_start":1,"column_start":1,"is_primary":false},{"file_name":"src/a.rs","line_start":3,"column_start":9,"is_primary":true}]}}"#,
+            r#"{"reason":"compiler-message","message":{"message":"in dependency","level":"warning","code":null,"spans":[{"file_name":"/home/u/.cargo/registry/dep/src/lib.rs","line_start":1,"column_start":1,"is_primary":true}]}}"#,
+            r#"{"reason":"compiler-message","message":{"message":"aborting due to 1 previous error","level":"error","code":null,"spans":[]}}"#,
+            r#"{"reason":"build-finished","success":false}"#,
+        ]
+        .join("\n");
+        let diagnostics = parse_clippy(&stdout, root);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "src/lib.rs");
+        assert_eq!(
+            diagnostics[0].code.as_deref(),
+            Some("clippy::needless_return")
+        );
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (3, 9));
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[1].message, "mismatched types");
+    }
+
+    #[test]
+    fn test_parse_eslint_and_ruff() {
+        let root = Path::new("/work/proj");
+        let eslint = r#"[{"filePath":"/work/proj/web/app.js","messages":[
+            {"ruleId":"no-unused-vars","severity":2,"message":"'x' is defined but never used.","line":4,"column":7},
+            {"ruleId":null,"severity":1,"message":"File ignored","line":0,"column":0}]},
+            {"filePath":"/work/proj/web/ok.js","messages":[]}]"#;
+        let diagnostics = parse_eslint(eslint, root).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "web/app.js");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[1].code, None);
+
+        let ruff = r#"[{"code":"F401","message":"`os` imported but unused","filename":"/work/proj/tool/main.py","location":{"row":1,"column":8},"fix":null},
+            {"code":null,"message":"SyntaxError: unexpected indent","filename":"/work/proj/tool/bad.py","location":{"row":2,"column":1}}]"#;
+        let diagnostics = parse_ruff(ruff, root).unwrap();
+        assert_eq!(diagnostics[0].file, "tool/main.py");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+
+        assert!(parse_ruff("error: unknown flag", root).is_err());
+    }
+
+    #[test]
+    fn test_scope_paths_under_focus() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(project.path().join("packages/api")).unwrap();
+        let mounts = SandboxMounts::new(project.path().to_path_buf()).unwrap();
+        assert_eq!(
+            scope_paths(&mounts, &["./src/".to_string()]).unwrap(),
+            vec!["src/"]
+        );
+        assert!(scope_paths(&mounts, &["../x".to_string()]).is_err());
+        assert!(scope_paths(&mounts, &[]).unwrap().is_empty());
+        assert_eq!(at_project_root(&mounts, "ruff check".into()), "ruff check");
+
+        mounts.set_focus(Some(Path::new("packages/api"))).unwrap();
+        assert_eq!(scope_paths(&mounts, &[]).unwrap(), vec!["packages/api"]);
+        assert_eq!(
+            scope_paths(&mounts, &["src/a.ts".to_string(), ".".to_string()]).unwrap(),
+            vec!["packages/api/src/a.ts", "packages/api"]
+        );
+        assert_eq!(
+            at_project_root(&mounts, "ruff check".into()),
+            format!(
+                "cd {} && ruff check",
+                shell_quote(&project.path().display().to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_report_groups_filters_and_caps() {
+        let diagnostic = |file: &str, line, severity| Diagnostic {
+            linter: Linter::Clippy,
+            file: file.to_string(),
+            line,
+            column: 1,
+            severity,
+            code: Some("clippy::x".to_string()),
+            message: "msg".to_string(),
+        };
+        let diagnostics = vec![
+            diagnostic("src/b.rs", 9, Severity::Warning),
+            diagnostic("src/a.rs", 20, Severity::Warning),
+            diagnostic("src/a.rs", 3, Severity::Error),
+        ];
+        let statuses = vec![
+            (Linter::Clippy, Ok(3)),
+            (
+                Linter::Ruff,
+                Err("not installed in the sandbox".to_string()),
+            ),
+        ];
+        let report = format_report(&statuses, diagnostics.clone(), 2);
+        assert!(report.starts_with("clippy: 3 diagnostic(s)\nruff: failed: not installed"));
+        assert!(report.contains(
+            "===== src/a.rs =====\n3:1 error [clippy clippy::x] msg\n20:1 warning [clippy clippy::x] msg"
+        ));
+        assert!(!report.contains("src/b.rs"));
+        assert!(
+            report.ends_with("[1 of 3 diagnostics not shown; pass paths or raise max_diagnostics]")
+        );
+
+        assert!(in_paths("src/a.rs", &["src/".to_string()]));
+        assert!(in_paths("src/a.rs", &["src/a.rs".to_string()]));
+        assert!(!in_paths("src/ab.rs", &["src/a".to_string()]));
+        assert_eq!(
+            Linter::Ruff.command(&["a b.py".to_string()]),
+            "ruff check --output-format json --no-cache --exit-zero 'a b.py'"
+        );
+    }
+}
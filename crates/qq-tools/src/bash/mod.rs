@@ -3,27 +3,67 @@
 //! Provides kernel-level process isolation via hakoniwa (Linux) with
 //! graceful fallback to app-level sandboxing on other platforms.
 
+pub mod access_log;
+pub mod check_build;
+pub mod coverage;
+pub mod diff_stats;
+pub mod explain;
+pub mod git_write;
+pub mod linters;
 pub mod mounts;
 pub mod network_access;
+#[cfg(feature = "outline")]
+pub mod outline;
+pub mod output_mode;
 pub mod parse;
 pub mod permissions;
+pub mod plugin_tool;
+pub mod read_files;
 pub mod sandbox;
 pub mod sensitive_access;
+pub mod write_policy;
+pub mod write_preview;
 
 use async_trait::async_trait;
 use serde::Deserialize;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
-use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+use qq_core::narrowing::scale;
+use qq_core::{
+    Error, PropertySchema, ScopeRetry, Tool, ToolDefinition, ToolOutput, ToolParameters,
+    TypedContent, CONTINUATION_MARKER,
+};
+use qq_core::text::{ceil_grapheme_boundary, floor_grapheme_boundary, truncate_bytes};
+
+use crate::untrusted::quarantine;
 
+pub use access_log::{AccessEntry, AccessKind, AccessLog, UntrackedReadPolicy};
+pub use check_build::{detect_checkers, BuildDiagnostic, CheckBuildTool, Checker};
+pub use coverage::{detect_coverage_runners, CoverageData, CoverageMapTool, CoverageRunner};
+pub use diff_stats::{ChangeKind, ChangeSnapshot, ChangeStats, FileChange};
+pub use explain::{explain_with_rules, CommandExplainer};
+pub use git_write::{GitBranchTool, GitCommitTool, GitSandbox, GitStageTool};
+pub use linters::{detect_linters, Diagnostic, Linter, RunLintersTool, Severity};
 pub use mounts::{MountExternalTool, MountPoint, SandboxMounts};
 pub use network_access::RequestNetworkAccessTool;
+#[cfg(feature = "outline")]
+pub use outline::OutlineFileTool;
+pub use output_mode::{JsonFilter, OutputMode};
 pub use permissions::{
     create_approval_channel, ApprovalChannel, ApprovalRequest, ApprovalResponse, PermissionStore,
-    Tier,
+    Tier, TierSource,
 };
-pub use sandbox::{SandboxExecutor, SandboxPathPolicy};
+pub use plugin_tool::{
+    plugin_tool_name, PluginGrants, PluginTool, PluginToolSpec, PLUGIN_API_VERSION,
+};
+pub use read_files::ReadFilesTool;
+pub use sandbox::{SandboxBackend, SandboxCapabilities, SandboxExecutor, SandboxPathPolicy};
 pub use sensitive_access::RequestSensitiveAccessTool;
+pub use write_policy::{
+    ModelWriteClassifier, WriteAction, WriteClassifier, WriteOperation, WritePolicy, WriteRule,
+    WriteVerdict,
+};
 
 /// Default command timeout in seconds.
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
@@ -39,6 +79,10 @@ const MAX_OUTPUT_BYTES: usize = 64 * 1024; // 64KB
 /// spill file itself, mirroring the inline head+tail strategy.
 const MAX_SPILL_BYTES: usize = 16 * 1024 * 1024; // 16 MB
 
+/// Maximum size of one `continue_from` segment. Kept under the chunker's
+/// default threshold so segments reach the model verbatim.
+const MAX_CONTINUATION_BYTES: usize = 32 * 1024; // 32KB
+
 /// Sandboxed run tool for executing shell commands.
 pub struct RunTool {
     mounts: Arc<SandboxMounts>,
@@ -49,10 +93,14 @@ pub struct RunTool {
     tool_desc: String,
     timeout_secs: u64,
     read_only: bool,
+    write_classifiers: Vec<Arc<dyn WriteClassifier>>,
+    access_log: Option<Arc<AccessLog>>,
+    explainer: Option<Arc<CommandExplainer>>,
 }
 
 #[derive(Deserialize)]
 struct RunArgs {
+    #[serde(default)]
     command: String,
     #[serde(default)]
     description: Option<String>,
@@ -60,6 +108,14 @@ struct RunArgs {
     timeout: Option<u64>,
     #[serde(default)]
     stdin: Option<String>,
+    #[serde(default)]
+    continue_from: Option<String>,
+    #[serde(default)]
+    output_mode: Option<OutputMode>,
+    #[serde(default)]
+    tail_lines: Option<usize>,
+    #[serde(default)]
+    filter: Option<String>,
 }
 
 impl RunTool {
@@ -79,7 +135,25 @@ impl RunTool {
         path_policy: Arc<RwLock<SandboxPathPolicy>>,
         has_network: bool,
     ) -> Self {
-        let executor = SandboxExecutor::detect();
+        Self::with_executor(
+            mounts,
+            permissions,
+            approval,
+            path_policy,
+            has_network,
+            SandboxExecutor::detect(),
+        )
+    }
+
+    /// Build with an already-detected executor (see [`SandboxExecutor::detect_with`]).
+    pub fn with_executor(
+        mounts: Arc<SandboxMounts>,
+        permissions: Arc<PermissionStore>,
+        approval: ApprovalChannel,
+        path_policy: Arc<RwLock<SandboxPathPolicy>>,
+        has_network: bool,
+        executor: SandboxExecutor,
+    ) -> Self {
         let tool_desc = build_tool_description(&mounts, &executor, has_network, false);
         Self {
             mounts,
@@ -90,6 +164,9 @@ impl RunTool {
             tool_desc,
             timeout_secs: DEFAULT_TIMEOUT_SECS,
             read_only: false,
+            write_classifiers: Vec::new(),
+            access_log: None,
+            explainer: None,
         }
     }
 
@@ -110,6 +187,38 @@ impl RunTool {
         }
         self
     }
+
+    /// Check commands that write against `classifier` before running them.
+    /// Classifiers run in the order added; the strictest verdict wins.
+    pub fn with_write_classifier(mut self, classifier: Arc<dyn WriteClassifier>) -> Self {
+        self.write_classifiers.push(classifier);
+        self
+    }
+
+    /// Report the files read commands read to `log`.
+    pub fn with_access_log(mut self, log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+
+    /// Show `explainer`'s plain-English line next to commands awaiting approval.
+    pub fn with_explainer(mut self, explainer: Arc<CommandExplainer>) -> Self {
+        self.explainer = Some(explainer);
+        self
+    }
+
+    /// Verdict of the write classifiers for `command`; `Allow` when nothing
+    /// in it writes.
+    async fn check_write_policy(&self, command: &str, commands: &[String]) -> WriteVerdict {
+        if self.write_classifiers.is_empty() {
+            return WriteVerdict::Allow;
+        }
+        let cwd = self.mounts.work_dir();
+        match write_policy::write_operation(command, commands, self.mounts.project_root(), &cwd) {
+            Some(op) => write_policy::classify_write(&self.write_classifiers, &op).await,
+            None => WriteVerdict::Allow,
+        }
+    }
 }
 
 #[async_trait]
@@ -132,9 +241,10 @@ impl Tool for RunTool {
                 .add_property(
                     "command",
                     PropertySchema::string(
-                        "The shell command to execute. Supports pipes, redirects, and globs in kernel sandbox mode.",
+                        "The shell command to execute. Supports pipes, redirects, and globs in kernel sandbox mode. \
+                         Required unless continue_from is set.",
                     ),
-                    true,
+                    false,
                 )
                 .add_property(
                     "description",
@@ -156,6 +266,47 @@ impl Tool for RunTool {
                         "Optional data to pipe to the command's standard input.",
                     ),
                     false,
+                )
+                .add_property(
+                    "continue_from",
+                    PropertySchema::string(
+                        "Continuation token from a truncated result (e.g. \"qq-spill-3:81\"). \
+                         Returns the next segment of that output without re-running the command; \
+                         command is ignored.",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "output_mode",
+                    PropertySchema::enum_string(
+                        "How much output to return: full (default), tail (last tail_lines lines), \
+                         exit_code_only (status only, stderr tail on failure), or jsonl (each \
+                         stdout line parsed as JSON and passed through filter). Use tail or \
+                         exit_code_only for noisy commands.",
+                        vec![
+                            "full".into(),
+                            "tail".into(),
+                            "exit_code_only".into(),
+                            "jsonl".into(),
+                        ],
+                    ),
+                    false,
+                )
+                .add_property(
+                    "tail_lines",
+                    PropertySchema::integer(
+                        "Lines kept by output_mode=tail (default: 20, max: 200).",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "filter",
+                    PropertySchema::string(
+                        "jq-like filter for output_mode=jsonl: paths (.a.b, .items[0]), \
+                         comma-separated paths, select(.path == \"value\") with == != < <= > >=, \
+                         joined by |. Example: select(.level == \"error\") | .msg",
+                    ),
+                    false,
                 ),
         )
     }
@@ -164,10 +315,74 @@ impl Tool for RunTool {
         false // Async: approval await + spawn_blocking for hakoniwa
     }
 
+    fn write_targets(&self, arguments: &serde_json::Value) -> Vec<String> {
+        if self.read_only || arguments.get("continue_from").is_some_and(|v| !v.is_null()) {
+            return Vec::new();
+        }
+        let command = arguments["command"].as_str().unwrap_or_default().trim();
+        let Ok(commands) = parse::extract_commands(command) else {
+            return Vec::new();
+        };
+        let root = self.mounts.project_root();
+        let cwd = self.mounts.work_dir();
+        let Some(op) = write_policy::write_operation(command, &commands, root, &cwd) else {
+            return Vec::new();
+        };
+        // Every argument of a write command counts as a target; drop the
+        // ones that can't be paths (`s/a/b/` of sed) so they don't collide
+        op.targets
+            .into_iter()
+            .filter(|target| {
+                let path = root.join(target);
+                path.exists() || path.parent().is_some_and(Path::is_dir)
+            })
+            .collect()
+    }
+
+    /// Fewer lines through `output_mode: tail`, and a hint to filter in the
+    /// command itself.
+    fn narrower_scope(&self, arguments: &serde_json::Value, fraction: f64) -> Option<ScopeRetry> {
+        let args: RunArgs = serde_json::from_value(arguments.clone()).ok()?;
+        if args.continue_from.is_some() {
+            return None;
+        }
+        let mut retry = ScopeRetry::new(arguments.clone());
+        match args.output_mode.unwrap_or_default() {
+            mode @ (OutputMode::Full | OutputMode::Tail) => {
+                let lines = match mode {
+                    OutputMode::Full => MAX_OUTPUT_LINES,
+                    _ => args.tail_lines.unwrap_or(output_mode::DEFAULT_TAIL_LINES),
+                }
+                .min(MAX_OUTPUT_LINES);
+                let narrowed = scale(lines as u64, fraction) as usize;
+                if mode == OutputMode::Full || narrowed < lines {
+                    retry.arguments["output_mode"] = serde_json::json!("tail");
+                    retry.arguments["tail_lines"] = serde_json::json!(narrowed);
+                    retry
+                        .changes
+                        .push(format!("output_mode {} -> tail, last {} lines", mode.name(), narrowed));
+                }
+                retry.hints.push(
+                    "pipe the command through `grep` or `head` to keep only the lines you need"
+                        .to_string(),
+                );
+            }
+            OutputMode::Jsonl => retry
+                .hints
+                .push("narrow `filter` to select fewer lines or fields".to_string()),
+            OutputMode::ExitCodeOnly => return None,
+        }
+        retry.into_option()
+    }
+
     async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
         let args: RunArgs = serde_json::from_value(arguments)
             .map_err(|e| Error::tool("run", format!("Invalid arguments: {}", e)))?;
 
+        if let Some(ref token) = args.continue_from {
+            return Ok(read_continuation(&self.mounts, token));
+        }
+
         let command = args.command.trim();
         if command.is_empty() {
             return Ok(ToolOutput::error("Command cannot be empty."));
@@ -178,6 +393,24 @@ impl Tool for RunTool {
             .map(|t| t.min(300))
             .unwrap_or(self.timeout_secs);
 
+        let output_mode = args.output_mode.unwrap_or_default();
+        let tail_lines = args
+            .tail_lines
+            .map(|n| n.clamp(1, MAX_OUTPUT_LINES))
+            .unwrap_or(output_mode::DEFAULT_TAIL_LINES);
+        let filter = match (output_mode, args.filter.as_deref()) {
+            (OutputMode::Jsonl, Some(filter)) => match JsonFilter::parse(filter) {
+                Ok(filter) => Some(filter),
+                Err(e) => return Ok(ToolOutput::error(format!("Invalid filter: {}", e))),
+            },
+            (_, Some(f)) if !f.trim().is_empty() => {
+                return Ok(ToolOutput::error(
+                    "filter applies only to output_mode=jsonl.",
+                ));
+            }
+            _ => None,
+        };
+
         // 1. Extract commands from pipeline
         let commands = match parse::extract_commands(command) {
             Ok(cmds) => cmds,
@@ -200,7 +433,7 @@ impl Tool for RunTool {
         }
 
         // 2. Check permissions
-        match self.permissions.check_pipeline(&commands) {
+        let trigger_cmds = match self.permissions.check_pipeline(&commands) {
             permissions::PipelinePermission::Restricted(cmds) => {
                 return Ok(ToolOutput::error(format!(
                     "Restricted commands cannot be executed: {}. \
@@ -226,37 +459,89 @@ impl Tool for RunTool {
                         trigger_cmds.join(", ")
                     )));
                 }
+                trigger_cmds
+            }
+            permissions::PipelinePermission::Allowed => Vec::new(),
+        };
 
-                // 3. Request user approval via channel
-                match self
-                    .approval
-                    .request_approval(command.to_string(), trigger_cmds.clone(), "Command")
-                    .await
-                {
-                    Ok(permissions::ApprovalResponse::Allow) => { /* proceed */ }
-                    Ok(permissions::ApprovalResponse::AllowForSession) => {
-                        for cmd in &trigger_cmds {
-                            self.permissions.promote_to_session(cmd);
-                        }
-                    }
-                    Ok(permissions::ApprovalResponse::Deny(reason)) => {
-                        let msg = match reason {
-                            Some(r) => format!("Command denied by user: {r}"),
-                            None => "Command denied by user.".to_string(),
-                        };
-                        return Ok(ToolOutput::error(msg));
-                    }
-                    Err(e) => {
-                        return Ok(ToolOutput::error(format!(
-                            "Approval system unavailable: {}",
-                            e
-                        )));
+        // 3. Check write policies (path rules the tiers can't express)
+        let policy_reason = match self.check_write_policy(command, &commands).await {
+            WriteVerdict::Allow => None,
+            WriteVerdict::Ask(reason) => Some(reason),
+            WriteVerdict::Block(reason) => {
+                tracing::info!(command = %command, reason = %reason, "Write blocked by policy");
+                return Ok(ToolOutput::error(format!(
+                    "Blocked by write policy: {}. Do not retry this or an equivalent command; \
+                     ask the user if the change is needed.",
+                    reason
+                )));
+            }
+        };
+
+        // 4. Request user approval via channel
+        if !trigger_cmds.is_empty() || policy_reason.is_some() {
+            let (triggers, category) = match policy_reason {
+                Some(reason) => {
+                    let mut triggers = trigger_cmds.clone();
+                    triggers.push(format!("write policy: {}", reason));
+                    (triggers, "Write policy")
+                }
+                None => (trigger_cmds.clone(), "Command"),
+            };
+            let explanation = match self.explainer {
+                Some(ref explainer) => explainer.explain(command).await,
+                None => None,
+            };
+            let preview = write_preview::write_preview(
+                command,
+                self.mounts.project_root(),
+                &self.mounts.work_dir(),
+            );
+            match self
+                .approval
+                .request_previewed_approval(
+                    command.to_string(),
+                    triggers,
+                    category,
+                    explanation,
+                    preview,
+                )
+                .await
+            {
+                Ok(permissions::ApprovalResponse::Allow) => { /* proceed */ }
+                Ok(permissions::ApprovalResponse::AllowForSession) => {
+                    // Promotes the commands only; policy checks still apply
+                    for cmd in &trigger_cmds {
+                        self.permissions.promote_to_session(cmd);
                     }
                 }
+                Ok(permissions::ApprovalResponse::Deny(reason)) => {
+                    let msg = match reason {
+                        Some(r) => format!("Command denied by user: {r}"),
+                        None => "Command denied by user.".to_string(),
+                    };
+                    return Ok(ToolOutput::error(msg));
+                }
+                Err(e) => {
+                    return Ok(ToolOutput::error(format!(
+                        "Approval system unavailable: {}",
+                        e
+                    )));
+                }
+            }
+        }
+
+        // Reads of files git doesn't track are logged, and may need approval
+        if let Some(ref log) = self.access_log {
+            let reads = access_log::read_paths(command, &self.mounts.work_dir());
+            if let Err(e) = log.check("run", &reads).await {
+                return Ok(ToolOutput::error(e));
             }
-            permissions::PipelinePermission::Allowed => { /* proceed */ }
         }
 
+        // Output of files read from outside the project is third-party content
+        let untrusted = untrusted_source(&self.mounts, command);
+
         // Log the execution
         if let Some(desc) = &args.description {
             tracing::info!(command = %command, description = %desc, "Executing command");
@@ -264,7 +549,18 @@ impl Tool for RunTool {
             tracing::info!(command = %command, "Executing command");
         }
 
-        // 4. Execute in sandbox
+        // 5. Snapshot the files a write command targets, for the change footer
+        let snapshot = if self.read_only {
+            None
+        } else {
+            let root = self.mounts.project_root();
+            let cwd = self.mounts.work_dir();
+            write_policy::write_operation(command, &commands, root, &cwd)
+                .map(|op| ChangeSnapshot::capture(root, &op.targets))
+                .filter(|snapshot| !snapshot.is_empty())
+        };
+
+        // 6. Execute in sandbox
         let path_policy = match self.path_policy.read() {
             Ok(p) => p.clone(),
             Err(_) => return Ok(ToolOutput::error("Path policy lock poisoned.")),
@@ -285,8 +581,45 @@ impl Tool for RunTool {
             Err(e) => return Ok(ToolOutput::error(format!("Execution failed: {}", e))),
         };
 
-        // 5. Format output
-        Ok(format_output(result, &self.mounts))
+        // 7. Format output, with what the command changed
+        let mut output = output_mode::format_in_mode(
+            result,
+            output_mode,
+            tail_lines,
+            filter.as_ref(),
+            &self.mounts,
+        );
+        if let Some(ref source) = untrusted {
+            for part in &mut output.content {
+                if let TypedContent::Text { text } = part {
+                    *text = quarantine(source, text);
+                }
+            }
+        }
+        if let Some(changes) = snapshot.map(|s| s.changes()).filter(|c| !c.is_empty()) {
+            output
+                .content
+                .push(TypedContent::text(format!("\n\n{}", changes.render())));
+        }
+        Ok(output)
+    }
+}
+
+/// Quarantine source for the output of `command` when it reads files from
+/// outside the project root and session `/tmp` (e.g. an extra mount), or
+/// `None` when everything it reads is project content.
+fn untrusted_source(mounts: &SandboxMounts, command: &str) -> Option<String> {
+    let outside: Vec<PathBuf> = access_log::read_paths(command, &mounts.work_dir())
+        .into_iter()
+        .filter(|path| {
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            !read_files::in_project(mounts, &path)
+        })
+        .collect();
+    match outside.as_slice() {
+        [] => None,
+        [path] => Some(path.display().to_string()),
+        [path, rest @ ..] => Some(format!("{} (+{} more)", path.display(), rest.len())),
     }
 }
 
@@ -354,6 +687,8 @@ fn is_binary_output(data: &str) -> bool {
 struct TruncationResult {
     output: String,
     truncated: bool,
+    /// Leading lines of the original shown verbatim before the omission.
+    head_lines: usize,
     original_lines: usize,
     original_bytes: usize,
 }
@@ -369,6 +704,7 @@ fn truncate_output(output: &str, max_lines: usize, max_bytes: usize) -> Truncati
         return TruncationResult {
             output: output.to_string(),
             truncated: false,
+            head_lines: original_lines,
             original_lines,
             original_bytes,
         };
@@ -391,16 +727,22 @@ fn truncate_output(output: &str, max_lines: usize, max_bytes: usize) -> Truncati
         result.push('\n');
     }
 
+    let mut head_lines = head_count.min(original_lines);
+
     // Secondary byte cap
     if result.len() > max_bytes {
-        let cut_point = result[..max_bytes].rfind('\n').unwrap_or(max_bytes);
+        let limit = floor_grapheme_boundary(&result, max_bytes);
+        let cut_point = result[..limit].rfind('\n').unwrap_or(limit);
         result.truncate(cut_point);
         result.push_str("\n[... truncated at byte limit ...]");
+        // Count complete head lines that survived the cut
+        head_lines = head_lines.min(result[..cut_point].matches('\n').count());
     }
 
     TruncationResult {
         output: result,
         truncated: true,
+        head_lines,
         original_lines,
         original_bytes,
     }
@@ -420,6 +762,8 @@ fn truncate_output(output: &str, max_lines: usize, max_bytes: usize) -> Truncati
 struct SpillInfo {
     /// Path as the sandbox sees it, e.g. `/tmp/qq-spill-3.txt`.
     sandbox_path: String,
+    /// File stem used in continuation tokens, e.g. `qq-spill-3`.
+    name: String,
     /// True if the spill file itself was head/tail-capped at `MAX_SPILL_BYTES`.
     hard_capped: bool,
 }
@@ -436,10 +780,10 @@ fn write_spill_file(mounts: &SandboxMounts, stdout: &str) -> Option<SpillInfo> {
     let write_result = if stdout.len() <= MAX_SPILL_BYTES {
         std::fs::write(&host_path, stdout.as_bytes())
     } else {
-        // Hard-cap: head 8 MB + marker + tail 8 MB, on char boundaries.
+        // Hard-cap: head 8 MB + marker + tail 8 MB, on grapheme boundaries.
         let half = MAX_SPILL_BYTES / 2;
-        let head_end = floor_char_boundary(stdout, half);
-        let tail_start = ceil_char_boundary(stdout, stdout.len() - half);
+        let head_end = floor_grapheme_boundary(stdout, half);
+        let tail_start = ceil_grapheme_boundary(stdout, stdout.len() - half);
         let mut capped = String::with_capacity(MAX_SPILL_BYTES + 128);
         capped.push_str(&stdout[..head_end]);
         capped.push_str("\n\n[... spill file hard-capped, middle omitted ...]\n\n");
@@ -450,6 +794,7 @@ fn write_spill_file(mounts: &SandboxMounts, stdout: &str) -> Option<SpillInfo> {
     match write_result {
         Ok(()) => Some(SpillInfo {
             sandbox_path: format!("/tmp/{}", file_name),
+            name: file_name.trim_end_matches(".txt").to_string(),
             hard_capped: stdout.len() > MAX_SPILL_BYTES,
         }),
         Err(e) => {
@@ -463,25 +808,87 @@ fn write_spill_file(mounts: &SandboxMounts, stdout: &str) -> Option<SpillInfo> {
     }
 }
 
-/// Largest valid char-boundary index `<= index`.
-///
-/// `str::floor_char_boundary` is nightly-only, so we hand-roll it to keep the
-/// workspace on stable Rust.
-fn floor_char_boundary(s: &str, index: usize) -> usize {
-    let mut i = index.min(s.len());
-    while i > 0 && !s.is_char_boundary(i) {
-        i -= 1;
-    }
-    i
+// =============================================================================
+// Continuation
+// =============================================================================
+
+/// Parse a `qq-spill-<N>:<line>` token. Only spill names are accepted, so a
+/// token can never point outside the session's spill files.
+fn parse_continuation_token(token: &str) -> Option<(&str, usize)> {
+    let (name, line) = token.trim().split_once(':')?;
+    let id = name.strip_prefix("qq-spill-")?;
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let line: usize = line.parse().ok().filter(|&l| l >= 1)?;
+    Some((name, line))
 }
 
-/// Smallest valid char-boundary index `>= index`.
-fn ceil_char_boundary(s: &str, index: usize) -> usize {
-    let mut i = index.min(s.len());
-    while i < s.len() && !s.is_char_boundary(i) {
-        i += 1;
+/// Return the next segment of a spilled output, starting at the token's line.
+///
+/// Segments are at most `MAX_OUTPUT_LINES` lines / `MAX_CONTINUATION_BYTES`
+/// and end with a new token when more remains, so the model pages through the
+/// output deterministically.
+fn read_continuation(mounts: &SandboxMounts, token: &str) -> ToolOutput {
+    let Some((name, start)) = parse_continuation_token(token) else {
+        return ToolOutput::error(format!(
+            "Invalid continue_from token '{}'. Use the exact token from a truncated result, \
+             e.g. \"qq-spill-3:81\".",
+            token
+        ));
+    };
+
+    let host_path = mounts.tmp_dir().join(format!("{}.txt", name));
+    let content = match std::fs::read_to_string(&host_path) {
+        Ok(c) => c,
+        Err(_) => {
+            return ToolOutput::error(format!(
+                "No saved output for '{}'. Spill files only exist for the current session.",
+                name
+            ))
+        }
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+    let total = lines.len();
+    if start > total {
+        return ToolOutput::success(format!(
+            "(no more output)\n\n---\nEnd of /tmp/{}.txt ({} lines)",
+            name, total
+        ));
     }
-    i
+
+    let mut segment = String::new();
+    let mut end = start - 1;
+    for line in &lines[start - 1..] {
+        if end - (start - 1) >= MAX_OUTPUT_LINES
+            || segment.len() + line.len() + 1 > MAX_CONTINUATION_BYTES
+        {
+            break;
+        }
+        segment.push_str(line);
+        segment.push('\n');
+        end += 1;
+    }
+
+    // A single line over the byte budget is cut so paging always advances
+    if end < start {
+        let line = lines[start - 1];
+        segment.push_str(truncate_bytes(line, MAX_CONTINUATION_BYTES));
+        segment.push_str("\n[... line truncated ...]\n");
+        end = start;
+    }
+
+    segment.push_str(&format!(
+        "\n---\nLines {}-{} of {} from /tmp/{}.txt",
+        start, end, total, name
+    ));
+    if end < total {
+        segment.push_str(&format!("\n{}\"{}:{}\"", CONTINUATION_MARKER, name, end + 1));
+    } else {
+        segment.push_str(" (end of output)");
+    }
+    ToolOutput::success(segment)
 }
 
 // =============================================================================
@@ -621,6 +1028,16 @@ pub(crate) fn format_output(
                     format_bytes(MAX_SPILL_BYTES),
                 ));
             }
+
+            // Spill line numbers count the blank lines trimmed from stdout
+            let leading = result.stdout.len() - result.stdout.trim_start().len();
+            let leading_lines = result.stdout[..leading].matches('\n').count();
+            output.push_str(&format!(
+                "\n{}\"{}:{}\"",
+                CONTINUATION_MARKER,
+                info.name,
+                leading_lines + tr.head_lines + 1,
+            ));
         }
     } else if !stderr.is_empty() {
         // No stdout, but has stderr
@@ -696,14 +1113,16 @@ fn build_tool_description(
     read_only: bool,
 ) -> String {
     let mode = executor.mode_name();
-    let supports_shell = executor.supports_shell();
+    let capabilities = executor.capabilities();
+    let supports_shell = capabilities.shell;
+    let guarantees = capabilities.summary();
     let root = mounts.project_root().display();
 
     if read_only {
         let mut desc = format!(
             "Execute read-only shell commands in a sandboxed environment. \
              This tool is restricted to read-only operations — all write commands are blocked.\n\n\
-             Sandbox mode: {mode}\n\
+             Sandbox mode: {mode} ({guarantees})\n\
              Project root: {root} (read-only)\n\
              /tmp: writable scratch space (persists across commands, session-scoped)\n"
         );
@@ -745,10 +1164,14 @@ fn build_tool_description(
             untruncated output is automatically saved to /tmp/qq-spill-<N>.txt. The \
             inline response shows head+tail plus the spill file path. Inspect the \
             omitted middle with `sed -n 'X,Yp'`, `grep`, or `head`/`tail` on the spill \
-            file — do NOT re-run the original command, and do NOT delegate to a \
-            sub-agent to work around the truncation.\n\n\
-            Commands execute with a 30-second default timeout.\n\n\
-            Examples:\n\
+            file, or page through it in order by calling run with the \
+            `continue_from` token from the result — do NOT re-run the original \
+            command, and do NOT delegate to a sub-agent to work around the truncation.\n\n\
+            Commands execute with a 30-second default timeout.\n\n",
+        );
+        desc.push_str(output_mode::OUTPUT_MODES_HELP);
+        desc.push_str(
+            "\nExamples:\n\
             - List files: ls -la src/\n\
             - Read file: cat src/main.rs\n\
             - Search code: grep -rn 'TODO' src/ | sort\n\
@@ -763,7 +1186,7 @@ fn build_tool_description(
     let mut desc = format!(
         "Execute shell commands in a sandboxed environment. This is your primary tool for \
          ALL file operations, code searching, building, and testing.\n\n\
-         Sandbox mode: {mode}\n\
+         Sandbox mode: {mode} ({guarantees})\n\
          Project root: {root} (read-write)\n\
          /tmp: writable scratch space (persists across commands, session-scoped)\n"
     );
@@ -794,6 +1217,11 @@ fn build_tool_description(
             - Simple commands only (e.g., 'ls -la', 'grep pattern file')\n",
         );
     }
+    if supports_shell && !capabilities.filesystem_isolation {
+        desc.push_str(
+            "- Host filesystem outside the project root and /tmp is visible but read-only\n",
+        );
+    }
 
     let network_text = if has_network {
         "Network access is available — commands like curl, wget, git clone, npm install, etc. can use the network freely."
@@ -807,7 +1235,10 @@ If you have trouble connecting, check that you have requested network access fir
         - Write: cat > file.txt << 'EOF'\\n...\\nEOF, or tee file.txt\n\
         - Edit: sed -i 's/old/new/g' file.txt\n\
         - Search: grep -rn 'pattern' src/, find . -name '*.rs'\n\
-        - File ops: cp, mv, mkdir -p, rm\n\n\
+        - File ops: cp, mv, mkdir -p, rm\n\
+        Commands that change the files they name end with a `[changes]` summary: files, \
+        hunks, lines added/removed, and the functions or types touched. Use it to confirm \
+        an edit landed and when reporting what you changed.\n\n\
         Scratch space (/tmp):\n\
         Use /tmp for scripts, intermediate results, and working notes — it persists across commands.\n\
         Prefer writing scripts to /tmp over inlining them: echo 'script' > /tmp/check.sh && sh /tmp/check.sh\n\n\
@@ -815,9 +1246,11 @@ If you have trouble connecting, check that you have requested network access fir
         When a command produces more than 200 lines or 64 KB of stdout, the full \
         untruncated output is automatically saved to /tmp/qq-spill-<N>.txt. The inline \
         response shows head+tail plus the spill file path. Inspect the omitted middle \
-        with `sed -n 'X,Yp'`, `grep`, or `head`/`tail` on the spill file — do NOT \
-        re-run the original command, and do NOT delegate to a sub-agent to work around \
-        the truncation.\n\n\
+        with `sed -n 'X,Yp'`, `grep`, or `head`/`tail` on the spill file, or page \
+        through it in order by calling run with the `continue_from` token from the \
+        result — do NOT re-run the original command, and do NOT delegate to a \
+        sub-agent to work around the truncation.\n\n\
+        {}\n\
         Permission tiers:\n\
         - Session (run immediately): ls, cat, grep, find, git log, git diff, cargo build, cargo test, npm test, etc.\n\
         - Per-call (requires user approval): cargo run, npm install, git commit, rm, mv, python, etc.\n\
@@ -834,7 +1267,8 @@ Commands execute with a 30-second default timeout.\n\n\
         - Write file: cat > output.txt << 'EOF'\\ncontent here\\nEOF\n\
         - Edit file: sed -i 's/old_func/new_func/g' src/lib.rs\n\
         - Run binary: cargo run --bin myapp (requires approval)\n\
-        - Install deps: npm install (requires approval)", network_text));
+        - Install deps: npm install (requires approval)",
+        output_mode::OUTPUT_MODES_HELP, network_text));
 
     desc
 }
@@ -842,45 +1276,114 @@ Commands execute with a 30-second default timeout.\n\n\
 /// Create run tools for registration in a tool registry.
 ///
 /// Returns `(tools, read_only_run)`:
-/// - `tools`: `run`, `mount_external`, and `request_sensitive_access` as a bundle,
-///   plus `request_network_access` when `ask_network` is true.
+/// - `tools`: `run`, `read_files`, `run_linters`, `check_build`, `map_test_coverage`,
+///   `mount_external`, and `request_sensitive_access` as a bundle, plus `request_network_access`
+///   when `ask_network` is true.
 /// - `read_only_run`: a read-only variant of the `run` tool that blocks write commands
 ///   and mounts the project root read-only in kernel sandbox mode.
 ///
 /// The `path_policy` is wrapped in an `Arc<RwLock<>>` shared between `RunTool` and
 /// `RequestSensitiveAccessTool` so that approved sensitive-directory access
-/// takes effect immediately.
+/// takes effect immediately. Both run variants, `run_linters`, `check_build`, and
+/// `map_test_coverage` share `executor`, so the backend chain is probed once, as do the
+/// git write tools (`git_stage`, `git_commit`, `git_branch`), which ask under `permissions`. `write_classifiers` apply to the writable
+/// variant and to the files a `git_branch` switch changes; the read-only one never writes. Both run variants, `read_files`, and `outline_file`
+/// report reads of files git doesn't track to `access_log`, and both explain commands awaiting
+/// approval with `explainer` when given. `session_base`, the revision the worktree was at when
+/// the session started, is what `map_test_coverage` diffs against by default.
+#[allow(clippy::too_many_arguments)]
 pub fn create_run_tools(
     mounts: Arc<SandboxMounts>,
     permissions: Arc<PermissionStore>,
     approval: ApprovalChannel,
     path_policy: SandboxPathPolicy,
     ask_network: bool,
+    executor: SandboxExecutor,
+    write_classifiers: Vec<Arc<dyn WriteClassifier>>,
+    access_log: Arc<AccessLog>,
+    explainer: Option<Arc<CommandExplainer>>,
+    session_base: Option<String>,
 ) -> (Vec<Arc<dyn Tool>>, Arc<dyn Tool>) {
     let path_policy = Arc::new(RwLock::new(path_policy));
-    let run = Arc::new(RunTool::with_network_mode(
-        Arc::clone(&mounts),
-        Arc::clone(&permissions),
-        approval.clone(),
-        Arc::clone(&path_policy),
-        !ask_network,
-    ));
-    let read_only_run: Arc<dyn Tool> = Arc::new(
-        RunTool::with_network_mode(
+    // Write policies (protected paths included) hold for read-only agents too
+    let read_only_run = write_classifiers.iter().cloned().fold(
+        RunTool::with_executor(
             Arc::clone(&mounts),
             Arc::clone(&permissions),
             approval.clone(),
             Arc::clone(&path_policy),
             !ask_network,
+            executor.clone(),
         )
         .with_read_only(true),
+        RunTool::with_write_classifier,
+    );
+    let run = write_classifiers.iter().cloned().fold(
+        RunTool::with_executor(
+            Arc::clone(&mounts),
+            Arc::clone(&permissions),
+            approval.clone(),
+            Arc::clone(&path_policy),
+            !ask_network,
+            executor.clone(),
+        ),
+        RunTool::with_write_classifier,
+    );
+    let mut run = run.with_access_log(Arc::clone(&access_log));
+    let mut read_only_run = read_only_run.with_access_log(Arc::clone(&access_log));
+    if let Some(explainer) = explainer {
+        run = run.with_explainer(Arc::clone(&explainer));
+        read_only_run = read_only_run.with_explainer(explainer);
+    }
+    let run = Arc::new(run);
+    let read_only_run: Arc<dyn Tool> = Arc::new(read_only_run);
+    let read_files = Arc::new(
+        ReadFilesTool::new(Arc::clone(&mounts), Arc::clone(&path_policy))
+            .with_access_log(Arc::clone(&access_log)),
     );
+    #[cfg(feature = "outline")]
+    let outline: Arc<dyn Tool> = Arc::new(
+        OutlineFileTool::new(Arc::clone(&mounts), Arc::clone(&path_policy))
+            .with_access_log(Arc::clone(&access_log)),
+    );
+    let linters = Arc::new(RunLintersTool::new(
+        Arc::clone(&mounts),
+        Arc::clone(&path_policy),
+        executor.clone(),
+    ));
+    let check_build = Arc::new(CheckBuildTool::new(
+        Arc::clone(&mounts),
+        Arc::clone(&path_policy),
+        executor.clone(),
+    ));
+    let git_tools = write_classifiers
+        .into_iter()
+        .fold(
+            GitSandbox::new(
+                Arc::clone(&mounts),
+                Arc::clone(&permissions),
+                approval.clone(),
+                Arc::clone(&path_policy),
+                executor.clone(),
+            ),
+            GitSandbox::with_write_classifier,
+        )
+        .into_tools();
+    let coverage = CoverageMapTool::new(Arc::clone(&mounts), Arc::clone(&path_policy), executor);
+    let coverage = Arc::new(match session_base {
+        Some(base) => coverage.with_base(base),
+        None => coverage,
+    });
     let mount_ext = Arc::new(MountExternalTool::new(mounts, approval.clone()));
     let sensitive = Arc::new(RequestSensitiveAccessTool::new(
         path_policy,
         approval.clone(),
     ));
-    let mut tools: Vec<Arc<dyn Tool>> = vec![run, mount_ext, sensitive];
+    let mut tools: Vec<Arc<dyn Tool>> =
+        vec![run, read_files, linters, check_build, coverage, mount_ext, sensitive];
+    tools.extend(git_tools);
+    #[cfg(feature = "outline")]
+    tools.push(outline);
     if ask_network {
         tools.push(Arc::new(RequestNetworkAccessTool::new(approval)));
     }
@@ -1212,6 +1715,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_continuation_pages_through_spill() {
+        let input = big_stdout(500);
+        let (mounts, _root) = test_mounts();
+        let text = format_output(make_result(&input, "", 0), &mounts).text_content();
+
+        // Head shows lines 1-80, so paging resumes at 81
+        assert!(
+            text.contains("Continue with continue_from=\"qq-spill-1:81\""),
+            "missing continuation token: {}",
+            text
+        );
+
+        let page = read_continuation(&mounts, "qq-spill-1:81").text_content();
+        assert!(page.starts_with("line 81\n"));
+        assert!(page.contains("line 280\n"));
+        assert!(!page.contains("line 281\n"));
+        assert!(page.contains("Lines 81-280 of 500"));
+        assert!(page.contains("continue_from=\"qq-spill-1:281\""));
+
+        let last = read_continuation(&mounts, "qq-spill-1:481").text_content();
+        assert!(last.contains("Lines 481-500 of 500"));
+        assert!(last.contains("(end of output)"));
+        assert!(!last.contains("continue_from="));
+
+        assert!(read_continuation(&mounts, "qq-spill-1:501")
+            .text_content()
+            .contains("(no more output)"));
+    }
+
+    #[test]
+    fn test_continuation_token_validation() {
+        assert_eq!(parse_continuation_token("qq-spill-12:5"), Some(("qq-spill-12", 5)));
+        assert!(parse_continuation_token("qq-spill-1:0").is_none());
+        assert!(parse_continuation_token("../etc/passwd:1").is_none());
+        assert!(parse_continuation_token("qq-spill-../x:1").is_none());
+
+        let (mounts, _root) = test_mounts();
+        assert!(read_continuation(&mounts, "bogus").is_error);
+        assert!(read_continuation(&mounts, "qq-spill-9:1").is_error);
+    }
+
+    #[test]
+    fn test_continuation_splits_oversized_line() {
+        let (mounts, _root) = test_mounts();
+        let long = "x".repeat(MAX_CONTINUATION_BYTES * 2);
+        std::fs::write(mounts.tmp_dir().join("qq-spill-1.txt"), format!("{}\nnext", long)).unwrap();
+
+        let page = read_continuation(&mounts, "qq-spill-1:1").text_content();
+        assert!(page.contains("[... line truncated ...]"));
+        assert!(page.contains("continue_from=\"qq-spill-1:2\""));
+    }
+
     #[test]
     fn test_char_boundary_helpers_on_multibyte() {
         // 4-byte char at the boundary.
@@ -1219,12 +1775,24 @@ mod tests {
         let crab_start = 3; // byte index of 🦀
         let crab_end = 7;
 
-        // floor_char_boundary rounds down into the middle of the 4-byte seq.
-        assert_eq!(floor_char_boundary(s, crab_start + 2), crab_start);
-        assert_eq!(floor_char_boundary(s, crab_end), crab_end);
-        // ceil_char_boundary rounds up.
-        assert_eq!(ceil_char_boundary(s, crab_start + 2), crab_end);
-        assert_eq!(ceil_char_boundary(s, crab_start), crab_start);
+        // floor_grapheme_boundary rounds down into the middle of the 4-byte seq.
+        assert_eq!(floor_grapheme_boundary(s, crab_start + 2), crab_start);
+        assert_eq!(floor_grapheme_boundary(s, crab_end), crab_end);
+        // ceil_grapheme_boundary rounds up.
+        assert_eq!(ceil_grapheme_boundary(s, crab_start + 2), crab_end);
+        assert_eq!(ceil_grapheme_boundary(s, crab_start), crab_start);
+    }
+
+    #[test]
+    fn test_byte_cap_on_multibyte_output() {
+        // One long line of 3-byte chars: the byte cap lands mid-character
+        let output = "日本語".repeat(400);
+        let tr = truncate_output(&output, 100, 1000);
+        assert!(tr.truncated);
+        assert!(tr.output.ends_with("[... truncated at byte limit ...]"));
+        let kept = tr.output.trim_end_matches("\n[... truncated at byte limit ...]");
+        assert!(kept.len() <= 1000);
+        assert!(output.starts_with(kept));
     }
 
     // =========================================================================
@@ -1308,6 +1876,118 @@ mod tests {
         assert!(!result.text_content().contains("Read-only agent"));
     }
 
+    #[tokio::test]
+    async fn test_write_policy_blocks_and_escalates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mounts = Arc::new(SandboxMounts::new(dir.path().to_path_buf()).unwrap());
+        let permissions = Arc::new(PermissionStore::new(std::collections::HashMap::new()));
+        let (approval, mut rx) = create_approval_channel();
+        let path_policy = Arc::new(RwLock::new(SandboxPathPolicy::system_only()));
+        let rules = vec![
+            WriteRule {
+                path: ".env".into(),
+                action: WriteAction::Block,
+                reason: None,
+            },
+            WriteRule {
+                path: "/infra".into(),
+                action: WriteAction::Ask,
+                reason: Some("infra changes need review".into()),
+            },
+        ];
+        let tool = RunTool::new(mounts, permissions, approval, path_policy)
+            .with_write_classifier(Arc::new(WritePolicy::new(rules, dir.path())));
+
+        // echo is session tier, but the redirect target is protected
+        let result = tool
+            .execute(serde_json::json!({"command": "echo KEY=1 >> .env"}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("Blocked by write policy"));
+        assert!(!dir.path().join(".env").exists());
+
+        let responder = tokio::spawn(async move {
+            let request = rx.recv().await.unwrap();
+            let _ = request
+                .response_tx
+                .send(ApprovalResponse::Deny(None));
+            (request.category, request.trigger_commands, request.preview)
+        });
+        let result = tool
+            .execute(serde_json::json!({"command": "cat > infra/main.tf <<'EOF'\nx\nEOF"}))
+            .await
+            .unwrap();
+        assert!(result.text_content().contains("denied by user"));
+        let (category, triggers, preview) = responder.await.unwrap();
+        assert_eq!(category, "Write policy");
+        assert_eq!(triggers, vec!["write policy: infra changes need review"]);
+        assert!(preview.unwrap().contains("+++ b/infra/main.tf\n@@ -0,0 +1 @@\n+x\n"));
+    }
+
+    #[test]
+    fn test_untrusted_source() {
+        let (mounts, root) = test_mounts();
+        let outside = tempfile::TempDir::new().unwrap();
+        std::fs::write(root.path().join("lib.rs"), "fn main() {}").unwrap();
+        std::fs::write(outside.path().join("notes.md"), "x").unwrap();
+        std::fs::write(outside.path().join("todo.md"), "x").unwrap();
+        let notes = outside.path().join("notes.md");
+
+        assert_eq!(untrusted_source(&mounts, "cat lib.rs"), None);
+        assert_eq!(untrusted_source(&mounts, "ls /nonexistent"), None);
+        assert_eq!(
+            untrusted_source(&mounts, &format!("cat lib.rs {}", notes.display())),
+            Some(notes.display().to_string())
+        );
+        let both = format!(
+            "grep -n TODO {} {}",
+            notes.display(),
+            outside.path().join("todo.md").display()
+        );
+        assert_eq!(
+            untrusted_source(&mounts, &both),
+            Some(format!("{} (+1 more)", notes.display()))
+        );
+    }
+
+    #[test]
+    fn test_write_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let mounts = Arc::new(SandboxMounts::new(dir.path().to_path_buf()).unwrap());
+        let permissions = Arc::new(PermissionStore::new(std::collections::HashMap::new()));
+        let (approval, _rx) = create_approval_channel();
+        let path_policy = Arc::new(RwLock::new(SandboxPathPolicy::system_only()));
+        let tool = RunTool::new(mounts, permissions, approval, path_policy);
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+
+        let targets = |args: serde_json::Value| tool.write_targets(&args);
+        assert_eq!(
+            targets(serde_json::json!({"command": "sed -i 's/a/b/' ./src/lib.rs"})),
+            vec!["src/lib.rs"]
+        );
+        assert_eq!(
+            targets(serde_json::json!({"command": "grep -n foo src/lib.rs > out.txt"})),
+            vec!["out.txt"]
+        );
+        // Absolute paths under the root and `..` segments resolve to the
+        // same root-relative target
+        let absolute = dir.path().join("src/lib.rs");
+        assert_eq!(
+            targets(serde_json::json!({"command": format!("rm {}", absolute.display())})),
+            vec!["src/lib.rs"]
+        );
+        assert_eq!(
+            targets(serde_json::json!({"command": "touch src/../src/./lib.rs"})),
+            vec!["src/lib.rs"]
+        );
+        assert!(targets(serde_json::json!({"command": "cat src/lib.rs"})).is_empty());
+        assert!(targets(serde_json::json!({"continue_from": "qq-spill-1:81"})).is_empty());
+        assert!(make_read_only_run_tool()
+            .write_targets(&serde_json::json!({"command": "rm -rf src"}))
+            .is_empty());
+    }
+
     #[test]
     fn test_read_only_tool_description() {
         let tool = make_read_only_run_tool();
@@ -1324,5 +2004,6 @@ mod tests {
             !desc.contains("Permission tiers"),
             "Description should not show permission tiers"
         );
+        assert!(desc.contains("exit_code_only"), "Description should steer toward output modes");
     }
 }
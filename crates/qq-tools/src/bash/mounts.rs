@@ -2,7 +2,7 @@
 
 use async_trait::async_trait;
 use serde::Deserialize;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use tempfile::TempDir;
@@ -26,6 +26,13 @@ pub struct SandboxMounts {
     extra: RwLock<Vec<MountPoint>>,
     tmp_dir: TempDir,
     spill_counter: AtomicUsize,
+    /// Whether writes may go through a symlink whose target stays inside a
+    /// writable root. Escaping symlinks are always rejected.
+    follow_symlinks: bool,
+    /// Subdirectory of the project root set by `/focus`, relative to it
+    focus: RwLock<Option<PathBuf>>,
+    /// Directories extra mounts must be inside; `None` allows any
+    allowed_mounts: Option<Vec<PathBuf>>,
 }
 
 impl SandboxMounts {
@@ -36,13 +43,89 @@ impl SandboxMounts {
             extra: RwLock::new(Vec::new()),
             tmp_dir,
             spill_counter: AtomicUsize::new(0),
+            follow_symlinks: true,
+            focus: RwLock::new(None),
+            allowed_mounts: None,
         })
     }
 
+    /// Only allow extra mounts inside `roots` (canonical paths).
+    pub fn with_allowed_mounts(mut self, roots: Vec<PathBuf>) -> Self {
+        self.allowed_mounts = Some(roots);
+        self
+    }
+
+    /// Set whether writes may follow symlinks that stay inside a writable root.
+    ///
+    /// When false, any write whose final path component is a symlink is refused.
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    pub fn follow_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
     pub fn project_root(&self) -> &PathBuf {
         &self.project_root
     }
 
+    /// Focus directory relative to the project root, if one is set.
+    pub fn focus(&self) -> Option<PathBuf> {
+        self.focus.read().ok().and_then(|f| f.clone())
+    }
+
+    /// Directory commands run in and relative paths resolve against: the
+    /// focus directory when set, otherwise the project root.
+    pub fn work_dir(&self) -> PathBuf {
+        match self.focus() {
+            Some(focus) => self.project_root.join(focus),
+            None => self.project_root.clone(),
+        }
+    }
+
+    /// Narrow the working directory to `dir`, or restore the project root
+    /// with `None`. A relative `dir` is taken from the project root; it must
+    /// be an existing directory inside it. Returns the new focus relative to
+    /// the project root (`None` when that is the root itself).
+    ///
+    /// The sandbox still mounts the whole project; the focus only moves the
+    /// starting point of commands and path resolution.
+    pub fn set_focus(&self, dir: Option<&Path>) -> Result<Option<PathBuf>, String> {
+        let focus = match dir {
+            None => None,
+            Some(dir) => {
+                let root = self
+                    .project_root
+                    .canonicalize()
+                    .map_err(|e| format!("Cannot resolve project root: {}", e))?;
+                let canonical = self
+                    .project_root
+                    .join(dir)
+                    .canonicalize()
+                    .map_err(|e| format!("{}: {}", dir.display(), e))?;
+                if !canonical.is_dir() {
+                    return Err(format!("{} is not a directory", dir.display()));
+                }
+                let relative = canonical.strip_prefix(&root).map_err(|_| {
+                    format!(
+                        "{} is outside the project root {}",
+                        dir.display(),
+                        self.project_root.display()
+                    )
+                })?;
+                (!relative.as_os_str().is_empty()).then(|| relative.to_path_buf())
+            }
+        };
+        let mut current = self
+            .focus
+            .write()
+            .map_err(|_| "Focus lock poisoned".to_string())?;
+        *current = focus.clone();
+        Ok(focus)
+    }
+
     /// Per-instance scratch directory that persists across bash commands.
     pub fn tmp_dir(&self) -> &Path {
         self.tmp_dir.path()
@@ -59,13 +142,34 @@ impl SandboxMounts {
         self.tmp_dir.path().join(format!("qq-spill-{}.txt", id))
     }
 
-    pub fn add_mount(&self, mount: MountPoint) {
+    /// Whether `path` (canonical) may be mounted, with the allowed
+    /// directories in the error when it may not.
+    pub fn check_mount(&self, path: &Path) -> Result<(), String> {
+        let Some(roots) = &self.allowed_mounts else {
+            return Ok(());
+        };
+        if roots.iter().any(|root| path.starts_with(root)) {
+            return Ok(());
+        }
+        let allowed: Vec<String> = roots.iter().map(|r| r.display().to_string()).collect();
+        Err(format!(
+            "{} is outside the directories allowed to be mounted ({})",
+            path.display(),
+            if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") }
+        ))
+    }
+
+    /// Add an extra read-only mount; refused when [`Self::check_mount`]
+    /// doesn't allow it.
+    pub fn add_mount(&self, mount: MountPoint) -> Result<(), String> {
+        self.check_mount(&mount.host_path)?;
         if let Ok(mut extra) = self.extra.write() {
             // Don't add duplicates
             if !extra.iter().any(|m| m.host_path == mount.host_path) {
                 extra.push(mount);
             }
         }
+        Ok(())
     }
 
     pub fn remove_mount(&self, path: &PathBuf) {
@@ -78,6 +182,75 @@ impl SandboxMounts {
         self.extra.read().map(|e| e.clone()).unwrap_or_default()
     }
 
+    /// Resolve a write target and verify it cannot escape a writable root.
+    ///
+    /// Relative paths are resolved against the working directory (see
+    /// [`work_dir`](Self::work_dir)). Paths that do not
+    /// name a location under the project root or the session tmp dir are
+    /// returned normalized but unchecked — they are outside by name and are
+    /// governed by the permission model instead. For paths that do, the final
+    /// parent directory is canonicalized, a symlinked final component is
+    /// either followed or refused (see [`with_follow_symlinks`](Self::with_follow_symlinks)),
+    /// and the resolved target must sit under the same device/inode as one of
+    /// the writable roots. This catches a symlink inside the root that points
+    /// outside it.
+    pub fn resolve_write_path(&self, path: &Path) -> Result<PathBuf, String> {
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.work_dir().join(path)
+        };
+        let normalized = normalize_lexically(&absolute);
+
+        let roots: Vec<PathBuf> = [self.project_root.as_path(), self.tmp_dir.path()]
+            .iter()
+            .flat_map(|root| {
+                let mut forms = vec![normalize_lexically(root)];
+                if let Ok(canonical) = root.canonicalize() {
+                    forms.push(canonical);
+                }
+                forms
+            })
+            .collect();
+
+        if !roots.iter().any(|root| normalized.starts_with(root)) {
+            return Ok(normalized);
+        }
+
+        let mut target = match (normalized.parent(), normalized.file_name()) {
+            (Some(parent), Some(name)) => canonicalize_existing_prefix(parent)?.join(name),
+            _ => canonicalize_existing_prefix(&normalized)?,
+        };
+
+        if let Ok(meta) = std::fs::symlink_metadata(&target) {
+            if meta.file_type().is_symlink() {
+                if !self.follow_symlinks {
+                    return Err(format!(
+                        "Refusing to write through symlink: {} (follow_symlinks is disabled)",
+                        path.display()
+                    ));
+                }
+                let link = std::fs::read_link(&target)
+                    .map_err(|e| format!("Failed to read symlink {}: {}", target.display(), e))?;
+                let link_target = match target.parent() {
+                    Some(parent) if link.is_relative() => parent.join(link),
+                    _ => link,
+                };
+                target = canonicalize_existing_prefix(&normalize_lexically(&link_target))?;
+            }
+        }
+
+        if roots.iter().any(|root| is_contained(&target, root)) {
+            Ok(target)
+        } else {
+            Err(format!(
+                "Write target escapes the sandbox root via symlink: {} -> {}",
+                path.display(),
+                target.display()
+            ))
+        }
+    }
+
     /// Format mounts for display.
     pub fn format_mounts(&self) -> String {
         let mut lines = Vec::new();
@@ -89,6 +262,9 @@ impl SandboxMounts {
             "  /tmp -> {} (read-write, per-session scratch)",
             self.tmp_dir.path().display()
         ));
+        if let Some(focus) = self.focus() {
+            lines.push(format!("  working directory: {}", focus.display()));
+        }
         if let Ok(extra) = self.extra.read() {
             for mount in extra.iter() {
                 let label = mount
@@ -107,6 +283,70 @@ impl SandboxMounts {
     }
 }
 
+/// Collapse `.` and `..` components without touching the filesystem.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+/// Canonicalize the longest existing prefix of `path` and re-append the
+/// remaining (not yet created) components. Missing components cannot be
+/// symlinks, so the result is fully resolved.
+fn canonicalize_existing_prefix(path: &Path) -> Result<PathBuf, String> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        match existing.canonicalize() {
+            Ok(canonical) => {
+                let mut resolved = canonical;
+                for name in missing.iter().rev() {
+                    resolved.push(name);
+                }
+                return Ok(resolved);
+            }
+            Err(_) => match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name.to_os_string());
+                    existing = parent;
+                }
+                _ => return Err(format!("Failed to resolve path: {}", path.display())),
+            },
+        }
+    }
+}
+
+/// Whether `target` lives under `root`.
+///
+/// On Unix the check walks the target's existing ancestors and compares
+/// device/inode pairs with the root, so it holds even when the two paths are
+/// spelled differently (bind mounts, hard-to-canonicalize prefixes).
+fn is_contained(target: &Path, root: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let Ok(root_meta) = std::fs::metadata(root) else {
+            return false;
+        };
+        target
+            .ancestors()
+            .filter_map(|p| std::fs::metadata(p).ok())
+            .any(|m| m.dev() == root_meta.dev() && m.ino() == root_meta.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        target.starts_with(root)
+    }
+}
+
 /// LLM-callable tool for requesting additional mounts.
 pub struct MountExternalTool {
     mounts: Arc<SandboxMounts>,
@@ -213,6 +453,9 @@ impl Tool for MountExternalTool {
                 canonical.display()
             )));
         }
+        if let Err(e) = self.mounts.check_mount(&canonical) {
+            return Ok(ToolOutput::error(format!("Mount not allowed: {}", e)));
+        }
 
         // Request approval from user
         let approval_msg = format!("mount_external: {}", canonical.display());
@@ -227,10 +470,12 @@ impl Tool for MountExternalTool {
         {
             Ok(super::permissions::ApprovalResponse::Allow)
             | Ok(super::permissions::ApprovalResponse::AllowForSession) => {
-                self.mounts.add_mount(MountPoint {
+                if let Err(e) = self.mounts.add_mount(MountPoint {
                     host_path: canonical.clone(),
                     label: Some(args.reason),
-                });
+                }) {
+                    return Ok(ToolOutput::error(format!("Mount not allowed: {}", e)));
+                }
                 Ok(ToolOutput::success(format!(
                     "Mount approved. {} is now accessible (read-only) in bash commands.",
                     canonical.display()
@@ -247,3 +492,127 @@ impl Tool for MountExternalTool {
         }
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn setup() -> (TempDir, TempDir, SandboxMounts) {
+        let root = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let mounts = SandboxMounts::new(root.path().to_path_buf()).unwrap();
+        (root, outside, mounts)
+    }
+
+    #[test]
+    fn test_resolve_write_path_plain_file() {
+        let (root, _outside, mounts) = setup();
+        let resolved = mounts.resolve_write_path(Path::new("new/file.txt")).unwrap();
+        assert!(resolved.starts_with(root.path().canonicalize().unwrap()));
+        assert!(resolved.ends_with("new/file.txt"));
+    }
+
+    #[test]
+    fn test_resolve_write_path_rejects_escaping_file_symlink() {
+        let (root, outside, mounts) = setup();
+        let target = outside.path().join("secret.txt");
+        std::fs::write(&target, "x").unwrap();
+        symlink(&target, root.path().join("link.txt")).unwrap();
+
+        let err = mounts.resolve_write_path(Path::new("link.txt")).unwrap_err();
+        assert!(err.contains("escapes"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolve_write_path_rejects_escaping_dir_symlink() {
+        let (root, outside, mounts) = setup();
+        symlink(outside.path(), root.path().join("linkdir")).unwrap();
+
+        let err = mounts
+            .resolve_write_path(&root.path().join("linkdir/new.txt"))
+            .unwrap_err();
+        assert!(err.contains("escapes"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolve_write_path_rejects_dangling_escape() {
+        let (root, outside, mounts) = setup();
+        symlink(outside.path().join("not-yet.txt"), root.path().join("dangling")).unwrap();
+
+        assert!(mounts.resolve_write_path(Path::new("dangling")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_write_path_internal_symlink_follow_toggle() {
+        let (root, _outside, mounts) = setup();
+        std::fs::write(root.path().join("real.txt"), "x").unwrap();
+        symlink(root.path().join("real.txt"), root.path().join("alias.txt")).unwrap();
+
+        let resolved = mounts.resolve_write_path(Path::new("alias.txt")).unwrap();
+        assert!(resolved.ends_with("real.txt"));
+
+        let strict = SandboxMounts::new(root.path().to_path_buf())
+            .unwrap()
+            .with_follow_symlinks(false);
+        let err = strict.resolve_write_path(Path::new("alias.txt")).unwrap_err();
+        assert!(err.contains("follow_symlinks"), "{}", err);
+    }
+
+    #[test]
+    fn test_focus_moves_work_dir_within_project() {
+        let (root, outside, mounts) = setup();
+        std::fs::create_dir_all(root.path().join("packages/api")).unwrap();
+        std::fs::write(root.path().join("README.md"), "x").unwrap();
+
+        let focus = mounts.set_focus(Some(Path::new("./packages/api/"))).unwrap();
+        assert_eq!(focus, Some(PathBuf::from("packages/api")));
+        assert_eq!(mounts.work_dir(), root.path().join("packages/api"));
+        let resolved = mounts.resolve_write_path(Path::new("src/lib.rs")).unwrap();
+        assert!(resolved.ends_with("packages/api/src/lib.rs"));
+
+        // Rejected targets leave the focus unchanged
+        assert!(mounts.set_focus(Some(Path::new("README.md"))).is_err());
+        assert!(mounts.set_focus(Some(Path::new("missing"))).is_err());
+        let err = mounts.set_focus(Some(outside.path())).unwrap_err();
+        assert!(err.contains("outside the project root"), "{}", err);
+        assert_eq!(mounts.focus(), Some(PathBuf::from("packages/api")));
+
+        assert_eq!(mounts.set_focus(Some(root.path())).unwrap(), None);
+        mounts.set_focus(Some(Path::new("packages"))).unwrap();
+        assert_eq!(mounts.set_focus(None).unwrap(), None);
+        assert_eq!(&mounts.work_dir(), mounts.project_root());
+    }
+
+    #[test]
+    fn test_add_mount_respects_allowed_mounts() {
+        let (root, outside, mounts) = setup();
+        let data = outside.path().canonicalize().unwrap().join("data");
+        std::fs::create_dir_all(&data).unwrap();
+        let mount = |path: &Path| MountPoint {
+            host_path: path.to_path_buf(),
+            label: None,
+        };
+
+        // Anything goes without a list
+        mounts.add_mount(mount(&data)).unwrap();
+        assert_eq!(mounts.list_extra().len(), 1);
+
+        let mounts = SandboxMounts::new(root.path().to_path_buf())
+            .unwrap()
+            .with_allowed_mounts(vec![data.clone()]);
+        mounts.add_mount(mount(&data.join("sets"))).unwrap();
+        let err = mounts.add_mount(mount(outside.path())).unwrap_err();
+        assert!(err.contains("outside the directories allowed"), "{}", err);
+        // Prefixes only match whole components
+        assert!(mounts.check_mount(&data.with_file_name("data2")).is_err());
+        assert_eq!(mounts.list_extra().len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_write_path_outside_by_name_is_unchecked() {
+        let (_root, outside, mounts) = setup();
+        let path = outside.path().join("file.txt");
+        assert_eq!(mounts.resolve_write_path(&path).unwrap(), path);
+    }
+}
@@ -0,0 +1,736 @@
+//! Code outline tool backed by tree-sitter grammars.
+//!
+//! `outline_file` returns the symbol structure of a source file or directory
+//! (functions, types, impl blocks, classes) with line ranges, so agents can
+//! find the code they need and then read just those lines with `read_files`
+//! instead of whole files. Paths are limited to the `run` sandbox mounts, the
+//! same as `read_files`.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tree_sitter::{Language, Node, Parser};
+
+use qq_core::{
+    Checkpoint, Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters,
+};
+
+use super::access_log::AccessLog;
+use super::mounts::SandboxMounts;
+use super::read_files::resolve_readable;
+use super::sandbox::SandboxPathPolicy;
+
+/// Default output budget.
+const DEFAULT_BUDGET_BYTES: usize = 48 * 1024;
+
+/// Upper bound for a caller-supplied `max_bytes`.
+const MAX_BUDGET_BYTES: usize = 256 * 1024;
+
+/// Most files outlined for one directory.
+const MAX_FILES: usize = 200;
+
+/// Files larger than this are skipped (generated code, bundles).
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Symbols nested deeper than this are not listed.
+const MAX_NESTING: usize = 4;
+
+/// Longest signature kept per symbol.
+const MAX_LABEL_CHARS: usize = 160;
+
+/// Directories never descended into when outlining a directory.
+const SKIP_DIRS: &[&str] = &[
+    "target",
+    "node_modules",
+    "vendor",
+    "dist",
+    "build",
+    "__pycache__",
+    "venv",
+];
+
+const OUTLINE_TOOL_DESC: &str = "\
+Outline source code: the functions, types, impl blocks, classes, and methods of a file \
+or directory, each with its signature and line range. Much cheaper than reading whole \
+files — use it first to find the code you need, then read only those lines with \
+read_files (start_line/end_line).
+
+Supports Rust, Python, JavaScript/JSX, TypeScript/TSX, and Go. A directory is outlined \
+recursively (skipping hidden, build, and dependency directories), files sorted by path. \
+Output is capped at max_bytes (default 48KB); outline a subdirectory for more detail.";
+
+/// Tool returning a symbol outline of source files.
+pub struct OutlineFileTool {
+    mounts: Arc<SandboxMounts>,
+    path_policy: Arc<RwLock<SandboxPathPolicy>>,
+    access_log: Option<Arc<AccessLog>>,
+}
+
+#[derive(Deserialize)]
+struct OutlineArgs {
+    path: String,
+    #[serde(default)]
+    max_bytes: Option<usize>,
+}
+
+/// Languages with an outline grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+/// How a node that names a symbol is listed.
+struct SymbolRule {
+    /// Whether symbols inside it (methods of an impl or class) are listed too
+    nested: bool,
+    /// Keyword prepended when the node's own text doesn't start with one
+    prefix: Option<&'static str>,
+}
+
+impl SymbolRule {
+    const LEAF: Self = Self {
+        nested: false,
+        prefix: None,
+    };
+    const CONTAINER: Self = Self {
+        nested: true,
+        prefix: None,
+    };
+}
+
+/// One outlined symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Symbol {
+    depth: usize,
+    /// 1-based, inclusive
+    start_line: usize,
+    end_line: usize,
+    label: String,
+}
+
+impl Lang {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    fn grammar(self) -> Language {
+        match self {
+            Self::Rust => Language::new(tree_sitter_rust::LANGUAGE),
+            Self::Python => Language::new(tree_sitter_python::LANGUAGE),
+            Self::JavaScript => Language::new(tree_sitter_javascript::LANGUAGE),
+            Self::TypeScript => Language::new(tree_sitter_typescript::LANGUAGE_TYPESCRIPT),
+            Self::Tsx => Language::new(tree_sitter_typescript::LANGUAGE_TSX),
+            Self::Go => Language::new(tree_sitter_go::LANGUAGE),
+        }
+    }
+
+    /// How `node` is listed, or `None` if it isn't a symbol.
+    fn classify(self, node: &Node) -> Option<SymbolRule> {
+        let kind = node.kind();
+        match self {
+            Self::Rust => match kind {
+                "impl_item" | "trait_item" | "mod_item" => Some(SymbolRule::CONTAINER),
+                "function_item"
+                | "function_signature_item"
+                | "struct_item"
+                | "enum_item"
+                | "union_item"
+                | "type_item"
+                | "const_item"
+                | "static_item"
+                | "macro_definition" => Some(SymbolRule::LEAF),
+                _ => None,
+            },
+            Self::Python => match kind {
+                "class_definition" => Some(SymbolRule::CONTAINER),
+                "function_definition" => Some(SymbolRule::LEAF),
+                _ => None,
+            },
+            Self::JavaScript | Self::TypeScript | Self::Tsx => match kind {
+                "class_declaration" | "abstract_class_declaration" | "internal_module" => {
+                    Some(SymbolRule::CONTAINER)
+                }
+                "function_declaration"
+                | "generator_function_declaration"
+                | "method_definition"
+                | "interface_declaration"
+                | "type_alias_declaration"
+                | "enum_declaration" => Some(SymbolRule::LEAF),
+                // `const handler = async (req) => { ... }`
+                "variable_declarator" => node
+                    .child_by_field_name("value")
+                    .filter(|value| {
+                        matches!(
+                            value.kind(),
+                            "arrow_function" | "function_expression" | "function"
+                        )
+                    })
+                    .map(|_| SymbolRule::LEAF),
+                _ => None,
+            },
+            Self::Go => match kind {
+                "function_declaration" | "method_declaration" => Some(SymbolRule::LEAF),
+                "type_spec" | "type_alias" => Some(SymbolRule {
+                    nested: false,
+                    prefix: Some("type"),
+                }),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Outline `source`, returning its symbols in document order.
+fn outline_source(lang: Lang, source: &str) -> Result<Vec<Symbol>, String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&lang.grammar())
+        .map_err(|e| format!("grammar load failed: {}", e))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| "parse failed".to_string())?;
+    let mut symbols = Vec::new();
+    collect(lang, tree.root_node(), source, 0, &mut symbols);
+    Ok(symbols)
+}
+
+fn collect(lang: Lang, node: Node, source: &str, depth: usize, out: &mut Vec<Symbol>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match lang.classify(&child) {
+            Some(rule) => {
+                out.push(Symbol {
+                    depth,
+                    start_line: child.start_position().row + 1,
+                    end_line: child.end_position().row + 1,
+                    label: label(&child, source, rule.prefix),
+                });
+                if rule.nested && depth + 1 < MAX_NESTING {
+                    collect(lang, child, source, depth + 1, out);
+                }
+            }
+            // Wrappers: export statements, decorators, declaration lists, blocks
+            None => collect(lang, child, source, depth, out),
+        }
+    }
+}
+
+/// The symbol's signature: its text up to the body, whitespace collapsed.
+fn label(node: &Node, source: &str, prefix: Option<&str>) -> String {
+    let body = node.child_by_field_name("body").or_else(|| {
+        node.child_by_field_name("value")
+            .and_then(|value| value.child_by_field_name("body"))
+    });
+    let text = match body {
+        Some(body) => source
+            .get(node.start_byte()..body.start_byte())
+            .unwrap_or(""),
+        // No body (constants, aliases, unit structs): first line, before any initializer
+        None => {
+            let text = source.get(node.start_byte()..node.end_byte()).unwrap_or("");
+            let line = text.lines().next().unwrap_or("");
+            line.split(" = ").next().unwrap_or(line)
+        }
+    };
+    let mut label = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    loop {
+        let trimmed = label
+            .trim_end_matches(|c: char| matches!(c, '{' | ':' | ';') || c.is_whitespace())
+            .trim_end_matches("=>");
+        if trimmed.len() == label.len() {
+            break;
+        }
+        label.truncate(trimmed.len());
+    }
+    if let Some(prefix) = prefix {
+        label = format!("{} {}", prefix, label);
+    }
+    if label.chars().count() > MAX_LABEL_CHARS {
+        label = label.chars().take(MAX_LABEL_CHARS).collect::<String>() + "…";
+    }
+    label
+}
+
+/// Short form of a label for change summaries: keyword and name, without
+/// visibility, generics, parameters, or types (`impl Store`, `fn get`).
+fn short_name(label: &str) -> String {
+    let mut out = String::new();
+    let mut generics = 0usize;
+    for c in label.chars() {
+        match c {
+            '<' => generics += 1,
+            '>' if generics > 0 => generics -= 1,
+            _ if generics > 0 => {}
+            '(' if !out.ends_with(' ') && !out.is_empty() => break,
+            ':' | '=' | '{' => break,
+            _ => out.push(c),
+        }
+    }
+    let mut name = out.split_whitespace().collect::<Vec<_>>();
+    while name.first().is_some_and(|word| {
+        word.starts_with("pub") || matches!(*word, "export" | "default" | "async" | "unsafe")
+    }) {
+        name.remove(0);
+    }
+    name.join(" ")
+}
+
+/// Symbols of `path` whose lines overlap `ranges` (1-based, inclusive),
+/// innermost only, in document order. Empty for unsupported languages.
+pub(crate) fn touched_symbols(path: &Path, source: &str, ranges: &[(usize, usize)]) -> Vec<String> {
+    let Some(lang) = Lang::from_path(path) else {
+        return Vec::new();
+    };
+    let Ok(symbols) = outline_source(lang, source) else {
+        return Vec::new();
+    };
+    let overlaps = |s: &Symbol| {
+        ranges
+            .iter()
+            .any(|&(start, end)| s.start_line <= end && start <= s.end_line)
+    };
+    let touched: Vec<&Symbol> = symbols.iter().filter(|s| overlaps(s)).collect();
+    let mut names: Vec<String> = Vec::new();
+    for symbol in &touched {
+        let has_touched_child = touched.iter().any(|t| {
+            t.depth > symbol.depth
+                && t.start_line >= symbol.start_line
+                && t.end_line <= symbol.end_line
+        });
+        let name = short_name(&symbol.label);
+        if !has_touched_child && !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Render one file's outline section.
+fn render_file(display: &str, source: &str, symbols: &[Symbol]) -> String {
+    let mut out = format!(
+        "===== {} ({} lines, {} symbols) =====\n",
+        display,
+        source.lines().count(),
+        symbols.len()
+    );
+    for symbol in symbols {
+        let range = if symbol.start_line == symbol.end_line {
+            format!("L{}", symbol.start_line)
+        } else {
+            format!("L{}-{}", symbol.start_line, symbol.end_line)
+        };
+        out.push_str(&format!(
+            "{}{}  {}\n",
+            "  ".repeat(symbol.depth),
+            symbol.label,
+            range
+        ));
+    }
+    out
+}
+
+/// Outline one file; `Err` for unreadable or unparsable files.
+fn outline_file(path: &Path, display: &str) -> Result<String, String> {
+    let lang = Lang::from_path(path).ok_or_else(|| {
+        format!(
+            "{}: no outline grammar for this file type (supported: .rs, .py, .js, .jsx, .ts, .tsx, .go)",
+            display
+        )
+    })?;
+    let meta = std::fs::metadata(path).map_err(|e| format!("cannot stat {}: {}", display, e))?;
+    if meta.len() > MAX_FILE_BYTES {
+        return Err(format!(
+            "{} is {} bytes (max {}); skipped",
+            display,
+            meta.len(),
+            MAX_FILE_BYTES
+        ));
+    }
+    let source =
+        std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", display, e))?;
+    let symbols = outline_source(lang, &source).map_err(|e| format!("{}: {}", display, e))?;
+    Ok(render_file(display, &source, &symbols))
+}
+
+/// Source files under `dir` with a supported language, sorted by path.
+/// Stops early, with what it found so far, once `checkpoint` is cancelled.
+fn source_files(dir: &Path, checkpoint: &Checkpoint) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        if checkpoint.is_cancelled() {
+            break;
+        }
+        let Ok(entries) = std::fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_ref()) {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() && Lang::from_path(&entry.path()).is_some() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Outline a file or directory within `budget` bytes. Runs on the blocking
+/// pool, checking `checkpoint` between directories and files.
+fn outline_path(
+    requested: &str,
+    resolved: &Path,
+    budget: usize,
+    checkpoint: &Checkpoint,
+) -> Result<String, String> {
+    if !resolved.is_dir() {
+        let mut out = outline_file(resolved, requested)?;
+        if out.len() > budget {
+            let end = qq_core::text::floor_grapheme_boundary(&out, budget);
+            let cut = out[..end].rfind('\n').map_or(0, |i| i + 1);
+            out.truncate(cut);
+            out.push_str("[truncated: output budget reached; read the file in ranges]\n");
+        }
+        return Ok(out.trim_end().to_string());
+    }
+
+    let files = source_files(resolved, checkpoint);
+    if files.is_empty() {
+        return Err(format!(
+            "{} contains no .rs, .py, .js, .jsx, .ts, .tsx, or .go files",
+            requested
+        ));
+    }
+
+    let mut out = String::new();
+    let mut shown = 0;
+    for path in files.iter().take(MAX_FILES) {
+        if checkpoint.is_cancelled() {
+            break;
+        }
+        let relative = path.strip_prefix(resolved).unwrap_or(path);
+        let display = Path::new(requested).join(relative).display().to_string();
+        let section = match outline_file(path, &display) {
+            Ok(section) => section,
+            Err(e) => format!("===== {} =====\nError: {}\n", display, e),
+        };
+        if out.len() + section.len() > budget {
+            break;
+        }
+        out.push_str(&section);
+        out.push('\n');
+        shown += 1;
+    }
+    if shown < files.len() {
+        out.push_str(&format!(
+            "[{} of {} files not shown: output budget reached; outline a subdirectory]\n",
+            files.len() - shown,
+            files.len()
+        ));
+    }
+    Ok(out.trim_end().to_string())
+}
+
+impl OutlineFileTool {
+    pub fn new(mounts: Arc<SandboxMounts>, path_policy: Arc<RwLock<SandboxPathPolicy>>) -> Self {
+        Self {
+            mounts,
+            path_policy,
+            access_log: None,
+        }
+    }
+
+    /// Report outlines of files git doesn't track to `log`.
+    pub fn with_access_log(mut self, log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for OutlineFileTool {
+    fn name(&self) -> &str {
+        "outline_file"
+    }
+
+    fn description(&self) -> &str {
+        "Outline functions, types, and classes of a source file or directory with line ranges"
+    }
+
+    fn tool_description(&self) -> &str {
+        OUTLINE_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "path",
+                    PropertySchema::string(
+                        "Source file or directory, relative to the working directory or absolute within the sandbox mounts",
+                    ),
+                    true,
+                )
+                .add_property(
+                    "max_bytes",
+                    PropertySchema::integer("Output budget in bytes (default 49152)"),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: OutlineArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("outline_file", format!("Invalid arguments: {}", e)))?;
+        let budget = args
+            .max_bytes
+            .unwrap_or(DEFAULT_BUDGET_BYTES)
+            .clamp(1024, MAX_BUDGET_BYTES);
+
+        let resolved = match resolve_readable(&self.mounts, &self.path_policy, &args.path) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        if let Some(ref log) = self.access_log {
+            if let Err(e) = log.check("outline_file", std::slice::from_ref(&resolved)).await {
+                return Ok(ToolOutput::error(e));
+            }
+        }
+        let requested = args.path;
+        let checkpoint = Checkpoint::current();
+        let walk = checkpoint.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            outline_path(&requested, &resolved, budget, &walk)
+        })
+        .await
+        .map_err(|e| Error::tool("outline_file", format!("Outline task failed: {}", e)))?;
+        // A cancelled walk stopped early; its partial outline is dropped
+        checkpoint.check()?;
+
+        Ok(match result {
+            Ok(text) => ToolOutput::success(text),
+            Err(e) => ToolOutput::error(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(lang: Lang, source: &str) -> Vec<(usize, String, usize, usize)> {
+        outline_source(lang, source)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.depth, s.label, s.start_line, s.end_line))
+            .collect()
+    }
+
+    #[test]
+    fn test_outline_rust() {
+        let source = "\
+use std::fmt;
+
+const LIMIT: usize = 10;
+
+pub struct Store<T> {
+    items: Vec<T>,
+}
+
+impl<T: Clone> Store<T> {
+    pub fn new() -> Self {
+        fn helper() {}
+        Self { items: Vec::new() }
+    }
+
+    pub async fn get(&self, index: usize) -> Option<T> {
+        self.items.get(index).cloned()
+    }
+}
+
+trait Named {
+    fn name(&self) -> &str;
+}
+";
+        assert_eq!(
+            labels(Lang::Rust, source),
+            vec![
+                (0, "const LIMIT: usize".to_string(), 3, 3),
+                (0, "pub struct Store<T>".to_string(), 5, 7),
+                (0, "impl<T: Clone> Store<T>".to_string(), 9, 18),
+                (1, "pub fn new() -> Self".to_string(), 10, 13),
+                (
+                    1,
+                    "pub async fn get(&self, index: usize) -> Option<T>".to_string(),
+                    15,
+                    17
+                ),
+                (0, "trait Named".to_string(), 20, 22),
+                (1, "fn name(&self) -> &str".to_string(), 21, 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outline_python_and_typescript() {
+        let python = "\
+@dataclass
+class Point:
+    x: int
+
+    def norm(self) -> float:
+        return 0.0
+
+def main():
+    pass
+";
+        assert_eq!(
+            labels(Lang::Python, python),
+            vec![
+                (0, "class Point".to_string(), 2, 6),
+                (1, "def norm(self) -> float".to_string(), 5, 6),
+                (0, "def main()".to_string(), 8, 9),
+            ]
+        );
+
+        let typescript = "\
+export interface Props { name: string }
+export const handler = async (req: Request) => {
+  return 1;
+};
+class Api {
+  fetch(url: string): Promise<void> {}
+}
+";
+        assert_eq!(
+            labels(Lang::TypeScript, typescript),
+            vec![
+                (0, "interface Props".to_string(), 1, 1),
+                (0, "handler = async (req: Request)".to_string(), 2, 4),
+                (0, "class Api".to_string(), 5, 7),
+                (1, "fetch(url: string): Promise<void>".to_string(), 6, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_touched_symbols_innermost_short_names() {
+        let source = "\
+const LIMIT: usize = 10;
+
+impl<T: Clone> Store<T> {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    pub async fn get(&self, index: usize) -> Option<T> {
+        self.items.get(index).cloned()
+    }
+}
+";
+        let path = Path::new("src/store.rs");
+        assert_eq!(
+            touched_symbols(path, source, &[(1, 1), (5, 5), (9, 9)]),
+            vec!["const LIMIT", "fn new", "fn get"]
+        );
+        assert_eq!(touched_symbols(path, source, &[(11, 11)]), vec!["impl Store"]);
+        assert!(touched_symbols(Path::new("notes.md"), source, &[(1, 1)]).is_empty());
+        assert_eq!(short_name("func (s *Server) Start() error"), "func (s *Server) Start");
+        assert_eq!(short_name("class Point(Base)"), "class Point");
+    }
+
+    #[tokio::test]
+    async fn test_outlines_directory_within_mounts() {
+        let root = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(root.path().join("src")).unwrap();
+        std::fs::create_dir_all(root.path().join("target/debug")).unwrap();
+        std::fs::write(root.path().join("src/lib.rs"), "pub fn a() {}\n").unwrap();
+        std::fs::write(
+            root.path().join("src/main.go"),
+            "package main\n\nfunc main() {}\n\ntype Config struct {\n\tName string\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.path().join("target/debug/gen.rs"),
+            "fn generated() {}\n",
+        )
+        .unwrap();
+        std::fs::write(root.path().join("notes.txt"), "not code\n").unwrap();
+
+        let mounts = Arc::new(SandboxMounts::new(root.path().to_path_buf()).unwrap());
+        let policy = Arc::new(RwLock::new(SandboxPathPolicy::system_only()));
+        let tool = OutlineFileTool::new(mounts, policy);
+
+        let out = tool
+            .execute(serde_json::json!({"path": "."}))
+            .await
+            .unwrap();
+        assert!(!out.is_error);
+        let text = out.text_content();
+        assert!(text.contains("===== ./src/lib.rs (1 lines, 1 symbols) =====\npub fn a()  L1"));
+        assert!(text.contains("func main()  L3"));
+        assert!(text.contains("type Config struct  L5-7"));
+        assert!(!text.contains("generated"));
+
+        let out = tool
+            .execute(serde_json::json!({"path": "notes.txt"}))
+            .await
+            .unwrap();
+        assert!(out.is_error);
+        let out = tool
+            .execute(serde_json::json!({"path": "/etc/hostname"}))
+            .await
+            .unwrap();
+        assert!(out.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_outline_stops() {
+        let root = tempfile::TempDir::new().unwrap();
+        std::fs::write(root.path().join("lib.rs"), "pub fn a() {}\n").unwrap();
+        let mounts = Arc::new(SandboxMounts::new(root.path().to_path_buf()).unwrap());
+        let policy = Arc::new(RwLock::new(SandboxPathPolicy::system_only()));
+        let tool = OutlineFileTool::new(mounts, policy);
+
+        let token = qq_core::CancellationToken::new();
+        token.cancel();
+        let result = qq_core::with_cancellation(
+            Some(token),
+            tool.execute(serde_json::json!({"path": "."})),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        let walk = qq_core::with_cancellation(Some(qq_core::CancellationToken::new()), async {
+            Checkpoint::current()
+        })
+        .await;
+        assert!(outline_path(".", root.path(), 4096, &walk).is_ok());
+    }
+}
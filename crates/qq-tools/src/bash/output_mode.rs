@@ -0,0 +1,616 @@
+//! Compact output modes for the run tool.
+//!
+//! Noisy commands (full test suites, verbose builds, JSON-lines logs) can
+//! flood the context with output the agent never reads. `output_mode` trims
+//! the result before it is returned:
+//!
+//! - `full` (default): head+tail truncation as always
+//! - `tail`: the last `tail_lines` lines of stdout and stderr
+//! - `exit_code_only`: the status line, plus a short stderr tail on failure
+//! - `jsonl`: each stdout line parsed as JSON and passed through a jq-like
+//!   `filter` (`.path`, `select(...)`, `|`)
+//!
+//! Whenever stdout is left out, the full output is spilled to `/tmp` as for
+//! truncated `full` output, so the agent can still grep it without re-running
+//! the command.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use qq_core::ToolOutput;
+
+use super::sandbox::CommandResult;
+use super::{
+    exit_code_meaning, format_bytes, format_output, is_binary_output, truncate_output,
+    write_spill_file, SandboxMounts, MAX_OUTPUT_BYTES, MAX_OUTPUT_LINES,
+};
+
+/// Lines kept by `tail` when `tail_lines` is not given.
+pub const DEFAULT_TAIL_LINES: usize = 20;
+
+/// Stderr lines kept by `exit_code_only` and `jsonl` when the command fails.
+const FAILURE_TAIL_LINES: usize = 10;
+
+/// Tool-description section steering models toward the compact modes.
+pub const OUTPUT_MODES_HELP: &str = "Output modes (output_mode parameter):\n\
+    - full (default): stdout and stderr, head+tail truncated past 200 lines\n\
+    - tail: only the last tail_lines lines (default 20) — test suites, builds, long logs\n\
+    - exit_code_only: just the exit status (plus a stderr tail on failure) — when you \
+    only need to know whether it worked: installs, formatters, `make`, `git fetch`\n\
+    - jsonl: parse each stdout line as JSON and keep what `filter` selects, e.g. \
+    `select(.reason == \"compiler-message\") | .message.rendered` for \
+    `cargo build --message-format=json`, or `select(.level == \"error\") | .msg, .ts`\n\
+    Prefer tail or exit_code_only for noisy commands whose full output you won't read; \
+    anything left out is saved to a spill file you can grep.\n";
+
+/// How much of a command's output the run tool returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    #[default]
+    Full,
+    Tail,
+    #[serde(alias = "exit_code")]
+    ExitCodeOnly,
+    #[serde(alias = "json_lines")]
+    Jsonl,
+}
+
+impl OutputMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputMode::Full => "full",
+            OutputMode::Tail => "tail",
+            OutputMode::ExitCodeOnly => "exit_code_only",
+            OutputMode::Jsonl => "jsonl",
+        }
+    }
+}
+
+// =============================================================================
+// JSON-lines filter
+// =============================================================================
+
+/// One step into a JSON value.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// A path such as `.message.spans[0].file_name`; empty for `.`.
+#[derive(Debug, Clone, PartialEq)]
+struct Path {
+    text: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stage {
+    /// Keep the value when the path is truthy, or compares true
+    Select(Path, Option<(Op, Value)>),
+    /// Replace the value with one path, or an object of several
+    Project(Vec<Path>),
+}
+
+/// A parsed jq-like filter: stages joined by `|`.
+///
+/// Supported: paths (`.`, `.a.b`, `.a[0]`, `."odd key"`), comma-separated
+/// paths (collected into an object keyed by path), and `select(PATH)` or
+/// `select(PATH OP LITERAL)` with `==`, `!=`, `<`, `<=`, `>`, `>=`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonFilter {
+    stages: Vec<Stage>,
+}
+
+/// Records a [`JsonFilter`] kept from JSON-lines output.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonlOutput {
+    /// Compact JSON of each kept value
+    pub lines: Vec<String>,
+    /// Lines that parsed as JSON
+    pub records: usize,
+    /// Non-empty lines that did not
+    pub skipped: usize,
+}
+
+impl JsonFilter {
+    pub fn parse(filter: &str) -> Result<Self, String> {
+        let filter = filter.trim();
+        if filter.is_empty() {
+            return Ok(Self {
+                stages: vec![Stage::Project(vec![Path {
+                    text: ".".into(),
+                    steps: Vec::new(),
+                }])],
+            });
+        }
+        let stages = split_top(filter, '|')
+            .into_iter()
+            .map(parse_stage)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { stages })
+    }
+
+    /// Run the filter over one value; `None` when a `select` drops it.
+    fn apply(&self, value: Value) -> Option<Value> {
+        let mut value = value;
+        for stage in &self.stages {
+            match stage {
+                Stage::Select(path, test) => {
+                    let found = lookup(&value, path);
+                    let keep = match test {
+                        None => truthy(found),
+                        Some((op, literal)) => compare(found.unwrap_or(&Value::Null), *op, literal),
+                    };
+                    if !keep {
+                        return None;
+                    }
+                }
+                Stage::Project(paths) if paths.len() == 1 => {
+                    value = lookup(&value, &paths[0]).cloned().unwrap_or(Value::Null);
+                }
+                Stage::Project(paths) => {
+                    let object = paths
+                        .iter()
+                        .map(|path| {
+                            let key = path.text.trim_start_matches('.').to_string();
+                            let found = lookup(&value, path).cloned().unwrap_or(Value::Null);
+                            (key, found)
+                        })
+                        .collect();
+                    value = Value::Object(object);
+                }
+            }
+        }
+        Some(value)
+    }
+
+    /// Filter JSON-lines text.
+    pub fn apply_lines(&self, text: &str) -> JsonlOutput {
+        let mut out = JsonlOutput::default();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let Ok(value) = serde_json::from_str::<Value>(line) else {
+                out.skipped += 1;
+                continue;
+            };
+            out.records += 1;
+            if let Some(kept) = self.apply(value) {
+                out.lines.push(match kept {
+                    // Bare strings read better unquoted, as with `jq -r`
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                });
+            }
+        }
+        out
+    }
+}
+
+/// Split `s` on `sep` outside double quotes and parentheses.
+fn split_top(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c == sep && depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts
+}
+
+fn parse_stage(stage: &str) -> Result<Stage, String> {
+    if let Some(inner) = stage
+        .strip_prefix("select(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        return parse_select(inner.trim());
+    }
+    let paths = split_top(stage, ',')
+        .into_iter()
+        .map(parse_path)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Stage::Project(paths))
+}
+
+fn parse_select(inner: &str) -> Result<Stage, String> {
+    // Longest operators first so `<=` isn't read as `<`
+    const OPS: [(&str, Op); 6] = [
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ];
+    let path_end = path_len(inner);
+    let (path, rest) = inner.split_at(path_end);
+    let path = parse_path(path)?;
+    let rest = rest.trim();
+    if rest.is_empty() {
+        return Ok(Stage::Select(path, None));
+    }
+    for (token, op) in OPS {
+        if let Some(literal) = rest.strip_prefix(token) {
+            let literal: Value = serde_json::from_str(literal.trim())
+                .map_err(|_| format!("invalid literal in select: {}", literal.trim()))?;
+            return Ok(Stage::Select(path, Some((op, literal))));
+        }
+    }
+    Err(format!("unsupported select condition: {}", inner))
+}
+
+/// Length of the path at the start of `s`.
+fn path_len(s: &str) -> usize {
+    let mut in_string = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            c if !in_string && (c.is_whitespace() || "=!<>".contains(c)) => return i,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+fn parse_path(text: &str) -> Result<Path, String> {
+    let text = text.trim();
+    let invalid = || {
+        format!(
+            "invalid path `{}` (expected e.g. `.`, `.a.b`, `.items[0]`)",
+            text
+        )
+    };
+    let mut rest = text.strip_prefix('.').ok_or_else(invalid)?;
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(invalid)?;
+            let index = after[..end].trim().parse().map_err(|_| invalid())?;
+            steps.push(Step::Index(index));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('"') {
+            let end = after.find('"').ok_or_else(invalid)?;
+            steps.push(Step::Key(after[..end].to_string()));
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(invalid());
+            }
+            steps.push(Step::Key(key.to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.strip_prefix('.').unwrap_or(rest);
+    }
+    Ok(Path {
+        text: text.to_string(),
+        steps,
+    })
+}
+
+fn lookup<'a>(value: &'a Value, path: &Path) -> Option<&'a Value> {
+    path.steps
+        .iter()
+        .try_fold(value, |current, step| match step {
+            Step::Key(key) => current.get(key),
+            Step::Index(index) => current.get(index),
+        })
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    !matches!(value, None | Some(Value::Null) | Some(Value::Bool(false)))
+}
+
+fn compare(found: &Value, op: Op, literal: &Value) -> bool {
+    use std::cmp::Ordering;
+    let ordering = match (found, literal) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match op {
+        Op::Eq => found == literal,
+        Op::Ne => found != literal,
+        Op::Lt => ordering == Some(Ordering::Less),
+        Op::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Op::Gt => ordering == Some(Ordering::Greater),
+        Op::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+// =============================================================================
+// Formatting
+// =============================================================================
+
+/// Last `n` lines of `text`.
+fn last_lines(text: &str, n: usize) -> (Vec<&str>, usize) {
+    let lines: Vec<&str> = text.lines().collect();
+    let total = lines.len();
+    (lines[total.saturating_sub(n)..].to_vec(), total)
+}
+
+fn push_stderr(output: &mut String, stderr: &str, lines: usize, is_error: bool) {
+    let (tail, total) = last_lines(stderr, lines);
+    output.push_str(if is_error {
+        "STDERR"
+    } else {
+        "STDERR (warnings)"
+    });
+    if total > tail.len() {
+        output.push_str(&format!(" (last {} of {} lines)", tail.len(), total));
+    }
+    output.push_str(":\n");
+    for line in tail {
+        output.push_str("  ");
+        output.push_str(line);
+        output.push('\n');
+    }
+}
+
+/// Format a CommandResult in `mode`. `filter` is used by `jsonl`.
+pub(crate) fn format_in_mode(
+    result: CommandResult,
+    mode: OutputMode,
+    tail_lines: usize,
+    filter: Option<&JsonFilter>,
+    mounts: &SandboxMounts,
+) -> ToolOutput {
+    let stdout = result.stdout.trim();
+    if mode == OutputMode::Full || is_binary_output(stdout) {
+        return format_output(result, mounts);
+    }
+    let is_error = result.exit_code != 0 || result.timed_out || result.sandbox_error.is_some();
+    let stderr = result.stderr.trim();
+    let total_lines = stdout.lines().count();
+
+    let mut output = String::new();
+    if result.timed_out {
+        output.push_str("[Command timed out]\n\n");
+    } else if let Some(ref err) = result.sandbox_error {
+        output.push_str(&format!("[Sandbox error: {}]\n\n", err));
+    }
+
+    // Whether any stdout was left out of the response
+    let omitted = match mode {
+        OutputMode::Full => unreachable!("handled above"),
+        OutputMode::Tail => {
+            let (tail, _) = last_lines(stdout, tail_lines);
+            if total_lines > tail.len() {
+                output.push_str(&format!("[last {} of {} lines]\n", tail.len(), total_lines));
+            }
+            for line in &tail {
+                output.push_str(line);
+                output.push('\n');
+            }
+            if !stderr.is_empty() {
+                output.push('\n');
+                push_stderr(&mut output, stderr, tail_lines, is_error);
+            }
+            total_lines > tail.len()
+        }
+        OutputMode::ExitCodeOnly => {
+            if is_error {
+                if !stderr.is_empty() {
+                    push_stderr(&mut output, stderr, FAILURE_TAIL_LINES, is_error);
+                } else if !stdout.is_empty() {
+                    let (tail, total) = last_lines(stdout, FAILURE_TAIL_LINES);
+                    output.push_str(&format!(
+                        "STDOUT (last {} of {} lines):\n",
+                        tail.len(),
+                        total
+                    ));
+                    for line in tail {
+                        output.push_str("  ");
+                        output.push_str(line);
+                        output.push('\n');
+                    }
+                }
+            }
+            !stdout.is_empty()
+        }
+        OutputMode::Jsonl => {
+            let identity = JsonFilter::parse(".").expect("identity filter");
+            let kept = filter.unwrap_or(&identity).apply_lines(stdout);
+            output.push_str(&format!(
+                "[jsonl: {} of {} records kept",
+                kept.lines.len(),
+                kept.records
+            ));
+            if kept.skipped > 0 {
+                output.push_str(&format!("; {} non-JSON lines skipped", kept.skipped));
+            }
+            output.push_str("]\n");
+            let body = kept.lines.join("\n");
+            let tr = truncate_output(&body, MAX_OUTPUT_LINES, MAX_OUTPUT_BYTES);
+            if !body.is_empty() {
+                output.push_str(&tr.output);
+                if !tr.output.ends_with('\n') {
+                    output.push('\n');
+                }
+            }
+            if is_error && !stderr.is_empty() {
+                output.push('\n');
+                push_stderr(&mut output, stderr, FAILURE_TAIL_LINES, is_error);
+            }
+            !stdout.is_empty()
+        }
+    };
+
+    output.push_str(&format!(
+        "\n---\nExit code: {} ({}) | Duration: {:.2}s | Lines: {} | Size: {} | Mode: {}",
+        result.exit_code,
+        exit_code_meaning(result.exit_code),
+        result.duration.as_secs_f64(),
+        total_lines,
+        format_bytes(stdout.len()),
+        mode.name(),
+    ));
+    if omitted {
+        if let Some(info) = write_spill_file(mounts, &result.stdout) {
+            output.push_str(&format!(
+                "\nFull output saved to {p} — inspect with:\n  \
+                 grep -n PATTERN {p}\n  \
+                 sed -n 'X,Yp' {p}",
+                p = info.sandbox_path,
+            ));
+        }
+    }
+
+    if is_error {
+        ToolOutput::error(output)
+    } else {
+        ToolOutput::success(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn result(stdout: &str, stderr: &str, exit_code: i32) -> CommandResult {
+        CommandResult {
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            exit_code,
+            timed_out: false,
+            sandbox_error: None,
+            duration: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn test_json_filter() {
+        let lines = concat!(
+            r#"{"reason":"compiler-artifact","target":{"name":"qq"}}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"level":"error","rendered":"E0308"}}"#,
+            "\n",
+            "   Compiling qq v0.1.0\n",
+            r#"{"reason":"compiler-message","message":{"level":"warning","rendered":"unused"}}"#,
+            "\n",
+        );
+
+        let filter =
+            JsonFilter::parse(r#"select(.reason == "compiler-message") | .message.rendered"#)
+                .unwrap();
+        let out = filter.apply_lines(lines);
+        assert_eq!(out.lines, ["E0308", "unused"]);
+        assert_eq!((out.records, out.skipped), (3, 1));
+
+        let filter =
+            JsonFilter::parse(r#"select(.message.level != "warning") | .reason, .target.name"#)
+                .unwrap();
+        assert_eq!(
+            filter.apply_lines(lines).lines,
+            [
+                r#"{"reason":"compiler-artifact","target.name":"qq"}"#,
+                r#"{"reason":"compiler-message","target.name":null}"#
+            ]
+        );
+
+        let numbers = "{\"n\":3,\"xs\":[1,2]}\n{\"n\":10,\"xs\":[5]}\n";
+        let filter = JsonFilter::parse("select(.n >= 5) | .xs[0]").unwrap();
+        assert_eq!(filter.apply_lines(numbers).lines, ["5"]);
+        let filter = JsonFilter::parse("select(.xs[1])").unwrap();
+        assert_eq!(filter.apply_lines(numbers).lines, [r#"{"n":3,"xs":[1,2]}"#]);
+        assert_eq!(
+            JsonFilter::parse("")
+                .unwrap()
+                .apply_lines(numbers)
+                .lines
+                .len(),
+            2
+        );
+
+        assert!(JsonFilter::parse("reason").is_err());
+        assert!(JsonFilter::parse("select(.n ~ 3)").is_err());
+        assert!(JsonFilter::parse("select(.n == nope)").is_err());
+    }
+
+    #[test]
+    fn test_compact_modes() {
+        let root = tempfile::TempDir::new().unwrap();
+        let mounts = SandboxMounts::new(root.path().to_path_buf()).unwrap();
+        let noisy: String = (1..=100).map(|i| format!("test t{} ... ok\n", i)).collect();
+
+        let out = format_in_mode(result(&noisy, "", 0), OutputMode::Tail, 3, None, &mounts);
+        let body = out.text_content();
+        assert!(!out.is_error);
+        assert!(body.starts_with("[last 3 of 100 lines]\ntest t98 ... ok\n"));
+        assert!(body.contains("Lines: 100 | Size: "));
+        assert!(body.contains("| Mode: tail\nFull output saved to /tmp/qq-spill-"));
+
+        let out = format_in_mode(
+            result(&noisy, "error: 2 tests failed\n", 101),
+            OutputMode::ExitCodeOnly,
+            DEFAULT_TAIL_LINES,
+            None,
+            &mounts,
+        );
+        let body = out.text_content();
+        assert!(out.is_error);
+        assert!(body.starts_with("STDERR:\n  error: 2 tests failed\n"));
+        assert!(!body.contains("t50"));
+        assert!(body.contains("Exit code: 101"));
+
+        // Nothing left out, nothing spilled
+        let out = format_in_mode(
+            result("", "", 0),
+            OutputMode::ExitCodeOnly,
+            DEFAULT_TAIL_LINES,
+            None,
+            &mounts,
+        );
+        assert!(out
+            .text_content()
+            .starts_with("\n---\nExit code: 0 (success)"));
+        assert!(!out.text_content().contains("Full output saved"));
+
+        let filter = JsonFilter::parse(".id").unwrap();
+        let out = format_in_mode(
+            result("{\"id\":1}\nnot json\n{\"id\":2}\n", "", 0),
+            OutputMode::Jsonl,
+            DEFAULT_TAIL_LINES,
+            Some(&filter),
+            &mounts,
+        );
+        assert!(out
+            .text_content()
+            .starts_with("[jsonl: 2 of 2 records kept; 1 non-JSON lines skipped]\n1\n2\n"));
+    }
+}
@@ -3,7 +3,7 @@
 /// Tools that support subcommand-level permission classification.
 /// When a command starts with one of these, the first non-flag word after
 /// the tool name is extracted as `tool-subcmd` (e.g., `cargo build` → `cargo-build`).
-const SUBCOMMAND_TOOLS: &[&str] = &[
+pub(crate) const SUBCOMMAND_TOOLS: &[&str] = &[
     "cargo", "git", "npm", "npx", "yarn", "pnpm", "pip", "pip3", "poetry",
 ];
 
@@ -170,7 +170,7 @@ pub fn tokenize(input: &str) -> Result<Vec<String>, String> {
 
 /// Parse a heredoc delimiter from the characters after `<<` or `<<-`.
 /// Returns `Some((delimiter, chars_consumed))` or `None` if no valid delimiter found.
-fn parse_heredoc_delimiter(chars: &[char], start: usize) -> Option<(String, usize)> {
+pub(super) fn parse_heredoc_delimiter(chars: &[char], start: usize) -> Option<(String, usize)> {
     let mut pos = start;
 
     // Skip whitespace between << and delimiter
@@ -245,7 +245,7 @@ fn extract_last_line(s: &str) -> &str {
 
 /// Split a command string on pipeline operators (`|`, `&&`, `||`, `;`)
 /// while respecting quotes and heredoc syntax.
-fn split_pipeline(input: &str) -> Vec<String> {
+pub(crate) fn split_pipeline(input: &str) -> Vec<String> {
     let mut segments = Vec::new();
     let mut current = String::new();
     let mut in_single_quote = false;
@@ -367,7 +367,7 @@ fn split_pipeline(input: &str) -> Vec<String> {
 /// Extract the first command name from a command segment.
 /// Handles environment variable assignments (e.g., `FOO=bar cmd`).
 /// Returns `tool-<subcommand>` for tools in [`SUBCOMMAND_TOOLS`].
-fn extract_first_command(segment: &str) -> String {
+pub(crate) fn extract_first_command(segment: &str) -> String {
     let trimmed = segment.trim();
 
     // Skip leading environment variable assignments (VAR=value)
@@ -23,6 +23,40 @@ pub enum Tier {
     Restricted = 2,
 }
 
+impl Tier {
+    pub const ALL: [Tier; 3] = [Tier::Session, Tier::PerCall, Tier::Restricted];
+
+    /// Name as used in config and `/permissions` (`session`, `per-call`, `restricted`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tier::Session => "session",
+            Tier::PerCall => "per-call",
+            Tier::Restricted => "restricted",
+        }
+    }
+
+    /// Parse a tier name; accepts `per_call` as well as `per-call`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "session" => Some(Tier::Session),
+            "per-call" | "per_call" | "percall" => Some(Tier::PerCall),
+            "restricted" => Some(Tier::Restricted),
+            _ => None,
+        }
+    }
+}
+
+/// Where a command's effective tier comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TierSource {
+    /// `[tools.bash_permissions]` override.
+    Config,
+    /// Promoted by an approval during this session.
+    SessionPromotion,
+    /// Built-in classification.
+    Default,
+}
+
 /// Result of checking a full pipeline's permissions.
 #[derive(Debug)]
 pub enum PipelinePermission {
@@ -38,34 +72,41 @@ pub enum PipelinePermission {
 pub struct PermissionStore {
     /// Commands promoted to session-tier during this session.
     session_promoted: RwLock<HashSet<String>>,
-    /// Config-level overrides.
-    config_overrides: HashMap<String, Tier>,
+    /// Config-level overrides (editable at runtime via `/permissions`).
+    config_overrides: RwLock<HashMap<String, Tier>>,
 }
 
 impl PermissionStore {
     pub fn new(overrides: HashMap<String, Tier>) -> Self {
         Self {
             session_promoted: RwLock::new(HashSet::new()),
-            config_overrides: overrides,
+            config_overrides: RwLock::new(overrides),
         }
     }
 
     /// Check the tier of a single command.
     pub fn check_tier(&self, command: &str) -> Tier {
+        self.explain_tier(command).0
+    }
+
+    /// Check the tier of a single command and report which layer decided it.
+    pub fn explain_tier(&self, command: &str) -> (Tier, TierSource) {
         // 1. Config overrides take precedence
-        if let Some(&tier) = self.config_overrides.get(command) {
-            return tier;
+        if let Ok(overrides) = self.config_overrides.read() {
+            if let Some(&tier) = overrides.get(command) {
+                return (tier, TierSource::Config);
+            }
         }
 
         // 2. Session promotions (highest runtime tier)
         if let Ok(promoted) = self.session_promoted.read() {
             if promoted.contains(command) {
-                return Tier::Session;
+                return (Tier::Session, TierSource::SessionPromotion);
             }
         }
 
         // 3. Default classification
-        default_tier(command)
+        (default_tier(command), TierSource::Default)
     }
 
     /// Check a pipeline (list of extracted command names) and return the required permission level.
@@ -100,10 +141,112 @@ impl PermissionStore {
             promoted.insert(command.to_string());
         }
     }
+
+    /// Drop a session promotion. Returns false if the command wasn't promoted.
+    pub fn revoke_session(&self, command: &str) -> bool {
+        self.session_promoted
+            .write()
+            .map(|mut promoted| promoted.remove(command))
+            .unwrap_or(false)
+    }
+
+    /// Set (or replace) a config-level override.
+    pub fn set_override(&self, command: &str, tier: Tier) {
+        if let Ok(mut overrides) = self.config_overrides.write() {
+            overrides.insert(command.to_string(), tier);
+        }
+    }
+
+    /// Remove a config-level override. Returns the tier it had, if any.
+    pub fn clear_override(&self, command: &str) -> Option<Tier> {
+        self.config_overrides
+            .write()
+            .ok()
+            .and_then(|mut overrides| overrides.remove(command))
+    }
+
+    /// Config-level overrides, sorted by command.
+    pub fn overrides(&self) -> Vec<(String, Tier)> {
+        let mut list: Vec<_> = self
+            .config_overrides
+            .read()
+            .map(|o| o.iter().map(|(c, t)| (c.clone(), *t)).collect())
+            .unwrap_or_default();
+        list.sort();
+        list
+    }
+
+    /// Commands promoted during this session, sorted.
+    pub fn session_promotions(&self) -> Vec<String> {
+        let mut list: Vec<_> = self
+            .session_promoted
+            .read()
+            .map(|p| p.iter().cloned().collect())
+            .unwrap_or_default();
+        list.sort();
+        list
+    }
+
+    /// Config overrides grouped into `(session, per_call, restricted)` lists,
+    /// the shape of `[tools.bash_permissions]`.
+    pub fn overrides_by_tier(&self) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let mut grouped = (Vec::new(), Vec::new(), Vec::new());
+        for (command, tier) in self.overrides() {
+            match tier {
+                Tier::Session => grouped.0.push(command),
+                Tier::PerCall => grouped.1.push(command),
+                Tier::Restricted => grouped.2.push(command),
+            }
+        }
+        grouped
+    }
+
+    /// Human-readable listing of overrides, promotions, and built-in tiers.
+    pub fn format_permissions(&self) -> String {
+        let mut out = String::from("Config overrides ([tools.bash_permissions]):\n");
+        let overrides = self.overrides();
+        if overrides.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        for tier in Tier::ALL {
+            let commands: Vec<&str> = overrides
+                .iter()
+                .filter(|(_, t)| *t == tier)
+                .map(|(c, _)| c.as_str())
+                .collect();
+            if !commands.is_empty() {
+                out.push_str(&format!("  {}: {}\n", tier.name(), commands.join(", ")));
+            }
+        }
+
+        out.push_str("\nSession promotions (until exit):\n");
+        let promoted = self.session_promotions();
+        if promoted.is_empty() {
+            out.push_str("  (none)\n");
+        } else {
+            out.push_str(&format!("  {}\n", promoted.join(", ")));
+        }
+
+        out.push_str("\nBuilt-in defaults:\n");
+        for (tier, commands) in [
+            (Tier::Session, SESSION_COMMANDS),
+            (Tier::PerCall, PER_CALL_COMMANDS),
+            (Tier::Restricted, RESTRICTED_COMMANDS),
+        ] {
+            out.push_str(&format!(
+                "  {} ({}): {}\n",
+                tier.name(),
+                commands.len(),
+                commands.join(", ")
+            ));
+        }
+        out.push_str("  Unlisted commands are per-call.\n");
+        out
+    }
 }
 
 /// Default tier classification for a command.
-fn default_tier(command: &str) -> Tier {
+pub(super) fn default_tier(command: &str) -> Tier {
     // Check restricted first
     if RESTRICTED_COMMANDS.contains(&command) {
         return Tier::Restricted;
@@ -553,6 +696,42 @@ mod tests {
         assert_eq!(s.check_tier("python"), Tier::Restricted);
     }
 
+    #[test]
+    fn test_runtime_override_edits() {
+        let store = store();
+        store.set_override("make", Tier::Session);
+        assert_eq!(store.explain_tier("make"), (Tier::Session, TierSource::Config));
+        store.set_override("curl", Tier::Restricted);
+        assert_eq!(
+            store.overrides_by_tier(),
+            (vec!["make".to_string()], vec![], vec!["curl".to_string()])
+        );
+
+        assert_eq!(store.clear_override("make"), Some(Tier::Session));
+        assert_eq!(store.explain_tier("make"), (Tier::PerCall, TierSource::Default));
+        assert_eq!(store.clear_override("make"), None);
+    }
+
+    #[test]
+    fn test_revoke_session_promotion() {
+        let store = store();
+        store.promote_to_session("npm");
+        assert_eq!(store.explain_tier("npm"), (Tier::Session, TierSource::SessionPromotion));
+        assert_eq!(store.session_promotions(), vec!["npm".to_string()]);
+        assert!(store.revoke_session("npm"));
+        assert!(!store.revoke_session("npm"));
+        assert_eq!(store.check_tier("npm"), Tier::PerCall);
+    }
+
+    #[test]
+    fn test_tier_names_round_trip() {
+        for tier in Tier::ALL {
+            assert_eq!(Tier::parse(tier.name()), Some(tier));
+        }
+        assert_eq!(Tier::parse("per_call"), Some(Tier::PerCall));
+        assert_eq!(Tier::parse("bogus"), None);
+    }
+
     #[test]
     fn test_config_override_beats_session_promotion() {
         let overrides = parse_config_overrides(&[], &[], &["cargo".to_string()]);
@@ -0,0 +1,278 @@
+//! Tools provided by plugins.
+//!
+//! A plugin tool is an executable shipped in a plugin directory. Each call
+//! runs it in the kernel sandbox with the call's JSON arguments on stdin;
+//! stdout is the result, and a non-zero exit reports an error. The sandbox
+//! enforces what the plugin was granted: without `write` the project root is
+//! mounted read-only, and without `home` the tool sees only system
+//! directories (as in agent mode), not `$HOME`. The plugin directory itself
+//! is always mounted read-only so the executable can be found.
+
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use qq_core::{Error, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use super::linters::{shell_quote, tail};
+use super::mounts::SandboxMounts;
+use super::sandbox::{SandboxExecutor, SandboxPathPolicy};
+
+/// Version of the plugin protocol this build speaks.
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// Placeholder in a plugin tool's command for its plugin directory.
+pub const PLUGIN_DIR_PLACEHOLDER: &str = "{plugin_dir}";
+
+/// Output kept when a plugin tool fails.
+const MAX_ERROR_CHARS: usize = 2000;
+
+/// A tool as declared in a plugin manifest.
+#[derive(Debug, Clone)]
+pub struct PluginToolSpec {
+    /// Plugin the tool belongs to
+    pub plugin: String,
+    /// Tool name within the plugin
+    pub name: String,
+    pub description: String,
+    /// JSON Schema of the arguments
+    pub parameters: serde_json::Value,
+    /// Program and arguments. A program starting with `./` and any
+    /// `{plugin_dir}` are resolved against the plugin directory.
+    pub command: Vec<String>,
+    pub timeout_secs: u64,
+    /// Whether the tool only reads (no effect on the dedup cache)
+    pub read_only: bool,
+}
+
+/// What the sandbox lets a plugin's tools do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PluginGrants {
+    /// Project root mounted writable
+    pub write: bool,
+    /// `$HOME` and the host environment visible, as for bash in user mode
+    pub home: bool,
+}
+
+/// A plugin tool bound to the session sandbox.
+pub struct PluginTool {
+    spec: PluginToolSpec,
+    /// `plugin__<plugin>__<tool>`
+    wire_name: String,
+    /// `plugin:<plugin>/<tool>`
+    display_name: String,
+    command: String,
+    mounts: Arc<SandboxMounts>,
+    policy: SandboxPathPolicy,
+    executor: SandboxExecutor,
+    writable: bool,
+}
+
+impl PluginTool {
+    /// `session_policy` is the bash tool's path policy; it is used as-is only
+    /// when the plugin was granted `home`.
+    pub fn new(
+        spec: PluginToolSpec,
+        plugin_dir: &Path,
+        grants: PluginGrants,
+        mounts: Arc<SandboxMounts>,
+        session_policy: &SandboxPathPolicy,
+        executor: SandboxExecutor,
+    ) -> Self {
+        let mut policy = if grants.home {
+            session_policy.clone()
+        } else {
+            SandboxPathPolicy::system_only()
+        };
+        if !policy.ro_mounts.iter().any(|m| plugin_dir.starts_with(m)) {
+            policy.ro_mounts.push(plugin_dir.to_path_buf());
+        }
+        policy.env_vars.extend([
+            ("QQ_PLUGIN_API".to_string(), PLUGIN_API_VERSION.to_string()),
+            (
+                "QQ_PLUGIN_DIR".to_string(),
+                plugin_dir.display().to_string(),
+            ),
+        ]);
+        Self {
+            wire_name: plugin_tool_name(&spec.plugin, &spec.name),
+            display_name: format!("plugin:{}/{}", spec.plugin, spec.name),
+            command: plugin_command(&spec.command, plugin_dir),
+            spec,
+            mounts,
+            policy,
+            executor,
+            writable: grants.write,
+        }
+    }
+}
+
+/// Wire name of a plugin tool, namespaced like MCP tools.
+pub fn plugin_tool_name(plugin: &str, tool: &str) -> String {
+    format!("plugin__{}__{}", plugin, tool)
+}
+
+/// Shell command for a manifest `command`, with plugin-relative parts resolved.
+fn plugin_command(command: &[String], plugin_dir: &Path) -> String {
+    let dir = plugin_dir.display().to_string();
+    command
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let part = match part.strip_prefix("./") {
+                Some(rel) if i == 0 => PathBuf::from(&dir).join(rel).display().to_string(),
+                _ => part.replace(PLUGIN_DIR_PLACEHOLDER, &dir),
+            };
+            shell_quote(&part)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[async_trait]
+impl Tool for PluginTool {
+    fn name(&self) -> &str {
+        &self.wire_name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(&self.wire_name, &self.spec.description)
+            .with_parameters(ToolParameters::from_raw(self.spec.parameters.clone()))
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.spec.read_only && !self.writable
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        if !self.executor.supports_shell() {
+            return Ok(ToolOutput::error(format!(
+                "{} is not available in app-level sandbox mode. \
+                 Plugin tools require the kernel sandbox.",
+                self.display_name
+            )));
+        }
+        let arguments = if arguments.is_null() {
+            serde_json::json!({})
+        } else {
+            arguments
+        };
+        tracing::info!(tool = %self.display_name, command = %self.command, "Running plugin tool");
+        let result = self
+            .executor
+            .execute(
+                &self.command,
+                &self.mounts,
+                self.spec.timeout_secs,
+                &self.policy,
+                Some(&arguments.to_string()),
+                !self.writable,
+            )
+            .await
+            .map_err(|e| Error::tool(&self.wire_name, e))?;
+
+        if let Some(e) = result.sandbox_error {
+            return Ok(ToolOutput::error(format!("Sandbox error: {}", e)));
+        }
+        if result.timed_out {
+            return Ok(ToolOutput::error(format!(
+                "{} timed out after {}s",
+                self.display_name, self.spec.timeout_secs
+            )));
+        }
+        if result.exit_code != 0 {
+            let output = if result.stderr.trim().is_empty() {
+                &result.stdout
+            } else {
+                &result.stderr
+            };
+            return Ok(ToolOutput::error(format!(
+                "{} failed (exit {}): {}",
+                self.display_name,
+                result.exit_code,
+                tail(output, MAX_ERROR_CHARS)
+            )));
+        }
+        Ok(ToolOutput::success(result.stdout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(command: &[&str]) -> PluginToolSpec {
+        PluginToolSpec {
+            plugin: "k8s".into(),
+            name: "logs".into(),
+            description: "Fetch pod logs".into(),
+            parameters: serde_json::json!({"type": "object", "properties": {}}),
+            command: command.iter().map(|s| s.to_string()).collect(),
+            timeout_secs: 30,
+            read_only: true,
+        }
+    }
+
+    #[test]
+    fn test_command_and_sandbox_policy() {
+        let dir = Path::new("/home/u/.config/qq/plugins/k8s");
+        assert_eq!(
+            plugin_command(
+                &spec(&["./bin/logs", "--format", "{plugin_dir}/fmt file"]).command,
+                dir
+            ),
+            "/home/u/.config/qq/plugins/k8s/bin/logs --format \
+             '/home/u/.config/qq/plugins/k8s/fmt file'"
+        );
+        assert_eq!(
+            plugin_command(&spec(&["python3", "./main.py"]).command, dir),
+            "python3 ./main.py"
+        );
+
+        let project = tempfile::tempdir().unwrap();
+        let mounts = Arc::new(SandboxMounts::new(project.path().to_path_buf()).unwrap());
+        let mut session = SandboxPathPolicy::system_only();
+        session.ro_mounts.push(PathBuf::from("/home/u"));
+
+        // Without `home`, only system dirs and the plugin itself are visible
+        let tool = PluginTool::new(
+            spec(&["./bin/logs"]),
+            dir,
+            PluginGrants::default(),
+            Arc::clone(&mounts),
+            &session,
+            SandboxExecutor::AppLevel,
+        );
+        assert_eq!(tool.name(), "plugin__k8s__logs");
+        assert_eq!(tool.display_name(), "plugin:k8s/logs");
+        assert_eq!(tool.policy.ro_mounts, vec![dir.to_path_buf()]);
+        assert!(tool
+            .policy
+            .env_vars
+            .contains(&("QQ_PLUGIN_API".to_string(), "1".to_string())));
+        assert!(tool.is_read_only());
+
+        // With `home`, the session policy already covers the plugin dir
+        let tool = PluginTool::new(
+            spec(&["./bin/logs"]),
+            dir,
+            PluginGrants {
+                write: true,
+                home: true,
+            },
+            mounts,
+            &session,
+            SandboxExecutor::AppLevel,
+        );
+        assert_eq!(tool.policy.ro_mounts, vec![PathBuf::from("/home/u")]);
+        assert!(!tool.is_read_only());
+    }
+}
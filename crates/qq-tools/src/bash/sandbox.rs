@@ -1,13 +1,13 @@
-//! Sandbox execution backends: kernel (hakoniwa) and app-level fallback.
+//! Sandbox execution backends: hakoniwa, bubblewrap, firejail, and an app-level fallback.
 
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU8, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 
 use super::mounts::SandboxMounts;
-use super::parse;
+use super::{parse, permissions};
 
 /// Result of a command execution.
 #[derive(Debug)]
@@ -23,14 +23,131 @@ pub struct CommandResult {
 }
 
 /// Sandbox executor backend.
+#[derive(Debug, Clone)]
 pub enum SandboxExecutor {
     /// Kernel-level isolation via hakoniwa (Linux with user namespaces).
     #[cfg(feature = "sandbox")]
     Kernel,
+    /// Kernel-level isolation via the `bwrap` binary. Works on distros that
+    /// block unprivileged user namespaces for arbitrary binaries but ship
+    /// bubblewrap setuid or with its own AppArmor profile.
+    Bubblewrap { program: PathBuf },
+    /// Firejail (seccomp, dropped capabilities, blacklisted sensitive dirs).
+    /// The host filesystem stays visible read-only outside the project root.
+    Firejail { program: PathBuf },
     /// Application-level sandboxing (restricted, no pipes).
     AppLevel,
 }
 
+/// A backend in the sandbox detection chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxBackend {
+    Hakoniwa,
+    Bubblewrap,
+    Firejail,
+    AppLevel,
+}
+
+impl SandboxBackend {
+    /// Detection order when none is configured.
+    pub const DEFAULT_ORDER: &'static [SandboxBackend] = &[
+        SandboxBackend::Hakoniwa,
+        SandboxBackend::Bubblewrap,
+        SandboxBackend::Firejail,
+        SandboxBackend::AppLevel,
+    ];
+
+    /// Parse a backend name: `hakoniwa`, `bubblewrap`, `firejail`, or `app-level`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "hakoniwa" | "kernel" => Ok(SandboxBackend::Hakoniwa),
+            "bubblewrap" | "bwrap" => Ok(SandboxBackend::Bubblewrap),
+            "firejail" => Ok(SandboxBackend::Firejail),
+            "app-level" | "app_level" | "app" => Ok(SandboxBackend::AppLevel),
+            other => Err(format!(
+                "Unknown sandbox backend '{}'. Valid backends: hakoniwa, bubblewrap, firejail, app-level",
+                other
+            )),
+        }
+    }
+
+    /// Parse a configured priority list. An empty list means [`Self::DEFAULT_ORDER`].
+    pub fn parse_order(names: &[String]) -> Result<Vec<Self>, String> {
+        if names.is_empty() {
+            return Ok(Self::DEFAULT_ORDER.to_vec());
+        }
+        names.iter().map(|n| Self::parse(n)).collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SandboxBackend::Hakoniwa => "hakoniwa",
+            SandboxBackend::Bubblewrap => "bubblewrap",
+            SandboxBackend::Firejail => "firejail",
+            SandboxBackend::AppLevel => "app-level",
+        }
+    }
+
+    /// What this backend enforces.
+    pub fn capabilities(&self) -> SandboxCapabilities {
+        match self {
+            SandboxBackend::Hakoniwa | SandboxBackend::Bubblewrap => SandboxCapabilities {
+                shell: true,
+                filesystem_isolation: true,
+                read_only_root: true,
+                hides_sensitive_dirs: true,
+            },
+            SandboxBackend::Firejail => SandboxCapabilities {
+                shell: true,
+                filesystem_isolation: false,
+                read_only_root: true,
+                hides_sensitive_dirs: true,
+            },
+            SandboxBackend::AppLevel => SandboxCapabilities {
+                shell: false,
+                filesystem_isolation: false,
+                read_only_root: false,
+                hides_sensitive_dirs: false,
+            },
+        }
+    }
+}
+
+/// Guarantees a sandbox backend provides, shown in the `run` tool description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxCapabilities {
+    /// Shell operators (pipes, redirects, subshells) are available.
+    pub shell: bool,
+    /// Paths outside the mounts do not exist inside the sandbox.
+    pub filesystem_isolation: bool,
+    /// Read-only agents see the project root mounted read-only.
+    pub read_only_root: bool,
+    /// Sensitive directories (`~/.ssh`, `~/.aws`, ...) are hidden.
+    pub hides_sensitive_dirs: bool,
+}
+
+impl SandboxCapabilities {
+    /// Comma-separated list of the enforced guarantees.
+    pub fn summary(&self) -> String {
+        let flags = [
+            (self.shell, "shell operators"),
+            (self.filesystem_isolation, "isolated filesystem"),
+            (self.read_only_root, "read-only mounts"),
+            (self.hides_sensitive_dirs, "sensitive dirs hidden"),
+        ];
+        let enabled: Vec<&str> = flags
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, name)| *name)
+            .collect();
+        if enabled.is_empty() {
+            "none".to_string()
+        } else {
+            enabled.join(", ")
+        }
+    }
+}
+
 /// Resolved PATH and mount configuration for the kernel sandbox.
 ///
 /// All branching happens at construction time; `execute_kernel` iterates
@@ -182,23 +299,70 @@ fn is_under_system_prefix(path: &str) -> bool {
 }
 
 impl SandboxExecutor {
-    /// Detect the best available sandbox backend.
+    /// Detect the best available sandbox backend in the default order.
     pub fn detect() -> Self {
-        #[cfg(feature = "sandbox")]
-        {
-            if probe_user_namespaces() {
-                tracing::info!("Kernel sandbox available (user namespaces supported)");
-                return SandboxExecutor::Kernel;
+        Self::detect_with(SandboxBackend::DEFAULT_ORDER)
+    }
+
+    /// Try each backend in `order` and return the first that works.
+    ///
+    /// Falls back to app-level when nothing in `order` is available, so the
+    /// caller decides (via [`supports_shell`](Self::supports_shell)) whether
+    /// that is acceptable.
+    pub fn detect_with(order: &[SandboxBackend]) -> Self {
+        for backend in order {
+            if let Some(executor) = Self::probe(*backend) {
+                tracing::info!(backend = backend.name(), "Sandbox backend selected");
+                return executor;
             }
-            tracing::warn!("User namespaces not available, falling back to app-level sandbox");
+            tracing::warn!(
+                backend = backend.name(),
+                "Sandbox backend unavailable, trying next"
+            );
         }
+        tracing::warn!("No sandbox backend available, falling back to app-level sandbox");
+        SandboxExecutor::AppLevel
+    }
 
-        #[cfg(not(feature = "sandbox"))]
-        {
-            tracing::info!("Sandbox feature disabled, using app-level sandbox");
+    fn probe(backend: SandboxBackend) -> Option<Self> {
+        match backend {
+            SandboxBackend::Hakoniwa => {
+                #[cfg(feature = "sandbox")]
+                {
+                    if probe_user_namespaces() {
+                        return Some(SandboxExecutor::Kernel);
+                    }
+                }
+                #[cfg(not(feature = "sandbox"))]
+                {
+                    tracing::info!("Sandbox feature disabled, hakoniwa backend unavailable");
+                }
+                None
+            }
+            SandboxBackend::Bubblewrap => {
+                probe_bubblewrap().map(|program| SandboxExecutor::Bubblewrap { program })
+            }
+            SandboxBackend::Firejail => {
+                probe_firejail().map(|program| SandboxExecutor::Firejail { program })
+            }
+            SandboxBackend::AppLevel => Some(SandboxExecutor::AppLevel),
         }
+    }
 
-        SandboxExecutor::AppLevel
+    /// The backend this executor runs on.
+    pub fn backend(&self) -> SandboxBackend {
+        match self {
+            #[cfg(feature = "sandbox")]
+            SandboxExecutor::Kernel => SandboxBackend::Hakoniwa,
+            SandboxExecutor::Bubblewrap { .. } => SandboxBackend::Bubblewrap,
+            SandboxExecutor::Firejail { .. } => SandboxBackend::Firejail,
+            SandboxExecutor::AppLevel => SandboxBackend::AppLevel,
+        }
+    }
+
+    /// What this executor enforces.
+    pub fn capabilities(&self) -> SandboxCapabilities {
+        self.backend().capabilities()
     }
 
     /// Human-readable name for this executor.
@@ -206,17 +370,15 @@ impl SandboxExecutor {
         match self {
             #[cfg(feature = "sandbox")]
             SandboxExecutor::Kernel => "kernel",
+            SandboxExecutor::Bubblewrap { .. } => "bubblewrap",
+            SandboxExecutor::Firejail { .. } => "firejail",
             SandboxExecutor::AppLevel => "app-level",
         }
     }
 
     /// Whether this executor supports shell operators (pipes, redirects, etc.).
     pub fn supports_shell(&self) -> bool {
-        match self {
-            #[cfg(feature = "sandbox")]
-            SandboxExecutor::Kernel => true,
-            SandboxExecutor::AppLevel => false,
-        }
+        self.capabilities().shell
     }
 
     /// Execute a command string in the sandbox.
@@ -253,6 +415,22 @@ impl SandboxExecutor {
                 .await
                 .map_err(|e| format!("Sandbox task failed: {}", e))?
             }
+            SandboxExecutor::Bubblewrap { program } => {
+                let mut cmd = tokio::process::Command::new(program);
+                cmd.args(bubblewrap_args(command, mounts, path_policy, read_only)?);
+                let mut result = run_with_timeout(cmd, timeout_secs, stdin_data).await?;
+                // bwrap reports its own setup failures as "bwrap: ..." with exit 1
+                if result.exit_code == 1 && result.stderr.starts_with("bwrap: ") {
+                    result.sandbox_error = result.stderr.lines().next().map(str::to_string);
+                }
+                Ok(result)
+            }
+            SandboxExecutor::Firejail { program } => {
+                let mut cmd = tokio::process::Command::new(program);
+                cmd.args(firejail_args(command, mounts, path_policy, read_only)?);
+                cmd.current_dir(mounts.work_dir());
+                run_with_timeout(cmd, timeout_secs, stdin_data).await
+            }
             SandboxExecutor::AppLevel => {
                 execute_app_level(command, mounts, timeout_secs, stdin_data).await
             }
@@ -412,8 +590,12 @@ pub fn execute_kernel(
         .collect();
     env_vars.push(("PATH", &policy.path_value));
 
+    let work_dir = mounts.work_dir();
+    let work_str = work_dir
+        .to_str()
+        .ok_or("Working directory is not valid UTF-8")?;
     let mut cmd = container.command("/bin/sh");
-    let cmd = cmd.arg("-c").arg(command).current_dir(root_str);
+    let cmd = cmd.arg("-c").arg(command).current_dir(work_str);
     for (key, value) in &env_vars {
         cmd.env(key, value);
     }
@@ -425,16 +607,14 @@ pub fn execute_kernel(
     if let Some(data) = stdin_data {
         let tmp = mounts.tmp_dir().join(".stdin_pipe");
         std::fs::write(&tmp, data).map_err(|e| format!("Failed to write stdin data: {}", e))?;
-        effective_command = format!(
-            "cat {} | /bin/sh -c {}",
-            tmp.display(),
-            shell_escape(command)
-        );
+        // The session tmp dir is mounted at /tmp inside the container, so the
+        // host path doesn't exist there
+        effective_command = format!("cat /tmp/.stdin_pipe | /bin/sh -c {}", shell_escape(command));
         _stdin_tmpfile = Some(tmp);
 
         // Re-build the command with the piped version
         let mut cmd2 = container.command("/bin/sh");
-        let cmd2 = cmd2.arg("-c").arg(&effective_command).current_dir(root_str);
+        let cmd2 = cmd2.arg("-c").arg(&effective_command).current_dir(work_str);
         for (key, value) in &env_vars {
             cmd2.env(key, value);
         }
@@ -492,17 +672,338 @@ fn classify_hakoniwa_status(output: &hakoniwa::Output) -> (bool, Option<String>)
 }
 
 /// Shell-escape a string for use in a shell command.
-#[cfg(feature = "sandbox")]
-fn shell_escape(s: &str) -> String {
+pub(crate) fn shell_escape(s: &str) -> String {
     format!("'{}'", s.replace('\'', "'\\''"))
 }
 
+/// Cached `bwrap` probe result (path when usable).
+static BWRAP_PROBE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Cached `firejail` probe result (path when usable).
+static FIREJAIL_PROBE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Find `bwrap` on PATH and check it can create namespaces here.
+fn probe_bubblewrap() -> Option<PathBuf> {
+    BWRAP_PROBE
+        .get_or_init(|| {
+            let program = resolve_program("bwrap").ok()?;
+            let args = [
+                "--die-with-parent",
+                "--unshare-all",
+                "--share-net",
+                "--ro-bind",
+                "/",
+                "/",
+                "--proc",
+                "/proc",
+                "--dev",
+                "/dev",
+                "/bin/true",
+            ];
+            probe_command(&program, &args).then_some(program)
+        })
+        .clone()
+}
+
+/// Find `firejail` on PATH and check it can start a sandbox here.
+fn probe_firejail() -> Option<PathBuf> {
+    FIREJAIL_PROBE
+        .get_or_init(|| {
+            let program = resolve_program("firejail").ok()?;
+            let args = ["--quiet", "--noprofile", "/bin/true"];
+            probe_command(&program, &args).then_some(program)
+        })
+        .clone()
+}
+
+/// Run a probe command, treating anything but a clean exit within 5s as failure.
+fn probe_command(program: &Path, args: &[&str]) -> bool {
+    let child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return false;
+    };
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(20)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    }
+}
+
+/// One mount in the bubblewrap layout, keyed by its destination.
+enum BwrapMount {
+    RoBind(PathBuf),
+    /// Read-only bind that is skipped when the source is missing.
+    RoBindTry(PathBuf),
+    Bind(PathBuf),
+    Tmpfs,
+    /// Hide a single file by binding /dev/null over it.
+    Hide,
+    Proc,
+    Dev,
+}
+
+/// Build the `bwrap` arguments for a command, mirroring the hakoniwa layout
+/// in `execute_kernel`.
+///
+/// bwrap applies mounts in argument order, so they are sorted by destination
+/// (parents first) — otherwise a read-only `$HOME` mount would cover a
+/// project root beneath it.
+fn bubblewrap_args(
+    command: &str,
+    mounts: &SandboxMounts,
+    policy: &SandboxPathPolicy,
+    read_only: bool,
+) -> Result<Vec<String>, String> {
+    let root = mounts.project_root();
+    let work_dir = mounts.work_dir();
+    let work_str = work_dir
+        .to_str()
+        .ok_or("Working directory is not valid UTF-8")?;
+
+    let mut layout: Vec<(PathBuf, BwrapMount)> = Vec::new();
+    for dir in ["/bin", "/usr", "/lib", "/etc", "/sbin", "/lib64", "/lib32"] {
+        layout.push((dir.into(), BwrapMount::RoBindTry(dir.into())));
+    }
+    if let Ok(real) = std::fs::canonicalize("/etc/resolv.conf") {
+        if let Some(parent) = real.parent() {
+            if !is_under_system_prefix(&parent.to_string_lossy()) {
+                layout.push((
+                    parent.to_path_buf(),
+                    BwrapMount::RoBind(parent.to_path_buf()),
+                ));
+            }
+        }
+    }
+    layout.push(("/proc".into(), BwrapMount::Proc));
+    layout.push(("/dev".into(), BwrapMount::Dev));
+    layout.push((
+        "/tmp".into(),
+        BwrapMount::Bind(mounts.tmp_dir().to_path_buf()),
+    ));
+    layout.push((
+        root.clone(),
+        if read_only {
+            BwrapMount::RoBind(root.clone())
+        } else {
+            BwrapMount::Bind(root.clone())
+        },
+    ));
+
+    let extra_mounts = mounts.list_extra();
+    let extra_mount_set: HashSet<&Path> =
+        extra_mounts.iter().map(|m| m.host_path.as_path()).collect();
+    for mount in &extra_mounts {
+        layout.push((
+            mount.host_path.clone(),
+            BwrapMount::RoBind(mount.host_path.clone()),
+        ));
+    }
+    for dir in &policy.ro_mounts {
+        if dir == root || extra_mount_set.contains(dir.as_path()) {
+            continue;
+        }
+        layout.push((dir.clone(), BwrapMount::RoBind(dir.clone())));
+    }
+    for path in &policy.tmpfs_mounts {
+        let kind = if path.is_dir() {
+            BwrapMount::Tmpfs
+        } else {
+            BwrapMount::Hide
+        };
+        layout.push((path.clone(), kind));
+    }
+    layout.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut args: Vec<String> = [
+        "--die-with-parent",
+        "--new-session",
+        "--unshare-all",
+        "--share-net",
+    ]
+    .map(String::from)
+    .into();
+    for (dest, mount) in layout {
+        let dest = dest.to_string_lossy().into_owned();
+        match mount {
+            BwrapMount::RoBind(src) => {
+                args.extend(["--ro-bind".into(), src.to_string_lossy().into_owned(), dest])
+            }
+            BwrapMount::RoBindTry(src) => args.extend([
+                "--ro-bind-try".into(),
+                src.to_string_lossy().into_owned(),
+                dest,
+            ]),
+            BwrapMount::Bind(src) => {
+                args.extend(["--bind".into(), src.to_string_lossy().into_owned(), dest])
+            }
+            BwrapMount::Tmpfs => args.extend(["--tmpfs".into(), dest]),
+            BwrapMount::Hide => args.extend(["--ro-bind".into(), "/dev/null".into(), dest]),
+            BwrapMount::Proc => args.extend(["--proc".into(), dest]),
+            BwrapMount::Dev => args.extend(["--dev".into(), dest]),
+        }
+    }
+
+    args.push("--clearenv".into());
+    for (key, value) in &policy.env_vars {
+        args.extend(["--setenv".into(), key.clone(), value.clone()]);
+    }
+    args.extend(["--setenv".into(), "PATH".into(), policy.path_value.clone()]);
+    args.extend(["--chdir".into(), work_str.into()]);
+    args.extend(["/bin/sh".into(), "-c".into(), command.into()]);
+    Ok(args)
+}
+
+/// Build the `firejail` arguments for a command.
+///
+/// Firejail cannot bind the session scratch dir over `/tmp`, so `/tmp`
+/// references in the command and environment are rewritten to it instead.
+fn firejail_args(
+    command: &str,
+    mounts: &SandboxMounts,
+    policy: &SandboxPathPolicy,
+    read_only: bool,
+) -> Result<Vec<String>, String> {
+    let root_str = mounts
+        .project_root()
+        .to_str()
+        .ok_or("Project root is not valid UTF-8")?;
+    let tmp_str = mounts
+        .tmp_dir()
+        .to_str()
+        .ok_or("Instance /tmp path is not valid UTF-8")?;
+
+    let mut args: Vec<String> = [
+        "--quiet",
+        "--noprofile",
+        "--noroot",
+        "--caps.drop=all",
+        "--seccomp",
+        "--private-dev",
+        "--read-only=/",
+    ]
+    .map(String::from)
+    .into();
+    args.push(format!("--read-write={}", tmp_str));
+    if !read_only {
+        args.push(format!("--read-write={}", root_str));
+    }
+    for path in &policy.tmpfs_mounts {
+        args.push(format!("--blacklist={}", path.display()));
+    }
+    for (key, value) in &policy.env_vars {
+        args.push(format!(
+            "--env={}={}",
+            key,
+            remap_tmp_in_token(value, tmp_str)
+        ));
+    }
+    args.push(format!("--env=PATH={}", policy.path_value));
+    args.extend([
+        "/bin/sh".into(),
+        "-c".into(),
+        remap_tmp_in_command(command, tmp_str),
+    ]);
+    Ok(args)
+}
+
+/// Rewrite `/tmp` path references in a shell command to the session temp dir.
+///
+/// Only whole path components are rewritten: `/tmp` must start a word (or
+/// follow `=`, a quote, or a shell operator) and end at `/` or a word boundary.
+fn remap_tmp_in_command(command: &str, session_tmp: &str) -> String {
+    const BEFORE: &[u8] = b" \t\n'\"=(<>:;|&`";
+    const AFTER: &[u8] = b"/ \t\n'\";)<>|&`";
+
+    let bytes = command.as_bytes();
+    let mut out = String::with_capacity(command.len());
+    let mut last = 0;
+    for (i, _) in command.match_indices("/tmp") {
+        let starts_word = i == 0 || BEFORE.contains(&bytes[i - 1]);
+        let ends_word = bytes.get(i + 4).is_none_or(|b| AFTER.contains(b));
+        if starts_word && ends_word {
+            out.push_str(&command[last..i]);
+            out.push_str(session_tmp);
+            last = i + 4;
+        }
+    }
+    out.push_str(&command[last..]);
+    out
+}
+
+/// Run a prepared process with optional stdin and a timeout.
+///
+/// The child is killed if the timeout fires.
+async fn run_with_timeout(
+    mut cmd: tokio::process::Command,
+    timeout_secs: u64,
+    stdin_data: Option<&str>,
+) -> Result<CommandResult, String> {
+    cmd.stdout(std::process::Stdio::piped());
+    cmd.stderr(std::process::Stdio::piped());
+    cmd.stdin(if stdin_data.is_some() {
+        std::process::Stdio::piped()
+    } else {
+        std::process::Stdio::null()
+    });
+    cmd.kill_on_drop(true);
+
+    let start = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to spawn command: {}", e))?;
+    if let (Some(data), Some(mut stdin)) = (stdin_data, child.stdin.take()) {
+        use tokio::io::AsyncWriteExt;
+        let _ = stdin.write_all(data.as_bytes()).await;
+        drop(stdin);
+    }
+    let result =
+        tokio::time::timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await;
+    let duration = start.elapsed();
+
+    match result {
+        Ok(Ok(output)) => Ok(CommandResult {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.status.code().unwrap_or(-1),
+            timed_out: false,
+            sandbox_error: None,
+            duration,
+        }),
+        Ok(Err(e)) => Err(format!("Failed to execute command: {}", e)),
+        Err(_) => Ok(CommandResult {
+            stdout: String::new(),
+            stderr: format!("Command timed out after {} seconds", timeout_secs),
+            exit_code: -1,
+            timed_out: true,
+            sandbox_error: None,
+            duration,
+        }),
+    }
+}
+
 /// Execute a command using app-level sandboxing (no kernel isolation).
 ///
 /// This is significantly more restricted:
 /// - No pipes, redirects, or shell operators
 /// - Direct exec only (no shell)
 /// - Only session-tier commands allowed (enforced by caller)
+/// - Path arguments of write-capable commands must not escape the project
+///   root or session tmp dir through symlinks
+///
+/// The kernel sandbox needs no such check: paths outside the mounts do not
+/// exist inside the namespace, so an escaping symlink dangles.
 async fn execute_app_level(
     command: &str,
     mounts: &Arc<SandboxMounts>,
@@ -536,63 +1037,56 @@ async fn execute_app_level(
     let program = &tokens[0];
     let args = &tokens[1..];
 
+    // Without a mount namespace, a symlink inside the project root that
+    // points elsewhere would let write commands reach outside it.
+    if is_write_capable(program) {
+        check_write_args(args, mounts)?;
+    }
+
     // Resolve the program path
     let program_path = resolve_program(program)?;
 
     let mut cmd = tokio::process::Command::new(&program_path);
     cmd.args(args);
-    cmd.current_dir(mounts.project_root());
+    cmd.current_dir(mounts.work_dir());
     cmd.env("HOME", tmp_str);
     cmd.env("TMPDIR", tmp_str);
     cmd.env("TERM", "dumb");
     cmd.env("GIT_TERMINAL_PROMPT", "0");
     cmd.env("LC_ALL", "C.UTF-8");
 
-    // Capture output
-    cmd.stdout(std::process::Stdio::piped());
-    cmd.stderr(std::process::Stdio::piped());
+    run_with_timeout(cmd, timeout_secs, stdin_data).await
+}
 
-    if stdin_data.is_some() {
-        cmd.stdin(std::process::Stdio::piped());
-    }
+/// Whether a command may write to its path arguments.
+///
+/// Anything not classified as read-only counts, plus `tee`, which is
+/// session-tier but writes to every file it is given.
+fn is_write_capable(program: &str) -> bool {
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(program);
+    name == "tee" || permissions::default_tier(name) != permissions::Tier::Session
+}
 
-    let start = Instant::now();
-    let result = if let Some(data) = stdin_data {
-        // Spawn, write to stdin, then wait
-        let child = cmd
-            .spawn()
-            .map_err(|e| format!("Failed to spawn command: {}", e))?;
-        let mut child = child;
-        if let Some(mut stdin) = child.stdin.take() {
-            use tokio::io::AsyncWriteExt;
-            let _ = stdin.write_all(data.as_bytes()).await;
-            drop(stdin);
+/// Reject path arguments that escape a writable root through a symlink.
+fn check_write_args(args: &[String], mounts: &SandboxMounts) -> Result<(), String> {
+    for arg in args {
+        let value = match arg.strip_prefix('-') {
+            // `--output=path` style: check the value; bare flags are skipped
+            Some(flag) => match flag.split_once('=') {
+                Some((_, value)) => value,
+                None => continue,
+            },
+            None => arg.as_str(),
+        };
+        if value.is_empty() {
+            continue;
         }
-        tokio::time::timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await
-    } else {
-        tokio::time::timeout(Duration::from_secs(timeout_secs), cmd.output()).await
-    };
-    let duration = start.elapsed();
-
-    match result {
-        Ok(Ok(output)) => Ok(CommandResult {
-            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
-            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
-            exit_code: output.status.code().unwrap_or(-1),
-            timed_out: false,
-            sandbox_error: None,
-            duration,
-        }),
-        Ok(Err(e)) => Err(format!("Failed to execute command: {}", e)),
-        Err(_) => Ok(CommandResult {
-            stdout: String::new(),
-            stderr: format!("Command timed out after {} seconds", timeout_secs),
-            exit_code: -1,
-            timed_out: true,
-            sandbox_error: None,
-            duration: start.elapsed(),
-        }),
+        mounts.resolve_write_path(Path::new(value))?;
     }
+    Ok(())
 }
 
 /// Rewrite `/tmp` references in a single command token to point at the session temp dir.
@@ -664,6 +1158,98 @@ mod tests {
         let _ = executor.mode_name();
     }
 
+    #[test]
+    fn test_backend_order_parsing() {
+        assert_eq!(
+            SandboxBackend::parse_order(&[]).unwrap(),
+            SandboxBackend::DEFAULT_ORDER.to_vec()
+        );
+        assert_eq!(
+            SandboxBackend::parse_order(&["bwrap".into(), "Firejail".into()]).unwrap(),
+            vec![SandboxBackend::Bubblewrap, SandboxBackend::Firejail]
+        );
+        assert!(SandboxBackend::parse_order(&["gvisor".into()]).is_err());
+    }
+
+    #[test]
+    fn test_detect_with_falls_back_to_app_level() {
+        let executor = SandboxExecutor::detect_with(&[]);
+        assert_eq!(executor.backend(), SandboxBackend::AppLevel);
+        assert!(!executor.supports_shell());
+        assert_eq!(executor.capabilities().summary(), "none");
+
+        let executor = SandboxExecutor::detect_with(&[SandboxBackend::AppLevel]);
+        assert_eq!(executor.mode_name(), "app-level");
+    }
+
+    #[test]
+    fn test_bubblewrap_args_mount_parents_first() {
+        let home = tempfile::TempDir::new().unwrap();
+        let root = home.path().join("project");
+        let ssh = home.path().join(".ssh");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&ssh).unwrap();
+        let mounts = SandboxMounts::new(root.clone()).unwrap();
+        let policy = SandboxPathPolicy {
+            path_value: "/usr/bin:/bin".into(),
+            ro_mounts: vec![home.path().to_path_buf()],
+            tmpfs_mounts: vec![ssh.clone()],
+            env_vars: vec![("TMPDIR".into(), "/tmp".into())],
+        };
+
+        let args = bubblewrap_args("echo hi", &mounts, &policy, false).unwrap();
+        let pos = |needle: &[&str]| {
+            args.windows(needle.len())
+                .position(|w| w.iter().zip(needle).all(|(a, b)| a == b))
+                .unwrap_or_else(|| panic!("missing {:?} in {:?}", needle, args))
+        };
+        let home_str = home.path().to_str().unwrap();
+        let root_str = root.to_str().unwrap();
+        let tmp_str = mounts.tmp_dir().to_str().unwrap();
+
+        let home_mount = pos(&["--ro-bind", home_str, home_str]);
+        let root_mount = pos(&["--bind", root_str, root_str]);
+        let ssh_mount = pos(&["--tmpfs", ssh.to_str().unwrap()]);
+        assert!(home_mount < root_mount && home_mount < ssh_mount);
+        pos(&["--bind", tmp_str, "/tmp"]);
+        pos(&["--setenv", "PATH", "/usr/bin:/bin"]);
+        assert_eq!(&args[args.len() - 3..], ["/bin/sh", "-c", "echo hi"]);
+
+        let args = bubblewrap_args("true", &mounts, &policy, true).unwrap();
+        assert!(args
+            .windows(3)
+            .any(|w| w == ["--ro-bind", root_str, root_str]));
+    }
+
+    #[test]
+    fn test_firejail_args_remap_tmp() {
+        let root = tempfile::TempDir::new().unwrap();
+        let mounts = SandboxMounts::new(root.path().to_path_buf()).unwrap();
+        let policy = SandboxPathPolicy::system_only();
+        let tmp_str = mounts.tmp_dir().to_str().unwrap().to_string();
+
+        let args = firejail_args("ls /tmp/out > /tmp/list", &mounts, &policy, true).unwrap();
+        assert!(args.contains(&format!("--read-write={}", tmp_str)));
+        assert!(!args.contains(&format!("--read-write={}", root.path().display())));
+        assert!(args.contains(&format!("--env=TMPDIR={}", tmp_str)));
+        assert_eq!(
+            args.last().unwrap(),
+            &format!("ls {0}/out > {0}/list", tmp_str)
+        );
+    }
+
+    #[test]
+    fn test_remap_tmp_in_command() {
+        assert_eq!(remap_tmp_in_command("cat /tmp/a", "/s"), "cat /s/a");
+        assert_eq!(remap_tmp_in_command("cd /tmp && ls", "/s"), "cd /s && ls");
+        assert_eq!(remap_tmp_in_command("x=\"/tmp/b\"", "/s"), "x=\"/s/b\"");
+        // Not a /tmp path component
+        assert_eq!(
+            remap_tmp_in_command("ls /tmpfiles /var/tmp", "/s"),
+            "ls /tmpfiles /var/tmp"
+        );
+    }
+
     #[test]
     fn test_resolve_program_ls() {
         let path = resolve_program("ls").unwrap();
@@ -693,6 +1279,23 @@ mod tests {
         assert!(result.unwrap_err().contains("not supported"));
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_app_level_rejects_symlink_escape_on_write() {
+        let root = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("escape")).unwrap();
+        let mounts = Arc::new(SandboxMounts::new(root.path().to_path_buf()).unwrap());
+
+        let result = execute_app_level("touch escape/pwned", &mounts, 10, None).await;
+        assert!(result.unwrap_err().contains("escapes"));
+        assert!(!outside.path().join("pwned").exists());
+
+        // Read-only commands may still traverse the link
+        let result = execute_app_level("ls escape", &mounts, 10, None).await;
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_remap_tmp_exact() {
         assert_eq!(remap_tmp_in_token("/tmp", "/sess/tmp"), "/sess/tmp");
@@ -763,6 +1366,22 @@ mod tests {
         assert_eq!(result.exit_code, 0);
     }
 
+    #[tokio::test]
+    async fn test_stdin_reaches_sandboxed_command() {
+        let executor = SandboxExecutor::detect();
+        if !executor.supports_shell() {
+            return;
+        }
+        let mounts = Arc::new(SandboxMounts::new(std::env::current_dir().unwrap()).unwrap());
+        let policy = SandboxPathPolicy::from_host_env(&[]);
+        let result = executor
+            .execute("cat", &mounts, 10, &policy, Some("piped input"), false)
+            .await
+            .unwrap();
+        assert_eq!(result.stdout, "piped input");
+        assert_eq!(result.exit_code, 0);
+    }
+
     #[test]
     fn test_path_policy_filters_system_dirs() {
         // Test the filtering logic directly without mutating the process env
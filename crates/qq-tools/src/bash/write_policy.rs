@@ -0,0 +1,724 @@
+//! Pre-execution policy checks for write operations.
+//!
+//! Permission tiers decide by command name: `rm` needs approval, `cat` does
+//! not. They can't express "never touch `.env`" or "ask before anything under
+//! `infra/`". Before `run` executes a command that writes, each configured
+//! [`WriteClassifier`] sees the command and the paths it targets and returns a
+//! [`WriteVerdict`]; the strictest verdict wins. `Ask` forces an approval
+//! prompt even for commands promoted to session tier, and `Block` refuses the
+//! command outright.
+//!
+//! Two classifiers are built in:
+//! - [`WritePolicy`]: glob rules matched against target paths
+//! - [`ModelWriteClassifier`]: asks a (cheap) model to judge the command
+//!   against policies written in prose, for what globs can't see (scripts,
+//!   `python -c`, `find -delete`)
+//!
+//! A command writes if it has an output redirect, runs `tee`, or runs a
+//! command that isn't read-only by default (see [`default_tier`]). Every
+//! path-like argument of such a command counts as a target, so a write
+//! command that only reads a protected path (`cp .env backup`) still trips
+//! the rule.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use qq_core::{CompletionRequest, Message, Provider};
+
+use super::parse::{self, SUBCOMMAND_TOOLS};
+use super::permissions::{default_tier, Tier};
+
+/// Entries visited when checking what a directory target contains. Larger
+/// trees are only checked by their own path.
+const MAX_WALK_ENTRIES: usize = 20_000;
+
+/// A command that writes, as seen by a [`WriteClassifier`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOperation {
+    /// The full command line
+    pub command: String,
+    /// Command names in the pipeline (e.g. `["git-add", "rm"]`)
+    pub commands: Vec<String>,
+    /// Paths the command writes or passes to a write command, relative to
+    /// the project root (absolute when outside it)
+    pub targets: Vec<String>,
+}
+
+/// Outcome of a write policy check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteVerdict {
+    Allow,
+    /// Require user approval, with the reason shown in the prompt
+    Ask(String),
+    /// Refuse the command
+    Block(String),
+}
+
+impl WriteVerdict {
+    fn severity(&self) -> u8 {
+        match self {
+            WriteVerdict::Allow => 0,
+            WriteVerdict::Ask(_) => 1,
+            WriteVerdict::Block(_) => 2,
+        }
+    }
+
+    /// The stricter of two verdicts (the first on a tie).
+    pub fn max(self, other: WriteVerdict) -> WriteVerdict {
+        if other.severity() > self.severity() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Scores a proposed write before it runs.
+#[async_trait]
+pub trait WriteClassifier: Send + Sync {
+    async fn classify(&self, op: &WriteOperation) -> WriteVerdict;
+}
+
+/// Run `classifiers` in order and return the strictest verdict, stopping at
+/// the first `Block`.
+pub async fn classify_write(
+    classifiers: &[Arc<dyn WriteClassifier>],
+    op: &WriteOperation,
+) -> WriteVerdict {
+    let mut verdict = WriteVerdict::Allow;
+    for classifier in classifiers {
+        verdict = verdict.max(classifier.classify(op).await);
+        if matches!(verdict, WriteVerdict::Block(_)) {
+            break;
+        }
+    }
+    verdict
+}
+
+// =============================================================================
+// Target extraction
+// =============================================================================
+
+/// The write operation in `command`, or `None` if nothing in it writes.
+///
+/// `commands` are the pipeline's command names from
+/// [`parse::extract_commands`]. Relative paths are taken from `cwd`, the
+/// directory the command runs in; targets are reported relative to `root`.
+pub fn write_operation(
+    command: &str,
+    commands: &[String],
+    root: &Path,
+    cwd: &Path,
+) -> Option<WriteOperation> {
+    let mut writes = false;
+    let mut targets: Vec<String> = Vec::new();
+    for segment in parse::split_pipeline(command.trim()) {
+        let name = parse::extract_first_command(&segment);
+        if name.is_empty() {
+            continue;
+        }
+        let write_command = name == "tee" || default_tier(&name) != Tier::Session;
+        let (args, redirects) = segment_words(&segment);
+        if !write_command && redirects.is_empty() {
+            continue;
+        }
+        writes = true;
+        let mut paths = redirects;
+        if write_command {
+            paths.extend(args.into_iter().filter(|a| !a.starts_with('-')));
+        }
+        for path in paths {
+            let target = normalize(&path, root, cwd);
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+    writes.then(|| WriteOperation {
+        command: command.to_string(),
+        commands: commands.to_vec(),
+        targets,
+    })
+}
+
+/// Split one pipeline segment into the command's arguments (after the
+/// command and subcommand) and its output redirect targets.
+fn segment_words(segment: &str) -> (Vec<String>, Vec<String>) {
+    // A heredoc body is data, not arguments
+    let line = if segment.contains("<<") {
+        segment.lines().next().unwrap_or_default().to_string()
+    } else {
+        segment.replace('\n', " ")
+    };
+    let tokens = parse::tokenize(&line)
+        .unwrap_or_else(|_| line.split_whitespace().map(String::from).collect());
+
+    let mut args = Vec::new();
+    let mut redirects = Vec::new();
+    let mut words = 0;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        if let Some(rest) = redirect_target(&token) {
+            let target = if rest.is_empty() {
+                tokens.next()
+            } else {
+                Some(rest)
+            };
+            if let Some(target) = target {
+                if !target.starts_with('&') && !target.starts_with("/dev/") {
+                    redirects.push(target);
+                }
+            }
+            continue;
+        }
+        if token == "<" || token == "<<<" {
+            tokens.next();
+            continue;
+        }
+        if token.starts_with('<') {
+            continue;
+        }
+        // Leading VAR=value assignments, then the command (and subcommand)
+        if words == 0 && is_assignment(&token) {
+            continue;
+        }
+        words += 1;
+        if words == 1 {
+            let base = token.rsplit('/').next().unwrap_or(&token);
+            if SUBCOMMAND_TOOLS.contains(&base)
+                && tokens.peek().is_some_and(|next| !next.starts_with('-'))
+            {
+                tokens.next();
+            }
+            continue;
+        }
+        args.push(token);
+    }
+    (args, redirects)
+}
+
+/// For an output redirect token (`>`, `>>`, `2>`, `&>`, `>file`, ...), the
+/// target attached to it (empty when it is the next token).
+pub(super) fn redirect_target(token: &str) -> Option<String> {
+    let rest = token
+        .strip_prefix('&')
+        .or_else(|| token.strip_prefix(|c: char| c.is_ascii_digit()))
+        .unwrap_or(token);
+    let rest = rest.strip_prefix('>')?;
+    let rest = rest
+        .strip_prefix('>')
+        .or_else(|| rest.strip_prefix('|'))
+        .unwrap_or(rest);
+    Some(rest.to_string())
+}
+
+pub(super) fn is_assignment(token: &str) -> bool {
+    token.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// `path` (taken from `cwd`) relative to `root` with `.` and `..` resolved
+/// lexically, or the absolute path when it lies outside `root`.
+pub(super) fn normalize(path: &str, root: &Path, cwd: &Path) -> String {
+    let joined = cwd.join(path);
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    match resolved.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => resolved.to_string_lossy().into_owned(),
+    }
+}
+
+// =============================================================================
+// Rule engine
+// =============================================================================
+
+/// What a matching [`WriteRule`] does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteAction {
+    /// Require approval
+    Ask,
+    /// Refuse the command
+    Block,
+}
+
+/// A path rule, e.g. `{ path = "/infra", action = "ask" }`.
+///
+/// Paths follow `.gitignore` conventions: a pattern without a `/` (`.env`,
+/// `*.pem`) matches a file or directory at any depth; one with a `/` at the
+/// start or in the middle is anchored at the project root (`/infra`,
+/// `deploy/*.yaml`). `*` and `?` match within a path component, `**` across
+/// components. A match on a directory covers everything inside it.
+/// Anchored rules never match targets outside the project root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WriteRule {
+    pub path: String,
+    pub action: WriteAction,
+    /// Shown to the user and the model instead of the default message
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl WriteRule {
+    /// A rule that makes every write to `pattern` ask for approval, for
+    /// paths that should never change without the user's say.
+    pub fn protected(pattern: impl Into<String>) -> Self {
+        let path = pattern.into();
+        Self {
+            reason: Some(format!("'{}' is a protected path", path)),
+            path,
+            action: WriteAction::Ask,
+        }
+    }
+
+    /// Whether the rule covers `target` (a path from [`WriteOperation`]).
+    fn matches(&self, target: &str) -> bool {
+        let pattern = self.path.trim_end_matches('/');
+        let parts: Vec<&str> = target.split('/').filter(|p| !p.is_empty()).collect();
+        if let Some(anchored) = pattern
+            .strip_prefix('/')
+            .or_else(|| pattern.contains('/').then_some(pattern))
+        {
+            if target.starts_with('/') {
+                return false;
+            }
+            let pattern: Vec<&str> = anchored.split('/').filter(|p| !p.is_empty()).collect();
+            // The target or one of its ancestors
+            (1..=parts.len()).any(|n| glob_components(&pattern, &parts[..n]))
+        } else {
+            parts.iter().any(|part| glob_component(pattern, part))
+        }
+    }
+}
+
+/// Glob rules over write targets.
+#[derive(Debug, Clone)]
+pub struct WritePolicy {
+    rules: Vec<WriteRule>,
+    root: PathBuf,
+}
+
+impl WritePolicy {
+    pub fn new(rules: Vec<WriteRule>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            rules,
+            root: root.into(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Verdict for one target. A directory target (`rm -rf src`) is also
+    /// checked against everything inside it.
+    fn check_target(&self, target: &str) -> WriteVerdict {
+        let mut verdict = self.check_path(target);
+        if verdict.severity() < 2 {
+            let path = if Path::new(target).is_absolute() {
+                PathBuf::from(target)
+            } else {
+                self.root.join(target)
+            };
+            if path.is_dir() {
+                let mut visited = 0;
+                self.walk(&path, target, &mut visited, &mut verdict);
+            }
+        }
+        verdict
+    }
+
+    fn check_path(&self, target: &str) -> WriteVerdict {
+        let mut verdict = WriteVerdict::Allow;
+        for rule in self.rules.iter().filter(|r| r.matches(target)) {
+            let reason = rule
+                .reason
+                .clone()
+                .unwrap_or_else(|| format!("'{}' matches write policy '{}'", target, rule.path));
+            verdict = verdict.max(match rule.action {
+                WriteAction::Ask => WriteVerdict::Ask(reason),
+                WriteAction::Block => WriteVerdict::Block(reason),
+            });
+        }
+        verdict
+    }
+
+    fn walk(&self, dir: &Path, prefix: &str, visited: &mut usize, verdict: &mut WriteVerdict) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            *visited += 1;
+            if *visited > MAX_WALK_ENTRIES || verdict.severity() == 2 {
+                return;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let child = if prefix == "." {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            *verdict = verdict.clone().max(self.check_path(&child));
+            // Don't follow symlinks out of the tree
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                self.walk(&entry.path(), &child, visited, verdict);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl WriteClassifier for WritePolicy {
+    async fn classify(&self, op: &WriteOperation) -> WriteVerdict {
+        op.targets
+            .iter()
+            .map(|target| self.check_target(target))
+            .fold(WriteVerdict::Allow, WriteVerdict::max)
+    }
+}
+
+/// Match path components against pattern components (`**` spans any number).
+fn glob_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| glob_components(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((part, path_rest)) => {
+                glob_component(first, part) && glob_components(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match one component: `*` is any run of characters, `?` any one character.
+fn glob_component(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// =============================================================================
+// Model classifier
+// =============================================================================
+
+const CLASSIFIER_PROMPT: &str = "\
+You review shell commands an AI coding agent wants to run in a project, \
+before they run. Decide whether the command violates any of these policies:
+
+{policies}
+
+Command:
+{command}
+
+Paths it writes or passes to a write command: {targets}
+
+Reply with exactly one line:
+ALLOW
+ASK: <reason>   (if it might violate a policy, or you can't tell)
+BLOCK: <reason> (if it clearly violates a policy)";
+
+/// Asks a model to judge write commands against prose policies.
+///
+/// Fails closed: a provider error or an unreadable reply asks the user.
+pub struct ModelWriteClassifier {
+    provider: Arc<dyn Provider>,
+    policies: Vec<String>,
+}
+
+impl ModelWriteClassifier {
+    pub fn new(provider: Arc<dyn Provider>, policies: Vec<String>) -> Self {
+        Self { provider, policies }
+    }
+
+    fn prompt(&self, op: &WriteOperation) -> String {
+        let policies: Vec<String> = self.policies.iter().map(|p| format!("- {}", p)).collect();
+        let targets = if op.targets.is_empty() {
+            "(none detected)".to_string()
+        } else {
+            op.targets.join(", ")
+        };
+        CLASSIFIER_PROMPT
+            .replace("{policies}", &policies.join("\n"))
+            .replace("{command}", &op.command)
+            .replace("{targets}", &targets)
+    }
+}
+
+#[async_trait]
+impl WriteClassifier for ModelWriteClassifier {
+    async fn classify(&self, op: &WriteOperation) -> WriteVerdict {
+        if self.policies.is_empty() {
+            return WriteVerdict::Allow;
+        }
+        let mut request = CompletionRequest::new(vec![Message::user(self.prompt(op).as_str())])
+            .with_max_tokens(200);
+        if let Some(model) = self.provider.default_model() {
+            request = request.with_model(model);
+        }
+        match self.provider.complete(request).await {
+            Ok(response) => parse_verdict(&response.message.content.to_string_lossy()),
+            Err(e) => {
+                tracing::warn!(error = %e, "Write classifier failed");
+                WriteVerdict::Ask(format!("write classifier unavailable ({})", e))
+            }
+        }
+    }
+}
+
+/// Parse an `ALLOW` / `ASK: reason` / `BLOCK: reason` reply.
+fn parse_verdict(reply: &str) -> WriteVerdict {
+    let line = reply
+        .lines()
+        .map(|l| l.trim().trim_matches(['*', '`']).trim())
+        .find(|l| !l.is_empty())
+        .unwrap_or_default();
+    let (word, reason) = match line.split_once(':') {
+        Some((word, reason)) => (word.trim(), reason.trim()),
+        None => (line, ""),
+    };
+    let reason = if reason.is_empty() {
+        "flagged by write classifier".to_string()
+    } else {
+        format!("write classifier: {}", reason)
+    };
+    match word.to_ascii_uppercase().as_str() {
+        "ALLOW" => WriteVerdict::Allow,
+        "BLOCK" => WriteVerdict::Block(reason),
+        "ASK" => WriteVerdict::Ask(reason),
+        _ => WriteVerdict::Ask(format!("unclear write classifier reply: {}", line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(command: &str, root: &Path) -> Option<WriteOperation> {
+        let commands = parse::extract_commands(command).unwrap();
+        write_operation(command, &commands, root, root)
+    }
+
+    fn targets(command: &str) -> Vec<String> {
+        op(command, Path::new("/proj"))
+            .map(|o| o.targets)
+            .unwrap_or_default()
+    }
+
+    fn rule(path: &str, action: WriteAction) -> WriteRule {
+        WriteRule {
+            path: path.to_string(),
+            action,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn test_write_targets() {
+        assert_eq!(op("cat .env | grep KEY", Path::new("/proj")), None);
+        assert_eq!(
+            targets("rm -rf ./build /proj/infra/x.tf"),
+            vec!["build", "infra/x.tf"]
+        );
+        assert_eq!(targets("echo hi > out.txt 2>/dev/null"), vec!["out.txt"]);
+        assert_eq!(targets("grep -r x src >>log/a.txt"), vec!["log/a.txt"]);
+        assert_eq!(
+            targets("FOO=1 git add src/a.rs && git rm -q old.rs"),
+            vec!["src/a.rs", "old.rs"]
+        );
+        assert_eq!(
+            targets("cp -r a b; cat x | tee ../copy"),
+            vec!["a", "b", "/copy"]
+        );
+        assert_eq!(
+            targets("cat > notes/new.md <<'EOF'\n> quoted > line\nEOF"),
+            vec!["notes/new.md"]
+        );
+        assert_eq!(op("ls 2>&1", Path::new("/proj")), None);
+
+        // Under /focus, relative paths start from the focus directory
+        let command = "mv a.rs ../web/a.rs";
+        let commands = parse::extract_commands(command).unwrap();
+        let focused = write_operation(
+            command,
+            &commands,
+            Path::new("/proj"),
+            Path::new("/proj/packages/api"),
+        );
+        assert_eq!(
+            focused.unwrap().targets,
+            vec!["packages/api/a.rs", "packages/web/a.rs"]
+        );
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let env = rule(".env", WriteAction::Block);
+        assert!(env.matches(".env"));
+        assert!(env.matches("app/.env"));
+        assert!(!env.matches(".envrc"));
+
+        let infra = rule("/infra", WriteAction::Ask);
+        assert!(infra.matches("infra"));
+        assert!(infra.matches("infra/prod/main.tf"));
+        assert!(!infra.matches("src/infra/a.rs"));
+        assert!(!infra.matches("/infra/a.rs"));
+        assert!(rule("infra/", WriteAction::Ask).matches("src/infra/a.rs"));
+
+        let yaml = rule("deploy/**/*.yaml", WriteAction::Ask);
+        assert!(yaml.matches("deploy/a.yaml"));
+        assert!(yaml.matches("deploy/x/y/a.yaml"));
+        assert!(!yaml.matches("deploy/a.yml"));
+        assert!(rule("*.pem", WriteAction::Block).matches("certs/server.pem"));
+        assert!(glob_component("a*b?c", "aXXbYc"));
+        assert!(!glob_component("a*b?c", "abc"));
+    }
+
+    #[tokio::test]
+    async fn test_policy_verdicts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("app/config")).unwrap();
+        std::fs::write(dir.path().join("app/config/.env"), "KEY=1").unwrap();
+        let policy = WritePolicy::new(
+            vec![
+                rule("/infra", WriteAction::Ask),
+                WriteRule {
+                    reason: Some("never touch .env files".into()),
+                    ..rule(".env", WriteAction::Block)
+                },
+            ],
+            dir.path(),
+        );
+        let verdict = |command: &str| {
+            let op = op(command, dir.path()).unwrap();
+            let policy = policy.clone();
+            async move { policy.classify(&op).await }
+        };
+
+        assert_eq!(verdict("touch src/a.rs").await, WriteVerdict::Allow);
+        assert!(matches!(
+            verdict("sed -i s/a/b/ infra/main.tf").await,
+            WriteVerdict::Ask(_)
+        ));
+        assert_eq!(
+            verdict("echo KEY=2 >> .env").await,
+            WriteVerdict::Block("never touch .env files".into())
+        );
+        // The strictest rule wins
+        assert!(matches!(
+            verdict("rm infra/a .env").await,
+            WriteVerdict::Block(_)
+        ));
+        // Directory targets are checked for what they contain
+        assert!(matches!(
+            verdict("rm -rf app").await,
+            WriteVerdict::Block(_)
+        ));
+        assert_eq!(verdict("rm -rf app/other").await, WriteVerdict::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_protected_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".github/workflows")).unwrap();
+        std::fs::write(dir.path().join(".github/workflows/ci.yml"), "on: push").unwrap();
+        let policy = WritePolicy::new(
+            ["Cargo.lock", ".github/**", "migrations/**"]
+                .into_iter()
+                .map(WriteRule::protected)
+                .collect(),
+            dir.path(),
+        );
+        let verdict = |command: &str| {
+            let op = op(command, dir.path());
+            let policy = policy.clone();
+            async move {
+                match op {
+                    Some(op) => policy.classify(&op).await,
+                    None => WriteVerdict::Allow,
+                }
+            }
+        };
+
+        assert_eq!(verdict("cat Cargo.lock").await, WriteVerdict::Allow);
+        assert_eq!(verdict("touch src/migrations.rs").await, WriteVerdict::Allow);
+        assert_eq!(
+            verdict("echo x > crates/core/Cargo.lock").await,
+            WriteVerdict::Ask("'Cargo.lock' is a protected path".into())
+        );
+        for command in [
+            "sed -i s/a/b/ .github/workflows/ci.yml",
+            "mv migrations/001.sql migrations/002.sql",
+            "rm -rf .github",
+            "rm -rf .",
+        ] {
+            assert!(matches!(verdict(command).await, WriteVerdict::Ask(_)), "{}", command);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_model_classifier() {
+        let provider = Arc::new(qq_core::testing::MockProvider::new());
+        let classifier = ModelWriteClassifier::new(
+            Arc::clone(&provider) as Arc<dyn Provider>,
+            vec!["Never modify CI configuration".into()],
+        );
+        let write = op("rm .github/workflows/ci.yml", Path::new("/proj")).unwrap();
+
+        provider.queue_response("BLOCK: deletes the CI workflow");
+        assert_eq!(
+            classifier.classify(&write).await,
+            WriteVerdict::Block("write classifier: deletes the CI workflow".into())
+        );
+        let prompt = provider.last_request().unwrap().messages[0]
+            .content
+            .to_string_lossy();
+        assert!(prompt.contains("- Never modify CI configuration"));
+        assert!(prompt.contains(".github/workflows/ci.yml"));
+
+        provider.queue_response("**ALLOW**");
+        assert_eq!(classifier.classify(&write).await, WriteVerdict::Allow);
+        provider.queue_response("I think this is fine");
+        assert!(matches!(
+            classifier.classify(&write).await,
+            WriteVerdict::Ask(_)
+        ));
+        provider.queue_error(qq_core::Error::stream("down"));
+        assert!(matches!(
+            classifier.classify(&write).await,
+            WriteVerdict::Ask(_)
+        ));
+    }
+}
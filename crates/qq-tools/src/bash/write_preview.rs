@@ -0,0 +1,237 @@
+//! Diff previews of writes awaiting approval.
+//!
+//! An approval prompt for `cat > src/lib.rs <<'EOF' ...` shows a command
+//! several hundred lines long, and what it changes is hard to judge from
+//! that. [`write_preview`] works out the change ahead of time when the
+//! command spells it out, and returns it as a unified diff for the prompt to
+//! page through:
+//! - a heredoc written by `cat` (or `tee`) to a file, `>>` and `tee -a`
+//!   appending, diffed against the file on disk
+//! - a heredoc patch fed to `patch` or `git apply`, shown as written
+//!
+//! Unquoted heredocs are taken literally, without expanding `$VARS`. Any
+//! other write (`sed -i`, scripts) gets no preview.
+
+use std::path::{Component, Path};
+
+use super::diff_stats::{read_tracked, unified_diff};
+use super::parse;
+use super::write_policy::{normalize, redirect_target};
+
+/// Larger previews are cut with a note.
+const MAX_PREVIEW_BYTES: usize = 512 * 1024;
+
+/// Unified diff of what `command` would write, or `None` if it can't be
+/// worked out without running it.
+///
+/// Relative paths are taken from `cwd`; files outside `root` are not read
+/// and get no preview.
+pub fn write_preview(command: &str, root: &Path, cwd: &Path) -> Option<String> {
+    // Display path, contents before, contents after
+    let mut files: Vec<(String, Option<String>, String)> = Vec::new();
+    let mut patches: Vec<String> = Vec::new();
+    for segment in parse::split_pipeline(command.trim()) {
+        let Some(heredoc) = Heredoc::parse(&segment) else {
+            continue;
+        };
+        let name = parse::extract_first_command(&segment);
+        let Ok(tokens) = parse::tokenize(&heredoc.head) else {
+            continue;
+        };
+        match name.as_str() {
+            "patch" | "git-apply" => {
+                let reverse = tokens.iter().any(|t| t == "-R" || t == "--reverse");
+                if !reverse && heredoc.body.contains("\n@@ ") {
+                    patches.push(heredoc.body);
+                }
+            }
+            "cat" | "tee" => {
+                for (target, append) in write_targets(&name, &tokens) {
+                    let target = normalize(&target, root, cwd);
+                    let Some(path) = inside_root(root, &target) else {
+                        continue;
+                    };
+                    match files.iter_mut().find(|(t, _, _)| *t == target) {
+                        Some((_, _, after)) => stage(after, &heredoc.body, append),
+                        None => {
+                            let before = if path.exists() {
+                                match read_tracked(&path) {
+                                    Some(text) => Some(text),
+                                    None => continue,
+                                }
+                            } else {
+                                None
+                            };
+                            let mut after = before.clone().unwrap_or_default();
+                            stage(&mut after, &heredoc.body, append);
+                            files.push((target, before, after));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut preview: String = files
+        .iter()
+        .map(|(target, before, after)| unified_diff(target, before.as_deref(), Some(after)))
+        .collect();
+    for patch in patches {
+        preview.push_str(&patch);
+    }
+    if preview.is_empty() {
+        return None;
+    }
+    if preview.len() > MAX_PREVIEW_BYTES {
+        let mut cut = MAX_PREVIEW_BYTES;
+        while !preview.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        let cut = preview[..cut].rfind('\n').map_or(cut, |i| i + 1);
+        preview.truncate(cut);
+        preview.push_str("[preview truncated]\n");
+    }
+    Some(preview)
+}
+
+/// A segment's heredoc: the command line without the `<<WORD` operator, and
+/// the body.
+struct Heredoc {
+    head: String,
+    body: String,
+}
+
+impl Heredoc {
+    fn parse(segment: &str) -> Option<Self> {
+        let (first, rest) = segment.split_once('\n')?;
+        let chars: Vec<char> = first.chars().collect();
+        let start = (0..chars.len().saturating_sub(1)).find(|&i| {
+            chars[i] == '<'
+                && chars[i + 1] == '<'
+                && chars.get(i + 2) != Some(&'<')
+                && (i == 0 || chars[i - 1] != '<')
+        })?;
+        let mut after = start + 2;
+        let strip_tabs = chars.get(after) == Some(&'-');
+        if strip_tabs {
+            after += 1;
+        }
+        let (delimiter, consumed) = parse::parse_heredoc_delimiter(&chars, after)?;
+        let head: String = chars[..start]
+            .iter()
+            .chain(&chars[after + consumed..])
+            .collect();
+
+        let mut body = String::new();
+        for line in rest.lines() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let line = if strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if line == delimiter {
+                return Some(Self { head, body });
+            }
+            body.push_str(line);
+            body.push('\n');
+        }
+        // Unterminated: the shell would refuse it
+        None
+    }
+}
+
+/// Files a `cat` or `tee` head writes the heredoc to, and whether it appends.
+fn write_targets(name: &str, tokens: &[String]) -> Vec<(String, bool)> {
+    let mut targets = Vec::new();
+    let tee_append = name == "tee"
+        && tokens.iter().any(|t| {
+            t == "--append" || (t.starts_with('-') && !t.starts_with("--") && t.contains('a'))
+        });
+    let mut tokens = tokens.iter().skip(1);
+    while let Some(token) = tokens.next() {
+        if let Some(rest) = redirect_target(token) {
+            let target = if rest.is_empty() {
+                tokens.next().cloned()
+            } else {
+                Some(rest)
+            };
+            // Only stdout carries the heredoc
+            let stdout =
+                token.starts_with('>') || token.starts_with("1>") || token.starts_with("&>");
+            let target =
+                target.filter(|t| stdout && !t.starts_with('&') && !t.starts_with("/dev/"));
+            if let Some(target) = target {
+                targets.push((target, token.contains(">>")));
+            }
+            continue;
+        }
+        if name == "tee" && !token.starts_with('-') {
+            targets.push((token.clone(), tee_append));
+        }
+    }
+    targets
+}
+
+/// `target` (relative to `root`) as a path, unless it lies outside `root`.
+fn inside_root(root: &Path, target: &str) -> Option<std::path::PathBuf> {
+    let path = root.join(target);
+    let escapes = Path::new(target)
+        .components()
+        .any(|c| c == Component::ParentDir);
+    (!escapes && path.starts_with(root) && !path.is_dir()).then_some(path)
+}
+
+/// Write `body` into `contents`, replacing it unless appending.
+fn stage(contents: &mut String, body: &str, append: bool) {
+    if !append {
+        contents.clear();
+    }
+    contents.push_str(body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heredoc_writes_are_diffed_against_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        std::fs::write(root.join("log.txt"), "one\n").unwrap();
+
+        let command = "cat > lib.rs <<'EOF'\nfn a() {}\nfn c() {}\nEOF\n";
+        let preview = write_preview(command, root, &root.join("src")).unwrap();
+        assert!(preview.starts_with("diff --git a/src/lib.rs b/src/lib.rs\n"));
+        assert!(preview.contains("-fn b() {}\n+fn c() {}\n"));
+
+        let command = "tee -a log.txt notes.md > /dev/null <<-EOF\n\ttwo\n\tEOF";
+        let preview = write_preview(command, root, root).unwrap();
+        assert!(preview.contains("+++ b/log.txt\n@@ -1 +1,2 @@\n one\n+two\n"));
+        assert!(preview.contains("new file mode 100644\n--- /dev/null\n+++ b/notes.md\n"));
+
+        // A second write to the same file builds on the first
+        let command = "tee -a log.txt >> log.txt <<EOF\ntwo\nEOF";
+        let preview = write_preview(command, root, root).unwrap();
+        assert!(preview.contains(" one\n+two\n+two\n"));
+    }
+
+    #[test]
+    fn test_patches_shown_and_other_writes_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let patch = "--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n";
+        let command = format!("git apply <<'PATCH'\n{}PATCH", patch);
+        assert_eq!(write_preview(&command, root, root).as_deref(), Some(patch));
+        let command = format!("patch -p1 -R <<'PATCH'\n{}PATCH", patch);
+        assert_eq!(write_preview(&command, root, root), None);
+
+        assert_eq!(write_preview("sed -i 's/a/b/' x", root, root), None);
+        assert_eq!(write_preview("python3 > out.txt <<EOF\nprint(1)\nEOF", root, root), None);
+        assert_eq!(write_preview("cat > ../x <<EOF\nhi\nEOF", root, root), None);
+        assert_eq!(write_preview("cat > x <<EOF\nunterminated", root, root), None);
+    }
+}
@@ -0,0 +1,530 @@
+//! Date and time tool.
+//!
+//! Models have no clock and guess dates badly. `datetime` gives them the
+//! current time, timezone conversion, and date arithmetic. The same time
+//! expression parser backs `schedule_reminder`.
+
+use async_trait::async_trait;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+/// A timezone accepted by the tool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    /// The host's local timezone.
+    Local,
+    /// An IANA zone such as `Europe/Berlin` (or `UTC`).
+    Named(Tz),
+    /// A fixed offset such as `+05:30`.
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    /// Parse `local`, `UTC`, an IANA name, or an offset like `+05:30` / `-0800`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if s.is_empty() || s.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        if s.eq_ignore_ascii_case("utc") || s == "Z" {
+            return Ok(Zone::Named(Tz::UTC));
+        }
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(Zone::Named(tz));
+        }
+        parse_offset(s)
+            .map(Zone::Fixed)
+            .ok_or_else(|| format!("Unknown timezone '{}'. Use an IANA name (e.g. America/New_York), UTC, local, or an offset like +05:30", s))
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Zone::Local => "local".to_string(),
+            Zone::Named(tz) => tz.name().to_string(),
+            Zone::Fixed(offset) => offset.to_string(),
+        }
+    }
+
+    /// Express an instant in this zone.
+    pub fn at(&self, t: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Zone::Local => t.with_timezone(&Local).fixed_offset(),
+            Zone::Named(tz) => t.with_timezone(tz).fixed_offset(),
+            Zone::Fixed(offset) => t.with_timezone(offset),
+        }
+    }
+
+    /// Interpret a wall-clock time in this zone. Ambiguous times (DST
+    /// fall-back) take the earlier instant; skipped times are an error.
+    fn localize(&self, naive: NaiveDateTime) -> Result<DateTime<Utc>, String> {
+        let local = match self {
+            Zone::Local => Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|t| t.to_utc()),
+            Zone::Named(tz) => tz
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|t| t.to_utc()),
+            Zone::Fixed(offset) => offset
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|t| t.to_utc()),
+        };
+        local.ok_or_else(|| format!("{} does not exist in {} (DST gap)", naive, self.name()))
+    }
+}
+
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60))
+}
+
+/// Parse a duration like `2h30m`, `3d`, `1w 2d`, `90 minutes`, or `-15m`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (sign, body) = match s.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let invalid = || {
+        format!(
+            "Invalid duration '{}'. Examples: 90m, 2h30m, 3d, 1w 2d, -15m",
+            s
+        )
+    };
+
+    let mut total = Duration::zero();
+    let mut chars = body.chars().peekable();
+    let mut seen = false;
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+            number.push(c);
+        }
+        if number.is_empty() {
+            break;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut unit = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+            unit.push(c.to_ascii_lowercase());
+        }
+        let n: i64 = number.parse().map_err(|_| invalid())?;
+        let part = match unit.as_str() {
+            "w" | "wk" | "wks" | "week" | "weeks" => Duration::try_weeks(n),
+            "d" | "day" | "days" => Duration::try_days(n),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Duration::try_hours(n),
+            "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(n),
+            "s" | "sec" | "secs" | "second" | "seconds" => Duration::try_seconds(n),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        total = total.checked_add(&part).ok_or_else(invalid)?;
+        seen = true;
+    }
+    if !seen || chars.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(total * sign)
+}
+
+/// Format a duration as `3d 4h 5m` (seconds only when under a minute).
+pub fn format_duration(d: Duration) -> String {
+    let sign = if d < Duration::zero() { "-" } else { "" };
+    let secs = d.num_seconds().unsigned_abs();
+    let (days, hours, mins) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    let mut parts = Vec::new();
+    if days > 0 {
+        parts.push(format!("{}d", days));
+    }
+    if hours > 0 {
+        parts.push(format!("{}h", hours));
+    }
+    if mins > 0 {
+        parts.push(format!("{}m", mins));
+    }
+    if parts.is_empty() {
+        parts.push(format!("{}s", secs));
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+/// Parse a time expression relative to `now`, reading wall-clock times in `zone`.
+///
+/// Accepts `now`, `today`/`tomorrow`/`yesterday` with an optional `HH:MM`,
+/// RFC 3339 (`2026-03-01T09:00:00Z`), `YYYY-MM-DD[ HH:MM[:SS]]`, `@<unix
+/// seconds>`, and relative offsets (`in 2h`, `+3d`, `-1w`).
+pub fn parse_time(s: &str, zone: Zone, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    let lower = s.to_ascii_lowercase();
+
+    if lower.is_empty() || lower == "now" {
+        return Ok(now);
+    }
+    if let Some(rest) = lower.strip_prefix("in ") {
+        return Ok(now + parse_duration(rest)?);
+    }
+    if lower.starts_with('+') || lower.starts_with('-') {
+        return Ok(now + parse_duration(&lower)?);
+    }
+    if let Some(secs) = lower.strip_prefix('@') {
+        let secs: i64 = secs
+            .parse()
+            .map_err(|_| format!("Invalid unix timestamp '{}'", s))?;
+        return Utc
+            .timestamp_opt(secs, 0)
+            .single()
+            .ok_or_else(|| format!("Invalid unix timestamp '{}'", s));
+    }
+
+    let (day_word, rest) = lower.split_once(' ').unwrap_or((lower.as_str(), ""));
+    let day_offset = match day_word {
+        "today" => Some(0),
+        "tomorrow" => Some(1),
+        "yesterday" => Some(-1),
+        _ => None,
+    };
+    if let Some(offset) = day_offset {
+        let date = zone.at(now).date_naive() + Duration::days(offset);
+        let time = if rest.trim().is_empty() {
+            NaiveTime::MIN
+        } else {
+            parse_clock(rest.trim())
+                .ok_or_else(|| format!("Invalid time of day '{}'", rest.trim()))?
+        };
+        return zone.localize(date.and_time(time));
+    }
+
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.to_utc());
+    }
+    for fmt in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, fmt) {
+            return zone.localize(naive);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return zone.localize(date.and_time(NaiveTime::MIN));
+    }
+
+    Err(format!(
+        "Could not parse time '{}'. Use now, tomorrow 09:00, 2026-03-01 14:30, RFC 3339, @<unix>, or in 2h",
+        s
+    ))
+}
+
+fn parse_clock(s: &str) -> Option<NaiveTime> {
+    ["%H:%M", "%H:%M:%S", "%I:%M%P", "%I%P"]
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(s, fmt).ok())
+}
+
+/// Render an instant in a zone: ISO timestamp, weekday, zone, unix seconds.
+fn describe(t: DateTime<Utc>, zone: Zone) -> String {
+    let local = zone.at(t);
+    format!(
+        "{} ({}, {}, week {}) unix={}",
+        local.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        local.format("%A %B %-d %Y"),
+        zone.name(),
+        local.iso_week().week(),
+        t.timestamp()
+    )
+}
+
+const DATETIME_TOOL_DESC: &str = "\
+Current date/time, timezone conversion, and date arithmetic. Use this instead of \
+guessing dates (\"today\", \"next Friday\", \"in 3 days\").
+
+Actions:
+  - now: current time (optional timezone)
+  - convert: express `time` in `timezone` (wall-clock input is read in `from_timezone`)
+  - add: `time` (default now) plus `duration` (e.g. 3d, 2h30m, -1w)
+  - diff: duration from `time` to `to` (default now)
+
+Times: now, today/tomorrow/yesterday [HH:MM], 2026-03-01 14:30, RFC 3339, @<unix>, in 2h.
+Timezones: IANA names (Europe/Berlin), UTC, local, or offsets (+05:30). Default: local.";
+
+/// Tool for current time, timezone conversion, and date arithmetic.
+pub struct DateTimeTool;
+
+#[derive(Deserialize)]
+struct DateTimeArgs {
+    action: String,
+    #[serde(default)]
+    time: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(default)]
+    duration: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    from_timezone: Option<String>,
+}
+
+impl DateTimeTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn run(&self, args: DateTimeArgs, now: DateTime<Utc>) -> Result<String, String> {
+        let zone = Zone::parse(args.timezone.as_deref().unwrap_or("local"))?;
+        let from_zone = match args.from_timezone.as_deref() {
+            Some(z) => Zone::parse(z)?,
+            None => zone,
+        };
+        let time = |input: Option<&str>, z: Zone| parse_time(input.unwrap_or("now"), z, now);
+
+        match args.action.as_str() {
+            "now" => Ok(describe(now, zone)),
+            "convert" => {
+                let input = args.time.as_deref().ok_or("convert requires `time`")?;
+                let t = time(Some(input), from_zone)?;
+                Ok(format!(
+                    "{}\n= {}",
+                    describe(t, from_zone),
+                    describe(t, zone)
+                ))
+            }
+            "add" => {
+                let duration = args.duration.as_deref().ok_or("add requires `duration`")?;
+                let t = time(args.time.as_deref(), from_zone)? + parse_duration(duration)?;
+                Ok(describe(t, zone))
+            }
+            "diff" => {
+                let input = args.time.as_deref().ok_or("diff requires `time`")?;
+                let from = time(Some(input), from_zone)?;
+                let to = time(args.to.as_deref(), from_zone)?;
+                let d = to - from;
+                Ok(format!(
+                    "{} ({} seconds)",
+                    format_duration(d),
+                    d.num_seconds()
+                ))
+            }
+            other => Err(format!(
+                "Unknown action '{}'. Valid actions: now, convert, add, diff",
+                other
+            )),
+        }
+    }
+}
+
+impl Default for DateTimeTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for DateTimeTool {
+    fn name(&self) -> &str {
+        "datetime"
+    }
+
+    fn description(&self) -> &str {
+        "Get the current date/time, convert between timezones, and do date arithmetic"
+    }
+
+    fn tool_description(&self) -> &str {
+        DATETIME_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "action",
+                    PropertySchema::enum_string(
+                        "Operation to perform",
+                        vec!["now".into(), "convert".into(), "add".into(), "diff".into()],
+                    ),
+                    true,
+                )
+                .add_property(
+                    "time",
+                    PropertySchema::string("Time expression (default: now)"),
+                    false,
+                )
+                .add_property(
+                    "to",
+                    PropertySchema::string("End time for diff (default: now)"),
+                    false,
+                )
+                .add_property(
+                    "duration",
+                    PropertySchema::string("Duration for add, e.g. 3d, 2h30m, -1w"),
+                    false,
+                )
+                .add_property(
+                    "timezone",
+                    PropertySchema::string("Output timezone (default: local)"),
+                    false,
+                )
+                .add_property(
+                    "from_timezone",
+                    PropertySchema::string(
+                        "Timezone for reading wall-clock input times (default: timezone)",
+                    ),
+                    false,
+                ),
+        )
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: DateTimeArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("datetime", format!("Invalid arguments: {}", e)))?;
+        Ok(match self.run(args, Utc::now()) {
+            Ok(text) => ToolOutput::success(text),
+            Err(e) => ToolOutput::error(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed_now() -> DateTime<Utc> {
+        // Friday 2026-03-06 15:30:00 UTC
+        Utc.with_ymd_and_hms(2026, 3, 6, 15, 30, 0).unwrap()
+    }
+
+    fn args(json: serde_json::Value) -> DateTimeArgs {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("2h30m").unwrap(), Duration::minutes(150));
+        assert_eq!(parse_duration("1w 2d").unwrap(), Duration::days(9));
+        assert_eq!(parse_duration("90 minutes").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("-15m").unwrap(), Duration::minutes(-15));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("3 fortnights").is_err());
+        assert_eq!(
+            format_duration(Duration::minutes(-(26 * 60 + 5))),
+            "-1d 2h 5m"
+        );
+    }
+
+    #[test]
+    fn test_parse_time_expressions() {
+        let now = fixed_now();
+        let berlin = Zone::parse("Europe/Berlin").unwrap();
+        let utc = Zone::parse("UTC").unwrap();
+
+        assert_eq!(
+            parse_time("in 2h", utc, now).unwrap(),
+            now + Duration::hours(2)
+        );
+        assert_eq!(
+            parse_time("tomorrow 09:00", berlin, now).unwrap(),
+            Utc.with_ymd_and_hms(2026, 3, 7, 8, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_time("2026-07-01 12:00", berlin, now).unwrap(),
+            Utc.with_ymd_and_hms(2026, 7, 1, 10, 0, 0).unwrap(),
+            "summer time is UTC+2"
+        );
+        assert_eq!(
+            parse_time("2026-03-01T09:00:00-05:00", berlin, now).unwrap(),
+            Utc.with_ymd_and_hms(2026, 3, 1, 14, 0, 0).unwrap()
+        );
+        assert_eq!(parse_time("@0", utc, now).unwrap().timestamp(), 0);
+        assert!(parse_time("next blue moon", utc, now).is_err());
+    }
+
+    #[test]
+    fn test_zone_parse() {
+        assert_eq!(Zone::parse("local").unwrap(), Zone::Local);
+        assert_eq!(
+            Zone::parse("+05:30").unwrap(),
+            Zone::Fixed(FixedOffset::east_opt(5 * 3600 + 1800).unwrap())
+        );
+        assert!(Zone::parse("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_actions() {
+        let tool = DateTimeTool::new();
+        let now = fixed_now();
+
+        let out = tool
+            .run(
+                args(serde_json::json!({"action": "now", "timezone": "Asia/Tokyo"})),
+                now,
+            )
+            .unwrap();
+        assert!(out.starts_with("2026-03-07T00:30:00+09:00 (Saturday March 7 2026, Asia/Tokyo"));
+
+        let out = tool
+            .run(
+                args(serde_json::json!({
+                    "action": "convert",
+                    "time": "2026-03-06 09:00",
+                    "from_timezone": "America/New_York",
+                    "timezone": "UTC"
+                })),
+                now,
+            )
+            .unwrap();
+        assert!(out.contains("= 2026-03-06T14:00:00+00:00"));
+
+        let out = tool
+            .run(
+                args(serde_json::json!({"action": "add", "duration": "3d", "timezone": "UTC"})),
+                now,
+            )
+            .unwrap();
+        assert!(out.starts_with("2026-03-09T15:30:00+00:00 (Monday"));
+
+        let out = tool
+            .run(
+                args(
+                    serde_json::json!({"action": "diff", "time": "2026-03-01", "timezone": "UTC"}),
+                ),
+                now,
+            )
+            .unwrap();
+        assert!(out.starts_with("5d 15h 30m"));
+
+        assert!(tool
+            .run(args(serde_json::json!({"action": "explode"})), now)
+            .is_err());
+    }
+}
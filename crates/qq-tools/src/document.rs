@@ -0,0 +1,151 @@
+//! Document attachment tool for LLM agents.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use qq_core::{
+    attach_file, Error, PropertySchema, Provider, Tool, ToolDefinition, ToolOutput, ToolParameters,
+    TypedContent,
+};
+
+/// Tool that attaches a document (PDF, dataset, long text) to the
+/// conversation by uploading it to the provider's file store.
+pub struct AttachDocumentTool {
+    project_root: PathBuf,
+    provider: Arc<dyn Provider>,
+}
+
+#[derive(Deserialize)]
+struct AttachDocumentArgs {
+    path: String,
+}
+
+impl AttachDocumentTool {
+    pub fn new(project_root: PathBuf, provider: Arc<dyn Provider>) -> Self {
+        Self {
+            project_root,
+            provider,
+        }
+    }
+
+    fn resolve_path(&self, path: &str) -> PathBuf {
+        let p = PathBuf::from(path);
+        if p.is_absolute() {
+            p
+        } else {
+            self.project_root.join(p)
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for AttachDocumentTool {
+    fn name(&self) -> &str {
+        "attach_document"
+    }
+
+    fn description(&self) -> &str {
+        "Attach a PDF or large data file so you can read it directly"
+    }
+
+    fn tool_description(&self) -> &str {
+        "Upload a document (PDF, CSV, JSON, long text) to the model provider and attach it \
+         to the conversation by reference, so you can read the whole file without it being \
+         pasted into the context. Prefer this over reading large documents in pieces. \
+         Paths can be absolute or relative to the project root."
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new("attach_document", self.tool_description()).with_parameters(
+            ToolParameters::new().add_property(
+                "path",
+                PropertySchema::string(
+                    "Path to the document (absolute or relative to project root)",
+                ),
+                true,
+            ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let args: AttachDocumentArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::Unknown(format!("Invalid arguments: {}", e)))?;
+
+        let resolved = self.resolve_path(&args.path);
+        if !resolved.exists() {
+            return Ok(ToolOutput::error(format!(
+                "File not found: {}",
+                resolved.display()
+            )));
+        }
+
+        let content = match attach_file(self.provider.as_ref(), &resolved).await {
+            Ok(content) => content,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Failed to attach {}: {}",
+                    resolved.display(),
+                    e
+                )))
+            }
+        };
+
+        let info = match content {
+            TypedContent::Document { ref document } => format!(
+                "Attached {} ({}, {} bytes) as {}",
+                document.filename, document.media_type, document.size, document.id
+            ),
+            _ => format!(
+                "{} could not be uploaded; its contents follow",
+                resolved.display()
+            ),
+        };
+        Ok(ToolOutput::with_content(
+            vec![TypedContent::text(info), content],
+            false,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::testing::MockProvider;
+
+    #[tokio::test]
+    async fn test_attach_document() {
+        let dir = std::env::temp_dir().join("qq_test_attach_document");
+        std::fs::create_dir_all(&dir).ok();
+        std::fs::write(dir.join("data.csv"), "a,b\n1,2\n").unwrap();
+
+        let provider = Arc::new(MockProvider::new().with_file_uploads());
+        let tool = AttachDocumentTool::new(dir.clone(), provider.clone());
+        let result = tool
+            .execute(serde_json::json!({"path": "data.csv"}))
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+        assert_eq!(
+            result.text_content(),
+            "Attached data.csv (text/csv, 8 bytes) as file-1"
+        );
+        assert!(matches!(result.content[1], TypedContent::Document { .. }));
+        assert_eq!(*provider.uploaded_files.lock().unwrap(), vec!["data.csv"]);
+
+        let missing = tool
+            .execute(serde_json::json!({"path": "missing.pdf"}))
+            .await
+            .unwrap();
+        assert!(missing.is_error);
+        assert!(missing.text_content().contains("not found"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,313 @@
+//! Environment inspection tool.
+//!
+//! `env_info` answers the questions agents otherwise spend several `run`
+//! calls on: which OS and CPU, how much memory, which toolchain versions,
+//! which common commands are installed, and what the sandbox enforces.
+//! Only a fixed allowlist of commands is probed, with fixed version flags,
+//! and the result is computed once per session.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::sync::OnceCell;
+
+use qq_core::{Error, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use crate::bash::{SandboxCapabilities, SandboxExecutor};
+
+/// Toolchains whose versions are reported: (label, command, version args).
+const TOOLCHAINS: &[(&str, &str, &[&str])] = &[
+    ("rust", "rustc", &["--version"]),
+    ("cargo", "cargo", &["--version"]),
+    ("python", "python3", &["--version"]),
+    ("node", "node", &["--version"]),
+    ("go", "go", &["version"]),
+];
+
+/// Commands checked for availability on PATH (never executed).
+const COMMAND_ALLOWLIST: &[&str] = &[
+    "git", "gh", "make", "cmake", "gcc", "clang", "pip3", "uv", "poetry", "npm", "pnpm", "yarn",
+    "bun", "deno", "java", "mvn", "gradle", "docker", "podman", "rg", "fd", "jq", "curl", "wget",
+    "tar", "unzip", "sqlite3", "psql",
+];
+
+/// How long a single version check may take.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Longest version string kept (some tools print a banner).
+const MAX_VERSION_CHARS: usize = 120;
+
+const ENV_INFO_TOOL_DESC: &str = "\
+Structured facts about the environment as JSON: OS, distro, kernel, CPU cores and model, \
+memory, versions of rust/cargo/python/node/go, which common commands (git, make, docker, \
+rg, jq, package managers, ...) are installed, and the sandbox mode of the `run` tool.
+
+Call this once instead of running `uname`, `which`, or `--version` commands to learn the \
+environment. Takes no arguments.";
+
+/// What the `run` tool's sandbox enforces, captured at startup.
+#[derive(Debug, Clone, Copy)]
+struct SandboxInfo {
+    backend: &'static str,
+    capabilities: SandboxCapabilities,
+}
+
+/// Tool reporting OS, hardware, toolchain, and sandbox facts.
+pub struct EnvInfoTool {
+    sandbox: Option<SandboxInfo>,
+    cached: OnceCell<Value>,
+}
+
+impl EnvInfoTool {
+    pub fn new() -> Self {
+        Self {
+            sandbox: None,
+            cached: OnceCell::new(),
+        }
+    }
+
+    /// Report the sandbox backend the `run` tool uses.
+    pub fn with_sandbox(mut self, executor: &SandboxExecutor) -> Self {
+        self.sandbox = Some(SandboxInfo {
+            backend: executor.mode_name(),
+            capabilities: executor.capabilities(),
+        });
+        self
+    }
+
+    fn sandbox_json(&self) -> Value {
+        match self.sandbox {
+            Some(info) => json!({
+                "enabled": true,
+                "backend": info.backend,
+                "shell_operators": info.capabilities.shell,
+                "filesystem_isolation": info.capabilities.filesystem_isolation,
+                "read_only_mounts": info.capabilities.read_only_root,
+                "sensitive_dirs_hidden": info.capabilities.hides_sensitive_dirs,
+            }),
+            None => json!({ "enabled": false }),
+        }
+    }
+
+    async fn collect(&self) -> Value {
+        let os_release = std::fs::read_to_string("/etc/os-release").ok();
+        let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok();
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok();
+
+        let mut toolchains = serde_json::Map::new();
+        for (label, command, args) in TOOLCHAINS {
+            let version = match find_in_path(command) {
+                Some(path) => command_version(&path, args).await,
+                None => None,
+            };
+            toolchains.insert(label.to_string(), json!(version));
+        }
+
+        let (available, missing): (Vec<&str>, Vec<&str>) = COMMAND_ALLOWLIST
+            .iter()
+            .partition(|command| find_in_path(command).is_some());
+
+        json!({
+            "os": {
+                "os": std::env::consts::OS,
+                "family": std::env::consts::FAMILY,
+                "arch": std::env::consts::ARCH,
+                "distro": os_release.as_deref().and_then(parse_os_release),
+                "kernel": std::fs::read_to_string("/proc/sys/kernel/osrelease")
+                    .ok()
+                    .map(|s| s.trim().to_string()),
+            },
+            "cpu": {
+                "logical_cores": std::thread::available_parallelism().ok().map(|n| n.get()),
+                "model": cpuinfo.as_deref().and_then(parse_cpu_model),
+            },
+            "memory": meminfo.as_deref().map(|m| json!({
+                "total_mb": parse_meminfo_kb(m, "MemTotal").map(|kb| kb / 1024),
+                "available_mb": parse_meminfo_kb(m, "MemAvailable").map(|kb| kb / 1024),
+            })),
+            "toolchains": toolchains,
+            "commands": {
+                "available": available,
+                "missing": missing,
+            },
+            "shell": std::env::var("SHELL").ok(),
+            "cwd": std::env::current_dir().ok().map(|p| p.display().to_string()),
+            "sandbox": self.sandbox_json(),
+        })
+    }
+}
+
+impl Default for EnvInfoTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `PRETTY_NAME` (or `NAME`) from /etc/os-release.
+fn parse_os_release(contents: &str) -> Option<String> {
+    let field = |key: &str| {
+        contents.lines().find_map(|line| {
+            line.strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| value.trim().trim_matches('"').to_string())
+        })
+    };
+    field("PRETTY_NAME").or_else(|| field("NAME"))
+}
+
+/// First `model name` line from /proc/cpuinfo.
+fn parse_cpu_model(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "model name").then(|| value.trim().to_string())
+    })
+}
+
+/// A `Key:   12345 kB` value from /proc/meminfo, in kB.
+fn parse_meminfo_kb(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let (k, value) = line.split_once(':')?;
+        if k != key {
+            return None;
+        }
+        value.split_whitespace().next()?.parse().ok()
+    })
+}
+
+/// Locate an executable on PATH without spawning `which`.
+fn find_in_path(command: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(command))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// First non-empty output line of `<path> <args>`, or `None` on failure or timeout.
+async fn command_version(path: &Path, args: &[&str]) -> Option<String> {
+    let output = tokio::time::timeout(
+        VERSION_TIMEOUT,
+        tokio::process::Command::new(path)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // Older tools (python2) print the version to stderr
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(|line| line.chars().take(MAX_VERSION_CHARS).collect())
+}
+
+#[async_trait]
+impl Tool for EnvInfoTool {
+    fn name(&self) -> &str {
+        "env_info"
+    }
+
+    fn description(&self) -> &str {
+        "Report OS, CPU/memory, toolchain versions, installed commands, and sandbox mode as JSON"
+    }
+
+    fn tool_description(&self) -> &str {
+        ENV_INFO_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description())
+            .with_parameters(ToolParameters::new())
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, _arguments: Value) -> Result<ToolOutput, Error> {
+        let info = self.cached.get_or_init(|| self.collect()).await;
+        let text = serde_json::to_string_pretty(info)
+            .map_err(|e| Error::tool("env_info", format!("Failed to serialize: {}", e)))?;
+        Ok(ToolOutput::success(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_files() {
+        let os_release =
+            "NAME=\"Ubuntu\"\nVERSION_ID=\"24.04\"\nPRETTY_NAME=\"Ubuntu 24.04.1 LTS\"\n";
+        assert_eq!(
+            parse_os_release(os_release).as_deref(),
+            Some("Ubuntu 24.04.1 LTS")
+        );
+        assert_eq!(
+            parse_os_release("NAME=Alpine Linux\n").as_deref(),
+            Some("Alpine Linux")
+        );
+
+        let cpuinfo = "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel name\t: Intel(R) Xeon(R) CPU @ 2.20GHz\n";
+        assert_eq!(
+            parse_cpu_model(cpuinfo).as_deref(),
+            Some("Intel(R) Xeon(R) CPU @ 2.20GHz")
+        );
+
+        let meminfo =
+            "MemTotal:       16318412 kB\nMemFree:         1234 kB\nMemAvailable:    8000000 kB\n";
+        assert_eq!(parse_meminfo_kb(meminfo, "MemTotal"), Some(16318412));
+        assert_eq!(parse_meminfo_kb(meminfo, "MemAvailable"), Some(8000000));
+        assert_eq!(parse_meminfo_kb(meminfo, "SwapTotal"), None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_returns_structured_json() {
+        let tool = EnvInfoTool::new().with_sandbox(&SandboxExecutor::AppLevel);
+        let output = tool.execute(json!({})).await.unwrap();
+        assert!(!output.is_error);
+
+        let info: Value = serde_json::from_str(&output.text_content()).unwrap();
+        assert_eq!(info["os"]["os"], std::env::consts::OS);
+        assert_eq!(info["sandbox"]["backend"], "app-level");
+        assert_eq!(info["sandbox"]["shell_operators"], false);
+        assert!(info["toolchains"].get("rust").is_some());
+
+        // Every allowlisted command lands in exactly one bucket
+        let available = info["commands"]["available"].as_array().unwrap().len();
+        let missing = info["commands"]["missing"].as_array().unwrap().len();
+        assert_eq!(available + missing, COMMAND_ALLOWLIST.len());
+
+        assert_eq!(
+            EnvInfoTool::new().sandbox_json(),
+            json!({ "enabled": false })
+        );
+    }
+}
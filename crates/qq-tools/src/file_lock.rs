@@ -0,0 +1,313 @@
+//! Locks for state files shared by concurrent qq sessions.
+//!
+//! Several sessions can run at once in different terminals, and they all
+//! write the same files in the state directory (knowledge base, reminders,
+//! input history, named agent instances). Writers take a [`FileLock`] next
+//! to the file, re-read what is on disk, apply their change and replace the
+//! file with [`write_atomically`], so no session overwrites another's
+//! entries and a reader never sees a half-written file.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a writer waits for another session's lock before giving up.
+const LOCK_BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Poll interval while waiting for the lock.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A lock older than this was left behind by a crashed process.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+
+/// Exclusive `<file>.lock` held while writing; removed on drop.
+///
+/// The lock file holds the owner's pid. Other writers poll until
+/// [`LOCK_BUSY_TIMEOUT`], then fail with [`std::io::ErrorKind::WouldBlock`]
+/// naming the owner. A lock older than [`STALE_LOCK_AGE`], or whose owner
+/// is no longer running, belongs to a crashed process and is taken over.
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Take `target`'s lock, blocking the thread while another session
+    /// holds it. On a multi-threaded tokio runtime the wait is handed to
+    /// [`tokio::task::block_in_place`] so the worker's other tasks move on;
+    /// async callers should prefer [`FileLock::acquire_async`].
+    pub fn acquire(target: &Path) -> std::io::Result<Self> {
+        let path = prepare(target)?;
+        let deadline = Instant::now() + LOCK_BUSY_TIMEOUT;
+        let mut waited = false;
+        loop {
+            let Some(owner) = try_lock(&path)? else {
+                return Ok(Self { path });
+            };
+            wait_or_give_up(target, &path, owner, deadline, &mut waited)?;
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle)
+                    if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread =>
+                {
+                    tokio::task::block_in_place(|| std::thread::sleep(LOCK_RETRY_INTERVAL))
+                }
+                _ => std::thread::sleep(LOCK_RETRY_INTERVAL),
+            }
+        }
+    }
+
+    /// [`FileLock::acquire`] for async callers: waits with
+    /// [`tokio::time::sleep`] instead of blocking the thread.
+    pub async fn acquire_async(target: &Path) -> std::io::Result<Self> {
+        let path = prepare(target)?;
+        let deadline = Instant::now() + LOCK_BUSY_TIMEOUT;
+        let mut waited = false;
+        loop {
+            let Some(owner) = try_lock(&path)? else {
+                return Ok(Self { path });
+            };
+            wait_or_give_up(target, &path, owner, deadline, &mut waited)?;
+            tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Pid of the session currently holding `target`'s lock, if any.
+    pub fn holder(target: &Path) -> Option<u32> {
+        read_owner(&lock_path(target)).filter(|&pid| process_alive(pid))
+    }
+}
+
+fn prepare(target: &Path) -> std::io::Result<PathBuf> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(lock_path(target))
+}
+
+/// One attempt at the lock: `None` once it is ours, otherwise the current
+/// owner (if readable). Stale locks are taken over and retried right away.
+fn try_lock(path: &Path) -> std::io::Result<Option<Option<u32>>> {
+    loop {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+        {
+            Ok(mut file) => {
+                let _ = write!(file, "{}", std::process::id());
+                return Ok(None);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let stamp = LockStamp::read(path);
+                if !stamp.is_stale() {
+                    return Ok(Some(stamp.owner));
+                }
+                tracing::warn!(path = %path.display(), "Removing stale lock");
+                remove_stale(path, &stamp);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Log the first wait and fail once `deadline` has passed.
+fn wait_or_give_up(
+    target: &Path,
+    path: &Path,
+    owner: Option<u32>,
+    deadline: Instant,
+    waited: &mut bool,
+) -> std::io::Result<()> {
+    if !*waited {
+        *waited = true;
+        tracing::debug!(path = %path.display(), ?owner, "Waiting for another session's lock");
+    }
+    if Instant::now() >= deadline {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::WouldBlock,
+            format!(
+                "{} is locked by another qq session{}",
+                file_name(target),
+                owner
+                    .map(|pid| format!(" (pid {})", pid))
+                    .unwrap_or_default()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// What a lock file looked like when it was judged, so a takeover can tell
+/// whether it moved that same file or one a racing writer created since.
+#[derive(Debug, PartialEq)]
+struct LockStamp {
+    owner: Option<u32>,
+    modified: Option<SystemTime>,
+}
+
+impl LockStamp {
+    fn read(path: &Path) -> Self {
+        Self {
+            owner: read_owner(path),
+            modified: std::fs::metadata(path).and_then(|m| m.modified()).ok(),
+        }
+    }
+
+    fn is_stale(&self) -> bool {
+        self.owner.is_some_and(|pid| !process_alive(pid))
+            || self
+                .modified
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > STALE_LOCK_AGE)
+    }
+}
+
+/// Move the stale lock aside with an atomic rename rather than deleting it
+/// by name: if another writer took it over first, the rename picks up its
+/// fresh lock instead, which is put back untouched.
+fn remove_stale(path: &Path, stamp: &LockStamp) {
+    static TAKEOVERS: AtomicU64 = AtomicU64::new(0);
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(
+        ".stale.{}.{}",
+        std::process::id(),
+        TAKEOVERS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let aside = PathBuf::from(aside);
+    if std::fs::rename(path, &aside).is_err() {
+        // Already gone: someone else removed it
+        return;
+    }
+    if LockStamp::read(&aside) != *stamp {
+        // hard_link only creates `path` if it is still free
+        let _ = std::fs::hard_link(&aside, path);
+    }
+    let _ = std::fs::remove_file(&aside);
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Replace `path` with `contents` via a synced temp file and rename, so
+/// readers see either the old file or the new one.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(contents)?;
+    tmp.as_file().sync_all()?;
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+fn lock_path(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+fn file_name(target: &Path) -> String {
+    target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| target.display().to_string())
+}
+
+fn read_owner(lock: &Path) -> Option<u32> {
+    std::fs::read_to_string(lock).ok()?.trim().parse().ok()
+}
+
+/// Whether `pid` is a running process. Only checkable through `/proc`;
+/// elsewhere every pid is assumed alive (for locks, their age decides).
+pub fn process_alive(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    !proc.is_dir() || proc.join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_reports_owner_and_takes_over_dead_owners() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("reminders.json");
+
+        let held = FileLock::acquire(&path).unwrap();
+        assert_eq!(FileLock::holder(&path), Some(std::process::id()));
+        let err = FileLock::acquire(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "reminders.json is locked by another qq session (pid {})",
+                std::process::id()
+            )
+        );
+        drop(held);
+        assert_eq!(FileLock::holder(&path), None);
+
+        // Owner that no longer runs (pids are capped well below this)
+        if Path::new("/proc").is_dir() {
+            std::fs::write(lock_path(&path), "4294967").unwrap();
+            let start = Instant::now();
+            let _lock = FileLock::acquire(&path).unwrap();
+            assert!(start.elapsed() < LOCK_BUSY_TIMEOUT);
+        }
+    }
+
+    #[test]
+    fn test_stale_takeover_keeps_a_fresh_lock() {
+        let tmp = tempfile::tempdir().unwrap();
+        let lock = lock_path(&tmp.path().join("reminders.json"));
+        std::fs::write(&lock, "4294967").unwrap();
+        let stale = LockStamp::read(&lock);
+
+        // Another writer replaced the stale lock after it was judged
+        std::fs::remove_file(&lock).unwrap();
+        std::fs::write(&lock, std::process::id().to_string()).unwrap();
+        remove_stale(&lock, &stale);
+        assert_eq!(read_owner(&lock), Some(std::process::id()));
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 1);
+
+        remove_stale(&lock, &LockStamp::read(&lock));
+        assert!(!lock.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_lock_waits_without_blocking_the_runtime() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("history.txt");
+
+        let held = FileLock::acquire_async(&path).await.unwrap();
+        let waiter = {
+            let path = path.clone();
+            tokio::spawn(async move { FileLock::acquire_async(&path).await.map(|_| ()) })
+        };
+        let blocked = {
+            let path = path.clone();
+            tokio::spawn(async move { FileLock::acquire(&path).map(|_| ()) })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(held);
+        waiter.await.unwrap().unwrap();
+        blocked.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_write_atomically_replaces_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("state").join("history.json");
+        write_atomically(&path, b"first").unwrap();
+        write_atomically(&path, b"second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+    }
+}
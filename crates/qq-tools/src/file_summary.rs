@@ -0,0 +1,638 @@
+//! Cached per-file summaries (`summarize_file`).
+//!
+//! Agents learning a project used to read the same big, unchanged files
+//! into context every session. [`FileSummarizer`] asks a model for a short
+//! summary of a file once and caches it in the project's cache file under
+//! the SHA-256 of the file's content. A later request for an unchanged file
+//! returns at once: its size and mtime are checked first, and only a file
+//! whose stamp changed is read and hashed (a touched file with the same
+//! content keeps its summary). `qq index --summaries` fills the cache ahead
+//! of time.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use qq_core::text::truncate_bytes;
+use qq_core::{
+    CompletionRequest, Error, Message, PropertySchema, Provider, Tool, ToolDefinition,
+    ToolOutput, ToolParameters,
+};
+
+use crate::bash::AccessLog;
+use crate::file_lock::{write_atomically, FileLock};
+
+/// Files larger than this aren't summarized (generated code, data dumps).
+pub const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Most of a file sent to the model; the rest is left out with a note.
+const MAX_INPUT_BYTES: usize = 96 * 1024;
+
+/// Longest summary asked for.
+const MAX_SUMMARY_TOKENS: u32 = 600;
+
+/// Directories skipped when listing files outside a git repository.
+const SKIP_DIRS: &[&str] = &[
+    "target",
+    "node_modules",
+    "vendor",
+    "dist",
+    "build",
+    "__pycache__",
+    "venv",
+];
+
+const SUMMARY_PROMPT: &str = "\
+You summarize source and text files for developers who haven't read them. \
+In at most 12 short lines, say what the file is for, its main types, functions, \
+or sections and what they do, what it depends on, and anything surprising. \
+Name identifiers exactly. No preamble.";
+
+const SUMMARIZE_TOOL_DESC: &str = "\
+Get a short summary of what a file contains: its purpose, main types and functions, \
+and dependencies. Summaries are cached by file content, so asking about a file that \
+hasn't changed since it was last summarized (in any session) is instant and costs \
+nothing. Use it to decide whether a large file is worth reading, then read only the \
+parts you need with read_files or outline_file.
+
+Paths are relative to the project root or absolute within it. Set refresh to true to \
+summarize again even when the file is unchanged.";
+
+/// Size and modification time a file had when it was last hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    size: u64,
+    mtime_ns: u64,
+}
+
+impl FileStamp {
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        let mtime_ns = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self {
+            size: metadata.len(),
+            mtime_ns,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileEntry {
+    #[serde(flatten)]
+    stamp: FileStamp,
+    digest: String,
+}
+
+/// A summary as cached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedSummary {
+    pub summary: String,
+    pub lines: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheState {
+    /// Project-relative path to the content it had when last hashed
+    #[serde(default)]
+    files: HashMap<String, FileEntry>,
+    /// Summaries by content digest
+    #[serde(default)]
+    summaries: HashMap<String, CachedSummary>,
+}
+
+/// One project's summaries, saved as JSON in the cache directory.
+pub struct SummaryCache {
+    path: PathBuf,
+    state: Mutex<CacheState>,
+}
+
+impl SummaryCache {
+    /// The cache for the project at `project_root`, kept in `dir`.
+    pub fn open(dir: &Path, project_root: &Path) -> Self {
+        let key = hex_digest(project_root.to_string_lossy().as_bytes());
+        let path = dir.join(format!("{}.json", &key[..16]));
+        let state = load_state(&path);
+        Self {
+            path,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The summary of `file` if its stamp hasn't changed since it was hashed.
+    fn lookup(&self, file: &str, stamp: FileStamp) -> Option<CachedSummary> {
+        let state = self.state.lock().ok()?;
+        let entry = state.files.get(file).filter(|e| e.stamp == stamp)?;
+        state.summaries.get(&entry.digest).cloned()
+    }
+
+    fn by_digest(&self, digest: &str) -> Option<CachedSummary> {
+        self.state.lock().ok()?.summaries.get(digest).cloned()
+    }
+
+    /// Record `file`'s stamp and digest, and the summary of that content
+    /// when it is new.
+    fn insert(&self, file: &str, stamp: FileStamp, digest: &str, summary: Option<CachedSummary>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.files.insert(
+                file.to_string(),
+                FileEntry {
+                    stamp,
+                    digest: digest.to_string(),
+                },
+            );
+            if let Some(summary) = summary {
+                state.summaries.insert(digest.to_string(), summary);
+            }
+        }
+    }
+
+    /// Write the cache, merged with what other sessions saved meanwhile.
+    /// Summaries no file has anymore are dropped.
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| std::io::Error::other("summary cache lock poisoned"))?;
+        let on_disk = load_state(&self.path);
+        for (file, entry) in on_disk.files {
+            state.files.entry(file).or_insert(entry);
+        }
+        for (digest, summary) in on_disk.summaries {
+            state.summaries.entry(digest).or_insert(summary);
+        }
+        let CacheState { files, summaries } = &mut *state;
+        summaries.retain(|digest, _| files.values().any(|e| &e.digest == digest));
+
+        let json = serde_json::to_vec_pretty(&*state).map_err(std::io::Error::other)?;
+        write_atomically(&self.path, &json)
+    }
+}
+
+fn load_state(path: &Path) -> CacheState {
+    std::fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// A file's summary and whether it came from the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSummary {
+    /// Project-relative path
+    pub path: String,
+    pub summary: String,
+    pub lines: usize,
+    pub cached: bool,
+}
+
+/// Summarizes project files with a model, through a [`SummaryCache`].
+pub struct FileSummarizer {
+    root: PathBuf,
+    provider: Arc<dyn Provider>,
+    model: Option<String>,
+    cache: SummaryCache,
+    access_log: Option<Arc<AccessLog>>,
+}
+
+impl FileSummarizer {
+    /// Summarizer for files under `project_root`, caching in `cache_dir`.
+    pub fn new(project_root: PathBuf, provider: Arc<dyn Provider>, cache_dir: &Path) -> Self {
+        let root = project_root.canonicalize().unwrap_or(project_root);
+        let cache = SummaryCache::open(cache_dir, &root);
+        Self {
+            root,
+            provider,
+            model: None,
+            cache,
+            access_log: None,
+        }
+    }
+
+    /// Ask for summaries with `model` instead of the provider's default.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Check summaries of files git doesn't track with `log`, like reads.
+    pub fn with_access_log(mut self, log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn cache(&self) -> &SummaryCache {
+        &self.cache
+    }
+
+    /// Summary of `path` (relative to the project root or absolute within
+    /// it), from the cache unless the content changed or `refresh` is set.
+    pub async fn summarize(&self, path: &str, refresh: bool) -> Result<FileSummary, String> {
+        let requested = Path::new(path);
+        let host = if requested.is_absolute() {
+            requested.to_path_buf()
+        } else {
+            self.root.join(requested)
+        };
+        let canonical = host
+            .canonicalize()
+            .map_err(|e| format!("cannot open {}: {}", path, e))?;
+        let relative = canonical
+            .strip_prefix(&self.root)
+            .map_err(|_| format!("{} is outside the project root", path))?
+            .to_string_lossy()
+            .into_owned();
+        let metadata = std::fs::metadata(&canonical).map_err(|e| format!("{}: {}", path, e))?;
+        if !metadata.is_file() {
+            return Err(format!("{} is not a file", path));
+        }
+        if metadata.len() > MAX_FILE_BYTES {
+            return Err(format!(
+                "{} is {} bytes, over the {}-byte limit for summaries",
+                path,
+                metadata.len(),
+                MAX_FILE_BYTES
+            ));
+        }
+        // A cached summary discloses the file as much as a fresh one
+        if let Some(ref log) = self.access_log {
+            log.check("summarize_file", std::slice::from_ref(&canonical))
+                .await?;
+        }
+
+        let stamp = FileStamp::of(&metadata);
+        let hit = |cached: CachedSummary| FileSummary {
+            path: relative.clone(),
+            summary: cached.summary,
+            lines: cached.lines,
+            cached: true,
+        };
+        if !refresh {
+            if let Some(cached) = self.cache.lookup(&relative, stamp) {
+                return Ok(hit(cached));
+            }
+        }
+
+        let bytes = tokio::fs::read(&canonical)
+            .await
+            .map_err(|e| format!("cannot read {}: {}", path, e))?;
+        if bytes.contains(&0) {
+            return Err(format!("{} looks like a binary file", path));
+        }
+        let digest = hex_digest(&bytes);
+        if !refresh {
+            // Touched, or a copy of a file already summarized
+            if let Some(cached) = self.cache.by_digest(&digest) {
+                self.cache.insert(&relative, stamp, &digest, None);
+                self.save();
+                return Ok(hit(cached));
+            }
+        }
+
+        let text = String::from_utf8_lossy(&bytes);
+        let lines = text.lines().count();
+        let summary = self.ask(&relative, &text).await?;
+        let cached = CachedSummary {
+            summary: summary.clone(),
+            lines,
+            model: self
+                .model
+                .clone()
+                .or_else(|| self.provider.default_model().map(str::to_string)),
+            created: Utc::now(),
+        };
+        self.cache.insert(&relative, stamp, &digest, Some(cached));
+        self.save();
+        Ok(FileSummary {
+            path: relative,
+            summary,
+            lines,
+            cached: false,
+        })
+    }
+
+    async fn ask(&self, path: &str, text: &str) -> Result<String, String> {
+        let shown = truncate_bytes(text, MAX_INPUT_BYTES);
+        let note = if shown.len() < text.len() {
+            format!(" (first {} of {} bytes)", shown.len(), text.len())
+        } else {
+            String::new()
+        };
+        let mut request = CompletionRequest::new(vec![
+            Message::system(SUMMARY_PROMPT),
+            Message::user(format!("File: {}{}\n\n```\n{}\n```", path, note, shown)),
+        ])
+        .with_max_tokens(MAX_SUMMARY_TOKENS);
+        if let Some(ref model) = self.model {
+            request = request.with_model(model);
+        }
+        let response = self
+            .provider
+            .complete(request)
+            .await
+            .map_err(|e| format!("summarizing {} failed: {}", path, e))?;
+        let summary = response.message.content.to_string_lossy().trim().to_string();
+        if summary.is_empty() {
+            return Err(format!("the model returned an empty summary of {}", path));
+        }
+        Ok(summary)
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.cache.save() {
+            tracing::warn!(path = %self.cache.path().display(), "Failed to save summary cache: {}", e);
+        }
+    }
+}
+
+/// Files under `root` worth summarizing ahead of time, relative to it and
+/// sorted: what git tracks or would track, else every file outside hidden
+/// and build directories; only those of at least `min_bytes` and under
+/// [`MAX_FILE_BYTES`].
+pub fn summary_candidates(root: &Path, min_bytes: u64) -> Vec<String> {
+    let mut files = git_files(root).unwrap_or_else(|| {
+        let mut files = Vec::new();
+        walk(root, root, &mut files);
+        files
+    });
+    files.retain(|file| {
+        std::fs::metadata(root.join(file))
+            .is_ok_and(|m| m.is_file() && m.len() >= min_bytes && m.len() <= MAX_FILE_BYTES)
+    });
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn git_files(root: &Path) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .args(["ls-files", "-z", "--cached", "--others", "--exclude-standard"])
+        .current_dir(root)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let files: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect();
+    Some(files)
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || SKIP_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => walk(root, &path, files),
+            Ok(t) if t.is_file() => {
+                if let Ok(relative) = path.strip_prefix(root) {
+                    files.push(relative.to_string_lossy().into_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Tool returning a cached summary of a project file.
+pub struct SummarizeFileTool {
+    summarizer: Arc<FileSummarizer>,
+}
+
+#[derive(Deserialize)]
+struct SummarizeArgs {
+    path: String,
+    #[serde(default)]
+    refresh: bool,
+}
+
+impl SummarizeFileTool {
+    pub fn new(summarizer: Arc<FileSummarizer>) -> Self {
+        Self { summarizer }
+    }
+}
+
+#[async_trait]
+impl Tool for SummarizeFileTool {
+    fn name(&self) -> &str {
+        "summarize_file"
+    }
+
+    fn description(&self) -> &str {
+        "Short cached summary of a file's purpose and contents"
+    }
+
+    fn tool_description(&self) -> &str {
+        SUMMARIZE_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "path",
+                    PropertySchema::string("File to summarize, relative to the project root"),
+                    true,
+                )
+                .add_property(
+                    "refresh",
+                    PropertySchema::boolean("Summarize again even if the file is unchanged"),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let args: SummarizeArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("summarize_file", format!("Invalid arguments: {}", e)))?;
+        Ok(match self.summarizer.summarize(&args.path, args.refresh).await {
+            Ok(summary) => ToolOutput::success(format!(
+                "{} ({} lines, {})\n\n{}",
+                summary.path,
+                summary.lines,
+                if summary.cached { "cached summary" } else { "new summary" },
+                summary.summary
+            )),
+            Err(e) => ToolOutput::error(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::testing::MockProvider;
+
+    fn summarizer(root: &Path, cache: &Path, mock: &Arc<MockProvider>) -> FileSummarizer {
+        FileSummarizer::new(
+            root.to_path_buf(),
+            Arc::clone(mock) as Arc<dyn Provider>,
+            cache,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_summaries_are_cached_by_content() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let file = root.path().join("lib.rs");
+        std::fs::write(&file, "pub fn parse() {}\npub fn render() {}\n").unwrap();
+        let mock = Arc::new(MockProvider::new());
+
+        mock.queue_response("Parses and renders.");
+        let first = summarizer(root.path(), cache.path(), &mock)
+            .summarize("lib.rs", false)
+            .await
+            .unwrap();
+        assert_eq!(first.summary, "Parses and renders.");
+        assert_eq!(first.lines, 2);
+        assert!(!first.cached);
+        let prompt = mock.last_request().unwrap().messages[1].content.to_string_lossy();
+        assert!(prompt.contains("File: lib.rs") && prompt.contains("pub fn render"));
+
+        // A new session reads the saved cache; a touched file keeps its summary
+        let later = summarizer(root.path(), cache.path(), &mock);
+        assert!(later.summarize("lib.rs", false).await.unwrap().cached);
+        let touched = std::fs::File::options().write(true).open(&file).unwrap();
+        touched
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        assert!(later.summarize(file.to_str().unwrap(), false).await.unwrap().cached);
+        assert_eq!(mock.request_count(), 1);
+
+        // Changed content or a refresh asks again
+        std::fs::write(&file, "pub fn parse() {}\n").unwrap();
+        mock.queue_response("Parses.");
+        let changed = later.summarize("lib.rs", false).await.unwrap();
+        assert_eq!((changed.summary.as_str(), changed.cached), ("Parses.", false));
+        mock.queue_response("Parses input.");
+        assert!(!later.summarize("lib.rs", true).await.unwrap().cached);
+        assert_eq!(mock.request_count(), 3);
+
+        // The replaced summaries aren't kept
+        let saved: CacheState =
+            serde_json::from_slice(&std::fs::read(later.cache().path()).unwrap()).unwrap();
+        assert_eq!(saved.summaries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_rejects_outside_and_binary_files() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("notes.md"), "secret").unwrap();
+        std::fs::write(root.path().join("logo.png"), [0x89, b'P', 0, 0]).unwrap();
+        let mock = Arc::new(MockProvider::new());
+        let summarizer = summarizer(root.path(), cache.path(), &mock);
+
+        let outside_path = outside.path().join("notes.md");
+        let err = summarizer
+            .summarize(outside_path.to_str().unwrap(), false)
+            .await
+            .unwrap_err();
+        assert!(err.contains("outside the project root"), "{}", err);
+        let err = summarizer.summarize("logo.png", false).await.unwrap_err();
+        assert!(err.contains("binary"), "{}", err);
+        assert!(summarizer.summarize("missing.rs", false).await.is_err());
+        assert_eq!(mock.request_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_checks_access_log() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(root.path())
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        std::fs::write(root.path().join("lib.rs"), "pub fn parse() {}\n").unwrap();
+        std::fs::write(root.path().join("notes.md"), "private notes\n").unwrap();
+        if !(git(&["init", "-q"]) && git(&["add", "lib.rs"])) {
+            return; // git unavailable
+        }
+
+        let (approval, mut rx) = crate::approval::create_approval_channel();
+        let log = Arc::new(
+            AccessLog::new(root.path())
+                .with_policy(crate::bash::UntrackedReadPolicy::Ask, approval),
+        );
+        tokio::spawn(async move {
+            while let Some(request) = rx.recv().await {
+                let _ = request
+                    .response_tx
+                    .send(crate::approval::ApprovalResponse::Deny(None));
+            }
+        });
+        let mock = Arc::new(MockProvider::new());
+        let summarizer =
+            summarizer(root.path(), cache.path(), &mock).with_access_log(Arc::clone(&log));
+
+        let err = summarizer.summarize("notes.md", false).await.unwrap_err();
+        assert!(err.contains("denied by user"), "{}", err);
+        assert_eq!(mock.request_count(), 0);
+
+        // Tracked files don't ask
+        mock.queue_response("Parses.");
+        assert!(summarizer.summarize("lib.rs", false).await.is_ok());
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn test_summary_candidates_skip_small_and_build_files() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("src")).unwrap();
+        std::fs::create_dir_all(root.path().join("target/debug")).unwrap();
+        std::fs::write(root.path().join("src/big.rs"), "x".repeat(2048)).unwrap();
+        std::fs::write(root.path().join("src/small.rs"), "x").unwrap();
+        std::fs::write(root.path().join("target/debug/out.rs"), "x".repeat(2048)).unwrap();
+
+        assert_eq!(summary_candidates(root.path(), 1024), vec!["src/big.rs"]);
+    }
+}
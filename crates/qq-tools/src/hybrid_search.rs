@@ -0,0 +1,1133 @@
+//! Lexical + semantic code search in one call (`hybrid_search`).
+//!
+//! Agents searching a project had to pick between grep, which finds
+//! identifiers but not ideas, and reading files in the hope of finding the
+//! code behind a concept. [`HybridSearcher`] does both at once: ripgrep
+//! finds lines containing the query's terms while the query's embedding is
+//! compared against an index of file chunks, and the two sets of chunks are
+//! merged into one ranked list with a weighted score.
+//!
+//! Files are split into chunks of [`CHUNK_LINES`] lines, which is also how
+//! lexical hits are grouped, so a chunk found both ways is listed once. The
+//! index lives for the session: the first search embeds the project's files
+//! and later searches embed only files whose size or mtime changed. Without
+//! an embedding model the tool still answers, lexically.
+//!
+//! ripgrep runs in the `run` tool's sandbox; without one, files are scanned
+//! directly. Either way only files inside the project root (after resolving
+//! symlinks) are read, reads of untracked files go through the
+//! [`AccessLog`], and a cancelled run stops the search between files and
+//! embedding batches.
+//!
+//! ```text
+//! 2 results for "token refresh" (lexical + semantic, weights 0.50/0.50)
+//!
+//! 1. src/auth/session.rs:41-80  score 0.86 (lexical 0.80, semantic 0.91)
+//!    52: fn refresh_token(&mut self) -> Result<Token> {
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::{Mutex, Semaphore};
+
+use qq_core::chunker::cosine_similarity;
+use qq_core::text::truncate_bytes;
+use qq_core::{
+    Checkpoint, Error, PropertySchema, Provider, Tool, ToolDefinition, ToolOutput, ToolParameters,
+};
+
+use crate::bash::sandbox::shell_escape;
+use crate::bash::{AccessLog, SandboxExecutor, SandboxMounts, SandboxPathPolicy};
+use crate::file_summary::summary_candidates;
+use crate::knowledge::tokenize;
+
+/// Results returned when the caller doesn't say.
+pub const DEFAULT_MAX_RESULTS: usize = 10;
+
+/// Most results one call can ask for.
+const MAX_RESULTS_LIMIT: usize = 30;
+
+/// Lines per chunk, for both the index and grouping lexical hits.
+pub const CHUNK_LINES: usize = 40;
+
+/// Files larger than this aren't indexed (generated code, data dumps).
+const MAX_INDEX_FILE_BYTES: u64 = 512 * 1024;
+
+/// Most of a chunk sent to the embedding model.
+const MAX_CHUNK_BYTES: usize = 4000;
+
+/// Chunks per embedding request, and requests in flight at once.
+const EMBED_BATCH_SIZE: usize = 32;
+const EMBED_CONCURRENCY: usize = 4;
+
+/// Matching lines read per file, and in total, for lexical scoring.
+const MAX_LINES_PER_FILE: usize = 50;
+const MAX_LEXICAL_LINES: usize = 5000;
+
+/// Longest a ripgrep run may take.
+const RIPGREP_TIMEOUT_SECS: u64 = 60;
+
+/// Chunks less similar than this to the query aren't semantic hits.
+const MIN_SEMANTIC_SCORE: f32 = 0.2;
+
+/// Lines shown under each result.
+const SNIPPET_LINES: usize = 3;
+const MAX_SNIPPET_LINE_BYTES: usize = 160;
+
+const HYBRID_SEARCH_TOOL_DESC: &str = "\
+Search the project for code and text by keyword and by meaning in one call. Exact \
+matches of the query's words (identifiers, error messages, config keys) are found \
+with ripgrep; passages about the same concept are found by embedding similarity, \
+even when they use different words. Results are ranked by a combined score and each \
+lists the file, its line range, and the best matching lines.
+
+Use it when you don't know where something lives: 'where are retries configured', \
+'parse_manifest', 'code that validates user input'. Then read the lines you need \
+with read_files. Set path to search one directory. lexical_weight and \
+semantic_weight (0-1) shift the ranking toward exact matches or toward meaning.";
+
+/// Settings for [`HybridSearcher`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HybridSearchConfig {
+    /// Weight of the lexical score in the combined score
+    pub lexical_weight: f32,
+    /// Weight of the semantic score in the combined score
+    pub semantic_weight: f32,
+    pub max_results: usize,
+    /// Embedding model for the semantic half; unset searches lexically
+    pub embedding_model: Option<String>,
+    /// Most files indexed for semantic search
+    pub max_indexed_files: usize,
+}
+
+impl Default for HybridSearchConfig {
+    fn default() -> Self {
+        Self {
+            lexical_weight: 0.5,
+            semantic_weight: 0.5,
+            max_results: DEFAULT_MAX_RESULTS,
+            embedding_model: None,
+            max_indexed_files: 2000,
+        }
+    }
+}
+
+/// One ranked chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HybridHit {
+    /// Project-relative path
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: f32,
+    /// Share of the query's terms in the chunk, when any matched
+    pub lexical: Option<f32>,
+    /// Similarity to the query, when semantic search ran and it's close
+    pub semantic: Option<f32>,
+    /// Matching lines, or the chunk's first lines for semantic-only hits
+    pub lines: Vec<(usize, String)>,
+}
+
+/// The result of one search.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HybridResults {
+    pub hits: Vec<HybridHit>,
+    /// Weights used after normalizing, lexical then semantic
+    pub weights: (f32, f32),
+    /// Why the semantic half didn't run, when it didn't
+    pub lexical_only: Option<String>,
+}
+
+impl HybridResults {
+    /// Render for the tool output.
+    pub fn to_text(&self, query: &str) -> String {
+        if self.hits.is_empty() {
+            let mut out = format!("No results for \"{}\"", query);
+            if let Some(ref reason) = self.lexical_only {
+                out.push_str(&format!(" (lexical only: {})", reason));
+            }
+            return out;
+        }
+        let mode = match self.lexical_only {
+            Some(ref reason) => format!("lexical only: {}", reason),
+            None => format!(
+                "lexical + semantic, weights {:.2}/{:.2}",
+                self.weights.0, self.weights.1
+            ),
+        };
+        let mut out = format!(
+            "{} result{} for \"{}\" ({})\n",
+            self.hits.len(),
+            if self.hits.len() == 1 { "" } else { "s" },
+            query,
+            mode
+        );
+        for (i, hit) in self.hits.iter().enumerate() {
+            let parts: Vec<String> = [("lexical", hit.lexical), ("semantic", hit.semantic)]
+                .into_iter()
+                .filter_map(|(name, score)| score.map(|s| format!("{} {:.2}", name, s)))
+                .collect();
+            out.push_str(&format!(
+                "\n{}. {}:{}-{}  score {:.2} ({})\n",
+                i + 1,
+                hit.path,
+                hit.start_line,
+                hit.end_line,
+                hit.score,
+                parts.join(", ")
+            ));
+            for (line, text) in &hit.lines {
+                let text = text.trim();
+                let shown = truncate_bytes(text, MAX_SNIPPET_LINE_BYTES);
+                let cut = if shown.len() < text.len() { " ..." } else { "" };
+                out.push_str(&format!("   {}: {}{}\n", line, shown, cut));
+            }
+        }
+        out
+    }
+}
+
+/// Size and modification time a file had when it was indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    size: u64,
+    mtime_ns: u128,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let mtime_ns = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        Some(Self {
+            size: metadata.len(),
+            mtime_ns,
+        })
+    }
+}
+
+struct IndexedChunk {
+    start_line: usize,
+    end_line: usize,
+    /// First non-blank lines, shown for semantic-only hits
+    preview: Vec<(usize, String)>,
+    embedding: Vec<f32>,
+}
+
+struct IndexedFile {
+    stamp: FileStamp,
+    chunks: Vec<IndexedChunk>,
+}
+
+/// A chunk read from disk, waiting for its embedding.
+struct PendingChunk {
+    path: String,
+    start_line: usize,
+    end_line: usize,
+    preview: Vec<(usize, String)>,
+    text: String,
+}
+
+/// A line containing one of the query's terms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineHit {
+    path: String,
+    line: usize,
+    text: String,
+}
+
+/// Lexical score of one chunk.
+struct LexicalChunk {
+    score: f32,
+    hits: usize,
+    lines: Vec<(usize, String)>,
+}
+
+/// Semantic score of one indexed chunk.
+struct SemanticChunk {
+    score: f32,
+    end_line: usize,
+    preview: Vec<(usize, String)>,
+}
+
+/// Chunk scores keyed by (path, start line).
+type ChunkScores<T> = HashMap<(String, usize), T>;
+
+/// The `run` tool's sandbox, where ripgrep runs.
+struct SearchSandbox {
+    mounts: Arc<SandboxMounts>,
+    executor: SandboxExecutor,
+    path_policy: SandboxPathPolicy,
+}
+
+/// Searches a project's files lexically and, given an embedder, by meaning.
+pub struct HybridSearcher {
+    root: PathBuf,
+    config: HybridSearchConfig,
+    embedder: Option<Arc<dyn Provider>>,
+    sandbox: Option<SearchSandbox>,
+    access_log: Option<Arc<AccessLog>>,
+    index: Mutex<HashMap<String, IndexedFile>>,
+}
+
+impl HybridSearcher {
+    /// Searcher for files under `project_root`.
+    pub fn new(project_root: PathBuf, config: HybridSearchConfig) -> Self {
+        let root = project_root.canonicalize().unwrap_or(project_root);
+        Self {
+            root,
+            config,
+            embedder: None,
+            sandbox: None,
+            access_log: None,
+            index: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Embed with `provider` (using the config's `embedding_model`).
+    pub fn with_embedder(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.embedder = Some(provider);
+        self
+    }
+
+    /// Run ripgrep in `executor`'s sandbox, read-only; without a sandbox the
+    /// project's files are scanned directly.
+    pub fn with_sandbox(
+        mut self,
+        mounts: Arc<SandboxMounts>,
+        executor: SandboxExecutor,
+        path_policy: SandboxPathPolicy,
+    ) -> Self {
+        self.sandbox = Some(SearchSandbox {
+            mounts,
+            executor,
+            path_policy,
+        });
+        self
+    }
+
+    /// Log reads of untracked files, asking first under the log's policy.
+    pub fn with_access_log(mut self, log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+
+    pub fn config(&self) -> &HybridSearchConfig {
+        &self.config
+    }
+
+    /// Chunks currently in the semantic index.
+    pub async fn indexed_chunks(&self) -> usize {
+        self.index.lock().await.values().map(|f| f.chunks.len()).sum()
+    }
+
+    /// Search for `query` under `scope` (a project-relative or absolute
+    /// directory or file; the whole project when `None`). `weights`
+    /// overrides the configured lexical and semantic weights.
+    pub async fn search(
+        &self,
+        query: &str,
+        scope: Option<&str>,
+        max_results: Option<usize>,
+        weights: (Option<f32>, Option<f32>),
+    ) -> Result<HybridResults, String> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Err("query is empty".to_string());
+        }
+        let scope = self.resolve_scope(scope)?;
+        let max_results = max_results
+            .unwrap_or(self.config.max_results)
+            .clamp(1, MAX_RESULTS_LIMIT);
+        let weights = normalize_weights(
+            weights.0.unwrap_or(self.config.lexical_weight),
+            weights.1.unwrap_or(self.config.semantic_weight),
+        )?;
+
+        let mut terms = tokenize(query);
+        let mut seen = HashSet::new();
+        terms.retain(|t| seen.insert(t.clone()));
+        let phrase = (terms.len() > 1).then(|| query.to_lowercase());
+
+        let checkpoint = Checkpoint::current();
+        let lexical = self.lexical_hits(&scope, &terms, phrase.as_deref(), &checkpoint);
+        let semantic = async {
+            if weights.1 == 0.0 {
+                return Err("semantic_weight is 0".to_string());
+            }
+            self.semantic_scores(query, &scope, &checkpoint).await
+        };
+        let (lexical, semantic) = tokio::join!(lexical, semantic);
+        let lexical = lexical.map_err(|e| format!("lexical search failed: {}", e))?;
+        let lexical = score_lexical(&lexical, &terms, phrase.as_deref());
+
+        let mut results = HybridResults {
+            weights,
+            ..Default::default()
+        };
+        let semantic = match semantic {
+            Ok(scores) => scores,
+            Err(reason) => {
+                results.lexical_only = Some(reason);
+                results.weights = (1.0, 0.0);
+                HashMap::new()
+            }
+        };
+        results.hits = merge(lexical, semantic, results.weights, max_results);
+        for hit in &mut results.hits {
+            if hit.end_line == 0 {
+                hit.end_line = chunk_end(&self.root.join(&hit.path), hit.start_line);
+            }
+        }
+        Ok(results)
+    }
+
+    /// `scope` relative to the root, checked to be inside it.
+    fn resolve_scope(&self, scope: Option<&str>) -> Result<String, String> {
+        let Some(scope) = scope.map(str::trim).filter(|s| !s.is_empty() && *s != ".") else {
+            return Ok(String::new());
+        };
+        let requested = Path::new(scope);
+        let host = if requested.is_absolute() {
+            requested.to_path_buf()
+        } else {
+            self.root.join(requested)
+        };
+        let canonical = host
+            .canonicalize()
+            .map_err(|e| format!("cannot open {}: {}", scope, e))?;
+        let relative = canonical
+            .strip_prefix(&self.root)
+            .map_err(|_| format!("{} is outside the project root", scope))?;
+        Ok(relative.to_string_lossy().into_owned())
+    }
+
+    /// Project files, from git or a directory walk.
+    async fn candidates(&self) -> Result<Vec<String>, String> {
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || summary_candidates(&root, 1))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// The files of `paths` (project-relative) this search may read, with
+    /// their resolved host paths: ones that stay inside the root once
+    /// symlinks are resolved, and that the access log lets through. Untracked
+    /// files the user declines to share are left out rather than failing the
+    /// search.
+    async fn readable(&self, paths: Vec<String>) -> Vec<(String, PathBuf)> {
+        let mut files: Vec<(String, PathBuf)> = paths
+            .into_iter()
+            .filter_map(|path| contained(&self.root, &path).map(|host| (path, host)))
+            .collect();
+        let Some(ref log) = self.access_log else {
+            return files;
+        };
+        let hosts: Vec<PathBuf> = files.iter().map(|(_, host)| host.clone()).collect();
+        if let Err(reason) = log.check("hybrid_search", &hosts).await {
+            tracing::info!(reason = %reason, "Hybrid search skipping untracked files");
+            files.retain(|(_, host)| log.classify(host).is_none());
+        }
+        files
+    }
+
+    /// Lines under `scope` containing any of `terms` (or `phrase`),
+    /// case-insensitively: from ripgrep when it runs in the sandbox, which
+    /// also honors `.gitignore`, else by reading the project's files.
+    async fn lexical_hits(
+        &self,
+        scope: &str,
+        terms: &[String],
+        phrase: Option<&str>,
+        checkpoint: &Checkpoint,
+    ) -> Result<Vec<LineHit>, String> {
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let patterns: Vec<String> = terms.iter().cloned().chain(phrase.map(str::to_string)).collect();
+        if let Some(mut hits) = self.ripgrep(scope, &patterns).await {
+            let mut paths: Vec<String> = hits.iter().map(|h| h.path.clone()).collect();
+            paths.dedup();
+            let allowed: HashSet<String> =
+                self.readable(paths).await.into_iter().map(|(path, _)| path).collect();
+            hits.retain(|h| allowed.contains(&h.path));
+            return Ok(hits);
+        }
+        let candidates = self.candidates().await?;
+        let files = self
+            .readable(candidates.into_iter().filter(|p| in_scope(p, scope)).collect())
+            .await;
+        let checkpoint = checkpoint.clone();
+        tokio::task::spawn_blocking(move || scan_files(&files, &patterns, &checkpoint))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Matching lines from ripgrep in the sandbox; `None` without a sandbox
+    /// or when ripgrep can't run in it.
+    async fn ripgrep(&self, scope: &str, patterns: &[String]) -> Option<Vec<LineHit>> {
+        let sandbox = self.sandbox.as_ref()?;
+        // The project root is mounted at its host path
+        let root = sandbox.mounts.project_root();
+        let target = if scope.is_empty() { root.clone() } else { root.join(scope) };
+        let mut command = format!(
+            "rg --null --no-heading --line-number --with-filename --color never \
+             --ignore-case --fixed-strings --max-filesize 1M --max-count {}",
+            MAX_LINES_PER_FILE
+        );
+        for pattern in patterns {
+            command.push_str(" -e ");
+            command.push_str(&shell_escape(pattern));
+        }
+        command.push_str(" -- ");
+        command.push_str(&shell_escape(&target.to_string_lossy()));
+
+        let result = sandbox
+            .executor
+            .execute(&command, &sandbox.mounts, RIPGREP_TIMEOUT_SECS, &sandbox.path_policy, None, true)
+            .await;
+        let result = match result {
+            // Exit status 1 means no matches; 2 an error, possibly after some
+            Ok(result) if result.sandbox_error.is_none() && !result.timed_out && (0..=2).contains(&result.exit_code) => result,
+            Ok(result) => {
+                tracing::debug!(exit_code = result.exit_code, stderr = %result.stderr.trim(), "ripgrep unavailable, scanning files directly");
+                return None;
+            }
+            Err(e) => {
+                tracing::debug!(error = %e, "ripgrep unavailable, scanning files directly");
+                return None;
+            }
+        };
+        if result.exit_code == 2 {
+            tracing::warn!(stderr = %result.stderr.trim(), "ripgrep reported errors");
+        }
+        Some(parse_ripgrep(&result.stdout, root))
+    }
+
+    /// Similarity of every indexed chunk under `scope` to `query`, keyed by
+    /// (path, start line). Updates the index first.
+    async fn semantic_scores(
+        &self,
+        query: &str,
+        scope: &str,
+        checkpoint: &Checkpoint,
+    ) -> Result<ChunkScores<SemanticChunk>, String> {
+        let (Some(provider), Some(model)) = (self.embedder.as_ref(), self.config.embedding_model.as_deref())
+        else {
+            return Err("no embedding model is configured".to_string());
+        };
+
+        let mut index = self.index.lock().await;
+        let mut candidates = self.candidates().await.map_err(|e| format!("indexing failed: {}", e))?;
+        let max_files = self.config.max_indexed_files;
+        if candidates.len() > max_files {
+            tracing::warn!(
+                files = candidates.len(),
+                max_files,
+                "Too many files for the hybrid search index; indexing the first ones"
+            );
+            candidates.truncate(max_files);
+        }
+        let files = self.readable(candidates).await;
+        let pending = {
+            let stamps: HashMap<String, FileStamp> =
+                index.iter().map(|(path, file)| (path.clone(), file.stamp)).collect();
+            let checkpoint = checkpoint.clone();
+            tokio::task::spawn_blocking(move || stale_chunks(files, &stamps, &checkpoint))
+                .await
+                .map_err(|e| format!("indexing failed: {}", e))?
+        };
+        let (live, pending) = pending;
+        if checkpoint.is_cancelled() {
+            return Err("cancelled".to_string());
+        }
+        index.retain(|path, _| live.contains_key(path));
+
+        // The query rides along with the first batch
+        let mut texts = vec![truncate_bytes(query, MAX_CHUNK_BYTES).to_string()];
+        texts.extend(pending.iter().map(|c| c.text.clone()));
+        let mut vectors = checkpoint
+            .run(embed_all(provider, model, texts))
+            .await
+            .and_then(|vectors| vectors)
+            .map_err(|e| format!("embedding failed: {}", e))?
+            .into_iter();
+        let query_vector = vectors.next().unwrap_or_default();
+
+        let mut refreshed: HashMap<String, Vec<IndexedChunk>> = HashMap::new();
+        for (chunk, embedding) in pending.into_iter().zip(vectors) {
+            refreshed.entry(chunk.path).or_default().push(IndexedChunk {
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                preview: chunk.preview,
+                embedding,
+            });
+        }
+        for (path, stamp) in live {
+            if let Some(stamp) = stamp {
+                let chunks = refreshed.remove(&path).unwrap_or_default();
+                index.insert(path, IndexedFile { stamp, chunks });
+            }
+        }
+        tracing::debug!(
+            files = index.len(),
+            chunks = index.values().map(|f| f.chunks.len()).sum::<usize>(),
+            "Hybrid search index updated"
+        );
+
+        let mut scores = HashMap::new();
+        for (path, file) in index.iter().filter(|(path, _)| in_scope(path, scope)) {
+            for chunk in &file.chunks {
+                let similarity = cosine_similarity(&query_vector, &chunk.embedding);
+                if similarity >= MIN_SEMANTIC_SCORE {
+                    let scored = SemanticChunk {
+                        score: similarity.min(1.0),
+                        end_line: chunk.end_line,
+                        preview: chunk.preview.clone(),
+                    };
+                    scores.insert((path.clone(), chunk.start_line), scored);
+                }
+            }
+        }
+        Ok(scores)
+    }
+}
+
+/// Weights scaled to sum to 1.
+fn normalize_weights(lexical: f32, semantic: f32) -> Result<(f32, f32), String> {
+    if !(0.0..=1.0).contains(&lexical) || !(0.0..=1.0).contains(&semantic) {
+        return Err("weights must be between 0 and 1".to_string());
+    }
+    let total = lexical + semantic;
+    if total == 0.0 {
+        return Err("lexical_weight and semantic_weight can't both be 0".to_string());
+    }
+    Ok((lexical / total, semantic / total))
+}
+
+fn in_scope(path: &str, scope: &str) -> bool {
+    scope.is_empty()
+        || path == scope
+        || path.strip_prefix(scope).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// First line of the chunk holding `line`.
+fn chunk_start(line: usize) -> usize {
+    (line.saturating_sub(1) / CHUNK_LINES) * CHUNK_LINES + 1
+}
+
+/// `path` (project-relative) resolved on the host, unless it leads outside
+/// `root` (canonical), e.g. through a symlink.
+fn contained(root: &Path, path: &str) -> Option<PathBuf> {
+    let resolved = root.join(path).canonicalize().ok()?;
+    if resolved.starts_with(root) {
+        Some(resolved)
+    } else {
+        tracing::debug!(path, resolved = %resolved.display(), "Hybrid search skipping file outside the project root");
+        None
+    }
+}
+
+/// Last line of the chunk starting at `start`, clamped to the file's length.
+fn chunk_end(path: &Path, start: usize) -> usize {
+    let last = start + CHUNK_LINES - 1;
+    match std::fs::read_to_string(path) {
+        Ok(text) => last.min(text.lines().count()).max(start),
+        Err(_) => last,
+    }
+}
+
+/// Lines of `rg --null --line-number` output: path, NUL, number, colon,
+/// text. Paths are made relative to `root`.
+fn parse_ripgrep(stdout: &str, root: &Path) -> Vec<LineHit> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (path, rest) = line.split_once('\0')?;
+            let (number, text) = rest.split_once(':')?;
+            let path = Path::new(path);
+            let path = path.strip_prefix(root).or_else(|_| path.strip_prefix(".")).unwrap_or(path);
+            Some(LineHit {
+                path: path.to_string_lossy().into_owned(),
+                line: number.parse().ok()?,
+                text: text.to_string(),
+            })
+        })
+        .take(MAX_LEXICAL_LINES)
+        .collect()
+}
+
+/// Matching lines of `files` (project-relative and resolved paths), until
+/// the run is cancelled.
+fn scan_files(files: &[(String, PathBuf)], patterns: &[String], checkpoint: &Checkpoint) -> Vec<LineHit> {
+    let patterns: Vec<String> = patterns.iter().map(|p| p.to_lowercase()).collect();
+    let mut hits = Vec::new();
+    for (path, host) in files {
+        if checkpoint.is_cancelled() {
+            break;
+        }
+        let Ok(text) = std::fs::read_to_string(host) else {
+            continue;
+        };
+        let matching = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                let line = line.to_lowercase();
+                patterns.iter().any(|p| line.contains(p.as_str()))
+            })
+            .take(MAX_LINES_PER_FILE);
+        for (i, line) in matching {
+            hits.push(LineHit {
+                path: path.clone(),
+                line: i + 1,
+                text: line.to_string(),
+            });
+        }
+        if hits.len() >= MAX_LEXICAL_LINES {
+            hits.truncate(MAX_LEXICAL_LINES);
+            break;
+        }
+    }
+    hits
+}
+
+/// Score each chunk with hits by the share of `terms` it contains, with a
+/// bonus when it contains the whole `phrase`.
+fn score_lexical(
+    hits: &[LineHit],
+    terms: &[String],
+    phrase: Option<&str>,
+) -> ChunkScores<LexicalChunk> {
+    /// Terms matched (by index), whether the phrase matched, and the lines
+    #[derive(Default)]
+    struct Matches {
+        terms: HashSet<usize>,
+        phrase: bool,
+        lines: Vec<(usize, String)>,
+    }
+
+    let mut chunks: ChunkScores<Matches> = HashMap::new();
+    for hit in hits {
+        let lower = hit.text.to_lowercase();
+        let entry = chunks
+            .entry((hit.path.clone(), chunk_start(hit.line)))
+            .or_default();
+        entry.terms.extend(
+            terms
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| lower.contains(t.as_str()))
+                .map(|(i, _)| i),
+        );
+        entry.phrase |= phrase.is_some_and(|p| lower.contains(p));
+        entry.lines.push((hit.line, hit.text.clone()));
+    }
+    chunks
+        .into_iter()
+        .map(|(key, matches)| {
+            let coverage = matches.terms.len() as f32 / terms.len().max(1) as f32;
+            let score = match phrase {
+                Some(_) => 0.8 * coverage + if matches.phrase { 0.2 } else { 0.0 },
+                None => coverage,
+            };
+            let mut lines = matches.lines;
+            lines.sort();
+            let chunk = LexicalChunk {
+                score,
+                hits: lines.len(),
+                lines,
+            };
+            (key, chunk)
+        })
+        .collect()
+}
+
+/// Combine lexical and semantic chunk scores into the top `max_results`.
+fn merge(
+    lexical: ChunkScores<LexicalChunk>,
+    semantic: ChunkScores<SemanticChunk>,
+    weights: (f32, f32),
+    max_results: usize,
+) -> Vec<HybridHit> {
+    let keys: HashSet<&(String, usize)> = lexical.keys().chain(semantic.keys()).collect();
+    let mut hits: Vec<(HybridHit, usize)> = keys
+        .into_iter()
+        .map(|key| {
+            let lex = lexical.get(key);
+            let sem = semantic.get(key);
+            let score = weights.0 * lex.map_or(0.0, |l| l.score) + weights.1 * sem.map_or(0.0, |s| s.score);
+            let lines = match (lex, sem) {
+                (Some(l), _) => l.lines.iter().take(SNIPPET_LINES).cloned().collect(),
+                (None, Some(s)) => s.preview.clone(),
+                (None, None) => Vec::new(),
+            };
+            let hit = HybridHit {
+                path: key.0.clone(),
+                start_line: key.1,
+                // Filled in from the file for chunks the index hasn't seen
+                end_line: sem.map_or(0, |s| s.end_line),
+                score,
+                lexical: lex.map(|l| l.score),
+                semantic: sem.map(|s| s.score),
+                lines,
+            };
+            (hit, lex.map_or(0, |l| l.hits))
+        })
+        // A zero weight drops the hits found only that way
+        .filter(|(hit, _)| hit.score > 0.0)
+        .collect();
+    hits.sort_by(|(a, a_hits), (b, b_hits)| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b_hits.cmp(a_hits))
+            .then_with(|| (&a.path, a.start_line).cmp(&(&b.path, b.start_line)))
+    });
+    hits.into_iter().take(max_results).map(|(hit, _)| hit).collect()
+}
+
+/// Of `files` (project-relative and resolved paths), the ones to keep in
+/// the index with their current stamps (`None` for ones already indexed
+/// and unchanged), and the chunks of new or changed files. Stops early when
+/// the run is cancelled.
+fn stale_chunks(
+    files: Vec<(String, PathBuf)>,
+    indexed: &HashMap<String, FileStamp>,
+    checkpoint: &Checkpoint,
+) -> (HashMap<String, Option<FileStamp>>, Vec<PendingChunk>) {
+    let mut live = HashMap::new();
+    let mut pending = Vec::new();
+    for (path, host) in files {
+        if checkpoint.is_cancelled() {
+            break;
+        }
+        let Some(stamp) = FileStamp::of(&host).filter(|s| s.size <= MAX_INDEX_FILE_BYTES) else {
+            continue;
+        };
+        if indexed.get(&path) == Some(&stamp) {
+            live.insert(path, None);
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(&host) else {
+            continue;
+        };
+        live.insert(path.clone(), Some(stamp));
+        if bytes.contains(&0) {
+            continue;
+        }
+        pending.extend(chunk_file(&path, &String::from_utf8_lossy(&bytes)));
+    }
+    (live, pending)
+}
+
+/// `text` in chunks of [`CHUNK_LINES`] lines, skipping blank ones.
+fn chunk_file(path: &str, text: &str) -> Vec<PendingChunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    lines
+        .chunks(CHUNK_LINES)
+        .enumerate()
+        .filter(|(_, chunk)| chunk.iter().any(|l| !l.trim().is_empty()))
+        .map(|(i, chunk)| {
+            let start_line = i * CHUNK_LINES + 1;
+            let body = chunk.join("\n");
+            let preview = chunk
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| !l.trim().is_empty())
+                .take(SNIPPET_LINES)
+                .map(|(j, l)| (start_line + j, l.to_string()))
+                .collect();
+            PendingChunk {
+                path: path.to_string(),
+                start_line,
+                end_line: start_line + chunk.len() - 1,
+                preview,
+                // The path often says what the code is about
+                text: truncate_bytes(&format!("{}\n{}", path, body), MAX_CHUNK_BYTES).to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Embed `texts` in batches, several requests at a time, keeping order.
+async fn embed_all(provider: &Arc<dyn Provider>, model: &str, texts: Vec<String>) -> Result<Vec<Vec<f32>>, Error> {
+    let permits = Arc::new(Semaphore::new(EMBED_CONCURRENCY));
+    let mut tasks = tokio::task::JoinSet::new();
+    let batches: Vec<Vec<String>> = texts.chunks(EMBED_BATCH_SIZE).map(<[String]>::to_vec).collect();
+    for (i, batch) in batches.into_iter().enumerate() {
+        let provider = Arc::clone(provider);
+        let model = model.to_string();
+        let permits = Arc::clone(&permits);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let vectors = provider.embed(&model, &batch).await;
+            (i, batch.len(), vectors)
+        });
+    }
+    let mut results: Vec<(usize, Vec<Vec<f32>>)> = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (i, expected, vectors) = joined.map_err(|e| Error::Unknown(e.to_string()))?;
+        let vectors = vectors?;
+        if vectors.len() != expected {
+            return Err(Error::Unknown(format!(
+                "expected {} embeddings, got {}",
+                expected,
+                vectors.len()
+            )));
+        }
+        results.push((i, vectors));
+    }
+    results.sort_by_key(|(i, _)| *i);
+    Ok(results.into_iter().flat_map(|(_, vectors)| vectors).collect())
+}
+
+/// Tool searching the project lexically and semantically at once.
+pub struct HybridSearchTool {
+    searcher: Arc<HybridSearcher>,
+}
+
+#[derive(Deserialize)]
+struct HybridSearchArgs {
+    query: String,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    max_results: Option<usize>,
+    #[serde(default)]
+    lexical_weight: Option<f32>,
+    #[serde(default)]
+    semantic_weight: Option<f32>,
+}
+
+impl HybridSearchTool {
+    pub fn new(searcher: Arc<HybridSearcher>) -> Self {
+        Self { searcher }
+    }
+}
+
+#[async_trait]
+impl Tool for HybridSearchTool {
+    fn name(&self) -> &str {
+        "hybrid_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search the project by keyword and by meaning, ranked together"
+    }
+
+    fn tool_description(&self) -> &str {
+        HYBRID_SEARCH_TOOL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "query",
+                    PropertySchema::string("Identifiers, phrases, or a description of the code to find"),
+                    true,
+                )
+                .add_property(
+                    "path",
+                    PropertySchema::string("Directory or file to search, relative to the project root (default: all)"),
+                    false,
+                )
+                .add_property(
+                    "max_results",
+                    PropertySchema::integer(format!(
+                        "Results to return (default {}, max {})",
+                        self.searcher.config().max_results,
+                        MAX_RESULTS_LIMIT
+                    )),
+                    false,
+                )
+                .add_property(
+                    "lexical_weight",
+                    PropertySchema::number("Weight of exact keyword matches, 0-1"),
+                    false,
+                )
+                .add_property(
+                    "semantic_weight",
+                    PropertySchema::number("Weight of similarity in meaning, 0-1"),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let args: HybridSearchArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("hybrid_search", format!("Invalid arguments: {}", e)))?;
+        let search = self.searcher.search(
+            &args.query,
+            args.path.as_deref(),
+            args.max_results,
+            (args.lexical_weight, args.semantic_weight),
+        );
+        // Stopped scans return partial results; a cancelled run drops them
+        let results = Checkpoint::current().run(search).await?;
+        Ok(match results {
+            Ok(results) => ToolOutput::success(results.to_text(args.query.trim())),
+            Err(e) => ToolOutput::error(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qq_core::testing::MockProvider;
+
+    fn project() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("src")).unwrap();
+        std::fs::write(
+            root.path().join("src/retry.rs"),
+            "// Backoff between attempts when the server is busy\n\
+             pub fn backoff_delay(attempt: u32) -> u64 {\n    100 * 2u64.pow(attempt)\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.path().join("src/manifest.rs"),
+            "pub fn parse_manifest(text: &str) -> Manifest {\n    toml::from_str(text).unwrap()\n}\n",
+        )
+        .unwrap();
+        std::fs::write(root.path().join("README.md"), "Call parse_manifest to read a manifest.\n").unwrap();
+        root
+    }
+
+    fn searcher(root: &Path, embeddings: bool) -> HybridSearcher {
+        let config = HybridSearchConfig {
+            embedding_model: embeddings.then(|| "embed".to_string()),
+            ..Default::default()
+        };
+        let searcher = HybridSearcher::new(root.to_path_buf(), config);
+        if embeddings {
+            searcher.with_embedder(Arc::new(MockProvider::new()))
+        } else {
+            searcher
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lexical_hits_rank_by_term_coverage() {
+        let root = project();
+        let results = searcher(root.path(), false)
+            .search("parse_manifest toml", None, None, (None, None))
+            .await
+            .unwrap();
+        assert_eq!(results.lexical_only.as_deref(), Some("no embedding model is configured"));
+        let paths: Vec<&str> = results.hits.iter().map(|h| h.path.as_str()).collect();
+        assert_eq!(paths, ["src/manifest.rs", "README.md"]);
+        assert_eq!(results.hits[0].lexical, Some(0.8));
+        assert_eq!((results.hits[0].start_line, results.hits[0].end_line), (1, 3));
+        assert_eq!(results.hits[0].lines[0], (1, "pub fn parse_manifest(text: &str) -> Manifest {".to_string()));
+
+        let text = results.to_text("parse_manifest toml");
+        assert!(text.starts_with("2 results for \"parse_manifest toml\" (lexical only"));
+        assert!(text.contains("1. src/manifest.rs:1-3  score 0.80 (lexical 0.80)"));
+    }
+
+    #[tokio::test]
+    async fn test_semantic_hits_merge_with_lexical() {
+        let root = project();
+        let searcher = searcher(root.path(), true);
+        let results = searcher
+            .search("busy server backoff", None, None, (None, None))
+            .await
+            .unwrap();
+        assert!(results.lexical_only.is_none());
+        let top = &results.hits[0];
+        assert_eq!(top.path, "src/retry.rs");
+        assert!(top.lexical.is_some() && top.semantic.is_some());
+        // Found both ways, listed once
+        assert_eq!(results.hits.iter().filter(|h| h.path == "src/retry.rs").count(), 1);
+        assert_eq!(searcher.indexed_chunks().await, 3);
+
+        // Unchanged files aren't embedded again; a scope limits results
+        let scoped = searcher
+            .search("manifest", Some("src"), None, (Some(0.0), Some(1.0)))
+            .await
+            .unwrap();
+        assert_eq!(scoped.weights, (0.0, 1.0));
+        assert!(scoped.hits.iter().all(|h| h.path.starts_with("src/")));
+        assert!(!scoped.hits.is_empty());
+        assert!(scoped.hits.iter().all(|h| h.semantic == Some(h.score)));
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_bad_input() {
+        let root = project();
+        let outside = tempfile::tempdir().unwrap();
+        let searcher = searcher(root.path(), false);
+        let err = searcher
+            .search("x", outside.path().to_str(), None, (None, None))
+            .await
+            .unwrap_err();
+        assert!(err.contains("outside the project root"), "{}", err);
+        let err = searcher.search("x", None, None, (Some(0.0), Some(0.0))).await.unwrap_err();
+        assert!(err.contains("both be 0"), "{}", err);
+        assert!(searcher.search("  ", None, None, (None, None)).await.is_err());
+
+        let none = searcher.search("nonexistent_symbol", None, None, (None, None)).await.unwrap();
+        assert!(none.to_text("nonexistent_symbol").starts_with("No results"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlink_out_of_root_not_read() {
+        let root = project();
+        let outside = tempfile::tempdir().unwrap();
+        let secret = outside.path().join("secret.rs");
+        std::fs::write(&secret, "const API_TOKEN: &str = \"hunter2\";\n").unwrap();
+        std::os::unix::fs::symlink(&secret, root.path().join("src/leak.rs")).unwrap();
+
+        let searcher = searcher(root.path(), true);
+        let results = searcher.search("api_token hunter2", None, None, (None, None)).await.unwrap();
+        assert!(results.hits.iter().all(|h| h.path != "src/leak.rs"), "{:?}", results.hits);
+        assert!(results.hits.iter().all(|h| h.lines.iter().all(|(_, l)| !l.contains("hunter2"))));
+        // Only the three chunks of the project's own files were embedded
+        assert_eq!(searcher.indexed_chunks().await, 3);
+        assert_eq!(contained(&root.path().canonicalize().unwrap(), "src/leak.rs"), None);
+    }
+
+    #[test]
+    fn test_parse_ripgrep() {
+        let hits = parse_ripgrep(
+            "/work/src/a.rs\x0012:let x = a:b;\n./src/b c.rs\x003:fn main() {}\nnot a hit\n",
+            Path::new("/work"),
+        );
+        assert_eq!(
+            hits,
+            [
+                LineHit { path: "src/a.rs".into(), line: 12, text: "let x = a:b;".into() },
+                LineHit { path: "src/b c.rs".into(), line: 3, text: "fn main() {}".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunk_file() {
+        let text: String = (1..=90).map(|i| format!("line {}\n", i)).collect();
+        let chunks = chunk_file("a.txt", &text);
+        let ranges: Vec<(usize, usize)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, [(1, 40), (41, 80), (81, 90)]);
+        assert_eq!(chunks[1].preview[0], (41, "line 41".to_string()));
+        assert!(chunks[0].text.starts_with("a.txt\nline 1\n"));
+        assert_eq!(chunk_start(40), 1);
+        assert_eq!(chunk_start(41), 41);
+    }
+}
@@ -70,6 +70,10 @@ impl Tool for ReadImageTool {
         true
     }
 
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
         let args: ReadImageArgs = serde_json::from_value(arguments)
             .map_err(|e| Error::Unknown(format!("Invalid arguments: {}", e)))?;
@@ -0,0 +1,1126 @@
+//! Cross-session knowledge base.
+//!
+//! Finished session summaries and the results of selected agent calls (by
+//! default the researcher's) are appended to a JSON-lines file in the state
+//! directory, tagged with the project they came from. The `recall` tool
+//! searches the current project's entries and returns snippets with
+//! session/date citations, so a later session can reuse what an earlier one
+//! already figured out instead of researching it again.
+//!
+//! Retrieval is hybrid and fully local: BM25 over word tokens (the full-text
+//! half) blended with cosine similarity of hashed word/trigram feature
+//! vectors (the embedding half, which tolerates inflections and typos). The
+//! index is rebuilt per query; per-project entry counts stay small enough
+//! that a linear scan is cheaper than maintaining one on disk.
+//!
+//! The file is a journal: each entry is one line, written in a single
+//! append and synced before `add` returns, so a crash loses at most the
+//! line in flight (a torn last line is skipped on load). Concurrent
+//! sessions serialize writes through a `<file>.lock` sibling, waiting up to
+//! a busy timeout, and pick up each other's entries before assigning ids.
+//! [`KnowledgeStore::compact`] rewrites the file atomically without corrupt
+//! lines and duplicates; `export`/`import` move entries between machines.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use qq_core::{Error, PropertySchema, ScopeRetry, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use crate::file_lock::FileLock;
+
+/// Entries longer than this are truncated when ingested.
+const MAX_ENTRY_CHARS: usize = 6000;
+
+/// Snippets returned by `recall` are cut to roughly this length.
+const SNIPPET_CHARS: usize = 500;
+
+/// Default and maximum number of `recall` results.
+const DEFAULT_LIMIT: usize = 5;
+const MAX_LIMIT: usize = 10;
+
+/// Dimension of the hashed feature vectors.
+const VECTOR_DIM: usize = 512;
+
+/// Weight of the lexical (BM25) score in the blended score; the rest is
+/// vector similarity.
+const LEXICAL_WEIGHT: f32 = 0.7;
+
+/// Minimum vector similarity for an entry with no lexical match to count.
+const MIN_VECTOR_ONLY_SCORE: f32 = 0.25;
+
+/// New entries this similar to an existing one are treated as duplicates.
+const DUPLICATE_SIMILARITY: f32 = 0.95;
+
+/// BM25 parameters.
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+/// Words too common to carry meaning.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "how", "in", "is", "it", "of",
+    "on", "or", "that", "the", "this", "to", "was", "what", "when", "where", "which", "with",
+];
+
+/// Where a knowledge entry came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KnowledgeKind {
+    /// Summary of a finished session.
+    Summary,
+    /// Result of a tool or agent call worth keeping.
+    Finding,
+}
+
+/// One ingested piece of knowledge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeEntry {
+    pub id: u64,
+    /// Project key (the tools root) the entry belongs to.
+    pub project: String,
+    /// Session that produced the entry.
+    pub session: String,
+    pub created: DateTime<Utc>,
+    pub kind: KnowledgeKind,
+    /// Tool or agent that produced a finding.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub text: String,
+}
+
+impl KnowledgeEntry {
+    /// Citation shown with recalled snippets, e.g.
+    /// `[session 20260306-142210, 2026-03-06, researcher]`.
+    pub fn citation(&self) -> String {
+        let origin = match (self.kind, self.source.as_deref()) {
+            (KnowledgeKind::Summary, _) => "session summary",
+            (KnowledgeKind::Finding, Some(source)) => source,
+            (KnowledgeKind::Finding, None) => "finding",
+        };
+        format!(
+            "[session {}, {}, {}]",
+            self.session,
+            self.created.format("%Y-%m-%d"),
+            origin
+        )
+    }
+}
+
+/// Outcome of [`KnowledgeStore::compact`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub kept: usize,
+    /// Unparsable lines dropped (torn writes, hand edits)
+    pub corrupt_lines: usize,
+    /// Entries dropped as near-duplicates of an earlier one in the same project
+    pub duplicates: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Outcome of [`KnowledgeStore::import`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub added: usize,
+    /// Entries already present (near-identical text in the same project)
+    pub skipped: usize,
+}
+
+/// A search result.
+#[derive(Debug, Clone)]
+pub struct KnowledgeHit {
+    pub entry: KnowledgeEntry,
+    /// Blended score in `0.0..=1.0`.
+    pub score: f32,
+    /// The part of the entry that best matches the query.
+    pub snippet: String,
+}
+
+/// Knowledge store backed by an append-only JSON-lines file.
+///
+/// The store is bound to one project and one session: new entries are
+/// tagged with both, and searches only see the project's entries. Write
+/// failures (including a lock held past the busy timeout) are logged and
+/// the in-memory state stays authoritative.
+pub struct KnowledgeStore {
+    path: Option<PathBuf>,
+    project: String,
+    session: String,
+    entries: Mutex<Vec<KnowledgeEntry>>,
+}
+
+impl KnowledgeStore {
+    /// Load the knowledge base from `path`. A missing file starts empty;
+    /// corrupt lines are skipped.
+    pub fn load(path: PathBuf, project: impl Into<String>, session: impl Into<String>) -> Self {
+        let entries = match read_entries(&path) {
+            Ok((entries, corrupt)) => {
+                if corrupt > 0 {
+                    tracing::warn!(path = %path.display(), corrupt, "Skipped corrupt knowledge entries");
+                }
+                entries
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to read knowledge base");
+                Vec::new()
+            }
+        };
+        Self {
+            path: Some(path),
+            project: project.into(),
+            session: session.into(),
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Read the knowledge base at `path` without loading it, returning how
+    /// many entries parse and how many lines are corrupt. A missing file has
+    /// neither.
+    pub fn check(path: &Path) -> std::io::Result<(usize, usize)> {
+        read_entries(path).map(|(entries, corrupt)| (entries.len(), corrupt))
+    }
+
+    /// Store that is never written to disk.
+    pub fn in_memory(project: impl Into<String>, session: impl Into<String>) -> Self {
+        Self {
+            path: None,
+            project: project.into(),
+            session: session.into(),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Number of entries for the current project.
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.project == self.project)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Ingest `text` for the current project and session.
+    ///
+    /// Returns the new entry, or `None` when the text is empty or nearly
+    /// identical to something the project already knows.
+    pub fn add(
+        &self,
+        kind: KnowledgeKind,
+        source: Option<String>,
+        text: &str,
+    ) -> Option<KnowledgeEntry> {
+        let text = truncate_chars(text.trim(), MAX_ENTRY_CHARS);
+        if text.is_empty() {
+            return None;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let lock = self.lock_and_refresh(&mut entries);
+        let vector = embed(&text);
+        let duplicate = entries
+            .iter()
+            .filter(|e| e.project == self.project)
+            .any(|e| cosine(&vector, &embed(&e.text)) >= DUPLICATE_SIMILARITY);
+        if duplicate {
+            return None;
+        }
+
+        let entry = KnowledgeEntry {
+            id: entries.iter().map(|e| e.id).max().unwrap_or(0) + 1,
+            project: self.project.clone(),
+            session: self.session.clone(),
+            created: Utc::now(),
+            kind,
+            source,
+            text,
+        };
+        let written =
+            lock.map(|lock| lock.and_then(|_lock| self.append(std::slice::from_ref(&entry))));
+        if let Some(Err(e)) = written {
+            tracing::warn!(error = %e, "Failed to save knowledge entry");
+        }
+        entries.push(entry.clone());
+        Some(entry)
+    }
+
+    /// Best matches for `query` in the current project, highest score first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<KnowledgeHit> {
+        let terms = dedup(tokenize(query));
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let query_vector = embed(query);
+
+        let entries = self.entries.lock().unwrap();
+        let docs: Vec<(&KnowledgeEntry, Vec<String>)> = entries
+            .iter()
+            .filter(|e| e.project == self.project)
+            .map(|e| (e, tokenize(&e.text)))
+            .collect();
+        if docs.is_empty() {
+            return Vec::new();
+        }
+
+        let lexical = bm25(&terms, &docs);
+        let max_lexical = lexical.iter().cloned().fold(0.0_f32, f32::max);
+
+        let mut hits: Vec<KnowledgeHit> = docs
+            .iter()
+            .zip(&lexical)
+            .filter_map(|((entry, _), &lex)| {
+                let similarity = cosine(&query_vector, &embed(&entry.text));
+                if lex <= 0.0 && similarity < MIN_VECTOR_ONLY_SCORE {
+                    return None;
+                }
+                let lex = if max_lexical > 0.0 {
+                    lex / max_lexical
+                } else {
+                    0.0
+                };
+                Some(KnowledgeHit {
+                    entry: (*entry).clone(),
+                    score: LEXICAL_WEIGHT * lex + (1.0 - LEXICAL_WEIGHT) * similarity,
+                    snippet: best_snippet(&entry.text, &terms),
+                })
+            })
+            .collect();
+        // Newer entries win ties
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.entry.created.cmp(&a.entry.created))
+        });
+        hits.truncate(limit);
+        hits
+    }
+
+    /// Entries to export: the current project's, or every project's.
+    pub fn export(&self, all_projects: bool) -> Vec<KnowledgeEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(ref path) = self.path {
+            merge_from_disk(&mut entries, path);
+        }
+        entries
+            .iter()
+            .filter(|e| all_projects || e.project == self.project)
+            .cloned()
+            .collect()
+    }
+
+    /// Merge exported entries, skipping ones the project already knows.
+    ///
+    /// Imported entries keep their session and date but get new ids.
+    /// `project` retags all of them, for when the project lives at a
+    /// different path on this machine.
+    pub fn import(
+        &self,
+        incoming: Vec<KnowledgeEntry>,
+        project: Option<&str>,
+    ) -> std::io::Result<ImportReport> {
+        let mut entries = self.entries.lock().unwrap();
+        let _lock = self.lock_and_refresh(&mut entries).transpose()?;
+
+        let mut vectors: Vec<(String, Vec<f32>)> = entries
+            .iter()
+            .map(|e| (e.project.clone(), embed(&e.text)))
+            .collect();
+        let mut next_id = entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        let mut added = Vec::new();
+        let mut report = ImportReport::default();
+        for mut entry in incoming {
+            if let Some(project) = project {
+                entry.project = project.to_string();
+            }
+            let vector = embed(&entry.text);
+            let known = vectors
+                .iter()
+                .any(|(p, v)| *p == entry.project && cosine(&vector, v) >= DUPLICATE_SIMILARITY);
+            if known || entry.text.trim().is_empty() {
+                report.skipped += 1;
+                continue;
+            }
+            entry.id = next_id;
+            next_id += 1;
+            vectors.push((entry.project.clone(), vector));
+            added.push(entry);
+        }
+
+        if self.path.is_some() && !added.is_empty() {
+            self.append(&added)?;
+        }
+        report.added = added.len();
+        entries.extend(added);
+        Ok(report)
+    }
+
+    /// Rewrite the file without corrupt lines and near-duplicate entries.
+    ///
+    /// The new file is written next to the old one and renamed over it, so
+    /// a crash mid-compaction leaves the original intact.
+    pub fn compact(&self) -> std::io::Result<CompactionReport> {
+        let mut entries = self.entries.lock().unwrap();
+        let mut report = CompactionReport::default();
+        let mut all = match self.path {
+            Some(ref path) => {
+                let _lock = FileLock::acquire(path)?;
+                report.bytes_before = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+                let (mut on_disk, corrupt) = read_entries(path)?;
+                report.corrupt_lines = corrupt;
+                // Keep entries whose earlier write failed
+                for entry in entries.iter() {
+                    if !on_disk.iter().any(|e| e.same_as(entry)) {
+                        on_disk.push(entry.clone());
+                    }
+                }
+                let kept = dedup_entries(on_disk, &mut report);
+                report.bytes_after = write_atomically(path, &kept)?;
+                kept
+            }
+            None => dedup_entries(std::mem::take(&mut *entries), &mut report),
+        };
+        report.kept = all.len();
+        std::mem::swap(&mut *entries, &mut all);
+        Ok(report)
+    }
+
+    /// Take the file lock and pick up entries other sessions wrote since we
+    /// loaded. `None` for in-memory stores.
+    fn lock_and_refresh(
+        &self,
+        entries: &mut Vec<KnowledgeEntry>,
+    ) -> Option<std::io::Result<FileLock>> {
+        let path = self.path.as_deref()?;
+        Some(FileLock::acquire(path).inspect(|_| merge_from_disk(entries, path)))
+    }
+
+    /// Append entries in one write and sync it. Callers hold the file lock.
+    fn append(&self, new_entries: &[KnowledgeEntry]) -> std::io::Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        let mut buf = String::new();
+        for entry in new_entries {
+            buf.push_str(&serde_json::to_string(entry).map_err(std::io::Error::other)?);
+            buf.push('\n');
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(buf.as_bytes())?;
+        file.sync_data()
+    }
+}
+
+impl KnowledgeEntry {
+    /// Whether two entries are the same record (ids are unique per file and
+    /// sessions disambiguate stores that failed to write).
+    fn same_as(&self, other: &KnowledgeEntry) -> bool {
+        self.id == other.id && self.session == other.session
+    }
+}
+
+/// Parse a knowledge file into its entries and the number of corrupt
+/// lines. A missing file is empty.
+fn read_entries(path: &Path) -> std::io::Result<(Vec<KnowledgeEntry>, usize)> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(e),
+    };
+    let mut corrupt = 0;
+    let entries = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(_) => {
+                corrupt += 1;
+                None
+            }
+        })
+        .collect();
+    Ok((entries, corrupt))
+}
+
+/// Add entries from disk that `entries` doesn't have yet.
+fn merge_from_disk(entries: &mut Vec<KnowledgeEntry>, path: &Path) {
+    match read_entries(path) {
+        Ok((on_disk, _)) => {
+            for entry in on_disk {
+                if !entries.iter().any(|e| e.same_as(&entry)) {
+                    entries.push(entry);
+                }
+            }
+        }
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to refresh knowledge base");
+        }
+    }
+}
+
+/// Drop entries near-identical to an earlier one in the same project.
+fn dedup_entries(
+    entries: Vec<KnowledgeEntry>,
+    report: &mut CompactionReport,
+) -> Vec<KnowledgeEntry> {
+    let mut kept: Vec<(KnowledgeEntry, Vec<f32>)> = Vec::new();
+    for entry in entries {
+        let vector = embed(&entry.text);
+        let duplicate = kept
+            .iter()
+            .any(|(k, v)| k.project == entry.project && cosine(&vector, v) >= DUPLICATE_SIMILARITY);
+        if duplicate {
+            report.duplicates += 1;
+        } else {
+            kept.push((entry, vector));
+        }
+    }
+    kept.into_iter().map(|(entry, _)| entry).collect()
+}
+
+/// Replace `path` with `entries` via a synced temp file and rename.
+/// Returns the new file size.
+fn write_atomically(path: &Path, entries: &[KnowledgeEntry]) -> std::io::Result<u64> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    for entry in entries {
+        let line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+        writeln!(tmp, "{}", line)?;
+    }
+    tmp.as_file().sync_all()?;
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(std::fs::metadata(path)?.len())
+}
+
+/// Lowercase word tokens, minus stopwords and single characters.
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| w.chars().count() > 1)
+        .map(|w| w.to_lowercase())
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+fn dedup(terms: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    terms
+        .into_iter()
+        .filter(|t| seen.insert(t.clone()))
+        .collect()
+}
+
+/// BM25 score of each document for the query terms.
+fn bm25(terms: &[String], docs: &[(&KnowledgeEntry, Vec<String>)]) -> Vec<f32> {
+    let n = docs.len() as f32;
+    let avg_len = docs.iter().map(|(_, t)| t.len()).sum::<usize>() as f32 / n;
+    let doc_freq: HashMap<&str, f32> = terms
+        .iter()
+        .map(|term| {
+            let df = docs.iter().filter(|(_, t)| t.contains(term)).count();
+            (term.as_str(), df as f32)
+        })
+        .collect();
+
+    docs.iter()
+        .map(|(_, tokens)| {
+            let len = tokens.len() as f32;
+            terms
+                .iter()
+                .map(|term| {
+                    let tf = tokens.iter().filter(|t| *t == term).count() as f32;
+                    if tf == 0.0 {
+                        return 0.0;
+                    }
+                    let df = doc_freq[term.as_str()];
+                    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * len / avg_len.max(1.0));
+                    idf * tf * (BM25_K1 + 1.0) / (tf + norm)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Hashed feature vector of words and character trigrams, L2-normalized.
+fn embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0_f32; VECTOR_DIM];
+    for word in tokenize(text) {
+        vector[bucket(word.as_bytes())] += 1.0;
+        let padded: Vec<char> = format!("^{}$", word).chars().collect();
+        for gram in padded.windows(3) {
+            let gram: String = gram.iter().collect();
+            vector[bucket(gram.as_bytes())] += 0.5;
+        }
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// FNV-1a, so buckets are stable across runs and platforms.
+fn bucket(bytes: &[u8]) -> usize {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    (hash % VECTOR_DIM as u64) as usize
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// The paragraph of `text` with the most query-term hits, cut to size.
+fn best_snippet(text: &str, terms: &[String]) -> String {
+    let best = text
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .enumerate()
+        .max_by_key(|(i, p)| {
+            let tokens: HashSet<String> = tokenize(p).into_iter().collect();
+            // Earlier paragraphs win ties
+            (
+                terms.iter().filter(|t| tokens.contains(*t)).count(),
+                usize::MAX - i,
+            )
+        })
+        .map(|(_, p)| p.trim())
+        .unwrap_or(text);
+    let mut snippet = truncate_chars(best, SNIPPET_CHARS);
+    if snippet.len() < best.len() {
+        snippet.push_str(" ...");
+    }
+    snippet
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+// =============================================================================
+// RecallTool
+// =============================================================================
+
+const RECALL_DESC: &str = "\
+Search knowledge saved by earlier sessions in this project: session summaries \
+and research findings. Returns matching snippets with citations \
+([session, date, source]).
+
+Call this BEFORE researching a question from scratch; if an earlier session \
+already answered it, build on that answer and cite it. Results can be stale, \
+so re-check anything that may have changed.";
+
+/// Tool for searching the cross-session knowledge base.
+pub struct RecallTool {
+    store: Arc<KnowledgeStore>,
+}
+
+impl RecallTool {
+    pub fn new(store: Arc<KnowledgeStore>) -> Self {
+        Self { store }
+    }
+
+    fn run(&self, args: RecallArgs) -> Result<String, String> {
+        let query = args.query.trim();
+        if query.is_empty() {
+            return Err("`query` must not be empty".to_string());
+        }
+        let limit = args.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let hits = self.store.search(query, limit);
+        if hits.is_empty() {
+            return Ok(format!(
+                "No saved knowledge matches '{}' for this project.",
+                query
+            ));
+        }
+        let results: Vec<String> = hits
+            .iter()
+            .enumerate()
+            .map(|(i, hit)| {
+                format!(
+                    "{}. {} (score {:.2})\n{}",
+                    i + 1,
+                    hit.entry.citation(),
+                    hit.score,
+                    hit.snippet
+                )
+            })
+            .collect();
+        Ok(results.join("\n\n"))
+    }
+}
+
+#[derive(Deserialize)]
+struct RecallArgs {
+    query: String,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[async_trait]
+impl Tool for RecallTool {
+    fn name(&self) -> &str {
+        "recall"
+    }
+
+    fn description(&self) -> &str {
+        "Search knowledge saved by earlier sessions in this project"
+    }
+
+    fn tool_description(&self) -> &str {
+        RECALL_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "query",
+                    PropertySchema::string("What you want to know"),
+                    true,
+                )
+                .add_property(
+                    "limit",
+                    PropertySchema::integer("Maximum results (default 5, max 10)"),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: RecallArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("recall", format!("Invalid arguments: {}", e)))?;
+        Ok(match self.run(args) {
+            Ok(text) => ToolOutput::success(text),
+            Err(e) => ToolOutput::error(e),
+        })
+    }
+}
+
+// =============================================================================
+// KnowledgeCaptureTool
+// =============================================================================
+
+/// Wraps a tool (typically an agent) and ingests each successful result into
+/// the knowledge base as a finding, prefixed with the task that produced it.
+pub struct KnowledgeCaptureTool {
+    inner: Arc<dyn Tool>,
+    store: Arc<KnowledgeStore>,
+    /// Name findings are attributed to (e.g. the agent name).
+    source: String,
+}
+
+impl KnowledgeCaptureTool {
+    pub fn new(
+        inner: Arc<dyn Tool>,
+        store: Arc<KnowledgeStore>,
+        source: impl Into<String>,
+    ) -> Self {
+        Self {
+            inner,
+            store,
+            source: source.into(),
+        }
+    }
+
+    fn capture(&self, arguments: &serde_json::Value, output: &ToolOutput) {
+        if output.is_error {
+            return;
+        }
+        let result = output.text_content();
+        if result.trim().is_empty() {
+            return;
+        }
+        let text = match arguments.get("task").and_then(|t| t.as_str()) {
+            Some(task) => format!("Task: {}\n\n{}", task.trim(), result.trim()),
+            None => result,
+        };
+        if let Some(entry) =
+            self.store
+                .add(KnowledgeKind::Finding, Some(self.source.clone()), &text)
+        {
+            tracing::debug!(id = entry.id, source = %self.source, "Captured knowledge finding");
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for KnowledgeCaptureTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn display_name(&self) -> &str {
+        self.inner.display_name()
+    }
+
+    fn tool_description(&self) -> &str {
+        self.inner.tool_description()
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        self.inner.definition()
+    }
+
+    fn is_blocking(&self) -> bool {
+        self.inner.is_blocking()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    fn write_targets(&self, arguments: &serde_json::Value) -> Vec<String> {
+        self.inner.write_targets(arguments)
+    }
+
+    fn narrower_scope(&self, arguments: &serde_json::Value, fraction: f64) -> Option<ScopeRetry> {
+        self.inner.narrower_scope(arguments, fraction)
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let output = self.inner.execute(arguments.clone()).await?;
+        self.capture(&arguments, &output);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn seeded(project: &str) -> KnowledgeStore {
+        let store = KnowledgeStore::in_memory(project, "20260301-090000");
+        store.add(
+            KnowledgeKind::Finding,
+            Some("researcher".into()),
+            "Task: which TLS crate should we use?\n\nrustls is preferred over openssl: \
+             pure Rust, no system dependency, and reqwest supports it via the \
+             rustls-tls feature.",
+        );
+        store.add(
+            KnowledgeKind::Summary,
+            None,
+            "Refactored the chunker to filter output by relevance before summarizing.",
+        );
+        store
+    }
+
+    #[test]
+    fn test_search_ranks_and_cites() {
+        let store = seeded("/work/qq");
+
+        let hits = store.search("rustls vs openssl for reqwest", 5);
+        assert_eq!(hits.len(), 1);
+        let hit = &hits[0];
+        assert_eq!(hit.entry.source.as_deref(), Some("researcher"));
+        assert!(hit.snippet.contains("rustls is preferred"));
+        assert!(hit.score > 0.5);
+        assert!(hit
+            .entry
+            .citation()
+            .starts_with("[session 20260301-090000, "));
+        assert!(hit.entry.citation().ends_with(", researcher]"));
+
+        // Inflected forms still match through the trigram vectors
+        let hits = store.search("chunking summarization", 5);
+        assert_eq!(hits[0].entry.kind, KnowledgeKind::Summary);
+
+        assert!(store.search("kubernetes ingress", 5).is_empty());
+        assert!(store.search("the of", 5).is_empty());
+    }
+
+    #[test]
+    fn test_persists_per_project() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("state").join("knowledge.jsonl");
+
+        let store = KnowledgeStore::load(path.clone(), "/work/a", "s1");
+        store
+            .add(
+                KnowledgeKind::Finding,
+                None,
+                "The staging database is Postgres 15.",
+            )
+            .unwrap();
+        // Near-duplicates are not stored twice
+        assert!(store
+            .add(
+                KnowledgeKind::Finding,
+                None,
+                "The staging database is Postgres 15"
+            )
+            .is_none());
+        assert!(store.add(KnowledgeKind::Finding, None, "   ").is_none());
+
+        let other = KnowledgeStore::load(path.clone(), "/work/b", "s2");
+        assert!(other.is_empty());
+        assert!(other.search("staging database", 5).is_empty());
+        other.add(KnowledgeKind::Summary, None, "Project b uses MySQL.");
+
+        let reloaded = KnowledgeStore::load(path, "/work/a", "s3");
+        assert_eq!(reloaded.len(), 1);
+        let hits = reloaded.search("what database does staging run?", 5);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].entry.session, "s1");
+        assert_eq!(hits[0].entry.id, 1);
+    }
+
+    /// Text made of words no other call produces, so entries never dedup.
+    fn distinct_text(n: usize) -> String {
+        (0..6)
+            .map(|k| {
+                let mut x = n * 6 + k + 1;
+                let mut word = String::from("w");
+                while x > 0 {
+                    word.push((b'a' + (x % 26) as u8) as char);
+                    x /= 26;
+                }
+                word
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn test_concurrent_sessions_do_not_lose_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("knowledge.jsonl");
+
+        let handles: Vec<_> = (0..6)
+            .map(|t| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let store = KnowledgeStore::load(path, "/work/a", format!("s{}", t));
+                    for i in 0..10 {
+                        assert!(store
+                            .add(KnowledgeKind::Finding, None, &distinct_text(t * 100 + i))
+                            .is_some());
+                    }
+                    // Each session sees what the others wrote before its last add
+                    assert!(store.len() >= 10);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let (entries, corrupt) = read_entries(&path).unwrap();
+        assert_eq!(corrupt, 0);
+        assert_eq!(entries.len(), 60);
+        let ids: HashSet<u64> = entries.iter().map(|e| e.id).collect();
+        assert_eq!(ids.len(), 60, "ids must be unique across sessions");
+        assert!(!tmp.path().join("knowledge.jsonl.lock").exists());
+
+        let reloaded = KnowledgeStore::load(path, "/work/a", "s9");
+        assert_eq!(reloaded.len(), 60);
+    }
+
+    #[test]
+    fn test_lock_waits_for_holder_and_takes_over_stale_locks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("knowledge.jsonl");
+
+        // A live holder releases within the busy timeout
+        let held = FileLock::acquire(&path).unwrap();
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            drop(held);
+        });
+        let start = Instant::now();
+        let lock = FileLock::acquire(&path).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        releaser.join().unwrap();
+        drop(lock);
+
+        // A lock left behind by a crashed process is taken over
+        let lock_path = tmp.path().join("knowledge.jsonl.lock");
+        let file = std::fs::File::create(&lock_path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - Duration::from_secs(120))
+            .unwrap();
+        drop(file);
+        let store = KnowledgeStore::load(path.clone(), "/work/a", "s1");
+        store
+            .add(KnowledgeKind::Finding, None, "Deploys go through ArgoCD.")
+            .unwrap();
+        assert_eq!(read_entries(&path).unwrap().0.len(), 1);
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    fn test_compact_drops_corrupt_lines_and_duplicates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("knowledge.jsonl");
+        let store = KnowledgeStore::load(path.clone(), "/work/a", "s1");
+        store.add(KnowledgeKind::Finding, None, "CI runs on GitHub Actions.");
+        store.add(
+            KnowledgeKind::Summary,
+            None,
+            "Added retry logic to the uploader.",
+        );
+
+        // A duplicate written by an older version, then a torn write
+        let mut text = std::fs::read_to_string(&path).unwrap();
+        let first = text.lines().next().unwrap().replace("\"id\":1", "\"id\":3");
+        text.push_str(&first);
+        text.push_str("\n{\"id\":4,\"project\":\"/wo");
+        std::fs::write(&path, &text).unwrap();
+
+        assert_eq!(KnowledgeStore::check(&path).unwrap(), (3, 1));
+        let store = KnowledgeStore::load(path.clone(), "/work/a", "s2");
+        assert_eq!(store.len(), 3);
+        let report = store.compact().unwrap();
+        assert_eq!(report.kept, 2);
+        assert_eq!(report.corrupt_lines, 1);
+        assert_eq!(report.duplicates, 1);
+        assert!(report.bytes_after < report.bytes_before);
+        assert_eq!(store.len(), 2);
+
+        let (entries, corrupt) = read_entries(&path).unwrap();
+        assert_eq!((entries.len(), corrupt), (2, 0));
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = KnowledgeStore::load(tmp.path().join("a.jsonl"), "/home/me/qq", "s1");
+        source.add(
+            KnowledgeKind::Finding,
+            Some("researcher".into()),
+            "Use rustls, not openssl.",
+        );
+        source.add(KnowledgeKind::Summary, None, "Wrote the release checklist.");
+        let other = KnowledgeStore::load(tmp.path().join("a.jsonl"), "/home/me/other", "s1");
+        other.add(KnowledgeKind::Summary, None, "Unrelated project notes.");
+
+        let exported = source.export(false);
+        assert_eq!(exported.len(), 2);
+        assert_eq!(source.export(true).len(), 3);
+        let json = serde_json::to_string(&exported).unwrap();
+
+        // A new machine where the project lives elsewhere
+        let target_path = tmp.path().join("b.jsonl");
+        let target = KnowledgeStore::load(target_path.clone(), "/srv/qq", "s7");
+        target.add(KnowledgeKind::Summary, None, "Wrote the release checklist.");
+        let incoming: Vec<KnowledgeEntry> = serde_json::from_str(&json).unwrap();
+        let report = target.import(incoming.clone(), Some("/srv/qq")).unwrap();
+        assert_eq!(
+            report,
+            ImportReport {
+                added: 1,
+                skipped: 1
+            }
+        );
+
+        let reloaded = KnowledgeStore::load(target_path, "/srv/qq", "s8");
+        assert_eq!(reloaded.len(), 2);
+        let hit = &reloaded.search("rustls openssl", 5)[0];
+        assert_eq!(hit.entry.session, "s1");
+        assert_eq!(hit.entry.id, 2);
+
+        // Importing again adds nothing
+        let report = target.import(incoming, Some("/srv/qq")).unwrap();
+        assert_eq!(
+            report,
+            ImportReport {
+                added: 0,
+                skipped: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_recall_tool_output() {
+        let tool = RecallTool::new(Arc::new(seeded("/work/qq")));
+        let out = tool
+            .run(serde_json::from_value(serde_json::json!({"query": "openssl"})).unwrap())
+            .unwrap();
+        assert!(out.starts_with("1. [session 20260301-090000, "));
+        assert!(out.contains("rustls is preferred"));
+
+        let out = tool
+            .run(serde_json::from_value(serde_json::json!({"query": "graphql"})).unwrap())
+            .unwrap();
+        assert!(out.starts_with("No saved knowledge"));
+        assert!(tool
+            .run(serde_json::from_value(serde_json::json!({"query": " "})).unwrap())
+            .is_err());
+    }
+
+    struct EchoAgent;
+
+    #[async_trait]
+    impl Tool for EchoAgent {
+        fn name(&self) -> &str {
+            "Agent[researcher]"
+        }
+
+        fn description(&self) -> &str {
+            "echo"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new("Agent[researcher]", "echo")
+        }
+
+        async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+            let task = arguments["task"].as_str().unwrap_or_default();
+            Ok(if task == "fail" {
+                ToolOutput::error("Agent failed")
+            } else {
+                ToolOutput::success(format!("Answer to {}: use tokio::select!", task))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_capture_tool_ingests_successful_results() {
+        let store = Arc::new(KnowledgeStore::in_memory("/work/qq", "s1"));
+        let tool = KnowledgeCaptureTool::new(Arc::new(EchoAgent), Arc::clone(&store), "researcher");
+        assert_eq!(tool.name(), "Agent[researcher]");
+
+        tool.execute(serde_json::json!({"task": "racing two futures"}))
+            .await
+            .unwrap();
+        let out = tool
+            .execute(serde_json::json!({"task": "fail"}))
+            .await
+            .unwrap();
+        assert!(out.is_error);
+
+        assert_eq!(store.len(), 1);
+        let hits = store.search("racing futures", 5);
+        assert!(hits[0].entry.text.starts_with("Task: racing two futures"));
+        assert_eq!(hits[0].entry.source.as_deref(), Some("researcher"));
+    }
+}
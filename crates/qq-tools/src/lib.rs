@@ -2,24 +2,79 @@
 //!
 //! This crate provides the default tools available to LLM agents:
 //! - Run: sandboxed shell command execution (replaces all filesystem/memory tools)
-//! - Web: fetch and parse webpages
+//! - Read files: concurrent multi-file reads within the sandbox mounts
+//! - Outline: tree-sitter symbol outlines of source files (`outline` feature)
+//! - Web: fetch and parse webpages, search via a configurable backend
 //! - Tasks: session-scoped task tracking
+//! - Datetime: current time, timezone conversion, date arithmetic
+//! - Env info: OS, hardware, toolchain versions, and sandbox mode as JSON
+//! - Documents: upload PDFs and datasets to the provider, attached by reference
+//! - Read document: page-structured text from PDF, DOCX, and EPUB files
+//!   (`documents` feature)
+//! - Reminders: persisted reminders surfaced as tasks in a later session
+//! - Knowledge: cross-session knowledge base searched with `recall`
+//! - Untrusted: quarantine blocks and injection heuristics for web and
+//!   out-of-project content
+//! - Supervise: user review (run, edit, skip) of every tool call
+//! - Artifacts: large outputs saved by digest, read back with `fetch_artifact`
+//! - File summaries: model summaries of project files cached by content hash
+//! - Hybrid search: ripgrep and embedding search over the project, ranked together
 
 pub mod approval;
+pub mod artifact;
 pub mod bash;
+pub mod datetime;
+pub mod document;
+pub mod env_info;
+pub mod file_lock;
+pub mod file_summary;
+pub mod hybrid_search;
 pub mod image;
+pub mod knowledge;
+#[cfg(feature = "documents")]
+pub mod read_document;
+pub mod reminders;
+pub mod search;
+pub mod supervise;
 pub mod tasks;
+pub mod untrusted;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm_plugin;
 pub mod web;
 
 pub use approval::{create_approval_channel, ApprovalChannel, ApprovalRequest, ApprovalResponse};
+pub use artifact::{offload, ArtifactStore, FetchArtifactTool, OffloadPolicy, OffloadTool};
 pub use bash::{
-    create_run_tools, MountExternalTool, MountPoint, PermissionStore, RequestNetworkAccessTool,
-    RequestSensitiveAccessTool, RunTool, SandboxExecutor, SandboxMounts, SandboxPathPolicy,
+    create_run_tools, AccessEntry, AccessKind, AccessLog, ChangeKind, ChangeStats, CommandExplainer, CoverageMapTool, FileChange, GitBranchTool, GitCommitTool, GitSandbox, GitStageTool, MountExternalTool, MountPoint, PermissionStore, PluginGrants, PluginTool, PluginToolSpec, ReadFilesTool, RequestNetworkAccessTool,
+    RequestSensitiveAccessTool, CheckBuildTool, RunLintersTool, RunTool, SandboxBackend, SandboxCapabilities, SandboxExecutor, SandboxMounts,
+    SandboxPathPolicy, ModelWriteClassifier, WriteAction, WriteClassifier, WritePolicy, WriteRule, UntrackedReadPolicy,
 };
+#[cfg(feature = "outline")]
+pub use bash::OutlineFileTool;
+pub use datetime::DateTimeTool;
+pub use document::AttachDocumentTool;
+pub use env_info::EnvInfoTool;
+pub use file_lock::{write_atomically, FileLock};
+pub use file_summary::{summary_candidates, FileSummarizer, FileSummary, SummarizeFileTool};
+pub use hybrid_search::{HybridHit, HybridResults, HybridSearchConfig, HybridSearchTool, HybridSearcher};
 pub use image::{create_image_tools, ReadImageTool};
+#[cfg(feature = "documents")]
+pub use read_document::ReadDocumentTool;
+pub use knowledge::{
+    CompactionReport, ImportReport, KnowledgeCaptureTool, KnowledgeEntry, KnowledgeHit,
+    KnowledgeKind, KnowledgeStore, RecallTool,
+};
+pub use reminders::{format_surfaced, Reminder, ReminderStore, ScheduleReminderTool};
 pub use tasks::{
-    create_task_tools, create_task_tools_arc, GetTaskResultTool, TaskStore, WaitForTasksTool,
+    create_task_tools, create_task_tools_arc, GetTaskResultTool, PlanChange, PlanRevision,
+    PlanSnapshot, PlanStepView, RevisePlanTool, SubmitPlanTool, TaskStore, WaitForTasksTool,
 };
-pub use web::{
-    create_web_tools, create_web_tools_arc, create_web_tools_with_search, WebSearchConfig,
+pub use search::{SearchProvider, SearchResult, SearchResults, WebSearchConfig};
+pub use supervise::{
+    create_review_channel, parse_edited_arguments, supervise, ReviewChannel, SupervisedTool,
+    ToolReview, ToolReviewRequest,
 };
+pub use untrusted::{quarantine, InjectionRisk};
+#[cfg(feature = "wasm-plugins")]
+pub use wasm_plugin::{WasmManifest, WasmPlugin, WasmPluginTool, WasmToolSpec};
+pub use web::{create_web_tools, create_web_tools_arc, create_web_tools_with_search};
@@ -0,0 +1,894 @@
+//! Document text extraction tool.
+//!
+//! `read_document` returns the text of a PDF, DOCX, or EPUB file under the
+//! project root, split into pages (chapters for EPUB) so agents can read a
+//! range or search for a phrase instead of taking the whole document. Large
+//! results go through the agent loop's chunker like any other tool output.
+
+use async_trait::async_trait;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use crate::bash::AccessLog;
+
+/// Largest document accepted.
+const MAX_DOCUMENT_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Output beyond this is cut at a page boundary with a note to ask for a
+/// narrower page range.
+const MAX_OUTPUT_BYTES: usize = 2 * 1024 * 1024;
+
+/// Most matching lines listed for a search.
+const MAX_SEARCH_MATCHES: usize = 50;
+
+/// Longest matching line shown, in characters.
+const MAX_MATCH_CHARS: usize = 300;
+
+/// Supported document formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Pdf,
+    Docx,
+    Epub,
+}
+
+impl DocumentFormat {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Pdf => "PDF",
+            Self::Docx => "DOCX",
+            Self::Epub => "EPUB",
+        }
+    }
+
+    /// What one entry of [`ExtractedDocument::pages`] is called.
+    fn unit(self) -> &'static str {
+        match self {
+            Self::Epub => "chapter",
+            Self::Pdf | Self::Docx => "page",
+        }
+    }
+}
+
+/// Text of a document, one entry per page (or chapter).
+#[derive(Debug, Clone)]
+pub struct ExtractedDocument {
+    pub format: DocumentFormat,
+    pub pages: Vec<String>,
+}
+
+/// Extract the text of a PDF, DOCX, or EPUB document.
+///
+/// The format is taken from the content, so misnamed files still work.
+pub fn extract_document(bytes: &[u8]) -> Result<ExtractedDocument, String> {
+    if bytes.starts_with(b"%PDF-") {
+        return Ok(ExtractedDocument {
+            format: DocumentFormat::Pdf,
+            pages: extract_pdf(bytes)?,
+        });
+    }
+    if !bytes.starts_with(b"PK") {
+        return Err("not a PDF, DOCX, or EPUB document".to_string());
+    }
+
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("invalid archive: {}", e))?;
+    if archive.by_name("word/document.xml").is_ok() {
+        let xml = read_entry(&mut archive, "word/document.xml")?;
+        return Ok(ExtractedDocument {
+            format: DocumentFormat::Docx,
+            pages: docx_pages(&xml)?,
+        });
+    }
+    if archive.by_name("META-INF/container.xml").is_ok() {
+        return Ok(ExtractedDocument {
+            format: DocumentFormat::Epub,
+            pages: epub_chapters(&mut archive)?,
+        });
+    }
+    Err("archive is neither a DOCX nor an EPUB document".to_string())
+}
+
+fn extract_pdf(bytes: &[u8]) -> Result<Vec<String>, String> {
+    // The PDF parser panics on some malformed files; treat that as an error
+    // rather than taking down the tool thread.
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| "PDF parser failed on this file".to_string())?
+        .map_err(|e| format!("failed to extract PDF text: {}", e))?;
+    Ok(pages.iter().map(|page| tidy_text(page)).collect())
+}
+
+type Archive<'a> = zip::ZipArchive<Cursor<&'a [u8]>>;
+
+fn read_entry(archive: &mut Archive<'_>, name: &str) -> Result<String, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("missing {} in archive", name))?;
+    let mut text = String::new();
+    entry
+        .read_to_string(&mut text)
+        .map_err(|e| format!("failed to read {}: {}", name, e))?;
+    Ok(text)
+}
+
+fn xml_reader(xml: &str) -> Reader<&[u8]> {
+    let mut reader = Reader::from_str(xml);
+    // Chapter XHTML is not always strictly well-formed
+    reader.config_mut().check_end_names = false;
+    reader
+}
+
+fn attribute(element: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attr| attr.key.as_ref() == name || attr.key.local_name().as_ref() == name)
+        .map(|attr| String::from_utf8_lossy(&attr.value).into_owned())
+}
+
+/// Split `word/document.xml` into pages at explicit and last-rendered page
+/// breaks. Documents never opened in Word have no rendered breaks and come
+/// back as a single page.
+fn docx_pages(xml: &str) -> Result<Vec<String>, String> {
+    let mut reader = xml_reader(xml);
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => in_text = e.local_name().as_ref() == b"t",
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => page.push('\n'),
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"tab" => page.push('\t'),
+                b"br" if attribute(&e, b"type").as_deref() == Some("page") => {
+                    pages.push(tidy_text(&std::mem::take(&mut page)));
+                }
+                b"br" | b"cr" => page.push('\n'),
+                b"lastRenderedPageBreak" if !page.trim().is_empty() => {
+                    pages.push(tidy_text(&std::mem::take(&mut page)));
+                }
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_text => match t.unescape() {
+                Ok(text) => page.push_str(&text),
+                Err(_) => page.push_str(&String::from_utf8_lossy(&t)),
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("invalid document.xml: {}", e)),
+        }
+    }
+    pages.push(tidy_text(&page));
+    Ok(pages)
+}
+
+/// Chapters of an EPUB in reading (spine) order.
+fn epub_chapters(archive: &mut Archive<'_>) -> Result<Vec<String>, String> {
+    let container = read_entry(archive, "META-INF/container.xml")?;
+    let opf_path = first_attribute(&container, b"rootfile", b"full-path")
+        .ok_or("container.xml names no package file")?;
+    let opf = read_entry(archive, &opf_path)?;
+    let base = match opf_path.rfind('/') {
+        Some(i) => &opf_path[..=i],
+        None => "",
+    };
+
+    let mut manifest = HashMap::new();
+    let mut spine = Vec::new();
+    let mut reader = xml_reader(&opf);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) => match e.local_name().as_ref() {
+                b"item" => {
+                    if let (Some(id), Some(href)) = (attribute(&e, b"id"), attribute(&e, b"href")) {
+                        manifest.insert(id, href);
+                    }
+                }
+                b"itemref" => spine.extend(attribute(&e, b"idref")),
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("invalid package file {}: {}", opf_path, e)),
+        }
+    }
+
+    let mut chapters = Vec::new();
+    for id in spine {
+        let Some(href) = manifest.get(&id) else {
+            continue;
+        };
+        let href = href.split('#').next().unwrap_or(href).replace("%20", " ");
+        let xhtml = read_entry(archive, &format!("{}{}", base, href))?;
+        let text = xhtml_text(&xhtml);
+        if !text.is_empty() {
+            chapters.push(text);
+        }
+    }
+    if chapters.is_empty() {
+        return Err("EPUB has no readable chapters".to_string());
+    }
+    Ok(chapters)
+}
+
+fn first_attribute(xml: &str, element: &[u8], name: &[u8]) -> Option<String> {
+    let mut reader = xml_reader(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == element => {
+                return attribute(&e, name);
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Visible text of an XHTML chapter, with block elements on their own lines.
+fn xhtml_text(xhtml: &str) -> String {
+    const BLOCKS: &[&[u8]] = &[
+        b"p",
+        b"div",
+        b"br",
+        b"li",
+        b"tr",
+        b"h1",
+        b"h2",
+        b"h3",
+        b"h4",
+        b"h5",
+        b"h6",
+        b"blockquote",
+        b"section",
+        b"pre",
+    ];
+    const HIDDEN: &[&[u8]] = &[b"head", b"script", b"style"];
+
+    let mut reader = xml_reader(xhtml);
+    let mut text = String::new();
+    let mut hidden = 0usize;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = e.local_name();
+                if HIDDEN.contains(&name.as_ref()) {
+                    hidden += 1;
+                } else if BLOCKS.contains(&name.as_ref()) {
+                    text.push('\n');
+                }
+            }
+            Ok(Event::End(e)) => {
+                let name = e.local_name();
+                if HIDDEN.contains(&name.as_ref()) {
+                    hidden = hidden.saturating_sub(1);
+                } else if BLOCKS.contains(&name.as_ref()) {
+                    text.push('\n');
+                }
+            }
+            Ok(Event::Empty(e)) if BLOCKS.contains(&e.local_name().as_ref()) => text.push('\n'),
+            Ok(Event::Text(t)) if hidden == 0 => match t.unescape_with(html_entity) {
+                Ok(chunk) => text.push_str(&chunk),
+                Err(_) => text.push_str(&String::from_utf8_lossy(&t)),
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => {}
+        }
+    }
+    tidy_text(&text)
+}
+
+/// Common HTML entities that are not predefined in XML.
+fn html_entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "nbsp" => " ",
+        "mdash" => "\u{2014}",
+        "ndash" => "\u{2013}",
+        "hellip" => "\u{2026}",
+        "lsquo" => "\u{2018}",
+        "rsquo" => "\u{2019}",
+        "ldquo" => "\u{201c}",
+        "rdquo" => "\u{201d}",
+        "copy" => "\u{a9}",
+        _ => return None,
+    })
+}
+
+/// Trim trailing whitespace from lines and collapse runs of blank lines.
+fn tidy_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if blank {
+            out.push('\n');
+            blank = false;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// Parse a page selection such as `"3"`, `"3-5"`, `"10-"`, or `"1,4-6"` into
+/// sorted, 1-based page numbers within `total`.
+pub fn parse_page_range(spec: &str, total: usize) -> Result<Vec<usize>, String> {
+    let number = |s: &str| -> Result<usize, String> {
+        s.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n >= 1)
+            .ok_or_else(|| format!("invalid page number '{}'", s.trim()))
+    };
+
+    let mut pages = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, "")) => (number(start)?, total),
+            Some((start, end)) => (number(start)?, number(end)?),
+            None => {
+                let n = number(part)?;
+                (n, n)
+            }
+        };
+        if start > end {
+            return Err(format!("invalid page range '{}'", part));
+        }
+        if start > total {
+            return Err(format!(
+                "page {} is past the end of the document ({} pages)",
+                start, total
+            ));
+        }
+        pages.extend(start..=end.min(total));
+    }
+    if pages.is_empty() {
+        return Err("empty page range".to_string());
+    }
+    pages.sort_unstable();
+    pages.dedup();
+    Ok(pages)
+}
+
+/// Tool that extracts text from PDF, DOCX, and EPUB documents.
+pub struct ReadDocumentTool {
+    project_root: PathBuf,
+    access_log: Option<Arc<AccessLog>>,
+}
+
+#[derive(Deserialize)]
+struct ReadDocumentArgs {
+    path: String,
+    #[serde(default)]
+    pages: Option<String>,
+    #[serde(default)]
+    search: Option<String>,
+}
+
+impl ReadDocumentTool {
+    pub fn new(project_root: PathBuf) -> Self {
+        Self {
+            project_root,
+            access_log: None,
+        }
+    }
+
+    /// Report reads of documents git doesn't track to `log`.
+    pub fn with_access_log(mut self, log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+
+    /// Resolve `path` and check that it stays under the project root.
+    fn resolve_path(&self, path: &str) -> Result<PathBuf, String> {
+        let p = Path::new(path);
+        let joined = if p.is_absolute() {
+            p.to_path_buf()
+        } else {
+            self.project_root.join(p)
+        };
+        let resolved = joined
+            .canonicalize()
+            .map_err(|_| format!("File not found: {}", joined.display()))?;
+        let root = self
+            .project_root
+            .canonicalize()
+            .unwrap_or_else(|_| self.project_root.clone());
+        if !resolved.starts_with(&root) {
+            return Err(format!(
+                "{} is outside the project root {}",
+                resolved.display(),
+                root.display()
+            ));
+        }
+        Ok(resolved)
+    }
+}
+
+/// Lines on `pages` containing `query` (case-insensitive), tagged with their
+/// page number.
+fn search_pages(
+    document: &ExtractedDocument,
+    pages: &[usize],
+    query: &str,
+) -> (Vec<String>, usize) {
+    let needle = query.to_lowercase();
+    let mut matches = Vec::new();
+    let mut total = 0;
+    for &n in pages {
+        for line in document.pages[n - 1].lines() {
+            if !line.to_lowercase().contains(&needle) {
+                continue;
+            }
+            total += 1;
+            if matches.len() < MAX_SEARCH_MATCHES {
+                let line = line.trim();
+                let shown: String = line.chars().take(MAX_MATCH_CHARS).collect();
+                let ellipsis = if shown.len() < line.len() { "..." } else { "" };
+                matches.push(format!(
+                    "[{} {}] {}{}",
+                    document.format.unit(),
+                    n,
+                    shown,
+                    ellipsis
+                ));
+            }
+        }
+    }
+    (matches, total)
+}
+
+fn format_pages(document: &ExtractedDocument, pages: &[usize]) -> String {
+    let unit = document.format.unit();
+    let mut out = String::new();
+    for (i, &n) in pages.iter().enumerate() {
+        let section = format!(
+            "--- {}{} {} ---\n{}\n\n",
+            unit[..1].to_uppercase(),
+            &unit[1..],
+            n,
+            document.pages[n - 1]
+        );
+        if !out.is_empty() && out.len() + section.len() > MAX_OUTPUT_BYTES {
+            out.push_str(&format!(
+                "[Output truncated after {} {}; request pages=\"{}-\" for the rest]\n",
+                unit,
+                pages[i - 1],
+                n
+            ));
+            break;
+        }
+        out.push_str(&section);
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+#[async_trait]
+impl Tool for ReadDocumentTool {
+    fn name(&self) -> &str {
+        "read_document"
+    }
+
+    fn description(&self) -> &str {
+        "Extract text from PDF, DOCX, and EPUB documents"
+    }
+
+    fn tool_description(&self) -> &str {
+        "Extract the text of a PDF, DOCX, or EPUB document under the project root, split \
+         into pages (chapters for EPUB). Use `pages` to read a range such as \"3-5\" or \
+         \"1,10-\", and `search` to list only the lines containing a phrase with their page \
+         numbers — search first, then read the pages you need. Large results are \
+         summarized automatically. Paths can be absolute or relative to the project root."
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new("read_document", self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "path",
+                    PropertySchema::string(
+                        "Path to the document (absolute or relative to project root)",
+                    ),
+                    true,
+                )
+                .add_property(
+                    "pages",
+                    PropertySchema::string(
+                        "Pages to read, e.g. \"3\", \"3-5\", \"10-\", or \"1,4-6\" (default: all)",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "search",
+                    PropertySchema::string(
+                        "Case-insensitive phrase; returns matching lines with page numbers \
+                         instead of full text",
+                    ),
+                    false,
+                ),
+        )
+    }
+
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let args: ReadDocumentArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::Unknown(format!("Invalid arguments: {}", e)))?;
+
+        let resolved = match self.resolve_path(&args.path) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolOutput::error(e)),
+        };
+        if !resolved.is_file() {
+            return Ok(ToolOutput::error(format!(
+                "Not a file: {}",
+                resolved.display()
+            )));
+        }
+        let metadata = std::fs::metadata(&resolved)
+            .map_err(|e| Error::Unknown(format!("Cannot read metadata: {}", e)))?;
+        if metadata.len() > MAX_DOCUMENT_SIZE {
+            return Ok(ToolOutput::error(format!(
+                "File too large: {} bytes (max {})",
+                metadata.len(),
+                MAX_DOCUMENT_SIZE
+            )));
+        }
+        if let Some(ref log) = self.access_log {
+            if let Err(e) = log.check("read_document", std::slice::from_ref(&resolved)).await {
+                return Ok(ToolOutput::error(e));
+            }
+        }
+
+        let bytes = std::fs::read(&resolved)
+            .map_err(|e| Error::Unknown(format!("Failed to read file: {}", e)))?;
+        let document = match extract_document(&bytes) {
+            Ok(document) => document,
+            Err(e) => {
+                return Ok(ToolOutput::error(format!(
+                    "Cannot read {}: {}",
+                    resolved.display(),
+                    e
+                )))
+            }
+        };
+
+        let total = document.pages.len();
+        let pages = match args.pages.as_deref() {
+            Some(spec) => match parse_page_range(spec, total) {
+                Ok(pages) => pages,
+                Err(e) => return Ok(ToolOutput::error(e)),
+            },
+            None => (1..=total).collect(),
+        };
+
+        let name = resolved
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| resolved.display().to_string());
+        let unit = document.format.unit();
+        let mut header = format!(
+            "{}: {}, {} {}{}",
+            name,
+            document.format.label(),
+            total,
+            unit,
+            if total == 1 { "" } else { "s" }
+        );
+        if let Some(spec) = args.pages.as_deref() {
+            header.push_str(&format!(" (showing {})", spec.trim()));
+        }
+
+        let body = match args.search.as_deref().map(str::trim) {
+            Some(query) if !query.is_empty() => {
+                let (matches, found) = search_pages(&document, &pages, query);
+                if found == 0 {
+                    format!("No lines match \"{}\"", query)
+                } else {
+                    let mut body = format!("{} lines match \"{}\":\n", found, query);
+                    body.push_str(&matches.join("\n"));
+                    if found > matches.len() {
+                        body.push_str(&format!(
+                            "\n[{} more matches not shown; narrow the search or page range]",
+                            found - matches.len()
+                        ));
+                    }
+                    body
+                }
+            }
+            _ => format_pages(&document, &pages),
+        };
+
+        Ok(ToolOutput::success(format!("{}\n\n{}", header, body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn zip_bytes(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// A minimal PDF with one line of Helvetica text per page.
+    fn pdf(pages: &[&str]) -> Vec<u8> {
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            String::new(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+        let mut kids = Vec::new();
+        for text in pages {
+            let stream = format!("BT /F1 12 Tf 72 720 Td ({}) Tj ET", text);
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                stream.len(),
+                stream
+            ));
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                objects.len()
+            ));
+            kids.push(format!("{} 0 R", objects.len()));
+        }
+        objects[1] = format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        );
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+        }
+        let xref = out.len();
+        out.push_str(&format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            objects.len() + 1
+        ));
+        for offset in offsets {
+            out.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        out.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        ));
+        out.into_bytes()
+    }
+
+    fn docx() -> Vec<u8> {
+        zip_bytes(&[(
+            "word/document.xml",
+            r#"<?xml version="1.0"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:r><w:t>Quarterly</w:t></w:r><w:r><w:tab/><w:t>Report &amp; Outlook</w:t></w:r></w:p>
+<w:p><w:r><w:t>Revenue grew.</w:t></w:r></w:p>
+<w:p><w:r><w:br w:type="page"/><w:t>Appendix: revenue table</w:t></w:r></w:p>
+</w:body></w:document>"#,
+        )])
+    }
+
+    fn epub() -> Vec<u8> {
+        zip_bytes(&[
+            (
+                "META-INF/container.xml",
+                r#"<?xml version="1.0"?>
+<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+<rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<manifest>
+<item id="c2" href="text/two.xhtml" media-type="application/xhtml+xml"/>
+<item id="c1" href="text/one.xhtml" media-type="application/xhtml+xml"/>
+</manifest>
+<spine><itemref idref="c1"/><itemref idref="c2"/></spine>
+</package>"#,
+            ),
+            (
+                "OEBPS/text/one.xhtml",
+                "<html><head><title>Skip me</title><style>p{}</style></head>\
+                 <body><h1>Chapter One</h1><p>It was a dark night.</p></body></html>",
+            ),
+            (
+                "OEBPS/text/two.xhtml",
+                "<html><body><h1>Chapter Two</h1><p>Morning&nbsp;came&hellip;</p></body></html>",
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_extract_docx_pages() {
+        let document = extract_document(&docx()).unwrap();
+        assert_eq!(document.format, DocumentFormat::Docx);
+        assert_eq!(
+            document.pages,
+            vec![
+                "Quarterly\tReport & Outlook\nRevenue grew.",
+                "Appendix: revenue table"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_epub_chapters_in_spine_order() {
+        let document = extract_document(&epub()).unwrap();
+        assert_eq!(document.format, DocumentFormat::Epub);
+        assert_eq!(
+            document.pages,
+            vec![
+                "Chapter One\n\nIt was a dark night.",
+                "Chapter Two\n\nMorning came\u{2026}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_pdf_pages() {
+        let document = extract_document(&pdf(&["Hello first page", "Second page text"])).unwrap();
+        assert_eq!(document.format, DocumentFormat::Pdf);
+        assert_eq!(document.pages, vec!["Hello first page", "Second page text"]);
+    }
+
+    #[test]
+    fn test_extract_rejects_other_files() {
+        assert!(extract_document(b"plain text").is_err());
+        let err = extract_document(&zip_bytes(&[("a.txt", "hi")])).unwrap_err();
+        assert!(err.contains("neither"));
+        assert!(extract_document(b"%PDF-1.7\ngarbage").is_err());
+    }
+
+    #[test]
+    fn test_parse_page_range() {
+        assert_eq!(parse_page_range("3", 10).unwrap(), vec![3]);
+        assert_eq!(parse_page_range("3-5", 10).unwrap(), vec![3, 4, 5]);
+        assert_eq!(parse_page_range("8-", 10).unwrap(), vec![8, 9, 10]);
+        assert_eq!(parse_page_range("4-6, 1,5", 10).unwrap(), vec![1, 4, 5, 6]);
+        assert_eq!(parse_page_range("9-20", 10).unwrap(), vec![9, 10]);
+        assert!(parse_page_range("0", 10).is_err());
+        assert!(parse_page_range("5-3", 10).is_err());
+        assert!(parse_page_range("11", 10).is_err());
+        assert!(parse_page_range("x", 10).is_err());
+        assert!(parse_page_range("", 10).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_document_tool() {
+        let dir = std::env::temp_dir().join("qq_test_read_document");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("report.docx"), docx()).unwrap();
+        std::fs::write(dir.join("novel.epub"), epub()).unwrap();
+        let tool = ReadDocumentTool::new(dir.clone());
+
+        let result = tool
+            .execute(serde_json::json!({"path": "report.docx"}))
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+        assert_eq!(
+            result.text_content(),
+            "report.docx: DOCX, 2 pages\n\n--- Page 1 ---\nQuarterly\tReport & Outlook\n\
+             Revenue grew.\n\n--- Page 2 ---\nAppendix: revenue table"
+        );
+
+        let result = tool
+            .execute(serde_json::json!({"path": "novel.epub", "pages": "2"}))
+            .await
+            .unwrap();
+        assert_eq!(
+            result.text_content(),
+            "novel.epub: EPUB, 2 chapters (showing 2)\n\n--- Chapter 2 ---\n\
+             Chapter Two\n\nMorning came\u{2026}"
+        );
+
+        let result = tool
+            .execute(serde_json::json!({"path": "report.docx", "search": "REVENUE"}))
+            .await
+            .unwrap();
+        assert_eq!(
+            result.text_content(),
+            "report.docx: DOCX, 2 pages\n\n2 lines match \"REVENUE\":\n\
+             [page 1] Revenue grew.\n[page 2] Appendix: revenue table"
+        );
+
+        let result = tool
+            .execute(serde_json::json!({"path": "report.docx", "pages": "3"}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("past the end"));
+
+        let result = tool
+            .execute(serde_json::json!({"path": "../"}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("outside the project root"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_read_document_checks_access_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("report.docx"), docx()).unwrap();
+        std::fs::write(root.join("draft.docx"), docx()).unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(args)
+                .current_dir(root)
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        if !(git(&["init", "-q"]) && git(&["add", "report.docx"])) {
+            return; // git unavailable
+        }
+
+        let (approval, mut rx) = crate::approval::create_approval_channel();
+        let log = Arc::new(
+            AccessLog::new(root).with_policy(crate::bash::UntrackedReadPolicy::Ask, approval),
+        );
+        tokio::spawn(async move {
+            while let Some(request) = rx.recv().await {
+                let _ = request
+                    .response_tx
+                    .send(crate::approval::ApprovalResponse::Deny(None));
+            }
+        });
+        let tool = ReadDocumentTool::new(root.to_path_buf()).with_access_log(Arc::clone(&log));
+
+        let result = tool
+            .execute(serde_json::json!({"path": "draft.docx"}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("denied by user"));
+
+        let result = tool
+            .execute(serde_json::json!({"path": "report.docx"}))
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+        assert!(log.is_empty());
+    }
+}
@@ -0,0 +1,468 @@
+//! Persisted reminders and deferred tasks.
+//!
+//! The project manager can schedule a reminder ("re-run the benchmarks
+//! tomorrow", "check CI in 2h"). Reminders are written to a JSON file in the
+//! state directory so they outlive the session; at the start of the next
+//! session, due reminders are turned into `todo` tasks on the task board.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use qq_core::{Error, PropertySchema, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use crate::datetime::{format_duration, parse_time, Zone};
+use crate::file_lock::{write_atomically, FileLock};
+use crate::tasks::TaskStore;
+
+/// A scheduled reminder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: u32,
+    pub due: DateTime<Utc>,
+    pub message: String,
+    /// Agent the resulting task should be assigned to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ReminderFile {
+    #[serde(default)]
+    next_id: u32,
+    #[serde(default)]
+    reminders: Vec<Reminder>,
+}
+
+/// Reminder store backed by a JSON file.
+///
+/// Every change is written through immediately. Changes are made under the
+/// file lock against the file's current contents, so sessions running side
+/// by side see each other's reminders and a due reminder is surfaced only
+/// once. Write failures (including a lock held too long by another session)
+/// are logged and the in-memory state stays authoritative for the session.
+pub struct ReminderStore {
+    path: Option<PathBuf>,
+    inner: Mutex<ReminderFile>,
+}
+
+impl ReminderStore {
+    /// Load reminders from `path`. A missing or unreadable file starts empty.
+    pub fn load(path: PathBuf) -> Self {
+        let inner = read_file(&path).unwrap_or_default();
+        Self {
+            path: Some(path),
+            inner: Mutex::new(inner),
+        }
+    }
+
+    /// Store that is never written to disk.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            inner: Mutex::new(ReminderFile::default()),
+        }
+    }
+
+    /// Schedule a reminder. Returns it with its assigned id.
+    pub fn add(&self, due: DateTime<Utc>, message: String, assignee: Option<String>) -> Reminder {
+        self.update(|inner| {
+            inner.next_id = inner.next_id.max(1);
+            let reminder = Reminder {
+                id: inner.next_id,
+                due,
+                message,
+                assignee,
+                created: Utc::now(),
+            };
+            inner.next_id += 1;
+            inner.reminders.push(reminder.clone());
+            (reminder, true)
+        })
+    }
+
+    /// Remove a reminder by id. Returns whether it existed.
+    pub fn cancel(&self, id: u32) -> bool {
+        self.update(|inner| {
+            let before = inner.reminders.len();
+            inner.reminders.retain(|r| r.id != id);
+            let removed = inner.reminders.len() != before;
+            (removed, removed)
+        })
+    }
+
+    /// All pending reminders, soonest first.
+    pub fn list(&self) -> Vec<Reminder> {
+        let mut reminders = self.update(|inner| (inner.reminders.clone(), false));
+        reminders.sort_by_key(|r| r.due);
+        reminders
+    }
+
+    /// Remove and return reminders due at or before `now`, soonest first.
+    pub fn take_due(&self, now: DateTime<Utc>) -> Vec<Reminder> {
+        let mut due = self.update(|inner| {
+            let (due, pending): (Vec<_>, Vec<_>) =
+                inner.reminders.drain(..).partition(|r| r.due <= now);
+            inner.reminders = pending;
+            let changed = !due.is_empty();
+            (due, changed)
+        });
+        due.sort_by_key(|r| r.due);
+        due
+    }
+
+    /// Move due reminders onto the task board as `todo` tasks.
+    ///
+    /// Returns the surfaced reminders paired with their new task ids.
+    pub fn surface_due(&self, tasks: &TaskStore, now: DateTime<Utc>) -> Vec<(Reminder, String)> {
+        self.take_due(now)
+            .into_iter()
+            .map(|r| {
+                let description = format!(
+                    "Scheduled reminder #{} (due {}, created {})",
+                    r.id,
+                    r.due.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                    r.created.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                );
+                let task_id =
+                    tasks.add_task(r.message.clone(), Some(description), r.assignee.clone());
+                (r, task_id)
+            })
+            .collect()
+    }
+
+    /// Apply `change` to the latest reminders and save them if it reports a
+    /// change. Reminders written by other sessions are picked up first.
+    fn update<R>(&self, change: impl FnOnce(&mut ReminderFile) -> (R, bool)) -> R {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(ref path) = self.path else {
+            return change(&mut inner).0;
+        };
+        let lock = FileLock::acquire(path);
+        if lock.is_ok() {
+            if let Some(latest) = read_file(path) {
+                *inner = latest;
+            }
+        }
+        let (result, changed) = change(&mut inner);
+        if changed {
+            let saved = lock.and_then(|_lock| {
+                let json = serde_json::to_string_pretty(&*inner).map_err(std::io::Error::other)?;
+                write_atomically(path, json.as_bytes())
+            });
+            if let Err(e) = saved {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to save reminders");
+            }
+        }
+        result
+    }
+}
+
+/// Read a reminders file. `None` when missing or corrupt.
+fn read_file(path: &Path) -> Option<ReminderFile> {
+    let text = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text)
+        .inspect_err(|e| {
+            tracing::warn!(path = %path.display(), error = %e, "Ignoring corrupt reminders file");
+        })
+        .ok()
+}
+
+/// Render surfaced reminders as a note for the start of a session.
+pub fn format_surfaced(surfaced: &[(Reminder, String)]) -> Option<String> {
+    if surfaced.is_empty() {
+        return None;
+    }
+    let mut out = String::from("Due reminders (added to the task board):\n");
+    for (r, task_id) in surfaced {
+        out.push_str(&format!("- task {}: {}", task_id, r.message));
+        if let Some(ref assignee) = r.assignee {
+            out.push_str(&format!(" [{}]", assignee));
+        }
+        out.push('\n');
+    }
+    Some(out)
+}
+
+// =============================================================================
+// ScheduleReminderTool
+// =============================================================================
+
+const SCHEDULE_REMINDER_DESC: &str = "\
+Schedule a reminder or deferred task that persists across sessions. When it \
+comes due, it is added to the task board at the start of the next session.
+
+Actions:
+  - add: schedule `message` at `when` (e.g. \"in 2h\", \"tomorrow 09:00\", \"2026-03-01 14:30\")
+  - list: show pending reminders
+  - cancel: remove reminder `id`";
+
+/// Tool for scheduling persisted reminders.
+pub struct ScheduleReminderTool {
+    store: Arc<ReminderStore>,
+}
+
+impl ScheduleReminderTool {
+    pub fn new(store: Arc<ReminderStore>) -> Self {
+        Self { store }
+    }
+
+    fn run(&self, args: ScheduleReminderArgs, now: DateTime<Utc>) -> Result<String, String> {
+        match args.action.as_str() {
+            "add" => {
+                let when = args.when.as_deref().ok_or("add requires `when`")?;
+                let message = args
+                    .message
+                    .filter(|m| !m.trim().is_empty())
+                    .ok_or("add requires `message`")?;
+                let zone = Zone::parse(args.timezone.as_deref().unwrap_or("local"))?;
+                let due = parse_time(when, zone, now)?;
+                if due <= now {
+                    return Err(format!("'{}' is in the past", when));
+                }
+                let r = self.store.add(due, message, args.assignee);
+                Ok(format!(
+                    "Reminder #{} scheduled for {} (in {})",
+                    r.id,
+                    zone.at(r.due)
+                        .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                    format_duration(r.due - now)
+                ))
+            }
+            "list" => {
+                let reminders = self.store.list();
+                if reminders.is_empty() {
+                    return Ok("No pending reminders.".to_string());
+                }
+                let lines: Vec<String> = reminders
+                    .iter()
+                    .map(|r| {
+                        let assignee = r
+                            .assignee
+                            .as_deref()
+                            .map(|a| format!(" [{}]", a))
+                            .unwrap_or_default();
+                        format!(
+                            "#{} {} (in {}){}: {}",
+                            r.id,
+                            Zone::Local
+                                .at(r.due)
+                                .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                            format_duration(r.due - now),
+                            assignee,
+                            r.message
+                        )
+                    })
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            "cancel" => {
+                let id = args.id.ok_or("cancel requires `id`")?;
+                if self.store.cancel(id) {
+                    Ok(format!("Reminder #{} cancelled", id))
+                } else {
+                    Err(format!("Reminder #{} not found", id))
+                }
+            }
+            other => Err(format!(
+                "Unknown action '{}'. Valid actions: add, list, cancel",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ScheduleReminderArgs {
+    action: String,
+    #[serde(default)]
+    when: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    assignee: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    id: Option<u32>,
+}
+
+#[async_trait]
+impl Tool for ScheduleReminderTool {
+    fn name(&self) -> &str {
+        "schedule_reminder"
+    }
+
+    fn description(&self) -> &str {
+        "Schedule persisted reminders that become tasks in a later session"
+    }
+
+    fn tool_description(&self) -> &str {
+        SCHEDULE_REMINDER_DESC
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(self.name(), self.tool_description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "action",
+                    PropertySchema::enum_string(
+                        "Operation to perform",
+                        vec!["add".into(), "list".into(), "cancel".into()],
+                    ),
+                    true,
+                )
+                .add_property(
+                    "when",
+                    PropertySchema::string("When the reminder is due (for add)"),
+                    false,
+                )
+                .add_property(
+                    "message",
+                    PropertySchema::string("Task title to create when due (for add)"),
+                    false,
+                )
+                .add_property(
+                    "assignee",
+                    PropertySchema::string("Agent to assign the resulting task to"),
+                    false,
+                )
+                .add_property(
+                    "timezone",
+                    PropertySchema::string("Timezone for reading `when` (default: local)"),
+                    false,
+                )
+                .add_property(
+                    "id",
+                    PropertySchema::integer("Reminder id (for cancel)"),
+                    false,
+                ),
+        )
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: ScheduleReminderArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("schedule_reminder", format!("Invalid arguments: {}", e)))?;
+        Ok(match self.run(args, Utc::now()) {
+            Ok(text) => ToolOutput::success(text),
+            Err(e) => ToolOutput::error(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn args(json: serde_json::Value) -> ScheduleReminderArgs {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_reminders_persist_and_surface_as_tasks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("state").join("reminders.json");
+        let now = Utc.with_ymd_and_hms(2026, 3, 6, 12, 0, 0).unwrap();
+
+        let store = ReminderStore::load(path.clone());
+        store.add(
+            now + Duration::hours(1),
+            "check CI".into(),
+            Some("reviewer".into()),
+        );
+        store.add(now + Duration::days(2), "rerun benchmarks".into(), None);
+        assert!(path.is_file());
+
+        // Next session, three hours later
+        let store = ReminderStore::load(path.clone());
+        let tasks = TaskStore::new();
+        let surfaced = store.surface_due(&tasks, now + Duration::hours(3));
+        assert_eq!(surfaced.len(), 1);
+        let task = tasks.get_task(&surfaced[0].1).unwrap();
+        assert_eq!(task.title, "check CI");
+        assert_eq!(task.assignee.as_deref(), Some("reviewer"));
+
+        let note = format_surfaced(&surfaced).unwrap();
+        assert!(note.contains("check CI [reviewer]"));
+
+        // Surfaced reminders are consumed; the rest remain on disk
+        let store = ReminderStore::load(path);
+        let pending = store.list();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].message, "rerun benchmarks");
+        assert_eq!(pending[0].id, 2);
+    }
+
+    #[test]
+    fn test_concurrent_sessions_share_reminders() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("reminders.json");
+        let now = Utc.with_ymd_and_hms(2026, 3, 6, 12, 0, 0).unwrap();
+
+        // Both sessions loaded the file before either wrote to it
+        let first = ReminderStore::load(path.clone());
+        let second = ReminderStore::load(path.clone());
+        let a = first.add(now + Duration::hours(1), "check CI".into(), None);
+        let b = second.add(now + Duration::hours(1), "tag release".into(), None);
+        assert_ne!(a.id, b.id);
+        assert_eq!(first.list().len(), 2);
+
+        // A due reminder is surfaced by one session only
+        assert_eq!(first.take_due(now + Duration::hours(2)).len(), 2);
+        assert!(second.take_due(now + Duration::hours(2)).is_empty());
+        assert!(ReminderStore::load(path).list().is_empty());
+    }
+
+    #[test]
+    fn test_tool_add_list_cancel() {
+        let tool = ScheduleReminderTool::new(Arc::new(ReminderStore::in_memory()));
+        let now = Utc.with_ymd_and_hms(2026, 3, 6, 12, 0, 0).unwrap();
+
+        let out = tool
+            .run(
+                args(serde_json::json!({"action": "add", "when": "in 2h", "message": "check CI"})),
+                now,
+            )
+            .unwrap();
+        assert!(out.starts_with("Reminder #1 scheduled"));
+        assert!(out.ends_with("(in 2h)"));
+
+        let out = tool
+            .run(args(serde_json::json!({"action": "list"})), now)
+            .unwrap();
+        assert!(out.contains("#1 ") && out.contains("check CI"));
+
+        assert!(tool
+            .run(
+                args(serde_json::json!({"action": "add", "when": "-1h", "message": "late"})),
+                now
+            )
+            .is_err());
+        assert!(tool
+            .run(
+                args(serde_json::json!({"action": "add", "when": "in 1h"})),
+                now
+            )
+            .is_err());
+
+        tool.run(args(serde_json::json!({"action": "cancel", "id": 1})), now)
+            .unwrap();
+        assert!(tool
+            .run(args(serde_json::json!({"action": "cancel", "id": 1})), now)
+            .is_err());
+        assert_eq!(
+            tool.run(args(serde_json::json!({"action": "list"})), now)
+                .unwrap(),
+            "No pending reminders."
+        );
+    }
+}
@@ -0,0 +1,744 @@
+//! Web search backends.
+//!
+//! The `web_search` tool talks to a [`SearchProvider`], so the backend can be
+//! swapped in config without the LLM seeing a different tool. Every backend
+//! normalizes its response into [`SearchResults`]: an optional synthesized
+//! answer (Perplexica, Tavily, some SearXNG engines) plus a ranked list of
+//! title/url/snippet results.
+//!
+//! Backends:
+//! - [`PerplexicaSearch`]: self-hosted Perplexica (LLM-synthesized answer)
+//! - [`SearxngSearch`]: self-hosted SearXNG metasearch (JSON format enabled)
+//! - [`BraveSearch`]: Brave Search API (API key)
+//! - [`TavilySearch`]: Tavily API (API key)
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use qq_core::Error;
+
+/// Default number of results requested from backends that support a limit.
+pub const DEFAULT_MAX_RESULTS: usize = 8;
+
+const BRAVE_ENDPOINT: &str = "https://api.search.brave.com/res/v1/web/search";
+const TAVILY_ENDPOINT: &str = "https://api.tavily.com/search";
+
+// =============================================================================
+// Normalized results
+// =============================================================================
+
+/// A single search hit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    /// Short excerpt of the page; may be empty.
+    #[serde(default)]
+    pub snippet: String,
+}
+
+/// Normalized response from any search backend.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResults {
+    /// Synthesized answer, for backends that produce one.
+    pub answer: Option<String>,
+    pub results: Vec<SearchResult>,
+}
+
+impl SearchResults {
+    /// Render as markdown for the `web_search` tool output.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+
+        if let Some(answer) = self.answer.as_deref().filter(|a| !a.trim().is_empty()) {
+            out.push_str(answer.trim());
+        }
+
+        if !self.results.is_empty() {
+            if !out.is_empty() {
+                out.push_str("\n\n## Sources\n");
+            } else {
+                out.push_str("## Results\n");
+            }
+            for (i, r) in self.results.iter().enumerate() {
+                out.push_str(&format!("\n{}. [{}]({})\n", i + 1, r.title, r.url));
+                let snippet = r.snippet.trim();
+                if !snippet.is_empty() {
+                    out.push_str(&format!("   {}\n", snippet));
+                }
+            }
+        }
+
+        if out.is_empty() {
+            out.push_str("(No results found)");
+        }
+        out
+    }
+}
+
+// =============================================================================
+// Provider trait and config
+// =============================================================================
+
+/// A web search backend.
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    /// Backend name for logs and error messages.
+    fn name(&self) -> &str;
+
+    /// Run a search.
+    async fn search(&self, query: &str) -> Result<SearchResults, Error>;
+}
+
+/// Backend selection for the `web_search` tool.
+#[derive(Clone, Debug)]
+pub enum WebSearchConfig {
+    Perplexica {
+        /// Base URL of the Perplexica instance (e.g., "http://localhost:3000")
+        host: String,
+        /// Chat model name (e.g., "gpt-4o-mini")
+        chat_model: String,
+        /// Embedding model name (e.g., "text-embedding-3-large")
+        embed_model: String,
+    },
+    Searxng {
+        /// Base URL of the SearXNG instance (e.g., "http://localhost:8888")
+        host: String,
+        max_results: usize,
+    },
+    Brave {
+        api_key: String,
+        max_results: usize,
+    },
+    Tavily {
+        api_key: String,
+        max_results: usize,
+    },
+}
+
+impl WebSearchConfig {
+    /// Perplexica configuration (the original, and default, backend).
+    pub fn new(
+        host: impl Into<String>,
+        chat_model: impl Into<String>,
+        embed_model: impl Into<String>,
+    ) -> Self {
+        Self::Perplexica {
+            host: host.into(),
+            chat_model: chat_model.into(),
+            embed_model: embed_model.into(),
+        }
+    }
+
+    /// Instantiate the configured backend.
+    pub fn into_provider(self) -> Arc<dyn SearchProvider> {
+        match self {
+            Self::Perplexica {
+                host,
+                chat_model,
+                embed_model,
+            } => Arc::new(PerplexicaSearch::new(host, chat_model, embed_model)),
+            Self::Searxng { host, max_results } => Arc::new(SearxngSearch::new(host, max_results)),
+            Self::Brave {
+                api_key,
+                max_results,
+            } => Arc::new(BraveSearch::new(api_key, max_results)),
+            Self::Tavily {
+                api_key,
+                max_results,
+            } => Arc::new(TavilySearch::new(api_key, max_results)),
+        }
+    }
+}
+
+fn http_client(timeout_secs: u64) -> Client {
+    qq_core::http::client_builder()
+        .user_agent("qq-cli/0.1.0")
+        .timeout(Duration::from_secs(timeout_secs))
+        .build()
+        .unwrap_or_default()
+}
+
+fn search_error(backend: &str, message: impl std::fmt::Display) -> Error {
+    Error::tool("web_search", format!("{}: {}", backend, message))
+}
+
+/// Send a request and decode a JSON body, mapping failures to tool errors.
+async fn send_json<T: serde::de::DeserializeOwned>(
+    backend: &str,
+    request: reqwest::RequestBuilder,
+) -> Result<T, Error> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| search_error(backend, format!("Search request failed: {}", e)))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(search_error(
+            backend,
+            format!("Search API error {}: {}", status, body),
+        ));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| search_error(backend, format!("Failed to parse search response: {}", e)))
+}
+
+/// Remove inline HTML tags (Brave wraps matched terms in `<strong>`).
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for ch in text.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(ch),
+            _ => {}
+        }
+    }
+    out
+}
+
+// =============================================================================
+// Perplexica
+// =============================================================================
+
+/// Self-hosted Perplexica: returns an LLM-written answer with sources.
+pub struct PerplexicaSearch {
+    client: Client,
+    host: String,
+    chat_model: String,
+    embed_model: String,
+}
+
+impl PerplexicaSearch {
+    pub fn new(
+        host: impl Into<String>,
+        chat_model: impl Into<String>,
+        embed_model: impl Into<String>,
+    ) -> Self {
+        Self {
+            client: http_client(60),
+            host: host.into(),
+            chat_model: chat_model.into(),
+            embed_model: embed_model.into(),
+        }
+    }
+
+    /// Get provider IDs for the configured chat and embedding models.
+    /// Returns optional providers - if models aren't found, returns None for that provider
+    /// and lets the search API handle validation (matching Python behavior).
+    async fn get_provider_ids(
+        &self,
+    ) -> Result<(Option<ModelProvider>, Option<ModelProvider>), Error> {
+        let url = format!("{}/api/providers", self.host);
+        let data: ProvidersResponse = send_json(self.name(), self.client.get(&url)).await?;
+
+        let mut chat_provider: Option<ModelProvider> = None;
+        let mut embed_provider: Option<ModelProvider> = None;
+
+        for provider in &data.providers {
+            // Find chat model
+            if chat_provider.is_none() {
+                if let Some(model) = provider
+                    .chat_models
+                    .iter()
+                    .find(|m| m.name == self.chat_model)
+                {
+                    chat_provider = Some(ModelProvider {
+                        provider_id: provider.id.clone(),
+                        key: model.key.clone(),
+                    });
+                }
+            }
+
+            // Find embedding model
+            if embed_provider.is_none() {
+                if let Some(model) = provider
+                    .embedding_models
+                    .iter()
+                    .find(|m| m.name == self.embed_model)
+                {
+                    embed_provider = Some(ModelProvider {
+                        provider_id: provider.id.clone(),
+                        key: model.key.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok((chat_provider, embed_provider))
+    }
+}
+
+#[derive(Deserialize)]
+struct ProvidersResponse {
+    providers: Vec<PerplexicaProvider>,
+}
+
+#[derive(Deserialize)]
+struct PerplexicaProvider {
+    id: String,
+    #[serde(rename = "chatModels")]
+    chat_models: Vec<PerplexicaModel>,
+    #[serde(rename = "embeddingModels")]
+    embedding_models: Vec<PerplexicaModel>,
+}
+
+#[derive(Deserialize)]
+struct PerplexicaModel {
+    name: String,
+    key: String,
+}
+
+#[derive(Clone, Serialize)]
+struct ModelProvider {
+    #[serde(rename = "providerId")]
+    provider_id: String,
+    key: String,
+}
+
+#[derive(Serialize)]
+struct PerplexicaRequest {
+    #[serde(rename = "chatModel")]
+    chat_model: Option<ModelProvider>,
+    #[serde(rename = "embeddingModel")]
+    embedding_model: Option<ModelProvider>,
+    #[serde(rename = "optimizationMode")]
+    optimization_mode: String,
+    sources: Vec<String>,
+    query: String,
+    history: Vec<(String, String)>,
+    #[serde(rename = "systemInstructions")]
+    system_instructions: String,
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct PerplexicaResponse {
+    message: String,
+    sources: Vec<PerplexicaSource>,
+}
+
+#[derive(Deserialize)]
+struct PerplexicaSource {
+    #[serde(default)]
+    content: String,
+    metadata: PerplexicaSourceMetadata,
+}
+
+#[derive(Deserialize)]
+struct PerplexicaSourceMetadata {
+    title: String,
+    url: String,
+}
+
+impl From<PerplexicaResponse> for SearchResults {
+    fn from(r: PerplexicaResponse) -> Self {
+        Self {
+            answer: Some(r.message),
+            results: r
+                .sources
+                .into_iter()
+                .map(|s| SearchResult {
+                    title: s.metadata.title,
+                    url: s.metadata.url,
+                    snippet: s.content,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for PerplexicaSearch {
+    fn name(&self) -> &str {
+        "perplexica"
+    }
+
+    async fn search(&self, query: &str) -> Result<SearchResults, Error> {
+        // Get provider IDs (may be None if models not found - let API handle validation)
+        let (chat_model, embedding_model) = self.get_provider_ids().await?;
+
+        let request = PerplexicaRequest {
+            chat_model,
+            embedding_model,
+            optimization_mode: "speed".to_string(),
+            sources: vec!["web".to_string()],
+            query: query.to_string(),
+            history: vec![
+                ("human".to_string(), "Hi, how are you?".to_string()),
+                (
+                    "assistant".to_string(),
+                    "I am doing well, how can I help you today?".to_string(),
+                ),
+            ],
+            system_instructions: "Provide high level details.".to_string(),
+            stream: false,
+        };
+
+        let url = format!("{}/api/search", self.host);
+        let response: PerplexicaResponse =
+            send_json(self.name(), self.client.post(&url).json(&request)).await?;
+
+        // Perplexica's source content is the retrieved chunk, which can be
+        // long; the answer already summarizes it.
+        let mut results = SearchResults::from(response);
+        for r in &mut results.results {
+            r.snippet.clear();
+        }
+        Ok(results)
+    }
+}
+
+// =============================================================================
+// SearXNG
+// =============================================================================
+
+/// Self-hosted SearXNG. The instance must have `json` in `search.formats`.
+pub struct SearxngSearch {
+    client: Client,
+    host: String,
+    max_results: usize,
+}
+
+impl SearxngSearch {
+    pub fn new(host: impl Into<String>, max_results: usize) -> Self {
+        Self {
+            client: http_client(30),
+            host: host.into(),
+            max_results,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+    #[serde(default)]
+    answers: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct SearxngResult {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+impl SearxngResponse {
+    fn into_results(self, max_results: usize) -> SearchResults {
+        // Answers are plain strings in older releases, objects with an
+        // `answer` field in newer ones.
+        let answer = self
+            .answers
+            .iter()
+            .find_map(|a| {
+                a.as_str()
+                    .or_else(|| a.get("answer").and_then(|v| v.as_str()))
+                    .map(str::to_string)
+            });
+        SearchResults {
+            answer,
+            results: self
+                .results
+                .into_iter()
+                .take(max_results)
+                .map(|r| SearchResult {
+                    title: r.title,
+                    url: r.url,
+                    snippet: r.content,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for SearxngSearch {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &str) -> Result<SearchResults, Error> {
+        let url = format!("{}/search", self.host.trim_end_matches('/'));
+        let request = self
+            .client
+            .get(&url)
+            .query(&[("q", query), ("format", "json")]);
+        let response: SearxngResponse = send_json(self.name(), request).await?;
+        Ok(response.into_results(self.max_results))
+    }
+}
+
+// =============================================================================
+// Brave Search API
+// =============================================================================
+
+/// Brave Search API (https://brave.com/search/api/).
+pub struct BraveSearch {
+    client: Client,
+    api_key: String,
+    max_results: usize,
+}
+
+impl BraveSearch {
+    pub fn new(api_key: impl Into<String>, max_results: usize) -> Self {
+        Self {
+            client: http_client(30),
+            api_key: api_key.into(),
+            max_results,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BraveResponse {
+    #[serde(default)]
+    web: Option<BraveWeb>,
+}
+
+#[derive(Deserialize)]
+struct BraveWeb {
+    #[serde(default)]
+    results: Vec<BraveResult>,
+}
+
+#[derive(Deserialize)]
+struct BraveResult {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    description: String,
+}
+
+impl From<BraveResponse> for SearchResults {
+    fn from(r: BraveResponse) -> Self {
+        Self {
+            answer: None,
+            results: r
+                .web
+                .map(|w| w.results)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|r| SearchResult {
+                    title: strip_tags(&r.title),
+                    url: r.url,
+                    snippet: strip_tags(&r.description),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for BraveSearch {
+    fn name(&self) -> &str {
+        "brave"
+    }
+
+    async fn search(&self, query: &str) -> Result<SearchResults, Error> {
+        // Brave caps `count` at 20
+        let count = self.max_results.clamp(1, 20).to_string();
+        let request = self
+            .client
+            .get(BRAVE_ENDPOINT)
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key)
+            .query(&[("q", query), ("count", count.as_str())]);
+        let response: BraveResponse = send_json(self.name(), request).await?;
+        Ok(response.into())
+    }
+}
+
+// =============================================================================
+// Tavily
+// =============================================================================
+
+/// Tavily search API (https://tavily.com).
+pub struct TavilySearch {
+    client: Client,
+    api_key: String,
+    max_results: usize,
+}
+
+impl TavilySearch {
+    pub fn new(api_key: impl Into<String>, max_results: usize) -> Self {
+        Self {
+            client: http_client(60),
+            api_key: api_key.into(),
+            max_results,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TavilyRequest<'a> {
+    query: &'a str,
+    max_results: usize,
+    include_answer: bool,
+}
+
+#[derive(Deserialize)]
+struct TavilyResponse {
+    #[serde(default)]
+    answer: Option<String>,
+    #[serde(default)]
+    results: Vec<TavilyResult>,
+}
+
+#[derive(Deserialize)]
+struct TavilyResult {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+impl From<TavilyResponse> for SearchResults {
+    fn from(r: TavilyResponse) -> Self {
+        Self {
+            answer: r.answer,
+            results: r
+                .results
+                .into_iter()
+                .map(|r| SearchResult {
+                    title: r.title,
+                    url: r.url,
+                    snippet: r.content,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for TavilySearch {
+    fn name(&self) -> &str {
+        "tavily"
+    }
+
+    async fn search(&self, query: &str) -> Result<SearchResults, Error> {
+        let body = TavilyRequest {
+            query,
+            max_results: self.max_results,
+            include_answer: true,
+        };
+        let request = self
+            .client
+            .post(TAVILY_ENDPOINT)
+            .bearer_auth(&self.api_key)
+            .json(&body);
+        let response: TavilyResponse = send_json(self.name(), request).await?;
+        Ok(response.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_searxng_normalization() {
+        let json = serde_json::json!({
+            "query": "rust",
+            "results": [
+                {"title": "Rust", "url": "https://rust-lang.org", "content": "A language", "engine": "ddg"},
+                {"title": "Crates", "url": "https://crates.io"},
+                {"title": "Third", "url": "https://example.com", "content": "x"}
+            ],
+            "answers": [{"answer": "Rust is a language"}]
+        });
+        let response: SearxngResponse = serde_json::from_value(json).unwrap();
+        let results = response.into_results(2);
+
+        assert_eq!(results.answer.as_deref(), Some("Rust is a language"));
+        assert_eq!(results.results.len(), 2);
+        assert_eq!(results.results[1].snippet, "");
+    }
+
+    #[test]
+    fn test_brave_normalization_strips_tags() {
+        let json = serde_json::json!({
+            "type": "search",
+            "web": {"results": [{
+                "title": "The <strong>Rust</strong> Book",
+                "url": "https://doc.rust-lang.org/book/",
+                "description": "Learn <strong>Rust</strong> &amp; more"
+            }]}
+        });
+        let results: SearchResults = serde_json::from_value::<BraveResponse>(json).unwrap().into();
+
+        assert!(results.answer.is_none());
+        assert_eq!(results.results[0].title, "The Rust Book");
+        assert_eq!(results.results[0].snippet, "Learn Rust &amp; more");
+
+        // No web section (e.g. only news results) is an empty result set
+        let empty: SearchResults = serde_json::from_value::<BraveResponse>(serde_json::json!({}))
+            .unwrap()
+            .into();
+        assert!(empty.results.is_empty());
+    }
+
+    #[test]
+    fn test_tavily_and_perplexica_normalization() {
+        let tavily: SearchResults = serde_json::from_value::<TavilyResponse>(serde_json::json!({
+            "query": "q",
+            "answer": "Short answer",
+            "results": [{"title": "T", "url": "https://t.example", "content": "c", "score": 0.9}]
+        }))
+        .unwrap()
+        .into();
+        assert_eq!(tavily.answer.as_deref(), Some("Short answer"));
+        assert_eq!(tavily.results[0].snippet, "c");
+
+        let perplexica: SearchResults =
+            serde_json::from_value::<PerplexicaResponse>(serde_json::json!({
+                "message": "Answer",
+                "sources": [{"content": "chunk", "metadata": {"title": "P", "url": "https://p.example"}}]
+            }))
+            .unwrap()
+            .into();
+        assert_eq!(perplexica.answer.as_deref(), Some("Answer"));
+        assert_eq!(perplexica.results[0].url, "https://p.example");
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let results = SearchResults {
+            answer: None,
+            results: vec![SearchResult {
+                title: "Rust".into(),
+                url: "https://rust-lang.org".into(),
+                snippet: "A language".into(),
+            }],
+        };
+        assert_eq!(
+            results.to_markdown(),
+            "## Results\n\n1. [Rust](https://rust-lang.org)\n   A language\n"
+        );
+
+        let with_answer = SearchResults {
+            answer: Some("It is a language.".into()),
+            ..results
+        };
+        assert!(with_answer
+            .to_markdown()
+            .starts_with("It is a language.\n\n## Sources\n"));
+
+        assert_eq!(SearchResults::default().to_markdown(), "(No results found)");
+    }
+}
@@ -0,0 +1,276 @@
+//! Supervised mode: every tool call is reviewed before it runs.
+//!
+//! [`SupervisedTool`] wraps a tool and sends each call to the UI as a
+//! [`ToolReviewRequest`]. The user can run it as proposed, run it with edited
+//! arguments (fix a wrong path, adjust a regex), or skip it with an optional
+//! reason the model sees. Unlike the approval channel, this covers every tool,
+//! not just commands and writes the bash tool flags; those approvals still
+//! apply after a call is reviewed.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use qq_core::{Error, ScopeRetry, Tool, ToolDefinition, ToolOutput, ToolRegistry, TypedContent};
+
+/// A tool call waiting for the user's review.
+pub struct ToolReviewRequest {
+    /// Display name of the tool (e.g. "run", "Agent[coder]").
+    pub tool: String,
+    /// Arguments the model proposed.
+    pub arguments: Value,
+    /// Channel to send the user's decision back.
+    pub response_tx: oneshot::Sender<ToolReview>,
+}
+
+impl ToolReviewRequest {
+    /// Arguments as pretty-printed JSON, for display.
+    pub fn arguments_pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.arguments).unwrap_or_default()
+    }
+
+    /// Arguments as single-line JSON, the starting point for an edit.
+    pub fn arguments_compact(&self) -> String {
+        self.arguments.to_string()
+    }
+}
+
+/// Parse edited arguments; tool arguments are always a JSON object.
+pub fn parse_edited_arguments(text: &str) -> Result<Value, String> {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) if value.is_object() => Ok(value),
+        Ok(_) => Err("Arguments must be a JSON object".to_string()),
+        Err(e) => Err(format!("Invalid JSON: {}", e)),
+    }
+}
+
+/// The user's decision on a reviewed tool call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolReview {
+    /// Run the call as proposed.
+    Run,
+    /// Run the call with these arguments instead.
+    Edit(Value),
+    /// Don't run the call, with an optional reason for the model.
+    Skip(Option<String>),
+}
+
+/// Sender side of the review channel, held by supervised tools.
+#[derive(Clone)]
+pub struct ReviewChannel {
+    request_tx: mpsc::Sender<ToolReviewRequest>,
+}
+
+impl ReviewChannel {
+    /// Send a call for review and wait for the decision.
+    pub async fn review(&self, tool: &str, arguments: Value) -> Result<ToolReview, String> {
+        let (response_tx, response_rx) = oneshot::channel();
+
+        self.request_tx
+            .send(ToolReviewRequest {
+                tool: tool.to_string(),
+                arguments,
+                response_tx,
+            })
+            .await
+            .map_err(|_| "Review channel closed".to_string())?;
+
+        response_rx
+            .await
+            .map_err(|_| "Review cancelled".to_string())
+    }
+}
+
+/// Create a review channel pair.
+///
+/// Returns the sender (for supervised tools) and receiver (for TUI/CLI).
+pub fn create_review_channel() -> (ReviewChannel, mpsc::Receiver<ToolReviewRequest>) {
+    let (tx, rx) = mpsc::channel(8);
+    (ReviewChannel { request_tx: tx }, rx)
+}
+
+/// Wraps a tool so each call is reviewed by the user before it executes.
+pub struct SupervisedTool {
+    inner: Arc<dyn Tool>,
+    channel: ReviewChannel,
+}
+
+impl SupervisedTool {
+    pub fn new(inner: Arc<dyn Tool>, channel: ReviewChannel) -> Self {
+        Self { inner, channel }
+    }
+}
+
+/// Copy of `registry` with every tool supervised through `channel`.
+pub fn supervise(registry: &ToolRegistry, channel: &ReviewChannel) -> ToolRegistry {
+    registry.wrapped(|tool| Arc::new(SupervisedTool::new(tool, channel.clone())))
+}
+
+#[async_trait]
+impl Tool for SupervisedTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn display_name(&self) -> &str {
+        self.inner.display_name()
+    }
+
+    fn tool_description(&self) -> &str {
+        self.inner.tool_description()
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        self.inner.definition()
+    }
+
+    fn is_blocking(&self) -> bool {
+        self.inner.is_blocking()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    fn write_targets(&self, arguments: &Value) -> Vec<String> {
+        self.inner.write_targets(arguments)
+    }
+
+    fn narrower_scope(&self, arguments: &Value, fraction: f64) -> Option<ScopeRetry> {
+        self.inner.narrower_scope(arguments, fraction)
+    }
+
+    async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+        let review = self
+            .channel
+            .review(self.inner.display_name(), arguments.clone())
+            .await;
+        match review {
+            Ok(ToolReview::Run) => self.inner.execute(arguments).await,
+            Ok(ToolReview::Edit(edited)) => {
+                let note = format!(
+                    "Note: the user edited this call's arguments before it ran: {}\n\n",
+                    edited
+                );
+                let mut output = self.inner.execute(edited).await?;
+                output.content.insert(0, TypedContent::text(note));
+                Ok(output)
+            }
+            Ok(ToolReview::Skip(reason)) => Ok(ToolOutput::error(match reason {
+                Some(reason) => format!("The user skipped this tool call: {}", reason),
+                None => "The user skipped this tool call".to_string(),
+            })),
+            Err(e) => Ok(ToolOutput::error(format!("Tool call not run: {}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct EchoTool;
+
+    #[async_trait]
+    impl Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echo the path argument"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new("echo", "Echo the path argument")
+        }
+
+        async fn execute(&self, arguments: Value) -> Result<ToolOutput, Error> {
+            Ok(ToolOutput::success(format!(
+                "read {}",
+                arguments["path"].as_str().unwrap_or_default()
+            )))
+        }
+    }
+
+    /// Answer each review in order, recording the arguments shown.
+    fn answer(
+        mut rx: mpsc::Receiver<ToolReviewRequest>,
+        reviews: Vec<ToolReview>,
+    ) -> tokio::task::JoinHandle<Vec<Value>> {
+        tokio::spawn(async move {
+            let mut seen = Vec::new();
+            for review in reviews {
+                let request = rx.recv().await.unwrap();
+                assert_eq!(request.tool, "echo");
+                seen.push(request.arguments);
+                request.response_tx.send(review).unwrap();
+            }
+            seen
+        })
+    }
+
+    #[tokio::test]
+    async fn test_review_runs_edits_and_skips() {
+        let (channel, rx) = create_review_channel();
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoTool));
+        registry.register_with_key("__echo_ro", Arc::new(EchoTool));
+        let registry = supervise(&registry, &channel);
+        let tool = registry.get_arc("echo").unwrap();
+
+        let answers = answer(
+            rx,
+            vec![
+                ToolReview::Run,
+                ToolReview::Edit(json!({"path": "src/main.rs"})),
+                ToolReview::Skip(Some("wrong file".into())),
+                ToolReview::Skip(None),
+            ],
+        );
+
+        let out = tool.execute(json!({"path": "src/lib.rs"})).await.unwrap();
+        assert_eq!(out.text_content(), "read src/lib.rs");
+
+        let out = tool.execute(json!({"path": "src/mian.rs"})).await.unwrap();
+        assert!(!out.is_error);
+        let text = out.text_content();
+        assert!(text.contains("user edited"));
+        assert!(text.ends_with("read src/main.rs"));
+
+        let out = tool.execute(json!({"path": "README"})).await.unwrap();
+        assert!(out.is_error);
+        assert!(out.text_content().contains("wrong file"));
+
+        // Alternative variants are supervised too
+        let ro = registry.get_arc("__echo_ro").unwrap();
+        let out = ro.execute(json!({"path": "README"})).await.unwrap();
+        assert_eq!(out.text_content(), "The user skipped this tool call");
+
+        let seen = answers.await.unwrap();
+        assert_eq!(seen[1], json!({"path": "src/mian.rs"}));
+
+        // With nobody left to answer, the call is not run
+        let out = tool.execute(json!({"path": "x"})).await.unwrap();
+        assert!(out.is_error);
+    }
+
+    #[test]
+    fn test_parse_edited_arguments() {
+        assert_eq!(
+            parse_edited_arguments(r#"{"pattern": "fn \\w+"}"#).unwrap(),
+            json!({"pattern": "fn \\w+"})
+        );
+        assert!(parse_edited_arguments("[1, 2]").is_err());
+        assert!(parse_edited_arguments("{\"path\": ")
+            .unwrap_err()
+            .starts_with("Invalid JSON"));
+    }
+}
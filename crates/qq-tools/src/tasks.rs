@@ -72,6 +72,151 @@ pub struct Task {
     pub result: Option<String>,
 }
 
+// =============================================================================
+// Plan types
+// =============================================================================
+
+/// A structured plan submitted via `submit_plan`.
+///
+/// Each step is backed by a regular task, so step status and ownership are
+/// always read from the task board rather than duplicated here.
+struct Plan {
+    goal: String,
+    task_ids: Vec<String>,
+    /// Steps invalidated by `revise_plan`, with the reason
+    invalidated: HashMap<String, String>,
+    /// Revisions made with `revise_plan`, oldest first
+    revisions: Vec<PlanRevision>,
+}
+
+impl Plan {
+    fn new(goal: String, task_ids: Vec<String>) -> Self {
+        Self {
+            goal,
+            task_ids,
+            invalidated: HashMap::new(),
+            revisions: Vec::new(),
+        }
+    }
+}
+
+/// A revision of the active plan made with `revise_plan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanRevision {
+    /// 1-based revision number
+    pub number: usize,
+    /// Finding that prompted the revision
+    pub reason: String,
+    pub changes: Vec<PlanChange>,
+}
+
+/// One difference between a plan and its revision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanChange {
+    GoalChanged {
+        from: String,
+        to: String,
+    },
+    /// A step that no longer holds; its task is marked blocked.
+    Invalidated {
+        task_id: String,
+        title: String,
+        reason: String,
+    },
+    /// A field of a remaining step changed.
+    Updated {
+        task_id: String,
+        field: &'static str,
+        from: String,
+        to: String,
+    },
+    Added {
+        task_id: String,
+        title: String,
+        owner: String,
+    },
+    /// A step left as-is that builds on an invalidated one.
+    NeedsReview {
+        task_id: String,
+        title: String,
+        because: String,
+    },
+}
+
+impl std::fmt::Display for PlanChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanChange::GoalChanged { from, to } => write!(f, "goal: {} → {}", from, to),
+            PlanChange::Invalidated {
+                task_id,
+                title,
+                reason,
+            } => write!(f, "✗ #{}: {} — {}", task_id, title, reason),
+            PlanChange::Updated { task_id, field, .. } if *field == "description" => {
+                write!(f, "~ #{} description rewritten", task_id)
+            }
+            PlanChange::Updated {
+                task_id,
+                field,
+                from,
+                to,
+            } => write!(f, "~ #{} {}: {} → {}", task_id, field, from, to),
+            PlanChange::Added {
+                task_id,
+                title,
+                owner,
+            } => write!(f, "+ #{}: {} ({})", task_id, title, owner),
+            PlanChange::NeedsReview {
+                task_id,
+                title,
+                because,
+            } => write!(
+                f,
+                "? #{}: {} — depends on invalidated #{}",
+                task_id, title, because
+            ),
+        }
+    }
+}
+
+/// One step of the active plan, joined with its live task state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanStepView {
+    pub task_id: String,
+    pub title: String,
+    pub owner: Option<String>,
+    pub status: TaskStatus,
+    /// Why the step was invalidated, if it was
+    pub invalidated: Option<String>,
+}
+
+/// Point-in-time view of the active plan, used for rendering progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanSnapshot {
+    pub goal: String,
+    pub steps: Vec<PlanStepView>,
+    /// Revisions made so far, oldest first
+    pub revisions: Vec<PlanRevision>,
+}
+
+impl PlanSnapshot {
+    /// Number of steps whose task is done.
+    pub fn done_count(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| s.invalidated.is_none() && s.status == TaskStatus::Done)
+            .count()
+    }
+
+    /// Number of steps that have not been invalidated.
+    pub fn active_count(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| s.invalidated.is_none())
+            .count()
+    }
+}
+
 // =============================================================================
 // TaskStore
 // =============================================================================
@@ -80,6 +225,7 @@ pub struct Task {
 struct TaskStoreInner {
     tasks: HashMap<String, Task>,
     next_id: u32,
+    plan: Option<Plan>,
 }
 
 /// In-memory task store, session-scoped.
@@ -98,6 +244,7 @@ impl TaskStore {
             inner: Mutex::new(TaskStoreInner {
                 tasks: HashMap::new(),
                 next_id: 1,
+                plan: None,
             }),
             completion_notify: Notify::new(),
         }
@@ -138,6 +285,32 @@ impl TaskStore {
         self.completion_notify.notify_waiters();
     }
 
+    /// Create a `todo` task outside the tool interface. Returns its id.
+    pub fn add_task(
+        &self,
+        title: impl Into<String>,
+        description: Option<String>,
+        assignee: Option<String>,
+    ) -> String {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id.to_string();
+        inner.next_id += 1;
+        inner.tasks.insert(
+            id.clone(),
+            Task {
+                id: id.clone(),
+                title: title.into(),
+                status: TaskStatus::Todo,
+                assignee,
+                description,
+                blocked_by: Vec::new(),
+                notes: Vec::new(),
+                result: None,
+            },
+        );
+        id
+    }
+
     /// Wait until at least one task in `watched_ids` has status Done or Blocked.
     ///
     /// Returns the IDs of completed/blocked tasks. If any are already
@@ -185,6 +358,32 @@ impl TaskStore {
         }
     }
 
+    /// Snapshot the active plan with each step's current task state.
+    ///
+    /// Returns `None` if no plan has been submitted. Steps whose backing
+    /// task has since been deleted are omitted.
+    pub fn plan_snapshot(&self) -> Option<PlanSnapshot> {
+        let inner = self.inner.lock().unwrap();
+        let plan = inner.plan.as_ref()?;
+        let steps = plan
+            .task_ids
+            .iter()
+            .filter_map(|id| inner.tasks.get(id))
+            .map(|task| PlanStepView {
+                task_id: task.id.clone(),
+                title: task.title.clone(),
+                owner: task.assignee.clone(),
+                status: task.status.clone(),
+                invalidated: plan.invalidated.get(&task.id).cloned(),
+            })
+            .collect();
+        Some(PlanSnapshot {
+            goal: plan.goal.clone(),
+            steps,
+            revisions: plan.revisions.clone(),
+        })
+    }
+
     /// Format a compact markdown task board summary.
     ///
     /// Returns `None` if no tasks exist. Shows status, id, title, assignee,
@@ -213,6 +412,35 @@ impl TaskStore {
 
         let mut lines = vec!["## Current Task Board".to_string(), String::new()];
 
+        if let Some(plan) = &inner.plan {
+            let steps: Vec<&Task> = plan
+                .task_ids
+                .iter()
+                .filter_map(|id| inner.tasks.get(id))
+                .filter(|t| !plan.invalidated.contains_key(&t.id))
+                .collect();
+            let done = steps.iter().filter(|t| t.status == TaskStatus::Done).count();
+            let invalidated = if plan.invalidated.is_empty() {
+                String::new()
+            } else {
+                format!(", {} invalidated", plan.invalidated.len())
+            };
+            lines.push(format!(
+                "Plan: {} ({}/{} steps done{})",
+                plan.goal,
+                done,
+                steps.len(),
+                invalidated
+            ));
+            if let Some(revision) = plan.revisions.last() {
+                lines.push(format!(
+                    "Latest revision ({}): {}",
+                    revision.number, revision.reason
+                ));
+            }
+            lines.push(String::new());
+        }
+
         for task in &tasks {
             let assignee_str = task
                 .assignee
@@ -242,6 +470,14 @@ impl TaskStore {
                 lines.push(format!("  description: {}", desc));
             }
 
+            if let Some(reason) = inner
+                .plan
+                .as_ref()
+                .and_then(|p| p.invalidated.get(&task.id))
+            {
+                lines.push(format!("  invalidated: {}", reason));
+            }
+
             if let Some(note) = task.notes.last() {
                 lines.push(format!("  latest note: {}", note));
             }
@@ -254,11 +490,12 @@ impl TaskStore {
         Some(lines.join("\n"))
     }
 
-    /// Clear all tasks and reset the ID counter.
+    /// Clear all tasks and the active plan, and reset the ID counter.
     pub fn clear(&self) {
         let mut inner = self.inner.lock().unwrap();
         inner.tasks.clear();
         inner.next_id = 1;
+        inner.plan = None;
     }
 }
 
@@ -1027,6 +1264,610 @@ impl Tool for WaitForTasksTool {
     }
 }
 
+// =============================================================================
+// SubmitPlanTool
+// =============================================================================
+
+/// Tool for submitting a structured, machine-readable plan.
+///
+/// Each step becomes a tracked task (owner → assignee, `depends_on` →
+/// `blocked_by`), and the plan itself is recorded on the store so the UI
+/// can render live progress as sub-agents complete their steps.
+/// Submitting a new plan replaces the active one; tasks created for the
+/// previous plan stay on the board.
+pub struct SubmitPlanTool {
+    store: Arc<TaskStore>,
+}
+
+impl SubmitPlanTool {
+    pub fn new(store: Arc<TaskStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[derive(Deserialize)]
+struct SubmitPlanArgs {
+    goal: String,
+    steps: Vec<PlanStepArgs>,
+}
+
+#[derive(Deserialize)]
+struct PlanStepArgs {
+    title: String,
+    owner: String,
+    #[serde(default)]
+    description: Option<String>,
+    /// 1-based numbers of earlier steps this step depends on.
+    #[serde(default)]
+    depends_on: Vec<usize>,
+}
+
+#[async_trait]
+impl Tool for SubmitPlanTool {
+    fn name(&self) -> &str {
+        "submit_plan"
+    }
+
+    fn description(&self) -> &str {
+        "Submit the approved plan as ordered steps with owners. Creates one tracked task per step \
+         (with dependencies) and shows live plan progress to the user. Replaces any previous plan."
+    }
+
+    fn tool_description(&self) -> &str {
+        "Submit a structured plan; each step becomes a tracked task."
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        let step_schema = PropertySchema {
+            schema_type: "object".to_string(),
+            description: Some(
+                "A plan step: {title: string, owner: string (agent name), \
+                 description?: string, depends_on?: [step numbers]}. \
+                 depends_on uses 1-based numbers of EARLIER steps."
+                    .to_string(),
+            ),
+            enum_values: None,
+            default: None,
+            items: None,
+        };
+        ToolDefinition::new(self.name(), self.description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "goal",
+                    PropertySchema::string("One-line summary of what the plan delivers"),
+                    true,
+                )
+                .add_property(
+                    "steps",
+                    PropertySchema::array("Ordered plan steps", step_schema),
+                    true,
+                ),
+        )
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: SubmitPlanArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("submit_plan", format!("Invalid arguments: {}", e)))?;
+
+        if args.steps.is_empty() {
+            return Ok(ToolOutput::error("A plan needs at least one step".to_string()));
+        }
+
+        // Validate all steps before creating any tasks so a bad plan
+        // leaves the board untouched.
+        for (i, step) in args.steps.iter().enumerate() {
+            let number = i + 1;
+            for &dep in &step.depends_on {
+                if dep == 0 || dep >= number {
+                    return Ok(ToolOutput::error(format!(
+                        "Step {} depends on step {}, but steps may only depend on earlier steps (1..{})",
+                        number,
+                        dep,
+                        number - 1
+                    )));
+                }
+            }
+        }
+
+        let mut inner = self.store.inner.lock().unwrap();
+        let mut task_ids: Vec<String> = Vec::with_capacity(args.steps.len());
+        let mut lines = vec![format!("Plan submitted: {}", args.goal), String::new()];
+
+        for (i, step) in args.steps.into_iter().enumerate() {
+            let id = inner.next_id.to_string();
+            inner.next_id += 1;
+
+            let blocked_by: Vec<String> = step
+                .depends_on
+                .iter()
+                .map(|&dep| task_ids[dep - 1].clone())
+                .collect();
+
+            let deps_str = if blocked_by.is_empty() {
+                String::new()
+            } else {
+                let deps: Vec<String> = blocked_by.iter().map(|id| format!("#{}", id)).collect();
+                format!(" — blocked by {}", deps.join(", "))
+            };
+            lines.push(format!(
+                "{}. #{}: {} ({}){}",
+                i + 1,
+                id,
+                step.title,
+                step.owner,
+                deps_str
+            ));
+
+            inner.tasks.insert(
+                id.clone(),
+                Task {
+                    id: id.clone(),
+                    title: step.title,
+                    status: TaskStatus::Todo,
+                    assignee: Some(step.owner),
+                    description: step.description,
+                    blocked_by,
+                    notes: Vec::new(),
+                    result: None,
+                },
+            );
+            task_ids.push(id);
+        }
+
+        let replaced = inner.plan.is_some();
+        inner.plan = Some(Plan::new(args.goal, task_ids));
+
+        lines.push(String::new());
+        lines.push("Each step is a tracked task; dispatch ready steps by task ID.".to_string());
+        if replaced {
+            lines.push(
+                "This replaced the previous plan. To adjust a plan as findings come in, \
+                 use revise_plan instead."
+                    .to_string(),
+            );
+        }
+
+        Ok(ToolOutput::success(lines.join("\n")))
+    }
+}
+
+// =============================================================================
+// RevisePlanTool
+// =============================================================================
+
+/// Tool for revising the active plan when findings invalidate part of it.
+///
+/// Unlike `submit_plan`, a revision keeps the plan's tasks and history: the
+/// steps it invalidates stay on the board marked blocked, steps that build on
+/// them are flagged for review, and the revision is recorded with its reason
+/// so the user sees what changed and why.
+pub struct RevisePlanTool {
+    store: Arc<TaskStore>,
+}
+
+impl RevisePlanTool {
+    pub fn new(store: Arc<TaskStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[derive(Deserialize)]
+struct RevisePlanArgs {
+    reason: String,
+    #[serde(default)]
+    goal: Option<String>,
+    #[serde(default)]
+    invalidate: Vec<InvalidateStepArgs>,
+    #[serde(default)]
+    update: Vec<UpdateStepArgs>,
+    #[serde(default)]
+    add: Vec<AddStepArgs>,
+}
+
+#[derive(Deserialize)]
+struct InvalidateStepArgs {
+    task_id: String,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateStepArgs {
+    task_id: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    owner: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    blocked_by: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct AddStepArgs {
+    title: String,
+    owner: String,
+    #[serde(default)]
+    description: Option<String>,
+    /// 1-based numbers of earlier steps in this `add` list.
+    #[serde(default)]
+    depends_on: Vec<usize>,
+    /// Existing task IDs this step depends on.
+    #[serde(default)]
+    blocked_by: Vec<String>,
+}
+
+impl RevisePlanTool {
+    /// Check a revision against the current plan so a bad one leaves the
+    /// board untouched.
+    fn validate_parts(
+        tasks: &HashMap<String, Task>,
+        plan: &Plan,
+        args: &RevisePlanArgs,
+    ) -> Result<(), String> {
+        if args.goal.is_none()
+            && args.invalidate.is_empty()
+            && args.update.is_empty()
+            && args.add.is_empty()
+        {
+            return Err(
+                "A revision needs at least one of goal, invalidate, update or add".to_string(),
+            );
+        }
+
+        let in_plan = |id: &str| plan.task_ids.iter().any(|t| t == id) && tasks.contains_key(id);
+        let mut invalid: Vec<&str> = plan.invalidated.keys().map(String::as_str).collect();
+        for step in &args.invalidate {
+            if !in_plan(&step.task_id) {
+                return Err(format!("Task '{}' is not a step of the plan", step.task_id));
+            }
+            if invalid.contains(&step.task_id.as_str()) {
+                return Err(format!("Step #{} is already invalidated", step.task_id));
+            }
+            invalid.push(&step.task_id);
+        }
+
+        let check_deps = |deps: &[String], own: Option<&str>| -> Result<(), String> {
+            for dep in deps {
+                if Some(dep.as_str()) == own {
+                    return Err("A task cannot depend on itself".to_string());
+                }
+                if !tasks.contains_key(dep) {
+                    return Err(format!("Dependency task '{}' not found", dep));
+                }
+                if invalid.contains(&dep.as_str()) {
+                    return Err(format!("Cannot depend on invalidated step #{}", dep));
+                }
+            }
+            Ok(())
+        };
+
+        for step in &args.update {
+            if !in_plan(&step.task_id) {
+                return Err(format!("Task '{}' is not a step of the plan", step.task_id));
+            }
+            if invalid.contains(&step.task_id.as_str()) {
+                return Err(format!(
+                    "Step #{} is invalidated and cannot be updated",
+                    step.task_id
+                ));
+            }
+            if let Some(ref deps) = step.blocked_by {
+                check_deps(deps, Some(&step.task_id))?;
+            }
+        }
+
+        for (i, step) in args.add.iter().enumerate() {
+            let number = i + 1;
+            for &dep in &step.depends_on {
+                if dep == 0 || dep >= number {
+                    return Err(format!(
+                        "Added step {} depends on step {}, but added steps may only depend on earlier added steps (1..{})",
+                        number,
+                        dep,
+                        number - 1
+                    ));
+                }
+            }
+            check_deps(&step.blocked_by, None)?;
+        }
+        Ok(())
+    }
+}
+
+/// Join task IDs for display, e.g. `#1, #2`.
+fn format_ids(ids: &[String]) -> String {
+    if ids.is_empty() {
+        return "none".to_string();
+    }
+    ids.iter()
+        .map(|id| format!("#{}", id))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[async_trait]
+impl Tool for RevisePlanTool {
+    fn name(&self) -> &str {
+        "revise_plan"
+    }
+
+    fn description(&self) -> &str {
+        "Revise the active plan when findings invalidate part of it. Marks invalidated steps, \
+         updates or adds steps, flags steps that depend on invalidated ones, and records the \
+         revision with its reason so the user sees what changed. Keeps all existing tasks."
+    }
+
+    fn tool_description(&self) -> &str {
+        "Revise the active plan; invalidated steps and changes are recorded."
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        let invalidate_schema = PropertySchema {
+            schema_type: "object".to_string(),
+            description: Some(
+                "A step that no longer holds: {task_id: string, reason: string}".to_string(),
+            ),
+            enum_values: None,
+            default: None,
+            items: None,
+        };
+        let update_schema = PropertySchema {
+            schema_type: "object".to_string(),
+            description: Some(
+                "Changes to a remaining step: {task_id: string, title?: string, owner?: string, \
+                 description?: string, blocked_by?: [task IDs]}"
+                    .to_string(),
+            ),
+            enum_values: None,
+            default: None,
+            items: None,
+        };
+        let add_schema = PropertySchema {
+            schema_type: "object".to_string(),
+            description: Some(
+                "A new step: {title: string, owner: string (agent name), description?: string, \
+                 depends_on?: [numbers], blocked_by?: [task IDs]}. depends_on uses 1-based \
+                 numbers of EARLIER steps in this add list; blocked_by names existing tasks."
+                    .to_string(),
+            ),
+            enum_values: None,
+            default: None,
+            items: None,
+        };
+        ToolDefinition::new(self.name(), self.description()).with_parameters(
+            ToolParameters::new()
+                .add_property(
+                    "reason",
+                    PropertySchema::string("The finding that makes the revision necessary"),
+                    true,
+                )
+                .add_property(
+                    "goal",
+                    PropertySchema::string("New one-line goal, if it changed"),
+                    false,
+                )
+                .add_property(
+                    "invalidate",
+                    PropertySchema::array("Steps that no longer hold", invalidate_schema),
+                    false,
+                )
+                .add_property(
+                    "update",
+                    PropertySchema::array("Changes to remaining steps", update_schema),
+                    false,
+                )
+                .add_property(
+                    "add",
+                    PropertySchema::array("New steps appended to the plan", add_schema),
+                    false,
+                ),
+        )
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let args: RevisePlanArgs = serde_json::from_value(arguments)
+            .map_err(|e| Error::tool("revise_plan", format!("Invalid arguments: {}", e)))?;
+
+        let mut guard = self.store.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let Some(plan) = inner.plan.as_mut() else {
+            return Ok(ToolOutput::error(
+                "No plan to revise; submit one with submit_plan first".to_string(),
+            ));
+        };
+        if let Err(e) = Self::validate_parts(&inner.tasks, plan, &args) {
+            return Ok(ToolOutput::error(e));
+        }
+
+        let number = plan.revisions.len() + 1;
+        let mut changes = Vec::new();
+
+        if let Some(goal) = args.goal {
+            if goal != plan.goal {
+                changes.push(PlanChange::GoalChanged {
+                    from: std::mem::replace(&mut plan.goal, goal.clone()),
+                    to: goal,
+                });
+            }
+        }
+
+        let mut newly_invalid = Vec::new();
+        for step in args.invalidate {
+            let task = inner.tasks.get_mut(&step.task_id).expect("validated");
+            task.status = TaskStatus::Blocked;
+            task.notes.push(format!(
+                "Invalidated in plan revision {}: {}",
+                number, step.reason
+            ));
+            changes.push(PlanChange::Invalidated {
+                task_id: step.task_id.clone(),
+                title: task.title.clone(),
+                reason: step.reason.clone(),
+            });
+            plan.invalidated.insert(step.task_id.clone(), step.reason);
+            newly_invalid.push(step.task_id);
+        }
+
+        for step in args.update {
+            let task = inner.tasks.get_mut(&step.task_id).expect("validated");
+            let mut record = |field, from: String, to: String| {
+                if from != to {
+                    changes.push(PlanChange::Updated {
+                        task_id: step.task_id.clone(),
+                        field,
+                        from,
+                        to,
+                    });
+                }
+            };
+            if let Some(title) = step.title {
+                record("title", task.title.clone(), title.clone());
+                task.title = title;
+            }
+            if let Some(owner) = step.owner {
+                record(
+                    "owner",
+                    task.assignee.clone().unwrap_or_else(|| "none".to_string()),
+                    owner.clone(),
+                );
+                task.assignee = Some(owner);
+            }
+            if let Some(description) = step.description {
+                record(
+                    "description",
+                    task.description.clone().unwrap_or_default(),
+                    description.clone(),
+                );
+                task.description = Some(description);
+            }
+            if let Some(deps) = step.blocked_by {
+                record(
+                    "blocked_by",
+                    format_ids(&task.blocked_by),
+                    format_ids(&deps),
+                );
+                task.blocked_by = deps;
+            }
+        }
+
+        let mut added_ids: Vec<String> = Vec::with_capacity(args.add.len());
+        for step in args.add {
+            let id = inner.next_id.to_string();
+            inner.next_id += 1;
+            let mut blocked_by = step.blocked_by;
+            blocked_by.extend(
+                step.depends_on
+                    .iter()
+                    .map(|&dep| added_ids[dep - 1].clone()),
+            );
+            changes.push(PlanChange::Added {
+                task_id: id.clone(),
+                title: step.title.clone(),
+                owner: step.owner.clone(),
+            });
+            inner.tasks.insert(
+                id.clone(),
+                Task {
+                    id: id.clone(),
+                    title: step.title,
+                    status: TaskStatus::Todo,
+                    assignee: Some(step.owner),
+                    description: step.description,
+                    blocked_by,
+                    notes: Vec::new(),
+                    result: None,
+                },
+            );
+            plan.task_ids.push(id.clone());
+            added_ids.push(id);
+        }
+
+        // Steps that still depend, directly or through other steps, on a
+        // step invalidated now were planned on assumptions that no longer
+        // hold; flag them so they are rechecked rather than run as-is.
+        let mut reached: HashMap<String, String> = HashMap::new();
+        let mut frontier: Vec<(String, String)> = newly_invalid
+            .iter()
+            .map(|id| (id.clone(), id.clone()))
+            .collect();
+        while let Some((id, root)) = frontier.pop() {
+            for step_id in &plan.task_ids {
+                if plan.invalidated.contains_key(step_id) || reached.contains_key(step_id) {
+                    continue;
+                }
+                let depends = inner
+                    .tasks
+                    .get(step_id)
+                    .is_some_and(|t| t.blocked_by.contains(&id));
+                if depends {
+                    reached.insert(step_id.clone(), root.clone());
+                    frontier.push((step_id.clone(), root.clone()));
+                }
+            }
+        }
+        for step_id in &plan.task_ids {
+            if let Some(root) = reached.remove(step_id) {
+                changes.push(PlanChange::NeedsReview {
+                    task_id: step_id.clone(),
+                    title: inner.tasks[step_id].title.clone(),
+                    because: root,
+                });
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(ToolOutput::error(
+                "The revision does not change the plan".to_string(),
+            ));
+        }
+
+        let needs_review = changes
+            .iter()
+            .any(|c| matches!(c, PlanChange::NeedsReview { .. }));
+        let mut lines = vec![
+            format!("Plan revised (revision {}): {}", number, args.reason),
+            String::new(),
+        ];
+        lines.extend(changes.iter().map(|c| c.to_string()));
+        plan.revisions.push(PlanRevision {
+            number,
+            reason: args.reason,
+            changes,
+        });
+
+        let active = plan
+            .task_ids
+            .iter()
+            .filter(|id| !plan.invalidated.contains_key(*id))
+            .count();
+        lines.push(String::new());
+        lines.push(format!(
+            "Plan: {} ({} active steps, {} invalidated)",
+            plan.goal,
+            active,
+            plan.invalidated.len()
+        ));
+        if needs_review {
+            lines.push(
+                "Steps marked ? depend on invalidated work: update, invalidate or re-dispatch \
+                 them before they run."
+                    .to_string(),
+            );
+        }
+
+        Ok(ToolOutput::success(lines.join("\n")))
+    }
+}
+
 // =============================================================================
 // Factory functions
 // =============================================================================
@@ -1040,7 +1881,9 @@ pub fn create_task_tools(store: Arc<TaskStore>) -> Vec<Box<dyn Tool>> {
         Box::new(DeleteTaskTool::new(store.clone())),
         Box::new(UpdateMyTaskTool::new(store.clone())),
         Box::new(GetTaskResultTool::new(store.clone())),
-        Box::new(WaitForTasksTool::new(store)),
+        Box::new(WaitForTasksTool::new(store.clone())),
+        Box::new(SubmitPlanTool::new(store.clone())),
+        Box::new(RevisePlanTool::new(store)),
     ]
 }
 
@@ -1053,7 +1896,9 @@ pub fn create_task_tools_arc(store: Arc<TaskStore>) -> Vec<Arc<dyn Tool>> {
         Arc::new(DeleteTaskTool::new(store.clone())),
         Arc::new(UpdateMyTaskTool::new(store.clone())),
         Arc::new(GetTaskResultTool::new(store.clone())),
-        Arc::new(WaitForTasksTool::new(store)),
+        Arc::new(WaitForTasksTool::new(store.clone())),
+        Arc::new(SubmitPlanTool::new(store.clone())),
+        Arc::new(RevisePlanTool::new(store)),
     ]
 }
 
@@ -1257,7 +2102,7 @@ mod tests {
         let store = new_store();
 
         let boxed = create_task_tools(store.clone());
-        assert_eq!(boxed.len(), 7);
+        assert_eq!(boxed.len(), 9);
         assert_eq!(boxed[0].name(), "create_task");
         assert_eq!(boxed[1].name(), "update_task");
         assert_eq!(boxed[2].name(), "list_tasks");
@@ -1265,11 +2110,15 @@ mod tests {
         assert_eq!(boxed[4].name(), "update_my_task");
         assert_eq!(boxed[5].name(), "get_task_result");
         assert_eq!(boxed[6].name(), "wait_for_tasks");
+        assert_eq!(boxed[7].name(), "submit_plan");
+        assert_eq!(boxed[8].name(), "revise_plan");
 
         let arced = create_task_tools_arc(store);
-        assert_eq!(arced.len(), 7);
+        assert_eq!(arced.len(), 9);
         assert_eq!(arced[0].name(), "create_task");
         assert_eq!(arced[6].name(), "wait_for_tasks");
+        assert_eq!(arced[7].name(), "submit_plan");
+        assert_eq!(arced[8].name(), "revise_plan");
     }
 
     // --- Dependency tests ---
@@ -1878,4 +2727,232 @@ mod tests {
         let board = store.format_board().unwrap();
         assert!(board.contains("result: [available"));
     }
+
+    // --- SubmitPlanTool tests ---
+
+    #[tokio::test]
+    async fn test_submit_plan_creates_tasks() {
+        let store = new_store();
+        let submit = SubmitPlanTool::new(store.clone());
+
+        let result = submit
+            .execute(serde_json::json!({
+                "goal": "Add auth",
+                "steps": [
+                    {"title": "Explore auth setup", "owner": "explore"},
+                    {"title": "Implement middleware", "owner": "coder", "depends_on": [1]},
+                    {"title": "Review", "owner": "reviewer", "depends_on": [1, 2]}
+                ]
+            }))
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+        assert!(result.text_content().contains("#2: Implement middleware (coder)"));
+
+        let task = store.get_task("3").unwrap();
+        assert_eq!(task.assignee.as_deref(), Some("reviewer"));
+        assert_eq!(task.blocked_by, vec!["1".to_string(), "2".to_string()]);
+
+        let snapshot = store.plan_snapshot().unwrap();
+        assert_eq!(snapshot.goal, "Add auth");
+        assert_eq!(snapshot.steps.len(), 3);
+        assert_eq!(snapshot.done_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_submit_plan_rejects_forward_dependency() {
+        let store = new_store();
+        let submit = SubmitPlanTool::new(store.clone());
+
+        let result = submit
+            .execute(serde_json::json!({
+                "goal": "Bad plan",
+                "steps": [
+                    {"title": "First", "owner": "coder", "depends_on": [2]},
+                    {"title": "Second", "owner": "coder"}
+                ]
+            }))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("earlier steps"));
+        // Nothing should have been created
+        assert!(store.format_board().is_none());
+        assert!(store.plan_snapshot().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_submit_plan_rejects_empty() {
+        let store = new_store();
+        let submit = SubmitPlanTool::new(store.clone());
+
+        let result = submit
+            .execute(serde_json::json!({"goal": "Nothing", "steps": []}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_plan_snapshot_tracks_task_status() {
+        let store = new_store();
+        let submit = SubmitPlanTool::new(store.clone());
+        let update_my = UpdateMyTaskTool::new(store.clone());
+
+        submit
+            .execute(serde_json::json!({
+                "goal": "Ship it",
+                "steps": [
+                    {"title": "Build", "owner": "coder"},
+                    {"title": "Verify", "owner": "qa", "depends_on": [1]}
+                ]
+            }))
+            .await
+            .unwrap();
+
+        update_my
+            .execute(serde_json::json!({"id": "1", "status": "done"}))
+            .await
+            .unwrap();
+        store.set_status("2", TaskStatus::InProgress);
+
+        let snapshot = store.plan_snapshot().unwrap();
+        assert_eq!(snapshot.done_count(), 1);
+        assert_eq!(snapshot.steps[1].status, TaskStatus::InProgress);
+
+        let board = store.format_board().unwrap();
+        assert!(board.contains("Plan: Ship it (1/2 steps done)"));
+
+        store.clear();
+        assert!(store.plan_snapshot().is_none());
+    }
+
+    // --- RevisePlanTool tests ---
+
+    #[tokio::test]
+    async fn test_revise_plan_invalidates_and_flags_dependents() {
+        let store = new_store();
+        let submit = SubmitPlanTool::new(store.clone());
+        let revise = RevisePlanTool::new(store.clone());
+
+        submit
+            .execute(serde_json::json!({
+                "goal": "Add auth",
+                "steps": [
+                    {"title": "Explore auth setup", "owner": "explore"},
+                    {"title": "Extend session middleware", "owner": "coder", "depends_on": [1]},
+                    {"title": "Write tests", "owner": "coder", "depends_on": [2]},
+                    {"title": "Review", "owner": "reviewer", "depends_on": [1]}
+                ]
+            }))
+            .await
+            .unwrap();
+
+        let result = revise
+            .execute(serde_json::json!({
+                "reason": "The project has no session middleware",
+                "invalidate": [{"task_id": "2", "reason": "no middleware to extend"}],
+                "update": [{"task_id": "4", "owner": "qa"}],
+                "add": [
+                    {"title": "Add JWT middleware", "owner": "coder", "blocked_by": ["1"]},
+                    {"title": "Wire middleware", "owner": "coder", "depends_on": [1]}
+                ]
+            }))
+            .await
+            .unwrap();
+        assert!(!result.is_error, "{}", result.text_content());
+        let text = result.text_content();
+        assert!(text.contains("Plan revised (revision 1): The project has no session middleware"));
+        assert!(text.contains("✗ #2: Extend session middleware — no middleware to extend"));
+        assert!(text.contains("~ #4 owner: reviewer → qa"));
+        assert!(text.contains("+ #5: Add JWT middleware (coder)"));
+        assert!(text.contains("? #3: Write tests — depends on invalidated #2"));
+
+        let invalidated = store.get_task("2").unwrap();
+        assert_eq!(invalidated.status, TaskStatus::Blocked);
+        assert_eq!(
+            invalidated.notes,
+            vec!["Invalidated in plan revision 1: no middleware to extend".to_string()]
+        );
+        assert_eq!(
+            store.get_task("6").unwrap().blocked_by,
+            vec!["5".to_string()]
+        );
+
+        let snapshot = store.plan_snapshot().unwrap();
+        assert_eq!(snapshot.steps.len(), 6);
+        assert_eq!(snapshot.active_count(), 5);
+        assert_eq!(
+            snapshot.steps[1].invalidated.as_deref(),
+            Some("no middleware to extend")
+        );
+        assert_eq!(snapshot.revisions.len(), 1);
+        assert_eq!(snapshot.revisions[0].changes.len(), 5);
+
+        let board = store.format_board().unwrap();
+        assert!(board.contains("Plan: Add auth (0/5 steps done, 1 invalidated)"));
+        assert!(board.contains("Latest revision (1): The project has no session middleware"));
+    }
+
+    #[tokio::test]
+    async fn test_revise_plan_rejects_bad_revisions() {
+        let store = new_store();
+        let revise = RevisePlanTool::new(store.clone());
+
+        let result = revise
+            .execute(serde_json::json!({"reason": "x", "goal": "y"}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.text_content().contains("submit_plan"));
+
+        SubmitPlanTool::new(store.clone())
+            .execute(serde_json::json!({
+                "goal": "Ship",
+                "steps": [
+                    {"title": "Build", "owner": "coder"},
+                    {"title": "Verify", "owner": "qa", "depends_on": [1]}
+                ]
+            }))
+            .await
+            .unwrap();
+
+        for (args, expected) in [
+            (serde_json::json!({"reason": "x"}), "at least one"),
+            (
+                serde_json::json!({"reason": "x", "invalidate": [{"task_id": "9", "reason": "r"}]}),
+                "not a step",
+            ),
+            (
+                serde_json::json!({
+                    "reason": "x",
+                    "invalidate": [{"task_id": "1", "reason": "r"}],
+                    "add": [{"title": "t", "owner": "coder", "blocked_by": ["1"]}]
+                }),
+                "invalidated step #1",
+            ),
+            (
+                serde_json::json!({"reason": "x", "add": [{"title": "t", "owner": "coder", "depends_on": [1]}]}),
+                "earlier added steps",
+            ),
+            (
+                serde_json::json!({"reason": "x", "update": [{"task_id": "2", "owner": "qa"}]}),
+                "does not change",
+            ),
+        ] {
+            let result = revise.execute(args).await.unwrap();
+            assert!(result.is_error);
+            assert!(
+                result.text_content().contains(expected),
+                "{}",
+                result.text_content()
+            );
+        }
+
+        // Nothing was applied
+        let snapshot = store.plan_snapshot().unwrap();
+        assert_eq!(snapshot.active_count(), 2);
+        assert!(snapshot.revisions.is_empty());
+        assert_eq!(store.get_task("1").unwrap().status, TaskStatus::Todo);
+    }
 }
@@ -0,0 +1,657 @@
+//! WebAssembly plugins.
+//!
+//! A plugin can ship a `.wasm` module instead of (or besides) executables.
+//! The module runs in-process in an interpreter (wasmi) with no ambient
+//! access: no WASI, no sockets, no environment. The only way out is the
+//! `qq` host functions below, and those enforce the capabilities the plugin
+//! was granted: files resolve against the project root, reads outside it
+//! need `home` (and then only reach `$HOME`), and writes need `write` and
+//! stay inside the project root. Every call gets a fresh instance with a
+//! fuel and memory budget, so a plugin can neither keep state between
+//! calls nor spin forever.
+//!
+//! Guest exports:
+//!
+//! - `memory`
+//! - `qq_negotiate(host_max: i32) -> i32`: version negotiation. The host
+//!   passes the newest plugin API it speaks ([`PLUGIN_API_VERSION`]); the
+//!   plugin returns the version it will use, or a negative number to refuse.
+//! - `qq_alloc(len: i32) -> i32`: memory for data the host hands over.
+//! - `qq_manifest() -> i32`: writes `{"tools": [...], "agents": {...}}` as
+//!   JSON through `qq.output` and returns 0.
+//! - `qq_call(name_ptr, name_len, args_ptr, args_len) -> i32`: runs a tool
+//!   with its JSON arguments. Output written through `qq.output` is the
+//!   result; a non-zero return reports it as an error.
+//!
+//! Host imports (module `qq`):
+//!
+//! - `output(ptr, len)`: append to the result
+//! - `log(ptr, len)`: log a line at info level
+//! - `read_file(path_ptr, path_len) -> i64`: file contents in guest memory
+//!   as `ptr << 32 | len`, or `-1` when denied and `-2` when unreadable
+//! - `write_file(path_ptr, path_len, data_ptr, data_len) -> i32`: `0`, or
+//!   `-1` when denied and `-2` when the write failed
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use wasmi::{
+    Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+use qq_core::{Error, Tool, ToolDefinition, ToolOutput, ToolParameters};
+
+use crate::bash::{plugin_tool_name, PluginGrants, PLUGIN_API_VERSION};
+
+/// Instructions (roughly) a single call may execute, by default.
+const DEFAULT_CALL_FUEL: u64 = 1_000_000_000;
+
+/// Linear memory a plugin instance may grow to.
+const MAX_MEMORY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Output kept from a single call.
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Largest file `read_file` hands to a plugin.
+const MAX_READ_BYTES: u64 = 16 * 1024 * 1024;
+
+const DENIED: i32 = -1;
+const FAILED: i32 = -2;
+
+/// Tools and agents a module declares from `qq_manifest`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WasmManifest {
+    #[serde(default)]
+    pub tools: Vec<WasmToolSpec>,
+    /// Agent definitions, with the same fields as `[agents.<name>]`
+    #[serde(default)]
+    pub agents: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A tool implemented by a module.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WasmToolSpec {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema of the arguments
+    #[serde(default = "default_parameters")]
+    pub parameters: serde_json::Value,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+fn default_parameters() -> serde_json::Value {
+    serde_json::json!({"type": "object", "properties": {}})
+}
+
+/// A compiled plugin module whose API version has been negotiated.
+pub struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+    grants: PluginGrants,
+    api_version: u32,
+    manifest: WasmManifest,
+    /// Fuel each call starts with
+    fuel: u64,
+}
+
+impl std::fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("name", &self.name)
+            .field("grants", &self.grants)
+            .field("api_version", &self.api_version)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a running instance may touch, and what it has written so far.
+struct Host {
+    output: Vec<u8>,
+    /// Relative paths resolve against this; `None` denies all file access
+    base: Option<PathBuf>,
+    read_roots: Vec<PathBuf>,
+    write_root: Option<PathBuf>,
+    limits: StoreLimits,
+}
+
+impl Host {
+    fn sealed() -> Self {
+        Self {
+            output: Vec::new(),
+            base: None,
+            read_roots: Vec::new(),
+            write_root: None,
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .build(),
+        }
+    }
+}
+
+impl WasmPlugin {
+    /// Compile the module at `path`, negotiate the API version and read its
+    /// manifest.
+    pub fn load(name: &str, path: &Path, grants: PluginGrants) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(name, &bytes, grants)
+    }
+
+    /// Like [`load`](Self::load), from the module's bytes.
+    pub fn from_bytes(name: &str, bytes: &[u8], grants: PluginGrants) -> Result<Self, String> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| format!("Invalid module: {}", e))?;
+        let mut plugin = Self {
+            name: name.to_string(),
+            engine,
+            module,
+            grants,
+            api_version: 0,
+            manifest: WasmManifest::default(),
+            fuel: DEFAULT_CALL_FUEL,
+        };
+        let (mut store, instance) = plugin.instantiate(Host::sealed())?;
+        let manifest = instance
+            .get_typed_func::<(), i32>(&store, "qq_manifest")
+            .map_err(|e| format!("missing export qq_manifest: {}", e))?;
+        let status = manifest
+            .call(&mut store, ())
+            .map_err(|e| format!("qq_manifest failed: {}", e))?;
+        if status != 0 {
+            return Err(format!("qq_manifest returned {}", status));
+        }
+        plugin.manifest = serde_json::from_slice(&store.data().output)
+            .map_err(|e| format!("Invalid manifest from qq_manifest: {}", e))?;
+        Ok(plugin)
+    }
+
+    /// Limit each call to `fuel` units (about one per instruction).
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Version picked by the plugin during negotiation.
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    pub fn manifest(&self) -> &WasmManifest {
+        &self.manifest
+    }
+
+    /// A fresh instance with the host functions bound to `host`, after
+    /// version negotiation.
+    fn instantiate(&mut self, host: Host) -> Result<(Store<Host>, Instance), String> {
+        let (store, instance, version) = self.start(host)?;
+        self.api_version = version;
+        Ok((store, instance))
+    }
+
+    fn start(&self, host: Host) -> Result<(Store<Host>, Instance, u32), String> {
+        let mut store = Store::new(&self.engine, host);
+        store.limiter(|host| &mut host.limits);
+        store
+            .set_fuel(self.fuel)
+            .map_err(|e| format!("fuel: {}", e))?;
+        let linker = host_linker(&self.engine).map_err(|e| format!("host functions: {}", e))?;
+        let instance = linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| format!("Failed to instantiate: {}", e))?;
+
+        let negotiate = instance
+            .get_typed_func::<i32, i32>(&store, "qq_negotiate")
+            .map_err(|e| format!("missing export qq_negotiate: {}", e))?;
+        let version = negotiate
+            .call(&mut store, PLUGIN_API_VERSION as i32)
+            .map_err(|e| format!("qq_negotiate failed: {}", e))?;
+        match u32::try_from(version) {
+            Ok(v) if (1..=PLUGIN_API_VERSION).contains(&v) => Ok((store, instance, v)),
+            _ if version < 0 => Err(format!(
+                "plugin refused plugin API {}; it needs a newer qq",
+                PLUGIN_API_VERSION
+            )),
+            _ => Err(format!(
+                "plugin chose plugin API {}, this qq supports 1 to {}",
+                version, PLUGIN_API_VERSION
+            )),
+        }
+    }
+
+    /// Run tool `name` in a fresh instance. `Ok` is the tool's result,
+    /// `Err` a failure reported by the plugin or the runtime.
+    fn call(&self, name: &str, arguments: &str, host: Host) -> Result<String, String> {
+        let (mut store, instance, _) = self.start(host)?;
+        let call = instance
+            .get_typed_func::<(i32, i32, i32, i32), i32>(&store, "qq_call")
+            .map_err(|e| format!("missing export qq_call: {}", e))?;
+        let (name_ptr, name_len) =
+            give(&instance, &mut store, name.as_bytes()).map_err(|e| e.to_string())?;
+        let (args_ptr, args_len) =
+            give(&instance, &mut store, arguments.as_bytes()).map_err(|e| e.to_string())?;
+        let status = call
+            .call(&mut store, (name_ptr, name_len, args_ptr, args_len))
+            .map_err(|e| e.to_string())?;
+        let output = String::from_utf8_lossy(&store.data().output).into_owned();
+        if status == 0 {
+            Ok(output)
+        } else {
+            Err(output)
+        }
+    }
+}
+
+/// The `qq` host functions.
+fn host_linker(engine: &Engine) -> Result<Linker<Host>, wasmi::Error> {
+    let mut linker = Linker::<Host>::new(engine);
+    linker.func_wrap(
+        "qq",
+        "output",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            let bytes = take(&caller, ptr, len)?;
+            let output = &mut caller.data_mut().output;
+            let room = MAX_OUTPUT_BYTES.saturating_sub(output.len());
+            output.extend_from_slice(&bytes[..bytes.len().min(room)]);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "qq",
+        "log",
+        |caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            let bytes = take(&caller, ptr, len)?;
+            tracing::info!(message = %String::from_utf8_lossy(&bytes), "Plugin log");
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "qq",
+        "read_file",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<i64, wasmi::Error> {
+            let path = String::from_utf8_lossy(&take(&caller, ptr, len)?).into_owned();
+            let Some(path) = readable(caller.data(), &path) else {
+                tracing::warn!(path = %path, "Plugin read denied");
+                return Ok(DENIED.into());
+            };
+            let data = match std::fs::metadata(&path) {
+                Ok(meta) if meta.len() <= MAX_READ_BYTES => std::fs::read(&path),
+                Ok(_) => return Ok(FAILED.into()),
+                Err(e) => Err(e),
+            };
+            let Ok(data) = data else {
+                return Ok(FAILED.into());
+            };
+            let instance = caller_instance(&caller)?;
+            let (ptr, len) = give_from_caller(&instance, &mut caller, &data)?;
+            Ok(((ptr as u32 as i64) << 32) | len as u32 as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "qq",
+        "write_file",
+        |caller: Caller<'_, Host>,
+         path_ptr: i32,
+         path_len: i32,
+         data_ptr: i32,
+         data_len: i32|
+         -> Result<i32, wasmi::Error> {
+            let path = String::from_utf8_lossy(&take(&caller, path_ptr, path_len)?).into_owned();
+            let Some(path) = writable(caller.data(), &path) else {
+                tracing::warn!(path = %path, "Plugin write denied");
+                return Ok(DENIED);
+            };
+            let data = take(&caller, data_ptr, data_len)?;
+            Ok(match std::fs::write(&path, data) {
+                Ok(()) => 0,
+                Err(_) => FAILED,
+            })
+        },
+    )?;
+    Ok(linker)
+}
+
+/// The exports of the instance a host function was called from.
+struct CallerExports {
+    memory: Memory,
+    alloc: wasmi::TypedFunc<i32, i32>,
+}
+
+fn caller_instance(caller: &Caller<'_, Host>) -> Result<CallerExports, wasmi::Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("missing export memory"))?;
+    let alloc = caller
+        .get_export("qq_alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmi::Error::new("missing export qq_alloc"))?
+        .typed::<i32, i32>(caller)?;
+    Ok(CallerExports { memory, alloc })
+}
+
+/// Copy `len` bytes at `ptr` out of the caller's memory.
+fn take(caller: &Caller<'_, Host>, ptr: i32, len: i32) -> Result<Vec<u8>, wasmi::Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("missing export memory"))?;
+    let mut buf = vec![0; len.max(0) as usize];
+    memory
+        .read(caller, ptr as u32 as usize, &mut buf)
+        .map_err(|e| wasmi::Error::new(e.to_string()))?;
+    Ok(buf)
+}
+
+/// Allocate guest memory from a host function and copy `data` into it.
+fn give_from_caller(
+    exports: &CallerExports,
+    caller: &mut Caller<'_, Host>,
+    data: &[u8],
+) -> Result<(i32, i32), wasmi::Error> {
+    let len = i32::try_from(data.len()).map_err(|_| wasmi::Error::new("data too large"))?;
+    let ptr = exports.alloc.call(&mut *caller, len)?;
+    exports
+        .memory
+        .write(&mut *caller, ptr as u32 as usize, data)
+        .map_err(|e| wasmi::Error::new(e.to_string()))?;
+    Ok((ptr, len))
+}
+
+/// Allocate guest memory from the host side and copy `data` into it.
+fn give(
+    instance: &Instance,
+    store: &mut Store<Host>,
+    data: &[u8],
+) -> Result<(i32, i32), wasmi::Error> {
+    let memory = instance
+        .get_memory(&*store, "memory")
+        .ok_or_else(|| wasmi::Error::new("missing export memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&*store, "qq_alloc")?;
+    let len = i32::try_from(data.len()).map_err(|_| wasmi::Error::new("data too large"))?;
+    let ptr = alloc.call(&mut *store, len)?;
+    memory
+        .write(&mut *store, ptr as u32 as usize, data)
+        .map_err(|e| wasmi::Error::new(e.to_string()))?;
+    Ok((ptr, len))
+}
+
+/// `path` resolved against the project root.
+fn resolve(host: &Host, path: &str) -> Option<PathBuf> {
+    let base = host.base.as_ref()?;
+    let path = Path::new(path);
+    Some(if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base.join(path)
+    })
+}
+
+/// Canonical `path` if it lies under one of the read roots.
+fn readable(host: &Host, path: &str) -> Option<PathBuf> {
+    let path = resolve(host, path)?.canonicalize().ok()?;
+    host.read_roots
+        .iter()
+        .any(|root| path.starts_with(root))
+        .then_some(path)
+}
+
+/// Canonical target of a write to `path` if it lies under the write root.
+/// The parent must exist; a symlink at `path` is followed and checked too.
+fn writable(host: &Host, path: &str) -> Option<PathBuf> {
+    let root = host.write_root.as_ref()?;
+    let path = resolve(host, path)?;
+    let name = match path.components().next_back()? {
+        Component::Normal(name) => name.to_owned(),
+        _ => return None,
+    };
+    let mut target = path.parent()?.canonicalize().ok()?.join(name);
+    if target.symlink_metadata().is_ok() {
+        target = target.canonicalize().ok()?;
+    }
+    target.starts_with(root).then_some(target)
+}
+
+/// A tool implemented by a plugin module.
+pub struct WasmPluginTool {
+    plugin: Arc<WasmPlugin>,
+    spec: WasmToolSpec,
+    /// `plugin__<plugin>__<tool>`
+    wire_name: String,
+    /// `plugin:<plugin>/<tool>`
+    display_name: String,
+    project_root: PathBuf,
+    /// `$HOME`, used only when the plugin was granted `home`
+    home: Option<PathBuf>,
+}
+
+impl WasmPluginTool {
+    pub fn new(
+        plugin: Arc<WasmPlugin>,
+        spec: WasmToolSpec,
+        project_root: &Path,
+        home: Option<PathBuf>,
+    ) -> Self {
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        Self {
+            wire_name: plugin_tool_name(&plugin.name, &spec.name),
+            display_name: format!("plugin:{}/{}", plugin.name, spec.name),
+            project_root: canonical(project_root),
+            home: home
+                .filter(|_| plugin.grants.home)
+                .map(|h| canonical(&h)),
+            plugin,
+            spec,
+        }
+    }
+
+    fn host(&self) -> Host {
+        let mut host = Host::sealed();
+        host.base = Some(self.project_root.clone());
+        host.read_roots.push(self.project_root.clone());
+        host.read_roots.extend(self.home.clone());
+        if self.plugin.grants.write {
+            host.write_root = Some(self.project_root.clone());
+        }
+        host
+    }
+}
+
+#[async_trait]
+impl Tool for WasmPluginTool {
+    fn name(&self) -> &str {
+        &self.wire_name
+    }
+
+    fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(&self.wire_name, &self.spec.description)
+            .with_parameters(ToolParameters::from_raw(self.spec.parameters.clone()))
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.spec.read_only && !self.plugin.grants.write
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<ToolOutput, Error> {
+        let arguments = if arguments.is_null() {
+            serde_json::json!({})
+        } else {
+            arguments
+        };
+        tracing::info!(tool = %self.display_name, "Running WASM plugin tool");
+        let plugin = Arc::clone(&self.plugin);
+        let name = self.spec.name.clone();
+        let host = self.host();
+        let result = tokio::task::spawn_blocking(move || {
+            plugin.call(&name, &arguments.to_string(), host)
+        })
+        .await
+        .map_err(|e| Error::tool(&self.wire_name, e.to_string()))?;
+        Ok(match result {
+            Ok(output) => ToolOutput::success(output),
+            Err(e) => ToolOutput::error(format!("{} failed: {}", self.display_name, e)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{"tools": [{"name": "read", "description": "Read notes"}], "agents": {"notes": {"description": "Notes", "system_prompt": "You read notes."}}}"#;
+
+    /// A guest whose tools are picked by name length: `read` reads
+    /// notes.txt, `escape` reads ../secret.txt, `write` writes its arguments
+    /// to out.txt and anything else spins forever.
+    fn guest(negotiate: i32) -> Vec<u8> {
+        let wat = format!(
+            r#"(module
+  (import "qq" "output" (func $output (param i32 i32)))
+  (import "qq" "read_file" (func $read (param i32 i32) (result i64)))
+  (import "qq" "write_file" (func $write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4096))
+  (data (i32.const 0) "{manifest}")
+  (data (i32.const 512) "notes.txt")
+  (data (i32.const 528) "../secret.txt")
+  (data (i32.const 544) "out.txt")
+  (data (i32.const 560) "denied")
+  (func (export "qq_negotiate") (param $max i32) (result i32) (i32.const {negotiate}))
+  (func (export "qq_alloc") (param $len i32) (result i32)
+    (local $p i32)
+    (local.set $p (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (local.get $p))
+  (func (export "qq_manifest") (result i32)
+    (call $output (i32.const 0) (i32.const {manifest_len}))
+    (i32.const 0))
+  (func $emit (param $r i64) (result i32)
+    (if (i64.lt_s (local.get $r) (i64.const 0))
+      (then (call $output (i32.const 560) (i32.const 6)) (return (i32.const 1))))
+    (call $output
+      (i32.wrap_i64 (i64.shr_u (local.get $r) (i64.const 32)))
+      (i32.wrap_i64 (local.get $r)))
+    (i32.const 0))
+  (func (export "qq_call") (param $np i32) (param $nl i32) (param $ap i32) (param $al i32) (result i32)
+    (if (i32.eq (local.get $nl) (i32.const 4))
+      (then (return (call $emit (call $read (i32.const 512) (i32.const 9))))))
+    (if (i32.eq (local.get $nl) (i32.const 6))
+      (then (return (call $emit (call $read (i32.const 528) (i32.const 13))))))
+    (if (i32.eq (local.get $nl) (i32.const 5))
+      (then
+        (if (i32.ne (call $write (i32.const 544) (i32.const 7) (local.get $ap) (local.get $al)) (i32.const 0))
+          (then (call $output (i32.const 560) (i32.const 6)) (return (i32.const 1))))
+        (return (i32.const 0))))
+    (loop $spin (br $spin))
+    (i32.const 0)))"#,
+            manifest = MANIFEST.replace('"', "\\\""),
+            manifest_len = MANIFEST.len(),
+            negotiate = negotiate,
+        );
+        wat::parse_str(wat).unwrap()
+    }
+
+    fn spec(name: &str) -> WasmToolSpec {
+        WasmToolSpec {
+            name: name.into(),
+            description: String::new(),
+            parameters: default_parameters(),
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn test_negotiation_and_manifest() {
+        let plugin = WasmPlugin::from_bytes("notes", &guest(1), PluginGrants::default()).unwrap();
+        assert_eq!(plugin.api_version(), 1);
+        assert_eq!(plugin.manifest().tools[0].name, "read");
+        assert_eq!(plugin.manifest().tools[0].parameters["type"], "object");
+        assert!(plugin.manifest().agents.contains_key("notes"));
+
+        let err = WasmPlugin::from_bytes("notes", &guest(-1), PluginGrants::default()).unwrap_err();
+        assert!(err.contains("refused"), "{}", err);
+        let err = WasmPlugin::from_bytes("notes", &guest(9), PluginGrants::default()).unwrap_err();
+        assert!(err.contains("chose plugin API 9"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_host_calls_enforce_grants() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(project.join("notes.txt"), "remember the milk").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "hunter2").unwrap();
+
+        let plugin = Arc::new(
+            WasmPlugin::from_bytes("notes", &guest(1), PluginGrants::default()).unwrap(),
+        );
+        let tool = |name: &str| WasmPluginTool::new(Arc::clone(&plugin), spec(name), &project, None);
+
+        let out = tool("read").execute(serde_json::json!({})).await.unwrap();
+        assert!(!out.is_error);
+        assert_eq!(out.text_content(), "remember the milk");
+        assert_eq!(tool("read").name(), "plugin__notes__read");
+
+        // Outside the project root and no `home`
+        let out = tool("escape").execute(serde_json::json!({})).await.unwrap();
+        assert!(out.is_error);
+        assert!(out.text_content().contains("denied"));
+
+        // No `write`
+        let out = tool("write").execute(serde_json::json!({"a": 1})).await.unwrap();
+        assert!(out.is_error);
+        assert!(!project.join("out.txt").exists());
+
+        let writer = Arc::new(
+            WasmPlugin::from_bytes(
+                "notes",
+                &guest(1),
+                PluginGrants {
+                    write: true,
+                    home: false,
+                },
+            )
+            .unwrap(),
+        );
+        let tool = WasmPluginTool::new(writer, spec("write"), &project, None);
+        assert!(!tool.is_read_only());
+        let out = tool.execute(serde_json::json!({"a": 1})).await.unwrap();
+        assert!(!out.is_error, "{}", out.text_content());
+        assert_eq!(
+            std::fs::read_to_string(project.join("out.txt")).unwrap(),
+            r#"{"a":1}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_runaway_call_runs_out_of_fuel() {
+        let project = tempfile::tempdir().unwrap();
+        let plugin = Arc::new(
+            WasmPlugin::from_bytes("notes", &guest(1), PluginGrants::default())
+                .unwrap()
+                .with_fuel(1_000_000),
+        );
+        let tool = WasmPluginTool::new(plugin, spec("spin_forever"), project.path(), None);
+        let out = tool.execute(serde_json::json!({})).await.unwrap();
+        assert!(out.is_error);
+        assert!(out.text_content().contains("fuel"), "{}", out.text_content());
+    }
+}